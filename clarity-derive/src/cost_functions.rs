@@ -0,0 +1,239 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! `define_cost_functions!`
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{braced, parenthesized, Error, Ident, LitInt, LitStr, Path, Token};
+
+/// The forms a cost curve can take.  Must match `clarity::vm::costs::cost_functions::CostCurve`.
+const CURVES: &[&str] = &["Constant", "Linear", "LogN", "NLogN"];
+
+/// `Name { Variant(..), .. }`
+pub struct CostFunctions {
+    name: Ident,
+    functions: Punctuated<CostFunction, Token![,]>,
+}
+
+/// `Variant("name", arity = N, curve = Curve, bench = path)`
+struct CostFunction {
+    variant: Ident,
+    name: LitStr,
+    arity: LitInt,
+    curve: Ident,
+    bench: Option<Path>,
+}
+
+impl Parse for CostFunctions {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        let content;
+        braced!(content in input);
+        let functions = content.parse_terminated(CostFunction::parse, Token![,])?;
+        Ok(CostFunctions { name, functions })
+    }
+}
+
+impl Parse for CostFunction {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let variant = input.parse()?;
+        let content;
+        parenthesized!(content in input);
+        let name = content.parse()?;
+
+        let mut arity = None;
+        let mut curve = None;
+        let mut bench = None;
+        while !content.is_empty() {
+            content.parse::<Token![,]>()?;
+            if content.is_empty() {
+                break;
+            }
+            let key: Ident = content.parse()?;
+            content.parse::<Token![=]>()?;
+            if key == "arity" {
+                arity = Some(content.parse::<LitInt>()?);
+            } else if key == "curve" {
+                let value: Ident = content.parse()?;
+                if !CURVES.iter().any(|curve| value == curve) {
+                    return Err(Error::new_spanned(
+                        &value,
+                        format!("expected one of {}", CURVES.join(", ")),
+                    ));
+                }
+                curve = Some(value);
+            } else if key == "bench" {
+                bench = Some(content.parse::<Path>()?);
+            } else {
+                return Err(Error::new_spanned(
+                    key,
+                    "expected `arity`, `curve`, or `bench`",
+                ));
+            }
+        }
+
+        let arity = arity.ok_or_else(|| Error::new_spanned(&variant, "missing `arity`"))?;
+        arity.base10_parse::<usize>()?;
+        let curve = curve.ok_or_else(|| Error::new_spanned(&variant, "missing `curve`"))?;
+        Ok(CostFunction {
+            variant,
+            name,
+            arity,
+            curve,
+            bench,
+        })
+    }
+}
+
+pub fn expand(input: CostFunctions) -> Result<TokenStream2, Error> {
+    let name = &input.name;
+    let functions: Vec<_> = input.functions.iter().collect();
+    for (i, function) in functions.iter().enumerate() {
+        for other in functions[..i].iter() {
+            if other.variant == function.variant {
+                return Err(Error::new_spanned(
+                    &function.variant,
+                    "duplicate cost function variant",
+                ));
+            }
+            if other.name.value() == function.name.value() {
+                return Err(Error::new_spanned(
+                    &function.name,
+                    "duplicate cost function name",
+                ));
+            }
+        }
+    }
+
+    let variants: Vec<_> = functions.iter().map(|function| &function.variant).collect();
+    let names: Vec<_> = functions.iter().map(|function| &function.name).collect();
+
+    let schedule = functions.iter().map(|function| {
+        let variant = &function.variant;
+        let arity = &function.arity;
+        let curve = &function.curve;
+        let benchmark = match &function.bench {
+            Some(bench) => {
+                let bench = quote!(#bench).to_string().replace(' ', "");
+                quote!(Some(#bench))
+            }
+            None => quote!(None),
+        };
+        quote! {
+            ::clarity::vm::costs::cost_functions::CostFunctionSchedule {
+                function: #name::#variant,
+                arity: #arity,
+                curve: ::clarity::vm::costs::cost_functions::CostCurve::#curve,
+                benchmark: #benchmark,
+            }
+        }
+    });
+
+    let benchmarks = functions.iter().filter_map(|function| {
+        let variant = &function.variant;
+        function.bench.as_ref().map(|bench| {
+            quote! {
+                (#name::#variant, #bench as fn(&mut ::criterion::Criterion))
+            }
+        })
+    });
+
+    let test_name = format_ident!("{}_benchmarks_exist", to_snake_case(&name.to_string()));
+
+    Ok(quote! {
+        #[derive(::serde::Serialize, ::serde::Deserialize, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+        pub enum #name {
+            #(#variants),*
+        }
+
+        impl #name {
+            pub const ALL: &'static [#name] = &[#(#name::#variants),*];
+            pub const ALL_NAMES: &'static [&'static str] = &[#(#names),*];
+
+            /// The cost schedule of every cost function, in the same order as `ALL`
+            pub const SCHEDULE: &'static [::clarity::vm::costs::cost_functions::CostFunctionSchedule] = &[
+                #(#schedule),*
+            ];
+
+            /// Every cost function that has a benchmark, with its benchmark
+            #[cfg(any(test, feature = "testing"))]
+            pub const BENCHMARKS: &'static [(#name, fn(&mut ::criterion::Criterion))] = &[
+                #(#benchmarks),*
+            ];
+
+            pub fn lookup_by_name(name: &str) -> Option<Self> {
+                match name {
+                    #(#names => Some(#name::#variants),)*
+                    _ => None
+                }
+            }
+
+            pub fn get_name(&self) -> String {
+                self.get_name_str().to_string()
+            }
+
+            pub fn get_name_str(&self) -> &'static str {
+                match self {
+                    #(#name::#variants => #names,)*
+                }
+            }
+
+            /// This cost function's entry in `SCHEDULE`
+            pub fn schedule(&self) -> &'static ::clarity::vm::costs::cost_functions::CostFunctionSchedule {
+                &Self::SCHEDULE[*self as usize]
+            }
+        }
+
+        impl ::std::fmt::Display for #name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                write!(f, "{}", self.get_name_str())
+            }
+        }
+
+        #[cfg(test)]
+        #[test]
+        fn #test_name() {
+            for entry in #name::SCHEDULE.iter() {
+                let has_benchmark = #name::BENCHMARKS
+                    .iter()
+                    .any(|(function, _)| *function == entry.function);
+                assert_eq!(
+                    entry.benchmark.is_some(),
+                    has_benchmark,
+                    "benchmark for {} is not registered",
+                    entry.function
+                );
+            }
+        }
+    })
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
@@ -0,0 +1,388 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! `#[derive(StacksError)]`
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Error, Fields, Ident, Index, LitInt, LitStr, Member, Variant};
+
+/// Options set on the enum itself
+struct EnumOptions {
+    label: LitStr,
+    skip_display: bool,
+}
+
+/// Options set on one variant
+struct VariantOptions {
+    code: LitInt,
+    label: Option<LitStr>,
+    display: Option<LitStr>,
+    source: bool,
+}
+
+pub fn expand(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        Data::Struct(_) | Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "StacksError can only be derived for enums",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "StacksError can't be derived for generic enums",
+        ));
+    }
+    if variants.is_empty() {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "StacksError can't be derived for an enum with no variants",
+        ));
+    }
+
+    let options = enum_options(&input)?;
+    let prefix = options.label.value();
+    if !is_metric_label(&prefix) {
+        return Err(Error::new_spanned(
+            &options.label,
+            "labels may only contain lowercase letters, digits, and underscores",
+        ));
+    }
+
+    let mut codes: Vec<u32> = Vec::with_capacity(variants.len());
+    let mut labels: Vec<String> = Vec::with_capacity(variants.len());
+    let mut code_arms = Vec::with_capacity(variants.len());
+    let mut label_arms = Vec::with_capacity(variants.len());
+    let mut display_arms = Vec::with_capacity(variants.len());
+    let mut source_arms = Vec::with_capacity(variants.len());
+    for variant in variants.iter() {
+        let ident = &variant.ident;
+        let variant_options = variant_options(variant)?;
+
+        let code: u32 = variant_options.code.base10_parse()?;
+        if codes.contains(&code) {
+            return Err(Error::new_spanned(
+                &variant_options.code,
+                format!("more than one variant has the code {}", code),
+            ));
+        }
+        codes.push(code);
+
+        let label = match &variant_options.label {
+            Some(label) => {
+                if !is_metric_label(&label.value()) {
+                    return Err(Error::new_spanned(
+                        label,
+                        "labels may only contain lowercase letters, digits, and underscores",
+                    ));
+                }
+                format!("{}_{}", prefix, label.value())
+            }
+            None => format!("{}_{}", prefix, snake_case(&ident.to_string())),
+        };
+        if labels.contains(&label) {
+            return Err(Error::new_spanned(
+                ident,
+                format!(
+                    "more than one variant has the label '{}'; use #[error(label = \"...\")]",
+                    label
+                ),
+            ));
+        }
+
+        code_arms.push(quote! { Self::#ident { .. } => #code, });
+        label_arms.push(quote! { Self::#ident { .. } => #label, });
+        labels.push(label);
+
+        match (&variant_options.display, options.skip_display) {
+            (Some(display), true) => {
+                return Err(Error::new_spanned(
+                    display,
+                    "`display` has no effect when the enum is marked `skip_display`",
+                ))
+            }
+            (Some(display), false) => {
+                let (display, bindings) = rewrite_format(display, &variant.fields)?;
+                display_arms.push(quote! {
+                    Self::#ident { #(#bindings,)* .. } => ::std::write!(__formatter, #display),
+                });
+            }
+            (None, false) => {
+                let member = only_field(variant, "#[error(display = \"...\")]")?;
+                display_arms.push(quote! {
+                    Self::#ident { #member: inner, .. } => ::std::fmt::Display::fmt(inner, __formatter),
+                });
+            }
+            (None, true) => {}
+        }
+
+        if variant_options.source {
+            let member = only_field(variant, "`source`")?;
+            source_arms.push(quote! {
+                Self::#ident { #member: inner, .. } => ::std::option::Option::Some(inner),
+            });
+        } else {
+            source_arms.push(quote! {
+                Self::#ident { .. } => ::std::option::Option::None,
+            });
+        }
+    }
+
+    let name = &input.ident;
+    let display_impl = if options.skip_display {
+        quote! {}
+    } else {
+        quote! {
+            impl ::std::fmt::Display for #name {
+                fn fmt(&self, __formatter: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    match self {
+                        #(#display_arms)*
+                    }
+                }
+            }
+        }
+    };
+
+    Ok(quote! {
+        #display_impl
+
+        impl ::std::error::Error for #name {
+            fn source(&self) -> ::std::option::Option<&(dyn ::std::error::Error + 'static)> {
+                match self {
+                    #(#source_arms)*
+                }
+            }
+        }
+
+        impl ::stacks_common::util::telemetry::ErrorTelemetry for #name {
+            fn code(&self) -> u32 {
+                match self {
+                    #(#code_arms)*
+                }
+            }
+
+            fn metric_label(&self) -> &'static str {
+                match self {
+                    #(#label_arms)*
+                }
+            }
+        }
+    })
+}
+
+fn enum_options(input: &DeriveInput) -> Result<EnumOptions, Error> {
+    let mut label = None;
+    let mut skip_display = false;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("error"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("label") {
+                label = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else if meta.path.is_ident("skip_display") {
+                skip_display = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `label` or `skip_display`"))
+            }
+        })?;
+    }
+    let label = label.ok_or_else(|| {
+        Error::new_spanned(
+            &input.ident,
+            "StacksError needs a label prefix; add #[error(label = \"...\")] to the enum",
+        )
+    })?;
+    Ok(EnumOptions {
+        label,
+        skip_display,
+    })
+}
+
+fn variant_options(variant: &Variant) -> Result<VariantOptions, Error> {
+    let mut code = None;
+    let mut label = None;
+    let mut display = None;
+    let mut source = false;
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("error"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("code") {
+                code = Some(meta.value()?.parse::<LitInt>()?);
+                Ok(())
+            } else if meta.path.is_ident("label") {
+                label = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else if meta.path.is_ident("display") {
+                display = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else if meta.path.is_ident("source") {
+                source = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `code`, `label`, `display`, or `source`"))
+            }
+        })?;
+    }
+    let code = code.ok_or_else(|| {
+        Error::new_spanned(
+            &variant.ident,
+            "every variant needs a code; add #[error(code = ...)]",
+        )
+    })?;
+    Ok(VariantOptions {
+        code,
+        label,
+        display,
+        source,
+    })
+}
+
+/// The variant's field, if it has exactly one.  `needs` is what the variant would need to have
+/// more than one.
+fn only_field(variant: &Variant, needs: &str) -> Result<Member, Error> {
+    match &variant.fields {
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => Ok(Member::Unnamed(Index::from(0))),
+        Fields::Named(fields) if fields.named.len() == 1 => Ok(Member::Named(
+            fields.named[0]
+                .ident
+                .clone()
+                .expect("BUG: named field has no identifier"),
+        )),
+        _ => Err(Error::new_spanned(
+            &variant.ident,
+            format!("{} only applies to variants with exactly one field", needs),
+        )),
+    }
+}
+
+/// Rewrite a `display` format string so that its arguments refer to bindings of the variant's
+/// fields: `{0}` becomes `{_0}`, and `{name}` stays as it is.  Returns the new format string,
+/// along with the field patterns that bind the fields it refers to.
+fn rewrite_format(display: &LitStr, fields: &Fields) -> Result<(LitStr, Vec<TokenStream2>), Error> {
+    let format = display.value();
+    let mut rewritten = String::with_capacity(format.len());
+    let mut patterns = vec![];
+    let mut bindings: Vec<Ident> = vec![];
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        rewritten.push(c);
+        if c == '}' && chars.peek() == Some(&'}') {
+            rewritten.push(chars.next().unwrap());
+            continue;
+        }
+        if c != '{' {
+            continue;
+        }
+        if chars.peek() == Some(&'{') {
+            rewritten.push(chars.next().unwrap());
+            continue;
+        }
+
+        let mut argument = String::new();
+        while let Some(&c) = chars.peek() {
+            if c == ':' || c == '}' {
+                break;
+            }
+            argument.push(c);
+            chars.next();
+        }
+
+        let (pattern, binding) = if argument.is_empty() {
+            return Err(Error::new_spanned(
+                display,
+                "refer to fields by position (`{0}`) or by name (`{field}`)",
+            ));
+        } else if argument.chars().all(|c| c.is_ascii_digit()) {
+            let index: usize = argument
+                .parse()
+                .map_err(|_| Error::new_spanned(display, "field index is too large"))?;
+            match fields {
+                Fields::Unnamed(fields) if index < fields.unnamed.len() => {}
+                _ => {
+                    return Err(Error::new_spanned(
+                        display,
+                        format!("the variant has no field {}", index),
+                    ))
+                }
+            }
+            let member = Index::from(index);
+            let binding = format_ident!("_{}", index);
+            (quote! { #member: #binding }, binding)
+        } else {
+            let field = match fields {
+                Fields::Named(fields) => fields
+                    .named
+                    .iter()
+                    .filter_map(|field| field.ident.as_ref())
+                    .find(|ident| *ident == argument.as_str()),
+                _ => None,
+            };
+            match field {
+                Some(ident) => (quote! { #ident }, ident.clone()),
+                None => {
+                    return Err(Error::new_spanned(
+                        display,
+                        format!("the variant has no field `{}`", argument),
+                    ))
+                }
+            }
+        };
+
+        rewritten.push_str(&binding.to_string());
+        if !bindings.contains(&binding) {
+            patterns.push(pattern);
+            bindings.push(binding);
+        }
+    }
+    Ok((LitStr::new(&rewritten, display.span()), patterns))
+}
+
+/// `ASTError` becomes `ast_error`, and `NoSuchBlockError` becomes `no_such_block_error`
+fn snake_case(ident: &str) -> String {
+    let chars: Vec<char> = ident.trim_start_matches("r#").chars().collect();
+    let mut snake = String::with_capacity(chars.len() + 4);
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = matches!(chars.get(i + 1), Some(c) if c.is_ascii_lowercase());
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_is_lower)
+            {
+                snake.push('_');
+            }
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+fn is_metric_label(label: &str) -> bool {
+    !label.is_empty()
+        && label
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
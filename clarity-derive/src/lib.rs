@@ -0,0 +1,123 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Procedural macros for clarity:
+//!
+//! * `#[derive(ClarityTuple)]`, re-exported as `clarity::vm::types::ClarityTuple`, which converts
+//!   structs to and from Clarity tuples;
+//! * `define_cost_functions!`, which declares the `ClarityCostFunction` enum along with its cost
+//!   schedule and benchmarks;
+//! * `#[derive(StacksError)]`, which gives the error enums of clarity and the node their
+//!   `Display`, `Error`, and `stacks_common::util::telemetry::ErrorTelemetry` implementations.
+
+extern crate proc_macro;
+
+mod cost_functions;
+mod error;
+mod tuple;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput, Error};
+
+/// Deriving `ClarityTuple` on a struct with named fields maps it to a Clarity tuple with one entry
+/// per field.  It generates `TryFrom<Value>` for the struct, `From<struct>` for `Value`, and
+/// `ClarityTupleField` for the struct (so that it can be nested in other `ClarityTuple` structs).
+/// Every field's type must implement `clarity::vm::types::ClarityTupleField`.
+///
+/// A field's tuple entry is named after the field, with underscores replaced by dashes (so
+/// `lock_amount` becomes `lock-amount`).  Use `#[clarity(rename = "...")]` to give it a different
+/// name.  When converting from a tuple, entries that don't correspond to any field are ignored.
+///
+/// ```ignore
+/// #[derive(ClarityTuple)]
+/// pub struct PoxStackingResult {
+///     pub stacker: PrincipalData,
+///     pub lock_amount: u128,
+///     #[clarity(rename = "unlock-burn-height")]
+///     pub unlock_height: u64,
+/// }
+/// ```
+#[proc_macro_derive(ClarityTuple, attributes(clarity))]
+pub fn derive_clarity_tuple(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    tuple::expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Declare an enum of Clarity cost functions.  Each variant names the cost contract function it
+/// resolves to, how many input sizes it takes, and the form of its runtime cost curve.  A variant
+/// can also link the criterion benchmark that measures it:
+///
+/// ```ignore
+/// define_cost_functions!(ClarityCostFunction {
+///     AnalysisVisit("cost_analysis_visit", arity = 1, curve = Constant),
+///     Add("cost_add", arity = 1, curve = Linear, bench = benches::add),
+/// });
+/// ```
+///
+/// The curve is the name of a `clarity::vm::costs::cost_functions::CostCurve` variant, and a
+/// benchmark is a path to a `fn(&mut criterion::Criterion)`.
+///
+/// Besides what `define_named_enum!` generates (`ALL`, `ALL_NAMES`, `lookup_by_name()`,
+/// `get_name()`, `get_name_str()`, and `Display`), this generates:
+///
+/// * `SCHEDULE`, a `CostFunctionSchedule` per variant, in declaration order, and `schedule()`,
+///   which looks up a variant's entry;
+/// * `BENCHMARKS`, every linked benchmark, when compiled for tests or with the `testing` feature;
+/// * a test that every benchmark named in `SCHEDULE` is in `BENCHMARKS`.  Since `BENCHMARKS`
+///   refers to the benchmark functions directly, a link to a benchmark that doesn't exist fails
+///   to compile.
+#[proc_macro]
+pub fn define_cost_functions(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as cost_functions::CostFunctions);
+    cost_functions::expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Deriving `StacksError` on an enum implements `std::fmt::Display`, `std::error::Error`, and
+/// `stacks_common::util::telemetry::ErrorTelemetry` for it.  Every variant must be given a code,
+/// which is its `ErrorTelemetry::code()`; codes must be unique within the enum, and should come
+/// from the block of codes that `stacks_common::util::telemetry` assigns to it.  The enum must be
+/// given a label prefix, which, with the variant's name in snake_case, is the variant's
+/// `ErrorTelemetry::metric_label()`.  A variant can use `label` to override the latter half.
+///
+/// A variant is displayed with its `display` format string, which can refer to the variant's
+/// fields by position (`{0}`) or by name (`{field}`).  A variant with exactly one field and no
+/// `display` string is displayed as that field is.  Mark the enum `skip_display` to write the
+/// `Display` implementation by hand.
+///
+/// A variant marked `source` must have exactly one field, which is its `Error::source()`.
+///
+/// ```ignore
+/// #[derive(Debug, StacksError)]
+/// #[error(label = "net")]
+/// pub enum Error {
+///     #[error(code = 3001)]
+///     SerializeError(String),
+///     #[error(code = 3002, source)]
+///     ReadError(io::Error),
+///     #[error(code = 3003, display = "Peer {1} already connected", label = "duplicate_peer")]
+///     AlreadyConnected(usize, NeighborKey),
+/// }
+/// ```
+#[proc_macro_derive(StacksError, attributes(error))]
+pub fn derive_stacks_error(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    error::expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,197 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! `#[derive(ClarityTuple)]`
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, LitStr};
+
+/// Longest name a Clarity tuple entry can have
+const MAX_CLARITY_NAME_LEN: usize = 128;
+
+pub fn expand(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "ClarityTuple can only be derived for structs with named fields",
+                ))
+            }
+        },
+        Data::Enum(_) | Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "ClarityTuple can only be derived for structs",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "ClarityTuple can't be derived for generic structs",
+        ));
+    }
+    if fields.is_empty() {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "ClarityTuple can't be derived for a struct with no fields, since Clarity tuples can't be empty",
+        ));
+    }
+
+    let mut idents = Vec::with_capacity(fields.len());
+    let mut names = Vec::with_capacity(fields.len());
+    let mut types = Vec::with_capacity(fields.len());
+    for field in fields.iter() {
+        let ident = field
+            .ident
+            .clone()
+            .expect("BUG: named field has no identifier");
+        let name = entry_name(field)?;
+        if names.contains(&name) {
+            return Err(Error::new_spanned(
+                field,
+                format!("more than one field maps to the tuple entry '{}'", name),
+            ));
+        }
+        idents.push(ident);
+        names.push(name);
+        types.push(&field.ty);
+    }
+
+    let name = &input.ident;
+    Ok(quote! {
+        impl ::std::convert::TryFrom<::clarity::vm::types::Value> for #name {
+            type Error = ::clarity::vm::types::TupleConversionError;
+
+            fn try_from(
+                value: ::clarity::vm::types::Value,
+            ) -> ::std::result::Result<Self, Self::Error> {
+                let mut tuple = match value {
+                    ::clarity::vm::types::Value::Tuple(tuple) => tuple,
+                    value => {
+                        return Err(::clarity::vm::types::TupleConversionError::NotATuple(value));
+                    }
+                };
+                #(
+                    let #idents = match tuple.data_map.remove(#names) {
+                        Some(value) => <#types as ::clarity::vm::types::ClarityTupleField>::from_field(#names, value)?,
+                        None => {
+                            return Err(::clarity::vm::types::TupleConversionError::MissingField(#names));
+                        }
+                    };
+                )*
+                Ok(Self { #(#idents),* })
+            }
+        }
+
+        impl ::std::convert::From<#name> for ::clarity::vm::types::Value {
+            fn from(value: #name) -> ::clarity::vm::types::Value {
+                let data = vec![
+                    #((
+                        ::clarity::vm::representations::ClarityName::from(#names),
+                        ::clarity::vm::types::ClarityTupleField::into_field(value.#idents),
+                    )),*
+                ];
+                ::clarity::vm::types::Value::Tuple(
+                    ::clarity::vm::types::TupleData::from_data(data)
+                        .expect("BUG: ClarityTuple struct does not make a valid tuple"),
+                )
+            }
+        }
+
+        impl ::clarity::vm::types::ClarityTupleField for #name {
+            fn from_field(
+                name: &'static str,
+                value: ::clarity::vm::types::Value,
+            ) -> ::std::result::Result<Self, ::clarity::vm::types::TupleConversionError> {
+                match value {
+                    ::clarity::vm::types::Value::Tuple(_) => {
+                        <Self as ::std::convert::TryFrom<::clarity::vm::types::Value>>::try_from(value)
+                    }
+                    value => Err(::clarity::vm::types::TupleConversionError::BadField(name, value)),
+                }
+            }
+
+            fn into_field(self) -> ::clarity::vm::types::Value {
+                ::clarity::vm::types::Value::from(self)
+            }
+        }
+    })
+}
+
+/// The name of the tuple entry that `field` maps to
+fn entry_name(field: &syn::Field) -> Result<String, Error> {
+    let mut rename = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("clarity"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                rename = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename`"))
+            }
+        })?;
+    }
+
+    let name = match rename {
+        Some(rename) => {
+            let name = rename.value();
+            if !is_clarity_name(&name) {
+                return Err(Error::new_spanned(
+                    rename,
+                    format!("'{}' is not a valid Clarity name", name),
+                ));
+            }
+            name
+        }
+        None => {
+            let ident = field
+                .ident
+                .as_ref()
+                .expect("BUG: named field has no identifier")
+                .to_string();
+            let name = ident.trim_start_matches("r#").replace('_', "-");
+            if !is_clarity_name(&name) {
+                return Err(Error::new_spanned(
+                    field,
+                    format!(
+                        "'{}' is not a valid Clarity name; use #[clarity(rename = \"...\")]",
+                        name
+                    ),
+                ));
+            }
+            name
+        }
+    };
+    Ok(name)
+}
+
+/// Can `name` be used as a tuple entry name?  Mirrors the `ClarityName` grammar.
+fn is_clarity_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => {}
+        _ => return false,
+    }
+    name.len() <= MAX_CLARITY_NAME_LEN
+        && chars.all(|c| c.is_ascii_alphanumeric() || "-_!?+<>=/*".contains(c))
+}
@@ -0,0 +1,30 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Runs every benchmark linked from the cost schedule.
+//!
+//! `cargo bench -p clarity --features testing --bench cost_functions`
+
+use clarity::vm::costs::cost_functions::ClarityCostFunction;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+fn cost_functions(c: &mut Criterion) {
+    for (_, bench) in ClarityCostFunction::BENCHMARKS.iter() {
+        bench(c);
+    }
+}
+
+criterion_group!(benches, cost_functions);
+criterion_main!(benches);
@@ -21,6 +21,9 @@
 #![allow(non_upper_case_globals)]
 #![cfg_attr(test, allow(unused_variables, unused_assignments))]
 
+// lets code generated by `#[derive(ClarityTuple)]` name this crate as `clarity`
+extern crate self as clarity;
+
 extern crate rand;
 extern crate rand_chacha;
 extern crate rusqlite;
@@ -80,19 +83,24 @@ pub mod boot_util {
     use crate::vm::representations::ContractName;
     use crate::vm::types::QualifiedContractIdentifier;
     use stacks_common::types::chainstate::StacksAddress;
+    use stacks_common::types::NetworkId;
     use std::convert::TryFrom;
 
     pub fn boot_code_id(name: &str, mainnet: bool) -> QualifiedContractIdentifier {
-        let addr = boot_code_addr(mainnet);
-        QualifiedContractIdentifier::new(
-            addr.into(),
-            ContractName::try_from(name.to_string()).unwrap(),
-        )
+        network_boot_code_id(name, &NetworkId::from_mainnet(mainnet))
     }
 
     pub fn boot_code_addr(mainnet: bool) -> StacksAddress {
         StacksAddress::burn_address(mainnet)
     }
+
+    /// Get the identifier of a boot contract on the given network
+    pub fn network_boot_code_id(name: &str, network: &NetworkId) -> QualifiedContractIdentifier {
+        QualifiedContractIdentifier::new(
+            network.boot_address.into(),
+            ContractName::try_from(name.to_string()).unwrap(),
+        )
+    }
 }
 
 // set via _compile-time_ envars
@@ -15,7 +15,11 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::marker::PhantomData;
 
+use serde::de::{DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
 use stacks_common::types::StacksEpochId;
 
 use crate::vm::analysis::errors::{CheckError, CheckErrors, CheckResult};
@@ -29,6 +33,186 @@ use crate::vm::types::{FunctionType, QualifiedContractIdentifier, TraitIdentifie
 
 use crate::vm::ClarityVersion;
 
+/// Version of the contract analysis storage format written by this node.
+pub const CONTRACT_ANALYSIS_FORMAT_VERSION: u32 = 2;
+
+/// A contract analysis, as it is persisted in the analysis database.
+///
+/// Version 2 of the storage format is a self-describing JSON envelope of the form
+///   `{"format_version": 2, "fields": {"<field name>": <field JSON>, ...}}`
+/// where each field is the serde serialization of the `ContractAnalysis` field of the same name.
+/// Version 1 (legacy) analyses are the serde serialization of `ContractAnalysis` itself, whose
+/// top-level keys are the same field names, so both versions are read the same way.
+///
+/// The stored JSON is kept as-is, and a field is only decoded into its Rust type when it is
+/// asked for -- everything else in the blob is skipped over without being decoded.  Fields this
+/// node does not know about are ignored, and fields this node does know about but which are
+/// absent (e.g. because they were added after the analysis was stored) are decoded as their
+/// defaults, since every analysis output of `ContractAnalysis` is `#[serde(default)]`.  This
+/// lets new analysis outputs be added without invalidating analyses already in the chainstate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredContractAnalysis {
+    format_version: u32,
+    json: String,
+}
+
+/// How analyses written by this node begin.  Any other analysis is either a legacy analysis or
+/// was written by a node that orders the envelope's keys differently.
+const FORMAT_VERSION_PREFIX: &str = "{\"format_version\":";
+/// How legacy analyses begin (the first field of `ContractAnalysis`)
+const LEGACY_FORMAT_PREFIX: &str = "{\"contract_identifier\":";
+
+/// Run `seed` against `json`, with serde's recursion limit lifted and `serde_stacker` guarding
+/// against stack overflows -- see clarity_serializable!(), as type signatures can nest deeper
+/// than serde's default limit.
+fn deserialize_guarded<'de, S: DeserializeSeed<'de>>(
+    json: &'de str,
+    seed: S,
+) -> Result<S::Value, serde_json::Error> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    deserializer.disable_recursion_limit();
+    let deserializer = serde_stacker::Deserializer::new(&mut deserializer);
+    seed.deserialize(deserializer)
+}
+
+/// Decodes the value found by following `path` through nested JSON objects, skipping over
+/// everything else without decoding it.  Evaluates to None if the path does not exist.
+struct PathSeed<'p, T> {
+    path: &'p [&'p str],
+    _value: PhantomData<T>,
+}
+
+impl<'p, T> PathSeed<'p, T> {
+    fn new(path: &'p [&'p str]) -> PathSeed<'p, T> {
+        PathSeed {
+            path,
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<'de, 'p, T: Deserialize<'de>> DeserializeSeed<'de> for PathSeed<'p, T> {
+    type Value = Option<T>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Option<T>, D::Error> {
+        if self.path.is_empty() {
+            T::deserialize(deserializer).map(Some)
+        } else {
+            deserializer.deserialize_map(self)
+        }
+    }
+}
+
+impl<'de, 'p, T: Deserialize<'de>> Visitor<'de> for PathSeed<'p, T> {
+    type Value = Option<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "a JSON object containing {}",
+            self.path.join(".")
+        )
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Option<T>, A::Error> {
+        let (key, rest) = self
+            .path
+            .split_first()
+            .expect("BUG: visited a map with an empty path");
+        let mut found = None;
+        while let Some(next_key) = map.next_key::<String>()? {
+            if found.is_none() && next_key == *key {
+                found = map.next_value_seed(PathSeed::new(rest))?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(found)
+    }
+}
+
+impl StoredContractAnalysis {
+    pub fn from_analysis(analysis: &ContractAnalysis) -> StoredContractAnalysis {
+        let fields =
+            serde_json::to_string(analysis).expect("Failed to serialize contract analysis");
+        StoredContractAnalysis {
+            format_version: CONTRACT_ANALYSIS_FORMAT_VERSION,
+            json: format!(
+                "{}{},\"fields\":{}}}",
+                FORMAT_VERSION_PREFIX, CONTRACT_ANALYSIS_FORMAT_VERSION, fields
+            ),
+        }
+    }
+
+    /// Storage format version this analysis was read from or will be written as
+    pub fn format_version(&self) -> u32 {
+        self.format_version
+    }
+
+    /// Work out which storage format version a stored analysis was written in.  This only scans
+    /// the blob if it was not written by this node, and is not a legacy analysis.
+    fn read_format_version(json: &str) -> u32 {
+        if let Some(rest) = json.strip_prefix(FORMAT_VERSION_PREFIX) {
+            let digits_len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            if let Ok(format_version) = rest[..digits_len].parse() {
+                return format_version;
+            }
+        }
+        if json.starts_with(LEGACY_FORMAT_PREFIX) {
+            return 1;
+        }
+        deserialize_guarded(json, PathSeed::<u32>::new(&["format_version"]))
+            .expect("Failed to deserialize contract analysis")
+            .unwrap_or(1)
+    }
+
+    /// Decode the value at `path` within the analysis' fields
+    fn extract<T: DeserializeOwned>(&self, path: &[&str]) -> Option<T> {
+        let mut full_path = vec![];
+        if self.format_version >= 2 {
+            full_path.push("fields");
+        }
+        full_path.extend_from_slice(path);
+        deserialize_guarded(&self.json, PathSeed::new(&full_path))
+            .expect("Failed to deserialize contract analysis field")
+    }
+
+    /// Decode a single top-level field.
+    /// Returns None if the field is not present in the stored analysis.
+    pub fn get_field<T: DeserializeOwned>(&self, field_name: &str) -> Option<T> {
+        self.extract(&[field_name])
+    }
+
+    /// Decode a single entry of a top-level map field (e.g. one function type out of
+    /// `public_function_types`), without decoding the rest of the map.
+    pub fn get_map_entry<T: DeserializeOwned>(&self, field_name: &str, key: &str) -> Option<T> {
+        self.extract(&[field_name, key])
+    }
+
+    /// Decode the whole analysis
+    pub fn to_contract_analysis(&self) -> ContractAnalysis {
+        self.extract(&[])
+            .expect("Failed to deserialize contract analysis: missing fields")
+    }
+}
+
+impl ClaritySerializable for StoredContractAnalysis {
+    fn serialize(&self) -> String {
+        self.json.clone()
+    }
+}
+
+impl ClarityDeserializable<StoredContractAnalysis> for StoredContractAnalysis {
+    fn deserialize(json: &str) -> StoredContractAnalysis {
+        StoredContractAnalysis {
+            format_version: StoredContractAnalysis::read_format_version(json),
+            json: json.to_string(),
+        }
+    }
+}
+
 pub struct AnalysisDatabase<'a> {
     store: RollbackWrapper<'a>,
 }
@@ -87,17 +271,26 @@ impl<'a> AnalysisDatabase<'a> {
             .has_metadata_entry(contract_identifier, AnalysisDatabase::storage_key())
     }
 
-    /// Load a contract from the database, without canonicalizing its types.
-    pub fn load_contract_non_canonical(
+    /// Load a contract's stored analysis from the database, without decoding it.
+    pub fn load_stored_contract(
         &mut self,
         contract_identifier: &QualifiedContractIdentifier,
-    ) -> Option<ContractAnalysis> {
+    ) -> Option<StoredContractAnalysis> {
         self.store
             .get_metadata(contract_identifier, AnalysisDatabase::storage_key())
             // treat NoSuchContract error thrown by get_metadata as an Option::None --
             //    the analysis will propagate that as a CheckError anyways.
             .ok()?
-            .map(|x| ContractAnalysis::deserialize(&x))
+            .map(|x| StoredContractAnalysis::deserialize(&x))
+    }
+
+    /// Load a contract from the database, without canonicalizing its types.
+    pub fn load_contract_non_canonical(
+        &mut self,
+        contract_identifier: &QualifiedContractIdentifier,
+    ) -> Option<ContractAnalysis> {
+        self.load_stored_contract(contract_identifier)
+            .map(|x| x.to_contract_analysis())
     }
 
     pub fn load_contract(
@@ -105,12 +298,7 @@ impl<'a> AnalysisDatabase<'a> {
         contract_identifier: &QualifiedContractIdentifier,
         epoch: &StacksEpochId,
     ) -> Option<ContractAnalysis> {
-        self.store
-            .get_metadata(contract_identifier, AnalysisDatabase::storage_key())
-            // treat NoSuchContract error thrown by get_metadata as an Option::None --
-            //    the analysis will propagate that as a CheckError anyways.
-            .ok()?
-            .map(|x| ContractAnalysis::deserialize(&x))
+        self.load_contract_non_canonical(contract_identifier)
             .and_then(|mut x| {
                 x.canonicalize_types(epoch);
                 Some(x)
@@ -127,8 +315,11 @@ impl<'a> AnalysisDatabase<'a> {
             return Err(CheckErrors::ContractAlreadyExists(contract_identifier.to_string()).into());
         }
 
-        self.store
-            .insert_metadata(contract_identifier, key, &contract.serialize());
+        self.store.insert_metadata(
+            contract_identifier,
+            key,
+            &StoredContractAnalysis::from_analysis(contract).serialize(),
+        );
         Ok(())
     }
 
@@ -136,14 +327,17 @@ impl<'a> AnalysisDatabase<'a> {
         &mut self,
         contract_identifier: &QualifiedContractIdentifier,
     ) -> CheckResult<ClarityVersion> {
-        // TODO: this function loads the whole contract to obtain the function type.
-        //         but it doesn't need to -- rather this information can just be
+        // TODO: this function loads the whole stored analysis to obtain the function type
+        //         (though it only decodes the requested entry). it doesn't need to
+        //         load it at all -- rather this information can just be
         //         stored as its own entry. the analysis cost tracking currently only
         //         charges based on the function type size.
         let contract = self
-            .load_contract_non_canonical(contract_identifier)
+            .load_stored_contract(contract_identifier)
             .ok_or(CheckErrors::NoSuchContract(contract_identifier.to_string()))?;
-        Ok(contract.clarity_version)
+        Ok(contract
+            .get_field("clarity_version")
+            .unwrap_or(ClarityVersion::Clarity1))
     }
 
     pub fn get_public_function_type(
@@ -152,15 +346,16 @@ impl<'a> AnalysisDatabase<'a> {
         function_name: &str,
         epoch: &StacksEpochId,
     ) -> CheckResult<Option<FunctionType>> {
-        // TODO: this function loads the whole contract to obtain the function type.
-        //         but it doesn't need to -- rather this information can just be
+        // TODO: this function loads the whole stored analysis to obtain the function type
+        //         (though it only decodes the requested entry). it doesn't need to
+        //         load it at all -- rather this information can just be
         //         stored as its own entry. the analysis cost tracking currently only
         //         charges based on the function type size.
         let contract = self
-            .load_contract_non_canonical(contract_identifier)
+            .load_stored_contract(contract_identifier)
             .ok_or(CheckErrors::NoSuchContract(contract_identifier.to_string()))?;
         Ok(contract
-            .get_public_function_type(function_name)
+            .get_map_entry::<FunctionType>("public_function_types", function_name)
             .and_then(|x| Some(x.canonicalize(epoch))))
     }
 
@@ -170,15 +365,16 @@ impl<'a> AnalysisDatabase<'a> {
         function_name: &str,
         epoch: &StacksEpochId,
     ) -> CheckResult<Option<FunctionType>> {
-        // TODO: this function loads the whole contract to obtain the function type.
-        //         but it doesn't need to -- rather this information can just be
+        // TODO: this function loads the whole stored analysis to obtain the function type
+        //         (though it only decodes the requested entry). it doesn't need to
+        //         load it at all -- rather this information can just be
         //         stored as its own entry. the analysis cost tracking currently only
         //         charges based on the function type size.
         let contract = self
-            .load_contract_non_canonical(contract_identifier)
+            .load_stored_contract(contract_identifier)
             .ok_or(CheckErrors::NoSuchContract(contract_identifier.to_string()))?;
         Ok(contract
-            .get_read_only_function_type(function_name)
+            .get_map_entry::<FunctionType>("read_only_function_types", function_name)
             .and_then(|x| Some(x.canonicalize(epoch))))
     }
 
@@ -188,15 +384,16 @@ impl<'a> AnalysisDatabase<'a> {
         trait_name: &str,
         epoch: &StacksEpochId,
     ) -> CheckResult<Option<BTreeMap<ClarityName, FunctionSignature>>> {
-        // TODO: this function loads the whole contract to obtain the function type.
-        //         but it doesn't need to -- rather this information can just be
+        // TODO: this function loads the whole stored analysis to obtain the function type
+        //         (though it only decodes the requested entry). it doesn't need to
+        //         load it at all -- rather this information can just be
         //         stored as its own entry. the analysis cost tracking currently only
         //         charges based on the function type size.
         let contract = self
-            .load_contract_non_canonical(contract_identifier)
+            .load_stored_contract(contract_identifier)
             .ok_or(CheckErrors::NoSuchContract(contract_identifier.to_string()))?;
         Ok(contract
-            .get_defined_trait(trait_name)
+            .get_map_entry::<BTreeMap<ClarityName, FunctionSignature>>("defined_traits", trait_name)
             .and_then(|trait_map| {
                 Some(
                     trait_map
@@ -212,9 +409,9 @@ impl<'a> AnalysisDatabase<'a> {
         contract_identifier: &QualifiedContractIdentifier,
     ) -> CheckResult<BTreeSet<TraitIdentifier>> {
         let contract = self
-            .load_contract_non_canonical(contract_identifier)
+            .load_stored_contract(contract_identifier)
             .ok_or(CheckErrors::NoSuchContract(contract_identifier.to_string()))?;
-        Ok(contract.implemented_traits)
+        Ok(contract.get_field("implemented_traits").unwrap_or_default())
     }
 
     pub fn destroy(self) -> RollbackWrapper<'a> {
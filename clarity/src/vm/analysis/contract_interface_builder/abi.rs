@@ -0,0 +1,631 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Conversion between JSON and Clarity values, driven by a contract's interface.
+//!
+//! Because the interface gives the type of every argument and return value, the JSON encoding
+//! does not need to carry type tags:
+//!
+//! | Clarity type                 | JSON                                             |
+//! |------------------------------|--------------------------------------------------|
+//! | `int`, `uint`                | decimal string (numbers are also accepted)       |
+//! | `bool`                       | `true` / `false`                                 |
+//! | `principal`, traits          | `"SP..."` or `"SP....contract-name"`             |
+//! | `(buff n)`                   | hex string, optionally `0x`-prefixed             |
+//! | `(string-ascii n)`           | string                                           |
+//! | `(string-utf8 n)`            | string                                           |
+//! | `(optional t)`               | `null` or the encoding of the `t` value          |
+//! | `(response ok err)`          | `{"ok": ...}` or `{"err": ...}`                  |
+//! | `(list n t)`                 | array                                            |
+//! | tuples                       | object                                           |
+//!
+//! Integers are written as strings because JSON numbers cannot represent all 128-bit values.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use stacks_common::types::StacksEpochId;
+use stacks_common::util::hash::{hex_bytes, to_hex};
+
+use super::{
+    ContractInterface, ContractInterfaceAtomType, ContractInterfaceFunction,
+    ContractInterfaceFunctionAccess,
+};
+use crate::vm::types::signatures::{BufferLength, StringUTF8Length};
+use crate::vm::types::{
+    CharType, PrincipalData, SequenceData, SequenceSubtype, StringSubtype, TupleData,
+    TupleTypeSignature, TypeSignature, Value,
+};
+use crate::vm::ClarityName;
+
+/// Errors from converting contract-call arguments or results.
+/// Each one names the function signature it was checked against.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AbiError {
+    /// The contract has no public or read-only function with this name
+    NoSuchFunction(String),
+    /// Wrong number of arguments: (signature, expected, given)
+    WrongArgumentCount(String, usize, usize),
+    /// An argument could not be converted: (signature, argument name, reason)
+    BadArgument(String, String, String),
+    /// The function's result could not be converted: (signature, reason)
+    BadResult(String, String),
+}
+
+impl fmt::Display for AbiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AbiError::NoSuchFunction(name) => write!(f, "No such public function: {}", name),
+            AbiError::WrongArgumentCount(signature, expected, given) => write!(
+                f,
+                "{}: expected {} argument(s), got {}",
+                signature, expected, given
+            ),
+            AbiError::BadArgument(signature, arg_name, reason) => {
+                write!(f, "{}: bad argument `{}`: {}", signature, arg_name, reason)
+            }
+            AbiError::BadResult(signature, reason) => {
+                write!(f, "{}: bad result: {}", signature, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AbiError {}
+
+impl fmt::Display for ContractInterfaceAtomType {
+    /// Write out this type the way it would be written in Clarity code
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContractInterfaceAtomType::none => write!(f, "none"),
+            ContractInterfaceAtomType::int128 => write!(f, "int"),
+            ContractInterfaceAtomType::uint128 => write!(f, "uint"),
+            ContractInterfaceAtomType::bool => write!(f, "bool"),
+            ContractInterfaceAtomType::principal => write!(f, "principal"),
+            ContractInterfaceAtomType::trait_reference => write!(f, "<trait>"),
+            ContractInterfaceAtomType::buffer { length } => write!(f, "(buff {})", length),
+            ContractInterfaceAtomType::string_ascii { length } => {
+                write!(f, "(string-ascii {})", length)
+            }
+            ContractInterfaceAtomType::string_utf8 { length } => {
+                write!(f, "(string-utf8 {})", length)
+            }
+            ContractInterfaceAtomType::optional(inner) => write!(f, "(optional {})", inner),
+            ContractInterfaceAtomType::response { ok, error } => {
+                write!(f, "(response {} {})", ok, error)
+            }
+            ContractInterfaceAtomType::list { type_f, length } => {
+                write!(f, "(list {} {})", length, type_f)
+            }
+            ContractInterfaceAtomType::tuple(entries) => {
+                write!(f, "(tuple")?;
+                for entry in entries.iter() {
+                    write!(f, " ({} {})", entry.name, entry.type_f)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl ContractInterfaceAtomType {
+    /// Get the Clarity type signature for this type.
+    /// Trait references are represented by principals, since that is how they are passed as
+    /// contract-call arguments.
+    pub fn to_type_signature(&self) -> Result<TypeSignature, String> {
+        let sig = match self {
+            ContractInterfaceAtomType::none => TypeSignature::NoType,
+            ContractInterfaceAtomType::int128 => TypeSignature::IntType,
+            ContractInterfaceAtomType::uint128 => TypeSignature::UIntType,
+            ContractInterfaceAtomType::bool => TypeSignature::BoolType,
+            ContractInterfaceAtomType::principal | ContractInterfaceAtomType::trait_reference => {
+                TypeSignature::PrincipalType
+            }
+            ContractInterfaceAtomType::buffer { length } => {
+                TypeSignature::SequenceType(SequenceSubtype::BufferType(
+                    BufferLength::try_from(*length).map_err(|e| e.to_string())?,
+                ))
+            }
+            ContractInterfaceAtomType::string_ascii { length } => {
+                TypeSignature::SequenceType(SequenceSubtype::StringType(StringSubtype::ASCII(
+                    BufferLength::try_from(*length).map_err(|e| e.to_string())?,
+                )))
+            }
+            ContractInterfaceAtomType::string_utf8 { length } => {
+                TypeSignature::SequenceType(SequenceSubtype::StringType(StringSubtype::UTF8(
+                    StringUTF8Length::try_from(*length).map_err(|e| e.to_string())?,
+                )))
+            }
+            ContractInterfaceAtomType::optional(inner) => {
+                TypeSignature::new_option(inner.to_type_signature()?).map_err(|e| e.to_string())?
+            }
+            ContractInterfaceAtomType::response { ok, error } => {
+                TypeSignature::new_response(ok.to_type_signature()?, error.to_type_signature()?)
+                    .map_err(|e| e.to_string())?
+            }
+            ContractInterfaceAtomType::list { type_f, length } => {
+                TypeSignature::list_of(type_f.to_type_signature()?, *length)
+                    .map_err(|e| e.to_string())?
+            }
+            ContractInterfaceAtomType::tuple(entries) => {
+                let mut type_map = vec![];
+                for entry in entries.iter() {
+                    let name =
+                        ClarityName::try_from(entry.name.clone()).map_err(|e| e.to_string())?;
+                    type_map.push((name, entry.type_f.to_type_signature()?));
+                }
+                TypeSignature::TupleType(
+                    TupleTypeSignature::try_from(type_map).map_err(|e| e.to_string())?,
+                )
+            }
+        };
+        Ok(sig)
+    }
+
+    /// Convert a JSON value into a Clarity value of this type
+    pub fn value_from_json(
+        &self,
+        json: &JsonValue,
+        epoch: &StacksEpochId,
+    ) -> Result<Value, String> {
+        let value = self.value_from_json_unchecked(json, epoch)?;
+        let sig = self.to_type_signature()?;
+        if !sig.admits(epoch, &value).map_err(|e| e.to_string())? {
+            return Err(format!("value {} is not a {}", value, self));
+        }
+        Ok(value)
+    }
+
+    fn value_from_json_unchecked(
+        &self,
+        json: &JsonValue,
+        epoch: &StacksEpochId,
+    ) -> Result<Value, String> {
+        let mismatch = || format!("expected {}, got {}", self, json);
+        match self {
+            ContractInterfaceAtomType::none => Err(mismatch()),
+            ContractInterfaceAtomType::int128 => match json {
+                JsonValue::String(s) => s.parse::<i128>().map(Value::Int).map_err(|_| mismatch()),
+                JsonValue::Number(n) => n
+                    .as_i64()
+                    .map(|i| Value::Int(i.into()))
+                    .ok_or_else(mismatch),
+                _ => Err(mismatch()),
+            },
+            ContractInterfaceAtomType::uint128 => match json {
+                JsonValue::String(s) => s.parse::<u128>().map(Value::UInt).map_err(|_| mismatch()),
+                JsonValue::Number(n) => n
+                    .as_u64()
+                    .map(|u| Value::UInt(u.into()))
+                    .ok_or_else(mismatch),
+                _ => Err(mismatch()),
+            },
+            ContractInterfaceAtomType::bool => json.as_bool().map(Value::Bool).ok_or_else(mismatch),
+            ContractInterfaceAtomType::principal | ContractInterfaceAtomType::trait_reference => {
+                let s = json.as_str().ok_or_else(mismatch)?;
+                let principal = PrincipalData::parse(s).map_err(|e| e.to_string())?;
+                if *self == ContractInterfaceAtomType::trait_reference {
+                    if let PrincipalData::Standard(_) = principal {
+                        return Err(format!("expected a contract principal, got {}", s));
+                    }
+                }
+                Ok(Value::Principal(principal))
+            }
+            ContractInterfaceAtomType::buffer { .. } => {
+                let s = json.as_str().ok_or_else(mismatch)?;
+                let bytes = hex_bytes(s.strip_prefix("0x").unwrap_or(s))
+                    .map_err(|_| format!("expected a hex string, got {}", json))?;
+                Value::buff_from(bytes).map_err(|e| e.to_string())
+            }
+            ContractInterfaceAtomType::string_ascii { .. } => {
+                let s = json.as_str().ok_or_else(mismatch)?;
+                Value::string_ascii_from_bytes(s.as_bytes().to_vec()).map_err(|e| e.to_string())
+            }
+            ContractInterfaceAtomType::string_utf8 { .. } => {
+                let s = json.as_str().ok_or_else(mismatch)?;
+                Value::string_utf8_from_bytes(s.as_bytes().to_vec()).map_err(|e| e.to_string())
+            }
+            ContractInterfaceAtomType::optional(inner) => match json {
+                JsonValue::Null => Ok(Value::none()),
+                _ => Value::some(inner.value_from_json_unchecked(json, epoch)?)
+                    .map_err(|e| e.to_string()),
+            },
+            ContractInterfaceAtomType::response { ok, error } => {
+                let obj = json.as_object().ok_or_else(mismatch)?;
+                match (obj.len(), obj.get("ok"), obj.get("err")) {
+                    (1, Some(ok_json), None) => {
+                        Value::okay(ok.value_from_json_unchecked(ok_json, epoch)?)
+                            .map_err(|e| e.to_string())
+                    }
+                    (1, None, Some(err_json)) => {
+                        Value::error(error.value_from_json_unchecked(err_json, epoch)?)
+                            .map_err(|e| e.to_string())
+                    }
+                    _ => Err(format!(
+                        "expected {{\"ok\": ...}} or {{\"err\": ...}}, got {}",
+                        json
+                    )),
+                }
+            }
+            ContractInterfaceAtomType::list { type_f, .. } => {
+                let items = json.as_array().ok_or_else(mismatch)?;
+                let values = items
+                    .iter()
+                    .map(|item| type_f.value_from_json_unchecked(item, epoch))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Value::cons_list(values, epoch).map_err(|e| e.to_string())
+            }
+            ContractInterfaceAtomType::tuple(entries) => {
+                let obj = json.as_object().ok_or_else(mismatch)?;
+                if obj.len() != entries.len() {
+                    return Err(mismatch());
+                }
+                let mut data = vec![];
+                for entry in entries.iter() {
+                    let field_json = obj
+                        .get(&entry.name)
+                        .ok_or_else(|| format!("missing tuple field `{}`", entry.name))?;
+                    let name =
+                        ClarityName::try_from(entry.name.clone()).map_err(|e| e.to_string())?;
+                    data.push((
+                        name,
+                        entry.type_f.value_from_json_unchecked(field_json, epoch)?,
+                    ));
+                }
+                TupleData::from_data(data)
+                    .map(Value::Tuple)
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Convert a Clarity value of this type into JSON
+    pub fn value_to_json(&self, value: &Value) -> Result<JsonValue, String> {
+        let mismatch = || format!("expected {}, got {}", self, value);
+        match (self, value) {
+            (ContractInterfaceAtomType::int128, Value::Int(i)) => {
+                Ok(JsonValue::String(i.to_string()))
+            }
+            (ContractInterfaceAtomType::uint128, Value::UInt(u)) => {
+                Ok(JsonValue::String(u.to_string()))
+            }
+            (ContractInterfaceAtomType::bool, Value::Bool(b)) => Ok(JsonValue::Bool(*b)),
+            (
+                ContractInterfaceAtomType::principal | ContractInterfaceAtomType::trait_reference,
+                Value::Principal(p),
+            ) => Ok(JsonValue::String(p.to_string())),
+            (
+                ContractInterfaceAtomType::principal | ContractInterfaceAtomType::trait_reference,
+                Value::CallableContract(callable),
+            ) => Ok(JsonValue::String(callable.contract_identifier.to_string())),
+            (
+                ContractInterfaceAtomType::buffer { .. },
+                Value::Sequence(SequenceData::Buffer(buff)),
+            ) => Ok(JsonValue::String(format!("0x{}", to_hex(&buff.data)))),
+            (
+                ContractInterfaceAtomType::string_ascii { .. },
+                Value::Sequence(SequenceData::String(CharType::ASCII(ascii))),
+            ) => String::from_utf8(ascii.data.clone())
+                .map(JsonValue::String)
+                .map_err(|e| e.to_string()),
+            (
+                ContractInterfaceAtomType::string_utf8 { .. },
+                Value::Sequence(SequenceData::String(CharType::UTF8(utf8))),
+            ) => String::from_utf8(utf8.data.concat())
+                .map(JsonValue::String)
+                .map_err(|e| e.to_string()),
+            (ContractInterfaceAtomType::optional(inner), Value::Optional(opt)) => match opt.data {
+                Some(ref data) => inner.value_to_json(data),
+                None => Ok(JsonValue::Null),
+            },
+            (ContractInterfaceAtomType::response { ok, error }, Value::Response(resp)) => {
+                let mut obj = JsonMap::new();
+                if resp.committed {
+                    obj.insert("ok".to_string(), ok.value_to_json(&resp.data)?);
+                } else {
+                    obj.insert("err".to_string(), error.value_to_json(&resp.data)?);
+                }
+                Ok(JsonValue::Object(obj))
+            }
+            (
+                ContractInterfaceAtomType::list { type_f, .. },
+                Value::Sequence(SequenceData::List(list)),
+            ) => Ok(JsonValue::Array(
+                list.data
+                    .iter()
+                    .map(|item| type_f.value_to_json(item))
+                    .collect::<Result<Vec<_>, _>>()?,
+            )),
+            (ContractInterfaceAtomType::tuple(entries), Value::Tuple(tuple)) => {
+                let mut obj = JsonMap::new();
+                for entry in entries.iter() {
+                    let field = tuple
+                        .get(&entry.name)
+                        .map_err(|_| format!("missing tuple field `{}`", entry.name))?;
+                    obj.insert(entry.name.clone(), entry.type_f.value_to_json(field)?);
+                }
+                Ok(JsonValue::Object(obj))
+            }
+            _ => Err(mismatch()),
+        }
+    }
+}
+
+impl ContractInterfaceFunction {
+    /// The function's signature, written the way it would be in Clarity code, e.g.
+    /// `(define-read-only (get-balance (who principal)) uint)`
+    pub fn signature(&self) -> String {
+        let define = match self.access {
+            ContractInterfaceFunctionAccess::public => "define-public",
+            ContractInterfaceFunctionAccess::read_only => "define-read-only",
+            ContractInterfaceFunctionAccess::private => "define-private",
+        };
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| format!(" ({} {})", arg.name, arg.type_f))
+            .collect();
+        format!(
+            "({} ({}{}) {})",
+            define,
+            self.name,
+            args.concat(),
+            self.outputs.type_f
+        )
+    }
+
+    /// Convert JSON arguments into the Clarity values to call this function with
+    pub fn args_from_json(
+        &self,
+        args: &[JsonValue],
+        epoch: &StacksEpochId,
+    ) -> Result<Vec<Value>, AbiError> {
+        if args.len() != self.args.len() {
+            return Err(AbiError::WrongArgumentCount(
+                self.signature(),
+                self.args.len(),
+                args.len(),
+            ));
+        }
+        self.args
+            .iter()
+            .zip(args.iter())
+            .map(|(arg, json)| {
+                arg.type_f.value_from_json(json, epoch).map_err(|reason| {
+                    AbiError::BadArgument(self.signature(), arg.name.clone(), reason)
+                })
+            })
+            .collect()
+    }
+
+    /// Convert the Clarity values this function is to be called with into JSON
+    pub fn args_to_json(&self, args: &[Value]) -> Result<Vec<JsonValue>, AbiError> {
+        if args.len() != self.args.len() {
+            return Err(AbiError::WrongArgumentCount(
+                self.signature(),
+                self.args.len(),
+                args.len(),
+            ));
+        }
+        self.args
+            .iter()
+            .zip(args.iter())
+            .map(|(arg, value)| {
+                arg.type_f.value_to_json(value).map_err(|reason| {
+                    AbiError::BadArgument(self.signature(), arg.name.clone(), reason)
+                })
+            })
+            .collect()
+    }
+
+    /// Check that `args` are acceptable arguments for this function
+    pub fn check_args(&self, args: &[Value], epoch: &StacksEpochId) -> Result<(), AbiError> {
+        if args.len() != self.args.len() {
+            return Err(AbiError::WrongArgumentCount(
+                self.signature(),
+                self.args.len(),
+                args.len(),
+            ));
+        }
+        for (arg, value) in self.args.iter().zip(args.iter()) {
+            let admitted = arg
+                .type_f
+                .to_type_signature()
+                .and_then(|sig| sig.admits(epoch, value).map_err(|e| e.to_string()));
+            match admitted {
+                Ok(true) => {}
+                Ok(false) => {
+                    return Err(AbiError::BadArgument(
+                        self.signature(),
+                        arg.name.clone(),
+                        format!("value {} is not a {}", value, arg.type_f),
+                    ))
+                }
+                Err(reason) => {
+                    return Err(AbiError::BadArgument(
+                        self.signature(),
+                        arg.name.clone(),
+                        reason,
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert a value returned by this function into JSON
+    pub fn result_to_json(&self, result: &Value) -> Result<JsonValue, AbiError> {
+        self.outputs
+            .type_f
+            .value_to_json(result)
+            .map_err(|reason| AbiError::BadResult(self.signature(), reason))
+    }
+
+    /// Convert a JSON-encoded result of this function into a Clarity value
+    pub fn result_from_json(
+        &self,
+        json: &JsonValue,
+        epoch: &StacksEpochId,
+    ) -> Result<Value, AbiError> {
+        self.outputs
+            .type_f
+            .value_from_json(json, epoch)
+            .map_err(|reason| AbiError::BadResult(self.signature(), reason))
+    }
+}
+
+impl ContractInterface {
+    /// Find a public or read-only function by name.  Private functions cannot be called from
+    /// outside the contract, so they are not returned.
+    pub fn get_callable_function(
+        &self,
+        name: &str,
+    ) -> Result<&ContractInterfaceFunction, AbiError> {
+        self.functions
+            .iter()
+            .find(|function| {
+                function.name == name && function.access != ContractInterfaceFunctionAccess::private
+            })
+            .ok_or_else(|| AbiError::NoSuchFunction(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::analysis::contract_interface_builder::build_contract_interface;
+    use crate::vm::analysis::mem_type_check;
+    use crate::vm::ClarityVersion;
+
+    const ABI_TEST_CONTRACT: &str = "
+        (define-read-only (get-info (who principal) (amount uint) (memo (optional (buff 4)))
+                                    (tags (list 3 (string-ascii 8)))
+                                    (pair { a: int, b: (string-utf8 10) }))
+            (ok { who: who, amount: amount, memo: memo, tags: tags, pair: pair }))
+        (define-public (fail (code uint))
+            (err code))
+        (define-private (hidden) true)
+    ";
+
+    fn test_interface() -> ContractInterface {
+        let (_, analysis) = mem_type_check(
+            ABI_TEST_CONTRACT,
+            ClarityVersion::Clarity2,
+            StacksEpochId::Epoch21,
+        )
+        .unwrap();
+        build_contract_interface(&analysis)
+    }
+
+    #[test]
+    fn test_abi_json_roundtrip() {
+        let epoch = StacksEpochId::Epoch21;
+        let interface = test_interface();
+        let function = interface.get_callable_function("get-info").unwrap();
+        assert_eq!(
+            function.signature(),
+            "(define-read-only (get-info (who principal) (amount uint) (memo (optional (buff 4))) \
+             (tags (list 3 (string-ascii 8))) (pair (tuple (a int) (b (string-utf8 10))))) \
+             (response (tuple (amount uint) (memo (optional (buff 4))) (pair (tuple (a int) (b (string-utf8 10)))) \
+             (tags (list 3 (string-ascii 8))) (who principal)) none))"
+        );
+
+        let args_json = vec![
+            json!("S1G2081040G2081040G2081040G208105NK8PE5.contract"),
+            json!("340282366920938463463374607431768211455"),
+            json!("0xdeadbeef"),
+            json!(["a", "bc"]),
+            json!({"a": -5, "b": "héllo"}),
+        ];
+        let args = function.args_from_json(&args_json, &epoch).unwrap();
+        assert_eq!(args[1], Value::UInt(u128::MAX));
+        assert_eq!(
+            args[2],
+            Value::some(Value::buff_from(vec![0xde, 0xad, 0xbe, 0xef]).unwrap()).unwrap()
+        );
+        function.check_args(&args, &epoch).unwrap();
+
+        // numbers come back as strings
+        let mut expected_json = args_json.clone();
+        expected_json[4] = json!({"a": "-5", "b": "héllo"});
+        assert_eq!(function.args_to_json(&args).unwrap(), expected_json);
+
+        let result = Value::okay(Value::Tuple(
+            TupleData::from_data(vec![
+                ("who".into(), args[0].clone()),
+                ("amount".into(), args[1].clone()),
+                ("memo".into(), Value::none()),
+                ("tags".into(), args[3].clone()),
+                ("pair".into(), args[4].clone()),
+            ])
+            .unwrap(),
+        ))
+        .unwrap();
+        let result_json = function.result_to_json(&result).unwrap();
+        assert_eq!(result_json["ok"]["memo"], JsonValue::Null);
+        assert_eq!(
+            function.result_from_json(&result_json, &epoch).unwrap(),
+            result
+        );
+    }
+
+    #[test]
+    fn test_abi_errors_name_signature() {
+        let epoch = StacksEpochId::Epoch21;
+        let interface = test_interface();
+
+        assert_eq!(
+            interface.get_callable_function("hidden"),
+            Err(AbiError::NoSuchFunction("hidden".into()))
+        );
+
+        let function = interface.get_callable_function("fail").unwrap();
+        assert_eq!(
+            function.args_from_json(&[], &epoch),
+            Err(AbiError::WrongArgumentCount(
+                "(define-public (fail (code uint)) (response none uint))".into(),
+                1,
+                0
+            ))
+        );
+
+        let err = function.args_from_json(&[json!("-1")], &epoch).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "(define-public (fail (code uint)) (response none uint)): bad argument `code`: expected uint, got \"-1\""
+        );
+
+        let err = function.check_args(&[Value::Int(1)], &epoch).unwrap_err();
+        assert!(matches!(err, AbiError::BadArgument(_, ref name, _) if name == "code"));
+
+        // too long for the declared type
+        let function = interface.get_callable_function("get-info").unwrap();
+        let err = function
+            .args_from_json(
+                &[
+                    json!("S1G2081040G2081040G2081040G208105NK8PE5"),
+                    json!(1),
+                    json!("0x0102030405"),
+                    json!([]),
+                    json!({"a": 1, "b": ""}),
+                ],
+                &epoch,
+            )
+            .unwrap_err();
+        assert!(matches!(err, AbiError::BadArgument(_, ref name, _) if name == "memo"));
+    }
+}
@@ -24,7 +24,7 @@ pub mod type_checker;
 pub mod types;
 
 use crate::types::StacksEpochId;
-use crate::vm::database::MemoryBackingStore;
+use crate::vm::database::{ClarityBackend, MemoryBackingStore};
 
 pub use self::types::{AnalysisPass, ContractAnalysis};
 
@@ -51,6 +51,17 @@ pub fn mem_type_check(
     snippet: &str,
     version: ClarityVersion,
     epoch: StacksEpochId,
+) -> CheckResult<(Option<TypeSignature>, ContractAnalysis)> {
+    type_check_with_backend(snippet, version, epoch, &mut MemoryBackingStore::new())
+}
+
+/// Type-check `snippet` as a transient contract, against the contracts deployed in `backend`.
+/// Returns the type of the snippet's last expression, and the contract analysis.
+pub fn type_check_with_backend(
+    snippet: &str,
+    version: ClarityVersion,
+    epoch: StacksEpochId,
+    backend: &mut dyn ClarityBackend,
 ) -> CheckResult<(Option<TypeSignature>, ContractAnalysis)> {
     let contract_identifier = QualifiedContractIdentifier::transient();
     let mut contract = build_ast_with_rules(
@@ -64,8 +75,7 @@ pub fn mem_type_check(
     .unwrap()
     .expressions;
 
-    let mut marf = MemoryBackingStore::new();
-    let mut analysis_db = marf.as_analysis_db();
+    let mut analysis_db = backend.as_analysis_db();
     let cost_tracker = LimitedCostTracker::new_free();
     match run_analysis(
         &QualifiedContractIdentifier::transient(),
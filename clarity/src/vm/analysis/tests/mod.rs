@@ -297,3 +297,117 @@ fn test_write_attempt_in_readonly() {
     assert!(format!("{}", err.diagnostic)
         .contains("expecting read-only statements, detected a writing operation"));
 }
+
+#[test]
+fn test_stored_contract_analysis_formats() {
+    use crate::vm::analysis::analysis_db::{
+        StoredContractAnalysis, CONTRACT_ANALYSIS_FORMAT_VERSION,
+    };
+    use crate::vm::database::{ClarityDeserializable, ClaritySerializable, MemoryBackingStore};
+    use crate::vm::types::{QualifiedContractIdentifier, TypeSignature};
+    use crate::vm::{ClarityName, ClarityVersion};
+    use stacks_common::types::StacksEpochId;
+    use std::collections::BTreeMap;
+
+    let snippet = "(define-trait my-trait ((foo (int) (response int int))))
+                   (define-read-only (get-one) 1)
+                   (define-public (set-one (x int)) (ok x))";
+    let contract_id = QualifiedContractIdentifier::local("stored").unwrap();
+    let epoch = StacksEpochId::Epoch21;
+    let version = ClarityVersion::Clarity2;
+
+    let mut marf = MemoryBackingStore::new();
+    let mut db = marf.as_analysis_db();
+    let mut contract = parse(&contract_id, snippet, version, epoch).unwrap();
+    let analysis = db
+        .execute(|db| {
+            db.test_insert_contract_hash(&contract_id);
+            type_check(&contract_id, &mut contract, db, true, &epoch, &version)
+        })
+        .unwrap();
+
+    // new analyses are written in the current format
+    db.begin();
+    let stored = db.load_stored_contract(&contract_id).unwrap();
+    assert_eq!(stored.format_version(), CONTRACT_ANALYSIS_FORMAT_VERSION);
+
+    let mut expected = analysis.clone();
+    expected.expressions = vec![];
+    expected.type_map = None;
+    expected.cost_track = None;
+    assert_eq!(stored.to_contract_analysis(), expected);
+
+    // individual entries can be read without decoding the whole analysis
+    assert_eq!(db.get_clarity_version(&contract_id).unwrap(), version);
+    assert!(db
+        .get_public_function_type(&contract_id, "set-one", &epoch)
+        .unwrap()
+        .is_some());
+    assert!(db
+        .get_public_function_type(&contract_id, "get-one", &epoch)
+        .unwrap()
+        .is_none());
+    assert!(db
+        .get_read_only_function_type(&contract_id, "get-one", &epoch)
+        .unwrap()
+        .is_some());
+    assert!(db
+        .get_defined_trait(&contract_id, "my-trait", &epoch)
+        .unwrap()
+        .is_some());
+    assert!(db.get_implemented_traits(&contract_id).unwrap().is_empty());
+    db.roll_back();
+
+    // legacy analyses are still readable
+    let legacy = StoredContractAnalysis::deserialize(&expected.serialize());
+    assert_eq!(legacy.format_version(), 1);
+    assert_eq!(legacy.to_contract_analysis(), expected);
+    assert_eq!(
+        legacy.get_field::<ClarityVersion>("clarity_version"),
+        Some(version)
+    );
+
+    // fields written by a newer node are ignored, and absent fields are reported as such
+    let mut newer: serde_json::Value = serde_json::from_str(&stored.serialize()).unwrap();
+    newer["format_version"] = json!(3);
+    newer["fields"]["future_analysis_output"] = json!(["a", "b"]);
+    let newer = StoredContractAnalysis::deserialize(&newer.to_string());
+    assert_eq!(newer.format_version(), 3);
+    assert_eq!(newer.to_contract_analysis(), expected);
+    assert_eq!(newer.get_field::<TypeSignature>("no_such_field"), None);
+
+    // analysis outputs missing from an older analysis are decoded as their defaults
+    let mut older: serde_json::Value = serde_json::from_str(&stored.serialize()).unwrap();
+    older["fields"]
+        .as_object_mut()
+        .unwrap()
+        .remove("defined_traits")
+        .unwrap();
+    let older = StoredContractAnalysis::deserialize(&older.to_string());
+    let mut expected_older = expected.clone();
+    expected_older.defined_traits.clear();
+    assert_eq!(older.to_contract_analysis(), expected_older);
+    assert_eq!(
+        older.get_field::<BTreeMap<ClarityName, serde_json::Value>>("defined_traits"),
+        None
+    );
+
+    // fields nested deeper than serde_json's default recursion limit are still readable
+    let deep_json = format!("{}{}", "[".repeat(1000), "]".repeat(1000));
+    let deep = StoredContractAnalysis::deserialize(&format!(
+        "{{\"fields\":{{\"deep_field\":{}}},\"format_version\":{}}}",
+        deep_json, CONTRACT_ANALYSIS_FORMAT_VERSION
+    ));
+    assert_eq!(
+        deep.get_field::<serde_json::Value>("deep_field")
+            .unwrap()
+            .to_string(),
+        deep_json
+    );
+
+    // round-trips
+    assert_eq!(
+        StoredContractAnalysis::deserialize(&stored.serialize()),
+        stored
+    );
+}
@@ -39,20 +39,36 @@ pub trait AnalysisPass {
     ) -> CheckResult<()>;
 }
 
+/// The output of analyzing a contract.  Analysis outputs are decoded as their defaults if they
+/// are absent from a stored analysis, so that new outputs can be added without breaking the
+/// analyses already in the chainstate (see `StoredContractAnalysis`).  Any output added here
+/// must be `#[serde(default)]` too.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ContractAnalysis {
     pub contract_identifier: QualifiedContractIdentifier,
+    #[serde(default)]
     pub private_function_types: BTreeMap<ClarityName, FunctionType>,
+    #[serde(default)]
     pub variable_types: BTreeMap<ClarityName, TypeSignature>,
+    #[serde(default)]
     pub public_function_types: BTreeMap<ClarityName, FunctionType>,
+    #[serde(default)]
     pub read_only_function_types: BTreeMap<ClarityName, FunctionType>,
+    #[serde(default)]
     pub map_types: BTreeMap<ClarityName, (TypeSignature, TypeSignature)>,
+    #[serde(default)]
     pub persisted_variable_types: BTreeMap<ClarityName, TypeSignature>,
+    #[serde(default)]
     pub fungible_tokens: BTreeSet<ClarityName>,
+    #[serde(default)]
     pub non_fungible_tokens: BTreeMap<ClarityName, TypeSignature>,
+    #[serde(default)]
     pub defined_traits: BTreeMap<ClarityName, BTreeMap<ClarityName, FunctionSignature>>,
+    #[serde(default)]
     pub implemented_traits: BTreeSet<TraitIdentifier>,
+    #[serde(default)]
     pub contract_interface: Option<ContractInterface>,
+    #[serde(default)]
     pub is_cost_contract_eligible: bool,
     pub epoch: StacksEpochId,
     pub clarity_version: ClarityVersion,
@@ -30,11 +30,11 @@ use crate::vm::representations::SymbolicExpression;
 use crate::vm::types::QualifiedContractIdentifier;
 
 use self::definition_sorter::DefinitionSorter;
-use self::errors::ParseResult;
+use self::errors::{ParseError, ParseErrors, ParseResult};
 use self::expression_identifier::ExpressionIdentifier;
-use self::parser::v1::parse as parse_v1;
 use self::parser::v1::parse_no_stack_limit as parse_v1_no_stack_limit;
-use self::parser::v2::parse as parse_v2;
+use self::parser::v1::parse_with_limits as parse_v1_with_limits;
+use self::parser::v2::parse_with_limits as parse_v2_with_limits;
 use self::stack_depth_checker::StackDepthChecker;
 use self::stack_depth_checker::VaryStackDepthChecker;
 use self::sugar_expander::SugarExpander;
@@ -44,6 +44,7 @@ pub use self::types::ContractAST;
 use crate::types::StacksEpochId;
 use crate::vm::costs::cost_functions::ClarityCostFunction;
 use crate::vm::diagnostic::{Diagnostic, Level};
+use crate::vm::limits::VmLimits;
 use crate::vm::representations::PreSymbolicExpression;
 use crate::vm::ClarityVersion;
 
@@ -70,14 +71,18 @@ fn parse_in_epoch(
     source_code: &str,
     epoch_id: StacksEpochId,
     ast_rules: ASTRules,
+    limits: &VmLimits,
 ) -> ParseResult<Vec<PreSymbolicExpression>> {
+    if source_code.len() > limits.max_contract_size {
+        return Err(ParseErrors::ProgramTooLarge.into());
+    }
     if epoch_id >= StacksEpochId::Epoch21 {
-        parse_v2(source_code)
+        parse_v2_with_limits(source_code, limits)
     } else {
         if ast_rules == ASTRules::Typical {
             parse_v1_no_stack_limit(source_code)
         } else {
-            parse_v1(source_code)
+            parse_v1_with_limits(source_code, limits)
         }
     }
 }
@@ -88,13 +93,29 @@ fn parse_in_epoch(
 pub fn ast_check_size(
     contract_identifier: &QualifiedContractIdentifier,
     source_code: &str,
-    clarity_version: ClarityVersion,
+    _clarity_version: ClarityVersion,
     epoch_id: StacksEpochId,
 ) -> ParseResult<ContractAST> {
-    let pre_expressions = parse_in_epoch(source_code, epoch_id, ASTRules::PrecheckSize)?;
+    ast_check_size_with_limits(
+        contract_identifier,
+        source_code,
+        epoch_id,
+        &VmLimits::for_epoch(epoch_id),
+    )
+}
+
+/// Same as `ast_check_size()`, but checks against the given limits instead of the
+/// epoch's consensus defaults.
+pub fn ast_check_size_with_limits(
+    contract_identifier: &QualifiedContractIdentifier,
+    source_code: &str,
+    epoch_id: StacksEpochId,
+    limits: &VmLimits,
+) -> ParseResult<ContractAST> {
+    let pre_expressions = parse_in_epoch(source_code, epoch_id, ASTRules::PrecheckSize, limits)?;
     let mut contract_ast = ContractAST::new(contract_identifier.clone(), pre_expressions);
-    StackDepthChecker::run_pass(&mut contract_ast, clarity_version)?;
-    VaryStackDepthChecker::run_pass(&mut contract_ast, clarity_version)?;
+    StackDepthChecker::run_pass_with_limits(&mut contract_ast, limits)?;
+    VaryStackDepthChecker::run_pass_with_limits(&mut contract_ast, limits)?;
     Ok(contract_ast)
 }
 
@@ -106,6 +127,27 @@ pub fn build_ast_with_rules<T: CostTracker>(
     clarity_version: ClarityVersion,
     epoch: StacksEpochId,
     ruleset: ASTRules,
+) -> ParseResult<ContractAST> {
+    build_ast_with_limits(
+        contract_identifier,
+        source_code,
+        cost_track,
+        clarity_version,
+        epoch,
+        ruleset,
+        &VmLimits::for_epoch(epoch),
+    )
+}
+
+/// Build an AST according to a ruleset, enforcing the given parser limits
+pub fn build_ast_with_limits<T: CostTracker>(
+    contract_identifier: &QualifiedContractIdentifier,
+    source_code: &str,
+    cost_track: &mut T,
+    clarity_version: ClarityVersion,
+    epoch: StacksEpochId,
+    ruleset: ASTRules,
+    limits: &VmLimits,
 ) -> ParseResult<ContractAST> {
     match ruleset {
         // After epoch 2.1, prechecking the size is required
@@ -115,6 +157,7 @@ pub fn build_ast_with_rules<T: CostTracker>(
             cost_track,
             clarity_version,
             epoch,
+            limits,
         ),
         _ => build_ast_precheck_size(
             contract_identifier,
@@ -122,6 +165,7 @@ pub fn build_ast_with_rules<T: CostTracker>(
             cost_track,
             clarity_version,
             epoch,
+            limits,
         ),
     }
 }
@@ -133,6 +177,7 @@ fn build_ast_typical<T: CostTracker>(
     cost_track: &mut T,
     clarity_version: ClarityVersion,
     epoch: StacksEpochId,
+    limits: &VmLimits,
 ) -> ParseResult<ContractAST> {
     let (contract, _, _) = inner_build_ast(
         contract_identifier,
@@ -142,6 +187,7 @@ fn build_ast_typical<T: CostTracker>(
         epoch,
         ASTRules::Typical,
         true,
+        limits,
     )?;
     Ok(contract)
 }
@@ -165,6 +211,7 @@ pub fn build_ast_with_diagnostics<T: CostTracker>(
         epoch,
         ASTRules::PrecheckSize,
         false,
+        &VmLimits::for_epoch(epoch),
     )
     .unwrap()
 }
@@ -177,7 +224,17 @@ fn inner_build_ast<T: CostTracker>(
     epoch: StacksEpochId,
     ast_rules: ASTRules,
     error_early: bool,
+    limits: &VmLimits,
 ) -> ParseResult<(ContractAST, Vec<Diagnostic>, bool)> {
+    if source_code.len() > limits.max_contract_size {
+        if error_early {
+            return Err(ParseErrors::ProgramTooLarge.into());
+        }
+        let err = ParseError::new(ParseErrors::ProgramTooLarge);
+        let contract_ast = ContractAST::new(contract_identifier.clone(), vec![]);
+        return Ok((contract_ast, vec![err.diagnostic], false));
+    }
+
     let cost_err = match runtime_cost(
         ClarityCostFunction::AstParse,
         cost_track,
@@ -190,15 +247,15 @@ fn inner_build_ast<T: CostTracker>(
 
     let (pre_expressions, mut diagnostics, mut success) = if epoch >= StacksEpochId::Epoch21 {
         if error_early {
-            let exprs = parser::v2::parse(source_code)?;
+            let exprs = parser::v2::parse_with_limits(source_code, limits)?;
             (exprs, Vec::new(), true)
         } else {
-            parser::v2::parse_collect_diagnostics(source_code)
+            parser::v2::parse_collect_diagnostics_with_limits(source_code, limits)
         }
     } else {
         let parse_result = match ast_rules {
             ASTRules::Typical => parse_v1_no_stack_limit(source_code),
-            ASTRules::PrecheckSize => parse_v1(source_code),
+            ASTRules::PrecheckSize => parse_v1_with_limits(source_code, limits),
         };
         match parse_result {
             Ok(pre_expressions) => (pre_expressions, vec![], true),
@@ -220,7 +277,7 @@ fn inner_build_ast<T: CostTracker>(
     }
 
     let mut contract_ast = ContractAST::new(contract_identifier.clone(), pre_expressions);
-    match StackDepthChecker::run_pass(&mut contract_ast, clarity_version) {
+    match StackDepthChecker::run_pass_with_limits(&mut contract_ast, limits) {
         Err(e) if error_early => return Err(e),
         Err(e) => {
             diagnostics.push(e.diagnostic);
@@ -231,7 +288,7 @@ fn inner_build_ast<T: CostTracker>(
 
     if ast_rules != ASTRules::Typical {
         // run extra stack-depth pass for tuples
-        match VaryStackDepthChecker::run_pass(&mut contract_ast, clarity_version) {
+        match VaryStackDepthChecker::run_pass_with_limits(&mut contract_ast, limits) {
             Err(e) if error_early => return Err(e),
             Err(e) => {
                 diagnostics.push(e.diagnostic);
@@ -291,6 +348,7 @@ fn build_ast_precheck_size<T: CostTracker>(
     cost_track: &mut T,
     clarity_version: ClarityVersion,
     epoch: StacksEpochId,
+    limits: &VmLimits,
 ) -> ParseResult<ContractAST> {
     let (contract, _, _) = inner_build_ast(
         contract_identifier,
@@ -300,6 +358,7 @@ fn build_ast_precheck_size<T: CostTracker>(
         epoch,
         ASTRules::PrecheckSize,
         true,
+        limits,
     )?;
     Ok(contract)
 }
@@ -319,6 +378,7 @@ pub fn build_ast<T: CostTracker>(
         cost_track,
         clarity_version,
         epoch_id,
+        &VmLimits::for_epoch(epoch_id),
     )
 }
 
@@ -326,7 +386,7 @@ pub fn build_ast<T: CostTracker>(
 mod test {
     use crate::vm::ast::errors::ParseErrors;
     use crate::vm::ast::stack_depth_checker::AST_CALL_STACK_DEPTH_BUFFER;
-    use crate::vm::ast::{build_ast, build_ast_with_rules, ASTRules};
+    use crate::vm::ast::{build_ast, build_ast_with_limits, build_ast_with_rules, ASTRules};
     use crate::vm::costs::LimitedCostTracker;
     use crate::vm::costs::*;
     use crate::vm::representations::depth_traverse;
@@ -334,6 +394,7 @@ mod test {
     use crate::vm::ClarityCostFunction;
     use crate::vm::ClarityName;
     use crate::vm::ClarityVersion;
+    use crate::vm::VmLimits;
     use crate::vm::MAX_CALL_STACK_DEPTH;
     use stacks_common::types::StacksEpochId;
     use std::collections::HashMap;
@@ -627,4 +688,57 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_build_ast_with_custom_limits() {
+        let limits = VmLimits {
+            max_call_stack_depth: 4,
+            ast_call_stack_depth_buffer: 1,
+            max_contract_size: 64,
+        };
+        let deep_list = format!("{}u1{}", "(list ".repeat(6), ")".repeat(6));
+        let too_big = format!("(list {})", "u1 ".repeat(32));
+
+        for epoch in &[StacksEpochId::Epoch2_05, StacksEpochId::Epoch21] {
+            // fine under the default limits
+            for program in &[&deep_list, &too_big] {
+                build_ast_with_rules(
+                    &QualifiedContractIdentifier::transient(),
+                    program,
+                    &mut LimitedCostTracker::new_free(),
+                    ClarityVersion::Clarity2,
+                    *epoch,
+                    ASTRules::PrecheckSize,
+                )
+                .unwrap();
+            }
+
+            let err = build_ast_with_limits(
+                &QualifiedContractIdentifier::transient(),
+                &deep_list,
+                &mut LimitedCostTracker::new_free(),
+                ClarityVersion::Clarity2,
+                *epoch,
+                ASTRules::PrecheckSize,
+                &limits,
+            )
+            .expect_err("Contract should exceed the lowered depth limit");
+            assert!(
+                err.err == ParseErrors::ExpressionStackDepthTooDeep
+                    || err.err == ParseErrors::VaryExpressionStackDepthTooDeep
+            );
+
+            let err = build_ast_with_limits(
+                &QualifiedContractIdentifier::transient(),
+                &too_big,
+                &mut LimitedCostTracker::new_free(),
+                ClarityVersion::Clarity2,
+                *epoch,
+                ASTRules::PrecheckSize,
+                &limits,
+            )
+            .expect_err("Contract should exceed the lowered size limit");
+            assert_eq!(err.err, ParseErrors::ProgramTooLarge);
+        }
+    }
 }
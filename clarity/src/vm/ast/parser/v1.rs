@@ -27,6 +27,7 @@ use std::cmp;
 use std::convert::TryInto;
 
 use crate::vm::ast::stack_depth_checker::AST_CALL_STACK_DEPTH_BUFFER;
+use crate::vm::limits::VmLimits;
 use crate::vm::MAX_CALL_STACK_DEPTH;
 
 pub const CONTRACT_MIN_NAME_LENGTH: usize = 1;
@@ -726,6 +727,15 @@ pub fn parse(input: &str) -> ParseResult<Vec<PreSymbolicExpression>> {
     parse_lexed(lexed)
 }
 
+/// Parse a program, bounding its nesting depth by the given limits instead of the defaults
+pub fn parse_with_limits(
+    input: &str,
+    limits: &VmLimits,
+) -> ParseResult<Vec<PreSymbolicExpression>> {
+    let lexed = inner_lex(input, limits.max_nesting_depth())?;
+    parse_lexed(lexed)
+}
+
 pub fn parse_no_stack_limit(input: &str) -> ParseResult<Vec<PreSymbolicExpression>> {
     let lexed = inner_lex(input, u64::MAX)?;
     parse_lexed(lexed)
@@ -11,6 +11,7 @@ use self::lexer::Lexer;
 use crate::vm::ast::errors::{ParseError, ParseErrors, ParseResult, PlacedError};
 use crate::vm::ast::stack_depth_checker::AST_CALL_STACK_DEPTH_BUFFER;
 use crate::vm::diagnostic::{DiagnosableError, Diagnostic, Level};
+use crate::vm::limits::VmLimits;
 use crate::vm::representations::{
     ClarityName, ContractName, PreSymbolicExpression, PreSymbolicExpressionType, Span,
 };
@@ -31,6 +32,7 @@ pub struct Parser<'a> {
     // context of a stacks-node, while normal mode is useful for developers.
     fail_fast: bool,
     nesting_depth: u64,
+    max_nesting_depth: u64,
 }
 
 pub const MAX_STRING_LEN: usize = 128;
@@ -83,6 +85,7 @@ impl<'a> Parser<'a> {
             success: true,
             fail_fast,
             nesting_depth: 0,
+            max_nesting_depth: MAX_NESTING_DEPTH,
         };
 
         loop {
@@ -828,7 +831,7 @@ impl<'a> Parser<'a> {
                     match &token.token {
                         Token::Lparen => {
                             self.nesting_depth += 1;
-                            if self.nesting_depth > MAX_NESTING_DEPTH {
+                            if self.nesting_depth > self.max_nesting_depth {
                                 self.add_diagnostic(
                                     ParseErrors::ExpressionStackDepthTooDeep,
                                     token.span.clone(),
@@ -847,7 +850,7 @@ impl<'a> Parser<'a> {
                         }
                         Token::Lbrace => {
                             // This sugared syntax for tuple becomes a list of pairs, so depth is increased by 2.
-                            if self.nesting_depth + 2 > MAX_NESTING_DEPTH {
+                            if self.nesting_depth + 2 > self.max_nesting_depth {
                                 self.add_diagnostic(
                                     ParseErrors::ExpressionStackDepthTooDeep,
                                     token.span.clone(),
@@ -1086,6 +1089,11 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Bound the nesting depth by the given limits instead of `MAX_NESTING_DEPTH`
+    pub fn set_limits(&mut self, limits: &VmLimits) {
+        self.max_nesting_depth = limits.max_nesting_depth();
+    }
+
     pub fn parse(&mut self) -> ParseResult<Vec<PreSymbolicExpression>> {
         let mut nodes = vec![];
 
@@ -1102,10 +1110,18 @@ impl<'a> Parser<'a> {
 }
 
 pub fn parse(input: &str) -> ParseResult<Vec<PreSymbolicExpression>> {
+    parse_with_limits(input, &VmLimits::default())
+}
+
+pub fn parse_with_limits(
+    input: &str,
+    limits: &VmLimits,
+) -> ParseResult<Vec<PreSymbolicExpression>> {
     let mut parser = match Parser::new(input, true) {
         Ok(parser) => parser,
         Err(e) => return Err(ParseError::new(e)),
     };
+    parser.set_limits(limits);
     let stmts = parser.parse()?;
     if parser.success {
         Ok(stmts)
@@ -1117,9 +1133,17 @@ pub fn parse(input: &str) -> ParseResult<Vec<PreSymbolicExpression>> {
 
 pub fn parse_collect_diagnostics(
     input: &str,
+) -> (Vec<PreSymbolicExpression>, Vec<Diagnostic>, bool) {
+    parse_collect_diagnostics_with_limits(input, &VmLimits::default())
+}
+
+pub fn parse_collect_diagnostics_with_limits(
+    input: &str,
+    limits: &VmLimits,
 ) -> (Vec<PreSymbolicExpression>, Vec<Diagnostic>, bool) {
     // When not in fail_fast mode, Parser::new always returns Ok.
     let mut parser = Parser::new(input, false).unwrap();
+    parser.set_limits(limits);
 
     // When not in fail_fast mode, Parser::parse always returns Ok.
     let stmts = parser.parse().unwrap();
@@ -20,6 +20,7 @@ use crate::vm::representations::PreSymbolicExpression;
 use crate::vm::representations::PreSymbolicExpressionType::List;
 use crate::vm::representations::PreSymbolicExpressionType::Tuple;
 
+use crate::vm::limits::VmLimits;
 use crate::vm::{ClarityVersion, MAX_CALL_STACK_DEPTH};
 
 // allow  the AST to get deeper than the max call stack depth,
@@ -27,13 +28,13 @@ use crate::vm::{ClarityVersion, MAX_CALL_STACK_DEPTH};
 //    AST depth, without impacting the stack depth).
 pub const AST_CALL_STACK_DEPTH_BUFFER: u64 = 5;
 
-fn check(args: &[PreSymbolicExpression], depth: u64) -> ParseResult<()> {
-    if depth >= (AST_CALL_STACK_DEPTH_BUFFER + MAX_CALL_STACK_DEPTH as u64) {
+fn check(args: &[PreSymbolicExpression], depth: u64, max_depth: u64) -> ParseResult<()> {
+    if depth >= max_depth {
         return Err(ParseErrors::ExpressionStackDepthTooDeep.into());
     }
     for expression in args.iter() {
         match expression.pre_expr {
-            List(ref exprs) => check(exprs, depth + 1),
+            List(ref exprs) => check(exprs, depth + 1, max_depth),
             _ => {
                 // Other symbolic expressions don't have depth
                 //  impacts.
@@ -46,20 +47,34 @@ fn check(args: &[PreSymbolicExpression], depth: u64) -> ParseResult<()> {
 
 pub struct StackDepthChecker;
 
+impl StackDepthChecker {
+    /// Check the AST's depth against a (possibly non-default) set of limits
+    pub fn run_pass_with_limits(
+        contract_ast: &mut ContractAST,
+        limits: &VmLimits,
+    ) -> ParseResult<()> {
+        check(&contract_ast.pre_expressions, 0, limits.max_ast_depth())
+    }
+}
+
 impl BuildASTPass for StackDepthChecker {
     fn run_pass(contract_ast: &mut ContractAST, _version: ClarityVersion) -> ParseResult<()> {
-        check(&contract_ast.pre_expressions, 0)
+        check(
+            &contract_ast.pre_expressions,
+            0,
+            AST_CALL_STACK_DEPTH_BUFFER + MAX_CALL_STACK_DEPTH as u64,
+        )
     }
 }
 
-fn check_vary(args: &[PreSymbolicExpression], depth: u64) -> ParseResult<()> {
-    if depth >= (AST_CALL_STACK_DEPTH_BUFFER + MAX_CALL_STACK_DEPTH as u64) {
+fn check_vary(args: &[PreSymbolicExpression], depth: u64, max_depth: u64) -> ParseResult<()> {
+    if depth >= max_depth {
         return Err(ParseErrors::VaryExpressionStackDepthTooDeep.into());
     }
     for expression in args.iter() {
         match expression.pre_expr {
-            List(ref exprs) => check_vary(exprs, depth + 1),
-            Tuple(ref exprs) => check_vary(exprs, depth + 1),
+            List(ref exprs) => check_vary(exprs, depth + 1, max_depth),
+            Tuple(ref exprs) => check_vary(exprs, depth + 1, max_depth),
             _ => {
                 // Other symbolic expressions don't have depth
                 //  impacts.
@@ -72,8 +87,22 @@ fn check_vary(args: &[PreSymbolicExpression], depth: u64) -> ParseResult<()> {
 
 pub struct VaryStackDepthChecker;
 
+impl VaryStackDepthChecker {
+    /// Check the AST's depth (including tuples) against a (possibly non-default) set of limits
+    pub fn run_pass_with_limits(
+        contract_ast: &mut ContractAST,
+        limits: &VmLimits,
+    ) -> ParseResult<()> {
+        check_vary(&contract_ast.pre_expressions, 0, limits.max_ast_depth())
+    }
+}
+
 impl BuildASTPass for VaryStackDepthChecker {
     fn run_pass(contract_ast: &mut ContractAST, _version: ClarityVersion) -> ParseResult<()> {
-        check_vary(&contract_ast.pre_expressions, 0)
+        check_vary(
+            &contract_ast.pre_expressions,
+            0,
+            AST_CALL_STACK_DEPTH_BUFFER + MAX_CALL_STACK_DEPTH as u64,
+        )
     }
 }
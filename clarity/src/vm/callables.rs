@@ -144,6 +144,23 @@ impl DefinedFunction {
     }
 
     pub fn execute_apply(&self, args: &[Value], env: &mut Environment) -> Result<Value> {
+        if !env.global_context.is_observed() && !env.global_context.cost_track.is_profiling() {
+            return self.inner_execute_apply(args, env);
+        }
+        env.global_context
+            .notify_vm_observers(|observer| observer.will_call_function(&self.identifier, args));
+        env.global_context
+            .cost_track
+            .profile_function_entry(&self.identifier.identifier);
+        let result = self.inner_execute_apply(args, env);
+        env.global_context.cost_track.profile_function_exit();
+        env.global_context.notify_vm_observers(|observer| {
+            observer.did_return_from_function(&self.identifier, &result)
+        });
+        result
+    }
+
+    fn inner_execute_apply(&self, args: &[Value], env: &mut Environment) -> Result<Value> {
         runtime_cost(
             ClarityCostFunction::UserFunctionApplication,
             env,
@@ -14,8 +14,9 @@ use crate::vm::events::StacksTransactionEvent;
 use crate::vm::types::{BuffData, PrincipalData, QualifiedContractIdentifier};
 use crate::vm::ClarityVersion;
 use crate::vm::ContractContext;
+use crate::vm::VmLimits;
 use crate::vm::{ast, SymbolicExpression, Value};
-use stacks_common::types::StacksEpochId;
+use stacks_common::types::{NetworkId, StacksEpochId};
 use std::fmt;
 
 #[derive(Debug)]
@@ -111,6 +112,12 @@ pub trait ClarityConnection {
 
     fn get_epoch(&self) -> StacksEpochId;
 
+    /// The parser and interpreter limits that contracts run with on this connection.
+    /// Unless overridden, these are the consensus limits of the connection's epoch.
+    fn get_vm_limits(&self) -> VmLimits {
+        VmLimits::for_epoch(self.get_epoch())
+    }
+
     fn with_clarity_db_readonly<F, R>(&mut self, to_do: F) -> R
     where
         F: FnOnce(&mut ClarityDatabase) -> R,
@@ -120,27 +127,50 @@ pub trait ClarityConnection {
 
     fn with_readonly_clarity_env<F, R>(
         &mut self,
-        mainnet: bool,
-        chain_id: u32,
+        network: NetworkId,
         clarity_version: ClarityVersion,
         sender: PrincipalData,
         sponsor: Option<PrincipalData>,
         cost_track: LimitedCostTracker,
         to_do: F,
     ) -> Result<R, InterpreterError>
+    where
+        F: FnOnce(&mut Environment) -> Result<R, InterpreterError>,
+    {
+        self.with_readonly_clarity_env_events(
+            network,
+            clarity_version,
+            sender,
+            sponsor,
+            cost_track,
+            to_do,
+        )
+        .map(|(result, _, _)| result)
+    }
+
+    /// Like `with_readonly_clarity_env`, but also returns the asset movements and events that
+    /// `to_do` produced.  Any changes `to_do` makes are still discarded.
+    fn with_readonly_clarity_env_events<F, R>(
+        &mut self,
+        network: NetworkId,
+        clarity_version: ClarityVersion,
+        sender: PrincipalData,
+        sponsor: Option<PrincipalData>,
+        cost_track: LimitedCostTracker,
+        to_do: F,
+    ) -> Result<(R, AssetMap, Vec<StacksTransactionEvent>), InterpreterError>
     where
         F: FnOnce(&mut Environment) -> Result<R, InterpreterError>,
     {
         let epoch_id = self.get_epoch();
+        let limits = self.get_vm_limits();
         self.with_clarity_db_readonly_owned(|clarity_db| {
             let initial_context =
                 ContractContext::new(QualifiedContractIdentifier::transient(), clarity_version);
-            let mut vm_env = OwnedEnvironment::new_cost_limited(
-                mainnet, chain_id, clarity_db, cost_track, epoch_id,
-            );
-            let result = vm_env
-                .execute_in_env(sender, sponsor, Some(initial_context), to_do)
-                .map(|(result, _, _)| result);
+            let mut vm_env =
+                OwnedEnvironment::new_cost_limited(network, clarity_db, cost_track, epoch_id)
+                    .with_limits(limits);
+            let result = vm_env.execute_in_env(sender, sponsor, Some(initial_context), to_do);
             let (db, _) = vm_env
                 .destruct()
                 .expect("Failed to recover database reference after executing transaction");
@@ -185,15 +215,17 @@ pub trait TransactionConnection: ClarityConnection {
         ast_rules: ASTRules,
     ) -> Result<(ContractAST, ContractAnalysis), Error> {
         let epoch_id = self.get_epoch();
+        let limits = self.get_vm_limits();
 
         self.with_analysis_db(|db, mut cost_track| {
-            let ast_result = ast::build_ast_with_rules(
+            let ast_result = ast::build_ast_with_limits(
                 identifier,
                 contract_content,
                 &mut cost_track,
                 clarity_version,
                 epoch_id,
                 ast_rules,
+                &limits,
             );
 
             let mut contract_ast = match ast_result {
@@ -28,6 +28,7 @@ use crate::vm::costs::{
     cost_functions, runtime_cost, ClarityCostFunctionReference, CostErrors, CostTracker,
     ExecutionCost, LimitedCostTracker,
 };
+use crate::vm::coverage::ExpressionCoverage;
 use crate::vm::database::{
     ClarityDatabase, DataMapMetadata, DataVariableMetadata, FungibleTokenMetadata,
     NonFungibleTokenMetadata,
@@ -36,6 +37,8 @@ use crate::vm::errors::{
     CheckErrors, InterpreterError, InterpreterResult as Result, RuntimeErrorType,
 };
 use crate::vm::events::*;
+use crate::vm::limits::VmLimits;
+use crate::vm::observer::{ObservedTransfer, VmObserver};
 use crate::vm::representations::{ClarityName, ContractName, SymbolicExpression};
 use crate::vm::stx_transfer_consolidated;
 use crate::vm::types::signatures::FunctionSignature;
@@ -47,9 +50,10 @@ use crate::vm::{eval, is_reserved};
 use crate::{types::chainstate::StacksBlockId, types::StacksEpochId};
 
 use crate::vm::costs::cost_functions::ClarityCostFunction;
+use crate::vm::costs::profile::CostProfile;
 use crate::vm::version::ClarityVersion;
 
-use stacks_common::consts::CHAIN_ID_TESTNET;
+use stacks_common::types::NetworkId;
 
 use serde::Serialize;
 
@@ -201,12 +205,17 @@ pub struct GlobalContext<'a, 'hooks> {
     pub database: ClarityDatabase<'a>,
     read_only: Vec<bool>,
     pub cost_track: LimitedCostTracker,
-    pub mainnet: bool,
+    /// The network this transaction is executing on
+    pub network: NetworkId,
     /// This is the epoch of the the block that this transaction is executing within.
     pub epoch_id: StacksEpochId,
-    /// This is the chain ID of the transaction
-    pub chain_id: u32,
     pub eval_hooks: Option<Vec<&'hooks mut dyn EvalHook>>,
+    /// Observers notified of function calls, evaluations, asset transfers, and data accesses
+    vm_observers: Vec<&'hooks mut dyn VmObserver>,
+    /// Parser and interpreter limits in effect for this transaction
+    pub limits: VmLimits,
+    /// Expression-level coverage, if enabled with `enable_coverage()`
+    pub coverage: Option<ExpressionCoverage>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -546,8 +555,7 @@ impl<'a, 'hooks> OwnedEnvironment<'a, 'hooks> {
     pub fn new(database: ClarityDatabase<'a>, epoch: StacksEpochId) -> OwnedEnvironment<'a, '_> {
         OwnedEnvironment {
             context: GlobalContext::new(
-                false,
-                CHAIN_ID_TESTNET,
+                NetworkId::testnet(),
                 database,
                 LimitedCostTracker::new_free(),
                 epoch,
@@ -569,8 +577,7 @@ impl<'a, 'hooks> OwnedEnvironment<'a, 'hooks> {
         );
         OwnedEnvironment {
             context: GlobalContext::new(
-                false,
-                CHAIN_ID_TESTNET,
+                NetworkId::testnet(),
                 database,
                 LimitedCostTracker::new_free(),
                 epoch,
@@ -585,27 +592,28 @@ impl<'a, 'hooks> OwnedEnvironment<'a, 'hooks> {
         epoch: StacksEpochId,
         use_mainnet: bool,
     ) -> OwnedEnvironment<'a, '_> {
-        use crate::vm::tests::test_only_mainnet_to_chain_id;
         let cost_track = LimitedCostTracker::new_max_limit(&mut database, epoch, use_mainnet)
             .expect("FAIL: problem instantiating cost tracking");
-        let chain_id = test_only_mainnet_to_chain_id(use_mainnet);
 
         OwnedEnvironment {
-            context: GlobalContext::new(use_mainnet, chain_id, database, cost_track, epoch),
+            context: GlobalContext::new(
+                NetworkId::from_mainnet(use_mainnet),
+                database,
+                cost_track,
+                epoch,
+            ),
             call_stack: CallStack::new(),
         }
     }
 
     pub fn new_free(
-        mainnet: bool,
-        chain_id: u32,
+        network: NetworkId,
         database: ClarityDatabase<'a>,
         epoch_id: StacksEpochId,
     ) -> OwnedEnvironment<'a, '_> {
         OwnedEnvironment {
             context: GlobalContext::new(
-                mainnet,
-                chain_id,
+                network,
                 database,
                 LimitedCostTracker::new_free(),
                 epoch_id,
@@ -615,18 +623,23 @@ impl<'a, 'hooks> OwnedEnvironment<'a, 'hooks> {
     }
 
     pub fn new_cost_limited(
-        mainnet: bool,
-        chain_id: u32,
+        network: NetworkId,
         database: ClarityDatabase<'a>,
         cost_tracker: LimitedCostTracker,
         epoch_id: StacksEpochId,
     ) -> OwnedEnvironment<'a, '_> {
         OwnedEnvironment {
-            context: GlobalContext::new(mainnet, chain_id, database, cost_tracker, epoch_id),
+            context: GlobalContext::new(network, database, cost_tracker, epoch_id),
             call_stack: CallStack::new(),
         }
     }
 
+    /// Override the parser and interpreter limits of this environment's global context
+    pub fn with_limits(mut self, limits: VmLimits) -> Self {
+        self.context.limits = limits;
+        self
+    }
+
     pub fn get_exec_environment<'b>(
         &'b mut self,
         sender: Option<PrincipalData>,
@@ -865,6 +878,26 @@ impl<'a, 'hooks> OwnedEnvironment<'a, 'hooks> {
             self.context.eval_hooks = Some(vec![hook]);
         }
     }
+
+    pub fn add_vm_observer(&mut self, observer: &'hooks mut dyn VmObserver) {
+        self.context.add_vm_observer(observer);
+    }
+
+    pub fn enable_coverage(&mut self) {
+        self.context.enable_coverage();
+    }
+
+    pub fn take_coverage(&mut self) -> Option<ExpressionCoverage> {
+        self.context.take_coverage()
+    }
+
+    pub fn enable_cost_profile(&mut self) {
+        self.context.enable_cost_profile();
+    }
+
+    pub fn take_cost_profile(&mut self) -> Option<CostProfile> {
+        self.context.take_cost_profile()
+    }
 }
 
 impl CostTracker for Environment<'_, '_, '_> {
@@ -994,13 +1027,15 @@ impl<'a, 'b, 'hooks> Environment<'a, 'b, 'hooks> {
     ) -> Result<Value> {
         let clarity_version = self.contract_context.clarity_version.clone();
 
-        let parsed = ast::build_ast_with_rules(
+        let limits = self.global_context.limits;
+        let parsed = ast::build_ast_with_limits(
             contract_identifier,
             program,
             self,
             clarity_version,
             self.global_context.epoch_id,
             rules,
+            &limits,
         )?
         .expressions;
 
@@ -1049,13 +1084,15 @@ impl<'a, 'b, 'hooks> Environment<'a, 'b, 'hooks> {
         let contract_id = QualifiedContractIdentifier::transient();
         let clarity_version = self.contract_context.clarity_version.clone();
 
-        let parsed = ast::build_ast_with_rules(
+        let limits = self.global_context.limits;
+        let parsed = ast::build_ast_with_limits(
             &contract_id,
             program,
             self,
             clarity_version,
             self.global_context.epoch_id,
             rules,
+            &limits,
         )?
         .expressions;
 
@@ -1279,13 +1316,15 @@ impl<'a, 'b, 'hooks> Environment<'a, 'b, 'hooks> {
     ) -> Result<()> {
         let clarity_version = self.contract_context.clarity_version.clone();
 
-        let contract_ast = ast::build_ast_with_rules(
+        let limits = self.global_context.limits;
+        let contract_ast = ast::build_ast_with_limits(
             &contract_identifier,
             contract_content,
             self,
             clarity_version,
             self.global_context.epoch_id,
             ast_rules,
+            &limits,
         )?;
         self.initialize_contract_from_ast(
             contract_identifier,
@@ -1332,6 +1371,14 @@ impl<'a, 'b, 'hooks> Environment<'a, 'b, 'hooks> {
             let memory_use = contract_string.len() as u64;
             self.add_memory(memory_use)?;
 
+            if let Some(coverage) = self.global_context.coverage.as_mut() {
+                coverage.register_contract(
+                    &contract_identifier,
+                    contract_version,
+                    &contract_content.expressions,
+                );
+            }
+
             let result = Contract::initialize_from_ast(
                 contract_identifier.clone(),
                 contract_content,
@@ -1578,8 +1625,7 @@ impl<'a, 'b, 'hooks> Environment<'a, 'b, 'hooks> {
 impl<'a, 'hooks> GlobalContext<'a, 'hooks> {
     // Instantiate a new Global Context
     pub fn new(
-        mainnet: bool,
-        chain_id: u32,
+        network: NetworkId,
         database: ClarityDatabase<'a>,
         cost_track: LimitedCostTracker,
         epoch_id: StacksEpochId,
@@ -1590,17 +1636,69 @@ impl<'a, 'hooks> GlobalContext<'a, 'hooks> {
             read_only: Vec::new(),
             asset_maps: Vec::new(),
             event_batches: Vec::new(),
-            mainnet,
+            network,
             epoch_id,
-            chain_id,
             eval_hooks: None,
+            vm_observers: Vec::new(),
+            limits: VmLimits::for_epoch(epoch_id),
+            coverage: None,
         }
     }
 
+    /// Override the parser and interpreter limits for this context.
+    /// Used by private networks which do not run with the consensus defaults.
+    pub fn with_limits(mut self, limits: VmLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     pub fn is_top_level(&self) -> bool {
         self.asset_maps.len() == 0
     }
 
+    pub fn add_vm_observer(&mut self, observer: &'hooks mut dyn VmObserver) {
+        self.vm_observers.push(observer);
+    }
+
+    /// Start tracking expression-level coverage.  Does nothing if it is already being tracked.
+    pub fn enable_coverage(&mut self) {
+        if self.coverage.is_none() {
+            self.coverage = Some(ExpressionCoverage::new());
+        }
+    }
+
+    /// Stop tracking coverage, returning what was tracked so far
+    pub fn take_coverage(&mut self) -> Option<ExpressionCoverage> {
+        self.coverage.take()
+    }
+
+    /// Start attributing this transaction's costs to the functions that incur them.  Does
+    /// nothing if costs aren't being tracked.
+    pub fn enable_cost_profile(&mut self) {
+        self.cost_track.enable_cost_profile();
+    }
+
+    /// Stop profiling costs, returning the per-function profile gathered so far
+    pub fn take_cost_profile(&mut self) -> Option<CostProfile> {
+        self.cost_track.take_cost_profile()
+    }
+
+    /// Is any `VmObserver` installed?  Callers can check this to avoid building up an
+    /// observation that nobody will see.
+    pub fn is_observed(&self) -> bool {
+        !self.vm_observers.is_empty()
+    }
+
+    /// Run `f` on each installed `VmObserver`, in the order they were added
+    pub fn notify_vm_observers<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut dyn VmObserver),
+    {
+        for observer in self.vm_observers.iter_mut() {
+            f(&mut **observer);
+        }
+    }
+
     fn get_asset_map(&mut self) -> &mut AssetMap {
         self.asset_maps
             .last_mut()
@@ -1618,6 +1716,13 @@ impl<'a, 'hooks> GlobalContext<'a, 'hooks> {
             contract_identifier: contract_identifier.clone(),
             asset_name: asset_name.clone(),
         };
+        self.notify_vm_observers(|observer| {
+            observer.did_transfer_asset(&ObservedTransfer::NonFungibleToken {
+                sender,
+                asset: &asset_identifier,
+                value: &transfered,
+            })
+        });
         self.get_asset_map()
             .add_asset_transfer(sender, asset_identifier, transfered)
     }
@@ -1633,15 +1738,34 @@ impl<'a, 'hooks> GlobalContext<'a, 'hooks> {
             contract_identifier: contract_identifier.clone(),
             asset_name: asset_name.clone(),
         };
+        self.notify_vm_observers(|observer| {
+            observer.did_transfer_asset(&ObservedTransfer::FungibleToken {
+                sender,
+                asset: &asset_identifier,
+                amount: transfered,
+            })
+        });
         self.get_asset_map()
             .add_token_transfer(sender, asset_identifier, transfered)
     }
 
     pub fn log_stx_transfer(&mut self, sender: &PrincipalData, transfered: u128) -> Result<()> {
+        self.notify_vm_observers(|observer| {
+            observer.did_transfer_asset(&ObservedTransfer::Stx {
+                sender,
+                amount: transfered,
+            })
+        });
         self.get_asset_map().add_stx_transfer(sender, transfered)
     }
 
     pub fn log_stx_burn(&mut self, sender: &PrincipalData, transfered: u128) -> Result<()> {
+        self.notify_vm_observers(|observer| {
+            observer.did_transfer_asset(&ObservedTransfer::StxBurn {
+                sender,
+                amount: transfered,
+            })
+        });
         self.get_asset_map().add_stx_burn(sender, transfered)
     }
 
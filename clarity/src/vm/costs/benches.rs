@@ -0,0 +1,154 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Criterion benchmarks for the cost functions of Clarity natives.
+//!
+//! Each benchmark evaluates the native at a range of input sizes, where an input size means what
+//! it does to the native's cost function (the number of arguments for arithmetic, the length of
+//! the buffer for hashes).  Benchmarks are linked to their cost functions in
+//! `cost_functions.rs`, and run by `benches/cost_functions.rs`.  Hashes are measured on the
+//! implementations `vm::precompiles` uses.
+
+use criterion::{BenchmarkId, Criterion};
+
+use crate::vm::ast::{self, ASTRules};
+use crate::vm::contexts::{ContractContext, GlobalContext};
+use crate::vm::costs::cost_functions::{ClarityCostFunction, CostCurve};
+use crate::vm::costs::LimitedCostTracker;
+use crate::vm::database::MemoryBackingStore;
+use crate::vm::types::QualifiedContractIdentifier;
+use crate::vm::{eval_all, ClarityVersion};
+use stacks_common::types::NetworkId;
+use stacks_common::types::StacksEpochId;
+
+/// Input sizes to measure a cost function at, unless its cost is constant
+const INPUT_SIZES: &[u64] = &[1, 16, 128, 1024];
+
+/// Measure how long `program(n)` takes to evaluate, for each input size `n` of `cost_function`.
+/// Programs are parsed ahead of time, so only their evaluation is measured.
+fn bench_program<F>(c: &mut Criterion, cost_function: ClarityCostFunction, program: F)
+where
+    F: Fn(u64) -> String,
+{
+    let sizes = match cost_function.schedule().curve {
+        CostCurve::Constant => &INPUT_SIZES[..1],
+        _ => INPUT_SIZES,
+    };
+    let version = ClarityVersion::Clarity2;
+    let epoch = StacksEpochId::Epoch21;
+    let contract_id = QualifiedContractIdentifier::transient();
+
+    let mut group = c.benchmark_group(cost_function.get_name_str());
+    for size in sizes.iter() {
+        let program = program(*size);
+        let expressions = ast::build_ast_with_rules(
+            &contract_id,
+            &program,
+            &mut (),
+            version,
+            epoch,
+            ASTRules::PrecheckSize,
+        )
+        .expect("failed to parse benchmark program")
+        .expressions;
+
+        let mut store = MemoryBackingStore::new();
+        let mut global_context = GlobalContext::new(
+            NetworkId::testnet(),
+            store.as_clarity_db(),
+            LimitedCostTracker::new_free(),
+            epoch,
+        );
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
+            b.iter(|| {
+                let mut contract_context = ContractContext::new(contract_id.clone(), version);
+                global_context
+                    .execute(|g| eval_all(&expressions, &mut contract_context, g, None))
+                    .expect("failed to evaluate benchmark program")
+            })
+        });
+    }
+    group.finish();
+}
+
+/// `(op 1 1 ...)`, with `n` arguments
+fn variadic(op: &str, n: u64) -> String {
+    format!("({}{})", op, " 1".repeat(n as usize))
+}
+
+/// `(op 0x0000...)`, with an `n`-byte buffer
+fn buffer_arg(op: &str, n: u64) -> String {
+    format!("({} 0x{})", op, "00".repeat(n as usize))
+}
+
+pub fn add(c: &mut Criterion) {
+    bench_program(c, ClarityCostFunction::Add, |n| variadic("+", n));
+}
+
+pub fn sub(c: &mut Criterion) {
+    bench_program(c, ClarityCostFunction::Sub, |n| variadic("-", n));
+}
+
+pub fn mul(c: &mut Criterion) {
+    bench_program(c, ClarityCostFunction::Mul, |n| variadic("*", n));
+}
+
+pub fn sha256(c: &mut Criterion) {
+    bench_program(c, ClarityCostFunction::Sha256, |n| buffer_arg("sha256", n));
+}
+
+pub fn sha512(c: &mut Criterion) {
+    bench_program(c, ClarityCostFunction::Sha512, |n| buffer_arg("sha512", n));
+}
+
+pub fn sha512t256(c: &mut Criterion) {
+    bench_program(c, ClarityCostFunction::Sha512t256, |n| {
+        buffer_arg("sha512/256", n)
+    });
+}
+
+pub fn keccak256(c: &mut Criterion) {
+    bench_program(c, ClarityCostFunction::Keccak256, |n| {
+        buffer_arg("keccak256", n)
+    });
+}
+
+pub fn bitwise_and(c: &mut Criterion) {
+    bench_program(c, ClarityCostFunction::BitwiseAnd, |n| {
+        variadic("bit-and", n)
+    });
+}
+
+pub fn bitwise_or(c: &mut Criterion) {
+    bench_program(c, ClarityCostFunction::BitwiseOr, |n| variadic("bit-or", n));
+}
+
+pub fn bitwise_not(c: &mut Criterion) {
+    bench_program(c, ClarityCostFunction::BitwiseNot, |_| {
+        "(bit-not 1)".to_string()
+    });
+}
+
+pub fn bitwise_left_shift(c: &mut Criterion) {
+    bench_program(c, ClarityCostFunction::BitwiseLShift, |_| {
+        "(bit-shift-left 1 u64)".to_string()
+    });
+}
+
+pub fn bitwise_right_shift(c: &mut Criterion) {
+    bench_program(c, ClarityCostFunction::BitwiseRShift, |_| {
+        "(bit-shift-right 1 u64)".to_string()
+    });
+}
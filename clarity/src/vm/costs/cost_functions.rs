@@ -14,145 +14,182 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-define_named_enum!(ClarityCostFunction {
-    AnalysisTypeAnnotate("cost_analysis_type_annotate"),
-    AnalysisTypeCheck("cost_analysis_type_check"),
-    AnalysisTypeLookup("cost_analysis_type_lookup"),
-    AnalysisVisit("cost_analysis_visit"),
-    AnalysisIterableFunc("cost_analysis_iterable_func"),
-    AnalysisOptionCons("cost_analysis_option_cons"),
-    AnalysisOptionCheck("cost_analysis_option_check"),
-    AnalysisBindName("cost_analysis_bind_name"),
-    AnalysisListItemsCheck("cost_analysis_list_items_check"),
-    AnalysisCheckTupleGet("cost_analysis_check_tuple_get"),
-    AnalysisCheckTupleMerge("cost_analysis_check_tuple_merge"),
-    AnalysisCheckTupleCons("cost_analysis_check_tuple_cons"),
-    AnalysisTupleItemsCheck("cost_analysis_tuple_items_check"),
-    AnalysisCheckLet("cost_analysis_check_let"),
-    AnalysisLookupFunction("cost_analysis_lookup_function"),
-    AnalysisLookupFunctionTypes("cost_analysis_lookup_function_types"),
-    AnalysisLookupVariableConst("cost_analysis_lookup_variable_const"),
-    AnalysisLookupVariableDepth("cost_analysis_lookup_variable_depth"),
-    AstParse("cost_ast_parse"),
-    AstCycleDetection("cost_ast_cycle_detection"),
-    AnalysisStorage("cost_analysis_storage"),
-    AnalysisUseTraitEntry("cost_analysis_use_trait_entry"),
-    AnalysisGetFunctionEntry("cost_analysis_get_function_entry"),
-    AnalysisFetchContractEntry("cost_analysis_fetch_contract_entry"),
-    LookupVariableDepth("cost_lookup_variable_depth"),
-    LookupVariableSize("cost_lookup_variable_size"),
-    LookupFunction("cost_lookup_function"),
-    BindName("cost_bind_name"),
-    InnerTypeCheckCost("cost_inner_type_check_cost"),
-    UserFunctionApplication("cost_user_function_application"),
-    Let("cost_let"),
-    If("cost_if"),
-    Asserts("cost_asserts"),
-    Map("cost_map"),
-    Filter("cost_filter"),
-    Len("cost_len"),
-    ElementAt("cost_element_at"),
-    IndexOf("cost_index_of"),
-    Fold("cost_fold"),
-    ListCons("cost_list_cons"),
-    TypeParseStep("cost_type_parse_step"),
-    TupleGet("cost_tuple_get"),
-    TupleMerge("cost_tuple_merge"),
-    TupleCons("cost_tuple_cons"),
-    Add("cost_add"),
-    Sub("cost_sub"),
-    Mul("cost_mul"),
-    Div("cost_div"),
-    Geq("cost_geq"),
-    Leq("cost_leq"),
-    Le("cost_le"),
-    Ge("cost_ge"),
-    IntCast("cost_int_cast"),
-    Mod("cost_mod"),
-    Pow("cost_pow"),
-    Sqrti("cost_sqrti"),
-    Log2("cost_log2"),
-    Xor("cost_xor"),
-    Not("cost_not"),
-    Eq("cost_eq"),
-    Begin("cost_begin"),
-    Hash160("cost_hash160"),
-    Sha256("cost_sha256"),
-    Sha512("cost_sha512"),
-    Sha512t256("cost_sha512t256"),
-    Keccak256("cost_keccak256"),
-    Secp256k1recover("cost_secp256k1recover"),
-    Secp256k1verify("cost_secp256k1verify"),
-    Print("cost_print"),
-    SomeCons("cost_some_cons"),
-    OkCons("cost_ok_cons"),
-    ErrCons("cost_err_cons"),
-    DefaultTo("cost_default_to"),
-    UnwrapRet("cost_unwrap_ret"),
-    UnwrapErrOrRet("cost_unwrap_err_or_ret"),
-    IsOkay("cost_is_okay"),
-    IsNone("cost_is_none"),
-    IsErr("cost_is_err"),
-    IsSome("cost_is_some"),
-    Unwrap("cost_unwrap"),
-    UnwrapErr("cost_unwrap_err"),
-    TryRet("cost_try_ret"),
-    Match("cost_match"),
-    Or("cost_or"),
-    And("cost_and"),
-    Append("cost_append"),
-    Concat("cost_concat"),
-    AsMaxLen("cost_as_max_len"),
-    ContractCall("cost_contract_call"),
-    ContractOf("cost_contract_of"),
-    PrincipalOf("cost_principal_of"),
-    AtBlock("cost_at_block"),
-    LoadContract("cost_load_contract"),
-    CreateMap("cost_create_map"),
-    CreateVar("cost_create_var"),
-    CreateNft("cost_create_nft"),
-    CreateFt("cost_create_ft"),
-    FetchEntry("cost_fetch_entry"),
-    SetEntry("cost_set_entry"),
-    FetchVar("cost_fetch_var"),
-    SetVar("cost_set_var"),
-    ContractStorage("cost_contract_storage"),
-    BlockInfo("cost_block_info"),
-    StxBalance("cost_stx_balance"),
-    StxTransfer("cost_stx_transfer"),
-    FtMint("cost_ft_mint"),
-    FtTransfer("cost_ft_transfer"),
-    FtBalance("cost_ft_balance"),
-    FtSupply("cost_ft_get_supply"),
-    FtBurn("cost_ft_burn"),
-    NftMint("cost_nft_mint"),
-    NftTransfer("cost_nft_transfer"),
-    NftOwner("cost_nft_owner"),
-    NftBurn("cost_nft_burn"),
-    PoisonMicroblock("poison_microblock"),
-    BuffToIntLe("cost_buff_to_int_le"),
-    BuffToUIntLe("cost_buff_to_uint_le"),
-    BuffToIntBe("cost_buff_to_int_be"),
-    BuffToUIntBe("cost_buff_to_uint_be"),
-    IsStandard("cost_is_standard"),
-    PrincipalDestruct("cost_principal_destruct"),
-    PrincipalConstruct("cost_principal_construct"),
-    StringToInt("cost_string_to_int"),
-    StringToUInt("cost_string_to_uint"),
-    IntToAscii("cost_int_to_ascii"),
-    IntToUtf8("cost_int_to_utf8"),
-    GetBurnBlockInfo("cost_burn_block_info"),
-    StxGetAccount("cost_stx_account"),
-    Slice("cost_slice"),
-    ToConsensusBuff("cost_to_consensus_buff"),
-    FromConsensusBuff("cost_from_consensus_buff"),
-    StxTransferMemo("cost_stx_transfer_memo"),
-    ReplaceAt("cost_replace_at"),
-    AsContract("cost_as_contract"),
-    BitwiseAnd("cost_bitwise_and"),
-    BitwiseOr("cost_bitwise_or"),
-    BitwiseNot("cost_bitwise_not"),
-    BitwiseLShift("cost_bitwise_left_shift"),
-    BitwiseRShift("cost_bitwise_right_shift"),
-    Unimplemented("cost_unimplemented"),
+//! The Clarity cost functions, and the cost schedule they follow.
+//!
+//! Each cost function is charged through a function of the same name in the cost contract in
+//! effect (`costs-3`, for epoch 2.1 and later).  The schedule recorded here mirrors that contract:
+//! every cost function takes `arity` input sizes, and its runtime cost grows with them along
+//! `curve`.  Cost functions for natives should link the benchmark in `benches` that measures
+//! them, so that the schedule and the benchmark suite don't drift apart as natives are added.
+
+use clarity_derive::define_cost_functions;
+
+#[cfg(any(test, feature = "testing"))]
+use crate::vm::costs::benches;
+
+/// The form of a cost function's runtime curve in the cost contract, where `n` is its input size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CostCurve {
+    /// doesn't depend on `n`
+    Constant,
+    /// `a*n + b`
+    Linear,
+    /// `a*log2(n) + b`
+    LogN,
+    /// `a*n*log2(n) + b`
+    NLogN,
+}
+
+/// A cost function's entry in the cost schedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CostFunctionSchedule {
+    pub function: ClarityCostFunction,
+    /// How many input sizes the cost function takes
+    pub arity: usize,
+    pub curve: CostCurve,
+    /// The path of the benchmark that measures the cost function, relative to this module
+    pub benchmark: Option<&'static str>,
+}
+
+define_cost_functions!(ClarityCostFunction {
+    AnalysisTypeAnnotate("cost_analysis_type_annotate", arity = 1, curve = Linear),
+    AnalysisTypeCheck("cost_analysis_type_check", arity = 1, curve = Linear),
+    AnalysisTypeLookup("cost_analysis_type_lookup", arity = 1, curve = Linear),
+    AnalysisVisit("cost_analysis_visit", arity = 1, curve = Constant),
+    AnalysisIterableFunc("cost_analysis_iterable_func", arity = 1, curve = Linear),
+    AnalysisOptionCons("cost_analysis_option_cons", arity = 1, curve = Constant),
+    AnalysisOptionCheck("cost_analysis_option_check", arity = 1, curve = Constant),
+    AnalysisBindName("cost_analysis_bind_name", arity = 1, curve = Linear),
+    AnalysisListItemsCheck("cost_analysis_list_items_check", arity = 1, curve = Linear),
+    AnalysisCheckTupleGet("cost_analysis_check_tuple_get", arity = 1, curve = LogN),
+    AnalysisCheckTupleMerge("cost_analysis_check_tuple_merge", arity = 1, curve = NLogN),
+    AnalysisCheckTupleCons("cost_analysis_check_tuple_cons", arity = 1, curve = NLogN),
+    AnalysisTupleItemsCheck("cost_analysis_tuple_items_check", arity = 1, curve = Linear),
+    AnalysisCheckLet("cost_analysis_check_let", arity = 1, curve = Linear),
+    AnalysisLookupFunction("cost_analysis_lookup_function", arity = 1, curve = Constant),
+    AnalysisLookupFunctionTypes("cost_analysis_lookup_function_types", arity = 1, curve = Linear),
+    AnalysisLookupVariableConst("cost_analysis_lookup_variable_const", arity = 1, curve = Constant),
+    AnalysisLookupVariableDepth("cost_analysis_lookup_variable_depth", arity = 1, curve = NLogN),
+    AstParse("cost_ast_parse", arity = 1, curve = Linear),
+    AstCycleDetection("cost_ast_cycle_detection", arity = 1, curve = Linear),
+    AnalysisStorage("cost_analysis_storage", arity = 1, curve = Linear),
+    AnalysisUseTraitEntry("cost_analysis_use_trait_entry", arity = 1, curve = Linear),
+    AnalysisGetFunctionEntry("cost_analysis_get_function_entry", arity = 1, curve = Linear),
+    AnalysisFetchContractEntry("cost_analysis_fetch_contract_entry", arity = 1, curve = Linear),
+    LookupVariableDepth("cost_lookup_variable_depth", arity = 1, curve = Linear),
+    LookupVariableSize("cost_lookup_variable_size", arity = 1, curve = Linear),
+    LookupFunction("cost_lookup_function", arity = 1, curve = Constant),
+    BindName("cost_bind_name", arity = 1, curve = Constant),
+    InnerTypeCheckCost("cost_inner_type_check_cost", arity = 1, curve = Linear),
+    UserFunctionApplication("cost_user_function_application", arity = 1, curve = Linear),
+    Let("cost_let", arity = 1, curve = Linear),
+    If("cost_if", arity = 1, curve = Constant),
+    Asserts("cost_asserts", arity = 1, curve = Constant),
+    Map("cost_map", arity = 1, curve = Linear),
+    Filter("cost_filter", arity = 1, curve = Constant),
+    Len("cost_len", arity = 1, curve = Constant),
+    ElementAt("cost_element_at", arity = 1, curve = Constant),
+    IndexOf("cost_index_of", arity = 1, curve = Linear),
+    Fold("cost_fold", arity = 1, curve = Constant),
+    ListCons("cost_list_cons", arity = 1, curve = Linear),
+    TypeParseStep("cost_type_parse_step", arity = 1, curve = Constant),
+    TupleGet("cost_tuple_get", arity = 1, curve = NLogN),
+    TupleMerge("cost_tuple_merge", arity = 1, curve = Linear),
+    TupleCons("cost_tuple_cons", arity = 1, curve = NLogN),
+    Add("cost_add", arity = 1, curve = Linear, bench = benches::add),
+    Sub("cost_sub", arity = 1, curve = Linear, bench = benches::sub),
+    Mul("cost_mul", arity = 1, curve = Linear, bench = benches::mul),
+    Div("cost_div", arity = 1, curve = Linear),
+    Geq("cost_geq", arity = 1, curve = Linear),
+    Leq("cost_leq", arity = 1, curve = Linear),
+    Le("cost_le", arity = 1, curve = Linear),
+    Ge("cost_ge", arity = 1, curve = Linear),
+    IntCast("cost_int_cast", arity = 1, curve = Constant),
+    Mod("cost_mod", arity = 1, curve = Constant),
+    Pow("cost_pow", arity = 1, curve = Constant),
+    Sqrti("cost_sqrti", arity = 1, curve = Constant),
+    Log2("cost_log2", arity = 1, curve = Constant),
+    Xor("cost_xor", arity = 1, curve = Linear),
+    Not("cost_not", arity = 1, curve = Constant),
+    Eq("cost_eq", arity = 1, curve = Linear),
+    Begin("cost_begin", arity = 1, curve = Constant),
+    Hash160("cost_hash160", arity = 1, curve = Linear),
+    Sha256("cost_sha256", arity = 1, curve = Linear, bench = benches::sha256),
+    Sha512("cost_sha512", arity = 1, curve = Linear, bench = benches::sha512),
+    Sha512t256("cost_sha512t256", arity = 1, curve = Linear, bench = benches::sha512t256),
+    Keccak256("cost_keccak256", arity = 1, curve = Linear, bench = benches::keccak256),
+    Secp256k1recover("cost_secp256k1recover", arity = 1, curve = Constant),
+    Secp256k1verify("cost_secp256k1verify", arity = 1, curve = Constant),
+    Print("cost_print", arity = 1, curve = Linear),
+    SomeCons("cost_some_cons", arity = 1, curve = Constant),
+    OkCons("cost_ok_cons", arity = 1, curve = Constant),
+    ErrCons("cost_err_cons", arity = 1, curve = Constant),
+    DefaultTo("cost_default_to", arity = 1, curve = Constant),
+    UnwrapRet("cost_unwrap_ret", arity = 1, curve = Constant),
+    UnwrapErrOrRet("cost_unwrap_err_or_ret", arity = 1, curve = Constant),
+    IsOkay("cost_is_okay", arity = 1, curve = Constant),
+    IsNone("cost_is_none", arity = 1, curve = Constant),
+    IsErr("cost_is_err", arity = 1, curve = Constant),
+    IsSome("cost_is_some", arity = 1, curve = Constant),
+    Unwrap("cost_unwrap", arity = 1, curve = Constant),
+    UnwrapErr("cost_unwrap_err", arity = 1, curve = Constant),
+    TryRet("cost_try_ret", arity = 1, curve = Constant),
+    Match("cost_match", arity = 1, curve = Constant),
+    Or("cost_or", arity = 1, curve = Linear),
+    And("cost_and", arity = 1, curve = Linear),
+    Append("cost_append", arity = 1, curve = Linear),
+    Concat("cost_concat", arity = 1, curve = Linear),
+    AsMaxLen("cost_as_max_len", arity = 1, curve = Constant),
+    ContractCall("cost_contract_call", arity = 1, curve = Constant),
+    ContractOf("cost_contract_of", arity = 1, curve = Constant),
+    PrincipalOf("cost_principal_of", arity = 1, curve = Constant),
+    AtBlock("cost_at_block", arity = 1, curve = Constant),
+    LoadContract("cost_load_contract", arity = 1, curve = Linear),
+    CreateMap("cost_create_map", arity = 1, curve = Linear),
+    CreateVar("cost_create_var", arity = 1, curve = Linear),
+    CreateNft("cost_create_nft", arity = 1, curve = Linear),
+    CreateFt("cost_create_ft", arity = 1, curve = Constant),
+    FetchEntry("cost_fetch_entry", arity = 1, curve = Linear),
+    SetEntry("cost_set_entry", arity = 1, curve = Linear),
+    FetchVar("cost_fetch_var", arity = 1, curve = Linear),
+    SetVar("cost_set_var", arity = 1, curve = Linear),
+    ContractStorage("cost_contract_storage", arity = 1, curve = Linear),
+    BlockInfo("cost_block_info", arity = 1, curve = Constant),
+    StxBalance("cost_stx_balance", arity = 1, curve = Constant),
+    StxTransfer("cost_stx_transfer", arity = 1, curve = Constant),
+    FtMint("cost_ft_mint", arity = 1, curve = Constant),
+    FtTransfer("cost_ft_transfer", arity = 1, curve = Constant),
+    FtBalance("cost_ft_balance", arity = 1, curve = Constant),
+    FtSupply("cost_ft_get_supply", arity = 1, curve = Constant),
+    FtBurn("cost_ft_burn", arity = 1, curve = Constant),
+    NftMint("cost_nft_mint", arity = 1, curve = Linear),
+    NftTransfer("cost_nft_transfer", arity = 1, curve = Linear),
+    NftOwner("cost_nft_owner", arity = 1, curve = Linear),
+    NftBurn("cost_nft_burn", arity = 1, curve = Linear),
+    PoisonMicroblock("poison_microblock", arity = 1, curve = Constant),
+    BuffToIntLe("cost_buff_to_int_le", arity = 1, curve = Constant),
+    BuffToUIntLe("cost_buff_to_uint_le", arity = 1, curve = Constant),
+    BuffToIntBe("cost_buff_to_int_be", arity = 1, curve = Constant),
+    BuffToUIntBe("cost_buff_to_uint_be", arity = 1, curve = Constant),
+    IsStandard("cost_is_standard", arity = 1, curve = Constant),
+    PrincipalDestruct("cost_principal_destruct", arity = 1, curve = Constant),
+    PrincipalConstruct("cost_principal_construct", arity = 1, curve = Constant),
+    StringToInt("cost_string_to_int", arity = 1, curve = Constant),
+    StringToUInt("cost_string_to_uint", arity = 1, curve = Constant),
+    IntToAscii("cost_int_to_ascii", arity = 1, curve = Constant),
+    IntToUtf8("cost_int_to_utf8", arity = 1, curve = Constant),
+    GetBurnBlockInfo("cost_burn_block_info", arity = 1, curve = Constant),
+    StxGetAccount("cost_stx_account", arity = 1, curve = Constant),
+    Slice("cost_slice", arity = 1, curve = Constant),
+    ToConsensusBuff("cost_to_consensus_buff", arity = 1, curve = Linear),
+    FromConsensusBuff("cost_from_consensus_buff", arity = 1, curve = NLogN),
+    StxTransferMemo("cost_stx_transfer_memo", arity = 1, curve = Constant),
+    ReplaceAt("cost_replace_at", arity = 1, curve = Linear),
+    AsContract("cost_as_contract", arity = 1, curve = Constant),
+    BitwiseAnd("cost_bitwise_and", arity = 1, curve = Linear, bench = benches::bitwise_and),
+    BitwiseOr("cost_bitwise_or", arity = 1, curve = Linear, bench = benches::bitwise_or),
+    BitwiseNot("cost_bitwise_not", arity = 1, curve = Constant, bench = benches::bitwise_not),
+    BitwiseLShift("cost_bitwise_left_shift", arity = 1, curve = Constant, bench = benches::bitwise_left_shift),
+    BitwiseRShift("cost_bitwise_right_shift", arity = 1, curve = Constant, bench = benches::bitwise_right_shift),
+    Unimplemented("cost_unimplemented", arity = 1, curve = Constant),
 });
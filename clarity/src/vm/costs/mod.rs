@@ -21,10 +21,11 @@ use std::{cmp, fmt};
 use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
 use serde::{Deserialize, Serialize};
 
-use crate::boot_util::boot_code_id;
+use crate::boot_util::network_boot_code_id;
 use crate::vm::ast::ContractAST;
 use crate::vm::contexts::{ContractContext, Environment, GlobalContext, OwnedEnvironment};
 use crate::vm::costs::cost_functions::ClarityCostFunction;
+use crate::vm::costs::profile::CostProfile;
 use crate::vm::database::{clarity_store::NullBackingStore, ClarityDatabase};
 use crate::vm::errors::{Error, InterpreterResult};
 use crate::vm::types::signatures::FunctionType::Fixed;
@@ -35,10 +36,13 @@ use crate::vm::types::{
     TypeSignature, NONE,
 };
 use crate::vm::{ast, eval_all, ClarityName, SymbolicExpression, Value};
-use stacks_common::types::StacksEpochId;
+use stacks_common::types::{NetworkId, StacksEpochId};
 
+#[cfg(any(test, feature = "testing"))]
+pub mod benches;
 pub mod constants;
 pub mod cost_functions;
+pub mod profile;
 
 type Result<T> = std::result::Result<T, CostErrors>;
 
@@ -240,8 +244,9 @@ pub struct TrackerData {
     ///  the Clarity cost functions. If the tracker *is* free, then those functions do not need to be
     ///  evaluated, so no epoch identifier is necessary.
     epoch: StacksEpochId,
-    mainnet: bool,
-    chain_id: u32,
+    network: NetworkId,
+    /// Per-function breakdown of `total`, if profiling is enabled
+    profile: Option<CostProfile>,
 }
 
 #[derive(Clone)]
@@ -321,8 +326,11 @@ pub enum CostErrors {
     CostContractLoadFailure,
 }
 
-fn load_state_summary(mainnet: bool, clarity_db: &mut ClarityDatabase) -> Result<CostStateSummary> {
-    let cost_voting_contract = boot_code_id("cost-voting", mainnet);
+fn load_state_summary(
+    network: &NetworkId,
+    clarity_db: &mut ClarityDatabase,
+) -> Result<CostStateSummary> {
+    let cost_voting_contract = network_boot_code_id("cost-voting", network);
 
     let clarity_epoch = clarity_db.get_clarity_epoch_version();
     let last_processed_at = match clarity_db.get_value(
@@ -350,12 +358,12 @@ fn load_state_summary(mainnet: bool, clarity_db: &mut ClarityDatabase) -> Result
 }
 
 fn store_state_summary(
-    mainnet: bool,
+    network: &NetworkId,
     clarity_db: &mut ClarityDatabase,
     to_store: &CostStateSummary,
 ) -> Result<()> {
     let block_height = clarity_db.get_current_block_height();
-    let cost_voting_contract = boot_code_id("cost-voting", mainnet);
+    let cost_voting_contract = network_boot_code_id("cost-voting", network);
     let epoch = clarity_db.get_clarity_epoch_version();
     clarity_db
         .put_value(
@@ -387,7 +395,7 @@ fn store_state_summary(
 ///   fork.
 ///
 fn load_cost_functions(
-    mainnet: bool,
+    network: &NetworkId,
     clarity_db: &mut ClarityDatabase,
     apply_updates: bool,
 ) -> Result<CostStateSummary> {
@@ -402,7 +410,7 @@ fn load_cost_functions(
         .map(|result| result.value)
         .unwrap_or(Value::UInt(0))
         .expect_u128();
-    let cost_voting_contract = boot_code_id("cost-voting", mainnet);
+    let cost_voting_contract = network_boot_code_id("cost-voting", network);
     let confirmed_proposals_count = clarity_db
         .lookup_variable_unknown_descriptor(
             &cost_voting_contract,
@@ -417,7 +425,7 @@ fn load_cost_functions(
 
     // we need to process any confirmed proposals in the range [fetch-start, fetch-end)
     let (fetch_start, fetch_end) = (last_processed_count, confirmed_proposals_count);
-    let mut state_summary = load_state_summary(mainnet, clarity_db)?;
+    let mut state_summary = load_state_summary(network, clarity_db)?;
     if !apply_updates {
         return Ok(state_summary);
     }
@@ -564,7 +572,7 @@ fn load_cost_functions(
             }
         };
 
-        if target_contract == boot_code_id("costs", mainnet) {
+        if target_contract == network_boot_code_id("costs", network) {
             // refering to one of the boot code cost functions
             let target = match ClarityCostFunction::lookup_by_name(&target_function) {
                 Some(ClarityCostFunction::Unimplemented) => {
@@ -628,7 +636,7 @@ fn load_cost_functions(
         }
     }
     if confirmed_proposals_count > last_processed_count {
-        store_state_summary(mainnet, clarity_db, &state_summary)?;
+        store_state_summary(network, clarity_db, &state_summary)?;
         clarity_db
             .put_value(
                 "vm-costs::last_processed_count",
@@ -643,8 +651,7 @@ fn load_cost_functions(
 
 impl LimitedCostTracker {
     pub fn new(
-        mainnet: bool,
-        chain_id: u32,
+        network: NetworkId,
         limit: ExecutionCost,
         clarity_db: &mut ClarityDatabase,
         epoch: StacksEpochId,
@@ -658,8 +665,8 @@ impl LimitedCostTracker {
             total: ExecutionCost::zero(),
             memory: 0,
             epoch,
-            mainnet,
-            chain_id,
+            network,
+            profile: None,
         };
         assert!(clarity_db.is_stack_empty());
         cost_tracker.load_costs(clarity_db, true)?;
@@ -667,8 +674,7 @@ impl LimitedCostTracker {
     }
 
     pub fn new_mid_block(
-        mainnet: bool,
-        chain_id: u32,
+        network: NetworkId,
         limit: ExecutionCost,
         clarity_db: &mut ClarityDatabase,
         epoch: StacksEpochId,
@@ -682,8 +688,8 @@ impl LimitedCostTracker {
             total: ExecutionCost::zero(),
             memory: 0,
             epoch,
-            mainnet,
-            chain_id,
+            network,
+            profile: None,
         };
         cost_tracker.load_costs(clarity_db, false)?;
         Ok(Self::Limited(cost_tracker))
@@ -695,12 +701,9 @@ impl LimitedCostTracker {
         epoch: StacksEpochId,
         use_mainnet: bool,
     ) -> Result<LimitedCostTracker> {
-        use crate::vm::tests::test_only_mainnet_to_chain_id;
-        let chain_id = test_only_mainnet_to_chain_id(use_mainnet);
         assert!(clarity_db.is_stack_empty());
         LimitedCostTracker::new(
-            use_mainnet,
-            chain_id,
+            NetworkId::from_mainnet(use_mainnet),
             ExecutionCost::max_value(),
             clarity_db,
             epoch,
@@ -733,15 +736,15 @@ impl TrackerData {
     fn load_costs(&mut self, clarity_db: &mut ClarityDatabase, apply_updates: bool) -> Result<()> {
         clarity_db.begin();
         let epoch_id = clarity_db.get_clarity_epoch_version();
-        let boot_costs_id = boot_code_id(
+        let boot_costs_id = network_boot_code_id(
             &LimitedCostTracker::default_cost_contract_for_epoch(epoch_id),
-            self.mainnet,
+            &self.network,
         );
 
         let CostStateSummary {
             contract_call_circuits,
             mut cost_function_references,
-        } = load_cost_functions(self.mainnet, clarity_db, apply_updates).map_err(|e| {
+        } = load_cost_functions(&self.network, clarity_db, apply_updates).map_err(|e| {
             clarity_db.roll_back();
             e
         })?;
@@ -833,6 +836,52 @@ impl LimitedCostTracker {
             Self::Free => u64::MAX,
         }
     }
+    /// Start attributing costs to the functions that incur them.  Does nothing if the tracker
+    /// is free (since it has no costs to attribute) or if profiling is already enabled.
+    pub fn enable_cost_profile(&mut self) {
+        match self {
+            Self::Limited(ref mut data) => {
+                if data.profile.is_none() {
+                    data.profile = Some(CostProfile::new());
+                }
+            }
+            Self::Free => {}
+        }
+    }
+    /// Stop profiling, returning the profile gathered so far
+    pub fn take_cost_profile(&mut self) -> Option<CostProfile> {
+        match self {
+            Self::Limited(ref mut data) => data.profile.take(),
+            Self::Free => None,
+        }
+    }
+    /// Tell the profile (if any) that `function` is starting
+    pub fn profile_function_entry(&mut self, function: &str) {
+        if let Self::Limited(TrackerData {
+            profile: Some(ref mut profile),
+            ..
+        }) = self
+        {
+            profile.enter_function(function);
+        }
+    }
+    /// Tell the profile (if any) that the innermost function has returned
+    pub fn profile_function_exit(&mut self) {
+        if let Self::Limited(TrackerData {
+            profile: Some(ref mut profile),
+            ..
+        }) = self
+        {
+            profile.exit_function();
+        }
+    }
+    /// Is a cost profile being gathered?
+    pub fn is_profiling(&self) -> bool {
+        match self {
+            Self::Limited(TrackerData { profile, .. }) => profile.is_some(),
+            Self::Free => false,
+        }
+    }
 }
 
 fn parse_cost(
@@ -887,17 +936,11 @@ fn compute_cost(
     input_sizes: &[u64],
     eval_in_epoch: StacksEpochId,
 ) -> Result<ExecutionCost> {
-    let mainnet = cost_tracker.mainnet;
-    let chain_id = cost_tracker.chain_id;
+    let network = cost_tracker.network;
     let mut null_store = NullBackingStore::new();
     let conn = null_store.as_clarity_db();
-    let mut global_context = GlobalContext::new(
-        mainnet,
-        chain_id,
-        conn,
-        LimitedCostTracker::new_free(),
-        eval_in_epoch,
-    );
+    let mut global_context =
+        GlobalContext::new(network, conn, LimitedCostTracker::new_free(), eval_in_epoch);
 
     let cost_contract = cost_tracker
         .cost_contracts
@@ -931,6 +974,9 @@ fn compute_cost(
 
 fn add_cost(s: &mut TrackerData, cost: ExecutionCost) -> std::result::Result<(), CostErrors> {
     s.total.add(&cost)?;
+    if let Some(profile) = s.profile.as_mut() {
+        profile.record(&cost);
+    }
     if cfg!(feature = "disable-costs") {
         // Disable check for exceeding the cost limit to allow mining large blocks for profiling purposes.
         return Ok(());
@@ -0,0 +1,212 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Per-function cost profiles.
+//!
+//! When profiling is enabled on a `LimitedCostTracker` (see `GlobalContext::enable_cost_profile()`),
+//! every cost it is charged is attributed to the user-defined function that was executing at the
+//! time -- including functions of other contracts reached through `contract-call?`.  Costs
+//! charged outside of any function, like those for parsing, analyzing, and initializing a
+//! contract, are attributed to the top level.  The profile is retrieved with
+//! `GlobalContext::take_cost_profile()`.
+
+use std::collections::BTreeMap;
+
+use crate::vm::costs::ExecutionCost;
+
+/// The costs attributed to one user-defined function
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCost {
+    /// How many times the function was called
+    pub calls: u64,
+    /// Cost of the function's own code, excluding the functions it called
+    pub self_cost: ExecutionCost,
+    /// Cost of the function's own code and of everything it called
+    pub total_cost: ExecutionCost,
+}
+
+impl FunctionCost {
+    fn new() -> FunctionCost {
+        FunctionCost {
+            calls: 0,
+            self_cost: ExecutionCost::zero(),
+            total_cost: ExecutionCost::zero(),
+        }
+    }
+}
+
+/// The costs a cost tracker was charged, broken down by user-defined function
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostProfile {
+    /// Cost charged outside of any user-defined function
+    pub top_level: ExecutionCost,
+    /// Costs of each function, keyed by function identifier (`<contract>:<function>`)
+    functions: BTreeMap<String, FunctionCost>,
+    /// The functions currently executing, innermost last, with what each has cost so far
+    call_stack: Vec<(String, ExecutionCost)>,
+}
+
+impl CostProfile {
+    pub fn new() -> CostProfile {
+        CostProfile {
+            top_level: ExecutionCost::zero(),
+            functions: BTreeMap::new(),
+            call_stack: vec![],
+        }
+    }
+
+    /// The costs of `function`, identified as `<contract>:<function>`, if it was called
+    pub fn get(&self, function: &str) -> Option<&FunctionCost> {
+        self.functions.get(function)
+    }
+
+    /// The costs of every function called, ordered by function identifier
+    pub fn functions(&self) -> impl Iterator<Item = (&str, &FunctionCost)> {
+        self.functions
+            .iter()
+            .map(|(function, cost)| (function.as_str(), cost))
+    }
+
+    /// The functions called, most expensive first.  A function's expense is its total cost, as a
+    /// scalar proportion of `limit` (see `ExecutionCost::proportion_dot_product()`).  Ties are
+    /// broken by function identifier.
+    pub fn ranked(&self, limit: &ExecutionCost) -> Vec<(&str, &FunctionCost)> {
+        let mut ranked: Vec<_> = self
+            .functions()
+            .map(|(function, cost)| {
+                (
+                    cost.total_cost.proportion_dot_product(limit, 1_000_000),
+                    function,
+                    cost,
+                )
+            })
+            .collect();
+        ranked.sort_by(|(a_expense, a_name, _), (b_expense, b_name, _)| {
+            b_expense.cmp(a_expense).then_with(|| a_name.cmp(b_name))
+        });
+        ranked
+            .into_iter()
+            .map(|(_, function, cost)| (function, cost))
+            .collect()
+    }
+
+    /// A call to `function` is starting
+    pub fn enter_function(&mut self, function: &str) {
+        self.functions
+            .entry(function.to_string())
+            .or_insert_with(FunctionCost::new)
+            .calls += 1;
+        self.call_stack
+            .push((function.to_string(), ExecutionCost::zero()));
+    }
+
+    /// The innermost executing function has returned (or failed)
+    pub fn exit_function(&mut self) {
+        let (function, cost) = match self.call_stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        if let Some(function_cost) = self.functions.get_mut(&function) {
+            saturating_add(&mut function_cost.total_cost, &cost);
+        }
+        // the caller's total includes everything this call cost
+        if let Some((_, caller_cost)) = self.call_stack.last_mut() {
+            saturating_add(caller_cost, &cost);
+        }
+    }
+
+    /// Attribute `cost` to the innermost executing function
+    pub fn record(&mut self, cost: &ExecutionCost) {
+        match self.call_stack.last_mut() {
+            Some((function, function_total)) => {
+                saturating_add(function_total, cost);
+                if let Some(function_cost) = self.functions.get_mut(function.as_str()) {
+                    saturating_add(&mut function_cost.self_cost, cost);
+                }
+            }
+            None => saturating_add(&mut self.top_level, cost),
+        }
+    }
+}
+
+impl Default for CostProfile {
+    fn default() -> CostProfile {
+        CostProfile::new()
+    }
+}
+
+/// A profile only reports costs, so it saturates rather than failing the way the tracker's own
+/// total does
+fn saturating_add(total: &mut ExecutionCost, cost: &ExecutionCost) {
+    total.runtime = total.runtime.saturating_add(cost.runtime);
+    total.read_count = total.read_count.saturating_add(cost.read_count);
+    total.read_length = total.read_length.saturating_add(cost.read_length);
+    total.write_count = total.write_count.saturating_add(cost.write_count);
+    total.write_length = total.write_length.saturating_add(cost.write_length);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cost(runtime: u64, read_count: u64) -> ExecutionCost {
+        ExecutionCost {
+            runtime,
+            read_count,
+            ..ExecutionCost::zero()
+        }
+    }
+
+    #[test]
+    fn test_cost_profile_attribution() {
+        let mut profile = CostProfile::new();
+        profile.record(&cost(1, 0));
+
+        profile.enter_function("a.outer:f");
+        profile.record(&cost(10, 1));
+        for _ in 0..2 {
+            profile.enter_function("b.callee:g");
+            profile.record(&cost(100, 2));
+            profile.exit_function();
+        }
+        profile.record(&cost(10, 0));
+        profile.exit_function();
+
+        profile.record(&cost(1, 0));
+
+        assert_eq!(profile.top_level, cost(2, 0));
+
+        let outer = profile.get("a.outer:f").unwrap();
+        assert_eq!(outer.calls, 1);
+        assert_eq!(outer.self_cost, cost(20, 1));
+        assert_eq!(outer.total_cost, cost(220, 5));
+
+        let callee = profile.get("b.callee:g").unwrap();
+        assert_eq!(callee.calls, 2);
+        assert_eq!(callee.self_cost, cost(200, 4));
+        assert_eq!(callee.total_cost, cost(200, 4));
+
+        let ranked: Vec<_> = profile
+            .ranked(&cost(1000, 10))
+            .into_iter()
+            .map(|(function, _)| function)
+            .collect();
+        assert_eq!(ranked, vec!["a.outer:f", "b.callee:g"]);
+
+        // unbalanced exits are ignored
+        profile.exit_function();
+        assert_eq!(profile.get("a.outer:f").unwrap().total_cost, cost(220, 5));
+    }
+}
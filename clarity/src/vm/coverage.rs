@@ -1,10 +1,13 @@
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fs::File,
     io::Write,
 };
 
+use crate::vm::functions::lookup_reserved_functions;
+use crate::vm::representations::{ClarityName, Span};
 use crate::vm::types::QualifiedContractIdentifier;
+use crate::vm::version::ClarityVersion;
 use crate::vm::SymbolicExpression;
 use serde_json::Value as JsonValue;
 
@@ -248,3 +251,206 @@ impl EvalHook for CoverageReporter {
     ) {
     }
 }
+
+/// Expression-level coverage of the contracts a `GlobalContext` executes, enabled with
+/// `GlobalContext::enable_coverage()`.
+///
+/// Every function application (i.e. every list expression that `eval()` can be handed) is
+/// identified by its contract and its source span, and counted each time it is evaluated.  Contracts
+/// deployed while coverage is enabled are registered automatically, which is what lets a report
+/// list the expressions that never ran.  Everything is kept in sorted maps, so the same execution
+/// always produces the same report.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpressionCoverage {
+    contracts: BTreeMap<QualifiedContractIdentifier, ContractCoverage>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ContractCoverage {
+    /// Spans of the function applications in the contract's executable code
+    executable: BTreeSet<Span>,
+    /// How many times each span was evaluated
+    hits: BTreeMap<Span, u64>,
+}
+
+impl ExpressionCoverage {
+    pub fn new() -> ExpressionCoverage {
+        ExpressionCoverage::default()
+    }
+
+    /// Register the function applications in a contract's code as executable
+    pub fn register_contract(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        version: ClarityVersion,
+        exprs: &[SymbolicExpression],
+    ) {
+        let executable = Self::executable_spans(exprs, &version);
+        self.contracts
+            .entry(contract.clone())
+            .or_default()
+            .executable
+            .extend(executable);
+    }
+
+    /// Count an evaluation of `expr` in `contract`.  Only function applications with a source
+    /// span are counted.
+    pub fn record_eval(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        expr: &SymbolicExpression,
+    ) {
+        if expr.match_list().is_none() || expr.span == Span::zero() {
+            return;
+        }
+        if let Some(coverage) = self.contracts.get_mut(contract) {
+            *coverage.hits.entry(expr.span.clone()).or_insert(0) += 1;
+        } else {
+            let mut coverage = ContractCoverage::default();
+            coverage.hits.insert(expr.span.clone(), 1);
+            self.contracts.insert(contract.clone(), coverage);
+        }
+    }
+
+    /// How many times the expression at `span` in `contract` was evaluated
+    pub fn hits(&self, contract: &QualifiedContractIdentifier, span: &Span) -> u64 {
+        self.contracts
+            .get(contract)
+            .and_then(|coverage| coverage.hits.get(span))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    /// Add `other`'s registrations and counts to this coverage
+    pub fn merge(&mut self, other: &ExpressionCoverage) {
+        for (contract, other_coverage) in other.contracts.iter() {
+            let coverage = self.contracts.entry(contract.clone()).or_default();
+            coverage
+                .executable
+                .extend(other_coverage.executable.iter().cloned());
+            for (span, count) in other_coverage.hits.iter() {
+                *coverage.hits.entry(span.clone()).or_insert(0) += *count;
+            }
+        }
+    }
+
+    /// Write an lcov report.  Each contract gets one record, whose source file is named by
+    /// `src_files` (or is the contract identifier, if it isn't there).  Besides the usual `DA`
+    /// line counts -- where a line's count is that of the most-evaluated expression starting on
+    /// it -- each record has one `EXPR:<start line>,<start column>,<end line>,<end column>,<count>`
+    /// entry per expression, followed by `EXPRF`/`EXPRH` totals of the expressions found and hit.
+    pub fn write_lcov<W: Write>(
+        &self,
+        out: &mut W,
+        src_files: &HashMap<QualifiedContractIdentifier, String>,
+    ) -> std::io::Result<()> {
+        for (contract, coverage) in self.contracts.iter() {
+            let spans: BTreeSet<&Span> = coverage
+                .executable
+                .iter()
+                .chain(coverage.hits.keys())
+                .collect();
+            let count = |span: &Span| coverage.hits.get(span).cloned().unwrap_or(0);
+
+            let mut lines = BTreeMap::new();
+            for span in spans.iter() {
+                let line_count = lines.entry(span.start_line).or_insert(0);
+                *line_count = std::cmp::max(*line_count, count(span));
+            }
+
+            let contract_name = contract.to_string();
+            writeln!(out, "TN:{}", &contract_name)?;
+            writeln!(
+                out,
+                "SF:{}",
+                src_files.get(contract).unwrap_or(&contract_name)
+            )?;
+            for span in spans.iter() {
+                writeln!(
+                    out,
+                    "EXPR:{},{},{},{},{}",
+                    span.start_line,
+                    span.start_column,
+                    span.end_line,
+                    span.end_column,
+                    count(span)
+                )?;
+            }
+            writeln!(out, "EXPRF:{}", spans.len())?;
+            writeln!(
+                out,
+                "EXPRH:{}",
+                spans.iter().filter(|span| count(span) > 0).count()
+            )?;
+            for (line, line_count) in lines.iter() {
+                writeln!(out, "DA:{},{}", line, line_count)?;
+            }
+            writeln!(out, "LF:{}", lines.len())?;
+            writeln!(
+                out,
+                "LH:{}",
+                lines.values().filter(|count| **count > 0).count()
+            )?;
+            writeln!(out, "end_of_record")?;
+        }
+        Ok(())
+    }
+
+    /// The spans of the function applications in the executable parts of `exprs`: function
+    /// bodies, and the values of constants, data vars and bounded token supplies.  An application
+    /// is a list headed by the name of a native function or of a function the contract defines;
+    /// other lists (like `let` bindings or tuple entries) are never evaluated on their own.
+    fn executable_spans(exprs: &[SymbolicExpression], version: &ClarityVersion) -> Vec<Span> {
+        let mut defined_functions: HashSet<&ClarityName> = HashSet::new();
+        let mut frontier = vec![];
+        for expression in exprs.iter() {
+            match DefineFunctionsParsed::try_parse(expression).ok().flatten() {
+                Some(DefineFunctionsParsed::Constant { name: _, value }) => {
+                    frontier.push(value);
+                }
+                Some(DefineFunctionsParsed::PrivateFunction { signature, body })
+                | Some(DefineFunctionsParsed::PublicFunction { signature, body })
+                | Some(DefineFunctionsParsed::ReadOnlyFunction { signature, body }) => {
+                    if let Some(name) = signature.first().and_then(|name| name.match_atom()) {
+                        defined_functions.insert(name);
+                    }
+                    frontier.push(body);
+                }
+                Some(DefineFunctionsParsed::BoundedFungibleToken {
+                    name: _,
+                    max_supply,
+                }) => {
+                    frontier.push(max_supply);
+                }
+                Some(DefineFunctionsParsed::PersistedVariable {
+                    name: _,
+                    data_type: _,
+                    initial,
+                }) => {
+                    frontier.push(initial);
+                }
+                Some(_) => {}
+                None => frontier.push(expression),
+            }
+        }
+
+        let mut spans = vec![];
+        while let Some(cur_expr) = frontier.pop() {
+            if let Some(children) = cur_expr.match_list() {
+                let is_application = children
+                    .first()
+                    .and_then(|head| head.match_atom())
+                    .map(|name| {
+                        defined_functions.contains(name)
+                            || lookup_reserved_functions(name, version).is_some()
+                    })
+                    .unwrap_or(false);
+                if is_application && cur_expr.span != Span::zero() {
+                    spans.push(cur_expr.span.clone());
+                }
+                frontier.extend(children);
+            }
+        }
+        spans
+    }
+}
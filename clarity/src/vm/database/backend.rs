@@ -0,0 +1,92 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Pluggable data stores for the Clarity VM.
+//!
+//! A `ClarityBackend` is anything that can hand the VM a `ClarityBackingStore` to read and write
+//! through, and so stand up a `ClarityDatabase` or an `AnalysisDatabase`.  This crate provides the
+//! in-memory backend (`MemoryBackingStore`); the node implements it for its MARF stores, and for a
+//! store that reads committed state from a node over RPC.  Tooling that embeds the VM (in wasm, or
+//! in a test harness) can implement it for its own store without linking the MARF.
+
+use crate::vm::analysis::AnalysisDatabase;
+use crate::vm::database::{
+    BurnStateDB, ClarityBackingStore, ClarityDatabase, HeadersDB, NULL_BURN_STATE_DB,
+    NULL_HEADER_DB,
+};
+
+pub trait ClarityBackend {
+    /// The store that the VM reads and writes through
+    fn backing_store(&mut self) -> &mut dyn ClarityBackingStore;
+
+    /// Open a Clarity database on this backend, which looks up block and burnchain state in
+    /// `headers_db` and `burn_state_db`
+    fn as_clarity_db_with_databases<'a>(
+        &'a mut self,
+        headers_db: &'a dyn HeadersDB,
+        burn_state_db: &'a dyn BurnStateDB,
+    ) -> ClarityDatabase<'a> {
+        ClarityDatabase::new(self.backing_store(), headers_db, burn_state_db)
+    }
+
+    /// Open a Clarity database on this backend, with no block or burnchain state
+    fn as_clarity_db<'a>(&'a mut self) -> ClarityDatabase<'a> {
+        self.as_clarity_db_with_databases(&NULL_HEADER_DB, &NULL_BURN_STATE_DB)
+    }
+
+    /// Open an analysis database on this backend
+    fn as_analysis_db<'a>(&'a mut self) -> AnalysisDatabase<'a> {
+        AnalysisDatabase::new(self.backing_store())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vm::ast::ASTRules;
+    use crate::vm::database::{MemoryBackingStore, StoreType};
+    use crate::vm::types::QualifiedContractIdentifier;
+    use crate::vm::{execute_with_backend, ClarityVersion, Value};
+    use stacks_common::types::{NetworkId, StacksEpochId};
+
+    #[test]
+    fn test_memory_backend() {
+        let mut store = MemoryBackingStore::new();
+        let backend: &mut dyn ClarityBackend = &mut store;
+
+        let result = execute_with_backend(
+            "(define-data-var n int 1) (var-set n 2) (var-get n)",
+            backend,
+            NetworkId::testnet(),
+            ClarityVersion::Clarity2,
+            StacksEpochId::Epoch21,
+            ASTRules::PrecheckSize,
+        )
+        .unwrap();
+        assert_eq!(result, Some(Value::Int(2)));
+
+        // the data var was committed to the backend's store
+        let contract_id = QualifiedContractIdentifier::transient();
+        let key = ClarityDatabase::make_key_for_trip(&contract_id, StoreType::Variable, "n");
+        let mut db = backend.as_clarity_db();
+        db.begin();
+        let stored: String = db.get(&key).unwrap();
+        assert_eq!(
+            Value::try_deserialize_hex_untyped(&stored).unwrap(),
+            Value::Int(2)
+        );
+        db.roll_back();
+    }
+}
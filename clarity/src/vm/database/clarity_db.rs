@@ -17,6 +17,7 @@
 use std::collections::{HashMap, VecDeque};
 use std::convert::{TryFrom, TryInto};
 
+use crate::vm::analysis::analysis_db::StoredContractAnalysis;
 use crate::vm::analysis::{AnalysisDatabase, ContractAnalysis};
 use crate::vm::ast::ASTRules;
 use crate::vm::contracts::Contract;
@@ -659,7 +660,7 @@ impl<'a> ClarityDatabase<'a> {
             // treat NoSuchContract error thrown by get_metadata as an Option::None --
             //    the analysis will propagate that as a CheckError anyways.
             .ok()?
-            .map(|x| ContractAnalysis::deserialize(&x))
+            .map(|x| StoredContractAnalysis::deserialize(&x).to_contract_analysis())
     }
 
     pub fn get_contract_size(
@@ -21,8 +21,8 @@ use rusqlite::Connection;
 
 use crate::vm::analysis::AnalysisDatabase;
 use crate::vm::database::{
-    BurnStateDB, ClarityDatabase, ClarityDeserializable, ClaritySerializable, HeadersDB,
-    SqliteConnection, NULL_BURN_STATE_DB, NULL_HEADER_DB,
+    BurnStateDB, ClarityBackend, ClarityDatabase, ClarityDeserializable, ClaritySerializable,
+    HeadersDB, SqliteConnection, NULL_BURN_STATE_DB, NULL_HEADER_DB,
 };
 use crate::vm::errors::{
     CheckErrors, IncomparableError, InterpreterError, InterpreterResult as Result,
@@ -277,6 +277,12 @@ impl MemoryBackingStore {
     }
 }
 
+impl ClarityBackend for MemoryBackingStore {
+    fn backing_store(&mut self) -> &mut dyn ClarityBackingStore {
+        self
+    }
+}
+
 impl ClarityBackingStore for MemoryBackingStore {
     fn set_block_hash(&mut self, bhh: StacksBlockId) -> InterpreterResult<StacksBlockId> {
         Err(RuntimeErrorType::UnknownBlockHeaderHash(BlockHeaderHash(bhh.0)).into())
@@ -16,6 +16,7 @@
 
 use std::collections::HashMap;
 
+pub use self::backend::ClarityBackend;
 pub use self::clarity_db::{
     BurnStateDB, ClarityDatabase, HeadersDB, StoreType, NULL_BURN_STATE_DB, NULL_HEADER_DB,
     STORE_CONTRACT_SRC_INTERFACE,
@@ -29,6 +30,7 @@ pub use self::structures::{
     FungibleTokenMetadata, NonFungibleTokenMetadata, STXBalance,
 };
 
+pub mod backend;
 pub mod clarity_db;
 pub mod clarity_store;
 mod key_value_wrapper;
@@ -152,6 +152,52 @@ impl SqliteConnection {
         }
     }
 
+    /// List the identifiers of contracts which have a `key` metadata entry in any block, in
+    /// order of their metadata key.  Only contracts ordered after `after` (if given) are listed,
+    /// so that a caller can walk the whole set in batches of at most `limit` contracts.
+    /// Contracts whose metadata entry was written in a block that is not an ancestor of a
+    /// given chain tip are listed as well -- it is up to the caller to check that the contract
+    /// is visible at the tip it cares about.
+    pub fn list_contracts_with_metadata(
+        conn: &Connection,
+        key: &str,
+        after: Option<&str>,
+        limit: u32,
+    ) -> Vec<String> {
+        let prefix = "clr-meta::";
+        let suffix = format!("::{}", key);
+        let after_key = after
+            .map(|contract| format!("{}{}{}", prefix, contract, suffix))
+            .unwrap_or_default();
+        // contract identifiers never contain `::`, so this excludes metadata keys which merely
+        // end in `::{key}` (such as the data descriptor of a data var named `key`)
+        let pattern = format!("{}%{}", prefix, suffix);
+        let exclude_pattern = format!("{}%::%::%{}", prefix, suffix);
+        let params: [&dyn ToSql; 4] = [&pattern, &exclude_pattern, &after_key, &limit];
+
+        let keys: Vec<String> = conn
+            .prepare(
+                "SELECT DISTINCT key FROM metadata_table WHERE key LIKE ?1 AND key NOT LIKE ?2 AND key > ?3 ORDER BY key LIMIT ?4",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_map(&params, |row| row.get(0))?
+                    .collect::<std::result::Result<Vec<String>, rusqlite::Error>>()
+            })
+            .unwrap_or_else(|e| {
+                error!("Failed to list contracts with metadata key {}: {:?}", key, &e);
+                panic!("{}", SQL_FAIL_MESSAGE);
+            });
+
+        keys.into_iter()
+            .filter_map(|metadata_key| {
+                metadata_key
+                    .strip_prefix(prefix)
+                    .and_then(|rest| rest.strip_suffix(suffix.as_str()))
+                    .map(|contract| contract.to_string())
+            })
+            .collect()
+    }
+
     pub fn has_entry(conn: &Connection, key: &str) -> bool {
         sqlite_has_entry(conn, key)
     }
@@ -25,6 +25,7 @@ use crate::vm::types::{
 };
 use serde::Deserialize;
 use stacks_common::util::hash::{hex_bytes, to_hex};
+use stacks_common::util::ustx;
 use std::convert::TryInto;
 use std::io::Write;
 
@@ -441,11 +442,9 @@ impl<'db, 'conn> STXBalanceSnapshot<'db, 'conn> {
         // from `unlock_available_tokens_if_any` call above, `self.balance` should
         //  be canonicalized already
 
-        let new_amount_unlocked = self
-            .balance
-            .get_total_balance()
-            .checked_sub(amount_to_lock)
-            .expect("STX underflow");
+        let new_amount_unlocked =
+            ustx::checked_sub(self.balance.get_total_balance(), amount_to_lock)
+                .expect("STX underflow");
 
         self.balance = STXBalance::LockedPoxOne {
             amount_unlocked: new_amount_unlocked,
@@ -488,13 +487,10 @@ impl<'db, 'conn> STXBalanceSnapshot<'db, 'conn> {
             "FATAL: account must lock more after `increase_lock_v2`"
         );
 
-        let total_amount = self
-            .balance
-            .amount_unlocked()
-            .checked_add(self.balance.amount_locked())
-            .expect("STX balance overflowed u128");
-        let amount_unlocked = total_amount
-            .checked_sub(new_total_locked)
+        let total_amount =
+            ustx::checked_add(self.balance.amount_unlocked(), self.balance.amount_locked())
+                .expect("STX balance overflowed u128");
+        let amount_unlocked = ustx::checked_sub(total_amount, new_total_locked)
             .expect("STX underflow: more is locked than total balance");
 
         self.balance = STXBalance::LockedPoxTwo {
@@ -555,11 +551,9 @@ impl<'db, 'conn> STXBalanceSnapshot<'db, 'conn> {
         // from `unlock_available_tokens_if_any` call above, `self.balance` should
         //  be canonicalized already
 
-        let new_amount_unlocked = self
-            .balance
-            .get_total_balance()
-            .checked_sub(amount_to_lock)
-            .expect("STX underflow");
+        let new_amount_unlocked =
+            ustx::checked_sub(self.balance.get_total_balance(), amount_to_lock)
+                .expect("STX underflow");
 
         self.balance = STXBalance::LockedPoxTwo {
             amount_unlocked: new_amount_unlocked,
@@ -595,11 +589,9 @@ impl<'db, 'conn> STXBalanceSnapshot<'db, 'conn> {
         // from `unlock_available_tokens_if_any` call above, `self.balance` should
         //  be canonicalized already
 
-        let new_amount_unlocked = self
-            .balance
-            .get_total_balance()
-            .checked_sub(amount_to_lock)
-            .expect("FATAL: account locks more STX than balance possessed");
+        let new_amount_unlocked =
+            ustx::checked_sub(self.balance.get_total_balance(), amount_to_lock)
+                .expect("FATAL: account locks more STX than balance possessed");
 
         self.balance = STXBalance::LockedPoxThree {
             amount_unlocked: new_amount_unlocked,
@@ -657,13 +649,10 @@ impl<'db, 'conn> STXBalanceSnapshot<'db, 'conn> {
             "FATAL: account must lock more after `increase_lock_v3`"
         );
 
-        let total_amount = self
-            .balance
-            .amount_unlocked()
-            .checked_add(self.balance.amount_locked())
-            .expect("STX balance overflowed u128");
-        let amount_unlocked = total_amount
-            .checked_sub(new_total_locked)
+        let total_amount =
+            ustx::checked_add(self.balance.amount_unlocked(), self.balance.amount_locked())
+                .expect("STX balance overflowed u128");
+        let amount_unlocked = ustx::checked_sub(total_amount, new_total_locked)
             .expect("STX underflow: more is locked than total balance");
 
         self.balance = STXBalance::LockedPoxThree {
@@ -827,7 +816,8 @@ impl STXBalance {
             | STXBalance::LockedPoxThree {
                 amount_unlocked, ..
             } => {
-                *amount_unlocked = amount_unlocked.checked_sub(delta).expect("STX underflow");
+                *amount_unlocked =
+                    ustx::checked_sub(*amount_unlocked, delta).expect("STX underflow");
             }
         }
     }
@@ -963,7 +953,7 @@ impl STXBalance {
                 ..
             } => (*amount_unlocked, *amount_locked),
         };
-        unlocked.checked_add(locked).expect("STX overflow")
+        ustx::checked_add(unlocked, locked).expect("STX overflow")
     }
 
     pub fn was_locked_by_v1(&self) -> bool {
@@ -1,28 +1,54 @@
-use crate::vm::analysis::{mem_type_check, ContractAnalysis};
+use crate::vm::analysis::{type_check_with_backend, ContractAnalysis};
 use crate::vm::docs::{get_input_type_string, get_output_type_string, get_signature};
-use crate::vm::types::{FunctionType, Value};
+use crate::vm::types::{FunctionSignature, FunctionType, Value};
 
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error;
+use std::fmt;
 use std::iter::FromIterator;
 
+use regex::Regex;
+
 use crate::types::StacksEpochId;
-use crate::vm::ast::{build_ast_with_rules, ASTRules};
-use crate::vm::contexts::GlobalContext;
-use crate::vm::costs::LimitedCostTracker;
-use crate::vm::database::MemoryBackingStore;
-use crate::vm::types::QualifiedContractIdentifier;
+use crate::vm::ast::ASTRules;
+use crate::vm::database::{ClarityBackend, MemoryBackingStore};
 use crate::vm::version::ClarityVersion;
-use crate::vm::{self, ContractContext};
+use crate::vm::{self, ClarityName};
+
+use stacks_common::types::NetworkId;
 
-use stacks_common::consts::CHAIN_ID_TESTNET;
+/// Epoch in which boot contracts are documented when the caller does not need a particular one
+pub const DOCS_GENERATION_EPOCH: StacksEpochId = StacksEpochId::Epoch2_05;
 
-const DOCS_GENERATION_EPOCH: StacksEpochId = StacksEpochId::Epoch2_05;
+/// Version of the document produced by `render_docs_json`.  Bump this whenever a field is
+/// removed or changes meaning.
+pub const CONTRACT_DOCS_SCHEMA_VERSION: u32 = 1;
 
 #[derive(Serialize)]
 pub struct ContractRef {
     public_functions: Vec<FunctionRef>,
     read_only_functions: Vec<FunctionRef>,
     error_codes: Vec<ErrorCode>,
+    constants: Vec<ConstantRef>,
+    maps: Vec<MapRef>,
+    data_vars: Vec<DataVarRef>,
+    traits: Vec<TraitRef>,
+    /// Only documented if private items were requested
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    private_functions: Vec<FunctionRef>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fungible_tokens: Vec<FungibleTokenRef>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    non_fungible_tokens: Vec<NonFungibleTokenRef>,
+}
+
+/// Boot contract references for one epoch, as published by the docs pipeline
+#[derive(Serialize)]
+struct ContractDocs<'a> {
+    schema_version: u32,
+    epoch: String,
+    clarity_version: String,
+    contracts: &'a BTreeMap<String, ContractRef>,
 }
 
 #[derive(Serialize)]
@@ -42,151 +68,825 @@ struct ErrorCode {
     value: String,
 }
 
+#[derive(Serialize)]
+struct ConstantRef {
+    name: String,
+    #[serde(rename = "type")]
+    value_type: String,
+    value: String,
+}
+
+#[derive(Serialize)]
+struct MapRef {
+    name: String,
+    key_type: String,
+    value_type: String,
+}
+
+#[derive(Serialize)]
+struct DataVarRef {
+    name: String,
+    #[serde(rename = "type")]
+    value_type: String,
+}
+
+#[derive(Serialize)]
+struct FungibleTokenRef {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct NonFungibleTokenRef {
+    name: String,
+    #[serde(rename = "type")]
+    value_type: String,
+}
+
+#[derive(Serialize)]
+struct TraitRef {
+    name: String,
+    functions: Vec<TraitFunctionRef>,
+}
+
+#[derive(Serialize)]
+struct TraitFunctionRef {
+    name: String,
+    input_types: Vec<String>,
+    output_type: String,
+    signature: String,
+}
+
+/// Which `define-constant`s, besides the `ERR_` error codes, a contract's docs list.  A
+/// constant is listed if its name is in `names` or matches any of `patterns`.  The default
+/// lists none.
+#[derive(Default)]
+pub struct ConstantDocsPolicy {
+    pub names: HashSet<&'static str>,
+    pub patterns: Vec<Regex>,
+}
+
+impl ConstantDocsPolicy {
+    pub fn includes(&self, name: &str) -> bool {
+        self.names.contains(name) || self.patterns.iter().any(|pattern| pattern.is_match(name))
+    }
+}
+
 pub struct ContractSupportDocs {
     pub descriptions: HashMap<&'static str, &'static str>,
     pub skip_func_display: HashSet<&'static str>,
+    pub constants: ConstantDocsPolicy,
 }
 
-fn make_func_ref(func_name: &str, func_type: &FunctionType, description: &str) -> FunctionRef {
+#[derive(Debug, Clone, PartialEq)]
+pub enum DocsError {
+    /// The contract does not type-check in the requested epoch
+    TypeCheck(String),
+    /// These public or read-only functions have no description
+    MissingDescriptions(Vec<String>),
+    /// No signature could be built for this function
+    NoSignature(String),
+    /// The contract's top-level expressions could not be evaluated
+    ContractEvaluation(String),
+    /// This constant could not be evaluated, and why
+    ConstantEvaluation(String, String),
+    /// The named contract could not be documented
+    Contract(String, Box<DocsError>),
+}
+
+impl fmt::Display for DocsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DocsError::TypeCheck(e) => write!(f, "failed to type-check: {}", e),
+            DocsError::MissingDescriptions(func_names) => {
+                write!(f, "no description for {}", func_names.join(", "))
+            }
+            DocsError::NoSignature(func_name) => {
+                write!(f, "failed to build signature for {}", func_name)
+            }
+            DocsError::ContractEvaluation(e) => write!(f, "failed to evaluate contract: {}", e),
+            DocsError::ConstantEvaluation(var_name, e) => {
+                write!(f, "failed to evaluate {}: {}", var_name, e)
+            }
+            DocsError::Contract(contract_name, e) => write!(f, "{}: {}", contract_name, e),
+        }
+    }
+}
+
+impl error::Error for DocsError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            DocsError::Contract(_, e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+fn make_func_ref(
+    func_name: &str,
+    func_type: &FunctionType,
+    description: &str,
+) -> Result<FunctionRef, DocsError> {
     let input_type = get_input_type_string(func_type);
     let output_type = get_output_type_string(func_type);
     let signature = get_signature(func_name, func_type)
-        .expect("BUG: failed to build signature for boot contract");
-    FunctionRef {
+        .ok_or_else(|| DocsError::NoSignature(func_name.to_string()))?;
+    Ok(FunctionRef {
         input_type,
         output_type,
         signature,
         name: func_name.to_string(),
         description: description.to_string(),
+    })
+}
+
+/// Document the public or read-only functions in `function_types` which are not skipped.  Every
+///  such function must have a description.
+fn make_api_func_refs(
+    function_types: &BTreeMap<ClarityName, FunctionType>,
+    support_docs: &ContractSupportDocs,
+) -> Result<Vec<FunctionRef>, DocsError> {
+    let mut func_refs = vec![];
+    let mut undescribed = vec![];
+    for (func_name, func_type) in function_types.iter() {
+        if support_docs.skip_func_display.contains(func_name.as_str()) {
+            continue;
+        }
+        match support_docs.descriptions.get(func_name.as_str()) {
+            Some(description) => func_refs.push(make_func_ref(func_name, func_type, description)?),
+            None => undescribed.push(func_name.to_string()),
+        }
+    }
+    if !undescribed.is_empty() {
+        return Err(DocsError::MissingDescriptions(undescribed));
     }
+    Ok(func_refs)
 }
 
-fn get_constant_value(var_name: &str, contract_content: &str) -> Value {
+fn make_trait_ref(
+    trait_name: &ClarityName,
+    functions: &BTreeMap<ClarityName, FunctionSignature>,
+) -> TraitRef {
+    let functions = functions
+        .iter()
+        .map(|(func_name, func_sig)| {
+            let input_types: Vec<_> = func_sig.args.iter().map(|arg| arg.to_string()).collect();
+            let output_type = func_sig.returns.to_string();
+            let signature = format!(
+                "({} ({}) {})",
+                func_name,
+                input_types.join(" "),
+                output_type
+            );
+            TraitFunctionRef {
+                name: func_name.to_string(),
+                input_types,
+                output_type,
+                signature,
+            }
+        })
+        .collect();
+    TraitRef {
+        name: trait_name.to_string(),
+        functions,
+    }
+}
+
+fn get_constant_value(
+    var_name: &str,
+    contract_content: &str,
+    epoch: StacksEpochId,
+) -> Result<Value, DocsError> {
     let to_eval = format!("{}\n{}", contract_content, var_name);
-    doc_execute(&to_eval)
-        .expect("BUG: failed to evaluate contract for constant value")
-        .expect("BUG: failed to return constant value")
-}
-
-fn doc_execute(program: &str) -> Result<Option<Value>, vm::Error> {
-    let contract_id = QualifiedContractIdentifier::transient();
-    let mut contract_context = ContractContext::new(contract_id.clone(), ClarityVersion::Clarity2);
-    let mut marf = MemoryBackingStore::new();
-    let conn = marf.as_clarity_db();
-    let mut global_context = GlobalContext::new(
-        false,
-        CHAIN_ID_TESTNET,
-        conn,
-        LimitedCostTracker::new_free(),
-        DOCS_GENERATION_EPOCH,
-    );
-    global_context.execute(|g| {
-        let parsed = vm::ast::build_ast_with_rules(
-            &contract_id,
-            program,
-            &mut (),
-            ClarityVersion::latest(),
-            StacksEpochId::latest(),
-            ASTRules::PrecheckSize,
-        )?
-        .expressions;
-        vm::eval_all(&parsed, &mut contract_context, g, None)
-    })
+    doc_execute(&to_eval, epoch, &mut MemoryBackingStore::new())
+        .map_err(|e| DocsError::ConstantEvaluation(var_name.to_string(), e.to_string()))?
+        .ok_or_else(|| {
+            DocsError::ConstantEvaluation(var_name.to_string(), "no value returned".into())
+        })
+}
+
+/// Evaluate `program` as it would be evaluated in `epoch`, with that epoch's default Clarity
+///  version, against the state in `backend`
+fn doc_execute(
+    program: &str,
+    epoch: StacksEpochId,
+    backend: &mut dyn ClarityBackend,
+) -> Result<Option<Value>, vm::Error> {
+    vm::execute_with_backend(
+        program,
+        backend,
+        NetworkId::testnet(),
+        ClarityVersion::default_for_epoch(epoch),
+        epoch,
+        ASTRules::PrecheckSize,
+    )
+}
+
+/// Document a contract as it type-checks and evaluates in `epoch`, using that epoch's default
+///  Clarity version.  If `include_private` is set, private functions and token definitions are
+///  documented as well, so the reference covers the contract's whole structure and not just its
+///  public API.
+pub fn make_docs(
+    content: &str,
+    support_docs: &ContractSupportDocs,
+    epoch: StacksEpochId,
+    include_private: bool,
+) -> Result<ContractRef, DocsError> {
+    make_docs_with_backend(
+        content,
+        support_docs,
+        epoch,
+        include_private,
+        &mut MemoryBackingStore::new(),
+    )
 }
 
-pub fn make_docs(content: &str, support_docs: &ContractSupportDocs) -> ContractRef {
-    let (_, contract_analysis) =
-        mem_type_check(content, ClarityVersion::latest(), StacksEpochId::latest())
-            .expect("BUG: failed to type check boot contract");
+/// Like `make_docs`, but type-checks and evaluates the contract against the state in `backend`,
+///  so it can refer to contracts that are already deployed there
+pub fn make_docs_with_backend(
+    content: &str,
+    support_docs: &ContractSupportDocs,
+    epoch: StacksEpochId,
+    include_private: bool,
+    backend: &mut dyn ClarityBackend,
+) -> Result<ContractRef, DocsError> {
+    let (_, contract_analysis) = type_check_with_backend(
+        content,
+        ClarityVersion::default_for_epoch(epoch),
+        epoch,
+        backend,
+    )
+    .map_err(|e| DocsError::TypeCheck(e.to_string()))?;
 
     let ContractAnalysis {
+        private_function_types,
         public_function_types,
         read_only_function_types,
         variable_types,
+        map_types,
+        persisted_variable_types,
+        fungible_tokens,
+        non_fungible_tokens,
+        defined_traits,
         ..
     } = contract_analysis;
-    let public_functions: Vec<_> = public_function_types
-        .iter()
-        .filter(|(func_name, _)| !support_docs.skip_func_display.contains(func_name.as_str()))
-        .map(|(func_name, func_type)| {
-            let description = support_docs
-                .descriptions
-                .get(func_name.as_str())
-                .expect(&format!("BUG: no description for {}", func_name.as_str()));
-            make_func_ref(func_name, func_type, description)
+    let (public_functions, read_only_functions) = match (
+        make_api_func_refs(&public_function_types, support_docs),
+        make_api_func_refs(&read_only_function_types, support_docs),
+    ) {
+        (Ok(public_functions), Ok(read_only_functions)) => (public_functions, read_only_functions),
+        // report every undescribed function at once
+        (
+            Err(DocsError::MissingDescriptions(mut public)),
+            Err(DocsError::MissingDescriptions(read_only)),
+        ) => {
+            public.extend(read_only);
+            return Err(DocsError::MissingDescriptions(public));
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            return Err(e);
+        }
+    };
+
+    // evaluate every documented constant at once, as the entries of a single tuple
+    let is_error_code = |var_name: &ClarityName| var_name.starts_with("ERR_");
+    let documented_names = variable_types
+        .keys()
+        .filter(|var_name| {
+            is_error_code(*var_name) || support_docs.constants.includes(var_name.as_str())
         })
-        .collect();
+        .map(|var_name| format!("{}: {}", var_name.as_str(), var_name.as_str()))
+        .collect::<Vec<_>>();
+    let constant_values = if documented_names.is_empty() {
+        None
+    } else {
+        let to_eval = format!("{}\n {{ {} }}", content, documented_names.join(", "));
+        match doc_execute(&to_eval, epoch, backend) {
+            Ok(Some(Value::Tuple(constant_values))) => Some(constant_values),
+            Ok(_) => {
+                return Err(DocsError::ConstantEvaluation(
+                    "constants".into(),
+                    "did not evaluate to a tuple".into(),
+                ));
+            }
+            Err(e) => {
+                return Err(DocsError::ContractEvaluation(e.to_string()));
+            }
+        }
+    };
+    let documented_value = |var_name: &ClarityName| {
+        constant_values
+            .as_ref()
+            .and_then(|values| values.get(var_name).ok())
+            .map(|value| value.to_string())
+            .ok_or_else(|| {
+                DocsError::ConstantEvaluation(var_name.to_string(), "not evaluated".into())
+            })
+    };
 
-    let read_only_functions: Vec<_> = read_only_function_types
+    let mut error_codes = vec![];
+    let mut constants = vec![];
+    for (var_name, type_signature) in variable_types.iter() {
+        if is_error_code(var_name) {
+            error_codes.push(ErrorCode {
+                name: var_name.to_string(),
+                value: documented_value(var_name)?,
+                value_type: type_signature.to_string(),
+            });
+        } else if support_docs.constants.includes(var_name.as_str()) {
+            constants.push(ConstantRef {
+                name: var_name.to_string(),
+                value: documented_value(var_name)?,
+                value_type: type_signature.to_string(),
+            });
+        }
+    }
+
+    let maps = map_types
         .iter()
-        .filter(|(func_name, _)| !support_docs.skip_func_display.contains(func_name.as_str()))
-        .map(|(func_name, func_type)| {
-            let description = support_docs
-                .descriptions
-                .get(func_name.as_str())
-                .expect(&format!("BUG: no description for {}", func_name.as_str()));
-            make_func_ref(func_name, func_type, description)
+        .map(|(map_name, (key_type, value_type))| MapRef {
+            name: map_name.to_string(),
+            key_type: key_type.to_string(),
+            value_type: value_type.to_string(),
         })
         .collect();
 
-    let ecode_names = variable_types
+    let data_vars = persisted_variable_types
         .iter()
-        .filter_map(|(var_name, _)| {
-            if var_name.starts_with("ERR_") {
-                Some(format!("{}: {}", var_name.as_str(), var_name.as_str()))
-            } else {
-                None
-            }
+        .map(|(var_name, var_type)| DataVarRef {
+            name: var_name.to_string(),
+            value_type: var_type.to_string(),
         })
-        .collect::<Vec<_>>()
-        .join(", ");
-    let ecode_to_eval = format!("{}\n {{ {} }}", content, ecode_names);
-    let ecode_result = doc_execute(&ecode_to_eval)
-        .expect("BUG: failed to evaluate contract for constant value")
-        .expect("BUG: failed to return constant value")
-        .expect_tuple();
-
-    let error_codes = variable_types
+        .collect();
+
+    let traits = defined_traits
         .iter()
-        .filter_map(|(var_name, type_signature)| {
-            if var_name.starts_with("ERR_") {
-                let value = ecode_result
-                    .get(var_name)
-                    .expect("BUG: failed to fetch tuple entry from ecode output")
-                    .to_string();
-                Some(ErrorCode {
-                    name: var_name.to_string(),
-                    value,
-                    value_type: type_signature.to_string(),
-                })
-            } else {
-                None
-            }
-        })
+        .map(|(trait_name, functions)| make_trait_ref(trait_name, functions))
         .collect();
 
-    ContractRef {
+    let (private_functions, fungible_tokens, non_fungible_tokens) = if include_private {
+        // private functions are not part of the API, so they need not have a description
+        let private_functions = private_function_types
+            .iter()
+            .filter(|(func_name, _)| !support_docs.skip_func_display.contains(func_name.as_str()))
+            .map(|(func_name, func_type)| {
+                let description = support_docs
+                    .descriptions
+                    .get(func_name.as_str())
+                    .unwrap_or(&"");
+                make_func_ref(func_name, func_type, description)
+            })
+            .collect::<Result<_, _>>()?;
+
+        let fungible_tokens = fungible_tokens
+            .iter()
+            .map(|token_name| FungibleTokenRef {
+                name: token_name.to_string(),
+            })
+            .collect();
+
+        let non_fungible_tokens = non_fungible_tokens
+            .iter()
+            .map(|(token_name, token_type)| NonFungibleTokenRef {
+                name: token_name.to_string(),
+                value_type: token_type.to_string(),
+            })
+            .collect();
+
+        (private_functions, fungible_tokens, non_fungible_tokens)
+    } else {
+        (vec![], vec![], vec![])
+    };
+
+    Ok(ContractRef {
         public_functions,
         read_only_functions,
         error_codes,
-    }
+        constants,
+        maps,
+        data_vars,
+        traits,
+        private_functions,
+        fungible_tokens,
+        non_fungible_tokens,
+    })
 }
 
 /// Produce a set of documents for multiple contracts, supplied as a list of `(contract_name, contract_content)` pairs,
-///  and a map from `contract_name` to corresponding `ContractSupportDocs`
+///  and a map from `contract_name` to corresponding `ContractSupportDocs`.  Each contract is
+///  documented as by `make_docs`.  If any contract cannot be documented, the error for every
+///  such contract is returned.
 pub fn produce_docs_refs<A: AsRef<str>, B: AsRef<str>>(
     contracts: &[(A, B)],
     support_docs: &HashMap<&str, ContractSupportDocs>,
-) -> BTreeMap<String, ContractRef> {
+    epoch: StacksEpochId,
+    include_private: bool,
+) -> Result<BTreeMap<String, ContractRef>, Vec<DocsError>> {
     let mut docs = BTreeMap::new();
+    let mut errors = vec![];
 
     for (contract_name, content) in contracts.iter() {
         if let Some(contract_support) = support_docs.get(contract_name.as_ref()) {
-            let contract_ref = make_docs(content.as_ref(), contract_support);
+            match make_docs(content.as_ref(), contract_support, epoch, include_private) {
+                Ok(contract_ref) => {
+                    docs.insert(contract_name.as_ref().to_string(), contract_ref);
+                }
+                Err(e) => {
+                    errors.push(DocsError::Contract(
+                        contract_name.as_ref().to_string(),
+                        Box::new(e),
+                    ));
+                }
+            }
+        }
+    }
 
-            docs.insert(contract_name.as_ref().to_string(), contract_ref);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(docs)
+}
+
+/// Render a set of contract references produced for `epoch` as a versioned JSON document
+pub fn render_docs_json(epoch: StacksEpochId, docs: &BTreeMap<String, ContractRef>) -> String {
+    let out = ContractDocs {
+        schema_version: CONTRACT_DOCS_SCHEMA_VERSION,
+        epoch: epoch.to_string(),
+        clarity_version: ClarityVersion::default_for_epoch(epoch).to_string(),
+        contracts: docs,
+    };
+    serde_json::to_string(&out).expect("Failed to serialize documentation")
+}
+
+fn render_functions_markdown(out: &mut String, heading: &str, functions: &[FunctionRef]) {
+    if functions.is_empty() {
+        return;
+    }
+    out.push_str(&format!("## {}\n\n", heading));
+    for func in functions.iter() {
+        out.push_str(&format!("### `{}`\n\n", func.name));
+        out.push_str(&format!("**Signature:** `{}`\n\n", func.signature));
+        out.push_str(&format!("**Input:** `{}`\n\n", func.input_type));
+        out.push_str(&format!("**Output:** `{}`\n\n", func.output_type));
+        out.push_str(&format!("{}\n\n", func.description));
+    }
+}
+
+/// Render a contract reference produced for `epoch` as a complete Markdown page
+pub fn render_contract_markdown(
+    contract_name: &str,
+    epoch: StacksEpochId,
+    contract_ref: &ContractRef,
+) -> String {
+    let mut out = format!(
+        "# `{}`\n\nReference for the `{}` boot contract in epoch {} ({}).\n\n",
+        contract_name,
+        contract_name,
+        epoch,
+        ClarityVersion::default_for_epoch(epoch)
+    );
+
+    render_functions_markdown(&mut out, "Public functions", &contract_ref.public_functions);
+    render_functions_markdown(
+        &mut out,
+        "Read-only functions",
+        &contract_ref.read_only_functions,
+    );
+    render_functions_markdown(
+        &mut out,
+        "Private functions",
+        &contract_ref.private_functions,
+    );
+
+    if !contract_ref.constants.is_empty() {
+        out.push_str("## Constants\n\n| Name | Type | Value |\n| --- | --- | --- |\n");
+        for constant in contract_ref.constants.iter() {
+            out.push_str(&format!(
+                "| `{}` | `{}` | `{}` |\n",
+                constant.name, constant.value_type, constant.value
+            ));
+        }
+        out.push_str("\n");
+    }
+
+    if !contract_ref.maps.is_empty() {
+        out.push_str("## Maps\n\n| Name | Key type | Value type |\n| --- | --- | --- |\n");
+        for map in contract_ref.maps.iter() {
+            out.push_str(&format!(
+                "| `{}` | `{}` | `{}` |\n",
+                map.name, map.key_type, map.value_type
+            ));
         }
+        out.push_str("\n");
     }
 
-    docs
+    if !contract_ref.data_vars.is_empty() {
+        out.push_str("## Data variables\n\n| Name | Type |\n| --- | --- |\n");
+        for var in contract_ref.data_vars.iter() {
+            out.push_str(&format!("| `{}` | `{}` |\n", var.name, var.value_type));
+        }
+        out.push_str("\n");
+    }
+
+    if !contract_ref.fungible_tokens.is_empty() {
+        out.push_str("## Fungible tokens\n\n");
+        for token in contract_ref.fungible_tokens.iter() {
+            out.push_str(&format!("* `{}`\n", token.name));
+        }
+        out.push_str("\n");
+    }
+
+    if !contract_ref.non_fungible_tokens.is_empty() {
+        out.push_str("## Non-fungible tokens\n\n| Name | Asset type |\n| --- | --- |\n");
+        for token in contract_ref.non_fungible_tokens.iter() {
+            out.push_str(&format!("| `{}` | `{}` |\n", token.name, token.value_type));
+        }
+        out.push_str("\n");
+    }
+
+    if !contract_ref.traits.is_empty() {
+        out.push_str("## Traits\n\n");
+        for trait_ref in contract_ref.traits.iter() {
+            out.push_str(&format!("### `{}`\n\n", trait_ref.name));
+            for func in trait_ref.functions.iter() {
+                out.push_str(&format!("* `{}`\n", func.signature));
+            }
+            out.push_str("\n");
+        }
+    }
+
+    if !contract_ref.error_codes.is_empty() {
+        out.push_str("## Error codes\n\n| Name | Type | Value |\n| --- | --- | --- |\n");
+        for error_code in contract_ref.error_codes.iter() {
+            out.push_str(&format!(
+                "| `{}` | `{}` | `{}` |\n",
+                error_code.name, error_code.value_type, error_code.value
+            ));
+        }
+        out.push_str("\n");
+    }
+
+    out
+}
+
+/// Render a set of contract references produced for `epoch` as one Markdown page per contract,
+///  keyed by contract name
+pub fn render_docs_markdown(
+    epoch: StacksEpochId,
+    docs: &BTreeMap<String, ContractRef>,
+) -> BTreeMap<String, String> {
+    docs.iter()
+        .map(|(contract_name, contract_ref)| {
+            (
+                contract_name.clone(),
+                render_contract_markdown(contract_name, epoch, contract_ref),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DOCS_TEST_CONTRACT: &str = "
+(define-trait token-trait ((transfer (uint principal) (response bool uint))))
+(define-map balances principal uint)
+(define-data-var total-supply uint u0)
+(define-constant ERR_NOT_FOUND (err u404))
+(define-constant MAX_SUPPLY u1000000)
+(define-constant MIN_MINT_CYCLES (+ u1 u1))
+(define-constant UNDOCUMENTED u7)
+(define-public (mint (amount uint))
+    (begin (var-set total-supply (+ (var-get total-supply) amount)) (ok true)))
+(define-read-only (get-total-supply) (var-get total-supply))
+(define-read-only (hidden) u1)
+(define-private (add-supply (amount uint)) (+ (var-get total-supply) amount))
+(define-fungible-token stackaroo)
+(define-non-fungible-token badge uint)
+";
+
+    fn support_docs() -> ContractSupportDocs {
+        ContractSupportDocs {
+            descriptions: HashMap::from_iter(vec![
+                ("mint", "Mint some tokens."),
+                ("get-total-supply", "Get the total supply."),
+            ]),
+            skip_func_display: HashSet::from_iter(vec!["hidden"]),
+            constants: ConstantDocsPolicy {
+                names: HashSet::from_iter(vec!["MAX_SUPPLY"]),
+                patterns: vec![Regex::new("_CYCLES$").unwrap()],
+            },
+        }
+    }
+
+    #[test]
+    fn test_contract_docs_listings() {
+        let docs = make_docs(
+            DOCS_TEST_CONTRACT,
+            &support_docs(),
+            StacksEpochId::Epoch21,
+            false,
+        )
+        .unwrap();
+        assert_eq!(docs.public_functions.len(), 1);
+        assert_eq!(docs.read_only_functions.len(), 1);
+        assert_eq!(docs.error_codes.len(), 1);
+        assert_eq!(docs.error_codes[0].value, "(err u404)");
+        assert_eq!(docs.constants.len(), 2);
+        assert_eq!(docs.constants[0].name, "MAX_SUPPLY");
+        assert_eq!(docs.constants[0].value, "u1000000");
+        assert_eq!(docs.constants[1].name, "MIN_MINT_CYCLES");
+        assert_eq!(docs.constants[1].value, "u2");
+        assert_eq!(docs.maps.len(), 1);
+        assert_eq!(docs.maps[0].key_type, "principal");
+        assert_eq!(docs.data_vars.len(), 1);
+        assert_eq!(docs.data_vars[0].name, "total-supply");
+        assert_eq!(docs.traits.len(), 1);
+        assert_eq!(
+            docs.traits[0].functions[0].signature,
+            "(transfer (uint principal) (response bool uint))"
+        );
+
+        let markdown = render_contract_markdown("test", StacksEpochId::Epoch21, &docs);
+        assert!(markdown.starts_with("# `test`\n"));
+        assert!(markdown.contains("epoch 2.1 (Clarity 2)"));
+        for section in [
+            "## Public functions",
+            "## Read-only functions",
+            "## Maps",
+            "## Data variables",
+            "## Traits",
+            "## Constants",
+            "## Error codes",
+        ] {
+            assert!(markdown.contains(section), "missing {}", section);
+        }
+        assert!(markdown.contains("### `mint`"));
+        assert!(!markdown.contains("hidden"));
+        assert!(!markdown.contains("add-supply"));
+        assert!(!markdown.contains("## Fungible tokens"));
+        assert!(!markdown.contains("UNDOCUMENTED"));
+
+        let refs = BTreeMap::from_iter(vec![("test".to_string(), docs)]);
+        let json: serde_json::Value =
+            serde_json::from_str(&render_docs_json(StacksEpochId::Epoch21, &refs)).unwrap();
+        assert_eq!(json["schema_version"], CONTRACT_DOCS_SCHEMA_VERSION);
+        assert_eq!(json["epoch"], "2.1");
+        assert_eq!(json["contracts"]["test"]["data_vars"][0]["type"], "uint");
+        assert!(json["contracts"]["test"]["private_functions"].is_null());
+        assert_eq!(
+            render_docs_markdown(StacksEpochId::Epoch21, &refs)["test"],
+            markdown
+        );
+    }
+
+    #[test]
+    fn test_contract_docs_private_items() {
+        let docs = make_docs(
+            DOCS_TEST_CONTRACT,
+            &support_docs(),
+            StacksEpochId::Epoch21,
+            true,
+        )
+        .unwrap();
+        assert_eq!(docs.public_functions.len(), 1);
+        assert_eq!(docs.private_functions.len(), 1);
+        assert_eq!(docs.private_functions[0].name, "add-supply");
+        assert_eq!(docs.private_functions[0].description, "");
+        assert_eq!(docs.fungible_tokens.len(), 1);
+        assert_eq!(docs.fungible_tokens[0].name, "stackaroo");
+        assert_eq!(docs.non_fungible_tokens.len(), 1);
+        assert_eq!(docs.non_fungible_tokens[0].value_type, "uint");
+
+        let markdown = render_contract_markdown("test", StacksEpochId::Epoch21, &docs);
+        for section in [
+            "## Private functions",
+            "## Fungible tokens",
+            "## Non-fungible tokens",
+        ] {
+            assert!(markdown.contains(section), "missing {}", section);
+        }
+        assert!(markdown.contains("### `add-supply`"));
+        assert!(markdown.contains("| `badge` | `uint` |"));
+
+        let refs = BTreeMap::from_iter(vec![("test".to_string(), docs)]);
+        let json: serde_json::Value =
+            serde_json::from_str(&render_docs_json(StacksEpochId::Epoch21, &refs)).unwrap();
+        assert_eq!(
+            json["contracts"]["test"]["private_functions"][0]["signature"],
+            "(add-supply amount)"
+        );
+        assert_eq!(
+            json["contracts"]["test"]["non_fungible_tokens"][0]["name"],
+            "badge"
+        );
+    }
+
+    // `stx-account` is new in Clarity 2, so this contract can only be documented from epoch 2.1
+    const CLARITY_2_TEST_CONTRACT: &str = "
+(define-constant ERR_LOCKED (err (get locked (stx-account tx-sender))))
+(define-read-only (get-unlock-height) (get unlock-height (stx-account tx-sender)))
+";
+
+    fn clarity_2_support_docs() -> ContractSupportDocs {
+        ContractSupportDocs {
+            descriptions: HashMap::from_iter(vec![("get-unlock-height", "Get the unlock height.")]),
+            skip_func_display: HashSet::new(),
+            constants: ConstantDocsPolicy::default(),
+        }
+    }
+
+    #[test]
+    fn test_contract_docs_epoch() {
+        let docs = make_docs(
+            CLARITY_2_TEST_CONTRACT,
+            &clarity_2_support_docs(),
+            StacksEpochId::Epoch21,
+            false,
+        )
+        .unwrap();
+        assert_eq!(docs.read_only_functions.len(), 1);
+        assert_eq!(docs.error_codes.len(), 1);
+        assert_eq!(docs.error_codes[0].value, "(err u0)");
+    }
+
+    #[test]
+    fn test_contract_docs_epoch_too_early() {
+        let result = make_docs(
+            CLARITY_2_TEST_CONTRACT,
+            &clarity_2_support_docs(),
+            StacksEpochId::Epoch2_05,
+            false,
+        );
+        assert!(matches!(result, Err(DocsError::TypeCheck(_))));
+    }
+
+    #[test]
+    fn test_contract_docs_errors() {
+        let undescribed = ContractSupportDocs {
+            descriptions: HashMap::new(),
+            skip_func_display: HashSet::from_iter(vec!["hidden"]),
+            constants: ConstantDocsPolicy::default(),
+        };
+        assert_eq!(
+            make_docs(
+                DOCS_TEST_CONTRACT,
+                &undescribed,
+                StacksEpochId::Epoch21,
+                false
+            )
+            .err(),
+            Some(DocsError::MissingDescriptions(vec![
+                "mint".to_string(),
+                "get-total-supply".to_string()
+            ]))
+        );
+
+        let bad_contract = "
+(define-constant ERR_OK (err u1))
+(define-constant ERR_OVERFLOW (err (+ u340282366920938463463374607431768211455 u1)))
+";
+        let support_docs = ContractSupportDocs {
+            descriptions: HashMap::new(),
+            skip_func_display: HashSet::new(),
+            constants: ConstantDocsPolicy::default(),
+        };
+        let result = make_docs(bad_contract, &support_docs, StacksEpochId::Epoch21, false);
+        assert!(matches!(result, Err(DocsError::ContractEvaluation(_))));
+
+        // every failing contract is reported, with its name
+        let contracts = vec![
+            ("undescribed", DOCS_TEST_CONTRACT),
+            ("bad-contract", bad_contract),
+            ("clarity-2", CLARITY_2_TEST_CONTRACT),
+        ];
+        let all_support_docs = HashMap::from_iter(vec![
+            ("undescribed", undescribed),
+            ("bad-contract", support_docs),
+            ("clarity-2", clarity_2_support_docs()),
+        ]);
+        let errors = produce_docs_refs(
+            &contracts,
+            &all_support_docs,
+            StacksEpochId::Epoch2_05,
+            false,
+        )
+        .err()
+        .unwrap();
+        let contract_names: Vec<_> = errors
+            .iter()
+            .map(|e| match e {
+                DocsError::Contract(contract_name, _) => contract_name.as_str(),
+                _ => panic!("Unexpected error {:?}", e),
+            })
+            .collect();
+        assert_eq!(
+            contract_names,
+            vec!["undescribed", "bad-contract", "clarity-2"]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "undescribed: no description for mint, get-total-supply"
+        );
+    }
 }
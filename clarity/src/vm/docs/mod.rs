@@ -2661,7 +2661,7 @@ mod test {
 
     use crate::vm::ast::ASTRules;
     use crate::vm::costs::ExecutionCost;
-    use stacks_common::consts::CHAIN_ID_TESTNET;
+    use stacks_common::types::NetworkId;
 
     struct DocHeadersDB {}
     const DOC_HEADER_DB: DocHeadersDB = DocHeadersDB {};
@@ -2913,8 +2913,7 @@ mod test {
         let mut contract_context =
             ContractContext::new(contract_id.clone(), ClarityVersion::latest());
         let mut global_context = GlobalContext::new(
-            false,
-            CHAIN_ID_TESTNET,
+            NetworkId::testnet(),
             conn,
             LimitedCostTracker::new_free(),
             StacksEpochId::latest(),
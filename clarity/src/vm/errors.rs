@@ -23,9 +23,12 @@ use crate::vm::ast::errors::ParseError;
 use crate::vm::contexts::StackTrace;
 use crate::vm::costs::CostErrors;
 use crate::vm::types::{TypeSignature, Value};
+use clarity_derive::StacksError;
 use rusqlite::Error as SqliteError;
 use serde_json::Error as SerdeJSONErr;
-use std::error;
+use stacks_common::util::hash::to_hex;
+use stacks_common::util::telemetry::ErrorTelemetry;
+use std::collections::BTreeMap;
 use std::error::Error as ErrorTrait;
 use std::fmt;
 
@@ -34,14 +37,19 @@ pub struct IncomparableError<T> {
     pub err: T,
 }
 
-#[derive(Debug)]
+#[derive(Debug, StacksError)]
+#[error(label = "vm", skip_display)]
 pub enum Error {
     /// UncheckedErrors are errors that *should* be caught by the
     ///   TypeChecker and other check passes. Test executions may
     ///   trigger these errors.
+    #[error(code = 1001)]
     Unchecked(CheckErrors),
+    #[error(code = 1002)]
     Interpreter(InterpreterError),
+    #[error(code = 1003)]
     Runtime(RuntimeErrorType, Option<StackTrace>),
+    #[error(code = 1004)]
     ShortReturn(ShortReturnType),
 }
 
@@ -68,37 +76,66 @@ pub enum InterpreterError {
 
 /// RuntimeErrors are errors that smart contracts are expected
 ///   to be able to trigger during execution (e.g., arithmetic errors)
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, StacksError)]
+#[error(label = "vm_runtime", skip_display)]
 pub enum RuntimeErrorType {
+    #[error(code = 1101)]
     Arithmetic(String),
+    #[error(code = 1102)]
     ArithmeticOverflow,
+    #[error(code = 1103)]
     ArithmeticUnderflow,
+    #[error(code = 1104)]
     SupplyOverflow(u128, u128),
+    #[error(code = 1105)]
     SupplyUnderflow(u128, u128),
+    #[error(code = 1106)]
     DivisionByZero,
     // error in parsing types
+    #[error(code = 1107)]
     ParseError(String),
     // error in parsing the AST
+    #[error(code = 1108)]
     ASTError(ParseError),
+    #[error(code = 1109)]
     MaxStackDepthReached,
+    #[error(code = 1110)]
     MaxContextDepthReached,
+    #[error(code = 1111)]
     ListDimensionTooHigh,
+    #[error(code = 1112)]
     BadTypeConstruction,
+    #[error(code = 1113)]
     ValueTooLarge,
+    #[error(code = 1114)]
     BadBlockHeight(String),
+    #[error(code = 1115)]
     TransferNonPositiveAmount,
+    #[error(code = 1116)]
     NoSuchToken,
+    #[error(code = 1117)]
     NotImplemented,
+    #[error(code = 1118)]
     NoCallerInContext,
+    #[error(code = 1119)]
     NoSenderInContext,
+    #[error(code = 1120)]
     NonPositiveTokenSupply,
+    #[error(code = 1121)]
     JSONParseError(IncomparableError<SerdeJSONErr>),
+    #[error(code = 1122)]
     AttemptToFetchInTransientContext,
+    #[error(code = 1123)]
     BadNameValue(&'static str, String),
+    #[error(code = 1124)]
     UnknownBlockHeaderHash(BlockHeaderHash),
+    #[error(code = 1125)]
     BadBlockHash(Vec<u8>),
+    #[error(code = 1126)]
     UnwrapFailure,
+    #[error(code = 1127)]
     DefunctPoxContract,
+    #[error(code = 1128)]
     PoxAlreadyLocked,
 }
 
@@ -155,15 +192,105 @@ impl fmt::Display for RuntimeErrorType {
     }
 }
 
-impl error::Error for Error {
-    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        None
+/// A machine-readable description of an error that ended a transaction's execution, for
+/// transaction receipts: the error's `ErrorTelemetry` code and label, plus whatever values the
+/// error carries, by name.  Consumers should classify errors by `code`, which never changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VmErrorCode {
+    pub code: u32,
+    pub label: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub context: BTreeMap<String, String>,
+}
+
+impl VmErrorCode {
+    fn new<E: ErrorTelemetry>(err: &E, context: Vec<(&str, String)>) -> VmErrorCode {
+        VmErrorCode {
+            code: err.code(),
+            label: err.metric_label().to_string(),
+            context: context
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        }
+    }
+
+    /// Describe a check error that was only caught at runtime
+    pub fn from_check_error(err: CheckErrors) -> VmErrorCode {
+        VmErrorCode::from(&Error::Unchecked(err))
+    }
+}
+
+impl RuntimeErrorType {
+    /// The values this error carries, by name
+    pub fn context(&self) -> Vec<(&'static str, String)> {
+        match self {
+            RuntimeErrorType::Arithmetic(message) | RuntimeErrorType::ParseError(message) => {
+                vec![("message", message.clone())]
+            }
+            RuntimeErrorType::SupplyOverflow(new_supply, total_supply) => vec![
+                ("new_supply", new_supply.to_string()),
+                ("total_supply", total_supply.to_string()),
+            ],
+            RuntimeErrorType::SupplyUnderflow(current_supply, amount) => vec![
+                ("current_supply", current_supply.to_string()),
+                ("amount", amount.to_string()),
+            ],
+            RuntimeErrorType::ASTError(err) => vec![("message", err.to_string())],
+            RuntimeErrorType::BadBlockHeight(height) => vec![("block_height", height.clone())],
+            RuntimeErrorType::JSONParseError(err) => vec![("message", err.err.to_string())],
+            RuntimeErrorType::BadNameValue(name_type, value) => {
+                vec![("type", name_type.to_string()), ("value", value.clone())]
+            }
+            RuntimeErrorType::UnknownBlockHeaderHash(hash) => {
+                vec![("block_header_hash", hash.to_hex())]
+            }
+            RuntimeErrorType::BadBlockHash(bytes) => vec![("block_hash", to_hex(bytes))],
+            RuntimeErrorType::ArithmeticOverflow
+            | RuntimeErrorType::ArithmeticUnderflow
+            | RuntimeErrorType::DivisionByZero
+            | RuntimeErrorType::MaxStackDepthReached
+            | RuntimeErrorType::MaxContextDepthReached
+            | RuntimeErrorType::ListDimensionTooHigh
+            | RuntimeErrorType::BadTypeConstruction
+            | RuntimeErrorType::ValueTooLarge
+            | RuntimeErrorType::TransferNonPositiveAmount
+            | RuntimeErrorType::NoSuchToken
+            | RuntimeErrorType::NotImplemented
+            | RuntimeErrorType::NoCallerInContext
+            | RuntimeErrorType::NoSenderInContext
+            | RuntimeErrorType::NonPositiveTokenSupply
+            | RuntimeErrorType::AttemptToFetchInTransientContext
+            | RuntimeErrorType::UnwrapFailure
+            | RuntimeErrorType::DefunctPoxContract
+            | RuntimeErrorType::PoxAlreadyLocked => vec![],
+        }
+    }
+}
+
+impl From<&RuntimeErrorType> for VmErrorCode {
+    fn from(err: &RuntimeErrorType) -> VmErrorCode {
+        VmErrorCode::new(err, err.context())
     }
 }
 
-impl error::Error for RuntimeErrorType {
-    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        None
+impl From<&Error> for VmErrorCode {
+    /// Runtime errors are described by their `RuntimeErrorType`, which is more specific
+    fn from(err: &Error) -> VmErrorCode {
+        match err {
+            Error::Runtime(runtime_err, _) => VmErrorCode::from(runtime_err),
+            Error::Unchecked(check_err) => {
+                VmErrorCode::new(err, vec![("message", check_err.to_string())])
+            }
+            Error::Interpreter(interpreter_err) => {
+                VmErrorCode::new(err, vec![("message", format!("{:?}", interpreter_err))])
+            }
+            Error::ShortReturn(ShortReturnType::ExpectedValue(value))
+            | Error::ShortReturn(ShortReturnType::AssertionFailed(value)) => {
+                VmErrorCode::new(err, vec![("value", value.to_string())])
+            }
+        }
     }
 }
 
@@ -254,4 +381,84 @@ _native_:native_div
                 != Error::Interpreter(InterpreterError::InterpreterError("".to_string()))
         );
     }
+
+    #[test]
+    fn telemetry() {
+        let err = execute("(/ 10 0)").unwrap_err();
+        assert_eq!(err.code(), 1003);
+        assert_eq!(err.metric_label(), "vm_runtime");
+        match err {
+            Error::Runtime(runtime_err, _) => {
+                assert_eq!(runtime_err.code(), 1106);
+                assert_eq!(runtime_err.metric_label(), "vm_runtime_division_by_zero");
+            }
+            _ => panic!("expected a runtime error"),
+        }
+    }
+
+    #[test]
+    fn vm_error_codes() {
+        let err = execute("(/ 10 0)").unwrap_err();
+        let code = VmErrorCode::from(&err);
+        assert_eq!(code.code, 1106);
+        assert_eq!(code.label, "vm_runtime_division_by_zero");
+        assert!(code.context.is_empty());
+        assert_eq!(
+            serde_json::to_string(&code).unwrap(),
+            r#"{"code":1106,"label":"vm_runtime_division_by_zero"}"#
+        );
+
+        let code = VmErrorCode::from(&RuntimeErrorType::SupplyOverflow(6, 5));
+        assert_eq!(
+            serde_json::to_string(&code).unwrap(),
+            r#"{"code":1104,"label":"vm_runtime_supply_overflow","context":{"new_supply":"6","total_supply":"5"}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<VmErrorCode>(&serde_json::to_string(&code).unwrap()).unwrap(),
+            code
+        );
+
+        let err = execute("(asserts! false (err u1))").unwrap_err();
+        let code = VmErrorCode::from(&err);
+        assert_eq!((code.code, code.label.as_str()), (1004, "vm_short_return"));
+        assert_eq!(code.context.get("value").unwrap(), "(err u1)");
+
+        let code = VmErrorCode::from_check_error(CheckErrors::NoSuchContract("foo".into()));
+        assert_eq!((code.code, code.label.as_str()), (1001, "vm_unchecked"));
+        assert!(code.context.contains_key("message"));
+    }
+
+    #[derive(Debug, StacksError)]
+    #[error(label = "test")]
+    enum TestError {
+        #[error(code = 1)]
+        Message(String),
+        #[error(code = 2, source)]
+        Wrapped(RuntimeErrorType),
+        #[error(code = 3, display = "{1} of {0:?}", label = "too_many")]
+        TooManyItems(Vec<u8>, usize),
+        #[error(code = 4, display = "{{{name}}} not found")]
+        NotFound { name: String },
+    }
+
+    #[test]
+    fn derived_error() {
+        let err = TestError::Message("oops".into());
+        assert_eq!(err.to_string(), "oops");
+        assert!(err.source().is_none());
+        assert_eq!((err.code(), err.metric_label()), (1, "test_message"));
+
+        let err = TestError::Wrapped(RuntimeErrorType::DivisionByZero);
+        assert_eq!(err.to_string(), "DivisionByZero");
+        assert_eq!(err.source().unwrap().to_string(), "DivisionByZero");
+        assert_eq!((err.code(), err.metric_label()), (2, "test_wrapped"));
+
+        let err = TestError::TooManyItems(vec![1, 2], 2);
+        assert_eq!(err.to_string(), "2 of [1, 2]");
+        assert_eq!((err.code(), err.metric_label()), (3, "test_too_many"));
+
+        let err = TestError::NotFound { name: "foo".into() };
+        assert_eq!(err.to_string(), "{foo} not found");
+        assert_eq!((err.code(), err.metric_label()), (4, "test_not_found"));
+    }
 }
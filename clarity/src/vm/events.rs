@@ -29,6 +29,7 @@ pub enum StacksTransactionEvent {
     STXEvent(STXEventType),
     NFTEvent(NFTEventType),
     FTEvent(FTEventType),
+    PoxDefunctCallEvent(PoxDefunctCallEventData),
 }
 
 impl StacksTransactionEvent {
@@ -74,6 +75,13 @@ impl StacksTransactionEvent {
                 "type": "stx_lock_event",
                 "stx_lock_event": event_data.json_serialize()
             }),
+            StacksTransactionEvent::STXEvent(STXEventType::STXUnlockEvent(event_data)) => json!({
+                "txid": format!("0x{:?}", txid),
+                "event_index": event_index,
+                "committed": committed,
+                "type": "stx_unlock_event",
+                "stx_unlock_event": event_data.json_serialize()
+            }),
             StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(event_data)) => json!({
                 "txid": format!("0x{:?}", txid),
                 "event_index": event_index,
@@ -116,6 +124,13 @@ impl StacksTransactionEvent {
                 "type": "ft_burn_event",
                 "ft_burn_event": event_data.json_serialize()
             }),
+            StacksTransactionEvent::PoxDefunctCallEvent(event_data) => json!({
+                "txid": format!("0x{:?}", txid),
+                "event_index": event_index,
+                "committed": committed,
+                "type": "pox_defunct_call_event",
+                "pox_defunct_call_event": event_data.json_serialize()
+            }),
         }
     }
 }
@@ -126,6 +141,7 @@ pub enum STXEventType {
     STXMintEvent(STXMintEventData),
     STXBurnEvent(STXBurnEventData),
     STXLockEvent(STXLockEventData),
+    STXUnlockEvent(STXUnlockEventData),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -176,12 +192,33 @@ impl STXMintEventData {
     }
 }
 
+/// An account's STX lock, as it was before a lock event was applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct STXLockState {
+    pub locked_amount: u128,
+    pub unlock_height: u64,
+}
+
+impl STXLockState {
+    pub fn json_serialize(&self) -> serde_json::Value {
+        json!({
+            "locked_amount": format!("{}", self.locked_amount),
+            "unlock_height": format!("{}", self.unlock_height),
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct STXLockEventData {
     pub locked_amount: u128,
     pub unlock_height: u64,
     pub locked_address: PrincipalData,
     pub contract_identifier: QualifiedContractIdentifier,
+    /// PoX function whose result produced this lock (e.g. `stack-stx` or `stack-extend`)
+    pub function_name: String,
+    /// The account's lock before this event, if it had one.  Lets observers tell new locks
+    /// apart from extensions and increases of an existing lock.
+    pub prior_lock: Option<STXLockState>,
 }
 
 impl STXLockEventData {
@@ -191,6 +228,32 @@ impl STXLockEventData {
             "unlock_height": format!("{}", self.unlock_height),
             "locked_address": format!("{}", self.locked_address),
             "contract_identifier": self.contract_identifier.to_string(),
+            "function_name": self.function_name,
+            "prior_lock": self.prior_lock.as_ref().map(|lock| lock.json_serialize()),
+        })
+    }
+}
+
+/// An account's STX lock was released before its unlock height
+#[derive(Debug, Clone, PartialEq)]
+pub struct STXUnlockEventData {
+    pub unlocked_amount: u128,
+    pub unlocked_address: PrincipalData,
+    pub contract_identifier: QualifiedContractIdentifier,
+    /// PoX function whose result released this lock
+    pub function_name: String,
+    /// The account's lock before it was released
+    pub prior_lock: STXLockState,
+}
+
+impl STXUnlockEventData {
+    pub fn json_serialize(&self) -> serde_json::Value {
+        json!({
+            "unlocked_amount": format!("{}", self.unlocked_amount),
+            "unlocked_address": format!("{}", self.unlocked_address),
+            "contract_identifier": self.contract_identifier.to_string(),
+            "function_name": self.function_name,
+            "prior_lock": self.prior_lock.json_serialize(),
         })
     }
 }
@@ -359,3 +422,23 @@ impl SmartContractEventData {
         })
     }
 }
+
+/// A call to a PoX contract which is no longer active (e.g. `stack-stx` in pox.clar after the
+/// 2.1 transition) was rejected with `DefunctPoxContract`.  The transaction's other events are
+/// rolled back with it, so this is the only event its receipt carries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoxDefunctCallEventData {
+    pub sender: PrincipalData,
+    pub contract_identifier: QualifiedContractIdentifier,
+    pub function_name: String,
+}
+
+impl PoxDefunctCallEventData {
+    pub fn json_serialize(&self) -> serde_json::Value {
+        json!({
+            "sender": format!("{}", self.sender),
+            "contract_identifier": self.contract_identifier.to_string(),
+            "function_name": self.function_name,
+        })
+    }
+}
@@ -23,6 +23,7 @@ use crate::vm::errors::{
     check_argument_count, check_arguments_at_least, CheckErrors, Error,
     InterpreterResult as Result, RuntimeErrorType, ShortReturnType,
 };
+use crate::vm::precompiles::HashFunction;
 use crate::vm::representations::SymbolicExpressionType::{Atom, List};
 use crate::vm::representations::{ClarityName, SymbolicExpression, SymbolicExpressionType};
 use crate::vm::types::StacksAddressExtensions;
@@ -32,16 +33,14 @@ use crate::vm::types::{
 };
 use crate::vm::{eval, ClarityVersion, Environment, LocalContext};
 use stacks_common::address::AddressHashMode;
-use stacks_common::address::{
-    C32_ADDRESS_VERSION_MAINNET_SINGLESIG, C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
-};
-use stacks_common::util::hash;
+use stacks_common::address::C32_ADDRESS_VERSION_TESTNET_SINGLESIG;
+use stacks_common::types::NetworkId;
 use stacks_common::util::secp256k1::{secp256k1_recover, secp256k1_verify, Secp256k1PublicKey};
 
 use crate::types::chainstate::StacksAddress;
 
 macro_rules! native_hash_func {
-    ($name:ident, $module:ty) => {
+    ($name:ident, $function:expr) => {
         pub fn $name(input: Value) -> Result<Value> {
             let bytes = match input {
                 Value::Int(value) => Ok(value.to_le_bytes().to_vec()),
@@ -56,17 +55,16 @@ macro_rules! native_hash_func {
                     input,
                 )),
             }?;
-            let hash = <$module>::from_data(&bytes);
-            Value::buff_from(hash.as_bytes().to_vec())
+            Value::buff_from($function.digest(&bytes))
         }
     };
 }
 
-native_hash_func!(native_hash160, hash::Hash160);
-native_hash_func!(native_sha256, hash::Sha256Sum);
-native_hash_func!(native_sha512, hash::Sha512Sum);
-native_hash_func!(native_sha512trunc256, hash::Sha512Trunc256Sum);
-native_hash_func!(native_keccak256, hash::Keccak256Hash);
+native_hash_func!(native_hash160, HashFunction::Hash160);
+native_hash_func!(native_sha256, HashFunction::Sha256);
+native_hash_func!(native_sha512, HashFunction::Sha512);
+native_hash_func!(native_sha512trunc256, HashFunction::Sha512Trunc256);
+native_hash_func!(native_keccak256, HashFunction::Keccak256);
 
 // Note: Clarity1 had a bug in how the address is computed (issues/2619).
 // This method preserves the old, incorrect behavior for those running Clarity1.
@@ -82,14 +80,9 @@ fn pubkey_to_address_v1(pub_key: Secp256k1PublicKey) -> StacksAddress {
 
 // Note: Clarity1 had a bug in how the address is computed (issues/2619).
 // This version contains the code for Clarity2 and going forward.
-fn pubkey_to_address_v2(pub_key: Secp256k1PublicKey, is_mainnet: bool) -> StacksAddress {
-    let network_byte = if is_mainnet {
-        C32_ADDRESS_VERSION_MAINNET_SINGLESIG
-    } else {
-        C32_ADDRESS_VERSION_TESTNET_SINGLESIG
-    };
+fn pubkey_to_address_v2(pub_key: Secp256k1PublicKey, network: &NetworkId) -> StacksAddress {
     StacksAddress::from_public_keys(
-        network_byte,
+        network.address_version_singlesig,
         &AddressHashMode::SerializeP2PKH,
         1,
         &vec![pub_key],
@@ -123,7 +116,7 @@ pub fn special_principal_of(
         // Note: Clarity1 had a bug in how the address is computed (issues/2619).
         // We want to preserve the old behavior unless the version is greater.
         let addr = if *env.contract_context.get_clarity_version() > ClarityVersion::Clarity1 {
-            pubkey_to_address_v2(pub_key, env.global_context.mainnet)
+            pubkey_to_address_v2(pub_key, &env.global_context.network)
         } else {
             pubkey_to_address_v1(pub_key)
         };
@@ -27,6 +27,7 @@ use crate::vm::errors::{
     check_argument_count, check_arguments_at_least, CheckErrors, InterpreterError,
     InterpreterResult as Result, RuntimeErrorType,
 };
+use crate::vm::observer::DataAccess;
 use crate::vm::representations::{SymbolicExpression, SymbolicExpressionType};
 use crate::vm::types::{
     BlockInfoProperty, BuffData, BurnBlockInfoProperty, OptionalData, PrincipalData, SequenceData,
@@ -223,6 +224,40 @@ pub fn special_contract_call(
     Ok(result)
 }
 
+/// Tell any `VmObserver`s that this contract's data var or data map `name` was read
+fn notify_data_read(env: &mut Environment, name: &str, key: Option<&Value>, result: &Value) {
+    let contract = &env.contract_context.contract_identifier;
+    env.global_context.notify_vm_observers(|observer| {
+        observer.did_read_data(&DataAccess {
+            contract,
+            name,
+            key,
+            value: Some(result),
+            result,
+        })
+    });
+}
+
+/// Tell any `VmObserver`s that this contract's data var or data map `name` was written to
+fn notify_data_write(
+    env: &mut Environment,
+    name: &str,
+    key: Option<&Value>,
+    value: Option<&Value>,
+    result: &Value,
+) {
+    let contract = &env.contract_context.contract_identifier;
+    env.global_context.notify_vm_observers(|observer| {
+        observer.did_write_data(&DataAccess {
+            contract,
+            name,
+            key,
+            value,
+            result,
+        })
+    });
+}
+
 pub fn special_fetch_variable_v200(
     args: &[SymbolicExpression],
     env: &mut Environment,
@@ -247,9 +282,12 @@ pub fn special_fetch_variable_v200(
     )?;
 
     let epoch = env.epoch().clone();
-    env.global_context
+    let result = env
+        .global_context
         .database
-        .lookup_variable(contract, var_name, data_types, &epoch)
+        .lookup_variable(contract, var_name, data_types, &epoch)?;
+    notify_data_read(env, var_name, None, &result);
+    Ok(result)
 }
 
 /// The Stacks v205 version of fetch_variable uses the actual stored size of the
@@ -284,7 +322,9 @@ pub fn special_fetch_variable_v205(
 
     runtime_cost(ClarityCostFunction::FetchVar, env, result_size)?;
 
-    result.map(|data| data.value)
+    let result = result?.value;
+    notify_data_read(env, var_name, None, &result);
+    Ok(result)
 }
 
 pub fn special_set_variable_v200(
@@ -299,6 +339,7 @@ pub fn special_set_variable_v200(
     check_argument_count(2, args)?;
 
     let value = eval(&args[1], env, &context)?;
+    let observed_value = env.global_context.is_observed().then(|| value.clone());
 
     let var_name = args[0].match_atom().ok_or(CheckErrors::ExpectedName)?;
 
@@ -319,10 +360,15 @@ pub fn special_set_variable_v200(
     env.add_memory(value.get_memory_use())?;
 
     let epoch = env.epoch().clone();
-    env.global_context
+    let result = env
+        .global_context
         .database
-        .set_variable(contract, var_name, value, data_types, &epoch)
-        .map(|data| data.value)
+        .set_variable(contract, var_name, value, data_types, &epoch)?
+        .value;
+    if let Some(value) = observed_value {
+        notify_data_write(env, var_name, None, Some(&value), &result);
+    }
+    Ok(result)
 }
 
 /// The Stacks v205 version of set_variable uses the actual stored size of the
@@ -339,6 +385,7 @@ pub fn special_set_variable_v205(
     check_argument_count(2, args)?;
 
     let value = eval(&args[1], env, &context)?;
+    let observed_value = env.global_context.is_observed().then(|| value.clone());
 
     let var_name = args[0].match_atom().ok_or(CheckErrors::ExpectedName)?;
 
@@ -365,7 +412,11 @@ pub fn special_set_variable_v205(
 
     env.add_memory(result_size)?;
 
-    result.map(|data| data.value)
+    let result = result?.value;
+    if let Some(value) = observed_value {
+        notify_data_write(env, var_name, None, Some(&value), &result);
+    }
+    Ok(result)
 }
 
 pub fn special_fetch_entry_v200(
@@ -394,9 +445,12 @@ pub fn special_fetch_entry_v200(
     )?;
 
     let epoch = env.epoch().clone();
-    env.global_context
+    let result = env
+        .global_context
         .database
-        .fetch_entry(contract, map_name, &key, data_types, &epoch)
+        .fetch_entry(contract, map_name, &key, data_types, &epoch)?;
+    notify_data_read(env, map_name, Some(&key), &result);
+    Ok(result)
 }
 
 /// The Stacks v205 version of fetch_entry uses the actual stored size of the
@@ -433,7 +487,9 @@ pub fn special_fetch_entry_v205(
 
     runtime_cost(ClarityCostFunction::FetchEntry, env, result_size)?;
 
-    result.map(|data| data.value)
+    let result = result?.value;
+    notify_data_read(env, map_name, Some(&key), &result);
+    Ok(result)
 }
 
 pub fn special_at_block(
@@ -477,6 +533,10 @@ pub fn special_set_entry_v200(
     let key = eval(&args[1], env, &context)?;
 
     let value = eval(&args[2], env, &context)?;
+    let observed_entry = env
+        .global_context
+        .is_observed()
+        .then(|| (key.clone(), value.clone()));
 
     let map_name = args[0].match_atom().ok_or(CheckErrors::ExpectedName)?;
 
@@ -498,10 +558,15 @@ pub fn special_set_entry_v200(
     env.add_memory(value.get_memory_use())?;
 
     let epoch = env.epoch().clone();
-    env.global_context
+    let result = env
+        .global_context
         .database
-        .set_entry(contract, map_name, key, value, data_types, &epoch)
-        .map(|data| data.value)
+        .set_entry(contract, map_name, key, value, data_types, &epoch)?
+        .value;
+    if let Some((key, value)) = observed_entry {
+        notify_data_write(env, map_name, Some(&key), Some(&value), &result);
+    }
+    Ok(result)
 }
 
 /// The Stacks v205 version of set_entry uses the actual stored size of the
@@ -520,6 +585,10 @@ pub fn special_set_entry_v205(
     let key = eval(&args[1], env, &context)?;
 
     let value = eval(&args[2], env, &context)?;
+    let observed_entry = env
+        .global_context
+        .is_observed()
+        .then(|| (key.clone(), value.clone()));
 
     let map_name = args[0].match_atom().ok_or(CheckErrors::ExpectedName)?;
 
@@ -546,7 +615,11 @@ pub fn special_set_entry_v205(
 
     env.add_memory(result_size)?;
 
-    result.map(|data| data.value)
+    let result = result?.value;
+    if let Some((key, value)) = observed_entry {
+        notify_data_write(env, map_name, Some(&key), Some(&value), &result);
+    }
+    Ok(result)
 }
 
 pub fn special_insert_entry_v200(
@@ -563,6 +636,10 @@ pub fn special_insert_entry_v200(
     let key = eval(&args[1], env, &context)?;
 
     let value = eval(&args[2], env, &context)?;
+    let observed_entry = env
+        .global_context
+        .is_observed()
+        .then(|| (key.clone(), value.clone()));
 
     let map_name = args[0].match_atom().ok_or(CheckErrors::ExpectedName)?;
 
@@ -585,10 +662,15 @@ pub fn special_insert_entry_v200(
 
     let epoch = env.epoch().clone();
 
-    env.global_context
+    let result = env
+        .global_context
         .database
-        .insert_entry(contract, map_name, key, value, data_types, &epoch)
-        .map(|data| data.value)
+        .insert_entry(contract, map_name, key, value, data_types, &epoch)?
+        .value;
+    if let Some((key, value)) = observed_entry {
+        notify_data_write(env, map_name, Some(&key), Some(&value), &result);
+    }
+    Ok(result)
 }
 
 /// The Stacks v205 version of insert_entry uses the actual stored size of the
@@ -607,6 +689,10 @@ pub fn special_insert_entry_v205(
     let key = eval(&args[1], env, &context)?;
 
     let value = eval(&args[2], env, &context)?;
+    let observed_entry = env
+        .global_context
+        .is_observed()
+        .then(|| (key.clone(), value.clone()));
 
     let map_name = args[0].match_atom().ok_or(CheckErrors::ExpectedName)?;
 
@@ -633,7 +719,11 @@ pub fn special_insert_entry_v205(
 
     env.add_memory(result_size)?;
 
-    result.map(|data| data.value)
+    let result = result?.value;
+    if let Some((key, value)) = observed_entry {
+        notify_data_write(env, map_name, Some(&key), Some(&value), &result);
+    }
+    Ok(result)
 }
 
 pub fn special_delete_entry_v200(
@@ -668,10 +758,13 @@ pub fn special_delete_entry_v200(
     env.add_memory(key.get_memory_use())?;
 
     let epoch = env.epoch().clone();
-    env.global_context
+    let result = env
+        .global_context
         .database
-        .delete_entry(contract, map_name, &key, data_types, &epoch)
-        .map(|data| data.value)
+        .delete_entry(contract, map_name, &key, data_types, &epoch)?
+        .value;
+    notify_data_write(env, map_name, Some(&key), None, &result);
+    Ok(result)
 }
 
 /// The Stacks v205 version of delete_entry uses the actual stored size of the
@@ -714,7 +807,9 @@ pub fn special_delete_entry_v205(
 
     env.add_memory(result_size)?;
 
-    result.map(|data| data.value)
+    let result = result?.value;
+    notify_data_write(env, map_name, Some(&key), None, &result);
+    Ok(result)
 }
 
 pub fn special_get_block_info(
@@ -16,11 +16,6 @@ use crate::vm::{eval, ContractName, Environment, LocalContext};
 use stacks_common::util::hash::hex_bytes;
 use std::convert::TryFrom;
 
-use stacks_common::address::{
-    C32_ADDRESS_VERSION_MAINNET_MULTISIG, C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
-    C32_ADDRESS_VERSION_TESTNET_MULTISIG, C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
-};
-
 use crate::vm::representations::{CONTRACT_MAX_NAME_LENGTH, CONTRACT_MIN_NAME_LENGTH};
 
 pub enum PrincipalConstructErrorCode {
@@ -29,27 +24,11 @@ pub enum PrincipalConstructErrorCode {
     CONTRACT_NAME = 2,
 }
 
-/// Returns true if `version` indicates a mainnet address.
-fn version_matches_mainnet(version: u8) -> bool {
-    version == C32_ADDRESS_VERSION_MAINNET_MULTISIG
-        || version == C32_ADDRESS_VERSION_MAINNET_SINGLESIG
-}
-
-/// Returns true if `version` indicates a testnet address.
-fn version_matches_testnet(version: u8) -> bool {
-    version == C32_ADDRESS_VERSION_TESTNET_MULTISIG
-        || version == C32_ADDRESS_VERSION_TESTNET_SINGLESIG
-}
-
 /// Returns true if `version` indicates an address type that matches the network we are "currently
 /// operating in", as indicated by the GlobalContext.
 fn version_matches_current_network(version: u8, global_context: &GlobalContext) -> bool {
-    let context_is_mainnet = global_context.mainnet;
-    let context_is_testnet = !global_context.mainnet;
-
-    // Note: It is possible for the version to match neither mainnet or testnet.
-    (version_matches_mainnet(version) && context_is_mainnet)
-        || (version_matches_testnet(version) && context_is_testnet)
+    // Note: It is possible for the version to match no network at all.
+    global_context.network.is_network_version(version)
 }
 
 pub fn special_is_standard(
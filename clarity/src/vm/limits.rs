@@ -0,0 +1,86 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::vm::ast::stack_depth_checker::AST_CALL_STACK_DEPTH_BUFFER;
+use crate::vm::MAX_CALL_STACK_DEPTH;
+use stacks_common::types::StacksEpochId;
+
+/// Largest contract source (in bytes) that the parser will accept by default.
+/// This matches the maximum size of a Stacks transaction, so the default limit
+/// never rejects a contract that could otherwise be mined.
+pub const MAX_CONTRACT_SIZE: usize = 2 * 1024 * 1024;
+
+/// Structural limits enforced by the Clarity parser and interpreter.
+///
+/// The defaults returned by `VmLimits::for_epoch()` are the consensus limits used by the
+/// Stacks mainnet and testnet.  Private networks may construct their own `VmLimits` and
+/// install them on a `GlobalContext` (or pass them to the `*_with_limits` AST builders) in
+/// order to raise or lower these limits without patching constants.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VmLimits {
+    /// Maximum depth of the interpreter's call stack
+    pub max_call_stack_depth: usize,
+    /// How much deeper than `max_call_stack_depth` the AST is allowed to nest
+    pub ast_call_stack_depth_buffer: u64,
+    /// Maximum length, in bytes, of a contract's source code
+    pub max_contract_size: usize,
+}
+
+impl Default for VmLimits {
+    fn default() -> VmLimits {
+        VmLimits {
+            max_call_stack_depth: MAX_CALL_STACK_DEPTH,
+            ast_call_stack_depth_buffer: AST_CALL_STACK_DEPTH_BUFFER,
+            max_contract_size: MAX_CONTRACT_SIZE,
+        }
+    }
+}
+
+impl VmLimits {
+    /// Get the consensus limits in effect for the given epoch.
+    /// These have not changed across epochs to date.
+    pub fn for_epoch(_epoch_id: StacksEpochId) -> VmLimits {
+        VmLimits::default()
+    }
+
+    /// Maximum depth the AST may reach before the stack depth checker rejects it
+    pub fn max_ast_depth(&self) -> u64 {
+        self.ast_call_stack_depth_buffer
+            .saturating_add(self.max_call_stack_depth as u64)
+    }
+
+    /// Maximum nesting depth the parsers will tolerate before bailing out
+    pub fn max_nesting_depth(&self) -> u64 {
+        self.max_ast_depth().saturating_add(1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vm::ast::parser::v2::MAX_NESTING_DEPTH;
+
+    #[test]
+    fn test_default_limits_match_consensus_constants() {
+        let limits = VmLimits::for_epoch(StacksEpochId::Epoch21);
+        assert_eq!(limits, VmLimits::default());
+        assert_eq!(limits.max_nesting_depth(), MAX_NESTING_DEPTH);
+        assert_eq!(
+            limits.max_ast_depth(),
+            AST_CALL_STACK_DEPTH_BUFFER + MAX_CALL_STACK_DEPTH as u64
+        );
+    }
+}
@@ -37,12 +37,16 @@ pub mod variables;
 
 pub mod analysis;
 pub mod docs;
+pub mod limits;
+pub mod precompiles;
 pub mod version;
 
 pub mod coverage;
 
 pub mod events;
 
+pub mod observer;
+
 #[cfg(any(test, feature = "testing"))]
 pub mod tests;
 
@@ -54,7 +58,7 @@ pub mod clarity;
 use serde_json;
 
 // publish the non-generic StacksEpoch form for use throughout module
-use crate::types::StacksEpochId;
+use crate::types::{NetworkId, StacksEpochId};
 pub use crate::vm::database::clarity_db::StacksEpoch;
 
 use crate::vm::callables::CallableType;
@@ -64,6 +68,7 @@ use crate::vm::costs::{
     cost_functions, runtime_cost, CostOverflowingMath, CostTracker, LimitedCostTracker,
     MemoryConsumer,
 };
+use crate::vm::database::ClarityBackend;
 use crate::vm::errors::{
     CheckErrors, Error, InterpreterError, InterpreterResult as Result, RuntimeErrorType,
 };
@@ -80,6 +85,7 @@ pub use crate::vm::representations::{
 pub use crate::vm::contexts::MAX_CONTEXT_DEPTH;
 use crate::vm::costs::cost_functions::ClarityCostFunction;
 pub use crate::vm::functions::stx_transfer_consolidated;
+pub use crate::vm::limits::VmLimits;
 pub use crate::vm::version::ClarityVersion;
 use std::collections::BTreeMap;
 use std::convert::{TryFrom, TryInto};
@@ -250,7 +256,7 @@ pub fn apply(
         return Err(CheckErrors::CircularReference(vec![identifier.to_string()]).into());
     }
 
-    if env.call_stack.depth() >= MAX_CALL_STACK_DEPTH {
+    if env.call_stack.depth() >= env.global_context.limits.max_call_stack_depth {
         return Err(RuntimeErrorType::MaxStackDepthReached.into());
     }
 
@@ -329,6 +335,11 @@ pub fn eval<'a>(
         }
         env.global_context.eval_hooks = Some(eval_hooks);
     }
+    if let Some(coverage) = env.global_context.coverage.as_mut() {
+        coverage.record_eval(&env.contract_context.contract_identifier, exp);
+    }
+    env.global_context
+        .notify_vm_observers(|observer| observer.will_eval(exp));
 
     let res = match exp.expr {
         AtomValue(ref value) | LiteralValue(ref value) => Ok(value.clone()),
@@ -353,6 +364,8 @@ pub fn eval<'a>(
         }
         env.global_context.eval_hooks = Some(eval_hooks);
     }
+    env.global_context
+        .notify_vm_observers(|observer| observer.did_eval(exp, &res));
 
     res
 }
@@ -511,30 +524,21 @@ pub fn execute_on_network(program: &str, use_mainnet: bool) -> Result<Option<Val
     epoch_205_result
 }
 
-/// Runs `program` in a test environment with the provided parameters.
-#[cfg(any(test, feature = "testing"))]
-pub fn execute_with_parameters(
+/// Runs `program` as a transient contract against the state in `backend`, without charging
+/// costs.  Whatever the program writes is committed to the backend.
+pub fn execute_with_backend(
     program: &str,
+    backend: &mut dyn ClarityBackend,
+    network: NetworkId,
     clarity_version: ClarityVersion,
     epoch: StacksEpochId,
     ast_rules: ast::ASTRules,
-    use_mainnet: bool,
 ) -> Result<Option<Value>> {
-    use crate::vm::database::MemoryBackingStore;
-    use crate::vm::tests::test_only_mainnet_to_chain_id;
-
     let contract_id = QualifiedContractIdentifier::transient();
     let mut contract_context = ContractContext::new(contract_id.clone(), clarity_version);
-    let mut marf = MemoryBackingStore::new();
-    let conn = marf.as_clarity_db();
-    let chain_id = test_only_mainnet_to_chain_id(use_mainnet);
-    let mut global_context = GlobalContext::new(
-        use_mainnet,
-        chain_id,
-        conn,
-        LimitedCostTracker::new_free(),
-        epoch,
-    );
+    let conn = backend.as_clarity_db();
+    let mut global_context =
+        GlobalContext::new(network, conn, LimitedCostTracker::new_free(), epoch);
     global_context.execute(|g| {
         let parsed = ast::build_ast_with_rules(
             &contract_id,
@@ -549,6 +553,28 @@ pub fn execute_with_parameters(
     })
 }
 
+/// Runs `program` in a test environment with the provided parameters.
+#[cfg(any(test, feature = "testing"))]
+pub fn execute_with_parameters(
+    program: &str,
+    clarity_version: ClarityVersion,
+    epoch: StacksEpochId,
+    ast_rules: ast::ASTRules,
+    use_mainnet: bool,
+) -> Result<Option<Value>> {
+    use crate::vm::database::MemoryBackingStore;
+
+    let mut marf = MemoryBackingStore::new();
+    execute_with_backend(
+        program,
+        &mut marf,
+        NetworkId::from_mainnet(use_mainnet),
+        clarity_version,
+        epoch,
+        ast_rules,
+    )
+}
+
 /// Execute for test with `version`, Epoch20, testnet.
 #[cfg(any(test, feature = "testing"))]
 pub fn execute_against_version(program: &str, version: ClarityVersion) -> Result<Option<Value>> {
@@ -603,7 +629,7 @@ mod test {
 
     use super::ClarityVersion;
 
-    use stacks_common::consts::CHAIN_ID_TESTNET;
+    use stacks_common::types::NetworkId;
 
     #[test]
     fn test_simple_user_function() {
@@ -641,8 +667,7 @@ mod test {
 
         let mut marf = MemoryBackingStore::new();
         let mut global_context = GlobalContext::new(
-            false,
-            CHAIN_ID_TESTNET,
+            NetworkId::testnet(),
             marf.as_clarity_db(),
             LimitedCostTracker::new_free(),
             StacksEpochId::Epoch2_05,
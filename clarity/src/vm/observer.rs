@@ -0,0 +1,235 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Step-level tracing of Clarity execution.
+//!
+//! A `VmObserver` installed on a `GlobalContext` (see `OwnedEnvironment::add_vm_observer()`) is
+//! told about every user-defined function call and return, every expression evaluation, every
+//! asset transfer recorded in the transaction's asset map, and every data-var and data-map access.
+//! Observers only watch: they can't alter execution, and they don't charge any cost.
+//! `JsonTraceObserver` is a reference implementation that records a structured JSON trace.
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::vm::callables::FunctionIdentifier;
+use crate::vm::errors::Error;
+use crate::vm::representations::SymbolicExpression;
+use crate::vm::types::{AssetIdentifier, PrincipalData, QualifiedContractIdentifier, Value};
+
+/// An asset movement, as recorded in the transaction's asset map
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObservedTransfer<'a> {
+    Stx {
+        sender: &'a PrincipalData,
+        amount: u128,
+    },
+    StxBurn {
+        sender: &'a PrincipalData,
+        amount: u128,
+    },
+    FungibleToken {
+        sender: &'a PrincipalData,
+        asset: &'a AssetIdentifier,
+        amount: u128,
+    },
+    NonFungibleToken {
+        sender: &'a PrincipalData,
+        asset: &'a AssetIdentifier,
+        value: &'a Value,
+    },
+}
+
+/// An access to a contract's data var or data map
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DataAccess<'a> {
+    pub contract: &'a QualifiedContractIdentifier,
+    /// Name of the data var or data map
+    pub name: &'a str,
+    /// The map key, if this is a data map
+    pub key: Option<&'a Value>,
+    /// The value read, or the value written.  `None` for a map deletion.
+    pub value: Option<&'a Value>,
+    /// What the Clarity function that made the access evaluated to
+    pub result: &'a Value,
+}
+
+/// Observes Clarity execution.  Every callback has a no-op default, so an observer only needs to
+/// implement the events it cares about.
+pub trait VmObserver {
+    /// Called before a user-defined function's body is evaluated, with its arguments
+    fn will_call_function(&mut self, _function: &FunctionIdentifier, _args: &[Value]) {}
+
+    /// Called once a user-defined function has returned (or failed)
+    fn did_return_from_function(
+        &mut self,
+        _function: &FunctionIdentifier,
+        _result: &Result<Value, Error>,
+    ) {
+    }
+
+    /// Called before an expression is evaluated
+    fn will_eval(&mut self, _expr: &SymbolicExpression) {}
+
+    /// Called after an expression is evaluated
+    fn did_eval(&mut self, _expr: &SymbolicExpression, _result: &Result<Value, Error>) {}
+
+    /// Called as an asset transfer is logged to the transaction's asset map
+    fn did_transfer_asset(&mut self, _transfer: &ObservedTransfer) {}
+
+    /// Called after a data var or data map is read
+    fn did_read_data(&mut self, _access: &DataAccess) {}
+
+    /// Called after a data var or data map is written to
+    fn did_write_data(&mut self, _access: &DataAccess) {}
+}
+
+/// A `VmObserver` that records each event as a JSON object.  Every object has an `"event"` field
+/// naming the callback and a `"depth"` field giving the user-defined function call depth at which
+/// it happened.  Clarity values are rendered in their Clarity syntax.
+pub struct JsonTraceObserver {
+    events: Vec<JsonValue>,
+    depth: u64,
+    trace_exprs: bool,
+}
+
+impl JsonTraceObserver {
+    /// Make a new observer.  If `trace_exprs` is false, expression evaluations are not recorded,
+    /// which keeps the trace to a manageable size for long-running transactions.
+    pub fn new(trace_exprs: bool) -> JsonTraceObserver {
+        JsonTraceObserver {
+            events: vec![],
+            depth: 0,
+            trace_exprs,
+        }
+    }
+
+    /// The events recorded so far
+    pub fn events(&self) -> &[JsonValue] {
+        &self.events
+    }
+
+    /// Consume the observer, returning the trace as a JSON array
+    pub fn into_trace(self) -> JsonValue {
+        JsonValue::Array(self.events)
+    }
+
+    fn push(&mut self, event: &str, mut body: JsonValue) {
+        if let Some(obj) = body.as_object_mut() {
+            obj.insert("event".into(), json!(event));
+            obj.insert("depth".into(), json!(self.depth));
+        }
+        self.events.push(body);
+    }
+
+    fn result_to_json(result: &Result<Value, Error>) -> JsonValue {
+        match result {
+            Ok(value) => json!({ "ok": value.to_string() }),
+            Err(e) => json!({ "err": e.to_string() }),
+        }
+    }
+
+    fn access_to_json(access: &DataAccess) -> JsonValue {
+        json!({
+            "contract": access.contract.to_string(),
+            "name": access.name,
+            "key": access.key.map(|key| key.to_string()),
+            "value": access.value.map(|value| value.to_string()),
+            "result": access.result.to_string(),
+        })
+    }
+}
+
+impl VmObserver for JsonTraceObserver {
+    fn will_call_function(&mut self, function: &FunctionIdentifier, args: &[Value]) {
+        let args: Vec<_> = args.iter().map(|arg| arg.to_string()).collect();
+        self.push(
+            "call",
+            json!({ "function": function.to_string(), "args": args }),
+        );
+        self.depth += 1;
+    }
+
+    fn did_return_from_function(
+        &mut self,
+        function: &FunctionIdentifier,
+        result: &Result<Value, Error>,
+    ) {
+        self.depth = self.depth.saturating_sub(1);
+        self.push(
+            "return",
+            json!({
+                "function": function.to_string(),
+                "result": Self::result_to_json(result),
+            }),
+        );
+    }
+
+    fn did_eval(&mut self, expr: &SymbolicExpression, result: &Result<Value, Error>) {
+        if !self.trace_exprs {
+            return;
+        }
+        self.push(
+            "eval",
+            json!({
+                "expr": expr.to_string(),
+                "line": expr.span.start_line,
+                "column": expr.span.start_column,
+                "result": Self::result_to_json(result),
+            }),
+        );
+    }
+
+    fn did_transfer_asset(&mut self, transfer: &ObservedTransfer) {
+        let body = match transfer {
+            ObservedTransfer::Stx { sender, amount } => json!({
+                "asset": "stx",
+                "sender": sender.to_string(),
+                "amount": amount.to_string(),
+            }),
+            ObservedTransfer::StxBurn { sender, amount } => json!({
+                "asset": "stx-burn",
+                "sender": sender.to_string(),
+                "amount": amount.to_string(),
+            }),
+            ObservedTransfer::FungibleToken {
+                sender,
+                asset,
+                amount,
+            } => json!({
+                "asset": asset.to_string(),
+                "sender": sender.to_string(),
+                "amount": amount.to_string(),
+            }),
+            ObservedTransfer::NonFungibleToken {
+                sender,
+                asset,
+                value,
+            } => json!({
+                "asset": asset.to_string(),
+                "sender": sender.to_string(),
+                "value": value.to_string(),
+            }),
+        };
+        self.push("transfer", body);
+    }
+
+    fn did_read_data(&mut self, access: &DataAccess) {
+        self.push("read", Self::access_to_json(access));
+    }
+
+    fn did_write_data(&mut self, access: &DataAccess) {
+        self.push("write", Self::access_to_json(access));
+    }
+}
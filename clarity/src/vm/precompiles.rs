@@ -0,0 +1,105 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Native paths for Clarity's hash functions.
+//!
+//! Every hash function runs on a vetted implementation:
+//!   * `sha256` (and so `hash160`), `sha512`, and `sha512/256` run on the `sha2` crate, which
+//!     detects the CPU's support at runtime and uses its SHA-NI kernel for SHA-256 and its AVX2
+//!     kernel for SHA-512 when it can.
+//!   * `keccak256` runs on the `sha3` crate's portable Keccak implementation.
+//!
+//! Every path computes the same digest, so none of this is visible to consensus.  In particular,
+//! a hash function's cost is always charged from the cost schedule by input size, whichever path
+//! computes it.  The schedule links each hash function to the benchmark in `costs::benches` that
+//! measures it.
+
+use stacks_common::util::hash::{Hash160, Keccak256Hash, Sha256Sum, Sha512Sum, Sha512Trunc256Sum};
+
+/// The hash functions available to Clarity contracts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashFunction {
+    Hash160,
+    Sha256,
+    Sha512,
+    Sha512Trunc256,
+    Keccak256,
+}
+
+impl HashFunction {
+    /// Compute the digest of `input`
+    pub fn digest(&self, input: &[u8]) -> Vec<u8> {
+        match self {
+            HashFunction::Hash160 => Hash160::from_data(input).as_bytes().to_vec(),
+            HashFunction::Sha256 => Sha256Sum::from_data(input).as_bytes().to_vec(),
+            HashFunction::Sha512 => Sha512Sum::from_data(input).as_bytes().to_vec(),
+            HashFunction::Sha512Trunc256 => Sha512Trunc256Sum::from_data(input).as_bytes().to_vec(),
+            HashFunction::Keccak256 => Keccak256Hash::from_data(input).as_bytes().to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use stacks_common::util::hash::hex_bytes;
+
+    #[test]
+    fn test_hash_function_known_answers() {
+        let cases = [
+            (
+                HashFunction::Hash160,
+                "",
+                "b472a266d0bd89c13706a4132ccfb16f7c3b9fcb",
+            ),
+            (
+                HashFunction::Sha256,
+                "abc",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                HashFunction::Sha512,
+                "abc",
+                "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+                 2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            ),
+            (
+                HashFunction::Sha512Trunc256,
+                "abc",
+                "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23",
+            ),
+            (
+                HashFunction::Keccak256,
+                "",
+                "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+            ),
+            (
+                HashFunction::Keccak256,
+                "abc",
+                "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45",
+            ),
+        ];
+        for (function, input, expected) in cases.iter() {
+            assert_eq!(
+                function.digest(input.as_bytes()),
+                hex_bytes(&expected.replace(' ', "")).unwrap(),
+                "{:?}({:?})",
+                function,
+                input
+            );
+        }
+    }
+}
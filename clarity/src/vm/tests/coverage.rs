@@ -0,0 +1,136 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use crate::vm::ast::ASTRules;
+use crate::vm::coverage::ExpressionCoverage;
+use crate::vm::tests::{
+    execute, symbols_from_values, test_epochs, tl_env_factory as env_factory,
+    TopLevelMemoryEnvironmentGenerator,
+};
+use crate::vm::types::{PrincipalData, QualifiedContractIdentifier, Value};
+use stacks_common::types::StacksEpochId;
+
+const COVERED_CONTRACT: &str = "(define-public (f (a int))
+  (if (> a 0)
+      (ok (+ a 1))
+      (ok (- a 1))))";
+
+fn run_covered(
+    epoch: StacksEpochId,
+    env_factory: &mut TopLevelMemoryEnvironmentGenerator,
+    contract_id: &QualifiedContractIdentifier,
+    sender: &PrincipalData,
+) -> ExpressionCoverage {
+    let mut owned_env = env_factory.get_env(epoch);
+    owned_env.enable_coverage();
+    owned_env
+        .initialize_contract(
+            contract_id.clone(),
+            COVERED_CONTRACT,
+            None,
+            ASTRules::PrecheckSize,
+        )
+        .unwrap();
+    let (result, _asset_map, _events) = owned_env
+        .execute_transaction(
+            sender.clone(),
+            None,
+            contract_id.clone(),
+            "f",
+            &symbols_from_values(vec![Value::Int(5)]),
+        )
+        .unwrap();
+    assert_eq!(result, Value::okay(Value::Int(6)).unwrap());
+    owned_env.take_coverage().unwrap()
+}
+
+fn lcov(
+    coverage: &ExpressionCoverage,
+    src_files: &HashMap<QualifiedContractIdentifier, String>,
+) -> String {
+    let mut out = vec![];
+    coverage.write_lcov(&mut out, src_files).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[apply(test_epochs)]
+fn test_expression_coverage(
+    epoch: StacksEpochId,
+    mut env_factory: TopLevelMemoryEnvironmentGenerator,
+) {
+    let p1 = execute("'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR");
+    let (p1_principal, p1_std) = match p1 {
+        Value::Principal(PrincipalData::Standard(ref data)) => {
+            (PrincipalData::Standard(data.clone()), data.clone())
+        }
+        _ => panic!(),
+    };
+    let contract_id = QualifiedContractIdentifier::new(p1_std, "covered".into());
+    let src_files = HashMap::from([(contract_id.clone(), "covered.clar".to_string())]);
+
+    let coverage = run_covered(epoch, &mut env_factory, &contract_id, &p1_principal);
+    let report = lcov(&coverage, &src_files);
+    let lines: Vec<&str> = report.lines().collect();
+
+    assert_eq!(lines[0], format!("TN:{}", &contract_id));
+    assert_eq!(lines[1], "SF:covered.clar");
+
+    // (if ..), (> a 0), (ok (+ a 1)), (+ a 1), (ok (- a 1)), and (- a 1)
+    let expr_hits: Vec<&str> = lines
+        .iter()
+        .filter_map(|line| line.strip_prefix("EXPR:"))
+        .map(|expr| expr.rsplit(',').next().unwrap())
+        .collect();
+    assert_eq!(expr_hits.len(), 6);
+    assert_eq!(expr_hits.iter().filter(|hits| **hits == "1").count(), 4);
+    assert_eq!(expr_hits.iter().filter(|hits| **hits == "0").count(), 2);
+
+    let summary: Vec<&str> = lines
+        .iter()
+        .filter(|line| !line.starts_with("EXPR:"))
+        .cloned()
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            format!("TN:{}", &contract_id).as_str(),
+            "SF:covered.clar",
+            "EXPRF:6",
+            "EXPRH:4",
+            "DA:2,1",
+            "DA:3,1",
+            "DA:4,0",
+            "LF:3",
+            "LH:2",
+            "end_of_record",
+        ]
+    );
+
+    // the same execution always produces the same report
+    let mut env_factory = crate::vm::tests::tl_env_factory();
+    let rerun = run_covered(epoch, &mut env_factory, &contract_id, &p1_principal);
+    assert_eq!(rerun, coverage);
+    assert_eq!(lcov(&rerun, &src_files), report);
+
+    let mut merged = coverage.clone();
+    merged.merge(&rerun);
+    let merged_report = lcov(&merged, &HashMap::new());
+    assert!(merged_report.contains(&format!("SF:{}", &contract_id)));
+    assert!(merged_report.contains("DA:2,2"));
+    assert!(merged_report.contains("DA:4,0"));
+    assert!(merged_report.contains("LH:2"));
+}
@@ -0,0 +1,199 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use serde_json::json;
+
+use crate::vm::ast::ASTRules;
+use crate::vm::callables::FunctionIdentifier;
+use crate::vm::errors::Error;
+use crate::vm::observer::{JsonTraceObserver, VmObserver};
+use crate::vm::representations::SymbolicExpression;
+use crate::vm::tests::{
+    execute, symbols_from_values, test_epochs, tl_env_factory as env_factory,
+    TopLevelMemoryEnvironmentGenerator,
+};
+use crate::vm::types::{PrincipalData, QualifiedContractIdentifier, Value};
+use stacks_common::types::StacksEpochId;
+
+const TRACED_CONTRACT: &str = "(define-fungible-token tok)
+    (define-data-var counter uint u0)
+    (define-map balances principal uint)
+    (define-private (bump (by uint))
+      (begin
+        (var-set counter (+ (var-get counter) by))
+        (var-get counter)))
+    (define-public (go (to principal))
+      (begin
+        (map-set balances to (bump u2))
+        (map-delete balances tx-sender)
+        (try! (ft-mint? tok u10 tx-sender))
+        (try! (ft-transfer? tok u3 tx-sender to))
+        (try! (stx-transfer? u5 tx-sender to))
+        (ok (map-get? balances to))))";
+
+/// Counts expression evaluations
+#[derive(Default)]
+struct EvalCounter {
+    will_eval: usize,
+    did_eval: usize,
+    calls: usize,
+}
+
+impl VmObserver for EvalCounter {
+    fn will_call_function(&mut self, _function: &FunctionIdentifier, _args: &[Value]) {
+        self.calls += 1;
+    }
+
+    fn will_eval(&mut self, _expr: &SymbolicExpression) {
+        self.will_eval += 1;
+    }
+
+    fn did_eval(&mut self, _expr: &SymbolicExpression, _result: &Result<Value, Error>) {
+        self.did_eval += 1;
+    }
+}
+
+#[apply(test_epochs)]
+fn test_vm_observer_trace(
+    epoch: StacksEpochId,
+    mut env_factory: TopLevelMemoryEnvironmentGenerator,
+) {
+    let p1 = execute("'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR");
+    let p2 = execute("'SM2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQVX8X0G");
+    let (p1_principal, p1_std) = match p1 {
+        Value::Principal(PrincipalData::Standard(ref data)) => {
+            (PrincipalData::Standard(data.clone()), data.clone())
+        }
+        _ => panic!(),
+    };
+    let contract_id = QualifiedContractIdentifier::new(p1_std, "traced".into());
+
+    let mut json_observer = JsonTraceObserver::new(false);
+    let mut counter = EvalCounter::default();
+    {
+        let mut owned_env = env_factory.get_env(epoch);
+        owned_env
+            .initialize_contract(
+                contract_id.clone(),
+                TRACED_CONTRACT,
+                None,
+                ASTRules::PrecheckSize,
+            )
+            .unwrap();
+        owned_env.stx_faucet(&p1_principal, 1000);
+
+        owned_env.add_vm_observer(&mut json_observer);
+        owned_env.add_vm_observer(&mut counter);
+
+        let (result, _asset_map, _events) = owned_env
+            .execute_transaction(
+                p1_principal.clone(),
+                None,
+                contract_id.clone(),
+                "go",
+                &symbols_from_values(vec![p2.clone()]),
+            )
+            .unwrap();
+        assert_eq!(
+            result,
+            Value::okay(Value::some(Value::UInt(2)).unwrap()).unwrap()
+        );
+    }
+
+    assert_eq!(counter.calls, 2);
+    assert!(counter.will_eval > 0);
+    assert_eq!(counter.will_eval, counter.did_eval);
+
+    let contract = contract_id.to_string();
+    let go = format!("{}:go", &contract);
+    let bump = format!("{}:bump", &contract);
+    let tok = format!("{}::tok", &contract);
+    let (p1, p2) = (p1.to_string(), p2.to_string());
+    let p1_sender = p1_principal.to_string();
+
+    let expected = json!([
+        { "event": "call", "depth": 0, "function": go, "args": [p2] },
+        { "event": "call", "depth": 1, "function": bump, "args": ["u2"] },
+        { "event": "read", "depth": 2, "contract": contract, "name": "counter",
+          "key": null, "value": "u0", "result": "u0" },
+        { "event": "write", "depth": 2, "contract": contract, "name": "counter",
+          "key": null, "value": "u2", "result": "true" },
+        { "event": "read", "depth": 2, "contract": contract, "name": "counter",
+          "key": null, "value": "u2", "result": "u2" },
+        { "event": "return", "depth": 1, "function": bump, "result": { "ok": "u2" } },
+        { "event": "write", "depth": 1, "contract": contract, "name": "balances",
+          "key": p2, "value": "u2", "result": "true" },
+        { "event": "write", "depth": 1, "contract": contract, "name": "balances",
+          "key": p1, "value": null, "result": "false" },
+        { "event": "transfer", "depth": 1, "asset": tok, "sender": p1_sender, "amount": "3" },
+        { "event": "transfer", "depth": 1, "asset": "stx", "sender": p1_sender, "amount": "5" },
+        { "event": "read", "depth": 1, "contract": contract, "name": "balances",
+          "key": p2, "value": "(some u2)", "result": "(some u2)" },
+        { "event": "return", "depth": 0, "function": go, "result": { "ok": "(ok (some u2))" } },
+    ]);
+    assert_eq!(json_observer.into_trace(), expected);
+}
+
+#[apply(test_epochs)]
+fn test_vm_observer_traces_exprs(
+    epoch: StacksEpochId,
+    mut env_factory: TopLevelMemoryEnvironmentGenerator,
+) {
+    let p1 = execute("'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR");
+    let (p1_principal, p1_std) = match p1 {
+        Value::Principal(PrincipalData::Standard(ref data)) => {
+            (PrincipalData::Standard(data.clone()), data.clone())
+        }
+        _ => panic!(),
+    };
+    let contract_id = QualifiedContractIdentifier::new(p1_std, "adder".into());
+
+    let mut json_observer = JsonTraceObserver::new(true);
+    {
+        let mut owned_env = env_factory.get_env(epoch);
+        owned_env
+            .initialize_contract(
+                contract_id.clone(),
+                "(define-read-only (add (a int)) (+ a 1))",
+                None,
+                ASTRules::PrecheckSize,
+            )
+            .unwrap();
+        owned_env.add_vm_observer(&mut json_observer);
+        owned_env
+            .execute_transaction(
+                p1_principal,
+                None,
+                contract_id.clone(),
+                "add",
+                &symbols_from_values(vec![Value::Int(41)]),
+            )
+            .unwrap();
+    }
+
+    let evals: Vec<_> = json_observer
+        .events()
+        .iter()
+        .filter(|event| event["event"] == "eval")
+        .map(|event| (event["expr"].clone(), event["result"].clone()))
+        .collect();
+    assert!(evals.contains(&(json!("a"), json!({ "ok": "41" }))));
+    assert!(evals.contains(&(json!("( + a 1 )"), json!({ "ok": "42" }))));
+    assert!(json_observer
+        .events()
+        .iter()
+        .filter(|event| event["event"] == "eval")
+        .all(|event| event["depth"] == 1));
+}
@@ -43,7 +43,7 @@ use stacks_common::consts::{CHAIN_ID_MAINNET, CHAIN_ID_TESTNET};
 use stacks_common::types::chainstate::StacksAddress;
 use stacks_common::types::chainstate::StacksPrivateKey;
 use stacks_common::types::chainstate::StacksPublicKey;
-use stacks_common::types::StacksEpochId;
+use stacks_common::types::{NetworkId, StacksEpochId};
 use stacks_common::util::hash::{hex_bytes, to_hex};
 
 #[test]
@@ -690,8 +690,7 @@ fn test_simple_if_functions(#[case] version: ClarityVersion, #[case] epoch: Stac
         );
         let mut marf = MemoryBackingStore::new();
         let mut global_context = GlobalContext::new(
-            false,
-            CHAIN_ID_TESTNET,
+            NetworkId::testnet(),
             marf.as_clarity_db(),
             LimitedCostTracker::new_free(),
             StacksEpochId::Epoch20,
@@ -16,6 +16,7 @@
 
 pub mod serialization;
 pub mod signatures;
+pub mod tuple_conversion;
 
 use std::collections::BTreeMap;
 use std::convert::{TryFrom, TryInto};
@@ -43,6 +44,9 @@ pub use crate::vm::types::signatures::{
     StringUTF8Length, TupleTypeSignature, TypeSignature, BUFF_1, BUFF_20, BUFF_21, BUFF_32,
     BUFF_33, BUFF_64, BUFF_65,
 };
+pub use crate::vm::types::tuple_conversion::{
+    ClarityTuple, ClarityTupleField, TupleConversionError,
+};
 
 use crate::vm::ClarityVersion;
 
@@ -0,0 +1,373 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Conversions between Clarity tuples and Rust structs.
+//!
+//! `#[derive(ClarityTuple)]` (see the `clarity-derive` crate) maps a struct with named fields to
+//! a Clarity tuple.  Each field is converted to and from its tuple entry through
+//! `ClarityTupleField`, which is implemented here for the Rust types that have an obvious Clarity
+//! counterpart:
+//!
+//! | Rust                                 | Clarity                                   |
+//! |--------------------------------------|-------------------------------------------|
+//! | `bool`                               | `bool`                                    |
+//! | `i128`                               | `int`                                     |
+//! | `u128`, `u64`, `u32`, `u16`, `u8`    | `uint` (must fit in the Rust type)        |
+//! | `PrincipalData`                      | `principal`                               |
+//! | `StandardPrincipalData`              | a standard `principal`                    |
+//! | `QualifiedContractIdentifier`        | a contract `principal`                    |
+//! | `Vec<u8>`                            | `buff`                                    |
+//! | `Option<T>`                          | `(optional T)`                            |
+//! | a `#[derive(ClarityTuple)]` struct   | a nested tuple                            |
+//! | `Value`                              | any value, unconverted                    |
+
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+
+pub use clarity_derive::ClarityTuple;
+
+use crate::vm::types::{
+    BuffData, OptionalData, PrincipalData, QualifiedContractIdentifier, SequenceData,
+    StandardPrincipalData, Value,
+};
+
+/// A Clarity value that can't be converted into a `ClarityTuple` struct
+#[derive(Debug, Clone, PartialEq)]
+pub enum TupleConversionError {
+    /// the value is not a tuple
+    NotATuple(Value),
+    /// the tuple has no such entry
+    MissingField(&'static str),
+    /// the tuple's entry has the wrong type
+    BadField(&'static str, Value),
+    /// the tuple's entry does not fit into the field's integer type
+    FieldOverflow(&'static str, u128),
+}
+
+impl fmt::Display for TupleConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TupleConversionError::NotATuple(v) => write!(f, "expected a tuple, got {}", v),
+            TupleConversionError::MissingField(name) => write!(f, "no '{}' in tuple", name),
+            TupleConversionError::BadField(name, v) => write!(f, "bad type for '{}': {}", name, v),
+            TupleConversionError::FieldOverflow(name, v) => {
+                write!(f, "'{}' overflow: {}", name, v)
+            }
+        }
+    }
+}
+
+impl error::Error for TupleConversionError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// A Rust type that can be a field of a `#[derive(ClarityTuple)]` struct
+pub trait ClarityTupleField: Sized {
+    /// Convert the value of the tuple entry `name`
+    fn from_field(name: &'static str, value: Value) -> Result<Self, TupleConversionError>;
+    /// Convert into the value of a tuple entry
+    fn into_field(self) -> Value;
+}
+
+impl ClarityTupleField for Value {
+    fn from_field(_name: &'static str, value: Value) -> Result<Value, TupleConversionError> {
+        Ok(value)
+    }
+
+    fn into_field(self) -> Value {
+        self
+    }
+}
+
+impl ClarityTupleField for bool {
+    fn from_field(name: &'static str, value: Value) -> Result<bool, TupleConversionError> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            v => Err(TupleConversionError::BadField(name, v)),
+        }
+    }
+
+    fn into_field(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl ClarityTupleField for i128 {
+    fn from_field(name: &'static str, value: Value) -> Result<i128, TupleConversionError> {
+        match value {
+            Value::Int(i) => Ok(i),
+            v => Err(TupleConversionError::BadField(name, v)),
+        }
+    }
+
+    fn into_field(self) -> Value {
+        Value::Int(self)
+    }
+}
+
+impl ClarityTupleField for u128 {
+    fn from_field(name: &'static str, value: Value) -> Result<u128, TupleConversionError> {
+        match value {
+            Value::UInt(u) => Ok(u),
+            v => Err(TupleConversionError::BadField(name, v)),
+        }
+    }
+
+    fn into_field(self) -> Value {
+        Value::UInt(self)
+    }
+}
+
+macro_rules! impl_clarity_tuple_field_for_uint {
+    ($thing:ty) => {
+        impl ClarityTupleField for $thing {
+            fn from_field(
+                name: &'static str,
+                value: Value,
+            ) -> Result<$thing, TupleConversionError> {
+                let value = u128::from_field(name, value)?;
+                <$thing>::try_from(value)
+                    .map_err(|_| TupleConversionError::FieldOverflow(name, value))
+            }
+
+            fn into_field(self) -> Value {
+                Value::UInt(self.into())
+            }
+        }
+    };
+}
+
+impl_clarity_tuple_field_for_uint!(u64);
+impl_clarity_tuple_field_for_uint!(u32);
+impl_clarity_tuple_field_for_uint!(u16);
+impl_clarity_tuple_field_for_uint!(u8);
+
+impl ClarityTupleField for PrincipalData {
+    fn from_field(name: &'static str, value: Value) -> Result<PrincipalData, TupleConversionError> {
+        match value {
+            Value::Principal(p) => Ok(p),
+            v => Err(TupleConversionError::BadField(name, v)),
+        }
+    }
+
+    fn into_field(self) -> Value {
+        Value::Principal(self)
+    }
+}
+
+impl ClarityTupleField for StandardPrincipalData {
+    fn from_field(
+        name: &'static str,
+        value: Value,
+    ) -> Result<StandardPrincipalData, TupleConversionError> {
+        match value {
+            Value::Principal(PrincipalData::Standard(p)) => Ok(p),
+            v => Err(TupleConversionError::BadField(name, v)),
+        }
+    }
+
+    fn into_field(self) -> Value {
+        Value::Principal(PrincipalData::Standard(self))
+    }
+}
+
+impl ClarityTupleField for QualifiedContractIdentifier {
+    fn from_field(
+        name: &'static str,
+        value: Value,
+    ) -> Result<QualifiedContractIdentifier, TupleConversionError> {
+        match value {
+            Value::Principal(PrincipalData::Contract(p)) => Ok(p),
+            v => Err(TupleConversionError::BadField(name, v)),
+        }
+    }
+
+    fn into_field(self) -> Value {
+        Value::Principal(PrincipalData::Contract(self))
+    }
+}
+
+impl ClarityTupleField for Vec<u8> {
+    fn from_field(name: &'static str, value: Value) -> Result<Vec<u8>, TupleConversionError> {
+        match value {
+            Value::Sequence(SequenceData::Buffer(BuffData { data })) => Ok(data),
+            v => Err(TupleConversionError::BadField(name, v)),
+        }
+    }
+
+    fn into_field(self) -> Value {
+        Value::Sequence(SequenceData::Buffer(BuffData { data: self }))
+    }
+}
+
+impl<T: ClarityTupleField> ClarityTupleField for Option<T> {
+    fn from_field(name: &'static str, value: Value) -> Result<Option<T>, TupleConversionError> {
+        match value {
+            Value::Optional(OptionalData { data: Some(data) }) => {
+                T::from_field(name, *data).map(Some)
+            }
+            Value::Optional(OptionalData { data: None }) => Ok(None),
+            v => Err(TupleConversionError::BadField(name, v)),
+        }
+    }
+
+    fn into_field(self) -> Value {
+        Value::Optional(OptionalData {
+            data: self.map(|data| Box::new(data.into_field())),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vm::types::TupleData;
+
+    #[derive(Debug, Clone, PartialEq, ClarityTuple)]
+    struct Inner {
+        flag: bool,
+    }
+
+    #[derive(Debug, Clone, PartialEq, ClarityTuple)]
+    struct Outer {
+        stacker: PrincipalData,
+        lock_amount: u128,
+        #[clarity(rename = "unlock-burn-height")]
+        unlock_height: u64,
+        delta: i128,
+        pox_addr: Option<Vec<u8>>,
+        inner: Inner,
+        raw: Value,
+    }
+
+    fn stacker() -> PrincipalData {
+        PrincipalData::Standard(StandardPrincipalData(22, [0x01; 20]))
+    }
+
+    fn outer() -> Outer {
+        Outer {
+            stacker: stacker(),
+            lock_amount: 1000,
+            unlock_height: 200,
+            delta: -1,
+            pox_addr: Some(vec![1, 2, 3]),
+            inner: Inner { flag: true },
+            raw: Value::none(),
+        }
+    }
+
+    fn tuple(fields: Vec<(&str, Value)>) -> Value {
+        Value::Tuple(
+            TupleData::from_data(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name.into(), value))
+                    .collect(),
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_clarity_tuple_round_trip() {
+        let value = Value::from(outer());
+        let expected = tuple(vec![
+            ("stacker", Value::Principal(stacker())),
+            ("lock-amount", Value::UInt(1000)),
+            ("unlock-burn-height", Value::UInt(200)),
+            ("delta", Value::Int(-1)),
+            (
+                "pox-addr",
+                Value::some(Value::buff_from(vec![1, 2, 3]).unwrap()).unwrap(),
+            ),
+            ("inner", tuple(vec![("flag", Value::Bool(true))])),
+            ("raw", Value::none()),
+        ]);
+        assert_eq!(value, expected);
+        assert_eq!(Outer::try_from(value), Ok(outer()));
+
+        let mut no_pox_addr = outer();
+        no_pox_addr.pox_addr = None;
+        let value = Value::from(no_pox_addr.clone());
+        assert_eq!(
+            value.clone().expect_tuple().get("pox-addr").unwrap(),
+            &Value::none()
+        );
+        assert_eq!(Outer::try_from(value), Ok(no_pox_addr));
+    }
+
+    #[test]
+    fn test_clarity_tuple_errors() {
+        let mut fields = vec![
+            ("stacker", Value::Principal(stacker())),
+            ("lock-amount", Value::UInt(1000)),
+            ("unlock-burn-height", Value::UInt(200)),
+            ("delta", Value::Int(-1)),
+            ("pox-addr", Value::none()),
+            ("inner", tuple(vec![("flag", Value::Bool(false))])),
+            ("raw", Value::UInt(1)),
+            // entries that don't map to a field are ignored
+            ("extra", Value::UInt(2)),
+        ];
+        assert!(Outer::try_from(tuple(fields.clone())).is_ok());
+
+        assert_eq!(
+            Outer::try_from(Value::UInt(1)),
+            Err(TupleConversionError::NotATuple(Value::UInt(1)))
+        );
+
+        fields[1] = ("lock-amount", Value::Int(1000));
+        assert_eq!(
+            Outer::try_from(tuple(fields.clone())),
+            Err(TupleConversionError::BadField(
+                "lock-amount",
+                Value::Int(1000)
+            ))
+        );
+
+        fields[1] = ("amount", Value::UInt(1000));
+        assert_eq!(
+            Outer::try_from(tuple(fields.clone())),
+            Err(TupleConversionError::MissingField("lock-amount"))
+        );
+
+        fields[1] = ("lock-amount", Value::UInt(1000));
+        fields[2] = ("unlock-burn-height", Value::UInt(u128::MAX));
+        assert_eq!(
+            Outer::try_from(tuple(fields.clone())),
+            Err(TupleConversionError::FieldOverflow(
+                "unlock-burn-height",
+                u128::MAX
+            ))
+        );
+
+        fields[2] = ("unlock-burn-height", Value::UInt(200));
+        fields[4] = ("pox-addr", Value::some(Value::UInt(1)).unwrap());
+        assert_eq!(
+            Outer::try_from(tuple(fields.clone())),
+            Err(TupleConversionError::BadField("pox-addr", Value::UInt(1)))
+        );
+
+        fields[4] = ("pox-addr", Value::none());
+        fields[5] = ("inner", Value::Bool(false));
+        assert_eq!(
+            Outer::try_from(tuple(fields.clone())),
+            Err(TupleConversionError::BadField("inner", Value::Bool(false)))
+        );
+    }
+}
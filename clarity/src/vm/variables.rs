@@ -115,11 +115,11 @@ pub fn lookup_reserved_variable(
                 Ok(Some(Value::Bool(reg)))
             }
             NativeVariables::Mainnet => {
-                let mainnet = env.global_context.mainnet;
+                let mainnet = env.global_context.network.mainnet;
                 Ok(Some(Value::Bool(mainnet)))
             }
             NativeVariables::ChainId => {
-                let chain_id = env.global_context.chain_id;
+                let chain_id = env.global_context.network.chain_id;
                 Ok(Some(Value::UInt(chain_id.into())))
             }
         }
@@ -0,0 +1,257 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! `#[derive(StacksMessageCodec)]`, re-exported as `stacks_common::codec::StacksMessageCodec`.
+//!
+//! The derived impl encodes a struct's fields one after the other, in declaration order, with no
+//! framing of its own:
+//!
+//! * a field whose type implements `StacksMessageCodec` is encoded with that impl;
+//! * a fixed-size array `[T; N]` is encoded as its `N` elements, with no length prefix;
+//! * a `Vec<T>` is encoded with a `u32` length prefix, as the `Vec<T>` impl does.  By default,
+//!   any length up to `u32::MAX` is accepted when decoding.  Annotate the field with
+//!   `#[codec(max_len = N)]` to reject more than `N` items, or with `#[codec(exact_len = N)]` to
+//!   reject anything but exactly `N` items.  `N` can be any `u32` expression.
+//!
+//! ```ignore
+//! #[derive(StacksMessageCodec)]
+//! pub struct NeighborsData {
+//!     #[codec(max_len = MAX_NEIGHBORS_DATA_LEN)]
+//!     pub neighbors: Vec<NeighborAddress>,
+//! }
+//! ```
+//!
+//! Enums and unions are not supported, since their encodings always carry a type prefix whose
+//! representation varies from type to type.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Error, Expr, Fields, GenericParam, Ident,
+    Index, Type,
+};
+
+/// How a single field is encoded
+enum FieldCodec {
+    /// With the field type's own `StacksMessageCodec` impl
+    Plain,
+    /// A fixed-size array, element by element
+    Array { elem: Box<Type>, len: Expr },
+    /// A length-prefixed `Vec` with at most this many items
+    AtMost(Expr),
+    /// A length-prefixed `Vec` with exactly this many items
+    Exact(Expr),
+}
+
+/// A field to encode, and how to get at it
+struct CodecField {
+    /// The expression for the field on `self`
+    access: TokenStream2,
+    /// The local variable it is decoded into
+    var: Ident,
+    ty: Type,
+    codec: FieldCodec,
+}
+
+#[proc_macro_derive(StacksMessageCodec, attributes(codec))]
+pub fn derive_stacks_message_codec(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        Data::Enum(_) | Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "StacksMessageCodec can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut codec_fields = Vec::with_capacity(fields.len());
+    for (i, field) in fields.iter().enumerate() {
+        let (access, var) = match &field.ident {
+            Some(ident) => (quote!(#ident), ident.clone()),
+            None => {
+                let index = Index::from(i);
+                (quote!(#index), format_ident!("field_{}", i))
+            }
+        };
+        codec_fields.push(CodecField {
+            access,
+            var,
+            ty: field.ty.clone(),
+            codec: field_codec(field)?,
+        });
+    }
+
+    let serialize = codec_fields.iter().map(serialize_field);
+    let deserialize = codec_fields.iter().map(deserialize_field);
+    let vars = codec_fields.iter().map(|field| &field.var);
+    let construct = match fields {
+        Fields::Named(_) => quote!(Self { #(#vars),* }),
+        Fields::Unnamed(_) => quote!(Self(#(#vars),*)),
+        Fields::Unit => quote!(Self),
+    };
+
+    // every type parameter has to be encodable for the struct to be
+    let type_params: Vec<_> = input
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => Some(param.ident.clone()),
+            _ => None,
+        })
+        .collect();
+    let where_clause = input.generics.make_where_clause();
+    for param in type_params.iter() {
+        where_clause
+            .predicates
+            .push(parse_quote!(#param: ::stacks_common::codec::StacksMessageCodec));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::stacks_common::codec::StacksMessageCodec for #name #ty_generics #where_clause {
+            fn consensus_serialize<W: ::std::io::Write>(
+                &self,
+                fd: &mut W,
+            ) -> ::std::result::Result<(), ::stacks_common::codec::Error> {
+                #(#serialize)*
+                Ok(())
+            }
+
+            fn consensus_deserialize<R: ::std::io::Read>(
+                fd: &mut R,
+            ) -> ::std::result::Result<Self, ::stacks_common::codec::Error> {
+                #(#deserialize)*
+                Ok(#construct)
+            }
+        }
+    })
+}
+
+/// Work out how to encode `field`, from its type and its `#[codec(..)]` attribute
+fn field_codec(field: &syn::Field) -> Result<FieldCodec, Error> {
+    let mut max_len = None;
+    let mut exact_len = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("codec"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("max_len") {
+                max_len = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else if meta.path.is_ident("exact_len") {
+                exact_len = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `max_len` or `exact_len`"))
+            }
+        })?;
+    }
+
+    if max_len.is_some() || exact_len.is_some() {
+        if !is_vec(&field.ty) {
+            return Err(Error::new_spanned(
+                &field.ty,
+                "`max_len` and `exact_len` only apply to `Vec` fields",
+            ));
+        }
+        return match (max_len, exact_len) {
+            (Some(max_len), None) => Ok(FieldCodec::AtMost(max_len)),
+            (None, Some(exact_len)) => Ok(FieldCodec::Exact(exact_len)),
+            _ => Err(Error::new_spanned(
+                &field.ty,
+                "`max_len` and `exact_len` can't both be given",
+            )),
+        };
+    }
+
+    match &field.ty {
+        Type::Array(array) => Ok(FieldCodec::Array {
+            elem: array.elem.clone(),
+            len: array.len.clone(),
+        }),
+        _ => Ok(FieldCodec::Plain),
+    }
+}
+
+fn is_vec(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "Vec")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+fn serialize_field(field: &CodecField) -> TokenStream2 {
+    let access = &field.access;
+    match &field.codec {
+        FieldCodec::Array { .. } => quote! {
+            for item in self.#access.iter() {
+                ::stacks_common::codec::write_next(fd, item)?;
+            }
+        },
+        FieldCodec::Plain | FieldCodec::AtMost(_) | FieldCodec::Exact(_) => quote! {
+            ::stacks_common::codec::write_next(fd, &self.#access)?;
+        },
+    }
+}
+
+fn deserialize_field(field: &CodecField) -> TokenStream2 {
+    let var = &field.var;
+    let ty = &field.ty;
+    match &field.codec {
+        FieldCodec::Plain => quote! {
+            let #var: #ty = ::stacks_common::codec::read_next(fd)?;
+        },
+        FieldCodec::AtMost(max_len) => quote! {
+            let #var: #ty = ::stacks_common::codec::read_next_at_most(fd, #max_len)?;
+        },
+        FieldCodec::Exact(len) => quote! {
+            let #var: #ty = ::stacks_common::codec::read_next_exact(fd, #len)?;
+        },
+        FieldCodec::Array { elem, len } => quote! {
+            let #var: #ty = {
+                let mut items: ::std::vec::Vec<#elem> = ::std::vec::Vec::with_capacity(#len);
+                for _ in 0..#len {
+                    items.push(::stacks_common::codec::read_next(fd)?);
+                }
+                <#ty as ::std::convert::TryFrom<::std::vec::Vec<#elem>>>::try_from(items)
+                    .map_err(|_| {
+                        ::stacks_common::codec::Error::DeserializeError(
+                            "Failed to build fixed-size array".to_string(),
+                        )
+                    })?
+            };
+        },
+    }
+}
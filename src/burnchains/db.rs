@@ -1123,6 +1123,15 @@ impl BurnchainDB {
         Ok(res)
     }
 
+    pub fn get_burnchain_header_by_hash(
+        conn: &DBConn,
+        block: &BurnchainHeaderHash,
+    ) -> Result<Option<BurnchainBlockHeader>, BurnchainError> {
+        let qry = "SELECT * FROM burnchain_db_block_headers WHERE block_hash = ?1 LIMIT 1";
+        let res: Option<BurnchainBlockHeader> = query_row(conn, qry, &[block])?;
+        Ok(res)
+    }
+
     pub fn get_burnchain_block(
         conn: &DBConn,
         block: &BurnchainHeaderHash,
@@ -38,6 +38,7 @@ use stacks_common::address::AddressHashMode;
 
 use stacks_common::types::chainstate::TrieHash;
 use stacks_common::types::chainstate::{BurnchainHeaderHash, PoxId, SortitionId};
+use stacks_common::util::ustx;
 
 impl<'a> SortitionHandleTx<'a> {
     /// Run a blockstack operation's "check()" method and return the result.
@@ -191,14 +192,19 @@ impl<'a> SortitionHandleTx<'a> {
         let initialize_bonus = if snapshot.sortition && parent_snapshot.total_burn == 0 {
             let blocks_without_winners =
                 snapshot.block_height - burnchain.initial_reward_start_block;
-            let mut total_reward = 0;
-            for burn_block_height in burnchain.initial_reward_start_block..snapshot.block_height {
-                total_reward += StacksChainState::get_coinbase_reward(
-                    burn_block_height,
-                    self.context.first_block_height,
-                );
-            }
-            let per_block = total_reward / INITIAL_MINING_BONUS_WINDOW as u128;
+            let total_reward = ustx::checked_sum(
+                (burnchain.initial_reward_start_block..snapshot.block_height).map(
+                    |burn_block_height| {
+                        StacksChainState::get_coinbase_reward(
+                            burn_block_height,
+                            self.context.first_block_height,
+                        )
+                    },
+                ),
+            )
+            .expect("FATAL: initial mining bonus overflow");
+            let per_block = ustx::checked_div(total_reward, INITIAL_MINING_BONUS_WINDOW as u128)
+                .expect("FATAL: no initial mining bonus window");
 
             info!("First sortition winner chosen";
                   "blocks_without_winners" => blocks_without_winners,
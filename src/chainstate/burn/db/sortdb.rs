@@ -4472,6 +4472,35 @@ impl SortitionDB {
         })
     }
 
+    /// Get the leader key consumed by the block commit that elected a Stacks block.
+    /// Returns None if the block was not elected in a sortition on the canonical PoX fork.
+    pub fn get_leader_key_for_stacks_block(
+        ic: &SortitionDBConn,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+    ) -> Result<Option<LeaderKeyRegisterOp>, db_error> {
+        let commit =
+            match SortitionDB::get_block_commit_for_stacks_block(ic, consensus_hash, block_hash) {
+                Ok(Some(commit)) => commit,
+                Ok(None) | Err(db_error::InvalidPoxSortition) => {
+                    return Ok(None);
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+        // the commit was found, so the snapshot exists
+        let sortition_id = SortitionDB::get_block_snapshot_consensus(ic, consensus_hash)?
+            .expect("FATAL: no snapshot for a sortition with a block commit")
+            .sortition_id;
+        SortitionDB::get_leader_key_at(
+            ic,
+            commit.key_block_ptr as u64,
+            commit.key_vtxindex as u32,
+            &sortition_id,
+        )
+    }
+
     /// Get a block snapshot for a winning block hash in a given burn chain fork.
     pub fn get_block_snapshot_for_winning_stacks_block(
         ic: &SortitionDBConn,
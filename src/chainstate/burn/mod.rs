@@ -34,7 +34,6 @@ use crate::chainstate::burn::db::sortdb::SortitionHandleTx;
 use crate::core::SYSTEM_FORK_SET_VERSION;
 use crate::types::chainstate::TrieHash;
 use crate::util_lib::db::Error as db_error;
-use stacks_common::util::hash::Hash32;
 use stacks_common::util::hash::Sha512Trunc256Sum;
 use stacks_common::util::hash::{to_hex, Hash160};
 use stacks_common::util::log;
@@ -40,6 +40,7 @@ use stacks_common::util::log;
 use stacks_common::util::uint::BitArray;
 use stacks_common::util::uint::Uint256;
 use stacks_common::util::uint::Uint512;
+use stacks_common::util::ustx;
 
 use crate::chainstate::burn::ConsensusHashExtensions;
 use crate::chainstate::stacks::index::ClarityMarfTrieId;
@@ -314,10 +315,11 @@ impl BlockSnapshot {
                 parent_snapshot.block_height,
                 first_block_height,
             );
-            parent_snapshot
-                .accumulated_coinbase_ustx
-                .saturating_add(missed_coinbase)
-                .saturating_add(initial_mining_bonus_ustx)
+            ustx::saturating_sum([
+                parent_snapshot.accumulated_coinbase_ustx,
+                missed_coinbase,
+                initial_mining_bonus_ustx,
+            ])
         };
 
         // next sortition hash
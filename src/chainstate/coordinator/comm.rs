@@ -21,6 +21,8 @@ use std::sync::{
 use std::time::{Duration, Instant};
 use std::{process, thread};
 
+use crate::util_lib::sched::sched_point;
+
 /// Trait for use by the ChainsCoordinator
 ///
 pub trait CoordinatorNotices {
@@ -123,16 +125,24 @@ impl SignalBools {
 
 impl CoordinatorReceivers {
     pub fn wait_on(&self) -> u8 {
-        let mut signal_bools = self.signal_bools.lock().unwrap();
-        if !signal_bools.activated_signal() {
-            signal_bools = self.signal_wakeup.wait(signal_bools).unwrap();
+        {
+            let signal_bools = self.signal_bools.lock().unwrap();
+            if !signal_bools.activated_signal() {
+                drop(self.signal_wakeup.wait(signal_bools).unwrap());
+            }
         }
+        // the signals are only read once it's the coordinator's turn, so that a replayed schedule
+        // delivers the same set of announcements.  The lock must not be held here, or announcers
+        // scheduled ahead of the coordinator could never take it.
+        sched_point("coordinator", "wake");
+        let mut signal_bools = self.signal_bools.lock().unwrap();
         signal_bools.receive_signal()
     }
 }
 
 impl CoordinatorChannels {
     pub fn announce_new_stacks_block(&self) -> bool {
+        sched_point("coordinator", "announce_new_stacks_block");
         let mut bools = self.signal_bools.lock().unwrap();
         bools.new_stacks_block = true;
         self.signal_wakeup.notify_all();
@@ -141,6 +151,7 @@ impl CoordinatorChannels {
     }
 
     pub fn announce_new_burn_block(&self) -> bool {
+        sched_point("coordinator", "announce_new_burn_block");
         let mut bools = self.signal_bools.lock().unwrap();
         bools.new_burn_block = true;
         self.signal_wakeup.notify_all();
@@ -149,6 +160,7 @@ impl CoordinatorChannels {
     }
 
     pub fn stop_chains_coordinator(&self) -> bool {
+        sched_point("coordinator", "stop");
         let mut bools = self.signal_bools.lock().unwrap();
         bools.stop = true;
         self.signal_wakeup.notify_all();
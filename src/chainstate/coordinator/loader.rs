@@ -0,0 +1,228 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The burnchain block loading stage of the chains coordinator.
+//!
+//! Evaluating a sortition has to happen on the coordinator thread, one burnchain block at a time,
+//! because each sortition builds on the one before it.  Reading a burnchain block's operations out
+//! of the burnchain DB and decoding them does not.  When the coordinator finds a run of
+//! unprocessed burnchain blocks (e.g. while syncing), a `BurnchainBlockLoader` loads them on worker
+//! threads, each with its own read-only connection, while the coordinator evaluates the blocks
+//! before them.  Worker `i` of `n` loads every `n`th block starting from the `i`th, into its own
+//! queue of at most `queue_depth` blocks, and the coordinator takes from the queues in turn, so it
+//! sees the blocks in the order it asked for them.
+
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread;
+
+use crate::burnchains::db::{BurnchainBlockData, BurnchainDB};
+use crate::burnchains::Error as BurnchainError;
+use crate::types::chainstate::BurnchainHeaderHash;
+
+/// Loads runs of burnchain blocks from the burnchain DB ahead of the coordinator
+pub struct BurnchainBlockLoader {
+    db_path: String,
+    num_threads: usize,
+    queue_depth: usize,
+}
+
+impl BurnchainBlockLoader {
+    /// Make a loader that uses `num_threads` threads to load blocks from the burnchain DB at
+    /// `db_path`, each getting at most `queue_depth` blocks ahead of the coordinator.  Returns
+    /// None if `num_threads` is 0.
+    pub fn new(
+        db_path: &str,
+        num_threads: usize,
+        queue_depth: usize,
+    ) -> Option<BurnchainBlockLoader> {
+        if num_threads == 0 {
+            return None;
+        }
+        info!(
+            "Burnchain blocks will be loaded on {} thread(s)",
+            num_threads
+        );
+        Some(BurnchainBlockLoader {
+            db_path: db_path.to_string(),
+            num_threads,
+            queue_depth: queue_depth.max(1),
+        })
+    }
+
+    /// Start loading `block_hashes`.  The threads exit once they have loaded their share of the
+    /// blocks, or once the returned `BurnchainBlockLoads` is dropped.
+    pub fn load(&self, block_hashes: Vec<BurnchainHeaderHash>) -> BurnchainBlockLoads {
+        let num_threads = self.num_threads.min(block_hashes.len()).max(1);
+        let mut queues = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let (queue_tx, queue_rx) = sync_channel(self.queue_depth);
+            let db_path = self.db_path.clone();
+            let thread_block_hashes: Vec<_> = block_hashes
+                .iter()
+                .skip(thread_id)
+                .step_by(num_threads)
+                .cloned()
+                .collect();
+            thread::Builder::new()
+                .name(format!("burnchain-loader-{}", thread_id))
+                .spawn(move || {
+                    BurnchainBlockLoader::run_loader_thread(&db_path, thread_block_hashes, queue_tx)
+                })
+                .expect("FATAL: failed to start burnchain block loader thread");
+            queues.push(queue_rx);
+        }
+        debug!(
+            "Loading {} burnchain block(s) on {} thread(s)",
+            block_hashes.len(),
+            num_threads
+        );
+        BurnchainBlockLoads {
+            block_hashes,
+            queues,
+            next: 0,
+        }
+    }
+
+    /// Load each block in turn, stopping at the first error or once nobody is listening
+    fn run_loader_thread(
+        db_path: &str,
+        block_hashes: Vec<BurnchainHeaderHash>,
+        queue: SyncSender<Result<BurnchainBlockData, BurnchainError>>,
+    ) {
+        let burnchain_db = match BurnchainDB::open(db_path, false) {
+            Ok(db) => db,
+            Err(e) => {
+                let _ = queue.send(Err(e));
+                return;
+            }
+        };
+        for block_hash in block_hashes.iter() {
+            let block = BurnchainDB::get_burnchain_block(burnchain_db.conn(), block_hash);
+            let failed = block.is_err();
+            if queue.send(block).is_err() || failed {
+                break;
+            }
+        }
+        debug!("Burnchain block loader thread exiting");
+    }
+}
+
+/// Burnchain blocks being loaded by a `BurnchainBlockLoader`, in the order they were asked for
+pub struct BurnchainBlockLoads {
+    block_hashes: Vec<BurnchainHeaderHash>,
+    queues: Vec<Receiver<Result<BurnchainBlockData, BurnchainError>>>,
+    next: usize,
+}
+
+impl BurnchainBlockLoads {
+    /// Wait for the next block.  Returns None once every block has been returned.
+    pub fn next_block(&mut self) -> Option<Result<BurnchainBlockData, BurnchainError>> {
+        let block_hash = self.block_hashes.get(self.next)?;
+        let queue = &self.queues[self.next % self.queues.len()];
+        self.next += 1;
+        match queue.recv() {
+            Ok(block) => Some(block),
+            // the thread stopped after an earlier error
+            Err(_) => Some(Err(BurnchainError::UnknownBlock(block_hash.clone()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::burnchains::tests::affirmation::make_simple_key_register;
+    use crate::burnchains::{Burnchain, BurnchainBlockHeader};
+    use crate::chainstate::burn::operations::BlockstackOperationType;
+    use std::fs;
+
+    #[test]
+    fn loader_returns_blocks_in_order() {
+        let path_root = "/tmp/test_burnchain_block_loader";
+        if fs::metadata(path_root).is_ok() {
+            fs::remove_dir_all(path_root).unwrap();
+        }
+        fs::create_dir_all(path_root).unwrap();
+
+        let burnchain = Burnchain::regtest(path_root);
+        let mut burnchain_db =
+            BurnchainDB::connect(&burnchain.get_burnchaindb_path(), &burnchain, true).unwrap();
+
+        let mut headers = vec![burnchain_db.get_canonical_chain_tip().unwrap()];
+        for i in 1..11 {
+            let parent = headers.last().unwrap().clone();
+            let header = BurnchainBlockHeader {
+                block_height: parent.block_height + 1,
+                block_hash: BurnchainHeaderHash([i as u8; 32]),
+                parent_block_hash: parent.block_hash.clone(),
+                num_txs: 1,
+                timestamp: i,
+            };
+            let key_register = make_simple_key_register(&header.block_hash, header.block_height, 1);
+            burnchain_db
+                .raw_store_burnchain_block(
+                    &burnchain,
+                    &headers,
+                    header.clone(),
+                    vec![BlockstackOperationType::LeaderKeyRegister(key_register)],
+                )
+                .unwrap();
+            headers.push(header);
+        }
+        let block_hashes: Vec<_> = headers
+            .iter()
+            .skip(1)
+            .map(|header| header.block_hash.clone())
+            .collect();
+
+        assert!(BurnchainBlockLoader::new(&burnchain.get_burnchaindb_path(), 0, 4).is_none());
+        let loader = BurnchainBlockLoader::new(&burnchain.get_burnchaindb_path(), 3, 1).unwrap();
+
+        let mut loads = loader.load(block_hashes.clone());
+        for block_hash in block_hashes.iter() {
+            let loaded = loads.next_block().unwrap().unwrap();
+            let expected =
+                BurnchainDB::get_burnchain_block(burnchain_db.conn(), block_hash).unwrap();
+            assert_eq!(loaded.header, expected.header);
+            assert_eq!(loaded.ops, expected.ops);
+            assert_eq!(loaded.ops.len(), 1);
+        }
+        assert!(loads.next_block().is_none());
+
+        // a missing block is an error in its place
+        let mut with_missing = block_hashes.clone();
+        with_missing.insert(4, BurnchainHeaderHash([0xff; 32]));
+        let mut loads = loader.load(with_missing);
+        for block_hash in block_hashes.iter().take(4) {
+            assert_eq!(
+                &loads.next_block().unwrap().unwrap().header.block_hash,
+                block_hash
+            );
+        }
+        match loads.next_block().unwrap() {
+            Err(BurnchainError::UnknownBlock(block_hash)) => {
+                assert_eq!(block_hash, BurnchainHeaderHash([0xff; 32]))
+            }
+            Err(e) => panic!("Unexpected error {:?}", &e),
+            Ok(_) => panic!("Loaded a block that does not exist"),
+        }
+
+        // dropping the loads part-way through does not hang the loader threads
+        let mut loads = loader.load(block_hashes.clone());
+        assert!(loads.next_block().unwrap().is_ok());
+        drop(loads);
+    }
+}
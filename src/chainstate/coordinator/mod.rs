@@ -31,7 +31,8 @@ use crate::burnchains::{
         BlockCommitMetadata, BurnchainBlockData, BurnchainDB, BurnchainDBTransaction,
         BurnchainHeaderReader,
     },
-    Address, Burnchain, BurnchainBlockHeader, Error as BurnchainError, PoxConstants, Txid,
+    Address, Burnchain, BurnchainBlockHeader, BurnchainStateTransition, Error as BurnchainError,
+    PoxConstants, Txid,
 };
 use crate::chainstate::burn::{
     db::sortdb::{SortitionDB, SortitionDBConn, SortitionDBTx, SortitionHandleTx},
@@ -43,6 +44,8 @@ use crate::chainstate::burn::{
 use crate::chainstate::coordinator::comm::{
     ArcCounterCoordinatorNotices, CoordinatorEvents, CoordinatorNotices, CoordinatorReceivers,
 };
+use crate::chainstate::coordinator::loader::BurnchainBlockLoader;
+use crate::chainstate::coordinator::validation::BlockValidationPool;
 use crate::chainstate::stacks::address::PoxAddress;
 use crate::chainstate::stacks::boot::POX_3_NAME;
 use crate::chainstate::stacks::index::MarfTrieId;
@@ -70,6 +73,7 @@ use clarity::vm::{
 };
 
 use crate::cost_estimates::{CostEstimator, FeeEstimator, PessimisticEstimator};
+use crate::indexer::pipeline::{IndexerBlock, IndexerPipeline};
 use crate::types::chainstate::{
     BlockHeaderHash, BurnchainHeaderHash, PoxId, SortitionId, StacksBlockId,
 };
@@ -86,8 +90,10 @@ use crate::core::FIRST_BURNCHAIN_CONSENSUS_HASH;
 use crate::core::FIRST_STACKS_BLOCK_HASH;
 
 pub mod comm;
+pub mod loader;
 #[cfg(test)]
 pub mod tests;
+pub mod validation;
 
 /// The 3 different states for the current
 ///  reward cycle's relationship to its PoX anchor
@@ -168,6 +174,22 @@ pub trait BlockEventDispatcher {
         burns: u64,
         reward_recipients: Vec<PoxAddress>,
     );
+
+    /// called whenever a burn block has been processed
+    ///  for sortition, with the resulting snapshot, the
+    ///  winning block-commit (if there was a sortition),
+    ///  every accepted block-commit, and the PoX payouts.
+    ///  like `announce_burn_block`, this will be called
+    ///  multiple times for the same burnchain header hash
+    ///  in the event of PoX forks.
+    fn announce_sortition(
+        &self,
+        snapshot: &BlockSnapshot,
+        winning_commit: Option<&LeaderBlockCommitOp>,
+        block_commits: &[LeaderBlockCommitOp],
+        rewards: Vec<(PoxAddress, u64)>,
+        burns: u64,
+    );
 }
 
 pub struct ChainsCoordinatorConfig {
@@ -177,6 +199,16 @@ pub struct ChainsCoordinatorConfig {
     /// true: always wait for canonical anchor blocks, even if it stalls the chain
     /// false: proceed to process new chain history even if we're missing an anchor block.
     pub require_affirmed_anchor_blocks: bool,
+    /// Number of threads that verify the transactions of staging blocks before the coordinator
+    /// processes them.  0 means transactions are only verified while their block is processed.
+    pub block_validation_threads: usize,
+    /// How many staging blocks can be queued for the block validation threads
+    pub block_validation_queue_depth: usize,
+    /// Number of threads that load unprocessed burnchain blocks while the coordinator evaluates
+    /// the sortitions before them.  0 means each block is loaded just before it is evaluated.
+    pub burnchain_load_threads: usize,
+    /// How many burnchain blocks each burnchain loading thread can load ahead
+    pub burnchain_load_queue_depth: usize,
 }
 
 impl ChainsCoordinatorConfig {
@@ -184,6 +216,10 @@ impl ChainsCoordinatorConfig {
         ChainsCoordinatorConfig {
             always_use_affirmation_maps: false,
             require_affirmed_anchor_blocks: true,
+            block_validation_threads: 0,
+            block_validation_queue_depth: 16,
+            burnchain_load_threads: 0,
+            burnchain_load_queue_depth: 16,
         }
     }
 }
@@ -206,11 +242,16 @@ pub struct ChainsCoordinator<
     dispatcher: Option<&'a T>,
     cost_estimator: Option<&'a mut CE>,
     fee_estimator: Option<&'a mut FE>,
+    indexers: Option<&'a mut IndexerPipeline>,
     reward_set_provider: R,
     notifier: N,
     atlas_config: AtlasConfig,
     config: ChainsCoordinatorConfig,
     burnchain_indexer: B,
+    /// Verifies staging blocks' transactions ahead of processing, if enabled
+    block_validation_pool: Option<BlockValidationPool>,
+    /// Loads unprocessed burnchain blocks ahead of sortition evaluation, if enabled
+    burnchain_block_loader: Option<BurnchainBlockLoader>,
 }
 
 #[derive(Debug)]
@@ -267,72 +308,9 @@ impl RewardSetProvider for OnChainRewardSetProvider {
         sortdb: &SortitionDB,
         block_id: &StacksBlockId,
     ) -> Result<RewardSet, Error> {
-        let cur_epoch = SortitionDB::get_stacks_epoch(sortdb.conn(), current_burn_height)?.expect(
-            &format!("FATAL: no epoch for burn height {}", current_burn_height),
-        );
-        match cur_epoch.epoch_id {
-            StacksEpochId::Epoch10
-            | StacksEpochId::Epoch20
-            | StacksEpochId::Epoch2_05
-            | StacksEpochId::Epoch21 => {
-                // Epochs 1.0 - 2.1 compute reward sets
-            }
-            StacksEpochId::Epoch22 | StacksEpochId::Epoch23 => {
-                info!("PoX reward cycle defaulting to burn in Epochs 2.2 and 2.3");
-                return Ok(RewardSet::empty());
-            }
-            StacksEpochId::Epoch24 => {
-                // Epoch 2.4 computes reward sets, but *only* if PoX-3 is active
-                if burnchain
-                    .pox_constants
-                    .active_pox_contract(current_burn_height)
-                    != POX_3_NAME
-                {
-                    // Note: this should not happen in mainnet or testnet, because the no reward cycle start height
-                    //        exists between Epoch 2.4's instantiation height and the pox-3 activation height.
-                    //  However, this *will* happen in testing if Epoch 2.4's instantiation height is set == a reward cycle
-                    //   start height
-                    info!("PoX reward cycle defaulting to burn in Epoch 2.4 because cycle start is before PoX-3 activation");
-                    return Ok(RewardSet::empty());
-                }
-            }
-        };
-
-        let registered_addrs =
-            chainstate.get_reward_addresses(burnchain, sortdb, current_burn_height, block_id)?;
-
-        let liquid_ustx = chainstate.get_liquid_ustx(block_id);
-
-        let (threshold, participation) = StacksChainState::get_reward_threshold_and_participation(
-            &burnchain.pox_constants,
-            &registered_addrs[..],
-            liquid_ustx,
-        );
-
-        if !burnchain
-            .pox_constants
-            .enough_participation(participation, liquid_ustx)
-        {
-            info!("PoX reward cycle did not have enough participation. Defaulting to burn";
-                  "burn_height" => current_burn_height,
-                  "participation" => participation,
-                  "liquid_ustx" => liquid_ustx,
-                  "registered_addrs" => registered_addrs.len());
-            return Ok(RewardSet::empty());
-        } else {
-            info!("PoX reward cycle threshold computed";
-                  "burn_height" => current_burn_height,
-                  "threshold" => threshold,
-                  "participation" => participation,
-                  "liquid_ustx" => liquid_ustx,
-                  "registered_addrs" => registered_addrs.len());
-        }
-
-        Ok(StacksChainState::make_reward_set(
-            threshold,
-            registered_addrs,
-            cur_epoch.epoch_id,
-        ))
+        chainstate
+            .get_reward_set_at_anchor(burnchain, sortdb, current_burn_height, block_id)
+            .map_err(Error::from)
     }
 }
 
@@ -354,6 +332,7 @@ impl<
         atlas_config: AtlasConfig,
         cost_estimator: Option<&mut CE>,
         fee_estimator: Option<&mut FE>,
+        indexers: Option<&mut IndexerPipeline>,
         miner_status: Arc<Mutex<MinerStatus>>,
         burnchain_indexer: B,
     ) where
@@ -374,6 +353,17 @@ impl<
         let canonical_sortition_tip =
             SortitionDB::get_canonical_sortition_tip(sortition_db.conn()).unwrap();
 
+        let block_validation_pool = BlockValidationPool::start(
+            &chain_state_db.blocks_path,
+            config.block_validation_threads,
+            config.block_validation_queue_depth,
+        );
+        let burnchain_block_loader = BurnchainBlockLoader::new(
+            &burnchain.get_burnchaindb_path(),
+            config.burnchain_load_threads,
+            config.burnchain_load_queue_depth,
+        );
+
         let arc_notices = ArcCounterCoordinatorNotices {
             stacks_blocks_processed,
             sortitions_processed,
@@ -391,9 +381,12 @@ impl<
             reward_set_provider: OnChainRewardSetProvider(),
             cost_estimator,
             fee_estimator,
+            indexers,
             atlas_config,
             config,
             burnchain_indexer,
+            block_validation_pool,
+            burnchain_block_loader,
         };
 
         loop {
@@ -491,8 +484,7 @@ impl<'a, T: BlockEventDispatcher, U: RewardSetProvider, B: BurnchainHeaderReader
         let burnchain_blocks_db =
             BurnchainDB::open(&burnchain.get_burnchaindb_path(), false).unwrap();
         let (chain_state_db, _) = StacksChainState::open_and_exec(
-            false,
-            chain_id,
+            stacks_common::types::NetworkId::from_chain_id(false, chain_id),
             &format!("{}/chainstate/", path),
             Some(&mut boot_data),
             None,
@@ -510,14 +502,91 @@ impl<'a, T: BlockEventDispatcher, U: RewardSetProvider, B: BurnchainHeaderReader
             dispatcher,
             cost_estimator: None,
             fee_estimator: None,
+            indexers: None,
             reward_set_provider,
             notifier: (),
             attachments_tx,
             atlas_config: AtlasConfig::default(false),
             config: ChainsCoordinatorConfig::new(),
             burnchain_indexer,
+            block_validation_pool: None,
+            burnchain_block_loader: None,
         }
     }
+
+    /// Load burnchain blocks ahead of sortition evaluation, as `run()` does when
+    /// `burnchain_load_threads` is set
+    #[cfg(test)]
+    pub fn set_burnchain_load_threads(&mut self, num_threads: usize, queue_depth: usize) {
+        self.burnchain_block_loader = BurnchainBlockLoader::new(
+            &self.burnchain.get_burnchaindb_path(),
+            num_threads,
+            queue_depth,
+        );
+    }
+}
+
+/// Hand a processed block to the sidecar indexers, and tell them if it moved the canonical
+/// Stacks tip onto another fork.  Indexer failures never stop block processing.
+fn announce_to_indexers(
+    indexers: &mut IndexerPipeline,
+    chain_state_db: &StacksChainState,
+    block_receipt: &StacksEpochReceipt,
+    canonical_stacks_tip: &StacksBlockId,
+) {
+    let block_id = block_receipt.header.index_block_hash();
+    let parent = match StacksChainState::get_parent_block_id(chain_state_db.db(), &block_id) {
+        Ok(Some(parent)) => parent,
+        Ok(None) => {
+            warn!(
+                "Indexers: no parent found for processed block {}",
+                &block_id
+            );
+            return;
+        }
+        Err(e) => {
+            warn!(
+                "Indexers: failed to load parent of processed block {}: {:?}",
+                &block_id, &e
+            );
+            return;
+        }
+    };
+
+    indexers.announce_block(&IndexerBlock {
+        header: &block_receipt.header,
+        parent: &parent,
+        receipts: &block_receipt.tx_receipts,
+        matured_rewards: &block_receipt.matured_rewards,
+    });
+
+    indexers.update_canonical_tip(canonical_stacks_tip, |old_tip| {
+        let descends = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chain_state_db.db(),
+            old_tip,
+        )
+        .and_then(|old_header| {
+            let old_header = match old_header {
+                Some(header) => header,
+                None => return Ok(false),
+            };
+            let ancestor = chain_state_db
+                .index_conn()?
+                .get_ancestor_block_hash(old_header.stacks_block_height, canonical_stacks_tip)?;
+            Ok(ancestor.as_ref() == Some(old_tip))
+        });
+        match descends {
+            Ok(descends) => descends,
+            Err(e) => {
+                // assume a reorg, so the indexers re-check their canonical branch
+                warn!(
+                    "Indexers: failed to check whether {} descends from {}: {:?}",
+                    canonical_stacks_tip, old_tip, &e
+                );
+                false
+            }
+        }
+    });
 }
 
 pub fn get_next_recipients<U: RewardSetProvider>(
@@ -644,6 +713,7 @@ pub fn get_reward_cycle_info<U: RewardSetProvider>(
     }
 }
 
+#[derive(Clone)]
 struct PaidRewards {
     pox: Vec<(PoxAddress, u64)>,
     burns: u64,
@@ -702,6 +772,38 @@ fn dispatcher_announce_burn_ops<T: BlockEventDispatcher>(
     );
 }
 
+fn dispatcher_announce_sortition<T: BlockEventDispatcher>(
+    dispatcher: &T,
+    snapshot: &BlockSnapshot,
+    transition: &BurnchainStateTransition,
+    paid_rewards: PaidRewards,
+) {
+    let block_commits: Vec<_> = transition
+        .accepted_ops
+        .iter()
+        .filter_map(|op| match op {
+            BlockstackOperationType::LeaderBlockCommit(commit) => Some(commit.clone()),
+            _ => None,
+        })
+        .collect();
+
+    let winning_commit = if snapshot.sortition {
+        block_commits
+            .iter()
+            .find(|commit| commit.txid == snapshot.winning_block_txid)
+    } else {
+        None
+    };
+
+    dispatcher.announce_sortition(
+        snapshot,
+        winning_commit,
+        &block_commits,
+        paid_rewards.pox,
+        paid_rewards.burns,
+    );
+}
+
 /// Forget that all Stacks blocks that were mined on descendants of `burn_header` are orphaned.
 /// They may be valid again, after a PoX reorg.
 fn forget_orphan_stacks_blocks(
@@ -1819,7 +1921,7 @@ impl<
 
             let ic = self.sortition_db.index_conn();
 
-            let mut chainstate_db_tx = self.chain_state_db.db_tx_begin()?;
+            let mut chainstate_db_tx = self.chain_state_db.write_tx_begin()?;
             for (burn_header, invalidation_height) in stacks_blocks_to_unorphan {
                 // permit re-processing of any associated stacks blocks if they're
                 // orphaned
@@ -1843,9 +1945,7 @@ impl<
             // by holding this lock as long as we do, we ensure that the sortition DB's
             // view of the canonical stacks chain tip can't get changed (since no
             // Stacks blocks can be processed).
-            chainstate_db_tx
-                .commit()
-                .map_err(|e| DBError::SqliteError(e))?;
+            chainstate_db_tx.commit()?;
 
             let highest_valid_snapshot = SortitionDB::get_block_snapshot(
                 &self.sortition_db.conn(),
@@ -2205,34 +2305,39 @@ impl<
                 break found_sortition;
             }
 
-            let current_block =
-                BurnchainDB::get_burnchain_block(&self.burnchain_blocks_db.conn(), &cursor)
-                    .map_err(|e| {
-                        warn!(
-                            "ChainsCoordinator: could not retrieve  block burnhash={}",
-                            &cursor
-                        );
-                        Error::NonContiguousBurnchainBlock(e)
-                    })?;
+            // only the headers are needed to find the unprocessed blocks; their operations are
+            // loaded below, possibly ahead of time by the burnchain loading threads
+            let current_header = BurnchainDB::get_burnchain_header_by_hash(
+                &self.burnchain_blocks_db.conn(),
+                &cursor,
+            )
+            .and_then(|header| header.ok_or_else(|| BurnchainError::UnknownBlock(cursor.clone())))
+            .map_err(|e| {
+                warn!(
+                    "ChainsCoordinator: could not retrieve  block burnhash={}",
+                    &cursor
+                );
+                Error::NonContiguousBurnchainBlock(e)
+            })?;
 
             debug!(
                 "Unprocessed block: ({}, {})",
-                &current_block.header.block_hash.to_string(),
-                current_block.header.block_height
+                &current_header.block_hash.to_string(),
+                current_header.block_height
             );
 
-            let parent = current_block.header.parent_block_hash.clone();
-            sortitions_to_process.push_front(current_block);
+            let parent = current_header.parent_block_hash.clone();
+            sortitions_to_process.push_front(current_header);
             cursor = parent;
         };
 
         let burn_header_hashes: Vec<_> = sortitions_to_process
             .iter()
-            .map(|block| {
+            .map(|header| {
                 format!(
                     "({}, {})",
-                    &block.header.block_hash.to_string(),
-                    block.header.block_height
+                    &header.block_hash.to_string(),
+                    header.block_height
                 )
             })
             .collect();
@@ -2246,7 +2351,36 @@ impl<
         // this wakes up anyone waiting for their block to have been processed.
         let mut revalidated_stacks_block = false;
 
-        for unprocessed_block in sortitions_to_process.into_iter() {
+        let mut burnchain_block_loads = match self.burnchain_block_loader.as_ref() {
+            Some(loader) if sortitions_to_process.len() > 1 => Some(
+                loader.load(
+                    sortitions_to_process
+                        .iter()
+                        .map(|header| header.block_hash.clone())
+                        .collect(),
+                ),
+            ),
+            _ => None,
+        };
+
+        for unprocessed_header in sortitions_to_process.into_iter() {
+            let unprocessed_block = match burnchain_block_loads.as_mut() {
+                Some(loads) => loads
+                    .next_block()
+                    .expect("BUG: burnchain loader returned fewer blocks than requested"),
+                None => BurnchainDB::get_burnchain_block(
+                    &self.burnchain_blocks_db.conn(),
+                    &unprocessed_header.block_hash,
+                ),
+            }
+            .map_err(|e| {
+                warn!(
+                    "ChainsCoordinator: could not retrieve  block burnhash={}",
+                    &unprocessed_header.block_hash
+                );
+                Error::NonContiguousBurnchainBlock(e)
+            })?;
+
             let BurnchainBlockData { header, ops } = unprocessed_block;
             if already_processed_burn_blocks.contains(&header.block_hash) {
                 // don't re-process something we recursively processed already, by means of finding
@@ -2346,7 +2480,7 @@ impl<
                     // new sortition -- go evaluate it.
                     // bind a reference here to avoid tripping up the borrow-checker
                     let dispatcher_ref = &self.dispatcher;
-                    let (next_snapshot, _) = self
+                    let (next_snapshot, transition) = self
                         .sortition_db
                         .evaluate_sortition(
                             &header,
@@ -2359,7 +2493,7 @@ impl<
                                     dispatcher_announce_burn_ops(
                                         *dispatcher,
                                         &header,
-                                        paid_rewards,
+                                        paid_rewards.clone(),
                                         reward_set_info,
                                     );
                                 }
@@ -2370,6 +2504,15 @@ impl<
                             Error::FailedToProcessSortition(e)
                         })?;
 
+                    if let Some(dispatcher) = self.dispatcher {
+                        dispatcher_announce_sortition(
+                            dispatcher,
+                            &next_snapshot,
+                            &transition,
+                            paid_rewards,
+                        );
+                    }
+
                     next_snapshot
                 }
             };
@@ -2424,7 +2567,7 @@ impl<
             if unorphan_blocks.len() > 0 {
                 revalidated_stacks_block = true;
                 let ic = self.sortition_db.index_conn();
-                let mut chainstate_db_tx = self.chain_state_db.db_tx_begin()?;
+                let mut chainstate_db_tx = self.chain_state_db.write_tx_begin()?;
                 for (burn_header, invalidation_height) in unorphan_blocks {
                     // permit re-processing of any associated stacks blocks if they're
                     // orphaned
@@ -2435,9 +2578,7 @@ impl<
                         invalidation_height,
                     )?;
                 }
-                chainstate_db_tx
-                    .commit()
-                    .map_err(|e| DBError::SqliteError(e))?;
+                chainstate_db_tx.commit()?;
             }
 
             let sortition_id = next_snapshot.sortition_id;
@@ -2882,6 +3023,12 @@ impl<
             "FAIL: processing a new Stacks block, but don't have a canonical sortition tip",
         );
 
+        // let the block validation threads, if any, verify the transactions of the staging blocks
+        // queued up behind the ones processed here
+        if let Some(pool) = self.block_validation_pool.as_mut() {
+            pool.queue_unprocessed_blocks(self.chain_state_db.db());
+        }
+
         let burnchain_db_conn = self.burnchain_blocks_db.conn();
         let sortdb_handle = self
             .sortition_db
@@ -2971,6 +3118,16 @@ impl<
                         }
                     }
 
+                    // update sidecar indexes
+                    if let Some(ref mut indexers) = self.indexers {
+                        announce_to_indexers(
+                            indexers,
+                            &self.chain_state_db,
+                            &block_receipt,
+                            &new_canonical_stacks_block,
+                        );
+                    }
+
                     // Was this block sufficiently confirmed by the prepare phase that it was a PoX
                     // anchor block?  And if we're in epoch 2.1, does it match the heaviest-confirmed
                     // block-commit in the burnchain DB, and is it affirmed by the majority of the
@@ -3035,6 +3192,10 @@ impl<
             }
             // TODO: do something with a poison result
 
+            if let Some(pool) = self.block_validation_pool.as_mut() {
+                pool.queue_unprocessed_blocks(self.chain_state_db.db());
+            }
+
             let sortdb_handle = self
                 .sortition_db
                 .tx_handle_begin(&canonical_sortition_tip)?;
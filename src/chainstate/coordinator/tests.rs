@@ -80,6 +80,7 @@ use stacks_common::types::chainstate::{
 use stacks_common::deps_common::bitcoin::blockdata::block::{BlockHeader, LoneBlockHeader};
 use stacks_common::deps_common::bitcoin::network::serialize::BitcoinHash;
 use stacks_common::deps_common::bitcoin::util::hash::Sha256dHash;
+use stacks_common::types::NetworkId;
 
 lazy_static! {
     pub static ref BURN_BLOCK_HEADERS: Arc<AtomicU64> = Arc::new(AtomicU64::new(1));
@@ -401,8 +402,7 @@ pub fn setup_states_with_epochs(
         boot_data.post_flight_callback = Some(Box::new(post_flight_callback));
 
         let (chain_state_db, _) = StacksChainState::open_and_exec(
-            false,
-            0x80000000,
+            NetworkId::testnet(),
             &format!("{}/chainstate/", path),
             Some(&mut boot_data),
             None,
@@ -445,6 +445,16 @@ impl BlockEventDispatcher for NullEventDispatcher {
         _slot_holders: Vec<PoxAddress>,
     ) {
     }
+
+    fn announce_sortition(
+        &self,
+        _snapshot: &BlockSnapshot,
+        _winning_commit: Option<&LeaderBlockCommitOp>,
+        _block_commits: &[LeaderBlockCommitOp],
+        _rewards: Vec<(PoxAddress, u64)>,
+        _burns: u64,
+    ) {
+    }
 }
 
 pub fn make_coordinator<'a>(
@@ -1229,8 +1239,7 @@ fn missed_block_commits_2_05() {
                 &StacksBlockId::new(&stacks_tip.0, &stacks_tip.1),
                 |conn| conn
                     .with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity1,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -1575,8 +1584,7 @@ fn missed_block_commits_2_1() {
                 &StacksBlockId::new(&stacks_tip.0, &stacks_tip.1),
                 |conn| conn
                     .with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity1,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -1916,8 +1924,7 @@ fn late_block_commits_2_1() {
                 &StacksBlockId::new(&stacks_tip.0, &stacks_tip.1),
                 |conn| conn
                     .with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity1,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -1939,6 +1946,59 @@ fn late_block_commits_2_1() {
     }
 }
 
+#[test]
+fn test_burnchain_load_threads() {
+    let path = "/tmp/stacks-blockchain-burnchain-load-threads";
+    // a second set of states whose coordinator loads burnchain blocks ahead of time
+    let path_loaded = "/tmp/stacks-blockchain-burnchain-load-threads.loaded";
+    let _r = std::fs::remove_dir_all(path);
+    let _r = std::fs::remove_dir_all(path_loaded);
+
+    let vrf_keys: Vec<_> = (0..10).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..10).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(
+        &[path, path_loaded],
+        &vrf_keys,
+        &committers,
+        None,
+        None,
+        StacksEpochId::Epoch2_05,
+    );
+
+    let mut coord = make_coordinator(path, None);
+    let mut coord_loaded = make_coordinator(path_loaded, None);
+    coord_loaded.set_burnchain_load_threads(3, 1);
+
+    // queue up a run of burnchain blocks, and process them all at once
+    let b = get_burnchain(path, None);
+    let mut burnchain = get_burnchain_db(path, None);
+    let mut burnchain_loaded = [get_burnchain_db(path_loaded, None)];
+    for _ in 0..10 {
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+        produce_burn_block(
+            &b,
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![],
+            burnchain_loaded.iter_mut(),
+        );
+    }
+
+    coord.handle_new_burnchain_block().unwrap();
+    coord_loaded.handle_new_burnchain_block().unwrap();
+
+    let tip =
+        SortitionDB::get_canonical_burn_chain_tip(get_sortition_db(path, None).conn()).unwrap();
+    let loaded_tip =
+        SortitionDB::get_canonical_burn_chain_tip(get_sortition_db(path_loaded, None).conn())
+            .unwrap();
+    assert_eq!(tip.block_height, 11);
+    assert_eq!(tip.sortition_id, loaded_tip.sortition_id);
+    assert_eq!(tip.consensus_hash, loaded_tip.consensus_hash);
+    assert_eq!(tip.burn_header_hash, loaded_tip.burn_header_hash);
+}
+
 #[test]
 fn test_simple_setup() {
     let path = "/tmp/stacks-blockchain-simple-setup";
@@ -2088,8 +2148,7 @@ fn test_simple_setup() {
                 &StacksBlockId::new(&stacks_tip.0, &stacks_tip.1),
                 |conn| conn
                     .with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity1,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -2397,8 +2456,7 @@ fn test_sortition_with_reward_set() {
                 &StacksBlockId::new(&stacks_tip.0, &stacks_tip.1),
                 |conn| conn
                     .with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity1,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -2641,8 +2699,7 @@ fn test_sortition_with_burner_reward_set() {
                 &StacksBlockId::new(&stacks_tip.0, &stacks_tip.1),
                 |conn| conn
                     .with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity1,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -2924,8 +2981,7 @@ fn test_pox_btc_ops() {
                 &StacksBlockId::new(&stacks_tip.0, &stacks_tip.1),
                 |conn| conn
                     .with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity1,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -3255,8 +3311,7 @@ fn test_stx_transfer_btc_ops() {
                 &StacksBlockId::new(&stacks_tip.0, &stacks_tip.1),
                 |conn| conn
                     .with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity1,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -3291,8 +3346,7 @@ fn get_delegation_info_pox_2(
     let result = chainstate
         .with_read_only_clarity_tx(burn_dbconn, parent_tip, |conn| {
             conn.with_readonly_clarity_env(
-                false,
-                CHAIN_ID_TESTNET,
+                NetworkId::testnet(),
                 ClarityVersion::Clarity2,
                 PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                 None,
@@ -3639,8 +3693,7 @@ fn test_delegate_stx_btc_ops() {
                 &StacksBlockId::new(&stacks_tip.0, &stacks_tip.1),
                 |conn| conn
                     .with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity1,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -3881,8 +3934,7 @@ fn test_initial_coinbase_reward_distributions() {
                 &StacksBlockId::new(&stacks_tip.0, &stacks_tip.1),
                 |conn| conn
                     .with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity1,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -4508,8 +4560,7 @@ fn get_total_stacked_info(
     chainstate
         .with_read_only_clarity_tx(burn_dbconn, parent_tip, |conn| {
             conn.with_readonly_clarity_env(
-                false,
-                CHAIN_ID_TESTNET,
+                NetworkId::testnet(),
                 ClarityVersion::Clarity2,
                 PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                 None,
@@ -5108,8 +5159,7 @@ fn test_sortition_with_sunset() {
                 &StacksBlockId::new(&stacks_tip.0, &stacks_tip.1),
                 |conn| conn
                     .with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity1,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -5454,8 +5504,7 @@ fn test_sortition_with_sunset_and_epoch_switch() {
                 &StacksBlockId::new(&stacks_tip.0, &stacks_tip.1),
                 |conn| conn
                     .with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity1,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -6358,8 +6407,7 @@ fn eval_at_chain_tip(chainstate_path: &str, sort_db: &SortitionDB, eval: &str) -
             &StacksBlockId::new(&stacks_tip.0, &stacks_tip.1),
             |conn| {
                 conn.with_readonly_clarity_env(
-                    false,
-                    CHAIN_ID_TESTNET,
+                    NetworkId::testnet(),
                     ClarityVersion::Clarity1,
                     PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                     None,
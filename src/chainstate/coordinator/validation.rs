@@ -0,0 +1,398 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The Stacks block validation stage of the chains coordinator.
+//!
+//! Applying a Stacks block has to happen on the coordinator thread, one block at a time, because
+//! it reads and writes the sortition DB and the chainstate MARF.  Checking each transaction's
+//! signatures does not: it only depends on the transaction's bytes.  While the coordinator
+//! applies one block, a `BlockValidationPool` loads the staging blocks queued up behind it and
+//! verifies their transactions' signatures on worker threads.  The txids of transactions that
+//! verified are remembered in a bounded process-wide set, and
+//! `StacksChainState::process_transaction_precheck()` skips `verify()` for them.
+//!
+//! This is sound because a txid is the hash of the whole serialized transaction, including its
+//! authorization, so any transaction with a remembered txid is byte-for-byte one that verified.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::burnchains::Txid;
+use crate::chainstate::burn::ConsensusHash;
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::chainstate::stacks::hash_cache::compute_txids;
+use crate::chainstate::stacks::{Error as ChainstateError, StacksTransaction};
+use crate::types::chainstate::{BlockHeaderHash, StacksBlockId};
+use crate::util_lib::db::{query_rows, u64_to_sql, DBConn, Error as DBError, FromColumn, FromRow};
+
+use rusqlite::types::ToSql;
+use rusqlite::Row;
+
+/// How many verified txids are remembered before the oldest are forgotten
+pub const VERIFIED_TXIDS_CAPACITY: usize = 65536;
+
+lazy_static! {
+    static ref VERIFIED_TXIDS: Mutex<VerifiedTxids> =
+        Mutex::new(VerifiedTxids::new(VERIFIED_TXIDS_CAPACITY));
+}
+
+/// A bounded set of txids whose signatures are known to verify.  Once full, the txid that was
+/// inserted first is forgotten first.
+pub struct VerifiedTxids {
+    txids: HashSet<Txid>,
+    order: VecDeque<Txid>,
+    capacity: usize,
+}
+
+impl VerifiedTxids {
+    pub fn new(capacity: usize) -> VerifiedTxids {
+        VerifiedTxids {
+            txids: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn insert(&mut self, txid: Txid) {
+        if self.capacity == 0 || !self.txids.insert(txid.clone()) {
+            return;
+        }
+        self.order.push_back(txid);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.txids.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn contains(&self, txid: &Txid) -> bool {
+        self.txids.contains(txid)
+    }
+
+    /// Remove `txid`, returning whether it was present.  Its place in the eviction order is left
+    /// behind and dropped when it reaches the front.
+    pub fn take(&mut self, txid: &Txid) -> bool {
+        self.txids.remove(txid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.txids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.txids.is_empty()
+    }
+}
+
+/// Was this transaction's signature already checked by the validation stage?  A hit is consumed,
+/// so each verification is used for at most one block.  Does not hash the transaction if no
+/// transactions have been verified ahead of time (e.g. because the stage is off).
+pub fn take_verified_tx(tx: &StacksTransaction) -> bool {
+    if VERIFIED_TXIDS
+        .lock()
+        .expect("FATAL: verified txid set lock poisoned")
+        .is_empty()
+    {
+        return false;
+    }
+    let txid = tx.txid();
+    VERIFIED_TXIDS
+        .lock()
+        .expect("FATAL: verified txid set lock poisoned")
+        .take(&txid)
+}
+
+/// Verify each transaction's signatures, and remember the txids of the ones that pass.  Returns
+/// how many passed.
+pub fn verify_txs(txs: &[StacksTransaction]) -> usize {
+    let txids = compute_txids(txs);
+    let mut verified = vec![];
+    for (tx, txid) in txs.iter().zip(txids.into_iter()) {
+        if tx.verify().is_ok() {
+            verified.push(txid);
+        }
+    }
+    let num_verified = verified.len();
+    let mut verified_txids = VERIFIED_TXIDS
+        .lock()
+        .expect("FATAL: verified txid set lock poisoned");
+    for txid in verified.into_iter() {
+        verified_txids.insert(txid);
+    }
+    num_verified
+}
+
+/// A staging block that the coordinator has not processed yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationJob {
+    pub index_block_hash: StacksBlockId,
+    pub consensus_hash: ConsensusHash,
+    pub anchored_block_hash: BlockHeaderHash,
+}
+
+impl FromRow<ValidationJob> for ValidationJob {
+    fn from_row<'a>(row: &'a Row) -> Result<ValidationJob, DBError> {
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let consensus_hash = ConsensusHash::from_column(row, "consensus_hash")?;
+        let anchored_block_hash = BlockHeaderHash::from_column(row, "anchored_block_hash")?;
+        Ok(ValidationJob {
+            index_block_hash,
+            consensus_hash,
+            anchored_block_hash,
+        })
+    }
+}
+
+/// Pool of threads that verify the transactions of staging blocks ahead of the coordinator
+pub struct BlockValidationPool {
+    queue: SyncSender<ValidationJob>,
+    queue_depth: usize,
+    /// Blocks handed to the workers that the coordinator has not processed yet, so they are not
+    /// queued again on the next pass
+    queued: HashSet<StacksBlockId>,
+}
+
+impl BlockValidationPool {
+    /// Start `num_threads` validation threads that load blocks from `blocks_path`, sharing a queue
+    /// of at most `queue_depth` blocks.  Returns None if `num_threads` is 0.  The threads exit once
+    /// the pool is dropped.
+    pub fn start(
+        blocks_path: &str,
+        num_threads: usize,
+        queue_depth: usize,
+    ) -> Option<BlockValidationPool> {
+        if num_threads == 0 {
+            return None;
+        }
+        let queue_depth = queue_depth.max(1);
+        let (queue_tx, queue_rx) = sync_channel(queue_depth);
+        let queue_rx = Arc::new(Mutex::new(queue_rx));
+        for thread_id in 0..num_threads {
+            let queue_rx = queue_rx.clone();
+            let blocks_path = blocks_path.to_string();
+            thread::Builder::new()
+                .name(format!("block-validation-{}", thread_id))
+                .spawn(move || BlockValidationPool::run_validation_thread(&blocks_path, queue_rx))
+                .expect("FATAL: failed to start block validation thread");
+        }
+        info!("Started {} block validation thread(s)", num_threads);
+        Some(BlockValidationPool {
+            queue: queue_tx,
+            queue_depth,
+            queued: HashSet::new(),
+        })
+    }
+
+    /// Verify queued blocks until the pool is dropped
+    fn run_validation_thread(blocks_path: &str, queue: Arc<Mutex<Receiver<ValidationJob>>>) {
+        loop {
+            let job = match queue
+                .lock()
+                .expect("FATAL: block validation queue lock poisoned")
+                .recv()
+            {
+                Ok(job) => job,
+                Err(_) => break,
+            };
+            let block = match StacksChainState::load_block(
+                blocks_path,
+                &job.consensus_hash,
+                &job.anchored_block_hash,
+            ) {
+                Ok(Some(block)) => block,
+                Ok(None) => continue,
+                Err(e) => {
+                    debug!(
+                        "Failed to load staging block {} for validation: {:?}",
+                        &job.index_block_hash, &e
+                    );
+                    continue;
+                }
+            };
+            let num_verified = verify_txs(&block.txs);
+            debug!(
+                "Verified {} of {} transaction(s) in staging block {} ahead of processing",
+                num_verified,
+                block.txs.len(),
+                &job.index_block_hash
+            );
+        }
+        debug!("Block validation thread exiting");
+    }
+
+    /// Find the unprocessed staging blocks the coordinator will process next, lowest first
+    fn get_unprocessed_blocks(
+        blocks_conn: &DBConn,
+        limit: usize,
+    ) -> Result<Vec<ValidationJob>, ChainstateError> {
+        let sql = "SELECT index_block_hash, consensus_hash, anchored_block_hash FROM staging_blocks WHERE processed = 0 AND orphaned = 0 ORDER BY height ASC LIMIT ?1";
+        let args: &[&dyn ToSql] = &[&u64_to_sql(limit as u64)?];
+        query_rows(blocks_conn, sql, args).map_err(ChainstateError::DBError)
+    }
+
+    /// Hand the workers the unprocessed staging blocks they have not seen yet, without blocking
+    /// if their queue is full.  Returns how many blocks were queued.
+    pub fn queue_unprocessed_blocks(&mut self, blocks_conn: &DBConn) -> usize {
+        let pending =
+            match BlockValidationPool::get_unprocessed_blocks(blocks_conn, self.queue_depth) {
+                Ok(pending) => pending,
+                Err(e) => {
+                    warn!("Failed to find staging blocks to validate: {:?}", &e);
+                    return 0;
+                }
+            };
+
+        // forget blocks that have since been processed or orphaned
+        let pending_ids: HashSet<_> = pending
+            .iter()
+            .map(|job| job.index_block_hash.clone())
+            .collect();
+        self.queued
+            .retain(|block_id| pending_ids.contains(block_id));
+
+        let mut num_queued = 0;
+        for job in pending.into_iter() {
+            if self.queued.contains(&job.index_block_hash) {
+                continue;
+            }
+            let block_id = job.index_block_hash.clone();
+            match self.queue.try_send(job) {
+                Ok(()) => {
+                    self.queued.insert(block_id);
+                    num_queued += 1;
+                }
+                Err(TrySendError::Full(_)) => break,
+                Err(TrySendError::Disconnected(_)) => {
+                    warn!("Block validation threads are gone");
+                    break;
+                }
+            }
+        }
+        if num_queued > 0 {
+            debug!("Queued {} staging block(s) for validation", num_queued);
+        }
+        num_queued
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::stacks::db::blocks::test::{
+        make_empty_coinbase_block, store_staging_block,
+    };
+    use crate::chainstate::stacks::db::test::instantiate_chainstate;
+    use crate::chainstate::stacks::hash_cache::tx_merkle_root;
+    use crate::chainstate::stacks::{
+        StacksPrivateKey, StacksTransactionSigner, TokenTransferMemo, TransactionAuth,
+        TransactionPayload, TransactionVersion,
+    };
+    use crate::types::chainstate::StacksAddress;
+    use std::time::{Duration, Instant};
+
+    /// Make a signed token transfer from a fresh key, and the same transfer unsigned
+    fn make_transfer() -> (StacksTransaction, StacksTransaction) {
+        let privk = StacksPrivateKey::new();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let mut unsigned = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            auth,
+            TransactionPayload::TokenTransfer(
+                StacksAddress::burn_address(false).into(),
+                123,
+                TokenTransferMemo([0u8; 34]),
+            ),
+        );
+        unsigned.chain_id = 0x80000000;
+        unsigned.set_tx_fee(0);
+
+        let mut signer = StacksTransactionSigner::new(&unsigned);
+        signer.sign_origin(&privk).unwrap();
+        let signed = signer.get_tx().unwrap();
+        (signed, unsigned)
+    }
+
+    #[test]
+    fn verified_txids_are_bounded() {
+        let mut verified = VerifiedTxids::new(2);
+        verified.insert(Txid([1u8; 32]));
+        verified.insert(Txid([2u8; 32]));
+        verified.insert(Txid([2u8; 32]));
+        assert_eq!(verified.len(), 2);
+
+        verified.insert(Txid([3u8; 32]));
+        assert_eq!(verified.len(), 2);
+        assert!(!verified.contains(&Txid([1u8; 32])));
+        assert!(verified.contains(&Txid([2u8; 32])));
+        assert!(verified.contains(&Txid([3u8; 32])));
+
+        assert!(verified.take(&Txid([2u8; 32])));
+        assert!(!verified.take(&Txid([2u8; 32])));
+        assert_eq!(verified.len(), 1);
+
+        let mut disabled = VerifiedTxids::new(0);
+        disabled.insert(Txid([1u8; 32]));
+        assert!(disabled.is_empty());
+    }
+
+    #[test]
+    fn verify_txs_remembers_verified_txids() {
+        let (signed, unsigned) = make_transfer();
+
+        assert_eq!(verify_txs(&[signed.clone(), unsigned.clone()]), 1);
+        assert!(!take_verified_tx(&unsigned));
+        assert!(take_verified_tx(&signed));
+
+        // each verification is only used once
+        assert!(!take_verified_tx(&signed));
+    }
+
+    #[test]
+    fn validation_pool_verifies_staging_blocks() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+        assert!(BlockValidationPool::start(&chainstate.blocks_path, 0, 16).is_none());
+
+        let (signed, _) = make_transfer();
+        let mut block = make_empty_coinbase_block(&StacksPrivateKey::new());
+        block.txs.push(signed.clone());
+        block.header.tx_merkle_root = tx_merkle_root(&block.txs);
+        store_staging_block(
+            &mut chainstate,
+            &ConsensusHash([2u8; 20]),
+            &block,
+            &ConsensusHash([1u8; 20]),
+            1,
+            2,
+        );
+
+        let mut pool = BlockValidationPool::start(&chainstate.blocks_path, 2, 16).unwrap();
+        assert_eq!(pool.queue_unprocessed_blocks(chainstate.db()), 1);
+
+        // not queued again while it is unprocessed
+        assert_eq!(pool.queue_unprocessed_blocks(chainstate.db()), 0);
+
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while !take_verified_tx(&signed) {
+            assert!(
+                Instant::now() < deadline,
+                "validation thread never verified the block"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
@@ -44,6 +44,7 @@ use crate::net::STACKS_PUBLIC_KEY_ENCODED_SIZE;
 use crate::types::StacksPublicKeyBuffer;
 use stacks_common::address::public_keys_to_address_hash;
 use stacks_common::address::AddressHashMode;
+use stacks_common::types::NetworkId;
 use stacks_common::util::hash::to_hex;
 use stacks_common::util::hash::Hash160;
 use stacks_common::util::hash::Sha512Trunc256Sum;
@@ -250,6 +251,13 @@ impl MultisigSpendingCondition {
         }
     }
 
+    pub fn address_for_network(&self, network: &NetworkId) -> StacksAddress {
+        StacksAddress {
+            version: network.address_version_multisig,
+            bytes: self.signer.clone(),
+        }
+    }
+
     /// Authenticate a spending condition against an initial sighash.
     /// In doing so, recover all public keys and verify that they hash to the signer
     /// via the given hash mode.
@@ -426,6 +434,17 @@ impl SinglesigSpendingCondition {
         }
     }
 
+    pub fn address_for_network(&self, network: &NetworkId) -> StacksAddress {
+        let version = match self.hash_mode {
+            SinglesigHashMode::P2PKH => network.address_version_singlesig,
+            SinglesigHashMode::P2WPKH => network.address_version_multisig,
+        };
+        StacksAddress {
+            version: version,
+            bytes: self.signer.clone(),
+        }
+    }
+
     /// Authenticate a spending condition against an initial sighash.
     /// In doing so, recover all public keys and verify that they hash to the signer
     /// via the given hash mode.
@@ -710,6 +729,14 @@ impl TransactionSpendingCondition {
         }
     }
 
+    /// Get the account address of the spending condition on the given network
+    pub fn address_for_network(&self, network: &NetworkId) -> StacksAddress {
+        match *self {
+            TransactionSpendingCondition::Singlesig(ref data) => data.address_for_network(network),
+            TransactionSpendingCondition::Multisig(ref data) => data.address_for_network(network),
+        }
+    }
+
     /// Clear fee rate, nonces, signatures, and public keys
     pub fn clear(&mut self) -> () {
         match *self {
@@ -3651,4 +3678,57 @@ mod test {
             assert_eq!(next_pubkey, StacksPublicKey::from_private(&keys[i]));
         }
     }
+
+    #[test]
+    fn tx_stacks_spending_condition_address_for_network() {
+        let singlesig_p2pkh = TransactionSpendingCondition::Singlesig(SinglesigSpendingCondition {
+            signer: Hash160([0x11; 20]),
+            hash_mode: SinglesigHashMode::P2PKH,
+            key_encoding: TransactionPublicKeyEncoding::Compressed,
+            nonce: 0,
+            tx_fee: 0,
+            signature: MessageSignature::empty(),
+        });
+        let singlesig_p2wpkh =
+            TransactionSpendingCondition::Singlesig(SinglesigSpendingCondition {
+                signer: Hash160([0x11; 20]),
+                hash_mode: SinglesigHashMode::P2WPKH,
+                key_encoding: TransactionPublicKeyEncoding::Compressed,
+                nonce: 0,
+                tx_fee: 0,
+                signature: MessageSignature::empty(),
+            });
+        let multisig = TransactionSpendingCondition::Multisig(MultisigSpendingCondition {
+            signer: Hash160([0x11; 20]),
+            hash_mode: MultisigHashMode::P2SH,
+            nonce: 0,
+            tx_fee: 0,
+            fields: vec![],
+            signatures_required: 2,
+        });
+
+        // the stock networks agree with the mainnet flag
+        for cond in [&singlesig_p2pkh, &singlesig_p2wpkh, &multisig] {
+            assert_eq!(
+                cond.address_for_network(&NetworkId::mainnet()),
+                cond.get_address(true)
+            );
+            assert_eq!(
+                cond.address_for_network(&NetworkId::testnet()),
+                cond.get_address(false)
+            );
+        }
+
+        // custom networks use their own address versions
+        let custom = NetworkId {
+            address_version_singlesig: 1,
+            address_version_multisig: 2,
+            ..NetworkId::testnet()
+        };
+        assert_eq!(singlesig_p2pkh.address_for_network(&custom).version, 1);
+        assert_eq!(singlesig_p2wpkh.address_for_network(&custom).version, 2);
+        assert_eq!(multisig.address_for_network(&custom).version, 2);
+        assert!(custom.is_network_address(&multisig.address_for_network(&custom)));
+        assert!(!custom.is_network_address(&multisig.get_address(false)));
+    }
 }
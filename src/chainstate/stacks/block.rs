@@ -18,6 +18,7 @@ use std::collections::{HashMap, HashSet};
 use std::io;
 use std::io::prelude::*;
 use std::io::{Read, Write};
+use std::mem;
 
 use sha2::Digest;
 use sha2::Sha512_256;
@@ -27,6 +28,7 @@ use crate::burnchains::PublicKey;
 use crate::chainstate::burn::operations::*;
 use crate::chainstate::burn::ConsensusHash;
 use crate::chainstate::burn::*;
+use crate::chainstate::stacks::hash_cache;
 use crate::chainstate::stacks::Error;
 use crate::chainstate::stacks::*;
 use crate::codec::MAX_MESSAGE_LEN;
@@ -305,12 +307,46 @@ impl StacksMessageCodec for StacksBlock {
     }
 
     fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<StacksBlock, codec_error> {
+        StacksBlock::consensus_deserialize_with_tx_lens(fd).map(|(block, _)| block)
+    }
+}
+
+/// Decode a length-prefixed list of transactions exactly as `read_next` would, but also return
+/// the number of bytes each transaction occupied.
+fn read_txs_with_lens<R: Read>(
+    fd: &mut R,
+) -> Result<(Vec<StacksTransaction>, Vec<u64>), codec_error> {
+    let len: u32 = read_next(fd)?;
+    if (mem::size_of::<StacksTransaction>() as u128) * (len as u128) > MAX_MESSAGE_LEN as u128 {
+        return Err(codec_error::DeserializeError(format!(
+            "Message occupies too many bytes (tried to allocate {}*{}={})",
+            mem::size_of::<StacksTransaction>() as u128,
+            len,
+            (mem::size_of::<StacksTransaction>() as u128) * (len as u128)
+        )));
+    }
+
+    let mut txs = Vec::with_capacity(len as usize);
+    let mut tx_lens = Vec::with_capacity(len as usize);
+    for _i in 0..len {
+        let (tx, tx_len) = StacksTransaction::consensus_deserialize_with_len(fd)?;
+        txs.push(tx);
+        tx_lens.push(tx_len);
+    }
+    Ok((txs, tx_lens))
+}
+
+impl StacksBlock {
+    /// Decode a block, along with the encoded length in bytes of each of its transactions.
+    pub fn consensus_deserialize_with_tx_lens<R: Read>(
+        fd: &mut R,
+    ) -> Result<(StacksBlock, Vec<u64>), codec_error> {
         // NOTE: don't worry about size clamps here; do that when receiving the data from the peer
         // network.  This code assumes that the block will be small enough.
         let header: StacksBlockHeader = read_next(fd)?;
-        let txs: Vec<StacksTransaction> = {
+        let (txs, tx_lens) = {
             let mut bound_read = BoundReader::from_reader(fd, MAX_MESSAGE_LEN as u64);
-            read_next(&mut bound_read)
+            read_txs_with_lens(&mut bound_read)
         }?;
 
         // there must be at least one transaction (the coinbase)
@@ -331,7 +367,8 @@ impl StacksMessageCodec for StacksBlock {
         }
 
         // all transactions are unique
-        if !StacksBlock::validate_transactions_unique(&txs) {
+        let txids = hash_cache::compute_txids(&txs);
+        if !StacksBlock::validate_txids_unique(&txids) {
             warn!("Invalid block: Found duplicate transaction");
             return Err(codec_error::DeserializeError(
                 "Invalid block: found duplicate transaction".to_string(),
@@ -339,10 +376,7 @@ impl StacksMessageCodec for StacksBlock {
         }
 
         // header and transactions must be consistent
-        let txid_vecs = txs.iter().map(|tx| tx.txid().as_bytes().to_vec()).collect();
-
-        let merkle_tree = MerkleTree::<Sha512Trunc256Sum>::new(&txid_vecs);
-        let tx_merkle_root = merkle_tree.root();
+        let tx_merkle_root = hash_cache::txid_merkle_root(&txids);
 
         if tx_merkle_root != header.tx_merkle_root {
             warn!("Invalid block: Tx Merkle root mismatch");
@@ -375,11 +409,9 @@ impl StacksMessageCodec for StacksBlock {
             ));
         }
 
-        Ok(StacksBlock { header, txs })
+        Ok((StacksBlock { header, txs }, tx_lens))
     }
-}
 
-impl StacksBlock {
     pub fn from_parent(
         parent_header: &StacksBlockHeader,
         parent_microblock_header: &StacksMicroblockHeader,
@@ -389,12 +421,7 @@ impl StacksBlock {
         state_index_root: &TrieHash,
         microblock_pubkey_hash: &Hash160,
     ) -> StacksBlock {
-        let txids = txs
-            .iter()
-            .map(|ref tx| tx.txid().as_bytes().to_vec())
-            .collect();
-        let merkle_tree = MerkleTree::<Sha512Trunc256Sum>::new(&txids);
-        let tx_merkle_root = merkle_tree.root();
+        let tx_merkle_root = hash_cache::tx_merkle_root(&txs);
         let header = StacksBlockHeader::from_parent(
             parent_header,
             Some(parent_microblock_header),
@@ -441,21 +468,19 @@ impl StacksBlock {
 
     /// verify no duplicate txids
     pub fn validate_transactions_unique(txs: &[StacksTransaction]) -> bool {
+        StacksBlock::validate_txids_unique(&hash_cache::compute_txids(txs))
+    }
+
+    /// verify no duplicate txids, given the already-computed txids of a block's transactions
+    pub fn validate_txids_unique(txids: &[Txid]) -> bool {
         // no duplicates
-        let mut txids = HashMap::new();
-        for (i, tx) in txs.iter().enumerate() {
-            let txid = tx.txid();
-            if txids.get(&txid).is_some() {
-                warn!(
-                    "Duplicate tx {}: at index {} and {}",
-                    txid,
-                    txids.get(&txid).unwrap(),
-                    i
-                );
-                test_debug!("{:?}", &tx);
+        let mut seen = HashMap::new();
+        for (i, txid) in txids.iter().enumerate() {
+            if let Some(prior) = seen.get(txid) {
+                warn!("Duplicate tx {}: at index {} and {}", txid, prior, i);
                 return false;
             }
-            txids.insert(txid, i);
+            seen.insert(txid.clone(), i);
         }
         return true;
     }
@@ -792,11 +817,20 @@ impl StacksMessageCodec for StacksMicroblock {
     }
 
     fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<StacksMicroblock, codec_error> {
+        StacksMicroblock::consensus_deserialize_with_tx_lens(fd).map(|(microblock, _)| microblock)
+    }
+}
+
+impl StacksMicroblock {
+    /// Decode a microblock, along with the encoded length in bytes of each of its transactions.
+    pub fn consensus_deserialize_with_tx_lens<R: Read>(
+        fd: &mut R,
+    ) -> Result<(StacksMicroblock, Vec<u64>), codec_error> {
         // NOTE: maximum size must be checked elsewhere!
         let header: StacksMicroblockHeader = read_next(fd)?;
-        let txs: Vec<StacksTransaction> = {
+        let (txs, tx_lens) = {
             let mut bound_read = BoundReader::from_reader(fd, MAX_MESSAGE_LEN as u64);
-            read_next(&mut bound_read)
+            read_txs_with_lens(&mut bound_read)
         }?;
 
         if txs.len() == 0 {
@@ -821,10 +855,7 @@ impl StacksMessageCodec for StacksMicroblock {
         }
 
         // header and transactions must be consistent
-        let txid_vecs = txs.iter().map(|tx| tx.txid().as_bytes().to_vec()).collect();
-
-        let merkle_tree = MerkleTree::<Sha512Trunc256Sum>::new(&txid_vecs);
-        let tx_merkle_root = merkle_tree.root();
+        let tx_merkle_root = hash_cache::tx_merkle_root(&txs);
 
         if tx_merkle_root != header.tx_merkle_root {
             return Err(codec_error::DeserializeError(
@@ -839,21 +870,14 @@ impl StacksMessageCodec for StacksMicroblock {
             ));
         }
 
-        Ok(StacksMicroblock { header, txs })
+        Ok((StacksMicroblock { header, txs }, tx_lens))
     }
-}
 
-impl StacksMicroblock {
     pub fn first_unsigned(
         parent_block_hash: &BlockHeaderHash,
         txs: Vec<StacksTransaction>,
     ) -> StacksMicroblock {
-        let txids = txs
-            .iter()
-            .map(|ref tx| tx.txid().as_bytes().to_vec())
-            .collect();
-        let merkle_tree = MerkleTree::<Sha512Trunc256Sum>::new(&txids);
-        let tx_merkle_root = merkle_tree.root();
+        let tx_merkle_root = hash_cache::tx_merkle_root(&txs);
         let header = StacksMicroblockHeader::first_unsigned(parent_block_hash, &tx_merkle_root);
         StacksMicroblock {
             header: header,
@@ -865,12 +889,7 @@ impl StacksMicroblock {
         parent_header: &StacksMicroblockHeader,
         txs: Vec<StacksTransaction>,
     ) -> Option<StacksMicroblock> {
-        let txids = txs
-            .iter()
-            .map(|ref tx| tx.txid().as_bytes().to_vec())
-            .collect();
-        let merkle_tree = MerkleTree::<Sha512Trunc256Sum>::new(&txids);
-        let tx_merkle_root = merkle_tree.root();
+        let tx_merkle_root = hash_cache::tx_merkle_root(&txs);
         let header =
             match StacksMicroblockHeader::from_parent_unsigned(parent_header, &tx_merkle_root) {
                 Some(h) => h,
@@ -6,8 +6,8 @@ use crate::burnchains::Burnchain;
 use crate::chainstate::burn::ConsensusHash;
 use crate::chainstate::stacks::address::PoxAddress;
 use crate::chainstate::stacks::boot::{
-    BOOT_CODE_COST_VOTING_TESTNET as BOOT_CODE_COST_VOTING, BOOT_CODE_POX_TESTNET,
-    POX_2_TESTNET_CODE,
+    BOOT_CODE_COST_VOTING_TESTNET as BOOT_CODE_COST_VOTING, BOOT_CODE_POX_TESTNET, POX_1_NAME,
+    POX_2_NAME, POX_2_TESTNET_CODE, POX_3_NAME, POX_3_TESTNET_CODE, STACKS_BOOT_CODE_TESTNET,
 };
 use crate::chainstate::stacks::db::{MinerPaymentSchedule, StacksHeaderInfo};
 use crate::chainstate::stacks::index::MarfTrieId;
@@ -15,6 +15,7 @@ use crate::chainstate::stacks::index::{ClarityMarfTrieId, TrieMerkleProof};
 use crate::chainstate::stacks::C32_ADDRESS_VERSION_TESTNET_SINGLESIG;
 use crate::chainstate::stacks::*;
 use crate::clarity_vm::database::marf::MarfedKV;
+use crate::clarity_vm::database::MemoryBackingStore;
 use crate::core::{
     BITCOIN_REGTEST_FIRST_BLOCK_HASH, BITCOIN_REGTEST_FIRST_BLOCK_HEIGHT,
     BITCOIN_REGTEST_FIRST_BLOCK_TIMESTAMP, FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH,
@@ -33,8 +34,11 @@ use clarity::vm::errors::{
     RuntimeErrorType,
 };
 use clarity::vm::eval;
+use clarity::vm::events::{STXEventType, StacksTransactionEvent};
 use clarity::vm::representations::SymbolicExpression;
-use clarity::vm::test_util::{execute, symbols_from_values, TEST_BURN_STATE_DB, TEST_HEADER_DB};
+use clarity::vm::test_util::{
+    execute, generate_test_burn_state_db, symbols_from_values, TEST_BURN_STATE_DB, TEST_HEADER_DB,
+};
 use clarity::vm::types::Value::Response;
 use clarity::vm::types::{
     OptionalData, PrincipalData, QualifiedContractIdentifier, ResponseData, StandardPrincipalData,
@@ -271,6 +275,171 @@ impl ClarityTestSim {
     }
 }
 
+/// A fixture for unit-testing calls into boot contracts, without the MARF and the block-by-block
+/// simulation of `ClarityTestSim`.  It deploys the testnet flavors of the named boot contracts into
+/// a `MemoryBackingStore` at a fixed epoch, and credits each of `USER_KEYS` with
+/// `USTX_PER_HOLDER`.  Contract calls go through the same special-case handling (e.g. STX locking)
+/// that they would in a block.
+///
+/// ```ignore
+/// let mut test = BootContractTest::new(StacksEpochId::Epoch21, &[POX_2_NAME]);
+/// test.call(&USER_KEYS[0], POX_2_NAME, "stack-stx", vec![...])
+///     .assert_value("(ok { stacker: ..., lock-amount: ..., unlock-burn-height: ... })")
+///     .assert_event_count(2);
+/// test.assert_eval(POX_2_NAME, "(is-some (get-stacker-info 'ST...))", "true");
+/// ```
+pub struct BootContractTest {
+    store: MemoryBackingStore,
+    epoch: StacksEpochId,
+}
+
+/// The outcome of a committed `BootContractTest::call()`
+pub struct BootContractCall {
+    pub value: Value,
+    pub events: Vec<StacksTransactionEvent>,
+}
+
+impl BootContractTest {
+    pub fn new(epoch: StacksEpochId, boot_contracts: &[&str]) -> BootContractTest {
+        let mut test = BootContractTest {
+            store: MemoryBackingStore::new(),
+            epoch,
+        };
+        let version = ClarityVersion::default_for_epoch(epoch);
+
+        test.with_env(|env| {
+            env.execute_in_env::<_, _, Error>(
+                QualifiedContractIdentifier::transient().issuer.into(),
+                None,
+                None,
+                |env| {
+                    env.global_context.database.set_clarity_epoch_version(epoch);
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+            for user_key in USER_KEYS.iter() {
+                env.stx_faucet(
+                    &StandardPrincipalData::from(user_key).into(),
+                    USTX_PER_HOLDER,
+                );
+            }
+
+            for name in boot_contracts.iter() {
+                env.initialize_versioned_contract(
+                    boot_code_id(name, false),
+                    version,
+                    boot_code_testnet(name),
+                    None,
+                    ASTRules::PrecheckSize,
+                )
+                .unwrap_or_else(|e| panic!("Failed to deploy boot contract {}: {:?}", name, e));
+            }
+        });
+        test
+    }
+
+    /// Run `f` against the fixture's state.  Anything `f` commits persists into later calls.
+    pub fn with_env<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut OwnedEnvironment) -> R,
+    {
+        let burn_state_db = generate_test_burn_state_db(self.epoch);
+        let db = self
+            .store
+            .as_clarity_db_with_databases(&TEST_HEADER_DB, &burn_state_db);
+        let mut owned_env = OwnedEnvironment::new(db, self.epoch);
+        f(&mut owned_env)
+    }
+
+    /// Call `function` of the boot contract `contract` as `sender`.  Panics if the call aborts
+    /// with a runtime error; a call that returns an `(err ..)` is committed like any other.
+    pub fn call(
+        &mut self,
+        sender: &StacksPrivateKey,
+        contract: &str,
+        function: &str,
+        args: Vec<Value>,
+    ) -> BootContractCall {
+        let (value, _, events) = self
+            .with_env(|env| {
+                env.execute_transaction(
+                    sender.into(),
+                    None,
+                    boot_code_id(contract, false),
+                    function,
+                    &symbols_from_values(args),
+                )
+            })
+            .unwrap_or_else(|e| panic!("Failed to call {}.{}: {:?}", contract, function, e));
+        BootContractCall { value, events }
+    }
+
+    /// Evaluate the read-only `program` in the context of the boot contract `contract`
+    pub fn eval(&mut self, contract: &str, program: &str) -> Value {
+        self.with_env(|env| env.eval_read_only(&boot_code_id(contract, false), program))
+            .unwrap_or_else(|e| panic!("Failed to evaluate {}: {:?}", program, e))
+            .0
+    }
+
+    /// Assert that `program`, evaluated in the context of the boot contract `contract`, yields the
+    /// value of the Clarity expression `expected`.  Use this to check state changes.
+    pub fn assert_eval(&mut self, contract: &str, program: &str, expected: &str) -> &mut Self {
+        assert_eq!(
+            self.eval(contract, program),
+            execute(expected),
+            "Unexpected result of {}",
+            program
+        );
+        self
+    }
+}
+
+impl BootContractCall {
+    /// Assert that the call returned the value of the Clarity expression `expected`
+    pub fn assert_value(&self, expected: &str) -> &Self {
+        assert_eq!(self.value, execute(expected));
+        self
+    }
+
+    /// Assert that the call emitted `count` events
+    pub fn assert_event_count(&self, count: usize) -> &Self {
+        assert_eq!(
+            self.events.len(),
+            count,
+            "Unexpected events: {:?}",
+            self.events
+        );
+        self
+    }
+
+    /// The values of the call's `print` events, in order
+    pub fn print_events(&self) -> Vec<&Value> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                StacksTransactionEvent::SmartContractEvent(data) => Some(&data.value),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// The testnet code of the boot contract `name`
+fn boot_code_testnet(name: &str) -> &'static str {
+    match name {
+        POX_1_NAME => BOOT_CODE_POX_TESTNET.as_str(),
+        POX_2_NAME => POX_2_TESTNET_CODE.as_str(),
+        POX_3_NAME => POX_3_TESTNET_CODE.as_str(),
+        _ => STACKS_BOOT_CODE_TESTNET
+            .iter()
+            .find(|(boot_name, _)| *boot_name == name)
+            .map(|(_, code)| *code)
+            .unwrap_or_else(|| panic!("No such boot contract: {}", name)),
+    }
+}
+
 pub fn test_sim_height_to_hash(burn_height: u64, fork: u64) -> [u8; 32] {
     let mut out = [0; 32];
     out[0..8].copy_from_slice(&burn_height.to_le_bytes());
@@ -1692,79 +1861,116 @@ fn test_deploy_smart_contract(
 
 #[test]
 fn recency_tests() {
-    let mut sim = ClarityTestSim::new();
+    let mut test = BootContractTest::new(StacksEpochId::Epoch20, &[POX_1_NAME]);
     let delegator = StacksPrivateKey::new();
 
-    sim.execute_next_block(|env| {
-        env.initialize_versioned_contract(
-            POX_CONTRACT_TESTNET.clone(),
-            ClarityVersion::Clarity2,
-            &BOOT_CODE_POX_TESTNET,
-            None,
-            ASTRules::PrecheckSize,
-        )
-        .unwrap()
-    });
-    sim.execute_next_block(|env| {
-        // try to issue a far future stacking tx
-        assert_eq!(
-            env.execute_transaction(
-                (&USER_KEYS[0]).into(),
-                None,
-                POX_CONTRACT_TESTNET.clone(),
-                "stack-stx",
-                &symbols_from_values(vec![
-                    Value::UInt(USTX_PER_HOLDER),
-                    POX_ADDRS[0].clone(),
-                    Value::UInt(3000),
-                    Value::UInt(3),
-                ])
-            )
-            .unwrap()
-            .0
-            .to_string(),
-            "(err 24)".to_string()
-        );
-        // let's delegate, and check if the delegate can issue a far future
-        //   stacking tx
-        assert_eq!(
-            env.execute_transaction(
-                (&USER_KEYS[0]).into(),
-                None,
-                POX_CONTRACT_TESTNET.clone(),
-                "delegate-stx",
-                &symbols_from_values(vec![
-                    Value::UInt(2 * USTX_PER_HOLDER),
-                    (&delegator).into(),
-                    Value::none(),
-                    Value::none()
-                ])
-            )
-            .unwrap()
-            .0,
-            Value::okay_true()
-        );
+    // try to issue a far future stacking tx
+    test.call(
+        &USER_KEYS[0],
+        POX_1_NAME,
+        "stack-stx",
+        vec![
+            Value::UInt(USTX_PER_HOLDER),
+            POX_ADDRS[0].clone(),
+            Value::UInt(3000),
+            Value::UInt(3),
+        ],
+    )
+    .assert_value("(err 24)");
+
+    // let's delegate, and check if the delegate can issue a far future
+    //   stacking tx
+    test.call(
+        &USER_KEYS[0],
+        POX_1_NAME,
+        "delegate-stx",
+        vec![
+            Value::UInt(2 * USTX_PER_HOLDER),
+            (&delegator).into(),
+            Value::none(),
+            Value::none(),
+        ],
+    )
+    .assert_value("(ok true)");
 
-        assert_eq!(
-            env.execute_transaction(
-                (&delegator).into(),
-                None,
-                POX_CONTRACT_TESTNET.clone(),
-                "delegate-stack-stx",
-                &symbols_from_values(vec![
-                    (&USER_KEYS[0]).into(),
-                    Value::UInt(USTX_PER_HOLDER),
-                    POX_ADDRS[1].clone(),
-                    Value::UInt(3000),
-                    Value::UInt(2)
-                ])
-            )
-            .unwrap()
-            .0
-            .to_string(),
-            "(err 24)".to_string()
-        );
-    });
+    test.call(
+        &delegator,
+        POX_1_NAME,
+        "delegate-stack-stx",
+        vec![
+            (&USER_KEYS[0]).into(),
+            Value::UInt(USTX_PER_HOLDER),
+            POX_ADDRS[1].clone(),
+            Value::UInt(3000),
+            Value::UInt(2),
+        ],
+    )
+    .assert_value("(err 24)");
+
+    // nothing was stacked
+    test.assert_eval(
+        POX_1_NAME,
+        &format!("(get-stacker-info '{})", Value::from(&USER_KEYS[0])),
+        "none",
+    );
+}
+
+#[test]
+fn boot_contract_test_fixture() {
+    let mut test = BootContractTest::new(StacksEpochId::Epoch21, &[POX_2_NAME]);
+    let stacker = Value::from(&USER_KEYS[0]);
+    let expected_unlock_height = POX_TESTNET_CYCLE_LENGTH * 4;
+
+    let call = test.call(
+        &USER_KEYS[0],
+        POX_2_NAME,
+        "stack-stx",
+        vec![
+            Value::UInt(USTX_PER_HOLDER),
+            POX_ADDRS[0].clone(),
+            Value::UInt(0),
+            Value::UInt(3),
+        ],
+    );
+    call.assert_value(&format!(
+        "(ok {{ stacker: '{}, lock-amount: u{}, unlock-burn-height: u{} }})",
+        stacker, USTX_PER_HOLDER, expected_unlock_height
+    ))
+    // the synthesized print event, then the lock-up
+    .assert_event_count(2);
+    assert_eq!(call.print_events().len(), 1);
+    match &call.events[1] {
+        StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(data)) => {
+            assert_eq!(data.locked_amount, USTX_PER_HOLDER);
+            assert_eq!(data.unlock_height, expected_unlock_height as u64);
+        }
+        e => panic!("Expected a lock event, got {:?}", e),
+    }
+
+    // the stacker's state persists into later calls
+    test.assert_eval(
+        POX_2_NAME,
+        &format!("(get locked (stx-account '{}))", stacker),
+        &format!("u{}", USTX_PER_HOLDER),
+    )
+    .assert_eval(
+        POX_2_NAME,
+        &format!("(is-some (get-stacker-info '{}))", stacker),
+        "true",
+    );
+    test.call(
+        &USER_KEYS[0],
+        POX_2_NAME,
+        "stack-stx",
+        vec![
+            Value::UInt(USTX_PER_HOLDER),
+            POX_ADDRS[0].clone(),
+            Value::UInt(0),
+            Value::UInt(3),
+        ],
+    )
+    .assert_value("(err 3)")
+    .assert_event_count(0);
 }
 
 #[test]
@@ -1,8 +1,13 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::iter::FromIterator;
 
 use super::STACKS_BOOT_CODE_MAINNET;
-use clarity::vm::docs::contracts::{produce_docs_refs, ContractSupportDocs};
+use crate::core::StacksEpochId;
+use clarity::vm::docs::contracts::{
+    produce_docs_refs, render_docs_json, render_docs_markdown, ConstantDocsPolicy,
+    ContractSupportDocs, DocsError, DOCS_GENERATION_EPOCH,
+};
+use regex::Regex;
 
 fn make_contract_support_docs() -> HashMap<&'static str, ContractSupportDocs> {
     let pox_descriptions = vec![
@@ -126,6 +131,17 @@ If your name is in a namespace where names do not expire, then you never need to
             ContractSupportDocs {
                 descriptions: HashMap::from_iter(pox_descriptions.into_iter()),
                 skip_func_display: HashSet::from_iter(pox_skip_display.into_iter()),
+                constants: ConstantDocsPolicy {
+                    names: HashSet::from_iter(vec![
+                        "MIN_POX_REWARD_CYCLES",
+                        "MAX_POX_REWARD_CYCLES",
+                        "POX_REJECTION_FRACTION",
+                    ]),
+                    patterns: vec![
+                        Regex::new("^(REWARD|PREPARE)_CYCLE_LENGTH$").unwrap(),
+                        Regex::new("^STACKING_THRESHOLD_").unwrap(),
+                    ],
+                },
             },
         ),
         (
@@ -133,26 +149,91 @@ If your name is in a namespace where names do not expire, then you never need to
             ContractSupportDocs {
                 descriptions: HashMap::from_iter(bns_descriptions.into_iter()),
                 skip_func_display: HashSet::from_iter(bns_skip_display.into_iter()),
+                constants: ConstantDocsPolicy {
+                    names: HashSet::new(),
+                    patterns: vec![Regex::new("_(TTL|DURATION)$").unwrap()],
+                },
             },
         ),
     ])
 }
 
-pub fn make_json_boot_contracts_reference() -> String {
+/// Produce the JSON reference of the boot contracts.  On failure, returns the error for each
+/// boot contract which could not be documented.
+pub fn make_json_boot_contracts_reference() -> Result<String, Vec<DocsError>> {
     let contract_supporting_docs = make_contract_support_docs();
-    let api_out = produce_docs_refs(&*STACKS_BOOT_CODE_MAINNET, &contract_supporting_docs);
-    format!(
+    let api_out = produce_docs_refs(
+        &*STACKS_BOOT_CODE_MAINNET,
+        &contract_supporting_docs,
+        DOCS_GENERATION_EPOCH,
+        false,
+    )?;
+    Ok(format!(
         "{}",
         serde_json::to_string(&api_out).expect("Failed to serialize documentation")
-    )
+    ))
+}
+
+/// Produce the versioned JSON reference of the boot contracts as they type-check in `epoch`.
+/// If `include_private` is set, the reference also covers private functions and tokens.
+pub fn make_versioned_json_boot_contracts_reference(
+    epoch: StacksEpochId,
+    include_private: bool,
+) -> Result<String, Vec<DocsError>> {
+    let contract_supporting_docs = make_contract_support_docs();
+    let api_out = produce_docs_refs(
+        &*STACKS_BOOT_CODE_MAINNET,
+        &contract_supporting_docs,
+        epoch,
+        include_private,
+    )?;
+    Ok(render_docs_json(epoch, &api_out))
+}
+
+/// Produce a Markdown page for each boot contract as it type-checks in `epoch`, keyed by
+/// contract name.  If `include_private` is set, the pages also cover private functions and tokens.
+pub fn make_markdown_boot_contracts_reference(
+    epoch: StacksEpochId,
+    include_private: bool,
+) -> Result<BTreeMap<String, String>, Vec<DocsError>> {
+    let contract_supporting_docs = make_contract_support_docs();
+    let api_out = produce_docs_refs(
+        &*STACKS_BOOT_CODE_MAINNET,
+        &contract_supporting_docs,
+        epoch,
+        include_private,
+    )?;
+    Ok(render_docs_markdown(epoch, &api_out))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::make_json_boot_contracts_reference;
+    use super::*;
 
     #[test]
     fn test_make_boot_contracts_reference() {
-        make_json_boot_contracts_reference();
+        make_json_boot_contracts_reference().unwrap();
+    }
+
+    #[test]
+    fn test_make_boot_contracts_reference_per_epoch() {
+        for epoch in [StacksEpochId::Epoch2_05, StacksEpochId::latest()] {
+            let json: serde_json::Value = serde_json::from_str(
+                &make_versioned_json_boot_contracts_reference(epoch, false).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(json["epoch"], epoch.to_string());
+            assert!(json["contracts"]["pox"]["maps"].as_array().unwrap().len() > 0);
+
+            let pages = make_markdown_boot_contracts_reference(epoch, false).unwrap();
+            assert!(pages["pox"].contains("## Maps"));
+            assert!(pages["bns"].contains("## Public functions"));
+            assert!(pages["pox"].contains("| `MAX_POX_REWARD_CYCLES` | `uint` | `u12` |"));
+            assert!(!pages["pox"].contains("## Private functions"));
+
+            let pages = make_markdown_boot_contracts_reference(epoch, true).unwrap();
+            assert!(pages["pox"].contains("## Private functions"));
+            assert!(pages["bns"].contains("## Non-fungible tokens"));
+        }
     }
 }
@@ -14,13 +14,17 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::borrow::Cow;
 use std::boxed::Box;
 use std::cmp;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::convert::TryInto;
+use std::sync::RwLock;
 
 use crate::burnchains::bitcoin::address::BitcoinAddress;
 use crate::burnchains::Burnchain;
+use crate::burnchains::Txid;
 use crate::burnchains::{Address, PoxConstants};
 use crate::chainstate::burn::db::sortdb::SortitionDB;
 use crate::chainstate::stacks::address::PoxAddress;
@@ -43,7 +47,7 @@ use clarity::vm::costs::{
     cost_functions::ClarityCostFunction, ClarityCostFunctionReference, CostStateSummary,
 };
 use clarity::vm::database::ClarityDatabase;
-use clarity::vm::database::{NULL_BURN_STATE_DB, NULL_HEADER_DB};
+use clarity::vm::database::{BurnStateDB, NULL_BURN_STATE_DB, NULL_HEADER_DB};
 use clarity::vm::errors::Error as VmError;
 use clarity::vm::errors::InterpreterError;
 use clarity::vm::events::StacksTransactionEvent;
@@ -56,10 +60,12 @@ use clarity::vm::types::{
 use clarity::vm::Environment;
 use stacks_common::address::AddressHashMode;
 use stacks_common::util::hash::Hash160;
+use stacks_common::util::ustx;
 
 use crate::chainstate::stacks::address::StacksAddressExtensions;
 use crate::clarity_vm::database::HeadersDBConn;
 use crate::types;
+use crate::types::chainstate::SortitionId;
 use crate::types::chainstate::StacksAddress;
 use crate::types::chainstate::StacksBlockId;
 use crate::util_lib::boot;
@@ -140,6 +146,65 @@ fn make_testnet_cost_voting() -> String {
         )
 }
 
+lazy_static! {
+    /// Boot contract source code to deploy in place of the built-in code, by contract name
+    static ref BOOT_CODE_OVERRIDES: RwLock<HashMap<String, String>> = RwLock::new(HashMap::new());
+}
+
+/// Names of all boot contracts, including the ones instantiated at later epochs
+pub const BOOT_CONTRACT_NAMES: [&'static str; 10] = [
+    "pox",
+    "lockup",
+    COSTS_1_NAME,
+    "cost-voting",
+    "bns",
+    "genesis",
+    COSTS_2_NAME,
+    POX_2_NAME,
+    COSTS_3_NAME,
+    POX_3_NAME,
+];
+
+/// Deploy the given source code in place of the built-in code of each named boot contract, for
+/// every chainstate in this process, whenever it instantiates that contract (at genesis, or at the
+/// start of the epoch that introduces it).  The overridden contract is deployed at the boot code
+/// address under its usual name, so `network_boot_code_id()` resolves to it.
+///
+/// This is for iterating on boot code in regtest and mocknet without rebuilding the node.
+/// Overrides never apply to mainnet.
+pub fn set_boot_code_overrides(overrides: HashMap<String, String>) {
+    for name in overrides.keys() {
+        assert!(
+            BOOT_CONTRACT_NAMES.contains(&name.as_str()),
+            "FATAL: no such boot contract '{}'",
+            name
+        );
+        warn!(
+            "Boot contract '{}' will be instantiated from overridden source code",
+            name
+        );
+    }
+    *BOOT_CODE_OVERRIDES
+        .write()
+        .expect("FATAL: boot code override lock poisoned") = overrides;
+}
+
+/// Get the source code to deploy for a boot contract: its override, if it has one, or else
+/// `default`
+pub fn get_boot_code<'a>(name: &str, mainnet: bool, default: &'a str) -> Cow<'a, str> {
+    if mainnet {
+        return Cow::Borrowed(default);
+    }
+    match BOOT_CODE_OVERRIDES
+        .read()
+        .expect("FATAL: boot code override lock poisoned")
+        .get(name)
+    {
+        Some(code) => Cow::Owned(code.clone()),
+        None => Cow::Borrowed(default),
+    }
+}
+
 pub fn make_contract_id(addr: &StacksAddress, name: &str) -> QualifiedContractIdentifier {
     QualifiedContractIdentifier::new(
         StandardPrincipalData::from(addr.clone()),
@@ -171,6 +236,83 @@ pub struct RewardSet {
     pub start_cycle_state: PoxStartCycleInfo,
 }
 
+/// The reward set of a past or current reward cycle, along with what became of the cycle's PoX
+/// anchor block.  Returned by `StacksChainState::get_reward_cycle_reward_set()`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum RewardCycleRewardSet {
+    /// No anchor block was selected for this reward cycle, so every PoX output in it burns.  This
+    /// is also the case for reward cycles before PoX started and after it sunset.
+    NoAnchorBlock,
+    /// An anchor block was selected, but it was never processed in this fork (or its
+    /// block-commit never won a sortition), so every PoX output in the reward cycle burns.
+    UnknownAnchorBlock {
+        anchor_block: BlockHeaderHash,
+        anchor_block_txid: Txid,
+    },
+    /// The anchor block was selected and processed, and the reward set was computed from the
+    /// chainstate as of that block.  The reward set can still be empty -- for example, if too few
+    /// STX were stacked, or if the cycle started in Epoch 2.2 or 2.3.
+    Known {
+        anchor_block: StacksBlockId,
+        anchor_block_txid: Txid,
+        reward_set: RewardSet,
+        /// Each address in `reward_set`, with the total uSTX stacked towards it, in the order in
+        /// which the address first appears in `reward_set.rewarded_addresses`
+        payouts: Vec<(PoxAddress, u128)>,
+    },
+}
+
+impl RewardCycleRewardSet {
+    /// Each address paid in this reward cycle, with the total uSTX stacked towards it
+    pub fn payouts(&self) -> &[(PoxAddress, u128)] {
+        match self {
+            RewardCycleRewardSet::Known { payouts, .. } => payouts,
+            RewardCycleRewardSet::NoAnchorBlock
+            | RewardCycleRewardSet::UnknownAnchorBlock { .. } => &[],
+        }
+    }
+}
+
+/// A stacker's entry in the PoX contract's `stacking-state` map
+#[derive(Debug, PartialEq, Clone)]
+pub struct StackingStateInfo {
+    /// The PoX address the stacker's reward slots pay out to.  `None` if the contract stored an
+    /// address that can't be paid.
+    pub pox_addr: Option<PoxAddress>,
+    pub first_reward_cycle: u64,
+    pub lock_period: u64,
+    /// The delegate that stacked on the stacker's behalf.  Only PoX-3 records this.
+    pub delegated_to: Option<PrincipalData>,
+}
+
+/// A stacker's entry in the PoX contract's `delegation-state` map
+#[derive(Debug, PartialEq, Clone)]
+pub struct DelegationStateInfo {
+    pub amount_ustx: u128,
+    pub delegated_to: PrincipalData,
+    /// Burnchain height at which the delegation expires, if it does
+    pub until_burn_height: Option<u64>,
+    /// The PoX address the delegate must stack to, if the stacker chose one
+    pub pox_addr: Option<PoxAddress>,
+}
+
+/// A stacker's stacking state as of some Stacks block.  Returned by
+/// `StacksChainState::get_stacker_info()`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StackerInfo {
+    /// uSTX locked in the stacker's account
+    pub amount_locked: u128,
+    /// Burnchain height at which the locked uSTX unlock.  These are the values stored in the
+    /// account, so the lock is over once the burnchain reaches this height even if the account
+    /// hasn't been touched since.
+    pub unlock_height: u64,
+    pub stacking: Option<StackingStateInfo>,
+    pub delegation: Option<DelegationStateInfo>,
+    /// The PoX address paid for the stacker's uSTX in the requested reward cycle, or `None` if it
+    /// isn't stacking in that cycle
+    pub reward_address: Option<PoxAddress>,
+}
+
 const POX_CYCLE_START_HANDLED_VALUE: &'static str = "1";
 
 impl PoxStartCycleInfo {
@@ -229,14 +371,12 @@ impl StacksChainState {
         pox_contract_name: &str,
     ) -> TupleData {
         // query the stacking state for this user before deleting it
-        let is_mainnet = clarity.is_mainnet();
-        let sender_addr = PrincipalData::from(boot::boot_code_addr(clarity.is_mainnet()));
-        let pox_contract = boot::boot_code_id(pox_contract_name, clarity.is_mainnet());
+        let network = clarity.network();
+        let sender_addr = PrincipalData::from(boot::network_boot_code_addr(&network));
+        let pox_contract = boot::network_boot_code_id(pox_contract_name, &network);
         let user_stacking_state = clarity
             .with_readonly_clarity_env(
-                is_mainnet,
-                // chain id doesn't matter since it won't be used
-                CHAIN_ID_MAINNET,
+                network,
                 ClarityVersion::Clarity2,
                 sender_addr,
                 None,
@@ -267,9 +407,9 @@ impl StacksChainState {
         cycle_number: u64,
         user_data: TupleData,
     ) -> Value {
-        let is_mainnet = clarity.is_mainnet();
-        let sender_addr = PrincipalData::from(boot::boot_code_addr(clarity.is_mainnet()));
-        let pox_contract = boot::boot_code_id(POX_2_NAME, clarity.is_mainnet());
+        let network = clarity.network();
+        let sender_addr = PrincipalData::from(boot::network_boot_code_addr(&network));
+        let pox_contract = boot::network_boot_code_id(POX_2_NAME, &network);
 
         let user_first_cycle_locked = user_data
             .get("first-reward-cycle")
@@ -282,9 +422,7 @@ impl StacksChainState {
 
         let result = clarity
             .with_readonly_clarity_env(
-                is_mainnet,
-                // chain id doesn't matter since it won't be used
-                CHAIN_ID_MAINNET,
+                network,
                 ClarityVersion::Clarity2,
                 sender_addr.clone(),
                 None,
@@ -374,35 +512,26 @@ impl StacksChainState {
             None => return Ok(vec![]),
         };
 
-        let sender_addr = PrincipalData::from(boot::boot_code_addr(clarity.is_mainnet()));
-        let pox_contract = boot::boot_code_id(pox_contract_name, clarity.is_mainnet());
+        let network = clarity.network();
+        let sender_addr = PrincipalData::from(boot::network_boot_code_addr(&network));
+        let pox_contract = boot::network_boot_code_id(pox_contract_name, &network);
 
         let mut total_events = vec![];
         for (principal, amount_locked) in cycle_info.missed_reward_slots.iter() {
             // we have to do several things for each principal
-            // 1. lookup their Stacks account and accelerate their unlock
+            // 1. lookup their Stacks account and accelerate their unlock (this is done by the
+            //     special-case handler for `handle-unlock`, which also emits an STX unlock event)
             // 2. remove the user's entries from every `reward-cycle-pox-address-list` they were in
             //     (a) this can be done by moving the last entry to the now vacated spot,
             //         and, if necessary, updating the associated `stacking-state` entry's pointer
             //     (b) or, if they were the only entry in the list, then just deleting them from the list
             // 3. correct the `reward-cycle-total-stacked` entry for every reward cycle they were in
             // 4. delete the user's stacking-state entry.
-            clarity.with_clarity_db(|db| {
-                // lookup the Stacks account and alter their unlock height to next block
-                let mut balance = db.get_stx_balance_snapshot(&principal);
-                if balance.canonical_balance_repr().amount_locked() < *amount_locked {
-                    panic!("Principal missed reward slots, but did not have as many locked tokens as expected. Actual: {}, Expected: {}", balance.canonical_balance_repr().amount_locked(), *amount_locked);
-                }
-
-                balance.accelerate_unlock();
-                balance.save();
-                Ok(())
-            }).expect("FATAL: failed to accelerate PoX unlock");
-
             // query the stacking state for this user before deleting it
             let user_data = Self::get_user_stacking_state(clarity, principal, pox_contract_name);
 
-            // perform the unlock
+            // perform the unlock.  This panics if the principal does not have as many locked
+            // tokens as expected.
             let (result, _, mut events, _) = clarity
                 .with_abort_callback(
                     |vm_env| {
@@ -432,10 +561,11 @@ impl StacksChainState {
             let event_info =
                 Self::synthesize_unlock_event_data(clarity, principal, cycle_number, user_data);
 
-            // Add synthetic print event for `handle-unlock`, since it alters stacking state
+            // Add synthetic print event for `handle-unlock`, since it alters stacking state.  It
+            // goes before the unlock event, as it would for any other PoX special case.
             let tx_event =
                 Environment::construct_print_transaction_event(&pox_contract, &event_info);
-            events.push(tx_event);
+            events.insert(0, tx_event);
             total_events.extend(events.into_iter());
         }
 
@@ -456,13 +586,54 @@ impl StacksChainState {
                 &stacks_block_id,
                 &HeadersDBConn(dbconn),
                 &iconn,
-                &boot::boot_code_id(boot_contract_name, self.mainnet),
+                &boot::network_boot_code_id(boot_contract_name, &self.network),
                 code,
                 ASTRules::PrecheckSize,
             )
             .map_err(Error::ClarityError)
     }
 
+    /// Read a `define-constant` value from the deployed boot contract `boot_contract_name`, as
+    /// stored in the chainstate at `stacks_block_id`.  Unlike evaluating the boot code source,
+    /// this always reflects the values the contract was actually instantiated with.
+    /// Returns Ok(None) if the contract is not deployed at this block or has no such constant.
+    pub fn get_boot_contract_constant(
+        &mut self,
+        sortdb: &SortitionDB,
+        stacks_block_id: &StacksBlockId,
+        boot_contract_name: &str,
+        constant_name: &str,
+    ) -> Result<Option<Value>, Error> {
+        let contract_id = boot::network_boot_code_id(boot_contract_name, &self.network);
+        self.maybe_read_only_clarity_tx(&sortdb.index_conn(), stacks_block_id, |clarity_tx| {
+            StacksChainState::get_constant(clarity_tx, &contract_id, constant_name)
+        })?
+        .ok_or_else(|| Error::NoSuchBlockError)?
+    }
+
+    /// Read a `uint` constant from a deployed boot contract.
+    /// Returns an error if the constant exists but is not a `uint`.
+    pub fn get_boot_contract_constant_u128(
+        &mut self,
+        sortdb: &SortitionDB,
+        stacks_block_id: &StacksBlockId,
+        boot_contract_name: &str,
+        constant_name: &str,
+    ) -> Result<Option<u128>, Error> {
+        match self.get_boot_contract_constant(
+            sortdb,
+            stacks_block_id,
+            boot_contract_name,
+            constant_name,
+        )? {
+            Some(Value::UInt(x)) => Ok(Some(x)),
+            Some(v) => Err(Error::ClarityError(ClarityError::Interpreter(
+                VmError::Unchecked(CheckErrors::TypeValueError(TypeSignature::UIntType, v)),
+            ))),
+            None => Ok(None),
+        }
+    }
+
     pub fn get_liquid_ustx(&mut self, stacks_block_id: &StacksBlockId) -> u128 {
         let mut connection = self.clarity_state.read_only_connection(
             stacks_block_id,
@@ -499,16 +670,14 @@ impl StacksChainState {
         pox_contract: &str,
     ) -> Result<u128, Error> {
         let function = "get-total-ustx-stacked";
-        let mainnet = self.mainnet;
-        let chain_id = self.chain_id;
-        let contract_identifier = boot::boot_code_id(pox_contract, mainnet);
+        let network = self.network;
+        let contract_identifier = boot::network_boot_code_id(pox_contract, &network);
         let cost_track = LimitedCostTracker::new_free();
         let sender = PrincipalData::Standard(StandardPrincipalData::transient());
         let result = self
             .maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
                 clarity_tx.with_readonly_clarity_env(
-                    mainnet,
-                    chain_id,
+                    network,
                     ClarityVersion::Clarity1,
                     sender,
                     None,
@@ -545,6 +714,143 @@ impl StacksChainState {
         .map(|value| value.expect_u128())
     }
 
+    /// Get `stacker`'s stacking state as of `block_id`, and the PoX address it is paid at in
+    /// `reward_cycle`, if it is stacking in that cycle.  `pox_contract` is the PoX contract that's
+    /// active at `block_id`.
+    ///
+    /// This reads the account and the contract's `stacking-state` and `delegation-state` maps
+    /// straight from the MARF, without evaluating any Clarity code, so it's cheap enough to serve
+    /// from RPC.  Returns NoSuchBlockError if `block_id` has not been processed.
+    pub fn get_stacker_info(
+        &mut self,
+        burn_dbconn: &dyn BurnStateDB,
+        block_id: &StacksBlockId,
+        pox_contract: &str,
+        stacker: &PrincipalData,
+        reward_cycle: u64,
+    ) -> Result<StackerInfo, Error> {
+        if !StacksChainState::has_stacks_block(self.db(), block_id)? {
+            return Err(Error::NoSuchBlockError);
+        }
+
+        let mainnet = self.network.mainnet;
+        let contract_id = boot::network_boot_code_id(pox_contract, &self.network);
+        let map_key = Value::Tuple(
+            TupleData::from_data(vec![("stacker".into(), Value::Principal(stacker.clone()))])
+                .expect("FATAL: failed to construct stacking-state key"),
+        );
+
+        let mut clarity_tx = self.clarity_state.read_only_connection_checked(
+            block_id,
+            &self.state_index,
+            burn_dbconn,
+        )?;
+        let (balance, stacking_state, delegation_state) = clarity_tx
+            .with_clarity_db_readonly(|clarity_db| {
+                let epoch = clarity_db.get_clarity_epoch_version();
+                let balance = clarity_db.get_account_stx_balance(stacker);
+                let stacking_state = clarity_db.fetch_entry_unknown_descriptor(
+                    &contract_id,
+                    "stacking-state",
+                    &map_key,
+                    &epoch,
+                )?;
+                let delegation_state = clarity_db.fetch_entry_unknown_descriptor(
+                    &contract_id,
+                    "delegation-state",
+                    &map_key,
+                    &epoch,
+                )?;
+                Ok((balance, stacking_state, delegation_state))
+            })
+            .map_err(|e: VmError| Error::ClarityError(ClarityError::from(e)))?;
+
+        let stacking = stacking_state.expect_optional().map(|value| {
+            let tuple = value.expect_tuple();
+            StackingStateInfo {
+                pox_addr: PoxAddress::try_from_pox_tuple(
+                    mainnet,
+                    tuple
+                        .get("pox-addr")
+                        .expect("FATAL: no 'pox-addr' in stacking-state"),
+                ),
+                first_reward_cycle: u64::try_from(
+                    tuple
+                        .get("first-reward-cycle")
+                        .expect("FATAL: no 'first-reward-cycle' in stacking-state")
+                        .to_owned()
+                        .expect_u128(),
+                )
+                .expect("FATAL: first reward cycle exceeds u64"),
+                lock_period: u64::try_from(
+                    tuple
+                        .get("lock-period")
+                        .expect("FATAL: no 'lock-period' in stacking-state")
+                        .to_owned()
+                        .expect_u128(),
+                )
+                .expect("FATAL: lock period exceeds u64"),
+                // not in PoX-1 or PoX-2
+                delegated_to: tuple.get("delegated-to").ok().and_then(|value| {
+                    value
+                        .to_owned()
+                        .expect_optional()
+                        .map(|principal| principal.expect_principal())
+                }),
+            }
+        });
+
+        let delegation = delegation_state.expect_optional().map(|value| {
+            let tuple = value.expect_tuple();
+            DelegationStateInfo {
+                amount_ustx: tuple
+                    .get("amount-ustx")
+                    .expect("FATAL: no 'amount-ustx' in delegation-state")
+                    .to_owned()
+                    .expect_u128(),
+                delegated_to: tuple
+                    .get("delegated-to")
+                    .expect("FATAL: no 'delegated-to' in delegation-state")
+                    .to_owned()
+                    .expect_principal(),
+                until_burn_height: tuple
+                    .get("until-burn-ht")
+                    .expect("FATAL: no 'until-burn-ht' in delegation-state")
+                    .to_owned()
+                    .expect_optional()
+                    .map(|height| {
+                        u64::try_from(height.expect_u128())
+                            .expect("FATAL: delegation expiry exceeds u64")
+                    }),
+                pox_addr: tuple
+                    .get("pox-addr")
+                    .expect("FATAL: no 'pox-addr' in delegation-state")
+                    .to_owned()
+                    .expect_optional()
+                    .and_then(|pox_addr| PoxAddress::try_from_pox_tuple(mainnet, &pox_addr)),
+            }
+        });
+
+        let reward_address = stacking
+            .as_ref()
+            .filter(|stacking| {
+                stacking.first_reward_cycle <= reward_cycle
+                    && reward_cycle
+                        < stacking
+                            .first_reward_cycle
+                            .saturating_add(stacking.lock_period)
+            })
+            .and_then(|stacking| stacking.pox_addr.clone());
+
+        Ok(StackerInfo {
+            amount_locked: balance.amount_locked(),
+            unlock_height: balance.unlock_height(),
+            stacking,
+            delegation,
+            reward_address,
+        })
+    }
+
     /// Is PoX active in the given reward cycle?
     pub fn is_pox_active(
         &mut self,
@@ -562,6 +868,195 @@ impl StacksChainState {
         .map(|value| value.expect_bool())
     }
 
+    /// Get the reward set of the reward cycle `reward_cycle` in the sortition history ending at
+    /// `sortition_tip` (usually the canonical sortition tip).  The reward cycle may be any one
+    /// that has started, not just the current one.
+    ///
+    /// This resolves the cycle's PoX anchor block the way the chains coordinator recorded it when
+    /// the cycle started, and then computes the reward set from the chainstate as of that anchor
+    /// block -- exactly as the coordinator did.  Returns `Error::PoxNoRewardCycle` if the reward
+    /// cycle hasn't started yet in this sortition history.
+    pub fn get_reward_cycle_reward_set(
+        &mut self,
+        burnchain: &Burnchain,
+        sortdb: &SortitionDB,
+        sortition_tip: &SortitionId,
+        reward_cycle: u64,
+    ) -> Result<RewardCycleRewardSet, Error> {
+        let cycle_start_height = burnchain.reward_cycle_to_block_height(reward_cycle);
+        let tip = SortitionDB::get_block_snapshot(sortdb.conn(), sortition_tip)?
+            .ok_or(Error::NoSuchBlockError)?;
+        let cycle_start = if tip.block_height == cycle_start_height {
+            tip
+        } else if tip.block_height < cycle_start_height {
+            return Err(Error::PoxNoRewardCycle);
+        } else {
+            sortdb
+                .index_handle(sortition_tip)
+                .get_block_snapshot_by_height(cycle_start_height)?
+                .ok_or(Error::PoxNoRewardCycle)?
+        };
+
+        // the anchor block chosen for a reward cycle is recorded in its first sortition
+        let handle = sortdb.index_handle(&cycle_start.sortition_id);
+        let (anchor_block_hash, anchor_block_txid) = match (
+            handle.get_last_anchor_block_hash()?,
+            handle.get_last_anchor_block_txid()?,
+        ) {
+            (Some(anchor_block_hash), Some(anchor_block_txid)) => {
+                (anchor_block_hash, anchor_block_txid)
+            }
+            _ => {
+                return Ok(RewardCycleRewardSet::NoAnchorBlock);
+            }
+        };
+
+        let anchor_snapshot = SortitionDB::get_block_snapshot_for_winning_stacks_block(
+            &sortdb.index_conn(),
+            &cycle_start.sortition_id,
+            &anchor_block_hash,
+        )?;
+        let anchor_consensus_hash = match anchor_snapshot {
+            Some(sn)
+                if StacksChainState::is_stacks_block_processed(
+                    self.db(),
+                    &sn.consensus_hash,
+                    &anchor_block_hash,
+                )? =>
+            {
+                sn.consensus_hash
+            }
+            _ => {
+                return Ok(RewardCycleRewardSet::UnknownAnchorBlock {
+                    anchor_block: anchor_block_hash,
+                    anchor_block_txid,
+                });
+            }
+        };
+
+        let anchor_block = StacksBlockId::new(&anchor_consensus_hash, &anchor_block_hash);
+        let (reward_set, payouts) =
+            self.compute_reward_set(burnchain, sortdb, cycle_start_height, &anchor_block)?;
+        Ok(RewardCycleRewardSet::Known {
+            anchor_block,
+            anchor_block_txid,
+            reward_set,
+            payouts,
+        })
+    }
+
+    /// Compute the reward set chosen by the PoX anchor block `anchor_block`, for the reward cycle
+    /// that starts at burnchain height `cycle_start_burn_height`.
+    pub fn get_reward_set_at_anchor(
+        &mut self,
+        burnchain: &Burnchain,
+        sortdb: &SortitionDB,
+        cycle_start_burn_height: u64,
+        anchor_block: &StacksBlockId,
+    ) -> Result<RewardSet, Error> {
+        self.compute_reward_set(burnchain, sortdb, cycle_start_burn_height, anchor_block)
+            .map(|(reward_set, _)| reward_set)
+    }
+
+    /// Compute the reward set chosen by `anchor_block`, along with the total uSTX stacked
+    /// towards each address in it.
+    fn compute_reward_set(
+        &mut self,
+        burnchain: &Burnchain,
+        sortdb: &SortitionDB,
+        cycle_start_burn_height: u64,
+        anchor_block: &StacksBlockId,
+    ) -> Result<(RewardSet, Vec<(PoxAddress, u128)>), Error> {
+        let cur_epoch = SortitionDB::get_stacks_epoch(sortdb.conn(), cycle_start_burn_height)?
+            .expect(&format!(
+                "FATAL: no epoch for burn height {}",
+                cycle_start_burn_height
+            ));
+        match cur_epoch.epoch_id {
+            StacksEpochId::Epoch10
+            | StacksEpochId::Epoch20
+            | StacksEpochId::Epoch2_05
+            | StacksEpochId::Epoch21 => {
+                // Epochs 1.0 - 2.1 compute reward sets
+            }
+            StacksEpochId::Epoch22 | StacksEpochId::Epoch23 => {
+                info!("PoX reward cycle defaulting to burn in Epochs 2.2 and 2.3");
+                return Ok((RewardSet::empty(), vec![]));
+            }
+            StacksEpochId::Epoch24 => {
+                // Epoch 2.4 computes reward sets, but *only* if PoX-3 is active
+                if burnchain
+                    .pox_constants
+                    .active_pox_contract(cycle_start_burn_height)
+                    != POX_3_NAME
+                {
+                    // Note: this should not happen in mainnet or testnet, because the no reward cycle start height
+                    //        exists between Epoch 2.4's instantiation height and the pox-3 activation height.
+                    //  However, this *will* happen in testing if Epoch 2.4's instantiation height is set == a reward cycle
+                    //   start height
+                    info!("PoX reward cycle defaulting to burn in Epoch 2.4 because cycle start is before PoX-3 activation");
+                    return Ok((RewardSet::empty(), vec![]));
+                }
+            }
+        };
+
+        let registered_addrs =
+            self.get_reward_addresses(burnchain, sortdb, cycle_start_burn_height, anchor_block)?;
+
+        let liquid_ustx = self.get_liquid_ustx(anchor_block);
+
+        let (threshold, participation) = StacksChainState::get_reward_threshold_and_participation(
+            &burnchain.pox_constants,
+            &registered_addrs[..],
+            liquid_ustx,
+        );
+
+        if !burnchain
+            .pox_constants
+            .enough_participation(participation, liquid_ustx)
+        {
+            info!("PoX reward cycle did not have enough participation. Defaulting to burn";
+                  "burn_height" => cycle_start_burn_height,
+                  "participation" => participation,
+                  "liquid_ustx" => liquid_ustx,
+                  "registered_addrs" => registered_addrs.len());
+            return Ok((RewardSet::empty(), vec![]));
+        } else {
+            info!("PoX reward cycle threshold computed";
+                  "burn_height" => cycle_start_burn_height,
+                  "threshold" => threshold,
+                  "participation" => participation,
+                  "liquid_ustx" => liquid_ustx,
+                  "registered_addrs" => registered_addrs.len());
+        }
+
+        // entries are combined into reward set addresses the same way make_reward_set() does it
+        let combine_key = |addr: &PoxAddress| {
+            if cur_epoch.epoch_id < StacksEpochId::Epoch21 {
+                addr.bytes()
+            } else {
+                addr.to_burnchain_repr().into_bytes()
+            }
+        };
+        let mut stacked = HashMap::new();
+        for entry in registered_addrs.iter() {
+            *stacked
+                .entry(combine_key(&entry.reward_address))
+                .or_insert(0u128) += entry.amount_stacked;
+        }
+
+        let reward_set =
+            StacksChainState::make_reward_set(threshold, registered_addrs, cur_epoch.epoch_id);
+
+        let mut payouts = vec![];
+        for addr in reward_set.rewarded_addresses.iter() {
+            if let Some(amount_stacked) = stacked.remove(&combine_key(addr)) {
+                payouts.push((addr.clone(), amount_stacked));
+            }
+        }
+        Ok((reward_set, payouts))
+    }
+
     /// Given a threshold and set of registered addresses, return a reward set where
     ///   every entry address has stacked more than the threshold, and addresses
     ///   are repeated floor(stacked_amt / threshold) times.
@@ -686,9 +1181,8 @@ impl StacksChainState {
         addresses: &[RawRewardSetEntry],
         liquid_ustx: u128,
     ) -> (u128, u128) {
-        let participation = addresses
-            .iter()
-            .fold(0, |agg, entry| agg + entry.amount_stacked);
+        let participation = ustx::checked_sum(addresses.iter().map(|entry| entry.amount_stacked))
+            .expect("FATAL: stacking participation overflowed u128");
 
         assert!(
             participation <= liquid_ustx,
@@ -766,11 +1260,10 @@ impl StacksChainState {
                 .expect(&format!("FATAL: no 'pox-addr' in return value from (get-reward-set-pox-address u{} u{})", reward_cycle, i))
                 .to_owned();
 
-            let reward_address = PoxAddress::try_from_pox_tuple(self.mainnet, &pox_addr_tuple)
-                .expect(&format!(
-                    "FATAL: not a valid PoX address: {:?}",
-                    &pox_addr_tuple
-                ));
+            let reward_address =
+                PoxAddress::try_from_pox_tuple(self.network.mainnet, &pox_addr_tuple).expect(
+                    &format!("FATAL: not a valid PoX address: {:?}", &pox_addr_tuple),
+                );
 
             let total_ustx = tuple_data
                 .get("total-ustx")
@@ -843,11 +1336,10 @@ impl StacksChainState {
                 .expect(&format!("FATAL: no `pox-addr` in return value from (get-reward-set-pox-address u{} u{})", reward_cycle, i))
                 .to_owned();
 
-            let reward_address = PoxAddress::try_from_pox_tuple(self.mainnet, &pox_addr_tuple)
-                .expect(&format!(
-                    "FATAL: not a valid PoX address: {:?}",
-                    &pox_addr_tuple
-                ));
+            let reward_address =
+                PoxAddress::try_from_pox_tuple(self.network.mainnet, &pox_addr_tuple).expect(
+                    &format!("FATAL: not a valid PoX address: {:?}", &pox_addr_tuple),
+                );
 
             let total_ustx = tuple
                 .get("total-ustx")
@@ -932,11 +1424,10 @@ impl StacksChainState {
                 .expect(&format!("FATAL: no `pox-addr` in return value from (get-reward-set-pox-address u{} u{})", reward_cycle, i))
                 .to_owned();
 
-            let reward_address = PoxAddress::try_from_pox_tuple(self.mainnet, &pox_addr_tuple)
-                .expect(&format!(
-                    "FATAL: not a valid PoX address: {:?}",
-                    &pox_addr_tuple
-                ));
+            let reward_address =
+                PoxAddress::try_from_pox_tuple(self.network.mainnet, &pox_addr_tuple).expect(
+                    &format!("FATAL: not a valid PoX address: {:?}", &pox_addr_tuple),
+                );
 
             let total_ustx = tuple
                 .get("total-ustx")
@@ -1958,6 +2449,251 @@ pub mod test {
         }
     }
 
+    #[test]
+    fn test_eval_read_only_at() {
+        let mut burnchain = Burnchain::default_unittest(
+            0,
+            &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+        );
+        burnchain.pox_constants.reward_cycle_length = 5;
+        burnchain.pox_constants.prepare_length = 2;
+        burnchain.pox_constants.anchor_threshold = 1;
+
+        let (mut peer, _keys) = instantiate_pox_peer(&burnchain, function_name!(), 6030);
+
+        let num_blocks = 10;
+        let mut liquid_ustx_at = vec![];
+
+        for tenure_id in 0..num_blocks {
+            let microblock_privkey = StacksPrivateKey::new();
+            let microblock_pubkeyhash =
+                Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = get_parent_tip(parent_opt, chainstate, sortdb);
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    let block_builder = StacksBlockBuilder::make_regtest_block_builder(
+                        &parent_tip,
+                        vrf_proof,
+                        tip.total_burn,
+                        microblock_pubkeyhash,
+                    )
+                    .unwrap();
+                    let (anchored_block, _size, _cost) =
+                        StacksBlockBuilder::make_anchored_block_from_txs(
+                            block_builder,
+                            chainstate,
+                            &sortdb.index_conn(),
+                            vec![coinbase_tx],
+                        )
+                        .unwrap();
+                    (anchored_block, vec![])
+                },
+            );
+
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            let (consensus_hash, block_bhh) = SortitionDB::get_canonical_stacks_chain_tip_hash(
+                peer.sortdb.as_ref().unwrap().conn(),
+            )
+            .unwrap();
+            let stacks_block_id = StacksBlockId::new(&consensus_hash, &block_bhh);
+            liquid_ustx_at.push((stacks_block_id, get_liquid_ustx(&mut peer)));
+        }
+
+        // the liquid supply grew as coinbases matured
+        assert!(liquid_ustx_at.first().unwrap().1 < liquid_ustx_at.last().unwrap().1);
+
+        with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+            let pox_contract = boot_code_id("pox", false);
+
+            // every block answers with its own state, not the tip's
+            for (stacks_block_id, liquid_ustx) in liquid_ustx_at.iter() {
+                let pox_info = chainstate
+                    .eval_read_only_at(
+                        &sortdb.index_conn(),
+                        stacks_block_id,
+                        &pox_contract,
+                        "get-pox-info",
+                        &[],
+                    )
+                    .unwrap()
+                    .expect_result_ok()
+                    .expect_tuple();
+                assert_eq!(
+                    pox_info.get("total-liquid-supply-ustx").unwrap(),
+                    &Value::UInt(*liquid_ustx)
+                );
+            }
+
+            let (stacks_block_id, _) = liquid_ustx_at.last().unwrap();
+            match chainstate.eval_read_only_at(
+                &sortdb.index_conn(),
+                &StacksBlockId([0x11; 32]),
+                &pox_contract,
+                "get-pox-info",
+                &[],
+            ) {
+                Err(chainstate_error::NoSuchBlockError) => {}
+                x => panic!("expected NoSuchBlockError, got {:?}", x),
+            }
+            match chainstate.eval_read_only_at(
+                &sortdb.index_conn(),
+                stacks_block_id,
+                &boot_code_id("no-such-contract", false),
+                "get-pox-info",
+                &[],
+            ) {
+                Err(chainstate_error::ClarityError(_)) => {}
+                x => panic!("expected ClarityError, got {:?}", x),
+            }
+            // only read-only functions can be called
+            assert!(chainstate
+                .eval_read_only_at(
+                    &sortdb.index_conn(),
+                    stacks_block_id,
+                    &pox_contract,
+                    "reject-pox",
+                    &[],
+                )
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn test_get_boot_contract_constant() {
+        let mut burnchain = Burnchain::default_unittest(
+            0,
+            &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+        );
+        burnchain.pox_constants.reward_cycle_length = 5;
+        burnchain.pox_constants.prepare_length = 2;
+        burnchain.pox_constants.anchor_threshold = 1;
+
+        let (mut peer, _keys) = instantiate_pox_peer(&burnchain, function_name!(), 6028);
+
+        let microblock_privkey = StacksPrivateKey::new();
+        let microblock_pubkeyhash =
+            Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+        let tip = SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+            .unwrap();
+
+        let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+            |ref mut miner,
+             ref mut sortdb,
+             ref mut chainstate,
+             vrf_proof,
+             ref parent_opt,
+             ref parent_microblock_header_opt| {
+                let parent_tip = get_parent_tip(parent_opt, chainstate, sortdb);
+                let coinbase_tx = make_coinbase(miner, 0);
+
+                let block_builder = StacksBlockBuilder::make_regtest_block_builder(
+                    &parent_tip,
+                    vrf_proof,
+                    tip.total_burn,
+                    microblock_pubkeyhash,
+                )
+                .unwrap();
+                let (anchored_block, _size, _cost) =
+                    StacksBlockBuilder::make_anchored_block_from_txs(
+                        block_builder,
+                        chainstate,
+                        &sortdb.index_conn(),
+                        vec![coinbase_tx],
+                    )
+                    .unwrap();
+                (anchored_block, vec![])
+            },
+        );
+
+        peer.next_burnchain_block(burn_ops.clone());
+        peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+        with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+            let (consensus_hash, block_bhh) =
+                SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn()).unwrap();
+            let stacks_block_id = StacksBlockId::new(&consensus_hash, &block_bhh);
+
+            // values match the deployed (testnet) constants
+            assert_eq!(
+                chainstate
+                    .get_boot_contract_constant_u128(
+                        sortdb,
+                        &stacks_block_id,
+                        "pox",
+                        "MIN_POX_REWARD_CYCLES"
+                    )
+                    .unwrap(),
+                Some(1)
+            );
+            assert_eq!(
+                chainstate
+                    .get_boot_contract_constant_u128(
+                        sortdb,
+                        &stacks_block_id,
+                        "pox",
+                        "STACKING_THRESHOLD_25"
+                    )
+                    .unwrap(),
+                Some(8000)
+            );
+            assert_eq!(
+                chainstate
+                    .get_boot_contract_constant(
+                        sortdb,
+                        &stacks_block_id,
+                        "pox",
+                        "ADDRESS_VERSION_P2SH"
+                    )
+                    .unwrap(),
+                Some(Value::buff_from_byte(0x01))
+            );
+
+            // no such constant
+            assert_eq!(
+                chainstate
+                    .get_boot_contract_constant(sortdb, &stacks_block_id, "pox", "NO_SUCH_CONSTANT")
+                    .unwrap(),
+                None
+            );
+
+            // not yet deployed
+            assert_eq!(
+                chainstate
+                    .get_boot_contract_constant(
+                        sortdb,
+                        &stacks_block_id,
+                        POX_2_NAME,
+                        "MIN_POX_REWARD_CYCLES"
+                    )
+                    .unwrap(),
+                None
+            );
+
+            // wrong type
+            assert!(chainstate
+                .get_boot_contract_constant_u128(
+                    sortdb,
+                    &stacks_block_id,
+                    "pox",
+                    "ADDRESS_VERSION_P2SH"
+                )
+                .is_err());
+        });
+    }
+
     #[test]
     fn test_lockups() {
         let mut peer_config = TestPeerConfig::new(function_name!(), 2000, 2001);
@@ -2503,6 +3239,162 @@ pub mod test {
         }
     }
 
+    #[test]
+    fn test_get_reward_cycle_reward_set() {
+        let mut burnchain = Burnchain::default_unittest(
+            0,
+            &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+        );
+        burnchain.pox_constants.reward_cycle_length = 5;
+        burnchain.pox_constants.prepare_length = 2;
+        burnchain.pox_constants.anchor_threshold = 1;
+
+        let (mut peer, mut keys) = instantiate_pox_peer(&burnchain, function_name!(), 6003);
+
+        let num_blocks = 15;
+
+        let alice = keys.pop().unwrap();
+        let mut lockup_reward_cycle = 0;
+
+        for tenure_id in 0..num_blocks {
+            let microblock_privkey = StacksPrivateKey::new();
+            let microblock_pubkeyhash =
+                Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            if tenure_id == 1 {
+                lockup_reward_cycle = burnchain
+                    .block_height_to_reward_cycle(tip.block_height)
+                    .unwrap();
+            }
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = get_parent_tip(parent_opt, chainstate, sortdb);
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    let mut block_txs = vec![coinbase_tx];
+
+                    if tenure_id == 1 {
+                        let alice_lockup = make_pox_lockup(
+                            &alice,
+                            0,
+                            1024 * POX_THRESHOLD_STEPS_USTX,
+                            AddressHashMode::SerializeP2PKH,
+                            key_to_stacks_addr(&alice).bytes,
+                            12,
+                            tip.block_height,
+                        );
+                        block_txs.push(alice_lockup);
+                    }
+
+                    let block_builder = StacksBlockBuilder::make_regtest_block_builder(
+                        &parent_tip,
+                        vrf_proof,
+                        tip.total_burn,
+                        microblock_pubkeyhash,
+                    )
+                    .unwrap();
+                    let (anchored_block, _size, _cost) =
+                        StacksBlockBuilder::make_anchored_block_from_txs(
+                            block_builder,
+                            chainstate,
+                            &sortdb.index_conn(),
+                            block_txs,
+                        )
+                        .unwrap();
+                    (anchored_block, vec![])
+                },
+            );
+
+            peer.next_burnchain_block(burn_ops);
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+        }
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(peer.sortdb().conn()).unwrap();
+        let sortition_tip = tip.sortition_id;
+        // the first block of a reward cycle is mod 1, so this is the last reward cycle to have
+        // started as of the tip
+        let tip_reward_cycle = burnchain
+            .block_height_to_reward_cycle(tip.block_height - 1)
+            .unwrap();
+
+        // Alice's lockup is certainly visible to the anchor blocks of the reward cycles that
+        // start after the one following her lockup
+        assert!(tip_reward_cycle > lockup_reward_cycle + 1);
+
+        for reward_cycle in 0..=tip_reward_cycle {
+            let reward_set = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                chainstate.get_reward_cycle_reward_set(
+                    &burnchain,
+                    sortdb,
+                    &sortition_tip,
+                    reward_cycle,
+                )
+            })
+            .unwrap();
+            eprintln!("reward cycle {}: {:?}", reward_cycle, &reward_set);
+
+            if reward_cycle <= lockup_reward_cycle {
+                assert!(reward_set.payouts().is_empty());
+            } else if reward_cycle > lockup_reward_cycle + 1 {
+                let (anchor_block, reward_set, payouts) = match reward_set {
+                    RewardCycleRewardSet::Known {
+                        anchor_block,
+                        reward_set,
+                        payouts,
+                        ..
+                    } => (anchor_block, reward_set, payouts),
+                    x => panic!("Expected a known anchor block, got {:?}", &x),
+                };
+
+                // matches the reward set the chains coordinator computed from the anchor block
+                let cycle_start_height = burnchain.reward_cycle_to_block_height(reward_cycle);
+                let expected = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                    chainstate.get_reward_set_at_anchor(
+                        &burnchain,
+                        sortdb,
+                        cycle_start_height,
+                        &anchor_block,
+                    )
+                })
+                .unwrap();
+                assert_eq!(reward_set, expected);
+
+                // one reward address, and it's Alice's
+                assert!(reward_set.rewarded_addresses.len() > 0);
+                assert_eq!(payouts.len(), 1);
+                assert_eq!(
+                    (payouts[0].0).version(),
+                    AddressHashMode::SerializeP2PKH as u8
+                );
+                assert_eq!((payouts[0].0).hash160(), key_to_stacks_addr(&alice).bytes);
+                assert_eq!(payouts[0].1, 1024 * POX_THRESHOLD_STEPS_USTX);
+                for addr in reward_set.rewarded_addresses.iter() {
+                    assert_eq!(addr, &payouts[0].0);
+                }
+            }
+        }
+
+        // the next reward cycle hasn't started yet
+        let res = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+            chainstate.get_reward_cycle_reward_set(
+                &burnchain,
+                sortdb,
+                &sortition_tip,
+                tip_reward_cycle + 1,
+            )
+        });
+        assert!(matches!(res, Err(chainstate_error::PoxNoRewardCycle)));
+    }
+
     #[test]
     fn test_pox_lockup_single_tx_sender_100() {
         let mut burnchain = Burnchain::default_unittest(
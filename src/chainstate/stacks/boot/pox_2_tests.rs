@@ -20,7 +20,7 @@ use crate::clarity_vm::database::marf::MarfedKV;
 use crate::clarity_vm::database::HeadersDBConn;
 use crate::core::*;
 use crate::util_lib::db::{DBConn, FromRow};
-use crate::vm::events::StacksTransactionEvent;
+use crate::vm::events::{STXEventType, STXLockState, StacksTransactionEvent};
 use clarity::types::Address;
 use clarity::vm::contexts::OwnedEnvironment;
 use clarity::vm::contracts::Contract;
@@ -63,6 +63,7 @@ use crate::clarity_vm::clarity::Error as ClarityError;
 use crate::chainstate::burn::operations::*;
 use clarity::vm::clarity::ClarityConnection;
 use clarity::vm::costs::LimitedCostTracker;
+use stacks_common::types::NetworkId;
 
 const USTX_PER_HOLDER: u128 = 1_000_000;
 
@@ -1057,6 +1058,27 @@ fn test_simple_pox_lockup_transition_pox_2() {
         Value::err_none(),
         "Alice tx2 should have resulted in a runtime error"
     );
+    assert!(
+        match alice_txs.get(&2).unwrap().events.as_slice() {
+            [StacksTransactionEvent::PoxDefunctCallEvent(data)] => {
+                data.sender == alice_address.to_account_principal()
+                    && data.contract_identifier == boot_code_id("pox", false)
+                    && data.function_name == "stack-stx"
+            }
+            _ => false,
+        },
+        "Alice tx2 should have reported the call to the defunct PoX contract"
+    );
+    assert_eq!(
+        alice_txs
+            .get(&2)
+            .unwrap()
+            .vm_error_code
+            .as_ref()
+            .map(|err| err.label.as_str()),
+        Some("vm_runtime_defunct_pox_contract"),
+        "Alice tx2 should have reported a machine-readable error code"
+    );
 
     //  TX0 -> Bob's initial lockup in PoX 2
     assert!(
@@ -1391,6 +1413,24 @@ fn test_simple_pox_2_auto_unlock(alice_first: bool) {
         burnchain_unlock_height: Value::UInt(42),
     };
     check_pox_print_event(&auto_unlock_tx, common_data, auto_unlock_op_data);
+
+    // Check that the unlock event follows the print event, and reports the released lock
+    let auto_unlock_event = &coinbase_txs[16].events[1];
+    match auto_unlock_event {
+        StacksTransactionEvent::STXEvent(STXEventType::STXUnlockEvent(data)) => {
+            assert_eq!(data.function_name, "handle-unlock");
+            assert_eq!(data.contract_identifier, boot_code_id(POX_2_NAME, false));
+            assert_eq!(
+                data.unlocked_address,
+                StacksAddress::from_string("ST1GCB6NH3XR67VT4R5PKVJ2PYXNVQ4AYQATXNP4P")
+                    .unwrap()
+                    .to_account_principal()
+            );
+            assert_eq!(data.unlocked_amount, 10000000000);
+            assert_eq!(data.prior_lock.locked_amount, 10000000000);
+        }
+        _ => panic!("Expected an unlock event, got {:?}", auto_unlock_event),
+    }
 }
 
 /// In this test case, Alice delegates to Bob.
@@ -1530,6 +1570,49 @@ fn delegate_stack_increase() {
 
     assert_eq!(alice_bal.amount_locked(), alice_first_lock_amount);
 
+    // the stacker info read from the PoX-2 data maps agrees with the account
+    let get_stacker_info = |peer: &mut TestPeer, stacker: &PrincipalData, reward_cycle: u64| {
+        with_sortdb(peer, |chainstate, sortdb| {
+            chainstate
+                .get_stacker_info(
+                    &sortdb.index_conn(),
+                    &latest_block,
+                    POX_2_NAME,
+                    stacker,
+                    reward_cycle,
+                )
+                .unwrap()
+        })
+    };
+
+    let alice_info = get_stacker_info(&mut peer, &alice_principal, EXPECTED_FIRST_V2_CYCLE + 3);
+    assert_eq!(alice_info.amount_locked, alice_first_lock_amount);
+    assert_eq!(alice_info.unlock_height, alice_bal.unlock_height());
+    assert_eq!(
+        alice_info.reward_address,
+        PoxAddress::try_from_pox_tuple(false, &bob_pox_addr)
+    );
+    let alice_stacking = alice_info.stacking.unwrap();
+    assert_eq!(alice_stacking.first_reward_cycle, EXPECTED_FIRST_V2_CYCLE);
+    assert_eq!(alice_stacking.lock_period, 6);
+    assert_eq!(alice_stacking.delegated_to, None);
+    let alice_delegation = alice_info.delegation.unwrap();
+    assert_eq!(alice_delegation.amount_ustx, alice_delegation_amount);
+    assert_eq!(alice_delegation.delegated_to, bob_principal);
+    assert_eq!(alice_delegation.until_burn_height, None);
+    assert_eq!(alice_delegation.pox_addr, None);
+
+    // alice's lock doesn't extend to this cycle
+    let alice_info = get_stacker_info(&mut peer, &alice_principal, EXPECTED_FIRST_V2_CYCLE + 6);
+    assert_eq!(alice_info.reward_address, None);
+
+    // bob only stacks on alice's behalf
+    let bob_info = get_stacker_info(&mut peer, &bob_principal, EXPECTED_FIRST_V2_CYCLE + 3);
+    assert_eq!(bob_info.amount_locked, 0);
+    assert_eq!(bob_info.stacking, None);
+    assert_eq!(bob_info.delegation, None);
+    assert_eq!(bob_info.reward_address, None);
+
     // check that the partial stacking state contains entries for bob
     for cycle_number in EXPECTED_FIRST_V2_CYCLE..(EXPECTED_FIRST_V2_CYCLE + 6) {
         let partial_stacked = get_partial_stacked(
@@ -1746,6 +1829,28 @@ fn delegate_stack_increase() {
         burnchain_unlock_height: Value::UInt(70),
     };
     check_pox_print_event(delegate_stack_increase_tx, common_data, delegate_op_data);
+
+    // Check that the lock event reports the new total
+    let delegate_stack_increase_lock_event = &bob_txs.get(&4).unwrap().events[1];
+    match delegate_stack_increase_lock_event {
+        StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(data)) => {
+            assert_eq!(data.function_name, "delegate-stack-increase");
+            assert_eq!(data.contract_identifier, boot_code_id(POX_2_NAME, false));
+            assert_eq!(data.locked_amount, 10230000000000);
+            assert_eq!(data.unlock_height, 70);
+            assert_eq!(
+                data.prior_lock,
+                Some(STXLockState {
+                    locked_amount: 5120000000000,
+                    unlock_height: 70,
+                })
+            );
+        }
+        _ => panic!(
+            "Expected a lock event, got {:?}",
+            delegate_stack_increase_lock_event
+        ),
+    }
 }
 
 /// In this test case, Alice stacks and interacts with the
@@ -1996,6 +2101,25 @@ fn stack_increase() {
         burnchain_unlock_height: Value::UInt(70),
     };
     check_pox_print_event(stack_increase_tx, common_data, stack_op_data);
+
+    // Check that the lock event reports the new total
+    let stack_increase_lock_event = &alice_txs.get(&success_increase).unwrap().events[1];
+    match stack_increase_lock_event {
+        StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(data)) => {
+            assert_eq!(data.function_name, "stack-increase");
+            assert_eq!(data.contract_identifier, boot_code_id(POX_2_NAME, false));
+            assert_eq!(data.locked_amount, first_lockup_amt + increase_amt);
+            assert_eq!(data.unlock_height, 70);
+            assert_eq!(
+                data.prior_lock,
+                Some(STXLockState {
+                    locked_amount: first_lockup_amt,
+                    unlock_height: 70,
+                })
+            );
+        }
+        _ => panic!("Expected a lock event, got {:?}", stack_increase_lock_event),
+    }
 }
 
 #[test]
@@ -2589,6 +2713,32 @@ fn test_pox_extend_transition_pox_2() {
         burnchain_unlock_height: Value::UInt(55),
     };
     check_pox_print_event(stack_extend_tx, common_data, stack_ext_op_data);
+
+    // Check that the lock events say which function produced them, and what they replaced
+    let stack_lock_event = &bob_txs.get(&0).unwrap().events[1];
+    match stack_lock_event {
+        StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(data)) => {
+            assert_eq!(data.function_name, "stack-stx");
+            assert_eq!(data.contract_identifier, boot_code_id(POX_2_NAME, false));
+            assert_eq!(data.prior_lock, None);
+        }
+        _ => panic!("Expected a lock event, got {:?}", stack_lock_event),
+    }
+    let stack_extend_lock_event = &bob_txs.get(&1).unwrap().events[1];
+    match stack_extend_lock_event {
+        StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(data)) => {
+            assert_eq!(data.function_name, "stack-extend");
+            assert_eq!(data.unlock_height, 60);
+            assert_eq!(
+                data.prior_lock,
+                Some(STXLockState {
+                    locked_amount: 5120000000000,
+                    unlock_height: 55,
+                })
+            );
+        }
+        _ => panic!("Expected a lock event, got {:?}", stack_extend_lock_event),
+    }
 }
 
 /// In this test case, two Stackers, Alice and Bob delegate stack and interact with the
@@ -3846,8 +3996,7 @@ fn test_get_pox_addrs() {
                         |clarity_tx| {
                             clarity_tx
                                 .with_readonly_clarity_env(
-                                    false,
-                                    0x80000000,
+                                    NetworkId::testnet(),
                                     ClarityVersion::Clarity2,
                                     PrincipalData::Standard(StandardPrincipalData::transient()),
                                     None,
@@ -4139,8 +4288,7 @@ fn test_stack_with_segwit() {
                         |clarity_tx| {
                             clarity_tx
                                 .with_readonly_clarity_env(
-                                    false,
-                                    0x80000000,
+                                    NetworkId::testnet(),
                                     ClarityVersion::Clarity2,
                                     PrincipalData::Standard(StandardPrincipalData::transient()),
                                     None,
@@ -69,6 +69,7 @@ use crate::clarity_vm::clarity::Error as ClarityError;
 use crate::chainstate::burn::operations::*;
 use clarity::vm::clarity::ClarityConnection;
 use clarity::vm::costs::LimitedCostTracker;
+use stacks_common::types::NetworkId;
 
 const USTX_PER_HOLDER: u128 = 1_000_000;
 
@@ -3336,8 +3337,7 @@ fn get_burn_pox_addr_info(peer: &mut TestPeer) -> (Vec<PoxAddress>, u128) {
             .maybe_read_only_clarity_tx(&sortdb.index_conn(), &tip_index_block, |clarity_tx| {
                 clarity_tx
                     .with_readonly_clarity_env(
-                        false,
-                        0x80000000,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity2,
                         PrincipalData::Standard(StandardPrincipalData::transient()),
                         None,
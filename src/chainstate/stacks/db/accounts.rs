@@ -35,6 +35,7 @@ use clarity::vm::types::*;
 use crate::types::chainstate::{StacksAddress, StacksBlockId};
 
 use crate::core::StacksEpochId;
+use stacks_common::util::ustx;
 
 /// A record of a coin reward for a miner.  There will be at most two of these for a miner: one for
 /// the coinbase + block-txs + confirmed-mblock-txs, and one for the produced-mblock-txs.  The
@@ -566,6 +567,41 @@ impl StacksChainState {
         Ok(out_balance)
     }
 
+    /// Release a STX lock up for PoX early, so that it unlocks at the next burn block.  Does NOT
+    /// touch the account nonce.
+    /// Returns Ok(locked_amount) when successful
+    ///
+    /// # Errors
+    /// - Returns Error::PoxUnlockNotLocked if this function was called on an account which
+    ///     does not have at least `expected_locked` uSTX locked by PoX v2 or v3.
+    pub fn pox_unlock(
+        db: &mut ClarityDatabase,
+        principal: &PrincipalData,
+        expected_locked: u128,
+    ) -> Result<u128, Error> {
+        let mut snapshot = db.get_stx_balance_snapshot(principal);
+
+        if !snapshot.is_v2_locked() && !snapshot.is_v3_locked() {
+            return Err(Error::PoxUnlockNotLocked);
+        }
+        let locked_amount = snapshot.canonical_balance_repr().amount_locked();
+        if locked_amount < expected_locked {
+            return Err(Error::PoxUnlockNotLocked);
+        }
+
+        snapshot.accelerate_unlock();
+
+        debug!(
+            "PoX lock released";
+            "locked_ustx" => locked_amount,
+            "unlock_burn_height" => snapshot.balance().unlock_height(),
+            "account" => %principal,
+        );
+
+        snapshot.save();
+        Ok(locked_amount)
+    }
+
     /// Extend a STX lock up for PoX for a time.  Does NOT touch the account nonce.
     /// Returns Ok(lock_amount) when successful
     ///
@@ -1080,7 +1116,8 @@ impl StacksChainState {
 
     /// What's the commission for reporting a poison microblock stream?
     fn poison_microblock_commission(coinbase: u128) -> u128 {
-        (coinbase * POISON_MICROBLOCK_COMMISSION_FRACTION) / 100
+        ustx::checked_percent(coinbase, POISON_MICROBLOCK_COMMISSION_FRACTION)
+            .expect("FATAL: poison microblock commission overflow")
     }
 
     /// Calculate a block mining participant's coinbase reward, given the block's miner and list of
@@ -1101,12 +1138,12 @@ impl StacksChainState {
         let (this_burn_total, other_burn_total) = {
             if participant.miner {
                 // we're calculating the miner's reward
-                let mut total_user: u128 = 0;
-                for user_support in users.iter() {
-                    total_user = total_user
-                        .checked_add(user_support.burnchain_commit_burn as u128)
-                        .expect("FATAL: user support burn overflow");
-                }
+                let total_user = ustx::checked_sum(
+                    users
+                        .iter()
+                        .map(|user_support| user_support.burnchain_commit_burn),
+                )
+                .expect("FATAL: user support burn overflow");
                 (participant.burnchain_commit_burn as u128, total_user)
             } else {
                 // we're calculating a user burn support's reward
@@ -1114,9 +1151,11 @@ impl StacksChainState {
                 let mut total_other: u128 = miner.burnchain_commit_burn as u128;
                 for user_support in users.iter() {
                     if user_support.address != participant.address {
-                        total_other = total_other
-                            .checked_add(user_support.burnchain_commit_burn as u128)
-                            .expect("FATAL: user support burn overflow");
+                        total_other = ustx::checked_add(
+                            total_other,
+                            user_support.burnchain_commit_burn as u128,
+                        )
+                        .expect("FATAL: user support burn overflow");
                     } else {
                         this_user = user_support.burnchain_commit_burn as u128;
                     }
@@ -1125,8 +1164,7 @@ impl StacksChainState {
             }
         };
 
-        let burn_total = other_burn_total
-            .checked_add(this_burn_total)
+        let burn_total = ustx::checked_add(other_burn_total, this_burn_total)
             .expect("FATAL: combined burns exceed u128");
 
         test_debug!(
@@ -1139,11 +1177,9 @@ impl StacksChainState {
 
         // each participant gets a share of the coinbase proportional to the fraction it burned out
         // of all participants' burns.
-        let coinbase_reward = participant
-            .coinbase
-            .checked_mul(this_burn_total as u128)
-            .expect("FATAL: STX coinbase reward overflow")
-            / (burn_total as u128);
+        let coinbase_reward =
+            ustx::checked_mul_div(participant.coinbase, this_burn_total, burn_total)
+                .expect("FATAL: STX coinbase reward overflow");
 
         // process poison -- someone can steal a fraction of the total coinbase if they can present
         // evidence that the miner forked the microblock stream.  The remainder of the coinbase is
@@ -1356,6 +1392,7 @@ mod test {
     use crate::chainstate::stacks::*;
     use crate::core::StacksEpochId;
     use clarity::vm::costs::ExecutionCost;
+    use clarity::vm::database::MemoryBackingStore;
     use stacks_common::util::hash::*;
 
     use crate::types::chainstate::BurnchainHeaderHash;
@@ -1487,6 +1524,54 @@ mod test {
         tip
     }
 
+    #[test]
+    fn pox_unlock_accelerates_lock() {
+        let mut store = MemoryBackingStore::new();
+        let mut db = store.as_clarity_db();
+        db.begin();
+
+        let principal = StacksAddress {
+            version: 26,
+            bytes: Hash160([0x01; 20]),
+        }
+        .to_account_principal();
+
+        // nothing to unlock
+        assert!(matches!(
+            StacksChainState::pox_unlock(&mut db, &principal, 0),
+            Err(Error::PoxUnlockNotLocked)
+        ));
+
+        let mut snapshot = db.get_stx_balance_snapshot(&principal);
+        snapshot.credit(1000);
+        snapshot.save();
+        StacksChainState::pox_lock_v2(&mut db, &principal, 600, 100).unwrap();
+
+        // less is locked than expected
+        assert!(matches!(
+            StacksChainState::pox_unlock(&mut db, &principal, 601),
+            Err(Error::PoxUnlockNotLocked)
+        ));
+        let balance = db.get_account_stx_balance(&principal);
+        assert_eq!(balance.amount_locked(), 600);
+        assert_eq!(balance.unlock_height(), 100);
+
+        // the lock is released at the next burn block
+        assert_eq!(
+            StacksChainState::pox_unlock(&mut db, &principal, 600).unwrap(),
+            600
+        );
+        let balance = db.get_account_stx_balance(&principal);
+        assert_eq!(balance.amount_locked(), 600);
+        assert_eq!(balance.amount_unlocked(), 400);
+        assert_eq!(
+            balance.unlock_height(),
+            u64::from(db.get_current_burnchain_block_height()) + 1
+        );
+
+        db.roll_back();
+    }
+
     #[test]
     fn get_tip_ancestor() {
         let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
@@ -0,0 +1,239 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Persistent list of leader keys whose miners produced consensus-invalid blocks.
+//!
+//! When an anchored block is found to be invalid, the VRF key its miner registered (and consumed
+//! in the block commit that elected the block) is banned.  Blocks that are only rejected because
+//! a microblock they confirm is invalid do not count against their miner, since that stream was
+//! produced by the parent block's miner.
+//!
+//! A ban has no effect on consensus.  The block downloader consults it to deprioritize or refuse
+//! further blocks elected by the same key, according to the node's `BannedLeaderKeyPolicy`.  Bans
+//! are never lifted automatically; they stay until the operator clears them.
+
+use rusqlite::types::ToSql;
+use rusqlite::{Connection, Row, NO_PARAMS};
+
+use crate::burnchains::Txid;
+use crate::chainstate::burn::db::sortdb::SortitionHandleTx;
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::chainstate::stacks::Error;
+use crate::types::chainstate::{BlockHeaderHash, ConsensusHash, SortitionId};
+use crate::util_lib::db::{
+    query_row, query_rows, u64_to_sql, DBTx, Error as db_error, FromColumn, FromRow,
+};
+use stacks_common::util::get_epoch_time_secs;
+use stacks_common::util::vrf::VRFPublicKey;
+
+/// A leader key banned for producing a consensus-invalid block
+#[derive(Debug, Clone, PartialEq)]
+pub struct BannedLeaderKey {
+    pub public_key: VRFPublicKey,
+    /// the invalid block that got the key banned
+    pub consensus_hash: ConsensusHash,
+    pub anchored_block_hash: BlockHeaderHash,
+    /// why the block was invalid
+    pub reason: String,
+    /// when the key was banned, in seconds since the epoch
+    pub banned_at: u64,
+}
+
+impl FromRow<BannedLeaderKey> for BannedLeaderKey {
+    fn from_row<'a>(row: &'a Row) -> Result<BannedLeaderKey, db_error> {
+        let public_key = VRFPublicKey::from_column(row, "public_key")?;
+        let consensus_hash = ConsensusHash::from_column(row, "consensus_hash")?;
+        let anchored_block_hash = BlockHeaderHash::from_column(row, "anchored_block_hash")?;
+        let reason: String = row.get_unwrap("reason");
+        let banned_at = u64::from_column(row, "banned_at")?;
+        Ok(BannedLeaderKey {
+            public_key,
+            consensus_hash,
+            anchored_block_hash,
+            reason,
+            banned_at,
+        })
+    }
+}
+
+impl StacksChainState {
+    /// Ban a leader key.  If the key is already banned, its original ban is kept.
+    /// Returns true if the key was not banned before.
+    pub fn ban_leader_key(tx: &DBTx, banned: &BannedLeaderKey) -> Result<bool, Error> {
+        let sql = "INSERT OR IGNORE INTO banned_leader_keys
+            (public_key, consensus_hash, anchored_block_hash, reason, banned_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)";
+        let args: &[&dyn ToSql] = &[
+            &banned.public_key.to_hex(),
+            &banned.consensus_hash,
+            &banned.anchored_block_hash,
+            &banned.reason,
+            &u64_to_sql(banned.banned_at)?,
+        ];
+        Ok(tx.execute(sql, args)? > 0)
+    }
+
+    /// Ban the leader key of the miner that produced an invalid anchored block.  The block was
+    /// elected by the block commit `winning_block_txid` in sortition `sortition_id`.
+    /// Returns true if the key was not banned before.
+    pub fn ban_block_leader_key(
+        tx: &DBTx,
+        sort_tx: &mut SortitionHandleTx,
+        sortition_id: &SortitionId,
+        winning_block_txid: &Txid,
+        consensus_hash: &ConsensusHash,
+        anchored_block_hash: &BlockHeaderHash,
+        reason: &str,
+    ) -> Result<bool, Error> {
+        let commit = sort_tx
+            .get_block_commit(winning_block_txid, sortition_id)?
+            .ok_or(Error::DBError(db_error::NotFoundError))?;
+        let leader_key = sort_tx
+            .get_leader_key_at(
+                commit.key_block_ptr as u64,
+                commit.key_vtxindex as u32,
+                sortition_id,
+            )?
+            .ok_or(Error::DBError(db_error::NotFoundError))?;
+
+        let banned = BannedLeaderKey {
+            public_key: leader_key.public_key,
+            consensus_hash: consensus_hash.clone(),
+            anchored_block_hash: anchored_block_hash.clone(),
+            reason: reason.to_string(),
+            banned_at: get_epoch_time_secs(),
+        };
+        let newly_banned = StacksChainState::ban_leader_key(tx, &banned)?;
+        if newly_banned {
+            warn!(
+                "Banned leader key {} for producing invalid block {}/{}: {}",
+                &banned.public_key.to_hex(),
+                consensus_hash,
+                anchored_block_hash,
+                reason
+            );
+        }
+        Ok(newly_banned)
+    }
+
+    /// Is this leader key banned?
+    pub fn is_leader_key_banned(
+        conn: &Connection,
+        public_key: &VRFPublicKey,
+    ) -> Result<bool, Error> {
+        Ok(StacksChainState::get_banned_leader_key(conn, public_key)?.is_some())
+    }
+
+    /// Get the ban for a leader key, if it is banned
+    pub fn get_banned_leader_key(
+        conn: &Connection,
+        public_key: &VRFPublicKey,
+    ) -> Result<Option<BannedLeaderKey>, Error> {
+        let sql = "SELECT * FROM banned_leader_keys WHERE public_key = ?1";
+        let args: &[&dyn ToSql] = &[&public_key.to_hex()];
+        Ok(query_row(conn, sql, args)?)
+    }
+
+    /// Get all banned leader keys, oldest ban first
+    pub fn get_banned_leader_keys(conn: &Connection) -> Result<Vec<BannedLeaderKey>, Error> {
+        let sql = "SELECT * FROM banned_leader_keys ORDER BY banned_at, rowid";
+        Ok(query_rows(conn, sql, NO_PARAMS)?)
+    }
+
+    /// Lift the ban on a leader key.  Returns true if the key was banned.
+    pub fn clear_banned_leader_key(tx: &DBTx, public_key: &VRFPublicKey) -> Result<bool, Error> {
+        let sql = "DELETE FROM banned_leader_keys WHERE public_key = ?1";
+        let args: &[&dyn ToSql] = &[&public_key.to_hex()];
+        Ok(tx.execute(sql, args)? > 0)
+    }
+
+    /// Lift all leader key bans.  Returns the number of bans lifted.
+    pub fn clear_banned_leader_keys(tx: &DBTx) -> Result<usize, Error> {
+        Ok(tx.execute("DELETE FROM banned_leader_keys", NO_PARAMS)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::stacks::db::test::instantiate_chainstate;
+    use stacks_common::util::hash::hex_bytes;
+    use stacks_common::util::vrf::VRFPrivateKey;
+
+    #[test]
+    fn test_banned_leader_keys() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+        let key_1 = VRFPublicKey::from_bytes(
+            &hex_bytes("a366b51292bef4edd64063d9145c617fec373bceb0758e98cd72becd84d54c7a").unwrap(),
+        )
+        .unwrap();
+        let key_2 = VRFPublicKey::from_private(&VRFPrivateKey::new());
+
+        let ban_1 = BannedLeaderKey {
+            public_key: key_1.clone(),
+            consensus_hash: ConsensusHash([0x01; 20]),
+            anchored_block_hash: BlockHeaderHash([0x02; 32]),
+            reason: "bad block".to_string(),
+            banned_at: 1000,
+        };
+        let ban_2 = BannedLeaderKey {
+            public_key: key_2.clone(),
+            consensus_hash: ConsensusHash([0x03; 20]),
+            anchored_block_hash: BlockHeaderHash([0x04; 32]),
+            reason: "another bad block".to_string(),
+            banned_at: 2000,
+        };
+
+        let tx = chainstate.index_tx_begin().unwrap();
+        assert!(StacksChainState::ban_leader_key(&tx, &ban_2).unwrap());
+        assert!(StacksChainState::ban_leader_key(&tx, &ban_1).unwrap());
+
+        // a second ban of the same key keeps the first
+        let mut ban_1_again = ban_1.clone();
+        ban_1_again.reason = "yet another bad block".to_string();
+        ban_1_again.banned_at = 3000;
+        assert!(!StacksChainState::ban_leader_key(&tx, &ban_1_again).unwrap());
+        tx.commit().unwrap();
+
+        assert!(StacksChainState::is_leader_key_banned(chainstate.db(), &key_1).unwrap());
+        assert_eq!(
+            StacksChainState::get_banned_leader_key(chainstate.db(), &key_1).unwrap(),
+            Some(ban_1.clone())
+        );
+        assert_eq!(
+            StacksChainState::get_banned_leader_keys(chainstate.db()).unwrap(),
+            vec![ban_1.clone(), ban_2.clone()]
+        );
+
+        let tx = chainstate.index_tx_begin().unwrap();
+        assert!(StacksChainState::clear_banned_leader_key(&tx, &key_1).unwrap());
+        assert!(!StacksChainState::clear_banned_leader_key(&tx, &key_1).unwrap());
+        tx.commit().unwrap();
+
+        assert!(!StacksChainState::is_leader_key_banned(chainstate.db(), &key_1).unwrap());
+        assert_eq!(
+            StacksChainState::get_banned_leader_keys(chainstate.db()).unwrap(),
+            vec![ban_2]
+        );
+
+        let tx = chainstate.index_tx_begin().unwrap();
+        assert_eq!(StacksChainState::clear_banned_leader_keys(&tx).unwrap(), 1);
+        tx.commit().unwrap();
+        assert!(StacksChainState::get_banned_leader_keys(chainstate.db())
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -41,7 +41,12 @@ use crate::chainstate::burn::operations::*;
 use crate::chainstate::burn::BlockSnapshot;
 use crate::chainstate::stacks::address::PoxAddress;
 use crate::chainstate::stacks::db::accounts::MinerReward;
+use crate::chainstate::stacks::db::fee_rebates::FeeRebate;
+use crate::chainstate::stacks::db::preemption::BlockPreemption;
 use crate::chainstate::stacks::db::transactions::TransactionNonceMismatch;
+use crate::chainstate::stacks::db::validation_cache::{
+    BlockValidationContext, BlockValidationVerdict,
+};
 use crate::chainstate::stacks::db::*;
 use crate::chainstate::stacks::index::MarfTrieId;
 use crate::chainstate::stacks::Error;
@@ -62,6 +67,7 @@ use crate::net::relay::Relayer;
 use crate::net::BlocksInvData;
 use crate::net::Error as net_error;
 use crate::net::ExtendedStacksHeader;
+use crate::net::{BlockFeedEncoding, BlockFeedFrame, BlockFeedRequest, BlockFeedStart};
 use crate::util_lib::db::u64_to_sql;
 use crate::util_lib::db::Error as db_error;
 use crate::util_lib::db::{
@@ -90,11 +96,15 @@ use crate::chainstate::coordinator::BlockEventDispatcher;
 use crate::chainstate::stacks::address::StacksAddressExtensions;
 use crate::chainstate::stacks::StacksBlockHeader;
 use crate::chainstate::stacks::StacksMicroblockHeader;
-use crate::monitoring::{set_last_block_transaction_count, set_last_execution_cost_observed};
-use crate::util_lib::boot::boot_code_id;
+use crate::monitoring::{
+    increment_errors_counter, observe_processed_tx_size, set_last_block_size,
+    set_last_block_transaction_count, set_last_execution_cost_observed, set_last_marf_flush_time,
+};
+use crate::util_lib::boot::{boot_code_id, network_boot_code_id};
 use crate::{types, util};
 use stacks_common::types::chainstate::BurnchainHeaderHash;
 use stacks_common::types::chainstate::{StacksAddress, StacksBlockId};
+use stacks_common::util::ustx;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct StagingMicroblock {
@@ -135,6 +145,98 @@ pub struct StagingUserBurnSupport {
     pub vtxindex: u32,
 }
 
+/// A microblock in the tree of microblock streams built off of an anchored block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MicroblockForkNode {
+    pub microblock_hash: BlockHeaderHash,
+    /// the previous microblock, or the anchored block's hash for a stream's first microblock
+    pub parent_hash: BlockHeaderHash,
+    pub sequence: u16,
+    pub processed: bool,
+    pub orphaned: bool,
+    /// microblocks built directly on this one.  More than one means the miner equivocated.
+    pub children: Vec<BlockHeaderHash>,
+    /// index block hashes of the anchored children whose confirmed stream includes this microblock
+    pub confirmed_by: Vec<StacksBlockId>,
+}
+
+/// An anchored child of a block, and the prefix of its parent's microblock streams it confirmed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MicroblockForkConfirmation {
+    pub index_block_hash: StacksBlockId,
+    pub consensus_hash: ConsensusHash,
+    pub block_hash: BlockHeaderHash,
+    pub height: u64,
+    pub processed: bool,
+    pub orphaned: bool,
+    /// the last microblock the child confirmed, or None if it confirmed no microblocks
+    pub parent_microblock_hash: Option<BlockHeaderHash>,
+    pub parent_microblock_seq: u16,
+    /// whether or not `parent_microblock_hash` is a microblock this node has
+    pub tail_known: bool,
+}
+
+/// Every microblock stream known to have been built off of an anchored block -- including forks
+/// and orphaned microblocks -- and which anchored children confirmed which prefix of them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MicroblockForkTree {
+    pub index_block_hash: StacksBlockId,
+    pub consensus_hash: ConsensusHash,
+    pub block_hash: BlockHeaderHash,
+    /// microblocks whose parent is not known: the streams' first microblocks, as well as any
+    /// microblocks that arrived without their ancestors
+    pub roots: Vec<BlockHeaderHash>,
+    /// all known microblocks, ordered by sequence number and then by hash
+    pub microblocks: Vec<MicroblockForkNode>,
+    /// the microblocks (or the anchored block itself) that have more than one child
+    pub fork_points: Vec<BlockHeaderHash>,
+    /// the block's anchored children, ordered by height and then by index block hash
+    pub confirmations: Vec<MicroblockForkConfirmation>,
+}
+
+/// Why a block in the staging queue has not been processed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StagingBlockStatus {
+    /// the parent block has not arrived
+    MissingParent,
+    /// the parent block has arrived, but has not been processed yet
+    ParentNotProcessed,
+    /// the parent block was processed, but this block was never marked attachable.  Use
+    /// `StacksChainState::requeue_staging_block()` to reconsider it.
+    NotAttachable,
+    /// not all of the parent microblocks this block confirms have arrived
+    MissingMicroblocks,
+    /// the block will be considered the next time blocks are processed
+    Ready,
+    /// the block, or one of its ancestors, was invalid.  It will never be processed.
+    Invalid,
+}
+
+/// A block in the staging queue, and why it has not been processed yet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StagingQueueEntry {
+    pub index_block_hash: StacksBlockId,
+    pub consensus_hash: ConsensusHash,
+    pub anchored_block_hash: BlockHeaderHash,
+    pub parent_consensus_hash: ConsensusHash,
+    pub parent_anchored_block_hash: BlockHeaderHash,
+    /// the last parent microblock the block confirms, or None if it confirms none
+    pub parent_microblock_hash: Option<BlockHeaderHash>,
+    pub parent_microblock_seq: u16,
+    pub height: u64,
+    pub attachable: bool,
+    /// when the block was stored, in seconds since the epoch
+    pub arrival_time: u64,
+    /// how long the block has been queued, in seconds
+    pub age: u64,
+    pub status: StagingBlockStatus,
+}
+
+/// Invalid blocks are listed in the staging queue only if they are among this many of the most
+/// recently arrived invalid blocks
+pub const MAX_STAGING_QUEUE_INVALID_BLOCKS: u32 = 100;
+
 #[derive(Debug)]
 pub enum MemPoolRejection {
     SerializationFailure(codec_error),
@@ -166,6 +268,8 @@ pub enum MemPoolRejection {
     DBError(db_error),
     EstimatorError(EstimatorError),
     TemporarilyBlacklisted,
+    DisallowedAnchorMode(TransactionAnchorMode),
+    InvalidTransactionBundle(String),
     Other(String),
 }
 
@@ -186,6 +290,15 @@ pub struct SetupBlockResult<'a, 'b> {
     pub burn_delegate_stx_ops: Vec<DelegateStxOp>,
 }
 
+/// An already-processed Stacks block, re-executed to regenerate the receipts it produced
+#[derive(Debug, Clone)]
+pub struct ReplayedStacksBlock {
+    pub block: StacksBlock,
+    pub parent_index_hash: StacksBlockId,
+    pub winner_txid: Txid,
+    pub epoch_receipt: StacksEpochReceipt,
+}
+
 pub struct DummyEventDispatcher;
 
 impl BlockEventDispatcher for DummyEventDispatcher {
@@ -224,6 +337,20 @@ impl BlockEventDispatcher for DummyEventDispatcher {
             "We should never try to announce to the dummy dispatcher"
         );
     }
+
+    fn announce_sortition(
+        &self,
+        _snapshot: &BlockSnapshot,
+        _winning_commit: Option<&LeaderBlockCommitOp>,
+        _block_commits: &[LeaderBlockCommitOp],
+        _rewards: Vec<(PoxAddress, u64)>,
+        _burns: u64,
+    ) {
+        assert!(
+            false,
+            "We should never try to announce to the dummy dispatcher"
+        );
+    }
 }
 
 impl MemPoolRejection {
@@ -312,6 +439,13 @@ impl MemPoolRejection {
                 Some(json!({"message": e.to_string()})),
             ),
             TemporarilyBlacklisted => ("TemporarilyBlacklisted", None),
+            DisallowedAnchorMode(anchor_mode) => (
+                "DisallowedAnchorMode",
+                Some(json!({ "anchor_mode": anchor_mode })),
+            ),
+            InvalidTransactionBundle(s) => {
+                ("InvalidTransactionBundle", Some(json!({ "message": s })))
+            }
             Other(s) => ("ServerFailureOther", Some(json!({ "message": s }))),
         };
         let mut result = json!({
@@ -492,6 +626,73 @@ impl MicroblockStreamData {
     }
 }
 
+impl HeaderStreamData {
+    /// Start the page envelope (or the bare list) before the headers are sent
+    fn open_envelope(&mut self) {
+        self.envelope_bytes = if self.paged {
+            b"{\"results\":[".to_vec()
+        } else {
+            b"[".to_vec()
+        };
+        self.envelope_ptr = 0;
+    }
+
+    /// Finish the page envelope once the headers have been sent.  If the page stopped because it
+    /// was full, then the next page starts from the parent of the last header sent.
+    fn cork_envelope(&mut self) {
+        self.envelope_ptr = 0;
+        self.corked = true;
+        if !self.paged {
+            self.envelope_bytes = b"]".to_vec();
+            return;
+        }
+
+        let mut trailer = format!(
+            "],\"limit\":{},\"offset\":0,\"total\":{}",
+            self.limit, self.total
+        );
+        if self.num_headers == 0 && (self.limit as u64) < self.total {
+            trailer.push_str(&format!(",\"next_cursor\":\"{}\"", &self.index_block_hash));
+        }
+        trailer.push('}');
+
+        self.envelope_bytes = trailer.into_bytes();
+    }
+
+    /// Send as much of the page envelope as possible
+    fn stream_envelope<W: Write>(&mut self, fd: &mut W) -> Result<u64, Error> {
+        let mut num_written = 0;
+        while self.envelope_ptr < self.envelope_bytes.len() {
+            let num_sent = match fd.write(&self.envelope_bytes[self.envelope_ptr..]) {
+                Ok(0) => {
+                    // done (disconnected)
+                    return Ok(num_written);
+                }
+                Ok(n) => {
+                    self.envelope_ptr += n;
+                    n as u64
+                }
+                Err(e) => {
+                    if e.kind() == io::ErrorKind::Interrupted {
+                        // EINTR; try again
+                        continue;
+                    } else if e.kind() == io::ErrorKind::WouldBlock
+                        || (cfg!(windows) && e.kind() == io::ErrorKind::TimedOut)
+                    {
+                        // blocked
+                        return Ok(num_written);
+                    } else {
+                        return Err(Error::WriteError(e));
+                    }
+                }
+            };
+            num_written += num_sent;
+            self.total_bytes += num_sent;
+        }
+        Ok(num_written)
+    }
+}
+
 impl StreamCursor {
     pub fn new_block(index_block_hash: StacksBlockId) -> StreamCursor {
         StreamCursor::Block(BlockStreamData {
@@ -561,10 +762,13 @@ impl StreamCursor {
         }))
     }
 
+    /// Stream up to `num_headers_requested` headers, ending at `tip`.  If `paged` is set, then the
+    /// headers are sent as the `results` of an `RPCPage`; otherwise, as a bare list.
     pub fn new_headers(
         chainstate: &StacksChainState,
         tip: &StacksBlockId,
         num_headers_requested: u32,
+        paged: bool,
     ) -> Result<StreamCursor, Error> {
         let header_info = StacksChainState::load_staging_block_info(chainstate.db(), tip)?
             .ok_or(Error::NoSuchBlockError)?;
@@ -582,7 +786,12 @@ impl StreamCursor {
             offset: 0,
             total_bytes: 0,
             num_headers: num_headers,
+            paged: paged,
+            limit: num_headers_requested,
+            total: header_info.height,
             header_bytes: None,
+            envelope_bytes: vec![],
+            envelope_ptr: 0,
             end_of_stream: false,
             corked: false,
         }))
@@ -611,30 +820,59 @@ impl StreamCursor {
         })
     }
 
-    fn stream_one_byte<W: Write>(fd: &mut W, b: u8) -> Result<u64, Error> {
-        loop {
-            match fd.write(&[b]) {
-                Ok(0) => {
-                    // done (disconnected)
-                    return Ok(0);
-                }
-                Ok(n) => {
-                    return Ok(n as u64);
-                }
-                Err(e) => {
-                    if e.kind() == io::ErrorKind::Interrupted {
-                        // EINTR; try again
-                        continue;
-                    } else if e.kind() == io::ErrorKind::WouldBlock
-                        || (cfg!(windows) && e.kind() == io::ErrorKind::TimedOut)
-                    {
-                        // blocked
-                        return Ok(0);
-                    } else {
-                        return Err(Error::WriteError(e));
-                    }
-                }
+    pub fn new_block_feed(
+        chainstate: &StacksChainState,
+        request: &BlockFeedRequest,
+        tip: StacksBlockId,
+        tip_height: u64,
+        heartbeat_interval: u64,
+    ) -> Result<StreamCursor, Error> {
+        let (next_height, cursor) = match request.start {
+            // there's no block data for the boot block
+            BlockFeedStart::Height(height) => (cmp::max(height, 1), None),
+            BlockFeedStart::Cursor(ref index_block_hash) => {
+                let header_info =
+                    StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                        chainstate.db(),
+                        index_block_hash,
+                    )?
+                    .ok_or(Error::NoSuchBlockError)?;
+                (
+                    header_info.stacks_block_height + 1,
+                    Some(index_block_hash.clone()),
+                )
             }
+        };
+
+        Ok(StreamCursor::BlockFeed(BlockFeedStreamData {
+            next_height,
+            cursor,
+            tip,
+            tip_height,
+            encoding: request.encoding,
+            frame_buf: vec![],
+            frame_buf_ptr: 0,
+            num_blocks: 0,
+            max_blocks: request.limit,
+            heartbeat_interval,
+            last_frame_time: 0,
+        }))
+    }
+
+    /// Can this stream run out of data to send without being finished?
+    /// This is true of a block feed that is following the chain tip.
+    pub fn is_live(&self) -> bool {
+        match self {
+            StreamCursor::BlockFeed(ref stream) => !stream.is_finished(),
+            _ => false,
+        }
+    }
+
+    /// Tell a block feed about the current canonical Stacks chain tip.  No-op for other streams.
+    pub fn set_chain_tip(&mut self, tip: &StacksBlockId, tip_height: u64) {
+        if let StreamCursor::BlockFeed(ref mut stream) = self {
+            stream.tip = tip.clone();
+            stream.tip_height = tip_height;
         }
     }
 
@@ -643,8 +881,8 @@ impl StreamCursor {
             StreamCursor::Block(ref stream) => stream.offset(),
             StreamCursor::Microblocks(ref stream) => stream.offset(),
             StreamCursor::Headers(ref stream) => stream.offset(),
-            // no-op for mempool txs
-            StreamCursor::MempoolTxs(..) => 0,
+            // no-op for mempool txs and block feeds
+            StreamCursor::MempoolTxs(..) | StreamCursor::BlockFeed(..) => 0,
         }
     }
 
@@ -653,8 +891,8 @@ impl StreamCursor {
             StreamCursor::Block(ref mut stream) => stream.add_bytes(nw),
             StreamCursor::Microblocks(ref mut stream) => stream.add_bytes(nw),
             StreamCursor::Headers(ref mut stream) => stream.add_bytes(nw),
-            // no-op fo mempool txs
-            StreamCursor::MempoolTxs(..) => (),
+            // no-op fo mempool txs and block feeds
+            StreamCursor::MempoolTxs(..) | StreamCursor::BlockFeed(..) => (),
         }
     }
 
@@ -682,31 +920,39 @@ impl StreamCursor {
             }
             StreamCursor::MempoolTxs(ref mut tx_stream) => mempool.stream_txs(fd, tx_stream, count),
             StreamCursor::Headers(ref mut stream) => {
-                let mut num_written = 0;
-                if stream.total_bytes == 0 {
+                if stream.total_bytes == 0 && stream.envelope_bytes.is_empty() {
                     test_debug!("Opening header stream");
-                    let byte_written = StreamCursor::stream_one_byte(fd, '[' as u8)?;
-                    num_written += byte_written;
-                    stream.total_bytes += byte_written;
-                }
-                if stream.total_bytes > 0 {
-                    let mut sent = chainstate.stream_headers(fd, stream, count)?;
-
-                    if stream.end_of_stream && !stream.corked {
-                        // end of stream; cork it
-                        test_debug!("Corking header stream");
-                        let byte_written = StreamCursor::stream_one_byte(fd, ']' as u8)?;
-                        if byte_written > 0 {
-                            sent += byte_written;
-                            stream.total_bytes += byte_written;
-                            stream.corked = true;
-                        }
-                    }
-                    num_written += sent;
+                    stream.open_envelope();
+                }
+                let mut num_written = stream.stream_envelope(fd)?;
+                if stream.envelope_ptr < stream.envelope_bytes.len() || stream.corked {
+                    return Ok(num_written);
+                }
+                if !stream.end_of_stream {
+                    num_written += chainstate.stream_headers(fd, stream, count)?;
+                }
+                if stream.end_of_stream {
+                    // end of stream; cork it
+                    test_debug!("Corking header stream");
+                    stream.cork_envelope();
+                    num_written += stream.stream_envelope(fd)?;
                 }
                 Ok(num_written)
             }
             StreamCursor::Block(ref mut stream) => chainstate.stream_block(fd, stream, count),
+            StreamCursor::BlockFeed(ref mut stream) => {
+                chainstate.stream_block_feed(fd, stream, count)
+            }
+        }
+    }
+}
+
+impl BlockFeedStreamData {
+    /// Have we sent all the blocks that were asked for?
+    pub fn is_finished(&self) -> bool {
+        match self.max_blocks {
+            Some(max_blocks) => self.num_blocks >= max_blocks,
+            None => false,
         }
     }
 }
@@ -1508,21 +1754,22 @@ impl StacksChainState {
         parent_anchored_block_hash: &BlockHeaderHash,
         tip_microblock_hash: &BlockHeaderHash,
         processed_only: bool,
-    ) -> Result<Option<Vec<StacksMicroblock>>, Error> {
+    ) -> Result<Option<(Vec<StacksMicroblock>, Vec<Vec<u64>>)>, Error> {
         let mut ret = vec![];
+        let mut ret_tx_lens = vec![];
         let mut mblock_hash = tip_microblock_hash.clone();
         let mut last_seq = u16::MAX;
 
         loop {
-            let microblock =
+            let (microblock, tx_lens) =
                 match StacksChainState::load_staging_microblock_bytes(blocks_conn, &mblock_hash)? {
-                    Some(mblock_data) => StacksMicroblock::consensus_deserialize(
-                        &mut &mblock_data[..],
-                    )
-                    .expect(&format!(
-                        "CORRUPTION: failed to parse microblock data for {}/{}-{}",
-                        parent_consensus_hash, parent_anchored_block_hash, &mblock_hash,
-                    )),
+                    Some(mblock_data) => {
+                        StacksMicroblock::consensus_deserialize_with_tx_lens(&mut &mblock_data[..])
+                            .expect(&format!(
+                                "CORRUPTION: failed to parse microblock data for {}/{}-{}",
+                                parent_consensus_hash, parent_anchored_block_hash, &mblock_hash,
+                            ))
+                    }
                     None => {
                         test_debug!(
                             "No such microblock (processed={}): {}/{}-{} ({})",
@@ -1575,12 +1822,14 @@ impl StacksChainState {
             mblock_hash = microblock.header.prev_block.clone();
             last_seq = microblock.header.sequence;
             ret.push(microblock);
+            ret_tx_lens.push(tx_lens);
 
             if mblock_hash == *parent_anchored_block_hash {
                 break;
             }
         }
         ret.reverse();
+        ret_tx_lens.reverse();
 
         if ret.len() > 0 {
             // should start with 0
@@ -1591,7 +1840,7 @@ impl StacksChainState {
                 return Ok(None);
             }
         }
-        Ok(Some(ret))
+        Ok(Some((ret, ret_tx_lens)))
     }
 
     /// Load up a microblock stream fork, even if its microblocks blocks aren't processed.
@@ -1601,6 +1850,23 @@ impl StacksChainState {
         parent_anchored_block_hash: &BlockHeaderHash,
         tip_microblock_hash: &BlockHeaderHash,
     ) -> Result<Option<Vec<StacksMicroblock>>, Error> {
+        Ok(StacksChainState::load_microblock_stream_fork_with_tx_lens(
+            blocks_conn,
+            parent_consensus_hash,
+            parent_anchored_block_hash,
+            tip_microblock_hash,
+        )?
+        .map(|(microblocks, _)| microblocks))
+    }
+
+    /// Load up a microblock stream fork, even if its microblocks blocks aren't processed, along
+    /// with the encoded length of each transaction in each microblock.
+    pub fn load_microblock_stream_fork_with_tx_lens(
+        blocks_conn: &DBConn,
+        parent_consensus_hash: &ConsensusHash,
+        parent_anchored_block_hash: &BlockHeaderHash,
+        tip_microblock_hash: &BlockHeaderHash,
+    ) -> Result<Option<(Vec<StacksMicroblock>, Vec<Vec<u64>>)>, Error> {
         StacksChainState::inner_load_microblock_stream_fork(
             blocks_conn,
             parent_consensus_hash,
@@ -1617,13 +1883,14 @@ impl StacksChainState {
         parent_anchored_block_hash: &BlockHeaderHash,
         tip_microblock_hash: &BlockHeaderHash,
     ) -> Result<Option<Vec<StacksMicroblock>>, Error> {
-        StacksChainState::inner_load_microblock_stream_fork(
+        Ok(StacksChainState::inner_load_microblock_stream_fork(
             blocks_conn,
             parent_consensus_hash,
             parent_anchored_block_hash,
             tip_microblock_hash,
             true,
-        )
+        )?
+        .map(|(microblocks, _)| microblocks))
     }
 
     pub fn load_descendant_staging_microblock_stream(
@@ -2172,45 +2439,365 @@ impl StacksChainState {
             })
     }
 
-    /// Given an anchor block's index hash, does it confirm any microblocks?
-    /// Due to the way we process microblocks -- i.e. all microblocks between a parent/child anchor
-    /// block are processed atomically -- it is sufficient to check that there exists a microblock
-    /// that is the parent microblock of this block, and is processed.
-    /// Used for RPC where the tail hash isn't known.
-    pub fn has_processed_microblocks(
-        &self,
-        child_index_block_hash: &StacksBlockId,
+    /// Get the tree of every microblock stream this node has seen built off of the given anchored
+    /// block -- including forks caused by miner equivocation, and orphaned microblocks -- along
+    /// with which prefix of which stream each of the block's anchored children confirmed.
+    /// Returns None if the anchored block is not known.
+    ///
+    /// DO NOT USE IN CONSENSUS CODE.
+    pub fn get_microblock_fork_tree(
+        blocks_conn: &DBConn,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<MicroblockForkTree>, Error> {
+        let (consensus_hash, block_hash) = match StacksChainState::inner_get_block_header_hashes(
+            blocks_conn,
+            index_block_hash,
+            "consensus_hash",
+            "anchored_block_hash",
+        )? {
+            Some(x) => x,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let sql = "SELECT * FROM staging_microblocks WHERE index_block_hash = ?1 ORDER BY sequence, microblock_hash";
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        let staging_microblocks =
+            query_rows::<StagingMicroblock, _>(blocks_conn, sql, args).map_err(Error::DBError)?;
+
+        let sql = "SELECT * FROM staging_blocks WHERE parent_consensus_hash = ?1 AND parent_anchored_block_hash = ?2 ORDER BY height, index_block_hash";
+        let args: &[&dyn ToSql] = &[&consensus_hash, &block_hash];
+        let children =
+            query_rows::<StagingBlock, _>(blocks_conn, sql, args).map_err(Error::DBError)?;
+
+        let mut microblocks: Vec<MicroblockForkNode> = staging_microblocks
+            .into_iter()
+            .map(|mblock| MicroblockForkNode {
+                microblock_hash: mblock.microblock_hash,
+                parent_hash: mblock.parent_hash,
+                sequence: mblock.sequence,
+                processed: mblock.processed,
+                orphaned: mblock.orphaned,
+                children: vec![],
+                confirmed_by: vec![],
+            })
+            .collect();
+
+        let positions: HashMap<BlockHeaderHash, usize> = microblocks
+            .iter()
+            .enumerate()
+            .map(|(i, mblock)| (mblock.microblock_hash.clone(), i))
+            .collect();
+
+        let mut roots = vec![];
+        let mut num_first_microblocks = 0;
+        for i in 0..microblocks.len() {
+            let microblock_hash = microblocks[i].microblock_hash.clone();
+            if microblocks[i].parent_hash == block_hash {
+                num_first_microblocks += 1;
+            }
+            match positions.get(&microblocks[i].parent_hash) {
+                Some(parent_pos) => microblocks[*parent_pos].children.push(microblock_hash),
+                None => roots.push(microblock_hash),
+            }
+        }
+
+        let mut fork_points = vec![];
+        if num_first_microblocks > 1 {
+            fork_points.push(block_hash.clone());
+        }
+        for mblock in microblocks.iter() {
+            if mblock.children.len() > 1 {
+                fork_points.push(mblock.microblock_hash.clone());
+            }
+        }
+
+        let mut confirmations = vec![];
+        for child in children.into_iter() {
+            let child_index_block_hash = StacksBlockHeader::make_index_block_hash(
+                &child.consensus_hash,
+                &child.anchored_block_hash,
+            );
+            let parent_microblock_hash =
+                if child.parent_microblock_hash == EMPTY_MICROBLOCK_PARENT_HASH {
+                    None
+                } else {
+                    Some(child.parent_microblock_hash.clone())
+                };
+
+            // walk back from the confirmed tail to the start of its stream
+            let mut cursor = parent_microblock_hash
+                .as_ref()
+                .and_then(|tail| positions.get(tail).cloned());
+            let tail_known = cursor.is_some();
+            let mut steps = 0;
+            while let Some(pos) = cursor {
+                if steps >= microblocks.len() {
+                    return Err(Error::DBError(db_error::Corruption));
+                }
+                microblocks[pos]
+                    .confirmed_by
+                    .push(child_index_block_hash.clone());
+                cursor = positions.get(&microblocks[pos].parent_hash).cloned();
+                steps += 1;
+            }
+
+            confirmations.push(MicroblockForkConfirmation {
+                index_block_hash: child_index_block_hash,
+                consensus_hash: child.consensus_hash,
+                block_hash: child.anchored_block_hash,
+                height: child.height,
+                processed: child.processed,
+                orphaned: child.orphaned,
+                parent_microblock_hash,
+                parent_microblock_seq: child.parent_microblock_seq,
+                tail_known,
+            });
+        }
+
+        Ok(Some(MicroblockForkTree {
+            index_block_hash: index_block_hash.clone(),
+            consensus_hash,
+            block_hash,
+            roots,
+            microblocks,
+            fork_points,
+            confirmations,
+        }))
+    }
+
+    /// Do we have every parent microblock that the given staging block confirms?  Only checks the
+    /// staging microblock index; doesn't load any microblock data.
+    fn has_parent_microblock_stream_indexed(
+        blocks_conn: &DBConn,
+        staging_block: &StagingBlock,
     ) -> Result<bool, Error> {
-        let (parent_consensus_hash, parent_block_hash) =
-            match StacksChainState::get_parent_block_header_hashes(
-                &self.db(),
-                &child_index_block_hash,
+        if staging_block.parent_microblock_hash == EMPTY_MICROBLOCK_PARENT_HASH
+            && staging_block.parent_microblock_seq == 0
+        {
+            return Ok(true);
+        }
+
+        let parent_index_block_hash = StacksBlockHeader::make_index_block_hash(
+            &staging_block.parent_consensus_hash,
+            &staging_block.parent_anchored_block_hash,
+        );
+
+        // walk back from the tail to the start of the stream
+        let mut microblock_hash = staging_block.parent_microblock_hash.clone();
+        let mut expected_seq = staging_block.parent_microblock_seq;
+        loop {
+            let mblock_info = match StacksChainState::load_staging_microblock_info(
+                blocks_conn,
+                &parent_index_block_hash,
+                &microblock_hash,
             )? {
-                Some(x) => x,
+                Some(info) => info,
                 None => {
-                    // no parent stored, so no confirmed microblocks
                     return Ok(false);
                 }
             };
+            if mblock_info.sequence != expected_seq {
+                return Ok(false);
+            }
+            if expected_seq == 0 {
+                return Ok(mblock_info.parent_hash == staging_block.parent_anchored_block_hash);
+            }
+            microblock_hash = mblock_info.parent_hash;
+            expected_seq -= 1;
+        }
+    }
 
-        let parent_index_block_hash =
-            StacksBlockHeader::make_index_block_hash(&parent_consensus_hash, &parent_block_hash);
+    /// Determine why a staging block has not been processed yet
+    fn get_staging_block_queue_status(
+        blocks_conn: &DBConn,
+        staging_block: &StagingBlock,
+    ) -> Result<StagingBlockStatus, Error> {
+        if staging_block.orphaned {
+            return Ok(StagingBlockStatus::Invalid);
+        }
 
-        let parent_microblock_hash =
-            match StacksChainState::get_staging_block_parent_microblock_hash(
-                &self.db(),
-                child_index_block_hash,
+        if !staging_block.is_first_mined() {
+            match StacksChainState::get_staging_block_status(
+                blocks_conn,
+                &staging_block.parent_consensus_hash,
+                &staging_block.parent_anchored_block_hash,
             )? {
-                Some(x) => x,
+                Some(true) => {}
+                Some(false) => {
+                    return Ok(StagingBlockStatus::ParentNotProcessed);
+                }
                 None => {
-                    // no header record for this block, so it cannot have confirmed anything
-                    return Ok(false);
+                    // the parent may have been processed without passing through staging
+                    let parent_index_block_hash = StacksBlockHeader::make_index_block_hash(
+                        &staging_block.parent_consensus_hash,
+                        &staging_block.parent_anchored_block_hash,
+                    );
+                    if StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                        blocks_conn,
+                        &parent_index_block_hash,
+                    )?
+                    .is_none()
+                    {
+                        return Ok(StagingBlockStatus::MissingParent);
+                    }
                 }
-            };
+            }
+        }
 
-        let sql = "SELECT 1 FROM staging_microblocks WHERE index_block_hash = ?1 AND microblock_hash = ?2 AND processed = 1 AND orphaned = 0";
-        let args: &[&dyn ToSql] = &[&parent_index_block_hash, &parent_microblock_hash];
-        let res = self
+        if !staging_block.attachable {
+            return Ok(StagingBlockStatus::NotAttachable);
+        }
+
+        if !StacksChainState::has_parent_microblock_stream_indexed(blocks_conn, staging_block)? {
+            return Ok(StagingBlockStatus::MissingMicroblocks);
+        }
+
+        Ok(StagingBlockStatus::Ready)
+    }
+
+    /// Get the blocks in the staging queue -- every block that has been stored but not processed,
+    /// as well as the most recent invalid blocks -- along with why each one has not been
+    /// processed.  Entries are ordered by height, and then by arrival time.
+    /// `now` is the current time in seconds since the epoch, and is used to calculate each
+    /// entry's age.
+    ///
+    /// DO NOT USE IN CONSENSUS CODE.
+    pub fn get_staging_queue(
+        blocks_conn: &DBConn,
+        now: u64,
+    ) -> Result<Vec<StagingQueueEntry>, Error> {
+        let sql = "SELECT * FROM staging_blocks WHERE processed = 0 AND orphaned = 0
+                   UNION ALL
+                   SELECT * FROM (SELECT * FROM staging_blocks WHERE orphaned = 1 ORDER BY arrival_time DESC LIMIT ?1)
+                   ORDER BY height, arrival_time";
+        let args: &[&dyn ToSql] = &[&MAX_STAGING_QUEUE_INVALID_BLOCKS];
+
+        let mut stmt = blocks_conn
+            .prepare(sql)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+        let mut rows = stmt
+            .query(args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+
+        let mut queued = vec![];
+        while let Some(row) = rows.next().map_err(|e| db_error::SqliteError(e))? {
+            let staging_block = StagingBlock::from_row(&row).map_err(Error::DBError)?;
+            let arrival_time = u64::from_column(&row, "arrival_time").map_err(Error::DBError)?;
+            queued.push((staging_block, arrival_time));
+        }
+
+        let mut entries = Vec::with_capacity(queued.len());
+        for (staging_block, arrival_time) in queued.into_iter() {
+            let status =
+                StacksChainState::get_staging_block_queue_status(blocks_conn, &staging_block)?;
+            entries.push(StagingQueueEntry {
+                index_block_hash: StacksBlockHeader::make_index_block_hash(
+                    &staging_block.consensus_hash,
+                    &staging_block.anchored_block_hash,
+                ),
+                parent_microblock_hash: if staging_block.parent_microblock_hash
+                    == EMPTY_MICROBLOCK_PARENT_HASH
+                {
+                    None
+                } else {
+                    Some(staging_block.parent_microblock_hash.clone())
+                },
+                consensus_hash: staging_block.consensus_hash,
+                anchored_block_hash: staging_block.anchored_block_hash,
+                parent_consensus_hash: staging_block.parent_consensus_hash,
+                parent_anchored_block_hash: staging_block.parent_anchored_block_hash,
+                parent_microblock_seq: staging_block.parent_microblock_seq,
+                height: staging_block.height,
+                attachable: staging_block.attachable,
+                arrival_time,
+                age: now.saturating_sub(arrival_time),
+                status,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Force an unprocessed staging block to be reconsidered for processing.  This marks it as
+    /// attachable if its parent has been processed, which repairs a block that is stuck with
+    /// the `NotAttachable` status.  Blocks that are missing their parent or parent microblocks
+    /// are left as-is, since they cannot be processed until the data arrives; invalid blocks cannot
+    /// be requeued, since their data has been deleted.
+    /// Returns the block's status after requeuing it, or None if it is not in the staging queue.
+    pub fn requeue_staging_block(
+        tx: &DBTx,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<StagingBlockStatus>, Error> {
+        let sql = "SELECT * FROM staging_blocks WHERE index_block_hash = ?1 AND processed = 0";
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        let mut staging_block =
+            match query_row::<StagingBlock, _>(tx, sql, args).map_err(Error::DBError)? {
+                Some(staging_block) => staging_block,
+                None => {
+                    return Ok(None);
+                }
+            };
+
+        let status = StacksChainState::get_staging_block_queue_status(tx, &staging_block)?;
+        if status != StagingBlockStatus::NotAttachable {
+            return Ok(Some(status));
+        }
+
+        info!(
+            "Requeue staging block {}/{} ({})",
+            &staging_block.consensus_hash, &staging_block.anchored_block_hash, index_block_hash
+        );
+        tx.execute(
+            "UPDATE staging_blocks SET attachable = 1 WHERE index_block_hash = ?1",
+            args,
+        )
+        .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+
+        staging_block.attachable = true;
+        let status = StacksChainState::get_staging_block_queue_status(tx, &staging_block)?;
+        Ok(Some(status))
+    }
+
+    /// Given an anchor block's index hash, does it confirm any microblocks?
+    /// Due to the way we process microblocks -- i.e. all microblocks between a parent/child anchor
+    /// block are processed atomically -- it is sufficient to check that there exists a microblock
+    /// that is the parent microblock of this block, and is processed.
+    /// Used for RPC where the tail hash isn't known.
+    pub fn has_processed_microblocks(
+        &self,
+        child_index_block_hash: &StacksBlockId,
+    ) -> Result<bool, Error> {
+        let (parent_consensus_hash, parent_block_hash) =
+            match StacksChainState::get_parent_block_header_hashes(
+                &self.db(),
+                &child_index_block_hash,
+            )? {
+                Some(x) => x,
+                None => {
+                    // no parent stored, so no confirmed microblocks
+                    return Ok(false);
+                }
+            };
+
+        let parent_index_block_hash =
+            StacksBlockHeader::make_index_block_hash(&parent_consensus_hash, &parent_block_hash);
+
+        let parent_microblock_hash =
+            match StacksChainState::get_staging_block_parent_microblock_hash(
+                &self.db(),
+                child_index_block_hash,
+            )? {
+                Some(x) => x,
+                None => {
+                    // no header record for this block, so it cannot have confirmed anything
+                    return Ok(false);
+                }
+            };
+
+        let sql = "SELECT 1 FROM staging_microblocks WHERE index_block_hash = ?1 AND microblock_hash = ?2 AND processed = 1 AND orphaned = 0";
+        let args: &[&dyn ToSql] = &[&parent_index_block_hash, &parent_microblock_hash];
+        let res = self
             .db()
             .query_row(sql, args, |_r| Ok(()))
             .optional()
@@ -3174,6 +3761,18 @@ impl StacksChainState {
         .and_then(|processed| Ok(processed.len() > 0))
     }
 
+    /// How many microblocks do we have stored in any capacity (processed, unprocessed, or
+    /// orphaned), given their parent anchored block's index block hash?
+    pub fn count_microblocks_indexed(
+        blocks_conn: &DBConn,
+        parent_index_block_hash: &StacksBlockId,
+    ) -> Result<u64, Error> {
+        let sql = "SELECT COUNT(*) FROM staging_microblocks WHERE index_block_hash = ?1";
+        let args: &[&dyn ToSql] = &[parent_index_block_hash];
+        let cnt = query_count(blocks_conn, sql, args).map_err(Error::DBError)?;
+        Ok(cnt as u64)
+    }
+
     /// Given an index block hash, get the consensus hash and block hash
     fn inner_get_block_header_hashes(
         blocks_db: &DBConn,
@@ -3638,6 +4237,170 @@ impl StacksChainState {
         StacksChainState::stream_data_from_chunk_store(&self.blocks_path, fd, stream, count)
     }
 
+    /// If a block feed's cursor is no longer on the canonical fork (because of a reorg, or because
+    /// the client resumed from a block on some other fork), find the highest ancestor of the
+    /// cursor that is.  Returns the rollback frame to send, and rewinds the stream to that block.
+    /// Returns None if the cursor is canonical (or there is no cursor).
+    fn check_block_feed_fork(
+        &self,
+        stream: &mut BlockFeedStreamData,
+    ) -> Result<Option<BlockFeedFrame>, Error> {
+        let from = match stream.cursor {
+            Some(cursor) => cursor,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let index_conn = self.index_conn()?;
+        let mut cursor = Some(from);
+        let mut height = stream.next_height.saturating_sub(1);
+        while let Some(block_id) = cursor.take() {
+            if height <= stream.tip_height {
+                match index_conn.get_ancestor_block_hash(height, &stream.tip)? {
+                    Some(ancestor) if ancestor == block_id => {
+                        cursor = Some(block_id);
+                        break;
+                    }
+                    Some(_) => {}
+                    None => {
+                        // chain tip isn't known to the index yet, so we can't tell
+                        return Ok(None);
+                    }
+                }
+            }
+            cursor = StacksChainState::get_parent_block_id(self.db(), &block_id)?;
+            height = height.saturating_sub(1);
+        }
+
+        if cursor.as_ref() == Some(&from) {
+            return Ok(None);
+        }
+
+        // the boot block has no block data, so if the forks share nothing else, start over at
+        // height 1
+        let height = if cursor.is_some() { height } else { 0 };
+        debug!(
+            "Block feed cursor {} is not on the canonical fork of {}; rolling back to {:?} (height {})",
+            &from, &stream.tip, &cursor, height
+        );
+        stream.cursor = cursor;
+        stream.next_height = height + 1;
+        Ok(Some(BlockFeedFrame::Rollback {
+            from,
+            cursor,
+            height,
+        }))
+    }
+
+    /// Load the next frame of a block feed into its frame buffer: a rollback if the last block
+    /// sent has fallen off the canonical fork, the next block on the canonical fork if we have
+    /// it, or a heartbeat if one is due.
+    /// Returns false if there's nothing to send right now.
+    fn load_next_block_feed_frame(&self, stream: &mut BlockFeedStreamData) -> Result<bool, Error> {
+        if stream.is_finished() {
+            return Ok(false);
+        }
+
+        let now = get_epoch_time_secs();
+        let frame = if let Some(rollback) = self.check_block_feed_fork(stream)? {
+            rollback
+        } else if stream.next_height <= stream.tip_height {
+            let index_block_hash = match self
+                .index_conn()?
+                .get_ancestor_block_hash(stream.next_height, &stream.tip)?
+            {
+                Some(index_block_hash) => index_block_hash,
+                None => {
+                    // chain tip isn't known to the index yet
+                    return Ok(false);
+                }
+            };
+            let header_info = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                self.db(),
+                &index_block_hash,
+            )?
+            .ok_or(Error::NoSuchBlockError)?;
+            let parent_block_id =
+                StacksChainState::get_parent_block_id(self.db(), &index_block_hash)?
+                    .ok_or(Error::NoSuchBlockError)?;
+            let block = StacksChainState::load_block(
+                &self.blocks_path,
+                &header_info.consensus_hash,
+                &header_info.anchored_header.block_hash(),
+            )?
+            .ok_or(Error::NoSuchBlockError)?;
+
+            stream.next_height += 1;
+            stream.num_blocks += 1;
+            stream.cursor = Some(index_block_hash);
+            BlockFeedFrame::Block {
+                cursor: index_block_hash,
+                height: header_info.stacks_block_height,
+                consensus_hash: header_info.consensus_hash,
+                parent_block_id,
+                block,
+            }
+        } else if stream.last_frame_time + stream.heartbeat_interval <= now {
+            BlockFeedFrame::Heartbeat {
+                cursor: stream.cursor.clone(),
+                tip_height: stream.tip_height,
+            }
+        } else {
+            return Ok(false);
+        };
+
+        stream.frame_buf.clear();
+        stream.frame_buf_ptr = 0;
+        match stream.encoding {
+            BlockFeedEncoding::Binary => {
+                frame
+                    .consensus_serialize(&mut stream.frame_buf)
+                    .map_err(Error::CodecError)?;
+            }
+            BlockFeedEncoding::Json => {
+                serde_json::to_writer(&mut stream.frame_buf, &frame).map_err(|e| {
+                    Error::WriteError(io::Error::new(io::ErrorKind::Other, e.to_string()))
+                })?;
+                stream.frame_buf.push(b'\n');
+            }
+        }
+        stream.last_frame_time = now;
+        Ok(true)
+    }
+
+    /// Stream a block feed: blocks on the canonical fork starting from the stream's next height,
+    /// and then heartbeats while we wait for the chain tip to advance.
+    /// Returns the number of bytes written, which can be 0 if there's nothing to send yet.
+    pub fn stream_block_feed<W: Write>(
+        &self,
+        fd: &mut W,
+        stream: &mut BlockFeedStreamData,
+        count: u64,
+    ) -> Result<u64, Error> {
+        let mut num_written = 0;
+        while num_written < count {
+            if stream.frame_buf_ptr >= stream.frame_buf.len() {
+                if !self.load_next_block_feed_frame(stream)? {
+                    break;
+                }
+            }
+
+            // write out buffered frame
+            let start = stream.frame_buf_ptr;
+            let end = cmp::min(
+                stream.frame_buf.len(),
+                ((start as u64) + (count - num_written)) as usize,
+            );
+            fd.write_all(&stream.frame_buf[start..end])
+                .map_err(Error::WriteError)?;
+
+            stream.frame_buf_ptr = end;
+            num_written += end.saturating_sub(start) as u64;
+        }
+        Ok(num_written)
+    }
+
     /// Stream unconfirmed microblocks from the staging DB.  Pull only from the staging DB.
     /// Returns the number of bytes written, and updates `stream` to point to the next point to
     /// read.  Wrties the bytes streamed to `fd`.
@@ -4170,8 +4933,8 @@ impl StacksChainState {
 
         let sort_handle = SortitionHandleConn::open_reader_consensus(sort_ic, consensus_hash)?;
 
-        let mainnet = self.mainnet;
-        let chain_id = self.chain_id;
+        let mainnet = self.network.mainnet;
+        let chain_id = self.network.chain_id;
         let blocks_path = self.blocks_path.clone();
 
         // optimistic check (before opening a tx): already in queue or already processed?
@@ -4184,7 +4947,7 @@ impl StacksChainState {
             return Ok(false);
         }
 
-        let mut block_tx = self.db_tx_begin()?;
+        let mut block_tx = self.write_tx_begin()?;
 
         // already in queue or already processed (within the tx; things might have changed)
         if StacksChainState::has_anchored_block(&block_tx, &blocks_path, consensus_hash, block)? {
@@ -4314,11 +5077,11 @@ impl StacksChainState {
             return Ok(false);
         }
 
-        let mainnet = self.mainnet;
-        let chain_id = self.chain_id;
+        let mainnet = self.network.mainnet;
+        let chain_id = self.network.chain_id;
         let blocks_path = self.blocks_path.clone();
 
-        let mut blocks_tx = self.db_tx_begin()?;
+        let mut blocks_tx = self.write_tx_begin()?;
 
         let pubkey_hash = if let Some(pubkh) = StacksChainState::load_block_pubkey_hash(
             &blocks_tx,
@@ -4438,7 +5201,8 @@ impl StacksChainState {
             125
         };
 
-        stx_reward * (MICROSTACKS_PER_STACKS as u128)
+        ustx::checked_mul(stx_reward, MICROSTACKS_PER_STACKS as u128)
+            .expect("FATAL: STX coinbase reward overflow")
     }
 
     /// Create the block reward.
@@ -4511,22 +5275,37 @@ impl StacksChainState {
         blocks_conn: &DBConn,
         staging_block: &StagingBlock,
     ) -> Result<Option<Vec<StacksMicroblock>>, Error> {
+        Ok(
+            StacksChainState::find_parent_microblock_stream_with_tx_lens(
+                blocks_conn,
+                staging_block,
+            )?
+            .map(|(microblocks, _)| microblocks),
+        )
+    }
+
+    /// Like `find_parent_microblock_stream()`, but also return the encoded length of each
+    /// transaction in each microblock.
+    pub fn find_parent_microblock_stream_with_tx_lens(
+        blocks_conn: &DBConn,
+        staging_block: &StagingBlock,
+    ) -> Result<Option<(Vec<StacksMicroblock>, Vec<Vec<u64>>)>, Error> {
         if staging_block.parent_microblock_hash == EMPTY_MICROBLOCK_PARENT_HASH
             && staging_block.parent_microblock_seq == 0
         {
             // no parent microblocks, ever
-            return Ok(Some(vec![]));
+            return Ok(Some((vec![], vec![])));
         }
 
         // find the microblock stream fork that this block confirms
-        match StacksChainState::load_microblock_stream_fork(
+        match StacksChainState::load_microblock_stream_fork_with_tx_lens(
             blocks_conn,
             &staging_block.parent_consensus_hash,
             &staging_block.parent_anchored_block_hash,
             &staging_block.parent_microblock_hash,
         )? {
-            Some(microblocks) => {
-                return Ok(Some(microblocks));
+            Some(microblocks_and_tx_lens) => {
+                return Ok(Some(microblocks_and_tx_lens));
             }
             None => {
                 // parent microblocks haven't arrived yet, or there are none
@@ -4641,13 +5420,16 @@ impl StacksChainState {
 
     /// Given access to the chain state (headers) and the staging blocks, find a staging block we
     /// can process, as well as its parent microblocks that it confirms
-    /// Returns Some(microblocks, staging block) if we found a sequence of blocks to process.
+    /// Returns Some(microblocks, microblock tx lengths, staging block) if we found a sequence of
+    /// blocks to process.
     /// Returns None if not.
+    /// If `preferred` is given and that block can be processed, it is chosen first.
     fn find_next_staging_block<'a>(
         blocks_tx: &mut StacksDBTx<'a>,
         blocks_path: &str,
         sort_tx: &mut SortitionHandleTx,
-    ) -> Result<Option<(Vec<StacksMicroblock>, StagingBlock)>, Error> {
+        preferred: Option<&StacksBlockId>,
+    ) -> Result<Option<(Vec<StacksMicroblock>, Vec<Vec<u64>>, StagingBlock)>, Error> {
         test_debug!("Find next staging block");
 
         let mut to_delete = vec![];
@@ -4658,13 +5440,18 @@ impl StacksChainState {
             // go through staging blocks and see if any of them match headers, are attachable, and are
             // recent (i.e. less than 10 minutes old)
             // pick randomly -- don't allow the network sender to choose the processing order!
-            let sql = "SELECT * FROM staging_blocks WHERE processed = 0 AND attachable = 1 AND orphaned = 0 ORDER BY RANDOM()".to_string();
+            // The only exception is a block that preempted the last block we processed (see
+            // `BlockPreemption`), which goes first.
+            let (sql, args): (&str, Vec<&dyn ToSql>) = match preferred {
+                Some(block_id) => ("SELECT * FROM staging_blocks WHERE processed = 0 AND attachable = 1 AND orphaned = 0 ORDER BY index_block_hash = ?1 DESC, RANDOM()", vec![block_id]),
+                None => ("SELECT * FROM staging_blocks WHERE processed = 0 AND attachable = 1 AND orphaned = 0 ORDER BY RANDOM()", vec![]),
+            };
             let mut stmt = blocks_tx
-                .prepare(&sql)
+                .prepare(sql)
                 .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
 
             let mut rows = stmt
-                .query(NO_PARAMS)
+                .query(args.as_slice())
                 .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
 
             while let Some(row) = rows.next().map_err(|e| db_error::SqliteError(e))? {
@@ -4781,9 +5568,15 @@ impl StacksChainState {
                     };
 
                     // find its microblock parent stream
-                    match StacksChainState::find_parent_microblock_stream(blocks_tx, &candidate)? {
-                        Some(parent_staging_microblocks) => {
-                            return Ok(Some((parent_staging_microblocks, candidate)));
+                    match StacksChainState::find_parent_microblock_stream_with_tx_lens(
+                        blocks_tx, &candidate,
+                    )? {
+                        Some((parent_staging_microblocks, parent_microblock_tx_lens)) => {
+                            return Ok(Some((
+                                parent_staging_microblocks,
+                                parent_microblock_tx_lens,
+                                candidate,
+                            )));
                         }
                         None => {
                             // no microblock data yet, so we can't process this block
@@ -4818,17 +5611,23 @@ impl StacksChainState {
     }
 
     /// Process a stream of microblocks
+    /// `microblock_tx_lens` holds the encoded length of each microblock's transactions, if known.
     /// Return the fees and burns.
     pub fn process_microblocks_transactions(
         clarity_tx: &mut ClarityTx,
         microblocks: &[StacksMicroblock],
+        microblock_tx_lens: &[Vec<u64>],
         ast_rules: ASTRules,
     ) -> Result<(u128, u128, Vec<StacksTransactionReceipt>), (Error, BlockHeaderHash)> {
         let mut fees = 0u128;
         let mut burns = 0u128;
         let mut receipts = vec![];
-        for microblock in microblocks.iter() {
+        for (i, microblock) in microblocks.iter().enumerate() {
             debug!("Process microblock {}", &microblock.block_hash());
+            let tx_lens = microblock_tx_lens
+                .get(i)
+                .map(|lens| &lens[..])
+                .unwrap_or(&[]);
             for (tx_index, tx) in microblock.txs.iter().enumerate() {
                 let (tx_fee, mut tx_receipt) =
                     StacksChainState::process_transaction(clarity_tx, tx, false, ast_rules)
@@ -4836,6 +5635,7 @@ impl StacksChainState {
 
                 tx_receipt.microblock_header = Some(microblock.header.clone());
                 tx_receipt.tx_index = tx_index as u32;
+                tx_receipt.tx_size = tx_lens.get(tx_index).copied().unwrap_or(0);
                 fees = fees.checked_add(tx_fee as u128).expect("Fee overflow");
                 burns = burns
                     .checked_add(tx_receipt.stx_burned as u128)
@@ -4998,7 +5798,7 @@ impl StacksChainState {
         active_pox_contract: &str,
     ) -> Vec<StacksTransactionReceipt> {
         let mut all_receipts = vec![];
-        let mainnet = clarity_tx.config.mainnet;
+        let network = clarity_tx.network();
         let cost_so_far = clarity_tx.cost_so_far();
         for stack_stx_op in operations.into_iter() {
             let StackStxOp {
@@ -5015,7 +5815,7 @@ impl StacksChainState {
                 tx.run_contract_call(
                     &sender.clone().into(),
                     None,
-                    &boot_code_id(active_pox_contract, mainnet),
+                    &network_boot_code_id(active_pox_contract, &network),
                     "stack-stx",
                     &[
                         Value::UInt(*stacked_ustx),
@@ -5059,7 +5859,9 @@ impl StacksChainState {
                             execution_cost,
                             microblock_header: None,
                             tx_index: 0,
+                            tx_size: 0,
                             vm_error: None,
+                            vm_error_code: None,
                         };
 
                         all_receipts.push(receipt);
@@ -5123,7 +5925,9 @@ impl StacksChainState {
                                     execution_cost: ExecutionCost::zero(),
                                     microblock_header: None,
                                     tx_index: 0,
+                                    tx_size: 0,
                                     vm_error: None,
+                                    vm_error_code: None,
                                 })
                             }
                             Err(e) => {
@@ -5150,7 +5954,7 @@ impl StacksChainState {
         active_pox_contract: &str,
     ) -> Vec<StacksTransactionReceipt> {
         let mut all_receipts = vec![];
-        let mainnet = clarity_tx.config.mainnet;
+        let network = clarity_tx.network();
         let cost_so_far = clarity_tx.cost_so_far();
         for delegate_stx_op in operations.into_iter() {
             let DelegateStxOp {
@@ -5188,7 +5992,7 @@ impl StacksChainState {
                 tx.run_contract_call(
                     &sender.clone().into(),
                     None,
-                    &boot_code_id(active_pox_contract, mainnet),
+                    &network_boot_code_id(active_pox_contract, &network),
                     "delegate-stx",
                     &[
                         Value::UInt(*delegated_ustx),
@@ -5229,7 +6033,9 @@ impl StacksChainState {
                             execution_cost,
                             microblock_header: None,
                             tx_index: 0,
+                            tx_size: 0,
                             vm_error: None,
+                            vm_error_code: None,
                         };
 
                         all_receipts.push(receipt);
@@ -5253,20 +6059,28 @@ impl StacksChainState {
 
     /// Process a single anchored block.
     /// Return the fees and burns.
+    /// If `preemption` is given, then stop with Error::BlockProcessingPreempted as soon as the
+    /// block is preempted.
     fn process_block_transactions(
         clarity_tx: &mut ClarityTx,
         block: &StacksBlock,
+        block_tx_lens: &[u64],
         mut tx_index: u32,
         ast_rules: ASTRules,
+        preemption: Option<&BlockPreemption>,
     ) -> Result<(u128, u128, Vec<StacksTransactionReceipt>), Error> {
         let mut fees = 0u128;
         let mut burns = 0u128;
         let mut receipts = vec![];
-        for tx in block.txs.iter() {
+        for (i, tx) in block.txs.iter().enumerate() {
+            if matches!(preemption, Some(p) if p.is_preempted()) {
+                return Err(Error::BlockProcessingPreempted);
+            }
             let (tx_fee, mut tx_receipt) =
                 StacksChainState::process_transaction(clarity_tx, tx, false, ast_rules)?;
             fees = fees.checked_add(tx_fee as u128).expect("Fee overflow");
             tx_receipt.tx_index = tx_index;
+            tx_receipt.tx_size = block_tx_lens.get(i).copied().unwrap_or(0);
             burns = burns
                 .checked_add(tx_receipt.stx_burned as u128)
                 .expect("Burns overflow");
@@ -5350,8 +6164,8 @@ impl StacksChainState {
     pub fn process_stx_unlocks<'a, 'b>(
         clarity_tx: &mut ClarityTx<'a, 'b>,
     ) -> Result<(u128, Vec<StacksTransactionEvent>), Error> {
-        let mainnet = clarity_tx.config.mainnet;
-        let lockup_contract_id = boot_code_id("lockup", mainnet);
+        let network = clarity_tx.network();
+        let lockup_contract_id = network_boot_code_id("lockup", &network);
         clarity_tx
             .connection()
             .as_transaction(|tx_connection| {
@@ -5697,6 +6511,7 @@ impl StacksChainState {
         parent_consensus_hash: ConsensusHash,
         parent_header_hash: BlockHeaderHash,
         parent_microblocks: &Vec<StacksMicroblock>,
+        parent_microblock_tx_lens: &[Vec<u64>],
         mainnet: bool,
         miner_id_opt: Option<usize>,
     ) -> Result<SetupBlockResult<'a, 'b>, Error> {
@@ -5807,6 +6622,7 @@ impl StacksChainState {
             match StacksChainState::process_microblocks_transactions(
                 &mut clarity_tx,
                 &parent_microblocks,
+                parent_microblock_tx_lens,
                 microblock_ast_rules,
             ) {
                 Ok((fees, burns, events)) => (fees, burns, events),
@@ -6022,11 +6838,15 @@ impl StacksChainState {
         chain_tip_burn_header_timestamp: u64,
         block: &StacksBlock,
         block_size: u64,
+        block_tx_lens: &[u64],
         microblocks: &Vec<StacksMicroblock>, // parent microblocks
+        microblock_tx_lens: &[Vec<u64>],
         burnchain_commit_burn: u64,
         burnchain_sortition_burn: u64,
         user_burns: &[StagingUserBurnSupport],
         affirmation_weight: u64,
+        preemption: &BlockPreemption,
+        fee_rebate_accounting: bool,
     ) -> Result<(StacksEpochReceipt, PreCommitClarityBlock<'a>), Error> {
         debug!(
             "Process block {:?} with {} transactions",
@@ -6140,6 +6960,7 @@ impl StacksChainState {
             parent_consensus_hash,
             parent_block_hash,
             microblocks,
+            microblock_tx_lens,
             mainnet,
             None,
         )?;
@@ -6232,9 +7053,16 @@ impl StacksChainState {
                 match StacksChainState::process_block_transactions(
                     &mut clarity_tx,
                     &block,
+                    block_tx_lens,
                     microblock_txs_receipts.len() as u32,
                     ast_rules,
+                    Some(preemption),
                 ) {
+                    Err(Error::BlockProcessingPreempted) => {
+                        // not the block's fault -- just throw away what we did so far
+                        clarity_tx.rollback_block();
+                        return Err(Error::BlockProcessingPreempted);
+                    }
                     Err(e) => {
                         let msg = format!("Invalid Stacks block {}: {:?}", block.block_hash(), &e);
                         warn!("{}", &msg);
@@ -6349,7 +7177,7 @@ impl StacksChainState {
                 burn_dbconn.context.first_block_height,
             );
 
-            let total_coinbase = coinbase_at_block.saturating_add(accumulated_rewards);
+            let total_coinbase = ustx::saturating_add(coinbase_at_block, accumulated_rewards);
 
             // calculate reward for this block's miner
             let scheduled_miner_reward = StacksChainState::make_scheduled_miner_reward(
@@ -6417,8 +7245,17 @@ impl StacksChainState {
 
         chainstate_tx.log_transactions_processed(&new_tip.index_block_hash(), &tx_receipts);
 
+        let parent_microblocks_size = StacksChainState::get_microblocks_size(microblocks);
         set_last_block_transaction_count(block.txs.len() as u64);
         set_last_execution_cost_observed(&block_execution_cost, &block_limit);
+        set_last_block_size(block_size, parent_microblocks_size);
+        for tx_receipt in tx_receipts.iter() {
+            if let TransactionOrigin::Stacks(..) = tx_receipt.transaction {
+                if tx_receipt.tx_size > 0 {
+                    observe_processed_tx_size(tx_receipt.tx_size);
+                }
+            }
+        }
 
         let epoch_receipt = StacksEpochReceipt {
             header: new_tip,
@@ -6427,6 +7264,7 @@ impl StacksChainState {
             matured_rewards_info,
             parent_microblocks_cost: microblock_execution_cost,
             anchored_block_cost: block_execution_cost,
+            parent_microblocks_size,
             parent_burn_block_hash,
             parent_burn_block_height,
             parent_burn_block_timestamp,
@@ -6434,9 +7272,34 @@ impl StacksChainState {
             epoch_transition: applied_epoch_transition,
         };
 
+        let fee_stats = BlockFeeStats::from_epoch_receipt(&epoch_receipt, &block_limit);
+        StacksChainState::store_block_fee_stats(&chainstate_tx.tx, &fee_stats)?;
+        StacksChainState::store_block_weight(
+            &chainstate_tx.tx,
+            &BlockWeight::from_epoch_receipt(&epoch_receipt),
+        )?;
+        StacksChainState::record_block_supply_stats(
+            &chainstate_tx.tx,
+            &parent_chain_tip.index_block_hash(),
+            block.is_first_mined(),
+            &epoch_receipt,
+        )?;
+        if fee_rebate_accounting && epoch_receipt.evaluated_epoch.fee_rebate_accounting() {
+            let fee_rebates = FeeRebate::from_epoch_receipt(&epoch_receipt, &block_limit);
+            StacksChainState::store_fee_rebates(&chainstate_tx.tx, &fee_rebates)?;
+        }
+
         Ok((epoch_receipt, clarity_commit))
     }
 
+    /// Get the total serialized size, in bytes, of a microblock stream
+    pub fn get_microblocks_size(microblocks: &[StacksMicroblock]) -> u64 {
+        microblocks
+            .iter()
+            .map(|microblock| microblock.serialize_to_vec().len() as u64)
+            .sum()
+    }
+
     /// Verify that a Stacks anchored block attaches to its parent anchored block.
     /// * checks .header.total_work.work
     /// * checks .header.parent_block
@@ -6509,9 +7372,12 @@ impl StacksChainState {
     }
 
     /// Extract and parse the block from a loaded staging block, and verify its integrity.
-    fn extract_stacks_block(next_staging_block: &StagingBlock) -> Result<StacksBlock, Error> {
-        let block = {
-            StacksBlock::consensus_deserialize(&mut &next_staging_block.block_data[..])
+    /// Decode a staging block, along with the encoded length of each of its transactions.
+    fn extract_stacks_block(
+        next_staging_block: &StagingBlock,
+    ) -> Result<(StacksBlock, Vec<u64>), Error> {
+        let (block, block_tx_lens) = {
+            StacksBlock::consensus_deserialize_with_tx_lens(&mut &next_staging_block.block_data[..])
                 .map_err(Error::CodecError)?
         };
 
@@ -6524,7 +7390,7 @@ impl StacksChainState {
             );
             return Err(Error::DBError(db_error::Corruption));
         }
-        Ok(block)
+        Ok((block, block_tx_lens))
     }
 
     /// Given the list of microblocks produced by the given block's parent (and given the parent's
@@ -6570,65 +7436,337 @@ impl StacksChainState {
         Ok(next_microblocks)
     }
 
-    /// Find and process the next staging block.
-    /// Return the next chain tip if we processed this block, or None if we couldn't.
-    /// Return a poison microblock transaction payload if the microblock stream contains a
-    /// deliberate miner fork (this is NOT consensus-critical information, but is instead meant for
-    /// consumption by future miners).
-    pub fn process_next_staging_block<'a, T: BlockEventDispatcher>(
+    /// Re-execute an already-processed anchored block, and the parent microblocks it confirmed,
+    /// on top of its parent's state in order to regenerate the receipts it produced.  This is
+    /// used to re-emit events to observers that were not attached when the block was processed.
+    /// Nothing is committed -- like the miner, this runs in a concurrent chainstate transaction
+    /// which is rolled back once the receipts have been gathered.
+    /// Returns Ok(None) if the block has not been processed, or if its data is unavailable.
+    /// Returns Err(..) if the block could not be re-executed, or if re-executing it did not
+    /// reproduce its state root.
+    pub fn replay_processed_block(
         &mut self,
-        burnchain_dbconn: &DBConn,
-        sort_tx: &mut SortitionHandleTx,
-        dispatcher_opt: Option<&'a T>,
-    ) -> Result<(Option<StacksEpochReceipt>, Option<TransactionPayload>), Error> {
-        let blocks_path = self.blocks_path.clone();
-        let (mut chainstate_tx, clarity_instance) = self.chainstate_tx_begin()?;
-
-        // this is a transaction against both the headers and staging blocks databases!
-        let (next_microblocks, next_staging_block) =
-            match StacksChainState::find_next_staging_block(
-                &mut chainstate_tx.tx,
-                &blocks_path,
-                sort_tx,
-            )? {
-                Some((next_microblocks, next_staging_block)) => {
-                    (next_microblocks, next_staging_block)
-                }
+        burn_dbconn: &SortitionDBConn,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<ReplayedStacksBlock>, Error> {
+        let header_info = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.db(),
+            index_block_hash,
+        )? {
+            Some(header_info) => header_info,
+            None => {
+                return Ok(None);
+            }
+        };
+        let staging_block =
+            match StacksChainState::load_staging_block_info(self.db(), index_block_hash)? {
+                Some(staging_block) => staging_block,
                 None => {
-                    // no more work to do!
-                    debug!("No staging blocks");
+                    return Ok(None);
+                }
+            };
+        let (block, block_tx_lens) = match StacksChainState::load_block_bytes(
+            &self.blocks_path,
+            &staging_block.consensus_hash,
+            &staging_block.anchored_block_hash,
+        )? {
+            Some(block_bytes) => {
+                StacksBlock::consensus_deserialize_with_tx_lens(&mut &block_bytes[..])
+                    .map_err(Error::CodecError)?
+            }
+            None => {
+                return Ok(None);
+            }
+        };
 
-                    // save any orphaning we did
-                    chainstate_tx.commit().map_err(Error::DBError)?;
-                    return Ok((None, None));
+        let snapshot = SortitionDB::get_block_snapshot_consensus(
+            burn_dbconn.conn(),
+            &staging_block.consensus_hash,
+        )?
+        .ok_or(Error::NoSuchBlockError)?;
+
+        let (parent_consensus_hash, parent_block_hash) = if block.is_first_mined() {
+            (
+                FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+                FIRST_STACKS_BLOCK_HASH.clone(),
+            )
+        } else {
+            (
+                staging_block.parent_consensus_hash.clone(),
+                staging_block.parent_anchored_block_hash.clone(),
+            )
+        };
+        let parent_index_hash = StacksBlockId::new(
+            &staging_block.parent_consensus_hash,
+            &staging_block.parent_anchored_block_hash,
+        );
+
+        let (parent_burn_block_hash, parent_burn_block_height, parent_burn_block_timestamp) =
+            if block.is_first_mined() {
+                (BurnchainHeaderHash([0; 32]), 0, 0)
+            } else {
+                match SortitionDB::get_block_snapshot_consensus(
+                    burn_dbconn.conn(),
+                    &parent_consensus_hash,
+                )? {
+                    Some(sn) => (
+                        sn.burn_header_hash,
+                        sn.block_height as u32,
+                        sn.burn_header_timestamp,
+                    ),
+                    None => (BurnchainHeaderHash([0; 32]), 0, 0),
                 }
             };
 
-        let (burn_header_hash, burn_header_height, burn_header_timestamp, winning_block_txid) =
-            match SortitionDB::get_block_snapshot_consensus(
-                sort_tx,
-                &next_staging_block.consensus_hash,
-            )? {
-                Some(sn) => (
-                    sn.burn_header_hash,
-                    sn.block_height as u32,
-                    sn.burn_header_timestamp,
-                    sn.winning_block_txid,
-                ),
+        let mainnet = self.network.mainnet;
+        let ast_rules = SortitionDB::get_ast_rules(burn_dbconn.conn(), snapshot.block_height)?;
+        let pox_constants = burn_dbconn.context.pox_constants.clone();
+        let blocks_path = self.blocks_path.clone();
+
+        let (mut chainstate_tx, clarity_instance) = self.chainstate_tx_begin()?;
+
+        let parent_header_info =
+            match StacksChainState::get_parent_header_info(&mut chainstate_tx, &staging_block)? {
+                Some(hinfo) => hinfo,
                 None => {
-                    // shouldn't happen
-                    panic!(
-                        "CORRUPTION: staging block {}/{} does not correspond to a burn block",
-                        &next_staging_block.consensus_hash, &next_staging_block.anchored_block_hash
-                    );
+                    return Ok(None);
                 }
             };
 
-        debug!(
-            "Process staging block {}/{} in burn block {}, parent microblock {}",
-            next_staging_block.consensus_hash,
-            next_staging_block.anchored_block_hash,
-            &burn_header_hash,
+        // find the parent microblocks this block confirmed, exactly as when it was processed
+        let (microblocks, microblock_tx_lens) = if StacksChainState::block_crosses_epoch_boundary(
+            &chainstate_tx.tx,
+            &parent_consensus_hash,
+            &parent_block_hash,
+        )? {
+            (vec![], vec![])
+        } else {
+            match StacksChainState::find_parent_microblock_stream_with_tx_lens(
+                &chainstate_tx.tx,
+                &staging_block,
+            )? {
+                Some((microblocks, mut microblock_tx_lens)) => {
+                    let microblocks = StacksChainState::extract_connecting_microblocks(
+                        &parent_header_info,
+                        &staging_block,
+                        &block,
+                        microblocks,
+                    )?;
+                    microblock_tx_lens.truncate(microblocks.len());
+                    (microblocks, microblock_tx_lens)
+                }
+                None => {
+                    return Ok(None);
+                }
+            }
+        };
+        let last_microblock_hash = match microblocks.last() {
+            Some(mblock) => mblock.block_hash(),
+            None => EMPTY_MICROBLOCK_PARENT_HASH.clone(),
+        };
+        if last_microblock_hash != block.header.parent_microblock {
+            debug!(
+                "Cannot replay {}: its confirmed microblocks are not available in {}",
+                index_block_hash, &blocks_path
+            );
+            return Ok(None);
+        }
+
+        let SetupBlockResult {
+            mut clarity_tx,
+            mut tx_receipts,
+            microblock_execution_cost,
+            microblock_txs_receipts,
+            matured_miner_rewards_opt,
+            evaluated_epoch,
+            applied_epoch_transition,
+            mut auto_unlock_events,
+            ..
+        } = StacksChainState::setup_block(
+            &mut chainstate_tx,
+            clarity_instance,
+            burn_dbconn,
+            burn_dbconn,
+            burn_dbconn.conn(),
+            &pox_constants,
+            &parent_header_info,
+            snapshot.parent_burn_header_hash.clone(),
+            snapshot.block_height as u32,
+            parent_consensus_hash,
+            parent_block_hash,
+            &microblocks,
+            &microblock_tx_lens,
+            mainnet,
+            None,
+        )?;
+
+        let (_, _, block_receipts) = match StacksChainState::process_block_transactions(
+            &mut clarity_tx,
+            &block,
+            &block_tx_lens,
+            microblock_txs_receipts.len() as u32,
+            ast_rules,
+            None,
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                clarity_tx.rollback_block();
+                return Err(e);
+            }
+        };
+        tx_receipts.extend(block_receipts.into_iter());
+
+        let anchored_block_cost = clarity_tx.cost_so_far();
+
+        let (matured_rewards, miner_payouts_opt) =
+            if let Some((miner_reward, mut user_rewards, parent_reward, reward_ptr)) =
+                matured_miner_rewards_opt
+            {
+                let mut ret = vec![];
+                ret.push(miner_reward.clone());
+                ret.append(&mut user_rewards);
+                ret.push(parent_reward.clone());
+                (
+                    ret,
+                    Some((miner_reward, user_rewards, parent_reward, reward_ptr)),
+                )
+            } else {
+                (vec![], None)
+            };
+
+        let mut lockup_events = match StacksChainState::finish_block(
+            &mut clarity_tx,
+            miner_payouts_opt.as_ref(),
+            block.header.total_work.work as u32,
+            block.header.microblock_pubkey_hash,
+        ) {
+            Ok(lockup_events) => lockup_events,
+            Err(e) => {
+                clarity_tx.rollback_block();
+                return Err(e);
+            }
+        };
+
+        // lockup and auto-unlock events are attached to the coinbase receipt, as in
+        // `append_block()`
+        if let Some(receipt) = tx_receipts.get_mut(0) {
+            if receipt.is_coinbase_tx() {
+                receipt.events.append(&mut lockup_events);
+                receipt.events.append(&mut auto_unlock_events);
+            }
+        }
+
+        let root_hash = clarity_tx.seal();
+        clarity_tx.rollback_block();
+        if root_hash != block.header.state_index_root {
+            let msg = format!(
+                "Replayed block {} state root mismatch: expected {}, got {}",
+                index_block_hash, block.header.state_index_root, root_hash,
+            );
+            warn!("{}", &msg);
+            return Err(Error::InvalidStacksBlock(msg));
+        }
+
+        tx_receipts.extend(microblock_txs_receipts.into_iter());
+
+        let matured_rewards_info = miner_payouts_opt.map(|(_, _, _, info)| info);
+        let epoch_receipt = StacksEpochReceipt {
+            header: header_info,
+            tx_receipts,
+            matured_rewards,
+            matured_rewards_info,
+            parent_microblocks_cost: microblock_execution_cost,
+            anchored_block_cost,
+            parent_microblocks_size: StacksChainState::get_microblocks_size(&microblocks),
+            parent_burn_block_hash,
+            parent_burn_block_height,
+            parent_burn_block_timestamp,
+            evaluated_epoch,
+            epoch_transition: applied_epoch_transition,
+        };
+
+        Ok(Some(ReplayedStacksBlock {
+            block,
+            parent_index_hash,
+            winner_txid: snapshot.winning_block_txid,
+            epoch_receipt,
+        }))
+    }
+
+    /// Find and process the next staging block.
+    /// Return the next chain tip if we processed this block, or None if we couldn't.
+    /// Return a poison microblock transaction payload if the microblock stream contains a
+    /// deliberate miner fork (this is NOT consensus-critical information, but is instead meant for
+    /// consumption by future miners).
+    /// Return Error::BlockProcessingPreempted if a better block preempted this one while it was
+    /// being executed, in which case nothing was written and the better block will be found next.
+    pub fn process_next_staging_block<'a, T: BlockEventDispatcher>(
+        &mut self,
+        burnchain_dbconn: &DBConn,
+        sort_tx: &mut SortitionHandleTx,
+        dispatcher_opt: Option<&'a T>,
+    ) -> Result<(Option<StacksEpochReceipt>, Option<TransactionPayload>), Error> {
+        let blocks_path = self.blocks_path.clone();
+        let validation_cache = self.block_validation_cache.clone();
+        let preemption = self.block_preemption.clone();
+        let preferred_block = preemption.take_preferred();
+        let fee_rebate_accounting = self.fee_rebate_accounting;
+        let (mut chainstate_tx, clarity_instance) = self.chainstate_tx_begin()?;
+
+        // this is a transaction against both the headers and staging blocks databases!
+        let (next_microblocks, mut next_microblock_tx_lens, next_staging_block) =
+            match StacksChainState::find_next_staging_block(
+                &mut chainstate_tx.tx,
+                &blocks_path,
+                sort_tx,
+                preferred_block.as_ref(),
+            )? {
+                Some((next_microblocks, next_microblock_tx_lens, next_staging_block)) => (
+                    next_microblocks,
+                    next_microblock_tx_lens,
+                    next_staging_block,
+                ),
+                None => {
+                    // no more work to do!
+                    debug!("No staging blocks");
+
+                    // save any orphaning we did
+                    chainstate_tx.commit().map_err(Error::DBError)?;
+                    return Ok((None, None));
+                }
+            };
+
+        let (
+            sortition_id,
+            burn_header_hash,
+            burn_header_height,
+            burn_header_timestamp,
+            winning_block_txid,
+        ) = match SortitionDB::get_block_snapshot_consensus(
+            sort_tx,
+            &next_staging_block.consensus_hash,
+        )? {
+            Some(sn) => (
+                sn.sortition_id,
+                sn.burn_header_hash,
+                sn.block_height as u32,
+                sn.burn_header_timestamp,
+                sn.winning_block_txid,
+            ),
+            None => {
+                // shouldn't happen
+                panic!(
+                    "CORRUPTION: staging block {}/{} does not correspond to a burn block",
+                    &next_staging_block.consensus_hash, &next_staging_block.anchored_block_hash
+                );
+            }
+        };
+
+        debug!(
+            "Process staging block {}/{} in burn block {}, parent microblock {}",
+            next_staging_block.consensus_hash,
+            next_staging_block.anchored_block_hash,
+            &burn_header_hash,
             &next_staging_block.parent_microblock_hash,
         );
 
@@ -6640,7 +7778,7 @@ impl StacksChainState {
             None => return Ok((None, None)),
         };
 
-        let block = StacksChainState::extract_stacks_block(&next_staging_block)?;
+        let (block, block_tx_lens) = StacksChainState::extract_stacks_block(&next_staging_block)?;
         let block_size = next_staging_block.block_data.len() as u64;
 
         // sanity check -- don't process this block again if we already did so
@@ -6698,6 +7836,31 @@ impl StacksChainState {
                 &next_staging_block.anchored_block_hash,
                 false,
             )?;
+            if let Err(e) = StacksChainState::record_invalid_block(
+                &chainstate_tx.tx,
+                &next_staging_block.consensus_hash,
+                &next_staging_block.anchored_block_hash,
+                &msg,
+            ) {
+                warn!(
+                    "Failed to record invalid block {}/{}: {:?}",
+                    &next_staging_block.consensus_hash, &next_staging_block.anchored_block_hash, &e
+                );
+            }
+            if let Err(e) = StacksChainState::ban_block_leader_key(
+                &chainstate_tx.tx,
+                sort_tx,
+                &sortition_id,
+                &winning_block_txid,
+                &next_staging_block.consensus_hash,
+                &next_staging_block.anchored_block_hash,
+                &msg,
+            ) {
+                warn!(
+                    "Failed to ban leader key of invalid block {}/{}: {:?}",
+                    &next_staging_block.consensus_hash, &next_staging_block.anchored_block_hash, &e
+                );
+            }
             chainstate_tx.commit().map_err(Error::DBError)?;
 
             return Err(Error::InvalidStacksBlock(msg));
@@ -6711,6 +7874,7 @@ impl StacksChainState {
             &block,
             next_microblocks,
         )?;
+        next_microblock_tx_lens.truncate(next_microblocks.len());
         let (last_microblock_hash, last_microblock_seq) = match next_microblocks.len() {
             0 => (EMPTY_MICROBLOCK_PARENT_HASH.clone(), 0),
             _ => {
@@ -6755,30 +7919,89 @@ impl StacksChainState {
             &block_am
         );
 
+        // if we already found this block to be invalid in this same context (e.g. it was
+        // re-downloaded after being orphaned), then don't execute it again.
+        let validation_context = BlockValidationContext {
+            consensus_hash: next_staging_block.consensus_hash.clone(),
+            parent_block_id: StacksBlockId::new(
+                &next_staging_block.parent_consensus_hash,
+                &next_staging_block.parent_anchored_block_hash,
+            ),
+            parent_microblock_hash: next_staging_block.parent_microblock_hash.clone(),
+            parent_microblock_seq: next_staging_block.parent_microblock_seq,
+        };
+        let cached_error = validation_cache
+            .lock()
+            .expect("FATAL: block validation cache lock poisoned")
+            .get(&next_staging_block.anchored_block_hash, &validation_context)
+            .and_then(|verdict| verdict.to_error());
+
         // attach the block to the chain state and calculate the next chain tip.
         // Execute the confirmed microblocks' transactions against the chain state, and then
         // execute the anchored block's transactions against the chain state.
         let pox_constants = sort_tx.context.pox_constants.clone();
-        let (epoch_receipt, clarity_commit) = match StacksChainState::append_block(
-            &mut chainstate_tx,
-            clarity_instance,
-            sort_tx,
-            &pox_constants,
-            &parent_header_info,
-            &next_staging_block.consensus_hash,
-            &burn_header_hash,
-            burn_header_height,
-            burn_header_timestamp,
-            &block,
-            block_size,
-            &next_microblocks,
-            next_staging_block.commit_burn,
-            next_staging_block.sortition_burn,
-            &user_supports,
-            block_am.weight(),
-        ) {
+        preemption.begin(
+            &StacksBlockId::new(
+                &next_staging_block.consensus_hash,
+                &next_staging_block.anchored_block_hash,
+            ),
+            block.header.total_work.work,
+        );
+        let append_result = if let Some(e) = cached_error {
+            info!(
+                "Block {}/{} was already found to be invalid; not executing it again",
+                &next_staging_block.consensus_hash, &next_staging_block.anchored_block_hash
+            );
+            Err(e)
+        } else {
+            StacksChainState::append_block(
+                &mut chainstate_tx,
+                clarity_instance,
+                sort_tx,
+                &pox_constants,
+                &parent_header_info,
+                &next_staging_block.consensus_hash,
+                &burn_header_hash,
+                burn_header_height,
+                burn_header_timestamp,
+                &block,
+                block_size,
+                &block_tx_lens,
+                &next_microblocks,
+                &next_microblock_tx_lens,
+                next_staging_block.commit_burn,
+                next_staging_block.sortition_burn,
+                &user_supports,
+                block_am.weight(),
+                &preemption,
+                fee_rebate_accounting,
+            )
+        };
+        preemption.end();
+
+        let (epoch_receipt, clarity_commit) = match append_result {
             Ok(next_chain_tip_info) => next_chain_tip_info,
+            Err(Error::BlockProcessingPreempted) => {
+                // the block is fine, but there's a better one to process first.  Leave it in
+                // staging, and drop the chainstate transaction so it's as if we never started.
+                info!(
+                    "Abandoned processing of block {}/{} in favor of a better block",
+                    &next_staging_block.consensus_hash, &next_staging_block.anchored_block_hash
+                );
+                return Err(Error::BlockProcessingPreempted);
+            }
             Err(e) => {
+                if let Some(verdict) = BlockValidationVerdict::from_error(&e) {
+                    validation_cache
+                        .lock()
+                        .expect("FATAL: block validation cache lock poisoned")
+                        .insert(
+                            next_staging_block.anchored_block_hash.clone(),
+                            validation_context,
+                            verdict,
+                        );
+                }
+
                 // something's wrong with this epoch -- either a microblock was invalid, or the
                 // anchored block was invalid.  Either way, the anchored block will _never be_
                 // valid, so we can drop it from the chunk store and orphan all of its descendants.
@@ -6801,6 +8024,47 @@ impl StacksChainState {
                     &block.header,
                 );
 
+                if let Err(record_err) = StacksChainState::record_invalid_block(
+                    &chainstate_tx.tx,
+                    &next_staging_block.consensus_hash,
+                    &next_staging_block.anchored_block_hash,
+                    &format!("{:?}", &e),
+                ) {
+                    warn!(
+                        "Failed to record invalid block {}/{}: {:?}",
+                        &next_staging_block.consensus_hash,
+                        &next_staging_block.anchored_block_hash,
+                        &record_err
+                    );
+                }
+
+                // an invalid anchored block is its miner's fault, but an invalid parent
+                // microblock stream is the parent block's miner's fault.  A block we only refuse
+                // by local policy (e.g. a problematic transaction) is nobody's fault.
+                let verdict = if BlockValidationVerdict::is_consensus_invalid(&e) {
+                    BlockValidationVerdict::from_error(&e)
+                } else {
+                    None
+                };
+                if let Some(BlockValidationVerdict::Invalid(ref reason)) = verdict {
+                    if let Err(ban_err) = StacksChainState::ban_block_leader_key(
+                        &chainstate_tx.tx,
+                        sort_tx,
+                        &sortition_id,
+                        &winning_block_txid,
+                        &next_staging_block.consensus_hash,
+                        &next_staging_block.anchored_block_hash,
+                        reason,
+                    ) {
+                        warn!(
+                            "Failed to ban leader key of invalid block {}/{}: {:?}",
+                            &next_staging_block.consensus_hash,
+                            &next_staging_block.anchored_block_hash,
+                            &ban_err
+                        );
+                    }
+                }
+
                 match e {
                     Error::InvalidStacksMicroblock(ref msg, ref header_hash) => {
                         // specifically, an ancestor microblock was invalid.  Drop any descendant microblocks --
@@ -6904,8 +8168,19 @@ impl StacksChainState {
             true,
         )?;
 
+        validation_cache
+            .lock()
+            .expect("FATAL: block validation cache lock poisoned")
+            .insert(
+                next_staging_block.anchored_block_hash.clone(),
+                validation_context,
+                BlockValidationVerdict::Valid,
+            );
+
         // this will panic if the Clarity commit fails.
+        let marf_flush_start = get_epoch_time_ms();
         clarity_commit.commit();
+        set_last_marf_flush_time(get_epoch_time_ms().saturating_sub(marf_flush_start) as u64);
         chainstate_tx.commit()
             .unwrap_or_else(|e| {
                 error!("Failed to commit chainstate transaction after committing Clarity block. The chainstate database is now corrupted.";
@@ -6947,7 +8222,7 @@ impl StacksChainState {
     ) -> Result<Vec<(Option<StacksEpochReceipt>, Option<TransactionPayload>)>, Error> {
         // first, clear out orphans
         let blocks_path = self.blocks_path.clone();
-        let mut block_tx = self.db_tx_begin()?;
+        let mut block_tx = self.write_tx_begin()?;
         let mut num_orphans = 0;
         loop {
             // delete up to max_blocks blocks
@@ -6973,7 +8248,22 @@ impl StacksChainState {
 
         for i in 0..max_blocks {
             // process up to max_blocks pending blocks
-            match self.process_next_staging_block(burnchain_db_conn, &mut sort_tx, dispatcher_opt) {
+            let res = loop {
+                let res = self.process_next_staging_block(
+                    burnchain_db_conn,
+                    &mut sort_tx,
+                    dispatcher_opt,
+                );
+                if let Err(Error::BlockProcessingPreempted) = res {
+                    // go straight on to the block that preempted it
+                    continue;
+                }
+                break res;
+            };
+            if let Err(ref e) = res {
+                increment_errors_counter(e);
+            }
+            match res {
                 Ok((next_tip_opt, next_microblock_poison_opt)) => match next_tip_opt {
                     Some(next_tip) => {
                         ret.push((Some(next_tip), next_microblock_poison_opt));
@@ -7017,14 +8307,8 @@ impl StacksChainState {
 
     /// Is the given address version currently supported?
     /// NOTE: not consensus-critical; only used for mempool admission
-    fn is_valid_address_version(mainnet: bool, version: u8) -> bool {
-        if mainnet {
-            version == C32_ADDRESS_VERSION_MAINNET_SINGLESIG
-                || version == C32_ADDRESS_VERSION_MAINNET_MULTISIG
-        } else {
-            version == C32_ADDRESS_VERSION_TESTNET_SINGLESIG
-                || version == C32_ADDRESS_VERSION_TESTNET_MULTISIG
-        }
+    fn is_valid_address_version(network: &NetworkId, version: u8) -> bool {
+        network.is_network_version(version)
     }
 
     /// Get the highest processed block on the canonical burn chain.
@@ -7071,9 +8355,9 @@ impl StacksChainState {
     ///   consulting chain state).
     fn can_admit_mempool_semantic(
         tx: &StacksTransaction,
-        is_mainnet: bool,
+        network: &NetworkId,
     ) -> Result<(), MemPoolRejection> {
-        if is_mainnet != tx.is_mainnet() {
+        if network.mainnet != tx.is_mainnet() {
             return Err(MemPoolRejection::BadTransactionVersion);
         }
         match tx.payload {
@@ -7085,7 +8369,7 @@ impl StacksChainState {
                 if amount == 0 {
                     return Err(MemPoolRejection::TransferAmountMustBePositive);
                 }
-                if !StacksChainState::is_valid_address_version(is_mainnet, recipient.version()) {
+                if !StacksChainState::is_valid_address_version(network, recipient.version()) {
                     return Err(MemPoolRejection::BadAddressVersionByte);
                 }
                 Ok(())
@@ -7105,8 +8389,8 @@ impl StacksChainState {
         tx: &StacksTransaction,
         tx_size: u64,
     ) -> Result<(), MemPoolRejection> {
-        let is_mainnet = self.clarity_state.is_mainnet();
-        StacksChainState::can_admit_mempool_semantic(tx, is_mainnet)?;
+        let network = self.network;
+        StacksChainState::can_admit_mempool_semantic(tx, &network)?;
 
         let conf = self.config();
         let staging_height =
@@ -7145,7 +8429,14 @@ impl StacksChainState {
         let current_tip =
             StacksChainState::get_parent_index_block(current_consensus_hash, current_block);
         let res = match self.with_read_only_clarity_tx(burn_state_db, &current_tip, |conn| {
-            StacksChainState::can_include_tx(conn, &conf, has_microblock_pubk, tx, tx_size)
+            StacksChainState::can_include_tx(
+                conn,
+                &conf,
+                &network,
+                has_microblock_pubk,
+                tx,
+                tx_size,
+            )
         }) {
             Some(r) => r,
             None => Err(MemPoolRejection::NoSuchChainTip(
@@ -7168,6 +8459,7 @@ impl StacksChainState {
                         StacksChainState::can_include_tx(
                             conn,
                             &conf,
+                            &network,
                             has_microblock_pubk,
                             tx,
                             tx_size,
@@ -7188,11 +8480,90 @@ impl StacksChainState {
         }
     }
 
+    /// Check whether or not a transaction bundle can be admitted to the mempool.  Besides the
+    /// submitter's signature, only the checks that can be performed on each transaction by
+    /// itself are carried out here, since
+    /// later transactions in a bundle may depend on the effects of earlier ones.  Whether or not
+    /// the bundle can actually be mined is only known once a miner tries to mine it.
+    pub fn will_admit_mempool_tx_bundle(
+        &self,
+        bundle: &StacksTransactionBundle,
+    ) -> Result<(), MemPoolRejection> {
+        if bundle.txs.len() < 2 || bundle.txs.len() > MAX_TRANSACTIONS_PER_BUNDLE as usize {
+            return Err(MemPoolRejection::InvalidTransactionBundle(format!(
+                "Bundles must have between 2 and {} transactions",
+                MAX_TRANSACTIONS_PER_BUNDLE
+            )));
+        }
+
+        bundle.verify().map_err(|e| {
+            MemPoolRejection::InvalidTransactionBundle(format!("Bad bundle signature: {:?}", &e))
+        })?;
+
+        // whoever assembled the bundle must have a stake in it
+        let submitter_hash = Hash160::from_node_public_key_buffer(&bundle.submitter);
+        if !bundle.txs.iter().any(|tx| {
+            tx.origin_address().bytes == submitter_hash
+                || tx
+                    .sponsor_address()
+                    .map(|sponsor| sponsor.bytes == submitter_hash)
+                    .unwrap_or(false)
+        }) {
+            return Err(MemPoolRejection::InvalidTransactionBundle(
+                "The submitter must be the origin or sponsor of a transaction in the bundle"
+                    .to_string(),
+            ));
+        }
+
+        let conf = self.config();
+        let mut txids = HashSet::new();
+        for tx in bundle.txs.iter() {
+            if !txids.insert(tx.txid()) {
+                return Err(MemPoolRejection::InvalidTransactionBundle(format!(
+                    "Transaction {} appears more than once",
+                    &tx.txid()
+                )));
+            }
+
+            match tx.payload {
+                TransactionPayload::Coinbase(..) => {
+                    return Err(MemPoolRejection::NoCoinbaseViaMempool);
+                }
+                TransactionPayload::PoisonMicroblock(..) => {
+                    return Err(MemPoolRejection::InvalidTransactionBundle(
+                        "Bundles may not contain poison microblocks".to_string(),
+                    ));
+                }
+                _ => {}
+            }
+
+            // bundles are only mined in anchored blocks
+            if tx.anchor_mode == TransactionAnchorMode::OffChainOnly {
+                return Err(MemPoolRejection::DisallowedAnchorMode(tx.anchor_mode));
+            }
+
+            StacksChainState::can_admit_mempool_semantic(tx, &self.network)?;
+            StacksChainState::process_transaction_precheck(&conf, tx)
+                .map_err(|e| MemPoolRejection::FailedToValidate(e))?;
+
+            let tx_size = tx.serialize_to_vec().len() as u64;
+            let fee = tx.get_tx_fee();
+            if fee < MINIMUM_TX_FEE || fee / tx_size < MINIMUM_TX_FEE_RATE_PER_BYTE {
+                return Err(MemPoolRejection::FeeTooLow(
+                    fee,
+                    cmp::max(MINIMUM_TX_FEE, tx_size * MINIMUM_TX_FEE_RATE_PER_BYTE),
+                ));
+            }
+        }
+        Ok(())
+    }
+
     /// Given an outstanding clarity connection, can we append the tx to the chain state?
     /// Used when determining whether a transaction can be added to the mempool.
     fn can_include_tx<T: ClarityConnection>(
         clarity_connection: &mut T,
         chainstate_config: &DBConfig,
+        network: &NetworkId,
         has_microblock_pubkey: bool,
         tx: &StacksTransaction,
         tx_size: u64,
@@ -7252,13 +8623,9 @@ impl StacksChainState {
                 }
             };
 
-        if !StacksChainState::is_valid_address_version(
-            chainstate_config.mainnet,
-            origin.principal.version(),
-        ) || !StacksChainState::is_valid_address_version(
-            chainstate_config.mainnet,
-            payer.principal.version(),
-        ) {
+        if !StacksChainState::is_valid_address_version(network, origin.principal.version())
+            || !StacksChainState::is_valid_address_version(network, payer.principal.version())
+        {
             return Err(MemPoolRejection::BadAddressVersionByte);
         }
 
@@ -7299,10 +8666,7 @@ impl StacksChainState {
         match &tx.payload {
             TransactionPayload::TokenTransfer(addr, amount, _memo) => {
                 // version byte matches?
-                if !StacksChainState::is_valid_address_version(
-                    chainstate_config.mainnet,
-                    addr.version(),
-                ) {
+                if !StacksChainState::is_valid_address_version(network, addr.version()) {
                     return Err(MemPoolRejection::BadAddressVersionByte);
                 }
 
@@ -7350,10 +8714,7 @@ impl StacksChainState {
                 function_args,
             }) => {
                 // version byte matches?
-                if !StacksChainState::is_valid_address_version(
-                    chainstate_config.mainnet,
-                    address.version,
-                ) {
+                if !StacksChainState::is_valid_address_version(network, address.version) {
                     return Err(MemPoolRejection::BadAddressVersionByte);
                 }
 
@@ -7452,6 +8813,7 @@ pub mod test {
     use crate::chainstate::stacks::Error as chainstate_error;
     use crate::chainstate::stacks::*;
     use crate::core::mempool::*;
+    use crate::net::pagination::RPCPage;
     use crate::net::test::*;
     use crate::net::ExtendedStacksHeader;
     use crate::util_lib::db::Error as db_error;
@@ -7946,7 +9308,7 @@ pub mod test {
         sortition_burn: u64,
     ) {
         let blocks_path = chainstate.blocks_path.clone();
-        let mut tx = chainstate.db_tx_begin().unwrap();
+        let mut tx = chainstate.write_tx_begin().unwrap();
         StacksChainState::store_staging_block(
             &mut tx,
             &blocks_path,
@@ -7974,7 +9336,7 @@ pub mod test {
         parent_anchored_block_hash: &BlockHeaderHash,
         microblock: &StacksMicroblock,
     ) {
-        let mut tx = chainstate.db_tx_begin().unwrap();
+        let mut tx = chainstate.write_tx_begin().unwrap();
         StacksChainState::store_staging_microblock(
             &mut tx,
             parent_consensus_hash,
@@ -8007,7 +9369,7 @@ pub mod test {
         );
         let blocks_path = chainstate.blocks_path.clone();
 
-        let mut tx = chainstate.db_tx_begin().unwrap();
+        let mut tx = chainstate.write_tx_begin().unwrap();
         StacksChainState::set_block_processed(
             &mut tx,
             None,
@@ -8032,7 +9394,7 @@ pub mod test {
     ) {
         let blocks_path = chainstate.blocks_path.clone();
 
-        let mut tx = chainstate.db_tx_begin().unwrap();
+        let mut tx = chainstate.write_tx_begin().unwrap();
         StacksChainState::set_block_orphaned(
             &mut tx,
             &blocks_path,
@@ -8066,7 +9428,7 @@ pub mod test {
         let parent_microblock_index_hash =
             StacksBlockHeader::make_index_block_hash(&parent_consensus_hash, &tail_microblock_hash);
 
-        let mut tx = chainstate.db_tx_begin().unwrap();
+        let mut tx = chainstate.write_tx_begin().unwrap();
 
         StacksChainState::set_microblocks_processed(
             &mut tx,
@@ -8089,7 +9451,7 @@ pub mod test {
 
     fn process_next_orphaned_staging_block(chainstate: &mut StacksChainState) -> bool {
         let blocks_path = chainstate.blocks_path.clone();
-        let mut tx = chainstate.db_tx_begin().unwrap();
+        let mut tx = chainstate.write_tx_begin().unwrap();
         let res =
             StacksChainState::process_next_orphaned_staging_block(&mut tx, &blocks_path).unwrap();
         tx.commit().unwrap();
@@ -8102,7 +9464,7 @@ pub mod test {
         anchored_block_hash: &BlockHeaderHash,
         invalid_microblock: &BlockHeaderHash,
     ) {
-        let mut tx = chainstate.db_tx_begin().unwrap();
+        let mut tx = chainstate.write_tx_begin().unwrap();
         StacksChainState::drop_staging_microblocks(
             &mut tx,
             consensus_hash,
@@ -8284,7 +9646,7 @@ pub mod test {
         )
         .unwrap());
 
-        let mut dbtx = chainstate.db_tx_begin().unwrap();
+        let mut dbtx = chainstate.write_tx_begin().unwrap();
         StacksChainState::forget_orphaned_epoch_data(
             &mut dbtx,
             &ConsensusHash([1u8; 20]),
@@ -10518,8 +11880,8 @@ pub mod test {
         count: u64,
     ) -> Result<Vec<u8>, chainstate_error> {
         let mempool = MemPoolDB::open_test(
-            chainstate.mainnet,
-            chainstate.chain_id,
+            chainstate.network.mainnet,
+            chainstate.network.chain_id,
             &chainstate.root_path,
         )
         .unwrap();
@@ -10538,8 +11900,8 @@ pub mod test {
         count: u64,
     ) -> Result<Vec<u8>, chainstate_error> {
         let mempool = MemPoolDB::open_test(
-            chainstate.mainnet,
-            chainstate.chain_id,
+            chainstate.network.mainnet,
+            chainstate.network.chain_id,
             &chainstate.root_path,
         )
         .unwrap();
@@ -10558,8 +11920,8 @@ pub mod test {
         count: u64,
     ) -> Result<Vec<u8>, chainstate_error> {
         let mempool = MemPoolDB::open_test(
-            chainstate.mainnet,
-            chainstate.chain_id,
+            chainstate.network.mainnet,
+            chainstate.network.chain_id,
             &chainstate.root_path,
         )
         .unwrap();
@@ -10771,12 +12133,14 @@ pub mod test {
         }
 
         // can't stream a non-existant header
-        assert!(StreamCursor::new_headers(&chainstate, &StacksBlockId([0x11; 32]), 1).is_err());
+        assert!(
+            StreamCursor::new_headers(&chainstate, &StacksBlockId([0x11; 32]), 1, false).is_err()
+        );
 
         // stream back individual headers
         for i in 0..blocks.len() {
             let mut stream =
-                StreamCursor::new_headers(&chainstate, &blocks_index_hashes[i], 1).unwrap();
+                StreamCursor::new_headers(&chainstate, &blocks_index_hashes[i], 1, false).unwrap();
             let mut next_header_bytes = vec![];
             loop {
                 // torture test
@@ -10828,7 +12192,7 @@ pub mod test {
 
         // get them all -- ask for more than there is
         let mut stream =
-            StreamCursor::new_headers(&chainstate, blocks_index_hashes.last().unwrap(), 4096)
+            StreamCursor::new_headers(&chainstate, blocks_index_hashes.last().unwrap(), 4096, true)
                 .unwrap();
         let header_bytes =
             stream_headers_to_vec(&mut chainstate, &mut stream, 1024 * 1024).unwrap();
@@ -10837,8 +12201,11 @@ pub mod test {
             "headers: {}",
             String::from_utf8(header_bytes.clone()).unwrap()
         );
-        let headers: Vec<ExtendedStacksHeader> =
+        let page: RPCPage<ExtendedStacksHeader> =
             serde_json::from_reader(&mut &header_bytes[..]).unwrap();
+        assert_eq!(page.total, Some(block_expected_headers.len() as u64));
+        assert_eq!(page.next_cursor, None);
+        let headers = page.results;
 
         assert_eq!(headers.len(), block_expected_headers.len());
         for ((i, h), eh) in headers
@@ -10853,9 +12220,14 @@ pub mod test {
             }
         }
 
-        let mut stream =
-            StreamCursor::new_headers(&chainstate, blocks_fork_index_hashes.last().unwrap(), 4096)
-                .unwrap();
+        // without a page, the headers are a bare list
+        let mut stream = StreamCursor::new_headers(
+            &chainstate,
+            blocks_fork_index_hashes.last().unwrap(),
+            4096,
+            false,
+        )
+        .unwrap();
         let header_bytes =
             stream_headers_to_vec(&mut chainstate, &mut stream, 1024 * 1024).unwrap();
         let fork_headers: Vec<ExtendedStacksHeader> =
@@ -10884,7 +12256,7 @@ pub mod test {
 
         // ask for only a few
         let mut stream =
-            StreamCursor::new_headers(&chainstate, blocks_index_hashes.last().unwrap(), 10)
+            StreamCursor::new_headers(&chainstate, blocks_index_hashes.last().unwrap(), 10, true)
                 .unwrap();
         let mut header_bytes = vec![];
         loop {
@@ -10901,9 +12273,16 @@ pub mod test {
             String::from_utf8(header_bytes.clone()).unwrap()
         );
 
-        let headers: Vec<ExtendedStacksHeader> =
+        let page: RPCPage<ExtendedStacksHeader> =
             serde_json::from_reader(&mut &header_bytes[..]).unwrap();
-
+        assert_eq!(page.limit, 10);
+        assert_eq!(page.total, Some(block_expected_headers.len() as u64));
+        assert_eq!(
+            page.next_cursor,
+            Some(block_expected_index_hashes[10].to_hex())
+        );
+        let headers = page.results;
+
         assert_eq!(headers.len(), 10);
         for (i, hdr) in headers.iter().enumerate() {
             assert_eq!(hdr.header, block_expected_headers[i]);
@@ -10911,9 +12290,13 @@ pub mod test {
         }
 
         // ask for only a few
-        let mut stream =
-            StreamCursor::new_headers(&chainstate, blocks_fork_index_hashes.last().unwrap(), 10)
-                .unwrap();
+        let mut stream = StreamCursor::new_headers(
+            &chainstate,
+            blocks_fork_index_hashes.last().unwrap(),
+            10,
+            false,
+        )
+        .unwrap();
         let mut header_bytes = vec![];
         loop {
             // torture test
@@ -11678,6 +13061,185 @@ pub mod test {
         }
     }
 
+    #[test]
+    fn test_replay_processed_block() {
+        let mut peer_config = TestPeerConfig::new(function_name!(), 21319, 21320);
+
+        let privk = StacksPrivateKey::new();
+        let addr = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk)],
+        )
+        .unwrap();
+
+        peer_config.initial_balances = vec![(addr.to_account_principal(), 1000000000)];
+        let recv_addr =
+            StacksAddress::from_string("ST1H1B54MY50RMBRRKS7GV2ZWG79RZ1RQ1ETW4E01").unwrap();
+
+        let mut peer = TestPeer::new(peer_config.clone());
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let mut mblock_nonce = 0;
+        let mut processed = vec![];
+        for tenure_id in 0..5 {
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref _parent_microblock_header_opt| {
+                    let parent_tip = match parent_opt {
+                        None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                        Some(block) => {
+                            let ic = sortdb.index_conn();
+                            let snapshot =
+                                SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                                    &ic,
+                                    &tip.sortition_id,
+                                    &block.block_hash(),
+                                )
+                                .unwrap()
+                                .unwrap(); // succeeds because we don't fork
+                            StacksChainState::get_anchored_block_header_info(
+                                chainstate.db(),
+                                &snapshot.consensus_hash,
+                                &snapshot.winning_stacks_block_hash,
+                            )
+                            .unwrap()
+                            .unwrap()
+                        }
+                    };
+
+                    let mut mempool =
+                        MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+                    let coinbase_tx =
+                        make_coinbase_with_nonce(miner, tenure_id as usize, tenure_id as u64, None);
+
+                    let microblock_privkey = StacksPrivateKey::new();
+                    let microblock_pubkeyhash = Hash160::from_node_public_key(
+                        &StacksPublicKey::from_private(&microblock_privkey),
+                    );
+                    let anchored_block = StacksBlockBuilder::build_anchored_block(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        &mut mempool,
+                        &parent_tip,
+                        tip.total_burn,
+                        vrf_proof,
+                        microblock_pubkeyhash,
+                        &coinbase_tx,
+                        BlockBuilderSettings::max_value(),
+                        None,
+                    )
+                    .unwrap();
+
+                    // a stream of STX transfers, which the next block confirms
+                    let mut microblocks: Vec<StacksMicroblock> = vec![];
+                    for _ in 0..2 {
+                        let auth = TransactionAuth::Standard(
+                            TransactionSpendingCondition::new_singlesig_p2pkh(
+                                StacksPublicKey::from_private(&privk),
+                            )
+                            .unwrap(),
+                        );
+                        let mut tx_stx_transfer = StacksTransaction::new(
+                            TransactionVersion::Testnet,
+                            auth,
+                            TransactionPayload::TokenTransfer(
+                                recv_addr.clone().into(),
+                                1,
+                                TokenTransferMemo([0u8; 34]),
+                            ),
+                        );
+                        tx_stx_transfer.chain_id = 0x80000000;
+                        tx_stx_transfer.post_condition_mode = TransactionPostConditionMode::Allow;
+                        tx_stx_transfer.set_tx_fee(0);
+                        tx_stx_transfer.set_origin_nonce(mblock_nonce);
+                        mblock_nonce += 1;
+
+                        let mut signer = StacksTransactionSigner::new(&tx_stx_transfer);
+                        signer.sign_origin(&privk).unwrap();
+
+                        let microblock = StacksMicroblockBuilder::make_next_microblock_from_txs(
+                            vec![signer.get_tx().unwrap()],
+                            &microblock_privkey,
+                            &anchored_block.0.block_hash(),
+                            microblocks.last().map(|mblock| &mblock.header),
+                            ASTRules::PrecheckSize,
+                        )
+                        .unwrap();
+                        microblocks.push(microblock);
+                    }
+
+                    (anchored_block.0, microblocks)
+                },
+            );
+
+            let (_, _, consensus_hash) = peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            processed.push(StacksBlockId::new(
+                &consensus_hash,
+                &stacks_block.block_hash(),
+            ));
+        }
+
+        let sortdb = peer.sortdb.take().unwrap();
+        let chainstate = peer.chainstate();
+        let tip = SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn()).unwrap();
+
+        for index_block_hash in processed.iter() {
+            let replayed = chainstate
+                .replay_processed_block(&sortdb.index_conn(), index_block_hash)
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                replayed.epoch_receipt.header.index_block_hash(),
+                *index_block_hash
+            );
+            assert!(replayed.epoch_receipt.tx_receipts[0].is_coinbase_tx());
+
+            // every block after the first confirms its parent's two microblock transactions
+            let num_mblock_txs = if replayed.block.has_microblock_parent() {
+                2
+            } else {
+                0
+            };
+            assert_eq!(
+                replayed.epoch_receipt.tx_receipts.len(),
+                replayed.block.txs.len() + num_mblock_txs
+            );
+
+            // nothing was committed, so replaying again gives the same receipts
+            let replayed_again = chainstate
+                .replay_processed_block(&sortdb.index_conn(), index_block_hash)
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                replayed.epoch_receipt.tx_receipts,
+                replayed_again.epoch_receipt.tx_receipts
+            );
+        }
+
+        assert_eq!(
+            SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn()).unwrap(),
+            tip
+        );
+        assert!(chainstate
+            .replay_processed_block(&sortdb.index_conn(), &StacksBlockId([0x11; 32]))
+            .unwrap()
+            .is_none());
+
+        peer.sortdb = Some(sortdb);
+    }
+
     #[test]
     fn test_get_parent_block_header() {
         let peer_config = TestPeerConfig::new(function_name!(), 21313, 21314);
@@ -12120,6 +13682,359 @@ pub mod test {
         );
     }
 
+    #[test]
+    fn stacks_db_microblock_fork_tree() {
+        // the same shape as stacks_db_staging_microblocks_multiple_forks: one stream of 5
+        // microblocks, forked at microblocks 1, 2, and 3, with each fork confirmed by a child.
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+        let privk = StacksPrivateKey::from_hex(
+            "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+        )
+        .unwrap();
+
+        let block_1 = make_empty_coinbase_block(&privk);
+        let mut blocks = vec![];
+
+        let mut mblocks = make_sample_microblock_stream(&privk, &block_1.block_hash());
+        mblocks.truncate(5);
+
+        let mut mblocks_branches = vec![];
+        let mut consensus_hashes = vec![ConsensusHash([2u8; 20])];
+
+        for i in 1..4 {
+            let mut mblocks_branch = make_sample_microblock_stream_fork(
+                &privk,
+                &mblocks[i].block_hash(),
+                mblocks[i].header.sequence + 1,
+            );
+            mblocks_branch.truncate(3);
+
+            let mut block = make_empty_coinbase_block(&privk);
+            block.header.parent_block = block_1.block_hash();
+            block.header.parent_microblock = mblocks_branch[2].block_hash();
+            block.header.parent_microblock_sequence = mblocks_branch[2].header.sequence;
+
+            mblocks_branches.push(mblocks_branch);
+            blocks.push(block);
+            consensus_hashes.push(ConsensusHash([(i + 2) as u8; 20]));
+        }
+
+        let parent_consensus_hash = ConsensusHash([1u8; 20]);
+        let index_block_hash =
+            StacksBlockHeader::make_index_block_hash(&consensus_hashes[0], &block_1.block_hash());
+
+        // nothing known yet
+        assert!(
+            StacksChainState::get_microblock_fork_tree(&chainstate.db(), &index_block_hash)
+                .unwrap()
+                .is_none()
+        );
+
+        store_staging_block(
+            &mut chainstate,
+            &consensus_hashes[0],
+            &block_1,
+            &parent_consensus_hash,
+            1,
+            2,
+        );
+
+        for (i, block) in blocks.iter().enumerate() {
+            store_staging_block(
+                &mut chainstate,
+                &consensus_hashes[i + 1],
+                &block,
+                &consensus_hashes[0],
+                1,
+                2,
+            );
+        }
+
+        for mblock in mblocks.iter() {
+            store_staging_microblock(
+                &mut chainstate,
+                &consensus_hashes[0],
+                &block_1.block_hash(),
+                mblock,
+            );
+        }
+
+        for mblock_branch in mblocks_branches.iter() {
+            for mblock in mblock_branch {
+                store_staging_microblock(
+                    &mut chainstate,
+                    &consensus_hashes[0],
+                    &block_1.block_hash(),
+                    mblock,
+                );
+            }
+        }
+
+        let tree = StacksChainState::get_microblock_fork_tree(&chainstate.db(), &index_block_hash)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(tree.consensus_hash, consensus_hashes[0]);
+        assert_eq!(tree.block_hash, block_1.block_hash());
+        assert_eq!(tree.microblocks.len(), 5 + 3 * 3);
+        assert_eq!(tree.roots, vec![mblocks[0].block_hash()]);
+        assert_eq!(
+            tree.fork_points,
+            vec![
+                mblocks[1].block_hash(),
+                mblocks[2].block_hash(),
+                mblocks[3].block_hash()
+            ]
+        );
+
+        let child_index_block_hashes: Vec<_> = blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| {
+                StacksBlockHeader::make_index_block_hash(
+                    &consensus_hashes[i + 1],
+                    &block.block_hash(),
+                )
+            })
+            .collect();
+
+        let find_node = |hash: &BlockHeaderHash| {
+            tree.microblocks
+                .iter()
+                .find(|node| &node.microblock_hash == hash)
+                .unwrap()
+                .clone()
+        };
+
+        // every child confirmed the first microblock, but none confirmed the last one
+        let mut confirmed_by = find_node(&mblocks[0].block_hash()).confirmed_by;
+        confirmed_by.sort();
+        let mut expected = child_index_block_hashes.clone();
+        expected.sort();
+        assert_eq!(confirmed_by, expected);
+        assert!(find_node(&mblocks[4].block_hash()).confirmed_by.is_empty());
+
+        // each fork is confirmed only by its own child
+        for (i, mblocks_branch) in mblocks_branches.iter().enumerate() {
+            for mblock in mblocks_branch.iter() {
+                assert_eq!(
+                    find_node(&mblock.block_hash()).confirmed_by,
+                    vec![child_index_block_hashes[i].clone()]
+                );
+            }
+        }
+
+        assert_eq!(tree.confirmations.len(), 3);
+        for confirmation in tree.confirmations.iter() {
+            let i = child_index_block_hashes
+                .iter()
+                .position(|h| h == &confirmation.index_block_hash)
+                .unwrap();
+            assert_eq!(
+                confirmation.parent_microblock_hash,
+                Some(mblocks_branches[i][2].block_hash())
+            );
+            assert_eq!(
+                confirmation.parent_microblock_seq,
+                mblocks_branches[i][2].header.sequence
+            );
+            assert!(confirmation.tail_known);
+        }
+    }
+
+    #[test]
+    fn stacks_db_staging_queue() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+        let privk = StacksPrivateKey::from_hex(
+            "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+        )
+        .unwrap();
+
+        // block_1 is the first block; block_2 and block_3 build on it, and block_3 confirms two
+        // microblocks.  block_4's parent is never stored.
+        let mut block_1 = make_empty_coinbase_block(&privk);
+        block_1.header.parent_block = FIRST_STACKS_BLOCK_HASH;
+        block_1.header.parent_microblock = EMPTY_MICROBLOCK_PARENT_HASH.clone();
+        block_1.header.parent_microblock_sequence = 0;
+
+        let mblocks = make_sample_microblock_stream(&privk, &block_1.block_hash());
+
+        let mut block_2 = make_empty_coinbase_block(&privk);
+        block_2.header.parent_block = block_1.block_hash();
+        block_2.header.parent_microblock = EMPTY_MICROBLOCK_PARENT_HASH.clone();
+        block_2.header.parent_microblock_sequence = 0;
+
+        let mut block_3 = make_empty_coinbase_block(&privk);
+        block_3.header.parent_block = block_1.block_hash();
+        block_3.header.parent_microblock = mblocks[1].block_hash();
+        block_3.header.parent_microblock_sequence = mblocks[1].header.sequence;
+
+        let mut block_4 = make_empty_coinbase_block(&privk);
+        block_4.header.parent_block = BlockHeaderHash([0x44; 32]);
+        block_4.header.parent_microblock = EMPTY_MICROBLOCK_PARENT_HASH.clone();
+        block_4.header.parent_microblock_sequence = 0;
+
+        let consensus_hashes = vec![
+            ConsensusHash([2u8; 20]),
+            ConsensusHash([3u8; 20]),
+            ConsensusHash([4u8; 20]),
+            ConsensusHash([5u8; 20]),
+        ];
+        let parent_consensus_hashes = vec![
+            FIRST_BURNCHAIN_CONSENSUS_HASH,
+            ConsensusHash([2u8; 20]),
+            ConsensusHash([2u8; 20]),
+            ConsensusHash([0x44; 20]),
+        ];
+        let blocks = &[&block_1, &block_2, &block_3, &block_4];
+
+        assert!(
+            StacksChainState::get_staging_queue(&chainstate.db(), get_epoch_time_secs())
+                .unwrap()
+                .is_empty()
+        );
+
+        for ((block, consensus_hash), parent_consensus_hash) in blocks
+            .iter()
+            .zip(&consensus_hashes)
+            .zip(&parent_consensus_hashes)
+        {
+            store_staging_block(
+                &mut chainstate,
+                consensus_hash,
+                block,
+                parent_consensus_hash,
+                1,
+                2,
+            );
+        }
+
+        let index_block_hashes: Vec<_> = blocks
+            .iter()
+            .zip(&consensus_hashes)
+            .map(|(block, consensus_hash)| {
+                StacksBlockHeader::make_index_block_hash(consensus_hash, &block.block_hash())
+            })
+            .collect();
+
+        let get_status = |chainstate: &StacksChainState, i: usize| {
+            StacksChainState::get_staging_queue(&chainstate.db(), get_epoch_time_secs())
+                .unwrap()
+                .into_iter()
+                .find(|entry| entry.index_block_hash == index_block_hashes[i])
+                .map(|entry| entry.status)
+        };
+
+        let now = get_epoch_time_secs() + 10;
+        let queue = StacksChainState::get_staging_queue(&chainstate.db(), now).unwrap();
+        assert_eq!(queue.len(), 4);
+        for entry in queue.iter() {
+            assert!(entry.age >= 10);
+            assert_eq!(entry.age, now - entry.arrival_time);
+        }
+
+        assert_eq!(get_status(&chainstate, 0), Some(StagingBlockStatus::Ready));
+        assert_eq!(
+            get_status(&chainstate, 1),
+            Some(StagingBlockStatus::ParentNotProcessed)
+        );
+        assert_eq!(
+            get_status(&chainstate, 2),
+            Some(StagingBlockStatus::ParentNotProcessed)
+        );
+        assert_eq!(
+            get_status(&chainstate, 3),
+            Some(StagingBlockStatus::MissingParent)
+        );
+
+        // processing block_1 removes it from the queue, and makes its children attachable
+        set_block_processed(
+            &mut chainstate,
+            &consensus_hashes[0],
+            &block_1.block_hash(),
+            true,
+        );
+        assert_eq!(get_status(&chainstate, 0), None);
+        assert_eq!(get_status(&chainstate, 1), Some(StagingBlockStatus::Ready));
+        assert_eq!(
+            get_status(&chainstate, 2),
+            Some(StagingBlockStatus::MissingMicroblocks)
+        );
+
+        // block_3 is not ready until every microblock it confirms has arrived
+        store_staging_microblock(
+            &mut chainstate,
+            &consensus_hashes[0],
+            &block_1.block_hash(),
+            &mblocks[0],
+        );
+        assert_eq!(
+            get_status(&chainstate, 2),
+            Some(StagingBlockStatus::MissingMicroblocks)
+        );
+        store_staging_microblock(
+            &mut chainstate,
+            &consensus_hashes[0],
+            &block_1.block_hash(),
+            &mblocks[1],
+        );
+        assert_eq!(get_status(&chainstate, 2), Some(StagingBlockStatus::Ready));
+
+        // a block that was never marked attachable can be requeued
+        {
+            let tx = chainstate.write_tx_begin().unwrap();
+            let args: &[&dyn ToSql] = &[&index_block_hashes[1]];
+            tx.execute(
+                "UPDATE staging_blocks SET attachable = 0 WHERE index_block_hash = ?1",
+                args,
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        }
+        assert_eq!(
+            get_status(&chainstate, 1),
+            Some(StagingBlockStatus::NotAttachable)
+        );
+        {
+            let tx = chainstate.write_tx_begin().unwrap();
+            assert_eq!(
+                StacksChainState::requeue_staging_block(&tx, &index_block_hashes[1]).unwrap(),
+                Some(StagingBlockStatus::Ready)
+            );
+            tx.commit().unwrap();
+        }
+        assert_eq!(get_status(&chainstate, 1), Some(StagingBlockStatus::Ready));
+
+        // requeuing a block that is missing its parent does nothing
+        {
+            let tx = chainstate.write_tx_begin().unwrap();
+            assert_eq!(
+                StacksChainState::requeue_staging_block(&tx, &index_block_hashes[3]).unwrap(),
+                Some(StagingBlockStatus::MissingParent)
+            );
+            tx.commit().unwrap();
+        }
+
+        // invalid blocks stay listed, but cannot be requeued
+        set_block_orphaned(&mut chainstate, &consensus_hashes[1], &block_2.block_hash());
+        assert_eq!(
+            get_status(&chainstate, 1),
+            Some(StagingBlockStatus::Invalid)
+        );
+        {
+            let tx = chainstate.write_tx_begin().unwrap();
+            assert_eq!(
+                StacksChainState::requeue_staging_block(&tx, &index_block_hashes[1]).unwrap(),
+                None
+            );
+            assert_eq!(
+                StacksChainState::requeue_staging_block(&tx, &index_block_hashes[0]).unwrap(),
+                None
+            );
+            tx.commit().unwrap();
+        }
+    }
+
     fn make_transfer_op(
         addr: &StacksAddress,
         recipient_addr: &StacksAddress,
@@ -13184,6 +15099,165 @@ pub mod test {
         }
     }
 
+    /// Store a header (but no block) for a child of `parent`.  Children of the same parent with
+    /// different `fork_id`s are siblings on different forks.
+    fn make_block_feed_header(
+        chainstate: &mut StacksChainState,
+        parent: &StacksHeaderInfo,
+        fork_id: u8,
+    ) -> StacksHeaderInfo {
+        let mut new_tip = parent.clone();
+        new_tip.anchored_header.parent_block = parent.anchored_header.block_hash();
+        new_tip.anchored_header.microblock_pubkey_hash = Hash160([fork_id; 20]);
+        new_tip.anchored_header.total_work.work = parent.anchored_header.total_work.work + 1;
+        new_tip.microblock_tail = None;
+        new_tip.stacks_block_height = parent.stacks_block_height + 1;
+
+        let mut ch_bytes = parent.consensus_hash.0.to_vec();
+        ch_bytes.push(fork_id);
+        new_tip.consensus_hash = ConsensusHash(Hash160::from_data(&ch_bytes).0);
+        new_tip.burn_header_hash =
+            BurnchainHeaderHash(Sha512Trunc256Sum::from_data(&new_tip.consensus_hash.0).0);
+        new_tip.burn_header_height = parent.burn_header_height + 1;
+
+        let mut block_reward = MinerPaymentSchedule::genesis(false);
+        block_reward.parent_consensus_hash = parent.consensus_hash;
+        block_reward.parent_block_hash = parent.anchored_header.block_hash();
+        block_reward.block_hash = new_tip.anchored_header.block_hash();
+        block_reward.consensus_hash = new_tip.consensus_hash;
+
+        let mut tx = chainstate.index_tx_begin().unwrap();
+        let tip = StacksChainState::advance_tip(
+            &mut tx,
+            &parent.anchored_header,
+            &parent.consensus_hash,
+            &new_tip.anchored_header,
+            &new_tip.consensus_hash,
+            &new_tip.burn_header_hash,
+            new_tip.burn_header_height,
+            new_tip.burn_header_timestamp,
+            None,
+            &block_reward,
+            &[],
+            None,
+            &ExecutionCost::zero(),
+            0,
+            false,
+            vec![],
+            vec![],
+            vec![],
+            parent.anchored_header.total_work.work + 1,
+        )
+        .unwrap();
+        tx.commit().unwrap();
+        tip
+    }
+
+    fn make_block_feed_stream(
+        chainstate: &StacksChainState,
+        cursor: &StacksHeaderInfo,
+        tip: &StacksHeaderInfo,
+    ) -> BlockFeedStreamData {
+        let request = BlockFeedRequest {
+            start: BlockFeedStart::Cursor(cursor.index_block_hash()),
+            encoding: BlockFeedEncoding::Binary,
+            limit: None,
+        };
+        match StreamCursor::new_block_feed(
+            chainstate,
+            &request,
+            tip.index_block_hash(),
+            tip.stacks_block_height,
+            60,
+        )
+        .unwrap()
+        {
+            StreamCursor::BlockFeed(stream) => stream,
+            _ => panic!("not a block feed"),
+        }
+    }
+
+    #[test]
+    fn test_block_feed_rollback() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+        let genesis = StacksHeaderInfo::regtest_genesis();
+
+        // genesis <- a <- b
+        //              \
+        //               c <- d   (canonical)
+        //
+        // genesis <- e
+        let a = make_block_feed_header(&mut chainstate, &genesis, 1);
+        let b = make_block_feed_header(&mut chainstate, &a, 1);
+        let c = make_block_feed_header(&mut chainstate, &a, 2);
+        let d = make_block_feed_header(&mut chainstate, &c, 2);
+        let e = make_block_feed_header(&mut chainstate, &genesis, 3);
+
+        // cursor on the canonical fork: no rollback
+        let mut stream = make_block_feed_stream(&chainstate, &c, &d);
+        assert!(chainstate
+            .check_block_feed_fork(&mut stream)
+            .unwrap()
+            .is_none());
+        assert_eq!(stream.next_height, 3);
+        assert_eq!(stream.cursor, Some(c.index_block_hash()));
+
+        // resuming from a block on another fork rewinds to the fork point
+        let mut stream = make_block_feed_stream(&chainstate, &b, &d);
+        assert!(chainstate.load_next_block_feed_frame(&mut stream).unwrap());
+        let frame = BlockFeedFrame::consensus_deserialize(&mut &stream.frame_buf[..]).unwrap();
+        assert_eq!(
+            frame,
+            BlockFeedFrame::Rollback {
+                from: b.index_block_hash(),
+                cursor: Some(a.index_block_hash()),
+                height: 1,
+            }
+        );
+        assert_eq!(stream.next_height, 2);
+        assert_eq!(stream.cursor, Some(a.index_block_hash()));
+        assert_eq!(stream.num_blocks, 0);
+
+        // ...after which the cursor is canonical again
+        assert!(chainstate
+            .check_block_feed_fork(&mut stream)
+            .unwrap()
+            .is_none());
+
+        // a reorg under a live feed is detected once the feed learns of the new tip
+        let mut stream = make_block_feed_stream(&chainstate, &b, &b);
+        assert!(chainstate
+            .check_block_feed_fork(&mut stream)
+            .unwrap()
+            .is_none());
+        let mut cursor = StreamCursor::BlockFeed(stream);
+        cursor.set_chain_tip(&d.index_block_hash(), d.stacks_block_height);
+        let mut stream = match cursor {
+            StreamCursor::BlockFeed(stream) => stream,
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            chainstate.check_block_feed_fork(&mut stream).unwrap(),
+            Some(BlockFeedFrame::Rollback {
+                from: b.index_block_hash(),
+                cursor: Some(a.index_block_hash()),
+                height: 1,
+            })
+        );
+
+        // forks that only share the boot block roll back to it
+        let mut stream = make_block_feed_stream(&chainstate, &e, &d);
+        assert_eq!(
+            chainstate.check_block_feed_fork(&mut stream).unwrap(),
+            Some(BlockFeedFrame::Rollback {
+                from: e.index_block_hash(),
+                cursor: Some(genesis.index_block_hash()),
+                height: 0,
+            })
+        );
+        assert_eq!(stream.next_height, 1);
+    }
+
     // TODO(test): test multiple anchored blocks confirming the same microblock stream (in the same
     // place, and different places, with/without orphans)
     // TODO(test): process_next_staging_block
@@ -52,6 +52,13 @@ use clarity::vm::database::ClarityDatabase;
 use clarity::vm::contracts::Contract;
 
 use crate::clarity_vm::clarity::ClarityConnection;
+use crate::clarity_vm::reanalysis::{
+    run_contract_reanalysis_step, ContractReanalysisDB, ReanalysisJob, CONTRACT_REANALYSIS_DB_NAME,
+};
+
+use clarity::vm::database::BurnStateDB;
+
+use stacks_common::types::chainstate::StacksBlockId;
 
 impl StacksChainState {
     pub fn get_contract<T: ClarityConnection>(
@@ -85,4 +92,52 @@ impl StacksChainState {
             })
             .map_err(Error::ClarityError)
     }
+
+    /// Look up the value of a `define-constant` in a deployed contract, as it was evaluated and
+    /// stored when the contract was instantiated.
+    /// Returns Ok(None) if the contract does not exist, or if it defines no such constant.
+    pub fn get_constant<T: ClarityConnection>(
+        clarity_tx: &mut T,
+        contract_id: &QualifiedContractIdentifier,
+        constant_name: &str,
+    ) -> Result<Option<Value>, Error> {
+        let contract = match StacksChainState::get_contract(clarity_tx, contract_id)? {
+            Some(c) => c,
+            None => {
+                return Ok(None);
+            }
+        };
+        Ok(contract
+            .contract_context
+            .lookup_variable(constant_name)
+            .cloned())
+    }
+
+    /// Path to the contract re-analysis database for this chainstate
+    pub fn contract_reanalysis_db_path(&self) -> PathBuf {
+        Path::new(&self.root_path).join(CONTRACT_REANALYSIS_DB_NAME)
+    }
+
+    /// Run one batch of the re-analysis of deployed contracts under the type rules of the epoch
+    /// that `tip` was evaluated in.  See `clarity_vm::reanalysis` for details.
+    /// Returns Err(Error::NoSuchBlockError) if `tip` is not a processed Stacks block.
+    pub fn run_contract_reanalysis_step(
+        &mut self,
+        reanalysis_db: &mut ContractReanalysisDB,
+        burn_dbconn: &dyn BurnStateDB,
+        tip: &StacksBlockId,
+        max_contracts: u32,
+    ) -> Result<ReanalysisJob, Error> {
+        if !StacksChainState::has_stacks_block(self.db(), tip)? {
+            return Err(Error::NoSuchBlockError);
+        }
+        run_contract_reanalysis_step(
+            reanalysis_db,
+            &mut self.clarity_state,
+            &self.state_index,
+            burn_dbconn,
+            tip,
+            max_contracts,
+        )
+    }
 }
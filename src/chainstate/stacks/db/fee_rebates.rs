@@ -0,0 +1,247 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fee rebate accounting, an experiment in uniform-price fee markets.
+//!
+//! Today each transaction pays the fee it offered.  Under a uniform-price market, each
+//! transaction in a block would instead pay the block's clearing fee rate (the lowest fee rate
+//! the block accepted) for the cost it actually consumed, and the rest of its fee would be
+//! rebated.  In other words, a transaction's fee buys a cost budget at the clearing rate, and the
+//! unused part of that budget is refunded.
+//!
+//! If the node opts in with `StacksChainState::set_fee_rebate_accounting()`, then for blocks
+//! evaluated in an epoch with `StacksEpochId::fee_rebate_accounting()`, the rebate each
+//! transaction *would* have received is recorded in the `fee_rebates` ledger as the block is
+//! processed.  Nothing is transferred: the ledger has no effect on consensus, and only exists so
+//! the effect of such a fee market can be measured against real blocks.
+//!
+//! The ledger gains one row per transaction in every processed block (including blocks that
+//! later fall off the canonical fork), and rows are never deleted, so it grows with the chain
+//! for as long as accounting is enabled.  Delete the rows to reclaim the space; nothing else
+//! reads them.
+//!
+//! Costs are measured as scalars with the same metric the miner uses to rank transactions by fee
+//! rate, so the recorded fee rates are comparable with the mempool's.
+
+use std::cmp;
+
+use rusqlite::types::ToSql;
+use rusqlite::{Connection, Row};
+
+use crate::chainstate::stacks::db::{StacksChainState, StacksEpochReceipt};
+use crate::chainstate::stacks::events::TransactionOrigin;
+use crate::chainstate::stacks::{Error, TransactionPayload, MAX_BLOCK_LEN};
+use crate::cost_estimates::metrics::{CostMetric, ProportionalDotProduct};
+use crate::util_lib::db::{query_rows, u64_to_sql, DBTx, Error as db_error, FromColumn, FromRow};
+use clarity::vm::costs::ExecutionCost;
+use clarity::vm::types::StacksAddressExtensions;
+use stacks_common::types::chainstate::StacksBlockId;
+
+use crate::burnchains::Txid;
+
+/// A rebate credit recorded for one transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeRebate {
+    pub txid: Txid,
+    pub index_block_hash: StacksBlockId,
+    /// the account that paid the fee (the sponsor, if there is one), as a principal string
+    pub payer: String,
+    pub fee: u64,
+    /// scalar cost the transaction consumed
+    pub cost_scalar: u64,
+    /// lowest fee rate (fee per unit of scalar cost) among the block's transactions
+    pub clearing_fee_rate: f64,
+    /// the part of `fee` that would not have been paid at the clearing fee rate
+    pub rebate: u64,
+}
+
+impl FromRow<FeeRebate> for FeeRebate {
+    fn from_row<'a>(row: &'a Row) -> Result<FeeRebate, db_error> {
+        let txid = Txid::from_column(row, "txid")?;
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let payer: String = row.get_unwrap("payer");
+        let fee = u64::from_column(row, "fee")?;
+        let cost_scalar = u64::from_column(row, "cost_scalar")?;
+        let clearing_fee_rate: f64 = row.get_unwrap("clearing_fee_rate");
+        let rebate = u64::from_column(row, "rebate")?;
+        Ok(FeeRebate {
+            txid,
+            index_block_hash,
+            payer,
+            fee,
+            cost_scalar,
+            clearing_fee_rate,
+            rebate,
+        })
+    }
+}
+
+impl FeeRebate {
+    /// Compute the rebate credits for a processed block's user transactions, including those in
+    /// the microblock stream it confirms.  Coinbases and burnchain operations pay no fee, and
+    /// get no rebate.
+    pub fn from_epoch_receipt(
+        receipt: &StacksEpochReceipt,
+        block_limit: &ExecutionCost,
+    ) -> Vec<FeeRebate> {
+        let metric = ProportionalDotProduct::new(MAX_BLOCK_LEN as u64);
+        let index_block_hash = receipt.header.index_block_hash();
+
+        // (txid, payer, fee, scalar cost)
+        let mut charges = vec![];
+        for tx_receipt in receipt.tx_receipts.iter() {
+            let tx = match tx_receipt.transaction {
+                TransactionOrigin::Stacks(ref tx) => tx,
+                TransactionOrigin::Burn(..) => continue,
+            };
+            if let TransactionPayload::Coinbase(..) = tx.payload {
+                continue;
+            }
+            let payer = tx
+                .sponsor_address()
+                .unwrap_or_else(|| tx.origin_address())
+                .to_account_principal();
+            let cost_scalar =
+                metric.from_cost_and_len(&tx_receipt.execution_cost, block_limit, tx.tx_len());
+            charges.push((tx.txid(), payer.to_string(), tx.get_tx_fee(), cost_scalar));
+        }
+
+        let clearing_fee_rate = charges
+            .iter()
+            .map(|(_, _, fee, cost_scalar)| *fee as f64 / cmp::max(*cost_scalar, 1) as f64)
+            .fold(None, |lowest: Option<f64>, rate| match lowest {
+                Some(lowest) if lowest <= rate => Some(lowest),
+                _ => Some(rate),
+            })
+            .unwrap_or(0.0);
+
+        charges
+            .into_iter()
+            .map(|(txid, payer, fee, cost_scalar)| {
+                let clearing_fee = (clearing_fee_rate * cost_scalar as f64).ceil();
+                let rebate = if clearing_fee >= fee as f64 {
+                    0
+                } else {
+                    fee - clearing_fee as u64
+                };
+                FeeRebate {
+                    txid,
+                    index_block_hash: index_block_hash.clone(),
+                    payer,
+                    fee,
+                    cost_scalar,
+                    clearing_fee_rate,
+                    rebate,
+                }
+            })
+            .collect()
+    }
+}
+
+impl StacksChainState {
+    /// Record a processed block's rebate credits in the fee rebate ledger
+    pub fn store_fee_rebates(tx: &DBTx, rebates: &[FeeRebate]) -> Result<(), Error> {
+        for rebate in rebates.iter() {
+            let sql = "INSERT OR REPLACE INTO fee_rebates
+                (txid, index_block_hash, payer, fee, cost_scalar, clearing_fee_rate, rebate)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
+            let args: &[&dyn ToSql] = &[
+                &rebate.txid,
+                &rebate.index_block_hash,
+                &rebate.payer,
+                &u64_to_sql(rebate.fee)?,
+                &u64_to_sql(rebate.cost_scalar)?,
+                &rebate.clearing_fee_rate,
+                &u64_to_sql(rebate.rebate)?,
+            ];
+            tx.execute(sql, args)?;
+        }
+        Ok(())
+    }
+
+    /// Get the rebate credits recorded for a processed block
+    pub fn get_fee_rebates_in_block(
+        conn: &Connection,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Vec<FeeRebate>, Error> {
+        let sql = "SELECT * FROM fee_rebates WHERE index_block_hash = ?1 ORDER BY rowid";
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        Ok(query_rows(conn, sql, args)?)
+    }
+
+    /// Get all rebate credits recorded for a payer.  Credits are recorded for blocks in every
+    /// fork, so callers that want the credits in one fork must filter them by block.
+    pub fn get_fee_rebates_for_payer(
+        conn: &Connection,
+        payer: &str,
+    ) -> Result<Vec<FeeRebate>, Error> {
+        let sql = "SELECT * FROM fee_rebates WHERE payer = ?1 ORDER BY rowid";
+        let args: &[&dyn ToSql] = &[&payer];
+        Ok(query_rows(conn, sql, args)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::stacks::db::test::instantiate_chainstate;
+
+    #[test]
+    fn test_fee_rebate_ledger() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+        let index_block_hash = StacksBlockId([0x01; 32]);
+        let rebates = vec![
+            FeeRebate {
+                txid: Txid([0x02; 32]),
+                index_block_hash: index_block_hash.clone(),
+                payer: "ST2ZRX0K27GW0SP3GJCEMHD95TQGJMKB7G9Y0X1MH".to_string(),
+                fee: 1000,
+                cost_scalar: 100,
+                clearing_fee_rate: 2.0,
+                rebate: 800,
+            },
+            FeeRebate {
+                txid: Txid([0x03; 32]),
+                index_block_hash: index_block_hash.clone(),
+                payer: "ST1SJ3DTE5DN7X54YDH5D64R3BCB6A2AG2ZQ8YPD5".to_string(),
+                fee: 200,
+                cost_scalar: 100,
+                clearing_fee_rate: 2.0,
+                rebate: 0,
+            },
+        ];
+
+        let tx = chainstate.index_tx_begin().unwrap();
+        StacksChainState::store_fee_rebates(&tx, &rebates).unwrap();
+        tx.commit().unwrap();
+
+        assert_eq!(
+            StacksChainState::get_fee_rebates_in_block(chainstate.db(), &index_block_hash).unwrap(),
+            rebates
+        );
+        assert_eq!(
+            StacksChainState::get_fee_rebates_for_payer(chainstate.db(), &rebates[0].payer)
+                .unwrap(),
+            vec![rebates[0].clone()]
+        );
+        assert!(StacksChainState::get_fee_rebates_in_block(
+            chainstate.db(),
+            &StacksBlockId([0x04; 32])
+        )
+        .unwrap()
+        .is_empty());
+    }
+}
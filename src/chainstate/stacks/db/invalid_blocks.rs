@@ -0,0 +1,402 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Persistent record of the anchored blocks this node found to be invalid, and which version of
+//! the node software found them invalid.
+//!
+//! An invalid block is orphaned along with all of its descendants, and its data is deleted, so the
+//! node never considers it again.  If the verdict was caused by a bug in the node, then the node
+//! stays on the wrong side of a chain split even after it is upgraded to a release that fixes the
+//! bug.  To avoid this, each time the chainstate is opened by a different version of the node than
+//! the one that found a block invalid, the node forgets that the block and its descendants were
+//! orphaned.  The block is then downloaded and validated again as if it had never been seen.
+//!
+//! A block that is still invalid is simply found to be invalid again, and recorded under the new
+//! version.
+
+use rusqlite::types::ToSql;
+use rusqlite::{Connection, Row, NO_PARAMS};
+
+use crate::chainstate::stacks::db::blocks::StagingBlock;
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::chainstate::stacks::Error;
+use crate::chainstate::stacks::StacksBlockHeader;
+use crate::types::chainstate::{BlockHeaderHash, ConsensusHash, StacksBlockId};
+use crate::util_lib::db::{
+    query_row, query_rows, u64_to_sql, DBTx, Error as db_error, FromColumn, FromRow,
+};
+use stacks_common::util::get_epoch_time_secs;
+
+/// An anchored block this node found to be invalid
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidBlock {
+    pub index_block_hash: StacksBlockId,
+    pub consensus_hash: ConsensusHash,
+    pub anchored_block_hash: BlockHeaderHash,
+    /// why the block was invalid
+    pub reason: String,
+    /// the version of the node software that found the block invalid
+    pub node_version: String,
+    /// when the block was found invalid, in seconds since the epoch
+    pub invalidated_at: u64,
+}
+
+impl FromRow<InvalidBlock> for InvalidBlock {
+    fn from_row<'a>(row: &'a Row) -> Result<InvalidBlock, db_error> {
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let consensus_hash = ConsensusHash::from_column(row, "consensus_hash")?;
+        let anchored_block_hash = BlockHeaderHash::from_column(row, "anchored_block_hash")?;
+        let reason: String = row.get_unwrap("reason");
+        let node_version: String = row.get_unwrap("node_version");
+        let invalidated_at = u64::from_column(row, "invalidated_at")?;
+        Ok(InvalidBlock {
+            index_block_hash,
+            consensus_hash,
+            anchored_block_hash,
+            reason,
+            node_version,
+            invalidated_at,
+        })
+    }
+}
+
+impl StacksChainState {
+    /// The version of the node software that is running, which is recorded alongside each block
+    /// it finds invalid
+    pub fn node_version() -> &'static str {
+        option_env!("STACKS_NODE_VERSION")
+            .or(option_env!("CARGO_PKG_VERSION"))
+            .unwrap_or("0.0.0.0")
+    }
+
+    /// Record that an anchored block was found to be invalid by this version of the node.  If the
+    /// block was already recorded, the record is replaced.
+    pub fn record_invalid_block(
+        tx: &DBTx,
+        consensus_hash: &ConsensusHash,
+        anchored_block_hash: &BlockHeaderHash,
+        reason: &str,
+    ) -> Result<(), Error> {
+        let invalid = InvalidBlock {
+            index_block_hash: StacksBlockHeader::make_index_block_hash(
+                consensus_hash,
+                anchored_block_hash,
+            ),
+            consensus_hash: consensus_hash.clone(),
+            anchored_block_hash: anchored_block_hash.clone(),
+            reason: reason.to_string(),
+            node_version: StacksChainState::node_version().to_string(),
+            invalidated_at: get_epoch_time_secs(),
+        };
+        StacksChainState::insert_invalid_block(tx, &invalid)
+    }
+
+    fn insert_invalid_block(tx: &DBTx, invalid: &InvalidBlock) -> Result<(), Error> {
+        let sql = "INSERT OR REPLACE INTO invalid_blocks
+            (index_block_hash, consensus_hash, anchored_block_hash, reason, node_version, invalidated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)";
+        let args: &[&dyn ToSql] = &[
+            &invalid.index_block_hash,
+            &invalid.consensus_hash,
+            &invalid.anchored_block_hash,
+            &invalid.reason,
+            &invalid.node_version,
+            &u64_to_sql(invalid.invalidated_at)?,
+        ];
+        tx.execute(sql, args)?;
+        Ok(())
+    }
+
+    /// Get the record of an invalid block, if this node found it to be invalid
+    pub fn get_invalid_block(
+        conn: &Connection,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<InvalidBlock>, Error> {
+        let sql = "SELECT * FROM invalid_blocks WHERE index_block_hash = ?1";
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        Ok(query_row(conn, sql, args)?)
+    }
+
+    /// Get all recorded invalid blocks, oldest first
+    pub fn get_invalid_blocks(conn: &Connection) -> Result<Vec<InvalidBlock>, Error> {
+        let sql = "SELECT * FROM invalid_blocks ORDER BY invalidated_at, rowid";
+        Ok(query_rows(conn, sql, NO_PARAMS)?)
+    }
+
+    /// Forget that an invalid block and all of its descendants were orphaned, so they will be
+    /// downloaded and processed again.  Any leader key ban the block caused is lifted, and the
+    /// block's record is dropped.
+    fn requeue_invalid_block(tx: &mut DBTx, invalid: &InvalidBlock) -> Result<(), Error> {
+        StacksChainState::forget_orphaned_epoch_data(
+            tx,
+            &invalid.consensus_hash,
+            &invalid.anchored_block_hash,
+        )?;
+
+        // descendants whose data was already deleted are forgotten as well.  Descendants that
+        // were orphaned but not yet deleted are kept, and become attachable once their parent is
+        // processed again.
+        let mut pending = vec![(
+            invalid.consensus_hash.clone(),
+            invalid.anchored_block_hash.clone(),
+        )];
+        while let Some((consensus_hash, anchored_block_hash)) = pending.pop() {
+            let sql = "SELECT * FROM staging_blocks WHERE parent_consensus_hash = ?1 AND parent_anchored_block_hash = ?2 AND orphaned = 1";
+            let args: &[&dyn ToSql] = &[&consensus_hash, &anchored_block_hash];
+            let children: Vec<StagingBlock> = query_rows(tx, sql, args)?;
+            for child in children.into_iter() {
+                if child.processed {
+                    StacksChainState::forget_orphaned_epoch_data(
+                        tx,
+                        &child.consensus_hash,
+                        &child.anchored_block_hash,
+                    )?;
+                } else {
+                    let sql = "UPDATE staging_blocks SET orphaned = 0, attachable = 0 WHERE consensus_hash = ?1 AND anchored_block_hash = ?2";
+                    let args: &[&dyn ToSql] = &[&child.consensus_hash, &child.anchored_block_hash];
+                    tx.execute(sql, args)?;
+                }
+                pending.push((child.consensus_hash, child.anchored_block_hash));
+            }
+        }
+
+        let sql =
+            "DELETE FROM banned_leader_keys WHERE consensus_hash = ?1 AND anchored_block_hash = ?2";
+        let args: &[&dyn ToSql] = &[&invalid.consensus_hash, &invalid.anchored_block_hash];
+        tx.execute(sql, args)?;
+
+        let sql = "DELETE FROM invalid_blocks WHERE index_block_hash = ?1";
+        let args: &[&dyn ToSql] = &[&invalid.index_block_hash];
+        tx.execute(sql, args)?;
+        Ok(())
+    }
+
+    /// Requeue every invalid block that was found to be invalid by a version of the node other
+    /// than `node_version`, so it gets validated again.  Returns the requeued blocks.
+    pub fn requeue_invalid_blocks(
+        tx: &mut DBTx,
+        node_version: &str,
+    ) -> Result<Vec<InvalidBlock>, Error> {
+        let sql =
+            "SELECT * FROM invalid_blocks WHERE node_version != ?1 ORDER BY invalidated_at, rowid";
+        let args: &[&dyn ToSql] = &[&node_version];
+        let invalid_blocks: Vec<InvalidBlock> = query_rows(tx, sql, args)?;
+        for invalid in invalid_blocks.iter() {
+            info!(
+                "Requeue block {}/{} ({}) for revalidation: found invalid by version {}, now running version {}: {}",
+                &invalid.consensus_hash,
+                &invalid.anchored_block_hash,
+                &invalid.index_block_hash,
+                &invalid.node_version,
+                node_version,
+                &invalid.reason
+            );
+            StacksChainState::requeue_invalid_block(tx, invalid)?;
+        }
+        Ok(invalid_blocks)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::stacks::db::banned_leader_keys::BannedLeaderKey;
+    use crate::chainstate::stacks::db::blocks::test::*;
+    use crate::chainstate::stacks::db::test::instantiate_chainstate;
+    use crate::chainstate::stacks::*;
+    use crate::core::*;
+    use stacks_common::util::vrf::{VRFPrivateKey, VRFPublicKey};
+
+    /// Load a staging block's row, whether or not it is processed or orphaned
+    fn load_staging_block_row(
+        chainstate: &StacksChainState,
+        consensus_hash: &ConsensusHash,
+        anchored_block_hash: &BlockHeaderHash,
+    ) -> StagingBlock {
+        let sql =
+            "SELECT * FROM staging_blocks WHERE consensus_hash = ?1 AND anchored_block_hash = ?2";
+        let args: &[&dyn ToSql] = &[consensus_hash, anchored_block_hash];
+        query_row(chainstate.db(), sql, args).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_requeue_invalid_blocks() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+        let privk = StacksPrivateKey::from_hex(
+            "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+        )
+        .unwrap();
+
+        // block_1 <- block_2 <- block_3, and block_2 is found invalid
+        let mut block_1 = make_empty_coinbase_block(&privk);
+        let mut block_2 = make_empty_coinbase_block(&privk);
+        let mut block_3 = make_empty_coinbase_block(&privk);
+
+        block_1.header.parent_block = FIRST_STACKS_BLOCK_HASH;
+        block_2.header.parent_block = block_1.block_hash();
+        block_3.header.parent_block = block_2.block_hash();
+
+        let consensus_hashes = vec![
+            ConsensusHash([2u8; 20]),
+            ConsensusHash([3u8; 20]),
+            ConsensusHash([4u8; 20]),
+        ];
+        let parent_consensus_hashes = vec![
+            FIRST_BURNCHAIN_CONSENSUS_HASH,
+            ConsensusHash([2u8; 20]),
+            ConsensusHash([3u8; 20]),
+        ];
+        let blocks = &[&block_1, &block_2, &block_3];
+
+        for ((block, consensus_hash), parent_consensus_hash) in blocks
+            .iter()
+            .zip(&consensus_hashes)
+            .zip(&parent_consensus_hashes)
+        {
+            store_staging_block(
+                &mut chainstate,
+                consensus_hash,
+                block,
+                parent_consensus_hash,
+                1,
+                2,
+            );
+        }
+
+        set_block_processed(
+            &mut chainstate,
+            &consensus_hashes[0],
+            &block_1.block_hash(),
+            true,
+        );
+        set_block_processed(
+            &mut chainstate,
+            &consensus_hashes[1],
+            &block_2.block_hash(),
+            false,
+        );
+
+        let leader_key = VRFPublicKey::from_private(&VRFPrivateKey::new());
+        {
+            let tx = chainstate.write_tx_begin().unwrap();
+            StacksChainState::record_invalid_block(
+                &tx,
+                &consensus_hashes[1],
+                &block_2.block_hash(),
+                "bad block",
+            )
+            .unwrap();
+            StacksChainState::ban_leader_key(
+                &tx,
+                &BannedLeaderKey {
+                    public_key: leader_key.clone(),
+                    consensus_hash: consensus_hashes[1].clone(),
+                    anchored_block_hash: block_2.block_hash(),
+                    reason: "bad block".to_string(),
+                    banned_at: get_epoch_time_secs(),
+                },
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        }
+
+        let block_2_index_hash =
+            StacksBlockHeader::make_index_block_hash(&consensus_hashes[1], &block_2.block_hash());
+        let invalid = StacksChainState::get_invalid_block(chainstate.db(), &block_2_index_hash)
+            .unwrap()
+            .unwrap();
+        assert_eq!(invalid.reason, "bad block");
+        assert_eq!(invalid.node_version, StacksChainState::node_version());
+
+        // block_2 is orphaned, and so is its child
+        let staging_block_2 =
+            load_staging_block_row(&chainstate, &consensus_hashes[1], &block_2.block_hash());
+        assert!(staging_block_2.orphaned);
+        let staging_block_3 =
+            load_staging_block_row(&chainstate, &consensus_hashes[2], &block_3.block_hash());
+        assert!(staging_block_3.orphaned);
+
+        // the same version does not requeue anything
+        {
+            let mut tx = chainstate.write_tx_begin().unwrap();
+            assert!(StacksChainState::requeue_invalid_blocks(
+                &mut tx,
+                StacksChainState::node_version()
+            )
+            .unwrap()
+            .is_empty());
+            tx.commit().unwrap();
+        }
+        assert_eq!(
+            StacksChainState::get_invalid_blocks(chainstate.db()).unwrap(),
+            vec![invalid.clone()]
+        );
+
+        // a new version requeues block_2, and un-orphans block_3
+        {
+            let mut tx = chainstate.write_tx_begin().unwrap();
+            assert_eq!(
+                StacksChainState::requeue_invalid_blocks(&mut tx, "99.0.0.0").unwrap(),
+                vec![invalid.clone()]
+            );
+            tx.commit().unwrap();
+        }
+
+        assert!(StacksChainState::get_invalid_blocks(chainstate.db())
+            .unwrap()
+            .is_empty());
+        assert!(!StacksChainState::is_leader_key_banned(chainstate.db(), &leader_key).unwrap());
+        assert!(StacksChainState::get_staging_block_status(
+            chainstate.db(),
+            &consensus_hashes[1],
+            &block_2.block_hash()
+        )
+        .unwrap()
+        .is_none());
+        assert!(!StacksChainState::has_stored_block(
+            chainstate.db(),
+            &chainstate.blocks_path,
+            &consensus_hashes[1],
+            &block_2.block_hash()
+        )
+        .unwrap());
+
+        let staging_block_3 =
+            load_staging_block_row(&chainstate, &consensus_hashes[2], &block_3.block_hash());
+        assert!(!staging_block_3.orphaned);
+        assert!(!staging_block_3.processed);
+        assert!(!staging_block_3.attachable);
+
+        // block_2 can be stored again, and block_3 becomes attachable once it is processed
+        store_staging_block(
+            &mut chainstate,
+            &consensus_hashes[1],
+            &block_2,
+            &parent_consensus_hashes[1],
+            1,
+            2,
+        );
+        set_block_processed(
+            &mut chainstate,
+            &consensus_hashes[1],
+            &block_2.block_hash(),
+            true,
+        );
+        let staging_block_3 =
+            load_staging_block_row(&chainstate, &consensus_hashes[2], &block_3.block_hash());
+        assert!(staging_block_3.attachable);
+    }
+}
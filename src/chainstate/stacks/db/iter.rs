@@ -0,0 +1,364 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Streaming iterators over a fork's history, for export, analytics, and replay tooling.
+//!
+//! `StacksChainState::iter_blocks()`, `iter_transactions()`, and `iter_receipts()` walk the
+//! ancestors of a chain tip whose heights are in a given range, lowest first, so callers do not
+//! need to know how headers, block files, and logged receipts are stored.  Headers and receipts
+//! are looked up a batch at a time.  Block bodies are read from disk by a background thread,
+//! which stays up to a batch ahead of the caller.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread;
+
+use rusqlite::Row;
+
+use crate::burnchains::Txid;
+use crate::chainstate::burn::ConsensusHash;
+use crate::chainstate::stacks::db::{StacksChainState, StacksHeaderInfo};
+use crate::chainstate::stacks::{Error, StacksBlock, StacksTransaction};
+use crate::codec::StacksMessageCodec;
+use crate::core::FIRST_STACKS_BLOCK_HASH;
+use crate::util_lib::db::{query_rows, Error as db_error, FromColumn, FromRow};
+use stacks_common::types::chainstate::{BlockHeaderHash, StacksBlockId};
+use stacks_common::util::hash::hex_bytes;
+
+/// Default number of blocks an iterator looks up at a time
+pub const DEFAULT_ITER_BATCH_SIZE: usize = 64;
+
+/// Find the headers of the ancestors of `tip` at each height in `heights`, in height order.
+/// Stops at the first height with no ancestor (i.e. above the tip), in which case the returned
+/// flag is true.  The boot block has no body, and is skipped.
+fn load_ancestor_headers(
+    chainstate: &StacksChainState,
+    tip: &StacksBlockId,
+    heights: Range<u64>,
+) -> Result<(Vec<StacksHeaderInfo>, bool), Error> {
+    let index_conn = chainstate.index_conn()?;
+    let mut headers = vec![];
+    for height in heights {
+        let block_id = match index_conn.get_ancestor_block_hash(height, tip)? {
+            Some(block_id) => block_id,
+            None => {
+                return Ok((headers, true));
+            }
+        };
+        let header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            &block_id,
+        )?
+        .ok_or(Error::NoSuchBlockError)?;
+        if header.anchored_header.block_hash() == FIRST_STACKS_BLOCK_HASH {
+            continue;
+        }
+        headers.push(header);
+    }
+    Ok((headers, false))
+}
+
+/// Iterator over the blocks of a fork, as (header, block) pairs
+pub struct StacksBlockIter<'a> {
+    chainstate: &'a StacksChainState,
+    tip: StacksBlockId,
+    /// heights that have not been requested yet
+    heights: Range<u64>,
+    batch_size: usize,
+    /// headers of the blocks requested from the prefetch thread, in the order they were requested
+    requested: VecDeque<StacksHeaderInfo>,
+    request_tx: SyncSender<(ConsensusHash, BlockHeaderHash)>,
+    block_rx: Receiver<Result<Option<StacksBlock>, Error>>,
+}
+
+impl<'a> StacksBlockIter<'a> {
+    fn new(
+        chainstate: &'a StacksChainState,
+        tip: &StacksBlockId,
+        heights: Range<u64>,
+        batch_size: usize,
+    ) -> Result<StacksBlockIter<'a>, Error> {
+        let batch_size = batch_size.max(1);
+        let (request_tx, request_rx) = sync_channel::<(ConsensusHash, BlockHeaderHash)>(batch_size);
+        let (block_tx, block_rx) = sync_channel(batch_size);
+        let blocks_path = chainstate.blocks_path.clone();
+
+        // the thread exits once either end of the iterator's channels is dropped
+        thread::Builder::new()
+            .name("chainstate-iter-prefetch".to_string())
+            .spawn(move || {
+                while let Ok((consensus_hash, block_hash)) = request_rx.recv() {
+                    let block =
+                        StacksChainState::load_block(&blocks_path, &consensus_hash, &block_hash);
+                    if block_tx.send(block).is_err() {
+                        break;
+                    }
+                }
+            })
+            .map_err(Error::ReadError)?;
+
+        Ok(StacksBlockIter {
+            chainstate,
+            tip: tip.clone(),
+            heights,
+            batch_size,
+            requested: VecDeque::new(),
+            request_tx,
+            block_rx,
+        })
+    }
+
+    /// Whenever at most half a batch of blocks is outstanding, ask the prefetch thread for the
+    /// next batch.  At most `batch_size` blocks are ever outstanding, so requests never block.
+    fn prefetch(&mut self) -> Result<(), Error> {
+        while self.requested.len() <= self.batch_size / 2 && self.heights.start < self.heights.end {
+            self.prefetch_batch()?;
+        }
+        Ok(())
+    }
+
+    fn prefetch_batch(&mut self) -> Result<(), Error> {
+        let num_heights = (self.batch_size - self.requested.len()) as u64;
+        let batch_end = self.heights.end.min(self.heights.start + num_heights);
+        let (headers, past_tip) =
+            load_ancestor_headers(self.chainstate, &self.tip, self.heights.start..batch_end)?;
+        self.heights.start = if past_tip {
+            self.heights.end
+        } else {
+            batch_end
+        };
+
+        for header in headers.into_iter() {
+            self.request_tx
+                .send((
+                    header.consensus_hash.clone(),
+                    header.anchored_header.block_hash(),
+                ))
+                .map_err(|_| Error::ChannelClosed("Block prefetch thread exited".to_string()))?;
+            self.requested.push_back(header);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for StacksBlockIter<'a> {
+    type Item = Result<(StacksHeaderInfo, StacksBlock), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(e) = self.prefetch() {
+            // stop after reporting the error
+            self.heights.start = self.heights.end;
+            return Some(Err(e));
+        }
+        let header = self.requested.pop_front()?;
+        let block = match self.block_rx.recv() {
+            Ok(Ok(Some(block))) => block,
+            Ok(Ok(None)) => {
+                // zero-sized block file, i.e. the block was found to be invalid
+                return Some(Err(Error::NoSuchBlockError));
+            }
+            Ok(Err(e)) => {
+                return Some(Err(e));
+            }
+            Err(_) => {
+                return Some(Err(Error::ChannelClosed(
+                    "Block prefetch thread exited".to_string(),
+                )));
+            }
+        };
+        Some(Ok((header, block)))
+    }
+}
+
+/// An anchored block's transaction, and where it was mined
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainstateTransaction {
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    /// position of the transaction in its block
+    pub tx_index: usize,
+    pub tx: StacksTransaction,
+}
+
+/// Iterator over the transactions in the anchored blocks of a fork
+pub struct StacksTransactionIter<'a> {
+    blocks: StacksBlockIter<'a>,
+    /// transactions of the current block that have not been returned yet
+    pending: VecDeque<ChainstateTransaction>,
+}
+
+impl<'a> Iterator for StacksTransactionIter<'a> {
+    type Item = Result<ChainstateTransaction, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            let (header, block) = match self.blocks.next()? {
+                Ok(header_and_block) => header_and_block,
+                Err(e) => {
+                    return Some(Err(e));
+                }
+            };
+            let index_block_hash = header.index_block_hash();
+            for (tx_index, tx) in block.txs.into_iter().enumerate() {
+                self.pending.push_back(ChainstateTransaction {
+                    index_block_hash: index_block_hash.clone(),
+                    block_height: header.stacks_block_height,
+                    tx_index,
+                    tx,
+                });
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+/// A transaction receipt logged to the `transactions` table (i.e. with
+/// `STACKS_TRANSACTION_LOG=1`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedTransactionReceipt {
+    pub txid: Txid,
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    /// None for burnchain operations
+    pub tx: Option<StacksTransaction>,
+    /// the transaction's result, as a printed Clarity value
+    pub result: String,
+}
+
+impl FromRow<LoggedTransactionReceipt> for LoggedTransactionReceipt {
+    fn from_row<'a>(row: &'a Row) -> Result<LoggedTransactionReceipt, db_error> {
+        let txid = Txid::from_column(row, "txid")?;
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let tx_hex: String = row.get_unwrap("tx_hex");
+        let result: String = row.get_unwrap("result");
+
+        // burnchain operations are logged as BTC(txid)
+        let tx = if tx_hex.starts_with("BTC(") {
+            None
+        } else {
+            let tx_bytes = hex_bytes(&tx_hex).map_err(|_| db_error::ParseError)?;
+            let tx = StacksTransaction::consensus_deserialize(&mut &tx_bytes[..])
+                .map_err(|_| db_error::ParseError)?;
+            Some(tx)
+        };
+
+        Ok(LoggedTransactionReceipt {
+            txid,
+            index_block_hash,
+            block_height: 0,
+            tx,
+            result,
+        })
+    }
+}
+
+/// Iterator over the logged transaction receipts of a fork
+pub struct StacksReceiptIter<'a> {
+    chainstate: &'a StacksChainState,
+    tip: StacksBlockId,
+    heights: Range<u64>,
+    batch_size: usize,
+    pending: VecDeque<LoggedTransactionReceipt>,
+}
+
+impl<'a> StacksReceiptIter<'a> {
+    /// Load the receipts of the next batch of blocks that have any
+    fn load_next_batch(&mut self) -> Result<(), Error> {
+        while self.pending.is_empty() && self.heights.start < self.heights.end {
+            let batch_end = self
+                .heights
+                .end
+                .min(self.heights.start + self.batch_size as u64);
+            let (headers, past_tip) =
+                load_ancestor_headers(self.chainstate, &self.tip, self.heights.start..batch_end)?;
+            self.heights.start = if past_tip {
+                self.heights.end
+            } else {
+                batch_end
+            };
+
+            for header in headers.iter() {
+                let index_block_hash = header.index_block_hash();
+                let receipts: Vec<LoggedTransactionReceipt> = query_rows(
+                    self.chainstate.db(),
+                    "SELECT * FROM transactions WHERE index_block_hash = ?1 ORDER BY id",
+                    &[&index_block_hash],
+                )?;
+                for mut receipt in receipts.into_iter() {
+                    receipt.block_height = header.stacks_block_height;
+                    self.pending.push_back(receipt);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for StacksReceiptIter<'a> {
+    type Item = Result<LoggedTransactionReceipt, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(e) = self.load_next_batch() {
+            self.heights.start = self.heights.end;
+            return Some(Err(e));
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+impl StacksChainState {
+    /// Iterate over the blocks of the fork ending at `tip` whose heights are in `heights`, lowest
+    /// first, as (header, block) pairs.  Iteration ends early if the tip is lower than
+    /// `heights.end`.
+    pub fn iter_blocks<'a>(
+        &'a self,
+        tip: &StacksBlockId,
+        heights: Range<u64>,
+    ) -> Result<StacksBlockIter<'a>, Error> {
+        StacksBlockIter::new(self, tip, heights, DEFAULT_ITER_BATCH_SIZE)
+    }
+
+    /// Iterate over the transactions of the anchored blocks of the fork ending at `tip` whose
+    /// heights are in `heights`, lowest first.  Microblock transactions are not included.
+    pub fn iter_transactions<'a>(
+        &'a self,
+        tip: &StacksBlockId,
+        heights: Range<u64>,
+    ) -> Result<StacksTransactionIter<'a>, Error> {
+        Ok(StacksTransactionIter {
+            blocks: self.iter_blocks(tip, heights)?,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Iterate over the logged transaction receipts of the blocks of the fork ending at `tip`
+    /// whose heights are in `heights`, lowest first.  Receipts are only logged if the node runs
+    /// with `STACKS_TRANSACTION_LOG=1`.
+    pub fn iter_receipts<'a>(
+        &'a self,
+        tip: &StacksBlockId,
+        heights: Range<u64>,
+    ) -> Result<StacksReceiptIter<'a>, Error> {
+        Ok(StacksReceiptIter {
+            chainstate: self,
+            tip: tip.clone(),
+            heights,
+            batch_size: DEFAULT_ITER_BATCH_SIZE,
+            pending: VecDeque::new(),
+        })
+    }
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::cmp;
 use std::collections::{btree_map::Entry, BTreeMap, HashSet};
 use std::fmt;
 use std::fs;
@@ -21,6 +22,7 @@ use std::io;
 use std::io::prelude::*;
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use clarity::vm::ast::ASTRules;
 use rusqlite::types::ToSql;
@@ -41,7 +43,9 @@ use crate::chainstate::burn::ConsensusHash;
 use crate::chainstate::stacks::boot::*;
 use crate::chainstate::stacks::db::accounts::*;
 use crate::chainstate::stacks::db::blocks::*;
+use crate::chainstate::stacks::db::preemption::BlockPreemption;
 use crate::chainstate::stacks::db::unconfirmed::UnconfirmedState;
+use crate::chainstate::stacks::db::validation_cache::BlockValidationCache;
 use crate::chainstate::stacks::events::*;
 use crate::chainstate::stacks::index::marf::{
     MARFOpenOpts, MarfConnection, BLOCK_HASH_TO_HEIGHT_MAPPING_KEY,
@@ -62,6 +66,7 @@ use crate::clarity_vm::clarity::{
 use crate::core::*;
 use crate::monitoring;
 use crate::net::atlas::BNS_CHARS_REGEX;
+use crate::net::BlockFeedEncoding;
 use crate::net::Error as net_error;
 use crate::net::MemPoolSyncData;
 use crate::util_lib::db::Error as db_error;
@@ -70,18 +75,21 @@ use crate::util_lib::db::{
     IndexDBConn, IndexDBTx,
 };
 use clarity::vm::analysis::analysis_db::AnalysisDatabase;
+use clarity::vm::analysis::errors::CheckErrors;
 use clarity::vm::analysis::run_analysis;
 use clarity::vm::clarity::TransactionConnection;
-use clarity::vm::contexts::OwnedEnvironment;
+use clarity::vm::contexts::{AssetMap, OwnedEnvironment};
 use clarity::vm::costs::{ExecutionCost, LimitedCostTracker};
 use clarity::vm::database::{
     BurnStateDB, ClarityDatabase, HeadersDB, STXBalance, SqliteConnection, NULL_BURN_STATE_DB,
 };
+use clarity::vm::errors::Error as InterpreterError;
 
 use crate::clarity_vm::clarity::PreCommitClarityBlock;
 use clarity::vm::events::*;
 use clarity::vm::representations::ClarityName;
 use clarity::vm::representations::ContractName;
+use clarity::vm::representations::SymbolicExpression;
 use clarity::vm::types::TupleData;
 use stacks_common::util;
 use stacks_common::util::hash::to_hex;
@@ -93,16 +101,27 @@ use crate::chainstate::stacks::StacksBlockHeader;
 use crate::chainstate::stacks::StacksMicroblockHeader;
 use crate::clarity_vm::database::marf::MarfedKV;
 use crate::clarity_vm::database::HeadersDBConn;
-use crate::util_lib::boot::{boot_code_acc, boot_code_addr, boot_code_id, boot_code_tx_auth};
+use crate::util_lib::boot::{
+    boot_code_acc, boot_code_tx_auth, network_boot_code_addr, network_boot_code_id,
+};
 use clarity::vm::Value;
+use clarity::vm::VmLimits;
 use stacks_common::types::chainstate::{StacksAddress, StacksBlockId, TrieHash};
+use stacks_common::types::NetworkId;
 
 pub mod accounts;
+pub mod banned_leader_keys;
 pub mod blocks;
 pub mod contracts;
+pub mod fee_rebates;
 pub mod headers;
+pub mod invalid_blocks;
+pub mod iter;
+pub mod preemption;
+pub mod supply;
 pub mod transactions;
 pub mod unconfirmed;
+pub mod validation_cache;
 
 lazy_static! {
     pub static ref TRANSACTION_LOG: bool =
@@ -123,8 +142,8 @@ impl StacksChainStateFaults {
 }
 
 pub struct StacksChainState {
-    pub mainnet: bool,
-    pub chain_id: u32,
+    /// The network this chainstate belongs to
+    pub network: NetworkId,
     pub clarity_state: ClarityInstance,
     pub state_index: MARF<StacksBlockId>,
     pub blocks_path: String,
@@ -133,7 +152,14 @@ pub struct StacksChainState {
     pub root_path: String,
     pub unconfirmed_state: Option<UnconfirmedState>,
     pub fault_injection: StacksChainStateFaults,
+    /// Verdicts of executed blocks, shared with every other instance opened on this chainstate
+    pub block_validation_cache: Arc<Mutex<BlockValidationCache>>,
+    /// Preemption of the block being processed, shared with every other instance opened on this
+    /// chainstate
+    pub block_preemption: Arc<BlockPreemption>,
     marf_opts: Option<MARFOpenOpts>,
+    vm_limits: Option<VmLimits>,
+    fee_rebate_accounting: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -192,6 +218,8 @@ pub struct StacksEpochReceipt {
     pub matured_rewards_info: Option<MinerRewardInfo>,
     pub parent_microblocks_cost: ExecutionCost,
     pub anchored_block_cost: ExecutionCost,
+    /// total serialized size, in bytes, of the parent microblocks this block confirmed
+    pub parent_microblocks_size: u64,
     pub parent_burn_block_hash: BurnchainHeaderHash,
     pub parent_burn_block_height: u32,
     pub parent_burn_block_timestamp: u64,
@@ -202,6 +230,211 @@ pub struct StacksEpochReceipt {
     pub epoch_transition: bool,
 }
 
+/// Minimum, median, and maximum of a set of fee rates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeRateStats {
+    pub min: f64,
+    pub median: f64,
+    pub max: f64,
+}
+
+/// Fee rates paid by a block's transactions, in microSTX per unit of each cost dimension.
+/// A dimension is `None` if none of the block's transactions consumed it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockFeeRates {
+    pub runtime: Option<FeeRateStats>,
+    pub read_count: Option<FeeRateStats>,
+    pub read_length: Option<FeeRateStats>,
+    pub write_count: Option<FeeRateStats>,
+    pub write_length: Option<FeeRateStats>,
+    /// fee rate per byte of serialized transaction
+    pub size: Option<FeeRateStats>,
+}
+
+/// Fraction of the block limit consumed by an anchored block, in each cost dimension
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockFullness {
+    pub runtime: f64,
+    pub read_count: f64,
+    pub read_length: f64,
+    pub write_count: f64,
+    pub write_length: f64,
+    /// fraction of MAX_BLOCK_LEN
+    pub size: f64,
+}
+
+/// Fee statistics for a processed Stacks block, computed when the block is appended to the
+/// chain state.  Fee totals and rates cover all of the user transactions the block confirmed,
+/// including those in its parent microblock stream; coinbases and burnchain operations are
+/// excluded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockFeeStats {
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub burn_block_height: u32,
+    pub tx_count: u64,
+    pub total_fees: u64,
+    pub fee_rates: BlockFeeRates,
+    pub fullness: BlockFullness,
+}
+
+impl FeeRateStats {
+    /// Summarize a list of fee rates.  Returns None if the list is empty.
+    pub fn from_rates(mut rates: Vec<f64>) -> Option<FeeRateStats> {
+        if rates.is_empty() {
+            return None;
+        }
+        rates.sort_by(|a, b| a.partial_cmp(b).unwrap_or(cmp::Ordering::Equal));
+        let len = rates.len();
+        let median = if len % 2 == 1 {
+            rates[len / 2]
+        } else {
+            (rates[len / 2 - 1] + rates[len / 2]) / 2.0
+        };
+        Some(FeeRateStats {
+            min: rates[0],
+            median,
+            max: rates[len - 1],
+        })
+    }
+}
+
+impl BlockFeeStats {
+    /// Compute the fee statistics for a processed block from its receipt
+    pub fn from_epoch_receipt(
+        receipt: &StacksEpochReceipt,
+        block_limit: &ExecutionCost,
+    ) -> BlockFeeStats {
+        let mut tx_count = 0;
+        let mut total_fees: u64 = 0;
+        let mut runtime_rates = vec![];
+        let mut read_count_rates = vec![];
+        let mut read_length_rates = vec![];
+        let mut write_count_rates = vec![];
+        let mut write_length_rates = vec![];
+        let mut size_rates = vec![];
+
+        for tx_receipt in receipt.tx_receipts.iter() {
+            let tx = match tx_receipt.transaction {
+                TransactionOrigin::Stacks(ref tx) => tx,
+                TransactionOrigin::Burn(..) => continue,
+            };
+            if let TransactionPayload::Coinbase(..) = tx.payload {
+                continue;
+            }
+
+            let fee = tx.get_tx_fee();
+            tx_count += 1;
+            total_fees = total_fees.saturating_add(fee);
+
+            let cost = &tx_receipt.execution_cost;
+            for (units, rates) in [
+                (cost.runtime, &mut runtime_rates),
+                (cost.read_count, &mut read_count_rates),
+                (cost.read_length, &mut read_length_rates),
+                (cost.write_count, &mut write_count_rates),
+                (cost.write_length, &mut write_length_rates),
+                (tx_receipt.tx_size, &mut size_rates),
+            ] {
+                if units > 0 {
+                    rates.push(fee as f64 / units as f64);
+                }
+            }
+        }
+
+        let fraction = |used: u64, limit: u64| {
+            if limit == 0 {
+                0.0
+            } else {
+                used as f64 / limit as f64
+            }
+        };
+        let block_cost = &receipt.anchored_block_cost;
+
+        BlockFeeStats {
+            index_block_hash: receipt.header.index_block_hash(),
+            block_height: receipt.header.stacks_block_height,
+            burn_block_height: receipt.header.burn_header_height,
+            tx_count,
+            total_fees,
+            fee_rates: BlockFeeRates {
+                runtime: FeeRateStats::from_rates(runtime_rates),
+                read_count: FeeRateStats::from_rates(read_count_rates),
+                read_length: FeeRateStats::from_rates(read_length_rates),
+                write_count: FeeRateStats::from_rates(write_count_rates),
+                write_length: FeeRateStats::from_rates(write_length_rates),
+                size: FeeRateStats::from_rates(size_rates),
+            },
+            fullness: BlockFullness {
+                runtime: fraction(block_cost.runtime, block_limit.runtime),
+                read_count: fraction(block_cost.read_count, block_limit.read_count),
+                read_length: fraction(block_cost.read_length, block_limit.read_length),
+                write_count: fraction(block_cost.write_count, block_limit.write_count),
+                write_length: fraction(block_cost.write_length, block_limit.write_length),
+                size: fraction(receipt.header.anchored_block_size, MAX_BLOCK_LEN as u64),
+            },
+        }
+    }
+}
+
+/// Serialized size and execution cost of one transaction in a processed block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TxWeight {
+    pub txid: Txid,
+    pub tx_index: u32,
+    /// the microblock the transaction was mined in, if it came from the parent microblock stream
+    pub microblock_hash: Option<BlockHeaderHash>,
+    /// serialized size in bytes.  0 for burnchain operations.
+    pub size: u64,
+    pub cost: ExecutionCost,
+}
+
+/// Serialized sizes and execution costs of a processed Stacks block, the parent microblocks it
+/// confirmed, and each of their transactions, computed when the block is appended to the chain
+/// state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockWeight {
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub burn_block_height: u32,
+    pub anchored_block_size: u64,
+    pub anchored_block_cost: ExecutionCost,
+    pub parent_microblocks_size: u64,
+    pub parent_microblocks_cost: ExecutionCost,
+    pub txs: Vec<TxWeight>,
+}
+
+impl BlockWeight {
+    /// Get the weight of a processed block from its receipt
+    pub fn from_epoch_receipt(receipt: &StacksEpochReceipt) -> BlockWeight {
+        let txs = receipt
+            .tx_receipts
+            .iter()
+            .map(|tx_receipt| TxWeight {
+                txid: tx_receipt.transaction.txid(),
+                tx_index: tx_receipt.tx_index,
+                microblock_hash: tx_receipt
+                    .microblock_header
+                    .as_ref()
+                    .map(|header| header.block_hash()),
+                size: tx_receipt.tx_size,
+                cost: tx_receipt.execution_cost.clone(),
+            })
+            .collect();
+
+        BlockWeight {
+            index_block_hash: receipt.header.index_block_hash(),
+            block_height: receipt.header.stacks_block_height,
+            burn_block_height: receipt.header.burn_header_height,
+            anchored_block_size: receipt.header.anchored_block_size,
+            anchored_block_cost: receipt.anchored_block_cost.clone(),
+            parent_microblocks_size: receipt.parent_microblocks_size,
+            parent_microblocks_cost: receipt.parent_microblocks_cost.clone(),
+            txs,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct DBConfig {
     pub version: String,
@@ -218,14 +451,52 @@ impl DBConfig {
                     || self.version == "2"
                     || self.version == "3"
                     || self.version == "4"
+                    || self.version == "5"
+                    || self.version == "6"
+                    || self.version == "7"
+                    || self.version == "8"
             }
             StacksEpochId::Epoch2_05 => {
-                self.version == "2" || self.version == "3" || self.version == "4"
+                self.version == "2"
+                    || self.version == "3"
+                    || self.version == "4"
+                    || self.version == "5"
+                    || self.version == "6"
+                    || self.version == "7"
+                    || self.version == "8"
+            }
+            StacksEpochId::Epoch21 => {
+                self.version == "3"
+                    || self.version == "4"
+                    || self.version == "5"
+                    || self.version == "6"
+                    || self.version == "7"
+                    || self.version == "8"
+            }
+            StacksEpochId::Epoch22 => {
+                self.version == "3"
+                    || self.version == "4"
+                    || self.version == "5"
+                    || self.version == "6"
+                    || self.version == "7"
+                    || self.version == "8"
+            }
+            StacksEpochId::Epoch23 => {
+                self.version == "3"
+                    || self.version == "4"
+                    || self.version == "5"
+                    || self.version == "6"
+                    || self.version == "7"
+                    || self.version == "8"
+            }
+            StacksEpochId::Epoch24 => {
+                self.version == "3"
+                    || self.version == "4"
+                    || self.version == "5"
+                    || self.version == "6"
+                    || self.version == "7"
+                    || self.version == "8"
             }
-            StacksEpochId::Epoch21 => self.version == "3" || self.version == "4",
-            StacksEpochId::Epoch22 => self.version == "3" || self.version == "4",
-            StacksEpochId::Epoch23 => self.version == "3" || self.version == "4",
-            StacksEpochId::Epoch24 => self.version == "3" || self.version == "4",
         }
     }
 }
@@ -349,6 +620,10 @@ impl<'a, 'b> ClarityConnection for ClarityTx<'a, 'b> {
     fn get_epoch(&self) -> StacksEpochId {
         self.block.get_epoch()
     }
+
+    fn get_vm_limits(&self) -> VmLimits {
+        self.block.get_vm_limits()
+    }
 }
 
 impl<'a, 'b> ClarityTx<'a, 'b> {
@@ -360,6 +635,10 @@ impl<'a, 'b> ClarityTx<'a, 'b> {
         self.block.get_epoch()
     }
 
+    pub fn network(&self) -> NetworkId {
+        self.block.network()
+    }
+
     /// Set the ClarityTx's cost tracker.
     /// Returns the replaced cost tracker.
     fn set_cost_tracker(&mut self, new_tracker: LimitedCostTracker) -> LimitedCostTracker {
@@ -524,6 +803,74 @@ impl<'a> DerefMut for ChainstateTx<'a> {
     }
 }
 
+/// A read-only transaction against the chainstate DB.  Every query made through it sees the same
+/// snapshot of the DB, even if another thread commits to it in the meantime, so a caller that
+/// makes several queries gets a consistent answer.  It derefs to the chainstate, so any read-only
+/// chainstate API can be used through it.  It only borrows the chainstate immutably, so no write
+/// transaction can be opened on the chainstate while it is open.
+///
+/// A read transaction opened while another one is open on the same chainstate joins the open
+/// one, rather than nesting inside it.
+pub struct ChainstateReadTx<'a> {
+    chainstate: &'a StacksChainState,
+    /// The underlying transaction, unless this joined one that was already open
+    tx: Option<DBTx<'a>>,
+}
+
+impl<'a> ChainstateReadTx<'a> {
+    fn new(chainstate: &'a StacksChainState) -> Result<ChainstateReadTx<'a>, Error> {
+        let conn = chainstate.db();
+        let tx = if conn.is_autocommit() {
+            Some(
+                conn.unchecked_transaction()
+                    .map_err(|e| Error::DBError(db_error::SqliteError(e)))?,
+            )
+        } else {
+            None
+        };
+        Ok(ChainstateReadTx { chainstate, tx })
+    }
+
+    /// Did this open its own transaction, rather than joining one that was already open?
+    pub fn is_outermost(&self) -> bool {
+        self.tx.is_some()
+    }
+}
+
+impl<'a> Deref for ChainstateReadTx<'a> {
+    type Target = StacksChainState;
+    fn deref(&self) -> &StacksChainState {
+        self.chainstate
+    }
+}
+
+/// A read-write transaction against the chainstate DB.  Does not affect the MARF or the Clarity
+/// state.  It borrows the chainstate mutably, so no other transaction -- read or write -- can be
+/// opened on the chainstate until it is committed or dropped; reads made while it is open go
+/// through it.  Dropping it without committing rolls it back.
+pub struct ChainstateWriteTx<'a> {
+    tx: DBTx<'a>,
+}
+
+impl<'a> ChainstateWriteTx<'a> {
+    pub fn commit(self) -> Result<(), db_error> {
+        self.tx.commit().map_err(db_error::SqliteError)
+    }
+}
+
+impl<'a> Deref for ChainstateWriteTx<'a> {
+    type Target = DBTx<'a>;
+    fn deref(&self) -> &DBTx<'a> {
+        &self.tx
+    }
+}
+
+impl<'a> DerefMut for ChainstateWriteTx<'a> {
+    fn deref_mut(&mut self) -> &mut DBTx<'a> {
+        &mut self.tx
+    }
+}
+
 /// Interface for streaming data
 pub trait Streamer {
     fn offset(&self) -> u64;
@@ -537,6 +884,7 @@ pub enum StreamCursor {
     Microblocks(MicroblockStreamData),
     Headers(HeaderStreamData),
     MempoolTxs(TxStreamData),
+    BlockFeed(BlockFeedStreamData),
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -584,8 +932,18 @@ pub struct HeaderStreamData {
     /// number of headers requested
     num_headers: u32,
 
+    /// whether or not the headers are sent in a page envelope, instead of as a bare list
+    paged: bool,
+    /// number of headers asked for, which is echoed back in the page envelope
+    limit: u32,
+    /// height of the block the page starts from, i.e. the number of headers to page through
+    total: u64,
+
     /// header buffer data
     header_bytes: Option<Vec<u8>>,
+    /// page envelope data that is sent before or after the headers
+    envelope_bytes: Vec<u8>,
+    envelope_ptr: usize,
     end_of_stream: bool,
     corked: bool,
 }
@@ -610,7 +968,29 @@ pub struct TxStreamData {
     pub corked: bool,
 }
 
-pub const CHAINSTATE_VERSION: &'static str = "3";
+#[derive(Debug, PartialEq, Clone)]
+pub struct BlockFeedStreamData {
+    /// height of the next block to send
+    pub next_height: u64,
+    /// index block hash of the last block sent, if any
+    pub cursor: Option<StacksBlockId>,
+    /// canonical Stacks chain tip, as last reported by the peer network
+    pub tip: StacksBlockId,
+    pub tip_height: u64,
+    /// how to encode frames
+    pub encoding: BlockFeedEncoding,
+    /// serialized frame that's being sent
+    pub frame_buf: Vec<u8>,
+    pub frame_buf_ptr: usize,
+    /// number of blocks sent so far, and the number after which to stop (if any)
+    pub num_blocks: u64,
+    pub max_blocks: Option<u64>,
+    /// seconds between heartbeat frames, and when the last frame was queued
+    pub heartbeat_interval: u64,
+    pub last_frame_time: u64,
+}
+
+pub const CHAINSTATE_VERSION: &'static str = "9";
 
 const CHAINSTATE_INITIAL_SCHEMA: &'static [&'static str] = &[
     "PRAGMA foreign_keys = ON;",
@@ -815,6 +1195,99 @@ const CHAINSTATE_SCHEMA_3: &'static [&'static str] = &[
     "#,
 ];
 
+const CHAINSTATE_SCHEMA_4: &'static [&'static str] = &[
+    // table of per-block fee statistics, computed as blocks are processed
+    r#"
+    CREATE TABLE block_fee_stats(
+        index_block_hash TEXT PRIMARY KEY,
+        -- this is a JSON-encoded BlockFeeStats
+        stats TEXT NOT NULL
+    );"#,
+    r#"
+    UPDATE db_config SET version = "4";
+    "#,
+];
+
+const CHAINSTATE_SCHEMA_5: &'static [&'static str] = &[
+    // table of per-block cumulative supply counters, carried forward as blocks are processed
+    r#"
+    CREATE TABLE block_supply_stats(
+        index_block_hash TEXT PRIMARY KEY,
+        -- this is a JSON-encoded BlockSupplyStats
+        stats TEXT NOT NULL
+    );"#,
+    r#"
+    UPDATE db_config SET version = "5";
+    "#,
+];
+
+const CHAINSTATE_SCHEMA_6: &'static [&'static str] = &[
+    // ledger of the fee rebates each transaction would have received under a uniform-price fee
+    // market (see `fee_rebates`).  This has no effect on consensus.
+    r#"
+    CREATE TABLE fee_rebates(
+        txid TEXT NOT NULL,
+        index_block_hash TEXT NOT NULL,
+        payer TEXT NOT NULL,
+        fee INTEGER NOT NULL,
+        cost_scalar INTEGER NOT NULL,
+        clearing_fee_rate REAL NOT NULL,
+        rebate INTEGER NOT NULL,
+
+        PRIMARY KEY(txid,index_block_hash)
+    );"#,
+    "CREATE INDEX IF NOT EXISTS index_fee_rebates_by_payer ON fee_rebates(payer);",
+    r#"
+    UPDATE db_config SET version = "6";
+    "#,
+];
+
+const CHAINSTATE_SCHEMA_7: &'static [&'static str] = &[
+    // leader keys whose miners produced consensus-invalid blocks (see `banned_leader_keys`).
+    // This has no effect on consensus.
+    r#"
+    CREATE TABLE banned_leader_keys(
+        public_key TEXT PRIMARY KEY,
+        consensus_hash TEXT NOT NULL,
+        anchored_block_hash TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        banned_at INTEGER NOT NULL
+    );"#,
+    r#"
+    UPDATE db_config SET version = "7";
+    "#,
+];
+
+const CHAINSTATE_SCHEMA_8: &'static [&'static str] = &[
+    // table of per-block size and cost accounting, computed as blocks are processed
+    r#"
+    CREATE TABLE block_weights(
+        index_block_hash TEXT PRIMARY KEY,
+        -- this is a JSON-encoded BlockWeight
+        weight TEXT NOT NULL
+    );"#,
+    r#"
+    UPDATE db_config SET version = "8";
+    "#,
+];
+
+const CHAINSTATE_SCHEMA_9: &'static [&'static str] = &[
+    // anchored blocks found to be invalid, and the node version that found them invalid (see
+    // `invalid_blocks`).  This has no effect on consensus.
+    r#"
+    CREATE TABLE invalid_blocks(
+        index_block_hash TEXT PRIMARY KEY,
+        consensus_hash TEXT NOT NULL,
+        anchored_block_hash TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        node_version TEXT NOT NULL,
+        invalidated_at INTEGER NOT NULL
+    );"#,
+    r#"
+    UPDATE db_config SET version = "9";
+    "#,
+];
+
 const CHAINSTATE_INDEXES: &'static [&'static str] = &[
     "CREATE INDEX IF NOT EXISTS index_block_hash_to_primary_key ON block_headers(index_block_hash,consensus_hash,block_hash);",
     "CREATE INDEX IF NOT EXISTS block_headers_hash_index ON block_headers(block_hash,block_height);",
@@ -1027,6 +1500,48 @@ impl StacksChainState {
                         }
                     }
                     "3" => {
+                        // migrate to 4
+                        info!("Migrating chainstate schema from version 3 to 4");
+                        for cmd in CHAINSTATE_SCHEMA_4.iter() {
+                            tx.execute_batch(cmd)?;
+                        }
+                    }
+                    "4" => {
+                        // migrate to 5
+                        info!("Migrating chainstate schema from version 4 to 5");
+                        for cmd in CHAINSTATE_SCHEMA_5.iter() {
+                            tx.execute_batch(cmd)?;
+                        }
+                    }
+                    "5" => {
+                        // migrate to 6
+                        info!("Migrating chainstate schema from version 5 to 6");
+                        for cmd in CHAINSTATE_SCHEMA_6.iter() {
+                            tx.execute_batch(cmd)?;
+                        }
+                    }
+                    "6" => {
+                        // migrate to 7
+                        info!("Migrating chainstate schema from version 6 to 7");
+                        for cmd in CHAINSTATE_SCHEMA_7.iter() {
+                            tx.execute_batch(cmd)?;
+                        }
+                    }
+                    "7" => {
+                        // migrate to 8
+                        info!("Migrating chainstate schema from version 7 to 8");
+                        for cmd in CHAINSTATE_SCHEMA_8.iter() {
+                            tx.execute_batch(cmd)?;
+                        }
+                    }
+                    "8" => {
+                        // migrate to 9
+                        info!("Migrating chainstate schema from version 8 to 9");
+                        for cmd in CHAINSTATE_SCHEMA_9.iter() {
+                            tx.execute_batch(cmd)?;
+                        }
+                    }
+                    "9" => {
                         // done
                         break;
                     }
@@ -1064,9 +1579,11 @@ impl StacksChainState {
             StacksChainState::instantiate_db(mainnet, chain_id, index_path, true)
         } else {
             let mut marf = StacksChainState::open_index(index_path)?;
-            let tx = marf.storage_tx()?;
+            let mut tx = marf.storage_tx()?;
             StacksChainState::apply_schema_migrations(&tx, mainnet, chain_id)?;
             StacksChainState::add_indexes(&tx)?;
+            // a different version of the node may not have the bug that got a block rejected
+            StacksChainState::requeue_invalid_blocks(&mut tx, StacksChainState::node_version())?;
             tx.commit()?;
             Ok(marf)
         }
@@ -1096,6 +1613,10 @@ impl StacksChainState {
         test_debug!("Open MARF index at {}", marf_path);
         let mut open_opts = MARFOpenOpts::default();
         open_opts.external_blobs = true;
+        #[cfg(feature = "marf_rocksdb")]
+        {
+            open_opts.rocksdb_blobs = true;
+        }
         let marf = MARF::from_path(marf_path, open_opts).map_err(|e| db_error::IndexError(e))?;
         Ok(marf)
     }
@@ -1151,18 +1672,20 @@ impl StacksChainState {
     /// Install the boot code into the chain history.
     fn install_boot_code(
         chainstate: &mut StacksChainState,
-        mainnet: bool,
+        network: NetworkId,
         boot_data: &mut ChainStateBootData,
     ) -> Result<Vec<StacksTransactionReceipt>, Error> {
         info!("Building genesis block");
 
+        let mainnet = network.mainnet;
+
         let tx_version = if mainnet {
             TransactionVersion::Mainnet
         } else {
             TransactionVersion::Testnet
         };
 
-        let boot_code_address = boot_code_addr(mainnet);
+        let boot_code_address = network_boot_code_addr(&network);
 
         let boot_code_auth = boot_code_tx_auth(boot_code_address);
 
@@ -1185,6 +1708,8 @@ impl StacksChainState {
                 *boot::STACKS_BOOT_CODE_TESTNET
             };
             for (boot_code_name, boot_code_contract) in boot_code.iter() {
+                let boot_code_contract =
+                    boot::get_boot_code(boot_code_name, mainnet, boot_code_contract);
                 debug!(
                     "Instantiate boot code contract '{}' ({} bytes)...",
                     boot_code_name,
@@ -1195,7 +1720,7 @@ impl StacksChainState {
                     TransactionSmartContract {
                         name: ContractName::try_from(boot_code_name.to_string())
                             .expect("FATAL: invalid boot-code contract name"),
-                        code_body: StacksString::from_str(boot_code_contract)
+                        code_body: StacksString::from_str(&boot_code_contract)
                             .expect("FATAL: invalid boot code body"),
                     },
                     None,
@@ -1298,7 +1823,7 @@ impl StacksChainState {
                         };
                     }
 
-                    let lockup_contract_id = boot_code_id("lockup", mainnet);
+                    let lockup_contract_id = network_boot_code_id("lockup", &network);
                     let epoch = clarity.get_epoch();
                     clarity
                         .with_clarity_db(|db| {
@@ -1319,7 +1844,7 @@ impl StacksChainState {
                 }
 
                 // BNS Namespace
-                let bns_contract_id = boot_code_id("bns", mainnet);
+                let bns_contract_id = network_boot_code_id("bns", &network);
                 if let Some(get_namespaces) = boot_data.get_bulk_initial_namespaces.take() {
                     info!("Initializing chain with namespaces");
                     let epoch = clarity.get_epoch();
@@ -1520,7 +2045,7 @@ impl StacksChainState {
 
             // Setup burnchain parameters for pox contract
             let pox_constants = &boot_data.pox_constants;
-            let contract = boot_code_id("pox", mainnet);
+            let contract = network_boot_code_id("pox", &network);
             let sender = PrincipalData::from(contract.clone());
             let params = vec![
                 Value::UInt(boot_data.first_burnchain_block_height as u128),
@@ -1621,24 +2146,57 @@ impl StacksChainState {
         Ok(receipts)
     }
 
+    /// The parameters of the network this chainstate belongs to
+    pub fn network_id(&self) -> NetworkId {
+        self.network
+    }
+
     pub fn open(
         mainnet: bool,
         chain_id: u32,
         path_str: &str,
         marf_opts: Option<MARFOpenOpts>,
     ) -> Result<(StacksChainState, Vec<StacksTransactionReceipt>), Error> {
-        StacksChainState::open_and_exec(mainnet, chain_id, path_str, None, marf_opts)
+        StacksChainState::open_and_exec(
+            NetworkId::from_chain_id(mainnet, chain_id),
+            path_str,
+            None,
+            marf_opts,
+        )
     }
 
     /// Re-open the chainstate -- i.e. to get a new handle to it using an existing chain state's
     /// parameters
     pub fn reopen(&self) -> Result<(StacksChainState, Vec<StacksTransactionReceipt>), Error> {
-        StacksChainState::open(
-            self.mainnet,
-            self.chain_id,
+        let (mut chainstate, receipts) = StacksChainState::open_and_exec(
+            self.network,
             &self.root_path,
+            None,
             self.marf_opts.clone(),
-        )
+        )?;
+        chainstate.set_vm_limits(self.vm_limits);
+        chainstate.set_fee_rebate_accounting(self.fee_rebate_accounting);
+        Ok((chainstate, receipts))
+    }
+
+    /// Run contracts with the given parser and interpreter limits, instead of each epoch's
+    /// consensus limits (or with the consensus limits again, if `None`).  Only private networks
+    /// should override them, and they should do so right after opening the chainstate.
+    pub fn set_vm_limits(&mut self, vm_limits: Option<VmLimits>) {
+        self.vm_limits = vm_limits;
+        self.clarity_state.set_vm_limits(vm_limits);
+    }
+
+    /// Record the fee rebates of each processed block in the `fee_rebates` ledger, in the epochs
+    /// that support it (see `fee_rebates`).  This is off by default, since the ledger gains a row
+    /// for every transaction and is never pruned.
+    pub fn set_fee_rebate_accounting(&mut self, enabled: bool) {
+        self.fee_rebate_accounting = enabled;
+    }
+
+    /// The parser and interpreter limits that contracts run with in the given epoch
+    pub fn get_vm_limits(&self, epoch_id: StacksEpochId) -> VmLimits {
+        self.clarity_state.get_vm_limits(epoch_id)
     }
 
     pub fn blocks_path(mut path: PathBuf) -> PathBuf {
@@ -1682,8 +2240,7 @@ impl StacksChainState {
     }
 
     pub fn open_and_exec(
-        mainnet: bool,
-        chain_id: u32,
+        network: NetworkId,
         path_str: &str,
         boot_data: Option<&mut ChainStateBootData>,
         marf_opts: Option<MARFOpenOpts>,
@@ -1721,7 +2278,8 @@ impl StacksChainState {
             Err(_) => true,
         };
 
-        let state_index = StacksChainState::open_db(mainnet, chain_id, &header_index_root)?;
+        let state_index =
+            StacksChainState::open_db(network.mainnet, network.chain_id, &header_index_root)?;
 
         let vm_state = MarfedKV::open(
             &clarity_state_index_root,
@@ -1733,11 +2291,19 @@ impl StacksChainState {
         )
         .map_err(|e| Error::ClarityError(e.into()))?;
 
-        let clarity_state = ClarityInstance::new(mainnet, chain_id, vm_state);
+        let clarity_state = ClarityInstance::new(network, vm_state);
+
+        let block_validation_cache = BlockValidationCache::for_chainstate(path_str);
+        if init_required {
+            // a new chainstate in place of an old one
+            block_validation_cache
+                .lock()
+                .expect("FATAL: block validation cache lock poisoned")
+                .clear();
+        }
 
         let mut chainstate = StacksChainState {
-            mainnet: mainnet,
-            chain_id: chain_id,
+            network,
             clarity_state: clarity_state,
             state_index: state_index,
             blocks_path: blocks_path_root,
@@ -1746,14 +2312,18 @@ impl StacksChainState {
             root_path: path_str.to_string(),
             unconfirmed_state: None,
             fault_injection: StacksChainStateFaults::new(),
+            block_validation_cache,
+            block_preemption: BlockPreemption::for_chainstate(path_str),
             marf_opts: marf_opts,
+            vm_limits: None,
+            fee_rebate_accounting: false,
         };
 
         let mut receipts = vec![];
         match (init_required, boot_data) {
             (true, Some(boot_data)) => {
                 let mut res =
-                    StacksChainState::install_boot_code(&mut chainstate, mainnet, boot_data)?;
+                    StacksChainState::install_boot_code(&mut chainstate, network, boot_data)?;
                 receipts.append(&mut res);
             }
             (true, None) => {
@@ -1769,8 +2339,8 @@ impl StacksChainState {
 
     pub fn config(&self) -> DBConfig {
         DBConfig {
-            mainnet: self.mainnet,
-            chain_id: self.chain_id,
+            mainnet: self.network.mainnet,
+            chain_id: self.network.chain_id,
             version: CHAINSTATE_VERSION.to_string(),
         }
     }
@@ -1785,10 +2355,17 @@ impl StacksChainState {
         Ok(StacksDBConn::new(&self.state_index, ()))
     }
 
-    /// Begin a transaction against the underlying DB
+    /// Begin a write transaction against the underlying DB
     /// Does not create a Clarity instance, and does not affect the MARF.
-    pub fn db_tx_begin<'a>(&'a mut self) -> Result<DBTx<'a>, Error> {
-        self.state_index.storage_tx().map_err(Error::DBError)
+    pub fn write_tx_begin<'a>(&'a mut self) -> Result<ChainstateWriteTx<'a>, Error> {
+        let tx = self.state_index.storage_tx().map_err(Error::DBError)?;
+        Ok(ChainstateWriteTx { tx })
+    }
+
+    /// Begin a read transaction against the underlying DB, so that a sequence of reads sees a
+    /// consistent view of it.
+    pub fn read_tx_begin<'a>(&'a self) -> Result<ChainstateReadTx<'a>, Error> {
+        ChainstateReadTx::new(self)
     }
 
     /// Simultaneously begin a transaction against both the headers and blocks.
@@ -2034,6 +2611,97 @@ impl StacksChainState {
         }
     }
 
+    /// Evaluate a call to the read-only `function` of `contract` with `args`, against the Clarity
+    /// state as of `block_id`.  The block can be any processed block, not just the chain tip, so
+    /// this can answer questions like "what was this account's balance at block N?".
+    ///
+    /// The call is evaluated in the epoch that was active when `block_id` was processed, with the
+    /// contract's Clarity version, and with the contract itself as `tx-sender`.  It is not
+    /// cost-limited.
+    ///
+    /// Returns NoSuchBlockError if `block_id` has not been processed.
+    pub fn eval_read_only_at(
+        &mut self,
+        burn_dbconn: &dyn BurnStateDB,
+        block_id: &StacksBlockId,
+        contract: &QualifiedContractIdentifier,
+        function: &str,
+        args: &[Value],
+    ) -> Result<Value, Error> {
+        if !StacksChainState::has_stacks_block(self.db(), block_id)? {
+            return Err(Error::NoSuchBlockError);
+        }
+
+        let network = self.network;
+        let sender = PrincipalData::from(contract.clone());
+        let args: Vec<_> = args
+            .iter()
+            .map(|arg| SymbolicExpression::atom_value(arg.clone()))
+            .collect();
+
+        let mut clarity_tx = self.clarity_state.read_only_connection_checked(
+            block_id,
+            &self.state_index,
+            burn_dbconn,
+        )?;
+        let clarity_version = clarity_tx
+            .with_analysis_db_readonly(|analysis_db| {
+                analysis_db.load_contract_non_canonical(contract)
+            })
+            .ok_or_else(|| {
+                clarity_error::from(InterpreterError::from(CheckErrors::NoSuchContract(
+                    contract.to_string(),
+                )))
+            })?
+            .clarity_version;
+
+        clarity_tx
+            .with_readonly_clarity_env(
+                network,
+                clarity_version,
+                sender,
+                None,
+                LimitedCostTracker::new_free(),
+                |env| env.execute_contract(contract, function, &args, true),
+            )
+            .map_err(|e| Error::ClarityError(clarity_error::from(e)))
+    }
+
+    /// Simulate mining `tx` in a block on top of `tip`, without keeping any of its effects.  The
+    /// transaction is processed exactly as a miner would process it -- including its nonce
+    /// check, fee payment, and post-conditions -- in a block that is rolled back afterwards.  Its
+    /// signatures are _not_ checked, so unsigned transactions can be simulated too.
+    ///
+    /// The simulated block only contains `tx`, and is evaluated in the epoch of `tip`.  Any
+    /// microblocks built on `tip` are ignored.
+    ///
+    /// Returns the fee, the transaction receipt (with the result, events, and execution cost), and
+    /// the assets the transaction moved out of each principal.  Returns NoSuchBlockError if `tip`
+    /// has not been processed, and any error that would make a block with `tx` invalid.
+    pub fn simulate_transaction(
+        &mut self,
+        burn_dbconn: &dyn BurnStateDB,
+        tip: &StacksBlockId,
+        tx: &StacksTransaction,
+        ast_rules: ASTRules,
+    ) -> Result<(u64, StacksTransactionReceipt, AssetMap), Error> {
+        let tip_header =
+            StacksChainState::get_stacks_block_header_info_by_index_block_hash(self.db(), tip)?
+                .ok_or(Error::NoSuchBlockError)?;
+
+        let mut clarity_tx = self.block_begin(
+            burn_dbconn,
+            &tip_header.consensus_hash,
+            &tip_header.anchored_header.block_hash(),
+            &MINER_BLOCK_CONSENSUS_HASH,
+            &MINER_BLOCK_HEADER_HASH,
+        );
+        let result =
+            StacksChainState::process_transaction_with_assets(&mut clarity_tx, tx, true, ast_rules);
+        clarity_tx.rollback_block();
+        result
+    }
+
     fn get_parent_index_block(
         parent_consensus_hash: &ConsensusHash,
         parent_block: &BlockHeaderHash,
@@ -2381,6 +3049,102 @@ impl StacksChainState {
         Ok(())
     }
 
+    /// Store a processed block's fee statistics
+    pub fn store_block_fee_stats(tx: &DBTx, stats: &BlockFeeStats) -> Result<(), Error> {
+        let stats_json =
+            serde_json::to_string(stats).expect("FATAL: could not serialize BlockFeeStats");
+        let sql =
+            "INSERT OR REPLACE INTO block_fee_stats (index_block_hash, stats) VALUES (?1, ?2)";
+        let args: &[&dyn ToSql] = &[&stats.index_block_hash, &stats_json];
+        tx.execute(sql, args)?;
+        Ok(())
+    }
+
+    /// Store a processed block's size and cost accounting
+    pub fn store_block_weight(tx: &DBTx, weight: &BlockWeight) -> Result<(), Error> {
+        let weight_json =
+            serde_json::to_string(weight).expect("FATAL: could not serialize BlockWeight");
+        let sql = "INSERT OR REPLACE INTO block_weights (index_block_hash, weight) VALUES (?1, ?2)";
+        let args: &[&dyn ToSql] = &[&weight.index_block_hash, &weight_json];
+        tx.execute(sql, args)?;
+        Ok(())
+    }
+
+    /// Get the size and cost accounting for a given index block hash, if it was recorded
+    pub fn get_block_weight(
+        conn: &Connection,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<BlockWeight>, Error> {
+        let sql = "SELECT weight FROM block_weights WHERE index_block_hash = ?1";
+        let args: &[&dyn ToSql] = &[index_block_hash];
+
+        let weight = conn
+            .query_row(sql, args, |r| {
+                let weight_json: String = r.get_unwrap(0);
+                let weight: BlockWeight = serde_json::from_str(&weight_json)
+                    .expect("FATAL: database corruption: could not parse BlockWeight JSON");
+                Ok(weight)
+            })
+            .optional()?;
+
+        Ok(weight)
+    }
+
+    /// Get the fee statistics for a given index block hash, if they were computed
+    pub fn get_block_fee_stats(
+        conn: &Connection,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<BlockFeeStats>, Error> {
+        let sql = "SELECT stats FROM block_fee_stats WHERE index_block_hash = ?1";
+        let args: &[&dyn ToSql] = &[index_block_hash];
+
+        let stats = conn
+            .query_row(sql, args, |r| {
+                let stats_json: String = r.get_unwrap(0);
+                let stats: BlockFeeStats = serde_json::from_str(&stats_json)
+                    .expect("FATAL: database corruption: could not parse BlockFeeStats JSON");
+                Ok(stats)
+            })
+            .optional()?;
+
+        Ok(stats)
+    }
+
+    /// Get the fee statistics for up to `count` blocks, starting at `index_block_hash` and
+    /// walking back through its ancestors.  Blocks processed before fee statistics were
+    /// recorded are skipped.
+    pub fn get_block_fee_stats_in_ancestors(
+        conn: &Connection,
+        index_block_hash: &StacksBlockId,
+        count: u64,
+    ) -> Result<Vec<BlockFeeStats>, Error> {
+        StacksChainState::get_block_fee_stats_page(conn, index_block_hash, count)
+            .map(|(stats, _)| stats)
+    }
+
+    /// Like `get_block_fee_stats_in_ancestors`, but also returns the ancestor to continue
+    /// walking back from, if there is one.
+    pub fn get_block_fee_stats_page(
+        conn: &Connection,
+        index_block_hash: &StacksBlockId,
+        count: u64,
+    ) -> Result<(Vec<BlockFeeStats>, Option<StacksBlockId>), Error> {
+        let mut ancestors =
+            StacksChainState::get_ancestor_index_hashes(conn, index_block_hash, count)?;
+        let next = if ancestors.len() as u64 > count {
+            ancestors.pop()
+        } else {
+            None
+        };
+        let mut ret = Vec::with_capacity(ancestors.len());
+        for ancestor in ancestors.iter() {
+            if let Some(stats) = StacksChainState::get_block_fee_stats(conn, ancestor)? {
+                ret.push(stats);
+            }
+        }
+        Ok((ret, next))
+    }
+
     /// Append a Stacks block to an existing Stacks block, and grant the miner the block reward.
     /// Return the new Stacks header info.
     pub fn advance_tip<'a>(
@@ -2546,6 +3310,18 @@ pub mod test {
         chain_id: u32,
         test_name: &str,
         balances: Vec<(StacksAddress, u64)>,
+    ) -> StacksChainState {
+        instantiate_network_chainstate(
+            NetworkId::from_chain_id(mainnet, chain_id),
+            test_name,
+            balances,
+        )
+    }
+
+    pub fn instantiate_network_chainstate(
+        network: NetworkId,
+        test_name: &str,
+        balances: Vec<(StacksAddress, u64)>,
     ) -> StacksChainState {
         let path = chainstate_path(test_name);
         match fs::metadata(&path) {
@@ -2573,7 +3349,7 @@ pub mod test {
             get_bulk_initial_namespaces: None,
         };
 
-        StacksChainState::open_and_exec(mainnet, chain_id, &path, Some(&mut boot_data), None)
+        StacksChainState::open_and_exec(network, &path, Some(&mut boot_data), None)
             .unwrap()
             .0
     }
@@ -2613,6 +3389,99 @@ pub mod test {
         }
     }
 
+    #[test]
+    fn test_instantiate_custom_network_chainstate() {
+        let network = NetworkId::custom(0x12345678, 20, 21);
+        let mut chainstate = instantiate_network_chainstate(network, function_name!(), vec![]);
+        assert_eq!(chainstate.network_id(), network);
+
+        let mut conn = chainstate.block_begin(
+            &TEST_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &MINER_BLOCK_CONSENSUS_HASH,
+            &MINER_BLOCK_HEADER_HASH,
+        );
+
+        // the boot code is published under the network's own boot address
+        for (boot_contract_name, _) in STACKS_BOOT_CODE_TESTNET.iter() {
+            let boot_contract_id = network_boot_code_id(boot_contract_name, &network);
+            assert!(StacksChainState::get_contract(&mut conn, &boot_contract_id)
+                .unwrap()
+                .is_some());
+
+            let testnet_contract_id =
+                network_boot_code_id(boot_contract_name, &NetworkId::testnet());
+            assert!(
+                StacksChainState::get_contract(&mut conn, &testnet_contract_id)
+                    .unwrap()
+                    .is_none()
+            );
+        }
+    }
+
+    #[test]
+    fn test_block_fee_stats() {
+        assert_eq!(FeeRateStats::from_rates(vec![]), None);
+        assert_eq!(
+            FeeRateStats::from_rates(vec![3.0, 1.0, 2.0]),
+            Some(FeeRateStats {
+                min: 1.0,
+                median: 2.0,
+                max: 3.0
+            })
+        );
+        assert_eq!(
+            FeeRateStats::from_rates(vec![4.0, 1.0, 2.0, 8.0]),
+            Some(FeeRateStats {
+                min: 1.0,
+                median: 3.0,
+                max: 8.0
+            })
+        );
+
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+        let stats = BlockFeeStats {
+            index_block_hash: StacksBlockId([0x11; 32]),
+            block_height: 1,
+            burn_block_height: 2,
+            tx_count: 3,
+            total_fees: 600,
+            fee_rates: BlockFeeRates {
+                runtime: FeeRateStats::from_rates(vec![0.5, 0.25]),
+                read_count: None,
+                read_length: None,
+                write_count: None,
+                write_length: None,
+                size: FeeRateStats::from_rates(vec![1.0, 2.0, 4.0]),
+            },
+            fullness: BlockFullness {
+                runtime: 0.5,
+                read_count: 0.0,
+                read_length: 0.0,
+                write_count: 0.0,
+                write_length: 0.0,
+                size: 0.25,
+            },
+        };
+
+        assert_eq!(
+            StacksChainState::get_block_fee_stats(chainstate.db(), &stats.index_block_hash)
+                .unwrap(),
+            None
+        );
+
+        let tx = chainstate.index_tx_begin().unwrap();
+        StacksChainState::store_block_fee_stats(&tx, &stats).unwrap();
+        tx.commit().unwrap();
+
+        assert_eq!(
+            StacksChainState::get_block_fee_stats(chainstate.db(), &stats.index_block_hash)
+                .unwrap(),
+            Some(stats)
+        );
+    }
+
     #[test]
     fn test_chainstate_sampled_genesis_consistency() {
         // Test root hash for the test chainstate data set
@@ -2675,10 +3544,14 @@ pub mod test {
             Err(_) => {}
         };
 
-        let mut chainstate =
-            StacksChainState::open_and_exec(false, 0x80000000, &path, Some(&mut boot_data), None)
-                .unwrap()
-                .0;
+        let mut chainstate = StacksChainState::open_and_exec(
+            NetworkId::testnet(),
+            &path,
+            Some(&mut boot_data),
+            None,
+        )
+        .unwrap()
+        .0;
 
         let genesis_root_hash = chainstate.clarity_state.with_marf(|marf| {
             let index_block_hash = StacksBlockHeader::make_index_block_hash(
@@ -2765,10 +3638,14 @@ pub mod test {
             Err(_) => {}
         };
 
-        let mut chainstate =
-            StacksChainState::open_and_exec(true, 0x000000001, &path, Some(&mut boot_data), None)
-                .unwrap()
-                .0;
+        let mut chainstate = StacksChainState::open_and_exec(
+            NetworkId::mainnet(),
+            &path,
+            Some(&mut boot_data),
+            None,
+        )
+        .unwrap()
+        .0;
 
         let genesis_root_hash = chainstate.clarity_state.with_marf(|marf| {
             let index_block_hash = StacksBlockHeader::make_index_block_hash(
@@ -2785,4 +3662,62 @@ pub mod test {
             MAINNET_2_0_GENESIS_ROOT_HASH
         );
     }
+
+    #[test]
+    fn test_chainstate_read_write_tx() {
+        use crate::chainstate::stacks::db::banned_leader_keys::BannedLeaderKey;
+        use stacks_common::util::vrf::{VRFPrivateKey, VRFPublicKey};
+
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+        let (mut writer, _) =
+            StacksChainState::open(false, 0x80000000, &chainstate_path(function_name!()), None)
+                .unwrap();
+        let ban = BannedLeaderKey {
+            public_key: VRFPublicKey::from_private(&VRFPrivateKey::new()),
+            consensus_hash: ConsensusHash([0x01; 20]),
+            anchored_block_hash: BlockHeaderHash([0x02; 32]),
+            reason: "bad block".to_string(),
+            banned_at: 1000,
+        };
+
+        {
+            let read_tx = chainstate.read_tx_begin().unwrap();
+            assert!(read_tx.is_outermost());
+            assert!(StacksChainState::get_banned_leader_keys(read_tx.db())
+                .unwrap()
+                .is_empty());
+
+            // another chainstate instance commits while the read is open...
+            let write_tx = writer.write_tx_begin().unwrap();
+            assert!(StacksChainState::ban_leader_key(&write_tx, &ban).unwrap());
+            write_tx.commit().unwrap();
+
+            // ...but the read keeps seeing the state it started with, including through a
+            // read transaction opened inside it
+            let inner_read_tx = read_tx.read_tx_begin().unwrap();
+            assert!(!inner_read_tx.is_outermost());
+            assert!(StacksChainState::get_banned_leader_keys(inner_read_tx.db())
+                .unwrap()
+                .is_empty());
+        }
+
+        let read_tx = chainstate.read_tx_begin().unwrap();
+        assert_eq!(
+            StacksChainState::get_banned_leader_keys(read_tx.db()).unwrap(),
+            vec![ban.clone()]
+        );
+        drop(read_tx);
+
+        // a dropped write transaction rolls back
+        let write_tx = chainstate.write_tx_begin().unwrap();
+        assert_eq!(
+            StacksChainState::clear_banned_leader_keys(&write_tx).unwrap(),
+            1
+        );
+        drop(write_tx);
+        assert_eq!(
+            StacksChainState::get_banned_leader_keys(chainstate.db()).unwrap(),
+            vec![ban]
+        );
+    }
 }
@@ -0,0 +1,182 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Preemption of in-progress block processing.
+//!
+//! The chains coordinator executes one staging block at a time, and picks the next one at random.
+//! During a chain race, it can be busy executing a block on a losing fork while a block that
+//! extends a longer fork -- for example, one that confirms the block our miner just produced --
+//! waits in the staging queue, and the miner keeps building on a stale tip in the meantime.
+//!
+//! When the relayer stores a new attachable block, it can offer it here.  If the block is strictly
+//! better than the one being executed (i.e. it has a greater Stacks height), the block being
+//! executed is preempted: the coordinator abandons it at the next transaction boundary, rolls back
+//! its partially-built MARF trie and chainstate transaction, and processes the better block next.
+//! The abandoned block is left in the staging queue, and will be executed again later.
+//!
+//! Like the block validation cache, this is shared by every `StacksChainState` opened on the same
+//! chainstate directory, since the relayer and the chains coordinator each open their own.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::types::chainstate::StacksBlockId;
+
+lazy_static! {
+    static ref BLOCK_PREEMPTIONS: Mutex<HashMap<String, Arc<BlockPreemption>>> =
+        Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Default)]
+struct PreemptionState {
+    /// The block being executed, and its Stacks height
+    in_progress: Option<(StacksBlockId, u64)>,
+    /// The best block offered so far, which should be processed next
+    preferred: Option<(StacksBlockId, u64)>,
+}
+
+#[derive(Debug, Default)]
+pub struct BlockPreemption {
+    state: Mutex<PreemptionState>,
+    /// Set when the block in progress is preempted.  Checked between transactions, so it's kept
+    /// out of the mutex.
+    preempted: AtomicBool,
+}
+
+impl BlockPreemption {
+    pub fn new() -> BlockPreemption {
+        BlockPreemption::default()
+    }
+
+    /// Get the preemption state shared by all chainstate instances opened on `root_path`
+    pub fn for_chainstate(root_path: &str) -> Arc<BlockPreemption> {
+        let mut preemptions = BLOCK_PREEMPTIONS
+            .lock()
+            .expect("FATAL: block preemption registry lock poisoned");
+        preemptions
+            .entry(root_path.to_string())
+            .or_insert_with(|| Arc::new(BlockPreemption::new()))
+            .clone()
+    }
+
+    /// Record that the given block is about to be executed.  Any preemption of a previous block
+    /// is forgotten.
+    pub fn begin(&self, block_id: &StacksBlockId, stacks_height: u64) {
+        let mut state = self
+            .state
+            .lock()
+            .expect("FATAL: block preemption lock poisoned");
+        state.in_progress = Some((block_id.clone(), stacks_height));
+        self.preempted.store(false, Ordering::SeqCst);
+    }
+
+    /// Record that the block being executed was either accepted, rejected, or abandoned
+    pub fn end(&self) {
+        let mut state = self
+            .state
+            .lock()
+            .expect("FATAL: block preemption lock poisoned");
+        state.in_progress = None;
+        self.preempted.store(false, Ordering::SeqCst);
+    }
+
+    /// Offer an attachable block to be processed next.  It's remembered if it's better than any
+    /// block offered before it.  Returns true if it preempts the block being executed.
+    pub fn preempt(&self, block_id: &StacksBlockId, stacks_height: u64) -> bool {
+        let mut state = self
+            .state
+            .lock()
+            .expect("FATAL: block preemption lock poisoned");
+        if let Some((_, preferred_height)) = state.preferred.as_ref() {
+            if *preferred_height >= stacks_height {
+                // already have one at least as good
+                return false;
+            }
+        }
+        state.preferred = Some((block_id.clone(), stacks_height));
+
+        match state.in_progress.as_ref() {
+            Some((in_progress_id, in_progress_height))
+                if in_progress_id != block_id && *in_progress_height < stacks_height =>
+            {
+                info!(
+                    "Preempt processing of block {} (height {}) in favor of block {} (height {})",
+                    in_progress_id, in_progress_height, block_id, stacks_height
+                );
+                self.preempted.store(true, Ordering::SeqCst);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Has the block being executed been preempted?
+    pub fn is_preempted(&self) -> bool {
+        self.preempted.load(Ordering::SeqCst)
+    }
+
+    /// Take the best block offered so far, so it can be processed next
+    pub fn take_preferred(&self) -> Option<StacksBlockId> {
+        let mut state = self
+            .state
+            .lock()
+            .expect("FATAL: block preemption lock poisoned");
+        state.preferred.take().map(|(block_id, _)| block_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_block_preemption() {
+        let preemption = BlockPreemption::new();
+
+        // nothing to preempt, but the block is remembered
+        assert!(!preemption.preempt(&StacksBlockId([1u8; 32]), 10));
+        assert_eq!(preemption.take_preferred(), Some(StacksBlockId([1u8; 32])));
+        assert_eq!(preemption.take_preferred(), None);
+
+        preemption.begin(&StacksBlockId([2u8; 32]), 10);
+
+        // not strictly better
+        assert!(!preemption.preempt(&StacksBlockId([3u8; 32]), 10));
+        assert!(!preemption.is_preempted());
+        assert_eq!(preemption.take_preferred(), Some(StacksBlockId([3u8; 32])));
+
+        // the block in progress can't preempt itself
+        assert!(!preemption.preempt(&StacksBlockId([2u8; 32]), 11));
+        assert!(!preemption.is_preempted());
+        preemption.take_preferred();
+
+        // strictly better
+        assert!(preemption.preempt(&StacksBlockId([4u8; 32]), 11));
+        assert!(preemption.is_preempted());
+
+        // a block no better than the one already offered is ignored
+        assert!(!preemption.preempt(&StacksBlockId([5u8; 32]), 11));
+        assert_eq!(preemption.take_preferred(), Some(StacksBlockId([4u8; 32])));
+
+        // the next block starts out unpreempted
+        preemption.begin(&StacksBlockId([4u8; 32]), 11);
+        assert!(!preemption.is_preempted());
+        preemption.end();
+        assert!(!preemption.preempt(&StacksBlockId([6u8; 32]), 12));
+        assert!(!preemption.is_preempted());
+    }
+}
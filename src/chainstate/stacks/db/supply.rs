@@ -0,0 +1,232 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! STX supply accounting.
+//!
+//! As each block is appended to the chain state, the total coinbase minted and the total STX
+//! burnt in its fork are carried forward from its parent and stored as the block's
+//! `BlockSupplyStats`.  The liquid supply and the amount of STX locked at a block are already
+//! tracked by the Clarity VM (as `stx-liquid-supply` and by the active PoX contract), so
+//! `StacksChainState::get_supply_at_height()` reads those at query time.  No query ever scans
+//! the chain history.
+//!
+//! Supply counters can only be carried forward from a parent which has them, so blocks
+//! processed before the chain state schema recorded them (and their descendants) have none.
+
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::burnchains::Burnchain;
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::stacks::db::{StacksChainState, StacksEpochReceipt};
+use crate::chainstate::stacks::Error;
+use crate::util_lib::db::DBTx;
+use rusqlite::types::ToSql;
+use stacks_common::types::chainstate::StacksBlockId;
+
+/// Cumulative supply counters for a processed Stacks block's fork, up to and including the block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockSupplyStats {
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub burn_block_height: u32,
+    /// uSTX minted by coinbases which have matured into miners' accounts
+    pub total_minted_ustx: u128,
+    /// uSTX destroyed by transactions
+    pub total_burned_ustx: u128,
+}
+
+/// The STX supply at a processed Stacks block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SupplyInfo {
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub burn_block_height: u32,
+    /// uSTX that can be spent or stacked, including those locked by stacking
+    pub circulating_supply_ustx: u128,
+    pub total_minted_ustx: u128,
+    pub total_burned_ustx: u128,
+    /// uSTX stacked in the reward cycle containing the block's burnchain block
+    pub total_locked_ustx: u128,
+}
+
+impl BlockSupplyStats {
+    /// Compute the supply counters for a processed block from its parent's counters and its
+    /// receipt.  Pass None for the parent's counters if the block's parent is the boot block.
+    pub fn from_epoch_receipt(
+        parent_stats: Option<&BlockSupplyStats>,
+        receipt: &StacksEpochReceipt,
+    ) -> BlockSupplyStats {
+        let (parent_minted, parent_burned) = parent_stats
+            .map(|stats| (stats.total_minted_ustx, stats.total_burned_ustx))
+            .unwrap_or((0, 0));
+
+        let minted = receipt
+            .matured_rewards
+            .iter()
+            .fold(0u128, |total, reward| total.saturating_add(reward.coinbase));
+        let burned = receipt.tx_receipts.iter().fold(0u128, |total, tx_receipt| {
+            total.saturating_add(tx_receipt.stx_burned)
+        });
+
+        BlockSupplyStats {
+            index_block_hash: receipt.header.index_block_hash(),
+            block_height: receipt.header.stacks_block_height,
+            burn_block_height: receipt.header.burn_header_height,
+            total_minted_ustx: parent_minted.saturating_add(minted),
+            total_burned_ustx: parent_burned.saturating_add(burned),
+        }
+    }
+}
+
+impl StacksChainState {
+    /// Store a processed block's supply counters
+    pub fn store_block_supply_stats(tx: &DBTx, stats: &BlockSupplyStats) -> Result<(), Error> {
+        let stats_json =
+            serde_json::to_string(stats).expect("FATAL: could not serialize BlockSupplyStats");
+        let sql =
+            "INSERT OR REPLACE INTO block_supply_stats (index_block_hash, stats) VALUES (?1, ?2)";
+        let args: &[&dyn ToSql] = &[&stats.index_block_hash, &stats_json];
+        tx.execute(sql, args)?;
+        Ok(())
+    }
+
+    /// Get the supply counters for a given index block hash, if they were computed
+    pub fn get_block_supply_stats(
+        conn: &Connection,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<BlockSupplyStats>, Error> {
+        let sql = "SELECT stats FROM block_supply_stats WHERE index_block_hash = ?1";
+        let args: &[&dyn ToSql] = &[index_block_hash];
+
+        let stats = conn
+            .query_row(sql, args, |r| {
+                let stats_json: String = r.get_unwrap(0);
+                let stats: BlockSupplyStats = serde_json::from_str(&stats_json)
+                    .expect("FATAL: database corruption: could not parse BlockSupplyStats JSON");
+                Ok(stats)
+            })
+            .optional()?;
+
+        Ok(stats)
+    }
+
+    /// Compute and store the supply counters for a block that was just appended.  The counters
+    /// are not stored if the block's parent has none.
+    pub(crate) fn record_block_supply_stats(
+        tx: &DBTx,
+        parent_index_block_hash: &StacksBlockId,
+        parent_is_boot_block: bool,
+        receipt: &StacksEpochReceipt,
+    ) -> Result<(), Error> {
+        let parent_stats = if parent_is_boot_block {
+            None
+        } else {
+            match StacksChainState::get_block_supply_stats(tx, parent_index_block_hash)? {
+                Some(stats) => Some(stats),
+                None => {
+                    debug!(
+                        "No supply counters for parent {}; not recording supply counters for {}",
+                        parent_index_block_hash,
+                        &receipt.header.index_block_hash()
+                    );
+                    return Ok(());
+                }
+            }
+        };
+        let stats = BlockSupplyStats::from_epoch_receipt(parent_stats.as_ref(), receipt);
+        StacksChainState::store_block_supply_stats(tx, &stats)
+    }
+
+    /// Get the STX supply at the block at `height` in the fork ending at `tip`.
+    /// Returns Ok(None) if there is no such block, or if its supply counters were not recorded.
+    pub fn get_supply_at_height(
+        &mut self,
+        sortdb: &SortitionDB,
+        burnchain: &Burnchain,
+        tip: &StacksBlockId,
+        height: u64,
+    ) -> Result<Option<SupplyInfo>, Error> {
+        let block_id = match self.index_conn()?.get_ancestor_block_hash(height, tip)? {
+            Some(block_id) => block_id,
+            None => {
+                return Ok(None);
+            }
+        };
+        let stats = match StacksChainState::get_block_supply_stats(self.db(), &block_id)? {
+            Some(stats) => stats,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let circulating_supply_ustx = self.get_liquid_ustx(&block_id);
+
+        let burn_height = stats.burn_block_height as u64;
+        let total_locked_ustx = match burnchain.block_height_to_reward_cycle(burn_height) {
+            Some(reward_cycle) => self.get_total_ustx_stacked(
+                sortdb,
+                &block_id,
+                reward_cycle as u128,
+                burnchain.pox_constants.active_pox_contract(burn_height),
+            )?,
+            None => 0,
+        };
+
+        Ok(Some(SupplyInfo {
+            index_block_hash: block_id,
+            block_height: stats.block_height,
+            burn_block_height: stats.burn_block_height,
+            circulating_supply_ustx,
+            total_minted_ustx: stats.total_minted_ustx,
+            total_burned_ustx: stats.total_burned_ustx,
+            total_locked_ustx,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::stacks::db::test::instantiate_chainstate;
+
+    #[test]
+    fn test_block_supply_stats() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+        let stats = BlockSupplyStats {
+            index_block_hash: StacksBlockId([0x01; 32]),
+            block_height: 10,
+            burn_block_height: 110,
+            total_minted_ustx: 1_000_000_000,
+            total_burned_ustx: 123,
+        };
+
+        assert_eq!(
+            StacksChainState::get_block_supply_stats(chainstate.db(), &stats.index_block_hash)
+                .unwrap(),
+            None
+        );
+
+        let tx = chainstate.index_tx_begin().unwrap();
+        StacksChainState::store_block_supply_stats(&tx, &stats).unwrap();
+        tx.commit().unwrap();
+
+        assert_eq!(
+            StacksChainState::get_block_supply_stats(chainstate.db(), &stats.index_block_hash)
+                .unwrap(),
+            Some(stats)
+        );
+    }
+}
@@ -23,6 +23,7 @@ use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
 use crate::chainstate::burn::db::sortdb::*;
+use crate::chainstate::coordinator::validation::take_verified_tx;
 use crate::chainstate::stacks::db::*;
 use crate::chainstate::stacks::Error;
 use crate::chainstate::stacks::*;
@@ -50,6 +51,8 @@ use clarity::vm::costs::CostTracker;
 use clarity::vm::costs::ExecutionCost;
 use clarity::vm::database::ClarityDatabase;
 use clarity::vm::errors::Error as InterpreterError;
+use clarity::vm::errors::RuntimeErrorType;
+use clarity::vm::errors::VmErrorCode;
 use clarity::vm::representations::ClarityName;
 use clarity::vm::representations::ContractName;
 use clarity::vm::types::StacksAddressExtensions as ClarityStacksAddressExt;
@@ -68,6 +71,7 @@ impl StacksTransactionReceipt {
         cost: ExecutionCost,
     ) -> StacksTransactionReceipt {
         StacksTransactionReceipt {
+            tx_size: 0,
             events: events,
             result: result,
             stx_burned: 0,
@@ -78,6 +82,7 @@ impl StacksTransactionReceipt {
             microblock_header: None,
             tx_index: 0,
             vm_error: None,
+            vm_error_code: None,
         }
     }
 
@@ -89,6 +94,7 @@ impl StacksTransactionReceipt {
         cost: ExecutionCost,
     ) -> StacksTransactionReceipt {
         StacksTransactionReceipt {
+            tx_size: 0,
             transaction: tx.into(),
             post_condition_aborted: false,
             events,
@@ -99,6 +105,7 @@ impl StacksTransactionReceipt {
             microblock_header: None,
             tx_index: 0,
             vm_error: None,
+            vm_error_code: None,
         }
     }
 
@@ -110,6 +117,7 @@ impl StacksTransactionReceipt {
         cost: ExecutionCost,
     ) -> StacksTransactionReceipt {
         StacksTransactionReceipt {
+            tx_size: 0,
             transaction: tx.into(),
             post_condition_aborted: true,
             events,
@@ -120,6 +128,7 @@ impl StacksTransactionReceipt {
             microblock_header: None,
             tx_index: 0,
             vm_error: None,
+            vm_error_code: None,
         }
     }
 
@@ -131,6 +140,7 @@ impl StacksTransactionReceipt {
         cost: ExecutionCost,
     ) -> StacksTransactionReceipt {
         StacksTransactionReceipt {
+            tx_size: 0,
             transaction: tx.into(),
             events,
             post_condition_aborted: false,
@@ -141,6 +151,7 @@ impl StacksTransactionReceipt {
             microblock_header: None,
             tx_index: 0,
             vm_error: None,
+            vm_error_code: None,
         }
     }
 
@@ -152,6 +163,7 @@ impl StacksTransactionReceipt {
         cost: ExecutionCost,
     ) -> StacksTransactionReceipt {
         StacksTransactionReceipt {
+            tx_size: 0,
             transaction: tx.into(),
             events,
             post_condition_aborted: true,
@@ -162,11 +174,13 @@ impl StacksTransactionReceipt {
             microblock_header: None,
             tx_index: 0,
             vm_error: None,
+            vm_error_code: None,
         }
     }
 
     pub fn from_coinbase(tx: StacksTransaction) -> StacksTransactionReceipt {
         StacksTransactionReceipt {
+            tx_size: 0,
             transaction: tx.into(),
             events: vec![],
             post_condition_aborted: false,
@@ -177,6 +191,7 @@ impl StacksTransactionReceipt {
             microblock_header: None,
             tx_index: 0,
             vm_error: None,
+            vm_error_code: None,
         }
     }
 
@@ -185,6 +200,7 @@ impl StacksTransactionReceipt {
         analysis_cost: ExecutionCost,
     ) -> StacksTransactionReceipt {
         StacksTransactionReceipt {
+            tx_size: 0,
             transaction: tx.into(),
             events: vec![],
             post_condition_aborted: false,
@@ -195,6 +211,7 @@ impl StacksTransactionReceipt {
             microblock_header: None,
             tx_index: 0,
             vm_error: None,
+            vm_error_code: None,
         }
     }
 
@@ -204,6 +221,7 @@ impl StacksTransactionReceipt {
         cost: ExecutionCost,
     ) -> StacksTransactionReceipt {
         StacksTransactionReceipt {
+            tx_size: 0,
             transaction: tx.into(),
             events: vec![],
             post_condition_aborted: false,
@@ -214,6 +232,7 @@ impl StacksTransactionReceipt {
             microblock_header: None,
             tx_index: 0,
             vm_error: None,
+            vm_error_code: None,
         }
     }
 
@@ -224,6 +243,7 @@ impl StacksTransactionReceipt {
         error: CheckErrors,
     ) -> StacksTransactionReceipt {
         StacksTransactionReceipt {
+            tx_size: 0,
             transaction: tx.into(),
             post_condition_aborted: false,
             result: Value::err_none(),
@@ -234,6 +254,7 @@ impl StacksTransactionReceipt {
             microblock_header: None,
             tx_index: 0,
             vm_error: Some(format!("{}", &error)),
+            vm_error_code: Some(VmErrorCode::from_check_error(error)),
         }
     }
 
@@ -243,6 +264,7 @@ impl StacksTransactionReceipt {
         error: CheckErrors,
     ) -> StacksTransactionReceipt {
         StacksTransactionReceipt {
+            tx_size: 0,
             transaction: tx.into(),
             post_condition_aborted: false,
             result: Value::err_none(),
@@ -253,6 +275,7 @@ impl StacksTransactionReceipt {
             microblock_header: None,
             tx_index: 0,
             vm_error: Some(format!("{}", &error)),
+            vm_error_code: Some(VmErrorCode::from_check_error(error)),
         }
     }
 
@@ -458,8 +481,10 @@ impl StacksChainState {
         config: &DBConfig,
         tx: &StacksTransaction,
     ) -> Result<(), Error> {
-        // valid auth?
-        tx.verify().map_err(Error::NetError)?;
+        // valid auth?  (the block validation stage may have already checked)
+        if !take_verified_tx(tx) {
+            tx.verify().map_err(Error::NetError)?;
+        }
 
         // destined for us?
         if config.chain_id != tx.chain_id {
@@ -894,6 +919,25 @@ impl StacksChainState {
         origin_account: &StacksAccount,
         ast_rules: ASTRules,
     ) -> Result<StacksTransactionReceipt, Error> {
+        StacksChainState::process_transaction_payload_with_assets(
+            clarity_tx,
+            tx,
+            origin_account,
+            ast_rules,
+        )
+        .map(|(receipt, _)| receipt)
+    }
+
+    /// Like `process_transaction_payload()`, but also returns the assets that the payload moved
+    /// out of each principal -- i.e. what the transaction's post-conditions were checked against.
+    /// If the payload was aborted by its post-conditions, these are the assets it would have
+    /// moved.  The asset map is empty if the payload moved nothing or failed at runtime.
+    fn process_transaction_payload_with_assets(
+        clarity_tx: &mut ClarityTransactionConnection,
+        tx: &StacksTransaction,
+        origin_account: &StacksAccount,
+        ast_rules: ASTRules,
+    ) -> Result<(StacksTransactionReceipt, AssetMap), Error> {
         match tx.payload {
             TransactionPayload::TokenTransfer(ref addr, ref amount, ref memo) => {
                 // post-conditions are not allowed for this variant, since they're non-sensical.
@@ -912,7 +956,7 @@ impl StacksChainState {
                 }
 
                 let cost_before = clarity_tx.cost_so_far();
-                let (value, _asset_map, events) = clarity_tx
+                let (value, asset_map, events) = clarity_tx
                     .run_stx_transfer(
                         &origin_account.principal,
                         addr,
@@ -934,7 +978,7 @@ impl StacksChainState {
                     value,
                     total_cost,
                 );
-                Ok(receipt)
+                Ok((receipt, asset_map))
             }
             TransactionPayload::ContractCall(ref contract_call) => {
                 // if this calls a function that doesn't exist or is syntactically invalid, then the
@@ -968,6 +1012,7 @@ impl StacksChainState {
                     .sub(&cost_before)
                     .expect("BUG: total block cost decreased");
 
+                let mut vm_error_code = None;
                 let (result, asset_map, events) = match contract_call_resp {
                     Ok((return_value, asset_map, events)) => {
                         info!("Contract-call successfully processed";
@@ -985,7 +1030,26 @@ impl StacksChainState {
                                       "function_name" => %contract_call.function_name,
                                       "function_args" => %VecDisplay(&contract_call.function_args),
                                       "error" => ?error);
-                            (Value::err_none(), AssetMap::new(), vec![])
+                            if let clarity_error::Interpreter(ref e) = error {
+                                vm_error_code = Some(VmErrorCode::from(e));
+                            }
+                            let events = match error {
+                                clarity_error::Interpreter(InterpreterError::Runtime(
+                                    RuntimeErrorType::DefunctPoxContract,
+                                    _,
+                                )) => {
+                                    // tell observers why the call did nothing
+                                    vec![StacksTransactionEvent::PoxDefunctCallEvent(
+                                        PoxDefunctCallEventData {
+                                            sender: origin_account.principal.clone(),
+                                            contract_identifier: contract_id.clone(),
+                                            function_name: contract_call.function_name.to_string(),
+                                        },
+                                    )]
+                                }
+                                _ => vec![],
+                            };
+                            (Value::err_none(), AssetMap::new(), events)
                         }
                         ClarityRuntimeTxError::AbortedByCallback(value, assets, events) => {
                             info!("Contract-call aborted by post-condition";
@@ -998,7 +1062,7 @@ impl StacksChainState {
                                     value.expect("BUG: Post condition contract call must provide would-have-been-returned value"),
                                     assets.get_stx_burned_total(),
                                     total_cost);
-                            return Ok(receipt);
+                            return Ok((receipt, assets));
                         }
                         ClarityRuntimeTxError::CostError(cost_after, budget) => {
                             warn!("Block compute budget exceeded: if included, this will invalidate a block"; "txid" => %tx.txid(), "cost" => %cost_after, "budget" => %budget);
@@ -1020,7 +1084,7 @@ impl StacksChainState {
                                         total_cost,
                                         check_error,
                                     );
-                                return Ok(receipt);
+                                return Ok((receipt, AssetMap::new()));
                             } else {
                                 // prior to 2.1, this is not permitted in a block.
                                 warn!("Unexpected analysis error invalidating transaction: if included, this will invalidate a block";
@@ -1044,14 +1108,15 @@ impl StacksChainState {
                     },
                 };
 
-                let receipt = StacksTransactionReceipt::from_contract_call(
+                let mut receipt = StacksTransactionReceipt::from_contract_call(
                     tx.clone(),
                     events,
                     result,
                     asset_map.get_stx_burned_total(),
                     total_cost,
                 );
-                Ok(receipt)
+                receipt.vm_error_code = vm_error_code;
+                Ok((receipt, asset_map))
             }
             TransactionPayload::SmartContract(ref smart_contract, ref version_opt) => {
                 let epoch_id = clarity_tx.get_epoch();
@@ -1142,7 +1207,7 @@ impl StacksChainState {
                                 );
 
                                 // abort now -- no burns
-                                return Ok(receipt);
+                                return Ok((receipt, AssetMap::new()));
                             }
                         }
                     }
@@ -1177,6 +1242,7 @@ impl StacksChainState {
                     .sub(&cost_before)
                     .expect("BUG: total block cost decreased");
 
+                let mut vm_error_code = None;
                 let (asset_map, events) = match initialize_resp {
                     Ok(x) => {
                         // store analysis -- if this fails, then the have some pretty bad problems
@@ -1192,6 +1258,9 @@ impl StacksChainState {
                                       "contract" => %contract_id,
                                       "code" => %contract_code_str,
                                       "error" => ?error);
+                            if let clarity_error::Interpreter(ref e) = error {
+                                vm_error_code = Some(VmErrorCode::from(e));
+                            }
                             (AssetMap::new(), vec![])
                         }
                         ClarityRuntimeTxError::AbortedByCallback(_, assets, events) => {
@@ -1203,7 +1272,7 @@ impl StacksChainState {
                                     contract_analysis,
                                     total_cost,
                                 );
-                            return Ok(receipt);
+                            return Ok((receipt, assets));
                         }
                         ClarityRuntimeTxError::CostError(cost_after, budget) => {
                             warn!("Block compute budget exceeded: if included, this will invalidate a block";
@@ -1229,7 +1298,7 @@ impl StacksChainState {
                                         contract_analysis,
                                         check_error,
                                     );
-                                return Ok(receipt);
+                                return Ok((receipt, AssetMap::new()));
                             } else {
                                 // prior to 2.1, this is not permitted in a block.
                                 warn!("Unexpected analysis error invalidating transaction: if included, this will invalidate a block";
@@ -1253,14 +1322,15 @@ impl StacksChainState {
                     },
                 };
 
-                let receipt = StacksTransactionReceipt::from_smart_contract(
+                let mut receipt = StacksTransactionReceipt::from_smart_contract(
                     tx.clone(),
                     events,
                     asset_map.get_stx_burned_total(),
                     contract_analysis,
                     total_cost,
                 );
-                Ok(receipt)
+                receipt.vm_error_code = vm_error_code;
+                Ok((receipt, asset_map))
             }
             TransactionPayload::PoisonMicroblock(ref mblock_header_1, ref mblock_header_2) => {
                 // post-conditions are not allowed for this variant, since they're non-sensical.
@@ -1285,14 +1355,14 @@ impl StacksChainState {
                 let receipt =
                     StacksTransactionReceipt::from_poison_microblock(tx.clone(), res, cost);
 
-                Ok(receipt)
+                Ok((receipt, AssetMap::new()))
             }
             TransactionPayload::Coinbase(..) => {
                 // no-op; not handled here
                 // NOTE: technically, post-conditions are allowed (even if they're non-sensical).
 
                 let receipt = StacksTransactionReceipt::from_coinbase(tx.clone());
-                Ok(receipt)
+                Ok((receipt, AssetMap::new()))
             }
         }
     }
@@ -1322,6 +1392,18 @@ impl StacksChainState {
         quiet: bool,
         ast_rules: ASTRules,
     ) -> Result<(u64, StacksTransactionReceipt), Error> {
+        StacksChainState::process_transaction_with_assets(clarity_block, tx, quiet, ast_rules)
+            .map(|(fee, receipt, _)| (fee, receipt))
+    }
+
+    /// Process a transaction.  Return the fee, the transaction receipt, and the assets the
+    /// transaction's payload moved (see `process_transaction_payload_with_assets()`).
+    pub fn process_transaction_with_assets(
+        clarity_block: &mut ClarityTx,
+        tx: &StacksTransaction,
+        quiet: bool,
+        ast_rules: ASTRules,
+    ) -> Result<(u64, StacksTransactionReceipt, AssetMap), Error> {
         debug!("Process transaction {} ({})", tx.txid(), tx.payload.name());
         let epoch = clarity_block.get_epoch();
 
@@ -1341,7 +1423,7 @@ impl StacksChainState {
         let mut transaction = clarity_block.connection().start_transaction_processing();
 
         let fee = tx.get_tx_fee();
-        let tx_receipt = if epoch >= StacksEpochId::Epoch21 {
+        let (tx_receipt, asset_map) = if epoch >= StacksEpochId::Epoch21 {
             // 2.1 and later: pay tx fee, then process transaction
             let (_origin_account, payer_account) =
                 StacksChainState::check_transaction_nonces(&mut transaction, tx, quiet)?;
@@ -1354,12 +1436,13 @@ impl StacksChainState {
             let origin_account =
                 StacksChainState::get_account(&mut transaction, &tx.origin_address().into());
 
-            let tx_receipt = StacksChainState::process_transaction_payload(
-                &mut transaction,
-                tx,
-                &origin_account,
-                ast_rules,
-            )?;
+            let (tx_receipt, asset_map) =
+                StacksChainState::process_transaction_payload_with_assets(
+                    &mut transaction,
+                    tx,
+                    &origin_account,
+                    ast_rules,
+                )?;
 
             // update the account nonces
             StacksChainState::update_account_nonce(
@@ -1376,18 +1459,19 @@ impl StacksChainState {
                 );
             }
 
-            tx_receipt
+            (tx_receipt, asset_map)
         } else {
             // pre-2.1: process transaction, then pay tx fee
             let (origin_account, payer_account) =
                 StacksChainState::check_transaction_nonces(&mut transaction, tx, quiet)?;
 
-            let tx_receipt = StacksChainState::process_transaction_payload(
-                &mut transaction,
-                tx,
-                &origin_account,
-                ast_rules,
-            )?;
+            let (tx_receipt, asset_map) =
+                StacksChainState::process_transaction_payload_with_assets(
+                    &mut transaction,
+                    tx,
+                    &origin_account,
+                    ast_rules,
+                )?;
 
             let new_payer_account = StacksChainState::get_payer_account(&mut transaction, tx);
             StacksChainState::pay_transaction_fee(&mut transaction, fee, new_payer_account)?;
@@ -1406,12 +1490,12 @@ impl StacksChainState {
                 );
             }
 
-            tx_receipt
+            (tx_receipt, asset_map)
         };
 
         transaction.commit();
 
-        Ok((fee, tx_receipt))
+        Ok((fee, tx_receipt, asset_map))
     }
 }
 
@@ -9474,6 +9558,9 @@ pub mod test {
         assert!(err_str
             .find("TypeValueError(OptionalType(CallableType(Trait(TraitIdentifier ")
             .is_some());
+        let err_code = tx_receipt.vm_error_code.unwrap();
+        assert_eq!(err_code.code, 1001);
+        assert_eq!(err_code.label, "vm_unchecked");
 
         let (fee, tx_receipt) = StacksChainState::process_transaction(
             &mut conn,
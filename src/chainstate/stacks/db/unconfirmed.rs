@@ -36,11 +36,13 @@ use clarity::vm::database::BurnStateDB;
 use clarity::vm::database::HeadersDB;
 use clarity::vm::database::NULL_BURN_STATE_DB;
 use clarity::vm::database::NULL_HEADER_DB;
+use clarity::vm::VmLimits;
 
 use crate::chainstate::burn::db::sortdb::SortitionDB;
 use crate::clarity_vm::database::marf::MarfedKV;
 use crate::types::chainstate::StacksBlockId;
 use stacks_common::types::chainstate::BurnchainHeaderHash;
+use stacks_common::types::NetworkId;
 
 pub type UnconfirmedTxMap = HashMap<Txid, (StacksTransaction, BlockHeaderHash, u16)>;
 
@@ -85,10 +87,10 @@ pub struct UnconfirmedState {
     num_mblocks_added: u64,
     have_state: bool,
 
-    mainnet: bool,
-    chain_id: u32,
+    network: NetworkId,
     clarity_state_index_root: String,
     marf_opts: Option<MARFOpenOpts>,
+    vm_limits: Option<VmLimits>,
 
     // fault injection for testing
     pub disable_cost_check: bool,
@@ -105,7 +107,8 @@ impl UnconfirmedState {
             chainstate.marf_opts.clone(),
         )?;
 
-        let clarity_instance = ClarityInstance::new(chainstate.mainnet, chainstate.chain_id, marf);
+        let mut clarity_instance = ClarityInstance::new(chainstate.network, marf);
+        clarity_instance.set_vm_limits(chainstate.vm_limits);
         let unconfirmed_tip = MARF::make_unconfirmed_chain_tip(&tip);
         let cost_so_far = StacksChainState::get_stacks_block_anchored_cost(chainstate.db(), &tip)?
             .ok_or(Error::NoSuchBlockError)?;
@@ -126,10 +129,10 @@ impl UnconfirmedState {
             num_mblocks_added: 0,
             have_state: false,
 
-            mainnet: chainstate.mainnet,
-            chain_id: chainstate.chain_id,
+            network: chainstate.network,
             clarity_state_index_root: chainstate.clarity_state_index_root.clone(),
             marf_opts: chainstate.marf_opts.clone(),
+            vm_limits: chainstate.vm_limits,
 
             disable_cost_check: check_fault_injection(FAULT_DISABLE_MICROBLOCKS_COST_CHECK),
             disable_bytes_check: check_fault_injection(FAULT_DISABLE_MICROBLOCKS_BYTES_CHECK),
@@ -145,7 +148,8 @@ impl UnconfirmedState {
             self.marf_opts.clone(),
         )?;
 
-        let clarity_instance = ClarityInstance::new(self.mainnet, self.chain_id, marf);
+        let mut clarity_instance = ClarityInstance::new(self.network, marf);
+        clarity_instance.set_vm_limits(self.vm_limits);
 
         Ok(UnconfirmedState {
             confirmed_chain_tip: self.confirmed_chain_tip.clone(),
@@ -163,10 +167,10 @@ impl UnconfirmedState {
             num_mblocks_added: self.num_mblocks_added,
             have_state: self.have_state,
 
-            mainnet: self.mainnet,
-            chain_id: self.chain_id,
+            network: self.network,
             clarity_state_index_root: self.clarity_state_index_root.clone(),
             marf_opts: self.marf_opts.clone(),
+            vm_limits: self.vm_limits,
 
             disable_cost_check: self.disable_cost_check,
             disable_bytes_check: self.disable_bytes_check,
@@ -184,7 +188,8 @@ impl UnconfirmedState {
             chainstate.marf_opts.clone(),
         )?;
 
-        let clarity_instance = ClarityInstance::new(chainstate.mainnet, chainstate.chain_id, marf);
+        let mut clarity_instance = ClarityInstance::new(chainstate.network, marf);
+        clarity_instance.set_vm_limits(chainstate.vm_limits);
         let unconfirmed_tip = MARF::make_unconfirmed_chain_tip(&tip);
         let cost_so_far = StacksChainState::get_stacks_block_anchored_cost(chainstate.db(), &tip)?
             .ok_or(Error::NoSuchBlockError)?;
@@ -205,10 +210,10 @@ impl UnconfirmedState {
             num_mblocks_added: 0,
             have_state: false,
 
-            mainnet: chainstate.mainnet,
-            chain_id: chainstate.chain_id,
+            network: chainstate.network,
             clarity_state_index_root: chainstate.clarity_state_index_root.clone(),
             marf_opts: chainstate.marf_opts.clone(),
+            vm_limits: chainstate.vm_limits,
 
             disable_cost_check: check_fault_injection(FAULT_DISABLE_MICROBLOCKS_COST_CHECK),
             disable_bytes_check: check_fault_injection(FAULT_DISABLE_MICROBLOCKS_BYTES_CHECK),
@@ -308,6 +313,7 @@ impl UnconfirmedState {
                     match StacksChainState::process_microblocks_transactions(
                         &mut clarity_tx,
                         &vec![mblock.clone()],
+                        &[],
                         ast_rules,
                     ) {
                         Ok(x) => x,
@@ -0,0 +1,311 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Cache of block validation verdicts.
+//!
+//! Once a block is found to be invalid, it is orphaned and its staging data is eventually deleted.
+//! If a peer pushes it to us again, or we download it again from another peer, it would be stored
+//! and executed all over again.  This cache remembers the outcome of executing a block so the
+//! relayer can refuse a block already known to be invalid, and block processing can skip
+//! re-executing it.
+//!
+//! A verdict only holds for the context the block was executed in, so each verdict records that
+//! context and is only returned for a lookup in the same context:
+//! * the parent block's index hash and the tail of the parent microblock stream it confirms,
+//! which determine the chain state the block is evaluated against;
+//! * the consensus hash of the sortition that elected the block, which determines the burnchain
+//! view (burn block height, PoX reward cycle, epoch) the block is evaluated in.
+//!
+//! The cache is shared by every `StacksChainState` opened on the same chainstate directory, since
+//! the relayer and the chains coordinator each open their own.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::chainstate::stacks::Error;
+use crate::types::chainstate::{BlockHeaderHash, ConsensusHash, StacksBlockId};
+
+/// Maximum number of verdicts to remember per chainstate
+pub const BLOCK_VALIDATION_CACHE_CAPACITY: usize = 4096;
+
+lazy_static! {
+    static ref BLOCK_VALIDATION_CACHES: Mutex<HashMap<String, Arc<Mutex<BlockValidationCache>>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// The context in which a block was validated
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockValidationContext {
+    pub consensus_hash: ConsensusHash,
+    pub parent_block_id: StacksBlockId,
+    pub parent_microblock_hash: BlockHeaderHash,
+    pub parent_microblock_seq: u16,
+}
+
+/// The outcome of executing a block
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockValidationVerdict {
+    Valid,
+    /// The anchored block was invalid
+    Invalid(String),
+    /// A microblock in the parent stream that the block confirms was invalid
+    InvalidMicroblock(String, BlockHeaderHash),
+}
+
+impl BlockValidationVerdict {
+    /// Get the verdict for an error encountered while executing a block, if the error means the
+    /// block will never be valid in this context.  Errors that could go away on a retry (e.g. I/O
+    /// and database errors) produce no verdict.
+    pub fn from_error(e: &Error) -> Option<BlockValidationVerdict> {
+        match e {
+            Error::InvalidStacksMicroblock(msg, header_hash) => Some(
+                BlockValidationVerdict::InvalidMicroblock(msg.clone(), header_hash.clone()),
+            ),
+            Error::InvalidStacksBlock(msg) => Some(BlockValidationVerdict::Invalid(msg.clone())),
+            Error::InvalidStacksTransaction(..)
+            | Error::InvalidFee
+            | Error::BlockTooBigError
+            | Error::BlockCostExceeded
+            | Error::CostOverflowError(..)
+            | Error::MicroblockStreamTooLongError
+            | Error::ProblematicTransaction(..) => {
+                Some(BlockValidationVerdict::Invalid(format!("{:?}", e)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Does this error mean the block breaks a consensus rule, so that every node would reject it?
+    /// Unlike `from_error()`, this excludes node-local policy rejections such as
+    /// `ProblematicTransaction`, which an honest miner running different software can trigger.
+    pub fn is_consensus_invalid(e: &Error) -> bool {
+        match e {
+            Error::InvalidStacksMicroblock(..)
+            | Error::InvalidStacksBlock(..)
+            | Error::InvalidStacksTransaction(..)
+            | Error::InvalidFee
+            | Error::BlockTooBigError
+            | Error::BlockCostExceeded
+            | Error::CostOverflowError(..)
+            | Error::MicroblockStreamTooLongError => true,
+            _ => false,
+        }
+    }
+
+    /// The error to report for a block with this verdict.  Returns None if the block is valid.
+    pub fn to_error(&self) -> Option<Error> {
+        match self {
+            BlockValidationVerdict::Valid => None,
+            BlockValidationVerdict::Invalid(msg) => Some(Error::InvalidStacksBlock(msg.clone())),
+            BlockValidationVerdict::InvalidMicroblock(msg, header_hash) => Some(
+                Error::InvalidStacksMicroblock(msg.clone(), header_hash.clone()),
+            ),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        *self == BlockValidationVerdict::Valid
+    }
+}
+
+/// Bounded map from anchored block hash to the verdict of its most recent execution
+#[derive(Debug)]
+pub struct BlockValidationCache {
+    verdicts: HashMap<BlockHeaderHash, (BlockValidationContext, BlockValidationVerdict)>,
+    /// Insertion order, for eviction
+    order: VecDeque<BlockHeaderHash>,
+    capacity: usize,
+}
+
+impl BlockValidationCache {
+    pub fn new(capacity: usize) -> BlockValidationCache {
+        BlockValidationCache {
+            verdicts: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Get the cache shared by all chainstate instances opened on `root_path`
+    pub fn for_chainstate(root_path: &str) -> Arc<Mutex<BlockValidationCache>> {
+        let mut caches = BLOCK_VALIDATION_CACHES
+            .lock()
+            .expect("FATAL: block validation cache lock poisoned");
+        caches
+            .entry(root_path.to_string())
+            .or_insert_with(|| {
+                Arc::new(Mutex::new(BlockValidationCache::new(
+                    BLOCK_VALIDATION_CACHE_CAPACITY,
+                )))
+            })
+            .clone()
+    }
+
+    /// Get the verdict for a block, if it was validated in the given context
+    pub fn get(
+        &self,
+        block_hash: &BlockHeaderHash,
+        context: &BlockValidationContext,
+    ) -> Option<&BlockValidationVerdict> {
+        match self.verdicts.get(block_hash) {
+            Some((cached_context, verdict)) if cached_context == context => Some(verdict),
+            _ => None,
+        }
+    }
+
+    /// Store the verdict for a block.  This replaces any verdict from a different context.
+    pub fn insert(
+        &mut self,
+        block_hash: BlockHeaderHash,
+        context: BlockValidationContext,
+        verdict: BlockValidationVerdict,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self
+            .verdicts
+            .insert(block_hash.clone(), (context, verdict))
+            .is_none()
+        {
+            self.order.push_back(block_hash);
+        }
+        while self.verdicts.len() > self.capacity {
+            match self.order.pop_front() {
+                Some(evicted) => {
+                    self.verdicts.remove(&evicted);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Forget the verdict for a block
+    pub fn remove(&mut self, block_hash: &BlockHeaderHash) {
+        if self.verdicts.remove(block_hash).is_some() {
+            self.order.retain(|h| h != block_hash);
+        }
+    }
+
+    /// Forget all verdicts, e.g. because the chainstate was re-instantiated
+    pub fn clear(&mut self) {
+        self.verdicts.clear();
+        self.order.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.verdicts.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::burnchains::Txid;
+
+    fn context(parent: u8, mblock_seq: u16) -> BlockValidationContext {
+        BlockValidationContext {
+            consensus_hash: ConsensusHash([1u8; 20]),
+            parent_block_id: StacksBlockId([parent; 32]),
+            parent_microblock_hash: BlockHeaderHash([mblock_seq as u8; 32]),
+            parent_microblock_seq: mblock_seq,
+        }
+    }
+
+    #[test]
+    fn test_block_validation_cache_context() {
+        let mut cache = BlockValidationCache::new(10);
+        let block = BlockHeaderHash([0xaa; 32]);
+        let verdict = BlockValidationVerdict::Invalid("bad".to_string());
+        cache.insert(block.clone(), context(1, 0), verdict.clone());
+
+        assert_eq!(cache.get(&block, &context(1, 0)), Some(&verdict));
+
+        // same block on a different parent, or confirming a different microblock tail, misses
+        assert_eq!(cache.get(&block, &context(2, 0)), None);
+        assert_eq!(cache.get(&block, &context(1, 1)), None);
+
+        // and so does the same block elected in a different sortition
+        let mut other_sortition = context(1, 0);
+        other_sortition.consensus_hash = ConsensusHash([2u8; 20]);
+        assert_eq!(cache.get(&block, &other_sortition), None);
+
+        // a verdict in a new context replaces the old one
+        cache.insert(block.clone(), context(2, 0), BlockValidationVerdict::Valid);
+        assert_eq!(cache.get(&block, &context(1, 0)), None);
+        assert!(cache.get(&block, &context(2, 0)).unwrap().is_valid());
+        assert_eq!(cache.len(), 1);
+
+        cache.remove(&block);
+        assert_eq!(cache.get(&block, &context(2, 0)), None);
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn test_block_validation_cache_eviction() {
+        let mut cache = BlockValidationCache::new(3);
+        for i in 0..5u8 {
+            cache.insert(
+                BlockHeaderHash([i; 32]),
+                context(0, 0),
+                BlockValidationVerdict::Valid,
+            );
+        }
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get(&BlockHeaderHash([0; 32]), &context(0, 0)), None);
+        assert_eq!(cache.get(&BlockHeaderHash([1; 32]), &context(0, 0)), None);
+        for i in 2..5u8 {
+            assert!(cache
+                .get(&BlockHeaderHash([i; 32]), &context(0, 0))
+                .is_some());
+        }
+    }
+
+    #[test]
+    fn test_block_validation_verdict_from_error() {
+        let mblock = BlockHeaderHash([3u8; 32]);
+        let verdict = BlockValidationVerdict::from_error(&Error::InvalidStacksMicroblock(
+            "bad mblock".to_string(),
+            mblock.clone(),
+        ))
+        .unwrap();
+        match verdict.to_error() {
+            Some(Error::InvalidStacksMicroblock(msg, hash)) => {
+                assert_eq!(msg, "bad mblock");
+                assert_eq!(hash, mblock);
+            }
+            _ => panic!("wrong error"),
+        }
+
+        assert!(BlockValidationVerdict::from_error(&Error::BlockTooBigError).is_some());
+
+        // transient errors are not verdicts
+        assert!(BlockValidationVerdict::from_error(&Error::NoSuchBlockError).is_none());
+        assert!(BlockValidationVerdict::from_error(&Error::MinerAborted).is_none());
+        assert!(BlockValidationVerdict::from_error(&Error::BlockProcessingPreempted).is_none());
+
+        // a problematic transaction gets a verdict, but is only this node's policy
+        let problematic = Error::ProblematicTransaction(Txid([4u8; 32]));
+        assert!(BlockValidationVerdict::from_error(&problematic).is_some());
+        assert!(!BlockValidationVerdict::is_consensus_invalid(&problematic));
+        assert!(BlockValidationVerdict::is_consensus_invalid(
+            &Error::BlockTooBigError
+        ));
+        assert!(!BlockValidationVerdict::is_consensus_invalid(
+            &Error::NoSuchBlockError
+        ));
+    }
+}
@@ -6,6 +6,7 @@ use crate::types::chainstate::StacksAddress;
 use clarity::util::hash::to_hex;
 use clarity::vm::analysis::ContractAnalysis;
 use clarity::vm::costs::ExecutionCost;
+use clarity::vm::errors::VmErrorCode;
 use clarity::vm::types::{
     AssetIdentifier, PrincipalData, QualifiedContractIdentifier, StandardPrincipalData, Value,
 };
@@ -53,6 +54,11 @@ pub struct StacksTransactionReceipt {
     pub execution_cost: ExecutionCost,
     pub microblock_header: Option<StacksMicroblockHeader>,
     pub tx_index: u32,
+    /// Encoded size of the transaction in bytes, as recorded when its block or microblock was
+    /// decoded, or when it was mined.  0 if not known (e.g. for burnchain operations).
+    pub tx_size: u64,
     /// This is really a string-formatted CheckError (which can't be clone()'ed)
     pub vm_error: Option<String>,
+    /// Stable error code and context fields for the runtime error the transaction hit, if any
+    pub vm_error_code: Option<VmErrorCode>,
 }
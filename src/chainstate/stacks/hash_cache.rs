@@ -0,0 +1,340 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Helpers for computing transaction IDs and block hashes at most once.
+//!
+//! `StacksTransaction`, `StacksBlock`, and `StacksMicroblock` expose their fields publicly, so a
+//! hash cached on the structs themselves could go stale whenever a caller mutates them.  Instead,
+//! the `Cached*` wrappers here take ownership of the value and memoize its hashes in a `OnceCell`
+//! the first time they are asked for.  The wrapped value can only be mutated through the
+//! wrapper's `*_mut()` methods, which forget the memoized hashes.  Code paths that hash the same
+//! data repeatedly -- mempool admission, relaying, and mining -- can carry a wrapper around
+//! instead of re-serializing.
+//!
+//! The batch helpers compute many hashes while reusing a single serialization buffer.
+
+use std::ops::Deref;
+
+use once_cell::sync::OnceCell;
+
+use crate::burnchains::Txid;
+use crate::chainstate::burn::ConsensusHash;
+use crate::chainstate::stacks::{StacksBlock, StacksMicroblock, StacksTransaction};
+use crate::codec::StacksMessageCodec;
+use crate::types::chainstate::{BlockHeaderHash, StacksBlockId};
+use stacks_common::util::hash::MerkleTree;
+use stacks_common::util::hash::Sha512Trunc256Sum;
+
+/// Compute the txids of a list of transactions, reusing one serialization buffer
+pub fn compute_txids(txs: &[StacksTransaction]) -> Vec<Txid> {
+    let mut buf = vec![];
+    txs.iter()
+        .map(|tx| {
+            buf.clear();
+            tx.consensus_serialize(&mut buf)
+                .expect("BUG: failed to serialize to a vec");
+            Txid::from_stacks_tx(&buf)
+        })
+        .collect()
+}
+
+/// Compute the block hashes of a list of microblocks, reusing one serialization buffer
+pub fn compute_microblock_hashes(microblocks: &[StacksMicroblock]) -> Vec<BlockHeaderHash> {
+    let mut buf = vec![];
+    microblocks
+        .iter()
+        .map(|mblock| {
+            buf.clear();
+            mblock
+                .header
+                .consensus_serialize(&mut buf)
+                .expect("BUG: failed to serialize to a vec");
+            BlockHeaderHash::from_serialized_header(&buf[..])
+        })
+        .collect()
+}
+
+/// Compute the transaction Merkle root over a list of already-computed txids
+pub fn txid_merkle_root(txids: &[Txid]) -> Sha512Trunc256Sum {
+    let txid_vecs = txids.iter().map(|txid| txid.as_bytes().to_vec()).collect();
+    MerkleTree::<Sha512Trunc256Sum>::new(&txid_vecs).root()
+}
+
+/// Compute the transaction Merkle root over a list of transactions
+pub fn tx_merkle_root(txs: &[StacksTransaction]) -> Sha512Trunc256Sum {
+    txid_merkle_root(&compute_txids(txs))
+}
+
+/// A transaction whose txid is computed at most once
+#[derive(Debug, Clone)]
+pub struct CachedTransaction {
+    tx: StacksTransaction,
+    txid: OnceCell<Txid>,
+}
+
+impl CachedTransaction {
+    pub fn new(tx: StacksTransaction) -> CachedTransaction {
+        CachedTransaction {
+            tx,
+            txid: OnceCell::new(),
+        }
+    }
+
+    /// Wrap a transaction whose txid is already known (e.g. because it was just loaded by txid).
+    /// The caller is responsible for `txid` being correct.
+    pub fn with_txid(tx: StacksTransaction, txid: Txid) -> CachedTransaction {
+        let cell = OnceCell::new();
+        let _ = cell.set(txid);
+        CachedTransaction { tx, txid: cell }
+    }
+
+    pub fn txid(&self) -> Txid {
+        self.txid.get_or_init(|| self.tx.txid()).clone()
+    }
+
+    pub fn tx(&self) -> &StacksTransaction {
+        &self.tx
+    }
+
+    /// Mutate the transaction.  Its txid will be recomputed the next time it is asked for.
+    pub fn tx_mut(&mut self) -> &mut StacksTransaction {
+        self.txid = OnceCell::new();
+        &mut self.tx
+    }
+
+    pub fn into_inner(self) -> StacksTransaction {
+        self.tx
+    }
+}
+
+impl Deref for CachedTransaction {
+    type Target = StacksTransaction;
+    fn deref(&self) -> &StacksTransaction {
+        &self.tx
+    }
+}
+
+impl From<StacksTransaction> for CachedTransaction {
+    fn from(tx: StacksTransaction) -> CachedTransaction {
+        CachedTransaction::new(tx)
+    }
+}
+
+impl PartialEq for CachedTransaction {
+    fn eq(&self, other: &CachedTransaction) -> bool {
+        self.tx == other.tx
+    }
+}
+
+/// An anchored block whose block hash and txids are computed at most once
+#[derive(Debug, Clone)]
+pub struct CachedStacksBlock {
+    block: StacksBlock,
+    block_hash: OnceCell<BlockHeaderHash>,
+    txids: OnceCell<Vec<Txid>>,
+}
+
+impl CachedStacksBlock {
+    pub fn new(block: StacksBlock) -> CachedStacksBlock {
+        CachedStacksBlock {
+            block,
+            block_hash: OnceCell::new(),
+            txids: OnceCell::new(),
+        }
+    }
+
+    pub fn block_hash(&self) -> BlockHeaderHash {
+        self.block_hash
+            .get_or_init(|| self.block.block_hash())
+            .clone()
+    }
+
+    pub fn index_block_hash(&self, consensus_hash: &ConsensusHash) -> StacksBlockId {
+        StacksBlockId::new(consensus_hash, &self.block_hash())
+    }
+
+    /// The txids of this block's transactions, in block order
+    pub fn txids(&self) -> &[Txid] {
+        self.txids.get_or_init(|| compute_txids(&self.block.txs))
+    }
+
+    pub fn block(&self) -> &StacksBlock {
+        &self.block
+    }
+
+    /// Mutate the block.  Its hashes will be recomputed the next time they are asked for.
+    pub fn block_mut(&mut self) -> &mut StacksBlock {
+        self.block_hash = OnceCell::new();
+        self.txids = OnceCell::new();
+        &mut self.block
+    }
+
+    pub fn into_inner(self) -> StacksBlock {
+        self.block
+    }
+}
+
+impl Deref for CachedStacksBlock {
+    type Target = StacksBlock;
+    fn deref(&self) -> &StacksBlock {
+        &self.block
+    }
+}
+
+impl From<StacksBlock> for CachedStacksBlock {
+    fn from(block: StacksBlock) -> CachedStacksBlock {
+        CachedStacksBlock::new(block)
+    }
+}
+
+impl PartialEq for CachedStacksBlock {
+    fn eq(&self, other: &CachedStacksBlock) -> bool {
+        self.block == other.block
+    }
+}
+
+/// A microblock whose block hash and txids are computed at most once
+#[derive(Debug, Clone)]
+pub struct CachedStacksMicroblock {
+    microblock: StacksMicroblock,
+    block_hash: OnceCell<BlockHeaderHash>,
+    txids: OnceCell<Vec<Txid>>,
+}
+
+impl CachedStacksMicroblock {
+    pub fn new(microblock: StacksMicroblock) -> CachedStacksMicroblock {
+        CachedStacksMicroblock {
+            microblock,
+            block_hash: OnceCell::new(),
+            txids: OnceCell::new(),
+        }
+    }
+
+    pub fn block_hash(&self) -> BlockHeaderHash {
+        self.block_hash
+            .get_or_init(|| self.microblock.block_hash())
+            .clone()
+    }
+
+    /// The txids of this microblock's transactions, in microblock order
+    pub fn txids(&self) -> &[Txid] {
+        self.txids
+            .get_or_init(|| compute_txids(&self.microblock.txs))
+    }
+
+    pub fn microblock(&self) -> &StacksMicroblock {
+        &self.microblock
+    }
+
+    /// Mutate the microblock.  Its hashes will be recomputed the next time they are asked for.
+    pub fn microblock_mut(&mut self) -> &mut StacksMicroblock {
+        self.block_hash = OnceCell::new();
+        self.txids = OnceCell::new();
+        &mut self.microblock
+    }
+
+    pub fn into_inner(self) -> StacksMicroblock {
+        self.microblock
+    }
+}
+
+impl Deref for CachedStacksMicroblock {
+    type Target = StacksMicroblock;
+    fn deref(&self) -> &StacksMicroblock {
+        &self.microblock
+    }
+}
+
+impl From<StacksMicroblock> for CachedStacksMicroblock {
+    fn from(microblock: StacksMicroblock) -> CachedStacksMicroblock {
+        CachedStacksMicroblock::new(microblock)
+    }
+}
+
+impl PartialEq for CachedStacksMicroblock {
+    fn eq(&self, other: &CachedStacksMicroblock) -> bool {
+        self.microblock == other.microblock
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::stacks::test::make_codec_test_block;
+
+    #[test]
+    fn test_cached_hashes_match_uncached() {
+        let block = make_codec_test_block(25);
+        let expected_txids: Vec<_> = block.txs.iter().map(|tx| tx.txid()).collect();
+
+        assert_eq!(compute_txids(&block.txs), expected_txids);
+        assert_eq!(tx_merkle_root(&block.txs), block.header.tx_merkle_root);
+
+        let cached_block = CachedStacksBlock::new(block.clone());
+        assert_eq!(cached_block.block_hash(), block.block_hash());
+        assert_eq!(cached_block.txids(), &expected_txids[..]);
+        // second call hits the cache
+        assert_eq!(cached_block.block_hash(), block.block_hash());
+
+        for (tx, txid) in block.txs.iter().zip(expected_txids.iter()) {
+            let cached_tx = CachedTransaction::new(tx.clone());
+            assert_eq!(&cached_tx.txid(), txid);
+            assert_eq!(cached_tx.tx(), tx);
+        }
+
+        let microblock =
+            StacksMicroblock::first_unsigned(&block.block_hash(), block.txs[1..].to_vec());
+        let cached_microblock = CachedStacksMicroblock::new(microblock.clone());
+        assert_eq!(cached_microblock.block_hash(), microblock.block_hash());
+        assert_eq!(
+            compute_microblock_hashes(&[microblock.clone()]),
+            vec![microblock.block_hash()]
+        );
+        assert_eq!(cached_microblock.txids(), &expected_txids[1..]);
+    }
+
+    #[test]
+    fn test_cached_hashes_invalidated_on_mutation() {
+        let block = make_codec_test_block(25);
+
+        let mut cached_tx = CachedTransaction::new(block.txs[1].clone());
+        let old_txid = cached_tx.txid();
+        cached_tx.tx_mut().set_tx_fee(12345);
+        assert!(cached_tx.txid() != old_txid);
+        assert_eq!(cached_tx.txid(), cached_tx.tx().txid());
+
+        let mut cached_block = CachedStacksBlock::new(block.clone());
+        let old_block_hash = cached_block.block_hash();
+        assert_eq!(cached_block.txids().len(), block.txs.len());
+        cached_block.block_mut().txs.pop();
+        cached_block.block_mut().header.tx_merkle_root = tx_merkle_root(&cached_block.block().txs);
+        assert!(cached_block.block_hash() != old_block_hash);
+        assert_eq!(cached_block.block_hash(), cached_block.block().block_hash());
+        assert_eq!(cached_block.txids().len(), block.txs.len() - 1);
+
+        let mut cached_microblock = CachedStacksMicroblock::new(StacksMicroblock::first_unsigned(
+            &block.block_hash(),
+            block.txs.clone(),
+        ));
+        let old_microblock_hash = cached_microblock.block_hash();
+        cached_microblock.microblock_mut().header.sequence += 1;
+        assert!(cached_microblock.block_hash() != old_microblock_hash);
+        assert_eq!(
+            cached_microblock.block_hash(),
+            cached_microblock.microblock().block_hash()
+        );
+    }
+}
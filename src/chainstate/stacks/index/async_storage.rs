@@ -0,0 +1,248 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! An async facade over the MARF.
+//!
+//! Every MARF read and write blocks on SQLite and on the trie blob file, so async code can't call
+//! into a `TrieStorageConnection` directly without stalling its executor.  `AsyncTrieStorage`
+//! instead hands each operation to a small pool of worker threads, and returns a future that
+//! resolves once a worker has run it.  Reads are spread across several workers, each with its own
+//! read-only connection to the MARF, so concurrent reads proceed in parallel.  Writes all go to a
+//! single worker that owns the read/write MARF, and run in the order they were submitted.
+//!
+//! The futures don't depend on any particular executor.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::thread::JoinHandle;
+
+use crate::chainstate::stacks::index::marf::{MarfConnection, MARF};
+use crate::chainstate::stacks::index::{Error, MARFValue, MarfTrieId, TrieMerkleProof};
+
+/// An operation to run on a worker's MARF
+type Job<T> = Box<dyn FnOnce(&mut MARF<T>) + Send>;
+
+/// The state shared between a `TrieFuture` and the worker that completes it
+struct Completion<R> {
+    result: Option<R>,
+    waker: Option<Waker>,
+    /// Set if the worker went away without running the job
+    abandoned: bool,
+}
+
+/// Future for the result of an operation submitted to an `AsyncTrieStorage`.
+/// Resolves to `Err(Error::WorkerShutdownError)` if the storage was shut down before the
+/// operation ran.
+pub struct TrieFuture<R> {
+    completion: Arc<Mutex<Completion<Result<R, Error>>>>,
+}
+
+impl<R> Future for TrieFuture<R> {
+    type Output = Result<R, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut completion = self
+            .completion
+            .lock()
+            .expect("FATAL: trie future lock poisoned");
+        if let Some(result) = completion.result.take() {
+            return Poll::Ready(result);
+        }
+        if completion.abandoned {
+            return Poll::Ready(Err(Error::WorkerShutdownError));
+        }
+        completion.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Wakes the future if its job is dropped without being run (e.g. because the worker exited)
+struct CompletionGuard<R> {
+    completion: Arc<Mutex<Completion<Result<R, Error>>>>,
+}
+
+impl<R> CompletionGuard<R> {
+    fn complete(self, result: Result<R, Error>) {
+        let mut completion = self
+            .completion
+            .lock()
+            .expect("FATAL: trie future lock poisoned");
+        completion.result = Some(result);
+        if let Some(waker) = completion.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<R> Drop for CompletionGuard<R> {
+    fn drop(&mut self) {
+        let mut completion = self
+            .completion
+            .lock()
+            .expect("FATAL: trie future lock poisoned");
+        if completion.result.is_none() {
+            completion.abandoned = true;
+            if let Some(waker) = completion.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// A pool of worker threads that run MARF operations on behalf of async callers
+pub struct AsyncTrieStorage<T: MarfTrieId> {
+    read_queue: Option<Sender<Job<T>>>,
+    write_queue: Option<Sender<Job<T>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl<T: MarfTrieId + Send + 'static> AsyncTrieStorage<T> {
+    /// Take ownership of `marf` and start the worker threads.  Writes run against `marf` itself,
+    /// and `num_readers` (at least 1) read-only reopenings of it serve reads.
+    pub fn new(marf: MARF<T>, num_readers: usize) -> Result<AsyncTrieStorage<T>, Error> {
+        let num_readers = num_readers.max(1);
+        let mut readers = Vec::with_capacity(num_readers);
+        for _ in 0..num_readers {
+            readers.push(marf.reopen_readonly()?);
+        }
+
+        let mut workers = Vec::with_capacity(num_readers + 1);
+
+        // readers share one queue, so an idle reader picks up the next read
+        let (read_queue, read_jobs) = channel::<Job<T>>();
+        let read_jobs = Arc::new(Mutex::new(read_jobs));
+        for (i, reader) in readers.into_iter().enumerate() {
+            let read_jobs = read_jobs.clone();
+            let worker = thread::Builder::new()
+                .name(format!("marf-reader-{}", i))
+                .spawn(move || Self::run_shared_worker(reader, read_jobs))?;
+            workers.push(worker);
+        }
+
+        let (write_queue, write_jobs) = channel::<Job<T>>();
+        let worker = thread::Builder::new()
+            .name("marf-writer".to_string())
+            .spawn(move || Self::run_worker(marf, write_jobs))?;
+        workers.push(worker);
+
+        Ok(AsyncTrieStorage {
+            read_queue: Some(read_queue),
+            write_queue: Some(write_queue),
+            workers,
+        })
+    }
+
+    fn run_worker(mut marf: MARF<T>, jobs: Receiver<Job<T>>) {
+        while let Ok(job) = jobs.recv() {
+            job(&mut marf);
+        }
+    }
+
+    fn run_shared_worker(mut marf: MARF<T>, jobs: Arc<Mutex<Receiver<Job<T>>>>) {
+        loop {
+            let job = {
+                let jobs = jobs.lock().expect("FATAL: MARF read queue lock poisoned");
+                match jobs.recv() {
+                    Ok(job) => job,
+                    Err(_) => return,
+                }
+            };
+            job(&mut marf);
+        }
+    }
+
+    fn submit<F, R>(queue: &Option<Sender<Job<T>>>, f: F) -> TrieFuture<R>
+    where
+        F: FnOnce(&mut MARF<T>) -> Result<R, Error> + Send + 'static,
+        R: Send + 'static,
+    {
+        let completion = Arc::new(Mutex::new(Completion {
+            result: None,
+            waker: None,
+            abandoned: false,
+        }));
+        let guard = CompletionGuard {
+            completion: completion.clone(),
+        };
+        let job: Job<T> = Box::new(move |marf: &mut MARF<T>| guard.complete(f(marf)));
+
+        // if the job can't be queued, it's dropped here, and the future resolves to an error
+        if let Some(queue) = queue.as_ref() {
+            let _ = queue.send(job);
+        }
+        TrieFuture { completion }
+    }
+
+    /// Run `f` on one of the read-only MARFs.
+    pub fn read<F, R>(&self, f: F) -> TrieFuture<R>
+    where
+        F: FnOnce(&mut MARF<T>) -> Result<R, Error> + Send + 'static,
+        R: Send + 'static,
+    {
+        Self::submit(&self.read_queue, f)
+    }
+
+    /// Run `f` on the read/write MARF, after every write submitted before it.
+    pub fn write<F, R>(&self, f: F) -> TrieFuture<R>
+    where
+        F: FnOnce(&mut MARF<T>) -> Result<R, Error> + Send + 'static,
+        R: Send + 'static,
+    {
+        Self::submit(&self.write_queue, f)
+    }
+
+    /// Get the value of `key` as of `block_hash`.
+    pub async fn get(&self, block_hash: &T, key: &str) -> Result<Option<MARFValue>, Error> {
+        let block_hash = block_hash.clone();
+        let key = key.to_string();
+        self.read(move |marf| marf.get(&block_hash, &key)).await
+    }
+
+    /// Get the value of `key` as of `block_hash`, along with a Merkle proof of it.
+    pub async fn proof(
+        &self,
+        block_hash: &T,
+        key: &str,
+    ) -> Result<Option<(MARFValue, TrieMerkleProof<T>)>, Error> {
+        let block_hash = block_hash.clone();
+        let key = key.to_string();
+        self.read(move |marf| marf.get_with_proof(&block_hash, &key))
+            .await
+    }
+
+    /// Insert `key` into the trie that the read/write MARF is currently building.  The trie must
+    /// have been begun with a prior `write()`.
+    pub async fn insert(&self, key: &str, value: MARFValue) -> Result<(), Error> {
+        let key = key.to_string();
+        self.write(move |marf| marf.insert(&key, value)).await
+    }
+}
+
+impl<T: MarfTrieId> Drop for AsyncTrieStorage<T> {
+    fn drop(&mut self) {
+        // closing the queues stops the workers once they finish what's already queued
+        self.read_queue.take();
+        self.write_queue.take();
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() {
+                warn!("MARF worker thread panicked");
+            }
+        }
+    }
+}
@@ -15,7 +15,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::char::from_digit;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
 use std::env;
 use std::fmt;
@@ -25,6 +25,7 @@ use std::io;
 use std::io::{BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::iter::FromIterator;
 use std::marker::PhantomData;
+use std::mem;
 use std::ops::{Deref, DerefMut};
 use std::os;
 use std::path::{Path, PathBuf};
@@ -54,6 +55,7 @@ use crate::util_lib::db::tx_begin_immediate;
 use crate::util_lib::db::tx_busy_handler;
 use crate::util_lib::db::Error as db_error;
 use crate::util_lib::db::SQLITE_MMAP_SIZE;
+use crate::util_lib::membudget::{BudgetAccount, MemoryBudget, MARF_NODE_CACHE};
 
 use stacks_common::types::chainstate::BlockHeaderHash;
 use stacks_common::types::chainstate::BLOCK_HEADER_HASH_ENCODED_SIZE;
@@ -77,15 +79,43 @@ pub struct TrieCacheState<T: MarfTrieId> {
     node_cache: HashMap<TrieNodeAddr, TrieNodeType>,
     /// cached trie root hashes
     hash_cache: HashMap<TrieNodeAddr, TrieHash>,
+
+    /// share of the node's memory budget used by the node and hash caches
+    budget: BudgetAccount,
+}
+
+/// Estimated number of bytes a cached node takes up
+fn node_cache_entry_size(node: &TrieNodeType) -> u64 {
+    let boxed_size = match node {
+        TrieNodeType::Node48(_) | TrieNodeType::Node256(_) => {
+            node.ptrs().len() * mem::size_of::<TriePtr>()
+        }
+        _ => 0,
+    };
+    (mem::size_of::<TrieNodeAddr>()
+        + mem::size_of::<TrieNodeType>()
+        + node.path_bytes().len()
+        + boxed_size) as u64
+}
+
+/// Estimated number of bytes a cached node hash takes up
+fn hash_cache_entry_size() -> u64 {
+    (mem::size_of::<TrieNodeAddr>() + mem::size_of::<TrieHash>()) as u64
 }
 
 impl<T: MarfTrieId> TrieCacheState<T> {
     pub fn new() -> TrieCacheState<T> {
+        TrieCacheState::with_budget(MemoryBudget::register_global(MARF_NODE_CACHE))
+    }
+
+    /// Make cache state whose cached nodes and hashes are charged to the given budget account
+    pub fn with_budget(budget: BudgetAccount) -> TrieCacheState<T> {
         TrieCacheState {
             block_hash_cache: HashMap::new(),
             block_id_cache: HashMap::new(),
             node_cache: HashMap::new(),
             hash_cache: HashMap::new(),
+            budget,
         }
     }
 
@@ -133,14 +163,78 @@ impl<T: MarfTrieId> TrieCacheState<T> {
 
     /// Cache just a node
     pub fn store_node(&mut self, block_id: u32, trieptr: TriePtr, node: TrieNodeType) {
-        self.node_cache
-            .insert(TrieNodeAddr(block_id, trieptr), node);
+        self.budget.charge(node_cache_entry_size(&node));
+        if let Some(old_node) = self
+            .node_cache
+            .insert(TrieNodeAddr(block_id, trieptr), node)
+        {
+            self.budget.release(node_cache_entry_size(&old_node));
+        }
+        self.enforce_budget();
     }
 
     /// Cache just a node hash
     pub fn store_node_hash(&mut self, block_id: u32, trieptr: TriePtr, hash: TrieHash) {
-        self.hash_cache
-            .insert(TrieNodeAddr(block_id, trieptr), hash);
+        if self
+            .hash_cache
+            .insert(TrieNodeAddr(block_id, trieptr), hash)
+            .is_none()
+        {
+            self.budget.charge(hash_cache_entry_size());
+        }
+        self.enforce_budget();
+    }
+
+    /// If the node's memory budget is exhausted, evict nodes and hashes until this cache is down
+    /// to its share of the budget.  Nodes are evicted along with their hashes, in no particular
+    /// order.
+    fn enforce_budget(&mut self) {
+        let target = match self.budget.eviction_target() {
+            Some(target) => target,
+            None => {
+                return;
+            }
+        };
+
+        let mut usage = self.budget.usage();
+        let mut evicted = vec![];
+        for (addr, node) in self.node_cache.iter() {
+            if usage <= target {
+                break;
+            }
+            usage = usage.saturating_sub(node_cache_entry_size(node));
+            if self.hash_cache.contains_key(addr) {
+                usage = usage.saturating_sub(hash_cache_entry_size());
+            }
+            evicted.push(addr.clone());
+        }
+        for addr in evicted.iter() {
+            self.node_cache.remove(addr);
+            self.hash_cache.remove(addr);
+        }
+
+        // hashes whose nodes were not cached
+        if usage > target {
+            let evicted_hashes: Vec<_> = self
+                .hash_cache
+                .keys()
+                .filter(|addr| !self.node_cache.contains_key(addr))
+                .take(((usage - target) / hash_cache_entry_size() + 1) as usize)
+                .cloned()
+                .collect();
+            for addr in evicted_hashes.iter() {
+                self.hash_cache.remove(addr);
+            }
+            usage = usage.saturating_sub(evicted_hashes.len() as u64 * hash_cache_entry_size());
+        }
+
+        debug!(
+            "MARF node cache over memory budget: evicted {} entries, now at {} bytes (target {})",
+            evicted.len(),
+            usage,
+            target
+        );
+        self.budget.set_usage(usage);
     }
 
     /// Load up a block hash, given its ID
@@ -164,6 +258,27 @@ impl<T: MarfTrieId> TrieCacheState<T> {
     pub fn load_block_id(&self, block_hash: &T) -> Option<u32> {
         self.block_id_cache.get(block_hash).map(|id| *id)
     }
+
+    /// Drop everything cached for a block (i.e. because its trie was pruned)
+    pub fn forget_block(&mut self, block_id: u32) {
+        if let Some(block_hash) = self.block_hash_cache.remove(&block_id) {
+            self.block_id_cache.remove(&block_hash);
+        }
+
+        let mut released = 0;
+        self.node_cache.retain(|addr, node| {
+            if addr.0 == block_id {
+                released += node_cache_entry_size(node);
+                false
+            } else {
+                true
+            }
+        });
+        let num_hashes = self.hash_cache.len();
+        self.hash_cache.retain(|addr, _| addr.0 != block_id);
+        released += (num_hashes - self.hash_cache.len()) as u64 * hash_cache_entry_size();
+        self.budget.release(released);
+    }
 }
 
 /// Trie node cache strategies
@@ -174,6 +289,228 @@ pub enum TrieCache<T: MarfTrieId> {
     Everything(TrieCacheState<T>),
     /// Cache only TrieNode256's
     Node256(TrieCacheState<T>),
+    /// Cache the most recently-used nodes and hashes, up to a given number of bytes.  The inner
+    /// TrieCacheState only caches block hashes and IDs.
+    Lru(TrieCacheState<T>, TrieNodeLruCache),
+}
+
+/// Default size of an LRU node cache, if it's chosen via the `STACKS_MARF_CACHE_STRATEGY` environ
+/// or a MARF's `cache_strategy` option.
+pub const DEFAULT_LRU_CACHE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Node and hash cache statistics
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TrieCacheStats {
+    /// number of loads that found the requested data in the cache
+    pub hits: u64,
+    /// number of loads that did not find the requested data in the cache
+    pub misses: u64,
+    /// number of entries evicted to stay under the cache's size bound
+    pub evictions: u64,
+    /// number of entries currently cached
+    pub entries: u64,
+    /// estimated number of bytes currently cached
+    pub bytes: u64,
+    /// maximum number of bytes the cache may hold
+    pub capacity: u64,
+}
+
+/// A cached node and/or its hash, and when it was last used
+struct TrieNodeLruEntry {
+    node: Option<TrieNodeType>,
+    hash: Option<TrieHash>,
+    last_used: u64,
+    size: u64,
+}
+
+impl TrieNodeLruEntry {
+    /// Estimated number of bytes this entry takes up in RAM
+    fn estimate_size(&self) -> u64 {
+        let node_size = match self.node {
+            Some(ref node) => {
+                let boxed_size = match node {
+                    TrieNodeType::Node48(_) | TrieNodeType::Node256(_) => {
+                        node.ptrs().len() * mem::size_of::<TriePtr>()
+                    }
+                    _ => 0,
+                };
+                node.path_bytes().len() + boxed_size
+            }
+            None => 0,
+        };
+        (mem::size_of::<TrieNodeAddr>()
+            + mem::size_of::<TrieNodeLruEntry>()
+            + mem::size_of::<u64>()
+            + node_size) as u64
+    }
+}
+
+/// A size-bounded cache of trie nodes and hashes, which evicts the least-recently-used ones first.
+pub struct TrieNodeLruCache {
+    entries: HashMap<TrieNodeAddr, TrieNodeLruEntry>,
+    /// use counter => address of the entry last used at that count
+    recency: BTreeMap<u64, TrieNodeAddr>,
+    /// monotonically-increasing use counter
+    clock: u64,
+    stats: TrieCacheStats,
+}
+
+impl TrieNodeLruCache {
+    pub fn new(capacity: u64) -> TrieNodeLruCache {
+        TrieNodeLruCache {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            stats: TrieCacheStats {
+                capacity,
+                ..TrieCacheStats::default()
+            },
+        }
+    }
+
+    /// Get the cache statistics
+    pub fn stats(&self) -> TrieCacheStats {
+        self.stats
+    }
+
+    /// Mark an entry as the most-recently used one
+    fn touch(&mut self, addr: &TrieNodeAddr) {
+        if let Some(entry) = self.entries.get_mut(addr) {
+            self.recency.remove(&entry.last_used);
+            self.clock += 1;
+            entry.last_used = self.clock;
+            self.recency.insert(self.clock, addr.clone());
+        }
+    }
+
+    /// Look up an entry's node and/or hash, and record a hit or a miss.
+    /// `found` decides whether or not the entry has the requested data.
+    fn load<R, F>(&mut self, addr: TrieNodeAddr, found: F) -> Option<R>
+    where
+        F: FnOnce(&TrieNodeLruEntry) -> Option<R>,
+    {
+        match self.entries.get(&addr).and_then(found) {
+            Some(ret) => {
+                self.stats.hits += 1;
+                self.touch(&addr);
+                Some(ret)
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn load_node(&mut self, block_id: u32, trieptr: &TriePtr) -> Option<TrieNodeType> {
+        self.load(TrieNodeAddr(block_id, trieptr.clone()), |entry| {
+            entry.node.clone()
+        })
+    }
+
+    pub fn load_node_hash(&mut self, block_id: u32, trieptr: &TriePtr) -> Option<TrieHash> {
+        self.load(TrieNodeAddr(block_id, trieptr.clone()), |entry| {
+            entry.hash.clone()
+        })
+    }
+
+    /// Only return data if we have *both* the node and hash
+    pub fn load_node_and_hash(
+        &mut self,
+        block_id: u32,
+        trieptr: &TriePtr,
+    ) -> Option<(TrieNodeType, TrieHash)> {
+        self.load(TrieNodeAddr(block_id, trieptr.clone()), |entry| {
+            match (entry.node.as_ref(), entry.hash.as_ref()) {
+                (Some(node), Some(hash)) => Some((node.clone(), hash.clone())),
+                _ => None,
+            }
+        })
+    }
+
+    /// Add or update an entry, and evict least-recently-used entries until the cache is back
+    /// under its capacity.
+    fn store(
+        &mut self,
+        block_id: u32,
+        trieptr: TriePtr,
+        node: Option<TrieNodeType>,
+        hash: Option<TrieHash>,
+    ) {
+        let addr = TrieNodeAddr(block_id, trieptr);
+        let mut entry = self.entries.remove(&addr).unwrap_or(TrieNodeLruEntry {
+            node: None,
+            hash: None,
+            last_used: 0,
+            size: 0,
+        });
+        self.recency.remove(&entry.last_used);
+        self.stats.bytes = self.stats.bytes.saturating_sub(entry.size);
+
+        if node.is_some() {
+            entry.node = node;
+        }
+        if hash.is_some() {
+            entry.hash = hash;
+        }
+        entry.size = entry.estimate_size();
+        self.clock += 1;
+        entry.last_used = self.clock;
+
+        self.stats.bytes += entry.size;
+        self.recency.insert(self.clock, addr.clone());
+        self.entries.insert(addr, entry);
+
+        while self.stats.bytes > self.stats.capacity {
+            let lru_addr = match self.recency.iter().next() {
+                Some((_, addr)) => addr.clone(),
+                None => {
+                    break;
+                }
+            };
+            if let Some(evicted) = self.entries.remove(&lru_addr) {
+                self.recency.remove(&evicted.last_used);
+                self.stats.bytes = self.stats.bytes.saturating_sub(evicted.size);
+                self.stats.evictions += 1;
+            }
+        }
+        self.stats.entries = self.entries.len() as u64;
+    }
+
+    pub fn store_node_and_hash(
+        &mut self,
+        block_id: u32,
+        trieptr: TriePtr,
+        node: TrieNodeType,
+        hash: TrieHash,
+    ) {
+        self.store(block_id, trieptr, Some(node), Some(hash))
+    }
+
+    pub fn store_node(&mut self, block_id: u32, trieptr: TriePtr, node: TrieNodeType) {
+        self.store(block_id, trieptr, Some(node), None)
+    }
+
+    pub fn store_node_hash(&mut self, block_id: u32, trieptr: TriePtr, hash: TrieHash) {
+        self.store(block_id, trieptr, None, Some(hash))
+    }
+
+    /// Drop all of a block's nodes and hashes.  These are not counted as evictions.
+    pub fn forget_block(&mut self, block_id: u32) {
+        let forgotten: Vec<_> = self
+            .entries
+            .keys()
+            .filter(|addr| addr.0 == block_id)
+            .cloned()
+            .collect();
+        for addr in forgotten.iter() {
+            if let Some(entry) = self.entries.remove(addr) {
+                self.recency.remove(&entry.last_used);
+                self.stats.bytes = self.stats.bytes.saturating_sub(entry.size);
+            }
+        }
+        self.stats.entries = self.entries.len() as u64;
+    }
 }
 
 impl<T: MarfTrieId> TrieCache<T> {
@@ -187,14 +524,28 @@ impl<T: MarfTrieId> TrieCache<T> {
         }
     }
 
+    /// Make a new LRU cache strategy, which holds at most `capacity` bytes of nodes and hashes.
+    pub fn new_lru(capacity: u64) -> TrieCache<T> {
+        TrieCache::Lru(TrieCacheState::new(), TrieNodeLruCache::new(capacity))
+    }
+
+    /// Get the node and hash cache statistics.  Only the LRU strategy tracks them.
+    pub fn stats(&self) -> Option<TrieCacheStats> {
+        match self {
+            TrieCache::Lru(_, ref lru) => Some(lru.stats()),
+            _ => None,
+        }
+    }
+
     /// Make a new cache strategy.
-    /// `strategy` must be one of "noop", "everything", or "node256".
+    /// `strategy` must be one of "noop", "everything", "node256", or "lru".
     /// Any other option causes a runtime panic.
     pub fn new(strategy: &str) -> TrieCache<T> {
         match strategy {
             "noop" => TrieCache::Noop(TrieCacheState::new()),
             "everything" => TrieCache::Everything(TrieCacheState::new()),
             "node256" => TrieCache::Node256(TrieCacheState::new()),
+            "lru" => TrieCache::new_lru(DEFAULT_LRU_CACHE_BYTES),
             _ => {
                 error!(
                     "Unsupported trie node cache strategy '{}'; falling back to `Noop` strategy",
@@ -211,6 +562,7 @@ impl<T: MarfTrieId> TrieCache<T> {
             TrieCache::Noop(ref state) => state,
             TrieCache::Everything(ref state) => state,
             TrieCache::Node256(ref state) => state,
+            TrieCache::Lru(ref state, _) => state,
         }
     }
 
@@ -220,15 +572,16 @@ impl<T: MarfTrieId> TrieCache<T> {
             TrieCache::Noop(ref mut state) => state,
             TrieCache::Everything(ref mut state) => state,
             TrieCache::Node256(ref mut state) => state,
+            TrieCache::Lru(ref mut state, _) => state,
         }
     }
 
     /// Load a node from the cache, given its block ID and trie pointer within the block.
     pub fn load_node(&mut self, block_id: u32, trieptr: &TriePtr) -> Option<TrieNodeType> {
-        if let TrieCache::Noop(_) = self {
-            None
-        } else {
-            self.state_mut().load_node(block_id, trieptr)
+        match self {
+            TrieCache::Noop(_) => None,
+            TrieCache::Lru(_, ref mut lru) => lru.load_node(block_id, trieptr),
+            _ => self.state_mut().load_node(block_id, trieptr),
         }
     }
 
@@ -239,19 +592,19 @@ impl<T: MarfTrieId> TrieCache<T> {
         block_id: u32,
         trieptr: &TriePtr,
     ) -> Option<(TrieNodeType, TrieHash)> {
-        if let TrieCache::Noop(_) = self {
-            None
-        } else {
-            self.state_mut().load_node_and_hash(block_id, trieptr)
+        match self {
+            TrieCache::Noop(_) => None,
+            TrieCache::Lru(_, ref mut lru) => lru.load_node_and_hash(block_id, trieptr),
+            _ => self.state_mut().load_node_and_hash(block_id, trieptr),
         }
     }
 
     /// Load a node's hash, given its node's block ID and trie pointer within the block.
     pub fn load_node_hash(&mut self, block_id: u32, trieptr: &TriePtr) -> Option<TrieHash> {
-        if let TrieCache::Noop(_) = self {
-            None
-        } else {
-            self.state_mut().load_node_hash(block_id, trieptr)
+        match self {
+            TrieCache::Noop(_) => None,
+            TrieCache::Lru(_, ref mut lru) => lru.load_node_hash(block_id, trieptr),
+            _ => self.state_mut().load_node_hash(block_id, trieptr),
         }
     }
 
@@ -275,6 +628,9 @@ impl<T: MarfTrieId> TrieCache<T> {
                 }
                 _ => {}
             },
+            TrieCache::Lru(_, ref mut lru) => {
+                lru.store_node_and_hash(block_id, trieptr, node, hash);
+            }
         }
     }
 
@@ -290,6 +646,7 @@ impl<T: MarfTrieId> TrieCache<T> {
                 }
                 _ => {}
             },
+            TrieCache::Lru(_, ref mut lru) => lru.store_node(block_id, trieptr, node),
         }
     }
 
@@ -314,6 +671,9 @@ impl<T: MarfTrieId> TrieCache<T> {
                 }
                 _ => {}
             },
+            TrieCache::Lru(_, ref mut lru) => {
+                lru.store_node_hash(block_id, trieptr, hash);
+            }
         }
     }
 
@@ -336,6 +696,14 @@ impl<T: MarfTrieId> TrieCache<T> {
     pub fn load_block_id(&self, block_hash: &T) -> Option<u32> {
         self.state_ref().load_block_id(block_hash)
     }
+
+    /// Drop everything cached for a block, given its ID
+    pub fn forget_block(&mut self, block_id: u32) {
+        if let TrieCache::Lru(_, ref mut lru) = self {
+            lru.forget_block(block_id);
+        }
+        self.state_mut().forget_block(block_id);
+    }
 }
 
 #[cfg(test)]
@@ -724,6 +1092,81 @@ pub mod test {
         assert_eq!(root_hash, root_hash_batched);
     }
 
+    #[test]
+    fn test_marf_node_cache_lru() {
+        let test_data = make_test_insert_data(128, 128);
+        let root_hash = test_marf_with_cache(
+            "test_marf_node_cache_lru",
+            "noop",
+            TrieHashCalculationMode::Immediate,
+            &test_data,
+            None,
+        );
+        eprintln!("Final root hash is {}", root_hash);
+
+        let root_hash_lru = test_marf_with_cache(
+            "test_marf_node_cache_lru",
+            "lru",
+            TrieHashCalculationMode::Immediate,
+            &test_data,
+            None,
+        );
+        assert_eq!(root_hash, root_hash_lru);
+
+        let root_hash_lru = test_marf_with_cache(
+            "test_marf_node_cache_lru",
+            "lru",
+            TrieHashCalculationMode::Deferred,
+            &test_data,
+            Some(64),
+        );
+        assert_eq!(root_hash, root_hash_lru);
+    }
+
+    #[test]
+    fn test_lru_node_cache_eviction() {
+        let leaf = TrieNodeType::Leaf(TrieLeaf::new(&[0x11; 20], &vec![0x22; 40]));
+        let entry_size = TrieNodeLruEntry {
+            node: Some(leaf.clone()),
+            hash: Some(TrieHash([0u8; 32])),
+            last_used: 0,
+            size: 0,
+        }
+        .estimate_size();
+
+        let mut cache = TrieNodeLruCache::new(entry_size * 10);
+        for i in 0..10u32 {
+            let ptr = TriePtr::new(TrieNodeID::Leaf as u8, 0, i);
+            cache.store_node_and_hash(1, ptr, leaf.clone(), TrieHash([i as u8; 32]));
+        }
+        assert_eq!(cache.stats().entries, 10);
+        assert_eq!(cache.stats().evictions, 0);
+
+        // use the first entry, so the second one is now least-recently used
+        let ptr_0 = TriePtr::new(TrieNodeID::Leaf as u8, 0, 0);
+        assert_eq!(
+            cache.load_node_and_hash(1, &ptr_0),
+            Some((leaf.clone(), TrieHash([0u8; 32])))
+        );
+
+        let ptr_10 = TriePtr::new(TrieNodeID::Leaf as u8, 0, 10);
+        cache.store_node_and_hash(1, ptr_10.clone(), leaf.clone(), TrieHash([10u8; 32]));
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 10);
+        assert_eq!(stats.evictions, 1);
+        assert!(stats.bytes <= stats.capacity);
+
+        let ptr_1 = TriePtr::new(TrieNodeID::Leaf as u8, 0, 1);
+        assert_eq!(cache.load_node(1, &ptr_1), None);
+        assert_eq!(cache.load_node_hash(1, &ptr_0), Some(TrieHash([0u8; 32])));
+        assert_eq!(cache.load_node_hash(1, &ptr_10), Some(TrieHash([10u8; 32])));
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 3);
+        assert_eq!(stats.misses, 1);
+    }
+
     #[test]
     fn test_marf_node_cache_node256() {
         let test_data = make_test_insert_data(128, 128);
@@ -870,4 +1313,40 @@ pub mod test {
         );
         assert_eq!(root_hash, root_hash_batched);
     }
+
+    #[test]
+    fn test_marf_node_cache_memory_budget() {
+        use stacks_common::types::chainstate::StacksBlockId;
+        use std::sync::Arc;
+
+        let leaf = TrieNodeType::Leaf(TrieLeaf::new(&[0x11; 20], &vec![0x22; 40]));
+        let entry_size = node_cache_entry_size(&leaf) + hash_cache_entry_size();
+        let limit = entry_size * 10;
+
+        let budget = Arc::new(MemoryBudget::new(limit));
+        let mut state: TrieCacheState<StacksBlockId> =
+            TrieCacheState::with_budget(MemoryBudget::register(&budget, MARF_NODE_CACHE));
+
+        for i in 0..100u32 {
+            let ptr = TriePtr::new(TrieNodeID::Leaf as u8, 0, i);
+            state.store_node_and_hash(1, ptr.clone(), leaf.clone(), TrieHash([i as u8; 32]));
+
+            // never more than one entry over budget
+            assert!(budget.total_usage() <= limit + entry_size);
+
+            // the node we just stored may have been evicted, but if it's cached, its hash is too
+            if state.load_node(1, &ptr).is_some() {
+                assert_eq!(state.load_node_hash(1, &ptr), Some(TrieHash([i as u8; 32])));
+            }
+        }
+        assert!(state.node_cache.len() <= 11);
+        assert_eq!(
+            budget.usage_by_name().get(MARF_NODE_CACHE),
+            Some(&budget.total_usage())
+        );
+
+        // dropping the cache releases its share
+        drop(state);
+        assert_eq!(budget.total_usage(), 0);
+    }
 }
@@ -32,6 +32,15 @@ use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use std::{cmp, error};
 
+#[cfg(unix)]
+use nix::libc::c_void;
+#[cfg(unix)]
+use nix::sys::mman::{mmap, munmap, MapFlags, ProtFlags};
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+#[cfg(unix)]
+use std::ptr;
+
 use rusqlite::{
     types::{FromSql, ToSql},
     Connection, Error as SqliteError, ErrorCode as SqliteErrorCode, OpenFlags, OptionalExtension,
@@ -73,8 +82,25 @@ pub struct TrieFileDisk {
     fd: fs::File,
     path: String,
     trie_offsets: TrieIdOffsets,
+    /// Read-only map of the file, used to serve node hash reads.  Created on first use, and
+    /// re-created whenever a read falls past its end because tries were appended since.
+    #[cfg(unix)]
+    mmap: Option<TrieFileMmap>,
+}
+
+/// Read-only, shared memory map of a whole trie blob file.  Writes made through the file's fd
+/// are visible through the map, since both go through the page cache.  Trie blob files are only
+/// ever appended to or overwritten in place, never truncated, so the mapped range stays valid.
+#[cfg(unix)]
+struct TrieFileMmap {
+    addr: *mut c_void,
+    len: usize,
 }
 
+// The map is never written through, and is owned by exactly one TrieFileDisk.
+#[cfg(unix)]
+unsafe impl Send for TrieFileMmap {}
+
 /// Handle to a flat in-memory buffer containing Trie blobs (used for testing)
 pub struct TrieFileRAM {
     fd: Cursor<Vec<u8>>,
@@ -82,15 +108,36 @@ pub struct TrieFileRAM {
     trie_offsets: TrieIdOffsets,
 }
 
+/// Handle to a RocksDB database containing Trie blobs.  Each write is stored as its own value in
+/// the `trie_blobs` column family, keyed by the big-endian offset at which it starts, so a
+/// serialized trie can be loaded with a single point lookup instead of many small random reads
+/// into a flat file.  The most recently read value is cached, since reads of a trie's nodes are
+/// usually clustered.
+#[cfg(feature = "marf_rocksdb")]
+pub struct TrieFileRocksDb {
+    db: rocksdb::DB,
+    path: String,
+    readonly: bool,
+    pos: u64,
+    cached_blob: Option<(u64, Vec<u8>)>,
+    trie_offsets: TrieIdOffsets,
+}
+
+/// Name of the RocksDB column family that holds trie blobs
+#[cfg(feature = "marf_rocksdb")]
+pub const TRIE_BLOBS_COLUMN_FAMILY: &str = "trie_blobs";
+
 /// This is flat-file storage for a MARF's tries.  All tries are stored as contiguous byte arrays
 /// within a larger byte array.  The variants differ in how those bytes are backed.  The `RAM`
-/// variant stores data in RAM in a byte buffer, and the `Disk` variant stores data in a flat file
-/// on disk.  This structure is used to support external trie blobs, so that the tries don't need
+/// variant stores data in RAM in a byte buffer, the `Disk` variant stores data in a flat file
+/// on disk, and the `RocksDb` variant stores data in a RocksDB column family.  This structure is used to support external trie blobs, so that the tries don't need
 /// to be stored in sqlite blobs (which incurs a sqlite paging overhead).  This is useful for when
 /// the tries are too big to fit into a single page, such as the Stacks chainstate.
 pub enum TrieFile {
     RAM(TrieFileRAM),
     Disk(TrieFileDisk),
+    #[cfg(feature = "marf_rocksdb")]
+    RocksDb(TrieFileRocksDb),
 }
 
 impl TrieFile {
@@ -105,6 +152,8 @@ impl TrieFile {
             fd,
             path: path.to_string(),
             trie_offsets: TrieIdOffsets::new(),
+            #[cfg(unix)]
+            mmap: None,
         }))
     }
 
@@ -117,22 +166,56 @@ impl TrieFile {
         })
     }
 
+    /// Make a new RocksDB-backed TrieFile
+    #[cfg(feature = "marf_rocksdb")]
+    fn new_rocksdb(path: &str, readonly: bool) -> Result<TrieFile, Error> {
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(!readonly);
+        opts.create_missing_column_families(!readonly);
+
+        let db = if readonly {
+            rocksdb::DB::open_cf_for_read_only(&opts, path, [TRIE_BLOBS_COLUMN_FAMILY], false)
+        } else {
+            rocksdb::DB::open_cf(&opts, path, [TRIE_BLOBS_COLUMN_FAMILY])
+        }
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        Ok(TrieFile::RocksDb(TrieFileRocksDb {
+            db,
+            path: path.to_string(),
+            readonly,
+            pos: 0,
+            cached_blob: None,
+            trie_offsets: TrieIdOffsets::new(),
+        }))
+    }
+
+    /// Does the file or directory at `path` exist?
+    fn path_exists(path: &str) -> Result<bool, Error> {
+        match fs::metadata(path) {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                if e.kind() == io::ErrorKind::NotFound {
+                    Ok(false)
+                } else {
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+
     /// Does the TrieFile exist at the expected path?
     pub fn exists(path: &str) -> Result<bool, Error> {
         if path == ":memory:" {
             Ok(false)
         } else {
-            let blob_path = format!("{}.blobs", path);
-            match fs::metadata(&blob_path) {
-                Ok(_) => Ok(true),
-                Err(e) => {
-                    if e.kind() == io::ErrorKind::NotFound {
-                        Ok(false)
-                    } else {
-                        return Err(e.into());
-                    }
+            #[cfg(feature = "marf_rocksdb")]
+            {
+                if TrieFile::path_exists(&format!("{}.rocksdb", path))? {
+                    return Ok(true);
                 }
             }
+            TrieFile::path_exists(&format!("{}.blobs", path))
         }
     }
 
@@ -142,21 +225,53 @@ impl TrieFile {
         match self {
             TrieFile::RAM(_) => ":memory:".to_string(),
             TrieFile::Disk(ref disk) => disk.path.clone(),
+            #[cfg(feature = "marf_rocksdb")]
+            TrieFile::RocksDb(ref rocks) => rocks.path.clone(),
         }
     }
 
     /// Instantiate a TrieFile, given the associated DB path.
     /// If path is ':memory:', then it'll be an in-RAM TrieFile.
+    /// If a RocksDB blob store already exists at `$db_path.rocksdb`, then it'll be opened.
     /// Otherwise, it'll be stored as `$db_path.blobs`.
     pub fn from_db_path(path: &str, readonly: bool) -> Result<TrieFile, Error> {
         if path == ":memory:" {
             Ok(TrieFile::new_ram(readonly))
         } else {
+            #[cfg(feature = "marf_rocksdb")]
+            {
+                let rocksdb_path = format!("{}.rocksdb", path);
+                if TrieFile::path_exists(&rocksdb_path)? {
+                    return TrieFile::new_rocksdb(&rocksdb_path, readonly);
+                }
+            }
             let blob_path = format!("{}.blobs", path);
             TrieFile::new_disk(&blob_path, readonly)
         }
     }
 
+    /// Instantiate a RocksDB-backed TrieFile, given the associated DB path.
+    /// If path is ':memory:', then it'll be an in-RAM TrieFile.
+    /// If a flat file already exists at `$db_path.blobs` (and no RocksDB blob store does), then
+    /// the flat file will continue to be used, since its offsets are already recorded in the DB.
+    /// Otherwise, it'll be stored in a RocksDB database at `$db_path.rocksdb`.
+    #[cfg(feature = "marf_rocksdb")]
+    pub fn from_db_path_rocksdb(path: &str, readonly: bool) -> Result<TrieFile, Error> {
+        if path == ":memory:" {
+            return Ok(TrieFile::new_ram(readonly));
+        }
+        let rocksdb_path = format!("{}.rocksdb", path);
+        let blob_path = format!("{}.blobs", path);
+        if !TrieFile::path_exists(&rocksdb_path)? && TrieFile::path_exists(&blob_path)? {
+            warn!(
+                "Trie blobs for {} are already stored in {}; not using RocksDB",
+                path, &blob_path
+            );
+            return TrieFile::new_disk(&blob_path, readonly);
+        }
+        TrieFile::new_rocksdb(&rocksdb_path, readonly)
+    }
+
     /// Append a new trie blob to external storage, and add the offset and length to the trie DB.
     /// Return the trie ID
     pub fn store_trie_blob<T: MarfTrieId>(
@@ -364,6 +479,147 @@ impl NodeHashReader for TrieFileNodeHashReader<'_> {
     }
 }
 
+/// NodeHashReader for TrieFile that copies hashes out of a memory map of the trie blob file,
+/// instead of issuing a seek and a read for each one.  On platforms without mmap support, this
+/// behaves like TrieFileNodeHashReader.
+pub struct TrieFileMmapNodeHashReader<'a> {
+    db: &'a Connection,
+    file: &'a mut TrieFile,
+    block_id: u32,
+}
+
+impl<'a> TrieFileMmapNodeHashReader<'a> {
+    pub fn new(
+        db: &'a Connection,
+        file: &'a mut TrieFile,
+        block_id: u32,
+    ) -> TrieFileMmapNodeHashReader<'a> {
+        TrieFileMmapNodeHashReader { db, file, block_id }
+    }
+}
+
+impl NodeHashReader for TrieFileMmapNodeHashReader<'_> {
+    fn read_node_hash_bytes<W: Write>(&mut self, ptr: &TriePtr, w: &mut W) -> Result<(), Error> {
+        let trie_offset = self.file.get_trie_offset(self.db, self.block_id)?;
+        let hash_buff = self
+            .file
+            .read_mapped_hash_bytes(trie_offset + (ptr.ptr() as u64))?;
+        w.write_all(&hash_buff).map_err(|e| e.into())
+    }
+}
+
+#[cfg(unix)]
+impl TrieFileMmap {
+    /// Map all of `fd`.  Returns None if the file is empty, since there is nothing to map.
+    fn new(fd: &fs::File) -> Result<Option<TrieFileMmap>, Error> {
+        let len = usize::try_from(fd.metadata()?.len())
+            .map_err(|_| Error::CorruptionError("Trie blob file is too big to map".to_string()))?;
+        if len == 0 {
+            return Ok(None);
+        }
+        let addr = unsafe {
+            mmap(
+                ptr::null_mut(),
+                len,
+                ProtFlags::PROT_READ,
+                MapFlags::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            )
+        }
+        .map_err(|e| Error::IOError(io::Error::from_raw_os_error(e as i32)))?;
+        Ok(Some(TrieFileMmap { addr, len }))
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.addr as *const u8, self.len) }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for TrieFileMmap {
+    fn drop(&mut self) {
+        if let Err(e) = unsafe { munmap(self.addr, self.len) } {
+            error!("Failed to unmap trie blob file: {:?}", &e);
+        }
+    }
+}
+
+/// Copy the hash at `pos` out of `buf`
+fn copy_hash_bytes(buf: &[u8], pos: u64) -> Result<[u8; TRIEHASH_ENCODED_SIZE], Error> {
+    let bytes = usize::try_from(pos)
+        .ok()
+        .and_then(|start| buf.get(start..start.checked_add(TRIEHASH_ENCODED_SIZE)?))
+        .ok_or_else(|| {
+            Error::CorruptionError(format!("Failed to read hash in full from offset {}", pos))
+        })?;
+    let mut hash_buff = [0u8; TRIEHASH_ENCODED_SIZE];
+    hash_buff.copy_from_slice(bytes);
+    Ok(hash_buff)
+}
+
+impl TrieFile {
+    /// Read the hash stored at file offset `pos` without a seek or read syscall, by copying it out
+    /// of the RAM buffer or of a memory map of the file.  The file is only re-mapped if `pos` lies
+    /// past the end of the current map.
+    #[cfg(unix)]
+    fn read_mapped_hash_bytes(&mut self, pos: u64) -> Result<[u8; TRIEHASH_ENCODED_SIZE], Error> {
+        match self {
+            TrieFile::RAM(ref ram) => copy_hash_bytes(ram.fd.get_ref(), pos),
+            TrieFile::Disk(ref mut disk) => {
+                let end = pos.saturating_add(TRIEHASH_ENCODED_SIZE as u64);
+                let covered = disk
+                    .mmap
+                    .as_ref()
+                    .map(|mmap| end <= mmap.len as u64)
+                    .unwrap_or(false);
+                if !covered {
+                    // tries were appended since the file was last mapped
+                    disk.mmap = TrieFileMmap::new(&disk.fd)?;
+                }
+                match disk.mmap {
+                    Some(ref mmap) => copy_hash_bytes(mmap.as_slice(), pos),
+                    None => copy_hash_bytes(&[], pos),
+                }
+            }
+            #[cfg(feature = "marf_rocksdb")]
+            TrieFile::RocksDb(ref mut rocks) => {
+                rocks.seek(SeekFrom::Start(pos))?;
+                read_hash_bytes(rocks)
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn read_mapped_hash_bytes(&mut self, pos: u64) -> Result<[u8; TRIEHASH_ENCODED_SIZE], Error> {
+        match self {
+            TrieFile::RAM(ref ram) => copy_hash_bytes(ram.fd.get_ref(), pos),
+            TrieFile::Disk(ref mut disk) => {
+                disk.seek(SeekFrom::Start(pos))?;
+                read_hash_bytes(disk)
+            }
+            #[cfg(feature = "marf_rocksdb")]
+            TrieFile::RocksDb(ref mut rocks) => {
+                rocks.seek(SeekFrom::Start(pos))?;
+                read_hash_bytes(rocks)
+            }
+        }
+    }
+
+    /// Obtain a TrieHash for a node, given its block ID and pointer, by way of
+    /// `read_mapped_hash_bytes()`
+    pub fn get_node_hash_bytes_mapped(
+        &mut self,
+        db: &Connection,
+        block_id: u32,
+        ptr: &TriePtr,
+    ) -> Result<TrieHash, Error> {
+        let offset = self.get_trie_offset(db, block_id)?;
+        let hash_buff = self.read_mapped_hash_bytes(offset + (ptr.ptr() as u64))?;
+        Ok(TrieHash(hash_buff))
+    }
+}
+
 impl TrieFile {
     /// Determine the file offset in the TrieFile where a serialized trie starts.
     /// The offsets are stored in the given DB, and are cached indefinitely once loaded.
@@ -371,20 +627,40 @@ impl TrieFile {
         let offset_opt = match self {
             TrieFile::RAM(ref ram) => ram.trie_offsets.get(&block_id),
             TrieFile::Disk(ref disk) => disk.trie_offsets.get(&block_id),
+            #[cfg(feature = "marf_rocksdb")]
+            TrieFile::RocksDb(ref rocks) => rocks.trie_offsets.get(&block_id),
         };
         match offset_opt {
             Some(offset) => Ok(*offset),
             None => {
                 let (offset, _length) = trie_sql::get_external_trie_offset_length(db, block_id)?;
-                match self {
-                    TrieFile::RAM(ref mut ram) => ram.trie_offsets.insert(block_id, offset),
-                    TrieFile::Disk(ref mut disk) => disk.trie_offsets.insert(block_id, offset),
-                };
+                self.set_trie_offset(block_id, offset);
                 Ok(offset)
             }
         }
     }
 
+    /// Is the offset of the given trie cached?
+    pub fn has_trie_offset(&self, block_id: u32) -> bool {
+        match self {
+            TrieFile::RAM(ref ram) => ram.trie_offsets.contains_key(&block_id),
+            TrieFile::Disk(ref disk) => disk.trie_offsets.contains_key(&block_id),
+            #[cfg(feature = "marf_rocksdb")]
+            TrieFile::RocksDb(ref rocks) => rocks.trie_offsets.contains_key(&block_id),
+        }
+    }
+
+    /// Cache the offset of the given trie, e.g. one loaded from an external trie block index
+    /// instead of from the DB.
+    pub fn set_trie_offset(&mut self, block_id: u32, offset: u64) {
+        match self {
+            TrieFile::RAM(ref mut ram) => ram.trie_offsets.insert(block_id, offset),
+            TrieFile::Disk(ref mut disk) => disk.trie_offsets.insert(block_id, offset),
+            #[cfg(feature = "marf_rocksdb")]
+            TrieFile::RocksDb(ref mut rocks) => rocks.trie_offsets.insert(block_id, offset),
+        };
+    }
+
     /// Obtain a TrieHash for a node, given its block ID and pointer
     pub fn get_node_hash_bytes(
         &mut self,
@@ -398,6 +674,21 @@ impl TrieFile {
         Ok(TrieHash(hash_buff))
     }
 
+    /// Overwrite the stored TrieHash of a node, given its block ID and pointer
+    pub fn write_node_hash_bytes(
+        &mut self,
+        db: &Connection,
+        block_id: u32,
+        ptr: &TriePtr,
+        hash: &TrieHash,
+    ) -> Result<(), Error> {
+        let offset = self.get_trie_offset(db, block_id)?;
+        self.seek(SeekFrom::Start(offset + (ptr.ptr() as u64)))?;
+        self.write_all(hash.as_bytes())?;
+        self.flush()?;
+        Ok(())
+    }
+
     /// Obtain a TrieNodeType and its associated TrieHash for a node, given its block ID and
     /// pointer
     pub fn read_node_type(
@@ -483,6 +774,73 @@ impl TrieFile {
         }
         Ok(offset)
     }
+
+    /// Release the space taken by a pruned trie's bytes, which start at `offset` and are `length`
+    /// bytes long.  The offsets of all other tries are unchanged.  On-disk trie files only
+    /// release the space on Linux, where a hole can be punched into the file.
+    pub fn discard_trie_blob(
+        &mut self,
+        block_id: u32,
+        offset: u64,
+        length: u64,
+    ) -> Result<(), Error> {
+        test_debug!("Discard trie of {} bytes at {}", length, offset);
+        match self {
+            TrieFile::RAM(ref mut ram) => {
+                if ram.readonly {
+                    return Err(Error::ReadOnlyError);
+                }
+                ram.trie_offsets.remove(&block_id);
+                let buf = ram.fd.get_mut();
+                let start = cmp::min(offset, buf.len() as u64) as usize;
+                let end = cmp::min(offset.saturating_add(length), buf.len() as u64) as usize;
+                for byte in buf[start..end].iter_mut() {
+                    *byte = 0;
+                }
+            }
+            TrieFile::Disk(ref mut disk) => {
+                disk.trie_offsets.remove(&block_id);
+                disk.punch_hole(offset, length)?;
+            }
+            #[cfg(feature = "marf_rocksdb")]
+            TrieFile::RocksDb(ref mut rocks) => {
+                rocks.trie_offsets.remove(&block_id);
+                rocks.delete_range(offset, offset.saturating_add(length))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TrieFileDisk {
+    /// Deallocate a range of the file, so it reads back as zeros but takes up no space
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn punch_hole(&self, offset: u64, length: u64) -> Result<(), Error> {
+        use nix::fcntl::{fallocate, FallocateFlags};
+        use nix::libc::off_t;
+        use std::os::unix::io::AsRawFd;
+
+        let offset = off_t::try_from(offset)
+            .map_err(|_| Error::CorruptionError(format!("Trie offset too big: {}", offset)))?;
+        let length = off_t::try_from(length)
+            .map_err(|_| Error::CorruptionError(format!("Trie length too big: {}", length)))?;
+        fallocate(
+            self.fd.as_raw_fd(),
+            FallocateFlags::FALLOC_FL_PUNCH_HOLE | FallocateFlags::FALLOC_FL_KEEP_SIZE,
+            offset,
+            length,
+        )
+        .map_err(|e| Error::IOError(io::Error::from(e)))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn punch_hole(&self, offset: u64, length: u64) -> Result<(), Error> {
+        debug!(
+            "Cannot release {} bytes at {} in {} on this platform",
+            length, offset, &self.path
+        );
+        Ok(())
+    }
 }
 
 /// Boilerplate Write implementation for TrieFileDisk.  Plumbs through to the inner fd.
@@ -513,6 +871,8 @@ impl Write for TrieFile {
         match self {
             TrieFile::RAM(ref mut ram) => ram.write(buf),
             TrieFile::Disk(ref mut disk) => disk.write(buf),
+            #[cfg(feature = "marf_rocksdb")]
+            TrieFile::RocksDb(ref mut rocks) => rocks.write(buf),
         }
     }
 
@@ -520,6 +880,8 @@ impl Write for TrieFile {
         match self {
             TrieFile::RAM(ref mut ram) => ram.flush(),
             TrieFile::Disk(ref mut disk) => disk.flush(),
+            #[cfg(feature = "marf_rocksdb")]
+            TrieFile::RocksDb(ref mut rocks) => rocks.flush(),
         }
     }
 }
@@ -544,6 +906,8 @@ impl Read for TrieFile {
         match self {
             TrieFile::RAM(ref mut ram) => ram.read(buf),
             TrieFile::Disk(ref mut disk) => disk.read(buf),
+            #[cfg(feature = "marf_rocksdb")]
+            TrieFile::RocksDb(ref mut rocks) => rocks.read(buf),
         }
     }
 }
@@ -562,11 +926,197 @@ impl Seek for TrieFileRAM {
     }
 }
 
+#[cfg(feature = "marf_rocksdb")]
+impl TrieFileRocksDb {
+    fn cf(&self) -> io::Result<&rocksdb::ColumnFamily> {
+        self.db.cf_handle(TRIE_BLOBS_COLUMN_FAMILY).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No column family {}", TRIE_BLOBS_COLUMN_FAMILY),
+            )
+        })
+    }
+
+    /// Find the stored value that contains `offset`, and return its starting offset and bytes.
+    /// Returns None if no stored value contains `offset` (i.e. it is past the end).
+    fn get_blob_at(&self, offset: u64) -> io::Result<Option<(u64, Vec<u8>)>> {
+        let cf = self.cf()?;
+        let start_key = offset.to_be_bytes();
+        let mut iter = self.db.iterator_cf(
+            cf,
+            rocksdb::IteratorMode::From(&start_key, rocksdb::Direction::Reverse),
+        );
+        match iter.next() {
+            Some(Ok((key, value))) => {
+                let start = TrieFileRocksDb::decode_offset(&key)?;
+                if offset < start + (value.len() as u64) {
+                    Ok(Some((start, value.into_vec())))
+                } else {
+                    Ok(None)
+                }
+            }
+            Some(Err(e)) => Err(io::Error::new(io::ErrorKind::Other, e)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the offset one past the last stored byte
+    fn get_end_offset(&self) -> io::Result<u64> {
+        let cf = self.cf()?;
+        let mut iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::End);
+        match iter.next() {
+            Some(Ok((key, value))) => {
+                Ok(TrieFileRocksDb::decode_offset(&key)? + (value.len() as u64))
+            }
+            Some(Err(e)) => Err(io::Error::new(io::ErrorKind::Other, e)),
+            None => Ok(0),
+        }
+    }
+
+    /// Delete every stored value that starts in the range [start, end)
+    fn delete_range(&mut self, start: u64, end: u64) -> io::Result<()> {
+        if self.readonly {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Trie blob store is read-only",
+            ));
+        }
+        let cf = self.cf()?;
+        let mut batch = rocksdb::WriteBatch::default();
+        batch.delete_range_cf(cf, start.to_be_bytes(), end.to_be_bytes());
+
+        let mut write_opts = rocksdb::WriteOptions::default();
+        write_opts.set_sync(true);
+        self.db
+            .write_opt(batch, &write_opts)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        self.cached_blob = None;
+        Ok(())
+    }
+
+    fn decode_offset(key: &[u8]) -> io::Result<u64> {
+        let bytes: [u8; 8] = key
+            .try_into()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Malformed trie blob key"))?;
+        Ok(u64::from_be_bytes(bytes))
+    }
+}
+
+/// Write implementation for TrieFileRocksDb.  Each write is stored as a single value.  Writes may
+/// only append, or replace everything from the start of a previously-written value onwards (which
+/// happens if a trie was appended but never recorded in the DB).
+#[cfg(feature = "marf_rocksdb")]
+impl Write for TrieFileRocksDb {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.readonly {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Trie blob store is read-only",
+            ));
+        }
+        if buf.len() == 0 {
+            return Ok(0);
+        }
+
+        let end = self.get_end_offset()?;
+        if self.pos > end {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cannot write past the end of the trie blob store",
+            ));
+        }
+
+        let cf = self.cf()?;
+        let mut batch = rocksdb::WriteBatch::default();
+        if self.pos < end {
+            match self.get_blob_at(self.pos)? {
+                Some((start, _)) if start == self.pos => {}
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Cannot overwrite part of a stored trie blob",
+                    ));
+                }
+            }
+            batch.delete_range_cf(cf, self.pos.to_be_bytes(), end.to_be_bytes());
+        }
+        batch.put_cf(cf, self.pos.to_be_bytes(), buf);
+
+        let mut write_opts = rocksdb::WriteOptions::default();
+        write_opts.set_sync(true);
+        self.db
+            .write_opt(batch, &write_opts)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        self.cached_blob = None;
+        self.pos += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    /// Writes are synced as they happen, so this is a no-op.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Read implementation for TrieFileRocksDb.  Reads from at most one stored value at a time.
+#[cfg(feature = "marf_rocksdb")]
+impl Read for TrieFileRocksDb {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let cached = match self.cached_blob {
+            Some((start, ref blob)) => self.pos >= start && self.pos < start + (blob.len() as u64),
+            None => false,
+        };
+        if !cached {
+            self.cached_blob = self.get_blob_at(self.pos)?;
+        }
+        let (start, blob) = match self.cached_blob {
+            Some((start, ref blob)) => (start, blob),
+            None => {
+                return Ok(0);
+            }
+        };
+
+        let blob_pos = (self.pos - start) as usize;
+        let nr = cmp::min(buf.len(), blob.len() - blob_pos);
+        buf[0..nr].copy_from_slice(&blob[blob_pos..(blob_pos + nr)]);
+        self.pos += nr as u64;
+        Ok(nr)
+    }
+}
+
+/// Seek implementation for TrieFileRocksDb.  Seeking from the end requires a lookup of the last
+/// stored value.
+#[cfg(feature = "marf_rocksdb")]
+impl Seek for TrieFileRocksDb {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, delta) = match pos {
+            SeekFrom::Start(offset) => {
+                self.pos = offset;
+                return Ok(self.pos);
+            }
+            SeekFrom::Current(delta) => (self.pos, delta),
+            SeekFrom::End(delta) => (self.get_end_offset()?, delta),
+        };
+        let new_pos = if delta >= 0 {
+            base.checked_add(delta as u64)
+        } else {
+            base.checked_sub(delta.unsigned_abs())
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid seek"))?;
+        self.pos = new_pos;
+        Ok(self.pos)
+    }
+}
+
 impl Seek for TrieFile {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         match self {
             TrieFile::RAM(ref mut ram) => ram.seek(pos),
             TrieFile::Disk(ref mut disk) => disk.seek(pos),
+            #[cfg(feature = "marf_rocksdb")]
+            TrieFile::RocksDb(ref mut rocks) => rocks.seek(pos),
         }
     }
 }
@@ -0,0 +1,335 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Write-ahead journal for the uncommitted trie.
+//!
+//! When enabled, every node written to the in-RAM trie that a `TrieFileStorage` is building is
+//! also appended to a journal file next to the MARF's database.  If the process dies before the
+//! trie is flushed, the journal holds everything needed to reinstate the trie exactly as it was
+//! at the last write, so the block that was being processed does not need to be replayed from
+//! its first transaction.
+//!
+//! Journal format:
+//!
+//! ```text
+//! 0       8                   40                  72
+//! |-------|-------------------|-------------------|---------|---------|---
+//!   magic   block hash          parent block hash   record    record    ...
+//! ```
+//!
+//! where each record is
+//!
+//! ```text
+//! 0           4           8                   40  41
+//! |-----------|-----------|-------------------|---|-------------------|
+//!   length      slot        node hash           id  ptrs & ptr data, path
+//! ```
+//!
+//! `length` counts the bytes after itself, and `slot` is the node's index in the `TrieRAM`.  A
+//! later record for a slot supersedes an earlier one.  A record that was only partially written
+//! when the process died is discarded when the journal is read.
+
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::chainstate::stacks::index::bits::{read_nodetype_at_head, write_nodetype_bytes};
+use crate::chainstate::stacks::index::node::TrieNodeType;
+use crate::chainstate::stacks::index::Error;
+use crate::chainstate::stacks::index::MarfTrieId;
+use stacks_common::types::chainstate::{TrieHash, TRIEHASH_ENCODED_SIZE};
+
+const JOURNAL_MAGIC: &[u8; 8] = b"MARFJNL1";
+const JOURNAL_HEADER_LEN: u64 = 8 + 32 + 32;
+
+/// A journal is compacted once it is this many times larger than the trie it describes
+const JOURNAL_COMPACTION_FACTOR: u64 = 4;
+/// Journals smaller than this are never compacted
+const JOURNAL_MIN_COMPACTION_BYTES: u64 = 1024 * 1024;
+
+/// Uncommitted trie recovered from a journal
+pub struct JournaledTrie<T: MarfTrieId> {
+    /// block hash the trie was being built for
+    pub block_hash: T,
+    /// block hash of the trie's parent
+    pub parent: T,
+    /// the trie's nodes and hashes, indexed by `TrieRAM` slot
+    pub nodes: Vec<(TrieNodeType, TrieHash)>,
+}
+
+/// Write-ahead journal of the nodes written to an uncommitted trie
+pub struct TrieJournal {
+    path: PathBuf,
+    /// open journal file, if a trie is being journaled
+    file: Option<fs::File>,
+    /// number of bytes in the journal file
+    len: u64,
+}
+
+impl TrieJournal {
+    /// Make a journal handle for the MARF stored at `db_path`.  Nothing is written until a trie is
+    /// started with `begin()`.
+    pub fn new(db_path: &str) -> TrieJournal {
+        TrieJournal {
+            path: TrieJournal::path_for(db_path),
+            file: None,
+            len: 0,
+        }
+    }
+
+    /// Path of the journal for the MARF stored at `db_path`
+    pub fn path_for(db_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.journal", db_path))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Is a trie being journaled?
+    pub fn is_open(&self) -> bool {
+        self.file.is_some()
+    }
+
+    /// Number of bytes in the journal
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Start journaling a new uncommitted trie for `block_hash`, discarding any prior journal.
+    pub fn begin<T: MarfTrieId>(&mut self, block_hash: &T, parent: &T) -> Result<(), Error> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&self.path)?;
+
+        let mut header = Vec::with_capacity(JOURNAL_HEADER_LEN as usize);
+        header.extend_from_slice(JOURNAL_MAGIC);
+        header.extend_from_slice(block_hash.as_bytes());
+        header.extend_from_slice(parent.as_bytes());
+        file.write_all(&header)?;
+
+        self.file = Some(file);
+        self.len = JOURNAL_HEADER_LEN;
+        Ok(())
+    }
+
+    /// Resume journaling the trie already recorded in the journal file, i.e. after it was read
+    /// back with `read()`.
+    pub fn resume(&mut self, valid_len: u64) -> Result<(), Error> {
+        let mut file = OpenOptions::new().read(true).write(true).open(&self.path)?;
+
+        // drop any torn record at the end
+        file.set_len(valid_len)?;
+        file.seek(SeekFrom::End(0))?;
+
+        self.file = Some(file);
+        self.len = valid_len;
+        Ok(())
+    }
+
+    /// Append a node write to the journal.  No-op if no trie is being journaled.
+    pub fn append(&mut self, slot: u32, node: &TrieNodeType, hash: &TrieHash) -> Result<(), Error> {
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => {
+                return Ok(());
+            }
+        };
+
+        let record = TrieJournal::encode_record(slot, node, hash)?;
+        file.write_all(&record)?;
+        self.len += record.len() as u64;
+        Ok(())
+    }
+
+    /// Should the journal be rewritten from the trie's current nodes?  `trie_bytes` is the size
+    /// of the trie being journaled.
+    pub fn needs_compaction(&self, trie_bytes: u64) -> bool {
+        self.is_open()
+            && self.len > JOURNAL_MIN_COMPACTION_BYTES
+            && self.len > JOURNAL_COMPACTION_FACTOR.saturating_mul(trie_bytes)
+    }
+
+    /// Rewrite the journal so it holds exactly one record per node of the trie.  Each node is only
+    /// recorded once no matter how many times it was rewritten, so this bounds the journal's size
+    /// to a small multiple of the trie's size.  The new journal is written next to the old one
+    /// and renamed over it, so a crash mid-compaction leaves the old journal intact.
+    pub fn compact<T: MarfTrieId>(
+        &mut self,
+        block_hash: &T,
+        parent: &T,
+        nodes: &[(TrieNodeType, TrieHash)],
+    ) -> Result<(), Error> {
+        let tmp_path = PathBuf::from(format!("{}.tmp", self.path.display()));
+        let old_len = self.len;
+        {
+            let mut tmp = TrieJournal {
+                path: tmp_path.clone(),
+                file: None,
+                len: 0,
+            };
+            tmp.begin(block_hash, parent)?;
+            for (slot, (node, hash)) in nodes.iter().enumerate() {
+                tmp.append(slot as u32, node, hash)?;
+            }
+            if let Some(file) = tmp.file.as_ref() {
+                file.sync_data()?;
+            }
+            self.len = tmp.len;
+        }
+        fs::rename(&tmp_path, &self.path)?;
+
+        let mut file = OpenOptions::new().read(true).write(true).open(&self.path)?;
+        file.seek(SeekFrom::End(0))?;
+        self.file = Some(file);
+
+        debug!(
+            "Compacted uncommitted trie journal {}: {} -> {} bytes",
+            self.path.display(),
+            old_len,
+            self.len
+        );
+        Ok(())
+    }
+
+    /// Stop journaling and remove the journal file.
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.file = None;
+        self.len = 0;
+        match fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(Error::IOError(e)),
+        }
+    }
+
+    /// Read back the trie recorded in the journal, if there is one.  Returns the trie and the
+    /// length of the journal's valid prefix (i.e. excluding a torn final record).
+    pub fn read<T: MarfTrieId>(&self) -> Result<Option<(JournaledTrie<T>, u64)>, Error> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(None);
+            }
+            Err(e) => {
+                return Err(Error::IOError(e));
+            }
+        };
+
+        if (bytes.len() as u64) < JOURNAL_HEADER_LEN || &bytes[0..8] != JOURNAL_MAGIC {
+            warn!(
+                "Ignoring uncommitted trie journal {}: no valid header",
+                self.path.display()
+            );
+            return Ok(None);
+        }
+
+        let mut block_hash_bytes = [0u8; 32];
+        block_hash_bytes.copy_from_slice(&bytes[8..40]);
+        let mut parent_bytes = [0u8; 32];
+        parent_bytes.copy_from_slice(&bytes[40..72]);
+
+        let mut nodes: Vec<(TrieNodeType, TrieHash)> = vec![];
+        let mut offset = JOURNAL_HEADER_LEN as usize;
+        while let Some((slot, node, hash, record_len)) =
+            TrieJournal::decode_record(&bytes[offset..])?
+        {
+            if (slot as usize) < nodes.len() {
+                nodes[slot as usize] = (node, hash);
+            } else if (slot as usize) == nodes.len() {
+                nodes.push((node, hash));
+            } else {
+                return Err(Error::CorruptionError(format!(
+                    "Uncommitted trie journal {} writes slot {} past the end of the trie ({} nodes)",
+                    self.path.display(),
+                    slot,
+                    nodes.len()
+                )));
+            }
+            offset += record_len;
+        }
+
+        if offset < bytes.len() {
+            warn!(
+                "Discarding {} bytes of torn records from uncommitted trie journal {}",
+                bytes.len() - offset,
+                self.path.display()
+            );
+        }
+
+        Ok(Some((
+            JournaledTrie {
+                block_hash: T::from_bytes(block_hash_bytes),
+                parent: T::from_bytes(parent_bytes),
+                nodes,
+            },
+            offset as u64,
+        )))
+    }
+
+    fn encode_record(slot: u32, node: &TrieNodeType, hash: &TrieHash) -> Result<Vec<u8>, Error> {
+        let mut node_bytes = Cursor::new(Vec::new());
+        write_nodetype_bytes(&mut node_bytes, node, hash.clone())?;
+        let node_bytes = node_bytes.into_inner();
+
+        let mut record = Vec::with_capacity(8 + node_bytes.len());
+        record.extend_from_slice(&((4 + node_bytes.len()) as u32).to_be_bytes());
+        record.extend_from_slice(&slot.to_be_bytes());
+        record.extend_from_slice(&node_bytes);
+        Ok(record)
+    }
+
+    /// Decode the record at the head of `bytes`.  Returns None if there are no more complete
+    /// records.  Returns the slot, node, hash, and number of bytes consumed.
+    fn decode_record(bytes: &[u8]) -> Result<Option<(u32, TrieNodeType, TrieHash, usize)>, Error> {
+        if bytes.len() < 8 {
+            return Ok(None);
+        }
+        let mut len_bytes = [0u8; 4];
+        len_bytes.copy_from_slice(&bytes[0..4]);
+        let len = u32::from_be_bytes(len_bytes) as usize;
+        if len < 4 + TRIEHASH_ENCODED_SIZE + 1 || bytes.len() < 4 + len {
+            return Ok(None);
+        }
+
+        let mut slot_bytes = [0u8; 4];
+        slot_bytes.copy_from_slice(&bytes[4..8]);
+        let slot = u32::from_be_bytes(slot_bytes);
+
+        let node_bytes = &bytes[8..4 + len];
+        let node_id = node_bytes[TRIEHASH_ENCODED_SIZE];
+        let mut cursor = Cursor::new(node_bytes);
+        let (node, hash) = match read_nodetype_at_head(&mut cursor, node_id) {
+            Ok(x) => x,
+            Err(e) => {
+                // a torn write can leave a plausible length in front of garbage
+                debug!("Failed to decode journaled node in slot {}: {:?}", slot, &e);
+                return Ok(None);
+            }
+        };
+        let mut rest = vec![];
+        cursor.read_to_end(&mut rest)?;
+        if rest.len() > 0 {
+            return Ok(None);
+        }
+        Ok(Some((slot, node, hash, 4 + len)))
+    }
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use std::error;
 use std::fmt;
 use std::fs;
@@ -34,6 +35,7 @@ use crate::chainstate::stacks::index::storage::{
     TrieFileStorage, TrieHashCalculationMode, TrieStorageConnection, TrieStorageTransaction,
 };
 use crate::chainstate::stacks::index::trie::Trie;
+use crate::chainstate::stacks::index::trie_index::SharedTrieIndex;
 use crate::chainstate::stacks::index::Error;
 use crate::chainstate::stacks::index::MARFValue;
 use crate::chainstate::stacks::index::MarfTrieId;
@@ -79,6 +81,12 @@ pub struct MARFOpenOpts {
     pub external_blobs: bool,
     /// unconditionally do a DB migration (used for testing)
     pub force_db_migrate: bool,
+    /// journal the trie being built to a file next to the DB, so it can be recovered with
+    /// `MarfTransaction::begin_recovered()` if the process stops before it is committed
+    pub uncommitted_journal: bool,
+    /// if storing trie blobs externally, store them in a RocksDB database instead of a flat file
+    #[cfg(feature = "marf_rocksdb")]
+    pub rocksdb_blobs: bool,
 }
 
 impl MARFOpenOpts {
@@ -88,6 +96,9 @@ impl MARFOpenOpts {
             cache_strategy: "noop".to_string(),
             external_blobs: false,
             force_db_migrate: false,
+            uncommitted_journal: false,
+            #[cfg(feature = "marf_rocksdb")]
+            rocksdb_blobs: false,
         }
     }
 
@@ -101,6 +112,9 @@ impl MARFOpenOpts {
             cache_strategy: cache_strategy.to_string(),
             external_blobs,
             force_db_migrate: false,
+            uncommitted_journal: false,
+            #[cfg(feature = "marf_rocksdb")]
+            rocksdb_blobs: false,
         }
     }
 
@@ -301,7 +315,6 @@ impl<'a, T: MarfTrieId> MarfTransaction<'a, T> {
         }
     }
 
-    #[cfg(test)]
     fn commit_tx(self) {
         self.storage.commit_tx()
     }
@@ -354,6 +367,36 @@ impl<'a, T: MarfTrieId> MarfTransaction<'a, T> {
         self.inner_setup_extension(chain_tip, next_chain_tip, block_height, true)
     }
 
+    /// Like `begin()`, but if the MARF was opened with `uncommitted_journal` and the process
+    /// stopped while it was building the trie for `next_chain_tip` off of `chain_tip`, then that
+    /// trie is reinstated from the journal instead of being started over.  The reinstated trie
+    /// holds every key that was inserted into it before the process stopped, so the caller only
+    /// needs to insert what came after.
+    /// Returns true if the trie was recovered, and false if there was nothing to recover and an
+    /// empty extension was begun instead.
+    pub fn begin_recovered(&mut self, chain_tip: &T, next_chain_tip: &T) -> Result<bool, Error> {
+        if self.storage.readonly() {
+            return Err(Error::ReadOnlyError);
+        }
+        if self.open_chain_tip.is_some() {
+            return Err(Error::InProgressError);
+        }
+        if self.storage.has_block(next_chain_tip)? {
+            error!("Block data already exists: {}", next_chain_tip);
+            return Err(Error::ExistsError);
+        }
+
+        let block_height = self.inner_get_extension_height(chain_tip, next_chain_tip)?;
+        let recovered = self.storage.recover_uncommitted_trie(next_chain_tip)?;
+        if !recovered {
+            MARF::extend_trie(&mut self.storage, next_chain_tip)?;
+        }
+
+        // re-inserting the block height keys is harmless if they were recovered
+        self.inner_setup_extension(chain_tip, next_chain_tip, block_height, true)?;
+        Ok(recovered)
+    }
+
     /// Set up the trie extension we're making.
     /// Sets storage pointer to chain_tip.
     /// Returns the height next_chain_tip would be at.
@@ -1243,6 +1286,20 @@ impl<T: MarfTrieId> MARF<T> {
         T::from_bytes(res_bytes)
     }
 
+    /// Make the chain tip of an ephemeral fork from the block it forks, so that it can't be
+    /// mistaken for a real block or for unconfirmed state built on the same block.
+    pub fn make_ephemeral_chain_tip(chain_tip: &T) -> T {
+        let mut bytes = [0u8; 41];
+        bytes[0..9].copy_from_slice(b"ephemeral");
+        bytes[9..41].copy_from_slice(chain_tip.as_bytes());
+
+        let h = Sha512Trunc256Sum::from_data(&bytes);
+        let mut res_bytes = [0u8; 32];
+        res_bytes[0..32].copy_from_slice(h.as_bytes());
+
+        T::from_bytes(res_bytes)
+    }
+
     /// Insert a batch of key/value pairs.  More efficient than inserting them individually, since
     /// the trie root hash will only be calculated once (which is an O(log B) operation).
     fn inner_insert_batch(
@@ -1252,48 +1309,100 @@ impl<T: MarfTrieId> MARF<T> {
         values: Vec<MARFValue>,
     ) -> Result<(), Error> {
         assert_eq!(keys.len(), values.len());
+        let entries: Vec<_> = keys.iter().cloned().zip(values.into_iter()).collect();
+        conn.insert_batch(block_hash, &entries)
+    }
+}
 
-        if keys.len() == 0 {
+impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
+    /// Insert a batch of key/value pairs into the trie being built for `block_hash`.  This is
+    /// faster than inserting the keys one at a time:
+    /// * each distinct key's path is computed and inserted once.  A key that appears more than
+    /// once is inserted at the position of its first appearance, with the value of its last.
+    /// * if node hashes are calculated as leaves are inserted
+    /// (`TrieHashCalculationMode::Immediate`), the interior nodes' hashes are calculated once for
+    /// the whole batch, instead of once per key along each key's path.  The root node's hash
+    /// and the MARF root hash are calculated once, by the last insert.
+    ///
+    /// The keys are inserted in the order given, not sorted.  A node's child pointers are stored
+    /// in the order they were inserted, and that order is part of the node's hash, so inserting
+    /// the same keys in a different order produces a different root hash.
+    pub fn insert_batch(
+        &mut self,
+        block_hash: &T,
+        entries: &[(String, MARFValue)],
+    ) -> Result<(), Error> {
+        if self.readonly() {
+            return Err(Error::ReadOnlyError);
+        }
+
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        let mut batch: Vec<(TriePath, MARFValue)> = Vec::with_capacity(entries.len());
+        for (key, value) in entries.iter() {
+            if let Some(position) = positions.get(key.as_str()) {
+                batch[*position].1 = value.clone();
+            } else {
+                positions.insert(key.as_str(), batch.len());
+                batch.push((TriePath::from_key(key), value.clone()));
+            }
+        }
+        if batch.len() == 0 {
             return Ok(());
         }
 
-        let (cur_block_hash, cur_block_id) = conn.get_cur_block_and_id();
+        let (cur_block_hash, cur_block_id) = self.get_cur_block_and_id();
+        let hash_calculation_mode = self.hash_calculation_mode;
+        let defer_node_hashes =
+            hash_calculation_mode == TrieHashCalculationMode::Immediate && batch.len() > 1;
+
+        let result = self.inner_insert_batch(block_hash, &batch, defer_node_hashes);
+
+        // restore
+        self.hash_calculation_mode = hash_calculation_mode;
+        self.open_block_maybe_id(&cur_block_hash, cur_block_id)?;
 
-        let last = keys.len() - 1;
+        result
+    }
+
+    fn inner_insert_batch(
+        &mut self,
+        block_hash: &T,
+        batch: &[(TriePath, MARFValue)],
+        defer_node_hashes: bool,
+    ) -> Result<(), Error> {
+        if defer_node_hashes {
+            self.hash_calculation_mode = TrieHashCalculationMode::Deferred;
+        }
+
+        let last = batch.len() - 1;
         let mut progress = 0;
-        let eta_enabled = keys.len() > 10_000;
-        let mut result = keys[0..last]
-            .iter()
-            .enumerate()
-            .zip(values[0..last].iter())
-            .try_for_each(|((index, key), value)| {
-                let marf_leaf = TrieLeaf::from_value(&vec![], value.clone());
-                let path = TriePath::from_key(key);
-
-                if eta_enabled {
-                    let updated_progress = 100 * index / last;
-                    if updated_progress > progress {
-                        progress = updated_progress;
-                        info!(
-                            "Batching insertions in MARF: {}% ({} out of {})",
-                            progress, index, last
-                        );
-                    }
+        let eta_enabled = batch.len() > 10_000;
+        for (index, (path, value)) in batch[0..last].iter().enumerate() {
+            if eta_enabled {
+                let updated_progress = 100 * index / last;
+                if updated_progress > progress {
+                    progress = updated_progress;
+                    info!(
+                        "Batching insertions in MARF: {}% ({} out of {})",
+                        progress, index, last
+                    );
                 }
-                MARF::insert_leaf_in_batch(conn, block_hash, &path, &marf_leaf)
-            });
-
-        if result.is_ok() {
-            // last insert updates the root with the skiplist hash
-            let marf_leaf = TrieLeaf::from_value(&vec![], values[last].clone());
-            let path = TriePath::from_key(&keys[last]);
-            result = MARF::insert_leaf(conn, block_hash, &path, &marf_leaf);
+            }
+            let marf_leaf = TrieLeaf::from_value(&vec![], value.clone());
+            MARF::insert_leaf_in_batch(self, block_hash, path, &marf_leaf)?;
         }
 
-        // restore
-        conn.open_block_maybe_id(&cur_block_hash, cur_block_id)?;
+        if defer_node_hashes {
+            // bring every interior node's hash up to date, so the last insert can calculate the
+            // root hashes from them
+            self.calculate_uncommitted_node_hashes()?;
+            self.hash_calculation_mode = TrieHashCalculationMode::Immediate;
+        }
 
-        result
+        // last insert updates the root with the skiplist hash
+        let (path, value) = &batch[last];
+        let marf_leaf = TrieLeaf::from_value(&vec![], value.clone());
+        MARF::insert_leaf(self, block_hash, path, &marf_leaf)
     }
 }
 
@@ -1307,6 +1416,35 @@ impl<T: MarfTrieId> MARF<T> {
         })
     }
 
+    /// Fork this MARF at `block_hash` for speculative writes, without copying any of its state.
+    /// The returned MARF has a trie open on top of `block_hash` whose chain tip is
+    /// `MARF::make_ephemeral_chain_tip(block_hash)`.  Keys can be inserted into it and read back
+    /// from it like any other open trie, but it lives only in RAM: committing it fails with
+    /// `ReadOnlyError`, and dropping the fork discards it.  This MARF is not affected either way.
+    pub fn fork_ephemeral_at(&self, block_hash: &T) -> Result<MARF<T>, Error> {
+        if self.open_chain_tip.is_some() {
+            error!(
+                "MARF at {} is already in the process of writing",
+                &self.storage.db_path
+            );
+            return Err(Error::InProgressError);
+        }
+
+        let storage = self.storage.fork_ephemeral_at(block_hash)?;
+        let mut fork = MARF {
+            storage,
+            open_chain_tip: None,
+        };
+
+        let ephemeral_tip = MARF::make_ephemeral_chain_tip(block_hash);
+        let mut tx = fork.begin_tx()?;
+        tx.begin(block_hash, &ephemeral_tip)?;
+        // nothing was written to the sqlite DB; the new trie lives in the storage's RAM
+        tx.commit_tx();
+
+        Ok(fork)
+    }
+
     /// Target the MARF's storage at a given block.
     pub fn open_block(&mut self, block_hash: &T) -> Result<(), Error> {
         self.storage.connection().open_block(block_hash)
@@ -1510,6 +1648,12 @@ impl<T: MarfTrieId> MARF<T> {
         self.storage.transaction().unwrap()
     }
 
+    /// Read this MARF's trie block index through an external index.  See
+    /// `TrieFileStorage::set_trie_index()`.
+    pub fn set_trie_index(&mut self, trie_index: SharedTrieIndex<T>) -> Result<u64, Error> {
+        self.storage.set_trie_index(trie_index)
+    }
+
     /// Make a raw transaction to the underlying storage
     pub fn storage_tx<'a>(&'a mut self) -> Result<Transaction<'a>, db_error> {
         self.storage.sqlite_tx()
@@ -1541,6 +1685,20 @@ impl<T: MarfTrieId> MARF<T> {
         })
     }
 
+    /// Reopen this MARF as a read-only snapshot, opened to `block_hash`.  The snapshot does not
+    /// see anything committed after it was taken, and has its own storage connection, so readers
+    /// can use it while this MARF keeps writing.  See `TrieFileStorage::reopen_readonly_at()`.
+    ///
+    /// Returns Err if `block_hash` has not been committed, or if a new underlying SQLite database
+    /// connection cannot be established.
+    pub fn reopen_readonly_at(&self, block_hash: &T) -> Result<MARF<T>, Error> {
+        let ro_storage = self.storage.reopen_readonly_at(block_hash)?;
+        Ok(MARF {
+            storage: ro_storage,
+            open_chain_tip: None,
+        })
+    }
+
     /// Get the root trie hash at a particular block
     pub fn get_root_hash_at(&mut self, block_hash: &T) -> Result<TrieHash, Error> {
         self.storage.connection().get_root_hash_at(block_hash)
@@ -34,15 +34,18 @@ use crate::types::chainstate::SortitionId;
 use crate::types::chainstate::StacksBlockId;
 use crate::types::chainstate::{TrieHash, TRIEHASH_ENCODED_SIZE};
 
+pub mod async_storage;
 pub mod bits;
 pub mod cache;
 pub mod file;
+pub mod journal;
 pub mod marf;
 pub mod node;
 pub mod profile;
 pub mod proofs;
 pub mod storage;
 pub mod trie;
+pub mod trie_index;
 pub mod trie_sql;
 
 #[cfg(test)]
@@ -336,6 +339,8 @@ pub enum Error {
     CursorError(node::CursorError),
     RestoreMarfBlockError(Box<Error>),
     NonMatchingForks([u8; 32], [u8; 32]),
+    IndexProviderError(String),
+    WorkerShutdownError,
 }
 
 impl From<io::Error> for Error {
@@ -401,6 +406,10 @@ impl fmt::Display for Error {
             Error::RequestedIdentifierForExtensionTrie => {
                 write!(f, "BUG: MARF requested the identifier for a RAM trie")
             }
+            Error::IndexProviderError(ref s) => write!(f, "Trie index provider error: {}", s),
+            Error::WorkerShutdownError => {
+                write!(f, "MARF worker shut down before running the operation")
+            }
         }
     }
 }
@@ -1586,3 +1586,192 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         TrieMerkleProof::from_path(storage, &path, value, root_block_header)
     }
 }
+
+/// A Merkle proof of a key's value that is generated one trie at a time, in the order in which
+/// its entries are serialized, so that a proof through many tries never needs to be held in RAM
+/// in its entirety.  Only the shunt proofs (which are small) are calculated up-front, so the
+/// number of entries is known before the first entry is produced.
+///
+/// Yields the same entries as `TrieMerkleProof::from_path()`.
+pub struct TrieMerkleProofStream<'a, 'b, T: MarfTrieId> {
+    storage: &'a mut TrieStorageConnection<'b, T>,
+    path: TriePath,
+    value: MARFValue,
+    root_block_header: T,
+    /// the tries the proof passes through, and the shunt proof that follows each trie's segment
+    /// proof, in proof order (i.e. the trie with the leaf comes first)
+    segments: VecDeque<(T, Vec<TrieMerkleProofType<T>>)>,
+    /// entries generated but not yet produced
+    pending: VecDeque<TrieMerkleProofType<T>>,
+    num_entries: u32,
+}
+
+impl<'a, 'b, T: MarfTrieId> TrieMerkleProofStream<'a, 'b, T> {
+    /// Begin streaming a proof of the value of `path` in the block `root_block_header`.
+    /// Returns NotFoundError if the path has no value.
+    pub fn new(
+        storage: &'a mut TrieStorageConnection<'b, T>,
+        path: &TriePath,
+        root_block_header: &T,
+    ) -> Result<TrieMerkleProofStream<'a, 'b, T>, Error> {
+        let mut segments = VecDeque::new();
+        let mut num_entries = 0;
+        let mut block_header = root_block_header.clone();
+
+        let value = loop {
+            storage.open_block(&block_header)?;
+            let (cursor, reached_node, backptr) =
+                TrieMerkleProof::walk_to_leaf_or_backptr(storage, path)?;
+
+            let shunt_proof = if is_backptr(backptr.id()) {
+                TrieMerkleProof::make_backptr_shunt_proof(storage, &backptr)?
+            } else {
+                TrieMerkleProof::make_initial_shunt_proof(storage)?
+            };
+            num_entries += cursor.node_ptrs.len() + shunt_proof.len();
+            segments.push_front((block_header.clone(), shunt_proof));
+
+            if cursor.ptr().id() == TrieNodeID::Leaf as u8 {
+                match reached_node {
+                    TrieNodeType::Leaf(ref data) => {
+                        break data.data.clone();
+                    }
+                    _ => {
+                        trace!("Did not find leaf at {:?}", path);
+                        return Err(Error::NotFoundError);
+                    }
+                }
+            }
+
+            storage.open_block(&block_header)?;
+            block_header = storage
+                .get_block_from_local_id(backptr.back_block())?
+                .clone();
+        };
+
+        storage.open_block(root_block_header)?;
+        Ok(TrieMerkleProofStream {
+            storage,
+            path: path.clone(),
+            value,
+            root_block_header: root_block_header.clone(),
+            segments,
+            pending: VecDeque::new(),
+            num_entries: num_entries as u32,
+        })
+    }
+
+    /// The value this proof proves
+    pub fn value(&self) -> &MARFValue {
+        &self.value
+    }
+
+    /// The total number of entries this proof will produce
+    pub fn num_entries(&self) -> u32 {
+        self.num_entries
+    }
+
+    /// Generate the entries for the next trie in the proof: its segment proof, followed by its
+    /// shunt proof.
+    fn next_segment(
+        &mut self,
+        block_header: &T,
+        shunt_proof: Vec<TrieMerkleProofType<T>>,
+    ) -> Result<(), Error> {
+        self.storage.open_block(block_header)?;
+        let (cursor, _, _) = TrieMerkleProof::walk_to_leaf_or_backptr(self.storage, &self.path)?;
+        let segment_proof = TrieMerkleProof::make_segment_proof(
+            self.storage,
+            &cursor.node_ptrs,
+            cursor.chr().ok_or_else(|| {
+                Error::CorruptionError("Trie cursor did not walk any nodes".to_string())
+            })?,
+        )?;
+
+        self.pending.extend(segment_proof.into_iter());
+        self.pending.extend(shunt_proof.into_iter());
+
+        if self.segments.is_empty() {
+            self.storage.open_block(&self.root_block_header)?;
+        }
+        Ok(())
+    }
+
+    /// Stream the consensus serialization of this proof, which is identical to the consensus
+    /// serialization of the equivalent `TrieMerkleProof`.
+    pub fn into_reader(self) -> TrieMerkleProofReader<'a, 'b, T> {
+        let buf = self.num_entries.to_be_bytes().to_vec();
+        TrieMerkleProofReader {
+            stream: self,
+            buf,
+            buf_pos: 0,
+        }
+    }
+}
+
+impl<'a, 'b, T: MarfTrieId> Iterator for TrieMerkleProofStream<'a, 'b, T> {
+    type Item = Result<TrieMerkleProofType<T>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_empty() {
+            let (block_header, shunt_proof) = self.segments.pop_front()?;
+            if let Err(e) = self.next_segment(&block_header, shunt_proof) {
+                // no further entries can be produced
+                self.segments.clear();
+                return Some(Err(e));
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+/// Reader over the consensus serialization of a TrieMerkleProofStream.
+pub struct TrieMerkleProofReader<'a, 'b, T: MarfTrieId> {
+    stream: TrieMerkleProofStream<'a, 'b, T>,
+    buf: Vec<u8>,
+    buf_pos: usize,
+}
+
+impl<'a, 'b, T: MarfTrieId> Read for TrieMerkleProofReader<'a, 'b, T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.buf_pos >= self.buf.len() {
+            let entry = match self.stream.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(Error::IOError(e))) => {
+                    return Err(e);
+                }
+                Some(Err(e)) => {
+                    return Err(io::Error::new(io::ErrorKind::Other, e.to_string()));
+                }
+                None => {
+                    return Ok(0);
+                }
+            };
+            self.buf.clear();
+            self.buf_pos = 0;
+            entry
+                .consensus_serialize(&mut self.buf)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        }
+
+        let nr = std::cmp::min(buf.len(), self.buf.len() - self.buf_pos);
+        buf[0..nr].copy_from_slice(&self.buf[self.buf_pos..(self.buf_pos + nr)]);
+        self.buf_pos += nr;
+        Ok(nr)
+    }
+}
+
+impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
+    /// Stream a Merkle proof of `key`'s value in the currently-open block.  The proof is produced
+    /// one trie segment at a time, either as entries (by iterating) or as bytes (via
+    /// `TrieMerkleProofStream::into_reader()`).
+    /// Returns NotFoundError if the key has no value.
+    pub fn stream_merkle_proof<'b>(
+        &'b mut self,
+        key: &str,
+    ) -> Result<TrieMerkleProofStream<'b, 'a, T>, Error> {
+        let root_block_header = self.get_cur_block();
+        let path = TriePath::from_key(key);
+        TrieMerkleProofStream::new(self, &path, &root_block_header)
+    }
+}
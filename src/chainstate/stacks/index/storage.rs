@@ -15,7 +15,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::char::from_digit;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
 use std::env;
 use std::fmt;
@@ -25,6 +25,7 @@ use std::io;
 use std::io::{BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::iter::FromIterator;
 use std::marker::PhantomData;
+use std::mem;
 use std::ops::{Deref, DerefMut};
 use std::os;
 use std::path::{Path, PathBuf};
@@ -34,24 +35,30 @@ use std::{cmp, error};
 use rusqlite::{
     types::{FromSql, ToSql},
     Connection, Error as SqliteError, ErrorCode as SqliteErrorCode, OpenFlags, OptionalExtension,
-    Transaction, NO_PARAMS,
+    Transaction, TransactionBehavior, NO_PARAMS,
 };
 use sha2::Digest;
 
 use crate::chainstate::stacks::index::bits::{
-    get_node_byte_len, get_node_hash, read_block_identifier, read_hash_bytes, read_node_hash_bytes,
-    read_nodetype, read_root_hash, write_nodetype_bytes,
+    get_leaf_hash, get_node_byte_len, get_node_hash, get_nodetype_hash_bytes,
+    read_block_identifier, read_hash_bytes, read_node_hash_bytes, read_nodetype, read_root_hash,
+    write_nodetype_bytes,
 };
 use crate::chainstate::stacks::index::cache::*;
 use crate::chainstate::stacks::index::file::TrieFile;
-use crate::chainstate::stacks::index::file::TrieFileNodeHashReader;
+use crate::chainstate::stacks::index::file::TrieFileMmapNodeHashReader;
+use crate::chainstate::stacks::index::journal::TrieJournal;
 use crate::chainstate::stacks::index::marf::MARFOpenOpts;
+use crate::chainstate::stacks::index::marf::MARF;
 use crate::chainstate::stacks::index::node::{
     clear_backptr, is_backptr, set_backptr, TrieNode, TrieNode16, TrieNode256, TrieNode4,
     TrieNode48, TrieNodeID, TrieNodeType, TriePath, TriePtr,
 };
 use crate::chainstate::stacks::index::profile::TrieBenchmark;
 use crate::chainstate::stacks::index::trie::Trie;
+use crate::chainstate::stacks::index::trie_index::{
+    copy_trie_index, SharedTrieIndex, SqliteTrieIndex, TrieIndexEntry, TrieIndexProvider,
+};
 use crate::chainstate::stacks::index::Error;
 use crate::chainstate::stacks::index::TrieHasher;
 use crate::chainstate::stacks::index::{trie_sql, BlockMap, MarfTrieId};
@@ -67,7 +74,7 @@ use stacks_common::util::hash::to_hex;
 use stacks_common::util::log;
 
 use crate::chainstate::stacks::index::TrieHashExtension;
-use crate::chainstate::stacks::index::{ClarityMarfTrieId, TrieLeaf};
+use crate::chainstate::stacks::index::{ClarityMarfTrieId, MARFValue, TrieLeaf};
 use crate::types::chainstate::BlockHeaderHash;
 use crate::types::chainstate::BLOCK_HEADER_HASH_ENCODED_SIZE;
 use stacks_common::types::chainstate::{TrieHash, TRIEHASH_ENCODED_SIZE};
@@ -78,11 +85,45 @@ pub trait NodeHashReader {
     fn read_node_hash_bytes<W: Write>(&mut self, ptr: &TriePtr, w: &mut W) -> Result<(), Error>;
 }
 
+/// Look up the block hash of the trie with the given block identifier.  Confirmed tries are
+/// looked up in the external trie index, if there is one; unconfirmed and mined tries are only
+/// ever indexed in SQLite.
+fn lookup_block_hash<T: MarfTrieId>(
+    db: &Connection,
+    trie_index: Option<&SharedTrieIndex<T>>,
+    id: u32,
+) -> Result<T, Error> {
+    if let Some(trie_index) = trie_index {
+        match trie_index.get_block_hash(id) {
+            Err(Error::NotFoundError) => {}
+            res => {
+                return res;
+            }
+        }
+    }
+    trie_sql::get_block_hash(db, id)
+}
+
+/// Look up the block identifier of the trie with the given block hash, in the external trie index
+/// first if there is one (see `lookup_block_hash()`).
+fn lookup_block_id<T: MarfTrieId>(
+    db: &Connection,
+    trie_index: Option<&SharedTrieIndex<T>>,
+    block_hash: &T,
+) -> Result<u32, Error> {
+    if let Some(trie_index) = trie_index {
+        if let Some(block_id) = trie_index.get_confirmed_block_identifier(block_hash)? {
+            return Ok(block_id);
+        }
+    }
+    trie_sql::get_block_identifier(db, block_hash)
+}
+
 impl<T: MarfTrieId> BlockMap for TrieFileStorage<T> {
     type TrieId = T;
 
     fn get_block_hash(&self, id: u32) -> Result<T, Error> {
-        trie_sql::get_block_hash(&self.db, id)
+        lookup_block_hash(&self.db, self.data.trie_index.as_ref(), id)
     }
 
     fn get_block_hash_caching(&mut self, id: u32) -> Result<&T, Error> {
@@ -98,7 +139,7 @@ impl<T: MarfTrieId> BlockMap for TrieFileStorage<T> {
     }
 
     fn get_block_id(&self, block_hash: &T) -> Result<u32, Error> {
-        trie_sql::get_block_identifier(&self.db, block_hash)
+        lookup_block_id(&self.db, self.data.trie_index.as_ref(), block_hash)
     }
 
     fn get_block_id_caching(&mut self, block_hash: &T) -> Result<u32, Error> {
@@ -121,7 +162,7 @@ impl<'a, T: MarfTrieId> BlockMap for TrieStorageConnection<'a, T> {
     type TrieId = T;
 
     fn get_block_hash(&self, id: u32) -> Result<T, Error> {
-        trie_sql::get_block_hash(&self.db, id)
+        lookup_block_hash(&self.db, self.data.trie_index.as_ref(), id)
     }
 
     fn get_block_hash_caching(&mut self, id: u32) -> Result<&T, Error> {
@@ -137,7 +178,7 @@ impl<'a, T: MarfTrieId> BlockMap for TrieStorageConnection<'a, T> {
     }
 
     fn get_block_id(&self, block_hash: &T) -> Result<u32, Error> {
-        trie_sql::get_block_identifier(&self.db, block_hash)
+        lookup_block_id(&self.db, self.data.trie_index.as_ref(), block_hash)
     }
 
     fn get_block_id_caching(&mut self, block_hash: &T) -> Result<u32, Error> {
@@ -219,6 +260,130 @@ impl<T: MarfTrieId> BlockMap for TrieSqlHashMapCursor<'_, T> {
     }
 }
 
+/// Block hashes of a trie's back-pointers, resolved ahead of time so that node hashes can be
+/// calculated on threads that cannot access the trie's storage.  The hashes are stored as
+/// `BlockHeaderHash`es because only their bytes are needed, and those can be shared across
+/// threads for any `MarfTrieId`.
+#[cfg(feature = "marf_parallel_hash")]
+struct ResolvedBlockMap<'a> {
+    block_hashes: &'a HashMap<u32, BlockHeaderHash>,
+}
+
+#[cfg(feature = "marf_parallel_hash")]
+impl BlockMap for ResolvedBlockMap<'_> {
+    type TrieId = BlockHeaderHash;
+
+    fn get_block_hash(&self, id: u32) -> Result<BlockHeaderHash, Error> {
+        self.block_hashes
+            .get(&id)
+            .cloned()
+            .ok_or(Error::NotFoundError)
+    }
+
+    fn get_block_hash_caching(&mut self, id: u32) -> Result<&BlockHeaderHash, Error> {
+        self.block_hashes.get(&id).ok_or(Error::NotFoundError)
+    }
+
+    fn is_block_hash_cached(&self, id: u32) -> bool {
+        self.block_hashes.contains_key(&id)
+    }
+
+    fn get_block_id(&self, block_hash: &BlockHeaderHash) -> Result<u32, Error> {
+        self.block_hashes
+            .iter()
+            .find(|(_, hash)| *hash == block_hash)
+            .map(|(id, _)| *id)
+            .ok_or(Error::NotFoundError)
+    }
+
+    fn get_block_id_caching(&mut self, block_hash: &BlockHeaderHash) -> Result<u32, Error> {
+        self.get_block_id(block_hash)
+    }
+}
+
+/// Tries with fewer nodes than this are always hashed on the calling thread
+#[cfg(feature = "marf_parallel_hash")]
+const PARALLEL_HASH_MIN_NODES: usize = 1024;
+/// Subtrees below this depth are hashed on the thread that hashes their ancestor
+#[cfg(feature = "marf_parallel_hash")]
+const PARALLEL_HASH_MAX_DEPTH: u32 = 2;
+
+/// Calculate the hash of the node at `node_ptr` in `nodes`, and the hashes of all of its
+/// descendants in the same trie.  Sibling subtrees are hashed concurrently down to
+/// `PARALLEL_HASH_MAX_DEPTH`.  Returns the node's hash, and the pointer and hash of each non-leaf
+/// descendant.
+#[cfg(feature = "marf_parallel_hash")]
+fn parallel_node_hashes(
+    nodes: &[(TrieNodeType, TrieHash)],
+    block_hashes: &HashMap<u32, BlockHeaderHash>,
+    node_ptr: u32,
+    depth: u32,
+) -> Result<(TrieHash, Vec<(u32, TrieHash)>), Error> {
+    use rayon::prelude::*;
+
+    let (node, node_hash) = nodes.get(node_ptr as usize).ok_or_else(|| {
+        Error::CorruptionError(format!("parallel_node_hashes: invalid ptr {}", node_ptr))
+    })?;
+    if node.is_leaf() {
+        return Ok((node_hash.clone(), vec![]));
+    }
+
+    let hash_child = |ptr: &TriePtr| -> Result<Option<(TrieHash, Vec<(u32, TrieHash)>)>, Error> {
+        if ptr.id() == TrieNodeID::Empty as u8 || is_backptr(ptr.id()) {
+            return Ok(None);
+        }
+        let (child_hash, mut descendant_hashes) =
+            parallel_node_hashes(nodes, block_hashes, ptr.ptr(), depth + 1)?;
+        if ptr.id() != TrieNodeID::Leaf as u8 {
+            descendant_hashes.push((ptr.ptr(), child_hash.clone()));
+        }
+        Ok(Some((child_hash, descendant_hashes)))
+    };
+
+    let children: Vec<Option<(TrieHash, Vec<(u32, TrieHash)>)>> = if depth < PARALLEL_HASH_MAX_DEPTH
+    {
+        node.ptrs()
+            .par_iter()
+            .map(hash_child)
+            .collect::<Result<_, Error>>()?
+    } else {
+        node.ptrs()
+            .iter()
+            .map(hash_child)
+            .collect::<Result<_, Error>>()?
+    };
+
+    // same byte sequence as TrieRAM::calculate_node_hashes()
+    let mut hasher = TrieHasher::new();
+    let empty_node_hash = TrieHash::from_data(&[]);
+    let mut block_map = ResolvedBlockMap { block_hashes };
+    node.write_consensus_bytes(&mut block_map, &mut hasher)
+        .expect("IO Failure pushing to hasher.");
+
+    let mut all_descendant_hashes = vec![];
+    for (ptr, child) in node.ptrs().iter().zip(children.into_iter()) {
+        if ptr.id() == TrieNodeID::Empty as u8 {
+            hasher.write_all(empty_node_hash.as_bytes())?;
+        } else if let Some((child_hash, descendant_hashes)) = child {
+            hasher.write_all(child_hash.as_bytes())?;
+            all_descendant_hashes.extend(descendant_hashes);
+        } else {
+            hasher.write_all(
+                block_map
+                    .get_block_hash_caching(ptr.back_block())?
+                    .as_bytes(),
+            )?;
+        }
+    }
+
+    let node_hash = {
+        let mut buf = [0u8; 32];
+        buf.copy_from_slice(hasher.finalize().as_slice());
+        TrieHash(buf)
+    };
+    Ok((node_hash, all_descendant_hashes))
+}
+
 enum FlushOptions<'a, T: MarfTrieId> {
     CurrentHeader,
     NewHeader(&'a T),
@@ -649,6 +814,13 @@ impl<T: MarfTrieId> TrieRAM<T> {
     ) -> Result<TrieHash, Error> {
         // find trie root hash
         debug!("Calculate trie root hash");
+        #[cfg(feature = "marf_parallel_hash")]
+        let root_trie_hash = if self.data.len() >= PARALLEL_HASH_MIN_NODES {
+            self.calculate_node_hashes_parallel(storage_tx)?
+        } else {
+            self.calculate_node_hashes(storage_tx, 0)?
+        };
+        #[cfg(not(feature = "marf_parallel_hash"))]
         let root_trie_hash = self.calculate_node_hashes(storage_tx, 0)?;
 
         // find marf root hash -- the hash of the trie root node hash, and the hashes of the
@@ -831,6 +1003,70 @@ impl<T: MarfTrieId> TrieRAM<T> {
         }
     }
 
+    /// Calculate all node hashes in this `TrieRAM` like `calculate_node_hashes()` does, but hash
+    /// sibling subtrees on different threads.  The block hashes of all back-pointers are first
+    /// resolved via `storage_tx` on this thread, since the storage cannot be shared.  Returns the
+    /// trie root hash, which is identical to the one `calculate_node_hashes()` would return.
+    #[cfg(feature = "marf_parallel_hash")]
+    fn calculate_node_hashes_parallel(
+        &mut self,
+        storage_tx: &mut TrieStorageTransaction<T>,
+    ) -> Result<TrieHash, Error> {
+        let start_time = storage_tx.bench.write_children_hashes_start();
+
+        let mut block_hashes = HashMap::new();
+        let mut frontier = vec![0u32];
+        while let Some(node_ptr) = frontier.pop() {
+            let (node, _) = self.get_nodetype(node_ptr)?;
+            if node.is_leaf() {
+                continue;
+            }
+            for ptr in node.ptrs().iter() {
+                if ptr.id() == TrieNodeID::Empty as u8 {
+                    continue;
+                }
+                if !is_backptr(ptr.id()) {
+                    frontier.push(ptr.ptr());
+                } else if !block_hashes.contains_key(&ptr.back_block()) {
+                    let block_hash = storage_tx.get_block_hash_caching(ptr.back_block())?;
+                    let mut bytes = [0u8; 32];
+                    bytes.copy_from_slice(block_hash.as_bytes());
+                    block_hashes.insert(ptr.back_block(), BlockHeaderHash(bytes));
+                }
+            }
+        }
+
+        let (root_hash, node_hashes) = parallel_node_hashes(&self.data, &block_hashes, 0, 0)?;
+
+        if TrieHashCalculationMode::Deferred == storage_tx.deref().hash_calculation_mode {
+            // need to store these hashes too, since we deferred calculation
+            for (node_ptr, node_hash) in node_hashes.into_iter() {
+                self.write_node_hash(node_ptr, node_hash)?;
+            }
+        }
+
+        storage_tx
+            .bench
+            .write_children_hashes_finish(start_time, true);
+        Ok(root_hash)
+    }
+
+    #[cfg(all(test, feature = "marf_parallel_hash"))]
+    pub fn test_calculate_node_hashes(
+        &mut self,
+        storage_tx: &mut TrieStorageTransaction<T>,
+    ) -> Result<TrieHash, Error> {
+        self.calculate_node_hashes(storage_tx, 0)
+    }
+
+    #[cfg(all(test, feature = "marf_parallel_hash"))]
+    pub fn test_calculate_node_hashes_parallel(
+        &mut self,
+        storage_tx: &mut TrieStorageTransaction<T>,
+    ) -> Result<TrieHash, Error> {
+        self.calculate_node_hashes_parallel(storage_tx)
+    }
+
     /// Walk through the buffered TrieNodes and dump them to f.
     /// This consumes this TrieRAM instance.
     fn dump_consume<F: Write + Seek>(mut self, f: &mut F) -> Result<u64, Error> {
@@ -1285,6 +1521,216 @@ pub struct TrieStorageTransientData<T: MarfTrieId> {
 
     /// Does this trie represent unconfirmed state?
     unconfirmed: bool,
+
+    /// Is this an ephemeral fork, whose writes are never persisted?  See
+    /// `TrieFileStorage::fork_ephemeral_at()`.
+    ephemeral: bool,
+
+    /// Write-ahead journal of the nodes written to `uncommitted_writes`, if enabled.
+    uncommitted_journal: Option<TrieJournal>,
+
+    /// External index of confirmed tries, if any (see `TrieFileStorage::set_trie_index()`)
+    trie_index: Option<SharedTrieIndex<T>>,
+
+    /// Changes to confirmed tries made in the open transaction, to be made to `trie_index` once
+    /// the transaction commits
+    pending_trie_index_updates: Vec<TrieIndexUpdate<T>>,
+}
+
+/// A change to make to an external trie index
+enum TrieIndexUpdate<T: MarfTrieId> {
+    /// A trie was committed
+    Insert(TrieIndexEntry<T>),
+    /// The trie with this block identifier was pruned
+    Prune(u32),
+}
+
+/// Make a committed transaction's changes to confirmed tries in an external trie index.  They
+/// are already durable in the SQLite index, so failures are logged instead of returned;
+/// `TrieFileStorage::set_trie_index()` copies any missing entries over again.
+fn apply_trie_index_updates<T: MarfTrieId>(
+    trie_index: &dyn TrieIndexProvider<T>,
+    updates: Vec<TrieIndexUpdate<T>>,
+) {
+    for update in updates.into_iter() {
+        let res = match update {
+            TrieIndexUpdate::Insert(entry) => match trie_index.get_entry(entry.block_id) {
+                // another MARF sharing this index got here first
+                Ok(Some(_)) => Ok(()),
+                Ok(None) => trie_index.insert_entry(&entry),
+                Err(e) => Err(e),
+            },
+            TrieIndexUpdate::Prune(block_id) => match trie_index.prune_entry(block_id) {
+                Err(Error::NotFoundError) => Ok(()),
+                res => res,
+            },
+        };
+        if let Err(e) = res {
+            error!(
+                "Failed to update {:?} trie index: {:?}",
+                trie_index.index_type(),
+                &e
+            );
+        }
+    }
+}
+
+/// A problem found in a persisted trie by `TrieFileStorage::verify_integrity()`
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrieIntegrityFault<T: MarfTrieId> {
+    /// The hash stored alongside a node does not match the hash recomputed from its contents.
+    /// For the root node, the recomputed hash includes the ancestor trie root hashes.
+    HashMismatch {
+        block_hash: T,
+        ptr: TriePtr,
+        stored_hash: TrieHash,
+        computed_hash: TrieHash,
+        repaired: bool,
+    },
+    /// A node in the trie could not be read or hashed, so the rest of the trie was not checked.
+    Unreadable {
+        block_hash: T,
+        ptr: TriePtr,
+        error: String,
+    },
+}
+
+/// A node still to be visited by a `TrieKeyIterator`
+struct TrieKeyIteratorEntry<T: MarfTrieId> {
+    /// block whose trie holds the node, and its ID if known
+    block_hash: T,
+    block_id: Option<u32>,
+    /// pointer to the node within that trie (never a back-pointer)
+    ptr: TriePtr,
+    /// path bytes consumed on the way to this node, excluding the node's own path
+    path_prefix: Vec<u8>,
+}
+
+/// Iterator over the (path, value) pairs visible from a block's trie, in ascending path order.
+/// Back-pointers are followed into ancestor tries, so this enumerates every key in the MARF as of
+/// that block.  Only key hashes are available -- the MARF does not store the keys themselves.
+/// The storage connection's open block is restored once the iterator is dropped.
+pub struct TrieKeyIterator<'a, 'b, T: MarfTrieId> {
+    storage: &'a mut TrieStorageConnection<'b, T>,
+    prefix: Vec<u8>,
+    stack: Vec<TrieKeyIteratorEntry<T>>,
+    restore: (T, Option<u32>),
+}
+
+impl<T: MarfTrieId> TrieKeyIterator<'_, '_, T> {
+    /// Could a path that starts with `path` match the iterator's prefix?
+    fn may_match(&self, path: &[u8]) -> bool {
+        let len = cmp::min(path.len(), self.prefix.len());
+        path[0..len] == self.prefix[0..len]
+    }
+
+    /// Visit one node: either produce its leaf, or queue up its children
+    fn visit(
+        &mut self,
+        entry: TrieKeyIteratorEntry<T>,
+    ) -> Result<Option<(TriePath, MARFValue)>, Error> {
+        self.storage
+            .open_block_maybe_id(&entry.block_hash, entry.block_id)?;
+        let (node, _) = self.storage.read_nodetype(&entry.ptr)?;
+
+        let mut path = entry.path_prefix.clone();
+        path.extend_from_slice(node.path_bytes());
+        if !self.may_match(&path) {
+            return Ok(None);
+        }
+
+        if let TrieNodeType::Leaf(leaf) = node {
+            let trie_path = TriePath::from_bytes(&path).ok_or_else(|| {
+                Error::CorruptionError(format!(
+                    "Leaf at {:?} in {} has a {}-byte path",
+                    &entry.ptr,
+                    &entry.block_hash,
+                    path.len()
+                ))
+            })?;
+            return Ok(Some((trie_path, leaf.data)));
+        }
+
+        let mut children = self
+            .storage
+            .trie_child_entries(&entry, &node, &path)?
+            .into_iter()
+            .filter(|child| self.may_match(&child.path_prefix))
+            .collect::<Vec<_>>();
+
+        // visit children in ascending order of their next path byte
+        children.sort_by(|a, b| b.ptr.chr().cmp(&a.ptr.chr()));
+        self.stack.extend(children);
+        Ok(None)
+    }
+}
+
+impl<T: MarfTrieId> Iterator for TrieKeyIterator<'_, '_, T> {
+    type Item = Result<(TriePath, MARFValue), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(entry) = self.stack.pop() {
+            match self.visit(entry) {
+                Ok(Some(item)) => return Some(Ok(item)),
+                Ok(None) => {}
+                Err(e) => {
+                    // can't safely continue past an unreadable node
+                    self.stack.clear();
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<T: MarfTrieId> Drop for TrieKeyIterator<'_, '_, T> {
+    fn drop(&mut self) {
+        let (ref block_hash, block_id) = self.restore;
+        if let Err(e) = self.storage.open_block_maybe_id(block_hash, block_id) {
+            warn!(
+                "Failed to re-open block {} after iterating keys: {:?}",
+                block_hash, &e
+            );
+        }
+    }
+}
+
+/// Outcome of walking every confirmed trie in a `TrieFileStorage`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrieIntegrityReport<T: MarfTrieId> {
+    /// Number of tries walked
+    pub tries_checked: u64,
+    /// Number of nodes whose hashes were recomputed
+    pub nodes_checked: u64,
+    /// Everything that was found to be wrong
+    pub faults: Vec<TrieIntegrityFault<T>>,
+}
+
+impl<T: MarfTrieId> TrieIntegrityReport<T> {
+    fn new() -> TrieIntegrityReport<T> {
+        TrieIntegrityReport {
+            tries_checked: 0,
+            nodes_checked: 0,
+            faults: vec![],
+        }
+    }
+
+    /// Did the check find no faults at all?
+    pub fn is_ok(&self) -> bool {
+        self.faults.is_empty()
+    }
+
+    /// Number of faults that were not (or could not be) repaired
+    pub fn num_unrepaired(&self) -> usize {
+        self.faults
+            .iter()
+            .filter(|fault| match fault {
+                TrieIntegrityFault::HashMismatch { repaired, .. } => !*repaired,
+                TrieIntegrityFault::Unreadable { .. } => true,
+            })
+            .count()
+    }
 }
 
 // disk-backed Trie.
@@ -1356,7 +1802,14 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
         if self.readonly() {
             return Err(Error::ReadOnlyError);
         }
-        let tx = tx_begin_immediate(&mut self.db)?;
+        self.data.pending_trie_index_updates.clear();
+        let tx = if self.data.ephemeral {
+            // nothing is ever written through an ephemeral fork's read-only connection
+            self.db
+                .transaction_with_behavior(TransactionBehavior::Deferred)?
+        } else {
+            tx_begin_immediate(&mut self.db)?
+        };
 
         Ok(TrieStorageTransaction(TrieStorageConnection {
             db: SqliteConnection::Tx(tx),
@@ -1429,7 +1882,15 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
         }
 
         let mut blobs = if marf_opts.external_blobs {
-            Some(TrieFile::from_db_path(&db_path, readonly)?)
+            #[cfg(feature = "marf_rocksdb")]
+            let blobs = if marf_opts.rocksdb_blobs {
+                TrieFile::from_db_path_rocksdb(&db_path, readonly)?
+            } else {
+                TrieFile::from_db_path(&db_path, readonly)?
+            };
+            #[cfg(not(feature = "marf_rocksdb"))]
+            let blobs = TrieFile::from_db_path(&db_path, readonly)?;
+            Some(blobs)
         } else {
             None
         };
@@ -1455,6 +1916,17 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
 
         let cache = TrieCache::new(&marf_opts.cache_strategy);
 
+        // unconfirmed tries are persisted as they are built, so they are never journaled
+        let uncommitted_journal = if marf_opts.uncommitted_journal
+            && !readonly
+            && !unconfirmed
+            && db_path != ":memory:"
+        {
+            Some(TrieJournal::new(&db_path))
+        } else {
+            None
+        };
+
         let ret = TrieFileStorage {
             db_path,
             db,
@@ -1481,6 +1953,12 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
 
                 readonly: readonly,
                 unconfirmed: unconfirmed,
+                ephemeral: false,
+
+                uncommitted_journal,
+
+                trie_index: None,
+                pending_trie_index_updates: vec![],
             },
 
             // used in testing in order to short-circuit block-height lookups
@@ -1501,6 +1979,18 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
         TrieFileStorage::open_opts(db_path, false, false, marf_opts)
     }
 
+    /// Open a TrieFileStorage whose trie nodes and hashes are cached in a size-bounded LRU cache
+    /// of (approximately) `cache_bytes` bytes, regardless of `marf_opts.cache_strategy`.
+    pub fn open_with_cache(
+        db_path: &str,
+        marf_opts: MARFOpenOpts,
+        cache_bytes: u64,
+    ) -> Result<TrieFileStorage<T>, Error> {
+        let mut storage = TrieFileStorage::open_opts(db_path, false, false, marf_opts)?;
+        storage.cache = TrieCache::new_lru(cache_bytes);
+        Ok(storage)
+    }
+
     pub fn open_readonly(
         db_path: &str,
         marf_opts: MARFOpenOpts,
@@ -1517,6 +2007,25 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
         TrieFileStorage::open_opts(db_path, false, true, marf_opts)
     }
 
+    /// Walk every confirmed trie, recompute each node's hash bottom-up from the node data stored
+    /// in its blob, and compare it to the stored hash.  Root nodes are checked against their
+    /// MARF root hash (i.e. mixed with the ancestor trie root hashes), which is the hash that
+    /// block headers commit to.
+    ///
+    /// If `repair` is true, then mismatched stored hashes are overwritten with the recomputed
+    /// ones.  Node data itself cannot be repaired -- tries with unreadable nodes are only
+    /// reported.
+    pub fn verify_integrity(&mut self, repair: bool) -> Result<TrieIntegrityReport<T>, Error> {
+        if repair {
+            let mut tx = self.transaction()?;
+            let report = tx.inner_verify_integrity(true)?;
+            tx.commit_tx();
+            Ok(report)
+        } else {
+            self.connection().inner_verify_integrity(false)
+        }
+    }
+
     pub fn readonly(&self) -> bool {
         self.data.readonly
     }
@@ -1529,6 +2038,11 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
         self.data.unconfirmed
     }
 
+    /// Is this storage an ephemeral fork made by `fork_ephemeral_at()`?
+    pub fn ephemeral(&self) -> bool {
+        self.data.ephemeral
+    }
+
     /// Returns a new TrieFileStorage in read-only mode.
     ///
     /// Returns Err if the underlying SQLite database connection cannot be created.
@@ -1570,6 +2084,152 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
 
                 readonly: true,
                 unconfirmed: self.unconfirmed(),
+                ephemeral: false,
+
+                uncommitted_journal: None,
+
+                trie_index: self.data.trie_index.clone(),
+                pending_trie_index_updates: vec![],
+            },
+
+            // used in testing in order to short-circuit block-height lookups
+            //   when the trie struct is tested outside of marf.rs usage
+            #[cfg(test)]
+            test_genesis_block: self.test_genesis_block.clone(),
+        };
+
+        Ok(ret)
+    }
+
+    /// Returns a new read-only TrieFileStorage that sees the MARF as it was when this method was
+    /// called, opened to `block_hash`.
+    ///
+    /// Unlike `reopen_readonly()`, the new storage holds a SQLite read transaction open for as long
+    /// as it exists, so tries committed by a writer in the meantime are not visible to it and
+    /// reads through it are consistent with one another.  It has its own connection, so it does
+    /// not contend with the writer's connection, and any number of these can be used at once
+    /// (e.g. one per RPC thread).  Uncommitted writes are not visible to it.
+    ///
+    /// Because the database cannot checkpoint its write-ahead log past the oldest open read
+    /// transaction, these should be short-lived.
+    ///
+    /// Returns Err(NotFoundError) if `block_hash` has not been committed.
+    pub fn reopen_readonly_at(&self, block_hash: &T) -> Result<TrieFileStorage<T>, Error> {
+        let db = marf_sqlite_open(&self.db_path, OpenFlags::SQLITE_OPEN_READ_ONLY, false)?;
+
+        // the snapshot is taken by the first read in the transaction
+        db.execute_batch("BEGIN DEFERRED")?;
+        let block_id = lookup_block_id(&db, self.data.trie_index.as_ref(), block_hash)?;
+
+        let blobs = if self.blobs.is_some() {
+            Some(TrieFile::from_db_path(&self.db_path, true)?)
+        } else {
+            None
+        };
+
+        trace!(
+            "Make read-only snapshot of TrieFileStorage: {} at {}",
+            &self.db_path,
+            block_hash
+        );
+
+        let ret = TrieFileStorage {
+            db_path: self.db_path.clone(),
+            db: db,
+            blobs,
+            cache: TrieCache::default(),
+            bench: TrieBenchmark::new(),
+            hash_calculation_mode: self.hash_calculation_mode,
+
+            data: TrieStorageTransientData {
+                uncommitted_writes: None,
+                cur_block: block_hash.clone(),
+                cur_block_id: Some(block_id),
+
+                read_count: 0,
+                read_backptr_count: 0,
+                read_node_count: 0,
+                read_leaf_count: 0,
+
+                write_count: 0,
+                write_node_count: 0,
+                write_leaf_count: 0,
+
+                trie_ancestor_hash_bytes_cache: None,
+
+                readonly: true,
+                unconfirmed: self.unconfirmed(),
+                ephemeral: false,
+
+                uncommitted_journal: None,
+
+                trie_index: self.data.trie_index.clone(),
+                pending_trie_index_updates: vec![],
+            },
+
+            // used in testing in order to short-circuit block-height lookups
+            //   when the trie struct is tested outside of marf.rs usage
+            #[cfg(test)]
+            test_genesis_block: self.test_genesis_block.clone(),
+        };
+
+        Ok(ret)
+    }
+
+    /// Returns a new TrieFileStorage that forks this one at `block_hash` for speculative writes.
+    /// Confirmed tries are read from disk through a read-only connection, and the fork's own trie
+    /// is held entirely in RAM -- it can be extended and written to, but never flushed.  Dropping
+    /// the fork discards its writes, and leaves this storage untouched.
+    /// The fork is opened to `block_hash`; use `MARF::fork_ephemeral_at()` to get a MARF with a
+    /// trie already open for writing on top of it.
+    pub fn fork_ephemeral_at(&self, block_hash: &T) -> Result<TrieFileStorage<T>, Error> {
+        let db = marf_sqlite_open(&self.db_path, OpenFlags::SQLITE_OPEN_READ_ONLY, false)?;
+        let block_id = lookup_block_id(&db, self.data.trie_index.as_ref(), block_hash)?;
+
+        let blobs = if self.blobs.is_some() {
+            Some(TrieFile::from_db_path(&self.db_path, true)?)
+        } else {
+            None
+        };
+
+        trace!(
+            "Make ephemeral fork of TrieFileStorage: {} at {}",
+            &self.db_path,
+            block_hash
+        );
+
+        let ret = TrieFileStorage {
+            db_path: self.db_path.clone(),
+            db: db,
+            blobs,
+            cache: TrieCache::default(),
+            bench: TrieBenchmark::new(),
+            hash_calculation_mode: self.hash_calculation_mode,
+
+            data: TrieStorageTransientData {
+                uncommitted_writes: None,
+                cur_block: block_hash.clone(),
+                cur_block_id: Some(block_id),
+
+                read_count: 0,
+                read_backptr_count: 0,
+                read_node_count: 0,
+                read_leaf_count: 0,
+
+                write_count: 0,
+                write_node_count: 0,
+                write_leaf_count: 0,
+
+                trie_ancestor_hash_bytes_cache: None,
+
+                readonly: false,
+                unconfirmed: false,
+                ephemeral: true,
+
+                uncommitted_journal: None,
+
+                trie_index: self.data.trie_index.clone(),
+                pending_trie_index_updates: vec![],
             },
 
             // used in testing in order to short-circuit block-height lookups
@@ -1592,6 +2252,88 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
     pub fn reset_benchmarks(&mut self) {
         self.bench.reset();
     }
+
+    /// Get the node cache's statistics, if its strategy tracks them
+    pub fn get_cache_stats(&self) -> Option<TrieCacheStats> {
+        self.cache.stats()
+    }
+
+    /// Read confirmed tries' entries through the given trie block index, before falling back to
+    /// the SQLite index, and mirror every trie committed or pruned from now on into it.  Only
+    /// MARFs that store their tries in an external blobs file can use an external index, since
+    /// the index only records where a trie's bytes are in that file.
+    ///
+    /// If this storage is writable, any confirmed tries missing from the index are copied into it
+    /// first (see `copy_trie_index()`).  Returns the number of entries copied.
+    pub fn set_trie_index(&mut self, trie_index: SharedTrieIndex<T>) -> Result<u64, Error> {
+        if self.blobs.is_none() {
+            return Err(Error::IndexProviderError(format!(
+                "{} does not store its tries in an external blobs file",
+                &self.db_path
+            )));
+        }
+        let num_copied = if self.readonly() {
+            0
+        } else {
+            copy_trie_index::<T>(&SqliteTrieIndex::new(&self.db), trie_index.as_ref())?
+        };
+        debug!(
+            "Use {:?} trie index for {}",
+            trie_index.index_type(),
+            &self.db_path
+        );
+        self.data.trie_index = Some(trie_index);
+        Ok(num_copied)
+    }
+
+    /// Get the external trie block index, if one was set
+    pub fn trie_index(&self) -> Option<&SharedTrieIndex<T>> {
+        self.data.trie_index.as_ref()
+    }
+
+    /// Release the space in the external blobs file taken up by tries that were pruned in
+    /// committed transactions (see `TrieStorageTransaction::prune_below()`).  Returns the number
+    /// of bytes released.
+    pub fn reclaim_pruned_tries(&mut self) -> Result<u64, Error> {
+        if self.readonly() {
+            return Err(Error::ReadOnlyError);
+        }
+        let index = SqliteTrieIndex::new(&self.db);
+        let entries: Vec<TrieIndexEntry<T>> = index.get_unreclaimed_pruned_entries()?;
+
+        let mut num_reclaimed = 0;
+        for entry in entries.iter() {
+            if let Some(blobs) = self.blobs.as_mut() {
+                if entry.external_length > 0 {
+                    blobs.discard_trie_blob(
+                        entry.block_id,
+                        entry.external_offset,
+                        entry.external_length,
+                    )?;
+                    num_reclaimed += entry.external_length;
+                }
+            }
+            TrieIndexProvider::<T>::set_pruned_entry_reclaimed(&index, entry.block_id)?;
+            if let Some(trie_index) = self.data.trie_index.as_ref() {
+                if let Err(e) = trie_index.set_pruned_entry_reclaimed(entry.block_id) {
+                    error!(
+                        "Failed to mark pruned trie {} as reclaimed in {:?} trie index: {:?}",
+                        entry.block_id,
+                        trie_index.index_type(),
+                        &e
+                    );
+                }
+            }
+        }
+
+        debug!(
+            "Reclaimed {} bytes from {} pruned tries in {}",
+            num_reclaimed,
+            entries.len(),
+            &self.db_path
+        );
+        Ok(num_reclaimed)
+    }
 }
 
 impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
@@ -1639,6 +2381,12 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
 
                 readonly: true,
                 unconfirmed: self.unconfirmed(),
+                ephemeral: false,
+
+                uncommitted_journal: None,
+
+                trie_index: self.data.trie_index.clone(),
+                pending_trie_index_updates: vec![],
             },
 
             // used in testing in order to short-circuit block-height lookups
@@ -1650,6 +2398,123 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
         Ok(ret)
     }
 
+    /// Calculate and store the hash of every interior node in the uncommitted trie, except for the
+    /// root.  Used to bring the trie's node hashes up to date after inserting leaves with
+    /// `TrieHashCalculationMode::Deferred`; the hash calculation mode must still be `Deferred`
+    /// when this is called, or the hashes will not be stored.  Returns the trie root node's hash.
+    pub fn calculate_uncommitted_node_hashes(&mut self) -> Result<TrieHash, Error> {
+        let (block_header, mut trie_ram) = match self.data.uncommitted_writes.take() {
+            Some((block_header, UncommittedState::RW(trie_ram))) => (block_header, trie_ram),
+            other => {
+                self.data.uncommitted_writes = other;
+                return Err(Error::WriteNotBegunError);
+            }
+        };
+        let res = trie_ram.calculate_node_hashes(self, 0);
+        self.data.uncommitted_writes = Some((block_header, UncommittedState::RW(trie_ram)));
+        res
+    }
+
+    /// Prune the tries of blocks below `height` that are not on a fork that reaches `height`.
+    /// A block is kept if it is at or above `height`, or if it is an ancestor of a block that is,
+    /// so every trie that a kept trie's back-pointers can refer to is kept as well.  If no block
+    /// reaches `height` yet, then nothing is pruned.  The most recently stored trie is never
+    /// pruned, so that block identifiers are never reassigned.
+    ///
+    /// Pruned tries are moved out of the trie block index and recorded as pruned, and anything
+    /// cached for them is dropped.  Their space in the external blobs file is released by
+    /// `TrieFileStorage::reclaim_pruned_tries()` once this transaction commits.
+    ///
+    /// Returns the hashes of the pruned blocks.
+    pub fn prune_below(&mut self, height: u32) -> Result<Vec<T>, Error> {
+        if self.data.readonly {
+            return Err(Error::ReadOnlyError);
+        }
+        if self.data.uncommitted_writes.is_some() {
+            return Err(Error::InProgressError);
+        }
+        if height == 0 {
+            return Ok(vec![]);
+        }
+
+        // (block ID, block hash, block height) of each confirmed trie
+        let max_block_id = trie_sql::count_blocks(&self.db)?;
+        let mut blocks = vec![];
+        for block_id in 1..=max_block_id {
+            let entry: Option<TrieIndexEntry<T>> =
+                SqliteTrieIndex::new(&self.db).get_entry(block_id)?;
+            let entry = match entry {
+                Some(entry) if !entry.unconfirmed => entry,
+                _ => {
+                    continue;
+                }
+            };
+            let block_height =
+                MARF::get_block_height_miner_tip(self, &entry.block_hash, &entry.block_hash)?
+                    .ok_or_else(|| {
+                        Error::CorruptionError(format!("No block height for {}", &entry.block_hash))
+                    })?;
+            blocks.push((entry.block_id, entry.block_hash, block_height));
+        }
+
+        if !blocks
+            .iter()
+            .any(|(_, _, block_height)| *block_height >= height)
+        {
+            debug!("No trie reaches height {}; nothing to prune", height);
+            return Ok(vec![]);
+        }
+
+        // find every block below `height` that a block at or above `height` descends from.
+        let mut ancestors = HashSet::new();
+        for (_, block_hash, block_height) in blocks.iter() {
+            if *block_height < height {
+                continue;
+            }
+            for ancestor_height in (0..height).rev() {
+                let ancestor = match MARF::get_block_at_height(self, ancestor_height, block_hash)? {
+                    Some(ancestor) => ancestor,
+                    None => {
+                        break;
+                    }
+                };
+                if !ancestors.insert(ancestor) {
+                    // this ancestor's own ancestors were already found
+                    break;
+                }
+            }
+        }
+
+        let (cur_block, _) = self.get_cur_block_and_id();
+        let mut pruned = vec![];
+        for (block_id, block_hash, block_height) in blocks.into_iter() {
+            if block_height >= height || block_id == max_block_id || ancestors.contains(&block_hash)
+            {
+                continue;
+            }
+            TrieIndexProvider::<T>::prune_entry(&SqliteTrieIndex::new(&self.db), block_id)?;
+            if self.data.trie_index.is_some() {
+                self.data
+                    .pending_trie_index_updates
+                    .push(TrieIndexUpdate::Prune(block_id));
+            }
+            self.cache.forget_block(block_id);
+            pruned.push(block_hash);
+        }
+
+        if pruned.contains(&cur_block) {
+            self.open_block(&T::sentinel())?;
+        }
+
+        debug!(
+            "Pruned {} tries below height {} in {}",
+            pruned.len(),
+            height,
+            &self.db_path
+        );
+        Ok(pruned)
+    }
+
     /// Run `cls` with a mutable reference to the inner trie blobs opt.
     fn with_trie_blobs<F, R>(&mut self, cls: F) -> R
     where
@@ -1672,6 +2537,10 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
         if self.data.readonly {
             return Err(Error::ReadOnlyError);
         }
+        if self.data.ephemeral && self.data.uncommitted_writes.is_some() {
+            // an ephemeral fork's writes can only be discarded
+            return Err(Error::ReadOnlyError);
+        }
         if let Some((bhh, trie_ram)) = self.data.uncommitted_writes.take() {
             trace!("Buffering block flush started.");
             let mut buffer = Cursor::new(Vec::new());
@@ -1683,6 +2552,13 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
 
             debug!("Flush: {} to {}", &bhh, flush_options);
 
+            // only tries in the block history are mirrored to an external trie index
+            let mirror_to_trie_index = self.data.trie_index.is_some()
+                && matches!(
+                    flush_options,
+                    FlushOptions::CurrentHeader | FlushOptions::NewHeader(_)
+                );
+
             let block_id = match flush_options {
                 FlushOptions::CurrentHeader => {
                     if self.unconfirmed() {
@@ -1737,6 +2613,21 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
 
             trie_sql::drop_lock(&self.db, &bhh)?;
 
+            if mirror_to_trie_index {
+                let entry: TrieIndexEntry<T> = SqliteTrieIndex::new(&self.db)
+                    .get_entry(block_id)?
+                    .ok_or_else(|| {
+                        Error::CorruptionError(format!("No index entry for trie {}", block_id))
+                    })?;
+                self.data
+                    .pending_trie_index_updates
+                    .push(TrieIndexUpdate::Insert(entry));
+            }
+
+            if let Some(journal) = self.data.uncommitted_journal.as_mut() {
+                journal.clear()?;
+            }
+
             debug!("Flush: identifier of {} is {}", flush_options, block_id);
         }
 
@@ -1768,8 +2659,19 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
         self.clear_cached_ancestor_hashes_bytes();
         if !self.data.readonly {
             if let Some((ref bhh, _)) = self.data.uncommitted_writes.take() {
-                trie_sql::drop_lock(&self.db, bhh)
-                    .expect("Corruption: Failed to drop the extended trie lock");
+                if !self.data.ephemeral {
+                    trie_sql::drop_lock(&self.db, bhh)
+                        .expect("Corruption: Failed to drop the extended trie lock");
+                }
+            }
+            if let Some(journal) = self.data.uncommitted_journal.as_mut() {
+                if let Err(e) = journal.clear() {
+                    warn!(
+                        "Failed to remove uncommitted trie journal {}: {:?}",
+                        journal.path().display(),
+                        &e
+                    );
+                }
             }
             self.data.uncommitted_writes = None;
             self.data.clear_block_id();
@@ -1835,16 +2737,101 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
 
         let trie_buf = TrieRAM::new(bhh, size_hint, &self.data.cur_block);
 
-        // place a lock on this block, so we can't extend to it again
-        if !trie_sql::lock_bhh_for_extension(self.sqlite_tx(), bhh, false)? {
+        // place a lock on this block, so we can't extend to it again.  An ephemeral fork's trie
+        // is never persisted, so it doesn't need one.
+        if !self.data.ephemeral && !trie_sql::lock_bhh_for_extension(self.sqlite_tx(), bhh, false)?
+        {
             warn!("Block already extended: {}", &bhh);
             return Err(Error::ExistsError);
         }
 
+        let cur_block = self.data.cur_block.clone();
+        if let Some(journal) = self.data.uncommitted_journal.as_mut() {
+            journal.begin(bhh, &cur_block)?;
+        }
+
         self.switch_trie(bhh, UncommittedState::RW(trie_buf));
         Ok(())
     }
 
+    /// Extend the forest of Tries to include a new confirmed block, reinstating its trie from the
+    /// write-ahead journal.  This only succeeds if the journal holds an uncommitted trie for `bhh`
+    /// that extends the currently-open block -- i.e. the process stopped while `bhh` was being
+    /// built on top of it.  The reinstated trie contains every node that was written to it before
+    /// the journal was last appended to, and writes to it continue to be journaled.
+    /// Returns false, having changed nothing, if there is no such trie to recover.
+    pub fn recover_uncommitted_trie(&mut self, bhh: &T) -> Result<bool, Error> {
+        self.clear_cached_ancestor_hashes_bytes();
+        if self.data.readonly {
+            return Err(Error::ReadOnlyError);
+        }
+        if self.data.unconfirmed {
+            return Err(Error::UnconfirmedError);
+        }
+        if self.data.uncommitted_writes.is_some() {
+            return Err(Error::InProgressError);
+        }
+
+        let (journaled, valid_len) = match self.data.uncommitted_journal {
+            Some(ref journal) => match journal.read::<T>()? {
+                Some(recovered) => recovered,
+                None => {
+                    return Ok(false);
+                }
+            },
+            None => {
+                return Ok(false);
+            }
+        };
+
+        if &journaled.block_hash != bhh
+            || journaled.parent != self.data.cur_block
+            || journaled.nodes.len() == 0
+        {
+            debug!(
+                "Uncommitted trie journal holds {} off of {} ({} nodes); not recovering {} off of {}",
+                &journaled.block_hash,
+                &journaled.parent,
+                journaled.nodes.len(),
+                bhh,
+                &self.data.cur_block
+            );
+            return Ok(false);
+        }
+
+        if self.get_block_id_caching(bhh).is_ok() {
+            warn!("Block already exists: {}", &bhh);
+            return Err(Error::ExistsError);
+        }
+
+        if !trie_sql::lock_bhh_for_extension(self.sqlite_tx(), bhh, false)? {
+            warn!("Block already extended: {}", &bhh);
+            return Err(Error::ExistsError);
+        }
+
+        info!(
+            "Recovered uncommitted trie {} off of {} from journal ({} nodes)",
+            bhh,
+            &journaled.parent,
+            journaled.nodes.len()
+        );
+
+        let total_bytes: usize = journaled
+            .nodes
+            .iter()
+            .map(|(node, _)| get_node_byte_len(node))
+            .sum();
+        let mut trie_buf = TrieRAM::from_data(bhh.clone(), journaled.nodes, journaled.parent);
+        trie_buf.total_bytes = total_bytes;
+
+        if let Some(journal) = self.data.uncommitted_journal.as_mut() {
+            journal.resume(valid_len)?;
+        }
+
+        self.switch_trie(bhh, UncommittedState::RW(trie_buf));
+        Ok(true)
+    }
+
     /// Extend the forest of Tries to include a new unconfirmed block.
     /// If the unconfirmed block (bhh) already exists, then load up its trie as the uncommitted_writes
     /// trie.
@@ -1940,6 +2927,8 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
     }
 
     pub fn commit_tx(self) {
+        let trie_index = self.0.data.trie_index.clone();
+        let updates = mem::take(&mut self.0.data.pending_trie_index_updates);
         match self.0.db {
             SqliteConnection::Tx(tx) => {
                 tx.commit().expect("CORRUPTION: Failed to commit MARF");
@@ -1950,9 +2939,13 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
                 );
             }
         }
+        if let Some(trie_index) = trie_index {
+            apply_trie_index_updates(trie_index.as_ref(), updates);
+        }
     }
 
     pub fn rollback(self) {
+        self.0.data.pending_trie_index_updates.clear();
         match self.0.db {
             SqliteConnection::Tx(tx) => {
                 tx.rollback().expect("CORRUPTION: Failed to commit MARF");
@@ -1975,6 +2968,10 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         self.data.unconfirmed
     }
 
+    pub fn ephemeral(&self) -> bool {
+        self.data.ephemeral
+    }
+
     pub fn set_cached_ancestor_hashes_bytes(&mut self, bhh: &T, bytes: Vec<TrieHash>) {
         self.data.trie_ancestor_hash_bytes_cache = Some((bhh.clone(), bytes));
     }
@@ -2043,6 +3040,146 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         trie_sql::clear_lock_data(&conn)
     }
 
+    /// Check (and optionally repair) every confirmed trie.  See
+    /// `TrieFileStorage::verify_integrity()`.
+    fn inner_verify_integrity(&mut self, repair: bool) -> Result<TrieIntegrityReport<T>, Error> {
+        let (cur_block_hash, cur_block_id) = self.get_cur_block_and_id();
+        let blocks = trie_sql::read_confirmed_block_ids_and_hashes::<T>(&self.db)?;
+        let mut report = TrieIntegrityReport::new();
+
+        for (block_id, block_hash) in blocks.into_iter() {
+            self.clear_cached_ancestor_hashes_bytes();
+            self.open_block_known_id(&block_hash, block_id)?;
+
+            let root_ptr = self.root_trieptr();
+            if let Err(e) =
+                self.verify_trie_node(block_id, &block_hash, &root_ptr, repair, &mut report)
+            {
+                warn!("Failed to verify trie {}: {:?}", &block_hash, &e);
+            }
+            report.tries_checked += 1;
+        }
+
+        self.clear_cached_ancestor_hashes_bytes();
+        self.open_block_maybe_id(&cur_block_hash, cur_block_id)?;
+
+        debug!(
+            "Checked {} tries ({} nodes): {} faults, {} unrepaired",
+            report.tries_checked,
+            report.nodes_checked,
+            report.faults.len(),
+            report.num_unrepaired()
+        );
+        Ok(report)
+    }
+
+    /// Recursively recompute the hash of the node at `ptr` in the currently-open trie, and compare
+    /// it to the stored hash.  Returns the recomputed hash.  On failure, an `Unreadable` fault will
+    /// have been recorded for the node that could not be read.
+    fn verify_trie_node(
+        &mut self,
+        block_id: u32,
+        block_hash: &T,
+        ptr: &TriePtr,
+        repair: bool,
+        report: &mut TrieIntegrityReport<T>,
+    ) -> Result<TrieHash, Error> {
+        let unreadable = |e: Error, report: &mut TrieIntegrityReport<T>| {
+            report.faults.push(TrieIntegrityFault::Unreadable {
+                block_hash: block_hash.clone(),
+                ptr: ptr.clone(),
+                error: format!("{:?}", &e),
+            });
+            e
+        };
+
+        // go to disk, not the cache -- the cache may be masking what's actually stored
+        let (node, stored_hash) = self
+            .inner_read_persisted_nodetype(block_id, ptr, true)
+            .map_err(|e| unreadable(e, report))?;
+
+        let computed_hash = match node {
+            TrieNodeType::Leaf(ref leaf) => get_leaf_hash(leaf),
+            _ => {
+                let mut child_hashes = Vec::with_capacity(node.ptrs().len());
+                for child_ptr in node.ptrs().iter() {
+                    let child_hash = if child_ptr.id() == TrieNodeID::Empty as u8 {
+                        TrieHash::from_data(&[])
+                    } else if is_backptr(child_ptr.id()) {
+                        // same as write_children_hashes(): a node in an ancestor trie contributes
+                        // the hash of the block that contains it.
+                        let back_block_hash = self
+                            .get_block_hash_caching(child_ptr.back_block())
+                            .map_err(|e| unreadable(e, report))?
+                            .clone();
+                        TrieHash(back_block_hash.to_bytes())
+                    } else {
+                        self.verify_trie_node(block_id, block_hash, child_ptr, repair, report)?
+                    };
+                    child_hashes.push(child_hash);
+                }
+
+                let content_hash = get_nodetype_hash_bytes::<T, _>(&node, &child_hashes, self);
+                if *ptr == self.root_trieptr() {
+                    Trie::get_trie_root_hash(self, &content_hash)
+                        .map_err(|e| unreadable(e, report))?
+                } else {
+                    content_hash
+                }
+            }
+        };
+        report.nodes_checked += 1;
+
+        if computed_hash != stored_hash {
+            warn!(
+                "Trie {} node {:?}: stored hash {} but computed {}",
+                block_hash, ptr, &stored_hash, &computed_hash
+            );
+            let repaired = if repair {
+                match self.inner_write_persisted_node_hash(block_id, ptr, &computed_hash) {
+                    Ok(()) => {
+                        self.cache
+                            .store_node_hash(block_id, ptr.clone(), computed_hash.clone());
+                        true
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Failed to repair trie {} node {:?}: {:?}",
+                            block_hash, ptr, &e
+                        );
+                        false
+                    }
+                }
+            } else {
+                false
+            };
+            report.faults.push(TrieIntegrityFault::HashMismatch {
+                block_hash: block_hash.clone(),
+                ptr: ptr.clone(),
+                stored_hash,
+                computed_hash: computed_hash.clone(),
+                repaired,
+            });
+        }
+        Ok(computed_hash)
+    }
+
+    /// Overwrite a persisted node's hash.
+    fn inner_write_persisted_node_hash(
+        &mut self,
+        block_id: u32,
+        ptr: &TriePtr,
+        hash: &TrieHash,
+    ) -> Result<(), Error> {
+        if self.data.readonly {
+            return Err(Error::ReadOnlyError);
+        }
+        match self.blobs.as_mut() {
+            Some(blobs) => blobs.write_node_hash_bytes(&self.db, block_id, ptr, hash),
+            None => trie_sql::write_node_hash_bytes(&self.db, block_id, ptr, hash),
+        }
+    }
+
     /// Read the Trie root node's hash from the block table.
     #[cfg(test)]
     pub fn read_block_root_hash(&mut self, bhh: &T) -> Result<TrieHash, Error> {
@@ -2108,6 +3245,11 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
     /// Is the given block in the marf_data DB table, and is it part of the block history (i.e. it's not mined and
     /// its not unconfirmed)?
     pub fn has_confirmed_block(&self, bhh: &T) -> Result<bool, Error> {
+        if let Some(trie_index) = self.data.trie_index.as_ref() {
+            if trie_index.get_confirmed_block_identifier(bhh)?.is_some() {
+                return Ok(true);
+            }
+        }
         match trie_sql::get_confirmed_block_identifier(&self.db, bhh) {
             Ok(Some(_)) => Ok(true),
             Ok(None) => Ok(false),
@@ -2286,6 +3428,186 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         (self.data.cur_block.clone(), self.data.cur_block_id.clone())
     }
 
+    /// Iterate over the (path, value) pairs in the MARF as of `block_hash`, restricted to paths
+    /// that start with `prefix` (which may be empty).  See `TrieKeyIterator`.
+    pub fn iter_keys_at<'b>(
+        &'b mut self,
+        block_hash: &T,
+        prefix: &[u8],
+    ) -> Result<TrieKeyIterator<'b, 'a, T>, Error> {
+        let restore = self.get_cur_block_and_id();
+        self.open_block(block_hash)?;
+        let (block_hash, block_id) = self.get_cur_block_and_id();
+        let root = TrieKeyIteratorEntry {
+            block_hash,
+            block_id,
+            ptr: self.root_trieptr(),
+            path_prefix: vec![],
+        };
+        Ok(TrieKeyIterator {
+            storage: self,
+            prefix: prefix.to_vec(),
+            stack: vec![root],
+            restore,
+        })
+    }
+
+    /// Get the entries for the children of `node`, which is stored at `entry` and whose full path
+    /// is `path`.  Back-pointers are resolved to the ancestor tries that hold the children.
+    fn trie_child_entries(
+        &mut self,
+        entry: &TrieKeyIteratorEntry<T>,
+        node: &TrieNodeType,
+        path: &[u8],
+    ) -> Result<Vec<TrieKeyIteratorEntry<T>>, Error> {
+        let mut children = vec![];
+        for child in node.ptrs().iter() {
+            if child.id() == TrieNodeID::Empty as u8 {
+                continue;
+            }
+            let mut child_path = path.to_vec();
+            child_path.push(child.chr());
+            let child_entry = if is_backptr(child.id()) {
+                let back_block_hash = self.get_block_from_local_id(child.back_block())?.clone();
+                TrieKeyIteratorEntry {
+                    block_hash: back_block_hash,
+                    block_id: Some(child.back_block()),
+                    ptr: child.from_backptr(),
+                    path_prefix: child_path,
+                }
+            } else {
+                TrieKeyIteratorEntry {
+                    block_hash: entry.block_hash.clone(),
+                    block_id: entry.block_id,
+                    ptr: child.clone(),
+                    path_prefix: child_path,
+                }
+            };
+            children.push(child_entry);
+        }
+        Ok(children)
+    }
+
+    /// Get every (path, value) pair in the subtree rooted at `entry`
+    fn trie_subtree_leaves(
+        &mut self,
+        entry: TrieKeyIteratorEntry<T>,
+    ) -> Result<BTreeMap<TriePath, MARFValue>, Error> {
+        let restore = self.get_cur_block_and_id();
+        let iter = TrieKeyIterator {
+            storage: self,
+            prefix: vec![],
+            stack: vec![entry],
+            restore,
+        };
+        iter.collect()
+    }
+
+    /// Get the set of paths (i.e. key hashes) whose values differ between the MARF as of
+    /// `block_a` and the MARF as of `block_b`.  This includes paths that are only present in one
+    /// of them.  Both blocks must be committed.
+    ///
+    /// The two tries are walked together, and any pair of subtrees with the same node hash is
+    /// skipped, so the cost is proportional to the size of the difference rather than the size of
+    /// the MARF.  The storage connection's open block is restored afterwards.
+    pub fn diff(&mut self, block_a: &T, block_b: &T) -> Result<BTreeSet<TriePath>, Error> {
+        let (restore_block, restore_id) = self.get_cur_block_and_id();
+        let res = self.inner_diff(block_a, block_b);
+        self.open_block_maybe_id(&restore_block, restore_id)?;
+        res
+    }
+
+    fn inner_diff(&mut self, block_a: &T, block_b: &T) -> Result<BTreeSet<TriePath>, Error> {
+        self.open_block(block_a)?;
+        let (block_hash, block_id) = self.get_cur_block_and_id();
+        let root_a = TrieKeyIteratorEntry {
+            block_hash,
+            block_id,
+            ptr: self.root_trieptr(),
+            path_prefix: vec![],
+        };
+
+        self.open_block(block_b)?;
+        let (block_hash, block_id) = self.get_cur_block_and_id();
+        let root_b = TrieKeyIteratorEntry {
+            block_hash,
+            block_id,
+            ptr: self.root_trieptr(),
+            path_prefix: vec![],
+        };
+
+        let mut differences = BTreeSet::new();
+
+        // pairs of nodes at the same position in each trie, and whether or not they are the roots
+        let mut pending = vec![(Some(root_a), Some(root_b), true)];
+        while let Some((entry_a, entry_b, is_root)) = pending.pop() {
+            let (entry_a, entry_b) = match (entry_a, entry_b) {
+                (Some(entry_a), Some(entry_b)) => (entry_a, entry_b),
+                (Some(entry), None) | (None, Some(entry)) => {
+                    // everything under this node is only in one trie
+                    differences.extend(self.trie_subtree_leaves(entry)?.into_keys());
+                    continue;
+                }
+                (None, None) => {
+                    continue;
+                }
+            };
+
+            if entry_a.block_hash == entry_b.block_hash && entry_a.ptr == entry_b.ptr {
+                // literally the same node
+                continue;
+            }
+
+            self.open_block_maybe_id(&entry_a.block_hash, entry_a.block_id)?;
+            let (node_a, hash_a) = self.read_nodetype(&entry_a.ptr)?;
+            self.open_block_maybe_id(&entry_b.block_hash, entry_b.block_id)?;
+            let (node_b, hash_b) = self.read_nodetype(&entry_b.ptr)?;
+
+            // a root's hash also covers its ancestors' root hashes, so only non-root nodes with
+            // the same hash are known to have the same contents.
+            if !is_root && hash_a == hash_b {
+                continue;
+            }
+
+            let mut path_a = entry_a.path_prefix.clone();
+            path_a.extend_from_slice(node_a.path_bytes());
+            let mut path_b = entry_b.path_prefix.clone();
+            path_b.extend_from_slice(node_b.path_bytes());
+
+            if node_a.is_leaf() || node_b.is_leaf() || path_a != path_b {
+                // the tries are shaped differently here, so just compare the leaves
+                let leaves_a = self.trie_subtree_leaves(entry_a)?;
+                let leaves_b = self.trie_subtree_leaves(entry_b)?;
+                for (path, value) in leaves_a.iter() {
+                    if leaves_b.get(path) != Some(value) {
+                        differences.insert(path.clone());
+                    }
+                }
+                for path in leaves_b.keys() {
+                    if !leaves_a.contains_key(path) {
+                        differences.insert(path.clone());
+                    }
+                }
+                continue;
+            }
+
+            // pair up the children by their next path byte
+            let mut children: BTreeMap<u8, (Option<_>, Option<_>)> = BTreeMap::new();
+            for child in self.trie_child_entries(&entry_a, &node_a, &path_a)? {
+                let chr = child.ptr.chr();
+                children.entry(chr).or_default().0 = Some(child);
+            }
+            for child in self.trie_child_entries(&entry_b, &node_b, &path_b)? {
+                let chr = child.ptr.chr();
+                children.entry(chr).or_default().1 = Some(child);
+            }
+            for (_, (child_a, child_b)) in children.into_iter() {
+                pending.push((child_a, child_b, false));
+            }
+        }
+        Ok(differences)
+    }
+
     /// Get the block hash of a given block ID (i.e. row ID)
     pub fn get_block_from_local_id(&mut self, local_id: u32) -> Result<&T, Error> {
         let res = self.get_block_hash_caching(local_id);
@@ -2381,7 +3703,7 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
                 error!("Failed to get cur block as hash reader");
                 Error::NotFoundError
             })?;
-            let mut cursor = TrieFileNodeHashReader::new(&self.db, blobs, block_id);
+            let mut cursor = TrieFileMmapNodeHashReader::new(&self.db, blobs, block_id);
             let res = TrieStorageConnection::<T>::inner_write_children_hashes(
                 &mut cursor,
                 &mut map,
@@ -2472,6 +3794,28 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         Ok(())
     }
 
+    /// If there is an external trie block index, cache the given confirmed trie's offset in the
+    /// external blobs file from it, so that the trie can be read even if only that index has it.
+    fn load_trie_offset(&mut self, block_id: u32) -> Result<(), Error> {
+        let (trie_index, blobs) = match (self.data.trie_index.as_ref(), self.blobs.as_mut()) {
+            (Some(trie_index), Some(blobs)) => (trie_index, blobs),
+            _ => {
+                return Ok(());
+            }
+        };
+        if blobs.has_trie_offset(block_id) {
+            return Ok(());
+        }
+        match trie_index.get_external_trie_offset_length(block_id) {
+            Ok((offset, _length)) => {
+                blobs.set_trie_offset(block_id, offset);
+                Ok(())
+            }
+            Err(Error::NotFoundError) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
     /// read a persisted node's hash
     fn inner_read_persisted_node_hash(
         &mut self,
@@ -2486,8 +3830,9 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
             );
             return trie_sql::get_node_hash_bytes(&self.db, block_id, ptr);
         }
+        self.load_trie_offset(block_id)?;
         let node_hash = match self.blobs.as_mut() {
-            Some(blobs) => blobs.get_node_hash_bytes(&self.db, block_id, ptr),
+            Some(blobs) => blobs.get_node_hash_bytes_mapped(&self.db, block_id, ptr),
             None => trie_sql::get_node_hash_bytes(&self.db, block_id, ptr),
         }?;
         Ok(node_hash)
@@ -2563,6 +3908,7 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
                     .map(|node| (node, TrieHash([0u8; TRIEHASH_ENCODED_SIZE])));
             }
         }
+        self.load_trie_offset(block_id)?;
         let (node_inst, node_hash) = match self.blobs.as_mut() {
             Some(blobs) => {
                 if read_hash {
@@ -2691,7 +4037,20 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         if let Some((ref uncommitted_bhh, ref mut uncommitted_trie)) = self.data.uncommitted_writes
         {
             if &self.data.cur_block == uncommitted_bhh {
-                return uncommitted_trie.write_nodetype(disk_ptr, node, hash);
+                uncommitted_trie.write_nodetype(disk_ptr, node, hash.clone())?;
+                if let Some(journal) = self.data.uncommitted_journal.as_mut() {
+                    journal.append(disk_ptr, node, &hash)?;
+
+                    let trie_ram = uncommitted_trie.trie_ram_ref();
+                    if journal.needs_compaction(trie_ram.total_bytes as u64) {
+                        journal.compact(
+                            &trie_ram.block_header,
+                            &trie_ram.parent,
+                            &trie_ram.data,
+                        )?;
+                    }
+                }
+                return Ok(());
             }
         }
 
@@ -2747,6 +4106,11 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         self.bench.reset();
     }
 
+    /// Get the node cache's statistics, if its strategy tracks them
+    pub fn get_cache_stats(&self) -> Option<TrieCacheStats> {
+        self.cache.stats()
+    }
+
     #[cfg(test)]
     pub fn transient_data(&self) -> &TrieStorageTransientData<T> {
         &self.data
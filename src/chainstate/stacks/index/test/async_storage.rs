@@ -0,0 +1,129 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs;
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+
+use crate::chainstate::stacks::index::async_storage::*;
+use crate::chainstate::stacks::index::marf::*;
+use crate::chainstate::stacks::index::storage::*;
+use crate::chainstate::stacks::index::*;
+use crate::types::chainstate::StacksBlockId;
+
+struct ThreadWaker(thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Minimal executor: poll `fut` on this thread until it's done
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = Box::pin(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[test]
+fn async_trie_storage_get_insert_proof() {
+    let marf_path = "/tmp/async_trie_storage_get_insert_proof";
+    if fs::metadata(marf_path).is_ok() {
+        fs::remove_file(marf_path).unwrap();
+    }
+    let f = TrieFileStorage::<StacksBlockId>::open(marf_path, MARFOpenOpts::default()).unwrap();
+    let mut marf = MARF::<StacksBlockId>::from_storage(f);
+
+    let block_1 = StacksBlockId([0x11; 32]);
+    let block_2 = StacksBlockId([0x22; 32]);
+
+    marf.begin(&StacksBlockId::sentinel(), &block_1).unwrap();
+    for i in 0..64u32 {
+        marf.insert(&format!("key-{}", i), MARFValue::from(i))
+            .unwrap();
+    }
+    marf.commit().unwrap();
+
+    let storage = AsyncTrieStorage::new(marf, 4).unwrap();
+
+    assert_eq!(
+        block_on(storage.get(&block_1, "key-1")).unwrap(),
+        Some(MARFValue::from(1))
+    );
+    assert_eq!(block_on(storage.get(&block_1, "nope")).unwrap(), None);
+    assert!(block_on(storage.get(&block_2, "key-1")).is_err());
+
+    let (value, proof) = block_on(storage.proof(&block_1, "key-2")).unwrap().unwrap();
+    assert_eq!(value, MARFValue::from(2));
+    assert!(proof.0.len() > 0);
+    assert!(block_on(storage.proof(&block_1, "nope")).unwrap().is_none());
+
+    // submit many reads before waiting on any of them
+    let reads: Vec<_> = (0..64u32)
+        .map(|i| {
+            let block_1 = block_1.clone();
+            storage.read(move |marf| marf.get(&block_1, &format!("key-{}", i)))
+        })
+        .collect();
+    for (i, read) in reads.into_iter().enumerate() {
+        assert_eq!(block_on(read).unwrap(), Some(MARFValue::from(i as u32)));
+    }
+
+    // can't insert until a trie is begun
+    match block_on(storage.insert("key-1", MARFValue::from(100))) {
+        Err(Error::WriteNotBegunError) => {}
+        x => panic!("Expected WriteNotBegunError, got {:?}", x),
+    }
+
+    // the readers can't write
+    match block_on(storage.read(|marf| marf.insert("key-1", MARFValue::from(100)))) {
+        Err(Error::ReadOnlyError) => {}
+        x => panic!("Expected ReadOnlyError, got {:?}", x),
+    }
+
+    let (b1, b2) = (block_1.clone(), block_2.clone());
+    block_on(storage.write(move |marf| marf.begin(&b1, &b2))).unwrap();
+
+    // writes run in the order they're submitted
+    let inserts: Vec<_> = (0..8u32)
+        .map(|i| storage.insert("key-1", MARFValue::from(100 + i)))
+        .collect();
+    for insert in inserts.into_iter() {
+        block_on(insert).unwrap();
+    }
+    block_on(storage.write(|marf| marf.commit())).unwrap();
+
+    assert_eq!(
+        block_on(storage.get(&block_2, "key-1")).unwrap(),
+        Some(MARFValue::from(107))
+    );
+    assert_eq!(
+        block_on(storage.get(&block_2, "key-2")).unwrap(),
+        Some(MARFValue::from(2))
+    );
+    assert_eq!(
+        block_on(storage.get(&block_1, "key-1")).unwrap(),
+        Some(MARFValue::from(1))
+    );
+}
@@ -162,3 +162,128 @@ fn test_migrate_existing_trie_blobs() {
         }
     }
 }
+
+#[cfg(feature = "marf_rocksdb")]
+#[test]
+fn test_load_store_trie_blob_rocksdb() {
+    use std::io::Read;
+
+    let mut db = setup_db("test_load_store_trie_blob_rocksdb");
+    let rocksdb_path = format!("{}.rocksdb", db_path("test_load_store_trie_blob_rocksdb"));
+    if fs::metadata(&rocksdb_path).is_ok() {
+        fs::remove_dir_all(&rocksdb_path).unwrap();
+    }
+    let mut blobs =
+        TrieFile::from_db_path_rocksdb(&db_path("test_load_store_trie_blob_rocksdb"), false)
+            .unwrap();
+    trie_sql::migrate_tables_if_needed::<BlockHeaderHash>(&mut db).unwrap();
+    assert_eq!(blobs.get_path(), rocksdb_path);
+
+    blobs
+        .store_trie_blob::<BlockHeaderHash>(&db, &BlockHeaderHash([0x01; 32]), &[1, 2, 3, 4, 5])
+        .unwrap();
+
+    // a blob appended but never recorded in the DB gets overwritten by the next append
+    blobs.append_trie_blob(&db, &[0xff; 16]).unwrap();
+    blobs
+        .store_trie_blob::<BlockHeaderHash>(
+            &db,
+            &BlockHeaderHash([0x02; 32]),
+            &[10, 20, 30, 40, 50],
+        )
+        .unwrap();
+    assert_eq!(blobs.seek(SeekFrom::End(0)).unwrap(), 10);
+
+    let block_id = trie_sql::get_block_identifier(&db, &BlockHeaderHash([0x01; 32])).unwrap();
+    assert_eq!(blobs.get_trie_offset(&db, block_id).unwrap(), 0);
+    let buf = blobs.read_trie_blob(&db, block_id).unwrap();
+    assert_eq!(buf, vec![1, 2, 3, 4, 5]);
+
+    let block_id = trie_sql::get_block_identifier(&db, &BlockHeaderHash([0x02; 32])).unwrap();
+    assert_eq!(blobs.get_trie_offset(&db, block_id).unwrap(), 5);
+    let buf = blobs.read_trie_blob(&db, block_id).unwrap();
+    assert_eq!(buf, vec![10, 20, 30, 40, 50]);
+
+    // reads can span stored values
+    blobs.seek(SeekFrom::Start(3)).unwrap();
+    let mut buf = [0u8; 4];
+    blobs.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, [4, 5, 10, 20]);
+
+    // the store is found again by path
+    drop(blobs);
+    let mut blobs =
+        TrieFile::from_db_path(&db_path("test_load_store_trie_blob_rocksdb"), true).unwrap();
+    assert_eq!(blobs.get_path(), rocksdb_path);
+    let buf = blobs.read_trie_blob(&db, block_id).unwrap();
+    assert_eq!(buf, vec![10, 20, 30, 40, 50]);
+    assert!(blobs.append_trie_blob(&db, &[1]).is_err());
+}
+
+#[test]
+fn test_mmap_node_hash_reader() {
+    let mut db = setup_db("test_mmap_node_hash_reader");
+    let blobs_path = format!("{}.blobs", db_path("test_mmap_node_hash_reader"));
+    if fs::metadata(&blobs_path).is_ok() {
+        fs::remove_file(&blobs_path).unwrap();
+    }
+    let mut blobs = TrieFile::from_db_path(&db_path("test_mmap_node_hash_reader"), false).unwrap();
+    trie_sql::migrate_tables_if_needed::<BlockHeaderHash>(&mut db).unwrap();
+
+    let blob_1: Vec<u8> = (0..100).collect();
+    let blob_2: Vec<u8> = (100..200).collect();
+
+    blobs
+        .store_trie_blob::<BlockHeaderHash>(&db, &BlockHeaderHash([0x01; 32]), &blob_1)
+        .unwrap();
+    let block_id_1 = trie_sql::get_block_identifier(&db, &BlockHeaderHash([0x01; 32])).unwrap();
+
+    let read_both = |blobs: &mut TrieFile, block_id: u32, offset: u32| {
+        let ptr = TriePtr::new(TrieNodeID::Node4 as u8, 0, offset);
+        let mut expected = vec![];
+        TrieFileNodeHashReader::new(&db, blobs, block_id)
+            .read_node_hash_bytes(&ptr, &mut expected)
+            .unwrap();
+        let mut mapped = vec![];
+        TrieFileMmapNodeHashReader::new(&db, blobs, block_id)
+            .read_node_hash_bytes(&ptr, &mut mapped)
+            .unwrap();
+        assert_eq!(expected, mapped);
+        mapped
+    };
+
+    assert_eq!(read_both(&mut blobs, block_id_1, 0), blob_1[0..32].to_vec());
+    assert_eq!(
+        read_both(&mut blobs, block_id_1, 68),
+        blob_1[68..100].to_vec()
+    );
+
+    // appending a trie after the file was mapped is picked up
+    blobs
+        .store_trie_blob::<BlockHeaderHash>(&db, &BlockHeaderHash([0x02; 32]), &blob_2)
+        .unwrap();
+    let block_id_2 = trie_sql::get_block_identifier(&db, &BlockHeaderHash([0x02; 32])).unwrap();
+    assert_eq!(
+        read_both(&mut blobs, block_id_2, 10),
+        blob_2[10..42].to_vec()
+    );
+
+    // and so is overwriting a hash in place
+    let hash = TrieHash([0xff; 32]);
+    let ptr = TriePtr::new(TrieNodeID::Node4 as u8, 0, 4);
+    blobs
+        .write_node_hash_bytes(&db, block_id_1, &ptr, &hash)
+        .unwrap();
+    assert_eq!(
+        blobs
+            .get_node_hash_bytes_mapped(&db, block_id_1, &ptr)
+            .unwrap(),
+        hash
+    );
+
+    // reading past the end of the file fails
+    let ptr = TriePtr::new(TrieNodeID::Node4 as u8, 0, 90);
+    assert!(blobs
+        .get_node_hash_bytes_mapped(&db, block_id_2, &ptr)
+        .is_err());
+}
@@ -0,0 +1,130 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2022 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs;
+use std::fs::OpenOptions;
+
+use crate::chainstate::stacks::index::journal::*;
+use crate::chainstate::stacks::index::node::*;
+use crate::chainstate::stacks::index::*;
+use crate::types::chainstate::StacksBlockId;
+use stacks_common::types::chainstate::TrieHash;
+
+fn journal_path(test_name: &str) -> String {
+    let path = format!("/tmp/{}", test_name);
+    let _ = fs::remove_file(&TrieJournal::path_for(&path));
+    path
+}
+
+fn make_nodes(count: u8) -> Vec<(TrieNodeType, TrieHash)> {
+    let mut nodes = vec![(
+        TrieNodeType::Node256(Box::new(TrieNode256::new(&vec![]))),
+        TrieHash([0xff; 32]),
+    )];
+    for i in 1..count {
+        nodes.push((
+            TrieNodeType::Leaf(TrieLeaf::new(&vec![i; 4], &vec![i; 40])),
+            TrieHash([i; 32]),
+        ));
+    }
+    nodes
+}
+
+#[test]
+fn test_journal_read_discards_torn_record() {
+    let path = journal_path("test_journal_read_discards_torn_record");
+    let block = StacksBlockId([0x01; 32]);
+    let parent = StacksBlockId([0x02; 32]);
+    let nodes = make_nodes(4);
+
+    let mut journal = TrieJournal::new(&path);
+    journal.begin(&block, &parent).unwrap();
+    for (slot, (node, hash)) in nodes.iter().enumerate() {
+        journal.append(slot as u32, node, hash).unwrap();
+    }
+
+    // a later write to a slot supersedes the earlier one
+    let rewritten = (
+        TrieNodeType::Leaf(TrieLeaf::new(&vec![0x11; 4], &vec![0x11; 40])),
+        TrieHash([0x11; 32]),
+    );
+    journal.append(1, &rewritten.0, &rewritten.1).unwrap();
+    let full_len = journal.len();
+
+    let (recovered, valid_len) = journal.read::<StacksBlockId>().unwrap().unwrap();
+    assert_eq!(recovered.block_hash, block);
+    assert_eq!(recovered.parent, parent);
+    assert_eq!(valid_len, full_len);
+    assert_eq!(recovered.nodes.len(), 4);
+    assert_eq!(recovered.nodes[0], nodes[0]);
+    assert_eq!(recovered.nodes[1], rewritten);
+    assert_eq!(recovered.nodes[3], nodes[3]);
+
+    // cut the last record short, as if the process stopped while writing it
+    let file = OpenOptions::new().write(true).open(journal.path()).unwrap();
+    file.set_len(full_len - 3).unwrap();
+
+    let (recovered, valid_len) = journal.read::<StacksBlockId>().unwrap().unwrap();
+    assert!(valid_len < full_len - 3);
+    assert_eq!(recovered.nodes.len(), 4);
+    assert_eq!(recovered.nodes[1], nodes[1]);
+
+    // resuming drops the torn record and appends after the valid prefix
+    journal.resume(valid_len).unwrap();
+    journal.append(1, &rewritten.0, &rewritten.1).unwrap();
+    let (recovered, _) = journal.read::<StacksBlockId>().unwrap().unwrap();
+    assert_eq!(recovered.nodes[1], rewritten);
+
+    journal.clear().unwrap();
+    assert!(fs::metadata(journal.path()).is_err());
+    assert!(journal.read::<StacksBlockId>().unwrap().is_none());
+}
+
+#[test]
+fn test_journal_compact() {
+    let path = journal_path("test_journal_compact");
+    let block = StacksBlockId([0x01; 32]);
+    let parent = StacksBlockId([0x02; 32]);
+    let nodes = make_nodes(8);
+
+    let mut journal = TrieJournal::new(&path);
+    journal.begin(&block, &parent).unwrap();
+    for _ in 0..100 {
+        for (slot, (node, hash)) in nodes.iter().enumerate() {
+            journal.append(slot as u32, node, hash).unwrap();
+        }
+    }
+    let uncompacted_len = journal.len();
+
+    journal.compact(&block, &parent, &nodes).unwrap();
+    assert!(journal.len() * 50 < uncompacted_len);
+    assert_eq!(fs::metadata(journal.path()).unwrap().len(), journal.len());
+
+    // still appendable after compaction
+    let extra = (
+        TrieNodeType::Leaf(TrieLeaf::new(&vec![0x22; 4], &vec![0x22; 40])),
+        TrieHash([0x22; 32]),
+    );
+    journal.append(8, &extra.0, &extra.1).unwrap();
+
+    let (recovered, valid_len) = journal.read::<StacksBlockId>().unwrap().unwrap();
+    assert_eq!(valid_len, journal.len());
+    assert_eq!(recovered.nodes.len(), 9);
+    assert_eq!(&recovered.nodes[0..8], &nodes[..]);
+    assert_eq!(recovered.nodes[8], extra);
+
+    journal.clear().unwrap();
+}
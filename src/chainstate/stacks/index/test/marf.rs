@@ -2310,3 +2310,351 @@ fn test_marf_unconfirmed() {
         assert!(false);
     }
 }
+
+#[test]
+fn marf_insert_batch_matches_single_inserts() {
+    // keys with shared prefixes, plus a repeated key whose last value must win
+    let mut keys: Vec<String> = (0..256).map(|i| format!("key-{}", i)).collect();
+    keys.push("key-7".to_string());
+    let values: Vec<MARFValue> = (0..keys.len()).map(|i| MARFValue::from(i as u32)).collect();
+
+    let block_1 = StacksBlockId([1u8; 32]);
+    let block_2 = StacksBlockId([2u8; 32]);
+
+    let mut last_root_hashes = None;
+    for marf_opts in MARFOpenOpts::all().into_iter() {
+        for batch in [false, true] {
+            test_debug!("With {:?}, batch = {}", &marf_opts, batch);
+            let f = TrieFileStorage::new_memory(marf_opts.clone()).unwrap();
+            let mut marf = MARF::from_storage(f);
+
+            for (block, range) in [(&block_1, 0..100), (&block_2, 100..keys.len())] {
+                let parent = if block == &block_1 {
+                    StacksBlockId::sentinel()
+                } else {
+                    block_1.clone()
+                };
+                marf.begin(&parent, block).unwrap();
+                if batch {
+                    marf.insert_batch(&keys[range.clone()].to_vec(), values[range].to_vec())
+                        .unwrap();
+                } else {
+                    for i in range {
+                        marf.insert(&keys[i], values[i].clone()).unwrap();
+                    }
+                }
+                marf.commit().unwrap();
+            }
+
+            assert_eq!(
+                marf.get(&block_2, "key-7").unwrap(),
+                Some(values[keys.len() - 1].clone())
+            );
+            assert_eq!(
+                marf.get(&block_2, "key-200").unwrap(),
+                Some(values[200].clone())
+            );
+            assert_eq!(
+                marf.get(&block_1, "key-50").unwrap(),
+                Some(values[50].clone())
+            );
+            assert_eq!(marf.get(&block_1, "key-200").unwrap(), None);
+
+            let next_root_hashes = marf
+                .borrow_storage_backend()
+                .read_root_to_block_table()
+                .unwrap();
+            if let Some(root_hashes) = last_root_hashes.take() {
+                assert_eq!(root_hashes, next_root_hashes);
+            }
+            last_root_hashes = Some(next_root_hashes);
+        }
+    }
+}
+
+#[test]
+fn test_marf_reopen_readonly_at_snapshot() {
+    let marf_path = "/tmp/test_marf_reopen_readonly_at_snapshot";
+    if let Ok(_) = std::fs::metadata(marf_path) {
+        std::fs::remove_file(marf_path).unwrap();
+    }
+    let f = TrieFileStorage::<StacksBlockId>::open(marf_path, MARFOpenOpts::default()).unwrap();
+    let mut marf = MARF::<StacksBlockId>::from_storage(f);
+
+    let block_1 = StacksBlockId([0x11; 32]);
+    let block_2 = StacksBlockId([0x22; 32]);
+
+    marf.begin(&StacksBlockId::sentinel(), &block_1).unwrap();
+    marf.insert("foo", MARFValue::from(1)).unwrap();
+    marf.insert("bar", MARFValue::from(1)).unwrap();
+    marf.commit().unwrap();
+
+    // can't snapshot a block that isn't committed yet
+    assert!(marf.reopen_readonly_at(&block_2).is_err());
+
+    let snapshots: Vec<_> = (0..4)
+        .map(|_| marf.reopen_readonly_at(&block_1).unwrap())
+        .collect();
+
+    // the writer keeps going while the snapshots are open
+    marf.begin(&block_1, &block_2).unwrap();
+    marf.insert("foo", MARFValue::from(2)).unwrap();
+    marf.insert("baz", MARFValue::from(2)).unwrap();
+    marf.commit().unwrap();
+
+    assert_eq!(marf.get(&block_2, "foo").unwrap(), Some(MARFValue::from(2)));
+
+    let readers: Vec<_> = snapshots
+        .into_iter()
+        .map(|mut snapshot| {
+            let block_1 = block_1.clone();
+            let block_2 = block_2.clone();
+            std::thread::spawn(move || {
+                for _ in 0..10 {
+                    assert_eq!(
+                        snapshot.get(&block_1, "foo").unwrap(),
+                        Some(MARFValue::from(1))
+                    );
+                    assert_eq!(
+                        snapshot.get(&block_1, "bar").unwrap(),
+                        Some(MARFValue::from(1))
+                    );
+                    assert_eq!(snapshot.get(&block_1, "baz").unwrap(), None);
+
+                    // the block committed after the snapshot was taken isn't visible
+                    assert!(snapshot.get(&block_2, "foo").is_err());
+                }
+            })
+        })
+        .collect();
+
+    for reader in readers {
+        reader.join().unwrap();
+    }
+
+    // a new snapshot sees the new block
+    let mut snapshot = marf.reopen_readonly_at(&block_2).unwrap();
+    assert_eq!(
+        snapshot.get(&block_2, "foo").unwrap(),
+        Some(MARFValue::from(2))
+    );
+    assert_eq!(
+        snapshot.get(&block_1, "foo").unwrap(),
+        Some(MARFValue::from(1))
+    );
+}
+
+#[test]
+fn marf_recover_uncommitted_trie_from_journal() {
+    let path = "/tmp/marf_recover_uncommitted_trie_from_journal";
+    let reference_path = "/tmp/marf_recover_uncommitted_trie_from_journal_reference";
+    for p in [path, reference_path].iter() {
+        if fs::metadata(p).is_ok() {
+            fs::remove_file(p).unwrap();
+        }
+    }
+
+    let mut marf_opts = MARFOpenOpts::default();
+    marf_opts.uncommitted_journal = true;
+    let journal_path = format!("{}.journal", path);
+
+    let block_1 = StacksBlockId([0x01; 32]);
+    let block_2 = StacksBlockId([0x02; 32]);
+    let block_3 = StacksBlockId([0x03; 32]);
+
+    let keys_before = vec!["foo".to_string(), "bar".to_string()];
+    let keys_after = vec!["baz".to_string()];
+
+    {
+        let mut marf = MARF::<StacksBlockId>::from_path(path, marf_opts.clone()).unwrap();
+
+        let mut tx = marf.begin_tx().unwrap();
+        tx.begin(&StacksBlockId::sentinel(), &block_1).unwrap();
+        tx.insert_batch(&keys_before, vec![MARFValue::from(1); 2])
+            .unwrap();
+        tx.commit().unwrap();
+
+        // the journal is dropped once the trie is committed
+        assert!(fs::metadata(&journal_path).is_err());
+
+        let mut tx = marf.begin_tx().unwrap();
+        tx.begin(&block_1, &block_2).unwrap();
+        tx.insert_batch(&keys_before, vec![MARFValue::from(2); 2])
+            .unwrap();
+
+        // the process stops before the trie is committed
+    }
+
+    assert!(fs::metadata(&journal_path).is_ok());
+
+    {
+        let mut marf = MARF::<StacksBlockId>::from_path(path, marf_opts.clone()).unwrap();
+
+        // the journal holds block_2's trie, not block_3's
+        let mut tx = marf.begin_tx().unwrap();
+        assert!(!tx.begin_recovered(&block_1, &block_3).unwrap());
+        tx.drop_current();
+        assert!(fs::metadata(&journal_path).is_err());
+    }
+
+    // try again, and this time recover
+    {
+        let mut marf = MARF::<StacksBlockId>::from_path(path, marf_opts.clone()).unwrap();
+        let mut tx = marf.begin_tx().unwrap();
+        tx.begin(&block_1, &block_2).unwrap();
+        tx.insert_batch(&keys_before, vec![MARFValue::from(2); 2])
+            .unwrap();
+    }
+    {
+        let mut marf = MARF::<StacksBlockId>::from_path(path, marf_opts.clone()).unwrap();
+
+        let mut tx = marf.begin_tx().unwrap();
+        assert!(tx.begin_recovered(&block_1, &block_2).unwrap());
+
+        // only the keys that were not yet inserted need to be inserted
+        tx.insert_batch(&keys_after, vec![MARFValue::from(2)])
+            .unwrap();
+        tx.commit().unwrap();
+        assert!(fs::metadata(&journal_path).is_err());
+
+        for key in keys_before.iter().chain(keys_after.iter()) {
+            assert_eq!(marf.get(&block_2, key).unwrap(), Some(MARFValue::from(2)));
+        }
+        assert_eq!(marf.get(&block_1, "foo").unwrap(), Some(MARFValue::from(1)));
+        assert_eq!(marf.get(&block_1, "baz").unwrap(), None);
+
+        // same trie as if the process had never stopped
+        let mut reference =
+            MARF::<StacksBlockId>::from_path(reference_path, MARFOpenOpts::default()).unwrap();
+        let mut tx = reference.begin_tx().unwrap();
+        tx.begin(&StacksBlockId::sentinel(), &block_1).unwrap();
+        tx.insert_batch(&keys_before, vec![MARFValue::from(1); 2])
+            .unwrap();
+        tx.commit().unwrap();
+
+        let mut tx = reference.begin_tx().unwrap();
+        tx.begin(&block_1, &block_2).unwrap();
+        tx.insert_batch(&keys_before, vec![MARFValue::from(2); 2])
+            .unwrap();
+        tx.insert_batch(&keys_after, vec![MARFValue::from(2)])
+            .unwrap();
+        tx.commit().unwrap();
+
+        assert_eq!(
+            marf.get_root_hash_at(&block_2).unwrap(),
+            reference.get_root_hash_at(&block_2).unwrap()
+        );
+    }
+}
+
+fn marf_fork_ephemeral_at(external_blobs: bool) {
+    let marf_path = format!(
+        "/tmp/test_marf_fork_ephemeral_at_{}",
+        if external_blobs { "blobs" } else { "sqlite" }
+    );
+    if fs::metadata(&marf_path).is_ok() {
+        fs::remove_file(&marf_path).unwrap();
+    }
+    let blobs_path = format!("{}.blobs", &marf_path);
+    if fs::metadata(&blobs_path).is_ok() {
+        fs::remove_file(&blobs_path).unwrap();
+    }
+
+    let mut marf_opts = MARFOpenOpts::default();
+    marf_opts.external_blobs = external_blobs;
+    let f = TrieFileStorage::<StacksBlockId>::open(&marf_path, marf_opts).unwrap();
+    let mut marf = MARF::<StacksBlockId>::from_storage(f);
+
+    let block_1 = StacksBlockId([0x11; 32]);
+    let block_2 = StacksBlockId([0x22; 32]);
+
+    marf.begin(&StacksBlockId::sentinel(), &block_1).unwrap();
+    marf.insert("foo", MARFValue::from(1)).unwrap();
+    marf.insert("bar", MARFValue::from(1)).unwrap();
+    marf.commit().unwrap();
+
+    marf.begin(&block_1, &block_2).unwrap();
+    marf.insert("foo", MARFValue::from(2)).unwrap();
+    marf.commit().unwrap();
+
+    let root_hash_2 = marf.get_root_hash_at(&block_2).unwrap();
+
+    // can't fork a block that doesn't exist
+    assert!(marf.fork_ephemeral_at(&StacksBlockId([0x33; 32])).is_err());
+
+    // fork from the middle of the chain, twice
+    for _ in 0..2 {
+        let mut fork = marf.fork_ephemeral_at(&block_1).unwrap();
+        let fork_tip = MARF::make_ephemeral_chain_tip(&block_1);
+        assert_eq!(fork.get_open_chain_tip(), Some(&fork_tip));
+        assert!(fork.borrow_storage_backend().ephemeral());
+        assert_eq!(
+            fork.get_block_height(&fork_tip, &fork_tip).unwrap(),
+            Some(1)
+        );
+
+        // the fork sees the state it was forked from, and its own writes on top of it
+        assert_eq!(
+            fork.get(&fork_tip, "foo").unwrap(),
+            Some(MARFValue::from(1))
+        );
+        fork.insert("foo", MARFValue::from(3)).unwrap();
+        fork.insert("baz", MARFValue::from(3)).unwrap();
+        assert_eq!(
+            fork.get(&fork_tip, "foo").unwrap(),
+            Some(MARFValue::from(3))
+        );
+        assert_eq!(
+            fork.get(&fork_tip, "bar").unwrap(),
+            Some(MARFValue::from(1))
+        );
+        assert_eq!(
+            fork.get(&fork_tip, "baz").unwrap(),
+            Some(MARFValue::from(3))
+        );
+        assert_eq!(fork.get(&block_1, "foo").unwrap(), Some(MARFValue::from(1)));
+
+        // the writer can keep going while the fork is open
+        let block_3 = StacksBlockId([0x33; 32]);
+        if !marf.borrow_storage_backend().has_block(&block_3).unwrap() {
+            marf.begin(&block_2, &block_3).unwrap();
+            marf.insert("baz", MARFValue::from(4)).unwrap();
+            marf.commit().unwrap();
+        }
+
+        // the fork's writes can't be persisted
+        match fork.commit() {
+            Err(Error::ReadOnlyError) => {}
+            x => panic!("Expected ReadOnlyError, got {:?}", x),
+        }
+    }
+
+    // nothing the forks wrote is visible in the MARF
+    assert_eq!(marf.get(&block_1, "foo").unwrap(), Some(MARFValue::from(1)));
+    assert_eq!(marf.get(&block_1, "baz").unwrap(), None);
+    assert_eq!(marf.get(&block_2, "foo").unwrap(), Some(MARFValue::from(2)));
+    assert_eq!(marf.get(&block_2, "baz").unwrap(), None);
+    assert_eq!(marf.get_root_hash_at(&block_2).unwrap(), root_hash_2);
+    assert!(!marf
+        .borrow_storage_backend()
+        .has_block(&MARF::make_ephemeral_chain_tip(&block_1))
+        .unwrap());
+
+    // a MARF that's in the middle of writing can't be forked
+    marf.begin(&StacksBlockId([0x33; 32]), &StacksBlockId([0x44; 32]))
+        .unwrap();
+    match marf.fork_ephemeral_at(&block_1) {
+        Err(Error::InProgressError) => {}
+        x => panic!("Expected InProgressError, got {:?}", x.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_marf_fork_ephemeral_at_sqlite() {
+    marf_fork_ephemeral_at(false);
+}
+
+#[test]
+fn test_marf_fork_ephemeral_at_external_blobs() {
+    marf_fork_ephemeral_at(true);
+}
@@ -41,13 +41,16 @@ use crate::chainstate::stacks::BlockHeaderHash;
 use crate::chainstate::stacks::TrieHash;
 use crate::types::chainstate::StacksBlockId;
 
+pub mod async_storage;
 pub mod cache;
 pub mod file;
+pub mod journal;
 pub mod marf;
 pub mod node;
 pub mod proofs;
 pub mod storage;
 pub mod trie;
+pub mod trie_index;
 
 /// Print out a trie to stderr
 pub fn dump_trie<T>(s: &mut TrieStorageConnection<T>)
@@ -198,3 +198,79 @@ fn ncc_verifier_catches_stale_proof() {
     println!("DEBUG: verify(old_v)");
     assert!(!proof_5.verify(&triepath_4, &marf_value_4, &root_hash_5, &root_to_block));
 }
+
+#[test]
+fn stream_merkle_proof_matches_proof() {
+    use crate::codec::StacksMessageCodec;
+    use std::io::Read;
+
+    let marf_opts = MARFOpenOpts::default();
+    let mut m = MARF::from_path(":memory:", marf_opts).unwrap();
+
+    let mut last_block = BlockHeaderHash::sentinel();
+    for i in 0..16u8 {
+        let block = BlockHeaderHash([i; 32]);
+        m.begin(&last_block, &block).unwrap();
+        // each key is written in a different block, so proofs pass through several tries
+        m.insert(
+            &format!("key-{}", i),
+            MARFValue::from_value(&format!("value-{}", i)),
+        )
+        .unwrap();
+        m.commit().unwrap();
+        last_block = block;
+    }
+
+    let tip = BlockHeaderHash([15u8; 32]);
+    let (_, root_hash) = {
+        let mut conn = m.borrow_storage_backend();
+        conn.open_block(&tip).unwrap();
+        Trie::read_root(&mut conn).unwrap()
+    };
+    let root_to_block = m
+        .borrow_storage_backend()
+        .read_root_to_block_table()
+        .unwrap();
+
+    for j in 0..16u8 {
+        let key = format!("key-{}", j);
+        let value = format!("value-{}", j);
+        let proof =
+            TrieMerkleProof::from_entry(&mut m.borrow_storage_backend(), &key, &value, &tip)
+                .unwrap();
+
+        let mut conn = m.borrow_storage_backend();
+        conn.open_block(&tip).unwrap();
+        let stream = conn.stream_merkle_proof(&key).unwrap();
+        assert_eq!(stream.value(), &MARFValue::from_value(&value));
+        assert_eq!(stream.num_entries() as usize, proof.len());
+
+        let entries: Vec<_> = stream.collect::<Result<_, _>>().unwrap();
+        let streamed_proof = TrieMerkleProof(entries);
+        assert_eq!(streamed_proof.0, proof.0);
+        assert!(streamed_proof.verify(
+            &TriePath::from_key(&key),
+            &MARFValue::from_value(&value),
+            &root_hash,
+            &root_to_block
+        ));
+        assert_eq!(conn.get_cur_block(), tip);
+
+        let mut bytes = vec![];
+        conn.stream_merkle_proof(&key)
+            .unwrap()
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .unwrap();
+        let mut expected_bytes = vec![];
+        proof.consensus_serialize(&mut expected_bytes).unwrap();
+        assert_eq!(bytes, expected_bytes);
+    }
+
+    let mut conn = m.borrow_storage_backend();
+    conn.open_block(&tip).unwrap();
+    match conn.stream_merkle_proof("no-such-key") {
+        Err(Error::NotFoundError) => {}
+        _ => panic!("expected NotFoundError"),
+    }
+}
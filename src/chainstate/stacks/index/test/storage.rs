@@ -17,8 +17,11 @@
 use std::collections::VecDeque;
 use std::fs;
 
+use crate::chainstate::stacks::index::file::TrieFile;
 use crate::chainstate::stacks::index::marf::*;
 use crate::chainstate::stacks::index::node::*;
+use crate::chainstate::stacks::index::trie_index::{SqliteTrieIndex, TrieIndexProvider};
+use crate::chainstate::stacks::index::trie_sql;
 use crate::chainstate::stacks::index::*;
 
 use rand::thread_rng;
@@ -138,6 +141,110 @@ fn trie_cmp<T: MarfTrieId>(
     return true;
 }
 
+#[test]
+fn test_prune_below() {
+    let path = "/tmp/test_prune_below.sqlite";
+    if fs::metadata(path).is_ok() {
+        fs::remove_file(path).unwrap();
+    }
+    if fs::metadata(&format!("{}.blobs", path)).is_ok() {
+        fs::remove_file(&format!("{}.blobs", path)).unwrap();
+    }
+    let marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "everything", true);
+    let mut marf: MARF<StacksBlockId> = MARF::from_path(path, marf_opts.clone()).unwrap();
+
+    let block = |i: u8| StacksBlockId([i; 32]);
+
+    // stored in this order, as (parent, child):
+    //   0 -> 1 -> 6 -> 7 -> 8      (the fork that reaches height 4)
+    //        1 -> 2 -> 3
+    //   0 -> 4
+    //        1 -> 5
+    let forks = [
+        (StacksBlockId::sentinel(), block(0)),
+        (block(0), block(1)),
+        (block(1), block(2)),
+        (block(2), block(3)),
+        (block(0), block(4)),
+        (block(1), block(5)),
+        (block(1), block(6)),
+        (block(6), block(7)),
+        (block(7), block(8)),
+    ];
+    for (parent, child) in forks.iter() {
+        marf.begin(parent, child).unwrap();
+        marf.insert(
+            &format!("key-{}", child),
+            MARFValue::from(child.0[0] as u32),
+        )
+        .unwrap();
+        marf.commit().unwrap();
+    }
+
+    // nothing reaches height 5
+    {
+        let mut tx = marf.borrow_storage_transaction();
+        assert_eq!(tx.prune_below(5).unwrap(), vec![]);
+        tx.commit_tx();
+    }
+
+    let pruned = {
+        let mut tx = marf.borrow_storage_transaction();
+        let pruned = tx.prune_below(4).unwrap();
+        tx.commit_tx();
+        pruned
+    };
+    assert_eq!(pruned, vec![block(2), block(3), block(4), block(5)]);
+
+    // the surviving fork is intact
+    for i in [0, 1, 6, 7, 8].iter() {
+        assert_eq!(
+            marf.get(&block(8), &format!("key-{}", block(*i))).unwrap(),
+            Some(MARFValue::from(*i as u32))
+        );
+    }
+
+    // the pruned tries are gone, and recorded as pruned
+    {
+        let mut tx = marf.borrow_storage_transaction();
+        for bhh in pruned.iter() {
+            assert!(!tx.has_block(bhh).unwrap());
+            assert!(SqliteTrieIndex::new(tx.sqlite_tx())
+                .is_pruned_block(bhh)
+                .unwrap());
+        }
+        assert!(!SqliteTrieIndex::new(tx.sqlite_tx())
+            .is_pruned_block(&block(8))
+            .unwrap());
+        tx.commit_tx();
+    }
+    assert!(marf
+        .get(&block(3), &format!("key-{}", block(3)))
+        .unwrap_or(None)
+        .is_none());
+
+    // pruning again does nothing
+    {
+        let mut tx = marf.borrow_storage_transaction();
+        assert_eq!(tx.prune_below(4).unwrap(), vec![]);
+        tx.commit_tx();
+    }
+    drop(marf);
+
+    // release the pruned tries' blob space, and check that the rest of the MARF is still readable
+    let mut storage = TrieFileStorage::<StacksBlockId>::open(path, marf_opts.clone()).unwrap();
+    assert!(storage.reclaim_pruned_tries().unwrap() > 0);
+    assert_eq!(storage.reclaim_pruned_tries().unwrap(), 0);
+
+    let mut marf = MARF::from_storage(storage);
+    for i in [0, 1, 6, 7, 8].iter() {
+        assert_eq!(
+            marf.get(&block(8), &format!("key-{}", block(*i))).unwrap(),
+            Some(MARFValue::from(*i as u32))
+        );
+    }
+}
+
 fn load_store_trie_m_n_same(m: u64, n: u64, same: bool) {
     let test_name = format!(
         "/tmp/load_store_trie_{}_{}_{}",
@@ -337,3 +444,457 @@ fn load_store_trie_4_256_same() {
 fn load_store_trie_4_256_unique() {
     load_store_trie_m_n_same(4, 256, false);
 }
+
+fn verify_integrity_repair(external_blobs: bool) {
+    let test_name = format!(
+        "/tmp/verify_integrity_repair_{}",
+        if external_blobs { "blobs" } else { "sqlite" }
+    );
+    if fs::metadata(&test_name).is_ok() {
+        fs::remove_file(&test_name).unwrap();
+    }
+    let blobs_name = format!("{}.blobs", &test_name);
+    if fs::metadata(&blobs_name).is_ok() {
+        fs::remove_file(&blobs_name).unwrap();
+    }
+
+    let mut marf_opts = MARFOpenOpts::default();
+    marf_opts.external_blobs = external_blobs;
+
+    let storage = TrieFileStorage::<StacksBlockId>::open(&test_name, marf_opts.clone()).unwrap();
+    let mut marf = MARF::<StacksBlockId>::from_storage(storage);
+
+    let mut parent = StacksBlockId::sentinel();
+    let mut last_block = parent.clone();
+    for b in 1..4u8 {
+        let block = StacksBlockId([b; 32]);
+        marf.begin(&parent, &block).unwrap();
+        for i in 0..32u64 {
+            let mut path_bytes = [b; 32];
+            path_bytes[24..32].copy_from_slice(&i.to_be_bytes());
+            let path = TriePath::from_bytes(&path_bytes).unwrap();
+            let value = TrieLeaf::new(&vec![], &[i as u8; 40].to_vec());
+            marf.insert_raw(path, value).unwrap();
+        }
+        marf.commit().unwrap();
+        parent = block.clone();
+        last_block = block;
+    }
+
+    let expected_root_hash = marf.get_root_hash_at(&last_block).unwrap();
+    drop(marf);
+
+    let mut storage =
+        TrieFileStorage::<StacksBlockId>::open(&test_name, marf_opts.clone()).unwrap();
+    let report = storage.verify_integrity(false).unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.tries_checked, 3);
+    assert!(report.nodes_checked > 96);
+
+    // clobber the last trie's root hash
+    let block_id = trie_sql::get_block_identifier(storage.sqlite_conn(), &last_block).unwrap();
+    let root_ptr = TriePtr::new(
+        TrieNodeID::Node256 as u8,
+        0,
+        TrieStorageConnection::<StacksBlockId>::root_ptr_disk(),
+    );
+    let bad_hash = TrieHash([0x11; 32]);
+    if external_blobs {
+        let mut blobs = TrieFile::from_db_path(&test_name, false).unwrap();
+        blobs
+            .write_node_hash_bytes(storage.sqlite_conn(), block_id, &root_ptr, &bad_hash)
+            .unwrap();
+    } else {
+        trie_sql::write_node_hash_bytes(storage.sqlite_conn(), block_id, &root_ptr, &bad_hash)
+            .unwrap();
+    }
+
+    let report = storage.verify_integrity(false).unwrap();
+    assert_eq!(
+        report.faults,
+        vec![TrieIntegrityFault::HashMismatch {
+            block_hash: last_block.clone(),
+            ptr: root_ptr.clone(),
+            stored_hash: bad_hash.clone(),
+            computed_hash: expected_root_hash.clone(),
+            repaired: false,
+        }]
+    );
+    assert_eq!(report.num_unrepaired(), 1);
+
+    let report = storage.verify_integrity(true).unwrap();
+    assert_eq!(report.faults.len(), 1);
+    assert_eq!(report.num_unrepaired(), 0);
+
+    let report = storage.verify_integrity(false).unwrap();
+    assert!(report.is_ok());
+
+    // the repaired hash is what a fresh MARF sees
+    drop(storage);
+    let mut marf = MARF::<StacksBlockId>::from_path(&test_name, marf_opts).unwrap();
+    assert_eq!(
+        marf.get_root_hash_at(&last_block).unwrap(),
+        expected_root_hash
+    );
+}
+
+#[test]
+fn verify_integrity_repair_sqlite() {
+    verify_integrity_repair(false);
+}
+
+#[test]
+fn verify_integrity_repair_external_blobs() {
+    verify_integrity_repair(true);
+}
+
+/// Make a MARF at `test_name` with `num_blocks` blocks of `num_keys` keys each, so the later
+/// blocks' tries have back-pointers into the earlier ones.  If `commit_last` is false, the last
+/// block's trie is left uncommitted.
+#[cfg(feature = "marf_parallel_hash")]
+fn make_parallel_hash_marf(
+    test_name: &str,
+    marf_opts: MARFOpenOpts,
+    num_blocks: u8,
+    num_keys: u64,
+    commit_last: bool,
+) -> MARF<StacksBlockId> {
+    if fs::metadata(test_name).is_ok() {
+        fs::remove_file(test_name).unwrap();
+    }
+    let mut marf = MARF::<StacksBlockId>::from_path(test_name, marf_opts).unwrap();
+
+    let mut parent = StacksBlockId::sentinel();
+    for block in 1..(num_blocks + 1) {
+        let block_id = StacksBlockId([block; 32]);
+        marf.begin(&parent, &block_id).unwrap();
+        for i in 0..num_keys {
+            let path_bytes = TrieHash::from_data(&[&[block][..], &i.to_be_bytes()[..]].concat());
+            let path = TriePath::from_bytes(path_bytes.as_bytes()).unwrap();
+            let value = TrieLeaf::new(&vec![], &[(i % 256) as u8; 40].to_vec());
+            marf.insert_raw(path, value).unwrap();
+        }
+        if block < num_blocks || commit_last {
+            marf.commit().unwrap();
+        }
+        parent = block_id;
+    }
+    marf
+}
+
+#[cfg(feature = "marf_parallel_hash")]
+#[test]
+fn parallel_node_hashes_match_sequential() {
+    let marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", false);
+    let mut marf = make_parallel_hash_marf(
+        "/tmp/parallel_node_hashes_match_sequential.marf",
+        marf_opts,
+        3,
+        4096,
+        false,
+    );
+
+    let (_, mut uncommitted) = marf
+        .borrow_storage_backend()
+        .transient_data()
+        .uncommitted_writes
+        .clone()
+        .unwrap();
+    let mut sequential_trie = uncommitted.trie_ram_mut().clone();
+    let mut parallel_trie = uncommitted.trie_ram_mut().clone();
+
+    // the trie is big enough to be hashed in parallel, and it has back-pointers
+    assert!(sequential_trie.data().len() > 1024);
+    assert!(sequential_trie
+        .data()
+        .iter()
+        .any(|(node, _)| !node.is_leaf() && node.ptrs().iter().any(|ptr| is_backptr(ptr.id()))));
+
+    let mut storage_tx = marf.borrow_storage_transaction();
+    let sequential_root_hash = sequential_trie
+        .test_calculate_node_hashes(&mut storage_tx)
+        .unwrap();
+    let parallel_root_hash = parallel_trie
+        .test_calculate_node_hashes_parallel(&mut storage_tx)
+        .unwrap();
+
+    assert_eq!(sequential_root_hash, parallel_root_hash);
+
+    // every intermediate node hash stored in deferred mode is the same, too
+    assert_eq!(sequential_trie.data().len(), parallel_trie.data().len());
+    for (i, ((_, sequential_hash), (_, parallel_hash))) in sequential_trie
+        .data()
+        .iter()
+        .zip(parallel_trie.data().iter())
+        .enumerate()
+    {
+        assert_eq!(sequential_hash, parallel_hash, "node {} differs", i);
+    }
+}
+
+#[cfg(feature = "marf_parallel_hash")]
+#[test]
+fn parallel_hash_root_hashes_match_immediate() {
+    // deferred mode hashes in parallel; immediate mode never does.  `All` mode does both and
+    // asserts that they agree on every commit.
+    let num_blocks = 4;
+    let mut marfs: Vec<_> = [
+        TrieHashCalculationMode::Immediate,
+        TrieHashCalculationMode::Deferred,
+        TrieHashCalculationMode::All,
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, mode)| {
+        make_parallel_hash_marf(
+            &format!("/tmp/parallel_hash_root_hashes_match_immediate_{}.marf", i),
+            MARFOpenOpts::new(*mode, "noop", false),
+            num_blocks,
+            2048,
+            true,
+        )
+    })
+    .collect();
+
+    for block in 1..(num_blocks + 1) {
+        let block_id = StacksBlockId([block; 32]);
+        let root_hashes: Vec<_> = marfs
+            .iter_mut()
+            .map(|marf| marf.get_root_hash_at(&block_id).unwrap())
+            .collect();
+        assert_eq!(root_hashes[0], root_hashes[1]);
+        assert_eq!(root_hashes[0], root_hashes[2]);
+    }
+}
+
+fn iter_keys_at(external_blobs: bool) {
+    let test_name = format!(
+        "/tmp/iter_keys_at_{}",
+        if external_blobs { "blobs" } else { "sqlite" }
+    );
+    if fs::metadata(&test_name).is_ok() {
+        fs::remove_file(&test_name).unwrap();
+    }
+    let blobs_name = format!("{}.blobs", &test_name);
+    if fs::metadata(&blobs_name).is_ok() {
+        fs::remove_file(&blobs_name).unwrap();
+    }
+
+    let mut marf_opts = MARFOpenOpts::default();
+    marf_opts.external_blobs = external_blobs;
+
+    let storage = TrieFileStorage::<StacksBlockId>::open(&test_name, marf_opts).unwrap();
+    let mut marf = MARF::<StacksBlockId>::from_storage(storage);
+
+    // expected (path, value) pairs as of each block
+    let mut expected: Vec<Vec<(Vec<u8>, Vec<u8>)>> = vec![];
+    let mut state = std::collections::BTreeMap::new();
+
+    let mut parent = StacksBlockId::sentinel();
+    for b in 1..4u8 {
+        let block = StacksBlockId([b; 32]);
+        marf.begin(&parent, &block).unwrap();
+
+        // keys shared by every block, overwritten each time
+        for i in 0..16u8 {
+            let mut path_bytes = [i; 32];
+            path_bytes[0] = i * 16;
+            let value = vec![b * 16 + i; 40];
+            marf.insert_raw(
+                TriePath::from_bytes(&path_bytes).unwrap(),
+                TrieLeaf::new(&vec![], &value),
+            )
+            .unwrap();
+            state.insert(path_bytes.to_vec(), value);
+        }
+
+        // a key only this block writes
+        let path_bytes = [0xf0 + b; 32];
+        let value = vec![b; 40];
+        marf.insert_raw(
+            TriePath::from_bytes(&path_bytes).unwrap(),
+            TrieLeaf::new(&vec![], &value),
+        )
+        .unwrap();
+        state.insert(path_bytes.to_vec(), value);
+
+        marf.commit().unwrap();
+        expected.push(state.clone().into_iter().collect());
+        parent = block;
+    }
+
+    let mut storage = marf.borrow_storage_backend();
+    storage.open_block(&StacksBlockId([3; 32])).unwrap();
+
+    for b in 1..4u8 {
+        let block = StacksBlockId([b; 32]);
+        let keys: Vec<_> = storage
+            .iter_keys_at(&block, &[])
+            .unwrap()
+            .map(|res| {
+                let (path, value) = res.unwrap();
+                (path.as_bytes().to_vec(), value.0.to_vec())
+            })
+            .collect();
+        // keys come back in ascending path order, with their latest values
+        assert_eq!(keys, expected[(b - 1) as usize]);
+    }
+
+    // the previously-open block is restored
+    assert_eq!(storage.get_cur_block(), StacksBlockId([3; 32]));
+
+    // prefixes narrow the scan, and may fall on either side of a back-pointer
+    let block = StacksBlockId([3; 32]);
+    let keys: Vec<_> = storage
+        .iter_keys_at(&block, &[0x10, 0x01])
+        .unwrap()
+        .map(|res| res.unwrap())
+        .collect();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].0.as_bytes()[0..2], [0x10, 0x01]);
+    assert_eq!(keys[0].1 .0, [3 * 16 + 1; 40]);
+
+    let keys: Vec<_> = storage
+        .iter_keys_at(&block, &[0xf1])
+        .unwrap()
+        .map(|res| res.unwrap())
+        .collect();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].1 .0, [1; 40]);
+
+    assert_eq!(
+        storage
+            .iter_keys_at(&block, &[0x11])
+            .unwrap()
+            .map(|res| res.unwrap())
+            .count(),
+        0
+    );
+
+    // unknown blocks can't be iterated
+    assert!(storage.iter_keys_at(&StacksBlockId([9; 32]), &[]).is_err());
+}
+
+#[test]
+fn iter_keys_at_sqlite() {
+    iter_keys_at(false);
+}
+
+#[test]
+fn iter_keys_at_external_blobs() {
+    iter_keys_at(true);
+}
+
+fn trie_diff(external_blobs: bool) {
+    let test_name = format!(
+        "/tmp/trie_diff_{}",
+        if external_blobs { "blobs" } else { "sqlite" }
+    );
+    if fs::metadata(&test_name).is_ok() {
+        fs::remove_file(&test_name).unwrap();
+    }
+    let blobs_name = format!("{}.blobs", &test_name);
+    if fs::metadata(&blobs_name).is_ok() {
+        fs::remove_file(&blobs_name).unwrap();
+    }
+
+    let mut marf_opts = MARFOpenOpts::default();
+    marf_opts.external_blobs = external_blobs;
+
+    let storage = TrieFileStorage::<StacksBlockId>::open(&test_name, marf_opts).unwrap();
+    let mut marf = MARF::<StacksBlockId>::from_storage(storage);
+
+    // blocks 1 <- 2 <- 3, and 1 <- 4.  Each block rewrites some of the keys its parent wrote,
+    // leaves the rest alone, and adds a key of its own.
+    let forks = [
+        (StacksBlockId::sentinel(), StacksBlockId([1; 32])),
+        (StacksBlockId([1; 32]), StacksBlockId([2; 32])),
+        (StacksBlockId([2; 32]), StacksBlockId([3; 32])),
+        (StacksBlockId([1; 32]), StacksBlockId([4; 32])),
+    ];
+    for (b, (parent, block)) in forks.iter().enumerate() {
+        let b = b as u8 + 1;
+        marf.begin(parent, block).unwrap();
+        for i in 0..64u8 {
+            if b > 1 && i % (b + 1) != 0 {
+                continue;
+            }
+            let mut path_bytes = [i; 32];
+            path_bytes[0] = i.wrapping_mul(37);
+            marf.insert_raw(
+                TriePath::from_bytes(&path_bytes).unwrap(),
+                TrieLeaf::new(&vec![], &vec![b; 40]),
+            )
+            .unwrap();
+        }
+        marf.insert_raw(
+            TriePath::from_bytes(&[0xf0 + b; 32]).unwrap(),
+            TrieLeaf::new(&vec![], &vec![b; 40]),
+        )
+        .unwrap();
+        marf.commit().unwrap();
+    }
+
+    let mut storage = marf.borrow_storage_backend();
+    storage.open_block(&StacksBlockId([2; 32])).unwrap();
+
+    // every key and value as of each block
+    let mut states = vec![];
+    for (_, block) in forks.iter() {
+        let state: std::collections::BTreeMap<_, _> = storage
+            .iter_keys_at(block, &[])
+            .unwrap()
+            .map(|res| res.unwrap())
+            .collect();
+        states.push(state);
+    }
+
+    for (a, (_, block_a)) in forks.iter().enumerate() {
+        for (b, (_, block_b)) in forks.iter().enumerate() {
+            let mut expected = std::collections::BTreeSet::new();
+            for (path, value) in states[a].iter() {
+                if states[b].get(path) != Some(value) {
+                    expected.insert(path.clone());
+                }
+            }
+            for path in states[b].keys() {
+                if !states[a].contains_key(path) {
+                    expected.insert(path.clone());
+                }
+            }
+
+            let diff = storage.diff(block_a, block_b).unwrap();
+            assert_eq!(diff, expected, "diff of {} and {}", block_a, block_b);
+            if a == b {
+                assert!(diff.is_empty());
+            } else {
+                assert!(!diff.is_empty());
+            }
+        }
+    }
+
+    // block 3 only rewrote every fourth key, and added one of its own
+    let diff = storage
+        .diff(&StacksBlockId([2; 32]), &StacksBlockId([3; 32]))
+        .unwrap();
+    assert_eq!(diff.len(), 16 + 1);
+
+    // the previously-open block is restored
+    assert_eq!(storage.get_cur_block(), StacksBlockId([2; 32]));
+
+    // unknown blocks can't be diffed
+    assert!(storage
+        .diff(&StacksBlockId([1; 32]), &StacksBlockId([9; 32]))
+        .is_err());
+    assert_eq!(storage.get_cur_block(), StacksBlockId([2; 32]));
+}
+
+#[test]
+fn trie_diff_sqlite() {
+    trie_diff(false);
+}
+
+#[test]
+fn trie_diff_external_blobs() {
+    trie_diff(true);
+}
@@ -0,0 +1,393 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use rusqlite::Connection;
+
+use crate::chainstate::stacks::index::marf::{MARFOpenOpts, MarfConnection, MARF};
+use crate::chainstate::stacks::index::storage::{TrieFileStorage, TrieHashCalculationMode};
+use crate::chainstate::stacks::index::trie_index::*;
+use crate::chainstate::stacks::index::{trie_sql, ClarityMarfTrieId, Error, MARFValue};
+use crate::types::chainstate::StacksBlockId;
+
+fn open_index_db() -> Connection {
+    let mut conn = Connection::open_in_memory().unwrap();
+    trie_sql::create_tables_if_needed(&mut conn).unwrap();
+    trie_sql::migrate_tables_if_needed::<StacksBlockId>(&mut conn).unwrap();
+    conn
+}
+
+#[test]
+fn test_copy_sqlite_trie_index() {
+    let from_conn = open_index_db();
+    let to_conn = open_index_db();
+    let from = SqliteTrieIndex::new(&from_conn);
+    let to = SqliteTrieIndex::new(&to_conn);
+
+    let id_1 = TrieIndexProvider::<StacksBlockId>::write_external_trie_blob(
+        &from,
+        &StacksBlockId([1u8; 32]),
+        0,
+        100,
+    )
+    .unwrap();
+    from.insert_entry(&TrieIndexEntry {
+        block_id: id_1 + 1,
+        block_hash: StacksBlockId([2u8; 32]),
+        external_offset: 0,
+        external_length: 0,
+        unconfirmed: true,
+    })
+    .unwrap();
+    let id_3 = from
+        .write_external_trie_blob(&StacksBlockId([3u8; 32]), 100, 50)
+        .unwrap();
+    assert_eq!(id_3, id_1 + 2);
+    assert_eq!(
+        TrieIndexProvider::<StacksBlockId>::count_blocks(&from).unwrap(),
+        id_3
+    );
+
+    // only confirmed tries are copied, under their original block identifiers
+    assert_eq!(copy_trie_index::<StacksBlockId>(&from, &to).unwrap(), 2);
+    assert_eq!(
+        to.get_block_identifier(&StacksBlockId([1u8; 32])).unwrap(),
+        id_1
+    );
+    assert_eq!(
+        to.get_block_identifier(&StacksBlockId([3u8; 32])).unwrap(),
+        id_3
+    );
+    assert!(to
+        .get_confirmed_block_identifier(&StacksBlockId([2u8; 32]))
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        TrieIndexProvider::<StacksBlockId>::get_external_trie_offset_length(&to, id_3).unwrap(),
+        (100, 50)
+    );
+    assert_eq!(
+        TrieIndexProvider::<StacksBlockId>::get_external_blobs_length(&to).unwrap(),
+        150
+    );
+
+    // copying again is a no-op
+    assert_eq!(copy_trie_index::<StacksBlockId>(&from, &to).unwrap(), 0);
+
+    // newly-indexed tries don't collide with copied identifiers
+    let id_4 = to
+        .write_external_trie_blob(&StacksBlockId([4u8; 32]), 150, 10)
+        .unwrap();
+    assert!(id_4 > id_3);
+    assert_eq!(
+        TrieIndexProvider::<StacksBlockId>::get_block_hash(&to, id_4).unwrap(),
+        StacksBlockId([4u8; 32])
+    );
+}
+
+/// A trie block index kept in RAM, standing in for an external store.
+#[derive(Default)]
+struct MemoryTrieIndex {
+    entries: Mutex<HashMap<u32, TrieIndexEntry<StacksBlockId>>>,
+    /// pruned entries, and whether or not their space has been reclaimed
+    pruned: Mutex<HashMap<u32, (TrieIndexEntry<StacksBlockId>, bool)>>,
+}
+
+impl MemoryTrieIndex {
+    fn find_entry<F>(&self, f: F) -> Option<TrieIndexEntry<StacksBlockId>>
+    where
+        F: Fn(&TrieIndexEntry<StacksBlockId>) -> bool,
+    {
+        self.entries
+            .lock()
+            .unwrap()
+            .values()
+            .find(|entry| f(entry))
+            .cloned()
+    }
+
+    fn max_block_id(&self) -> u32 {
+        let max_indexed = self.entries.lock().unwrap().keys().max().cloned();
+        let max_pruned = self.pruned.lock().unwrap().keys().max().cloned();
+        max_indexed.max(max_pruned).unwrap_or(0)
+    }
+}
+
+impl TrieIndexProvider<StacksBlockId> for MemoryTrieIndex {
+    fn index_type(&self) -> TrieIndexType {
+        TrieIndexType::SQLite
+    }
+
+    fn create_tables_if_needed(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn get_block_identifier(&self, bhh: &StacksBlockId) -> Result<u32, Error> {
+        self.find_entry(|entry| &entry.block_hash == bhh)
+            .map(|entry| entry.block_id)
+            .ok_or(Error::NotFoundError)
+    }
+
+    fn get_confirmed_block_identifier(&self, bhh: &StacksBlockId) -> Result<Option<u32>, Error> {
+        Ok(self
+            .find_entry(|entry| &entry.block_hash == bhh && !entry.unconfirmed)
+            .map(|entry| entry.block_id))
+    }
+
+    fn get_block_hash(&self, block_id: u32) -> Result<StacksBlockId, Error> {
+        self.get_entry(block_id)?
+            .map(|entry| entry.block_hash)
+            .ok_or(Error::NotFoundError)
+    }
+
+    fn get_entry(&self, block_id: u32) -> Result<Option<TrieIndexEntry<StacksBlockId>>, Error> {
+        Ok(self.entries.lock().unwrap().get(&block_id).cloned())
+    }
+
+    fn get_external_trie_offset_length(&self, block_id: u32) -> Result<(u64, u64), Error> {
+        self.get_entry(block_id)?
+            .map(|entry| (entry.external_offset, entry.external_length))
+            .ok_or(Error::NotFoundError)
+    }
+
+    fn get_external_blobs_length(&self) -> Result<u64, Error> {
+        let end =
+            |entry: &TrieIndexEntry<StacksBlockId>| entry.external_offset + entry.external_length;
+        let max_indexed = self.entries.lock().unwrap().values().map(end).max();
+        let max_pruned = self
+            .pruned
+            .lock()
+            .unwrap()
+            .values()
+            .map(|(entry, _)| end(entry))
+            .max();
+        Ok(max_indexed.max(max_pruned).unwrap_or(0))
+    }
+
+    fn count_blocks(&self) -> Result<u32, Error> {
+        Ok(self
+            .entries
+            .lock()
+            .unwrap()
+            .keys()
+            .max()
+            .cloned()
+            .unwrap_or(0))
+    }
+
+    fn is_unconfirmed_block(&self, block_id: u32) -> Result<bool, Error> {
+        Ok(self
+            .get_entry(block_id)?
+            .map(|entry| entry.unconfirmed)
+            .unwrap_or(false))
+    }
+
+    fn write_external_trie_blob(
+        &self,
+        bhh: &StacksBlockId,
+        offset: u64,
+        length: u64,
+    ) -> Result<u32, Error> {
+        let block_id = self.max_block_id() + 1;
+        self.insert_entry(&TrieIndexEntry {
+            block_id,
+            block_hash: *bhh,
+            external_offset: offset,
+            external_length: length,
+            unconfirmed: false,
+        })?;
+        Ok(block_id)
+    }
+
+    fn insert_entry(&self, entry: &TrieIndexEntry<StacksBlockId>) -> Result<(), Error> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.contains_key(&entry.block_id) {
+            return Err(Error::ExistsError);
+        }
+        entries.insert(entry.block_id, entry.clone());
+        Ok(())
+    }
+
+    fn prune_entry(&self, block_id: u32) -> Result<(), Error> {
+        let entry = self
+            .entries
+            .lock()
+            .unwrap()
+            .remove(&block_id)
+            .ok_or(Error::NotFoundError)?;
+        self.pruned.lock().unwrap().insert(block_id, (entry, false));
+        Ok(())
+    }
+
+    fn is_pruned_block(&self, bhh: &StacksBlockId) -> Result<bool, Error> {
+        Ok(self
+            .pruned
+            .lock()
+            .unwrap()
+            .values()
+            .any(|(entry, _)| &entry.block_hash == bhh))
+    }
+
+    fn get_unreclaimed_pruned_entries(&self) -> Result<Vec<TrieIndexEntry<StacksBlockId>>, Error> {
+        let mut entries: Vec<_> = self
+            .pruned
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|(_, reclaimed)| !reclaimed)
+            .map(|(entry, _)| entry.clone())
+            .collect();
+        entries.sort_by_key(|entry| entry.block_id);
+        Ok(entries)
+    }
+
+    fn set_pruned_entry_reclaimed(&self, block_id: u32) -> Result<(), Error> {
+        if let Some((_, reclaimed)) = self.pruned.lock().unwrap().get_mut(&block_id) {
+            *reclaimed = true;
+        }
+        Ok(())
+    }
+}
+
+/// Commit tries to a MARF that mirrors its trie block index into `trie_index`, and check that a
+/// read-only MARF can find them through `trie_index` alone.
+fn check_marf_external_trie_index(path: &str, trie_index: SharedTrieIndex<StacksBlockId>) {
+    if fs::metadata(path).is_ok() {
+        fs::remove_file(path).unwrap();
+    }
+    if fs::metadata(format!("{}.blobs", path)).is_ok() {
+        fs::remove_file(format!("{}.blobs", path)).unwrap();
+    }
+    let marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true);
+    let mut marf: MARF<StacksBlockId> = MARF::from_path(path, marf_opts.clone()).unwrap();
+
+    let block = |i: u8| StacksBlockId([i; 32]);
+    let key = |i: u8| format!("key-{}", block(i));
+
+    // stored in this order, as (parent, child):
+    //   0 -> 1 -> 2 -> 3
+    //   0 -> 4
+    let forks = [
+        (StacksBlockId::sentinel(), block(0)),
+        (block(0), block(1)),
+        (block(1), block(2)),
+        (block(0), block(4)),
+        (block(2), block(3)),
+    ];
+    for (i, (parent, child)) in forks.iter().enumerate() {
+        if i == 1 {
+            // tries committed before the index was set are copied into it
+            assert_eq!(marf.set_trie_index(trie_index.clone()).unwrap(), 1);
+        }
+        marf.begin(parent, child).unwrap();
+        marf.insert(&key(child.0[0]), MARFValue::from(child.0[0] as u32))
+            .unwrap();
+        marf.commit().unwrap();
+    }
+
+    // every committed trie was mirrored, under the same block identifier
+    let sqlite_entries: Vec<TrieIndexEntry<StacksBlockId>> = {
+        let tx = marf.borrow_storage_transaction();
+        let index = SqliteTrieIndex::new(tx.sqlite_tx());
+        let entries = (1..=TrieIndexProvider::<StacksBlockId>::count_blocks(&index).unwrap())
+            .filter_map(|block_id| index.get_entry(block_id).unwrap())
+            .collect();
+        tx.commit_tx();
+        entries
+    };
+    assert_eq!(sqlite_entries.len(), forks.len());
+    for entry in sqlite_entries.iter() {
+        assert_eq!(
+            trie_index.get_entry(entry.block_id).unwrap().as_ref(),
+            Some(entry)
+        );
+    }
+
+    // pruned tries are pruned from the external index too, but only once the prune commits
+    {
+        let mut tx = marf.borrow_storage_transaction();
+        assert_eq!(tx.prune_below(3).unwrap(), vec![block(4)]);
+        tx.rollback();
+    }
+    assert!(!trie_index.is_pruned_block(&block(4)).unwrap());
+    {
+        let mut tx = marf.borrow_storage_transaction();
+        assert_eq!(tx.prune_below(3).unwrap(), vec![block(4)]);
+        tx.commit_tx();
+    }
+    assert!(trie_index.is_pruned_block(&block(4)).unwrap());
+    assert!(trie_index
+        .get_confirmed_block_identifier(&block(4))
+        .unwrap()
+        .is_none());
+    drop(marf);
+
+    // drop the SQLite index's confirmed tries, so only the external index can find them
+    let conn = Connection::open(path).unwrap();
+    conn.execute(
+        "DELETE FROM marf_data WHERE unconfirmed = 0",
+        rusqlite::NO_PARAMS,
+    )
+    .unwrap();
+    drop(conn);
+
+    let storage = TrieFileStorage::open_readonly(path, marf_opts).unwrap();
+    let mut marf = MARF::from_storage(storage);
+    assert!(marf.get(&block(3), &key(3)).unwrap_or(None).is_none());
+
+    assert_eq!(marf.set_trie_index(trie_index.clone()).unwrap(), 0);
+    for i in [0, 1, 2, 3].iter() {
+        assert_eq!(
+            marf.get(&block(3), &key(*i)).unwrap(),
+            Some(MARFValue::from(*i as u32))
+        );
+    }
+    assert!(marf.get(&block(4), &key(4)).unwrap_or(None).is_none());
+}
+
+#[test]
+fn test_marf_external_trie_index() {
+    check_marf_external_trie_index(
+        "/tmp/test_marf_external_trie_index.sqlite",
+        Arc::new(MemoryTrieIndex::default()),
+    );
+}
+
+/// Runs against the (emptied) PostgreSQL database at `$STACKS_MARF_POSTGRES_TEST_URL`, if set.
+#[cfg(feature = "marf_postgres")]
+#[test]
+fn test_marf_postgres_trie_index() {
+    let url = match std::env::var("STACKS_MARF_POSTGRES_TEST_URL") {
+        Ok(url) => url,
+        Err(_) => {
+            eprintln!("STACKS_MARF_POSTGRES_TEST_URL is not set; skipping");
+            return;
+        }
+    };
+    postgres::Client::connect(&url, postgres::NoTls)
+        .unwrap()
+        .batch_execute("DROP TABLE IF EXISTS marf_data, marf_pruned_tries")
+        .unwrap();
+
+    let trie_index = PostgresTrieIndex::open(&url, 4).unwrap();
+    check_marf_external_trie_index(
+        "/tmp/test_marf_postgres_trie_index.sqlite",
+        Arc::new(trie_index),
+    );
+}
@@ -0,0 +1,638 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The MARF's trie block index maps each trie's block hash to the local block identifier that
+//! back-pointers refer to it by, and locates the trie's serialized bytes in the external blobs
+//! file.  `TrieFileStorage` keeps this index in the `marf_data` table of its SQLite database;
+//! the `TrieIndexProvider` trait lets the same index be read and written through other stores.
+//!
+//! With the `marf_postgres` feature, `PostgresTrieIndex` stores the index in PostgreSQL, behind
+//! a connection pool, so archival nodes and indexers can share it between concurrent readers.
+//! Use `copy_trie_index()` to export a node's SQLite index into it.
+//!
+//! A `TrieFileStorage` can be given an external index with `TrieFileStorage::set_trie_index()`
+//! (or `MARF::set_trie_index()`).  It then reads confirmed tries' entries from that index before
+//! falling back to its own SQLite index, and writes each trie it commits or prunes to both, so a
+//! reader can find a trie whose entry only the external index has.
+//!
+//! Tries that are pruned from the MARF (see `TrieStorageTransaction::prune_below()`) are moved
+//! out of the index and into a separate record of pruned tries, which remembers where their bytes
+//! were in the external blobs file until that space has been reclaimed.
+
+use std::sync::Arc;
+
+use rusqlite::types::ToSql;
+use rusqlite::{Connection, OptionalExtension, Row, NO_PARAMS};
+
+use crate::chainstate::stacks::index::trie_sql;
+use crate::chainstate::stacks::index::{Error, MarfTrieId};
+use crate::util_lib::db::{table_exists, u64_to_sql};
+
+/// Kind of store a trie block index is kept in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrieIndexType {
+    SQLite,
+    #[cfg(feature = "marf_postgres")]
+    Postgres,
+}
+
+/// One trie's entry in the trie block index
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrieIndexEntry<T: MarfTrieId> {
+    pub block_id: u32,
+    pub block_hash: T,
+    /// Offset of the trie's bytes in the external blobs file
+    pub external_offset: u64,
+    /// Length of the trie's bytes in the external blobs file
+    pub external_length: u64,
+    pub unconfirmed: bool,
+}
+
+/// Read and write access to a trie block index.
+/// Block identifiers are assigned by the index, and are stable once assigned: tries refer to each
+/// other by them.
+pub trait TrieIndexProvider<T: MarfTrieId> {
+    fn index_type(&self) -> TrieIndexType;
+
+    /// Create the index's tables if they do not exist yet
+    fn create_tables_if_needed(&self) -> Result<(), Error>;
+
+    /// Get the block identifier of a (confirmed or unconfirmed) trie
+    fn get_block_identifier(&self, bhh: &T) -> Result<u32, Error>;
+
+    /// Get the block identifier of a confirmed trie, if it is indexed
+    fn get_confirmed_block_identifier(&self, bhh: &T) -> Result<Option<u32>, Error>;
+
+    /// Get the block hash of the trie with the given block identifier
+    fn get_block_hash(&self, block_id: u32) -> Result<T, Error>;
+
+    /// Get the whole index entry of the trie with the given block identifier, if it is indexed
+    fn get_entry(&self, block_id: u32) -> Result<Option<TrieIndexEntry<T>>, Error>;
+
+    /// Get the offset and length of a trie's bytes in the external blobs file
+    fn get_external_trie_offset_length(&self, block_id: u32) -> Result<(u64, u64), Error>;
+
+    /// Get the offset at which the last trie in the external blobs file ends
+    fn get_external_blobs_length(&self) -> Result<u64, Error>;
+
+    /// Get the highest block identifier of a confirmed trie (0 if there are none)
+    fn count_blocks(&self) -> Result<u32, Error>;
+
+    fn is_unconfirmed_block(&self, block_id: u32) -> Result<bool, Error>;
+
+    /// Index a new confirmed trie whose bytes were stored in the external blobs file.
+    /// Returns its newly-assigned block identifier.
+    fn write_external_trie_blob(&self, bhh: &T, offset: u64, length: u64) -> Result<u32, Error>;
+
+    /// Index an entry with an already-assigned block identifier (i.e. one copied from another
+    /// index).  Later identifiers assigned by `write_external_trie_blob()` will not collide with
+    /// it.
+    fn insert_entry(&self, entry: &TrieIndexEntry<T>) -> Result<(), Error>;
+
+    /// Remove a confirmed trie from the index, and record it as pruned.  Its block identifier is
+    /// never reassigned.
+    fn prune_entry(&self, block_id: u32) -> Result<(), Error>;
+
+    /// Has the trie with the given block hash been pruned?
+    fn is_pruned_block(&self, bhh: &T) -> Result<bool, Error>;
+
+    /// Get the entries of pruned tries whose space in the external blobs file has not been
+    /// reclaimed yet
+    fn get_unreclaimed_pruned_entries(&self) -> Result<Vec<TrieIndexEntry<T>>, Error>;
+
+    /// Record that a pruned trie's space in the external blobs file has been reclaimed
+    fn set_pruned_entry_reclaimed(&self, block_id: u32) -> Result<(), Error>;
+}
+
+/// A trie block index that can be shared between MARF storage handles and threads
+pub type SharedTrieIndex<T> = Arc<dyn TrieIndexProvider<T> + Send + Sync>;
+
+/// Copy every confirmed trie's entry from one trie block index to another, preserving block
+/// identifiers.  Entries already present in `to` are left as they are, so an interrupted copy
+/// can simply be re-run.  Returns the number of entries copied.
+pub fn copy_trie_index<T: MarfTrieId>(
+    from: &dyn TrieIndexProvider<T>,
+    to: &dyn TrieIndexProvider<T>,
+) -> Result<u64, Error> {
+    to.create_tables_if_needed()?;
+
+    let mut num_copied = 0;
+    for block_id in 1..=from.count_blocks()? {
+        let entry = match from.get_entry(block_id)? {
+            Some(entry) => entry,
+            None => {
+                continue;
+            }
+        };
+        if entry.unconfirmed || to.get_entry(block_id)?.is_some() {
+            continue;
+        }
+        to.insert_entry(&entry)?;
+        num_copied += 1;
+    }
+
+    debug!(
+        "Copied {} trie index entries from {:?} to {:?}",
+        num_copied,
+        from.index_type(),
+        to.index_type()
+    );
+    Ok(num_copied)
+}
+
+static SQL_MARF_PRUNED_TRIES_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS marf_pruned_tries (
+   block_id INTEGER PRIMARY KEY,
+   block_hash TEXT UNIQUE NOT NULL,
+   external_offset INTEGER NOT NULL,
+   external_length INTEGER NOT NULL,
+   -- 1 if the trie's bytes in the external blobs file have been released
+   reclaimed INTEGER NOT NULL
+);
+";
+
+/// The trie block index in a MARF's SQLite database
+pub struct SqliteTrieIndex<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> SqliteTrieIndex<'a> {
+    pub fn new(conn: &'a Connection) -> SqliteTrieIndex<'a> {
+        SqliteTrieIndex { conn }
+    }
+
+    fn entry_from_row<T: MarfTrieId>(row: &Row) -> Result<TrieIndexEntry<T>, rusqlite::Error> {
+        let external_offset: i64 = row.get("external_offset")?;
+        let external_length: i64 = row.get("external_length")?;
+        let unconfirmed: i64 = row.get("unconfirmed")?;
+        Ok(TrieIndexEntry {
+            block_id: row.get("block_id")?,
+            block_hash: row.get("block_hash")?,
+            external_offset: external_offset as u64,
+            external_length: external_length as u64,
+            unconfirmed: unconfirmed != 0,
+        })
+    }
+
+    /// The pruned trie table only exists in MARFs that have been pruned, since read-only
+    /// connections cannot create it.
+    fn has_pruned_tries_table(&self) -> Result<bool, Error> {
+        table_exists(self.conn, "marf_pruned_tries").map_err(|e| e.into())
+    }
+}
+
+impl<'a, T: MarfTrieId> TrieIndexProvider<T> for SqliteTrieIndex<'a> {
+    fn index_type(&self) -> TrieIndexType {
+        TrieIndexType::SQLite
+    }
+
+    fn create_tables_if_needed(&self) -> Result<(), Error> {
+        // marf_data is created along with the rest of the MARF's tables by TrieFileStorage
+        self.conn.execute_batch(SQL_MARF_PRUNED_TRIES_TABLE)?;
+        Ok(())
+    }
+
+    fn get_block_identifier(&self, bhh: &T) -> Result<u32, Error> {
+        trie_sql::get_block_identifier(self.conn, bhh)
+    }
+
+    fn get_confirmed_block_identifier(&self, bhh: &T) -> Result<Option<u32>, Error> {
+        trie_sql::get_confirmed_block_identifier(self.conn, bhh)
+    }
+
+    fn get_block_hash(&self, block_id: u32) -> Result<T, Error> {
+        trie_sql::get_block_hash(self.conn, block_id)
+    }
+
+    fn get_entry(&self, block_id: u32) -> Result<Option<TrieIndexEntry<T>>, Error> {
+        self.conn
+            .query_row(
+                "SELECT block_id, block_hash, external_offset, external_length, unconfirmed FROM marf_data WHERE block_id = ?1",
+                &[&block_id],
+                |row| SqliteTrieIndex::entry_from_row(row),
+            )
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    fn get_external_trie_offset_length(&self, block_id: u32) -> Result<(u64, u64), Error> {
+        trie_sql::get_external_trie_offset_length(self.conn, block_id)
+    }
+
+    fn get_external_blobs_length(&self) -> Result<u64, Error> {
+        trie_sql::get_external_blobs_length(self.conn)
+    }
+
+    fn count_blocks(&self) -> Result<u32, Error> {
+        trie_sql::count_blocks(self.conn)
+    }
+
+    fn is_unconfirmed_block(&self, block_id: u32) -> Result<bool, Error> {
+        trie_sql::is_unconfirmed_block(self.conn, block_id)
+    }
+
+    fn write_external_trie_blob(&self, bhh: &T, offset: u64, length: u64) -> Result<u32, Error> {
+        trie_sql::write_external_trie_blob(self.conn, bhh, offset, length)
+    }
+
+    fn insert_entry(&self, entry: &TrieIndexEntry<T>) -> Result<(), Error> {
+        let empty_blob: &[u8] = &[];
+        let args: &[&dyn ToSql] = &[
+            &entry.block_id,
+            &entry.block_hash,
+            &empty_blob,
+            &entry.unconfirmed,
+            &u64_to_sql(entry.external_offset)?,
+            &u64_to_sql(entry.external_length)?,
+        ];
+        self.conn.execute(
+            "INSERT INTO marf_data (block_id, block_hash, data, unconfirmed, external_offset, external_length) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            args,
+        )?;
+        Ok(())
+    }
+
+    fn prune_entry(&self, block_id: u32) -> Result<(), Error> {
+        self.conn.execute_batch(SQL_MARF_PRUNED_TRIES_TABLE)?;
+        let num_pruned = self.conn.execute(
+            "INSERT INTO marf_pruned_tries (block_id, block_hash, external_offset, external_length, reclaimed) \
+             SELECT block_id, block_hash, external_offset, external_length, 0 FROM marf_data WHERE block_id = ?1 AND unconfirmed = 0",
+            &[&block_id],
+        )?;
+        if num_pruned == 0 {
+            return Err(Error::NotFoundError);
+        }
+        self.conn
+            .execute("DELETE FROM marf_data WHERE block_id = ?1", &[&block_id])?;
+        Ok(())
+    }
+
+    fn is_pruned_block(&self, bhh: &T) -> Result<bool, Error> {
+        if !self.has_pruned_tries_table()? {
+            return Ok(false);
+        }
+        let pruned = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM marf_pruned_tries WHERE block_hash = ?1",
+                &[bhh],
+                |_row| Ok(()),
+            )
+            .optional()?;
+        Ok(pruned.is_some())
+    }
+
+    fn get_unreclaimed_pruned_entries(&self) -> Result<Vec<TrieIndexEntry<T>>, Error> {
+        if !self.has_pruned_tries_table()? {
+            return Ok(vec![]);
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT block_id, block_hash, external_offset, external_length, 0 AS unconfirmed FROM marf_pruned_tries WHERE reclaimed = 0 ORDER BY block_id",
+        )?;
+        let rows = stmt.query_map(NO_PARAMS, |row| SqliteTrieIndex::entry_from_row(row))?;
+        let mut entries = vec![];
+        for row in rows {
+            entries.push(row?);
+        }
+        Ok(entries)
+    }
+
+    fn set_pruned_entry_reclaimed(&self, block_id: u32) -> Result<(), Error> {
+        self.conn.execute(
+            "UPDATE marf_pruned_tries SET reclaimed = 1 WHERE block_id = ?1",
+            &[&block_id],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "marf_postgres")]
+pub use self::pg::PostgresTrieIndex;
+
+#[cfg(feature = "marf_postgres")]
+mod pg {
+    use std::convert::TryFrom;
+
+    use postgres::NoTls;
+    use r2d2_postgres::r2d2::{Pool, PooledConnection};
+    use r2d2_postgres::PostgresConnectionManager;
+
+    use super::{TrieIndexEntry, TrieIndexProvider, TrieIndexType};
+    use crate::chainstate::stacks::index::{Error, MarfTrieId};
+
+    static PG_MARF_DATA_TABLE: &str = "
+    CREATE TABLE IF NOT EXISTS marf_data (
+        block_id INTEGER GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+        block_hash BYTEA UNIQUE NOT NULL,
+        unconfirmed BOOLEAN NOT NULL,
+        external_offset BIGINT NOT NULL,
+        external_length BIGINT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS unconfirmed_marf_data ON marf_data(unconfirmed);
+    CREATE INDEX IF NOT EXISTS index_external_offset ON marf_data(external_offset);
+
+    CREATE TABLE IF NOT EXISTS marf_pruned_tries (
+        block_id INTEGER PRIMARY KEY,
+        block_hash BYTEA UNIQUE NOT NULL,
+        external_offset BIGINT NOT NULL,
+        external_length BIGINT NOT NULL,
+        reclaimed BOOLEAN NOT NULL
+    );
+    ";
+
+    fn pg_error<E: std::fmt::Display>(e: E) -> Error {
+        Error::IndexProviderError(format!("PostgreSQL: {}", e))
+    }
+
+    fn to_block_id(block_id: i32) -> Result<u32, Error> {
+        u32::try_from(block_id).map_err(|_| {
+            Error::CorruptionError(format!("Negative MARF block identifier {}", block_id))
+        })
+    }
+
+    fn to_u64(x: i64) -> Result<u64, Error> {
+        u64::try_from(x).map_err(|_| Error::CorruptionError(format!("Negative blob offset {}", x)))
+    }
+
+    fn to_i64(x: u64) -> Result<i64, Error> {
+        i64::try_from(x).map_err(|_| Error::CorruptionError(format!("Blob offset too big: {}", x)))
+    }
+
+    fn to_trie_id<T: MarfTrieId>(bytes: &[u8]) -> Result<T, Error> {
+        let mut buf = [0u8; 32];
+        if bytes.len() != buf.len() {
+            return Err(Error::CorruptionError(format!(
+                "Invalid MARF block hash length {}",
+                bytes.len()
+            )));
+        }
+        buf.copy_from_slice(bytes);
+        Ok(T::from_bytes(buf))
+    }
+
+    /// The trie block index in a PostgreSQL database.
+    /// Each call checks a connection out of a pool, so a single instance can be shared by
+    /// concurrent readers.
+    pub struct PostgresTrieIndex {
+        pool: Pool<PostgresConnectionManager<NoTls>>,
+    }
+
+    impl PostgresTrieIndex {
+        /// Connect to the database at `conn_str` (a libpq-style connection string or URL), with
+        /// at most `max_connections` pooled connections.
+        pub fn open(conn_str: &str, max_connections: u32) -> Result<PostgresTrieIndex, Error> {
+            let config = conn_str.parse::<postgres::Config>().map_err(pg_error)?;
+            let manager = PostgresConnectionManager::new(config, NoTls);
+            let pool = Pool::builder()
+                .max_size(max_connections)
+                .build(manager)
+                .map_err(pg_error)?;
+            Ok(PostgresTrieIndex { pool })
+        }
+
+        fn conn(&self) -> Result<PooledConnection<PostgresConnectionManager<NoTls>>, Error> {
+            self.pool.get().map_err(pg_error)
+        }
+
+        fn query_block_id(&self, sql: &str, bhh: &[u8]) -> Result<Option<u32>, Error> {
+            self.conn()?
+                .query_opt(sql, &[&bhh])
+                .map_err(pg_error)?
+                .map(|row| to_block_id(row.get(0)))
+                .transpose()
+        }
+    }
+
+    impl<T: MarfTrieId> TrieIndexProvider<T> for PostgresTrieIndex {
+        fn index_type(&self) -> TrieIndexType {
+            TrieIndexType::Postgres
+        }
+
+        fn create_tables_if_needed(&self) -> Result<(), Error> {
+            self.conn()?
+                .batch_execute(PG_MARF_DATA_TABLE)
+                .map_err(pg_error)
+        }
+
+        fn get_block_identifier(&self, bhh: &T) -> Result<u32, Error> {
+            self.query_block_id(
+                "SELECT block_id FROM marf_data WHERE block_hash = $1",
+                bhh.as_bytes(),
+            )?
+            .ok_or(Error::NotFoundError)
+        }
+
+        fn get_confirmed_block_identifier(&self, bhh: &T) -> Result<Option<u32>, Error> {
+            self.query_block_id(
+                "SELECT block_id FROM marf_data WHERE block_hash = $1 AND NOT unconfirmed",
+                bhh.as_bytes(),
+            )
+        }
+
+        fn get_block_hash(&self, block_id: u32) -> Result<T, Error> {
+            let row = self
+                .conn()?
+                .query_opt(
+                    "SELECT block_hash FROM marf_data WHERE block_id = $1",
+                    &[&(block_id as i32)],
+                )
+                .map_err(pg_error)?
+                .ok_or_else(|| {
+                    error!("Failed to get block header hash of local ID {}", block_id);
+                    Error::NotFoundError
+                })?;
+            to_trie_id(row.get::<_, &[u8]>(0))
+        }
+
+        fn get_entry(&self, block_id: u32) -> Result<Option<TrieIndexEntry<T>>, Error> {
+            let row = match self
+                .conn()?
+                .query_opt(
+                    "SELECT block_id, block_hash, external_offset, external_length, unconfirmed FROM marf_data WHERE block_id = $1",
+                    &[&(block_id as i32)],
+                )
+                .map_err(pg_error)?
+            {
+                Some(row) => row,
+                None => {
+                    return Ok(None);
+                }
+            };
+            Ok(Some(TrieIndexEntry {
+                block_id: to_block_id(row.get(0))?,
+                block_hash: to_trie_id(row.get::<_, &[u8]>(1))?,
+                external_offset: to_u64(row.get(2))?,
+                external_length: to_u64(row.get(3))?,
+                unconfirmed: row.get(4),
+            }))
+        }
+
+        fn get_external_trie_offset_length(&self, block_id: u32) -> Result<(u64, u64), Error> {
+            let row = self
+                .conn()?
+                .query_opt(
+                    "SELECT external_offset, external_length FROM marf_data WHERE block_id = $1",
+                    &[&(block_id as i32)],
+                )
+                .map_err(pg_error)?
+                .ok_or(Error::NotFoundError)?;
+            Ok((to_u64(row.get(0))?, to_u64(row.get(1))?))
+        }
+
+        fn get_external_blobs_length(&self) -> Result<u64, Error> {
+            let row = self
+                .conn()?
+                .query_one(
+                    "SELECT COALESCE(MAX(external_offset + external_length), 0) FROM marf_data",
+                    &[],
+                )
+                .map_err(pg_error)?;
+            to_u64(row.get(0))
+        }
+
+        fn count_blocks(&self) -> Result<u32, Error> {
+            let row = self
+                .conn()?
+                .query_one(
+                    "SELECT COALESCE(MAX(block_id), 0) FROM marf_data WHERE NOT unconfirmed",
+                    &[],
+                )
+                .map_err(pg_error)?;
+            to_block_id(row.get(0))
+        }
+
+        fn is_unconfirmed_block(&self, block_id: u32) -> Result<bool, Error> {
+            let row = self
+                .conn()?
+                .query_opt(
+                    "SELECT unconfirmed FROM marf_data WHERE block_id = $1",
+                    &[&(block_id as i32)],
+                )
+                .map_err(pg_error)?
+                .ok_or(Error::NotFoundError)?;
+            Ok(row.get(0))
+        }
+
+        fn write_external_trie_blob(
+            &self,
+            bhh: &T,
+            offset: u64,
+            length: u64,
+        ) -> Result<u32, Error> {
+            let row = self
+                .conn()?
+                .query_one(
+                    "INSERT INTO marf_data (block_hash, unconfirmed, external_offset, external_length) VALUES ($1, FALSE, $2, $3) RETURNING block_id",
+                    &[&bhh.as_bytes(), &to_i64(offset)?, &to_i64(length)?],
+                )
+                .map_err(pg_error)?;
+            let block_id = to_block_id(row.get(0))?;
+
+            debug!(
+                "Wrote block trie {} to block ID {} offset {}",
+                bhh, block_id, offset
+            );
+            Ok(block_id)
+        }
+
+        fn insert_entry(&self, entry: &TrieIndexEntry<T>) -> Result<(), Error> {
+            let mut conn = self.conn()?;
+            let mut tx = conn.transaction().map_err(pg_error)?;
+            tx.execute(
+                "INSERT INTO marf_data (block_id, block_hash, unconfirmed, external_offset, external_length) VALUES ($1, $2, $3, $4, $5)",
+                &[
+                    &(entry.block_id as i32),
+                    &entry.block_hash.as_bytes(),
+                    &entry.unconfirmed,
+                    &to_i64(entry.external_offset)?,
+                    &to_i64(entry.external_length)?,
+                ],
+            )
+            .map_err(pg_error)?;
+            // keep the identity sequence ahead of explicitly-inserted identifiers
+            tx.execute(
+                "SELECT setval(pg_get_serial_sequence('marf_data', 'block_id'), (SELECT MAX(block_id) FROM marf_data))",
+                &[],
+            )
+            .map_err(pg_error)?;
+            tx.commit().map_err(pg_error)
+        }
+
+        fn prune_entry(&self, block_id: u32) -> Result<(), Error> {
+            let mut conn = self.conn()?;
+            let mut tx = conn.transaction().map_err(pg_error)?;
+            let num_pruned = tx
+                .execute(
+                    "INSERT INTO marf_pruned_tries (block_id, block_hash, external_offset, external_length, reclaimed) \
+                     SELECT block_id, block_hash, external_offset, external_length, FALSE FROM marf_data WHERE block_id = $1 AND NOT unconfirmed",
+                    &[&(block_id as i32)],
+                )
+                .map_err(pg_error)?;
+            if num_pruned == 0 {
+                return Err(Error::NotFoundError);
+            }
+            tx.execute(
+                "DELETE FROM marf_data WHERE block_id = $1",
+                &[&(block_id as i32)],
+            )
+            .map_err(pg_error)?;
+            tx.commit().map_err(pg_error)
+        }
+
+        fn is_pruned_block(&self, bhh: &T) -> Result<bool, Error> {
+            let row = self
+                .conn()?
+                .query_opt(
+                    "SELECT 1 FROM marf_pruned_tries WHERE block_hash = $1",
+                    &[&bhh.as_bytes()],
+                )
+                .map_err(pg_error)?;
+            Ok(row.is_some())
+        }
+
+        fn get_unreclaimed_pruned_entries(&self) -> Result<Vec<TrieIndexEntry<T>>, Error> {
+            let rows = self
+                .conn()?
+                .query(
+                    "SELECT block_id, block_hash, external_offset, external_length FROM marf_pruned_tries WHERE NOT reclaimed ORDER BY block_id",
+                    &[],
+                )
+                .map_err(pg_error)?;
+            rows.iter()
+                .map(|row| {
+                    Ok(TrieIndexEntry {
+                        block_id: to_block_id(row.get(0))?,
+                        block_hash: to_trie_id(row.get::<_, &[u8]>(1))?,
+                        external_offset: to_u64(row.get(2))?,
+                        external_length: to_u64(row.get(3))?,
+                        unconfirmed: false,
+                    })
+                })
+                .collect()
+        }
+
+        fn set_pruned_entry_reclaimed(&self, block_id: u32) -> Result<(), Error> {
+            self.conn()?
+                .execute(
+                    "UPDATE marf_pruned_tries SET reclaimed = TRUE WHERE block_id = $1",
+                    &[&(block_id as i32)],
+                )
+                .map_err(pg_error)?;
+            Ok(())
+        }
+    }
+}
@@ -454,6 +454,21 @@ pub fn read_all_block_hashes_and_roots<T: MarfTrieId>(
     rows.collect()
 }
 
+/// Get the block IDs and hashes of all confirmed tries, in the order in which they were stored
+pub fn read_confirmed_block_ids_and_hashes<T: MarfTrieId>(
+    conn: &Connection,
+) -> Result<Vec<(u32, T)>, Error> {
+    let mut s = conn.prepare(
+        "SELECT block_id, block_hash FROM marf_data WHERE unconfirmed = 0 ORDER BY block_id",
+    )?;
+    let rows = s.query_and_then(NO_PARAMS, |row| {
+        let block_id: u32 = row.get_unwrap("block_id");
+        let block_hash: T = row.get_unwrap("block_hash");
+        Ok((block_id, block_hash))
+    })?;
+    rows.collect()
+}
+
 /// Read a node's hash from a sqlite-stored blob, given the block ID
 pub fn read_node_hash_bytes<W: Write>(
     conn: &Connection,
@@ -472,6 +487,19 @@ pub fn read_node_hash_bytes<W: Write>(
     w.write_all(&hash_buff).map_err(|e| e.into())
 }
 
+/// Overwrite a node's hash in a sqlite-stored blob, given the block ID
+pub fn write_node_hash_bytes(
+    conn: &Connection,
+    block_id: u32,
+    ptr: &TriePtr,
+    hash: &TrieHash,
+) -> Result<(), Error> {
+    let mut blob = open_trie_blob(conn, block_id)?;
+    blob.seek(SeekFrom::Start(ptr.ptr() as u64))?;
+    blob.write_all(hash.as_bytes())?;
+    Ok(())
+}
+
 /// Read a node's hash from a sqlite-stored blob, given its block header hash
 pub fn read_node_hash_bytes_by_bhh<W: Write, T: MarfTrieId>(
     conn: &Connection,
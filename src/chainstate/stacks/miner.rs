@@ -40,6 +40,7 @@ use crate::chainstate::stacks::db::{
     MINER_REWARD_MATURITY,
 };
 use crate::chainstate::stacks::events::{StacksTransactionEvent, StacksTransactionReceipt};
+use crate::chainstate::stacks::hash_cache::{self, CachedTransaction};
 use crate::chainstate::stacks::Error;
 use crate::chainstate::stacks::*;
 use crate::clarity_vm::clarity::{ClarityConnection, ClarityInstance};
@@ -53,7 +54,6 @@ use crate::types::StacksPublicKeyBuffer;
 use clarity::vm::database::BurnStateDB;
 use serde::Deserialize;
 use stacks_common::util::get_epoch_time_ms;
-use stacks_common::util::hash::MerkleTree;
 use stacks_common::util::hash::Sha512Trunc256Sum;
 use stacks_common::util::secp256k1::{MessageSignature, Secp256k1PrivateKey};
 use stacks_common::util::vrf::*;
@@ -73,6 +73,7 @@ use clarity::vm::ast::ASTRules;
 use clarity::vm::clarity::TransactionConnection;
 use clarity::vm::errors::Error as InterpreterError;
 use clarity::vm::types::TypeSignature;
+use clarity::vm::Value;
 
 /// System status for mining.
 /// The miner can be Ready, in which case a miner is allowed to run
@@ -167,6 +168,9 @@ pub fn set_mining_spend_amount(miner_status: Arc<Mutex<MinerStatus>>, amt: u64)
         .set_spend_amount(amt);
 }
 
+/// Maximum number of transaction bundles a miner will try to include in one anchored block
+pub const MAX_TX_BUNDLES_PER_BLOCK: u64 = 8;
+
 #[derive(Debug, Clone)]
 pub struct BlockBuilderSettings {
     pub max_miner_time_ms: u64,
@@ -789,10 +793,7 @@ impl<'a> StacksMicroblockBuilder<'a> {
             return Err(Error::NoTransactionsToMine);
         }
 
-        let txid_vecs = txs.iter().map(|tx| tx.txid().as_bytes().to_vec()).collect();
-
-        let merkle_tree = MerkleTree::<Sha512Trunc256Sum>::new(&txid_vecs);
-        let tx_merkle_root = merkle_tree.root();
+        let tx_merkle_root = hash_cache::tx_merkle_root(&txs);
         let mut next_microblock_header = if let Some(ref prev_microblock) = prev_microblock_header {
             StacksMicroblockHeader::from_parent_unsigned(prev_microblock, &tx_merkle_root)
                 .ok_or(Error::MicroblockStreamTooLongError)?
@@ -937,6 +938,7 @@ impl<'a> StacksMicroblockBuilder<'a> {
         if let Err(e) = Relayer::static_check_problematic_relayed_tx(
             clarity_tx.config.mainnet,
             clarity_tx.get_epoch(),
+            &clarity_tx.get_vm_limits(),
             &tx,
             ast_rules,
         ) {
@@ -949,7 +951,10 @@ impl<'a> StacksMicroblockBuilder<'a> {
 
         let quiet = !cfg!(test);
         match StacksChainState::process_transaction(clarity_tx, &tx, quiet, ast_rules) {
-            Ok((fee, receipt)) => Ok(TransactionResult::success(&tx, fee, receipt)),
+            Ok((fee, mut receipt)) => {
+                receipt.tx_size = tx_len;
+                Ok(TransactionResult::success(&tx, fee, receipt))
+            }
             Err(e) => {
                 let (is_problematic, e) =
                     TransactionResult::is_problematic(&tx, e, clarity_tx.get_epoch());
@@ -1021,15 +1026,14 @@ impl<'a> StacksMicroblockBuilder<'a> {
 
         let mut result = Ok(());
         for (tx, tx_len) in txs_and_lens.into_iter() {
-            if considered.contains(&tx.txid()) {
+            let tx = CachedTransaction::new(tx);
+            if !considered.insert(tx.txid()) {
                 continue;
-            } else {
-                considered.insert(tx.txid());
             }
 
             match StacksMicroblockBuilder::mine_next_transaction(
                 &mut clarity_tx,
-                tx.clone(),
+                tx.tx().clone(),
                 tx_len,
                 bytes_so_far,
                 &block_limit_hit,
@@ -1042,7 +1046,7 @@ impl<'a> StacksMicroblockBuilder<'a> {
                             test_debug!("Include tx {} in microblock", tx.txid());
                             bytes_so_far += tx_len;
                             num_txs += 1;
-                            txs_included.push(tx);
+                            txs_included.push(tx.into_inner());
                         }
                         TransactionResult::Skipped(TransactionSkipped { error, .. })
                         | TransactionResult::ProcessingError(TransactionError { error, .. }) => {
@@ -1186,6 +1190,11 @@ impl<'a> StacksMicroblockBuilder<'a> {
                             considered.insert(mempool_tx.tx.txid());
                         }
 
+                        if !mempool_settings.anchor_policy.can_mine(&mempool_tx.tx, false) {
+                            return Ok(Some(TransactionResult::skipped(
+                                &mempool_tx.tx, "Transaction not allowed in microblocks by anchor mode policy.".to_string()).convert_to_event()));
+                        }
+
                         match StacksMicroblockBuilder::mine_next_transaction(
                             clarity_tx,
                             mempool_tx.tx.clone(),
@@ -1621,6 +1630,7 @@ impl StacksBlockBuilder {
             if let Err(e) = Relayer::static_check_problematic_relayed_tx(
                 clarity_tx.config.mainnet,
                 clarity_tx.get_epoch(),
+                &clarity_tx.get_vm_limits(),
                 &tx,
                 ast_rules,
             ) {
@@ -1633,7 +1643,10 @@ impl StacksBlockBuilder {
             let (fee, receipt) = match StacksChainState::process_transaction(
                 clarity_tx, tx, quiet, ast_rules,
             ) {
-                Ok((fee, receipt)) => (fee, receipt),
+                Ok((fee, mut receipt)) => {
+                    receipt.tx_size = tx_len;
+                    (fee, receipt)
+                }
                 Err(e) => {
                     let (is_problematic, e) =
                         TransactionResult::is_problematic(&tx, e, clarity_tx.get_epoch());
@@ -1702,6 +1715,7 @@ impl StacksBlockBuilder {
             if let Err(e) = Relayer::static_check_problematic_relayed_tx(
                 clarity_tx.config.mainnet,
                 clarity_tx.get_epoch(),
+                &clarity_tx.get_vm_limits(),
                 &tx,
                 ast_rules,
             ) {
@@ -1714,7 +1728,10 @@ impl StacksBlockBuilder {
             let (fee, receipt) = match StacksChainState::process_transaction(
                 clarity_tx, tx, quiet, ast_rules,
             ) {
-                Ok((fee, receipt)) => (fee, receipt),
+                Ok((fee, mut receipt)) => {
+                    receipt.tx_size = tx_len;
+                    (fee, receipt)
+                }
                 Err(e) => {
                     let (is_problematic, e) =
                         TransactionResult::is_problematic(&tx, e, clarity_tx.get_epoch());
@@ -1829,14 +1846,7 @@ impl StacksBlockBuilder {
 
     pub fn finalize_block(&mut self, clarity_tx: &mut ClarityTx) -> StacksBlock {
         // done!  Calculate state root and tx merkle root
-        let txid_vecs = self
-            .txs
-            .iter()
-            .map(|tx| tx.txid().as_bytes().to_vec())
-            .collect();
-
-        let merkle_tree = MerkleTree::<Sha512Trunc256Sum>::new(&txid_vecs);
-        let tx_merkle_root = merkle_tree.root();
+        let tx_merkle_root = hash_cache::tx_merkle_root(&self.txs);
         let state_root_hash = clarity_tx.seal();
 
         self.header.tx_merkle_root = tx_merkle_root;
@@ -1895,14 +1905,7 @@ impl StacksBlockBuilder {
 
     /// Cut the next microblock.
     pub fn mine_next_microblock<'a>(&mut self) -> Result<StacksMicroblock, Error> {
-        let txid_vecs = self
-            .micro_txs
-            .iter()
-            .map(|tx| tx.txid().as_bytes().to_vec())
-            .collect();
-
-        let merkle_tree = MerkleTree::<Sha512Trunc256Sum>::new(&txid_vecs);
-        let tx_merkle_root = merkle_tree.root();
+        let tx_merkle_root = hash_cache::tx_merkle_root(&self.micro_txs);
         let mut next_microblock_header =
             if self.prev_microblock_header.tx_merkle_root == Sha512Trunc256Sum([0u8; 32]) {
                 // .prev_block is the hash of the parent anchored block
@@ -2130,6 +2133,7 @@ impl StacksBlockBuilder {
             self.parent_consensus_hash,
             self.parent_header_hash,
             &info.parent_microblocks,
+            &[],
             info.mainnet,
             Some(self.miner_id),
         )?;
@@ -2336,6 +2340,126 @@ impl StacksBlockBuilder {
         Ok(builder)
     }
 
+    /// Did a transaction in a bundle succeed?  Transactions in a bundle must not only be mined,
+    /// but also take effect, so aborted transactions count as failures.
+    fn is_tx_bundle_success(tx_result: &TransactionResult) -> bool {
+        match tx_result {
+            TransactionResult::Success(TransactionSuccess { receipt, .. }) => {
+                if receipt.post_condition_aborted {
+                    return false;
+                }
+                match receipt.result {
+                    Value::Response(ref data) => data.committed,
+                    _ => true,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Mine the given transaction bundles, in order, into the block being built.
+    /// Returns the result of each transaction mined, or, if a bundle could not be mined in
+    /// full, that bundle's index and whether it failed for lack of space in the block.  A
+    /// bundle's transactions can't be rolled back once they are mined, so the caller must roll
+    /// back the block if a bundle fails.
+    fn try_mine_tx_bundles(
+        &mut self,
+        epoch_tx: &mut ClarityTx,
+        bundles: &[StacksTransactionBundle],
+        ast_rules: ASTRules,
+    ) -> Result<Vec<TransactionResult>, (usize, bool)> {
+        let mut tx_results = vec![];
+        for (i, bundle) in bundles.iter().enumerate() {
+            for tx in bundle.txs.iter() {
+                let tx_result = self.try_mine_tx_with_len(
+                    epoch_tx,
+                    tx,
+                    tx.tx_len(),
+                    &BlockLimitFunction::NO_LIMIT_HIT,
+                    ast_rules.clone(),
+                );
+                if StacksBlockBuilder::is_tx_bundle_success(&tx_result) {
+                    tx_results.push(tx_result);
+                    continue;
+                }
+                let out_of_space = match tx_result {
+                    TransactionResult::Skipped(TransactionSkipped { ref error, .. })
+                    | TransactionResult::ProcessingError(TransactionError { ref error, .. }) => {
+                        match error {
+                            Error::BlockTooBigError | Error::CostOverflowError(..) => true,
+                            _ => false,
+                        }
+                    }
+                    _ => false,
+                };
+                debug!("Transaction bundle cannot be mined in full";
+                       "bundle_id" => %bundle.bundle_id(),
+                       "txid" => %tx.txid(),
+                       "out_of_space" => out_of_space);
+                return Err((i, out_of_space));
+            }
+        }
+        Ok(tx_results)
+    }
+
+    /// Find which of the given transaction bundles can be mined, in full, right after the
+    /// coinbase of the block being built.  The bundles are tried in order in a throwaway copy of
+    /// the block, which is rebuilt without a bundle whenever that bundle fails.  Bundles are only
+    /// tried until `deadline`, after which none are selected, so that they can't use up the
+    /// miner's time budget.
+    /// Returns the bundles to mine, and the IDs of the bundles that failed for reasons other
+    /// than running out of space in the block, which should be dropped from the mempool.
+    fn select_tx_bundles(
+        chainstate_handle: &StacksChainState,
+        burn_dbconn: &SortitionDBConn,
+        parent_stacks_header: &StacksHeaderInfo,
+        proof: &VRFProof,
+        total_burn: u64,
+        pubkey_hash: &Hash160,
+        coinbase_tx: &StacksTransaction,
+        mut candidates: Vec<StacksTransactionBundle>,
+        deadline: u128,
+    ) -> Result<(Vec<StacksTransactionBundle>, Vec<Sha512Trunc256Sum>), Error> {
+        let mut to_drop = vec![];
+        while candidates.len() > 0 {
+            if get_epoch_time_ms() >= deadline {
+                debug!(
+                    "Miner mining time exceeded while selecting transaction bundles; {} not tried",
+                    candidates.len()
+                );
+                candidates.clear();
+                break;
+            }
+
+            let (mut chainstate, _) = chainstate_handle.reopen()?;
+            let mut builder = StacksBlockBuilder::make_block_builder(
+                chainstate.network.mainnet,
+                parent_stacks_header,
+                proof.clone(),
+                total_burn,
+                pubkey_hash.clone(),
+            )?;
+            let mut miner_epoch_info = builder.pre_epoch_begin(&mut chainstate, burn_dbconn)?;
+            let ast_rules = miner_epoch_info.ast_rules;
+            let (mut epoch_tx, _) = builder.epoch_begin(burn_dbconn, &mut miner_epoch_info)?;
+            builder.try_mine_tx(&mut epoch_tx, coinbase_tx, ast_rules.clone())?;
+
+            let failed = builder.try_mine_tx_bundles(&mut epoch_tx, &candidates, ast_rules);
+            epoch_tx.rollback_block();
+
+            match failed {
+                Ok(_) => break,
+                Err((i, out_of_space)) => {
+                    let bundle = candidates.remove(i);
+                    if !out_of_space {
+                        to_drop.push(bundle.bundle_id());
+                    }
+                }
+            }
+        }
+        Ok((candidates, to_drop))
+    }
+
     /// Given access to the mempool, mine an anchored block with no more than the given execution cost.
     ///   returns the assembled block, and the consumed execution budget.
     pub fn build_anchored_block(
@@ -2350,9 +2474,6 @@ impl StacksBlockBuilder {
         settings: BlockBuilderSettings,
         event_observer: Option<&dyn MemPoolEventDispatcher>,
     ) -> Result<(StacksBlock, ExecutionCost, u64), Error> {
-        let mempool_settings = settings.mempool_settings;
-        let max_miner_time_ms = settings.max_miner_time_ms;
-
         if let TransactionPayload::Coinbase(..) = coinbase_tx.payload {
         } else {
             return Err(Error::MemPoolError(
@@ -2360,29 +2481,110 @@ impl StacksBlockBuilder {
             ));
         }
 
-        let (tip_consensus_hash, tip_block_hash, tip_height) = (
-            parent_stacks_header.consensus_hash.clone(),
-            parent_stacks_header.anchored_header.block_hash(),
-            parent_stacks_header.stacks_block_height,
-        );
-
         debug!(
             "Build anchored block off of {}/{} height {}",
-            &tip_consensus_hash, &tip_block_hash, tip_height
+            &parent_stacks_header.consensus_hash,
+            &parent_stacks_header.anchored_header.block_hash(),
+            parent_stacks_header.stacks_block_height
         );
 
+        let ts_start = get_epoch_time_ms();
+        let deadline = ts_start + (settings.max_miner_time_ms as u128);
+
+        let mut candidate_bundles = mempool.get_tx_bundles(MAX_TX_BUNDLES_PER_BLOCK)?;
+        if candidate_bundles.len() > 0 {
+            // mined bundles are kept until the chain we build on has used their nonces
+            let (mut chainstate, _) = chainstate_handle.reopen()?;
+            if let Some(dropped) = chainstate.with_read_only_clarity_tx(
+                burn_dbconn,
+                &parent_stacks_header.index_block_hash(),
+                |clarity_tx| mempool.drop_confirmed_tx_bundles(clarity_tx),
+            ) {
+                if dropped?.len() > 0 {
+                    candidate_bundles = mempool.get_tx_bundles(MAX_TX_BUNDLES_PER_BLOCK)?;
+                }
+            }
+        }
+
+        let (mut tx_bundles, bundles_to_drop) = StacksBlockBuilder::select_tx_bundles(
+            chainstate_handle,
+            burn_dbconn,
+            parent_stacks_header,
+            &proof,
+            total_burn,
+            &pubkey_hash,
+            coinbase_tx,
+            candidate_bundles,
+            deadline,
+        )?;
+        if bundles_to_drop.len() > 0 {
+            mempool.drop_tx_bundles(&bundles_to_drop)?;
+        }
+
+        // a bundle that was found to be mineable can still fail in the real block, in which case
+        // the block is rebuilt without it
+        loop {
+            let built = StacksBlockBuilder::build_anchored_block_with_bundles(
+                chainstate_handle,
+                burn_dbconn,
+                mempool,
+                parent_stacks_header,
+                total_burn,
+                proof.clone(),
+                pubkey_hash.clone(),
+                coinbase_tx,
+                &settings,
+                event_observer,
+                &tx_bundles,
+                ts_start,
+            )?;
+            match built {
+                Ok(result) => return Ok(result),
+                Err((i, out_of_space)) => {
+                    let bundle = tx_bundles.remove(i);
+                    warn!("Failed to mine transaction bundle in full; rebuilding block without it";
+                          "bundle_id" => %bundle.bundle_id(),
+                          "out_of_space" => out_of_space);
+                    if !out_of_space {
+                        mempool.drop_tx_bundles(&[bundle.bundle_id()])?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Mine an anchored block that starts with the coinbase and then `tx_bundles`, followed by
+    /// as many mempool transactions as fit.  Returns the assembled block, the consumed execution
+    /// budget and the block size, or, if a bundle could not be mined in full, that bundle's index
+    /// and whether it failed for lack of space in the block.
+    fn build_anchored_block_with_bundles(
+        chainstate_handle: &StacksChainState,
+        burn_dbconn: &SortitionDBConn,
+        mempool: &mut MemPoolDB,
+        parent_stacks_header: &StacksHeaderInfo,
+        total_burn: u64,
+        proof: VRFProof,
+        pubkey_hash: Hash160,
+        coinbase_tx: &StacksTransaction,
+        settings: &BlockBuilderSettings,
+        event_observer: Option<&dyn MemPoolEventDispatcher>,
+        tx_bundles: &[StacksTransactionBundle],
+        ts_start: u128,
+    ) -> Result<Result<(StacksBlock, ExecutionCost, u64), (usize, bool)>, Error> {
+        let mempool_settings = settings.mempool_settings.clone();
+        let max_miner_time_ms = settings.max_miner_time_ms;
+        let tip_height = parent_stacks_header.stacks_block_height;
+
         let (mut chainstate, _) = chainstate_handle.reopen()?;
 
         let mut builder = StacksBlockBuilder::make_block_builder(
-            chainstate.mainnet,
+            chainstate.network.mainnet,
             parent_stacks_header,
             proof,
             total_burn,
             pubkey_hash,
         )?;
 
-        let ts_start = get_epoch_time_ms();
-
         let mut miner_epoch_info = builder.pre_epoch_begin(&mut chainstate, burn_dbconn)?;
         let ast_rules = miner_epoch_info.ast_rules;
         if ast_rules != ASTRules::Typical {
@@ -2422,6 +2624,29 @@ impl StacksBlockBuilder {
         let mut num_txs = 0;
         let mut blocked = false;
 
+        // mine the transaction bundles right after the coinbase, since that is where they were
+        // found to be mineable in full
+        let bundle_tx_results =
+            match builder.try_mine_tx_bundles(&mut epoch_tx, tx_bundles, ast_rules.clone()) {
+                Ok(tx_results) => tx_results,
+                Err(failed) => {
+                    epoch_tx.rollback_block();
+                    return Ok(Err(failed));
+                }
+            };
+        let bundle_txs = tx_bundles.iter().flat_map(|bundle| bundle.txs.iter());
+        for (tx, tx_result) in bundle_txs.zip(bundle_tx_results.into_iter()) {
+            tx_events.push(tx_result.convert_to_event());
+            considered.insert(tx.txid());
+            mined_origin_nonces.insert(tx.origin_address(), tx.get_origin_nonce());
+            if let (Some(sponsor_addr), Some(sponsor_nonce)) =
+                (tx.sponsor_address(), tx.get_sponsor_nonce())
+            {
+                mined_sponsor_nonces.insert(sponsor_addr, sponsor_nonce);
+            }
+            num_txs += 1;
+        }
+
         debug!(
             "Anchored block transaction selection begins (child of {})",
             &parent_stacks_header.anchored_header.block_hash()
@@ -2503,6 +2728,17 @@ impl StacksBlockBuilder {
                         considered.insert(txinfo.tx.txid());
                         num_considered += 1;
 
+                        if !mempool_settings.anchor_policy.can_mine(&txinfo.tx, true) {
+                            return Ok(Some(
+                                TransactionResult::skipped(
+                                    &txinfo.tx,
+                                    "Transaction not allowed in anchored blocks by anchor mode policy."
+                                        .to_string(),
+                                )
+                                .convert_to_event(),
+                            ));
+                        }
+
                         let tx_result = builder.try_mine_tx_with_len(
                             epoch_tx,
                             &txinfo.tx,
@@ -2664,6 +2900,6 @@ impl StacksBlockBuilder {
             })
         );
 
-        Ok((block, consumed, size))
+        Ok(Ok((block, consumed, size)))
     }
 }
@@ -16,14 +16,13 @@
 
 use std::convert::From;
 use std::convert::TryFrom;
-use std::error;
-use std::fmt;
 use std::io;
 use std::io::prelude::*;
 use std::io::{Read, Write};
 use std::ops::Deref;
 use std::ops::DerefMut;
 
+use clarity_derive::StacksError;
 use rusqlite::Error as RusqliteError;
 use sha2::{Digest, Sha512_256};
 
@@ -38,6 +37,7 @@ use crate::chainstate::stacks::index::Error as marf_error;
 use crate::clarity_vm::clarity::Error as clarity_error;
 use crate::codec::MAX_MESSAGE_LEN;
 use crate::net::Error as net_error;
+use crate::types::StacksPublicKeyBuffer;
 use crate::util_lib::db::DBConn;
 use crate::util_lib::db::Error as db_error;
 use crate::util_lib::strings::StacksString;
@@ -70,6 +70,7 @@ pub mod block;
 pub mod boot;
 pub mod db;
 pub mod events;
+pub mod hash_cache;
 pub mod index;
 pub mod miner;
 pub mod transaction;
@@ -90,46 +91,108 @@ pub const STACKS_BLOCK_VERSION_AST_PRECHECK_SIZE: u8 = 1;
 pub const MAX_BLOCK_LEN: u32 = 2 * 1024 * 1024;
 pub const MAX_TRANSACTION_LEN: u32 = MAX_BLOCK_LEN;
 
-#[derive(Debug)]
+#[derive(Debug, StacksError)]
+#[error(label = "chainstate")]
 pub enum Error {
+    #[error(code = 2001, display = "Invalid fee")]
     InvalidFee,
+    #[error(code = 2002)]
     InvalidStacksBlock(String),
+    #[error(code = 2003, display = "{0}")]
     InvalidStacksMicroblock(String, BlockHeaderHash),
     // The bool is true if the invalid transaction was quietly ignored.
+    #[error(code = 2004, display = "{0}")]
     InvalidStacksTransaction(String, bool),
     /// This error indicates that the considered transaction was skipped
     /// because of the current state of the block assembly algorithm,
     /// but the transaction otherwise may be valid (e.g., block assembly is
     /// only considering STX transfers and this tx isn't a transfer).
+    #[error(
+        code = 2005,
+        display = "Stacks transaction skipped during assembly due to: {0}"
+    )]
     StacksTransactionSkipped(String),
+    #[error(code = 2006)]
     PostConditionFailed(String),
+    #[error(code = 2007, display = "No such Stacks block")]
     NoSuchBlockError,
+    #[error(code = 2008, display = "Invalid chainstate database")]
     InvalidChainstateDB,
+    #[error(code = 2009, display = "Too much data in block")]
     BlockTooBigError,
+    #[error(code = 2010, display = "Too much data in transaction")]
     TransactionTooBigError,
+    #[error(code = 2011, display = "Block execution budget exceeded")]
     BlockCostExceeded,
+    #[error(code = 2012, display = "No transactions to mine")]
     NoTransactionsToMine,
+    #[error(code = 2013, display = "Too many microblocks in stream")]
     MicroblockStreamTooLongError,
+    #[error(
+        code = 2014,
+        display = "Spending condition is incompatible with this operation"
+    )]
     IncompatibleSpendingConditionError,
+    #[error(
+        code = 2015,
+        display = "Cost overflow: before={0:?}, after={1:?}, budget={2:?}"
+    )]
     CostOverflowError(ExecutionCost, ExecutionCost, ExecutionCost),
+    #[error(code = 2016, source)]
     ClarityError(clarity_error),
+    #[error(code = 2017, source)]
     DBError(db_error),
+    #[error(code = 2018, source)]
     NetError(net_error),
+    #[error(code = 2019, source)]
     CodecError(codec_error),
+    #[error(code = 2020, source)]
     MARFError(marf_error),
+    #[error(code = 2021, source)]
     ReadError(io::Error),
+    #[error(code = 2022, source)]
     WriteError(io::Error),
+    #[error(code = 2023)]
     MemPoolError(String),
+    #[error(code = 2024, display = "Account has already locked STX for PoX")]
     PoxAlreadyLocked,
+    #[error(code = 2025, display = "Not enough STX to lock")]
     PoxInsufficientBalance,
+    #[error(code = 2026, display = "No such reward cycle")]
     PoxNoRewardCycle,
+    #[error(
+        code = 2027,
+        display = "Account has not already locked STX for PoX extend"
+    )]
     PoxExtendNotLocked,
+    #[error(code = 2028, display = "PoX increase only allowed for pox-2 locks")]
     PoxIncreaseOnV1,
+    #[error(code = 2029, display = "PoX increase was invalid")]
     PoxInvalidIncrease,
+    #[error(
+        code = 2030,
+        display = "Account does not have the expected PoX STX lock to release"
+    )]
+    PoxUnlockNotLocked,
+    #[error(
+        code = 2031,
+        display = "A defunct PoX contract was called after transition"
+    )]
     DefunctPoxContract,
+    #[error(
+        code = 2032,
+        display = "Transaction {0} is problematic and will not be mined again"
+    )]
     ProblematicTransaction(Txid),
+    #[error(code = 2033, display = "Mining attempt aborted by signal")]
     MinerAborted,
+    #[error(code = 2034, display = "Channel '{0}' closed")]
     ChannelClosed(String),
+    #[error(
+        code = 2035,
+        display = "Block processing was preempted by a better block"
+    )]
+    BlockProcessingPreempted,
 }
 
 impl From<marf_error> for Error {
@@ -156,109 +219,6 @@ impl From<codec_error> for Error {
     }
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Error::InvalidFee => write!(f, "Invalid fee"),
-            Error::InvalidStacksBlock(ref s) => fmt::Display::fmt(s, f),
-            Error::InvalidStacksMicroblock(ref s, _) => fmt::Display::fmt(s, f),
-            Error::InvalidStacksTransaction(ref s, _) => fmt::Display::fmt(s, f),
-            Error::PostConditionFailed(ref s) => fmt::Display::fmt(s, f),
-            Error::NoSuchBlockError => write!(f, "No such Stacks block"),
-            Error::InvalidChainstateDB => write!(f, "Invalid chainstate database"),
-            Error::BlockTooBigError => write!(f, "Too much data in block"),
-            Error::TransactionTooBigError => write!(f, "Too much data in transaction"),
-            Error::BlockCostExceeded => write!(f, "Block execution budget exceeded"),
-            Error::MicroblockStreamTooLongError => write!(f, "Too many microblocks in stream"),
-            Error::IncompatibleSpendingConditionError => {
-                write!(f, "Spending condition is incompatible with this operation")
-            }
-            Error::CostOverflowError(ref c1, ref c2, ref c3) => write!(
-                f,
-                "{}",
-                &format!(
-                    "Cost overflow: before={:?}, after={:?}, budget={:?}",
-                    c1, c2, c3
-                )
-            ),
-            Error::ClarityError(ref e) => fmt::Display::fmt(e, f),
-            Error::DBError(ref e) => fmt::Display::fmt(e, f),
-            Error::NetError(ref e) => fmt::Display::fmt(e, f),
-            Error::CodecError(ref e) => fmt::Display::fmt(e, f),
-            Error::MARFError(ref e) => fmt::Display::fmt(e, f),
-            Error::ReadError(ref e) => fmt::Display::fmt(e, f),
-            Error::WriteError(ref e) => fmt::Display::fmt(e, f),
-            Error::MemPoolError(ref s) => fmt::Display::fmt(s, f),
-            Error::NoTransactionsToMine => write!(f, "No transactions to mine"),
-            Error::PoxAlreadyLocked => write!(f, "Account has already locked STX for PoX"),
-            Error::PoxExtendNotLocked => {
-                write!(f, "Account has not already locked STX for PoX extend")
-            }
-            Error::PoxInsufficientBalance => write!(f, "Not enough STX to lock"),
-            Error::PoxNoRewardCycle => write!(f, "No such reward cycle"),
-            Error::StacksTransactionSkipped(ref r) => {
-                write!(
-                    f,
-                    "Stacks transaction skipped during assembly due to: {}",
-                    r
-                )
-            }
-            Error::DefunctPoxContract => {
-                write!(f, "A defunct PoX contract was called after transition")
-            }
-            Error::ProblematicTransaction(ref txid) => write!(
-                f,
-                "Transaction {} is problematic and will not be mined again",
-                txid
-            ),
-            Error::PoxIncreaseOnV1 => write!(f, "PoX increase only allowed for pox-2 locks"),
-            Error::PoxInvalidIncrease => write!(f, "PoX increase was invalid"),
-            Error::MinerAborted => write!(f, "Mining attempt aborted by signal"),
-            Error::ChannelClosed(ref s) => write!(f, "Channel '{}' closed", s),
-        }
-    }
-}
-
-impl error::Error for Error {
-    fn cause(&self) -> Option<&dyn error::Error> {
-        match *self {
-            Error::InvalidFee => None,
-            Error::InvalidStacksBlock(ref _s) => None,
-            Error::InvalidStacksMicroblock(ref _s, ref _h) => None,
-            Error::InvalidStacksTransaction(ref _s, _q) => None,
-            Error::PostConditionFailed(ref _s) => None,
-            Error::NoSuchBlockError => None,
-            Error::InvalidChainstateDB => None,
-            Error::BlockTooBigError => None,
-            Error::TransactionTooBigError => None,
-            Error::BlockCostExceeded => None,
-            Error::MicroblockStreamTooLongError => None,
-            Error::IncompatibleSpendingConditionError => None,
-            Error::CostOverflowError(..) => None,
-            Error::ClarityError(ref e) => Some(e),
-            Error::DBError(ref e) => Some(e),
-            Error::NetError(ref e) => Some(e),
-            Error::CodecError(ref e) => Some(e),
-            Error::MARFError(ref e) => Some(e),
-            Error::ReadError(ref e) => Some(e),
-            Error::WriteError(ref e) => Some(e),
-            Error::MemPoolError(ref _s) => None,
-            Error::NoTransactionsToMine => None,
-            Error::PoxAlreadyLocked => None,
-            Error::PoxInsufficientBalance => None,
-            Error::PoxNoRewardCycle => None,
-            Error::PoxExtendNotLocked => None,
-            Error::DefunctPoxContract => None,
-            Error::StacksTransactionSkipped(ref _r) => None,
-            Error::ProblematicTransaction(ref _txid) => None,
-            Error::PoxIncreaseOnV1 => None,
-            Error::PoxInvalidIncrease => None,
-            Error::MinerAborted => None,
-            Error::ChannelClosed(ref _s) => None,
-        }
-    }
-}
-
 impl Error {
     fn name(&self) -> &'static str {
         match self {
@@ -293,8 +253,10 @@ impl Error {
             Error::ProblematicTransaction(ref _txid) => "ProblematicTransaction",
             Error::PoxIncreaseOnV1 => "PoxIncreaseOnV1",
             Error::PoxInvalidIncrease => "PoxInvalidIncrease",
+            Error::PoxUnlockNotLocked => "PoxUnlockNotLocked",
             Error::MinerAborted => "MinerAborted",
             Error::ChannelClosed(ref _s) => "ChannelClosed",
+            Error::BlockProcessingPreempted => "BlockProcessingPreempted",
         }
     }
 
@@ -833,6 +795,22 @@ pub struct StacksTransactionSigner {
     check_overlap: bool,
 }
 
+/// An experimental, all-or-nothing group of signed transactions.  A miner either includes every
+/// transaction in a bundle in the same anchored block, contiguously and in order, or includes
+/// none of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StacksTransactionBundle {
+    pub txs: Vec<StacksTransaction>,
+    /// public key of the account that assembled the bundle.  It must be the origin or sponsor of
+    /// one of the bundle's transactions.
+    pub submitter: StacksPublicKeyBuffer,
+    /// the submitter's signature over the bundle ID
+    pub signature: MessageSignature,
+}
+
+/// Maximum number of transactions in a bundle
+pub const MAX_TRANSACTIONS_PER_BUNDLE: u32 = 16;
+
 /// A block that contains blockchain-anchored data
 /// (corresponding to a LeaderBlockCommitOp)
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -64,6 +64,7 @@ use crate::core::*;
 use stacks_common::util::hash::MerkleTree;
 
 use clarity::vm::clarity::ClarityConnection;
+use stacks_common::types::NetworkId;
 
 // test that the bad (pre 2.1) microblock fee payment still works.  we have to support it for
 // eternity :(
@@ -312,7 +313,7 @@ fn test_bad_microblock_fees_pre_v210() {
                 }
 
                 let builder = StacksBlockBuilder::make_block_builder(
-                    chainstate.mainnet,
+                    chainstate.network.mainnet,
                     &parent_tip,
                     vrf_proof,
                     tip.total_burn,
@@ -633,7 +634,7 @@ fn test_bad_microblock_fees_fix_transition() {
                 }
 
                 let builder = StacksBlockBuilder::make_block_builder(
-                    chainstate.mainnet,
+                    chainstate.network.mainnet,
                     &parent_tip,
                     vrf_proof,
                     tip.total_burn,
@@ -987,7 +988,7 @@ fn test_get_block_info_v210() {
                 }
 
                 let builder = StacksBlockBuilder::make_block_builder(
-                    chainstate.mainnet,
+                    chainstate.network.mainnet,
                     &parent_tip,
                     vrf_proof,
                     tip.total_burn,
@@ -1036,8 +1037,7 @@ fn test_get_block_info_v210() {
                 &stacks_block_id,
                 |clarity_tx| {
                     let list_val = clarity_tx.with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity2,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -1289,7 +1289,7 @@ fn test_get_block_info_v210_no_microblocks() {
                     mblock_pubkey_hash
                 };
                 let builder = StacksBlockBuilder::make_block_builder(
-                    chainstate.mainnet,
+                    chainstate.network.mainnet,
                     &parent_tip,
                     vrf_proof,
                     tip.total_burn,
@@ -1338,8 +1338,7 @@ fn test_get_block_info_v210_no_microblocks() {
                 &stacks_block_id,
                 |clarity_tx| {
                     let list_val = clarity_tx.with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity2,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -1753,7 +1752,7 @@ fn test_coinbase_pay_to_alt_recipient_v210(pay_to_contract: bool) {
                 }
 
                 let builder = StacksBlockBuilder::make_block_builder(
-                    chainstate.mainnet,
+                    chainstate.network.mainnet,
                     &parent_tip,
                     vrf_proof,
                     tip.total_burn,
@@ -1805,8 +1804,7 @@ fn test_coinbase_pay_to_alt_recipient_v210(pay_to_contract: bool) {
                 &stacks_block_id,
                 |clarity_tx| {
                     let list_val = clarity_tx.with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity2,
                         PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                         None,
@@ -1836,8 +1834,7 @@ fn test_coinbase_pay_to_alt_recipient_v210(pay_to_contract: bool) {
 
                     if i >= 1 {
                         let miner_val = clarity_tx.with_readonly_clarity_env(
-                            false,
-                            CHAIN_ID_TESTNET,
+                            NetworkId::testnet(),
                             ClarityVersion::Clarity2,
                             PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                             None,
@@ -1912,8 +1909,7 @@ fn test_coinbase_pay_to_alt_recipient_v210(pay_to_contract: bool) {
         .with_read_only_clarity_tx(&sortdb.index_conn(), &stacks_block_id, |clarity_tx| {
             let recipient_balance_val = clarity_tx
                 .with_readonly_clarity_env(
-                    false,
-                    CHAIN_ID_TESTNET,
+                    NetworkId::testnet(),
                     ClarityVersion::Clarity2,
                     PrincipalData::parse("SP3Q4A5WWZ80REGBN0ZXNE540ECJ9JZ4A765Q5K2Q").unwrap(),
                     None,
@@ -76,6 +76,7 @@ use stacks_common::util::secp256k1::Secp256k1PrivateKey;
 
 use clarity::vm::clarity::ClarityConnection;
 use clarity::vm::test_util::TEST_BURN_STATE_DB;
+use stacks_common::types::NetworkId;
 
 #[test]
 fn test_build_anchored_blocks_empty() {
@@ -297,6 +298,186 @@ fn test_build_anchored_blocks_stx_transfers_single() {
     }
 }
 
+#[test]
+fn test_build_anchored_blocks_tx_bundles() {
+    let privks: Vec<_> = [
+        "42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01",
+        "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+    ]
+    .iter()
+    .map(|hex| StacksPrivateKey::from_hex(hex).unwrap())
+    .collect();
+    let addrs: Vec<_> = privks
+        .iter()
+        .map(|privk| {
+            StacksAddress::from_public_keys(
+                C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+                &AddressHashMode::SerializeP2PKH,
+                1,
+                &vec![StacksPublicKey::from_private(privk)],
+            )
+            .unwrap()
+        })
+        .collect();
+
+    let mut peer_config = TestPeerConfig::new(function_name!(), 2022, 2023);
+    peer_config.initial_balances = addrs
+        .iter()
+        .map(|addr| (addr.to_account_principal(), 1000000000))
+        .collect();
+
+    let mut peer = TestPeer::new(peer_config);
+
+    let chainstate_path = peer.chainstate_path.clone();
+
+    let recipient_addr_str = "ST1RFD5Q2QPK3E0F08HG9XDX7SSC7CNRS0QR0SGEV";
+    let recipient = StacksAddress::from_string(recipient_addr_str).unwrap();
+
+    // this bundle can be mined in full
+    let good_bundle = StacksTransactionBundle::new_signed(
+        (0..2)
+            .map(|nonce| {
+                make_user_stacks_transfer(
+                    &privks[0],
+                    nonce,
+                    200,
+                    &recipient.to_account_principal(),
+                    1,
+                )
+            })
+            .collect(),
+        &privks[0],
+    )
+    .unwrap();
+    // this bundle can't be mined, since its first transaction's nonce is too high
+    let bad_bundle = StacksTransactionBundle::new_signed(
+        (1..3)
+            .map(|nonce| {
+                make_user_stacks_transfer(
+                    &privks[1],
+                    nonce,
+                    300,
+                    &recipient.to_account_principal(),
+                    1,
+                )
+            })
+            .collect(),
+        &privks[1],
+    )
+    .unwrap();
+
+    for tenure_id in 0..3 {
+        let tip = SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+            .unwrap();
+
+        let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+            |ref mut miner,
+             ref mut sortdb,
+             ref mut chainstate,
+             vrf_proof,
+             ref parent_opt,
+             ref parent_microblock_header_opt| {
+                let parent_tip = match parent_opt {
+                    None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                    Some(block) => {
+                        let ic = sortdb.index_conn();
+                        let snapshot = SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                            &ic,
+                            &tip.sortition_id,
+                            &block.block_hash(),
+                        )
+                        .unwrap()
+                        .unwrap(); // succeeds because we don't fork
+                        StacksChainState::get_anchored_block_header_info(
+                            chainstate.db(),
+                            &snapshot.consensus_hash,
+                            &snapshot.winning_stacks_block_hash,
+                        )
+                        .unwrap()
+                        .unwrap()
+                    }
+                };
+
+                let parent_header_hash = parent_tip.anchored_header.block_hash();
+                let parent_consensus_hash = parent_tip.consensus_hash.clone();
+
+                let mut mempool =
+                    MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+
+                let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                if tenure_id == 1 {
+                    for bundle in [&good_bundle, &bad_bundle] {
+                        mempool
+                            .submit_tx_bundle(
+                                chainstate,
+                                &parent_consensus_hash,
+                                &parent_header_hash,
+                                bundle,
+                            )
+                            .unwrap();
+                    }
+                }
+
+                let build_block = |mempool: &mut MemPoolDB| {
+                    StacksBlockBuilder::build_anchored_block(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        mempool,
+                        &parent_tip,
+                        tip.total_burn,
+                        vrf_proof.clone(),
+                        Hash160([tenure_id as u8; 20]),
+                        &coinbase_tx,
+                        BlockBuilderSettings::max_value(),
+                        None,
+                    )
+                    .unwrap()
+                    .0
+                };
+
+                if tenure_id == 1 {
+                    // this block loses sortition
+                    let lost_block = build_block(&mut mempool);
+                    assert_eq!(lost_block.txs.len(), 3);
+                    assert_eq!(lost_block.txs[1..], good_bundle.txs[..]);
+
+                    // the bad bundle won't be tried again, but the good bundle is still there to
+                    // be mined in the next block
+                    assert!(mempool.has_tx_bundle(&good_bundle.bundle_id()).unwrap());
+                    assert!(!mempool.has_tx_bundle(&bad_bundle.bundle_id()).unwrap());
+                    assert_eq!(
+                        mempool.get_tx_bundles(10).unwrap(),
+                        vec![good_bundle.clone()]
+                    );
+                }
+
+                let anchored_block = build_block(&mut mempool);
+
+                if tenure_id == 2 {
+                    // the good bundle's nonces were used in the last block
+                    assert!(!mempool.has_tx_bundle(&good_bundle.bundle_id()).unwrap());
+                    assert!(mempool.get_tx_bundles(10).unwrap().is_empty());
+                }
+
+                (anchored_block, vec![])
+            },
+        );
+
+        peer.next_burnchain_block(burn_ops.clone());
+        peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+        if tenure_id == 1 {
+            // the good bundle was mined right after the coinbase, and nothing from the bad one
+            assert_eq!(stacks_block.txs.len(), 3);
+            assert_eq!(stacks_block.txs[1], good_bundle.txs[0]);
+            assert_eq!(stacks_block.txs[2], good_bundle.txs[1]);
+        } else if tenure_id == 2 {
+            assert_eq!(stacks_block.txs.len(), 1);
+        }
+    }
+}
+
 #[test]
 fn test_build_anchored_blocks_empty_with_builder_timeout() {
     let privk = StacksPrivateKey::from_hex(
@@ -3643,7 +3824,7 @@ fn test_contract_call_across_clarity_versions() {
                 let sort_ic = sortdb.index_conn();
 
                 let builder = StacksBlockBuilder::make_block_builder(
-                    chainstate.mainnet,
+                    chainstate.network.mainnet,
                     &parent_tip,
                     vrf_proof,
                     tip.total_burn,
@@ -3686,8 +3867,7 @@ fn test_contract_call_across_clarity_versions() {
             for tenure_id in 1..num_blocks {
                 clarity_tx
                     .with_readonly_clarity_env(
-                        false,
-                        CHAIN_ID_TESTNET,
+                        NetworkId::testnet(),
                         ClarityVersion::Clarity2,
                         PrincipalData::parse(&format!("{}", &addr_anchored)).unwrap(),
                         Some(PrincipalData::parse(&format!("{}", &addr_anchored)).unwrap()),
@@ -4761,20 +4941,9 @@ fn paramaterized_mempool_walk_test(
             .unwrap();
 
             if transaction_counter & 1 == 0 {
-                mempool_tx
-                    .execute(
-                        "UPDATE mempool SET fee_rate = ? WHERE txid = ?",
-                        rusqlite::params![Some(123.0), &txid],
-                    )
-                    .unwrap();
+                mempool_tx.update_fee_rate(&txid, Some(123.0)).unwrap();
             } else {
-                let none: Option<f64> = None;
-                mempool_tx
-                    .execute(
-                        "UPDATE mempool SET fee_rate = ? WHERE txid = ?",
-                        rusqlite::params![none, &txid],
-                    )
-                    .unwrap();
+                mempool_tx.update_fee_rate(&txid, None).unwrap();
             }
 
             mempool_tx.commit().unwrap();
@@ -57,6 +57,7 @@ use clarity::vm::costs::LimitedCostTracker;
 use crate::chainstate::stacks::miner::*;
 
 use stacks_common::consts::FIRST_BURNCHAIN_CONSENSUS_HASH;
+use stacks_common::types::NetworkId;
 use stacks_common::util::vrf::VRFPublicKey;
 
 use clarity::vm::clarity::ClarityConnection;
@@ -352,8 +353,8 @@ impl TestStacksNode {
         )
         .unwrap();
         let chainstate = open_chainstate(
-            self.chainstate.mainnet,
-            self.chainstate.chain_id,
+            self.chainstate.network.mainnet,
+            self.chainstate.network.chain_id,
             new_test_name,
         );
         TestStacksNode {
@@ -1330,7 +1331,12 @@ pub fn instantiate_and_exec(
         get_bulk_initial_namespaces: None,
     };
 
-    StacksChainState::open_and_exec(mainnet, chain_id, &path, Some(&mut boot_data), None)
-        .unwrap()
-        .0
+    StacksChainState::open_and_exec(
+        NetworkId::from_chain_id(mainnet, chain_id),
+        &path,
+        Some(&mut boot_data),
+        None,
+    )
+    .unwrap()
+    .0
 }
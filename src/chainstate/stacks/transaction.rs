@@ -15,10 +15,13 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::convert::TryFrom;
+use std::fmt;
 use std::io;
 use std::io::prelude::*;
 use std::io::{Read, Write};
 
+use crate::burnchains::PrivateKey;
+use crate::burnchains::PublicKey;
 use crate::burnchains::Txid;
 use crate::chainstate::stacks::*;
 use crate::core::*;
@@ -34,7 +37,9 @@ use stacks_common::util::retry::BoundReader;
 use stacks_common::util::secp256k1::MessageSignature;
 
 use crate::chainstate::stacks::StacksMicroblockHeader;
-use crate::codec::{read_next, write_next, Error as codec_error, StacksMessageCodec};
+use crate::codec::{
+    read_next, read_next_at_most, write_next, Error as codec_error, StacksMessageCodec,
+};
 use crate::types::chainstate::StacksAddress;
 use clarity::vm::ClarityVersion;
 
@@ -1067,6 +1072,83 @@ impl StacksTransactionSigner {
     }
 }
 
+impl StacksMessageCodec for StacksTransactionBundle {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &self.txs)?;
+        write_next(fd, &self.submitter)?;
+        write_next(fd, &self.signature)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<StacksTransactionBundle, codec_error> {
+        let txs: Vec<StacksTransaction> = read_next_at_most(fd, MAX_TRANSACTIONS_PER_BUNDLE)?;
+        let submitter: StacksPublicKeyBuffer = read_next(fd)?;
+        let signature: MessageSignature = read_next(fd)?;
+        Ok(StacksTransactionBundle {
+            txs,
+            submitter,
+            signature,
+        })
+    }
+}
+
+impl StacksTransactionBundle {
+    /// Make a bundle of `txs`, submitted by the owner of `privk`
+    pub fn new_signed(
+        txs: Vec<StacksTransaction>,
+        privk: &StacksPrivateKey,
+    ) -> Result<StacksTransactionBundle, net_error> {
+        let mut bundle = StacksTransactionBundle {
+            txs,
+            submitter: StacksPublicKeyBuffer([0u8; 33]),
+            signature: MessageSignature::empty(),
+        };
+        bundle.sign(privk)?;
+        Ok(bundle)
+    }
+
+    /// Sign the bundle ID with `privk`, whose public key becomes the bundle's submitter
+    pub fn sign(&mut self, privk: &StacksPrivateKey) -> Result<(), net_error> {
+        self.submitter =
+            StacksPublicKeyBuffer::from_public_key(&StacksPublicKey::from_private(privk));
+        self.signature = privk
+            .sign(self.bundle_id().as_bytes())
+            .map_err(|se| net_error::SigningError(se.to_string()))?;
+        Ok(())
+    }
+
+    /// Verify that the submitter signed the bundle ID
+    pub fn verify(&self) -> Result<(), net_error> {
+        let submitter = self
+            .submitter
+            .to_public_key()
+            .map_err(|e| net_error::VerifyingError(e.to_string()))?;
+        match submitter.verify(self.bundle_id().as_bytes(), &self.signature) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(net_error::VerifyingError(
+                "Bundle signature does not match its submitter".to_string(),
+            )),
+            Err(e) => Err(net_error::VerifyingError(e.to_string())),
+        }
+    }
+
+    /// The bundle ID commits to the IDs of its transactions, in order
+    pub fn bundle_id(&self) -> Sha512Trunc256Sum {
+        let mut bytes = Vec::with_capacity(self.txs.len() * 32);
+        for tx in self.txs.iter() {
+            bytes.extend_from_slice(tx.txid().as_bytes());
+        }
+        Sha512Trunc256Sum::from_data(&bytes)
+    }
+
+    /// Total fee paid by all transactions in the bundle
+    pub fn get_tx_fee(&self) -> u64 {
+        self.txs
+            .iter()
+            .fold(0, |total, tx| total.saturating_add(tx.get_tx_fee()))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::error::Error;
@@ -3209,6 +3291,63 @@ mod test {
         }
     }
 
+    #[test]
+    fn tx_stacks_transaction_bundle_codec() {
+        let all_txs = codec_all_transactions(
+            &TransactionVersion::Mainnet,
+            0,
+            &TransactionAnchorMode::OnChainOnly,
+            &TransactionPostConditionMode::Deny,
+        );
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let bundle = StacksTransactionBundle::new_signed(all_txs[0..3].to_vec(), &privk).unwrap();
+        assert_eq!(
+            bundle.submitter,
+            StacksPublicKeyBuffer::from_public_key(&StacksPublicKey::from_private(&privk))
+        );
+
+        let mut bundle_bytes = vec![];
+        write_next(&mut bundle_bytes, &(bundle.txs.len() as u32)).unwrap();
+        for tx in bundle.txs.iter() {
+            tx.consensus_serialize(&mut bundle_bytes).unwrap();
+        }
+        bundle_bytes.extend_from_slice(bundle.submitter.as_bytes());
+        bundle_bytes.extend_from_slice(bundle.signature.as_bytes());
+        check_codec_and_corruption::<StacksTransactionBundle>(&bundle, &bundle_bytes);
+
+        // the bundle ID commits to the transactions and their order
+        let mut reversed = bundle.clone();
+        reversed.txs.reverse();
+        assert_ne!(bundle.bundle_id(), reversed.bundle_id());
+        assert_eq!(bundle.bundle_id(), bundle.clone().bundle_id());
+
+        // so does the submitter's signature
+        bundle.verify().unwrap();
+        assert!(reversed.verify().is_err());
+        reversed.sign(&privk).unwrap();
+        reversed.verify().unwrap();
+
+        let mut forged = bundle.clone();
+        forged.submitter = StacksPublicKeyBuffer::from_public_key(&StacksPublicKey::from_private(
+            &StacksPrivateKey::new(),
+        ));
+        assert!(forged.verify().is_err());
+
+        // too many transactions
+        let big_bundle = StacksTransactionBundle {
+            txs: vec![all_txs[0].clone(); (MAX_TRANSACTIONS_PER_BUNDLE + 1) as usize],
+            submitter: bundle.submitter.clone(),
+            signature: bundle.signature.clone(),
+        };
+        let big_bundle_bytes = big_bundle.serialize_to_vec();
+        assert!(
+            StacksTransactionBundle::consensus_deserialize(&mut &big_bundle_bytes[..]).is_err()
+        );
+    }
+
     fn tx_stacks_transaction_test_txs(auth: &TransactionAuth) -> Vec<StacksTransaction> {
         let header_1 = StacksMicroblockHeader {
             version: 0x12,
@@ -92,14 +92,13 @@ use crate::clarity_vm::database::marf::MarfedKV;
 use crate::clarity_vm::database::marf::WritableMarfStore;
 use crate::clarity_vm::database::MemoryBackingStore;
 use crate::core::StacksEpochId;
-use stacks_common::consts::CHAIN_ID_MAINNET;
-use stacks_common::consts::CHAIN_ID_TESTNET;
 use stacks_common::types::chainstate::BlockHeaderHash;
 use stacks_common::types::chainstate::BurnchainHeaderHash;
 use stacks_common::types::chainstate::ConsensusHash;
 use stacks_common::types::chainstate::StacksAddress;
 use stacks_common::types::chainstate::StacksBlockId;
 use stacks_common::types::chainstate::VRFSeed;
+use stacks_common::types::NetworkId;
 use std::str::FromStr;
 
 lazy_static! {
@@ -269,8 +268,7 @@ fn run_analysis<C: ClarityStorage>(
     let mainnet = header_db.is_mainnet();
     let clarity_version = ClarityVersion::default_for_epoch(DEFAULT_CLI_EPOCH);
     let cost_track = LimitedCostTracker::new(
-        mainnet,
-        default_chain_id(mainnet),
+        NetworkId::from_mainnet(mainnet),
         if mainnet {
             BLOCK_LIMIT_MAINNET_205.clone()
         } else {
@@ -435,15 +433,6 @@ where
     result
 }
 
-fn default_chain_id(mainnet: bool) -> u32 {
-    let chain_id = if mainnet {
-        CHAIN_ID_MAINNET
-    } else {
-        CHAIN_ID_TESTNET
-    };
-    chain_id
-}
-
 fn with_env_costs<F, R>(
     mainnet: bool,
     header_db: &CLIHeadersDB,
@@ -456,8 +445,7 @@ where
 {
     let mut db = marf.as_clarity_db(header_db, &NULL_BURN_STATE_DB);
     let cost_track = LimitedCostTracker::new(
-        mainnet,
-        default_chain_id(mainnet),
+        NetworkId::from_mainnet(mainnet),
         if mainnet {
             BLOCK_LIMIT_MAINNET_205.clone()
         } else {
@@ -468,8 +456,7 @@ where
     )
     .unwrap();
     let mut vm_env = OwnedEnvironment::new_cost_limited(
-        mainnet,
-        default_chain_id(mainnet),
+        NetworkId::from_mainnet(mainnet),
         db,
         cost_track,
         DEFAULT_CLI_EPOCH,
@@ -490,8 +477,7 @@ pub fn vm_execute(program: &str, clarity_version: ClarityVersion) -> Result<Opti
     let mut marf = MemoryBackingStore::new();
     let conn = marf.as_clarity_db();
     let mut global_context = GlobalContext::new(
-        false,
-        default_chain_id(false),
+        NetworkId::from_mainnet(false),
         conn,
         LimitedCostTracker::new_free(),
         DEFAULT_CLI_EPOCH,
@@ -864,7 +850,7 @@ fn install_boot_code<C: ClarityStorage>(header_db: &CLIHeadersDB, marf: &mut C)
     {
         let db = marf.get_clarity_db(header_db, &NULL_BURN_STATE_DB);
         let mut vm_env =
-            OwnedEnvironment::new_free(mainnet, default_chain_id(mainnet), db, DEFAULT_CLI_EPOCH);
+            OwnedEnvironment::new_free(NetworkId::from_mainnet(mainnet), db, DEFAULT_CLI_EPOCH);
         vm_env
             .execute_in_env(
                 QualifiedContractIdentifier::transient().issuer.into(),
@@ -908,8 +894,7 @@ fn install_boot_code<C: ClarityStorage>(header_db: &CLIHeadersDB, marf: &mut C)
             Ok(_) => {
                 let db = marf.get_clarity_db(header_db, &NULL_BURN_STATE_DB);
                 let mut vm_env = OwnedEnvironment::new_free(
-                    mainnet,
-                    default_chain_id(mainnet),
+                    NetworkId::from_mainnet(mainnet),
                     db,
                     DEFAULT_CLI_EPOCH,
                 );
@@ -947,7 +932,7 @@ fn install_boot_code<C: ClarityStorage>(header_db: &CLIHeadersDB, marf: &mut C)
 
     let db = marf.get_clarity_db(header_db, &NULL_BURN_STATE_DB);
     let mut vm_env =
-        OwnedEnvironment::new_free(mainnet, default_chain_id(mainnet), db, DEFAULT_CLI_EPOCH);
+        OwnedEnvironment::new_free(NetworkId::from_mainnet(mainnet), db, DEFAULT_CLI_EPOCH);
     vm_env
         .execute_transaction(
             sender,
@@ -1246,8 +1231,7 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
             };
             let mut marf = MemoryBackingStore::new();
             let mut vm_env = OwnedEnvironment::new_free(
-                mainnet,
-                default_chain_id(mainnet),
+                NetworkId::from_mainnet(mainnet),
                 marf.as_clarity_db(),
                 DEFAULT_CLI_EPOCH,
             );
@@ -1321,8 +1305,7 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
             let mut analysis_marf = MemoryBackingStore::new();
             let mut marf = MemoryBackingStore::new();
             let mut vm_env = OwnedEnvironment::new_free(
-                true,
-                default_chain_id(true),
+                NetworkId::from_mainnet(true),
                 marf.as_clarity_db(),
                 DEFAULT_CLI_EPOCH,
             );
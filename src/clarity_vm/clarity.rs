@@ -19,6 +19,7 @@ use std::error;
 use std::fmt;
 use std::thread;
 
+use crate::chainstate::stacks::boot::get_boot_code;
 use crate::chainstate::stacks::boot::BOOT_CODE_COSTS_2_TESTNET;
 use crate::chainstate::stacks::boot::POX_2_MAINNET_CODE;
 use crate::chainstate::stacks::boot::POX_2_TESTNET_CODE;
@@ -51,7 +52,7 @@ use crate::types::chainstate::BurnchainHeaderHash;
 use crate::types::chainstate::SortitionId;
 use crate::types::chainstate::StacksBlockId;
 use crate::types::chainstate::TrieHash;
-use crate::util_lib::boot::{boot_code_acc, boot_code_addr, boot_code_id, boot_code_tx_auth};
+use crate::util_lib::boot::{boot_code_acc, boot_code_tx_auth, network_boot_code_id};
 use crate::util_lib::strings::StacksString;
 use crate::{
     burnchains::Burnchain,
@@ -78,13 +79,14 @@ use clarity::vm::types::{
 };
 use clarity::vm::ClarityVersion;
 use clarity::vm::ContractName;
+use clarity::vm::VmLimits;
 
 use crate::util_lib::db::Error as DatabaseError;
 pub use clarity::vm::clarity::ClarityConnection;
 pub use clarity::vm::clarity::Error;
 use clarity::vm::clarity::TransactionConnection;
 
-use stacks_common::consts::CHAIN_ID_TESTNET;
+use stacks_common::types::NetworkId;
 use stacks_common::util::secp256k1::MessageSignature;
 
 ///
@@ -111,8 +113,9 @@ use stacks_common::util::secp256k1::MessageSignature;
 ///
 pub struct ClarityInstance {
     datastore: MarfedKV,
-    mainnet: bool,
-    chain_id: u32,
+    network: NetworkId,
+    /// Parser and interpreter limits to run with instead of each epoch's consensus limits
+    vm_limits: Option<VmLimits>,
 }
 
 ///
@@ -139,9 +142,9 @@ pub struct ClarityBlockConnection<'a, 'b> {
     header_db: &'b dyn HeadersDB,
     burn_state_db: &'b dyn BurnStateDB,
     cost_track: Option<LimitedCostTracker>,
-    mainnet: bool,
-    chain_id: u32,
+    network: NetworkId,
     epoch: StacksEpochId,
+    vm_limits: Option<VmLimits>,
 }
 
 ///
@@ -155,9 +158,9 @@ pub struct ClarityTransactionConnection<'a, 'b> {
     header_db: &'a dyn HeadersDB,
     burn_state_db: &'a dyn BurnStateDB,
     cost_track: &'a mut Option<LimitedCostTracker>,
-    mainnet: bool,
-    chain_id: u32,
+    network: NetworkId,
     epoch: StacksEpochId,
+    vm_limits: Option<VmLimits>,
 }
 
 pub struct ClarityReadOnlyConnection<'a> {
@@ -165,6 +168,7 @@ pub struct ClarityReadOnlyConnection<'a> {
     header_db: &'a dyn HeadersDB,
     burn_state_db: &'a dyn BurnStateDB,
     epoch: StacksEpochId,
+    vm_limits: Option<VmLimits>,
 }
 
 impl From<ChainstateError> for Error {
@@ -207,9 +211,9 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
             header_db,
             burn_state_db,
             cost_track: Some(LimitedCostTracker::new_free()),
-            mainnet: false,
-            chain_id: CHAIN_ID_TESTNET,
+            network: NetworkId::testnet(),
             epoch: epoch,
+            vm_limits: None,
         }
     }
 
@@ -248,14 +252,27 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
 }
 
 impl ClarityInstance {
-    pub fn new(mainnet: bool, chain_id: u32, datastore: MarfedKV) -> ClarityInstance {
+    pub fn new(network: NetworkId, datastore: MarfedKV) -> ClarityInstance {
         ClarityInstance {
             datastore,
-            mainnet,
-            chain_id,
+            network,
+            vm_limits: None,
         }
     }
 
+    /// Run contracts with the given parser and interpreter limits, instead of each epoch's
+    /// consensus limits (or with the consensus limits again, if `None`).  Only private networks
+    /// should override them.
+    pub fn set_vm_limits(&mut self, vm_limits: Option<VmLimits>) {
+        self.vm_limits = vm_limits;
+    }
+
+    /// The parser and interpreter limits that contracts run with in the given epoch
+    pub fn get_vm_limits(&self, epoch_id: StacksEpochId) -> VmLimits {
+        self.vm_limits
+            .unwrap_or_else(|| VmLimits::for_epoch(epoch_id))
+    }
+
     pub fn with_marf<F, R>(&mut self, f: F) -> R
     where
         F: FnOnce(&mut MARF<StacksBlockId>) -> R,
@@ -264,7 +281,12 @@ impl ClarityInstance {
     }
 
     pub fn is_mainnet(&self) -> bool {
-        self.mainnet
+        self.network.mainnet
+    }
+
+    /// The network this instance's contracts run on
+    pub fn network(&self) -> NetworkId {
+        self.network
     }
 
     /// Returns the Stacks epoch of the burn block that elected `stacks_block`
@@ -307,8 +329,7 @@ impl ClarityInstance {
             let mut clarity_db = datastore.as_clarity_db(&NULL_HEADER_DB, &NULL_BURN_STATE_DB);
             Some(
                 LimitedCostTracker::new(
-                    self.mainnet,
-                    self.chain_id,
+                    self.network,
                     epoch.block_limit.clone(),
                     &mut clarity_db,
                     epoch.epoch_id,
@@ -322,9 +343,9 @@ impl ClarityInstance {
             header_db,
             burn_state_db,
             cost_track,
-            mainnet: self.mainnet,
-            chain_id: self.chain_id,
+            network: self.network,
             epoch: epoch.epoch_id,
+            vm_limits: self.vm_limits,
         }
     }
 
@@ -346,9 +367,9 @@ impl ClarityInstance {
             header_db,
             burn_state_db,
             cost_track,
-            mainnet: self.mainnet,
-            chain_id: self.chain_id,
+            network: self.network,
             epoch,
+            vm_limits: self.vm_limits,
         }
     }
 
@@ -372,16 +393,16 @@ impl ClarityInstance {
             header_db,
             burn_state_db,
             cost_track,
-            mainnet: self.mainnet,
-            chain_id: self.chain_id,
+            network: self.network,
             epoch,
+            vm_limits: self.vm_limits,
         };
 
-        let use_mainnet = self.mainnet;
+        let network = self.network;
         conn.as_transaction(|clarity_db| {
             let (ast, _analysis) = clarity_db
                 .analyze_smart_contract(
-                    &boot_code_id("costs", use_mainnet),
+                    &network_boot_code_id("costs", &network),
                     ClarityVersion::Clarity1,
                     BOOT_CODE_COSTS,
                     ASTRules::PrecheckSize,
@@ -389,7 +410,7 @@ impl ClarityInstance {
                 .unwrap();
             clarity_db
                 .initialize_smart_contract(
-                    &boot_code_id("costs", use_mainnet),
+                    &network_boot_code_id("costs", &network),
                     ClarityVersion::Clarity1,
                     &ast,
                     BOOT_CODE_COSTS,
@@ -402,7 +423,7 @@ impl ClarityInstance {
         conn.as_transaction(|clarity_db| {
             let (ast, analysis) = clarity_db
                 .analyze_smart_contract(
-                    &boot_code_id("cost-voting", use_mainnet),
+                    &network_boot_code_id("cost-voting", &network),
                     ClarityVersion::Clarity1,
                     &*BOOT_CODE_COST_VOTING,
                     ASTRules::PrecheckSize,
@@ -410,7 +431,7 @@ impl ClarityInstance {
                 .unwrap();
             clarity_db
                 .initialize_smart_contract(
-                    &boot_code_id("cost-voting", use_mainnet),
+                    &network_boot_code_id("cost-voting", &network),
                     ClarityVersion::Clarity1,
                     &ast,
                     &*BOOT_CODE_COST_VOTING,
@@ -420,14 +441,14 @@ impl ClarityInstance {
                 .unwrap();
 
             clarity_db
-                .save_analysis(&boot_code_id("cost-voting", use_mainnet), &analysis)
+                .save_analysis(&network_boot_code_id("cost-voting", &network), &analysis)
                 .unwrap();
         });
 
         conn.as_transaction(|clarity_db| {
             let (ast, _analysis) = clarity_db
                 .analyze_smart_contract(
-                    &boot_code_id("pox", use_mainnet),
+                    &network_boot_code_id("pox", &network),
                     ClarityVersion::Clarity1,
                     &*BOOT_CODE_POX_TESTNET,
                     ASTRules::PrecheckSize,
@@ -435,7 +456,7 @@ impl ClarityInstance {
                 .unwrap();
             clarity_db
                 .initialize_smart_contract(
-                    &boot_code_id("pox", use_mainnet),
+                    &network_boot_code_id("pox", &network),
                     ClarityVersion::Clarity1,
                     &ast,
                     &*BOOT_CODE_POX_TESTNET,
@@ -468,17 +489,17 @@ impl ClarityInstance {
             header_db,
             burn_state_db,
             cost_track,
-            mainnet: self.mainnet,
-            chain_id: self.chain_id,
+            network: self.network,
             epoch,
+            vm_limits: self.vm_limits,
         };
 
-        let use_mainnet = self.mainnet;
+        let network = self.network;
 
         conn.as_transaction(|clarity_db| {
             let (ast, _analysis) = clarity_db
                 .analyze_smart_contract(
-                    &boot_code_id("costs-2", use_mainnet),
+                    &network_boot_code_id("costs-2", &network),
                     ClarityVersion::Clarity1,
                     BOOT_CODE_COSTS_2,
                     ASTRules::PrecheckSize,
@@ -486,7 +507,7 @@ impl ClarityInstance {
                 .unwrap();
             clarity_db
                 .initialize_smart_contract(
-                    &boot_code_id("costs-2", use_mainnet),
+                    &network_boot_code_id("costs-2", &network),
                     ClarityVersion::Clarity1,
                     &ast,
                     BOOT_CODE_COSTS_2,
@@ -499,7 +520,7 @@ impl ClarityInstance {
         conn.as_transaction(|clarity_db| {
             let (ast, _analysis) = clarity_db
                 .analyze_smart_contract(
-                    &boot_code_id("costs-3", use_mainnet),
+                    &network_boot_code_id("costs-3", &network),
                     ClarityVersion::Clarity2,
                     BOOT_CODE_COSTS_3,
                     ASTRules::PrecheckSize,
@@ -507,7 +528,7 @@ impl ClarityInstance {
                 .unwrap();
             clarity_db
                 .initialize_smart_contract(
-                    &boot_code_id("costs-3", use_mainnet),
+                    &network_boot_code_id("costs-3", &network),
                     ClarityVersion::Clarity2,
                     &ast,
                     BOOT_CODE_COSTS_3,
@@ -520,7 +541,7 @@ impl ClarityInstance {
         conn.as_transaction(|clarity_db| {
             let (ast, _analysis) = clarity_db
                 .analyze_smart_contract(
-                    &boot_code_id("pox-2", use_mainnet),
+                    &network_boot_code_id("pox-2", &network),
                     ClarityVersion::Clarity2,
                     &*POX_2_TESTNET_CODE,
                     ASTRules::PrecheckSize,
@@ -528,7 +549,7 @@ impl ClarityInstance {
                 .unwrap();
             clarity_db
                 .initialize_smart_contract(
-                    &boot_code_id("pox-2", use_mainnet),
+                    &network_boot_code_id("pox-2", &network),
                     ClarityVersion::Clarity2,
                     &ast,
                     &*POX_2_TESTNET_CODE,
@@ -560,8 +581,7 @@ impl ClarityInstance {
             let mut clarity_db = datastore.as_clarity_db(&NULL_HEADER_DB, &NULL_BURN_STATE_DB);
             Some(
                 LimitedCostTracker::new(
-                    self.mainnet,
-                    self.chain_id,
+                    self.network,
                     epoch.block_limit.clone(),
                     &mut clarity_db,
                     epoch.epoch_id,
@@ -575,9 +595,9 @@ impl ClarityInstance {
             header_db,
             burn_state_db,
             cost_track,
-            mainnet: self.mainnet,
-            chain_id: self.chain_id,
+            network: self.network,
             epoch: epoch.epoch_id,
+            vm_limits: self.vm_limits,
         }
     }
 
@@ -615,6 +635,7 @@ impl ClarityInstance {
             header_db,
             burn_state_db,
             epoch,
+            vm_limits: self.vm_limits,
         })
     }
 
@@ -643,7 +664,11 @@ impl ClarityInstance {
             result
         };
 
-        let mut env = OwnedEnvironment::new_free(self.mainnet, self.chain_id, clarity_db, epoch_id);
+        let limits = self
+            .vm_limits
+            .unwrap_or_else(|| VmLimits::for_epoch(epoch_id));
+        let mut env =
+            OwnedEnvironment::new_free(self.network, clarity_db, epoch_id).with_limits(limits);
         env.eval_read_only_with_rules(contract, program, ast_rules)
             .map(|(x, _, _)| x)
             .map_err(Error::from)
@@ -682,6 +707,11 @@ impl<'a, 'b> ClarityConnection for ClarityBlockConnection<'a, 'b> {
     fn get_epoch(&self) -> StacksEpochId {
         self.epoch
     }
+
+    fn get_vm_limits(&self) -> VmLimits {
+        self.vm_limits
+            .unwrap_or_else(|| VmLimits::for_epoch(self.epoch))
+    }
 }
 
 impl ClarityConnection for ClarityReadOnlyConnection<'_> {
@@ -713,6 +743,11 @@ impl ClarityConnection for ClarityReadOnlyConnection<'_> {
     fn get_epoch(&self) -> StacksEpochId {
         self.epoch
     }
+
+    fn get_vm_limits(&self) -> VmLimits {
+        self.vm_limits
+            .unwrap_or_else(|| VmLimits::for_epoch(self.epoch))
+    }
 }
 
 impl<'a> PreCommitClarityBlock<'a> {
@@ -723,6 +758,11 @@ impl<'a> PreCommitClarityBlock<'a> {
 }
 
 impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
+    /// The network this block is being processed on
+    pub fn network(&self) -> NetworkId {
+        self.network
+    }
+
     /// Rolls back all changes in the current block by
     /// (1) dropping all writes from the current MARF tip,
     /// (2) rolling back side-storage
@@ -808,7 +848,7 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
             // epoch initialization is *free*
             self.cost_track.replace(LimitedCostTracker::new_free());
 
-            let mainnet = self.mainnet;
+            let mainnet = self.network.mainnet;
 
             // get the boot code account information
             //  for processing the pox contract initialization
@@ -818,7 +858,7 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
                 TransactionVersion::Testnet
             };
 
-            let boot_code_address = boot_code_addr(mainnet);
+            let boot_code_address = self.network.boot_address;
             let boot_code_auth = boot_code_tx_auth(boot_code_address);
             let boot_code_nonce = self.with_clarity_db_readonly(|db| {
                 db.get_account_nonce(&boot_code_address.clone().into())
@@ -826,17 +866,21 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
             let boot_code_account = boot_code_acc(boot_code_address, boot_code_nonce);
 
             // instantiate costs 2 contract...
-            let cost_2_code = if mainnet {
-                &*BOOT_CODE_COSTS_2
-            } else {
-                &*BOOT_CODE_COSTS_2_TESTNET
-            };
+            let cost_2_code = get_boot_code(
+                COSTS_2_NAME,
+                mainnet,
+                if mainnet {
+                    &*BOOT_CODE_COSTS_2
+                } else {
+                    &*BOOT_CODE_COSTS_2_TESTNET
+                },
+            );
 
             let payload = TransactionPayload::SmartContract(
                 TransactionSmartContract {
                     name: ContractName::try_from(COSTS_2_NAME)
                         .expect("FATAL: invalid boot-code contract name"),
-                    code_body: StacksString::from_str(cost_2_code)
+                    code_body: StacksString::from_str(&cost_2_code)
                         .expect("FATAL: invalid boot code body"),
                 },
                 None,
@@ -897,7 +941,7 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
             // This is important because pox-2 is instantiated before costs-3.
             self.cost_track.replace(LimitedCostTracker::new_free());
 
-            let mainnet = self.mainnet;
+            let mainnet = self.network.mainnet;
             let first_block_height = self.burn_state_db.get_burn_start_height();
             let pox_prepare_length = self.burn_state_db.get_pox_prepare_length();
             let pox_reward_cycle_length = self.burn_state_db.get_pox_reward_cycle_length();
@@ -919,7 +963,7 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
                 TransactionVersion::Testnet
             };
 
-            let boot_code_address = boot_code_addr(mainnet);
+            let boot_code_address = self.network.boot_address;
 
             let boot_code_auth = TransactionAuth::Standard(
                 TransactionSpendingCondition::Singlesig(SinglesigSpendingCondition {
@@ -943,19 +987,23 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
             };
 
             /////////////////// .pox-2 ////////////////////////
-            let pox_2_code = if mainnet {
-                &*POX_2_MAINNET_CODE
-            } else {
-                &*POX_2_TESTNET_CODE
-            };
+            let pox_2_code = get_boot_code(
+                POX_2_NAME,
+                mainnet,
+                if mainnet {
+                    &*POX_2_MAINNET_CODE
+                } else {
+                    &*POX_2_TESTNET_CODE
+                },
+            );
 
-            let pox_2_contract_id = boot_code_id(POX_2_NAME, mainnet);
+            let pox_2_contract_id = network_boot_code_id(POX_2_NAME, &self.network);
 
             let payload = TransactionPayload::SmartContract(
                 TransactionSmartContract {
                     name: ContractName::try_from(POX_2_NAME)
                         .expect("FATAL: invalid boot-code contract name"),
-                    code_body: StacksString::from_str(pox_2_code)
+                    code_body: StacksString::from_str(&pox_2_code)
                         .expect("FATAL: invalid boot code body"),
                 },
                 Some(ClarityVersion::Clarity2),
@@ -1023,13 +1071,13 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
             }
 
             /////////////////// .costs-3 ////////////////////////
-            let cost_3_code = &*BOOT_CODE_COSTS_3;
+            let cost_3_code = get_boot_code(COSTS_3_NAME, mainnet, &*BOOT_CODE_COSTS_3);
 
             let payload = TransactionPayload::SmartContract(
                 TransactionSmartContract {
                     name: ContractName::try_from(COSTS_3_NAME)
                         .expect("FATAL: invalid boot-code contract name"),
-                    code_body: StacksString::from_str(cost_3_code)
+                    code_body: StacksString::from_str(&cost_3_code)
                         .expect("FATAL: invalid boot code body"),
                 },
                 None,
@@ -1161,7 +1209,7 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
             });
 
             /////////////////// .pox-3 ////////////////////////
-            let mainnet = self.mainnet;
+            let mainnet = self.network.mainnet;
             let first_block_height = self.burn_state_db.get_burn_start_height();
             let pox_prepare_length = self.burn_state_db.get_pox_prepare_length();
             let pox_reward_cycle_length = self.burn_state_db.get_pox_reward_cycle_length();
@@ -1183,7 +1231,7 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
                 TransactionVersion::Testnet
             };
 
-            let boot_code_address = boot_code_addr(mainnet);
+            let boot_code_address = self.network.boot_address;
 
             let boot_code_auth = TransactionAuth::Standard(
                 TransactionSpendingCondition::Singlesig(SinglesigSpendingCondition {
@@ -1206,19 +1254,23 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
                 stx_balance: STXBalance::zero(),
             };
 
-            let pox_3_code = if mainnet {
-                &*POX_3_MAINNET_CODE
-            } else {
-                &*POX_3_TESTNET_CODE
-            };
+            let pox_3_code = get_boot_code(
+                POX_3_NAME,
+                mainnet,
+                if mainnet {
+                    &*POX_3_MAINNET_CODE
+                } else {
+                    &*POX_3_TESTNET_CODE
+                },
+            );
 
-            let pox_3_contract_id = boot_code_id(POX_3_NAME, mainnet);
+            let pox_3_contract_id = network_boot_code_id(POX_3_NAME, &self.network);
 
             let payload = TransactionPayload::SmartContract(
                 TransactionSmartContract {
                     name: ContractName::try_from(POX_3_NAME)
                         .expect("FATAL: invalid boot-code contract name"),
-                    code_body: StacksString::from_str(pox_3_code)
+                    code_body: StacksString::from_str(&pox_3_code)
                         .expect("FATAL: invalid boot code body"),
                 },
                 Some(ClarityVersion::Clarity2),
@@ -1282,8 +1334,7 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
         let cost_track = &mut self.cost_track;
         let header_db = &self.header_db;
         let burn_state_db = &self.burn_state_db;
-        let mainnet = self.mainnet;
-        let chain_id = self.chain_id;
+        let network = self.network;
         let mut log = RollbackWrapperPersistedLog::new();
         log.nest();
         ClarityTransactionConnection {
@@ -1292,9 +1343,9 @@ impl<'a, 'b> ClarityBlockConnection<'a, 'b> {
             header_db,
             burn_state_db,
             log: Some(log),
-            mainnet,
-            chain_id,
+            network,
             epoch: self.epoch,
+            vm_limits: self.vm_limits,
         }
     }
 
@@ -1383,6 +1434,11 @@ impl<'a, 'b> ClarityConnection for ClarityTransactionConnection<'a, 'b> {
     fn get_epoch(&self) -> StacksEpochId {
         self.epoch
     }
+
+    fn get_vm_limits(&self) -> VmLimits {
+        self.vm_limits
+            .unwrap_or_else(|| VmLimits::for_epoch(self.epoch))
+    }
 }
 
 impl<'a, 'b> Drop for ClarityTransactionConnection<'a, 'b> {
@@ -1415,6 +1471,7 @@ impl<'a, 'b> TransactionConnection for ClarityTransactionConnection<'a, 'b> {
         A: FnOnce(&AssetMap, &mut ClarityDatabase) -> bool,
         F: FnOnce(&mut OwnedEnvironment) -> Result<(R, AssetMap, Vec<StacksTransactionEvent>), E>,
     {
+        let limits = self.get_vm_limits();
         using!(self.log, "log", |log| {
             using!(self.cost_track, "cost tracker", |cost_track| {
                 let rollback_wrapper = RollbackWrapper::from_persisted_log(self.store, log);
@@ -1427,13 +1484,9 @@ impl<'a, 'b> TransactionConnection for ClarityTransactionConnection<'a, 'b> {
                 // wrap the whole contract-call in a claritydb transaction,
                 //   so we can abort on call_back's boolean retun
                 db.begin();
-                let mut vm_env = OwnedEnvironment::new_cost_limited(
-                    self.mainnet,
-                    self.chain_id,
-                    db,
-                    cost_track,
-                    self.epoch,
-                );
+                let mut vm_env =
+                    OwnedEnvironment::new_cost_limited(self.network, db, cost_track, self.epoch)
+                        .with_limits(limits);
                 let result = to_do(&mut vm_env);
                 let (mut db, cost_track) = vm_env
                     .destruct()
@@ -1537,7 +1590,12 @@ impl<'a, 'b> ClarityTransactionConnection<'a, 'b> {
     }
 
     pub fn is_mainnet(&self) -> bool {
-        return self.mainnet;
+        return self.network.mainnet;
+    }
+
+    /// The network this transaction runs on
+    pub fn network(&self) -> NetworkId {
+        self.network
     }
 
     /// Commit the changes from the edit log.
@@ -1610,7 +1668,7 @@ mod tests {
     #[test]
     pub fn bad_syntax_test() {
         let marf = MarfedKV::temporary();
-        let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+        let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
 
         let contract_identifier = QualifiedContractIdentifier::local("foo").unwrap();
 
@@ -1664,7 +1722,7 @@ mod tests {
     #[test]
     pub fn test_initialize_contract_tx_sender_contract_caller() {
         let marf = MarfedKV::temporary();
-        let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+        let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
         let contract_identifier = QualifiedContractIdentifier::local("foo").unwrap();
 
         clarity_instance
@@ -1723,7 +1781,7 @@ mod tests {
     #[test]
     pub fn tx_rollback() {
         let marf = MarfedKV::temporary();
-        let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+        let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
 
         let contract_identifier = QualifiedContractIdentifier::local("foo").unwrap();
         let contract = "(define-public (foo (x int) (y int)) (ok (+ x y)))";
@@ -1836,7 +1894,7 @@ mod tests {
     #[test]
     pub fn simple_test() {
         let marf = MarfedKV::temporary();
-        let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+        let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
 
         let contract_identifier = QualifiedContractIdentifier::local("foo").unwrap();
 
@@ -1906,7 +1964,7 @@ mod tests {
     #[test]
     pub fn test_block_roll_back() {
         let marf = MarfedKV::temporary();
-        let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+        let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
         let contract_identifier = QualifiedContractIdentifier::local("foo").unwrap();
 
         {
@@ -1971,7 +2029,7 @@ mod tests {
 
         let confirmed_marf = MarfedKV::open(test_name, None, None).unwrap();
         let mut confirmed_clarity_instance =
-            ClarityInstance::new(false, CHAIN_ID_TESTNET, confirmed_marf);
+            ClarityInstance::new(NetworkId::testnet(), confirmed_marf);
         let contract_identifier = QualifiedContractIdentifier::local("foo").unwrap();
 
         let contract = "
@@ -2001,7 +2059,7 @@ mod tests {
             )
             .unwrap();
 
-        let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+        let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
 
         // make an unconfirmed block off of the confirmed block
         {
@@ -2115,7 +2173,7 @@ mod tests {
     #[test]
     pub fn test_tx_roll_backs() {
         let marf = MarfedKV::temporary();
-        let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+        let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
         let contract_identifier = QualifiedContractIdentifier::local("foo").unwrap();
         let sender = StandardPrincipalData::transient().into();
 
@@ -2269,7 +2327,7 @@ mod tests {
         use stacks_common::util::secp256k1::MessageSignature;
 
         let marf = MarfedKV::temporary();
-        let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+        let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
         let sender = StandardPrincipalData::transient().into();
 
         let spending_cond = TransactionSpendingCondition::Singlesig(SinglesigSpendingCondition {
@@ -2394,7 +2452,7 @@ mod tests {
     #[test]
     pub fn test_block_limit() {
         let marf = MarfedKV::temporary();
-        let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+        let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
         let contract_identifier = QualifiedContractIdentifier::local("foo").unwrap();
         let sender = StandardPrincipalData::transient().into();
 
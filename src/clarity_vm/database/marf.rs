@@ -9,7 +9,7 @@ use crate::core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
 use crate::util_lib::db::IndexDBConn;
 use clarity::vm::analysis::AnalysisDatabase;
 use clarity::vm::database::{
-    BurnStateDB, ClarityBackingStore, ClarityDatabase, HeadersDB, SqliteConnection,
+    BurnStateDB, ClarityBackend, ClarityBackingStore, ClarityDatabase, HeadersDB, SqliteConnection,
 };
 use clarity::vm::errors::{
     IncomparableError, InterpreterError, InterpreterResult, RuntimeErrorType,
@@ -290,6 +290,12 @@ impl<'a> ReadOnlyMarfStore<'a> {
     }
 }
 
+impl<'a> ClarityBackend for ReadOnlyMarfStore<'a> {
+    fn backing_store(&mut self) -> &mut dyn ClarityBackingStore {
+        self
+    }
+}
+
 impl<'a> ClarityBackingStore for ReadOnlyMarfStore<'a> {
     fn get_side_store(&mut self) -> &Connection {
         self.marf.sqlite_conn()
@@ -514,6 +520,12 @@ impl<'a> WritableMarfStore<'a> {
     }
 }
 
+impl<'a> ClarityBackend for WritableMarfStore<'a> {
+    fn backing_store(&mut self) -> &mut dyn ClarityBackingStore {
+        self
+    }
+}
+
 impl<'a> ClarityBackingStore for WritableMarfStore<'a> {
     fn set_block_hash(&mut self, bhh: StacksBlockId) -> InterpreterResult<StacksBlockId> {
         self.marf
@@ -13,7 +13,7 @@ use crate::util_lib::db::FromColumn;
 use crate::util_lib::db::{DBConn, FromRow};
 use clarity::vm::analysis::AnalysisDatabase;
 use clarity::vm::database::{
-    BurnStateDB, ClarityBackingStore, ClarityDatabase, HeadersDB, SqliteConnection,
+    BurnStateDB, ClarityBackend, ClarityBackingStore, ClarityDatabase, HeadersDB, SqliteConnection,
     NULL_BURN_STATE_DB, NULL_HEADER_DB,
 };
 use clarity::vm::errors::{InterpreterResult, RuntimeErrorType};
@@ -38,6 +38,7 @@ use stacks_common::types::chainstate::ConsensusHash;
 use stacks_common::types::Address;
 
 pub mod marf;
+pub mod remote;
 
 pub struct HeadersDBConn<'a>(pub &'a Connection);
 
@@ -666,6 +667,12 @@ impl MemoryBackingStore {
     }
 }
 
+impl ClarityBackend for MemoryBackingStore {
+    fn backing_store(&mut self) -> &mut dyn ClarityBackingStore {
+        self
+    }
+}
+
 impl ClarityBackingStore for MemoryBackingStore {
     fn set_block_hash(&mut self, bhh: StacksBlockId) -> InterpreterResult<StacksBlockId> {
         Err(RuntimeErrorType::UnknownBlockHeaderHash(BlockHeaderHash(bhh.0)).into())
@@ -0,0 +1,394 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A Clarity backing store that reads committed state from elsewhere, such as a node's RPC
+//! interface.
+//!
+//! `RemoteBackingStore` is pinned to a single chain tip.  Reads that miss its local state go to a
+//! `ClarityStateSource` and are cached; writes stay local, in an in-memory side store.  This lets
+//! tooling evaluate Clarity against a node's state without a copy of its MARF.
+//!
+//! Blocks below the pinned tip cannot be looked up, so `at-block` and `get-block-info?` only work
+//! at the tip itself.
+//!
+//! Remote reads can fail, but `ClarityBackingStore::get()` has no way to report an error.  When one
+//! of those reads fails, the store reports no value and remembers the failure.  From then on, every
+//! fallible store operation (such as loading a contract's metadata) fails, and the failure is
+//! available from `take_read_error()`.  A caller must check `take_read_error()` before trusting a
+//! result computed over this store.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+
+use clarity::vm::database::clarity_store::make_contract_hash_key;
+use clarity::vm::database::{
+    ClarityBackend, ClarityBackingStore, SpecialCaseHandler, SqliteConnection,
+};
+use clarity::vm::errors::{CheckErrors, InterpreterError, InterpreterResult, RuntimeErrorType};
+use clarity::vm::types::QualifiedContractIdentifier;
+use rusqlite::Connection;
+
+use crate::chainstate::stacks::index::ClarityMarfTrieId;
+use crate::clarity_vm::special::handle_contract_call_special_cases;
+use crate::net::Error as net_error;
+use crate::net::{
+    HttpRequestMetadata, HttpRequestType, HttpResponseType, PeerHost, StacksHttp,
+    StacksHttpMessage, TipRequest,
+};
+use crate::types::chainstate::{BlockHeaderHash, StacksAddress, StacksBlockId};
+use stacks_common::util::hash::hex_bytes;
+
+/// Where a `RemoteBackingStore` reads committed Clarity state from.  All reads are at the chain
+/// tip the source is pinned to.
+pub trait ClarityStateSource {
+    /// The raw value stored under `key`
+    fn get(&mut self, key: &str) -> Result<Option<String>, net_error>;
+    /// The raw value stored under `key`, and its MARF proof
+    fn get_with_proof(&mut self, key: &str) -> Result<Option<(String, Vec<u8>)>, net_error>;
+    /// The raw metadata entry `key` of `contract`
+    fn get_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> Result<Option<String>, net_error>;
+}
+
+/// Reads committed Clarity state from a node's `/v2/clarity` RPC endpoints
+pub struct RPCClarityStateSource {
+    peer: SocketAddr,
+    tip: StacksBlockId,
+}
+
+impl RPCClarityStateSource {
+    pub fn new(peer: SocketAddr, tip: StacksBlockId) -> RPCClarityStateSource {
+        RPCClarityStateSource { peer, tip }
+    }
+
+    /// Send `request` to the node, on a connection of its own
+    fn send(&self, mut request: HttpRequestType) -> Result<HttpResponseType, net_error> {
+        request.metadata_mut().keep_alive = false;
+        let request_path = request.request_path();
+        let request_bytes = StacksHttp::serialize_request(&request)?;
+
+        let mut sock = TcpStream::connect(&self.peer).map_err(net_error::ReadError)?;
+        sock.write_all(&request_bytes)
+            .map_err(net_error::WriteError)?;
+        let mut response_bytes = vec![];
+        sock.read_to_end(&mut response_bytes)
+            .map_err(net_error::ReadError)?;
+
+        match StacksHttp::parse_response(&request_path, &response_bytes)? {
+            StacksHttpMessage::Response(response) => Ok(response),
+            _ => Err(net_error::InvalidMessage),
+        }
+    }
+
+    fn get_marf_value(
+        &mut self,
+        key: &str,
+        with_proof: bool,
+    ) -> Result<Option<(String, Option<String>)>, net_error> {
+        let request = HttpRequestType::GetClarityMarfValue(
+            HttpRequestMetadata::from_host(PeerHost::from_socketaddr(&self.peer), None),
+            key.to_string(),
+            TipRequest::SpecificTip(self.tip.clone()),
+            with_proof,
+        );
+        match self.send(request)? {
+            HttpResponseType::GetClarityMarfValue(_, response) => {
+                Ok(Some((response.data, response.marf_proof)))
+            }
+            HttpResponseType::NotFound(..) => Ok(None),
+            response => Err(net_error::DeserializeError(format!(
+                "Unexpected response to GetClarityMarfValue: {:?}",
+                &response
+            ))),
+        }
+    }
+
+    /// Get the node's canonical chain tip, and its height
+    pub fn get_canonical_tip(peer: SocketAddr) -> Result<(StacksBlockId, u32), net_error> {
+        let source = RPCClarityStateSource::new(peer, StacksBlockId::sentinel());
+        let request = HttpRequestType::GetInfo(HttpRequestMetadata::from_host(
+            PeerHost::from_socketaddr(&peer),
+            None,
+        ));
+        match source.send(request)? {
+            HttpResponseType::PeerInfo(_, info) => {
+                let tip = StacksBlockId::new(&info.stacks_tip_consensus_hash, &info.stacks_tip);
+                let height = u32::try_from(info.stacks_tip_height)
+                    .map_err(|_| net_error::OverflowError("Chain tip height too big".into()))?;
+                Ok((tip, height))
+            }
+            response => Err(net_error::DeserializeError(format!(
+                "Unexpected response to GetInfo: {:?}",
+                &response
+            ))),
+        }
+    }
+}
+
+impl ClarityStateSource for RPCClarityStateSource {
+    fn get(&mut self, key: &str) -> Result<Option<String>, net_error> {
+        Ok(self.get_marf_value(key, false)?.map(|(data, _)| data))
+    }
+
+    fn get_with_proof(&mut self, key: &str) -> Result<Option<(String, Vec<u8>)>, net_error> {
+        match self.get_marf_value(key, true)? {
+            Some((data, Some(proof_hex))) => {
+                let proof = hex_bytes(proof_hex.trim_start_matches("0x")).map_err(|_| {
+                    net_error::DeserializeError("Failed to decode MARF proof".into())
+                })?;
+                Ok(Some((data, proof)))
+            }
+            Some((_, None)) => Err(net_error::DeserializeError(
+                "Node did not supply a MARF proof".into(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn get_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> Result<Option<String>, net_error> {
+        let request = HttpRequestType::GetClarityMetadata(
+            HttpRequestMetadata::from_host(PeerHost::from_socketaddr(&self.peer), None),
+            StacksAddress::from(contract.issuer.clone()),
+            contract.name.clone(),
+            key.to_string(),
+            TipRequest::SpecificTip(self.tip.clone()),
+        );
+        match self.send(request)? {
+            HttpResponseType::GetClarityMetadata(_, response) => Ok(Some(response.data)),
+            HttpResponseType::NotFound(..) => Ok(None),
+            response => Err(net_error::DeserializeError(format!(
+                "Unexpected response to GetClarityMetadata: {:?}",
+                &response
+            ))),
+        }
+    }
+}
+
+/// A Clarity backing store over the state of a `ClarityStateSource`, pinned to the chain tip
+/// `tip` at height `tip_height`.  The VM evaluates as if it were building the block after `tip`.
+pub struct RemoteBackingStore<S: ClarityStateSource> {
+    source: S,
+    tip: StacksBlockId,
+    tip_height: u32,
+    /// Values read from the source
+    cache: HashMap<String, Option<String>>,
+    /// Metadata entries read from the source
+    metadata_cache: HashMap<(String, String), Option<String>>,
+    /// Values and metadata written by the VM
+    side_store: Connection,
+    /// The first remote read that failed in a method that can't report it
+    read_error: Option<net_error>,
+}
+
+impl RemoteBackingStore<RPCClarityStateSource> {
+    /// Open a store over the state of the node at `peer`, as of its canonical chain tip
+    pub fn connect(
+        peer: SocketAddr,
+    ) -> Result<RemoteBackingStore<RPCClarityStateSource>, net_error> {
+        let (tip, tip_height) = RPCClarityStateSource::get_canonical_tip(peer)?;
+        let source = RPCClarityStateSource::new(peer, tip.clone());
+        Ok(RemoteBackingStore::new(source, tip, tip_height))
+    }
+}
+
+impl<S: ClarityStateSource> RemoteBackingStore<S> {
+    pub fn new(source: S, tip: StacksBlockId, tip_height: u32) -> RemoteBackingStore<S> {
+        RemoteBackingStore {
+            source,
+            tip,
+            tip_height,
+            cache: HashMap::new(),
+            metadata_cache: HashMap::new(),
+            side_store: SqliteConnection::memory().expect("FATAL: failed to open side store"),
+            read_error: None,
+        }
+    }
+
+    /// Take the error of the first remote read that failed in a method that couldn't report it.
+    /// If there is one, reads since then may have wrongly found no value.
+    pub fn take_read_error(&mut self) -> Option<net_error> {
+        self.read_error.take()
+    }
+
+    /// Remember the failure of a read that couldn't report it
+    fn record_read_error(&mut self, key: &str, e: net_error) {
+        warn!("Failed to read from remote Clarity state"; "key" => key, "error" => ?e);
+        if self.read_error.is_none() {
+            self.read_error = Some(e);
+        }
+    }
+
+    /// Fail if an earlier read failed without reporting it
+    fn check_read_error(&self) -> InterpreterResult<()> {
+        match self.read_error.as_ref() {
+            Some(e) => Err(remote_read_error(e)),
+            None => Ok(()),
+        }
+    }
+
+    fn fetch(&mut self, key: &str) -> Result<Option<String>, net_error> {
+        if let Some(value) = self.cache.get(key) {
+            return Ok(value.clone());
+        }
+        let value = self.source.get(key)?;
+        self.cache.insert(key.to_string(), value.clone());
+        Ok(value)
+    }
+
+    fn fetch_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> InterpreterResult<Option<String>> {
+        self.check_read_error()?;
+        let cache_key = (contract.to_string(), key.to_string());
+        if let Some(value) = self.metadata_cache.get(&cache_key) {
+            return Ok(value.clone());
+        }
+        let value = self
+            .source
+            .get_metadata(contract, key)
+            .map_err(|e| remote_read_error(&e))?;
+        self.metadata_cache.insert(cache_key, value.clone());
+        Ok(value)
+    }
+}
+
+fn remote_read_error(e: &net_error) -> clarity::vm::errors::Error {
+    InterpreterError::DBError(format!("Failed to read remote Clarity state: {:?}", e)).into()
+}
+
+impl<S: ClarityStateSource> ClarityBackend for RemoteBackingStore<S> {
+    fn backing_store(&mut self) -> &mut dyn ClarityBackingStore {
+        self
+    }
+}
+
+impl<S: ClarityStateSource> ClarityBackingStore for RemoteBackingStore<S> {
+    fn put_all(&mut self, items: Vec<(String, String)>) {
+        for (key, value) in items.into_iter() {
+            SqliteConnection::put(&self.side_store, &key, &value);
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<String> {
+        if let Some(value) = SqliteConnection::get(&self.side_store, key) {
+            return Some(value);
+        }
+        match self.fetch(key) {
+            Ok(value) => value,
+            Err(e) => {
+                self.record_read_error(key, e);
+                None
+            }
+        }
+    }
+
+    fn get_with_proof(&mut self, key: &str) -> Option<(String, Vec<u8>)> {
+        if let Some(value) = SqliteConnection::get(&self.side_store, key) {
+            // written locally, so there is nothing to prove
+            return Some((value, vec![]));
+        }
+        match self.source.get_with_proof(key) {
+            Ok(value) => value,
+            Err(e) => {
+                self.record_read_error(key, e);
+                None
+            }
+        }
+    }
+
+    fn set_block_hash(&mut self, bhh: StacksBlockId) -> InterpreterResult<StacksBlockId> {
+        self.check_read_error()?;
+        if bhh == self.tip {
+            Ok(self.tip.clone())
+        } else {
+            Err(RuntimeErrorType::UnknownBlockHeaderHash(BlockHeaderHash(bhh.0)).into())
+        }
+    }
+
+    fn get_block_at_height(&mut self, height: u32) -> Option<StacksBlockId> {
+        if height == self.tip_height {
+            Some(self.tip.clone())
+        } else {
+            None
+        }
+    }
+
+    fn get_current_block_height(&mut self) -> u32 {
+        self.tip_height + 1
+    }
+
+    fn get_open_chain_tip_height(&mut self) -> u32 {
+        self.tip_height + 1
+    }
+
+    fn get_open_chain_tip(&mut self) -> StacksBlockId {
+        StacksBlockId::sentinel()
+    }
+
+    fn get_side_store(&mut self) -> &Connection {
+        &self.side_store
+    }
+
+    fn get_cc_special_cases_handler(&self) -> Option<SpecialCaseHandler> {
+        Some(&handle_contract_call_special_cases)
+    }
+
+    fn get_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> InterpreterResult<Option<String>> {
+        // contracts deployed through this store keep their metadata in the side store
+        let open_tip = self.get_open_chain_tip();
+        let local =
+            SqliteConnection::get_metadata(&self.side_store, &open_tip, &contract.to_string(), key);
+        if local.is_some() {
+            return Ok(local);
+        }
+        let contract_hash_key = make_contract_hash_key(contract);
+        if SqliteConnection::get(&self.side_store, &contract_hash_key).is_none()
+            && self
+                .fetch(&contract_hash_key)
+                .map_err(|e| remote_read_error(&e))?
+                .is_none()
+        {
+            return Err(CheckErrors::NoSuchContract(contract.to_string()).into());
+        }
+        self.fetch_metadata(contract, key)
+    }
+
+    fn get_metadata_manual(
+        &mut self,
+        at_height: u32,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> InterpreterResult<Option<String>> {
+        if at_height != self.tip_height {
+            warn!("Unknown block height when manually querying remote metadata"; "block_height" => at_height);
+            return Err(RuntimeErrorType::BadBlockHeight(at_height.to_string()).into());
+        }
+        self.fetch_metadata(contract, key)
+    }
+}
@@ -3,8 +3,17 @@ pub mod clarity;
 
 pub mod special;
 
+/// Parsers for the values returned by the PoX contracts' locking functions
+pub mod pox_return_parser;
+pub use self::special::{
+    get_special_case_handler, register_special_case_handler, SpecialCaseHandler,
+};
+
 /// Stacks blockchain specific Clarity database implementations and wrappers
 pub mod database;
 
+/// Re-analysis of deployed contracts under a new epoch's type rules
+pub mod reanalysis;
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,285 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Parsers for the values returned by the PoX contracts' locking functions.
+//!
+//! The PoX special-case handlers read the value a locking function returned in order to apply
+//! the lock to the stacker's account.  The shape of that value is fixed by the boot contract, so
+//! a value of any other shape means the contract and the node disagree (e.g. the node is running
+//! a boot contract from a different fork).  Instead of panicking, these parsers report such a
+//! value as a `PoxParseError`, which the handlers surface as an `InterpreterError`.
+//!
+//! Each parser returns `Ok(Ok(..))` if the function succeeded, and `Ok(Err(code))` with the
+//! contract's error code if it failed.
+
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+
+use clarity::vm::errors::{Error as InterpreterErrorWrapper, InterpreterError};
+use clarity::vm::types::{ClarityTuple, PrincipalData, TupleConversionError, Value};
+
+/// A value returned by a PoX locking function which does not have the expected shape
+#[derive(Debug, Clone, PartialEq)]
+pub enum PoxParseError {
+    /// the function did not return a response
+    NotAResponse(Value),
+    /// the function's `ok` value was not a tuple
+    NotATuple(Value),
+    /// the function's `err` value was not an `int`
+    BadErrorCode(Value),
+    /// the `ok` tuple has no such field
+    MissingField(&'static str),
+    /// the `ok` tuple's field has the wrong type
+    BadField(&'static str, Value),
+    /// the `ok` tuple's field does not fit into the expected integer type
+    FieldOverflow(&'static str, u128),
+}
+
+impl fmt::Display for PoxParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PoxParseError::NotAResponse(v) => write!(f, "expected a response, got {}", v),
+            PoxParseError::NotATuple(v) => write!(f, "expected an (ok tuple), got (ok {})", v),
+            PoxParseError::BadErrorCode(v) => write!(f, "expected an (err int), got (err {})", v),
+            PoxParseError::MissingField(name) => write!(f, "no '{}' in result tuple", name),
+            PoxParseError::BadField(name, v) => write!(f, "bad type for '{}': {}", name, v),
+            PoxParseError::FieldOverflow(name, v) => write!(f, "'{}' overflow: {}", name, v),
+        }
+    }
+}
+
+impl error::Error for PoxParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+impl From<TupleConversionError> for PoxParseError {
+    fn from(e: TupleConversionError) -> PoxParseError {
+        match e {
+            TupleConversionError::NotATuple(v) => PoxParseError::NotATuple(v),
+            TupleConversionError::MissingField(name) => PoxParseError::MissingField(name),
+            TupleConversionError::BadField(name, v) => PoxParseError::BadField(name, v),
+            TupleConversionError::FieldOverflow(name, v) => PoxParseError::FieldOverflow(name, v),
+        }
+    }
+}
+
+impl From<PoxParseError> for InterpreterErrorWrapper {
+    fn from(e: PoxParseError) -> InterpreterErrorWrapper {
+        InterpreterErrorWrapper::Interpreter(InterpreterError::InterpreterError(format!(
+            "Unexpected PoX return value: {}",
+            &e
+        )))
+    }
+}
+
+pub type PoxParseResult<T> = Result<Result<T, i128>, PoxParseError>;
+
+/// Successful result of `stack-stx` and `delegate-stack-stx`
+#[derive(Debug, Clone, PartialEq, ClarityTuple)]
+pub struct PoxStackingResult {
+    pub stacker: PrincipalData,
+    pub lock_amount: u128,
+    pub unlock_burn_height: u64,
+}
+
+/// Successful result of `stack-extend` and `delegate-stack-extend`
+#[derive(Debug, Clone, PartialEq, ClarityTuple)]
+pub struct PoxExtendResult {
+    pub stacker: PrincipalData,
+    pub unlock_burn_height: u64,
+}
+
+/// Successful result of `stack-increase` and `delegate-stack-increase`
+#[derive(Debug, Clone, PartialEq, ClarityTuple)]
+pub struct PoxIncreaseResult {
+    pub stacker: PrincipalData,
+    pub total_locked: u128,
+}
+
+/// Convert a returned response's `ok` tuple into a `T`, or get its `err` code
+fn parse_response<T>(result: &Value) -> PoxParseResult<T>
+where
+    T: TryFrom<Value, Error = TupleConversionError>,
+{
+    let response = match result {
+        Value::Response(response) => response,
+        _ => {
+            return Err(PoxParseError::NotAResponse(result.clone()));
+        }
+    };
+    if response.committed {
+        Ok(Ok(T::try_from((*response.data).clone())?))
+    } else {
+        // in the error case, the function should have returned `int` error code
+        match *response.data {
+            Value::Int(code) => Ok(Err(code)),
+            ref v => Err(PoxParseError::BadErrorCode(v.clone())),
+        }
+    }
+}
+
+/// Parse the returned value from PoX `stack-stx` and `delegate-stack-stx` functions.
+/// pox.clar returns `(ok (tuple (stacker principal) (lock-amount uint) (unlock-burn-height uint)))`,
+/// and pox-2.clar and pox-3.clar return a tuple with at least these fields.
+pub fn parse_pox_stacking_result(result: &Value) -> PoxParseResult<PoxStackingResult> {
+    parse_response(result)
+}
+
+/// Parse the returned value from PoX2 or PoX3 `stack-extend` and `delegate-stack-extend`
+/// functions, which return `(ok { stacker: principal, unlock-burn-height: uint .. })`.
+pub fn parse_pox_extend_result(result: &Value) -> PoxParseResult<PoxExtendResult> {
+    parse_response(result)
+}
+
+/// Parse the returned value from PoX2 or PoX3 `stack-increase` and `delegate-stack-increase`
+/// functions, which return `(ok { stacker: principal, total-locked: uint .. })`.
+pub fn parse_pox_increase(result: &Value) -> PoxParseResult<PoxIncreaseResult> {
+    parse_response(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clarity::vm::representations::ClarityName;
+    use clarity::vm::types::{StandardPrincipalData, TupleData};
+
+    fn stacker() -> PrincipalData {
+        PrincipalData::Standard(StandardPrincipalData(22, [0x01; 20]))
+    }
+
+    fn ok_tuple(fields: Vec<(&str, Value)>) -> Value {
+        Value::okay(Value::Tuple(
+            TupleData::from_data(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (ClarityName::try_from(name.to_string()).unwrap(), value))
+                    .collect(),
+            )
+            .unwrap(),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_pox_stacking_result() {
+        let result = ok_tuple(vec![
+            ("stacker", Value::Principal(stacker())),
+            ("lock-amount", Value::UInt(1000)),
+            ("unlock-burn-height", Value::UInt(200)),
+            ("pox-addr", Value::none()),
+        ]);
+        assert_eq!(
+            parse_pox_stacking_result(&result),
+            Ok(Ok(PoxStackingResult {
+                stacker: stacker(),
+                lock_amount: 1000,
+                unlock_burn_height: 200,
+            }))
+        );
+
+        let result = Value::error(Value::Int(3)).unwrap();
+        assert_eq!(parse_pox_stacking_result(&result), Ok(Err(3)));
+
+        // malformed results are errors, not panics
+        assert_eq!(
+            parse_pox_stacking_result(&Value::UInt(1)),
+            Err(PoxParseError::NotAResponse(Value::UInt(1)))
+        );
+        assert_eq!(
+            parse_pox_stacking_result(&Value::error(Value::UInt(3)).unwrap()),
+            Err(PoxParseError::BadErrorCode(Value::UInt(3)))
+        );
+        assert_eq!(
+            parse_pox_stacking_result(&Value::okay_true()),
+            Err(PoxParseError::NotATuple(Value::Bool(true)))
+        );
+        let result = ok_tuple(vec![
+            ("stacker", Value::Principal(stacker())),
+            ("unlock-burn-height", Value::UInt(200)),
+        ]);
+        assert_eq!(
+            parse_pox_stacking_result(&result),
+            Err(PoxParseError::MissingField("lock-amount"))
+        );
+        let result = ok_tuple(vec![
+            ("stacker", Value::Principal(stacker())),
+            ("lock-amount", Value::Int(1000)),
+            ("unlock-burn-height", Value::UInt(200)),
+        ]);
+        assert_eq!(
+            parse_pox_stacking_result(&result),
+            Err(PoxParseError::BadField("lock-amount", Value::Int(1000)))
+        );
+        let result = ok_tuple(vec![
+            ("stacker", Value::Principal(stacker())),
+            ("lock-amount", Value::UInt(1000)),
+            ("unlock-burn-height", Value::UInt(u128::MAX)),
+        ]);
+        assert_eq!(
+            parse_pox_stacking_result(&result),
+            Err(PoxParseError::FieldOverflow(
+                "unlock-burn-height",
+                u128::MAX
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_pox_extend_and_increase_results() {
+        let result = ok_tuple(vec![
+            ("stacker", Value::Principal(stacker())),
+            ("unlock-burn-height", Value::UInt(300)),
+        ]);
+        assert_eq!(
+            parse_pox_extend_result(&result),
+            Ok(Ok(PoxExtendResult {
+                stacker: stacker(),
+                unlock_burn_height: 300,
+            }))
+        );
+        assert_eq!(
+            parse_pox_increase(&result),
+            Err(PoxParseError::MissingField("total-locked"))
+        );
+
+        let result = ok_tuple(vec![
+            ("stacker", Value::UInt(1)),
+            ("total-locked", Value::UInt(2000)),
+        ]);
+        assert_eq!(
+            parse_pox_increase(&result),
+            Err(PoxParseError::BadField("stacker", Value::UInt(1)))
+        );
+        let result = ok_tuple(vec![
+            ("stacker", Value::Principal(stacker())),
+            ("total-locked", Value::UInt(2000)),
+        ]);
+        assert_eq!(
+            parse_pox_increase(&result),
+            Ok(Ok(PoxIncreaseResult {
+                stacker: stacker(),
+                total_locked: 2000,
+            }))
+        );
+        assert_eq!(
+            parse_pox_increase(&Value::error(Value::Int(9)).unwrap()),
+            Ok(Err(9))
+        );
+    }
+}
@@ -0,0 +1,477 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Re-analysis of deployed contracts after an epoch transition.
+//!
+//! A contract's analysis is computed once, under the type rules of the epoch in which it was
+//! deployed, and is then read back by every later contract call and deployment that refers to
+//! it.  When a new epoch changes the type checker, this module re-runs the analysis of every
+//! contract deployed before the transition under the new epoch's rules and compares the result
+//! with the stored analysis (as it is canonicalized for the new epoch at runtime).  Contracts
+//! which no longer type-check, or whose analyzed types differ, are flagged for operators.
+//!
+//! The stored analyses themselves are never rewritten: they are consensus-critical, and the
+//! re-analysis only reports on them.
+//!
+//! Re-analysis runs in small batches (see `run_contract_reanalysis_step()`), and its progress
+//! is checkpointed in its own database after each batch, so it can be spread across many
+//! calls and resumed after a restart.
+
+use std::convert::TryFrom;
+use std::path::Path;
+
+use rusqlite::types::ToSql;
+use rusqlite::{Row, Transaction, NO_PARAMS};
+
+use clarity::vm::analysis;
+use clarity::vm::analysis::AnalysisDatabase;
+use clarity::vm::ast;
+use clarity::vm::ast::ASTRules;
+use clarity::vm::costs::LimitedCostTracker;
+use clarity::vm::database::{BurnStateDB, HeadersDB, SqliteConnection};
+use clarity::vm::types::QualifiedContractIdentifier;
+use clarity::vm::ClarityVersion;
+
+use crate::chainstate::stacks::index::marf::MarfConnection;
+use crate::chainstate::stacks::Error as ChainstateError;
+use crate::clarity_vm::clarity::{ClarityConnection, ClarityInstance, ClarityReadOnlyConnection};
+use crate::types::chainstate::StacksBlockId;
+use crate::types::StacksEpochId;
+use crate::util_lib::db::Error as db_error;
+use crate::util_lib::db::{
+    query_row, query_rows, sqlite_open, table_exists, tx_begin_immediate, u64_to_sql, DBConn,
+    FromColumn, FromRow,
+};
+
+use clarity::vm::analysis::analysis_db::StoredContractAnalysis;
+
+/// Name of the re-analysis progress database, relative to the chainstate root directory
+pub const CONTRACT_REANALYSIS_DB_NAME: &str = "contract_reanalysis.sqlite";
+
+/// Fields of a stored contract analysis which determine what a contract defines and how it may
+/// be called.  A difference in any of these between the stored analysis and the re-analysis
+/// flags the contract.
+const REANALYSIS_COMPARED_FIELDS: &[&str] = &[
+    "private_function_types",
+    "variable_types",
+    "public_function_types",
+    "read_only_function_types",
+    "map_types",
+    "persisted_variable_types",
+    "fungible_tokens",
+    "non_fungible_tokens",
+    "defined_traits",
+    "implemented_traits",
+];
+
+const CONTRACT_REANALYSIS_SCHEMA: &[&str] = &[
+    r#"
+    CREATE TABLE reanalysis_jobs(
+        -- the epoch whose type rules contracts are re-analyzed under
+        epoch INTEGER PRIMARY KEY NOT NULL,
+        -- the chain tip at which the job's contracts are enumerated and loaded
+        tip TEXT NOT NULL,
+        -- checkpoint: the last contract that was processed, in side-store key order
+        last_contract TEXT,
+        num_processed INTEGER NOT NULL,
+        num_flagged INTEGER NOT NULL,
+        finished INTEGER NOT NULL
+    );"#,
+    r#"
+    CREATE TABLE reanalysis_flags(
+        epoch INTEGER NOT NULL,
+        contract_id TEXT NOT NULL,
+        -- JSON-encoded ReanalysisChange
+        change TEXT NOT NULL,
+
+        PRIMARY KEY(epoch, contract_id),
+        FOREIGN KEY(epoch) REFERENCES reanalysis_jobs(epoch)
+    );"#,
+];
+
+/// How a contract's analysis changes under a new epoch's type rules
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReanalysisChange {
+    /// The contract no longer parses or type-checks.  Carries the error message.
+    CheckFailed(String),
+    /// The contract still type-checks, but these analysis fields differ from the stored analysis
+    FieldsChanged(Vec<String>),
+}
+
+/// Result of re-analyzing one contract
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReanalysisOutcome {
+    /// The contract is not visible at the job's tip, or it was already analyzed under the target
+    /// epoch's rules
+    Skipped,
+    /// The re-analysis agrees with the stored analysis
+    Unchanged,
+    /// The re-analysis differs from the stored analysis
+    Changed(ReanalysisChange),
+}
+
+/// Progress of re-analyzing all contracts under one epoch's rules
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReanalysisJob {
+    pub epoch: StacksEpochId,
+    pub tip: StacksBlockId,
+    /// Checkpoint: the last contract that was processed, in side-store key order
+    pub last_contract: Option<String>,
+    pub num_processed: u64,
+    pub num_flagged: u64,
+    pub finished: bool,
+}
+
+/// A contract that was flagged by a re-analysis job
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlaggedContract {
+    pub epoch: StacksEpochId,
+    pub contract_id: QualifiedContractIdentifier,
+    pub change: ReanalysisChange,
+}
+
+fn epoch_from_row(row: &Row, column: &str) -> Result<StacksEpochId, db_error> {
+    let epoch_u32: u32 = row.get_unwrap(column);
+    StacksEpochId::try_from(epoch_u32).map_err(|_| db_error::ParseError)
+}
+
+fn contract_id_from_str(contract_id: &str) -> Result<QualifiedContractIdentifier, db_error> {
+    QualifiedContractIdentifier::parse(contract_id).map_err(|_| db_error::ParseError)
+}
+
+impl FromRow<ReanalysisJob> for ReanalysisJob {
+    fn from_row<'a>(row: &'a Row) -> Result<ReanalysisJob, db_error> {
+        let epoch = epoch_from_row(row, "epoch")?;
+        let tip = StacksBlockId::from_column(row, "tip")?;
+        let last_contract: Option<String> = row.get_unwrap("last_contract");
+        let num_processed = u64::from_column(row, "num_processed")?;
+        let num_flagged = u64::from_column(row, "num_flagged")?;
+        let finished: bool = row.get_unwrap("finished");
+
+        Ok(ReanalysisJob {
+            epoch,
+            tip,
+            last_contract,
+            num_processed,
+            num_flagged,
+            finished,
+        })
+    }
+}
+
+impl FromRow<FlaggedContract> for FlaggedContract {
+    fn from_row<'a>(row: &'a Row) -> Result<FlaggedContract, db_error> {
+        let epoch = epoch_from_row(row, "epoch")?;
+        let contract_id_str: String = row.get_unwrap("contract_id");
+        let change_json: String = row.get_unwrap("change");
+        let change =
+            serde_json::from_str(&change_json).map_err(|e| db_error::SerializationError(e))?;
+
+        Ok(FlaggedContract {
+            epoch,
+            contract_id: contract_id_from_str(&contract_id_str)?,
+            change,
+        })
+    }
+}
+
+/// Database of re-analysis jobs, their checkpoints, and the contracts they flagged
+pub struct ContractReanalysisDB {
+    conn: DBConn,
+}
+
+impl ContractReanalysisDB {
+    /// Open the re-analysis database at the given path, creating it if it does not exist.
+    pub fn open(path: &Path) -> Result<ContractReanalysisDB, db_error> {
+        let mut conn = sqlite_open(
+            path,
+            rusqlite::OpenFlags::SQLITE_OPEN_CREATE | rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE,
+            true,
+        )?;
+
+        let tx = tx_begin_immediate(&mut conn)?;
+        if !table_exists(&tx, "reanalysis_jobs")? {
+            for cmd in CONTRACT_REANALYSIS_SCHEMA.iter() {
+                tx.execute_batch(cmd)?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(ContractReanalysisDB { conn })
+    }
+
+    /// Get the job that re-analyzes contracts under `epoch`'s rules, if it was started
+    pub fn get_job(&self, epoch: StacksEpochId) -> Result<Option<ReanalysisJob>, db_error> {
+        let args: &[&dyn ToSql] = &[&(epoch as u32)];
+        query_row(
+            &self.conn,
+            "SELECT * FROM reanalysis_jobs WHERE epoch = ?1",
+            args,
+        )
+    }
+
+    /// Get all jobs, in epoch order
+    pub fn get_jobs(&self) -> Result<Vec<ReanalysisJob>, db_error> {
+        query_rows(
+            &self.conn,
+            "SELECT * FROM reanalysis_jobs ORDER BY epoch",
+            NO_PARAMS,
+        )
+    }
+
+    /// Get the contracts flagged by the job for `epoch`, in contract order
+    pub fn get_flagged_contracts(
+        &self,
+        epoch: StacksEpochId,
+    ) -> Result<Vec<FlaggedContract>, db_error> {
+        let args: &[&dyn ToSql] = &[&(epoch as u32)];
+        query_rows(
+            &self.conn,
+            "SELECT * FROM reanalysis_flags WHERE epoch = ?1 ORDER BY contract_id",
+            args,
+        )
+    }
+
+    fn insert_job(tx: &Transaction, job: &ReanalysisJob) -> Result<(), db_error> {
+        let args: &[&dyn ToSql] = &[
+            &(job.epoch as u32),
+            &job.tip,
+            &job.last_contract,
+            &u64_to_sql(job.num_processed)?,
+            &u64_to_sql(job.num_flagged)?,
+            &job.finished,
+        ];
+        tx.execute(
+            "INSERT OR REPLACE INTO reanalysis_jobs
+             (epoch, tip, last_contract, num_processed, num_flagged, finished)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            args,
+        )?;
+        Ok(())
+    }
+
+    /// Atomically record a batch's flagged contracts along with the job's new checkpoint, so
+    /// that a batch is either fully recorded or re-run after a restart.
+    fn checkpoint(
+        &mut self,
+        job: &ReanalysisJob,
+        flagged: &[(QualifiedContractIdentifier, ReanalysisChange)],
+    ) -> Result<(), db_error> {
+        let tx = tx_begin_immediate(&mut self.conn)?;
+        ContractReanalysisDB::insert_job(&tx, job)?;
+        for (contract_id, change) in flagged.iter() {
+            let change_json =
+                serde_json::to_string(change).map_err(|e| db_error::SerializationError(e))?;
+            let args: &[&dyn ToSql] =
+                &[&(job.epoch as u32), &contract_id.to_string(), &change_json];
+            tx.execute(
+                "INSERT OR REPLACE INTO reanalysis_flags (epoch, contract_id, change) VALUES (?1, ?2, ?3)",
+                args,
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// Re-analyze a single deployed contract under `epoch`'s type rules, and compare the result
+/// with its stored analysis.
+pub fn reanalyze_contract(
+    conn: &mut ClarityReadOnlyConnection,
+    contract_id: &QualifiedContractIdentifier,
+    epoch: StacksEpochId,
+) -> Result<ReanalysisOutcome, ChainstateError> {
+    let stored = match conn.with_analysis_db_readonly(|db| db.load_stored_contract(contract_id)) {
+        Some(stored) => stored,
+        None => {
+            // deployed on another fork
+            return Ok(ReanalysisOutcome::Skipped);
+        }
+    };
+    if stored.get_field::<StacksEpochId>("epoch") == Some(epoch) {
+        return Ok(ReanalysisOutcome::Skipped);
+    }
+
+    let clarity_version = stored
+        .get_field("clarity_version")
+        .unwrap_or(ClarityVersion::Clarity1);
+    let contract_src = conn
+        .with_clarity_db_readonly(|db| db.get_contract_src(contract_id))
+        .ok_or_else(|| {
+            ChainstateError::InvalidStacksBlock(format!(
+                "No source stored for analyzed contract {}",
+                contract_id
+            ))
+        })?;
+
+    let reanalysis = conn.with_analysis_db_readonly(|db| {
+        let mut cost_track = LimitedCostTracker::new_free();
+        let mut contract_ast = ast::build_ast_with_rules(
+            contract_id,
+            &contract_src,
+            &mut cost_track,
+            clarity_version,
+            epoch,
+            ASTRules::PrecheckSize,
+        )
+        .map_err(|e| e.to_string())?;
+        analysis::run_analysis(
+            contract_id,
+            &mut contract_ast.expressions,
+            db,
+            false,
+            cost_track,
+            epoch,
+            clarity_version,
+        )
+        .map_err(|(e, _)| e.to_string())
+    });
+    let reanalysis = match reanalysis {
+        Ok(reanalysis) => StoredContractAnalysis::from_analysis(&reanalysis),
+        Err(msg) => {
+            return Ok(ReanalysisOutcome::Changed(ReanalysisChange::CheckFailed(
+                msg,
+            )))
+        }
+    };
+
+    // compare against the stored analysis as it is seen by the new epoch at runtime
+    let mut stored_analysis = stored.to_contract_analysis();
+    stored_analysis.canonicalize_types(&epoch);
+    let stored = StoredContractAnalysis::from_analysis(&stored_analysis);
+
+    let changed_fields: Vec<String> = REANALYSIS_COMPARED_FIELDS
+        .iter()
+        .filter(|field| {
+            stored.get_field::<serde_json::Value>(field)
+                != reanalysis.get_field::<serde_json::Value>(field)
+        })
+        .map(|field| field.to_string())
+        .collect();
+
+    if changed_fields.is_empty() {
+        Ok(ReanalysisOutcome::Unchanged)
+    } else {
+        Ok(ReanalysisOutcome::Changed(ReanalysisChange::FieldsChanged(
+            changed_fields,
+        )))
+    }
+}
+
+/// List the next `limit` contracts with a stored analysis after `after`, in side-store key order.
+/// This includes contracts deployed on any fork.
+fn list_analyzed_contracts(
+    clarity_instance: &mut ClarityInstance,
+    after: Option<&str>,
+    limit: u32,
+) -> Vec<String> {
+    clarity_instance.with_marf(|marf| {
+        SqliteConnection::list_contracts_with_metadata(
+            marf.sqlite_conn(),
+            AnalysisDatabase::storage_key(),
+            after,
+            limit,
+        )
+    })
+}
+
+/// Run one batch of contract re-analysis at chain tip `tip`.
+///
+/// If no job has been started for the epoch `tip` was evaluated in, a job is started for it,
+/// pinned to `tip`; the job's contracts are then enumerated and loaded at that tip even as the
+/// chain grows, since contracts deployed afterwards were analyzed under the new rules already.
+/// At most `max_contracts` contracts are re-analyzed, and the job's progress and any flagged
+/// contracts are checkpointed before returning.  Returns the job's updated state.
+pub fn run_contract_reanalysis_step(
+    reanalysis_db: &mut ContractReanalysisDB,
+    clarity_instance: &mut ClarityInstance,
+    header_db: &dyn HeadersDB,
+    burn_state_db: &dyn BurnStateDB,
+    tip: &StacksBlockId,
+    max_contracts: u32,
+) -> Result<ReanalysisJob, ChainstateError> {
+    let epoch = clarity_instance
+        .read_only_connection_checked(tip, header_db, burn_state_db)?
+        .get_epoch();
+
+    let mut job = match reanalysis_db.get_job(epoch)? {
+        Some(job) => job,
+        None => {
+            info!("Starting contract re-analysis for epoch {}", epoch; "tip" => %tip);
+            ReanalysisJob {
+                epoch,
+                tip: tip.clone(),
+                last_contract: None,
+                num_processed: 0,
+                num_flagged: 0,
+                finished: false,
+            }
+        }
+    };
+    if job.finished {
+        return Ok(job);
+    }
+
+    let batch = list_analyzed_contracts(
+        clarity_instance,
+        job.last_contract.as_deref(),
+        max_contracts,
+    );
+    let mut flagged = vec![];
+    {
+        let mut conn =
+            clarity_instance.read_only_connection_checked(&job.tip, header_db, burn_state_db)?;
+        for contract_id_str in batch.iter() {
+            job.last_contract = Some(contract_id_str.clone());
+            let contract_id = match QualifiedContractIdentifier::parse(contract_id_str) {
+                Ok(contract_id) => contract_id,
+                Err(e) => {
+                    warn!(
+                        "Skipping unparseable contract ID {}: {:?}",
+                        contract_id_str, &e
+                    );
+                    continue;
+                }
+            };
+
+            match reanalyze_contract(&mut conn, &contract_id, epoch)? {
+                ReanalysisOutcome::Skipped => {}
+                ReanalysisOutcome::Unchanged => {
+                    job.num_processed += 1;
+                }
+                ReanalysisOutcome::Changed(change) => {
+                    warn!("Contract analysis changes under new epoch rules";
+                          "contract_id" => %contract_id,
+                          "epoch" => %epoch,
+                          "change" => ?change);
+                    job.num_processed += 1;
+                    job.num_flagged += 1;
+                    flagged.push((contract_id, change));
+                }
+            }
+        }
+    }
+
+    if (batch.len() as u64) < u64::from(max_contracts) {
+        info!("Finished contract re-analysis for epoch {}", epoch;
+              "num_processed" => job.num_processed,
+              "num_flagged" => job.num_flagged);
+        job.finished = true;
+    }
+
+    reanalysis_db.checkpoint(&job, &flagged)?;
+    Ok(job)
+}
@@ -19,13 +19,15 @@ use clarity::vm::costs::cost_functions::ClarityCostFunction;
 use clarity::vm::costs::{CostTracker, MemoryConsumer};
 use clarity::vm::{ast, eval_all};
 use std::cmp;
-use std::convert::{TryFrom, TryInto};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::{Arc, RwLock};
 
 use crate::chainstate::stacks::boot::{POX_1_NAME, POX_2_NAME, POX_3_NAME};
 use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::Error as ChainstateError;
 use crate::chainstate::stacks::StacksMicroblockHeader;
-use crate::util_lib::boot::boot_code_id;
+use crate::util_lib::boot::{boot_code_id, network_boot_code_id};
 use clarity::vm::contexts::{Environment, GlobalContext};
 use clarity::vm::errors::Error;
 use clarity::vm::errors::{
@@ -38,141 +40,43 @@ use clarity::vm::types::{
 };
 
 use clarity::vm::clarity::Error as clarity_interpreter_error;
-use clarity::vm::events::{STXEventType, STXLockEventData, StacksTransactionEvent};
+use clarity::vm::database::ClarityDatabase;
+use clarity::vm::events::{
+    STXEventType, STXLockEventData, STXLockState, STXUnlockEventData, StacksTransactionEvent,
+};
 use clarity::vm::ClarityVersion;
 
 use crate::chainstate::stacks::address::PoxAddress;
+use crate::clarity_vm::pox_return_parser::{
+    parse_pox_extend_result, parse_pox_increase, parse_pox_stacking_result, PoxExtendResult,
+    PoxIncreaseResult, PoxStackingResult,
+};
 use crate::core::StacksEpochId;
 use stacks_common::util::hash::Hash160;
 
 use crate::vm::costs::runtime_cost;
 
-/// Parse the returned value from PoX `stack-stx` and `delegate-stack-stx` functions
-///  from pox-2.clar or pox-3.clar into a format more readily digestible in rust.
-/// Panics if the supplied value doesn't match the expected tuple structure
-fn parse_pox_stacking_result(
-    result: &Value,
-) -> std::result::Result<(PrincipalData, u128, u64), i128> {
-    match result.clone().expect_result() {
-        Ok(res) => {
-            // should have gotten back (ok { stacker: principal, lock-amount: uint, unlock-burn-height: uint .. } .. })))
-            let tuple_data = res.expect_tuple();
-            let stacker = tuple_data
-                .get("stacker")
-                .expect(&format!("FATAL: no 'stacker'"))
-                .to_owned()
-                .expect_principal();
-
-            let lock_amount = tuple_data
-                .get("lock-amount")
-                .expect(&format!("FATAL: no 'lock-amount'"))
-                .to_owned()
-                .expect_u128();
-
-            let unlock_burn_height = tuple_data
-                .get("unlock-burn-height")
-                .expect(&format!("FATAL: no 'unlock-burn-height'"))
-                .to_owned()
-                .expect_u128()
-                .try_into()
-                .expect("FATAL: 'unlock-burn-height' overflow");
-
-            Ok((stacker, lock_amount, unlock_burn_height))
-        }
-        Err(e) => Err(e.expect_i128()),
-    }
-}
-
-/// Parse the returned value from PoX `stack-stx` and `delegate-stack-stx` functions
-///  from pox.clar into a format more readily digestible in rust.
-/// Panics if the supplied value doesn't match the expected tuple structure
-fn parse_pox_stacking_result_v1(
-    result: &Value,
-) -> std::result::Result<(PrincipalData, u128, u64), i128> {
-    match result.clone().expect_result() {
-        Ok(res) => {
-            // should have gotten back (ok (tuple (stacker principal) (lock-amount uint) (unlock-burn-height uint)))
-            let tuple_data = res.expect_tuple();
-            let stacker = tuple_data
-                .get("stacker")
-                .expect(&format!("FATAL: no 'stacker'"))
-                .to_owned()
-                .expect_principal();
-
-            let lock_amount = tuple_data
-                .get("lock-amount")
-                .expect(&format!("FATAL: no 'lock-amount'"))
-                .to_owned()
-                .expect_u128();
-
-            let unlock_burn_height = tuple_data
-                .get("unlock-burn-height")
-                .expect(&format!("FATAL: no 'unlock-burn-height'"))
-                .to_owned()
-                .expect_u128()
-                .try_into()
-                .expect("FATAL: 'unlock-burn-height' overflow");
-
-            Ok((stacker, lock_amount, unlock_burn_height))
-        }
-        Err(e) => Err(e.expect_i128()),
-    }
-}
-
-/// Parse the returned value from PoX2 or PoX3 `stack-extend` and `delegate-stack-extend` functions
-///  into a format more readily digestible in rust.
-/// Panics if the supplied value doesn't match the expected tuple structure
-fn parse_pox_extend_result(result: &Value) -> std::result::Result<(PrincipalData, u64), i128> {
-    match result.clone().expect_result() {
-        Ok(res) => {
-            // should have gotten back (ok { stacker: principal, unlock-burn-height: uint .. } .. })
-            let tuple_data = res.expect_tuple();
-            let stacker = tuple_data
-                .get("stacker")
-                .expect(&format!("FATAL: no 'stacker'"))
-                .to_owned()
-                .expect_principal();
-
-            let unlock_burn_height = tuple_data
-                .get("unlock-burn-height")
-                .expect(&format!("FATAL: no 'unlock-burn-height'"))
-                .to_owned()
-                .expect_u128()
-                .try_into()
-                .expect("FATAL: 'unlock-burn-height' overflow");
-
-            Ok((stacker, unlock_burn_height))
-        }
-        // in the error case, the function should have returned `int` error code
-        Err(e) => Err(e.expect_i128()),
-    }
-}
-
-/// Parse the returned value from PoX2 or PoX3 `stack-increase` function
-///  into a format more readily digestible in rust.
-/// Panics if the supplied value doesn't match the expected tuple structure
-fn parse_pox_increase(result: &Value) -> std::result::Result<(PrincipalData, u128), i128> {
-    match result.clone().expect_result() {
-        Ok(res) => {
-            // should have gotten back (ok { stacker: principal, total-locked: uint .. } .. })
-            let tuple_data = res.expect_tuple();
-            let stacker = tuple_data
-                .get("stacker")
-                .expect(&format!("FATAL: no 'stacker'"))
-                .to_owned()
-                .expect_principal();
-
-            let total_locked = tuple_data
-                .get("total-locked")
-                .expect(&format!("FATAL: no 'total-locked'"))
-                .to_owned()
-                .expect_u128();
-
-            Ok((stacker, total_locked))
-        }
-        // in the error case, the function should have returned `int` error code
-        Err(e) => Err(e.expect_i128()),
+/// PoX functions that release a stacker's lock before its unlock height.  Each takes the
+/// stacker as its first argument and the amount it expects to be locked as its second, and once
+/// it has removed the stacker's stacking state, the VM unlocks the stacker's STX and emits an
+/// `STXUnlockEvent`.
+/// `handle-unlock` is private, and is only invoked by the block processor at the start of a
+/// reward cycle, for stackers who missed their reward slots.
+const POX_UNLOCK_FUNCTIONS: &[&str] = &["handle-unlock"];
+
+/// Read the lock state of `principal`'s account, before a PoX special-case handler updates it
+fn get_prior_lock_state(
+    db: &mut ClarityDatabase,
+    principal: &PrincipalData,
+) -> Option<STXLockState> {
+    let mut snapshot = db.get_stx_balance_snapshot(principal);
+    if !snapshot.has_locked_tokens() {
+        return None;
     }
+    Some(STXLockState {
+        locked_amount: snapshot.balance().amount_locked(),
+        unlock_height: snapshot.balance().unlock_height(),
+    })
 }
 
 /// Handle special cases when calling into the PoX API contract
@@ -185,7 +89,7 @@ fn handle_pox_v1_api_contract_call(
     if function_name == "stack-stx" || function_name == "delegate-stack-stx" {
         debug!(
             "Handle special-case contract-call to {:?} {} (which returned {:?})",
-            boot_code_id(POX_1_NAME, global_context.mainnet),
+            network_boot_code_id(POX_1_NAME, &global_context.network),
             function_name,
             value
         );
@@ -197,12 +101,17 @@ fn handle_pox_v1_api_contract_call(
             1,
         )?;
 
-        match parse_pox_stacking_result_v1(value) {
-            Ok((stacker, locked_amount, unlock_height)) => {
+        match parse_pox_stacking_result(value)? {
+            Ok(PoxStackingResult {
+                stacker,
+                lock_amount: locked_amount,
+                unlock_burn_height: unlock_height,
+            }) => {
                 // in most cases, if this fails, then there's a bug in the contract (since it already does
                 // the necessary checks), but with v2 introduction, that's no longer true -- if someone
                 // locks on PoX v2, and then tries to lock again in PoX v1, that's not captured by the v1
                 // contract.
+                let prior_lock = get_prior_lock_state(&mut global_context.database, &stacker);
                 match StacksChainState::pox_lock_v1(
                     &mut global_context.database,
                     &stacker,
@@ -216,10 +125,12 @@ fn handle_pox_v1_api_contract_call(
                                     locked_amount,
                                     unlock_height,
                                     locked_address: stacker,
-                                    contract_identifier: boot_code_id(
+                                    contract_identifier: network_boot_code_id(
                                         "pox",
-                                        global_context.mainnet,
+                                        &global_context.network,
                                     ),
+                                    function_name: function_name.to_string(),
+                                    prior_lock,
                                 }),
                             ));
                         }
@@ -680,7 +591,7 @@ fn handle_stack_lockup_pox_v2(
 ) -> Result<Option<StacksTransactionEvent>> {
     debug!(
         "Handle special-case contract-call to {:?} {} (which returned {:?})",
-        boot_code_id(POX_2_NAME, global_context.mainnet),
+        network_boot_code_id(POX_2_NAME, &global_context.network),
         function_name,
         value
     );
@@ -691,8 +602,13 @@ fn handle_stack_lockup_pox_v2(
         1,
     )?;
 
-    match parse_pox_stacking_result(value) {
-        Ok((stacker, locked_amount, unlock_height)) => {
+    match parse_pox_stacking_result(value)? {
+        Ok(PoxStackingResult {
+            stacker,
+            lock_amount: locked_amount,
+            unlock_burn_height: unlock_height,
+        }) => {
+            let prior_lock = get_prior_lock_state(&mut global_context.database, &stacker);
             match StacksChainState::pox_lock_v2(
                 &mut global_context.database,
                 &stacker,
@@ -705,7 +621,12 @@ fn handle_stack_lockup_pox_v2(
                             locked_amount,
                             unlock_height,
                             locked_address: stacker,
-                            contract_identifier: boot_code_id("pox-2", global_context.mainnet),
+                            contract_identifier: network_boot_code_id(
+                                "pox-2",
+                                &global_context.network,
+                            ),
+                            function_name: function_name.to_string(),
+                            prior_lock,
                         },
                     ));
                     return Ok(Some(event));
@@ -745,7 +666,7 @@ fn handle_stack_lockup_extension_pox_v2(
     // applying a pox lock at this point is equivalent to evaluating a transfer
     debug!(
         "Handle special-case contract-call to {:?} {} (which returned {:?})",
-        boot_code_id("pox-2", global_context.mainnet),
+        network_boot_code_id("pox-2", &global_context.network),
         function_name,
         value
     );
@@ -756,7 +677,12 @@ fn handle_stack_lockup_extension_pox_v2(
         1,
     )?;
 
-    if let Ok((stacker, unlock_height)) = parse_pox_extend_result(value) {
+    if let Ok(PoxExtendResult {
+        stacker,
+        unlock_burn_height: unlock_height,
+    }) = parse_pox_extend_result(value)?
+    {
+        let prior_lock = get_prior_lock_state(&mut global_context.database, &stacker);
         match StacksChainState::pox_lock_extend_v2(
             &mut global_context.database,
             &stacker,
@@ -768,7 +694,9 @@ fn handle_stack_lockup_extension_pox_v2(
                         locked_amount,
                         unlock_height,
                         locked_address: stacker,
-                        contract_identifier: boot_code_id("pox-2", global_context.mainnet),
+                        contract_identifier: network_boot_code_id("pox-2", &global_context.network),
+                        function_name: function_name.to_string(),
+                        prior_lock,
                     },
                 ));
                 return Ok(Some(event));
@@ -807,7 +735,7 @@ fn handle_stack_lockup_increase_pox_v2(
     // applying a pox lock at this point is equivalent to evaluating a transfer
     debug!(
         "Handle special-case contract-call";
-        "contract" => ?boot_code_id("pox-2", global_context.mainnet),
+        "contract" => ?network_boot_code_id("pox-2", &global_context.network),
         "function" => function_name,
         "return-value" => %value,
     );
@@ -818,7 +746,12 @@ fn handle_stack_lockup_increase_pox_v2(
         1,
     )?;
 
-    if let Ok((stacker, total_locked)) = parse_pox_increase(value) {
+    if let Ok(PoxIncreaseResult {
+        stacker,
+        total_locked,
+    }) = parse_pox_increase(value)?
+    {
+        let prior_lock = get_prior_lock_state(&mut global_context.database, &stacker);
         match StacksChainState::pox_lock_increase_v2(
             &mut global_context.database,
             &stacker,
@@ -830,7 +763,9 @@ fn handle_stack_lockup_increase_pox_v2(
                         locked_amount: new_balance.amount_locked(),
                         unlock_height: new_balance.unlock_height(),
                         locked_address: stacker,
-                        contract_identifier: boot_code_id("pox-2", global_context.mainnet),
+                        contract_identifier: network_boot_code_id("pox-2", &global_context.network),
+                        function_name: function_name.to_string(),
+                        prior_lock,
                     },
                 ));
 
@@ -854,6 +789,78 @@ fn handle_stack_lockup_increase_pox_v2(
     }
 }
 
+/// Handle responses from pox-2 and pox-3 functions that *release already-locked* STX before
+/// their unlock height (see `POX_UNLOCK_FUNCTIONS`).  The stacker's STX unlock at the next burn
+/// block.
+fn handle_stack_unlock(
+    global_context: &mut GlobalContext,
+    contract_id: &QualifiedContractIdentifier,
+    function_name: &str,
+    args: &[Value],
+    value: &Value,
+) -> Result<Option<StacksTransactionEvent>> {
+    debug!(
+        "Handle special-case contract-call";
+        "contract" => %contract_id,
+        "function" => function_name,
+        "return-value" => %value,
+    );
+
+    // applying a pox unlock at this point is equivalent to evaluating a transfer
+    runtime_cost(
+        ClarityCostFunction::StxTransfer,
+        &mut global_context.cost_track,
+        1,
+    )?;
+
+    let committed = match value {
+        Value::Response(response) => response.committed,
+        _ => false,
+    };
+    if !committed {
+        return Ok(None);
+    }
+
+    let (stacker, expected_locked) = match args {
+        [Value::Principal(stacker), Value::UInt(expected_locked), ..] => {
+            (stacker.clone(), *expected_locked)
+        }
+        _ => {
+            return Err(InterpreterError::InterpreterError(format!(
+                "Unexpected arguments to PoX unlock function {}: {:?}",
+                function_name, args
+            ))
+            .into());
+        }
+    };
+
+    let prior_lock =
+        get_prior_lock_state(&mut global_context.database, &stacker).ok_or_else(|| -> Error {
+            InterpreterError::InterpreterError(format!(
+                "Failed to release lock for {}: account is not locked",
+                stacker
+            ))
+            .into()
+        })?;
+
+    match StacksChainState::pox_unlock(&mut global_context.database, &stacker, expected_locked) {
+        Ok(unlocked_amount) => Ok(Some(StacksTransactionEvent::STXEvent(
+            STXEventType::STXUnlockEvent(STXUnlockEventData {
+                unlocked_amount,
+                unlocked_address: stacker,
+                contract_identifier: contract_id.clone(),
+                function_name: function_name.to_string(),
+                prior_lock,
+            }),
+        ))),
+        Err(e) => Err(InterpreterError::InterpreterError(format!(
+            "Failed to release lock for {}: {}",
+            stacker, &e
+        ))
+        .into()),
+    }
+}
+
 /// Handle special cases when calling into the PoX API contract
 fn handle_pox_v2_api_contract_call(
     global_context: &mut GlobalContext,
@@ -907,6 +914,8 @@ fn handle_pox_v2_api_contract_call(
         handle_stack_lockup_extension_pox_v2(global_context, function_name, value)?
     } else if function_name == "stack-increase" || function_name == "delegate-stack-increase" {
         handle_stack_lockup_increase_pox_v2(global_context, function_name, value)?
+    } else if POX_UNLOCK_FUNCTIONS.contains(&function_name) {
+        handle_stack_unlock(global_context, contract_id, function_name, args, value)?
     } else {
         None
     };
@@ -934,7 +943,7 @@ fn handle_stack_lockup_pox_v3(
 ) -> Result<Option<StacksTransactionEvent>> {
     debug!(
         "Handle special-case contract-call to {:?} {} (which returned {:?})",
-        boot_code_id(POX_3_NAME, global_context.mainnet),
+        network_boot_code_id(POX_3_NAME, &global_context.network),
         function_name,
         value
     );
@@ -945,8 +954,13 @@ fn handle_stack_lockup_pox_v3(
         1,
     )?;
 
-    match parse_pox_stacking_result(value) {
-        Ok((stacker, locked_amount, unlock_height)) => {
+    match parse_pox_stacking_result(value)? {
+        Ok(PoxStackingResult {
+            stacker,
+            lock_amount: locked_amount,
+            unlock_burn_height: unlock_height,
+        }) => {
+            let prior_lock = get_prior_lock_state(&mut global_context.database, &stacker);
             match StacksChainState::pox_lock_v3(
                 &mut global_context.database,
                 &stacker,
@@ -959,7 +973,12 @@ fn handle_stack_lockup_pox_v3(
                             locked_amount,
                             unlock_height,
                             locked_address: stacker,
-                            contract_identifier: boot_code_id(POX_3_NAME, global_context.mainnet),
+                            contract_identifier: network_boot_code_id(
+                                POX_3_NAME,
+                                &global_context.network,
+                            ),
+                            function_name: function_name.to_string(),
+                            prior_lock,
                         },
                     ));
                     return Ok(Some(event));
@@ -999,7 +1018,7 @@ fn handle_stack_lockup_extension_pox_v3(
     // applying a pox lock at this point is equivalent to evaluating a transfer
     debug!(
         "Handle special-case contract-call to {:?} {} (which returned {:?})",
-        boot_code_id("pox-3", global_context.mainnet),
+        network_boot_code_id("pox-3", &global_context.network),
         function_name,
         value
     );
@@ -1010,7 +1029,12 @@ fn handle_stack_lockup_extension_pox_v3(
         1,
     )?;
 
-    if let Ok((stacker, unlock_height)) = parse_pox_extend_result(value) {
+    if let Ok(PoxExtendResult {
+        stacker,
+        unlock_burn_height: unlock_height,
+    }) = parse_pox_extend_result(value)?
+    {
+        let prior_lock = get_prior_lock_state(&mut global_context.database, &stacker);
         match StacksChainState::pox_lock_extend_v3(
             &mut global_context.database,
             &stacker,
@@ -1022,7 +1046,12 @@ fn handle_stack_lockup_extension_pox_v3(
                         locked_amount,
                         unlock_height,
                         locked_address: stacker,
-                        contract_identifier: boot_code_id(POX_3_NAME, global_context.mainnet),
+                        contract_identifier: network_boot_code_id(
+                            POX_3_NAME,
+                            &global_context.network,
+                        ),
+                        function_name: function_name.to_string(),
+                        prior_lock,
                     },
                 ));
                 return Ok(Some(event));
@@ -1061,7 +1090,7 @@ fn handle_stack_lockup_increase_pox_v3(
     // applying a pox lock at this point is equivalent to evaluating a transfer
     debug!(
         "Handle special-case contract-call";
-        "contract" => ?boot_code_id("pox-3", global_context.mainnet),
+        "contract" => ?network_boot_code_id("pox-3", &global_context.network),
         "function" => function_name,
         "return-value" => %value,
     );
@@ -1072,7 +1101,12 @@ fn handle_stack_lockup_increase_pox_v3(
         1,
     )?;
 
-    if let Ok((stacker, total_locked)) = parse_pox_increase(value) {
+    if let Ok(PoxIncreaseResult {
+        stacker,
+        total_locked,
+    }) = parse_pox_increase(value)?
+    {
+        let prior_lock = get_prior_lock_state(&mut global_context.database, &stacker);
         match StacksChainState::pox_lock_increase_v3(
             &mut global_context.database,
             &stacker,
@@ -1084,7 +1118,12 @@ fn handle_stack_lockup_increase_pox_v3(
                         locked_amount: new_balance.amount_locked(),
                         unlock_height: new_balance.unlock_height(),
                         locked_address: stacker,
-                        contract_identifier: boot_code_id(POX_3_NAME, global_context.mainnet),
+                        contract_identifier: network_boot_code_id(
+                            POX_3_NAME,
+                            &global_context.network,
+                        ),
+                        function_name: function_name.to_string(),
+                        prior_lock,
                     },
                 ));
 
@@ -1161,6 +1200,8 @@ fn handle_pox_v3_api_contract_call(
         handle_stack_lockup_extension_pox_v3(global_context, function_name, value)?
     } else if function_name == "stack-increase" || function_name == "delegate-stack-increase" {
         handle_stack_lockup_increase_pox_v3(global_context, function_name, value)?
+    } else if POX_UNLOCK_FUNCTIONS.contains(&function_name) {
+        handle_stack_unlock(global_context, contract_id, function_name, args, value)?
     } else {
         None
     };
@@ -1219,8 +1260,34 @@ fn is_pox_v2_read_only(func_name: &str) -> bool {
         || "get-total-pox-rejection" == func_name
 }
 
-/// Handle special cases of contract-calls -- namely, those into PoX that should lock up STX
-pub fn handle_contract_call_special_cases(
+/// Handle the special cases of calls into PoX-1
+fn handle_pox_v1_contract_call_special_case(
+    global_context: &mut GlobalContext,
+    sender: Option<&PrincipalData>,
+    _sponsor: Option<&PrincipalData>,
+    contract_id: &QualifiedContractIdentifier,
+    function_name: &str,
+    _args: &[Value],
+    result: &Value,
+) -> Result<()> {
+    if !is_pox_v1_read_only(function_name)
+        && global_context.database.get_v1_unlock_height()
+            <= global_context.database.get_current_burnchain_block_height()
+    {
+        // NOTE: get-pox-info is read-only, so it can call old pox v1 stuff
+        warn!("PoX-1 function call attempted on an account after v1 unlock height";
+              "v1_unlock_ht" => global_context.database.get_v1_unlock_height(),
+              "current_burn_ht" => global_context.database.get_current_burnchain_block_height(),
+              "function_name" => function_name,
+              "contract_id" => %contract_id
+        );
+        return Err(Error::Runtime(RuntimeErrorType::DefunctPoxContract, None));
+    }
+    handle_pox_v1_api_contract_call(global_context, sender, function_name, result)
+}
+
+/// Handle the special cases of calls into PoX-2
+fn handle_pox_v2_contract_call_special_case(
     global_context: &mut GlobalContext,
     sender: Option<&PrincipalData>,
     _sponsor: Option<&PrincipalData>,
@@ -1229,52 +1296,177 @@ pub fn handle_contract_call_special_cases(
     args: &[Value],
     result: &Value,
 ) -> Result<()> {
-    if *contract_id == boot_code_id(POX_1_NAME, global_context.mainnet) {
-        if !is_pox_v1_read_only(function_name)
-            && global_context.database.get_v1_unlock_height()
-                <= global_context.database.get_current_burnchain_block_height()
-        {
-            // NOTE: get-pox-info is read-only, so it can call old pox v1 stuff
-            warn!("PoX-1 function call attempted on an account after v1 unlock height";
-                  "v1_unlock_ht" => global_context.database.get_v1_unlock_height(),
-                  "current_burn_ht" => global_context.database.get_current_burnchain_block_height(),
-                  "function_name" => function_name,
-                  "contract_id" => %contract_id
-            );
-            return Err(Error::Runtime(RuntimeErrorType::DefunctPoxContract, None));
-        }
-        return handle_pox_v1_api_contract_call(global_context, sender, function_name, result);
-    } else if *contract_id == boot_code_id(POX_2_NAME, global_context.mainnet) {
-        if !is_pox_v2_read_only(function_name) && global_context.epoch_id >= StacksEpochId::Epoch22
-        {
-            warn!("PoX-2 function call attempted on an account after Epoch 2.2";
-                  "v2_unlock_ht" => global_context.database.get_v2_unlock_height(),
-                  "current_burn_ht" => global_context.database.get_current_burnchain_block_height(),
-                  "function_name" => function_name,
-                  "contract_id" => %contract_id
-            );
-            return Err(Error::Runtime(RuntimeErrorType::DefunctPoxContract, None));
-        }
+    if !is_pox_v2_read_only(function_name) && global_context.epoch_id >= StacksEpochId::Epoch22 {
+        warn!("PoX-2 function call attempted on an account after Epoch 2.2";
+              "v2_unlock_ht" => global_context.database.get_v2_unlock_height(),
+              "current_burn_ht" => global_context.database.get_current_burnchain_block_height(),
+              "function_name" => function_name,
+              "contract_id" => %contract_id
+        );
+        return Err(Error::Runtime(RuntimeErrorType::DefunctPoxContract, None));
+    }
+
+    handle_pox_v2_api_contract_call(
+        global_context,
+        sender,
+        contract_id,
+        function_name,
+        args,
+        result,
+    )
+}
+
+/// Handle the special cases of calls into PoX-3
+fn handle_pox_v3_contract_call_special_case(
+    global_context: &mut GlobalContext,
+    sender: Option<&PrincipalData>,
+    _sponsor: Option<&PrincipalData>,
+    contract_id: &QualifiedContractIdentifier,
+    function_name: &str,
+    args: &[Value],
+    result: &Value,
+) -> Result<()> {
+    handle_pox_v3_api_contract_call(
+        global_context,
+        sender,
+        contract_id,
+        function_name,
+        args,
+        result,
+    )
+}
 
-        return handle_pox_v2_api_contract_call(
+/// A handler for the special cases of calls into one contract.  It runs after each call into the
+/// contract's public functions that returns a response, whether `ok` or `err`, and can update the
+/// chainstate in ways that the contract itself cannot (e.g. lock STX).  An error aborts the
+/// transaction.
+///
+/// Any `Fn` with the same arguments as `handle_contract_call_special_cases` is a handler.
+pub trait SpecialCaseHandler: Send + Sync {
+    fn handle(
+        &self,
+        global_context: &mut GlobalContext,
+        sender: Option<&PrincipalData>,
+        sponsor: Option<&PrincipalData>,
+        contract_id: &QualifiedContractIdentifier,
+        function_name: &str,
+        args: &[Value],
+        result: &Value,
+    ) -> Result<()>;
+}
+
+impl<F> SpecialCaseHandler for F
+where
+    F: Fn(
+            &mut GlobalContext,
+            Option<&PrincipalData>,
+            Option<&PrincipalData>,
+            &QualifiedContractIdentifier,
+            &str,
+            &[Value],
+            &Value,
+        ) -> Result<()>
+        + Send
+        + Sync,
+{
+    fn handle(
+        &self,
+        global_context: &mut GlobalContext,
+        sender: Option<&PrincipalData>,
+        sponsor: Option<&PrincipalData>,
+        contract_id: &QualifiedContractIdentifier,
+        function_name: &str,
+        args: &[Value],
+        result: &Value,
+    ) -> Result<()> {
+        self(
             global_context,
             sender,
+            sponsor,
             contract_id,
             function_name,
             args,
             result,
-        );
-    } else if *contract_id == boot_code_id(POX_3_NAME, global_context.mainnet) {
-        return handle_pox_v3_api_contract_call(
+        )
+    }
+}
+
+lazy_static! {
+    /// The special-case handlers, by the contract they handle.  The PoX contracts are registered
+    /// on both mainnet and testnet.
+    static ref SPECIAL_CASE_HANDLERS: RwLock<HashMap<QualifiedContractIdentifier, Arc<dyn SpecialCaseHandler>>> = {
+        let mut handlers: HashMap<QualifiedContractIdentifier, Arc<dyn SpecialCaseHandler>> =
+            HashMap::new();
+        for mainnet in [true, false] {
+            handlers.insert(
+                boot_code_id(POX_1_NAME, mainnet),
+                Arc::new(handle_pox_v1_contract_call_special_case),
+            );
+            handlers.insert(
+                boot_code_id(POX_2_NAME, mainnet),
+                Arc::new(handle_pox_v2_contract_call_special_case),
+            );
+            handlers.insert(
+                boot_code_id(POX_3_NAME, mainnet),
+                Arc::new(handle_pox_v3_contract_call_special_case),
+            );
+        }
+        RwLock::new(handlers)
+    };
+}
+
+/// Register the special-case handler for calls into `contract_id`, replacing (and returning)
+/// the one already registered, if any.  Handlers are consensus-critical: every node must
+/// register the same ones before processing any blocks.
+pub fn register_special_case_handler(
+    contract_id: QualifiedContractIdentifier,
+    handler: Arc<dyn SpecialCaseHandler>,
+) -> Option<Arc<dyn SpecialCaseHandler>> {
+    SPECIAL_CASE_HANDLERS
+        .write()
+        .expect("FATAL: special-case handler registry lock poisoned")
+        .insert(contract_id, handler)
+}
+
+/// Get the special-case handler for calls into `contract_id`, if there is one
+pub fn get_special_case_handler(
+    contract_id: &QualifiedContractIdentifier,
+) -> Option<Arc<dyn SpecialCaseHandler>> {
+    SPECIAL_CASE_HANDLERS
+        .read()
+        .expect("FATAL: special-case handler registry lock poisoned")
+        .get(contract_id)
+        .cloned()
+}
+
+/// Handle special cases of contract-calls -- namely, those into PoX that should lock up STX.
+/// This dispatches to the handler registered for the called contract, if any.  The boot
+/// contracts of a custom network are dispatched like those of the stock network it is based on.
+pub fn handle_contract_call_special_cases(
+    global_context: &mut GlobalContext,
+    sender: Option<&PrincipalData>,
+    sponsor: Option<&PrincipalData>,
+    contract_id: &QualifiedContractIdentifier,
+    function_name: &str,
+    args: &[Value],
+    result: &Value,
+) -> Result<()> {
+    let network = global_context.network;
+    let handler = if contract_id.issuer == network.boot_address.into() {
+        get_special_case_handler(&boot_code_id(contract_id.name.as_str(), network.mainnet))
+    } else {
+        get_special_case_handler(contract_id)
+    };
+    match handler {
+        Some(handler) => handler.handle(
             global_context,
             sender,
+            sponsor,
             contract_id,
             function_name,
             args,
             result,
-        );
+        ),
+        None => Ok(()),
     }
-
-    // TODO: insert more special cases here, as needed
-    Ok(())
 }
@@ -41,8 +41,8 @@ use crate::clarity_vm::clarity::ClarityConnection;
 use crate::clarity_vm::database::marf::MarfedKV;
 use crate::types::chainstate::{BlockHeaderHash, StacksBlockId};
 use crate::types::StacksEpochId;
-use clarity::vm::tests::test_only_mainnet_to_chain_id;
 use clarity::vm::ClarityVersion;
+use stacks_common::types::NetworkId;
 
 pub fn test_tracked_costs(
     prog: &str,
@@ -51,8 +51,8 @@ pub fn test_tracked_costs(
     version: ClarityVersion,
 ) -> ExecutionCost {
     let marf = MarfedKV::temporary();
-    let chain_id = test_only_mainnet_to_chain_id(use_mainnet);
-    let mut clarity_instance = ClarityInstance::new(use_mainnet, chain_id, marf);
+    let network = NetworkId::from_mainnet(use_mainnet);
+    let mut clarity_instance = ClarityInstance::new(network, marf);
 
     let p1 = vm_execute("'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR")
         .unwrap()
@@ -14,6 +14,7 @@ use stacks_common::types::StacksEpochId;
 use rstest::rstest;
 #[cfg(test)]
 use rstest_reuse::{self, *};
+use stacks_common::types::NetworkId;
 
 fn dependency_edge_counting_runtime(
     iters: usize,
@@ -30,7 +31,7 @@ fn dependency_edge_counting_runtime(
     }
 
     let marf = MarfedKV::temporary();
-    let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+    let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
 
     clarity_instance
         .begin_test_genesis_block(
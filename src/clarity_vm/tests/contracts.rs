@@ -59,6 +59,8 @@ use clarity::vm::database::MemoryBackingStore;
 use crate::chainstate::stacks::boot::contract_tests::{test_sim_height_to_hash, ClarityTestSim};
 use crate::clarity::vm::clarity::ClarityConnection;
 use crate::clarity::vm::clarity::TransactionConnection;
+use crate::clarity_vm::{get_special_case_handler, register_special_case_handler};
+use std::sync::{Arc, Mutex};
 
 #[test]
 // Here, we set up a basic test to see if we can recover a path from the ClarityTestSim.
@@ -893,3 +895,82 @@ fn trait_with_trait_invocation_cross_epoch() {
         });
     });
 }
+
+/// Test that a special-case handler registered for a contract runs after each call into it, and
+/// can abort the transaction.
+#[test]
+fn test_registered_special_case_handler() {
+    let mut sim = ClarityTestSim::new();
+    let sender = StacksAddress::burn_address(false).into();
+    let contract_id = QualifiedContractIdentifier::local("special-case-handled").unwrap();
+    let contract = "(define-public (handled (x int)) (ok x))";
+
+    let calls = Arc::new(Mutex::new(vec![]));
+    let handler_calls = calls.clone();
+    let handler = move |_global_context: &mut GlobalContext,
+                        _sender: Option<&PrincipalData>,
+                        _sponsor: Option<&PrincipalData>,
+                        _contract_id: &QualifiedContractIdentifier,
+                        function_name: &str,
+                        args: &[Value],
+                        result: &Value|
+          -> Result<(), Error> {
+        handler_calls.lock().unwrap().push((
+            function_name.to_string(),
+            args.to_vec(),
+            result.clone(),
+        ));
+        if args[0] == Value::Int(-1) {
+            return Err(Error::Runtime(RuntimeErrorType::DefunctPoxContract, None));
+        }
+        Ok(())
+    };
+    assert!(register_special_case_handler(contract_id.clone(), Arc::new(handler)).is_none());
+    assert!(get_special_case_handler(&contract_id).is_some());
+
+    sim.execute_next_block_as_conn(|conn| {
+        let clarity_version = ClarityVersion::default_for_epoch(conn.get_epoch());
+        publish_contract(conn, &contract_id, contract, clarity_version).unwrap();
+    });
+
+    sim.execute_next_block_as_conn(|conn| {
+        conn.as_transaction(|clarity_db| {
+            clarity_db
+                .run_contract_call(
+                    &sender,
+                    None,
+                    &contract_id,
+                    "handled",
+                    &[Value::Int(1)],
+                    |_, _| false,
+                )
+                .unwrap();
+            clarity_db
+                .run_contract_call(
+                    &sender,
+                    None,
+                    &contract_id,
+                    "handled",
+                    &[Value::Int(-1)],
+                    |_, _| false,
+                )
+                .unwrap_err();
+        });
+    });
+
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec![
+            (
+                "handled".to_string(),
+                vec![Value::Int(1)],
+                Value::okay(Value::Int(1)).unwrap()
+            ),
+            (
+                "handled".to_string(),
+                vec![Value::Int(-1)],
+                Value::okay(Value::Int(-1)).unwrap()
+            ),
+        ]
+    );
+}
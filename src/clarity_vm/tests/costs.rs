@@ -49,7 +49,7 @@ use std::collections::HashMap;
 
 use crate::clarity_vm::database::marf::MarfedKV;
 use clarity::vm::database::MemoryBackingStore;
-use clarity::vm::tests::test_only_mainnet_to_chain_id;
+use stacks_common::types::NetworkId;
 
 lazy_static! {
     static ref COST_VOTING_MAINNET_CONTRACT: QualifiedContractIdentifier =
@@ -187,8 +187,8 @@ where
     F: Fn(OwnedEnvironment) -> R,
 {
     let marf_kv = MarfedKV::temporary();
-    let chain_id = test_only_mainnet_to_chain_id(use_mainnet);
-    let mut clarity_instance = ClarityInstance::new(use_mainnet, chain_id, marf_kv);
+    let network = NetworkId::from_mainnet(use_mainnet);
+    let mut clarity_instance = ClarityInstance::new(network, marf_kv);
 
     let first_block = StacksBlockId::new(&FIRST_BURNCHAIN_CONSENSUS_HASH, &FIRST_STACKS_BLOCK_HASH);
     clarity_instance
@@ -1022,8 +1022,8 @@ fn epoch_21_test_all_testnet() {
 
 fn test_cost_contract_short_circuits(use_mainnet: bool, clarity_version: ClarityVersion) {
     let marf_kv = MarfedKV::temporary();
-    let chain_id = test_only_mainnet_to_chain_id(use_mainnet);
-    let mut clarity_instance = ClarityInstance::new(use_mainnet, chain_id, marf_kv);
+    let network = NetworkId::from_mainnet(use_mainnet);
+    let mut clarity_instance = ClarityInstance::new(network, marf_kv);
     let burn_db = if clarity_version == ClarityVersion::Clarity2 {
         &TEST_BURN_STATE_DB_21
     } else {
@@ -1059,7 +1059,7 @@ fn test_cost_contract_short_circuits(use_mainnet: bool, clarity_version: Clarity
     let caller = QualifiedContractIdentifier::new(p1_principal.clone(), "caller".into());
 
     let mut marf_kv = {
-        let mut clarity_inst = ClarityInstance::new(use_mainnet, chain_id, marf_kv);
+        let mut clarity_inst = ClarityInstance::new(network, marf_kv);
         let mut block_conn = clarity_inst.begin_block(
             &StacksBlockId::new(&FIRST_BURNCHAIN_CONSENSUS_HASH, &FIRST_STACKS_BLOCK_HASH),
             &StacksBlockId([1 as u8; 32]),
@@ -1270,8 +1270,8 @@ fn test_cost_contract_short_circuits_testnet() {
 
 fn test_cost_voting_integration(use_mainnet: bool, clarity_version: ClarityVersion) {
     let marf_kv = MarfedKV::temporary();
-    let chain_id = test_only_mainnet_to_chain_id(use_mainnet);
-    let mut clarity_instance = ClarityInstance::new(use_mainnet, chain_id, marf_kv);
+    let network = NetworkId::from_mainnet(use_mainnet);
+    let mut clarity_instance = ClarityInstance::new(network, marf_kv);
     let burn_db = if clarity_version == ClarityVersion::Clarity2 {
         &TEST_BURN_STATE_DB_21
     } else {
@@ -1311,7 +1311,7 @@ fn test_cost_voting_integration(use_mainnet: bool, clarity_version: ClarityVersi
     let caller = QualifiedContractIdentifier::new(p1_principal.clone(), "caller".into());
 
     let mut marf_kv = {
-        let mut clarity_inst = ClarityInstance::new(use_mainnet, chain_id, marf_kv);
+        let mut clarity_inst = ClarityInstance::new(network, marf_kv);
         let mut block_conn = clarity_inst.begin_block(
             &StacksBlockId::new(&FIRST_BURNCHAIN_CONSENSUS_HASH, &FIRST_STACKS_BLOCK_HASH),
             &StacksBlockId([1 as u8; 32]),
@@ -1696,3 +1696,78 @@ fn test_cost_voting_integration_testnet() {
     test_cost_voting_integration(false, ClarityVersion::Clarity1);
     test_cost_voting_integration(false, ClarityVersion::Clarity2);
 }
+
+#[test]
+fn test_cost_profile_attribution() {
+    let callee = "(define-data-var counter int 0)
+                  (define-public (bump)
+                    (begin
+                      (var-set counter (+ (var-get counter) 1))
+                      (ok (var-get counter))))";
+    let caller = "(define-public (go)
+                    (begin
+                      (try! (contract-call? .callee bump))
+                      (contract-call? .callee bump)))";
+
+    let p1 = execute("'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR");
+    let p1_principal = match p1 {
+        Value::Principal(PrincipalData::Standard(ref data)) => data.clone(),
+        _ => panic!(),
+    };
+    let callee_id = QualifiedContractIdentifier::new(p1_principal.clone(), "callee".into());
+    let caller_id = QualifiedContractIdentifier::new(p1_principal.clone(), "caller".into());
+
+    with_owned_env(StacksEpochId::Epoch21, false, |mut owned_env| {
+        owned_env
+            .initialize_contract(callee_id.clone(), callee, None, ASTRules::PrecheckSize)
+            .unwrap();
+        owned_env
+            .initialize_contract(caller_id.clone(), caller, None, ASTRules::PrecheckSize)
+            .unwrap();
+
+        let cost_before = owned_env.get_cost_total();
+        owned_env.enable_cost_profile();
+        let (result, _, _) = execute_transaction(
+            &mut owned_env,
+            p1_principal.clone().into(),
+            &caller_id,
+            "go",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(result, Value::okay(Value::Int(2)).unwrap());
+        let profile = owned_env.take_cost_profile().unwrap();
+        let mut tx_cost = owned_env.get_cost_total();
+        tx_cost.sub(&cost_before).unwrap();
+
+        let go_id = format!("{}:go", &caller_id);
+        let bump_id = format!("{}:bump", &callee_id);
+        let go = profile.get(&go_id).unwrap();
+        let bump = profile.get(&bump_id).unwrap();
+        assert_eq!(go.calls, 1);
+        assert_eq!(bump.calls, 2);
+
+        // the callee's data var writes are its own, but count towards the caller's total
+        assert_eq!(go.self_cost.write_count, 0);
+        assert_eq!(bump.self_cost.write_count, 2);
+        assert_eq!(bump.self_cost, bump.total_cost);
+        let mut go_total = go.self_cost.clone();
+        go_total.add(&bump.total_cost).unwrap();
+        assert_eq!(go.total_cost, go_total);
+
+        // everything the transaction cost is accounted for
+        let mut profiled = profile.top_level.clone();
+        profiled.add(&go.total_cost).unwrap();
+        assert_eq!(profiled, tx_cost);
+
+        let ranked: Vec<_> = profile
+            .ranked(&tx_cost)
+            .into_iter()
+            .map(|(function, _)| function.to_string())
+            .collect();
+        assert_eq!(ranked, vec![go_id, bump_id]);
+
+        // profiling is off once the profile is taken
+        assert!(owned_env.take_cost_profile().is_none());
+    });
+}
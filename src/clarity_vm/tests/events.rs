@@ -33,9 +33,9 @@ use stacks_common::types::StacksEpochId;
 use crate::vm::ast::ASTRules;
 use crate::vm::database::MemoryBackingStore;
 
-use clarity::vm::tests::test_only_mainnet_to_chain_id;
 use clarity::vm::ClarityVersion;
 use clarity::vm::ContractContext;
+use stacks_common::types::NetworkId;
 
 fn helper_execute(contract: &str, method: &str) -> (Value, Vec<StacksTransactionEvent>) {
     helper_execute_epoch(contract, method, None, StacksEpochId::Epoch21, false)
@@ -53,8 +53,8 @@ fn helper_execute_epoch(
     let sender = execute(address).expect_principal();
 
     let marf_kv = MarfedKV::temporary();
-    let chain_id = test_only_mainnet_to_chain_id(use_mainnet);
-    let mut clarity_instance = ClarityInstance::new(use_mainnet, chain_id, marf_kv);
+    let network = NetworkId::from_mainnet(use_mainnet);
+    let mut clarity_instance = ClarityInstance::new(network, marf_kv);
     let mut genesis = clarity_instance.begin_test_genesis_block(
         &StacksBlockId::sentinel(),
         &StacksBlockHeader::make_index_block_hash(
@@ -54,6 +54,7 @@ use stacks_common::types::StacksEpochId;
 use crate::chainstate::stacks::boot::{BOOT_CODE_COSTS, BOOT_CODE_COSTS_2, BOOT_CODE_COSTS_3};
 use crate::util_lib::boot::boot_code_id;
 use clarity::vm::tests::test_clarity_versions;
+use stacks_common::types::NetworkId;
 
 fn test_block_headers(n: u8) -> StacksBlockId {
     StacksBlockId([n as u8; 32])
@@ -101,7 +102,7 @@ fn test_simple_token_system(#[case] version: ClarityVersion, #[case] epoch: Stac
     if epoch < StacksEpochId::Epoch2_05 {
         return;
     }
-    let mut clarity = ClarityInstance::new(false, CHAIN_ID_TESTNET, MarfedKV::temporary());
+    let mut clarity = ClarityInstance::new(NetworkId::testnet(), MarfedKV::temporary());
     let p1 = PrincipalData::from(
         PrincipalData::parse_standard_principal("SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR")
             .unwrap(),
@@ -667,7 +668,7 @@ pub fn rollback_log_memory_test(
     #[case] epoch_id: StacksEpochId,
 ) {
     let marf = MarfedKV::temporary();
-    let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+    let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
     let EXPLODE_N = 100;
     let burn_db = &generate_test_burn_state_db(epoch_id);
 
@@ -736,7 +737,7 @@ pub fn rollback_log_memory_test(
 #[apply(test_clarity_versions)]
 pub fn let_memory_test(#[case] clarity_version: ClarityVersion, #[case] epoch_id: StacksEpochId) {
     let marf = MarfedKV::temporary();
-    let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+    let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
     let EXPLODE_N = 100;
     let burn_db = &generate_test_burn_state_db(epoch_id);
 
@@ -814,7 +815,7 @@ pub fn argument_memory_test(
     #[case] epoch_id: StacksEpochId,
 ) {
     let marf = MarfedKV::temporary();
-    let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+    let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
     let EXPLODE_N = 100;
 
     let contract_identifier = QualifiedContractIdentifier::local("foo").unwrap();
@@ -889,7 +890,7 @@ pub fn argument_memory_test(
 #[apply(test_clarity_versions)]
 pub fn fcall_memory_test(#[case] clarity_version: ClarityVersion, #[case] epoch_id: StacksEpochId) {
     let marf = MarfedKV::temporary();
-    let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+    let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
     let COUNT_PER_FUNC = 10;
     let FUNCS = 10;
     let burn_db = &generate_test_burn_state_db(epoch_id);
@@ -1009,7 +1010,7 @@ pub fn fcall_memory_test(#[case] clarity_version: ClarityVersion, #[case] epoch_
 #[apply(test_clarity_versions)]
 pub fn ccall_memory_test(#[case] clarity_version: ClarityVersion, #[case] epoch_id: StacksEpochId) {
     let marf = MarfedKV::temporary();
-    let mut clarity_instance = ClarityInstance::new(false, CHAIN_ID_TESTNET, marf);
+    let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), marf);
     let COUNT_PER_CONTRACT = 20;
     let CONTRACTS = 5;
     let burn_db = &generate_test_burn_state_db(epoch_id);
@@ -1119,7 +1120,7 @@ pub fn ccall_memory_test(#[case] clarity_version: ClarityVersion, #[case] epoch_
 
 #[test]
 fn test_deep_tuples() {
-    let mut clarity = ClarityInstance::new(false, CHAIN_ID_TESTNET, MarfedKV::temporary());
+    let mut clarity = ClarityInstance::new(NetworkId::testnet(), MarfedKV::temporary());
     let p1 = PrincipalData::from(
         PrincipalData::parse_standard_principal("SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR")
             .unwrap(),
@@ -1201,7 +1202,7 @@ fn test_deep_tuples() {
 
 #[test]
 fn test_deep_tuples_ast_precheck() {
-    let mut clarity = ClarityInstance::new(false, CHAIN_ID_TESTNET, MarfedKV::temporary());
+    let mut clarity = ClarityInstance::new(NetworkId::testnet(), MarfedKV::temporary());
     let p1 = PrincipalData::from(
         PrincipalData::parse_standard_principal("SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR")
             .unwrap(),
@@ -1270,7 +1271,7 @@ fn test_deep_tuples_ast_precheck() {
 
 #[test]
 fn test_deep_type_nesting() {
-    let mut clarity = ClarityInstance::new(false, CHAIN_ID_TESTNET, MarfedKV::temporary());
+    let mut clarity = ClarityInstance::new(NetworkId::testnet(), MarfedKV::temporary());
     let p1 = PrincipalData::from(
         PrincipalData::parse_standard_principal("SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR")
             .unwrap(),
@@ -22,4 +22,6 @@ pub mod epoch_switch;
 pub mod events;
 pub mod forking;
 pub mod large_contract;
+pub mod reanalysis;
+pub mod remote_store;
 pub mod simple_tests;
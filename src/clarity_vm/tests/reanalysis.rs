@@ -0,0 +1,214 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::env;
+use std::fs;
+
+use clarity::vm::ast::ASTRules;
+use clarity::vm::clarity::TransactionConnection;
+use clarity::vm::test_util::TEST_HEADER_DB;
+use clarity::vm::tests::UnitTestBurnStateDB;
+use clarity::vm::types::QualifiedContractIdentifier;
+use clarity::vm::ClarityVersion;
+
+use crate::chainstate::stacks::index::ClarityMarfTrieId;
+use crate::clarity_vm::clarity::ClarityInstance;
+use crate::clarity_vm::database::marf::MarfedKV;
+use crate::clarity_vm::reanalysis::{
+    run_contract_reanalysis_step, ContractReanalysisDB, ReanalysisChange,
+};
+use crate::types::chainstate::StacksBlockId;
+use crate::types::StacksEpochId;
+use stacks_common::types::NetworkId;
+
+#[test]
+fn test_contract_reanalysis_after_epoch_transition() {
+    let mut clarity_instance = ClarityInstance::new(NetworkId::testnet(), MarfedKV::temporary());
+    let burn_state_db_2_05 = UnitTestBurnStateDB {
+        epoch_id: StacksEpochId::Epoch2_05,
+        ast_rules: ASTRules::PrecheckSize,
+    };
+    let burn_state_db = UnitTestBurnStateDB {
+        epoch_id: StacksEpochId::Epoch21,
+        ast_rules: ASTRules::PrecheckSize,
+    };
+
+    let unchanged_id = QualifiedContractIdentifier::local("unchanged").unwrap();
+    let unchanged_src = "(define-map m uint int) (define-read-only (get-x) (map-get? m u1))";
+
+    // simulate a contract whose stored analysis no longer matches what its source analyzes to:
+    // its analysis says `get-x` returns a uint, but its source returns an int.
+    let changed_id = QualifiedContractIdentifier::local("changed").unwrap();
+    let changed_src = "(define-read-only (get-x) 1)";
+    let stored_src = "(define-read-only (get-x) u1)";
+
+    clarity_instance
+        .begin_test_genesis_block(
+            &StacksBlockId::sentinel(),
+            &StacksBlockId([0 as u8; 32]),
+            &TEST_HEADER_DB,
+            &burn_state_db_2_05,
+        )
+        .commit_block();
+
+    // deploy both contracts in 2.05
+    {
+        let mut conn = clarity_instance.begin_block(
+            &StacksBlockId([0 as u8; 32]),
+            &StacksBlockId([1 as u8; 32]),
+            &TEST_HEADER_DB,
+            &burn_state_db_2_05,
+        );
+        conn.initialize_epoch_2_05().unwrap();
+
+        conn.as_transaction(|conn| {
+            let (ast, analysis) = conn
+                .analyze_smart_contract(
+                    &unchanged_id,
+                    ClarityVersion::Clarity1,
+                    unchanged_src,
+                    ASTRules::PrecheckSize,
+                )
+                .unwrap();
+            conn.initialize_smart_contract(
+                &unchanged_id,
+                ClarityVersion::Clarity1,
+                &ast,
+                unchanged_src,
+                None,
+                |_, _| false,
+            )
+            .unwrap();
+            conn.save_analysis(&unchanged_id, &analysis).unwrap();
+
+            let (ast, _) = conn
+                .analyze_smart_contract(
+                    &changed_id,
+                    ClarityVersion::Clarity1,
+                    changed_src,
+                    ASTRules::PrecheckSize,
+                )
+                .unwrap();
+            let (_, stored_analysis) = conn
+                .analyze_smart_contract(
+                    &changed_id,
+                    ClarityVersion::Clarity1,
+                    stored_src,
+                    ASTRules::PrecheckSize,
+                )
+                .unwrap();
+            conn.initialize_smart_contract(
+                &changed_id,
+                ClarityVersion::Clarity1,
+                &ast,
+                changed_src,
+                None,
+                |_, _| false,
+            )
+            .unwrap();
+            conn.save_analysis(&changed_id, &stored_analysis).unwrap();
+        });
+
+        conn.commit_block();
+    }
+
+    // cross into 2.1
+    {
+        let mut conn = clarity_instance.begin_block(
+            &StacksBlockId([1 as u8; 32]),
+            &StacksBlockId([2 as u8; 32]),
+            &TEST_HEADER_DB,
+            &burn_state_db,
+        );
+        conn.initialize_epoch_2_1().unwrap();
+        conn.commit_block();
+    }
+
+    let tip = StacksBlockId([2 as u8; 32]);
+    let mut db_path = env::temp_dir();
+    db_path.push("test_contract_reanalysis_after_epoch_transition.sqlite");
+    if fs::metadata(&db_path).is_ok() {
+        fs::remove_file(&db_path).unwrap();
+    }
+
+    // one contract per step, so every step is checkpointed
+    let mut reanalysis_db = ContractReanalysisDB::open(&db_path).unwrap();
+    let job = run_contract_reanalysis_step(
+        &mut reanalysis_db,
+        &mut clarity_instance,
+        &TEST_HEADER_DB,
+        &burn_state_db,
+        &tip,
+        1,
+    )
+    .unwrap();
+    assert_eq!(job.epoch, StacksEpochId::Epoch21);
+    assert_eq!(job.tip, tip);
+    assert!(job.last_contract.is_some());
+    assert!(!job.finished);
+    assert_eq!(
+        reanalysis_db.get_job(StacksEpochId::Epoch21).unwrap(),
+        Some(job)
+    );
+
+    // resume from the checkpoint after "restarting"
+    drop(reanalysis_db);
+    let mut reanalysis_db = ContractReanalysisDB::open(&db_path).unwrap();
+    let mut num_steps = 1;
+    let job = loop {
+        let job = run_contract_reanalysis_step(
+            &mut reanalysis_db,
+            &mut clarity_instance,
+            &TEST_HEADER_DB,
+            &burn_state_db,
+            &tip,
+            1,
+        )
+        .unwrap();
+        num_steps += 1;
+        if job.finished {
+            break job;
+        }
+        assert!(num_steps < 100, "re-analysis did not finish");
+    };
+
+    // boot contracts deployed in 2.1 are skipped; both test contracts are re-analyzed
+    assert!(job.num_processed >= 2);
+    assert_eq!(job.num_flagged, 1);
+
+    let flagged = reanalysis_db
+        .get_flagged_contracts(StacksEpochId::Epoch21)
+        .unwrap();
+    assert_eq!(flagged.len(), 1);
+    assert_eq!(flagged[0].contract_id, changed_id);
+    assert_eq!(
+        flagged[0].change,
+        ReanalysisChange::FieldsChanged(vec!["read_only_function_types".to_string()])
+    );
+
+    // a finished job is not run again
+    let rerun = run_contract_reanalysis_step(
+        &mut reanalysis_db,
+        &mut clarity_instance,
+        &TEST_HEADER_DB,
+        &burn_state_db,
+        &tip,
+        1,
+    )
+    .unwrap();
+    assert_eq!(rerun, job);
+    assert_eq!(reanalysis_db.get_jobs().unwrap(), vec![job]);
+}
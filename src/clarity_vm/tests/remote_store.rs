@@ -0,0 +1,133 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use clarity::vm::ast::ASTRules;
+use clarity::vm::contexts::OwnedEnvironment;
+use clarity::vm::database::{ClarityBackend, ClarityBackingStore};
+use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier, Value};
+use stacks_common::types::chainstate::StacksBlockId;
+use stacks_common::types::StacksEpochId;
+
+use crate::clarity_vm::database::remote::{ClarityStateSource, RemoteBackingStore};
+use crate::clarity_vm::database::MemoryBackingStore;
+use crate::net::Error as net_error;
+
+const COUNTER: &str = "
+(define-data-var n int 1)
+(define-public (incr) (ok (var-set n (+ (var-get n) 1))))
+(define-read-only (get-n) (var-get n))
+";
+
+/// Serves the committed state of a `MemoryBackingStore`, as a node's RPC interface would
+impl<'a> ClarityStateSource for &'a mut MemoryBackingStore {
+    fn get(&mut self, key: &str) -> Result<Option<String>, net_error> {
+        Ok(ClarityBackingStore::get(*self, key))
+    }
+
+    fn get_with_proof(&mut self, key: &str) -> Result<Option<(String, Vec<u8>)>, net_error> {
+        Ok(ClarityBackingStore::get_with_proof(*self, key))
+    }
+
+    fn get_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> Result<Option<String>, net_error> {
+        Ok(ClarityBackingStore::get_metadata(*self, contract, key).unwrap_or(None))
+    }
+}
+
+#[test]
+fn test_remote_store_reads_through_and_writes_locally() {
+    let epoch = StacksEpochId::Epoch21;
+    let contract_id = QualifiedContractIdentifier::local("counter").unwrap();
+    let sender: PrincipalData = contract_id.issuer.clone().into();
+
+    let mut node_state = MemoryBackingStore::new();
+    {
+        let mut owned_env = OwnedEnvironment::new(node_state.as_clarity_db(), epoch);
+        owned_env
+            .initialize_contract(contract_id.clone(), COUNTER, None, ASTRules::PrecheckSize)
+            .unwrap();
+    }
+
+    {
+        let mut store = RemoteBackingStore::new(&mut node_state, StacksBlockId([1; 32]), 10);
+        assert_eq!(store.get_current_block_height(), 11);
+        assert_eq!(store.get_block_at_height(10), Some(StacksBlockId([1; 32])));
+        assert_eq!(store.get_block_at_height(9), None);
+
+        let mut owned_env = OwnedEnvironment::new(store.as_clarity_db(), epoch);
+        owned_env
+            .execute_transaction(sender, None, contract_id.clone(), "incr", &[])
+            .unwrap();
+        let (value, ..) = owned_env.eval_read_only(&contract_id, "(get-n)").unwrap();
+        assert_eq!(value, Value::Int(2));
+
+        let missing = QualifiedContractIdentifier::local("missing").unwrap();
+        assert!(owned_env.eval_read_only(&missing, "(get-n)").is_err());
+    }
+
+    // the increment never reached the node's state
+    let mut owned_env = OwnedEnvironment::new(node_state.as_clarity_db(), epoch);
+    let (value, ..) = owned_env.eval_read_only(&contract_id, "(get-n)").unwrap();
+    assert_eq!(value, Value::Int(1));
+}
+
+/// A source whose node has gone away
+struct UnreachableSource;
+
+impl ClarityStateSource for UnreachableSource {
+    fn get(&mut self, _key: &str) -> Result<Option<String>, net_error> {
+        Err(net_error::ConnectionBroken)
+    }
+
+    fn get_with_proof(&mut self, _key: &str) -> Result<Option<(String, Vec<u8>)>, net_error> {
+        Err(net_error::ConnectionBroken)
+    }
+
+    fn get_metadata(
+        &mut self,
+        _contract: &QualifiedContractIdentifier,
+        _key: &str,
+    ) -> Result<Option<String>, net_error> {
+        Err(net_error::ConnectionBroken)
+    }
+}
+
+#[test]
+fn test_remote_store_read_errors() {
+    let epoch = StacksEpochId::Epoch21;
+    let contract_id = QualifiedContractIdentifier::local("counter").unwrap();
+
+    let mut store = RemoteBackingStore::new(UnreachableSource, StacksBlockId([1; 32]), 10);
+
+    // reads that can fail return an error instead of crashing
+    assert!(ClarityBackingStore::get_metadata(&mut store, &contract_id, "contract-size").is_err());
+    {
+        let mut owned_env = OwnedEnvironment::new(store.as_clarity_db(), epoch);
+        assert!(owned_env.eval_read_only(&contract_id, "(get-n)").is_err());
+    }
+
+    // reads that can't fail remember the error
+    assert_eq!(ClarityBackingStore::get(&mut store, "some-key"), None);
+    assert!(store.set_block_hash(StacksBlockId([1; 32])).is_err());
+    match store.take_read_error() {
+        Some(net_error::ConnectionBroken) => {}
+        e => panic!("Unexpected read error {:?}", e),
+    }
+    assert!(store.take_read_error().is_none());
+    assert!(store.set_block_hash(StacksBlockId([1; 32])).is_ok());
+}
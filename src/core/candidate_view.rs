@@ -0,0 +1,564 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A materialized view of the mempool's minable candidates.
+//!
+//! Without it, assembling a block walks the whole `mempool` table in fee-rate order, checking
+//! each transaction's nonces along the way.  On a large mempool, most of those transactions can't
+//! be mined yet (their nonces are too high) or ever again (their nonces were already used), so
+//! the miner spends most of its walk skipping them, and does it again on every pass.
+//!
+//! This view keeps each origin account's transactions in nonce order, and orders each account's
+//! next minable transaction -- its "head" -- by fee rate.  A walk only visits heads, and only
+//! moves on to an account's next transaction once the one before it has been mined.  The view is
+//! updated as transactions are added, replaced, re-estimated, and removed, when the `MemPoolTx`
+//! that made the change commits.  It also remembers the account nonces the miner has looked up,
+//! so transactions with used nonces are skipped on later walks; these are forgotten when the
+//! miner moves to a new chain tip (see `MemPoolDB::reset_nonce_cache()`).
+//!
+//! The view is shared by every `MemPoolDB` opened on the same database in this process, since the
+//! relayer and the miner each open their own.  It is loaded from the database when the first one
+//! is opened, and dropped along with the last one.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::mem;
+use std::ops::Bound::{Excluded, Included, Unbounded};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+use rusqlite::NO_PARAMS;
+
+use crate::burnchains::Txid;
+use crate::core::mempool::MemPoolTxInfoPartial;
+use crate::types::chainstate::StacksAddress;
+use crate::util_lib::db::{query_rows, DBConn, Error as db_error};
+use crate::util_lib::membudget::{BudgetAccount, MemoryBudget, MEMPOOL_CANDIDATE_VIEW};
+
+lazy_static! {
+    static ref CANDIDATE_VIEWS: Mutex<HashMap<String, Weak<Mutex<CandidateView>>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Where a transaction sorts among the candidates.  Keys sort in the order the miner considers
+/// transactions: those with a fee rate estimate first, highest fee rate first, and then those
+/// without one.  Ties are broken by txid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CandidateKey {
+    Estimated(Reverse<u64>, Txid),
+    Unestimated(Txid),
+}
+
+impl CandidateKey {
+    pub fn new(tx: &MemPoolTxInfoPartial) -> CandidateKey {
+        match tx.fee_rate {
+            // non-negative floats sort the same way as their bit patterns do
+            Some(fee_rate) if fee_rate > 0.0 => {
+                CandidateKey::Estimated(Reverse(fee_rate.to_bits()), tx.txid)
+            }
+            Some(_) => CandidateKey::Estimated(Reverse(0), tx.txid),
+            None => CandidateKey::Unestimated(tx.txid),
+        }
+    }
+
+    /// The lowest key with (or without) a fee rate estimate
+    fn first(estimated: bool) -> CandidateKey {
+        if estimated {
+            CandidateKey::Estimated(Reverse(u64::MAX), Txid([0u8; 32]))
+        } else {
+            CandidateKey::Unestimated(Txid([0u8; 32]))
+        }
+    }
+
+    pub fn txid(&self) -> &Txid {
+        match self {
+            CandidateKey::Estimated(_, txid) | CandidateKey::Unestimated(txid) => txid,
+        }
+    }
+
+    pub fn is_estimated(&self) -> bool {
+        matches!(self, CandidateKey::Estimated(..))
+    }
+}
+
+/// A change to the `mempool` table, to be applied to the view once it's committed
+#[derive(Debug, Clone)]
+pub enum CandidateChange {
+    /// A transaction was stored, replacing any with the same txid, origin nonce, or sponsor nonce
+    Insert(MemPoolTxInfoPartial),
+    /// A transaction's fee rate estimate changed
+    SetFeeRate(Txid, Option<f64>),
+    /// A transaction was removed
+    Remove(Txid),
+}
+
+pub struct CandidateView {
+    /// Every transaction in the mempool
+    txs: HashMap<Txid, MemPoolTxInfoPartial>,
+    /// Each origin account's transactions, by nonce
+    by_origin: HashMap<StacksAddress, BTreeMap<u64, Txid>>,
+    /// Transactions by sponsor account and nonce
+    by_sponsor: HashMap<(StacksAddress, u64), Txid>,
+    /// Account nonces as of the miner's chain tip, for the accounts it has looked up so far
+    origin_nonces: HashMap<StacksAddress, u64>,
+    /// Each origin account's lowest-nonce transaction that has not been mined as of
+    /// `origin_nonces`, in the order the miner should consider them
+    heads: BTreeSet<CandidateKey>,
+    /// The key each account's head is filed under
+    head_keys: HashMap<StacksAddress, CandidateKey>,
+    /// Share of the node's memory budget used by this view.  The view must hold every transaction
+    /// in the mempool, so it reports its size but never evicts.
+    budget: BudgetAccount,
+}
+
+impl CandidateView {
+    /// Estimated number of bytes a transaction takes up across the view's indexes
+    const ENTRY_SIZE: u64 = (mem::size_of::<MemPoolTxInfoPartial>()
+        + mem::size_of::<(u64, Txid)>()
+        + mem::size_of::<((StacksAddress, u64), Txid)>()
+        + mem::size_of::<(StacksAddress, CandidateKey)>()
+        + mem::size_of::<CandidateKey>()) as u64;
+
+    fn new() -> CandidateView {
+        CandidateView {
+            txs: HashMap::new(),
+            by_origin: HashMap::new(),
+            by_sponsor: HashMap::new(),
+            origin_nonces: HashMap::new(),
+            heads: BTreeSet::new(),
+            head_keys: HashMap::new(),
+            budget: MemoryBudget::register_global(MEMPOOL_CANDIDATE_VIEW),
+        }
+    }
+
+    /// Build the view of a mempool database from its `mempool` table
+    pub fn load(conn: &DBConn) -> Result<CandidateView, db_error> {
+        let sql = "SELECT txid, origin_nonce, origin_address, sponsor_nonce, sponsor_address, fee_rate FROM mempool";
+        let txs: Vec<MemPoolTxInfoPartial> = query_rows(conn, sql, NO_PARAMS)?;
+
+        let mut view = CandidateView::new();
+        for tx in txs.into_iter() {
+            view.insert(tx);
+        }
+        view.budget
+            .set_usage(view.txs.len() as u64 * Self::ENTRY_SIZE);
+        debug!("Loaded mempool candidate view";
+               "txs" => view.num_txs(),
+               "accounts" => view.by_origin.len());
+        Ok(view)
+    }
+
+    /// Get the view shared by every `MemPoolDB` opened on `db_path`, loading it from `conn` if
+    /// there isn't one.  If `reload` is set, the view is loaded again even if there is one (e.g.
+    /// because the database was just created).
+    pub fn for_mempool(
+        db_path: &str,
+        conn: &DBConn,
+        reload: bool,
+    ) -> Result<Arc<Mutex<CandidateView>>, db_error> {
+        let mut views = CANDIDATE_VIEWS
+            .lock()
+            .expect("FATAL: mempool candidate view registry lock poisoned");
+        views.retain(|_, view| view.strong_count() > 0);
+
+        if !reload {
+            if let Some(view) = views.get(db_path).and_then(|view| view.upgrade()) {
+                return Ok(view);
+            }
+        }
+
+        let view = Arc::new(Mutex::new(CandidateView::load(conn)?));
+        views.insert(db_path.to_string(), Arc::downgrade(&view));
+        Ok(view)
+    }
+
+    /// Apply changes that were just committed to the `mempool` table
+    pub fn apply(&mut self, changes: Vec<CandidateChange>) {
+        for change in changes.into_iter() {
+            match change {
+                CandidateChange::Insert(tx) => self.insert(tx),
+                CandidateChange::SetFeeRate(txid, fee_rate) => self.set_fee_rate(&txid, fee_rate),
+                CandidateChange::Remove(txid) => self.remove(&txid),
+            }
+        }
+        self.budget
+            .set_usage(self.txs.len() as u64 * Self::ENTRY_SIZE);
+    }
+
+    /// Add a transaction.  Like the `mempool` table, this replaces any transaction with the same
+    /// txid, origin nonce, or sponsor nonce.
+    fn insert(&mut self, tx: MemPoolTxInfoPartial) {
+        self.remove(&tx.txid);
+        if let Some(txid) = self
+            .by_origin
+            .get(&tx.origin_address)
+            .and_then(|txs| txs.get(&tx.origin_nonce))
+            .copied()
+        {
+            self.remove(&txid);
+        }
+        if let Some(txid) = self
+            .by_sponsor
+            .get(&(tx.sponsor_address, tx.sponsor_nonce))
+            .copied()
+        {
+            self.remove(&txid);
+        }
+
+        let origin = tx.origin_address;
+        self.by_origin
+            .entry(origin)
+            .or_insert_with(BTreeMap::new)
+            .insert(tx.origin_nonce, tx.txid);
+        self.by_sponsor
+            .insert((tx.sponsor_address, tx.sponsor_nonce), tx.txid);
+        self.txs.insert(tx.txid, tx);
+        self.refresh_head(&origin);
+    }
+
+    fn remove(&mut self, txid: &Txid) {
+        let tx = match self.txs.remove(txid) {
+            Some(tx) => tx,
+            None => return,
+        };
+        if let Some(txs) = self.by_origin.get_mut(&tx.origin_address) {
+            if txs.get(&tx.origin_nonce) == Some(txid) {
+                txs.remove(&tx.origin_nonce);
+            }
+            if txs.is_empty() {
+                self.by_origin.remove(&tx.origin_address);
+            }
+        }
+        let sponsor_key = (tx.sponsor_address, tx.sponsor_nonce);
+        if self.by_sponsor.get(&sponsor_key) == Some(txid) {
+            self.by_sponsor.remove(&sponsor_key);
+        }
+        self.refresh_head(&tx.origin_address);
+    }
+
+    fn set_fee_rate(&mut self, txid: &Txid, fee_rate: Option<f64>) {
+        let origin = match self.txs.get_mut(txid) {
+            Some(tx) => {
+                tx.fee_rate = fee_rate;
+                tx.origin_address
+            }
+            None => return,
+        };
+        self.refresh_head(&origin);
+    }
+
+    /// Re-file an account's head, after its transactions or its nonce changed
+    fn refresh_head(&mut self, origin: &StacksAddress) {
+        if let Some(key) = self.head_keys.remove(origin) {
+            self.heads.remove(&key);
+        }
+        let min_nonce = self.origin_nonces.get(origin).copied().unwrap_or(0);
+        let head_key = self
+            .by_origin
+            .get(origin)
+            .and_then(|txs| txs.range(min_nonce..).next())
+            .and_then(|(_, txid)| self.txs.get(txid))
+            .map(CandidateKey::new);
+        if let Some(key) = head_key {
+            self.heads.insert(key);
+            self.head_keys.insert(*origin, key);
+        }
+    }
+
+    /// Record an account's nonce as of the miner's chain tip.  Its transactions with lower nonces
+    /// are no longer candidates.
+    pub fn set_origin_nonce(&mut self, origin: &StacksAddress, nonce: u64) {
+        if !self.by_origin.contains_key(origin) {
+            return;
+        }
+        if self.origin_nonces.insert(*origin, nonce) != Some(nonce) {
+            self.refresh_head(origin);
+        }
+    }
+
+    /// Forget all account nonces, because the miner's chain tip changed
+    pub fn reset_nonces(&mut self) {
+        let origins: Vec<StacksAddress> = self
+            .origin_nonces
+            .drain()
+            .map(|(origin, _)| origin)
+            .collect();
+        for origin in origins.iter() {
+            self.refresh_head(origin);
+        }
+    }
+
+    /// The first head after `after` (or the first head, if `after` is None) among those with (or
+    /// without) a fee rate estimate
+    fn next_head(
+        &self,
+        after: Option<&CandidateKey>,
+        estimated: bool,
+    ) -> Option<(CandidateKey, MemPoolTxInfoPartial)> {
+        let start = match after {
+            Some(key) => Excluded(*key),
+            None => Included(CandidateKey::first(estimated)),
+        };
+        let key = self
+            .heads
+            .range((start, Unbounded))
+            .next()
+            .filter(|key| key.is_estimated() == estimated)?;
+        self.txs.get(key.txid()).map(|tx| (*key, tx.clone()))
+    }
+
+    /// Get an account's transaction with the given nonce
+    pub fn get_by_origin(
+        &self,
+        origin: &StacksAddress,
+        nonce: u64,
+    ) -> Option<&MemPoolTxInfoPartial> {
+        self.by_origin
+            .get(origin)
+            .and_then(|txs| txs.get(&nonce))
+            .and_then(|txid| self.txs.get(txid))
+    }
+
+    pub fn get(&self, txid: &Txid) -> Option<&MemPoolTxInfoPartial> {
+        self.txs.get(txid)
+    }
+
+    /// Number of transactions in the view
+    pub fn num_txs(&self) -> usize {
+        self.txs.len()
+    }
+
+    /// Number of accounts with a candidate to mine
+    pub fn num_heads(&self) -> usize {
+        self.heads.len()
+    }
+}
+
+/// One walk over the candidates, in the order the miner should consider them.  Besides the
+/// view's heads, it yields the transactions that become minable as the walk goes on and earlier
+/// ones are mined.  The view is only locked briefly at each step, so the mempool can still be
+/// written to while the miner executes transactions.
+pub struct CandidateWalk {
+    view: Arc<Mutex<CandidateView>>,
+    /// The last heads taken from the view, with and without a fee rate estimate
+    last_estimated: Option<CandidateKey>,
+    last_unestimated: Option<CandidateKey>,
+    /// Transactions that became minable during the walk
+    pending: BTreeMap<CandidateKey, MemPoolTxInfoPartial>,
+    /// Transactions already yielded
+    visited: HashSet<Txid>,
+}
+
+impl CandidateWalk {
+    pub fn new(view: Arc<Mutex<CandidateView>>) -> CandidateWalk {
+        CandidateWalk {
+            view,
+            last_estimated: None,
+            last_unestimated: None,
+            pending: BTreeMap::new(),
+            visited: HashSet::new(),
+        }
+    }
+
+    fn lock_view(&self) -> MutexGuard<CandidateView> {
+        self.view
+            .lock()
+            .expect("FATAL: mempool candidate view lock poisoned")
+    }
+
+    /// Take the next candidate among those with (or without) a fee rate estimate
+    pub fn next(&mut self, estimated: bool) -> Option<MemPoolTxInfoPartial> {
+        loop {
+            let last = if estimated {
+                self.last_estimated
+            } else {
+                self.last_unestimated
+            };
+            let from_view = self.lock_view().next_head(last.as_ref(), estimated);
+            let from_pending = self
+                .pending
+                .range(CandidateKey::first(estimated)..)
+                .next()
+                .map(|(key, _)| *key)
+                .filter(|key| key.is_estimated() == estimated);
+
+            let tx = match (from_view, from_pending) {
+                (None, None) => return None,
+                (Some((view_key, tx)), pending_key)
+                    if pending_key.map(|key| view_key < key).unwrap_or(true) =>
+                {
+                    if estimated {
+                        self.last_estimated = Some(view_key);
+                    } else {
+                        self.last_unestimated = Some(view_key);
+                    }
+                    tx
+                }
+                (_, Some(pending_key)) => self
+                    .pending
+                    .remove(&pending_key)
+                    .expect("BUG: pending candidate disappeared"),
+                (Some(_), None) => unreachable!("BUG: view candidate not taken"),
+            };
+
+            if self.visited.insert(tx.txid) {
+                return Some(tx);
+            }
+        }
+    }
+
+    /// Consider the account's transaction with the given nonce, if there is one, because the
+    /// account's nonce reached it during the walk
+    pub fn push_origin_nonce(&mut self, origin: &StacksAddress, nonce: u64) {
+        let tx = self.lock_view().get_by_origin(origin, nonce).cloned();
+        if let Some(tx) = tx {
+            if !self.visited.contains(&tx.txid) {
+                self.pending.insert(CandidateKey::new(&tx), tx);
+            }
+        }
+    }
+
+    /// Record an account's nonce as of the miner's chain tip
+    pub fn set_origin_nonce(&self, origin: &StacksAddress, nonce: u64) {
+        self.lock_view().set_origin_nonce(origin, nonce);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use stacks_common::util::hash::Hash160;
+
+    fn addr(n: u8) -> StacksAddress {
+        StacksAddress {
+            version: 26,
+            bytes: Hash160([n; 20]),
+        }
+    }
+
+    fn candidate(
+        id: u8,
+        origin: u8,
+        origin_nonce: u64,
+        fee_rate: Option<f64>,
+    ) -> MemPoolTxInfoPartial {
+        MemPoolTxInfoPartial {
+            txid: Txid([id; 32]),
+            fee_rate,
+            origin_address: addr(origin),
+            origin_nonce,
+            sponsor_address: addr(origin),
+            sponsor_nonce: origin_nonce,
+        }
+    }
+
+    fn walk_all(view: &Arc<Mutex<CandidateView>>) -> Vec<Txid> {
+        let mut walk = CandidateWalk::new(view.clone());
+        let mut txids = vec![];
+        while let Some(tx) = walk.next(true).or_else(|| walk.next(false)) {
+            txids.push(tx.txid);
+        }
+        txids
+    }
+
+    #[test]
+    fn test_candidate_view_heads() {
+        let view = Arc::new(Mutex::new(CandidateView::new()));
+        view.lock().unwrap().apply(vec![
+            CandidateChange::Insert(candidate(1, 1, 0, Some(1.0))),
+            CandidateChange::Insert(candidate(2, 1, 1, Some(100.0))),
+            CandidateChange::Insert(candidate(3, 2, 0, Some(10.0))),
+            CandidateChange::Insert(candidate(4, 3, 0, None)),
+        ]);
+        assert_eq!(view.lock().unwrap().num_txs(), 4);
+        assert_eq!(view.lock().unwrap().num_heads(), 3);
+
+        // only each account's lowest nonce, highest fee rate first, and unestimated last
+        assert_eq!(
+            walk_all(&view),
+            vec![Txid([3; 32]), Txid([1; 32]), Txid([4; 32])]
+        );
+
+        // the account's next transaction becomes a candidate once its nonce is reached
+        let mut walk = CandidateWalk::new(view.clone());
+        assert_eq!(walk.next(true).unwrap().txid, Txid([3; 32]));
+        assert_eq!(walk.next(true).unwrap().txid, Txid([1; 32]));
+        walk.push_origin_nonce(&addr(1), 1);
+        assert_eq!(walk.next(true).unwrap().txid, Txid([2; 32]));
+        assert!(walk.next(true).is_none());
+        assert_eq!(walk.next(false).unwrap().txid, Txid([4; 32]));
+        assert!(walk.next(false).is_none());
+
+        // used nonces are skipped until the tip changes
+        view.lock().unwrap().set_origin_nonce(&addr(1), 1);
+        assert_eq!(
+            walk_all(&view),
+            vec![Txid([2; 32]), Txid([3; 32]), Txid([4; 32])]
+        );
+        view.lock().unwrap().set_origin_nonce(&addr(2), 1);
+        assert_eq!(walk_all(&view), vec![Txid([2; 32]), Txid([4; 32])]);
+        view.lock().unwrap().reset_nonces();
+        assert_eq!(
+            walk_all(&view),
+            vec![Txid([3; 32]), Txid([1; 32]), Txid([4; 32])]
+        );
+
+        // re-estimating a head re-orders it
+        view.lock()
+            .unwrap()
+            .apply(vec![CandidateChange::SetFeeRate(Txid([4; 32]), Some(50.0))]);
+        assert_eq!(
+            walk_all(&view),
+            vec![Txid([4; 32]), Txid([3; 32]), Txid([1; 32])]
+        );
+    }
+
+    #[test]
+    fn test_candidate_view_replace_and_remove() {
+        let view = Arc::new(Mutex::new(CandidateView::new()));
+        view.lock().unwrap().apply(vec![
+            CandidateChange::Insert(candidate(1, 1, 0, Some(1.0))),
+            CandidateChange::Insert(candidate(2, 1, 1, Some(1.0))),
+        ]);
+
+        // replace-by-fee at the same origin nonce
+        view.lock()
+            .unwrap()
+            .apply(vec![CandidateChange::Insert(candidate(3, 1, 0, Some(2.0)))]);
+        assert_eq!(view.lock().unwrap().num_txs(), 2);
+        assert!(view.lock().unwrap().get(&Txid([1; 32])).is_none());
+        assert_eq!(walk_all(&view), vec![Txid([3; 32])]);
+
+        // a sponsored transaction replaces the one with the same sponsor nonce
+        let mut sponsored = candidate(4, 2, 0, Some(1.0));
+        sponsored.sponsor_address = addr(1);
+        sponsored.sponsor_nonce = 1;
+        view.lock()
+            .unwrap()
+            .apply(vec![CandidateChange::Insert(sponsored)]);
+        assert!(view.lock().unwrap().get(&Txid([2; 32])).is_none());
+        assert!(view.lock().unwrap().get_by_origin(&addr(1), 1).is_none());
+        assert_eq!(walk_all(&view), vec![Txid([3; 32]), Txid([4; 32])]);
+
+        // removing a head promotes the account's next transaction
+        view.lock().unwrap().apply(vec![
+            CandidateChange::Remove(Txid([4; 32])),
+            CandidateChange::Insert(candidate(5, 1, 1, Some(1.0))),
+            CandidateChange::Remove(Txid([3; 32])),
+        ]);
+        assert_eq!(view.lock().unwrap().num_txs(), 1);
+        assert_eq!(view.lock().unwrap().num_heads(), 1);
+        assert_eq!(walk_all(&view), vec![Txid([5; 32])]);
+    }
+}
@@ -19,9 +19,11 @@ use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
 use std::hash::Hasher;
 use std::io::{Read, Write};
+use std::mem;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use rand::distributions::Uniform;
 use rand::prelude::Distribution;
@@ -44,13 +46,20 @@ use crate::chainstate::stacks::{
     db::blocks::MemPoolRejection, db::ClarityTx, db::StacksChainState, db::TxStreamData,
     index::Error as MarfError, Error as ChainstateError, StacksTransaction,
 };
-use crate::chainstate::stacks::{StacksMicroblock, TransactionPayload};
+use crate::chainstate::stacks::{
+    StacksMicroblock, StacksTransactionBundle, TransactionAnchorMode, TransactionPayload,
+};
+use crate::core::candidate_view::{CandidateChange, CandidateView, CandidateWalk};
+use crate::core::mempool_mirror::{
+    MemPoolMirror, MemPoolMirrorEntry, MemPoolSnapshot, MirrorChange,
+};
 use crate::core::ExecutionCost;
 use crate::core::StacksEpochId;
 use crate::core::FIRST_BURNCHAIN_CONSENSUS_HASH;
 use crate::core::FIRST_STACKS_BLOCK_HASH;
 use crate::monitoring::increment_stx_mempool_gc;
 use crate::util_lib::db::query_int;
+use crate::util_lib::db::query_row;
 use crate::util_lib::db::query_row_columns;
 use crate::util_lib::db::query_rows;
 use crate::util_lib::db::sqlite_open;
@@ -59,8 +68,10 @@ use crate::util_lib::db::tx_busy_handler;
 use crate::util_lib::db::u64_to_sql;
 use crate::util_lib::db::Error as db_error;
 use crate::util_lib::db::FromColumn;
-use crate::util_lib::db::{query_row, Error};
 use crate::util_lib::db::{sql_pragma, DBConn, DBTx, FromRow};
+use crate::util_lib::membudget::{
+    BudgetAccount, MemoryBudget, MEMPOOL_CANDIDATE_CACHE, MEMPOOL_NONCE_CACHE,
+};
 use clarity::vm::types::PrincipalData;
 use stacks_common::util::get_epoch_time_ms;
 use stacks_common::util::get_epoch_time_secs;
@@ -292,6 +303,100 @@ impl MemPoolTxMetadata {
     }
 }
 
+/// Where a node is willing to include a class of transactions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnchorModePolicy {
+    /// Only include these transactions in anchored blocks
+    AnchoredOnly,
+    /// Only include these transactions in microblocks
+    MicroblocksOnly,
+    /// Include these transactions wherever their anchor mode allows
+    Any,
+}
+
+impl AnchorModePolicy {
+    pub fn from_str(s: &str) -> Option<AnchorModePolicy> {
+        match s {
+            "anchored_only" => Some(AnchorModePolicy::AnchoredOnly),
+            "microblocks_only" => Some(AnchorModePolicy::MicroblocksOnly),
+            "any" => Some(AnchorModePolicy::Any),
+            _ => None,
+        }
+    }
+
+    /// Does this policy allow mining a transaction into an anchored block (`anchored == true`)
+    /// or into a microblock (`anchored == false`)?
+    pub fn allows(&self, anchored: bool) -> bool {
+        match self {
+            AnchorModePolicy::AnchoredOnly => anchored,
+            AnchorModePolicy::MicroblocksOnly => !anchored,
+            AnchorModePolicy::Any => true,
+        }
+    }
+
+    /// Is there anywhere this policy allows a transaction with the given anchor mode to be
+    /// mined?
+    pub fn admits(&self, anchor_mode: &TransactionAnchorMode) -> bool {
+        match anchor_mode {
+            TransactionAnchorMode::OnChainOnly => self.allows(true),
+            TransactionAnchorMode::OffChainOnly => self.allows(false),
+            TransactionAnchorMode::Any => true,
+        }
+    }
+}
+
+/// Anchor mode policies for each class of transaction that can be submitted to the mempool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionAnchorPolicy {
+    pub token_transfer: AnchorModePolicy,
+    pub contract_call: AnchorModePolicy,
+    pub smart_contract: AnchorModePolicy,
+}
+
+impl TransactionAnchorPolicy {
+    /// Apply the same policy to every class of transaction
+    pub fn all(policy: AnchorModePolicy) -> TransactionAnchorPolicy {
+        TransactionAnchorPolicy {
+            token_transfer: policy,
+            contract_call: policy,
+            smart_contract: policy,
+        }
+    }
+
+    pub fn any() -> TransactionAnchorPolicy {
+        TransactionAnchorPolicy::all(AnchorModePolicy::Any)
+    }
+
+    /// The policy for the class of the given transaction.  Transactions that never come from
+    /// the mempool (coinbases and poison microblocks) are not restricted.
+    pub fn policy_for(&self, payload: &TransactionPayload) -> AnchorModePolicy {
+        match payload {
+            TransactionPayload::TokenTransfer(..) => self.token_transfer,
+            TransactionPayload::ContractCall(..) => self.contract_call,
+            TransactionPayload::SmartContract(..) => self.smart_contract,
+            _ => AnchorModePolicy::Any,
+        }
+    }
+
+    /// Can this transaction be mined into an anchored block (`anchored == true`) or a
+    /// microblock (`anchored == false`)?
+    pub fn can_mine(&self, tx: &StacksTransaction, anchored: bool) -> bool {
+        self.policy_for(&tx.payload).allows(anchored)
+    }
+
+    /// Will this transaction be admitted to the mempool?  Transactions are only admitted if
+    /// both their anchor mode and this policy allow them to be mined somewhere.
+    pub fn will_admit(&self, tx: &StacksTransaction) -> bool {
+        self.policy_for(&tx.payload).admits(&tx.anchor_mode)
+    }
+}
+
+impl Default for TransactionAnchorPolicy {
+    fn default() -> TransactionAnchorPolicy {
+        TransactionAnchorPolicy::any()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MemPoolWalkSettings {
     /// Minimum transaction fee that will be considered
@@ -308,6 +413,8 @@ pub struct MemPoolWalkSettings {
     /// Size of the candidate cache. These are the candidates that will be retried after each
     /// transaction is mined.
     pub candidate_retry_cache_size: u64,
+    /// Which transactions may be mined into anchored blocks and microblocks
+    pub anchor_policy: TransactionAnchorPolicy,
 }
 
 impl MemPoolWalkSettings {
@@ -318,6 +425,7 @@ impl MemPoolWalkSettings {
             consider_no_estimate_tx_prob: 5,
             nonce_cache_size: 1024 * 1024,
             candidate_retry_cache_size: 64 * 1024,
+            anchor_policy: TransactionAnchorPolicy::any(),
         }
     }
     pub fn zero() -> MemPoolWalkSettings {
@@ -327,6 +435,7 @@ impl MemPoolWalkSettings {
             consider_no_estimate_tx_prob: 5,
             nonce_cache_size: 1024 * 1024,
             candidate_retry_cache_size: 64 * 1024,
+            anchor_policy: TransactionAnchorPolicy::any(),
         }
     }
 }
@@ -389,6 +498,14 @@ impl FromRow<MemPoolTxInfo> for MemPoolTxInfo {
     }
 }
 
+impl FromRow<StacksTransactionBundle> for StacksTransactionBundle {
+    fn from_row<'a>(row: &'a Row) -> Result<StacksTransactionBundle, db_error> {
+        let bundle_bytes: Vec<u8> = row.get_unwrap("bundle");
+        StacksTransactionBundle::consensus_deserialize(&mut &bundle_bytes[..])
+            .map_err(|_e| db_error::ParseError)
+    }
+}
+
 impl FromRow<MemPoolTxInfoPartial> for MemPoolTxInfoPartial {
     fn from_row<'a>(row: &'a Row) -> Result<MemPoolTxInfoPartial, db_error> {
         let txid = Txid::from_column(row, "txid")?;
@@ -556,6 +673,23 @@ const MEMPOOL_SCHEMA_6_NONCES: &'static [&'static str] = &[
     "#,
 ];
 
+const MEMPOOL_SCHEMA_7_TX_BUNDLES: &'static [&'static str] = &[
+    r#"
+    -- Experimental all-or-nothing transaction bundles.  These are kept apart from the mempool's
+    -- transactions, so they can never be mined one at a time.
+    CREATE TABLE tx_bundles(
+        bundle_id TEXT PRIMARY KEY NOT NULL,
+        tx_fee INTEGER NOT NULL,
+        height INTEGER NOT NULL,
+        accept_time INTEGER NOT NULL,
+        bundle BLOB NOT NULL
+    );
+    "#,
+    r#"
+    INSERT INTO schema_version (version) VALUES (7)
+    "#,
+];
+
 const MEMPOOL_INDEXES: &'static [&'static str] = &[
     "CREATE INDEX IF NOT EXISTS by_txid ON mempool(txid);",
     "CREATE INDEX IF NOT EXISTS by_height ON mempool(height);",
@@ -568,6 +702,8 @@ const MEMPOOL_INDEXES: &'static [&'static str] = &[
     "CREATE INDEX IF NOT EXISTS by_ordered_hashed_txid ON randomized_txids(hashed_txid ASC);",
     "CREATE INDEX IF NOT EXISTS by_hashed_txid ON randomized_txids(txid,hashed_txid);",
     "CREATE INDEX IF NOT EXISTS by_arrival_time_desc ON tx_blacklist(arrival_time DESC);",
+    "CREATE INDEX IF NOT EXISTS bundles_by_fee ON tx_bundles(tx_fee DESC);",
+    "CREATE INDEX IF NOT EXISTS bundles_by_height ON tx_bundles(height);",
 ];
 
 pub struct MemPoolDB {
@@ -580,12 +716,25 @@ pub struct MemPoolDB {
     metric: Box<dyn CostMetric>,
     pub blacklist_timeout: u64,
     pub blacklist_max_size: u64,
+    /// Which transactions will be admitted, based on their anchor modes
+    pub anchor_policy: TransactionAnchorPolicy,
+    /// The minable candidates, shared with every other `MemPoolDB` open on this database
+    candidates: Arc<Mutex<CandidateView>>,
+    /// The read-only mirror for RPC queries, shared with every other `MemPoolDB` open on this
+    /// database
+    mirror: Arc<MemPoolMirror>,
 }
 
 pub struct MemPoolTx<'a> {
     tx: DBTx<'a>,
     admitter: &'a mut MemPoolAdmitter,
     bloom_counter: Option<&'a mut BloomCounter<BloomNodeHasher>>,
+    candidates: Arc<Mutex<CandidateView>>,
+    /// Changes to apply to the candidate view once this transaction commits
+    candidate_changes: Vec<CandidateChange>,
+    mirror: Arc<MemPoolMirror>,
+    /// Changes to publish to the mirror once this transaction commits
+    mirror_changes: Vec<MirrorChange>,
 }
 
 impl<'a> Deref for MemPoolTx<'a> {
@@ -606,11 +755,17 @@ impl<'a> MemPoolTx<'a> {
         tx: DBTx<'a>,
         admitter: &'a mut MemPoolAdmitter,
         bloom_counter: &'a mut BloomCounter<BloomNodeHasher>,
+        candidates: Arc<Mutex<CandidateView>>,
+        mirror: Arc<MemPoolMirror>,
     ) -> MemPoolTx<'a> {
         MemPoolTx {
             tx,
             admitter,
             bloom_counter: Some(bloom_counter),
+            candidates,
+            candidate_changes: vec![],
+            mirror,
+            mirror_changes: vec![],
         }
     }
 
@@ -628,7 +783,36 @@ impl<'a> MemPoolTx<'a> {
     }
 
     pub fn commit(self) -> Result<(), db_error> {
-        self.tx.commit().map_err(db_error::SqliteError)
+        let MemPoolTx {
+            tx,
+            candidates,
+            candidate_changes,
+            mirror,
+            mirror_changes,
+            ..
+        } = self;
+        // Hold the view's lock across the commit, so that concurrent writers apply their changes
+        // to it (and publish them to the mirror) in the same order as they commit them.
+        let mut candidates = candidates
+            .lock()
+            .expect("FATAL: mempool candidate view lock poisoned");
+        tx.commit().map_err(db_error::SqliteError)?;
+        candidates.apply(candidate_changes);
+        mirror.publish(mirror_changes);
+        Ok(())
+    }
+
+    /// Set a transaction's fee rate estimate
+    pub fn update_fee_rate(&mut self, txid: &Txid, fee_rate: Option<f64>) -> Result<(), db_error> {
+        self.tx.execute(
+            "UPDATE mempool SET fee_rate = ? WHERE txid = ?",
+            rusqlite::params![fee_rate, txid],
+        )?;
+        self.candidate_changes
+            .push(CandidateChange::SetFeeRate(*txid, fee_rate));
+        self.mirror_changes
+            .push(MirrorChange::SetFeeRate(*txid, fee_rate));
+        Ok(())
     }
 
     /// Remove all txids at the given height from the bloom counter.
@@ -641,6 +825,9 @@ impl<'a> MemPoolTx<'a> {
 
         test_debug!("Prune bloom counter from height {}", target_height);
 
+        self.mirror_changes
+            .extend(txids.iter().map(|txid| MirrorChange::Evict(*txid)));
+
         // keep borrow-checker happy
         MemPoolTx::with_bloom_state(self, |ref mut dbtx, ref mut bloom_counter| {
             for txid in txids.into_iter() {
@@ -727,8 +914,8 @@ impl<'a> MemPoolTx<'a> {
         })
     }
 
-    /// Add the txid to our randomized page order
-    fn update_mempool_pager(&mut self, txid: &Txid) -> Result<(), MemPoolRejection> {
+    /// Add the txid to our randomized page order, and return its randomized txid
+    fn update_mempool_pager(&mut self, txid: &Txid) -> Result<Txid, MemPoolRejection> {
         let mut randomized_buff = self
             .bloom_counter
             .as_ref()
@@ -743,7 +930,7 @@ impl<'a> MemPoolTx<'a> {
 
         self.execute(sql, args).map_err(db_error::SqliteError)?;
 
-        Ok(())
+        Ok(hashed_txid)
     }
 }
 
@@ -792,9 +979,15 @@ struct NonceCache {
     cache: HashMap<StacksAddress, u64>,
     /// The maximum size that this cache can be.
     max_cache_size: usize,
+    /// Share of the node's memory budget used by this cache.  Every cached nonce is also in the
+    /// `nonces` table, so entries can be evicted freely.
+    budget: BudgetAccount,
 }
 
 impl NonceCache {
+    /// Estimated number of bytes a cached nonce takes up
+    const ENTRY_SIZE: u64 = (mem::size_of::<StacksAddress>() + mem::size_of::<u64>()) as u64;
+
     fn new(nonce_cache_size: u64) -> Self {
         let max_size: usize = nonce_cache_size
             .try_into()
@@ -802,6 +995,27 @@ impl NonceCache {
         Self {
             cache: HashMap::new(),
             max_cache_size: max_size,
+            budget: MemoryBudget::register_global(MEMPOOL_NONCE_CACHE),
+        }
+    }
+
+    /// Cache a nonce in RAM, if there is space in both the cache and the memory budget
+    fn insert(&mut self, address: StacksAddress, nonce: u64) {
+        if let Some(target) = self.budget.eviction_target() {
+            let num_evicted = self
+                .cache
+                .len()
+                .saturating_sub((target / Self::ENTRY_SIZE) as usize);
+            let evicted: Vec<_> = self.cache.keys().take(num_evicted).cloned().collect();
+            for address in evicted.iter() {
+                self.cache.remove(address);
+            }
+            self.budget
+                .set_usage(self.cache.len() as u64 * Self::ENTRY_SIZE);
+        }
+        if self.cache.len() < self.max_cache_size && self.budget.has_room(Self::ENTRY_SIZE) {
+            self.cache.insert(address, nonce);
+            self.budget.charge(Self::ENTRY_SIZE);
         }
     }
 
@@ -841,9 +1055,7 @@ impl NonceCache {
                 match opt_nonce {
                     Some(nonce) => {
                         // Copy this into the in-memory cache if there is space
-                        if self.cache.len() < self.max_cache_size {
-                            self.cache.insert(address.clone(), nonce);
-                        }
+                        self.insert(address.clone(), nonce);
                         (nonce, false)
                     }
                     None => {
@@ -858,9 +1070,7 @@ impl NonceCache {
                             }
                         };
 
-                        if self.cache.len() < self.max_cache_size {
-                            self.cache.insert(address.clone(), nonce);
-                        }
+                        self.insert(address.clone(), nonce);
                         (nonce, should_store_again)
                     }
                 }
@@ -935,9 +1145,15 @@ struct CandidateCache {
     next: VecDeque<MemPoolTxInfoPartial>,
     /// The maximum size that this cache can be.
     max_cache_size: usize,
+    /// Share of the node's memory budget used by this cache.  Candidates are not cached while the
+    /// budget is exhausted; they will be found again on a later walk of the mempool.
+    budget: BudgetAccount,
 }
 
 impl CandidateCache {
+    /// Estimated number of bytes a cached candidate takes up
+    const ENTRY_SIZE: u64 = mem::size_of::<MemPoolTxInfoPartial>() as u64;
+
     fn new(candidate_retry_cache_size: u64) -> Self {
         let max_size: usize = candidate_retry_cache_size
             .try_into()
@@ -946,18 +1162,24 @@ impl CandidateCache {
             cache: VecDeque::new(),
             next: VecDeque::new(),
             max_cache_size: max_size,
+            budget: MemoryBudget::register_global(MEMPOOL_CANDIDATE_CACHE),
         }
     }
 
     /// Retrieve the next candidate transaction from the cache.
     fn next(&mut self) -> Option<MemPoolTxInfoPartial> {
-        self.cache.pop_front()
+        let next = self.cache.pop_front();
+        if next.is_some() {
+            self.budget.release(Self::ENTRY_SIZE);
+        }
+        next
     }
 
     /// Push a candidate to the cache for the next iteration.
     fn push(&mut self, tx: MemPoolTxInfoPartial) {
-        if self.next.len() < self.max_cache_size {
+        if self.next.len() < self.max_cache_size && self.budget.has_room(Self::ENTRY_SIZE) {
             self.next.push_back(tx);
+            self.budget.charge(Self::ENTRY_SIZE);
         }
 
         #[cfg(test)]
@@ -1076,6 +1298,9 @@ impl MemPoolDB {
                     MemPoolDB::instantiate_nonces(tx)?;
                 }
                 6 => {
+                    MemPoolDB::instantiate_tx_bundles(tx)?;
+                }
+                7 => {
                     break;
                 }
                 _ => {
@@ -1147,6 +1372,15 @@ impl MemPoolDB {
         Ok(())
     }
 
+    /// Add the transaction bundle table
+    fn instantiate_tx_bundles(tx: &DBTx) -> Result<(), db_error> {
+        for sql_exec in MEMPOOL_SCHEMA_7_TX_BUNDLES {
+            tx.execute_batch(sql_exec)?;
+        }
+
+        Ok(())
+    }
+
     pub fn db_path(chainstate_root_path: &str) -> Result<String, db_error> {
         let mut path = PathBuf::from(chainstate_root_path);
 
@@ -1198,6 +1432,9 @@ impl MemPoolDB {
         let bloom_counter = BloomCounter::<BloomNodeHasher>::try_load(&conn, BLOOM_COUNTER_TABLE)?
             .ok_or(db_error::Other(format!("Failed to load bloom counter")))?;
 
+        let candidates = CandidateView::for_mempool(db_path, &conn, create_flag)?;
+        let mirror = MemPoolMirror::for_mempool(db_path, &conn, create_flag)?;
+
         Ok(MemPoolDB {
             db: conn,
             path: db_path.to_owned(),
@@ -1208,6 +1445,9 @@ impl MemPoolDB {
             metric,
             blacklist_timeout: DEFAULT_BLACKLIST_TIMEOUT,
             blacklist_max_size: DEFAULT_BLACKLIST_MAX_SIZE,
+            anchor_policy: TransactionAnchorPolicy::any(),
+            candidates,
+            mirror,
         })
     }
 
@@ -1243,6 +1483,10 @@ impl MemPoolDB {
         debug!("reset nonce cache");
         let sql = "DELETE FROM nonces";
         self.db.execute(sql, rusqlite::NO_PARAMS)?;
+        self.candidates
+            .lock()
+            .expect("FATAL: mempool candidate view lock poisoned")
+            .reset_nonces();
         Ok(())
     }
 
@@ -1285,6 +1529,8 @@ impl MemPoolDB {
             &[max_updates],
         )?;
         let mut updated = 0;
+        let mut candidate_changes = vec![];
+        let mut mirror_changes = vec![];
         for tx_to_estimate in txs {
             let txid = tx_to_estimate.tx.txid();
             let estimator_result = cost_estimates::estimate_fee_rate(
@@ -1309,10 +1555,18 @@ impl MemPoolDB {
                 "UPDATE mempool SET fee_rate = ? WHERE txid = ?",
                 rusqlite::params![fee_rate_f64, &txid],
             )?;
+            candidate_changes.push(CandidateChange::SetFeeRate(txid, fee_rate_f64));
+            mirror_changes.push(MirrorChange::SetFeeRate(txid, fee_rate_f64));
             updated += 1;
         }
 
+        let mut candidates = self
+            .candidates
+            .lock()
+            .expect("FATAL: mempool candidate view lock poisoned");
         sql_tx.commit()?;
+        candidates.apply(candidate_changes);
+        self.mirror.publish(mirror_changes);
 
         Ok(updated)
     }
@@ -1340,11 +1594,12 @@ impl MemPoolDB {
     /// `todo` will be called once for each transaction that is a valid
     /// candidate for inclusion in the next block, meaning its origin and
     /// sponsor nonces are equal to the nonces of the corresponding accounts.
-    /// Best effort will be made to process the transactions in fee-rate order.
-    /// That is, transactions will be processed in fee-rate order until the
-    /// candidate cache is full, at which point, transactions with a lower
-    /// fee-rate may be considered before those with a higher fee-rate.
-    /// When the candidate cache fills, a subsequent call to
+    /// Transactions are taken from the mempool's candidate view, which only
+    /// offers each origin account's next minable transaction, in fee-rate
+    /// order.  An account's following transaction is considered once the one
+    /// before it is mined.  Transactions whose sponsor nonce is too high are
+    /// kept in the candidate cache, and retried after each transaction is
+    /// considered.  When the candidate cache fills, a subsequent call to
     /// `iterate_candidates` will be needed to reconsider transactions which
     /// were skipped on the first pass, but become valid after some lower
     /// fee-rate transactions are considered.
@@ -1387,38 +1642,12 @@ impl MemPoolDB {
         let mut rng = rand::thread_rng();
         let mut candidate_cache = CandidateCache::new(settings.candidate_retry_cache_size);
         let mut nonce_cache = NonceCache::new(settings.nonce_cache_size);
+        let mut walk = CandidateWalk::new(self.candidates.clone());
 
         // set of (address, nonce) to store after the inner loop completes.  This will be done in a
         // single transaction.  This cannot grow to more than `settings.nonce_cache_size` entries.
         let mut retry_store = HashMap::new();
 
-        let sql = "
-             SELECT txid, origin_nonce, origin_address, sponsor_nonce, sponsor_address, fee_rate
-             FROM mempool
-             WHERE fee_rate IS NULL
-             ";
-        let mut query_stmt_null = self
-            .db
-            .prepare(&sql)
-            .map_err(|err| Error::SqliteError(err))?;
-        let mut null_iterator = query_stmt_null
-            .query(NO_PARAMS)
-            .map_err(|err| Error::SqliteError(err))?;
-
-        let sql = "
-            SELECT txid, origin_nonce, origin_address, sponsor_nonce, sponsor_address, fee_rate
-            FROM mempool
-            WHERE fee_rate IS NOT NULL
-            ORDER BY fee_rate DESC
-            ";
-        let mut query_stmt_fee = self
-            .db
-            .prepare(&sql)
-            .map_err(|err| Error::SqliteError(err))?;
-        let mut fee_iterator = query_stmt_fee
-            .query(NO_PARAMS)
-            .map_err(|err| Error::SqliteError(err))?;
-
         loop {
             if start_time.elapsed().as_millis() > settings.max_walk_time_ms as u128 {
                 debug!("Mempool iteration deadline exceeded";
@@ -1426,50 +1655,29 @@ impl MemPoolDB {
                 break;
             }
 
-            let start_with_no_estimate =
-                tx_consideration_sampler.sample(&mut rng) < settings.consider_no_estimate_tx_prob;
-
             // First, try to read from the retry list
-            let (candidate, update_estimate) = match candidate_cache.next() {
-                Some(tx) => {
-                    let update_estimate = tx.fee_rate.is_none();
-                    (tx, update_estimate)
-                }
+            let candidate = match candidate_cache.next() {
+                Some(tx) => tx,
                 None => {
-                    // When the retry list is empty, read from the mempool db,
+                    // When the retry list is empty, take the next candidate from the view,
                     // randomly selecting from either the null fee-rate transactions
-                    // or those with fee-rate estimates.
-                    let opt_tx = if start_with_no_estimate {
-                        null_iterator
-                            .next()
-                            .map_err(|err| Error::SqliteError(err))?
-                    } else {
-                        fee_iterator.next().map_err(|err| Error::SqliteError(err))?
-                    };
-                    match opt_tx {
-                        Some(row) => (MemPoolTxInfoPartial::from_row(row)?, start_with_no_estimate),
+                    // or those with fee-rate estimates.  If the selected kind is exhausted,
+                    // take the other.
+                    let start_with_no_estimate = tx_consideration_sampler.sample(&mut rng)
+                        < settings.consider_no_estimate_tx_prob;
+                    match walk
+                        .next(!start_with_no_estimate)
+                        .or_else(|| walk.next(start_with_no_estimate))
+                    {
+                        Some(tx) => tx,
                         None => {
-                            // If the selected iterator is empty, check the other
-                            match if start_with_no_estimate {
-                                fee_iterator.next().map_err(|err| Error::SqliteError(err))?
-                            } else {
-                                null_iterator
-                                    .next()
-                                    .map_err(|err| Error::SqliteError(err))?
-                            } {
-                                Some(row) => (
-                                    MemPoolTxInfoPartial::from_row(row)?,
-                                    !start_with_no_estimate,
-                                ),
-                                None => {
-                                    debug!("No more transactions to consider in mempool");
-                                    break;
-                                }
-                            }
+                            debug!("No more transactions to consider in mempool");
+                            break;
                         }
                     }
                 }
             };
+            let update_estimate = candidate.fee_rate.is_none();
 
             // Check the nonces.
             let (expected_origin_nonce, retry_store_origin_nonce) =
@@ -1496,6 +1704,10 @@ impl MemPoolDB {
                 );
             }
 
+            // Let the view skip these accounts' transactions whose nonces were already used
+            walk.set_origin_nonce(&candidate.origin_address, expected_origin_nonce);
+            walk.set_origin_nonce(&candidate.sponsor_address, expected_sponsor_nonce);
+
             match order_nonces(
                 candidate.origin_nonce,
                 expected_origin_nonce,
@@ -1509,7 +1721,9 @@ impl MemPoolDB {
                         candidate.origin_nonce,
                         candidate.fee_rate.unwrap_or_default()
                     );
-                    // This transaction cannot execute in this pass, just drop it
+                    // This transaction cannot execute in this pass, just drop it.  The origin's
+                    // transaction with the expected nonce might, though.
+                    walk.push_origin_nonce(&candidate.origin_address, expected_origin_nonce);
                     continue;
                 }
                 Ordering::Greater => {
@@ -1574,6 +1788,15 @@ impl MemPoolDB {
                                     expected_origin_nonce + 1,
                                 );
                             }
+                            // The origin's next transaction can now be considered
+                            walk.set_origin_nonce(
+                                &consider.tx.metadata.origin_address,
+                                expected_origin_nonce + 1,
+                            );
+                            walk.push_origin_nonce(
+                                &consider.tx.metadata.origin_address,
+                                expected_origin_nonce + 1,
+                            );
 
                             if consider.tx.tx.auth.is_sponsored() {
                                 let stored = nonce_cache.update(
@@ -1589,6 +1812,15 @@ impl MemPoolDB {
                                         expected_sponsor_nonce + 1,
                                     );
                                 }
+                                // ...and so can the sponsor's, since it shares the same nonce
+                                walk.set_origin_nonce(
+                                    &consider.tx.metadata.sponsor_address,
+                                    expected_sponsor_nonce + 1,
+                                );
+                                walk.push_origin_nonce(
+                                    &consider.tx.metadata.sponsor_address,
+                                    expected_sponsor_nonce + 1,
+                                );
                             }
                             output_events.push(tx_event);
                         }
@@ -1614,14 +1846,6 @@ impl MemPoolDB {
             candidate_cache.reset();
         }
 
-        // drop these rusqlite statements and queries, since their existence as immutable borrows on the
-        // connection prevents us from beginning a transaction below (which requires a mutable
-        // borrow).
-        drop(null_iterator);
-        drop(fee_iterator);
-        drop(query_stmt_null);
-        drop(query_stmt_fee);
-
         if retry_store.len() > 0 {
             let tx = self.tx_begin()?;
             for (address, nonce) in retry_store.into_iter() {
@@ -1642,12 +1866,20 @@ impl MemPoolDB {
         &self.db
     }
 
+    /// Get a read-only snapshot of the mempool, for RPC queries.  It includes every change
+    /// committed so far, by any `MemPoolDB` open on this database, and never changes afterwards.
+    pub fn snapshot(&self) -> Arc<MemPoolSnapshot> {
+        self.mirror.snapshot()
+    }
+
     pub fn tx_begin<'a>(&'a mut self) -> Result<MemPoolTx<'a>, db_error> {
         let tx = tx_begin_immediate(&mut self.db)?;
         Ok(MemPoolTx::new(
             tx,
             &mut self.admitter,
             &mut self.bloom_counter,
+            self.candidates.clone(),
+            self.mirror.clone(),
         ))
     }
 
@@ -1668,6 +1900,17 @@ impl MemPoolDB {
         )
     }
 
+    /// Get a transaction's serialized bytes
+    pub fn get_tx_bytes(conn: &DBConn, txid: &Txid) -> Result<Option<Vec<u8>>, db_error> {
+        conn.query_row(
+            "SELECT tx FROM mempool WHERE txid = ?1",
+            &[txid as &dyn ToSql],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(db_error::SqliteError)
+    }
+
     /// Get all transactions across all tips
     #[cfg(test)]
     pub fn get_all_txs(conn: &DBConn) -> Result<Vec<MemPoolTxInfo>, db_error> {
@@ -1874,7 +2117,13 @@ impl MemPoolDB {
             return Err(MemPoolRejection::ConflictingNonceInMempool);
         }
 
-        tx.update_bloom_counter(height, &txid, prior_tx.as_ref().map(|tx| tx.txid.clone()))?;
+        let evict_txid =
+            tx.update_bloom_counter(height, &txid, prior_tx.as_ref().map(|tx| tx.txid.clone()))?;
+        if let Some(evict_txid) = evict_txid {
+            tx.mirror_changes.push(MirrorChange::Evict(evict_txid));
+        }
+
+        let accept_time = get_epoch_time_secs();
 
         let sql = "INSERT OR REPLACE INTO mempool (
             txid,
@@ -1902,14 +2151,40 @@ impl MemPoolDB {
             consensus_hash,
             block_header_hash,
             &u64_to_sql(height)?,
-            &u64_to_sql(get_epoch_time_secs())?,
+            &u64_to_sql(accept_time)?,
             &tx_bytes,
         ];
 
         tx.execute(sql, args)
             .map_err(|e| MemPoolRejection::DBError(db_error::SqliteError(e)))?;
 
-        tx.update_mempool_pager(&txid)?;
+        tx.candidate_changes
+            .push(CandidateChange::Insert(MemPoolTxInfoPartial {
+                txid,
+                fee_rate: None,
+                origin_address: *origin_address,
+                origin_nonce,
+                sponsor_address: *sponsor_address,
+                sponsor_nonce,
+            }));
+
+        let randomized_txid = tx.update_mempool_pager(&txid)?;
+
+        tx.mirror_changes
+            .push(MirrorChange::Insert(MemPoolMirrorEntry {
+                txid,
+                origin_address: *origin_address,
+                origin_nonce,
+                sponsor_address: *sponsor_address,
+                sponsor_nonce,
+                tx_fee,
+                fee_rate: None,
+                len: length,
+                height,
+                accept_time,
+                randomized_txid: Some(randomized_txid),
+                evicted: false,
+            }));
 
         // broadcast drop event if a tx is being replaced
         if let (Some(prior_tx), Some(event_observer)) = (prior_tx, event_observer) {
@@ -1928,15 +2203,23 @@ impl MemPoolDB {
     ) -> Result<(), db_error> {
         let args: &[&dyn ToSql] = &[&u64_to_sql(min_height)?];
 
+        let sql = "SELECT txid FROM mempool WHERE height < ?1";
+        let txids: Vec<Txid> = query_rows(tx, sql, args)?;
+
+        let sql = "DELETE FROM mempool WHERE height < ?1";
+        tx.execute(sql, args)?;
+
+        tx.candidate_changes
+            .extend(txids.iter().map(|txid| CandidateChange::Remove(*txid)));
+        tx.mirror_changes
+            .extend(txids.iter().map(|txid| MirrorChange::Remove(*txid)));
         if let Some(event_observer) = event_observer {
-            let sql = "SELECT txid FROM mempool WHERE height < ?1";
-            let txids = query_rows(tx, sql, args)?;
             event_observer.mempool_txs_dropped(txids, MemPoolDropReason::STALE_COLLECT);
         }
 
-        let sql = "DELETE FROM mempool WHERE height < ?1";
-
+        let sql = "DELETE FROM tx_bundles WHERE height < ?1";
         tx.execute(sql, args)?;
+
         increment_stx_mempool_gc();
         Ok(())
     }
@@ -2057,12 +2340,7 @@ impl MemPoolDB {
             event_observer,
         )?;
 
-        mempool_tx
-            .execute(
-                "UPDATE mempool SET fee_rate = ? WHERE txid = ?",
-                rusqlite::params![fee_rate_estimate, &txid],
-            )
-            .map_err(db_error::from)?;
+        mempool_tx.update_fee_rate(&txid, fee_rate_estimate)?;
 
         if let Err(e) = monitoring::mempool_accepted(&txid, &chainstate.root_path) {
             warn!("Failed to monitor TX receive: {:?}", e; "txid" => %txid);
@@ -2089,6 +2367,10 @@ impl MemPoolDB {
             return Err(MemPoolRejection::TemporarilyBlacklisted);
         }
 
+        if !self.anchor_policy.will_admit(tx) {
+            return Err(MemPoolRejection::DisallowedAnchorMode(tx.anchor_mode));
+        }
+
         let estimator_result = cost_estimates::estimate_fee_rate(
             tx,
             self.cost_estimator.as_ref(),
@@ -2297,10 +2579,12 @@ impl MemPoolDB {
 
     /// Inner code body for dropping transactions.
     /// Note that the bloom filter will *NOT* be updated.  That's the caller's job, if desired.
-    fn inner_drop_txs<'a>(tx: &DBTx<'a>, txids: &[Txid]) -> Result<(), db_error> {
+    fn inner_drop_txs(tx: &mut MemPoolTx, txids: &[Txid]) -> Result<(), db_error> {
         let sql = "DELETE FROM mempool WHERE txid = ?";
         for txid in txids.iter() {
             tx.execute(sql, &[txid])?;
+            tx.candidate_changes.push(CandidateChange::Remove(*txid));
+            tx.mirror_changes.push(MirrorChange::Remove(*txid));
         }
         Ok(())
     }
@@ -2308,8 +2592,8 @@ impl MemPoolDB {
     /// Drop transactions from the mempool.  Does not update the bloom filter, thereby ensuring that
     /// these transactions will still show up as present to the mempool sync logic.
     pub fn drop_txs(&mut self, txids: &[Txid]) -> Result<(), db_error> {
-        let mempool_tx = self.tx_begin()?;
-        MemPoolDB::inner_drop_txs(&mempool_tx, txids)?;
+        let mut mempool_tx = self.tx_begin()?;
+        MemPoolDB::inner_drop_txs(&mut mempool_tx, txids)?;
         mempool_tx.commit()?;
         Ok(())
     }
@@ -2324,8 +2608,8 @@ impl MemPoolDB {
         let blacklist_timeout = self.blacklist_timeout;
         let blacklist_max_size = self.blacklist_max_size;
 
-        let mempool_tx = self.tx_begin()?;
-        MemPoolDB::inner_drop_txs(&mempool_tx, txids)?;
+        let mut mempool_tx = self.tx_begin()?;
+        MemPoolDB::inner_drop_txs(&mut mempool_tx, txids)?;
         MemPoolDB::inner_blacklist_txs(&mempool_tx, txids, now)?;
         MemPoolDB::garbage_collect_tx_blacklist(
             &mempool_tx,
@@ -2423,6 +2707,48 @@ impl MemPoolDB {
         query_int(conn, sql, args).map(|cnt| cnt as u64)
     }
 
+    /// How many transactions are in the mempool, how many bytes do they occupy, and how much do
+    /// they pay in fees in total?
+    pub fn get_totals(conn: &DBConn) -> Result<(u64, u64, u64), db_error> {
+        let sql =
+            "SELECT COUNT(txid), COALESCE(SUM(length), 0), COALESCE(SUM(tx_fee), 0) FROM mempool";
+        conn.query_row(sql, NO_PARAMS, |row| {
+            let count: i64 = row.get(0)?;
+            let length: i64 = row.get(1)?;
+            let fee: i64 = row.get(2)?;
+            Ok((count as u64, length as u64, fee as u64))
+        })
+        .map_err(db_error::SqliteError)
+    }
+
+    /// How many transactions in the mempool have a fee rate in the range
+    /// [min_fee_rate, max_fee_rate)?  If `max_fee_rate` is `None`, the range is unbounded above.
+    pub fn count_txs_in_fee_rate_range(
+        conn: &DBConn,
+        min_fee_rate: f64,
+        max_fee_rate: Option<f64>,
+    ) -> Result<u64, db_error> {
+        let count = match max_fee_rate {
+            Some(max_fee_rate) => {
+                let sql = "SELECT COUNT(txid) FROM mempool WHERE fee_rate >= ?1 AND fee_rate < ?2";
+                let args: &[&dyn ToSql] = &[&min_fee_rate, &max_fee_rate];
+                query_int(conn, sql, args)?
+            }
+            None => {
+                let sql = "SELECT COUNT(txid) FROM mempool WHERE fee_rate >= ?1";
+                let args: &[&dyn ToSql] = &[&min_fee_rate];
+                query_int(conn, sql, args)?
+            }
+        };
+        Ok(count as u64)
+    }
+
+    /// How many transactions in the mempool have not yet had their fee rate estimated?
+    pub fn count_txs_without_fee_rate(conn: &DBConn) -> Result<u64, db_error> {
+        let sql = "SELECT COUNT(txid) FROM mempool WHERE fee_rate IS NULL";
+        query_int(conn, sql, NO_PARAMS).map(|cnt| cnt as u64)
+    }
+
     /// Make a mempool sync request.
     /// If sufficiently sparse, use a MemPoolSyncData::TxTags variant
     /// Otherwise, use a MemPoolSyncData::BloomFilter variant
@@ -2460,21 +2786,6 @@ impl MemPoolDB {
         max_run: u64,
     ) -> Result<(Vec<StacksTransaction>, Option<Txid>, u64), db_error> {
         let mut ret = vec![];
-        let sql = "SELECT mempool.txid AS txid, mempool.tx AS tx, randomized_txids.hashed_txid AS hashed_txid \
-                   FROM mempool JOIN randomized_txids \
-                   ON mempool.txid = randomized_txids.txid \
-                   WHERE randomized_txids.hashed_txid > ?1 \
-                   AND mempool.height > ?2 \
-                   AND NOT EXISTS \
-                        (SELECT 1 FROM removed_txids WHERE txid = mempool.txid) \
-                   ORDER BY randomized_txids.hashed_txid ASC LIMIT ?3";
-
-        let args: &[&dyn ToSql] = &[
-            &last_randomized_txid,
-            &u64_to_sql(height.saturating_sub(BLOOM_COUNTER_DEPTH as u64))?,
-            &u64_to_sql(max_run)?,
-        ];
-
         let mut tags_table = HashSet::new();
         if let MemPoolSyncData::TxTags(_, ref tags) = data {
             for tag in tags.iter() {
@@ -2482,19 +2793,23 @@ impl MemPoolDB {
             }
         }
 
-        let mut stmt = self.conn().prepare(sql)?;
-        let mut rows = stmt.query(args)?;
+        // walk the read-only mirror, so that mempool sync never contends with admission or
+        // mining.  Only the transactions we send back are loaded from the database.
+        let snapshot = self.snapshot();
+        let min_height = height.saturating_sub(BLOOM_COUNTER_DEPTH as u64);
         let mut num_rows_visited = 0;
         let mut next_page = None;
-        while let Some(row) = rows.next()? {
+        for entry in snapshot.iter_pager(last_randomized_txid, min_height) {
             if num_rows_visited >= max_run {
                 break;
             }
 
-            let txid = Txid::from_column(row, "txid")?;
+            let txid = entry.txid;
             num_rows_visited += 1;
 
-            let hashed_txid = Txid::from_column(row, "hashed_txid")?;
+            let hashed_txid = entry
+                .randomized_txid
+                .expect("BUG: paged out a transaction without a randomized txid");
             test_debug!(
                 "Consider txid {} ({}) at or after {}",
                 &txid,
@@ -2514,7 +2829,13 @@ impl MemPoolDB {
                 continue;
             }
 
-            let tx_bytes: Vec<u8> = row.get_unwrap("tx");
+            let tx_bytes = match MemPoolDB::get_tx_bytes(self.conn(), &txid)? {
+                Some(tx_bytes) => tx_bytes,
+                None => {
+                    // dropped since the snapshot was taken
+                    continue;
+                }
+            };
             let tx = StacksTransaction::consensus_deserialize(&mut &tx_bytes[..])
                 .map_err(|_e| db_error::ParseError)?;
 
@@ -2653,4 +2974,135 @@ impl MemPoolDB {
         }
         Ok(num_written)
     }
+
+    /// Is this transaction bundle in the mempool?
+    pub fn has_tx_bundle(&self, bundle_id: &Sha512Trunc256Sum) -> Result<bool, db_error> {
+        let sql = "SELECT 1 FROM tx_bundles WHERE bundle_id = ?1";
+        let args: &[&dyn ToSql] = &[&bundle_id.to_hex()];
+        Ok(query_row::<i64, _>(self.conn(), sql, args)?.is_some())
+    }
+
+    /// Submit an experimental transaction bundle.  Its transactions are not added to the
+    /// mempool individually, so they will only be mined together.  Bundles are not relayed to
+    /// other peers.
+    pub fn submit_tx_bundle(
+        &mut self,
+        chainstate: &StacksChainState,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+        bundle: &StacksTransactionBundle,
+    ) -> Result<(), MemPoolRejection> {
+        for tx in bundle.txs.iter() {
+            if self.is_tx_blacklisted(&tx.txid())? {
+                return Err(MemPoolRejection::TemporarilyBlacklisted);
+            }
+            if !self.anchor_policy.will_admit(tx) || !self.anchor_policy.can_mine(tx, true) {
+                return Err(MemPoolRejection::DisallowedAnchorMode(tx.anchor_mode));
+            }
+        }
+
+        chainstate.will_admit_mempool_tx_bundle(bundle)?;
+
+        let height = match chainstate.get_stacks_block_height(consensus_hash, block_hash) {
+            Ok(Some(h)) => h,
+            Ok(None) => {
+                if *consensus_hash == FIRST_BURNCHAIN_CONSENSUS_HASH {
+                    0
+                } else {
+                    return Err(MemPoolRejection::NoSuchChainTip(
+                        consensus_hash.clone(),
+                        block_hash.clone(),
+                    ));
+                }
+            }
+            Err(e) => {
+                return Err(MemPoolRejection::Other(format!(
+                    "Failed to load chain tip: {:?}",
+                    &e
+                )));
+            }
+        };
+
+        let sql = "INSERT OR IGNORE INTO tx_bundles (bundle_id, tx_fee, height, accept_time, bundle) VALUES (?1, ?2, ?3, ?4, ?5)";
+        let args: &[&dyn ToSql] = &[
+            &bundle.bundle_id().to_hex(),
+            &u64_to_sql(bundle.get_tx_fee())?,
+            &u64_to_sql(height)?,
+            &u64_to_sql(get_epoch_time_secs())?,
+            &bundle.serialize_to_vec(),
+        ];
+        self.db
+            .execute(sql, args)
+            .map_err(|e| MemPoolRejection::DBError(db_error::SqliteError(e)))?;
+        Ok(())
+    }
+
+    /// Get up to `max_bundles` transaction bundles, highest total fee first
+    pub fn get_tx_bundles(
+        &self,
+        max_bundles: u64,
+    ) -> Result<Vec<StacksTransactionBundle>, db_error> {
+        let sql = "SELECT bundle FROM tx_bundles ORDER BY tx_fee DESC, accept_time ASC LIMIT ?1";
+        let args: &[&dyn ToSql] = &[&u64_to_sql(max_bundles)?];
+        query_rows(self.conn(), sql, args)
+    }
+
+    /// Drop the transaction bundles with a transaction whose nonce is already used in the chain
+    /// state that `clarity_tx` reads.  Such a bundle was either mined in that chain state, or can
+    /// never be mined in it.  Mined bundles are only dropped this way, so that a bundle is tried
+    /// again if the block it was mined in loses sortition or is orphaned.
+    /// Returns the IDs of the dropped bundles.
+    pub fn drop_confirmed_tx_bundles<C: ClarityConnection>(
+        &mut self,
+        clarity_tx: &mut C,
+    ) -> Result<Vec<Sha512Trunc256Sum>, db_error> {
+        let sql = "SELECT bundle FROM tx_bundles";
+        let bundles: Vec<StacksTransactionBundle> = query_rows(self.conn(), sql, NO_PARAMS)?;
+
+        let mut nonces: HashMap<StacksAddress, u64> = HashMap::new();
+        let mut confirmed = vec![];
+        for bundle in bundles.iter() {
+            let mut spends = vec![];
+            for tx in bundle.txs.iter() {
+                spends.push((tx.origin_address(), tx.get_origin_nonce()));
+                if let (Some(sponsor), Some(sponsor_nonce)) =
+                    (tx.sponsor_address(), tx.get_sponsor_nonce())
+                {
+                    spends.push((sponsor, sponsor_nonce));
+                }
+            }
+
+            for (address, nonce) in spends.into_iter() {
+                let account_nonce = match nonces.get(&address) {
+                    Some(account_nonce) => *account_nonce,
+                    None => {
+                        let account_nonce =
+                            StacksChainState::get_nonce(clarity_tx, &address.clone().into());
+                        nonces.insert(address, account_nonce);
+                        account_nonce
+                    }
+                };
+                if account_nonce > nonce {
+                    confirmed.push(bundle.bundle_id());
+                    break;
+                }
+            }
+        }
+
+        if confirmed.len() > 0 {
+            self.drop_tx_bundles(&confirmed)?;
+        }
+        Ok(confirmed)
+    }
+
+    /// Drop transaction bundles from the mempool
+    pub fn drop_tx_bundles(&mut self, bundle_ids: &[Sha512Trunc256Sum]) -> Result<(), db_error> {
+        let mempool_tx = self.tx_begin()?;
+        let sql = "DELETE FROM tx_bundles WHERE bundle_id = ?1";
+        for bundle_id in bundle_ids.iter() {
+            mempool_tx.execute(sql, &[&bundle_id.to_hex()])?;
+        }
+        mempool_tx.commit()?;
+        Ok(())
+    }
 }
@@ -0,0 +1,542 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A read-only mirror of the mempool, for RPC queries.
+//!
+//! RPC endpoints that list or search the mempool would otherwise query the `mempool` table, or
+//! the candidate view, and so wait on transaction admission and on the miner's walk over the
+//! candidates.  Instead, they read an immutable `MemPoolSnapshot` of the mempool.
+//!
+//! The mirror only holds each transaction's metadata -- readers that need the transaction itself
+//! load it from the `mempool` table by txid, which does not contend with admission or mining.
+//!
+//! When a `MemPoolTx` commits, it queues its changes here.  They are not applied right away --
+//! the next reader to ask for a snapshot copies the current one, applies the queued changes to
+//! the copy, and swaps it in.  Copying a snapshot only copies pointers to its transactions.  So,
+//! writers only ever hold the queue's lock long enough to append to it, and readers only hold the
+//! snapshot's lock long enough to clone or swap a pointer to it (or, at most, to catch it up).
+//! Readers keep using the snapshot they were handed, even if a newer one is swapped in.
+//!
+//! Like the candidate view, the mirror is shared by every `MemPoolDB` opened on the same database
+//! in this process.
+
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+use std::ops::Bound;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+
+use rusqlite::Row;
+use rusqlite::NO_PARAMS;
+
+use crate::burnchains::Txid;
+use crate::types::chainstate::StacksAddress;
+use crate::util_lib::db::{query_rows, DBConn, Error as db_error, FromColumn, FromRow};
+use crate::util_lib::membudget::{BudgetAccount, MemoryBudget, MEMPOOL_MIRROR};
+
+lazy_static! {
+    static ref MEMPOOL_MIRRORS: Mutex<HashMap<String, Weak<MemPoolMirror>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// A transaction in the mempool, as the mirror records it
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemPoolMirrorEntry {
+    pub txid: Txid,
+    pub origin_address: StacksAddress,
+    pub origin_nonce: u64,
+    pub sponsor_address: StacksAddress,
+    pub sponsor_nonce: u64,
+    pub tx_fee: u64,
+    pub fee_rate: Option<f64>,
+    pub len: u64,
+    /// Stacks block height at which the transaction was received
+    pub height: u64,
+    pub accept_time: u64,
+    /// The transaction's position in the randomized order in which the mempool is paged out to
+    /// peers during mempool sync (see `MemPoolDB::find_next_missing_transactions()`)
+    pub randomized_txid: Option<Txid>,
+    /// Whether or not the transaction was evicted from the bloom counter, in which case it is no
+    /// longer paged out to peers
+    pub evicted: bool,
+}
+
+impl FromRow<MemPoolMirrorEntry> for MemPoolMirrorEntry {
+    fn from_row<'a>(row: &'a Row) -> Result<MemPoolMirrorEntry, db_error> {
+        let txid = Txid::from_column(row, "txid")?;
+        let origin_address = StacksAddress::from_column(row, "origin_address")?;
+        let origin_nonce = u64::from_column(row, "origin_nonce")?;
+        let sponsor_address = StacksAddress::from_column(row, "sponsor_address")?;
+        let sponsor_nonce = u64::from_column(row, "sponsor_nonce")?;
+        let tx_fee = u64::from_column(row, "tx_fee")?;
+        let fee_rate: Option<f64> = row.get("fee_rate")?;
+        let len = u64::from_column(row, "length")?;
+        let height = u64::from_column(row, "height")?;
+        let accept_time = u64::from_column(row, "accept_time")?;
+        let randomized_txid: Option<Txid> = row.get("hashed_txid")?;
+        let evicted: bool = row.get("evicted")?;
+
+        Ok(MemPoolMirrorEntry {
+            txid,
+            origin_address,
+            origin_nonce,
+            sponsor_address,
+            sponsor_nonce,
+            tx_fee,
+            fee_rate,
+            len,
+            height,
+            accept_time,
+            randomized_txid,
+            evicted,
+        })
+    }
+}
+
+/// A change to the `mempool` table, to be mirrored once it is committed
+#[derive(Debug, Clone, PartialEq)]
+pub enum MirrorChange {
+    /// A transaction was added, replacing any with the same txid, origin nonce, or sponsor nonce
+    Insert(MemPoolMirrorEntry),
+    /// A transaction's fee rate estimate changed
+    SetFeeRate(Txid, Option<f64>),
+    /// A transaction was evicted from the bloom counter
+    Evict(Txid),
+    /// A transaction was removed
+    Remove(Txid),
+}
+
+/// An immutable view of the whole mempool, as of some commit
+#[derive(Debug, Clone, Default)]
+pub struct MemPoolSnapshot {
+    /// Every transaction in the mempool
+    txs: HashMap<Txid, Arc<MemPoolMirrorEntry>>,
+    /// Transactions by origin account and nonce
+    by_origin: HashMap<(StacksAddress, u64), Txid>,
+    /// Transactions by sponsor account and nonce
+    by_sponsor: HashMap<(StacksAddress, u64), Txid>,
+    /// Transactions by randomized txid
+    by_randomized_txid: BTreeMap<Txid, Txid>,
+    total_len: u64,
+    total_fee: u64,
+    num_unestimated: u64,
+}
+
+impl MemPoolSnapshot {
+    /// Estimated number of bytes a transaction takes up across the snapshot's indexes
+    const ENTRY_SIZE: u64 = (mem::size_of::<MemPoolMirrorEntry>()
+        + mem::size_of::<(Txid, Arc<MemPoolMirrorEntry>)>()
+        + 2 * mem::size_of::<((StacksAddress, u64), Txid)>()
+        + mem::size_of::<(Txid, Txid)>()) as u64;
+
+    /// Get a transaction by txid
+    pub fn get(&self, txid: &Txid) -> Option<&MemPoolMirrorEntry> {
+        self.txs.get(txid).map(|entry| entry.as_ref())
+    }
+
+    /// Get the transaction with the given origin account and nonce
+    pub fn get_by_origin(
+        &self,
+        origin_address: &StacksAddress,
+        origin_nonce: u64,
+    ) -> Option<&MemPoolMirrorEntry> {
+        self.by_origin
+            .get(&(*origin_address, origin_nonce))
+            .and_then(|txid| self.get(txid))
+    }
+
+    /// Iterate over every transaction, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = &MemPoolMirrorEntry> {
+        self.txs.values().map(|entry| entry.as_ref())
+    }
+
+    /// Iterate over the transactions that are paged out to peers during mempool sync -- those
+    /// received after `min_height` and not evicted from the bloom counter -- in order of their
+    /// randomized txids, starting after `last_randomized_txid`.
+    pub fn iter_pager(
+        &self,
+        last_randomized_txid: &Txid,
+        min_height: u64,
+    ) -> impl Iterator<Item = &MemPoolMirrorEntry> {
+        self.by_randomized_txid
+            .range((Bound::Excluded(*last_randomized_txid), Bound::Unbounded))
+            .filter_map(move |(_, txid)| self.get(txid))
+            .filter(move |entry| entry.height > min_height && !entry.evicted)
+    }
+
+    pub fn num_txs(&self) -> u64 {
+        self.txs.len() as u64
+    }
+
+    /// The number of transactions, their total length, and their total fee.  Same as
+    /// `MemPoolDB::get_totals()`.
+    pub fn get_totals(&self) -> (u64, u64, u64) {
+        (self.num_txs(), self.total_len, self.total_fee)
+    }
+
+    /// How many transactions have not yet had their fee rate estimated?
+    pub fn count_txs_without_fee_rate(&self) -> u64 {
+        self.num_unestimated
+    }
+
+    /// How many transactions have a fee rate in the range [min_fee_rate, max_fee_rate)?  If
+    /// `max_fee_rate` is `None`, the range is unbounded above.
+    pub fn count_txs_in_fee_rate_range(&self, min_fee_rate: f64, max_fee_rate: Option<f64>) -> u64 {
+        self.txs
+            .values()
+            .filter_map(|entry| entry.fee_rate)
+            .filter(|fee_rate| match max_fee_rate {
+                Some(max_fee_rate) => *fee_rate >= min_fee_rate && *fee_rate < max_fee_rate,
+                None => *fee_rate >= min_fee_rate,
+            })
+            .count() as u64
+    }
+
+    /// Estimated number of bytes this snapshot takes up
+    fn mem_usage(&self) -> u64 {
+        self.num_txs() * Self::ENTRY_SIZE
+    }
+
+    fn apply(&mut self, changes: Vec<MirrorChange>) {
+        for change in changes.into_iter() {
+            match change {
+                MirrorChange::Insert(entry) => self.insert(entry),
+                MirrorChange::SetFeeRate(txid, fee_rate) => self.set_fee_rate(&txid, fee_rate),
+                MirrorChange::Evict(txid) => self.evict(&txid),
+                MirrorChange::Remove(txid) => self.remove(&txid),
+            }
+        }
+    }
+
+    /// Add a transaction.  Like the `mempool` table, this replaces any transaction with the same
+    /// txid, origin nonce, or sponsor nonce.  A transaction that replaces itself stays evicted.
+    fn insert(&mut self, mut entry: MemPoolMirrorEntry) {
+        if let Some(prior) = self.txs.get(&entry.txid) {
+            entry.evicted |= prior.evicted;
+        }
+        self.remove(&entry.txid);
+        if let Some(txid) = self
+            .by_origin
+            .get(&(entry.origin_address, entry.origin_nonce))
+            .copied()
+        {
+            self.remove(&txid);
+        }
+        if let Some(txid) = self
+            .by_sponsor
+            .get(&(entry.sponsor_address, entry.sponsor_nonce))
+            .copied()
+        {
+            self.remove(&txid);
+        }
+
+        self.total_len += entry.len;
+        self.total_fee += entry.tx_fee;
+        if entry.fee_rate.is_none() {
+            self.num_unestimated += 1;
+        }
+        self.by_origin
+            .insert((entry.origin_address, entry.origin_nonce), entry.txid);
+        self.by_sponsor
+            .insert((entry.sponsor_address, entry.sponsor_nonce), entry.txid);
+        if let Some(randomized_txid) = entry.randomized_txid {
+            self.by_randomized_txid.insert(randomized_txid, entry.txid);
+        }
+        self.txs.insert(entry.txid, Arc::new(entry));
+    }
+
+    fn remove(&mut self, txid: &Txid) {
+        let entry = match self.txs.remove(txid) {
+            Some(entry) => entry,
+            None => return,
+        };
+        self.total_len -= entry.len;
+        self.total_fee -= entry.tx_fee;
+        if entry.fee_rate.is_none() {
+            self.num_unestimated -= 1;
+        }
+        let origin_key = (entry.origin_address, entry.origin_nonce);
+        if self.by_origin.get(&origin_key) == Some(txid) {
+            self.by_origin.remove(&origin_key);
+        }
+        let sponsor_key = (entry.sponsor_address, entry.sponsor_nonce);
+        if self.by_sponsor.get(&sponsor_key) == Some(txid) {
+            self.by_sponsor.remove(&sponsor_key);
+        }
+        if let Some(randomized_txid) = entry.randomized_txid.as_ref() {
+            self.by_randomized_txid.remove(randomized_txid);
+        }
+    }
+
+    fn evict(&mut self, txid: &Txid) {
+        if let Some(entry) = self.txs.get_mut(txid) {
+            // only copies the entry if an older snapshot still shares it
+            Arc::make_mut(entry).evicted = true;
+        }
+    }
+
+    fn set_fee_rate(&mut self, txid: &Txid, fee_rate: Option<f64>) {
+        let entry = match self.txs.get_mut(txid) {
+            Some(entry) => entry,
+            None => return,
+        };
+        match (entry.fee_rate.is_none(), fee_rate.is_none()) {
+            (true, false) => self.num_unestimated -= 1,
+            (false, true) => self.num_unestimated += 1,
+            _ => {}
+        }
+        // only copies the entry if an older snapshot still shares it
+        Arc::make_mut(entry).fee_rate = fee_rate;
+    }
+}
+
+pub struct MemPoolMirror {
+    /// The latest snapshot.  Only locked to hand out or swap in a snapshot.
+    current: RwLock<Arc<MemPoolSnapshot>>,
+    /// Committed changes that are not yet in `current`, in commit order
+    pending: Mutex<Vec<MirrorChange>>,
+    /// Share of the node's memory budget used by the latest snapshot.  The mirror must hold the
+    /// metadata of every transaction in the mempool, so it reports its size but never evicts.
+    budget: BudgetAccount,
+}
+
+impl MemPoolMirror {
+    fn new(snapshot: MemPoolSnapshot) -> MemPoolMirror {
+        let budget = MemoryBudget::register_global(MEMPOOL_MIRROR);
+        budget.set_usage(snapshot.mem_usage());
+        MemPoolMirror {
+            current: RwLock::new(Arc::new(snapshot)),
+            pending: Mutex::new(vec![]),
+            budget,
+        }
+    }
+
+    /// Build the mirror of a mempool database from its `mempool` table
+    pub fn load(conn: &DBConn) -> Result<MemPoolMirror, db_error> {
+        let sql = "SELECT mempool.txid AS txid, origin_address, origin_nonce, sponsor_address, sponsor_nonce, tx_fee, fee_rate, length, height, accept_time, \
+                   randomized_txids.hashed_txid AS hashed_txid, removed_txids.txid IS NOT NULL AS evicted \
+                   FROM mempool \
+                   LEFT JOIN randomized_txids ON mempool.txid = randomized_txids.txid \
+                   LEFT JOIN removed_txids ON mempool.txid = removed_txids.txid";
+        let entries: Vec<MemPoolMirrorEntry> = query_rows(conn, sql, NO_PARAMS)?;
+
+        let mut snapshot = MemPoolSnapshot::default();
+        for entry in entries.into_iter() {
+            snapshot.insert(entry);
+        }
+        debug!("Loaded mempool mirror"; "txs" => snapshot.num_txs());
+        Ok(MemPoolMirror::new(snapshot))
+    }
+
+    /// Get the mirror shared by every `MemPoolDB` opened on `db_path`, loading it from `conn` if
+    /// there isn't one.  If `reload` is set, the mirror is loaded again even if there is one (e.g.
+    /// because the database was just created).
+    pub fn for_mempool(
+        db_path: &str,
+        conn: &DBConn,
+        reload: bool,
+    ) -> Result<Arc<MemPoolMirror>, db_error> {
+        let mut mirrors = MEMPOOL_MIRRORS
+            .lock()
+            .expect("FATAL: mempool mirror registry lock poisoned");
+        mirrors.retain(|_, mirror| mirror.strong_count() > 0);
+
+        if !reload {
+            if let Some(mirror) = mirrors.get(db_path).and_then(|mirror| mirror.upgrade()) {
+                return Ok(mirror);
+            }
+        }
+
+        let mirror = Arc::new(MemPoolMirror::load(conn)?);
+        mirrors.insert(db_path.to_string(), Arc::downgrade(&mirror));
+        Ok(mirror)
+    }
+
+    /// Queue changes that were just committed to the `mempool` table.  Callers must queue changes
+    /// in the order they commit them.
+    pub fn publish(&self, changes: Vec<MirrorChange>) {
+        if changes.is_empty() {
+            return;
+        }
+        self.pending
+            .lock()
+            .expect("FATAL: mempool mirror queue lock poisoned")
+            .extend(changes);
+    }
+
+    /// Get a snapshot of the mempool that includes every change published so far
+    pub fn snapshot(&self) -> Arc<MemPoolSnapshot> {
+        let caught_up = self
+            .pending
+            .lock()
+            .expect("FATAL: mempool mirror queue lock poisoned")
+            .is_empty();
+        if caught_up {
+            return self
+                .current
+                .read()
+                .expect("FATAL: mempool mirror lock poisoned")
+                .clone();
+        }
+
+        let mut current = self
+            .current
+            .write()
+            .expect("FATAL: mempool mirror lock poisoned");
+        // take the queued changes only once we hold the write lock, so that concurrent readers
+        // apply them in the order they were queued
+        let changes = mem::take(
+            &mut *self
+                .pending
+                .lock()
+                .expect("FATAL: mempool mirror queue lock poisoned"),
+        );
+        if !changes.is_empty() {
+            let mut next = MemPoolSnapshot::clone(&current);
+            next.apply(changes);
+            self.budget.set_usage(next.mem_usage());
+            *current = Arc::new(next);
+        }
+        current.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use stacks_common::util::hash::Hash160;
+
+    fn addr(n: u8) -> StacksAddress {
+        StacksAddress {
+            version: 26,
+            bytes: Hash160([n; 20]),
+        }
+    }
+
+    fn entry(id: u8, origin: u8, origin_nonce: u64, fee_rate: Option<f64>) -> MemPoolMirrorEntry {
+        MemPoolMirrorEntry {
+            txid: Txid([id; 32]),
+            origin_address: addr(origin),
+            origin_nonce,
+            sponsor_address: addr(origin),
+            sponsor_nonce: origin_nonce,
+            tx_fee: 100 * id as u64,
+            fee_rate,
+            len: 10 * id as u64,
+            height: id as u64,
+            accept_time: 0,
+            randomized_txid: Some(Txid([0xff - id; 32])),
+            evicted: false,
+        }
+    }
+
+    #[test]
+    fn test_mempool_mirror_snapshots() {
+        let mirror = MemPoolMirror::new(MemPoolSnapshot::default());
+        let empty = mirror.snapshot();
+
+        mirror.publish(vec![
+            MirrorChange::Insert(entry(1, 1, 0, None)),
+            MirrorChange::Insert(entry(2, 1, 1, Some(5.0))),
+            MirrorChange::Insert(entry(3, 2, 0, Some(50.0))),
+        ]);
+
+        // snapshots that were handed out don't change
+        assert_eq!(empty.num_txs(), 0);
+
+        let first = mirror.snapshot();
+        assert_eq!(first.get_totals(), (3, 60, 600));
+        assert_eq!(first.count_txs_without_fee_rate(), 1);
+        assert_eq!(first.count_txs_in_fee_rate_range(0.0, Some(10.0)), 1);
+        assert_eq!(first.count_txs_in_fee_rate_range(5.0, None), 2);
+        assert_eq!(
+            first.get_by_origin(&addr(1), 1).unwrap().txid,
+            Txid([2; 32])
+        );
+
+        // nothing new to apply, so the same snapshot is handed out again
+        assert!(Arc::ptr_eq(&first, &mirror.snapshot()));
+
+        // replace-by-nonce, re-estimate, and remove
+        mirror.publish(vec![MirrorChange::Insert(entry(4, 1, 1, None))]);
+        mirror.publish(vec![
+            MirrorChange::SetFeeRate(Txid([1; 32]), Some(1.0)),
+            MirrorChange::Remove(Txid([3; 32])),
+        ]);
+
+        let second = mirror.snapshot();
+        assert!(second.get(&Txid([2; 32])).is_none());
+        assert!(second.get(&Txid([3; 32])).is_none());
+        assert_eq!(
+            second.get_by_origin(&addr(1), 1).unwrap().txid,
+            Txid([4; 32])
+        );
+        assert_eq!(second.get(&Txid([1; 32])).unwrap().fee_rate, Some(1.0));
+        assert_eq!(second.get_totals(), (2, 50, 500));
+        assert_eq!(second.count_txs_without_fee_rate(), 1);
+
+        // the older snapshot still sees the old fee rate
+        assert_eq!(first.get(&Txid([1; 32])).unwrap().fee_rate, None);
+        assert_eq!(first.num_txs(), 3);
+    }
+
+    #[test]
+    fn test_mempool_mirror_pager() {
+        let mirror = MemPoolMirror::new(MemPoolSnapshot::default());
+        mirror.publish(
+            (1..=5)
+                .map(|id| MirrorChange::Insert(entry(id, id, 0, None)))
+                .collect(),
+        );
+
+        let pager_txids = |snapshot: &MemPoolSnapshot, last: Txid, min_height: u64| {
+            snapshot
+                .iter_pager(&last, min_height)
+                .map(|entry| entry.txid)
+                .collect::<Vec<_>>()
+        };
+
+        // paged out in randomized txid order, which is the reverse of the txid order here
+        let snapshot = mirror.snapshot();
+        assert_eq!(
+            pager_txids(&snapshot, Txid([0; 32]), 0),
+            (1..=5).rev().map(|id| Txid([id; 32])).collect::<Vec<_>>()
+        );
+        // resume after a page
+        assert_eq!(
+            pager_txids(&snapshot, Txid([0xff - 4; 32]), 0),
+            vec![Txid([3; 32]), Txid([2; 32]), Txid([1; 32])]
+        );
+        // only recent transactions
+        assert_eq!(
+            pager_txids(&snapshot, Txid([0; 32]), 3),
+            vec![Txid([5; 32]), Txid([4; 32])]
+        );
+
+        // evicted and removed transactions are not paged out, and an evicted transaction stays
+        // evicted if it is added again
+        mirror.publish(vec![
+            MirrorChange::Evict(Txid([4; 32])),
+            MirrorChange::Remove(Txid([2; 32])),
+            MirrorChange::Insert(entry(4, 4, 0, None)),
+        ]);
+        let next = mirror.snapshot();
+        assert_eq!(
+            pager_txids(&next, Txid([0; 32]), 0),
+            vec![Txid([5; 32]), Txid([3; 32]), Txid([1; 32])]
+        );
+        assert_eq!(pager_txids(&snapshot, Txid([0; 32]), 0).len(), 5);
+    }
+}
@@ -27,7 +27,9 @@ use crate::types::chainstate::StacksBlockId;
 use crate::types::chainstate::{BlockHeaderHash, BurnchainHeaderHash};
 use stacks_common::types::StacksEpoch as GenericStacksEpoch;
 pub use stacks_common::types::StacksEpochId;
+pub mod candidate_view;
 pub mod mempool;
+pub mod mempool_mirror;
 
 #[cfg(test)]
 pub mod tests;
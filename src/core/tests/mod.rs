@@ -29,13 +29,14 @@ use crate::chainstate::stacks::db::StreamCursor;
 use crate::chainstate::stacks::events::StacksTransactionReceipt;
 use crate::chainstate::stacks::miner::TransactionResult;
 use crate::chainstate::stacks::test::codec_all_transactions;
+use crate::chainstate::stacks::tests::{make_user_coinbase, make_user_stacks_transfer};
 use crate::chainstate::stacks::{
     db::blocks::MemPoolRejection, db::StacksChainState, index::MarfTrieId, CoinbasePayload,
     Error as ChainstateError, SinglesigHashMode, SinglesigSpendingCondition, StacksPrivateKey,
-    StacksPublicKey, StacksTransaction, StacksTransactionSigner, TokenTransferMemo,
-    TransactionAnchorMode, TransactionAuth, TransactionContractCall, TransactionPayload,
-    TransactionPostConditionMode, TransactionPublicKeyEncoding, TransactionSmartContract,
-    TransactionSpendingCondition, TransactionVersion,
+    StacksPublicKey, StacksTransaction, StacksTransactionBundle, StacksTransactionSigner,
+    TokenTransferMemo, TransactionAnchorMode, TransactionAuth, TransactionContractCall,
+    TransactionPayload, TransactionPostConditionMode, TransactionPublicKeyEncoding,
+    TransactionSmartContract, TransactionSpendingCondition, TransactionVersion,
 };
 use crate::chainstate::stacks::{
     C32_ADDRESS_VERSION_MAINNET_SINGLESIG, C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
@@ -43,6 +44,7 @@ use crate::chainstate::stacks::{
 use crate::core::mempool::db_get_all_nonces;
 use crate::core::mempool::MemPoolWalkSettings;
 use crate::core::mempool::TxTag;
+use crate::core::mempool::{AnchorModePolicy, TransactionAnchorPolicy};
 use crate::core::mempool::{BLOOM_COUNTER_DEPTH, BLOOM_COUNTER_ERROR_RATE, MAX_BLOOM_COUNTER_TXS};
 use crate::core::FIRST_BURNCHAIN_CONSENSUS_HASH;
 use crate::core::FIRST_STACKS_BLOCK_HASH;
@@ -658,20 +660,9 @@ fn test_iterate_candidates_consider_no_estimate_tx_prob() {
         .unwrap();
 
         if nonce & 1 == 0 {
-            mempool_tx
-                .execute(
-                    "UPDATE mempool SET fee_rate = ? WHERE txid = ?",
-                    rusqlite::params![Some(123.0), &txid],
-                )
-                .unwrap();
+            mempool_tx.update_fee_rate(&txid, Some(123.0)).unwrap();
         } else {
-            let none: Option<f64> = None;
-            mempool_tx
-                .execute(
-                    "UPDATE mempool SET fee_rate = ? WHERE txid = ?",
-                    rusqlite::params![none, &txid],
-                )
-                .unwrap();
+            mempool_tx.update_fee_rate(&txid, None).unwrap();
         }
 
         mempool_tx.commit().unwrap();
@@ -1211,20 +1202,9 @@ fn test_iterate_candidates_concurrent_write_lock() {
         .unwrap();
 
         if nonce & 1 == 0 {
-            mempool_tx
-                .execute(
-                    "UPDATE mempool SET fee_rate = ? WHERE txid = ?",
-                    rusqlite::params![Some(123.0), &txid],
-                )
-                .unwrap();
+            mempool_tx.update_fee_rate(&txid, Some(123.0)).unwrap();
         } else {
-            let none: Option<f64> = None;
-            mempool_tx
-                .execute(
-                    "UPDATE mempool SET fee_rate = ? WHERE txid = ?",
-                    rusqlite::params![none, &txid],
-                )
-                .unwrap();
+            mempool_tx.update_fee_rate(&txid, None).unwrap();
         }
 
         mempool_tx.commit().unwrap();
@@ -2912,6 +2892,124 @@ fn test_drop_and_blacklist_txs_by_time() {
     }
 }
 
+#[test]
+fn test_mempool_snapshot() {
+    let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+    let chainstate_path = chainstate_path(function_name!());
+    let mut mempool = MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+
+    let addr = StacksAddress {
+        version: 1,
+        bytes: Hash160([0xff; 20]),
+    };
+    let mut txs = vec![];
+    let block_height = 10;
+
+    let before = mempool.snapshot();
+
+    let mut mempool_tx = mempool.tx_begin().unwrap();
+    for i in 0..10 {
+        let pk = StacksPrivateKey::new();
+        let mut tx = StacksTransaction {
+            version: TransactionVersion::Testnet,
+            chain_id: 0x80000000,
+            auth: TransactionAuth::from_p2pkh(&pk).unwrap(),
+            anchor_mode: TransactionAnchorMode::Any,
+            post_condition_mode: TransactionPostConditionMode::Allow,
+            post_conditions: vec![],
+            payload: TransactionPayload::TokenTransfer(
+                addr.to_account_principal(),
+                123,
+                TokenTransferMemo([0u8; 34]),
+            ),
+        };
+        tx.set_tx_fee(1000 + i);
+        tx.set_origin_nonce(0);
+
+        let txid = tx.txid();
+        let tx_bytes = tx.serialize_to_vec();
+        let origin_addr = tx.origin_address();
+        let origin_nonce = tx.get_origin_nonce();
+        let sponsor_addr = tx.sponsor_address().unwrap_or(origin_addr.clone());
+        let sponsor_nonce = tx.get_sponsor_nonce().unwrap_or(origin_nonce);
+        let tx_fee = tx.get_tx_fee();
+
+        MemPoolDB::try_add_tx(
+            &mut mempool_tx,
+            &mut chainstate,
+            &ConsensusHash([0x1 + (block_height as u8); 20]),
+            &BlockHeaderHash([0x2 + (block_height as u8); 32]),
+            txid.clone(),
+            tx_bytes,
+            tx_fee,
+            block_height as u64,
+            &origin_addr,
+            origin_nonce,
+            &sponsor_addr,
+            sponsor_nonce,
+            None,
+        )
+        .unwrap();
+        txs.push(tx);
+    }
+
+    mempool_tx.commit().unwrap();
+
+    // the snapshot matches the database
+    let snapshot = mempool.snapshot();
+    assert_eq!(
+        snapshot.get_totals(),
+        MemPoolDB::get_totals(mempool.conn()).unwrap()
+    );
+    assert_eq!(snapshot.count_txs_without_fee_rate(), 10);
+    for tx in txs.iter() {
+        let entry = snapshot.get(&tx.txid()).unwrap();
+        assert_eq!(entry.tx_fee, tx.get_tx_fee());
+        assert_eq!(entry.height, block_height);
+        assert_eq!(
+            entry.randomized_txid,
+            mempool.get_randomized_txid(&tx.txid()).unwrap()
+        );
+        assert_eq!(
+            MemPoolDB::get_tx_bytes(mempool.conn(), &tx.txid()).unwrap(),
+            Some(tx.serialize_to_vec())
+        );
+    }
+
+    // snapshots handed out earlier don't change
+    assert_eq!(before.num_txs(), 0);
+
+    // fee rate estimates are mirrored
+    let mut mempool_tx = mempool.tx_begin().unwrap();
+    for tx in txs[0..5].iter() {
+        mempool_tx.update_fee_rate(&tx.txid(), Some(10.0)).unwrap();
+    }
+    mempool_tx.commit().unwrap();
+
+    let snapshot = mempool.snapshot();
+    assert_eq!(snapshot.count_txs_without_fee_rate(), 5);
+    assert_eq!(snapshot.count_txs_in_fee_rate_range(5.0, Some(20.0)), 5);
+    assert_eq!(
+        snapshot.count_txs_in_fee_rate_range(5.0, Some(20.0)),
+        MemPoolDB::count_txs_in_fee_rate_range(mempool.conn(), 5.0, Some(20.0)).unwrap()
+    );
+
+    // so are dropped transactions, including to other instances open on the same database
+    let other_mempool = MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+    let txids: Vec<_> = txs[5..].iter().map(|tx| tx.txid()).collect();
+    mempool.drop_txs(&txids).unwrap();
+
+    let snapshot = other_mempool.snapshot();
+    assert_eq!(snapshot.num_txs(), 5);
+    for txid in txids.iter() {
+        assert!(snapshot.get(txid).is_none());
+    }
+    assert_eq!(
+        snapshot.get_totals(),
+        MemPoolDB::get_totals(other_mempool.conn()).unwrap()
+    );
+}
+
 #[test]
 fn test_drop_and_blacklist_txs_by_size() {
     let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
@@ -3013,3 +3111,187 @@ fn test_drop_and_blacklist_txs_by_size() {
 
     assert_eq!(num_blacklisted, 5);
 }
+
+#[test]
+fn test_anchor_mode_policy() {
+    let policy = TransactionAnchorPolicy {
+        token_transfer: AnchorModePolicy::Any,
+        contract_call: AnchorModePolicy::AnchoredOnly,
+        smart_contract: AnchorModePolicy::MicroblocksOnly,
+    };
+
+    for anchor_mode in [
+        TransactionAnchorMode::OnChainOnly,
+        TransactionAnchorMode::OffChainOnly,
+        TransactionAnchorMode::Any,
+    ] {
+        let txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &anchor_mode,
+            &TransactionPostConditionMode::Allow,
+        );
+        for tx in txs.iter() {
+            // the permissive policy admits and mines everything
+            assert!(TransactionAnchorPolicy::any().will_admit(tx));
+            assert!(TransactionAnchorPolicy::any().can_mine(tx, true));
+            assert!(TransactionAnchorPolicy::any().can_mine(tx, false));
+
+            let (expect_anchored, expect_microblocks) = match tx.payload {
+                TransactionPayload::ContractCall(..) => (true, false),
+                TransactionPayload::SmartContract(..) => (false, true),
+                _ => (true, true),
+            };
+            assert_eq!(policy.can_mine(tx, true), expect_anchored);
+            assert_eq!(policy.can_mine(tx, false), expect_microblocks);
+
+            // only admit transactions that both the anchor mode and the policy allow somewhere
+            let expect_admit = match anchor_mode {
+                TransactionAnchorMode::OnChainOnly => expect_anchored,
+                TransactionAnchorMode::OffChainOnly => expect_microblocks,
+                TransactionAnchorMode::Any => true,
+            };
+            assert_eq!(policy.will_admit(tx), expect_admit);
+        }
+    }
+
+    assert_eq!(
+        AnchorModePolicy::from_str("anchored_only"),
+        Some(AnchorModePolicy::AnchoredOnly)
+    );
+    assert_eq!(
+        AnchorModePolicy::from_str("microblocks_only"),
+        Some(AnchorModePolicy::MicroblocksOnly)
+    );
+    assert_eq!(
+        AnchorModePolicy::from_str("any"),
+        Some(AnchorModePolicy::Any)
+    );
+    assert_eq!(AnchorModePolicy::from_str("none"), None);
+}
+
+#[test]
+fn test_tx_bundles() {
+    let chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+    let chainstate_path = chainstate_path(function_name!());
+    let mut mempool = MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+
+    let privk = StacksPrivateKey::from_hex(
+        "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+    )
+    .unwrap();
+    let recipient = PrincipalData::from(StacksAddress {
+        version: 26,
+        bytes: Hash160([0x11; 20]),
+    });
+    let txs: Vec<_> = (0..3)
+        .map(|nonce| make_user_stacks_transfer(&privk, nonce, 1000 + nonce, &recipient, 1))
+        .collect();
+
+    let tip = (
+        FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+        FIRST_STACKS_BLOCK_HASH.clone(),
+    );
+    let submit_bundle = |mempool: &mut MemPoolDB, bundle: &StacksTransactionBundle| {
+        mempool.submit_tx_bundle(&chainstate, &tip.0, &tip.1, bundle)
+    };
+    let submit = |mempool: &mut MemPoolDB, txs: Vec<StacksTransaction>| {
+        submit_bundle(
+            mempool,
+            &StacksTransactionBundle::new_signed(txs, &privk).unwrap(),
+        )
+    };
+
+    // bundles must have at least two distinct transactions
+    match submit(&mut mempool, vec![txs[0].clone()]) {
+        Err(MemPoolRejection::InvalidTransactionBundle(..)) => {}
+        x => panic!("Expected InvalidTransactionBundle, got {:?}", &x),
+    }
+    match submit(&mut mempool, vec![txs[0].clone(), txs[0].clone()]) {
+        Err(MemPoolRejection::InvalidTransactionBundle(..)) => {}
+        x => panic!("Expected InvalidTransactionBundle, got {:?}", &x),
+    }
+
+    // no coinbases
+    match submit(
+        &mut mempool,
+        vec![txs[0].clone(), make_user_coinbase(&privk, 1, 1000)],
+    ) {
+        Err(MemPoolRejection::NoCoinbaseViaMempool) => {}
+        x => panic!("Expected NoCoinbaseViaMempool, got {:?}", &x),
+    }
+
+    // every transaction must be valid on its own
+    let mut bad_tx = txs[1].clone();
+    bad_tx.chain_id = 0x80000001;
+    match submit(&mut mempool, vec![txs[0].clone(), bad_tx]) {
+        Err(MemPoolRejection::FailedToValidate(..)) => {}
+        x => panic!("Expected FailedToValidate, got {:?}", &x),
+    }
+    assert!(mempool.get_tx_bundles(10).unwrap().is_empty());
+
+    // the submitter must sign the bundle
+    let mut unsigned_bundle =
+        StacksTransactionBundle::new_signed(vec![txs[0].clone(), txs[1].clone()], &privk).unwrap();
+    unsigned_bundle.signature = MessageSignature::empty();
+    match submit_bundle(&mut mempool, &unsigned_bundle) {
+        Err(MemPoolRejection::InvalidTransactionBundle(..)) => {}
+        x => panic!("Expected InvalidTransactionBundle, got {:?}", &x),
+    }
+    let mut forged_bundle = unsigned_bundle.clone();
+    forged_bundle.sign(&privk).unwrap();
+    forged_bundle.txs.swap(0, 1);
+    match submit_bundle(&mut mempool, &forged_bundle) {
+        Err(MemPoolRejection::InvalidTransactionBundle(..)) => {}
+        x => panic!("Expected InvalidTransactionBundle, got {:?}", &x),
+    }
+
+    // ...and must be the origin or sponsor of one of its transactions
+    let other_privk = StacksPrivateKey::from_hex(
+        "42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01",
+    )
+    .unwrap();
+    let outsider_bundle =
+        StacksTransactionBundle::new_signed(vec![txs[0].clone(), txs[1].clone()], &other_privk)
+            .unwrap();
+    outsider_bundle.verify().unwrap();
+    match submit_bundle(&mut mempool, &outsider_bundle) {
+        Err(MemPoolRejection::InvalidTransactionBundle(..)) => {}
+        x => panic!("Expected InvalidTransactionBundle, got {:?}", &x),
+    }
+    assert!(mempool.get_tx_bundles(10).unwrap().is_empty());
+
+    // bundles are returned highest-fee first
+    let bundle_1 =
+        StacksTransactionBundle::new_signed(vec![txs[0].clone(), txs[1].clone()], &privk).unwrap();
+    let bundle_2 =
+        StacksTransactionBundle::new_signed(vec![txs[1].clone(), txs[2].clone()], &privk).unwrap();
+    submit(&mut mempool, bundle_1.txs.clone()).unwrap();
+    submit(&mut mempool, bundle_2.txs.clone()).unwrap();
+
+    // resubmitting is a no-op
+    submit(&mut mempool, bundle_1.txs.clone()).unwrap();
+
+    assert!(mempool.has_tx_bundle(&bundle_1.bundle_id()).unwrap());
+    assert!(mempool.has_tx_bundle(&bundle_2.bundle_id()).unwrap());
+    assert_eq!(
+        mempool.get_tx_bundles(10).unwrap(),
+        vec![bundle_2.clone(), bundle_1.clone()]
+    );
+    assert_eq!(mempool.get_tx_bundles(1).unwrap(), vec![bundle_2.clone()]);
+
+    mempool.drop_tx_bundles(&[bundle_2.bundle_id()]).unwrap();
+    assert!(!mempool.has_tx_bundle(&bundle_2.bundle_id()).unwrap());
+    assert_eq!(mempool.get_tx_bundles(10).unwrap(), vec![bundle_1]);
+
+    // the anchor mode policy applies to every transaction in the bundle
+    mempool.anchor_policy = TransactionAnchorPolicy {
+        token_transfer: AnchorModePolicy::MicroblocksOnly,
+        contract_call: AnchorModePolicy::Any,
+        smart_contract: AnchorModePolicy::Any,
+    };
+    match submit(&mut mempool, bundle_2.txs.clone()) {
+        Err(MemPoolRejection::DisallowedAnchorMode(..)) => {}
+        x => panic!("Expected DisallowedAnchorMode, got {:?}", &x),
+    }
+}
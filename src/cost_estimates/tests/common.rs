@@ -43,6 +43,7 @@ pub fn make_block_receipt(tx_receipts: Vec<StacksTransactionReceipt>) -> StacksE
         matured_rewards_info: None,
         parent_microblocks_cost: ExecutionCost::zero(),
         anchored_block_cost: ExecutionCost::zero(),
+        parent_microblocks_size: 0,
         parent_burn_block_hash: BurnchainHeaderHash([0; 32]),
         parent_burn_block_height: 1,
         parent_burn_block_timestamp: 1,
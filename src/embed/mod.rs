@@ -0,0 +1,585 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Embedding API.
+//!
+//! This module lets another program open and drive the node's subsystems -- the sortition DB,
+//! the Stacks chainstate, the mempool, and the p2p and RPC network -- without going through the
+//! `stacks-node` binary and its run loop.
+//!
+//! The lifecycle of an embedded node is:
+//!   1. Configure an `EmbeddedNodeBuilder` with the network's burnchain parameters and a working
+//!      directory.  Everything else has a default.
+//!   2. `EmbeddedNodeBuilder::build()` opens the databases, creating and booting them if they do
+//!      not exist yet, and instantiates the `PeerNetwork` without binding it.
+//!   3. `EmbeddedNode::bind()` binds the p2p and RPC sockets.  Skip this step to only use the
+//!      databases.
+//!   4. Call `EmbeddedNode::run_network_pass()` in a loop.  Each pass polls the sockets, serves
+//!      RPC requests, drives the p2p state machines, and returns what it learned as a
+//!      `NetworkResult`.  Hand it to `EmbeddedNode::relay_network_result()` to store the blocks,
+//!      microblocks, and transactions it contains and to forward them to peers.
+//!   5. Drop the `EmbeddedNode` to shut it down.  This closes its sockets and database handles.
+//!
+//! The embedder remains responsible for everything that the `stacks-node` run loop does on other
+//! threads: downloading burnchain blocks, and running a `ChainsCoordinator` to process the Stacks
+//! blocks that relaying stores (whose `CoordinatorChannels` can be passed to
+//! `relay_network_result()` so it is woken up when new blocks arrive).  Mining is not covered.
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::burnchains::db::BurnchainHeaderReader;
+use crate::burnchains::Burnchain;
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::coordinator::comm::CoordinatorChannels;
+use crate::chainstate::stacks::db::{ChainStateBootData, StacksChainState};
+use crate::chainstate::stacks::index::marf::MARFOpenOpts;
+use crate::chainstate::stacks::Error as chainstate_error;
+use crate::core::mempool::MemPoolDB;
+use crate::core::StacksEpoch;
+use crate::core::{STACKS_EPOCHS_MAINNET, STACKS_EPOCHS_TESTNET};
+use crate::cost_estimates::metrics::UnitMetric;
+use crate::cost_estimates::UnitEstimator;
+use crate::net::atlas::{AtlasConfig, AtlasDB};
+use crate::net::connection::ConnectionOptions;
+use crate::net::db::PeerDB;
+use crate::net::dns::DNSClient;
+use crate::net::p2p::PeerNetwork;
+use crate::net::relay::{ProcessedNetReceipts, Relayer};
+use crate::net::rpc::RPCHandlerArgs;
+use crate::net::Error as net_error;
+use crate::net::{Neighbor, NetworkResult, PeerAddress, ServiceFlags};
+use crate::util_lib::db::Error as db_error;
+use crate::util_lib::strings::UrlString;
+use stacks_common::types::chainstate::StacksPrivateKey;
+use stacks_common::types::NetworkId;
+
+/// Default p2p address the node advertises to its peers
+pub const DEFAULT_P2P_ADDRESS: &'static str = "127.0.0.1:20444";
+/// Default URL the node advertises for its RPC interface
+pub const DEFAULT_DATA_URL: &'static str = "http://127.0.0.1:20443";
+
+#[derive(Debug)]
+pub enum Error {
+    /// Failed to open or create one of the node's databases
+    DBError(db_error),
+    /// Failed to open or boot the chainstate
+    ChainstateError(chainstate_error),
+    /// Network error
+    NetError(net_error),
+    /// The builder's configuration is invalid
+    InvalidConfig(String),
+    /// The network must be bound before it can be run
+    NotBound,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::DBError(ref e) => fmt::Display::fmt(e, f),
+            Error::ChainstateError(ref e) => fmt::Display::fmt(e, f),
+            Error::NetError(ref e) => fmt::Display::fmt(e, f),
+            Error::InvalidConfig(ref s) => write!(f, "Invalid configuration: {}", s),
+            Error::NotBound => write!(f, "Network is not bound"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            Error::DBError(ref e) => Some(e),
+            Error::ChainstateError(ref e) => Some(e),
+            Error::NetError(ref e) => Some(e),
+            Error::InvalidConfig(_) => None,
+            Error::NotBound => None,
+        }
+    }
+}
+
+impl From<db_error> for Error {
+    fn from(e: db_error) -> Error {
+        Error::DBError(e)
+    }
+}
+
+impl From<chainstate_error> for Error {
+    fn from(e: chainstate_error) -> Error {
+        Error::ChainstateError(e)
+    }
+}
+
+impl From<net_error> for Error {
+    fn from(e: net_error) -> Error {
+        Error::NetError(e)
+    }
+}
+
+/// Builder for an `EmbeddedNode`.
+///
+/// The databases are laid out under `working_dir` the same way the `stacks-node` binary lays them
+/// out under `{working_dir}/{mode}`, so an embedded node can open a chainstate synced by a regular
+/// node and vice versa.  The sortition DB lives wherever `burnchain.working_dir` says it does.
+pub struct EmbeddedNodeBuilder {
+    working_dir: String,
+    mainnet: bool,
+    chain_id: u32,
+    burnchain: Burnchain,
+    epochs: Vec<StacksEpoch>,
+    boot_data: Option<ChainStateBootData>,
+    marf_opts: Option<MARFOpenOpts>,
+    connection_options: ConnectionOptions,
+    atlas_config: AtlasConfig,
+    node_private_key: Option<StacksPrivateKey>,
+    p2p_address: SocketAddr,
+    data_url: String,
+    bootstrap_peers: Vec<Neighbor>,
+}
+
+impl EmbeddedNodeBuilder {
+    pub fn new(
+        working_dir: &str,
+        mainnet: bool,
+        chain_id: u32,
+        burnchain: Burnchain,
+    ) -> EmbeddedNodeBuilder {
+        let epochs = if mainnet {
+            STACKS_EPOCHS_MAINNET.to_vec()
+        } else {
+            STACKS_EPOCHS_TESTNET.to_vec()
+        };
+        EmbeddedNodeBuilder {
+            working_dir: working_dir.to_string(),
+            mainnet,
+            chain_id,
+            burnchain,
+            epochs,
+            boot_data: None,
+            marf_opts: None,
+            connection_options: ConnectionOptions::default(),
+            atlas_config: AtlasConfig::default(mainnet),
+            node_private_key: None,
+            p2p_address: DEFAULT_P2P_ADDRESS
+                .parse()
+                .expect("BUG: invalid default p2p address"),
+            data_url: DEFAULT_DATA_URL.to_string(),
+            bootstrap_peers: vec![],
+        }
+    }
+
+    /// Stacks epochs to instantiate a new sortition DB with.  Defaults to the mainnet or testnet
+    /// epochs.  Ignored if the sortition DB already exists.
+    pub fn epochs(mut self, epochs: Vec<StacksEpoch>) -> Self {
+        self.epochs = epochs;
+        self
+    }
+
+    /// Boot data to instantiate a new chainstate with.  Required if the chainstate does not
+    /// exist yet; ignored if it does.
+    pub fn boot_data(mut self, boot_data: ChainStateBootData) -> Self {
+        self.boot_data = Some(boot_data);
+        self
+    }
+
+    pub fn marf_opts(mut self, marf_opts: MARFOpenOpts) -> Self {
+        self.marf_opts = Some(marf_opts);
+        self
+    }
+
+    pub fn connection_options(mut self, connection_options: ConnectionOptions) -> Self {
+        self.connection_options = connection_options;
+        self
+    }
+
+    pub fn atlas_config(mut self, atlas_config: AtlasConfig) -> Self {
+        self.atlas_config = atlas_config;
+        self
+    }
+
+    /// Key the node uses to sign its p2p messages.  Defaults to the key already stored in the
+    /// peer DB, or a random key if the peer DB is new.
+    pub fn node_private_key(mut self, node_private_key: StacksPrivateKey) -> Self {
+        self.node_private_key = Some(node_private_key);
+        self
+    }
+
+    /// Address the node advertises to its peers.  This is not necessarily the address the p2p
+    /// socket is bound to (see `EmbeddedNode::bind()`).
+    pub fn p2p_address(mut self, p2p_address: SocketAddr) -> Self {
+        self.p2p_address = p2p_address;
+        self
+    }
+
+    /// URL the node advertises for its RPC interface
+    pub fn data_url(mut self, data_url: &str) -> Self {
+        self.data_url = data_url.to_string();
+        self
+    }
+
+    /// Peers to bootstrap from.  They are always allowed to connect.
+    pub fn bootstrap_peers(mut self, bootstrap_peers: Vec<Neighbor>) -> Self {
+        self.bootstrap_peers = bootstrap_peers;
+        self
+    }
+
+    fn chainstate_path(&self) -> PathBuf {
+        let mut path = PathBuf::from(&self.working_dir);
+        path.push("chainstate");
+        path
+    }
+
+    fn path_str(path: PathBuf) -> String {
+        path.to_str().expect("Unable to produce path").to_string()
+    }
+
+    /// Open (and create, if need be) all of the node's databases, and instantiate its network.
+    pub fn build(self) -> Result<EmbeddedNode, Error> {
+        let data_url = UrlString::try_from(self.data_url.clone())
+            .map_err(|_| Error::InvalidConfig(format!("bad data URL '{}'", &self.data_url)))?;
+
+        let chainstate_path = EmbeddedNodeBuilder::path_str(self.chainstate_path());
+        let chainstate_exists = fs::metadata(StacksChainState::vm_state_index_marf_path(
+            self.chainstate_path(),
+        ))
+        .is_ok();
+        if !chainstate_exists && self.boot_data.is_none() {
+            return Err(Error::InvalidConfig(
+                "no chainstate exists yet, and no boot data was given to instantiate one".into(),
+            ));
+        }
+
+        let mut peer_db_path = self.chainstate_path();
+        peer_db_path.set_file_name("peer.sqlite");
+        let peer_db_path = EmbeddedNodeBuilder::path_str(peer_db_path);
+        let mut atlas_db_path = self.chainstate_path();
+        atlas_db_path.set_file_name("atlas.sqlite");
+        let atlas_db_path = EmbeddedNodeBuilder::path_str(atlas_db_path);
+
+        let EmbeddedNodeBuilder {
+            mainnet,
+            chain_id,
+            burnchain,
+            epochs,
+            mut boot_data,
+            marf_opts,
+            connection_options,
+            atlas_config,
+            node_private_key,
+            p2p_address,
+            bootstrap_peers,
+            ..
+        } = self;
+
+        let sortdb = SortitionDB::connect(
+            &burnchain.get_db_path(),
+            burnchain.first_block_height,
+            &burnchain.first_block_hash,
+            burnchain.first_block_timestamp.into(),
+            &epochs,
+            burnchain.pox_constants.clone(),
+            true,
+        )?;
+
+        let (chainstate, _) = StacksChainState::open_and_exec(
+            NetworkId::from_chain_id(mainnet, chain_id),
+            &chainstate_path,
+            boot_data.as_mut(),
+            marf_opts,
+        )?;
+
+        let mempool = MemPoolDB::open(
+            mainnet,
+            chain_id,
+            &chainstate_path,
+            Box::new(UnitEstimator),
+            Box::new(UnitMetric),
+        )?;
+
+        let mut peerdb = PeerDB::connect(
+            &peer_db_path,
+            true,
+            chain_id,
+            burnchain.network_id,
+            node_private_key,
+            connection_options.private_key_lifetime.clone(),
+            PeerAddress::from_socketaddr(&p2p_address),
+            p2p_address.port(),
+            data_url,
+            &vec![],
+            Some(&bootstrap_peers),
+        )?;
+
+        {
+            let mut tx = peerdb.tx_begin()?;
+            for peer in bootstrap_peers.iter() {
+                // update peer in case public key changed
+                PeerDB::update_peer(&mut tx, peer)?;
+                PeerDB::set_allow_peer(
+                    &mut tx,
+                    peer.addr.network_id,
+                    &peer.addr.addrbytes,
+                    peer.addr.port,
+                    -1,
+                )?;
+            }
+            PeerDB::set_local_services(
+                &mut tx,
+                (ServiceFlags::RPC as u16)
+                    | (ServiceFlags::RELAY as u16)
+                    | (ServiceFlags::METADATA as u16),
+            )?;
+            tx.commit().map_err(db_error::SqliteError)?;
+        }
+
+        let atlasdb = AtlasDB::connect(atlas_config, &atlas_db_path, true)?;
+
+        let epochs = SortitionDB::get_stacks_epochs(sortdb.conn())?;
+        let sortition_tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+        let view = SortitionDB::get_burnchain_view(sortdb.conn(), &burnchain, &sortition_tip)?;
+        let local_peer = PeerDB::get_local_peer(peerdb.conn())?;
+
+        let mut network = PeerNetwork::new(
+            peerdb,
+            atlasdb,
+            local_peer,
+            burnchain.peer_version,
+            burnchain.clone(),
+            view,
+            connection_options,
+            epochs,
+        );
+        let relayer = Relayer::from_p2p(&mut network);
+
+        Ok(EmbeddedNode {
+            burnchain,
+            sortdb,
+            chainstate,
+            mempool,
+            network,
+            relayer,
+            bound: false,
+        })
+    }
+}
+
+/// A node's subsystems, opened and ready to be driven by the embedder.  See the module
+/// documentation for its lifecycle.
+pub struct EmbeddedNode {
+    burnchain: Burnchain,
+    sortdb: SortitionDB,
+    chainstate: StacksChainState,
+    mempool: MemPoolDB,
+    network: PeerNetwork,
+    relayer: Relayer,
+    bound: bool,
+}
+
+impl EmbeddedNode {
+    pub fn burnchain(&self) -> &Burnchain {
+        &self.burnchain
+    }
+
+    pub fn sortdb(&mut self) -> &mut SortitionDB {
+        &mut self.sortdb
+    }
+
+    pub fn chainstate(&mut self) -> &mut StacksChainState {
+        &mut self.chainstate
+    }
+
+    pub fn mempool(&mut self) -> &mut MemPoolDB {
+        &mut self.mempool
+    }
+
+    pub fn network(&mut self) -> &mut PeerNetwork {
+        &mut self.network
+    }
+
+    /// Do something with mutable references to several subsystems at once
+    pub fn with_components<F, R>(&mut self, todo: F) -> R
+    where
+        F: FnOnce(&mut SortitionDB, &mut StacksChainState, &mut MemPoolDB, &mut PeerNetwork) -> R,
+    {
+        todo(
+            &mut self.sortdb,
+            &mut self.chainstate,
+            &mut self.mempool,
+            &mut self.network,
+        )
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.bound
+    }
+
+    /// Bind the p2p and RPC sockets.  Must be called before running the network.
+    pub fn bind(&mut self, p2p_bind: &SocketAddr, rpc_bind: &SocketAddr) -> Result<(), Error> {
+        self.network.bind(p2p_bind, rpc_bind)?;
+        self.bound = true;
+        Ok(())
+    }
+
+    /// Run one pass of the p2p and RPC state machines, waiting at most `poll_timeout_ms` for
+    /// socket activity.  `ibd` indicates whether or not the node is in its initial block download.
+    /// `handler_args` configures how RPC requests are handled (e.g. which event observer is told
+    /// about new mempool transactions).
+    pub fn run_network_pass<B: BurnchainHeaderReader>(
+        &mut self,
+        indexer: &B,
+        dns_client_opt: Option<&mut DNSClient>,
+        ibd: bool,
+        poll_timeout_ms: u64,
+        handler_args: &RPCHandlerArgs,
+    ) -> Result<NetworkResult, Error> {
+        if !self.bound {
+            return Err(Error::NotBound);
+        }
+        let mut attachment_requests = HashSet::new();
+        let network_result = self.network.run(
+            indexer,
+            &self.sortdb,
+            &mut self.chainstate,
+            &mut self.mempool,
+            dns_client_opt,
+            false,
+            ibd,
+            poll_timeout_ms,
+            handler_args,
+            &mut attachment_requests,
+        )?;
+        Ok(network_result)
+    }
+
+    /// Store the blocks, microblocks, and transactions in a network result, and relay them to
+    /// this node's peers.  If `coord_comms` is given, the chains coordinator is notified of new
+    /// blocks to process.
+    pub fn relay_network_result(
+        &mut self,
+        network_result: &mut NetworkResult,
+        ibd: bool,
+        coord_comms: Option<&CoordinatorChannels>,
+    ) -> Result<ProcessedNetReceipts, Error> {
+        let receipts = self.relayer.process_network_result(
+            &self.network.local_peer,
+            network_result,
+            &mut self.sortdb,
+            &mut self.chainstate,
+            &mut self.mempool,
+            ibd,
+            coord_comms,
+            None,
+        )?;
+        Ok(receipts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::burnchains::bitcoin::indexer::BitcoinIndexer;
+    use crate::core::BITCOIN_REGTEST_FIRST_BLOCK_HASH;
+    use crate::core::CHAIN_ID_TESTNET;
+    use crate::core::STACKS_EPOCHS_REGTEST;
+    use crate::types::chainstate::BurnchainHeaderHash;
+
+    fn make_builder(test_name: &str) -> EmbeddedNodeBuilder {
+        let working_dir = format!("/tmp/stacks-node-tests/embed/{}", test_name);
+        let mut burnchain = Burnchain::default_unittest(
+            0,
+            &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+        );
+        burnchain.working_dir = format!("{}/burnchain", &working_dir);
+        let boot_data = ChainStateBootData::new(&burnchain, vec![], None);
+
+        EmbeddedNodeBuilder::new(&working_dir, false, CHAIN_ID_TESTNET, burnchain)
+            .epochs(STACKS_EPOCHS_REGTEST.to_vec())
+            .boot_data(boot_data)
+    }
+
+    #[test]
+    fn test_embedded_node_lifecycle() {
+        let working_dir = "/tmp/stacks-node-tests/embed/test_embedded_node_lifecycle";
+        if fs::metadata(working_dir).is_ok() {
+            fs::remove_dir_all(working_dir).unwrap();
+        }
+
+        let node_key = StacksPrivateKey::new();
+        {
+            let mut node = make_builder("test_embedded_node_lifecycle")
+                .node_private_key(node_key.clone())
+                .build()
+                .unwrap();
+
+            // the chainstate was booted
+            let tip = SortitionDB::get_canonical_burn_chain_tip(node.sortdb().conn()).unwrap();
+            assert_eq!(tip.block_height, 0);
+            assert_eq!(
+                MemPoolDB::get_all_txs(node.mempool().conn()).unwrap().len(),
+                0
+            );
+
+            // can't run the network until it's bound
+            let indexer = BitcoinIndexer::new_unit_test(&node.burnchain().working_dir);
+            let handler_args = RPCHandlerArgs::default();
+            match node.run_network_pass(&indexer, None, false, 1, &handler_args) {
+                Err(Error::NotBound) => {}
+                _ => panic!("ran an unbound network"),
+            }
+
+            node.bind(
+                &"127.0.0.1:52010".parse().unwrap(),
+                &"127.0.0.1:52011".parse().unwrap(),
+            )
+            .unwrap();
+            assert!(node.is_bound());
+
+            let mut network_result = node
+                .run_network_pass(&indexer, None, false, 1, &handler_args)
+                .unwrap();
+            let receipts = node
+                .relay_network_result(&mut network_result, false, None)
+                .unwrap();
+            assert_eq!(receipts.num_new_blocks, 0);
+        }
+
+        // re-opening an existing node does not need boot data, and keeps the node's identity
+        let mut builder = make_builder("test_embedded_node_lifecycle");
+        builder.boot_data = None;
+        let mut node = builder.build().unwrap();
+        assert_eq!(node.network().local_peer.private_key, node_key);
+    }
+
+    #[test]
+    fn test_embedded_node_needs_boot_data() {
+        let working_dir = "/tmp/stacks-node-tests/embed/test_embedded_node_needs_boot_data";
+        if fs::metadata(working_dir).is_ok() {
+            fs::remove_dir_all(working_dir).unwrap();
+        }
+
+        let mut builder = make_builder("test_embedded_node_needs_boot_data");
+        builder.boot_data = None;
+        match builder.build() {
+            Err(Error::InvalidConfig(_)) => {}
+            Err(e) => panic!("Unexpected error: {:?}", &e),
+            Ok(_) => panic!("Booted a chainstate without boot data"),
+        }
+    }
+}
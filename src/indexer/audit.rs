@@ -0,0 +1,203 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! STX balance consistency audit.
+//!
+//! Folds the STX balance changes recorded by the embedded indexer on the canonical fork up to a
+//! given height, and compares each account's resulting total against the balance stored in the
+//! MARF at the indexed block at that height.  Any difference means that either the indexer missed
+//! a block or an event, or that some balance change was not reported as an event.
+//!
+//! Lock events move STX between an account's unlocked and locked balance without changing its
+//! total, so only total balances are compared.  Genesis balances are not reported as events, so
+//! each account's balance in the boot block is used as its starting point.  Only accounts that
+//! appear in at least one indexed balance change are audited, and the audit is only meaningful if
+//! the indexer has seen every block since genesis.
+
+use std::collections::BTreeMap;
+
+use rusqlite::Connection;
+
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::chainstate::stacks::StacksBlockHeader;
+use crate::clarity_vm::clarity::ClarityConnection;
+use crate::core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
+use crate::types::chainstate::StacksBlockId;
+use crate::util_lib::db::Error as db_error;
+use crate::util_lib::db::{u64_to_sql, FromColumn};
+use clarity::vm::database::NULL_BURN_STATE_DB;
+use clarity::vm::types::PrincipalData;
+
+use super::{parse_amount, IndexerDB, STX_ASSET};
+
+/// An account whose indexed STX balance disagrees with its MARF balance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceDiscrepancy {
+    pub principal: String,
+    /// Balance in the boot block, plus indexed credits, minus indexed debits.  Signed decimal
+    /// string.
+    pub event_balance: String,
+    /// Total (unlocked plus locked) balance stored in the MARF.  Decimal string.
+    pub marf_balance: String,
+}
+
+/// The result of auditing STX balances at one block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceAuditReport {
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub accounts_checked: u64,
+    pub discrepancies: Vec<BalanceDiscrepancy>,
+}
+
+/// Total STX credited to and debited from one account by indexed events
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StxEventTotals {
+    pub credit: u128,
+    pub debit: u128,
+}
+
+impl StxEventTotals {
+    /// The balance of an account that started with `starting_balance`, as a signed decimal string
+    pub fn balance_from(&self, starting_balance: u128) -> String {
+        let credit = starting_balance.saturating_add(self.credit);
+        if credit >= self.debit {
+            (credit - self.debit).to_string()
+        } else {
+            format!("-{}", self.debit - credit)
+        }
+    }
+}
+
+impl IndexerDB {
+    /// Get the canonical indexed block at `block_height`, if there is one
+    pub fn get_canonical_block_at_height(
+        conn: &Connection,
+        block_height: u64,
+    ) -> Result<Option<StacksBlockId>, db_error> {
+        let mut stmt = conn.prepare(
+            "SELECT index_block_hash FROM blocks WHERE canonical = 1 AND block_height = ?1",
+        )?;
+        let mut rows = stmt.query(&[&u64_to_sql(block_height)?])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(StacksBlockId::from_column(row, "index_block_hash")?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Sum the STX balance changes of every account over the canonical blocks up to and
+    /// including `block_height`
+    pub fn get_stx_event_totals(
+        conn: &Connection,
+        block_height: u64,
+    ) -> Result<BTreeMap<String, StxEventTotals>, db_error> {
+        let mut stmt = conn.prepare(
+            "SELECT c.principal, c.credit, c.debit FROM balance_changes c \
+             JOIN blocks b ON c.index_block_hash = b.index_block_hash \
+             WHERE c.asset = ?1 AND b.canonical = 1 AND b.block_height <= ?2",
+        )?;
+        let mut rows = stmt.query(rusqlite::params![STX_ASSET, u64_to_sql(block_height)?])?;
+        let mut totals: BTreeMap<String, StxEventTotals> = BTreeMap::new();
+        while let Some(row) = rows.next()? {
+            let principal: String = row.get_unwrap("principal");
+            let credit: String = row.get_unwrap("credit");
+            let debit: String = row.get_unwrap("debit");
+            let total = totals.entry(principal).or_default();
+            total.credit = total.credit.saturating_add(parse_amount(&credit)?);
+            total.debit = total.debit.saturating_add(parse_amount(&debit)?);
+        }
+        Ok(totals)
+    }
+}
+
+/// Read the total STX balance of each of `principals` from the MARF at `block_id`
+fn get_marf_stx_balances(
+    chainstate: &mut StacksChainState,
+    block_id: &StacksBlockId,
+    principals: &[PrincipalData],
+) -> Result<Vec<u128>, db_error> {
+    chainstate
+        .with_read_only_clarity_tx(&NULL_BURN_STATE_DB, block_id, |clarity_tx| {
+            clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                principals
+                    .iter()
+                    .map(|principal| {
+                        clarity_db
+                            .get_account_stx_balance(principal)
+                            .get_total_balance()
+                    })
+                    .collect()
+            })
+        })
+        .ok_or(db_error::NotFoundError)
+}
+
+/// Compare the STX balances implied by the indexer's events against the MARF, at the canonical
+/// indexed block at `block_height`.  Fails with `NotFoundError` if the indexer has no canonical
+/// block at that height, or the chainstate does not have it.
+pub fn audit_stx_balances(
+    indexer_conn: &Connection,
+    chainstate: &mut StacksChainState,
+    block_height: u64,
+) -> Result<BalanceAuditReport, db_error> {
+    let index_block_hash = IndexerDB::get_canonical_block_at_height(indexer_conn, block_height)?
+        .ok_or(db_error::NotFoundError)?;
+    let event_totals = IndexerDB::get_stx_event_totals(indexer_conn, block_height)?;
+
+    let mut principals = Vec::with_capacity(event_totals.len());
+    let mut totals = Vec::with_capacity(event_totals.len());
+    for (principal_str, total) in event_totals.into_iter() {
+        let principal = PrincipalData::parse(&principal_str).map_err(|_| db_error::ParseError)?;
+        principals.push(principal);
+        totals.push((principal_str, total));
+    }
+
+    let boot_block_id = StacksBlockHeader::make_index_block_hash(
+        &FIRST_BURNCHAIN_CONSENSUS_HASH,
+        &FIRST_STACKS_BLOCK_HASH,
+    );
+    let genesis_balances = get_marf_stx_balances(chainstate, &boot_block_id, &principals)?;
+    let marf_balances = get_marf_stx_balances(chainstate, &index_block_hash, &principals)?;
+
+    let mut discrepancies = vec![];
+    for (((principal, total), genesis_balance), marf_balance) in totals
+        .into_iter()
+        .zip(genesis_balances.into_iter())
+        .zip(marf_balances.into_iter())
+    {
+        let event_balance = total.balance_from(genesis_balance);
+        let marf_balance = marf_balance.to_string();
+        if event_balance != marf_balance {
+            warn!("STX balance discrepancy";
+                  "principal" => %principal,
+                  "event_balance" => %event_balance,
+                  "marf_balance" => %marf_balance,
+                  "index_block_hash" => %index_block_hash);
+            discrepancies.push(BalanceDiscrepancy {
+                principal,
+                event_balance,
+                marf_balance,
+            });
+        }
+    }
+
+    Ok(BalanceAuditReport {
+        index_block_hash,
+        block_height,
+        accounts_checked: principals.len() as u64,
+        discrepancies,
+    })
+}
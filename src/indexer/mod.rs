@@ -0,0 +1,1076 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Embedded indexer.
+//!
+//! The embedded indexer maintains a small set of indices over processed Stacks blocks in its own
+//! SQLite database, so that a node can answer the most common API-server queries without running
+//! a separate event-observer stack:
+//!   * the transaction history of an address (or contract),
+//!   * the STX and fungible token balances of an address, and
+//!   * the `print` events emitted by a contract.
+//!
+//! Blocks are fed to the indexer as they are processed, on any fork.  Indexing a block never
+//! makes it canonical; instead, the chains coordinator tells the indexer its canonical Stacks tip
+//! whenever it changes (see `update_canonical_tip()`), and the indexed blocks on the branch to
+//! that tip become the canonical ones.  All queries only consider canonical blocks.
+//!
+//! Balances are computed from the STX and fungible token events, transaction fees, and matured
+//! miner rewards seen by the indexer.  They are only complete if the indexer has seen every block
+//! since genesis; in particular, genesis account balances and STX locked by stacking are not
+//! reflected.  Use `/v2/accounts` for authoritative STX balances.
+
+pub mod audit;
+pub mod pipeline;
+pub mod rebuild;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+use rusqlite::types::ToSql;
+use rusqlite::Row;
+use rusqlite::Transaction;
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+
+use crate::burnchains::Txid;
+use crate::chainstate::stacks::db::accounts::MinerReward;
+use crate::chainstate::stacks::db::StacksHeaderInfo;
+use crate::chainstate::stacks::events::{
+    StacksTransactionEvent, StacksTransactionReceipt, TransactionOrigin,
+};
+use crate::chainstate::stacks::{StacksTransaction, TransactionPayload};
+use crate::codec::StacksMessageCodec;
+use crate::types::chainstate::StacksBlockId;
+use crate::util_lib::db::sqlite_open;
+use crate::util_lib::db::tx_begin_immediate;
+use crate::util_lib::db::Error as db_error;
+use crate::util_lib::db::{query_count, query_row, query_rows, u64_to_sql, FromColumn, FromRow};
+use clarity::vm::events::{FTEventType, NFTEventType, STXEventType};
+use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier, Value};
+use stacks_common::util::hash::to_hex;
+
+pub const INDEXERDB_VERSION: &'static str = "1";
+
+/// Asset name under which STX balances are tracked
+pub const STX_ASSET: &'static str = "STX";
+
+const INDEXERDB_INITIAL_SCHEMA: &'static [&'static str] = &[
+    r#"
+    CREATE TABLE blocks(
+        index_block_hash TEXT PRIMARY KEY,
+        parent_index_block_hash TEXT NOT NULL,
+        block_height INTEGER NOT NULL,
+        burn_block_time INTEGER NOT NULL,
+        canonical INTEGER NOT NULL
+    );"#,
+    r#"
+    CREATE TABLE transactions(
+        txid TEXT NOT NULL,
+        index_block_hash TEXT NOT NULL,
+        tx_index INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        -- hex-encoded transaction, or NULL for burnchain operations
+        raw_tx TEXT,
+        PRIMARY KEY(txid, index_block_hash)
+    );"#,
+    r#"
+    CREATE TABLE address_txs(
+        principal TEXT NOT NULL,
+        txid TEXT NOT NULL,
+        index_block_hash TEXT NOT NULL,
+        PRIMARY KEY(principal, txid, index_block_hash)
+    );"#,
+    r#"
+    -- u128 amounts are stored as decimal strings
+    CREATE TABLE balance_changes(
+        principal TEXT NOT NULL,
+        asset TEXT NOT NULL,
+        index_block_hash TEXT NOT NULL,
+        credit TEXT NOT NULL,
+        debit TEXT NOT NULL,
+        PRIMARY KEY(principal, asset, index_block_hash)
+    );"#,
+    r#"
+    CREATE TABLE contract_events(
+        contract_id TEXT NOT NULL,
+        topic TEXT NOT NULL,
+        txid TEXT NOT NULL,
+        index_block_hash TEXT NOT NULL,
+        tx_index INTEGER NOT NULL,
+        event_index INTEGER NOT NULL,
+        -- hex-encoded consensus serialization of the event's Clarity value
+        value TEXT NOT NULL,
+        PRIMARY KEY(index_block_hash, tx_index, event_index)
+    );"#,
+    "CREATE TABLE db_config(version TEXT NOT NULL);",
+];
+
+const INDEXERDB_INDEXES: &'static [&'static str] = &[
+    "CREATE INDEX IF NOT EXISTS index_blocks_by_height ON blocks(block_height, canonical);",
+    "CREATE INDEX IF NOT EXISTS index_address_txs_by_principal ON address_txs(principal);",
+    "CREATE INDEX IF NOT EXISTS index_contract_events_by_contract ON contract_events(contract_id);",
+];
+
+/// A transaction that touched an address, as reported by the indexer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedTransaction {
+    pub txid: Txid,
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub burn_block_time: u64,
+    pub tx_index: u32,
+    /// `success`, `abort_by_response`, or `abort_by_post_condition`
+    pub status: String,
+    /// Hex-encoded transaction, or `None` for burnchain operations
+    pub raw_tx: Option<String>,
+}
+
+/// An address's balance of a single asset, as reported by the indexer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedBalance {
+    /// `STX`, or the fully-qualified fungible token identifier
+    pub asset: String,
+    /// Signed decimal string.  Can only be negative if the indexer has not seen all blocks.
+    pub balance: String,
+    pub total_received: String,
+    pub total_sent: String,
+}
+
+/// A `print` event emitted by a contract, as reported by the indexer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedContractEvent {
+    pub contract_id: String,
+    pub topic: String,
+    pub txid: Txid,
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub tx_index: u32,
+    pub event_index: u32,
+    /// Hex-encoded consensus serialization of the event's Clarity value
+    pub value: String,
+}
+
+/// The indexer's view of the chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexerStatus {
+    pub tip: Option<StacksBlockId>,
+    pub tip_height: u64,
+    pub blocks_indexed: u64,
+}
+
+impl FromRow<IndexedTransaction> for IndexedTransaction {
+    fn from_row<'a>(row: &'a Row) -> Result<IndexedTransaction, db_error> {
+        let txid = Txid::from_column(row, "txid")?;
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let block_height = u64::from_column(row, "block_height")?;
+        let burn_block_time = u64::from_column(row, "burn_block_time")?;
+        let tx_index: u32 = row.get_unwrap("tx_index");
+        let status: String = row.get_unwrap("status");
+        let raw_tx: Option<String> = row.get_unwrap("raw_tx");
+        Ok(IndexedTransaction {
+            txid,
+            index_block_hash,
+            block_height,
+            burn_block_time,
+            tx_index,
+            status,
+            raw_tx,
+        })
+    }
+}
+
+impl FromRow<IndexedContractEvent> for IndexedContractEvent {
+    fn from_row<'a>(row: &'a Row) -> Result<IndexedContractEvent, db_error> {
+        let contract_id: String = row.get_unwrap("contract_id");
+        let topic: String = row.get_unwrap("topic");
+        let txid = Txid::from_column(row, "txid")?;
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let block_height = u64::from_column(row, "block_height")?;
+        let tx_index: u32 = row.get_unwrap("tx_index");
+        let event_index: u32 = row.get_unwrap("event_index");
+        let value: String = row.get_unwrap("value");
+        Ok(IndexedContractEvent {
+            contract_id,
+            topic,
+            txid,
+            index_block_hash,
+            block_height,
+            tx_index,
+            event_index,
+            value,
+        })
+    }
+}
+
+/// Credits and debits of one asset to one principal in one block
+#[derive(Debug, Clone, PartialEq, Default)]
+struct BalanceChange {
+    credit: u128,
+    debit: u128,
+}
+
+/// Balance changes in a block, keyed by (principal, asset)
+type BalanceChanges = BTreeMap<(String, String), BalanceChange>;
+
+fn credit(changes: &mut BalanceChanges, principal: &PrincipalData, asset: &str, amount: u128) {
+    let change = changes
+        .entry((principal.to_string(), asset.to_string()))
+        .or_default();
+    change.credit = change.credit.saturating_add(amount);
+}
+
+fn debit(changes: &mut BalanceChanges, principal: &PrincipalData, asset: &str, amount: u128) {
+    let change = changes
+        .entry((principal.to_string(), asset.to_string()))
+        .or_default();
+    change.debit = change.debit.saturating_add(amount);
+}
+
+fn parse_amount(amount: &str) -> Result<u128, db_error> {
+    amount.parse::<u128>().map_err(|_| db_error::ParseError)
+}
+
+/// Get the status string for a transaction receipt, as reported to event observers
+fn receipt_status(receipt: &StacksTransactionReceipt) -> &'static str {
+    if receipt.post_condition_aborted {
+        return "abort_by_post_condition";
+    }
+    match receipt.result {
+        Value::Response(ref data) if !data.committed => "abort_by_response",
+        _ => "success",
+    }
+}
+
+/// Get the principals a transaction itself refers to (as opposed to its events)
+fn tx_principals(tx: &StacksTransaction) -> Vec<PrincipalData> {
+    let mut principals = vec![tx.origin_address().into()];
+    if let Some(sponsor) = tx.sponsor_address() {
+        principals.push(sponsor.into());
+    }
+    match tx.payload {
+        TransactionPayload::TokenTransfer(ref recipient, ..) => {
+            principals.push(recipient.clone());
+        }
+        TransactionPayload::ContractCall(ref cc) => {
+            principals.push(PrincipalData::Contract(cc.contract_identifier()));
+        }
+        TransactionPayload::SmartContract(ref sc, ..) => {
+            principals.push(PrincipalData::Contract(QualifiedContractIdentifier::new(
+                tx.origin_address().into(),
+                sc.name.clone(),
+            )));
+        }
+        TransactionPayload::Coinbase(_, Some(ref recipient)) => {
+            principals.push(recipient.clone());
+        }
+        TransactionPayload::Coinbase(_, None) | TransactionPayload::PoisonMicroblock(..) => {}
+    }
+    principals
+}
+
+/// Get the principals an event refers to, and apply its balance changes
+fn apply_event(
+    event: &StacksTransactionEvent,
+    principals: &mut Vec<PrincipalData>,
+    changes: &mut BalanceChanges,
+) {
+    match event {
+        StacksTransactionEvent::SmartContractEvent(data) => {
+            principals.push(PrincipalData::Contract(data.key.0.clone()));
+        }
+        StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(data)) => {
+            debit(changes, &data.sender, STX_ASSET, data.amount);
+            credit(changes, &data.recipient, STX_ASSET, data.amount);
+            principals.push(data.sender.clone());
+            principals.push(data.recipient.clone());
+        }
+        StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(data)) => {
+            credit(changes, &data.recipient, STX_ASSET, data.amount);
+            principals.push(data.recipient.clone());
+        }
+        StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(data)) => {
+            debit(changes, &data.sender, STX_ASSET, data.amount);
+            principals.push(data.sender.clone());
+        }
+        StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(data)) => {
+            // locked STX are still part of the account's balance
+            principals.push(data.locked_address.clone());
+        }
+        StacksTransactionEvent::STXEvent(STXEventType::STXUnlockEvent(data)) => {
+            principals.push(data.unlocked_address.clone());
+        }
+        StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(data)) => {
+            let asset = data.asset_identifier.to_string();
+            debit(changes, &data.sender, &asset, data.amount);
+            credit(changes, &data.recipient, &asset, data.amount);
+            principals.push(data.sender.clone());
+            principals.push(data.recipient.clone());
+        }
+        StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(data)) => {
+            credit(
+                changes,
+                &data.recipient,
+                &data.asset_identifier.to_string(),
+                data.amount,
+            );
+            principals.push(data.recipient.clone());
+        }
+        StacksTransactionEvent::FTEvent(FTEventType::FTBurnEvent(data)) => {
+            debit(
+                changes,
+                &data.sender,
+                &data.asset_identifier.to_string(),
+                data.amount,
+            );
+            principals.push(data.sender.clone());
+        }
+        StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(data)) => {
+            principals.push(data.sender.clone());
+            principals.push(data.recipient.clone());
+        }
+        StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(data)) => {
+            principals.push(data.recipient.clone());
+        }
+        StacksTransactionEvent::NFTEvent(NFTEventType::NFTBurnEvent(data)) => {
+            principals.push(data.sender.clone());
+        }
+        StacksTransactionEvent::PoxDefunctCallEvent(_) => {}
+    }
+}
+
+pub struct IndexerDB {
+    conn: Connection,
+    readwrite: bool,
+}
+
+impl IndexerDB {
+    fn instantiate(&mut self) -> Result<(), db_error> {
+        let tx = self.tx_begin()?;
+        for row_text in INDEXERDB_INITIAL_SCHEMA {
+            tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+        }
+        tx.execute(
+            "INSERT INTO db_config (version) VALUES (?1)",
+            &[&INDEXERDB_VERSION],
+        )
+        .map_err(db_error::SqliteError)?;
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    fn add_indexes(&mut self) -> Result<(), db_error> {
+        let tx = self.tx_begin()?;
+        for row_text in INDEXERDB_INDEXES {
+            tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Open the indexer database at `path`, creating it if `readwrite` is set and it does not
+    /// exist yet.
+    pub fn connect(path: &str, readwrite: bool) -> Result<IndexerDB, db_error> {
+        let mut create_flag = false;
+        let open_flags = if fs::metadata(path).is_err() {
+            // need to create
+            if readwrite {
+                create_flag = true;
+                OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+            } else {
+                return Err(db_error::NoDBError);
+            }
+        } else {
+            // can just open
+            if readwrite {
+                OpenFlags::SQLITE_OPEN_READ_WRITE
+            } else {
+                OpenFlags::SQLITE_OPEN_READ_ONLY
+            }
+        };
+
+        let conn = sqlite_open(path, open_flags, false)?;
+        let mut db = IndexerDB { conn, readwrite };
+        if create_flag {
+            db.instantiate()?;
+        }
+        if readwrite {
+            db.add_indexes()?;
+        }
+        Ok(db)
+    }
+
+    pub fn connect_memory() -> Result<IndexerDB, db_error> {
+        let conn = Connection::open_in_memory().map_err(db_error::SqliteError)?;
+        let mut db = IndexerDB {
+            conn,
+            readwrite: true,
+        };
+        db.instantiate()?;
+        db.add_indexes()?;
+        Ok(db)
+    }
+
+    pub fn conn(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub fn tx_begin<'a>(&'a mut self) -> Result<Transaction<'a>, db_error> {
+        if !self.readwrite {
+            return Err(db_error::ReadOnly);
+        }
+        let tx = tx_begin_immediate(&mut self.conn)?;
+        Ok(tx)
+    }
+
+    /// Get the canonical tip's index block hash and height, if any block has been indexed
+    fn get_canonical_tip(conn: &Connection) -> Result<Option<(StacksBlockId, u64)>, db_error> {
+        let mut stmt = conn.prepare(
+            "SELECT index_block_hash, block_height FROM blocks WHERE canonical = 1 ORDER BY block_height DESC LIMIT 1",
+        )?;
+        let mut rows = stmt.query(NO_PARAMS)?;
+        match rows.next()? {
+            Some(row) => {
+                let tip = StacksBlockId::from_column(row, "index_block_hash")?;
+                let height: u64 = u64::from_column(row, "block_height")?;
+                Ok(Some((tip, height)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Make the given (already-indexed) block the canonical tip.  Its indexed ancestors become
+    /// canonical, and every other block above the fork point stops being canonical.
+    fn set_canonical_tip(tx: &Transaction, tip: &StacksBlockId) -> Result<(), db_error> {
+        let mut branch = vec![];
+        let mut cursor = Some(tip.clone());
+        let mut fork_height = None;
+        while let Some(block_id) = cursor.take() {
+            let mut stmt = tx.prepare(
+                "SELECT parent_index_block_hash, block_height, canonical FROM blocks WHERE index_block_hash = ?1",
+            )?;
+            let mut rows = stmt.query(&[&block_id])?;
+            let row = match rows.next()? {
+                Some(row) => row,
+                None => {
+                    // reached the first block the indexer saw on this branch
+                    break;
+                }
+            };
+            let parent = StacksBlockId::from_column(row, "parent_index_block_hash")?;
+            let height: u64 = u64::from_column(row, "block_height")?;
+            let canonical: bool = row.get_unwrap("canonical");
+            if canonical {
+                fork_height = Some(height);
+                break;
+            }
+            fork_height = Some(height.saturating_sub(1));
+            branch.push(block_id);
+            cursor = Some(parent);
+        }
+
+        if let Some(fork_height) = fork_height {
+            tx.execute(
+                "UPDATE blocks SET canonical = 0 WHERE block_height > ?1",
+                &[&u64_to_sql(fork_height)?],
+            )?;
+        }
+        for block_id in branch.iter() {
+            tx.execute(
+                "UPDATE blocks SET canonical = 1 WHERE index_block_hash = ?1",
+                &[block_id],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Make the given block the canonical tip, as decided by the chains coordinator.  Does
+    /// nothing if the block has not been indexed.
+    pub fn update_canonical_tip(&mut self, tip: &StacksBlockId) -> Result<(), db_error> {
+        let tx = self.tx_begin()?;
+        IndexerDB::set_canonical_tip(&tx, tip)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Index a processed Stacks block.  Blocks that have already been indexed are ignored.  The
+    /// block is not canonical until `update_canonical_tip()` says so.
+    pub fn index_block(
+        &mut self,
+        metadata: &StacksHeaderInfo,
+        parent: &StacksBlockId,
+        receipts: &[StacksTransactionReceipt],
+        mature_rewards: &[MinerReward],
+    ) -> Result<(), db_error> {
+        let index_block_hash = metadata.index_block_hash();
+        let tx = self.tx_begin()?;
+
+        let already_indexed = query_count(
+            &tx,
+            "SELECT COUNT(*) FROM blocks WHERE index_block_hash = ?1",
+            &[&index_block_hash],
+        )? > 0;
+        if already_indexed {
+            return Ok(());
+        }
+
+        let args: &[&dyn ToSql] = &[
+            &index_block_hash,
+            parent,
+            &u64_to_sql(metadata.stacks_block_height)?,
+            &u64_to_sql(metadata.burn_header_timestamp)?,
+        ];
+        tx.execute(
+            "INSERT INTO blocks (index_block_hash, parent_index_block_hash, block_height, burn_block_time, canonical) VALUES (?1, ?2, ?3, ?4, 0)",
+            args,
+        )?;
+
+        let mut changes = BalanceChanges::new();
+        let mut event_index: u32 = 0;
+        for receipt in receipts.iter() {
+            let txid = receipt.transaction.txid();
+            let mut principals = vec![];
+            let raw_tx = match receipt.transaction {
+                TransactionOrigin::Stacks(ref stacks_tx) => {
+                    principals.append(&mut tx_principals(stacks_tx));
+                    let fee = stacks_tx.get_tx_fee() as u128;
+                    if fee > 0 {
+                        let payer = stacks_tx
+                            .sponsor_address()
+                            .unwrap_or(stacks_tx.origin_address());
+                        debit(&mut changes, &payer.into(), STX_ASSET, fee);
+                    }
+                    Some(to_hex(&stacks_tx.serialize_to_vec()))
+                }
+                TransactionOrigin::Burn(_) => None,
+            };
+
+            // post-condition-aborted transactions report their events, but none of them happened
+            for event in receipt.events.iter() {
+                if !receipt.post_condition_aborted {
+                    apply_event(event, &mut principals, &mut changes);
+                    if let StacksTransactionEvent::SmartContractEvent(ref data) = event {
+                        let args: &[&dyn ToSql] = &[
+                            &data.key.0.to_string(),
+                            &data.key.1,
+                            &txid,
+                            &index_block_hash,
+                            &receipt.tx_index,
+                            &event_index,
+                            &to_hex(&data.value.serialize_to_vec()),
+                        ];
+                        tx.execute(
+                            "INSERT INTO contract_events (contract_id, topic, txid, index_block_hash, tx_index, event_index, value) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                            args,
+                        )?;
+                    }
+                }
+                event_index += 1;
+            }
+
+            let args: &[&dyn ToSql] = &[
+                &txid,
+                &index_block_hash,
+                &receipt.tx_index,
+                &receipt_status(receipt),
+                &raw_tx,
+            ];
+            tx.execute(
+                "INSERT OR IGNORE INTO transactions (txid, index_block_hash, tx_index, status, raw_tx) VALUES (?1, ?2, ?3, ?4, ?5)",
+                args,
+            )?;
+
+            let principals: BTreeSet<String> = principals.iter().map(|p| p.to_string()).collect();
+            for principal in principals.iter() {
+                tx.execute(
+                    "INSERT OR IGNORE INTO address_txs (principal, txid, index_block_hash) VALUES (?1, ?2, ?3)",
+                    &[principal as &dyn ToSql, &txid, &index_block_hash],
+                )?;
+            }
+        }
+
+        for reward in mature_rewards.iter() {
+            credit(&mut changes, &reward.recipient, STX_ASSET, reward.total());
+        }
+
+        for ((principal, asset), change) in changes.iter() {
+            let args: &[&dyn ToSql] = &[
+                principal,
+                asset,
+                &index_block_hash,
+                &change.credit.to_string(),
+                &change.debit.to_string(),
+            ];
+            tx.execute(
+                "INSERT INTO balance_changes (principal, asset, index_block_hash, credit, debit) VALUES (?1, ?2, ?3, ?4, ?5)",
+                args,
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get the indexer's canonical tip and how many blocks it has indexed
+    pub fn get_status(conn: &Connection) -> Result<IndexerStatus, db_error> {
+        let (tip, tip_height) = match IndexerDB::get_canonical_tip(conn)? {
+            Some((tip, tip_height)) => (Some(tip), tip_height),
+            None => (None, 0),
+        };
+        let blocks_indexed = query_count(conn, "SELECT COUNT(*) FROM blocks", NO_PARAMS)? as u64;
+        Ok(IndexerStatus {
+            tip,
+            tip_height,
+            blocks_indexed,
+        })
+    }
+
+    /// Get a page of the canonical transactions that touched `principal`, newest first, and the
+    /// total number of such transactions.
+    pub fn get_address_txs(
+        conn: &Connection,
+        principal: &PrincipalData,
+        limit: u64,
+        offset: u64,
+    ) -> Result<(Vec<IndexedTransaction>, u64), db_error> {
+        let principal = principal.to_string();
+        let total = query_count(
+            conn,
+            "SELECT COUNT(*) FROM address_txs a JOIN blocks b ON a.index_block_hash = b.index_block_hash \
+             WHERE a.principal = ?1 AND b.canonical = 1",
+            &[&principal],
+        )? as u64;
+        let args: &[&dyn ToSql] = &[&principal, &u64_to_sql(limit)?, &u64_to_sql(offset)?];
+        let txs = query_rows(
+            conn,
+            "SELECT t.txid, t.index_block_hash, b.block_height, b.burn_block_time, t.tx_index, t.status, t.raw_tx \
+             FROM address_txs a \
+             JOIN blocks b ON a.index_block_hash = b.index_block_hash \
+             JOIN transactions t ON t.txid = a.txid AND t.index_block_hash = a.index_block_hash \
+             WHERE a.principal = ?1 AND b.canonical = 1 \
+             ORDER BY b.block_height DESC, t.tx_index DESC LIMIT ?2 OFFSET ?3",
+            args,
+        )?;
+        Ok((txs, total))
+    }
+
+    /// Get the balances of every asset `principal` has held on the canonical fork
+    pub fn get_balances(
+        conn: &Connection,
+        principal: &PrincipalData,
+    ) -> Result<Vec<IndexedBalance>, db_error> {
+        let mut stmt = conn.prepare(
+            "SELECT c.asset, c.credit, c.debit FROM balance_changes c \
+             JOIN blocks b ON c.index_block_hash = b.index_block_hash \
+             WHERE c.principal = ?1 AND b.canonical = 1",
+        )?;
+        let mut rows = stmt.query(&[&principal.to_string()])?;
+        let mut totals: BTreeMap<String, BalanceChange> = BTreeMap::new();
+        while let Some(row) = rows.next()? {
+            let asset: String = row.get_unwrap("asset");
+            let credit: String = row.get_unwrap("credit");
+            let debit: String = row.get_unwrap("debit");
+            let total = totals.entry(asset).or_default();
+            total.credit = total.credit.saturating_add(parse_amount(&credit)?);
+            total.debit = total.debit.saturating_add(parse_amount(&debit)?);
+        }
+
+        Ok(totals
+            .into_iter()
+            .map(|(asset, total)| {
+                let balance = if total.credit >= total.debit {
+                    (total.credit - total.debit).to_string()
+                } else {
+                    format!("-{}", total.debit - total.credit)
+                };
+                IndexedBalance {
+                    asset,
+                    balance,
+                    total_received: total.credit.to_string(),
+                    total_sent: total.debit.to_string(),
+                }
+            })
+            .collect())
+    }
+
+    /// Get a page of the canonical `print` events emitted by `contract_id`, newest first, and the
+    /// total number of such events.
+    pub fn get_contract_events(
+        conn: &Connection,
+        contract_id: &QualifiedContractIdentifier,
+        limit: u64,
+        offset: u64,
+    ) -> Result<(Vec<IndexedContractEvent>, u64), db_error> {
+        let contract_id = contract_id.to_string();
+        let total = query_count(
+            conn,
+            "SELECT COUNT(*) FROM contract_events e JOIN blocks b ON e.index_block_hash = b.index_block_hash \
+             WHERE e.contract_id = ?1 AND b.canonical = 1",
+            &[&contract_id],
+        )? as u64;
+        let args: &[&dyn ToSql] = &[&contract_id, &u64_to_sql(limit)?, &u64_to_sql(offset)?];
+        let events = query_rows(
+            conn,
+            "SELECT e.contract_id, e.topic, e.txid, e.index_block_hash, b.block_height, e.tx_index, e.event_index, e.value \
+             FROM contract_events e \
+             JOIN blocks b ON e.index_block_hash = b.index_block_hash \
+             WHERE e.contract_id = ?1 AND b.canonical = 1 \
+             ORDER BY b.block_height DESC, e.event_index DESC LIMIT ?2 OFFSET ?3",
+            args,
+        )?;
+        Ok((events, total))
+    }
+
+    /// Is the given block indexed and on the canonical fork?
+    pub fn is_canonical(conn: &Connection, block_id: &StacksBlockId) -> Result<bool, db_error> {
+        let canonical: Option<u64> = query_row(
+            conn,
+            "SELECT canonical FROM blocks WHERE index_block_hash = ?1",
+            &[block_id],
+        )?;
+        Ok(canonical == Some(1))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::chainstate::burn::ConsensusHash;
+    use crate::chainstate::stacks::{
+        StacksPrivateKey, TokenTransferMemo, TransactionAuth, TransactionVersion,
+    };
+    use crate::types::chainstate::{StacksAddress, StacksPublicKey};
+    use clarity::vm::costs::ExecutionCost;
+    use clarity::vm::events::{STXTransferEventData, SmartContractEventData};
+    use clarity::vm::types::BuffData;
+    use stacks_common::address::AddressHashMode;
+    use stacks_common::util::hash::Hash160;
+
+    fn make_header(id: u8, height: u64) -> StacksHeaderInfo {
+        let mut header = StacksHeaderInfo::regtest_genesis();
+        header.consensus_hash = ConsensusHash([id; 20]);
+        header.stacks_block_height = height;
+        header.burn_header_timestamp = 1000 + height;
+        header
+    }
+
+    fn make_transfer(
+        privk: &StacksPrivateKey,
+        recipient: &PrincipalData,
+        amount: u64,
+        nonce: u64,
+    ) -> StacksTransactionReceipt {
+        let mut tx = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(privk).unwrap(),
+            TransactionPayload::TokenTransfer(
+                recipient.clone(),
+                amount,
+                TokenTransferMemo([0u8; 34]),
+            ),
+        );
+        tx.set_tx_fee(10);
+        tx.set_origin_nonce(nonce);
+        let sender: PrincipalData = tx.origin_address().into();
+
+        StacksTransactionReceipt {
+            tx_size: tx.tx_len(),
+            transaction: TransactionOrigin::Stacks(tx),
+            events: vec![
+                StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(
+                    STXTransferEventData {
+                        sender,
+                        recipient: recipient.clone(),
+                        amount: amount as u128,
+                        memo: BuffData { data: vec![] },
+                    },
+                )),
+                StacksTransactionEvent::SmartContractEvent(SmartContractEventData {
+                    key: (
+                        QualifiedContractIdentifier::parse(
+                            "ST000000000000000000002AMW42H.test-contract",
+                        )
+                        .unwrap(),
+                        "print".to_string(),
+                    ),
+                    value: Value::UInt(amount as u128),
+                }),
+            ],
+            post_condition_aborted: false,
+            result: Value::okay_true(),
+            stx_burned: 0,
+            contract_analysis: None,
+            execution_cost: ExecutionCost::zero(),
+            microblock_header: None,
+            tx_index: nonce as u32,
+            vm_error: None,
+            vm_error_code: None,
+        }
+    }
+
+    fn get_balance(db: &IndexerDB, principal: &PrincipalData) -> Option<String> {
+        IndexerDB::get_balances(db.conn(), principal)
+            .unwrap()
+            .into_iter()
+            .find(|balance| balance.asset == STX_ASSET)
+            .map(|balance| balance.balance)
+    }
+
+    #[test]
+    fn test_indexer_follows_canonical_fork() {
+        let mut db = IndexerDB::connect_memory().unwrap();
+        let privk = StacksPrivateKey::from_hex(
+            "42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01",
+        )
+        .unwrap();
+        let sender: PrincipalData = StacksAddress::from_public_keys(
+            26,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk)],
+        )
+        .unwrap()
+        .into();
+        let recipient: PrincipalData = StacksAddress {
+            version: 26,
+            bytes: Hash160([0x02; 20]),
+        }
+        .into();
+        let contract_id =
+            QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.test-contract")
+                .unwrap();
+
+        let genesis = StacksBlockId([0xff; 32]);
+        let block_a = make_header(1, 1);
+        let block_b = make_header(2, 2);
+        let block_b_prime = make_header(3, 2);
+        let block_c = make_header(4, 3);
+
+        let miner_reward = MinerReward {
+            address: StacksAddress {
+                version: 26,
+                bytes: Hash160([0x03; 20]),
+            },
+            recipient: recipient.clone(),
+            coinbase: 1000,
+            tx_fees_anchored: 0,
+            tx_fees_streamed_produced: 0,
+            tx_fees_streamed_confirmed: 0,
+            vtxindex: 0,
+        };
+
+        db.index_block(
+            &block_a,
+            &genesis,
+            &[make_transfer(&privk, &recipient, 100, 0)],
+            &[],
+        )
+        .unwrap();
+        db.index_block(
+            &block_b,
+            &block_a.index_block_hash(),
+            &[make_transfer(&privk, &recipient, 200, 1)],
+            &[miner_reward],
+        )
+        .unwrap();
+
+        // nothing is canonical until the coordinator says so
+        assert!(!IndexerDB::is_canonical(db.conn(), &block_a.index_block_hash()).unwrap());
+        assert_eq!(IndexerDB::get_status(db.conn()).unwrap().tip, None);
+        db.update_canonical_tip(&block_b.index_block_hash())
+            .unwrap();
+
+        let (txs, total) = IndexerDB::get_address_txs(db.conn(), &recipient, 10, 0).unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(txs[0].block_height, 2);
+        assert_eq!(txs[1].block_height, 1);
+        let (txs, total) = IndexerDB::get_address_txs(db.conn(), &sender, 1, 1).unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].index_block_hash, block_a.index_block_hash());
+        assert_eq!(txs[0].status, "success");
+
+        assert_eq!(get_balance(&db, &recipient), Some("1300".to_string()));
+        assert_eq!(get_balance(&db, &sender), Some("-320".to_string()));
+
+        let (events, total) =
+            IndexerDB::get_contract_events(db.conn(), &contract_id, 10, 0).unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(
+            events[0].value,
+            to_hex(&Value::UInt(200).serialize_to_vec())
+        );
+
+        // a sibling at the same height doesn't become canonical by arriving...
+        db.index_block(&block_b_prime, &block_a.index_block_hash(), &[], &[])
+            .unwrap();
+        assert!(IndexerDB::is_canonical(db.conn(), &block_b.index_block_hash()).unwrap());
+        assert!(!IndexerDB::is_canonical(db.conn(), &block_b_prime.index_block_hash()).unwrap());
+
+        // ...but does once the coordinator's canonical tip moves to it
+        db.update_canonical_tip(&block_b_prime.index_block_hash())
+            .unwrap();
+        assert!(!IndexerDB::is_canonical(db.conn(), &block_b.index_block_hash()).unwrap());
+        assert!(IndexerDB::is_canonical(db.conn(), &block_a.index_block_hash()).unwrap());
+        let (_, total) = IndexerDB::get_address_txs(db.conn(), &recipient, 10, 0).unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(get_balance(&db, &recipient), Some("100".to_string()));
+        let (_, total) = IndexerDB::get_contract_events(db.conn(), &contract_id, 10, 0).unwrap();
+        assert_eq!(total, 1);
+
+        // re-indexing a block is a no-op
+        db.index_block(&block_b, &block_a.index_block_hash(), &[], &[])
+            .unwrap();
+        assert!(!IndexerDB::is_canonical(db.conn(), &block_b.index_block_hash()).unwrap());
+
+        // extending the old branch brings it back
+        db.index_block(&block_c, &block_b.index_block_hash(), &[], &[])
+            .unwrap();
+        db.update_canonical_tip(&block_c.index_block_hash())
+            .unwrap();
+        assert!(IndexerDB::is_canonical(db.conn(), &block_b.index_block_hash()).unwrap());
+        assert!(!IndexerDB::is_canonical(db.conn(), &block_b_prime.index_block_hash()).unwrap());
+        assert_eq!(get_balance(&db, &recipient), Some("1300".to_string()));
+
+        let status = IndexerDB::get_status(db.conn()).unwrap();
+        assert_eq!(status.tip, Some(block_c.index_block_hash()));
+        assert_eq!(status.tip_height, 3);
+        assert_eq!(status.blocks_indexed, 4);
+
+        // the canonical tip can move back down to an ancestor
+        db.update_canonical_tip(&block_a.index_block_hash())
+            .unwrap();
+        assert!(!IndexerDB::is_canonical(db.conn(), &block_b.index_block_hash()).unwrap());
+        assert!(!IndexerDB::is_canonical(db.conn(), &block_c.index_block_hash()).unwrap());
+        assert_eq!(get_balance(&db, &recipient), Some("100".to_string()));
+    }
+
+    #[test]
+    fn test_indexer_stx_event_totals() {
+        let mut db = IndexerDB::connect_memory().unwrap();
+        let privk = StacksPrivateKey::from_hex(
+            "42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01",
+        )
+        .unwrap();
+        let sender = StacksAddress::from_public_keys(
+            26,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk)],
+        )
+        .unwrap()
+        .to_string();
+        let recipient: PrincipalData = StacksAddress {
+            version: 26,
+            bytes: Hash160([0x02; 20]),
+        }
+        .into();
+
+        let genesis = StacksBlockId([0xff; 32]);
+        let block_a = make_header(1, 1);
+        let block_b = make_header(2, 2);
+        let block_b_prime = make_header(3, 2);
+
+        db.index_block(
+            &block_a,
+            &genesis,
+            &[make_transfer(&privk, &recipient, 100, 0)],
+            &[],
+        )
+        .unwrap();
+        db.index_block(
+            &block_b,
+            &block_a.index_block_hash(),
+            &[make_transfer(&privk, &recipient, 200, 1)],
+            &[],
+        )
+        .unwrap();
+        db.update_canonical_tip(&block_b.index_block_hash())
+            .unwrap();
+
+        assert_eq!(
+            IndexerDB::get_canonical_block_at_height(db.conn(), 2).unwrap(),
+            Some(block_b.index_block_hash())
+        );
+        assert_eq!(
+            IndexerDB::get_canonical_block_at_height(db.conn(), 3).unwrap(),
+            None
+        );
+
+        let totals = IndexerDB::get_stx_event_totals(db.conn(), 1).unwrap();
+        assert_eq!(totals[&recipient.to_string()].credit, 100);
+        assert_eq!(totals[&sender].debit, 110);
+        assert_eq!(totals[&sender].balance_from(1000), "890");
+
+        let totals = IndexerDB::get_stx_event_totals(db.conn(), 2).unwrap();
+        assert_eq!(totals[&recipient.to_string()].credit, 300);
+        assert_eq!(totals[&sender].debit, 320);
+        assert_eq!(totals[&sender].balance_from(300), "-20");
+
+        // only the canonical fork is summed
+        db.index_block(&block_b_prime, &block_a.index_block_hash(), &[], &[])
+            .unwrap();
+        db.update_canonical_tip(&block_b_prime.index_block_hash())
+            .unwrap();
+        let totals = IndexerDB::get_stx_event_totals(db.conn(), 2).unwrap();
+        assert_eq!(totals[&recipient.to_string()].credit, 100);
+        assert_eq!(totals[&sender].debit, 110);
+    }
+
+    #[test]
+    fn test_indexer_backfill_canonical() {
+        let mut db = IndexerDB::connect_memory().unwrap();
+        let genesis = StacksBlockId([0xff; 32]);
+        let block_a = make_header(1, 1);
+        let block_a_prime = make_header(2, 1);
+        let block_b = make_header(3, 2);
+
+        // the indexer was enabled at height 2, so block_b is the canonical tip...
+        db.index_block(&block_b, &block_a.index_block_hash(), &[], &[])
+            .unwrap();
+        db.update_canonical_tip(&block_b.index_block_hash())
+            .unwrap();
+        assert!(IndexerDB::is_indexed(db.conn(), &block_b.index_block_hash()).unwrap());
+        assert!(!IndexerDB::is_indexed(db.conn(), &block_a.index_block_hash()).unwrap());
+
+        // ...and blocks indexed below it afterwards do not become canonical on their own
+        db.index_block(&block_a_prime, &genesis, &[], &[]).unwrap();
+        db.index_block(&block_a, &genesis, &[], &[]).unwrap();
+        assert_eq!(
+            IndexerDB::get_canonical_block_at_height(db.conn(), 1).unwrap(),
+            None
+        );
+
+        db.set_canonical_at_height(1, &block_a.index_block_hash())
+            .unwrap();
+        assert_eq!(
+            IndexerDB::get_canonical_block_at_height(db.conn(), 1).unwrap(),
+            Some(block_a.index_block_hash())
+        );
+        assert_eq!(
+            IndexerDB::get_canonical_block_at_height(db.conn(), 2).unwrap(),
+            Some(block_b.index_block_hash())
+        );
+
+        db.set_canonical_at_height(1, &block_a_prime.index_block_hash())
+            .unwrap();
+        assert_eq!(
+            IndexerDB::get_canonical_block_at_height(db.conn(), 1).unwrap(),
+            Some(block_a_prime.index_block_hash())
+        );
+        assert_eq!(IndexerDB::get_status(db.conn()).unwrap().blocks_indexed, 3);
+    }
+}
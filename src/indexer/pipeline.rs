@@ -0,0 +1,493 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The pipeline that feeds processed blocks to sidecar indexes.
+//!
+//! Each index implements the `Indexer` trait and is registered with an `IndexerPipeline`, which
+//! the chains coordinator drives as it processes blocks.  The pipeline keeps a checkpoint per
+//! indexer -- the last block it indexed successfully -- so a failing indexer does not hold up
+//! the others, and can be told where to resume from.
+
+use std::fs;
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+
+use crate::chainstate::stacks::db::accounts::MinerReward;
+use crate::chainstate::stacks::db::StacksHeaderInfo;
+use crate::chainstate::stacks::events::StacksTransactionReceipt;
+use crate::chainstate::stacks::StacksMicroblockHeader;
+use crate::types::chainstate::StacksBlockId;
+use crate::util_lib::db::sqlite_open;
+use crate::util_lib::db::Error as db_error;
+use crate::util_lib::db::{u64_to_sql, FromColumn};
+
+use super::IndexerDB;
+
+const INDEXER_CHECKPOINTS_SCHEMA: &'static str = r#"
+    CREATE TABLE IF NOT EXISTS indexer_checkpoints(
+        name TEXT PRIMARY KEY,
+        index_block_hash TEXT NOT NULL,
+        block_height INTEGER NOT NULL
+    );"#;
+
+/// A processed Stacks block, as handed to each indexer
+pub struct IndexerBlock<'a> {
+    pub header: &'a StacksHeaderInfo,
+    pub parent: &'a StacksBlockId,
+    /// Receipts for the block's transactions, and for the microblock transactions it confirmed
+    pub receipts: &'a [StacksTransactionReceipt],
+    pub matured_rewards: &'a [MinerReward],
+}
+
+/// A sidecar index built from processed blocks.
+pub trait Indexer: Send {
+    /// Unique name of this indexer, under which its checkpoint is stored
+    fn name(&self) -> &str;
+
+    /// Index a processed block.  Called once per processed block, on any Stacks fork, after
+    /// `on_microblock()` has been called for each microblock it confirmed.
+    fn on_block(&mut self, block: &IndexerBlock) -> Result<(), db_error>;
+
+    /// The chains coordinator's canonical Stacks tip is now `tip`.  Called every time it
+    /// changes, whether or not the new tip descends from the old one.  The block has already been
+    /// passed to `on_block()`, if it was processed while this indexer was registered.
+    fn on_canonical_tip(&mut self, _tip: &StacksBlockId) -> Result<(), db_error> {
+        Ok(())
+    }
+
+    /// The canonical Stacks tip moved from `old_tip` to `new_tip`, which does not descend from
+    /// it.  Called after `on_canonical_tip()`.  Both blocks have already been passed to
+    /// `on_block()`, if they were processed while this indexer was registered.
+    fn on_reorg(
+        &mut self,
+        _old_tip: &StacksBlockId,
+        _new_tip: &StacksBlockId,
+    ) -> Result<(), db_error> {
+        Ok(())
+    }
+
+    /// A processed block with parent `parent` confirmed `microblock`, whose transactions produced
+    /// `receipts`.
+    fn on_microblock(
+        &mut self,
+        _parent: &StacksBlockId,
+        _microblock: &StacksMicroblockHeader,
+        _receipts: &[StacksTransactionReceipt],
+    ) -> Result<(), db_error> {
+        Ok(())
+    }
+}
+
+/// The last block an indexer indexed successfully
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexerCheckpoint {
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+}
+
+pub struct IndexerPipeline {
+    conn: Connection,
+    indexers: Vec<Box<dyn Indexer>>,
+    canonical_tip: Option<StacksBlockId>,
+}
+
+impl IndexerPipeline {
+    fn from_conn(conn: Connection) -> Result<IndexerPipeline, db_error> {
+        conn.execute_batch(INDEXER_CHECKPOINTS_SCHEMA)?;
+        Ok(IndexerPipeline {
+            conn,
+            indexers: vec![],
+            canonical_tip: None,
+        })
+    }
+
+    /// Open the checkpoint database at `path`, creating it if it does not exist yet.
+    pub fn open(path: &str) -> Result<IndexerPipeline, db_error> {
+        let open_flags = if fs::metadata(path).is_err() {
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+        };
+        let conn = sqlite_open(path, open_flags, false)?;
+        IndexerPipeline::from_conn(conn)
+    }
+
+    pub fn open_memory() -> Result<IndexerPipeline, db_error> {
+        let conn = Connection::open_in_memory()?;
+        IndexerPipeline::from_conn(conn)
+    }
+
+    /// Add an indexer to the pipeline.  Fails if one with the same name is already registered.
+    pub fn register(&mut self, indexer: Box<dyn Indexer>) -> Result<(), db_error> {
+        if self.indexers.iter().any(|i| i.name() == indexer.name()) {
+            return Err(db_error::Other(format!(
+                "Indexer '{}' is already registered",
+                indexer.name()
+            )));
+        }
+        self.indexers.push(indexer);
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indexers.is_empty()
+    }
+
+    /// Get the last block the named indexer indexed successfully, if any
+    pub fn get_checkpoint(&self, name: &str) -> Result<Option<IndexerCheckpoint>, db_error> {
+        self.conn
+            .query_row(
+                "SELECT index_block_hash, block_height FROM indexer_checkpoints WHERE name = ?1",
+                &[name],
+                |row| {
+                    Ok(IndexerCheckpoint {
+                        index_block_hash: StacksBlockId::from_column(row, "index_block_hash")
+                            .expect("FATAL: corrupt indexer checkpoint"),
+                        block_height: u64::from_column(row, "block_height")
+                            .expect("FATAL: corrupt indexer checkpoint"),
+                    })
+                },
+            )
+            .optional()
+            .map_err(db_error::SqliteError)
+    }
+
+    fn set_checkpoint(
+        conn: &Connection,
+        name: &str,
+        index_block_hash: &StacksBlockId,
+        block_height: u64,
+    ) -> Result<(), db_error> {
+        conn.execute(
+            "INSERT OR REPLACE INTO indexer_checkpoints (name, index_block_hash, block_height) VALUES (?1, ?2, ?3)",
+            rusqlite::params![name, index_block_hash, u64_to_sql(block_height)?],
+        )?;
+        Ok(())
+    }
+
+    /// Hand a processed block to every indexer: first each microblock it confirmed, then the
+    /// block itself.  An indexer that fails is logged, and its checkpoint is left where it was.
+    pub fn announce_block(&mut self, block: &IndexerBlock) {
+        let index_block_hash = block.header.index_block_hash();
+
+        // receipts for the same confirmed microblock are contiguous
+        let mut microblocks: Vec<(&StacksMicroblockHeader, &[StacksTransactionReceipt])> = vec![];
+        let mut start = 0;
+        for (i, receipt) in block.receipts.iter().enumerate() {
+            let next = block.receipts.get(i + 1);
+            let group_ends = match next {
+                Some(next) => next.microblock_header != receipt.microblock_header,
+                None => true,
+            };
+            if group_ends {
+                if let Some(ref microblock) = receipt.microblock_header {
+                    microblocks.push((microblock, &block.receipts[start..i + 1]));
+                }
+                start = i + 1;
+            }
+        }
+
+        for indexer in self.indexers.iter_mut() {
+            let result = microblocks
+                .iter()
+                .try_for_each(|(microblock, receipts)| {
+                    indexer.on_microblock(block.parent, microblock, receipts)
+                })
+                .and_then(|_| indexer.on_block(block))
+                .and_then(|_| {
+                    IndexerPipeline::set_checkpoint(
+                        &self.conn,
+                        indexer.name(),
+                        &index_block_hash,
+                        block.header.stacks_block_height,
+                    )
+                });
+            if let Err(e) = result {
+                warn!("Indexer failed to index block";
+                      "indexer" => indexer.name(),
+                      "index_block_hash" => %index_block_hash,
+                      "stacks_height" => block.header.stacks_block_height,
+                      "error" => ?e);
+            }
+        }
+    }
+
+    /// Record the new canonical Stacks tip, and tell every indexer if it changed.  If it does not
+    /// descend from the previous one, as decided by `descends_from`, every indexer is also told
+    /// about the reorg.
+    pub fn update_canonical_tip<F>(&mut self, new_tip: &StacksBlockId, descends_from: F)
+    where
+        F: FnOnce(&StacksBlockId) -> bool,
+    {
+        let old_tip = self.canonical_tip.replace(new_tip.clone());
+        if old_tip.as_ref() == Some(new_tip) {
+            return;
+        }
+
+        for indexer in self.indexers.iter_mut() {
+            if let Err(e) = indexer.on_canonical_tip(new_tip) {
+                warn!("Indexer failed to update its canonical tip";
+                      "indexer" => indexer.name(),
+                      "new_tip" => %new_tip,
+                      "error" => ?e);
+            }
+        }
+
+        let old_tip = match old_tip {
+            Some(old_tip) => old_tip,
+            None => return,
+        };
+        if descends_from(&old_tip) {
+            return;
+        }
+
+        debug!(
+            "Indexers: canonical Stacks tip reorged from {} to {}",
+            &old_tip, new_tip
+        );
+        for indexer in self.indexers.iter_mut() {
+            if let Err(e) = indexer.on_reorg(&old_tip, new_tip) {
+                warn!("Indexer failed to handle reorg";
+                      "indexer" => indexer.name(),
+                      "old_tip" => %old_tip,
+                      "new_tip" => %new_tip,
+                      "error" => ?e);
+            }
+        }
+    }
+}
+
+impl Indexer for IndexerDB {
+    fn name(&self) -> &str {
+        "embedded"
+    }
+
+    fn on_block(&mut self, block: &IndexerBlock) -> Result<(), db_error> {
+        self.index_block(
+            block.header,
+            block.parent,
+            block.receipts,
+            block.matured_rewards,
+        )
+    }
+
+    fn on_canonical_tip(&mut self, tip: &StacksBlockId) -> Result<(), db_error> {
+        self.update_canonical_tip(tip)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    use crate::chainstate::burn::ConsensusHash;
+    use crate::chainstate::stacks::events::TransactionOrigin;
+    use crate::chainstate::stacks::{
+        CoinbasePayload, StacksPrivateKey, StacksTransaction, TransactionAuth, TransactionPayload,
+        TransactionVersion,
+    };
+    use clarity::vm::costs::ExecutionCost;
+    use clarity::vm::Value;
+    use stacks_common::types::chainstate::BlockHeaderHash;
+    use stacks_common::util::hash::Sha512Trunc256Sum;
+
+    /// Records every callback it sees, and fails on_block() for one given height
+    struct RecordingIndexer {
+        name: String,
+        fail_at: Option<u64>,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Indexer for RecordingIndexer {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn on_block(&mut self, block: &IndexerBlock) -> Result<(), db_error> {
+            if self.fail_at == Some(block.header.stacks_block_height) {
+                return Err(db_error::Other("injected failure".into()));
+            }
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("block {}", block.header.stacks_block_height));
+            Ok(())
+        }
+
+        fn on_canonical_tip(&mut self, tip: &StacksBlockId) -> Result<(), db_error> {
+            self.log.lock().unwrap().push(format!("tip {}", tip));
+            Ok(())
+        }
+
+        fn on_reorg(
+            &mut self,
+            old_tip: &StacksBlockId,
+            new_tip: &StacksBlockId,
+        ) -> Result<(), db_error> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("reorg {} {}", old_tip, new_tip));
+            Ok(())
+        }
+
+        fn on_microblock(
+            &mut self,
+            _parent: &StacksBlockId,
+            microblock: &StacksMicroblockHeader,
+            receipts: &[StacksTransactionReceipt],
+        ) -> Result<(), db_error> {
+            self.log.lock().unwrap().push(format!(
+                "microblock {} {}",
+                microblock.sequence,
+                receipts.len()
+            ));
+            Ok(())
+        }
+    }
+
+    fn make_header(id: u8, height: u64) -> StacksHeaderInfo {
+        let mut header = StacksHeaderInfo::regtest_genesis();
+        header.consensus_hash = ConsensusHash([id; 20]);
+        header.stacks_block_height = height;
+        header
+    }
+
+    fn make_receipt(microblock: Option<&StacksMicroblockHeader>) -> StacksTransactionReceipt {
+        let tx = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&StacksPrivateKey::new()).unwrap(),
+            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), None),
+        );
+        StacksTransactionReceipt {
+            tx_size: tx.tx_len(),
+            transaction: TransactionOrigin::Stacks(tx),
+            events: vec![],
+            post_condition_aborted: false,
+            result: Value::okay_true(),
+            stx_burned: 0,
+            contract_analysis: None,
+            execution_cost: ExecutionCost::zero(),
+            microblock_header: microblock.cloned(),
+            tx_index: 0,
+            vm_error: None,
+            vm_error_code: None,
+        }
+    }
+
+    #[test]
+    fn test_pipeline_checkpoints_and_callbacks() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut pipeline = IndexerPipeline::open_memory().unwrap();
+        pipeline
+            .register(Box::new(RecordingIndexer {
+                name: "good".into(),
+                fail_at: None,
+                log: log.clone(),
+            }))
+            .unwrap();
+        pipeline
+            .register(Box::new(RecordingIndexer {
+                name: "flaky".into(),
+                fail_at: Some(2),
+                log: Arc::new(Mutex::new(vec![])),
+            }))
+            .unwrap();
+        assert!(pipeline
+            .register(Box::new(RecordingIndexer {
+                name: "good".into(),
+                fail_at: None,
+                log: Arc::new(Mutex::new(vec![])),
+            }))
+            .is_err());
+
+        let mut mblock_0 = StacksMicroblockHeader::first_unsigned(
+            &BlockHeaderHash([1u8; 32]),
+            &Sha512Trunc256Sum([0u8; 32]),
+        );
+        mblock_0.sequence = 0;
+        let mut mblock_1 = mblock_0.clone();
+        mblock_1.sequence = 1;
+
+        let header_1 = make_header(1, 1);
+        let header_2 = make_header(2, 2);
+        let block_1 = header_1.index_block_hash();
+        let block_2 = header_2.index_block_hash();
+
+        pipeline.announce_block(&IndexerBlock {
+            header: &header_1,
+            parent: &StacksBlockId([0u8; 32]),
+            receipts: &[make_receipt(None)],
+            matured_rewards: &[],
+        });
+        pipeline.update_canonical_tip(&block_1, |_| panic!("no previous tip"));
+
+        let receipts = vec![
+            make_receipt(None),
+            make_receipt(Some(&mblock_0)),
+            make_receipt(Some(&mblock_0)),
+            make_receipt(Some(&mblock_1)),
+        ];
+        pipeline.announce_block(&IndexerBlock {
+            header: &header_2,
+            parent: &block_1,
+            receipts: &receipts,
+            matured_rewards: &[],
+        });
+        pipeline.update_canonical_tip(&block_2, |old_tip| old_tip == &block_1);
+
+        // a sibling of block 2 becomes canonical
+        let header_2b = make_header(3, 2);
+        let block_2b = header_2b.index_block_hash();
+        pipeline.update_canonical_tip(&block_2b, |_| false);
+
+        // an unchanged tip is not announced again
+        pipeline.update_canonical_tip(&block_2b, |_| panic!("tip did not change"));
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "block 1".to_string(),
+                format!("tip {}", &block_1),
+                "microblock 0 2".to_string(),
+                "microblock 1 1".to_string(),
+                "block 2".to_string(),
+                format!("tip {}", &block_2),
+                format!("tip {}", &block_2b),
+                format!("reorg {} {}", &block_2, &block_2b),
+            ]
+        );
+
+        assert_eq!(
+            pipeline.get_checkpoint("good").unwrap(),
+            Some(IndexerCheckpoint {
+                index_block_hash: block_2.clone(),
+                block_height: 2
+            })
+        );
+        // the failing indexer stays at the last block it indexed
+        assert_eq!(
+            pipeline.get_checkpoint("flaky").unwrap(),
+            Some(IndexerCheckpoint {
+                index_block_hash: block_1.clone(),
+                block_height: 1
+            })
+        );
+        assert_eq!(pipeline.get_checkpoint("unknown").unwrap(), None);
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Rebuilding the embedded indexer from the chainstate.
+//!
+//! The indexer normally only sees the blocks a node processes while it is enabled.  To index
+//! blocks the node processed earlier, each canonical block is re-executed on top of its parent's
+//! state to regenerate its receipts (see `StacksChainState::replay_processed_block()`), and fed
+//! to the indexer exactly as if it had just been processed.  No network re-sync is needed, and
+//! nothing is written to the chainstate.
+//!
+//! Blocks the indexer already has are not re-executed, so an interrupted rebuild can simply be
+//! started again and it will pick up where it stopped.
+
+use rusqlite::Connection;
+
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::chainstate::stacks::Error as ChainstateError;
+use crate::types::chainstate::StacksBlockId;
+use crate::util_lib::db::Error as db_error;
+use crate::util_lib::db::{query_count, u64_to_sql};
+
+use super::IndexerDB;
+
+/// Progress of a rebuild over a range of canonical Stacks block heights
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RebuildProgress {
+    pub start_height: u64,
+    pub end_height: u64,
+    /// The next height to index.  A rebuild that stopped early resumes from here.
+    pub next_height: u64,
+    /// Blocks that were re-executed and indexed
+    pub blocks_indexed: u64,
+    /// Blocks that the indexer already had, and so were not re-executed
+    pub blocks_skipped: u64,
+}
+
+impl RebuildProgress {
+    /// Has every height in the range been indexed?
+    pub fn is_finished(&self) -> bool {
+        self.next_height > self.end_height
+    }
+}
+
+impl IndexerDB {
+    /// Has the given block been indexed, on any fork?
+    pub fn is_indexed(conn: &Connection, block_id: &StacksBlockId) -> Result<bool, db_error> {
+        Ok(query_count(
+            conn,
+            "SELECT COUNT(*) FROM blocks WHERE index_block_hash = ?1",
+            &[block_id],
+        )? > 0)
+    }
+
+    /// Make the given (already-indexed) block the canonical block at `block_height`, and every
+    /// other block at that height non-canonical.  Used when indexing blocks below the canonical
+    /// tip, which `index_block()` does not make canonical on its own.
+    pub fn set_canonical_at_height(
+        &mut self,
+        block_height: u64,
+        block_id: &StacksBlockId,
+    ) -> Result<(), db_error> {
+        let tx = self.tx_begin()?;
+        tx.execute(
+            "UPDATE blocks SET canonical = (index_block_hash = ?1) WHERE block_height = ?2",
+            rusqlite::params![block_id, u64_to_sql(block_height)?],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn chainstate_error(e: ChainstateError) -> db_error {
+    match e {
+        ChainstateError::DBError(e) => e,
+        e => db_error::Other(format!("{:?}", &e)),
+    }
+}
+
+/// Index the canonical Stacks blocks from `start_height` up to and including `end_height`
+/// (which defaults to, and is capped at, the height of the canonical Stacks tip).
+/// `on_progress` is called after each height; if it returns false, the rebuild stops there.
+/// Returns how far the rebuild got.  Fails with `NotFoundError` if there is no canonical Stacks
+/// tip, or a canonical block's data is not available to re-execute.
+pub fn rebuild_index<F>(
+    indexer: &mut IndexerDB,
+    chainstate: &mut StacksChainState,
+    sortdb: &SortitionDB,
+    start_height: u64,
+    end_height: Option<u64>,
+    mut on_progress: F,
+) -> Result<RebuildProgress, db_error>
+where
+    F: FnMut(&RebuildProgress) -> bool,
+{
+    // the boot block has no receipts, and is never indexed
+    let start_height = start_height.max(1);
+
+    let (consensus_hash, block_hash) =
+        SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())?;
+    let tip = StacksBlockId::new(&consensus_hash, &block_hash);
+    let tip_height =
+        StacksChainState::get_stacks_block_header_info_by_index_block_hash(chainstate.db(), &tip)
+            .map_err(chainstate_error)?
+            .ok_or(db_error::NotFoundError)?
+            .stacks_block_height;
+    let end_height = end_height.unwrap_or(tip_height).min(tip_height);
+
+    let mut progress = RebuildProgress {
+        start_height,
+        end_height,
+        next_height: start_height,
+        blocks_indexed: 0,
+        blocks_skipped: 0,
+    };
+
+    while !progress.is_finished() {
+        let height = progress.next_height;
+        let block_id = chainstate
+            .index_conn()
+            .map_err(chainstate_error)?
+            .get_ancestor_block_hash(height, &tip)?
+            .ok_or(db_error::NotFoundError)?;
+
+        if IndexerDB::is_indexed(indexer.conn(), &block_id)? {
+            progress.blocks_skipped += 1;
+        } else {
+            let replayed = chainstate
+                .replay_processed_block(&sortdb.index_conn(), &block_id)
+                .map_err(chainstate_error)?
+                .ok_or_else(|| {
+                    warn!(
+                        "Indexer: block {} is not available to re-execute",
+                        &block_id
+                    );
+                    db_error::NotFoundError
+                })?;
+            indexer.index_block(
+                &replayed.epoch_receipt.header,
+                &replayed.parent_index_hash,
+                &replayed.epoch_receipt.tx_receipts,
+                &replayed.epoch_receipt.matured_rewards,
+            )?;
+            progress.blocks_indexed += 1;
+        }
+        indexer.set_canonical_at_height(height, &block_id)?;
+
+        progress.next_height = height + 1;
+        if !on_progress(&progress) {
+            break;
+        }
+    }
+
+    Ok(progress)
+}
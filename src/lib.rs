@@ -114,6 +114,10 @@ pub mod monitoring;
 
 pub mod cost_estimates;
 
+pub mod indexer;
+
+pub mod embed;
+
 pub mod clarity_cli;
 
 // set via _compile-time_ envars
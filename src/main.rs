@@ -33,6 +33,7 @@ extern crate slog;
 
 use std::io;
 use std::io::prelude::*;
+use std::net::ToSocketAddrs;
 use std::process;
 use std::thread;
 use std::{collections::HashMap, env};
@@ -57,9 +58,11 @@ use blockstack_lib::chainstate::burn::ConsensusHash;
 use blockstack_lib::chainstate::stacks::db::blocks::DummyEventDispatcher;
 use blockstack_lib::chainstate::stacks::db::blocks::StagingBlock;
 use blockstack_lib::chainstate::stacks::db::ChainStateBootData;
+use blockstack_lib::chainstate::stacks::index::file::TrieFile;
 use blockstack_lib::chainstate::stacks::index::marf::MARFOpenOpts;
 use blockstack_lib::chainstate::stacks::index::marf::MarfConnection;
 use blockstack_lib::chainstate::stacks::index::marf::MARF;
+use blockstack_lib::chainstate::stacks::index::storage::TrieFileStorage;
 use blockstack_lib::chainstate::stacks::index::ClarityMarfTrieId;
 use blockstack_lib::chainstate::stacks::miner::*;
 use blockstack_lib::chainstate::stacks::StacksBlockHeader;
@@ -71,17 +74,20 @@ use blockstack_lib::clarity_cli::vm_execute;
 use blockstack_lib::codec::StacksMessageCodec;
 use blockstack_lib::core::*;
 use blockstack_lib::cost_estimates::metrics::UnitMetric;
+use blockstack_lib::net::crawl::{Crawler, CrawlerConfig};
 use blockstack_lib::net::relay::Relayer;
 use blockstack_lib::net::{db::LocalPeer, p2p::PeerNetwork, PeerAddress};
 use blockstack_lib::types::chainstate::StacksAddress;
 use blockstack_lib::types::chainstate::{
     BlockHeaderHash, BurnchainHeaderHash, PoxId, StacksBlockId,
 };
+use blockstack_lib::types::NetworkId;
 use blockstack_lib::util::get_epoch_time_ms;
 use blockstack_lib::util::hash::{hex_bytes, to_hex};
 use blockstack_lib::util::log;
 use blockstack_lib::util::retry::LogReader;
 use blockstack_lib::util::sleep_ms;
+use blockstack_lib::util::vrf::VRFPublicKey;
 use blockstack_lib::util_lib::strings::UrlString;
 use blockstack_lib::{
     burnchains::{db::BurnchainBlockData, PoxConstants},
@@ -532,6 +538,138 @@ Given a <working-dir>, obtain a 2100 header hash block inventory (with an empty
         process::exit(0);
     }
 
+    if argv[1] == "crawl" {
+        if argv.len() < 4 {
+            eprintln!(
+                "Usage: {} crawl <mainnet|testnet> <seed-host:port> [seed-host:port...]
+
+Handshake with the given seed peers, then with every peer reachable through their neighbors,
+without syncing any blocks.  Prints what was learned about each peer as JSON.
+",
+                argv[0]
+            );
+            process::exit(1);
+        }
+
+        let config = match argv[2].as_str() {
+            "mainnet" => CrawlerConfig::mainnet(),
+            "testnet" => CrawlerConfig::testnet(),
+            _ => {
+                eprintln!("Unknown network '{}'", &argv[2]);
+                process::exit(1);
+            }
+        };
+
+        let mut seeds = vec![];
+        for seed in argv[3..].iter() {
+            let addrs = seed
+                .to_socket_addrs()
+                .expect(&format!("Failed to resolve '{}'", seed));
+            seeds.extend(addrs);
+        }
+
+        let peers = Crawler::new(config).crawl(&seeds);
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&peers).expect("Failed to serialize crawl results")
+        );
+        process::exit(0);
+    }
+
+    if argv[1] == "clear-banned-leader-keys" {
+        if argv.len() < 4 {
+            eprintln!(
+                "Usage: {} clear-banned-leader-keys <mainnet|testnet> <chainstate-dir> [vrf-public-key]
+
+Lift the ban on the given leader key, or on all leader keys if none is given.  Leader keys are
+banned when their miners produce consensus-invalid blocks.  Stop the node before running this.
+",
+                argv[0]
+            );
+            process::exit(1);
+        }
+
+        let (mainnet, chain_id) = match argv[2].as_str() {
+            "mainnet" => (true, CHAIN_ID_MAINNET),
+            "testnet" => (false, CHAIN_ID_TESTNET),
+            _ => {
+                eprintln!("Unknown network '{}'", &argv[2]);
+                process::exit(1);
+            }
+        };
+        let (mut chain_state, _) = StacksChainState::open(mainnet, chain_id, &argv[3], None)
+            .expect("Failed to open stacks chain state");
+
+        let tx = chain_state
+            .index_tx_begin()
+            .expect("Failed to begin chainstate transaction");
+        match argv.get(4) {
+            Some(public_key_hex) => {
+                let public_key = VRFPublicKey::from_hex(public_key_hex)
+                    .expect(&format!("Invalid VRF public key '{}'", public_key_hex));
+                if StacksChainState::clear_banned_leader_key(&tx, &public_key)
+                    .expect("Failed to clear banned leader key")
+                {
+                    println!("Cleared ban on leader key {}", public_key_hex);
+                } else {
+                    println!("Leader key {} is not banned", public_key_hex);
+                }
+            }
+            None => {
+                let num_cleared = StacksChainState::clear_banned_leader_keys(&tx)
+                    .expect("Failed to clear banned leader keys");
+                println!("Cleared {} banned leader key(s)", num_cleared);
+            }
+        }
+        tx.commit()
+            .expect("Failed to commit chainstate transaction");
+        process::exit(0);
+    }
+
+    if argv[1] == "requeue-staging-block" {
+        if argv.len() < 5 {
+            eprintln!(
+                "Usage: {} requeue-staging-block <mainnet|testnet> <chainstate-dir> <index-block-hash>
+
+Reconsider an unprocessed staging block for processing.  This repairs a block whose parent was
+processed, but which was never marked as attachable.  Stop the node before running this.
+",
+                argv[0]
+            );
+            process::exit(1);
+        }
+
+        let (mainnet, chain_id) = match argv[2].as_str() {
+            "mainnet" => (true, CHAIN_ID_MAINNET),
+            "testnet" => (false, CHAIN_ID_TESTNET),
+            _ => {
+                eprintln!("Unknown network '{}'", &argv[2]);
+                process::exit(1);
+            }
+        };
+        let index_block_hash = StacksBlockId::from_hex(&argv[4])
+            .expect(&format!("Invalid index block hash '{}'", &argv[4]));
+        let (mut chain_state, _) = StacksChainState::open(mainnet, chain_id, &argv[3], None)
+            .expect("Failed to open stacks chain state");
+
+        let tx = chain_state
+            .write_tx_begin()
+            .expect("Failed to begin chainstate transaction");
+        match StacksChainState::requeue_staging_block(&tx, &index_block_hash)
+            .expect("Failed to requeue staging block")
+        {
+            Some(status) => {
+                println!("Staging block {} is now {:?}", &index_block_hash, &status);
+            }
+            None => {
+                println!("Block {} is not in the staging queue", &index_block_hash);
+            }
+        }
+        tx.commit()
+            .expect("Failed to commit chainstate transaction");
+        process::exit(0);
+    }
+
     if argv[1] == "can-download-microblock" {
         if argv.len() < 3 {
             eprintln!(
@@ -954,6 +1092,41 @@ simulating a miner.
         return;
     }
 
+    if argv[1] == "marf-verify" {
+        if argv.len() < 3 {
+            eprintln!(
+                "Usage: {} marf-verify <marf-db-path> [--repair]
+
+Recompute the hash of every node in every confirmed trie of the given MARF, and report any
+that do not match what is stored.  With --repair, overwrite mismatched hashes.
+",
+                argv[0]
+            );
+            process::exit(1);
+        }
+        let path = &argv[2];
+        let repair = argv.len() > 3 && argv[3] == "--repair";
+
+        let mut marf_opts = MARFOpenOpts::default();
+        marf_opts.external_blobs = TrieFile::exists(path).expect("Failed to stat MARF blobs");
+        let mut storage =
+            TrieFileStorage::<StacksBlockId>::open(path, marf_opts).expect("Failed to open MARF");
+        let report = storage
+            .verify_integrity(repair)
+            .expect("Failed to verify MARF");
+        for fault in report.faults.iter() {
+            println!("{:?}", fault);
+        }
+        println!(
+            "Checked {} tries ({} nodes): {} faults, {} unrepaired",
+            report.tries_checked,
+            report.nodes_checked,
+            report.faults.len(),
+            report.num_unrepaired()
+        );
+        process::exit(if report.num_unrepaired() == 0 { 0 } else { 1 });
+    }
+
     if argv[1] == "get-ancestors" {
         let path = &argv[2];
         let tip = BlockHeaderHash::from_hex(&argv[3]).unwrap();
@@ -993,10 +1166,66 @@ simulating a miner.
     }
 
     if argv[1] == "docgen_boot" {
-        println!(
-            "{}",
-            blockstack_lib::chainstate::stacks::boot::docs::make_json_boot_contracts_reference()
-        );
+        use blockstack_lib::chainstate::stacks::boot::docs as boot_docs;
+
+        // report every boot contract which could not be documented, not just the first
+        fn unwrap_docs<T, E: std::fmt::Display>(docs_result: Result<T, Vec<E>>) -> T {
+            docs_result.unwrap_or_else(|errors| {
+                for e in errors {
+                    eprintln!("Failed to document boot contract {}", e);
+                }
+                process::exit(1);
+            })
+        }
+
+        if argv.len() < 3 {
+            println!(
+                "{}",
+                unwrap_docs(boot_docs::make_json_boot_contracts_reference())
+            );
+            return;
+        }
+        let include_private = argv.len() == 5 && argv[4] == "--private";
+        if argv.len() < 4
+            || (argv.len() > 4 && !include_private)
+            || (argv[2] != "--json" && argv[2] != "--markdown")
+        {
+            eprintln!(
+                "Usage: {} docgen_boot [--json EPOCH | --markdown EPOCH] [--private]",
+                argv[0]
+            );
+            process::exit(1);
+        }
+        let epoch_id = [
+            StacksEpochId::Epoch20,
+            StacksEpochId::Epoch2_05,
+            StacksEpochId::Epoch21,
+            StacksEpochId::Epoch22,
+            StacksEpochId::Epoch23,
+            StacksEpochId::Epoch24,
+        ]
+        .into_iter()
+        .find(|epoch_id| epoch_id.to_string() == argv[3])
+        .unwrap_or_else(|| {
+            eprintln!("Unknown epoch '{}' (expected e.g. '2.1')", &argv[3]);
+            process::exit(1);
+        });
+        if argv[2] == "--json" {
+            println!(
+                "{}",
+                unwrap_docs(boot_docs::make_versioned_json_boot_contracts_reference(
+                    epoch_id,
+                    include_private
+                ))
+            );
+        } else {
+            for (_, page) in unwrap_docs(boot_docs::make_markdown_boot_contracts_reference(
+                epoch_id,
+                include_private,
+            )) {
+                println!("{}", page);
+            }
+        }
         return;
     }
 
@@ -1138,8 +1367,7 @@ simulating a miner.
         };
 
         let (mut new_chainstate, _) = StacksChainState::open_and_exec(
-            false,
-            0x80000000,
+            NetworkId::testnet(),
             new_chainstate_path,
             Some(&mut boot_data),
             None,
@@ -24,28 +24,120 @@ use crate::util_lib::db::Error as DatabaseError;
 use crate::{
     burnchains::Txid,
     core::MemPoolDB,
-    net::{Error as net_error, HttpRequestType},
+    net::{Error as net_error, HttpRequestType, RPCChainQuality},
     util::get_epoch_time_secs,
     util_lib::db::{tx_busy_handler, DBConn},
 };
 use clarity::vm::costs::ExecutionCost;
+use stacks_common::util::telemetry::ErrorTelemetry;
 use stacks_common::util::uint::{Uint256, Uint512};
+use std::cell::RefCell;
 use std::convert::TryInto;
 use std::error::Error;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 #[cfg(feature = "monitoring_prom")]
 mod prometheus;
 
 #[cfg(feature = "monitoring_prom")]
+pub use self::prometheus::PrometheusMetrics;
+
+/// The metrics of one network.  A process which hosts several networks side by side keeps one
+/// of these per network, so that each network's metrics are served, and alerted on, separately.
+///
+/// Metrics are recorded into the calling thread's network metrics (see `enter()`), or into the
+/// process-wide metrics if the thread has not entered any.
+pub struct NetworkMetrics {
+    // Last-seen values of the node health signals which the node alerts on.  These are recorded
+    // whether or not Prometheus is enabled.
+    stacks_tip_height: AtomicU64,
+    burnchain_height: AtomicU64,
+    last_marf_flush_time_ms: AtomicU64,
+    last_sortition_won_height: AtomicU64,
+    pending_observer_payloads: AtomicU64,
+    #[cfg(feature = "monitoring_prom")]
+    burnchain_signer: Mutex<Option<BurnchainSigner>>,
+    #[cfg(feature = "monitoring_prom")]
+    prometheus: PrometheusMetrics,
+}
+
 lazy_static! {
-    static ref GLOBAL_BURNCHAIN_SIGNER: Mutex<Option<BurnchainSigner>> = Mutex::new(None);
+    /// Metrics of a process that hosts a single network, registered in the default Prometheus
+    /// registry
+    static ref PROCESS_METRICS: Arc<NetworkMetrics> = Arc::new(NetworkMetrics::create(true));
+}
+
+thread_local! {
+    static THREAD_METRICS: RefCell<Option<Arc<NetworkMetrics>>> = const { RefCell::new(None) };
+}
+
+impl NetworkMetrics {
+    #[allow(unused_variables)]
+    fn create(process_wide: bool) -> NetworkMetrics {
+        NetworkMetrics {
+            stacks_tip_height: AtomicU64::new(0),
+            burnchain_height: AtomicU64::new(0),
+            last_marf_flush_time_ms: AtomicU64::new(0),
+            last_sortition_won_height: AtomicU64::new(0),
+            pending_observer_payloads: AtomicU64::new(0),
+            #[cfg(feature = "monitoring_prom")]
+            burnchain_signer: Mutex::new(None),
+            #[cfg(feature = "monitoring_prom")]
+            prometheus: PrometheusMetrics::new(if process_wide {
+                ::prometheus::default_registry().clone()
+            } else {
+                ::prometheus::Registry::new()
+            }),
+        }
+    }
+
+    /// Create a network's metrics, with their own Prometheus registry
+    pub fn new() -> Arc<NetworkMetrics> {
+        Arc::new(NetworkMetrics::create(false))
+    }
+
+    /// Get the metrics the calling thread records into
+    pub fn current() -> Arc<NetworkMetrics> {
+        with_network_metrics(Arc::clone)
+    }
+
+    /// Record the calling thread's metrics into these metrics from now on.  Every thread that
+    /// works on behalf of a network should enter that network's metrics when it starts.
+    pub fn enter(self: &Arc<Self>) {
+        THREAD_METRICS.with(|thread_metrics| {
+            *thread_metrics.borrow_mut() = Some(Arc::clone(self));
+        });
+    }
+
+    /// Collect the current values of this network's Prometheus metrics
+    #[cfg(feature = "monitoring_prom")]
+    pub fn gather(&self) -> Vec<::prometheus::proto::MetricFamily> {
+        self.prometheus.gather()
+    }
+}
+
+fn with_network_metrics<F, R>(f: F) -> R
+where
+    F: FnOnce(&Arc<NetworkMetrics>) -> R,
+{
+    THREAD_METRICS.with(|thread_metrics| match *thread_metrics.borrow() {
+        Some(ref metrics) => f(metrics),
+        None => f(&PROCESS_METRICS),
+    })
+}
+
+#[cfg(feature = "monitoring_prom")]
+fn with_prometheus<F, R>(f: F) -> R
+where
+    F: FnOnce(&PrometheusMetrics) -> R,
+{
+    with_network_metrics(|metrics| f(&metrics.prometheus))
 }
 
 pub fn increment_rpc_calls_counter() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::RPC_CALL_COUNTER.inc();
+    with_prometheus(|prom| prom.rpc_call_counter.inc());
 }
 
 pub fn instrument_http_request_handler<F, R>(
@@ -59,9 +151,12 @@ where
     increment_rpc_calls_counter();
 
     #[cfg(feature = "monitoring_prom")]
-    let timer = prometheus::new_rpc_call_timer(req.get_path());
+    let timer = with_prometheus(|prom| prom.new_rpc_call_timer(req.get_path()));
 
     let res = handler(req);
+    if let Err(ref e) = res {
+        increment_errors_counter(e);
+    }
 
     #[cfg(feature = "monitoring_prom")]
     timer.stop_and_record();
@@ -71,37 +166,37 @@ where
 
 pub fn increment_stx_blocks_received_counter() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::STX_BLOCKS_RECEIVED_COUNTER.inc();
+    with_prometheus(|prom| prom.stx_blocks_received_counter.inc());
 }
 
 pub fn increment_stx_micro_blocks_received_counter() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::STX_MICRO_BLOCKS_RECEIVED_COUNTER.inc();
+    with_prometheus(|prom| prom.stx_micro_blocks_received_counter.inc());
 }
 
 pub fn increment_stx_blocks_served_counter() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::STX_BLOCKS_SERVED_COUNTER.inc();
+    with_prometheus(|prom| prom.stx_blocks_served_counter.inc());
 }
 
 pub fn increment_stx_micro_blocks_served_counter() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::STX_MICRO_BLOCKS_SERVED_COUNTER.inc();
+    with_prometheus(|prom| prom.stx_micro_blocks_served_counter.inc());
 }
 
 pub fn increment_stx_confirmed_micro_blocks_served_counter() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::STX_CONFIRMED_MICRO_BLOCKS_SERVED_COUNTER.inc();
+    with_prometheus(|prom| prom.stx_confirmed_micro_blocks_served_counter.inc());
 }
 
 pub fn increment_txs_received_counter() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::TXS_RECEIVED_COUNTER.inc();
+    with_prometheus(|prom| prom.txs_received_counter.inc());
 }
 
 pub fn increment_btc_blocks_received_counter() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::BTC_BLOCKS_RECEIVED_COUNTER.inc();
+    with_prometheus(|prom| prom.btc_blocks_received_counter.inc());
 }
 
 /// Log `execution_cost` as a ratio of `block_limit`.
@@ -111,18 +206,18 @@ pub fn set_last_execution_cost_observed(
     block_limit: &ExecutionCost,
 ) {
     #[cfg(feature = "monitoring_prom")]
-    {
-        prometheus::LAST_BLOCK_READ_COUNT
+    with_prometheus(|prom| {
+        prom.last_block_read_count
             .set(execution_cost.read_count as f64 / block_limit.read_count as f64);
-        prometheus::LAST_BLOCK_WRITE_COUNT
+        prom.last_block_write_count
             .set(execution_cost.write_count as f64 / block_limit.read_count as f64);
-        prometheus::LAST_BLOCK_READ_LENGTH
+        prom.last_block_read_length
             .set(execution_cost.read_length as f64 / block_limit.read_length as f64);
-        prometheus::LAST_BLOCK_WRITE_LENGTH
+        prom.last_block_write_length
             .set(execution_cost.write_length as f64 / block_limit.write_length as f64);
-        prometheus::LAST_BLOCK_RUNTIME
+        prom.last_block_runtime
             .set(execution_cost.runtime as f64 / block_limit.runtime as f64);
-    }
+    });
 }
 
 /// Log the number of transactions in the latest block.
@@ -130,33 +225,54 @@ pub fn set_last_execution_cost_observed(
 pub fn set_last_block_transaction_count(transactions_in_block: u64) {
     // Saturating cast from u64 to i64
     #[cfg(feature = "monitoring_prom")]
-    prometheus::LAST_BLOCK_TRANSACTION_COUNT
-        .set(i64::try_from(transactions_in_block).unwrap_or_else(|_| i64::MAX));
+    with_prometheus(|prom| {
+        prom.last_block_transaction_count
+            .set(i64::try_from(transactions_in_block).unwrap_or_else(|_| i64::MAX))
+    });
+}
+
+/// Log the serialized sizes of the latest block and the parent microblocks it confirmed.
+#[allow(unused_variables)]
+pub fn set_last_block_size(anchored_block_size: u64, parent_microblocks_size: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    with_prometheus(|prom| {
+        prom.last_block_size
+            .set(i64::try_from(anchored_block_size).unwrap_or(i64::MAX));
+        prom.last_block_microblocks_size
+            .set(i64::try_from(parent_microblocks_size).unwrap_or(i64::MAX));
+    });
+}
+
+/// Log the serialized size of a transaction processed in a block.
+#[allow(unused_variables)]
+pub fn observe_processed_tx_size(tx_size: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    with_prometheus(|prom| prom.processed_tx_size.observe(tx_size as f64));
 }
 
 pub fn increment_btc_ops_sent_counter() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::BTC_OPS_SENT_COUNTER.inc();
+    with_prometheus(|prom| prom.btc_ops_sent_counter.inc());
 }
 
 pub fn increment_stx_blocks_processed_counter() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::STX_BLOCKS_PROCESSED_COUNTER.inc();
+    with_prometheus(|prom| prom.stx_blocks_processed_counter.inc());
 }
 
 pub fn increment_stx_blocks_mined_counter() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::STX_BLOCKS_MINED_COUNTER.inc();
+    with_prometheus(|prom| prom.stx_blocks_mined_counter.inc());
 }
 
 pub fn increment_warning_emitted_counter() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::WARNING_EMITTED_COUNTER.inc();
+    with_prometheus(|prom| prom.warning_emitted_counter.inc());
 }
 
 pub fn increment_errors_emitted_counter() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::ERRORS_EMITTED_COUNTER.inc();
+    with_prometheus(|prom| prom.errors_emitted_counter.inc());
 }
 
 fn txid_tracking_db(chainstate_root_path: &str) -> Result<DBConn, DatabaseError> {
@@ -210,7 +326,7 @@ pub fn mempool_accepted(txid: &Txid, chainstate_root_path: &str) -> Result<(), D
             return Ok(());
         }
 
-        prometheus::MEMPOOL_OUTSTANDING_TXS.inc();
+        with_prometheus(|prom| prom.mempool_outstanding_txs.inc());
     }
 
     Ok(())
@@ -245,8 +361,10 @@ pub fn log_transaction_processed(
 
         let time_to_process = time_now - mempool_accept_time;
 
-        prometheus::MEMPOOL_OUTSTANDING_TXS.dec();
-        prometheus::MEMPOOL_TX_CONFIRM_TIME.observe(time_to_process as f64);
+        with_prometheus(|prom| {
+            prom.mempool_outstanding_txs.dec();
+            prom.mempool_tx_confirm_time.observe(time_to_process as f64);
+        });
     }
     Ok(())
 }
@@ -254,73 +372,186 @@ pub fn log_transaction_processed(
 #[allow(unused_variables)]
 pub fn update_active_miners_count_gauge(value: i64) {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::ACTIVE_MINERS_COUNT_GAUGE.set(value);
+    with_prometheus(|prom| prom.active_miners_count_gauge.set(value));
 }
 
-#[allow(unused_variables)]
 pub fn update_stacks_tip_height(value: i64) {
+    with_network_metrics(|metrics| {
+        metrics
+            .stacks_tip_height
+            .store(value as u64, Ordering::SeqCst)
+    });
     #[cfg(feature = "monitoring_prom")]
-    prometheus::STACKS_TIP_HEIGHT_GAUGE.set(value);
+    with_prometheus(|prom| prom.stacks_tip_height_gauge.set(value));
+}
+
+pub fn get_stacks_tip_height() -> u64 {
+    with_network_metrics(|metrics| metrics.stacks_tip_height.load(Ordering::SeqCst))
 }
 
-#[allow(unused_variables)]
 pub fn update_burnchain_height(value: i64) {
+    with_network_metrics(|metrics| {
+        metrics
+            .burnchain_height
+            .store(value as u64, Ordering::SeqCst)
+    });
     #[cfg(feature = "monitoring_prom")]
-    prometheus::BURNCHAIN_HEIGHT_GAUGE.set(value);
+    with_prometheus(|prom| prom.burnchain_height_gauge.set(value));
+}
+
+pub fn get_burnchain_height() -> u64 {
+    with_network_metrics(|metrics| metrics.burnchain_height.load(Ordering::SeqCst))
+}
+
+/// Record how long it took to flush the last processed block's MARF writes to disk
+pub fn set_last_marf_flush_time(time_ms: u64) {
+    with_network_metrics(|metrics| {
+        metrics
+            .last_marf_flush_time_ms
+            .store(time_ms, Ordering::SeqCst)
+    });
+}
+
+pub fn get_last_marf_flush_time_ms() -> u64 {
+    with_network_metrics(|metrics| metrics.last_marf_flush_time_ms.load(Ordering::SeqCst))
+}
+
+/// Record that this node's miner won the sortition at `burn_height`
+pub fn note_sortition_won(burn_height: u64) {
+    with_network_metrics(|metrics| {
+        metrics
+            .last_sortition_won_height
+            .fetch_max(burn_height, Ordering::SeqCst)
+    });
+}
+
+/// Get the burnchain height of the last sortition this node's miner won
+pub fn get_last_sortition_won_height() -> u64 {
+    with_network_metrics(|metrics| metrics.last_sortition_won_height.load(Ordering::SeqCst))
+}
+
+/// Record that a payload is now waiting to be delivered to an event observer
+pub fn increment_pending_observer_payloads() {
+    with_network_metrics(|metrics| {
+        metrics
+            .pending_observer_payloads
+            .fetch_add(1, Ordering::SeqCst)
+    });
+}
+
+/// Record that a payload was delivered to an event observer
+pub fn decrement_pending_observer_payloads() {
+    with_network_metrics(|metrics| {
+        metrics
+            .pending_observer_payloads
+            .fetch_sub(1, Ordering::SeqCst)
+    });
+}
+
+/// Get the number of payloads which are waiting to be delivered to an event observer
+pub fn get_pending_observer_payloads() -> u64 {
+    with_network_metrics(|metrics| metrics.pending_observer_payloads.load(Ordering::SeqCst))
+}
+
+/// Log the chain-quality metrics over the most recent window of burnchain blocks.
+#[allow(unused_variables)]
+pub fn set_chain_quality(chain_quality: &RPCChainQuality) {
+    #[cfg(feature = "monitoring_prom")]
+    with_prometheus(|prom| {
+        prom.chain_quality_sortition_rate
+            .set(chain_quality.sortition_rate);
+        prom.chain_quality_missed_sortitions
+            .set(i64::try_from(chain_quality.missed_sortitions).unwrap_or(i64::MAX));
+        prom.chain_quality_empty_tenures
+            .set(i64::try_from(chain_quality.empty_tenures).unwrap_or(i64::MAX));
+        prom.chain_quality_average_block_fill
+            .set(chain_quality.average_block_fill_pct.unwrap_or(0.0));
+        prom.chain_quality_microblock_confirmation_rate
+            .set(chain_quality.microblock_confirmation_rate.unwrap_or(0.0));
+    });
 }
 
 #[allow(unused_variables)]
 pub fn update_inbound_neighbors(value: i64) {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::INBOUND_NEIGHBORS_GAUGE.set(value);
+    with_prometheus(|prom| prom.inbound_neighbors_gauge.set(value));
 }
 
 #[allow(unused_variables)]
 pub fn update_outbound_neighbors(value: i64) {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::OUTBOUND_NEIGHBORS_GAUGE.set(value);
+    with_prometheus(|prom| prom.outbound_neighbors_gauge.set(value));
 }
 
 #[allow(unused_variables)]
 pub fn update_inbound_bandwidth(value: i64) {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::INBOUND_BANDWIDTH_GAUGE.add(value);
+    with_prometheus(|prom| prom.inbound_bandwidth_gauge.add(value));
 }
 
 #[allow(unused_variables)]
 pub fn update_outbound_bandwidth(value: i64) {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::OUTBOUND_BANDWIDTH_GAUGE.add(value);
+    with_prometheus(|prom| prom.outbound_bandwidth_gauge.add(value));
 }
 
 #[allow(unused_variables)]
 pub fn update_inbound_rpc_bandwidth(value: i64) {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::INBOUND_RPC_BANDWIDTH_GAUGE.add(value);
+    with_prometheus(|prom| prom.inbound_rpc_bandwidth_gauge.add(value));
 }
 
 #[allow(unused_variables)]
 pub fn update_outbound_rpc_bandwidth(value: i64) {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::OUTBOUND_RPC_BANDWIDTH_GAUGE.add(value);
+    with_prometheus(|prom| prom.outbound_rpc_bandwidth_gauge.add(value));
 }
 
 #[allow(unused_variables)]
 pub fn increment_msg_counter(name: String) {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::MSG_COUNTER_VEC
-        .with_label_values(&[&name])
-        .inc();
+    with_prometheus(|prom| prom.msg_counter_vec.with_label_values(&[&name]).inc());
+}
+
+#[allow(unused_variables)]
+pub fn increment_p2p_backpressure_drops(kind: &str, count: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    with_prometheus(|prom| {
+        prom.p2p_backpressure_drops_vec
+            .with_label_values(&[kind])
+            .inc_by(count as i64)
+    });
+}
+
+#[allow(unused_variables)]
+pub fn increment_readonly_call_cache_counter(kind: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    with_prometheus(|prom| {
+        prom.readonly_call_cache_vec
+            .with_label_values(&[kind])
+            .inc()
+    });
+}
+
+/// Count an error under its telemetry label
+#[allow(unused_variables)]
+pub fn increment_errors_counter(err: &dyn ErrorTelemetry) {
+    #[cfg(feature = "monitoring_prom")]
+    with_prometheus(|prom| {
+        prom.errors_vec
+            .with_label_values(&[err.metric_label()])
+            .inc()
+    });
 }
 
 pub fn increment_stx_mempool_gc() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::STX_MEMPOOL_GC.inc();
+    with_prometheus(|prom| prom.stx_mempool_gc.inc());
 }
 
 pub fn increment_contract_calls_processed() {
     #[cfg(feature = "monitoring_prom")]
-    prometheus::CONTRACT_CALLS_PROCESSED_COUNT.inc();
+    with_prometheus(|prom| prom.contract_calls_processed_count.inc());
 }
 
 /// Given a value (type uint256), return value/uint256::max() as an f64 value.
@@ -380,49 +611,87 @@ pub fn test_convert_uint256_to_f64() {
     assert_approx_eq!(convert_uint256_to_f64_percentage(original, 1000), 12.234567);
 }
 
+#[test]
+pub fn test_network_metrics_are_separate() {
+    let network_1 = NetworkMetrics::new();
+    let network_2 = NetworkMetrics::new();
+
+    let metrics = network_1.clone();
+    std::thread::spawn(move || {
+        metrics.enter();
+        update_stacks_tip_height(10);
+        note_sortition_won(5);
+        increment_pending_observer_payloads();
+    })
+    .join()
+    .unwrap();
+
+    let metrics = network_2.clone();
+    std::thread::spawn(move || {
+        metrics.enter();
+        update_stacks_tip_height(20);
+        assert_eq!(get_stacks_tip_height(), 20);
+        assert_eq!(get_last_sortition_won_height(), 0);
+        assert_eq!(get_pending_observer_payloads(), 0);
+    })
+    .join()
+    .unwrap();
+
+    network_1.enter();
+    assert_eq!(get_stacks_tip_height(), 10);
+    assert_eq!(get_last_sortition_won_height(), 5);
+    assert_eq!(get_pending_observer_payloads(), 1);
+    assert!(Arc::ptr_eq(&NetworkMetrics::current(), &network_1));
+}
+
 #[allow(unused_variables)]
 pub fn update_computed_relative_miner_score(value: Uint256) {
     #[cfg(feature = "monitoring_prom")]
-    {
+    with_prometheus(|prom| {
         let percentage = convert_uint256_to_f64_percentage(value, 7);
-        prometheus::COMPUTED_RELATIVE_MINER_SCORE.set(percentage);
-    }
+        prom.computed_relative_miner_score.set(percentage);
+    });
 }
 
 #[allow(unused_variables)]
 pub fn update_computed_miner_commitment(value: u128) {
     #[cfg(feature = "monitoring_prom")]
-    {
+    with_prometheus(|prom| {
         let high_bits = (value >> 64) as u64;
         let low_bits = value as u64;
-        prometheus::COMPUTED_MINER_COMMITMENT_HIGH.set(high_bits as i64);
-        prometheus::COMPUTED_MINER_COMMITMENT_LOW.set(low_bits as i64);
-    }
+        prom.computed_miner_commitment_high.set(high_bits as i64);
+        prom.computed_miner_commitment_low.set(low_bits as i64);
+    });
 }
 
 #[allow(unused_variables)]
 pub fn update_miner_current_median_commitment(value: u128) {
     #[cfg(feature = "monitoring_prom")]
-    {
+    with_prometheus(|prom| {
         let high_bits = (value >> 64) as u64;
         let low_bits = value as u64;
-        prometheus::MINER_CURRENT_MEDIAN_COMMITMENT_HIGH.set(high_bits as i64);
-        prometheus::MINER_CURRENT_MEDIAN_COMMITMENT_LOW.set(low_bits as i64);
-    }
+        prom.miner_current_median_commitment_high
+            .set(high_bits as i64);
+        prom.miner_current_median_commitment_low
+            .set(low_bits as i64);
+    });
 }
 
-/// Function sets the global variable `GLOBAL_BURNCHAIN_SIGNER`.
-/// Fails if there are multiple attempts to set this variable.
+/// Set the burnchain signer of the calling thread's network, whose commitments the miner
+/// metrics track.  Fails if the network's signer was already set.
 #[allow(unused_variables)]
 pub fn set_burnchain_signer(signer: BurnchainSigner) -> Result<(), SetGlobalBurnchainSignerError> {
     #[cfg(feature = "monitoring_prom")]
     {
-        let mut signer_mutex = GLOBAL_BURNCHAIN_SIGNER.lock().unwrap();
-        if signer_mutex.is_some() {
-            return Err(SetGlobalBurnchainSignerError);
-        }
+        with_network_metrics(|metrics| {
+            let mut signer_mutex = metrics.burnchain_signer.lock().unwrap();
+            if signer_mutex.is_some() {
+                return Err(SetGlobalBurnchainSignerError);
+            }
 
-        *signer_mutex = Some(signer);
+            *signer_mutex = Some(signer);
+            Ok(())
+        })?;
     }
     Ok(())
 }
@@ -431,7 +700,7 @@ pub fn set_burnchain_signer(signer: BurnchainSigner) -> Result<(), SetGlobalBurn
 pub fn get_burnchain_signer() -> Option<BurnchainSigner> {
     #[cfg(feature = "monitoring_prom")]
     {
-        return GLOBAL_BURNCHAIN_SIGNER.lock().unwrap().clone();
+        return with_network_metrics(|metrics| metrics.burnchain_signer.lock().unwrap().clone());
     }
     None
 }
@@ -441,7 +710,7 @@ pub struct SetGlobalBurnchainSignerError;
 
 impl fmt::Display for SetGlobalBurnchainSignerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.pad("A global default burnchain signer has already been set.")
+        f.pad("This network's burnchain signer has already been set.")
     }
 }
 
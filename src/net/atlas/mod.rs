@@ -27,8 +27,9 @@ use crate::burnchains::Txid;
 use crate::chainstate::burn::db::sortdb::SortitionDB;
 use crate::chainstate::burn::ConsensusHash;
 use crate::types::chainstate::StacksBlockId;
-use crate::util_lib::boot::boot_code_id;
+use crate::util_lib::boot::network_boot_code_id;
 use clarity::vm::types::{QualifiedContractIdentifier, SequenceData, TupleData, Value};
+use stacks_common::types::NetworkId;
 use stacks_common::util::hash::{to_hex, Hash160, MerkleHashFunc};
 
 use crate::types::chainstate::BlockHeaderHash;
@@ -66,8 +67,13 @@ pub struct AtlasConfig {
 
 impl AtlasConfig {
     pub fn default(mainnet: bool) -> AtlasConfig {
+        AtlasConfig::for_network(&NetworkId::from_mainnet(mainnet))
+    }
+
+    /// The default Atlas configuration for the given network, which tracks its BNS contract
+    pub fn for_network(network: &NetworkId) -> AtlasConfig {
         let mut contracts = HashSet::new();
-        contracts.insert(boot_code_id("bns", mainnet));
+        contracts.insert(network_boot_code_id("bns", network));
         AtlasConfig {
             contracts,
             attachments_max_size: 1_048_576,
@@ -130,6 +130,7 @@ pub struct NeighborStats {
     pub last_send_time: u64,
     pub last_recv_time: u64,
     pub last_handshake_time: u64,
+    pub last_node_metadata_request_time: u64,
     pub bytes_tx: u64,
     pub bytes_rx: u64,
     pub msgs_tx: u64,
@@ -153,6 +154,7 @@ impl NeighborStats {
             last_send_time: 0,
             last_recv_time: 0,
             last_handshake_time: 0,
+            last_node_metadata_request_time: 0,
             bytes_tx: 0,
             bytes_rx: 0,
             msgs_tx: 0,
@@ -637,6 +639,11 @@ impl ConversationP2P {
         (peer_services & expected_bits) == expected_bits
     }
 
+    /// Does this remote neighbor exchange signed node metadata records?
+    pub fn supports_node_metadata(peer_services: u16) -> bool {
+        (peer_services & (ServiceFlags::METADATA as u16)) != 0
+    }
+
     /// Determine whether or not a given (height, burn_header_hash) pair _disagrees_ with our
     /// burnchain view.  If it does, return true.  If it doesn't (including if the given pair is
     /// simply absent from the chain_view), then return False.
@@ -1271,6 +1278,82 @@ impl ConversationP2P {
         )))
     }
 
+    /// Reply to a GetNodeMetadata with our signed metadata record.
+    /// Called from the p2p network thread.
+    fn handle_get_node_metadata(
+        &mut self,
+        local_peer: &LocalPeer,
+        chain_view: &BurnchainView,
+    ) -> Result<Option<StacksMessage>, net_error> {
+        monitoring::increment_msg_counter("p2p_get_node_metadata".to_string());
+
+        let metadata =
+            NodeMetadata::from_local_peer(local_peer, &self.connection.options.operator_contact);
+        let signed_metadata = NodeMetadataData::sign(metadata, &local_peer.private_key)?;
+        Ok(Some(StacksMessage::from_chain_view(
+            self.version,
+            self.network_id,
+            chain_view,
+            StacksMessageType::NodeMetadata(signed_metadata),
+        )))
+    }
+
+    /// Handle a metadata record from the remote peer.  It is only stored if it was signed by the
+    /// key this conversation is authenticated with.
+    /// Called from the p2p network thread.
+    fn handle_node_metadata(
+        &mut self,
+        peerdb: &mut PeerDB,
+        signed_metadata: &NodeMetadataData,
+    ) -> Result<(), net_error> {
+        monitoring::increment_msg_counter("p2p_node_metadata".to_string());
+
+        let signed_by_peer = match (
+            self.connection.ref_public_key(),
+            signed_metadata.node_public_key.to_public_key(),
+        ) {
+            (Some(peer_pubkey), Ok(signer_pubkey)) => {
+                peer_pubkey.to_bytes_compressed() == signer_pubkey.to_bytes_compressed()
+            }
+            _ => false,
+        };
+        if !signed_by_peer {
+            debug!(
+                "{:?}: dropping node metadata signed by someone else ({})",
+                &self,
+                &to_hex(signed_metadata.node_public_key.as_bytes())
+            );
+            self.stats.msgs_err += 1;
+            return Ok(());
+        }
+        if let Err(e) = signed_metadata.verify() {
+            debug!("{:?}: dropping invalid node metadata: {:?}", &self, &e);
+            self.stats.msgs_err += 1;
+            return Ok(());
+        }
+
+        let mut tx = peerdb.tx_begin().map_err(net_error::DBError)?;
+        let stored = PeerDB::store_node_metadata(
+            &mut tx,
+            &self.to_neighbor_key(),
+            signed_metadata,
+            get_epoch_time_secs(),
+        )
+        .map_err(net_error::DBError)?;
+        tx.commit()
+            .map_err(|e| net_error::DBError(db_error::SqliteError(e)))?;
+
+        if stored {
+            debug!(
+                "{:?}: stored node metadata ({}, services {})",
+                &self,
+                &signed_metadata.metadata.node_version,
+                &to_hex(&signed_metadata.metadata.services.to_be_bytes())
+            );
+        }
+        Ok(())
+    }
+
     /// Handle an inbound GetNeighbors request.
     fn handle_getneighbors(
         &mut self,
@@ -2091,6 +2174,21 @@ impl ConversationP2P {
                 test_debug!("{:?}: Got NatPunchReply({})", &self, _m.nonce);
                 Ok(None)
             }
+            StacksMessageType::GetNodeMetadata => {
+                test_debug!("{:?}: Got GetNodeMetadata", &self);
+
+                consume = true;
+                self.handle_get_node_metadata(local_peer, burnchain_view)
+            }
+            StacksMessageType::NodeMetadata(ref data) => {
+                test_debug!("{:?}: Got NodeMetadata", &self);
+
+                // we send GetNodeMetadata without waiting for the reply, so consume it here
+                // unless someone is waiting for it
+                consume = !self.connection.is_solicited(msg);
+                self.handle_node_metadata(peerdb, data)
+                    .and_then(|_| Ok(None))
+            }
             _ => {
                 test_debug!(
                     "{:?}: Got a data-plane message (type {})",
@@ -2424,6 +2522,7 @@ mod test {
     use crate::net::*;
     use crate::util_lib::test::*;
     use clarity::vm::costs::ExecutionCost;
+    use stacks_common::types::NetworkId;
     use stacks_common::util::pipe::*;
     use stacks_common::util::secp256k1::*;
     use stacks_common::util::uint::*;
@@ -2486,8 +2585,7 @@ mod test {
         let mut boot_data = ChainStateBootData::new(&burnchain, vec![], None);
 
         let (chainstate, _) = StacksChainState::open_and_exec(
-            false,
-            network_id,
+            NetworkId::from_chain_id(false, network_id),
             &chainstate_path,
             Some(&mut boot_data),
             None,
@@ -3381,6 +3479,182 @@ mod test {
         }
     }
 
+    #[test]
+    fn convo_node_metadata() {
+        let mut conn_opts = ConnectionOptions::default();
+        conn_opts.operator_contact = "ops@peer2.com".to_string();
+
+        let socketaddr_1 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let socketaddr_2 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 8081);
+
+        let burnchain = testing_burnchain_config();
+
+        let mut chain_view = BurnchainView {
+            burn_block_height: 12348,
+            burn_block_hash: BurnchainHeaderHash([0x11; 32]),
+            burn_stable_block_height: 12341,
+            burn_stable_block_hash: BurnchainHeaderHash([0x22; 32]),
+            last_burn_block_hashes: HashMap::new(),
+        };
+        chain_view.make_test_data();
+
+        let (mut peerdb_1, mut sortdb_1, pox_id_1, mut chainstate_1) = make_test_chain_dbs(
+            "convo_node_metadata_1",
+            &burnchain,
+            0x9abcdef0,
+            12350,
+            "http://peer1.com".into(),
+            &vec![],
+            &vec![],
+        );
+        let (mut peerdb_2, mut sortdb_2, pox_id_2, mut chainstate_2) = make_test_chain_dbs(
+            "convo_node_metadata_2",
+            &burnchain,
+            0x9abcdef0,
+            12351,
+            "http://peer2.com".into(),
+            &vec![],
+            &vec![],
+        );
+
+        db_setup(&mut peerdb_1, &mut sortdb_1, &socketaddr_1, &chain_view);
+        db_setup(&mut peerdb_2, &mut sortdb_2, &socketaddr_2, &chain_view);
+
+        let local_peer_1 = PeerDB::get_local_peer(&peerdb_1.conn()).unwrap();
+        let local_peer_2 = PeerDB::get_local_peer(&peerdb_2.conn()).unwrap();
+
+        let mut convo_1 = ConversationP2P::new(
+            123,
+            456,
+            &burnchain,
+            &socketaddr_2,
+            &conn_opts,
+            true,
+            0,
+            StacksEpoch::unit_test_pre_2_05(0),
+        );
+        let mut convo_2 = ConversationP2P::new(
+            123,
+            456,
+            &burnchain,
+            &socketaddr_1,
+            &conn_opts,
+            true,
+            0,
+            StacksEpoch::unit_test_pre_2_05(0),
+        );
+
+        // convo_1 sends a handshake to convo_2, and asks for its metadata
+        let handshake_data_1 = HandshakeData::from_local_peer(&local_peer_1);
+        let handshake_1 = convo_1
+            .sign_message(
+                &chain_view,
+                &local_peer_1.private_key,
+                StacksMessageType::Handshake(handshake_data_1.clone()),
+            )
+            .unwrap();
+        let mut rh_handshake_1 = convo_1
+            .send_signed_request(handshake_1.clone(), 1000000)
+            .unwrap();
+
+        let getmetadata_1 = convo_1
+            .sign_message(
+                &chain_view,
+                &local_peer_1.private_key,
+                StacksMessageType::GetNodeMetadata,
+            )
+            .unwrap();
+        let mut rh_getmetadata_1 = convo_1
+            .send_signed_request(getmetadata_1.clone(), 1000000)
+            .unwrap();
+
+        convo_send_recv(
+            &mut convo_1,
+            vec![&mut rh_handshake_1, &mut rh_getmetadata_1],
+            &mut convo_2,
+        );
+        let unhandled_2 = convo_2
+            .chat(
+                &local_peer_2,
+                &mut peerdb_2,
+                &sortdb_2,
+                &pox_id_2,
+                &mut chainstate_2,
+                &mut BlockHeaderCache::new(),
+                &chain_view,
+            )
+            .unwrap();
+
+        convo_send_recv(
+            &mut convo_2,
+            vec![&mut rh_handshake_1, &mut rh_getmetadata_1],
+            &mut convo_1,
+        );
+        let unhandled_1 = convo_1
+            .chat(
+                &local_peer_1,
+                &mut peerdb_1,
+                &sortdb_1,
+                &pox_id_1,
+                &mut chainstate_1,
+                &mut BlockHeaderCache::new(),
+                &chain_view,
+            )
+            .unwrap();
+
+        let _reply_handshake_1 = rh_handshake_1.recv(0).unwrap();
+        let reply_metadata_1 = rh_getmetadata_1.recv(0).unwrap();
+
+        assert_eq!(unhandled_1.len(), 0);
+        assert_eq!(unhandled_2.len(), 1); // only the handshake is given back
+
+        // convo 2 replied with its own signed metadata
+        let signed_metadata = match reply_metadata_1.payload {
+            StacksMessageType::NodeMetadata(ref data) => data.clone(),
+            _ => panic!("Expected NodeMetadata"),
+        };
+        signed_metadata.verify().unwrap();
+        assert_eq!(
+            signed_metadata.node_public_key,
+            StacksPublicKeyBuffer::from_public_key(&Secp256k1PublicKey::from_private(
+                &local_peer_2.private_key
+            ))
+        );
+        assert_eq!(signed_metadata.metadata.operator_contact, "ops@peer2.com");
+        assert_eq!(signed_metadata.metadata.services, local_peer_2.services);
+
+        // convo 1 stored it
+        let neighbor_key_2 = convo_1.to_neighbor_key();
+        let record = PeerDB::get_node_metadata(
+            peerdb_1.conn(),
+            neighbor_key_2.network_id,
+            &neighbor_key_2.addrbytes,
+            neighbor_key_2.port,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(record.signed_metadata, signed_metadata);
+
+        // a newer record that convo 2's peer did not sign is dropped
+        let mut forged_metadata = signed_metadata.metadata.clone();
+        forged_metadata.operator_contact = "attacker@example.com".to_string();
+        forged_metadata.timestamp += 1;
+        let forged = NodeMetadataData::sign(forged_metadata, &Secp256k1PrivateKey::new()).unwrap();
+        convo_1
+            .handle_node_metadata(&mut peerdb_1, &forged)
+            .unwrap();
+
+        let record = PeerDB::get_node_metadata(
+            peerdb_1.conn(),
+            neighbor_key_2.network_id,
+            &neighbor_key_2.addrbytes,
+            neighbor_key_2.port,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(record.signed_metadata, signed_metadata);
+    }
+
     #[test]
     fn convo_handshake_ping_loop() {
         let conn_opts = ConnectionOptions::default();
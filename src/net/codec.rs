@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::cmp;
 use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::io;
@@ -39,7 +40,9 @@ use crate::core::PEER_VERSION_TESTNET;
 use crate::net::db::LocalPeer;
 use crate::net::Error as net_error;
 use crate::net::*;
+use crate::version_string;
 use stacks_common::codec::{read_next_at_most, read_next_exact, MAX_MESSAGE_LEN};
+use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::hash::to_hex;
 use stacks_common::util::hash::DoubleSha256;
 use stacks_common::util::hash::Hash160;
@@ -56,6 +59,7 @@ use crate::codec::{
 };
 use crate::types::chainstate::BlockHeaderHash;
 use crate::types::chainstate::BurnchainHeaderHash;
+use crate::types::chainstate::StacksBlockId;
 use crate::types::StacksPublicKeyBuffer;
 
 impl Preamble {
@@ -510,41 +514,6 @@ impl NeighborAddress {
     }
 }
 
-impl StacksMessageCodec for NeighborAddress {
-    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
-        write_next(fd, &self.addrbytes)?;
-        write_next(fd, &self.port)?;
-        write_next(fd, &self.public_key_hash)?;
-        Ok(())
-    }
-
-    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<NeighborAddress, codec_error> {
-        let addrbytes: PeerAddress = read_next(fd)?;
-        let port: u16 = read_next(fd)?;
-        let public_key_hash: Hash160 = read_next(fd)?;
-
-        Ok(NeighborAddress {
-            addrbytes,
-            port,
-            public_key_hash,
-        })
-    }
-}
-
-impl StacksMessageCodec for NeighborsData {
-    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
-        write_next(fd, &self.neighbors)?;
-        Ok(())
-    }
-
-    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<NeighborsData, codec_error> {
-        // don't allow list of more than the pre-set number of neighbors
-        let neighbors: Vec<NeighborAddress> =
-            read_next_at_most::<_, NeighborAddress>(fd, MAX_NEIGHBORS_DATA_LEN)?;
-        Ok(NeighborsData { neighbors })
-    }
-}
-
 impl HandshakeData {
     pub fn from_local_peer(local_peer: &LocalPeer) -> HandshakeData {
         let (addrbytes, port) = match local_peer.public_ip_address {
@@ -694,24 +663,143 @@ impl StacksMessageCodec for PongData {
     }
 }
 
-impl StacksMessageCodec for NatPunchData {
+fn write_metadata_string<W: Write>(fd: &mut W, s: &str) -> Result<(), codec_error> {
+    if s.as_bytes().len() > MAX_NODE_METADATA_STRING_LEN as usize {
+        return Err(codec_error::SerializeError(
+            "Failed to serialize node metadata string: too long".to_string(),
+        ));
+    }
+    write_next(fd, &(s.as_bytes().len() as u8))?;
+    fd.write_all(s.as_bytes())
+        .map_err(codec_error::WriteError)?;
+    Ok(())
+}
+
+fn read_metadata_string<R: Read>(fd: &mut R) -> Result<String, codec_error> {
+    let len_byte: u8 = read_next(fd)?;
+    if len_byte > MAX_NODE_METADATA_STRING_LEN {
+        return Err(codec_error::DeserializeError(
+            "Failed to deserialize node metadata string: too long".to_string(),
+        ));
+    }
+    let mut bytes = vec![0u8; len_byte as usize];
+    fd.read_exact(&mut bytes).map_err(codec_error::ReadError)?;
+    String::from_utf8(bytes).map_err(|_e| {
+        codec_error::DeserializeError(
+            "Failed to deserialize node metadata string: not utf8".to_string(),
+        )
+    })
+}
+
+/// Truncate a string to at most MAX_NODE_METADATA_STRING_LEN bytes, on a char boundary
+fn truncate_metadata_string(s: &str) -> String {
+    let mut end = cmp::min(s.len(), MAX_NODE_METADATA_STRING_LEN as usize);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s[..end].to_string()
+}
+
+impl NodeMetadata {
+    /// Describe this node.  Over-long strings are truncated so the record can always be encoded.
+    pub fn from_local_peer(local_peer: &LocalPeer, operator_contact: &str) -> NodeMetadata {
+        let node_version = version_string(
+            "stacks-node",
+            option_env!("STACKS_NODE_VERSION")
+                .or(option_env!("CARGO_PKG_VERSION"))
+                .unwrap_or("0.0.0.0"),
+        );
+        NodeMetadata {
+            peer_version: PEER_VERSION_TESTNET,
+            node_version: truncate_metadata_string(&node_version),
+            services: local_peer.services,
+            // same URL we'd advertise in a handshake
+            rpc_url: HandshakeData::from_local_peer(local_peer).data_url,
+            operator_contact: truncate_metadata_string(operator_contact),
+            timestamp: get_epoch_time_secs(),
+        }
+    }
+}
+
+impl StacksMessageCodec for NodeMetadata {
     fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
-        write_next(fd, &self.addrbytes)?;
-        write_next(fd, &self.port)?;
-        write_next(fd, &self.nonce)?;
+        write_next(fd, &self.peer_version)?;
+        write_metadata_string(fd, &self.node_version)?;
+        write_next(fd, &self.services)?;
+        write_next(fd, &self.rpc_url)?;
+        write_metadata_string(fd, &self.operator_contact)?;
+        write_next(fd, &self.timestamp)?;
         Ok(())
     }
 
-    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<NatPunchData, codec_error> {
-        let addrbytes: PeerAddress = read_next(fd)?;
-        let port: u16 = read_next(fd)?;
-        let nonce: u32 = read_next(fd)?;
-        Ok(NatPunchData {
-            addrbytes,
-            port,
-            nonce,
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<NodeMetadata, codec_error> {
+        let peer_version: u32 = read_next(fd)?;
+        let node_version = read_metadata_string(fd)?;
+        let services: u16 = read_next(fd)?;
+        let rpc_url: UrlString = read_next(fd)?;
+        let operator_contact = read_metadata_string(fd)?;
+        let timestamp: u64 = read_next(fd)?;
+        Ok(NodeMetadata {
+            peer_version,
+            node_version,
+            services,
+            rpc_url,
+            operator_contact,
+            timestamp,
+        })
+    }
+}
+
+impl NodeMetadataData {
+    fn digest(metadata: &NodeMetadata) -> Result<[u8; 32], net_error> {
+        let mut metadata_bits = vec![];
+        metadata.consensus_serialize(&mut metadata_bits)?;
+
+        let mut digest_bits = [0u8; 32];
+        let mut sha2 = Sha512_256::new();
+        sha2.update(&metadata_bits[..]);
+        digest_bits.copy_from_slice(sha2.finalize().as_slice());
+        Ok(digest_bits)
+    }
+
+    /// Sign a node's metadata with its p2p private key
+    pub fn sign(
+        metadata: NodeMetadata,
+        privkey: &Secp256k1PrivateKey,
+    ) -> Result<NodeMetadataData, net_error> {
+        let digest_bits = NodeMetadataData::digest(&metadata)?;
+        let signature = privkey
+            .sign(&digest_bits)
+            .map_err(|se| net_error::SigningError(se.to_string()))?;
+
+        Ok(NodeMetadataData {
+            metadata,
+            node_public_key: StacksPublicKeyBuffer::from_public_key(
+                &Secp256k1PublicKey::from_private(privkey),
+            ),
+            signature,
         })
     }
+
+    /// Verify that the metadata was signed by the key in node_public_key
+    pub fn verify(&self) -> Result<(), net_error> {
+        let pubkey = self
+            .node_public_key
+            .to_public_key()
+            .map_err(|e| net_error::DeserializeError(e.into()))?;
+        let digest_bits = NodeMetadataData::digest(&self.metadata)?;
+        let res = pubkey
+            .verify(&digest_bits, &self.signature)
+            .map_err(|_ve| net_error::VerifyingError("Failed to verify signature".to_string()))?;
+
+        if res {
+            Ok(())
+        } else {
+            Err(net_error::VerifyingError(
+                "Invalid node metadata signature".to_string(),
+            ))
+        }
+    }
 }
 
 impl StacksMessageCodec for MemPoolSyncData {
@@ -749,6 +837,110 @@ impl StacksMessageCodec for MemPoolSyncData {
     }
 }
 
+impl StacksMessageCodec for BlockFeedFrame {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        match *self {
+            BlockFeedFrame::Block {
+                ref cursor,
+                ref height,
+                ref consensus_hash,
+                ref parent_block_id,
+                ref block,
+            } => {
+                write_next(fd, &BlockFeedFrameID::Block.to_u8())?;
+                write_next(fd, cursor)?;
+                write_next(fd, height)?;
+                write_next(fd, consensus_hash)?;
+                write_next(fd, parent_block_id)?;
+                write_next(fd, block)?;
+            }
+            BlockFeedFrame::Heartbeat {
+                ref cursor,
+                ref tip_height,
+            } => {
+                write_next(fd, &BlockFeedFrameID::Heartbeat.to_u8())?;
+                write_block_feed_cursor(fd, cursor)?;
+                write_next(fd, tip_height)?;
+            }
+            BlockFeedFrame::Rollback {
+                ref from,
+                ref cursor,
+                ref height,
+            } => {
+                write_next(fd, &BlockFeedFrameID::Rollback.to_u8())?;
+                write_next(fd, from)?;
+                write_block_feed_cursor(fd, cursor)?;
+                write_next(fd, height)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<BlockFeedFrame, codec_error> {
+        let frame_id: u8 = read_next(fd)?;
+        match BlockFeedFrameID::from_u8(frame_id).ok_or(codec_error::DeserializeError(format!(
+            "Unrecognized BlockFeedFrameID {}",
+            &frame_id
+        )))? {
+            BlockFeedFrameID::Block => {
+                let cursor: StacksBlockId = read_next(fd)?;
+                let height: u64 = read_next(fd)?;
+                let consensus_hash: ConsensusHash = read_next(fd)?;
+                let parent_block_id: StacksBlockId = read_next(fd)?;
+                let block: StacksBlock = read_next(fd)?;
+                Ok(BlockFeedFrame::Block {
+                    cursor,
+                    height,
+                    consensus_hash,
+                    parent_block_id,
+                    block,
+                })
+            }
+            BlockFeedFrameID::Heartbeat => {
+                let cursor = read_block_feed_cursor(fd)?;
+                let tip_height: u64 = read_next(fd)?;
+                Ok(BlockFeedFrame::Heartbeat { cursor, tip_height })
+            }
+            BlockFeedFrameID::Rollback => {
+                let from: StacksBlockId = read_next(fd)?;
+                let cursor = read_block_feed_cursor(fd)?;
+                let height: u64 = read_next(fd)?;
+                Ok(BlockFeedFrame::Rollback {
+                    from,
+                    cursor,
+                    height,
+                })
+            }
+        }
+    }
+}
+
+/// Write an optional block feed cursor as a presence flag, followed by the cursor if present
+fn write_block_feed_cursor<W: Write>(
+    fd: &mut W,
+    cursor: &Option<StacksBlockId>,
+) -> Result<(), codec_error> {
+    match cursor {
+        Some(ref cursor) => {
+            write_next(fd, &1u8)?;
+            write_next(fd, cursor)
+        }
+        None => write_next(fd, &0u8),
+    }
+}
+
+fn read_block_feed_cursor<R: Read>(fd: &mut R) -> Result<Option<StacksBlockId>, codec_error> {
+    let has_cursor: u8 = read_next(fd)?;
+    match has_cursor {
+        0 => Ok(None),
+        1 => Ok(Some(read_next(fd)?)),
+        _ => Err(codec_error::DeserializeError(format!(
+            "Invalid block feed cursor flag {}",
+            has_cursor
+        ))),
+    }
+}
+
 impl StacksMessageCodec for RelayData {
     fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
         write_next(fd, &self.peer)?;
@@ -787,6 +979,8 @@ impl StacksMessageType {
             StacksMessageType::Pong(ref _m) => StacksMessageID::Pong,
             StacksMessageType::NatPunchRequest(ref _m) => StacksMessageID::NatPunchRequest,
             StacksMessageType::NatPunchReply(ref _m) => StacksMessageID::NatPunchReply,
+            StacksMessageType::GetNodeMetadata => StacksMessageID::GetNodeMetadata,
+            StacksMessageType::NodeMetadata(ref _m) => StacksMessageID::NodeMetadata,
         }
     }
 
@@ -811,6 +1005,8 @@ impl StacksMessageType {
             StacksMessageType::Pong(ref _m) => "Pong",
             StacksMessageType::NatPunchRequest(ref _m) => "NatPunchRequest",
             StacksMessageType::NatPunchReply(ref _m) => "NatPunchReply",
+            StacksMessageType::GetNodeMetadata => "GetNodeMetadata",
+            StacksMessageType::NodeMetadata(ref _m) => "NodeMetadata",
         }
     }
 
@@ -869,6 +1065,13 @@ impl StacksMessageType {
             StacksMessageType::NatPunchReply(ref m) => {
                 format!("NatPunchReply({},{}:{})", m.nonce, &m.addrbytes, m.port)
             }
+            StacksMessageType::GetNodeMetadata => "GetNodeMetadata".to_string(),
+            StacksMessageType::NodeMetadata(ref m) => format!(
+                "NodeMetadata({},{},{})",
+                &to_hex(&m.node_public_key.to_bytes()),
+                &m.metadata.node_version,
+                m.metadata.timestamp
+            ),
         }
     }
 }
@@ -902,6 +1105,8 @@ impl StacksMessageCodec for StacksMessageID {
             x if x == StacksMessageID::Pong as u8 => StacksMessageID::Pong,
             x if x == StacksMessageID::NatPunchRequest as u8 => StacksMessageID::NatPunchRequest,
             x if x == StacksMessageID::NatPunchReply as u8 => StacksMessageID::NatPunchReply,
+            x if x == StacksMessageID::GetNodeMetadata as u8 => StacksMessageID::GetNodeMetadata,
+            x if x == StacksMessageID::NodeMetadata as u8 => StacksMessageID::NodeMetadata,
             _ => {
                 return Err(codec_error::DeserializeError(
                     "Unknown message ID".to_string(),
@@ -935,6 +1140,8 @@ impl StacksMessageCodec for StacksMessageType {
             StacksMessageType::Pong(ref m) => write_next(fd, m)?,
             StacksMessageType::NatPunchRequest(ref nonce) => write_next(fd, nonce)?,
             StacksMessageType::NatPunchReply(ref m) => write_next(fd, m)?,
+            StacksMessageType::GetNodeMetadata => {}
+            StacksMessageType::NodeMetadata(ref m) => write_next(fd, m)?,
         }
         Ok(())
     }
@@ -1012,6 +1219,11 @@ impl StacksMessageCodec for StacksMessageType {
                 let m: NatPunchData = read_next(fd)?;
                 StacksMessageType::NatPunchReply(m)
             }
+            StacksMessageID::GetNodeMetadata => StacksMessageType::GetNodeMetadata,
+            StacksMessageID::NodeMetadata => {
+                let m: NodeMetadataData = read_next(fd)?;
+                StacksMessageType::NodeMetadata(m)
+            }
             StacksMessageID::Reserved => {
                 return Err(codec_error::DeserializeError(
                     "Unsupported message ID 'reserved'".to_string(),
@@ -1509,6 +1721,7 @@ pub mod test {
 
         assert_eq!(preamble_bytes.len() as u32, PREAMBLE_ENCODED_SIZE);
         check_codec_and_corruption::<Preamble>(&preamble, &preamble_bytes);
+        assert_wire_snapshot!("preamble", preamble);
     }
 
     #[test]
@@ -1767,6 +1980,7 @@ pub mod test {
         ];
 
         check_codec_and_corruption::<NeighborsData>(&data, &bytes);
+        assert_wire_snapshot!("neighbors_data", data);
     }
 
     #[test]
@@ -1802,6 +2016,7 @@ pub mod test {
         bytes.extend_from_slice(data.data_url.as_bytes());
 
         check_codec_and_corruption::<HandshakeData>(&data, &bytes);
+        assert_wire_snapshot!("handshake_data", data);
     }
 
     #[test]
@@ -1933,6 +2148,77 @@ pub mod test {
         check_codec_and_corruption::<NatPunchData>(&data, &bytes);
     }
 
+    #[test]
+    fn codec_NodeMetadata() {
+        let data = NodeMetadata {
+            peer_version: 0x18000007,
+            node_version: "v1".to_string(),
+            services: 0x0007,
+            rpc_url: UrlString::try_from("").unwrap(),
+            operator_contact: "ops".to_string(),
+            timestamp: 0x0102030405060708,
+        };
+        let bytes = vec![
+            // peer version
+            0x18, 0x00, 0x00, 0x07, // node version
+            0x02, 0x76, 0x31, // services
+            0x00, 0x07, // RPC URL
+            0x00, // operator contact
+            0x03, 0x6f, 0x70, 0x73, // timestamp
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+        ];
+
+        check_codec_and_corruption::<NodeMetadata>(&data, &bytes);
+
+        // strings are bounded
+        let mut too_long = data.clone();
+        too_long.operator_contact = "a".repeat(MAX_NODE_METADATA_STRING_LEN as usize + 1);
+        let mut buf = vec![];
+        assert!(too_long.consensus_serialize(&mut buf).is_err());
+    }
+
+    #[test]
+    fn node_metadata_sign_verify() {
+        let privkey = Secp256k1PrivateKey::new();
+        let metadata = NodeMetadata {
+            peer_version: 0x18000007,
+            node_version: "stacks-node 2.1".to_string(),
+            services: 0x0007,
+            rpc_url: UrlString::try_from("http://1.2.3.4:20443").unwrap(),
+            operator_contact: "ops@example.com".to_string(),
+            timestamp: 1234567890,
+        };
+
+        let signed = NodeMetadataData::sign(metadata, &privkey).unwrap();
+        assert_eq!(
+            signed
+                .node_public_key
+                .to_public_key()
+                .unwrap()
+                .to_bytes_compressed(),
+            Secp256k1PublicKey::from_private(&privkey).to_bytes_compressed()
+        );
+        signed.verify().unwrap();
+
+        let mut bytes = vec![];
+        signed.consensus_serialize(&mut bytes).unwrap();
+        let decoded = NodeMetadataData::consensus_deserialize(&mut &bytes[..]).unwrap();
+        assert_eq!(decoded, signed);
+        decoded.verify().unwrap();
+
+        // tampering with the record invalidates it
+        let mut tampered = signed.clone();
+        tampered.metadata.operator_contact = "attacker@example.com".to_string();
+        assert!(tampered.verify().is_err());
+
+        // so does claiming someone else's key
+        let mut rekeyed = signed.clone();
+        rekeyed.node_public_key = StacksPublicKeyBuffer::from_public_key(
+            &Secp256k1PublicKey::from_private(&Secp256k1PrivateKey::new()),
+        );
+        assert!(rekeyed.verify().is_err());
+    }
+
     #[test]
     fn codec_StacksMessage() {
         let payloads: Vec<StacksMessageType> = vec![
@@ -2049,6 +2335,21 @@ pub mod test {
                 port: 12345,
                 nonce: 0x12345678,
             }),
+            StacksMessageType::GetNodeMetadata,
+            StacksMessageType::NodeMetadata(
+                NodeMetadataData::sign(
+                    NodeMetadata {
+                        peer_version: 0x18000007,
+                        node_version: "stacks-node 2.1".to_string(),
+                        services: 0x0007,
+                        rpc_url: UrlString::try_from("").unwrap(),
+                        operator_contact: "".to_string(),
+                        timestamp: 0x01020304,
+                    },
+                    &Secp256k1PrivateKey::new(),
+                )
+                .unwrap(),
+            ),
         ];
 
         let mut maximal_relayers: Vec<RelayData> = vec![];
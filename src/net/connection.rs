@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::io;
@@ -56,7 +57,10 @@ use crate::net::neighbors::{
     WALK_MIN_DURATION, WALK_RESET_INTERVAL, WALK_RESET_PROB, WALK_RETRY_COUNT, WALK_STATE_TIMEOUT,
 };
 
-use clarity::vm::{costs::ExecutionCost, types::BOUND_VALUE_SERIALIZATION_HEX};
+use clarity::vm::{
+    costs::ExecutionCost,
+    types::{QualifiedContractIdentifier, BOUND_VALUE_SERIALIZATION_HEX},
+};
 
 use crate::chainstate::burn::ConsensusHash;
 
@@ -317,6 +321,29 @@ struct ConnectionOutbox<P: ProtocolFamily> {
     inflight: VecDeque<ReceiverNotify<P>>,
 }
 
+/// What the block downloader does with blocks elected by leader keys that are banned for
+/// producing consensus-invalid blocks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BannedLeaderKeyPolicy {
+    /// download them like any other block
+    Ignore,
+    /// download them only after the other blocks in the same scan
+    Deprioritize,
+    /// do not download them
+    Refuse,
+}
+
+impl BannedLeaderKeyPolicy {
+    pub fn from_str(s: &str) -> Option<BannedLeaderKeyPolicy> {
+        match s {
+            "ignore" => Some(BannedLeaderKeyPolicy::Ignore),
+            "deprioritize" => Some(BannedLeaderKeyPolicy::Deprioritize),
+            "refuse" => Some(BannedLeaderKeyPolicy::Refuse),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ConnectionOptions {
     pub inbox_maxlen: usize,
@@ -380,6 +407,25 @@ pub struct ConnectionOptions {
     pub mempool_max_tx_query: u64,
     /// how long a mempool sync is allowed to take, in total, before timing out
     pub mempool_sync_timeout: u64,
+    /// maximum number of read-only RPC sessions that may be open at once
+    pub max_rpc_sessions: u64,
+    /// how long, in seconds, a read-only RPC session lives before it expires
+    pub rpc_session_ttl: u64,
+    /// how often, in seconds, to send a heartbeat on a `/v2/stream/blocks` feed that is caught up
+    /// with the chain tip
+    pub block_feed_heartbeat_interval: u64,
+    /// whether or not to accept experimental transaction bundles on `/v2/transactions/bundle`
+    pub accept_tx_bundles: bool,
+    /// maximum number of read-only function call results to cache.  0 disables the cache.
+    pub read_only_call_cache_size: usize,
+    /// contracts whose read-only function call results are never cached
+    pub read_only_call_cache_excluded_contracts: HashSet<QualifiedContractIdentifier>,
+    /// how to reach this node's operator; shared with peers in this node's signed metadata record
+    pub operator_contact: String,
+    /// how often, in seconds, to ask each neighbor for its signed metadata record
+    pub node_metadata_refresh_interval: u64,
+    /// what to do with blocks elected by banned leader keys
+    pub banned_leader_key_policy: BannedLeaderKeyPolicy,
 
     // fault injection
     pub disable_neighbor_walk: bool,
@@ -466,6 +512,15 @@ impl std::default::Default for ConnectionOptions {
             mempool_sync_interval: 30, // number of seconds in-between mempool sync
             mempool_max_tx_query: 128, // maximum number of transactions to visit per mempool query
             mempool_sync_timeout: 180, // how long a mempool sync can go for (3 minutes)
+            max_rpc_sessions: 1024,    // number of pinned read-only RPC sessions
+            rpc_session_ttl: 600,      // how long a pinned read-only RPC session lasts (10 minutes)
+            block_feed_heartbeat_interval: 5, // must be less than idle_timeout
+            accept_tx_bundles: false,
+            read_only_call_cache_size: 0, // read-only call result caching is opt-in
+            read_only_call_cache_excluded_contracts: HashSet::new(),
+            operator_contact: "".to_string(),
+            node_metadata_refresh_interval: 3600, // re-fetch each neighbor's metadata once an hour
+            banned_leader_key_policy: BannedLeaderKeyPolicy::Ignore,
 
             // no faults on by default
             disable_neighbor_walk: false,
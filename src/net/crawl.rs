@@ -0,0 +1,378 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Network crawler.
+//!
+//! Maps the p2p network's topology without running a node.  Starting from a set of seed peers,
+//! the crawler connects to each peer in turn, handshakes with it, asks it for its neighbors, and
+//! then does the same for every neighbor it has not yet visited.  It never syncs blocks, and it
+//! does not listen for inbound connections.
+//!
+//! The crawler has no view of the burnchain, so the burn block heights in its preambles are the
+//! smallest self-consistent ones (a stable height of 0).  Peers treat it like any other stale
+//! peer.
+
+use std::collections::{HashSet, VecDeque};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+use rand::thread_rng;
+use rand::Rng;
+
+use crate::core::{
+    NETWORK_ID_MAINNET, NETWORK_ID_TESTNET, PEER_VERSION_MAINNET, PEER_VERSION_TESTNET,
+};
+use crate::net::Error as net_error;
+use crate::net::{
+    HandshakeAcceptData, HandshakeData, NeighborAddress, PeerAddress, StacksMessage,
+    StacksMessageType,
+};
+use crate::types::chainstate::BurnchainHeaderHash;
+use crate::types::StacksPublicKeyBuffer;
+use crate::util_lib::strings::UrlString;
+use stacks_common::codec::{read_next, write_next};
+use stacks_common::util::hash::Hash160;
+use stacks_common::util::secp256k1::{Secp256k1PrivateKey, Secp256k1PublicKey};
+
+/// How the crawler identifies itself, and how long it waits on each peer
+#[derive(Debug, Clone)]
+pub struct CrawlerConfig {
+    pub network_id: u32,
+    pub peer_version: u32,
+    /// Must match the crawled network's, or peers will reject our preambles
+    pub stable_confirmations: u32,
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    /// Stop after visiting this many peers
+    pub max_peers: usize,
+    /// How many unrelated messages to skip while waiting for a reply
+    pub max_skipped_messages: usize,
+}
+
+impl CrawlerConfig {
+    pub fn mainnet() -> CrawlerConfig {
+        CrawlerConfig {
+            network_id: NETWORK_ID_MAINNET,
+            peer_version: PEER_VERSION_MAINNET,
+            stable_confirmations: 7,
+            connect_timeout: Duration::from_secs(5),
+            read_timeout: Duration::from_secs(10),
+            max_peers: 1000,
+            max_skipped_messages: 8,
+        }
+    }
+
+    pub fn testnet() -> CrawlerConfig {
+        CrawlerConfig {
+            network_id: NETWORK_ID_TESTNET,
+            peer_version: PEER_VERSION_TESTNET,
+            ..CrawlerConfig::mainnet()
+        }
+    }
+}
+
+/// What the crawler learned about one peer.  If the peer could not be reached or did not
+/// complete the handshake, only `addr` and `error` are set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrawledPeer {
+    pub addr: SocketAddr,
+    pub public_key_hash: Option<Hash160>,
+    pub peer_version: Option<u32>,
+    pub network_id: Option<u32>,
+    pub services: Option<u16>,
+    pub data_url: Option<String>,
+    pub heartbeat_interval: Option<u32>,
+    pub expire_block_height: Option<u64>,
+    pub burn_block_height: Option<u64>,
+    pub burn_stable_block_height: Option<u64>,
+    pub neighbors: Vec<NeighborAddress>,
+    pub error: Option<String>,
+}
+
+impl CrawledPeer {
+    fn unreachable(addr: SocketAddr, error: net_error) -> CrawledPeer {
+        CrawledPeer {
+            addr,
+            public_key_hash: None,
+            peer_version: None,
+            network_id: None,
+            services: None,
+            data_url: None,
+            heartbeat_interval: None,
+            expire_block_height: None,
+            burn_block_height: None,
+            burn_stable_block_height: None,
+            neighbors: vec![],
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+pub struct Crawler {
+    config: CrawlerConfig,
+    /// Throwaway identity, used only to sign our messages
+    private_key: Secp256k1PrivateKey,
+}
+
+impl Crawler {
+    pub fn new(config: CrawlerConfig) -> Crawler {
+        Crawler {
+            config,
+            private_key: Secp256k1PrivateKey::new(),
+        }
+    }
+
+    /// Our handshake.  We do not listen, offer no services, and our key never expires.
+    fn handshake_data(&self) -> HandshakeData {
+        HandshakeData {
+            addrbytes: PeerAddress::from_ipv4(0, 0, 0, 0),
+            port: 0,
+            services: 0,
+            node_public_key: StacksPublicKeyBuffer::from_public_key(
+                &Secp256k1PublicKey::from_private(&self.private_key),
+            ),
+            expire_block_height: u64::MAX,
+            data_url: UrlString::try_from("").unwrap(),
+        }
+    }
+
+    /// Send `payload` to the peer and wait for its reply, skipping any unrelated messages the
+    /// peer sends in the meantime.  A NACK or handshake rejection is an error.
+    fn request(
+        &self,
+        sock: &mut TcpStream,
+        payload: StacksMessageType,
+    ) -> Result<StacksMessage, net_error> {
+        let seq = thread_rng().gen::<u32>();
+        let mut msg = StacksMessage::new(
+            self.config.peer_version,
+            self.config.network_id,
+            self.config.stable_confirmations as u64,
+            &BurnchainHeaderHash([0u8; 32]),
+            0,
+            &BurnchainHeaderHash([0u8; 32]),
+            payload,
+        );
+        msg.sign(seq, &self.private_key)?;
+        write_next(sock, &msg)?;
+
+        for _ in 0..=self.config.max_skipped_messages {
+            let reply: StacksMessage = read_next(sock)?;
+            if reply.preamble.seq != seq {
+                continue;
+            }
+            return match reply.payload {
+                StacksMessageType::Nack(ref nack) => {
+                    debug!("Peer NACKed crawler request"; "error_code" => nack.error_code);
+                    Err(net_error::InvalidMessage)
+                }
+                StacksMessageType::HandshakeReject => Err(net_error::InvalidHandshake),
+                _ => Ok(reply),
+            };
+        }
+        Err(net_error::RecvTimeout)
+    }
+
+    fn handshake(
+        &self,
+        sock: &mut TcpStream,
+    ) -> Result<(StacksMessage, HandshakeAcceptData), net_error> {
+        let reply = self.request(sock, StacksMessageType::Handshake(self.handshake_data()))?;
+        let accept = match reply.payload {
+            StacksMessageType::HandshakeAccept(ref accept) => accept.clone(),
+            _ => return Err(net_error::InvalidMessage),
+        };
+        // the peer must actually hold the key it claims
+        reply.verify_secp256k1(&accept.handshake.node_public_key)?;
+        Ok((reply, accept))
+    }
+
+    fn get_neighbors(&self, sock: &mut TcpStream) -> Result<Vec<NeighborAddress>, net_error> {
+        match self.request(sock, StacksMessageType::GetNeighbors)?.payload {
+            StacksMessageType::Neighbors(data) => Ok(data.neighbors),
+            _ => Err(net_error::InvalidMessage),
+        }
+    }
+
+    /// Handshake with the peer at `addr` and ask it for its neighbors
+    pub fn probe(&self, addr: &SocketAddr) -> CrawledPeer {
+        let mut sock = match TcpStream::connect_timeout(addr, self.config.connect_timeout) {
+            Ok(sock) => sock,
+            Err(e) => return CrawledPeer::unreachable(addr.clone(), net_error::ReadError(e)),
+        };
+        if let Err(e) = sock
+            .set_read_timeout(Some(self.config.read_timeout))
+            .and_then(|_| sock.set_write_timeout(Some(self.config.read_timeout)))
+        {
+            return CrawledPeer::unreachable(addr.clone(), net_error::ReadError(e));
+        }
+
+        let (reply, accept) = match self.handshake(&mut sock) {
+            Ok(x) => x,
+            Err(e) => return CrawledPeer::unreachable(addr.clone(), e),
+        };
+        let (neighbors, error) = match self.get_neighbors(&mut sock) {
+            Ok(neighbors) => (neighbors, None),
+            Err(e) => (vec![], Some(e.to_string())),
+        };
+
+        CrawledPeer {
+            addr: addr.clone(),
+            public_key_hash: Some(Hash160::from_node_public_key_buffer(
+                &accept.handshake.node_public_key,
+            )),
+            peer_version: Some(reply.preamble.peer_version),
+            network_id: Some(reply.preamble.network_id),
+            services: Some(accept.handshake.services),
+            data_url: Some(accept.handshake.data_url.to_string()),
+            heartbeat_interval: Some(accept.heartbeat_interval),
+            expire_block_height: Some(accept.handshake.expire_block_height),
+            burn_block_height: Some(reply.preamble.burn_block_height),
+            burn_stable_block_height: Some(reply.preamble.burn_stable_block_height),
+            neighbors,
+            error,
+        }
+    }
+
+    /// Visit `seeds` and, breadth-first, every peer they lead to, until there are no more or
+    /// `max_peers` have been visited.  Peers are returned in the order they were visited.
+    pub fn crawl(&self, seeds: &[SocketAddr]) -> Vec<CrawledPeer> {
+        let mut seen: HashSet<SocketAddr> = seeds.iter().cloned().collect();
+        let mut frontier: VecDeque<SocketAddr> = seeds.iter().cloned().collect();
+        let mut peers = vec![];
+
+        while let Some(addr) = frontier.pop_front() {
+            if peers.len() >= self.config.max_peers {
+                break;
+            }
+            let peer = self.probe(&addr);
+            debug!("Crawled peer";
+                   "addr" => %addr,
+                   "neighbors" => peer.neighbors.len(),
+                   "error" => ?peer.error);
+
+            for neighbor in peer.neighbors.iter() {
+                let neighbor_addr = neighbor.addrbytes.to_socketaddr(neighbor.port);
+                if seen.insert(neighbor_addr.clone()) {
+                    frontier.push_back(neighbor_addr);
+                }
+            }
+            peers.push(peer);
+        }
+        peers
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+    use crate::net::NeighborsData;
+
+    /// Answer one crawler connection as a peer whose only neighbor is `neighbor`
+    fn serve_one_crawl(listener: TcpListener, neighbor: NeighborAddress) {
+        let private_key = Secp256k1PrivateKey::new();
+        let (mut sock, _) = listener.accept().unwrap();
+        for _ in 0..2 {
+            let request: StacksMessage = read_next(&mut sock).unwrap();
+            let payload = match request.payload {
+                StacksMessageType::Handshake(_) => {
+                    StacksMessageType::HandshakeAccept(HandshakeAcceptData {
+                        handshake: HandshakeData {
+                            addrbytes: PeerAddress::from_ipv4(127, 0, 0, 1),
+                            port: 20444,
+                            services: 3,
+                            node_public_key: StacksPublicKeyBuffer::from_public_key(
+                                &Secp256k1PublicKey::from_private(&private_key),
+                            ),
+                            expire_block_height: 1000,
+                            data_url: UrlString::try_from("http://127.0.0.1:20443").unwrap(),
+                        },
+                        heartbeat_interval: 60,
+                    })
+                }
+                StacksMessageType::GetNeighbors => StacksMessageType::Neighbors(NeighborsData {
+                    neighbors: vec![neighbor.clone()],
+                }),
+                _ => panic!("unexpected request {:?}", &request.payload),
+            };
+
+            // send something unsolicited first, which the crawler must skip
+            let mut ping = StacksMessage::new(
+                request.preamble.peer_version,
+                request.preamble.network_id,
+                107,
+                &BurnchainHeaderHash([1u8; 32]),
+                100,
+                &BurnchainHeaderHash([2u8; 32]),
+                StacksMessageType::HandshakeReject,
+            );
+            ping.sign(request.preamble.seq.wrapping_add(1), &private_key)
+                .unwrap();
+            write_next(&mut sock, &ping).unwrap();
+
+            let mut reply = StacksMessage::new(
+                request.preamble.peer_version,
+                request.preamble.network_id,
+                107,
+                &BurnchainHeaderHash([1u8; 32]),
+                100,
+                &BurnchainHeaderHash([2u8; 32]),
+                payload,
+            );
+            reply.sign(request.preamble.seq, &private_key).unwrap();
+            write_next(&mut sock, &reply).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_crawl() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // points at a port nothing listens on
+        let dead_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let dead_addr = dead_listener.local_addr().unwrap();
+        drop(dead_listener);
+
+        let neighbor = NeighborAddress {
+            addrbytes: PeerAddress::from_socketaddr(&dead_addr),
+            port: dead_addr.port(),
+            public_key_hash: Hash160([9u8; 20]),
+        };
+        let server_neighbor = neighbor.clone();
+        let server = thread::spawn(move || serve_one_crawl(listener, server_neighbor));
+
+        let mut config = CrawlerConfig::testnet();
+        config.connect_timeout = Duration::from_secs(1);
+        let peers = Crawler::new(config).crawl(&[addr]);
+        server.join().unwrap();
+
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[0].addr, addr);
+        assert_eq!(peers[0].error, None);
+        assert_eq!(peers[0].services, Some(3));
+        assert_eq!(peers[0].burn_block_height, Some(107));
+        assert_eq!(peers[0].heartbeat_interval, Some(60));
+        assert_eq!(peers[0].neighbors, vec![neighbor]);
+
+        assert_eq!(peers[1].addr, dead_addr);
+        assert!(peers[1].public_key_hash.is_none());
+        assert!(peers[1].error.is_some());
+    }
+}
@@ -37,6 +37,7 @@ use stacks_common::util::hash::{
 };
 use stacks_common::util::log;
 use stacks_common::util::macros::is_big_endian;
+use stacks_common::util::secp256k1::MessageSignature;
 use stacks_common::util::secp256k1::Secp256k1PrivateKey;
 use stacks_common::util::secp256k1::Secp256k1PublicKey;
 
@@ -54,6 +55,8 @@ use crate::net::asn::ASEntry4;
 use crate::net::Neighbor;
 use crate::net::NeighborAddress;
 use crate::net::NeighborKey;
+use crate::net::NodeMetadata;
+use crate::net::NodeMetadataData;
 use crate::net::PeerAddress;
 use crate::net::ServiceFlags;
 
@@ -62,9 +65,10 @@ use crate::burnchains::PublicKey;
 
 use crate::core::NETWORK_P2P_PORT;
 
+use crate::types::StacksPublicKeyBuffer;
 use crate::util_lib::strings::UrlString;
 
-pub const PEERDB_VERSION: &'static str = "1";
+pub const PEERDB_VERSION: &'static str = "2";
 
 const NUM_SLOTS: usize = 8;
 
@@ -238,6 +242,68 @@ impl FromRow<LocalPeer> for LocalPeer {
     }
 }
 
+/// A signed metadata record received from a peer
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeMetadataRecord {
+    /// the address we were talking to when the peer sent us the record
+    pub addr: NeighborKey,
+    pub signed_metadata: NodeMetadataData,
+    /// when we received it
+    pub received_at: u64,
+}
+
+impl FromRow<NodeMetadataRecord> for NodeMetadataRecord {
+    fn from_row<'a>(row: &'a Row) -> Result<NodeMetadataRecord, db_error> {
+        let network_id: u32 = row.get_unwrap("network_id");
+        let addrbytes: PeerAddress = PeerAddress::from_column(row, "addrbytes")?;
+        let port: u16 = row.get_unwrap("port");
+        let public_key_hex: String = row.get_unwrap("public_key");
+        let peer_version: u32 = row.get_unwrap("peer_version");
+        let node_version: String = row.get_unwrap("node_version");
+        let services: u16 = row.get_unwrap("services");
+        let rpc_url_str: String = row.get_unwrap("rpc_url");
+        let operator_contact: String = row.get_unwrap("operator_contact");
+        let timestamp = u64::from_column(row, "timestamp")?;
+        let signature_hex: String = row.get_unwrap("signature");
+        let received_at = u64::from_column(row, "received_at")?;
+
+        let node_public_key = hex_bytes(&public_key_hex)
+            .ok()
+            .and_then(|bytes| StacksPublicKeyBuffer::from_bytes(&bytes))
+            .ok_or_else(|| {
+                error!("Unparseable node metadata public key {}", &public_key_hex);
+                db_error::ParseError
+            })?;
+        let signature = MessageSignature::from_hex(&signature_hex).map_err(|_e| {
+            error!("Unparseable node metadata signature {}", &signature_hex);
+            db_error::ParseError
+        })?;
+        let rpc_url = UrlString::try_from(rpc_url_str).map_err(|_e| db_error::ParseError)?;
+
+        Ok(NodeMetadataRecord {
+            addr: NeighborKey {
+                peer_version,
+                network_id,
+                addrbytes,
+                port,
+            },
+            signed_metadata: NodeMetadataData {
+                metadata: NodeMetadata {
+                    peer_version,
+                    node_version,
+                    services,
+                    rpc_url,
+                    operator_contact,
+                    timestamp,
+                },
+                node_public_key,
+                signature,
+            },
+            received_at,
+        })
+    }
+}
+
 impl FromRow<ASEntry4> for ASEntry4 {
     fn from_row<'a>(row: &'a Row) -> Result<ASEntry4, db_error> {
         let prefix: u32 = row.get_unwrap("prefix");
@@ -360,8 +426,29 @@ const PEERDB_INITIAL_SCHEMA: &'static [&'static str] = &[
     );"#,
 ];
 
-const PEERDB_INDEXES: &'static [&'static str] =
-    &["CREATE INDEX IF NOT EXISTS peer_address_index ON frontier(network_id,addrbytes,port);"];
+const PEERDB_SCHEMA_2: &'static [&'static str] = &[r#"
+    -- latest signed metadata record each peer has sent us
+    CREATE TABLE node_metadata(
+        network_id INTEGER NOT NULL,
+        addrbytes TEXT NOT NULL,
+        port INTEGER NOT NULL,
+        public_key TEXT NOT NULL,
+        peer_version INTEGER NOT NULL,
+        node_version TEXT NOT NULL,
+        services INTEGER NOT NULL,
+        rpc_url TEXT NOT NULL,
+        operator_contact TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        signature TEXT NOT NULL,
+        received_at INTEGER NOT NULL,
+
+        PRIMARY KEY(network_id,addrbytes,port)
+    );"#];
+
+const PEERDB_INDEXES: &'static [&'static str] = &[
+    "CREATE INDEX IF NOT EXISTS peer_address_index ON frontier(network_id,addrbytes,port);",
+    "CREATE INDEX IF NOT EXISTS node_metadata_by_public_key ON node_metadata(public_key);",
+];
 
 #[derive(Debug)]
 pub struct PeerDB {
@@ -398,11 +485,9 @@ impl PeerDB {
             tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
         }
 
-        tx.execute(
-            "INSERT INTO db_config (version) VALUES (?1)",
-            &[&PEERDB_VERSION],
-        )
-        .map_err(db_error::SqliteError)?;
+        tx.execute("INSERT INTO db_config (version) VALUES (?1)", &["1"])
+            .map_err(db_error::SqliteError)?;
+        PeerDB::apply_schema_migrations(&tx)?;
 
         let local_peer_args: &[&dyn ToSql] = &[
             &network_id,
@@ -447,6 +532,39 @@ impl PeerDB {
         Ok(())
     }
 
+    /// Get the database schema version, given a DB connection
+    fn get_schema_version(conn: &Connection) -> Result<String, db_error> {
+        let version = conn
+            .query_row("SELECT MAX(version) FROM db_config", NO_PARAMS, |row| {
+                row.get(0)
+            })
+            .map_err(db_error::SqliteError)?;
+        Ok(version)
+    }
+
+    fn apply_schema_2<'a>(tx: &Transaction<'a>) -> Result<(), db_error> {
+        for row_text in PEERDB_SCHEMA_2 {
+            tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+        }
+        tx.execute("INSERT INTO db_config (version) VALUES (?1)", &["2"])
+            .map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Bring the peer DB schema up to PEERDB_VERSION
+    fn apply_schema_migrations<'a>(tx: &Transaction<'a>) -> Result<(), db_error> {
+        loop {
+            let version = PeerDB::get_schema_version(tx)?;
+            if version == "1" {
+                PeerDB::apply_schema_2(tx)?;
+            } else if version == PEERDB_VERSION {
+                return Ok(());
+            } else {
+                panic!("The schema version of the peer DB is invalid.")
+            }
+        }
+    }
+
     fn add_indexes(&mut self) -> Result<(), db_error> {
         let tx = self.tx_begin()?;
         for row_text in PEERDB_INDEXES {
@@ -584,6 +702,7 @@ impl PeerDB {
 
             {
                 let mut tx = db.tx_begin()?;
+                PeerDB::apply_schema_migrations(&tx)?;
                 PeerDB::refresh_allows(&mut tx)?;
                 PeerDB::refresh_denies(&mut tx)?;
                 PeerDB::clear_initial_peers(&mut tx)?;
@@ -786,6 +905,72 @@ impl PeerDB {
         query_row::<Neighbor, _>(conn, &qry, &args)
     }
 
+    /// Store a signed metadata record a peer sent us, replacing the last one we got from that
+    /// address unless it is newer than this one.  The caller must have verified the signature.
+    /// Returns true if the record was stored.
+    pub fn store_node_metadata<'a>(
+        tx: &mut Transaction<'a>,
+        addr: &NeighborKey,
+        signed_metadata: &NodeMetadataData,
+        received_at: u64,
+    ) -> Result<bool, db_error> {
+        if let Some(existing) =
+            PeerDB::get_node_metadata(tx, addr.network_id, &addr.addrbytes, addr.port)?
+        {
+            if existing.signed_metadata.node_public_key == signed_metadata.node_public_key
+                && existing.signed_metadata.metadata.timestamp >= signed_metadata.metadata.timestamp
+            {
+                return Ok(false);
+            }
+        }
+
+        let metadata = &signed_metadata.metadata;
+        let args: &[&dyn ToSql] = &[
+            &addr.network_id,
+            &addr.addrbytes.to_bin(),
+            &addr.port,
+            &to_hex(signed_metadata.node_public_key.as_bytes()),
+            &metadata.peer_version,
+            &metadata.node_version,
+            &metadata.services,
+            &metadata.rpc_url.as_str(),
+            &metadata.operator_contact,
+            &u64_to_sql(metadata.timestamp)?,
+            &signed_metadata.signature.to_hex(),
+            &u64_to_sql(received_at)?,
+        ];
+        tx.execute("INSERT OR REPLACE INTO node_metadata (network_id, addrbytes, port, public_key, peer_version, node_version, services, rpc_url, operator_contact, timestamp, signature, received_at) VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12)", args)
+            .map_err(db_error::SqliteError)?;
+        Ok(true)
+    }
+
+    /// Get the last metadata record we received from a peer address
+    pub fn get_node_metadata(
+        conn: &DBConn,
+        network_id: u32,
+        peer_addr: &PeerAddress,
+        peer_port: u16,
+    ) -> Result<Option<NodeMetadataRecord>, db_error> {
+        let qry =
+            "SELECT * FROM node_metadata WHERE network_id = ?1 AND addrbytes = ?2 AND port = ?3";
+        let args = [
+            &network_id as &dyn ToSql,
+            &peer_addr.to_bin() as &dyn ToSql,
+            &peer_port as &dyn ToSql,
+        ];
+        query_row::<NodeMetadataRecord, _>(conn, qry, &args)
+    }
+
+    /// Get every metadata record we have received on this network, most recent first
+    pub fn get_all_node_metadata(
+        conn: &DBConn,
+        network_id: u32,
+    ) -> Result<Vec<NodeMetadataRecord>, db_error> {
+        let qry = "SELECT * FROM node_metadata WHERE network_id = ?1 ORDER BY received_at DESC";
+        let args = [&network_id as &dyn ToSql];
+        query_rows::<NodeMetadataRecord, _>(conn, qry, &args)
+    }
+
     /// Get peer by port (used in tests where the IP address doesn't really matter)
     #[cfg(test)]
     pub fn get_peer_by_port(
@@ -1450,6 +1635,82 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_node_metadata_store_and_retrieval() {
+        let mut db = PeerDB::connect_memory(
+            0x9abcdef0,
+            12345,
+            0,
+            "http://foo.com".into(),
+            &vec![],
+            &vec![],
+        )
+        .unwrap();
+
+        let addr = NeighborKey {
+            peer_version: 0x18000007,
+            network_id: 0x9abcdef0,
+            addrbytes: PeerAddress::from_ipv4(1, 2, 3, 4),
+            port: 20444,
+        };
+        let privkey = Secp256k1PrivateKey::new();
+        let make_record = |timestamp: u64, contact: &str| {
+            NodeMetadataData::sign(
+                NodeMetadata {
+                    peer_version: 0x18000007,
+                    node_version: "stacks-node 2.1".to_string(),
+                    services: 0x0007,
+                    rpc_url: "http://1.2.3.4:20443".into(),
+                    operator_contact: contact.to_string(),
+                    timestamp,
+                },
+                &privkey,
+            )
+            .unwrap()
+        };
+
+        assert!(
+            PeerDB::get_node_metadata(db.conn(), 0x9abcdef0, &addr.addrbytes, addr.port)
+                .unwrap()
+                .is_none()
+        );
+
+        let first = make_record(100, "ops@example.com");
+        let stale = make_record(50, "stale@example.com");
+        let newer = make_record(200, "oncall@example.com");
+
+        {
+            let mut tx = db.tx_begin().unwrap();
+            assert!(PeerDB::store_node_metadata(&mut tx, &addr, &first, 1000).unwrap());
+
+            // older records from the same key don't replace newer ones
+            assert!(!PeerDB::store_node_metadata(&mut tx, &addr, &stale, 1001).unwrap());
+            tx.commit().unwrap();
+        }
+
+        let record = PeerDB::get_node_metadata(db.conn(), 0x9abcdef0, &addr.addrbytes, addr.port)
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.signed_metadata, first);
+        assert_eq!(record.received_at, 1000);
+        record.signed_metadata.verify().unwrap();
+
+        {
+            let mut tx = db.tx_begin().unwrap();
+            assert!(PeerDB::store_node_metadata(&mut tx, &addr, &newer, 1002).unwrap());
+            tx.commit().unwrap();
+        }
+
+        let all = PeerDB::get_all_node_metadata(db.conn(), 0x9abcdef0).unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].signed_metadata, newer);
+        assert_eq!(all[0].addr, addr);
+
+        assert!(PeerDB::get_all_node_metadata(db.conn(), 0x9abcdef1)
+            .unwrap()
+            .is_empty());
+    }
+
     #[test]
     fn test_peer_insert_and_retrieval() {
         let neighbor = Neighbor {
@@ -51,6 +51,7 @@ use crate::core::FIRST_STACKS_BLOCK_HASH;
 use crate::net::asn::ASEntry4;
 use crate::net::atlas::AttachmentsDownloader;
 use crate::net::codec::*;
+use crate::net::connection::BannedLeaderKeyPolicy;
 use crate::net::connection::ConnectionOptions;
 use crate::net::connection::ReplyHandleHttp;
 use crate::net::db::PeerDB;
@@ -121,6 +122,8 @@ pub struct BlockRequestKey {
     pub download_start: u64,
     pub kind: BlockRequestKeyKind,
     pub canonical_stacks_tip_height: u64,
+    /// the block was elected by a banned leader key, so try it after the others
+    pub deprioritized: bool,
 }
 
 impl BlockRequestKey {
@@ -148,6 +151,7 @@ impl BlockRequestKey {
             download_start: get_epoch_time_secs(),
             kind,
             canonical_stacks_tip_height,
+            deprioritized: false,
         }
     }
 }
@@ -1102,6 +1106,26 @@ impl PeerNetwork {
         Ok(true)
     }
 
+    /// Was this block elected by a leader key that is banned for producing invalid blocks?
+    fn is_block_from_banned_leader(
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+    ) -> Result<bool, net_error> {
+        match SortitionDB::get_leader_key_for_stacks_block(
+            &sortdb.index_conn(),
+            consensus_hash,
+            block_hash,
+        )? {
+            Some(leader_key) => Ok(StacksChainState::is_leader_key_banned(
+                chainstate.db(),
+                &leader_key.public_key,
+            )?),
+            None => Ok(false),
+        }
+    }
+
     /// Are we able to download a microblock stream between two blocks at this time?
     pub fn can_download_microblock_stream(
         _local_peer: &LocalPeer,
@@ -1271,6 +1295,7 @@ impl PeerNetwork {
 
             let mut parent_block_header_opt = None;
             let mut parent_consensus_hash_opt = None;
+            let mut deprioritized = false;
 
             let index_block_hash =
                 StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash);
@@ -1306,6 +1331,29 @@ impl PeerNetwork {
                     &self.local_peer, &consensus_hash, &block_hash, &index_block_hash
                 );
 
+                let policy = self.connection_opts.banned_leader_key_policy;
+                if policy != BannedLeaderKeyPolicy::Ignore
+                    && PeerNetwork::is_block_from_banned_leader(
+                        sortdb,
+                        chainstate,
+                        &consensus_hash,
+                        &block_hash,
+                    )?
+                {
+                    if policy == BannedLeaderKeyPolicy::Refuse {
+                        debug!(
+                            "{:?}: Will not request anchored block {}/{}: elected by a banned leader key",
+                            &self.local_peer, &consensus_hash, &block_hash
+                        );
+                        continue;
+                    }
+                    debug!(
+                        "{:?}: Deprioritize anchored block {}/{}: elected by a banned leader key",
+                        &self.local_peer, &consensus_hash, &block_hash
+                    );
+                    deprioritized = true;
+                }
+
                 (consensus_hash, block_hash)
             } else {
                 // asking for microblocks
@@ -1517,7 +1565,7 @@ impl PeerNetwork {
                     &target_block_hash
                 );
 
-                let request = BlockRequestKey::new(
+                let mut request = BlockRequestKey::new(
                     nk,
                     data_url,
                     target_consensus_hash.clone(),
@@ -1533,6 +1581,7 @@ impl PeerNetwork {
                     },
                     self.burnchain_tip.canonical_stacks_tip_height,
                 );
+                request.deprioritized = deprioritized;
                 requests.push_back(request);
             }
 
@@ -1570,14 +1619,16 @@ impl PeerNetwork {
         self.make_requests(sortdb, chainstate, downloader, start_sortition_height, true)
     }
 
-    /// Prioritize block requests -- ask for the rarest blocks first
+    /// Prioritize block requests -- ask for the rarest blocks first, and for blocks elected by
+    /// banned leader keys last
     fn prioritize_requests(requests: &HashMap<u64, VecDeque<BlockRequestKey>>) -> Vec<u64> {
         let mut ordered = vec![];
         for (block_height, requests) in requests.iter() {
-            ordered.push((*block_height, requests.len()));
+            let deprioritized = requests.front().map(|r| r.deprioritized).unwrap_or(false);
+            ordered.push((*block_height, deprioritized, requests.len()));
         }
-        ordered.sort_by(|(_, ref l1), (_, ref l2)| l1.cmp(l2));
-        ordered.iter().map(|(ref h, _)| *h).collect()
+        ordered.sort_by(|(_, d1, l1), (_, d2, l2)| (d1, l1).cmp(&(d2, l2)));
+        ordered.iter().map(|(ref h, ..)| *h).collect()
     }
 
     /// Go start resolving block URLs to their IP addresses
@@ -2643,6 +2694,47 @@ pub mod test {
         availability
     }
 
+    #[test]
+    fn test_prioritize_requests_deprioritized_last() {
+        let make_requests = |sortition_height: u64, num_neighbors: u16, deprioritized: bool| {
+            let mut requests = VecDeque::new();
+            for i in 0..num_neighbors {
+                let mut request = BlockRequestKey::new(
+                    NeighborKey {
+                        peer_version: 0x18000000,
+                        network_id: 0x80000000,
+                        addrbytes: PeerAddress::from_ipv4(127, 0, 0, 1),
+                        port: 20000 + i,
+                    },
+                    "http://127.0.0.1:20443".into(),
+                    ConsensusHash([sortition_height as u8; 20]),
+                    BlockHeaderHash([sortition_height as u8; 32]),
+                    StacksBlockId([sortition_height as u8; 32]),
+                    None,
+                    None,
+                    sortition_height,
+                    BlockRequestKeyKind::Block,
+                    0,
+                );
+                request.deprioritized = deprioritized;
+                requests.push_back(request);
+            }
+            requests
+        };
+
+        let mut requests = HashMap::new();
+        requests.insert(1, make_requests(1, 3, false));
+        requests.insert(2, make_requests(2, 1, true));
+        requests.insert(3, make_requests(3, 2, false));
+        requests.insert(4, make_requests(4, 2, true));
+
+        // rarest first, but blocks from banned leader keys after all the others
+        assert_eq!(
+            PeerNetwork::prioritize_requests(&requests),
+            vec![3, 1, 2, 4]
+        );
+    }
+
     #[test]
     fn test_get_block_availability() {
         with_timeout(600, || {
@@ -3378,7 +3470,7 @@ pub mod test {
                         );
 
                         let mut builder = StacksBlockBuilder::make_block_builder(
-                            chainstate.mainnet,
+                            chainstate.network.mainnet,
                             &parent_tip,
                             vrfproof,
                             tip.total_burn,
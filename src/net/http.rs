@@ -30,7 +30,7 @@ use std::str::FromStr;
 use std::time::SystemTime;
 
 use clarity::vm::representations::MAX_STRING_LEN;
-use percent_encoding::percent_decode_str;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
 use regex::{Captures, Regex};
 use serde::{Deserialize, Serialize};
 use serde_json;
@@ -40,10 +40,12 @@ use url::{form_urlencoded, Url};
 use crate::burnchains::{Address, Txid};
 use crate::chainstate::burn::ConsensusHash;
 use crate::chainstate::stacks::{
-    StacksBlock, StacksMicroblock, StacksPublicKey, StacksTransaction,
+    StacksBlock, StacksMicroblock, StacksPublicKey, StacksTransaction, StacksTransactionBundle,
 };
 use crate::deps::httparse;
 use crate::net::atlas::Attachment;
+use crate::net::pagination::{PageRequest, RPCList, RPCPage};
+use crate::net::session::RPCSessionId;
 use crate::net::ClientError;
 use crate::net::Error as net_error;
 use crate::net::Error::ClarityError;
@@ -71,7 +73,11 @@ use crate::net::HTTP_PREAMBLE_MAX_NUM_HEADERS;
 use crate::net::HTTP_REQUEST_ID_RESERVED;
 use crate::net::MAX_HEADERS;
 use crate::net::MAX_MICROBLOCKS_UNCONFIRMED;
-use crate::net::{CallReadOnlyRequestBody, TipRequest};
+use crate::net::{BlockFeedEncoding, BlockFeedRequest, BlockFeedStart};
+use crate::net::{
+    CallPreviewResponse, CallReadOnlyBinaryRequestBody, CallReadOnlyRequestBody, TipRequest,
+    TransactionSimulationResponse,
+};
 use crate::net::{GetAttachmentResponse, GetAttachmentsInvResponse, PostTransactionRequestBody};
 use clarity::vm::ast::parser::v1::CLARITY_NAME_REGEX;
 use clarity::vm::types::{StandardPrincipalData, TraitIdentifier};
@@ -85,6 +91,7 @@ use clarity::vm::{
 use stacks_common::util::hash::hex_bytes;
 use stacks_common::util::hash::to_hex;
 use stacks_common::util::hash::Hash160;
+use stacks_common::util::hash::Sha512Trunc256Sum;
 use stacks_common::util::log;
 use stacks_common::util::retry::BoundReader;
 use stacks_common::util::retry::RetryReader;
@@ -103,18 +110,43 @@ lazy_static! {
     static ref PATH_GETINFO: Regex = Regex::new(r#"^/v2/info$"#).unwrap();
     static ref PATH_GETPOXINFO: Regex = Regex::new(r#"^/v2/pox$"#).unwrap();
     static ref PATH_GETNEIGHBORS: Regex = Regex::new(r#"^/v2/neighbors$"#).unwrap();
-    static ref PATH_GETHEADERS: Regex = Regex::new(r#"^/v2/headers/([0-9]+)$"#).unwrap();
+    static ref PATH_GET_NODE_METADATA: Regex =
+        Regex::new(r#"^/v2/neighbors/metadata$"#).unwrap();
+    static ref PATH_GET_BANNED_LEADER_KEYS: Regex = Regex::new(r#"^/v2/miners/banned$"#).unwrap();
+    static ref PATH_GET_TIP_SUMMARY: Regex = Regex::new(r#"^/v2/summary/tip$"#).unwrap();
+    static ref PATH_GET_BLOCKS_SUMMARY: Regex = Regex::new(r#"^/v2/summary/blocks$"#).unwrap();
+    static ref PATH_GET_MEMPOOL_SUMMARY: Regex = Regex::new(r#"^/v2/summary/mempool$"#).unwrap();
+    static ref PATH_GET_CHAIN_QUALITY: Regex =
+        Regex::new(r#"^/v2/summary/chain_quality$"#).unwrap();
+    static ref PATH_GET_PEERS_SUMMARY: Regex = Regex::new(r#"^/v2/summary/peers$"#).unwrap();
+    static ref PATH_POST_SESSION: Regex = Regex::new(r#"^/v2/sessions$"#).unwrap();
+    static ref PATH_POST_RELEASE_SESSION: Regex =
+        Regex::new(r#"^/v2/sessions/([0-9a-f]{32})/release$"#).unwrap();
+    static ref PATH_GETHEADERS: Regex = Regex::new(r#"^/v2/headers(/([0-9]+))?$"#).unwrap();
+    static ref PATH_STREAM_BLOCKS: Regex = Regex::new(r#"^/v2/stream/blocks$"#).unwrap();
     static ref PATH_GETBLOCK: Regex = Regex::new(r#"^/v2/blocks/([0-9a-f]{64})$"#).unwrap();
+    static ref PATH_GET_BLOCK_WEIGHT: Regex =
+        Regex::new(r#"^/v2/blocks/([0-9a-f]{64})/weight$"#).unwrap();
+    static ref PATH_GET_STAGING_QUEUE: Regex = Regex::new(r#"^/v2/blocks/staging$"#).unwrap();
     static ref PATH_GETMICROBLOCKS_INDEXED: Regex =
         Regex::new(r#"^/v2/microblocks/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_GETMICROBLOCKS_CONFIRMED: Regex =
         Regex::new(r#"^/v2/microblocks/confirmed/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_GETMICROBLOCKS_UNCONFIRMED: Regex =
         Regex::new(r#"^/v2/microblocks/unconfirmed/([0-9a-f]{64})/([0-9]{1,5})$"#).unwrap();
+    static ref PATH_GET_MICROBLOCK_FORKS: Regex =
+        Regex::new(r#"^/v2/microblocks/forks/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_GETTRANSACTION_UNCONFIRMED: Regex =
         Regex::new(r#"^/v2/transactions/unconfirmed/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_POSTTRANSACTION: Regex = Regex::new(r#"^/v2/transactions$"#).unwrap();
+    static ref PATH_POSTTRANSACTION_BUNDLE: Regex =
+        Regex::new(r#"^/v2/transactions/bundle$"#).unwrap();
+    static ref PATH_POST_SIMULATE_TRANSACTION: Regex =
+        Regex::new(r#"^/v2/transactions/simulate$"#).unwrap();
     static ref PATH_POST_FEE_RATE_ESIMATE: Regex = Regex::new(r#"^/v2/fees/transaction$"#).unwrap();
+    static ref PATH_GET_BLOCK_FEE_STATS: Regex =
+        Regex::new(r#"^/v2/fees/blocks(/([0-9]+))?$"#).unwrap();
+    static ref PATH_GET_SUPPLY: Regex = Regex::new(r#"^/v2/supply/([0-9]+)$"#).unwrap();
     static ref PATH_POSTBLOCK: Regex = Regex::new(r#"^/v2/blocks/upload/([0-9a-f]{40})$"#).unwrap();
     static ref PATH_POSTMICROBLOCK: Regex = Regex::new(r#"^/v2/microblocks$"#).unwrap();
     static ref PATH_GET_ACCOUNT: Regex = Regex::new(&format!(
@@ -132,11 +164,23 @@ lazy_static! {
         *STANDARD_PRINCIPAL_REGEX_STRING, *CONTRACT_NAME_REGEX_STRING, *CLARITY_NAME_REGEX
     ))
     .unwrap();
+    static ref PATH_GET_CLARITY_MARF_VALUE: Regex =
+        Regex::new(r#"^/v2/clarity/marf/(?P<key>.{1,1024})$"#).unwrap();
+    static ref PATH_GET_CLARITY_METADATA: Regex = Regex::new(&format!(
+        "^/v2/clarity/metadata/(?P<address>{})/(?P<contract>{})/(?P<key>.{{1,1024}})$",
+        *STANDARD_PRINCIPAL_REGEX_STRING, *CONTRACT_NAME_REGEX_STRING
+    ))
+    .unwrap();
     static ref PATH_POST_CALL_READ_ONLY: Regex = Regex::new(&format!(
         "^/v2/contracts/call-read/(?P<address>{})/(?P<contract>{})/(?P<function>{})$",
         *STANDARD_PRINCIPAL_REGEX_STRING, *CONTRACT_NAME_REGEX_STRING, *CLARITY_NAME_REGEX
     ))
     .unwrap();
+    static ref PATH_POST_CALL_PREVIEW: Regex = Regex::new(&format!(
+        "^/v2/contracts/call-preview/(?P<address>{})/(?P<contract>{})/(?P<function>{})$",
+        *STANDARD_PRINCIPAL_REGEX_STRING, *CONTRACT_NAME_REGEX_STRING, *CLARITY_NAME_REGEX
+    ))
+    .unwrap();
     static ref PATH_GET_CONTRACT_SRC: Regex = Regex::new(&format!(
         "^/v2/contracts/source/(?P<address>{})/(?P<contract>{})$",
         *STANDARD_PRINCIPAL_REGEX_STRING, *CONTRACT_NAME_REGEX_STRING
@@ -649,6 +693,15 @@ impl<'a, 'state, W: Write> HttpChunkedTransferWriter<'a, 'state, W> {
     pub fn corked(&self) -> bool {
         self.state.corked
     }
+
+    /// Send out any buffered chunk data, without ending the stream.
+    /// Used by streams that only produce data every so often, so it doesn't sit in the buffer.
+    pub fn send_buffered_chunk(&mut self) -> io::Result<()> {
+        if !self.state.corked && self.state.chunk_buf.len() > 0 {
+            self.flush_chunk()?;
+        }
+        Ok(())
+    }
 }
 
 impl<'a, 'state, W: Write> Write for HttpChunkedTransferWriter<'a, 'state, W> {
@@ -1504,8 +1557,68 @@ impl HttpRequestType {
                 &PATH_GETNEIGHBORS,
                 &HttpRequestType::parse_getneighbors,
             ),
+            (
+                "GET",
+                &PATH_GET_NODE_METADATA,
+                &HttpRequestType::parse_get_node_metadata,
+            ),
+            (
+                "GET",
+                &PATH_GET_BANNED_LEADER_KEYS,
+                &HttpRequestType::parse_get_banned_leader_keys,
+            ),
+            (
+                "GET",
+                &PATH_GET_TIP_SUMMARY,
+                &HttpRequestType::parse_get_tip_summary,
+            ),
+            (
+                "GET",
+                &PATH_GET_BLOCKS_SUMMARY,
+                &HttpRequestType::parse_get_blocks_summary,
+            ),
+            (
+                "GET",
+                &PATH_GET_MEMPOOL_SUMMARY,
+                &HttpRequestType::parse_get_mempool_summary,
+            ),
+            (
+                "GET",
+                &PATH_GET_CHAIN_QUALITY,
+                &HttpRequestType::parse_get_chain_quality,
+            ),
+            (
+                "GET",
+                &PATH_GET_PEERS_SUMMARY,
+                &HttpRequestType::parse_get_peers_summary,
+            ),
+            (
+                "POST",
+                &PATH_POST_SESSION,
+                &HttpRequestType::parse_open_session,
+            ),
+            (
+                "POST",
+                &PATH_POST_RELEASE_SESSION,
+                &HttpRequestType::parse_release_session,
+            ),
             ("GET", &PATH_GETHEADERS, &HttpRequestType::parse_getheaders),
+            (
+                "GET",
+                &PATH_STREAM_BLOCKS,
+                &HttpRequestType::parse_stream_blocks,
+            ),
             ("GET", &PATH_GETBLOCK, &HttpRequestType::parse_getblock),
+            (
+                "GET",
+                &PATH_GET_BLOCK_WEIGHT,
+                &HttpRequestType::parse_get_block_weight,
+            ),
+            (
+                "GET",
+                &PATH_GET_STAGING_QUEUE,
+                &HttpRequestType::parse_get_staging_queue,
+            ),
             (
                 "GET",
                 &PATH_GETMICROBLOCKS_INDEXED,
@@ -1521,6 +1634,11 @@ impl HttpRequestType {
                 &PATH_GETMICROBLOCKS_UNCONFIRMED,
                 &HttpRequestType::parse_getmicroblocks_unconfirmed,
             ),
+            (
+                "GET",
+                &PATH_GET_MICROBLOCK_FORKS,
+                &HttpRequestType::parse_get_microblock_forks,
+            ),
             (
                 "GET",
                 &PATH_GETTRANSACTION_UNCONFIRMED,
@@ -1531,11 +1649,27 @@ impl HttpRequestType {
                 &PATH_POST_FEE_RATE_ESIMATE,
                 &HttpRequestType::parse_post_fee_rate_estimate,
             ),
+            (
+                "GET",
+                &PATH_GET_BLOCK_FEE_STATS,
+                &HttpRequestType::parse_get_block_fee_stats,
+            ),
+            ("GET", &PATH_GET_SUPPLY, &HttpRequestType::parse_get_supply),
             (
                 "POST",
                 &PATH_POSTTRANSACTION,
                 &HttpRequestType::parse_posttransaction,
             ),
+            (
+                "POST",
+                &PATH_POSTTRANSACTION_BUNDLE,
+                &HttpRequestType::parse_posttransaction_bundle,
+            ),
+            (
+                "POST",
+                &PATH_POST_SIMULATE_TRANSACTION,
+                &HttpRequestType::parse_simulate_transaction,
+            ),
             ("POST", &PATH_POSTBLOCK, &HttpRequestType::parse_postblock),
             (
                 "POST",
@@ -1557,6 +1691,16 @@ impl HttpRequestType {
                 &PATH_GET_MAP_ENTRY,
                 &HttpRequestType::parse_get_map_entry,
             ),
+            (
+                "GET",
+                &PATH_GET_CLARITY_MARF_VALUE,
+                &HttpRequestType::parse_get_clarity_marf_value,
+            ),
+            (
+                "GET",
+                &PATH_GET_CLARITY_METADATA,
+                &HttpRequestType::parse_get_clarity_metadata,
+            ),
             (
                 "GET",
                 &PATH_GET_TRANSFER_COST,
@@ -1582,6 +1726,11 @@ impl HttpRequestType {
                 &PATH_POST_CALL_READ_ONLY,
                 &HttpRequestType::parse_call_read_only,
             ),
+            (
+                "POST",
+                &PATH_POST_CALL_PREVIEW,
+                &HttpRequestType::parse_call_preview,
+            ),
             (
                 "OPTIONS",
                 &PATH_OPTIONS_WILDCARD,
@@ -1680,7 +1829,7 @@ impl HttpRequestType {
             ));
         }
 
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
 
         Ok(HttpRequestType::GetPoxInfo(
             HttpRequestMetadata::from_preamble(preamble),
@@ -1692,7 +1841,7 @@ impl HttpRequestType {
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         _regex: &Captures,
-        _query: Option<&str>,
+        query: Option<&str>,
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
@@ -1703,6 +1852,198 @@ impl HttpRequestType {
 
         Ok(HttpRequestType::GetNeighbors(
             HttpRequestMetadata::from_preamble(preamble),
+            PageRequest::from_query(query)?,
+        ))
+    }
+
+    fn parse_get_node_metadata<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetNodeMetadata".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetNodeMetadata(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    fn parse_get_banned_leader_keys<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetBannedLeaderKeys".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetBannedLeaderKeys(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    fn parse_get_tip_summary<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetTipSummary".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetTipSummary(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    fn parse_get_blocks_summary<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetBlocksSummary".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetBlocksSummary(
+            HttpRequestMetadata::from_preamble(preamble),
+            PageRequest::from_query(query)?,
+        ))
+    }
+
+    fn parse_get_mempool_summary<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMempoolSummary".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetMempoolSummary(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    fn parse_get_chain_quality<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetChainQuality".to_string(),
+            ));
+        }
+
+        let mut window = None;
+        if let Some(query_string) = query {
+            for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
+                if key == "window" {
+                    window = Some(value.parse::<u64>().map_err(|_| {
+                        net_error::DeserializeError("Failed to parse window".to_string())
+                    })?);
+                }
+            }
+        }
+
+        Ok(HttpRequestType::GetChainQuality(
+            HttpRequestMetadata::from_preamble(preamble),
+            window,
+        ))
+    }
+
+    fn parse_get_peers_summary<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetPeersSummary".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetPeersSummary(
+            HttpRequestMetadata::from_preamble(preamble),
+            PageRequest::from_query(query)?,
+        ))
+    }
+
+    fn parse_open_session<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for OpenSession".to_string(),
+            ));
+        }
+
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
+
+        Ok(HttpRequestType::OpenSession(
+            HttpRequestMetadata::from_preamble(preamble),
+            tip,
+        ))
+    }
+
+    fn parse_release_session<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for ReleaseSession".to_string(),
+            ));
+        }
+
+        let session_id = regex
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to session ID".to_string(),
+            ))
+            .and_then(|session_id_str| {
+                RPCSessionId::from_hex(session_id_str.as_str()).map_err(|_e| {
+                    net_error::DeserializeError("Failed to parse session ID".to_string())
+                })
+            })?;
+
+        Ok(HttpRequestType::ReleaseSession(
+            HttpRequestMetadata::from_preamble(preamble),
+            session_id,
         ))
     }
 
@@ -1739,27 +2080,51 @@ impl HttpRequestType {
         !no_proof
     }
 
-    /// get the chain tip optional query argument (`tip`)
-    /// Take the first value we can parse.
-    fn get_chain_tip_query(query: Option<&str>) -> TipRequest {
+    /// Check whether the given option query string sets unanchored=true (asking for unconfirmed
+    /// microblock state alongside the anchored state).  Defaults to false.
+    fn get_unanchored_query(query: Option<&str>) -> bool {
+        if let Some(query_string) = query {
+            form_urlencoded::parse(query_string.as_bytes())
+                .find(|(key, _v)| key == "unanchored")
+                .map(|(_k, value)| value == "true" || value == "1")
+                .unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
+    /// get the chain tip optional query argument (`tip`), or the read-only session whose pinned
+    /// tip should be used instead (`session`).
+    /// Take the first value we can parse.  A session ID that can't be parsed is a client error,
+    /// since falling back to an unpinned tip would silently break the session's consistency.
+    fn get_chain_tip_query(query: Option<&str>) -> Result<TipRequest, net_error> {
         match query {
             Some(query_string) => {
                 for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
+                    if key == "session" {
+                        let session_id = RPCSessionId::from_hex(&value).map_err(|_| {
+                            net_error::ClientError(ClientError::Message(format!(
+                                "Invalid session ID '{}'",
+                                &value
+                            )))
+                        })?;
+                        return Ok(TipRequest::Session(session_id));
+                    }
                     if key != "tip" {
                         continue;
                     }
 
                     if value == "latest" {
-                        return TipRequest::UseLatestUnconfirmedTip;
+                        return Ok(TipRequest::UseLatestUnconfirmedTip);
                     }
                     if let Ok(tip) = StacksBlockId::from_hex(&value) {
-                        return TipRequest::SpecificTip(tip);
+                        return Ok(TipRequest::SpecificTip(tip));
                     }
                 }
-                return TipRequest::UseLatestAnchoredTip;
+                return Ok(TipRequest::UseLatestAnchoredTip);
             }
             None => {
-                return TipRequest::UseLatestAnchoredTip;
+                return Ok(TipRequest::UseLatestAnchoredTip);
             }
         }
     }
@@ -1803,13 +2168,20 @@ impl HttpRequestType {
         })?;
 
         let with_proof = HttpRequestType::get_proof_query(query);
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
+        let unanchored = HttpRequestType::get_unanchored_query(query);
+        if unanchored && with_proof {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: unanchored=true requires proof=0".to_string(),
+            ));
+        }
 
         Ok(HttpRequestType::GetAccount(
             HttpRequestMetadata::from_preamble(preamble),
             principal,
             tip,
             with_proof,
+            unanchored,
         ))
     }
 
@@ -1837,7 +2209,7 @@ impl HttpRequestType {
             .map_err(|_e| net_error::DeserializeError("Failed to parse data var name".into()))?;
 
         let with_proof = HttpRequestType::get_proof_query(query);
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
 
         Ok(HttpRequestType::GetDataVar(
             HttpRequestMetadata::from_preamble(preamble),
@@ -1849,45 +2221,104 @@ impl HttpRequestType {
         ))
     }
 
-    fn parse_get_map_entry<R: Read>(
+    fn parse_get_clarity_marf_value<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         captures: &Captures,
         query: Option<&str>,
-        fd: &mut R,
+        _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
         let content_len = preamble.get_content_length();
-        if !(content_len > 0 && content_len < (BOUND_VALUE_SERIALIZATION_HEX)) {
+        if content_len != 0 {
             return Err(net_error::DeserializeError(format!(
-                "Invalid Http request: invalid body length for GetMapEntry ({})",
+                "Invalid Http request: invalid body length for GetClarityMarfValue ({})",
                 content_len
             )));
         }
 
-        if preamble.content_type != Some(HttpContentType::JSON) {
-            return Err(net_error::DeserializeError(
-                "Invalid content-type: expected application/json".into(),
-            ));
-        }
-
-        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
-            net_error::DeserializeError("Failed to parse contract address".into())
-        })?;
-        let contract_name = ContractName::try_from(captures["contract"].to_string())
-            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
-        let map_name = ClarityName::try_from(captures["map"].to_string())
-            .map_err(|_e| net_error::DeserializeError("Failed to parse map name".into()))?;
-
-        let value_hex: String = serde_json::from_reader(fd)
-            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
-
-        let value = Value::try_deserialize_hex_untyped(&value_hex)
-            .map_err(|_e| net_error::DeserializeError("Failed to deserialize key value".into()))?;
-
+        let key = captures["key"].to_string();
         let with_proof = HttpRequestType::get_proof_query(query);
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
 
-        Ok(HttpRequestType::GetMapEntry(
+        Ok(HttpRequestType::GetClarityMarfValue(
+            HttpRequestMetadata::from_preamble(preamble),
+            key,
+            tip,
+            with_proof,
+        ))
+    }
+
+    fn parse_get_clarity_metadata<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if content_len != 0 {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for GetClarityMetadata ({})",
+                content_len
+            )));
+        }
+
+        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
+            net_error::DeserializeError("Failed to parse contract address".into())
+        })?;
+        let contract_name = ContractName::try_from(captures["contract"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        let key = captures["key"].to_string();
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
+
+        Ok(HttpRequestType::GetClarityMetadata(
+            HttpRequestMetadata::from_preamble(preamble),
+            contract_addr,
+            contract_name,
+            key,
+            tip,
+        ))
+    }
+
+    fn parse_get_map_entry<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < (BOUND_VALUE_SERIALIZATION_HEX)) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for GetMapEntry ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".into(),
+            ));
+        }
+
+        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
+            net_error::DeserializeError("Failed to parse contract address".into())
+        })?;
+        let contract_name = ContractName::try_from(captures["contract"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        let map_name = ClarityName::try_from(captures["map"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse map name".into()))?;
+
+        let value_hex: String = serde_json::from_reader(fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        let value = Value::try_deserialize_hex_untyped(&value_hex)
+            .map_err(|_e| net_error::DeserializeError("Failed to deserialize key value".into()))?;
+
+        let with_proof = HttpRequestType::get_proof_query(query);
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
+
+        Ok(HttpRequestType::GetMapEntry(
             HttpRequestMetadata::from_preamble(preamble),
             contract_addr,
             contract_name,
@@ -1913,12 +2344,6 @@ impl HttpRequestType {
             )));
         }
 
-        if preamble.content_type != Some(HttpContentType::JSON) {
-            return Err(net_error::DeserializeError(
-                "Invalid content-type: expected application/json".to_string(),
-            ));
-        }
-
         let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
             net_error::DeserializeError("Failed to parse contract address".into())
         })?;
@@ -1927,6 +2352,47 @@ impl HttpRequestType {
         let func_name = ClarityName::try_from(captures["function"].to_string())
             .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
 
+        let (sender, sponsor, arguments) = match preamble.content_type {
+            Some(HttpContentType::JSON) => HttpRequestType::parse_call_read_only_json(fd)?,
+            Some(HttpContentType::Bytes) => {
+                // arguments are read one by one straight from the body, and none of them
+                // can read past it
+                let mut bound_fd = BoundReader::from_reader(fd, content_len as u64);
+                let body: CallReadOnlyBinaryRequestBody = read_next(&mut bound_fd)?;
+                if bound_fd.num_read() != content_len as u64 {
+                    return Err(net_error::DeserializeError(
+                        "Invalid Http request: trailing bytes after CallReadOnly arguments"
+                            .to_string(),
+                    ));
+                }
+                (body.sender, body.sponsor, body.arguments)
+            }
+            _ => {
+                return Err(net_error::DeserializeError(
+                    "Invalid content-type: expected application/json or application/octet-stream"
+                        .to_string(),
+                ));
+            }
+        };
+
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
+
+        Ok(HttpRequestType::CallReadOnlyFunction(
+            HttpRequestMetadata::from_preamble(preamble),
+            contract_addr,
+            contract_name,
+            sender,
+            sponsor,
+            func_name,
+            arguments,
+            tip,
+        ))
+    }
+
+    /// Decode the sender, sponsor, and hex-encoded arguments of a read-only call's JSON body
+    fn parse_call_read_only_json<R: Read>(
+        fd: &mut R,
+    ) -> Result<(PrincipalData, Option<PrincipalData>, Vec<Value>), net_error> {
         let body: CallReadOnlyRequestBody = serde_json::from_reader(fd)
             .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
 
@@ -1950,18 +2416,39 @@ impl HttpRequestType {
                 net_error::DeserializeError("Failed to deserialize argument value".into())
             })?;
 
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        Ok((sender, sponsor, arguments))
+    }
 
-        Ok(HttpRequestType::CallReadOnlyFunction(
-            HttpRequestMetadata::from_preamble(preamble),
-            contract_addr,
-            contract_name,
-            sender,
-            sponsor,
-            func_name,
-            arguments,
-            tip,
-        ))
+    /// A call preview takes the same arguments as a read-only call
+    fn parse_call_preview<R: Read>(
+        protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        match HttpRequestType::parse_call_read_only(protocol, preamble, captures, query, fd)? {
+            HttpRequestType::CallReadOnlyFunction(
+                md,
+                contract_addr,
+                contract_name,
+                sender,
+                sponsor,
+                func_name,
+                arguments,
+                tip,
+            ) => Ok(HttpRequestType::CallPreviewFunction(
+                md,
+                contract_addr,
+                contract_name,
+                sender,
+                sponsor,
+                func_name,
+                arguments,
+                tip,
+            )),
+            _ => unreachable!("parse_call_read_only only produces CallReadOnlyFunction"),
+        }
     }
 
     fn parse_get_contract_arguments(
@@ -1994,7 +2481,7 @@ impl HttpRequestType {
         query: Option<&str>,
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
         HttpRequestType::parse_get_contract_arguments(preamble, captures).map(
             |(preamble, addr, name)| HttpRequestType::GetContractABI(preamble, addr, name, tip),
         )
@@ -2008,7 +2495,7 @@ impl HttpRequestType {
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
         let with_proof = HttpRequestType::get_proof_query(query);
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
         HttpRequestType::parse_get_contract_arguments(preamble, captures).map(
             |(preamble, addr, name)| {
                 HttpRequestType::GetContractSrc(preamble, addr, name, tip, with_proof)
@@ -2023,7 +2510,7 @@ impl HttpRequestType {
         query: Option<&str>,
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
                 "Invalid Http request: expected 0-length body".to_string(),
@@ -2055,6 +2542,44 @@ impl HttpRequestType {
         ))
     }
 
+    /// Get the pagination arguments of a list endpoint whose path may end in an item count, as
+    /// its older form always did (e.g. `/v2/headers/:count`).  The count becomes the page's
+    /// `limit`, but it doesn't ask for a page on its own, so the endpoint keeps answering with its
+    /// original response shape.
+    fn get_page_query_with_count(
+        captures: &Captures,
+        query: Option<&str>,
+        item_name: &str,
+    ) -> Result<PageRequest, net_error> {
+        let mut page = PageRequest::from_query(query)?;
+        if let Some(count_str) = captures.get(2) {
+            if page.limit.is_some() {
+                return Err(net_error::DeserializeError(format!(
+                    "Invalid Http request: {} count given in both path and `limit`",
+                    item_name
+                )));
+            }
+            let count: u64 = count_str.as_str().parse().map_err(|_| {
+                net_error::DeserializeError(format!("Failed to parse {} count", item_name))
+            })?;
+            page.limit = Some(count);
+        }
+        Ok(page)
+    }
+
+    /// Make the path of a request to a list endpoint whose path may end in an item count.  If no
+    /// page is asked for, the count goes in the path, which older nodes understand.
+    fn make_path_with_count(base_path: &str, page: &PageRequest, tip_req: &TipRequest) -> String {
+        let tip_query = HttpRequestType::make_tip_query_string(tip_req, true);
+        match page.limit {
+            Some(count) if !page.paged => format!("{}/{}{}", base_path, count, tip_query),
+            _ => {
+                let page_query = page.to_query_string(if tip_query.is_empty() { "?" } else { "&" });
+                format!("{}{}{}", base_path, tip_query, page_query)
+            }
+        }
+    }
+
     fn parse_getheaders<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -2068,26 +2593,135 @@ impl HttpRequestType {
             ));
         }
 
-        let quantity_str = captures
+        let page = HttpRequestType::get_page_query_with_count(captures, query, "header")?;
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
+
+        Ok(HttpRequestType::GetHeaders(
+            HttpRequestMetadata::from_preamble(preamble),
+            page,
+            tip,
+        ))
+    }
+
+    fn parse_get_block_fee_stats<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetBlockFeeStats".to_string(),
+            ));
+        }
+
+        let page = HttpRequestType::get_page_query_with_count(captures, query, "block")?;
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
+
+        Ok(HttpRequestType::GetBlockFeeStats(
+            HttpRequestMetadata::from_preamble(preamble),
+            page,
+            tip,
+        ))
+    }
+
+    fn parse_get_supply<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetSupply".to_string(),
+            ));
+        }
+
+        let height: u64 = captures
             .get(1)
             .ok_or(net_error::DeserializeError(
-                "Failed to match path to reward cycle group".to_string(),
+                "Failed to match path to block height".to_string(),
             ))?
-            .as_str();
-
-        let quantity: u64 = quantity_str
+            .as_str()
             .parse()
-            .map_err(|_| net_error::DeserializeError("Failed to parse reward cycle".to_string()))?;
+            .map_err(|_| net_error::DeserializeError("Failed to parse block height".to_string()))?;
 
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
 
-        Ok(HttpRequestType::GetHeaders(
+        Ok(HttpRequestType::GetSupply(
             HttpRequestMetadata::from_preamble(preamble),
-            quantity,
+            height,
             tip,
         ))
     }
 
+    fn parse_stream_blocks<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for StreamBlocks".to_string(),
+            ));
+        }
+
+        let mut start = None;
+        let mut encoding = BlockFeedEncoding::Binary;
+        let mut limit = None;
+        for (key, value) in form_urlencoded::parse(query.unwrap_or("").as_bytes()) {
+            match key.as_ref() {
+                "from" => {
+                    let height = value.parse::<u64>().map_err(|_| {
+                        net_error::DeserializeError("Failed to parse `from` height".to_string())
+                    })?;
+                    start = Some(BlockFeedStart::Height(height));
+                }
+                "cursor" => {
+                    let cursor = StacksBlockId::from_hex(&value).map_err(|_| {
+                        net_error::DeserializeError("Failed to parse `cursor`".to_string())
+                    })?;
+                    start = Some(BlockFeedStart::Cursor(cursor));
+                }
+                "format" => {
+                    encoding = match value.as_ref() {
+                        "binary" => BlockFeedEncoding::Binary,
+                        "json" => BlockFeedEncoding::Json,
+                        _ => {
+                            return Err(net_error::DeserializeError(
+                                "Invalid `format`: expected `binary` or `json`".to_string(),
+                            ));
+                        }
+                    };
+                }
+                "limit" => {
+                    let count = value.parse::<u64>().map_err(|_| {
+                        net_error::DeserializeError("Failed to parse `limit`".to_string())
+                    })?;
+                    limit = Some(count);
+                }
+                _ => {}
+            }
+        }
+
+        let start = start.ok_or(net_error::DeserializeError(
+            "Invalid Http request: expected `from` or `cursor` for StreamBlocks".to_string(),
+        ))?;
+
+        Ok(HttpRequestType::StreamBlocks(
+            HttpRequestMetadata::from_preamble(preamble),
+            BlockFeedRequest {
+                start,
+                encoding,
+                limit,
+            },
+        ))
+    }
+
     fn parse_getblock<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -2117,6 +2751,83 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_get_block_weight<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetBlockWeight".to_string(),
+            ));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to block hash group".to_string(),
+            ))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
+
+        Ok(HttpRequestType::GetBlockWeight(
+            HttpRequestMetadata::from_preamble(preamble),
+            block_hash,
+        ))
+    }
+
+    fn parse_get_staging_queue<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetStagingQueue".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetStagingQueue(
+            HttpRequestMetadata::from_preamble(preamble),
+            PageRequest::from_query(query)?,
+        ))
+    }
+
+    fn parse_get_microblock_forks<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMicroblockForks".to_string(),
+            ));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to block hash group".to_string(),
+            ))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
+
+        Ok(HttpRequestType::GetMicroblockForks(
+            HttpRequestMetadata::from_preamble(preamble),
+            block_hash,
+        ))
+    }
+
     fn parse_getmicroblocks_indexed<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -2414,6 +3125,104 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_posttransaction_bundle<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() == 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected non-zero-length body for PostTransactionBundle"
+                    .to_string(),
+            ));
+        }
+
+        if preamble.get_content_length() > MAX_PAYLOAD_LEN {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: PostTransactionBundle body is too big".to_string(),
+            ));
+        }
+
+        match preamble.content_type {
+            Some(HttpContentType::Bytes) => {}
+            _ => {
+                return Err(net_error::DeserializeError(
+                    "Wrong Content-Type for transaction bundle; expected application/octet-stream"
+                        .to_string(),
+                ));
+            }
+        }
+
+        let mut bound_fd = BoundReader::from_reader(fd, preamble.get_content_length() as u64);
+        let bundle =
+            StacksTransactionBundle::consensus_deserialize(&mut bound_fd).map_err(|e| {
+                if let codec_error::DeserializeError(msg) = e {
+                    net_error::ClientError(ClientError::Message(format!(
+                        "Failed to deserialize posted transaction bundle: {}",
+                        msg
+                    )))
+                } else {
+                    e.into()
+                }
+            })?;
+        Ok(HttpRequestType::PostTransactionBundle(
+            HttpRequestMetadata::from_preamble(preamble),
+            bundle,
+        ))
+    }
+
+    fn parse_simulate_transaction<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() == 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected non-zero-length body for SimulateTransaction"
+                    .to_string(),
+            ));
+        }
+
+        if preamble.get_content_length() > MAX_PAYLOAD_LEN {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: SimulateTransaction body is too big".to_string(),
+            ));
+        }
+
+        match preamble.content_type {
+            Some(HttpContentType::Bytes) => {}
+            _ => {
+                return Err(net_error::DeserializeError(
+                    "Wrong Content-Type for transaction; expected application/octet-stream"
+                        .to_string(),
+                ));
+            }
+        }
+
+        let mut bound_fd = BoundReader::from_reader(fd, preamble.get_content_length() as u64);
+        let tx = StacksTransaction::consensus_deserialize(&mut bound_fd).map_err(|e| {
+            if let codec_error::DeserializeError(msg) = e {
+                net_error::ClientError(ClientError::Message(format!(
+                    "Failed to deserialize transaction to simulate: {}",
+                    msg
+                )))
+            } else {
+                e.into()
+            }
+        })?;
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
+
+        Ok(HttpRequestType::SimulateTransaction(
+            HttpRequestMetadata::from_preamble(preamble),
+            tx,
+            tip,
+        ))
+    }
+
     fn parse_postblock<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -2512,7 +3321,7 @@ impl HttpRequestType {
         let mut bound_fd = BoundReader::from_reader(fd, preamble.get_content_length() as u64);
 
         let mb = StacksMicroblock::consensus_deserialize(&mut bound_fd)?;
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        let tip = HttpRequestType::get_chain_tip_query(query)?;
 
         Ok(HttpRequestType::PostMicroblock(
             HttpRequestMetadata::from_preamble(preamble),
@@ -2655,12 +3464,19 @@ impl HttpRequestType {
 
         let mut bound_fd = BoundReader::from_reader(fd, preamble.get_content_length() as u64);
         let mempool_query = MemPoolSyncData::consensus_deserialize(&mut bound_fd)?;
-        let page_id_opt = HttpRequestType::get_mempool_page_id_query(query);
+        let mut page = PageRequest::from_query(query)?;
+        if page.cursor.is_none() {
+            // older nodes send the cursor as `page_id`
+            if let Some(page_id) = HttpRequestType::get_mempool_page_id_query(query) {
+                page.cursor = Some(page_id.to_hex());
+                page.paged = true;
+            }
+        }
 
         Ok(HttpRequestType::MemPoolQuery(
             HttpRequestMetadata::from_preamble(preamble),
             mempool_query,
-            page_id_opt,
+            page,
         ))
     }
 
@@ -2681,29 +3497,49 @@ impl HttpRequestType {
         match *self {
             HttpRequestType::GetInfo(ref md) => md,
             HttpRequestType::GetPoxInfo(ref md, ..) => md,
-            HttpRequestType::GetNeighbors(ref md) => md,
+            HttpRequestType::GetNeighbors(ref md, _) => md,
+            HttpRequestType::GetNodeMetadata(ref md) => md,
+            HttpRequestType::GetBannedLeaderKeys(ref md) => md,
+            HttpRequestType::GetTipSummary(ref md) => md,
+            HttpRequestType::GetBlocksSummary(ref md, _) => md,
+            HttpRequestType::GetMempoolSummary(ref md) => md,
+            HttpRequestType::GetChainQuality(ref md, ..) => md,
+            HttpRequestType::GetPeersSummary(ref md, _) => md,
+            HttpRequestType::OpenSession(ref md, _) => md,
+            HttpRequestType::ReleaseSession(ref md, _) => md,
             HttpRequestType::GetHeaders(ref md, ..) => md,
+            HttpRequestType::StreamBlocks(ref md, ..) => md,
             HttpRequestType::GetBlock(ref md, _) => md,
+            HttpRequestType::GetBlockWeight(ref md, _) => md,
+            HttpRequestType::GetStagingQueue(ref md, _) => md,
             HttpRequestType::GetMicroblocksIndexed(ref md, _) => md,
             HttpRequestType::GetMicroblocksConfirmed(ref md, _) => md,
             HttpRequestType::GetMicroblocksUnconfirmed(ref md, _, _) => md,
+            HttpRequestType::GetMicroblockForks(ref md, _) => md,
             HttpRequestType::GetTransactionUnconfirmed(ref md, _) => md,
             HttpRequestType::PostTransaction(ref md, _, _) => md,
+            HttpRequestType::PostTransactionBundle(ref md, _) => md,
+            HttpRequestType::SimulateTransaction(ref md, ..) => md,
             HttpRequestType::PostBlock(ref md, ..) => md,
             HttpRequestType::PostMicroblock(ref md, ..) => md,
             HttpRequestType::GetAccount(ref md, ..) => md,
             HttpRequestType::GetDataVar(ref md, ..) => md,
             HttpRequestType::GetMapEntry(ref md, ..) => md,
+            HttpRequestType::GetClarityMarfValue(ref md, ..) => md,
+            HttpRequestType::GetClarityMetadata(ref md, ..) => md,
             HttpRequestType::GetTransferCost(ref md) => md,
             HttpRequestType::GetContractABI(ref md, ..) => md,
             HttpRequestType::GetContractSrc(ref md, ..) => md,
             HttpRequestType::GetIsTraitImplemented(ref md, ..) => md,
             HttpRequestType::CallReadOnlyFunction(ref md, ..) => md,
+            HttpRequestType::CallPreviewFunction(ref md, ..) => md,
             HttpRequestType::OptionsPreflight(ref md, ..) => md,
             HttpRequestType::GetAttachmentsInv(ref md, ..) => md,
             HttpRequestType::GetAttachment(ref md, ..) => md,
             HttpRequestType::MemPoolQuery(ref md, ..) => md,
             HttpRequestType::FeeRateEstimate(ref md, _, _) => md,
+            HttpRequestType::GetBlockFeeStats(ref md, ..) => md,
+            HttpRequestType::GetSupply(ref md, ..) => md,
             HttpRequestType::ClientError(ref md, ..) => md,
         }
     }
@@ -2712,29 +3548,49 @@ impl HttpRequestType {
         match *self {
             HttpRequestType::GetInfo(ref mut md) => md,
             HttpRequestType::GetPoxInfo(ref mut md, ..) => md,
-            HttpRequestType::GetNeighbors(ref mut md) => md,
+            HttpRequestType::GetNeighbors(ref mut md, _) => md,
+            HttpRequestType::GetNodeMetadata(ref mut md) => md,
+            HttpRequestType::GetBannedLeaderKeys(ref mut md) => md,
+            HttpRequestType::GetTipSummary(ref mut md) => md,
+            HttpRequestType::GetBlocksSummary(ref mut md, _) => md,
+            HttpRequestType::GetMempoolSummary(ref mut md) => md,
+            HttpRequestType::GetChainQuality(ref mut md, ..) => md,
+            HttpRequestType::GetPeersSummary(ref mut md, _) => md,
+            HttpRequestType::OpenSession(ref mut md, _) => md,
+            HttpRequestType::ReleaseSession(ref mut md, _) => md,
             HttpRequestType::GetHeaders(ref mut md, ..) => md,
+            HttpRequestType::StreamBlocks(ref mut md, ..) => md,
             HttpRequestType::GetBlock(ref mut md, _) => md,
+            HttpRequestType::GetBlockWeight(ref mut md, _) => md,
+            HttpRequestType::GetStagingQueue(ref mut md, _) => md,
             HttpRequestType::GetMicroblocksIndexed(ref mut md, _) => md,
             HttpRequestType::GetMicroblocksConfirmed(ref mut md, _) => md,
             HttpRequestType::GetMicroblocksUnconfirmed(ref mut md, _, _) => md,
+            HttpRequestType::GetMicroblockForks(ref mut md, _) => md,
             HttpRequestType::GetTransactionUnconfirmed(ref mut md, _) => md,
             HttpRequestType::PostTransaction(ref mut md, _, _) => md,
+            HttpRequestType::PostTransactionBundle(ref mut md, _) => md,
+            HttpRequestType::SimulateTransaction(ref mut md, ..) => md,
             HttpRequestType::PostBlock(ref mut md, ..) => md,
             HttpRequestType::PostMicroblock(ref mut md, ..) => md,
             HttpRequestType::GetAccount(ref mut md, ..) => md,
             HttpRequestType::GetDataVar(ref mut md, ..) => md,
             HttpRequestType::GetMapEntry(ref mut md, ..) => md,
+            HttpRequestType::GetClarityMarfValue(ref mut md, ..) => md,
+            HttpRequestType::GetClarityMetadata(ref mut md, ..) => md,
             HttpRequestType::GetTransferCost(ref mut md) => md,
             HttpRequestType::GetContractABI(ref mut md, ..) => md,
             HttpRequestType::GetContractSrc(ref mut md, ..) => md,
             HttpRequestType::GetIsTraitImplemented(ref mut md, ..) => md,
             HttpRequestType::CallReadOnlyFunction(ref mut md, ..) => md,
+            HttpRequestType::CallPreviewFunction(ref mut md, ..) => md,
             HttpRequestType::OptionsPreflight(ref mut md, ..) => md,
             HttpRequestType::GetAttachmentsInv(ref mut md, ..) => md,
             HttpRequestType::GetAttachment(ref mut md, ..) => md,
             HttpRequestType::MemPoolQuery(ref mut md, ..) => md,
             HttpRequestType::FeeRateEstimate(ref mut md, _, _) => md,
+            HttpRequestType::GetBlockFeeStats(ref mut md, ..) => md,
+            HttpRequestType::GetSupply(ref mut md, ..) => md,
             HttpRequestType::ClientError(ref mut md, ..) => md,
         }
     }
@@ -2747,6 +3603,11 @@ impl HttpRequestType {
             TipRequest::SpecificTip(tip) => {
                 format!("?tip={}{}", tip, if with_proof { "" } else { "&proof=0" })
             }
+            TipRequest::Session(session_id) => format!(
+                "?session={}{}",
+                session_id,
+                if with_proof { "" } else { "&proof=0" }
+            ),
             TipRequest::UseLatestAnchoredTip => {
                 if !with_proof {
                     format!("?proof=0")
@@ -2764,21 +3625,66 @@ impl HttpRequestType {
                 "/v2/pox{}",
                 HttpRequestType::make_tip_query_string(tip_req, true)
             ),
-            HttpRequestType::GetNeighbors(_md) => "/v2/neighbors".to_string(),
-            HttpRequestType::GetHeaders(_md, quantity, tip_req) => format!(
-                "/v2/headers/{}{}",
-                quantity,
+            HttpRequestType::GetNeighbors(_md, page) => {
+                format!("/v2/neighbors{}", page.to_query_string("?"))
+            }
+            HttpRequestType::GetNodeMetadata(_md) => "/v2/neighbors/metadata".to_string(),
+            HttpRequestType::GetBannedLeaderKeys(_md) => "/v2/miners/banned".to_string(),
+            HttpRequestType::GetTipSummary(_md) => "/v2/summary/tip".to_string(),
+            HttpRequestType::GetBlocksSummary(_md, page) => {
+                format!("/v2/summary/blocks{}", page.to_query_string("?"))
+            }
+            HttpRequestType::GetMempoolSummary(_md) => "/v2/summary/mempool".to_string(),
+            HttpRequestType::GetChainQuality(_md, window) => match window {
+                Some(window) => format!("/v2/summary/chain_quality?window={}", window),
+                None => "/v2/summary/chain_quality".to_string(),
+            },
+            HttpRequestType::GetPeersSummary(_md, page) => {
+                format!("/v2/summary/peers{}", page.to_query_string("?"))
+            }
+            HttpRequestType::OpenSession(_md, tip_req) => format!(
+                "/v2/sessions{}",
                 HttpRequestType::make_tip_query_string(tip_req, true)
             ),
+            HttpRequestType::ReleaseSession(_md, session_id) => {
+                format!("/v2/sessions/{}/release", session_id)
+            }
+            HttpRequestType::GetHeaders(_md, page, tip_req) => {
+                HttpRequestType::make_path_with_count("/v2/headers", page, tip_req)
+            }
+            HttpRequestType::StreamBlocks(_md, request) => {
+                let start = match request.start {
+                    BlockFeedStart::Height(height) => format!("from={}", height),
+                    BlockFeedStart::Cursor(ref cursor) => format!("cursor={}", cursor),
+                };
+                let format = match request.encoding {
+                    BlockFeedEncoding::Binary => "binary",
+                    BlockFeedEncoding::Json => "json",
+                };
+                let limit = match request.limit {
+                    Some(limit) => format!("&limit={}", limit),
+                    None => "".to_string(),
+                };
+                format!("/v2/stream/blocks?{}&format={}{}", start, format, limit)
+            }
             HttpRequestType::GetBlock(_md, block_hash) => {
                 format!("/v2/blocks/{}", block_hash.to_hex())
             }
+            HttpRequestType::GetBlockWeight(_md, block_hash) => {
+                format!("/v2/blocks/{}/weight", block_hash.to_hex())
+            }
+            HttpRequestType::GetStagingQueue(_md, page) => {
+                format!("/v2/blocks/staging{}", page.to_query_string("?"))
+            }
             HttpRequestType::GetMicroblocksIndexed(_md, block_hash) => {
                 format!("/v2/microblocks/{}", block_hash.to_hex())
             }
             HttpRequestType::GetMicroblocksConfirmed(_md, block_hash) => {
                 format!("/v2/microblocks/confirmed/{}", block_hash.to_hex())
             }
+            HttpRequestType::GetMicroblockForks(_md, block_hash) => {
+                format!("/v2/microblocks/forks/{}", block_hash.to_hex())
+            }
             HttpRequestType::GetMicroblocksUnconfirmed(_md, block_hash, min_seq) => format!(
                 "/v2/microblocks/unconfirmed/{}/{}",
                 block_hash.to_hex(),
@@ -2788,16 +3694,24 @@ impl HttpRequestType {
                 format!("/v2/transactions/unconfirmed/{}", txid)
             }
             HttpRequestType::PostTransaction(_md, ..) => "/v2/transactions".to_string(),
+            HttpRequestType::PostTransactionBundle(_md, ..) => {
+                "/v2/transactions/bundle".to_string()
+            }
+            HttpRequestType::SimulateTransaction(_md, _, tip_req) => format!(
+                "/v2/transactions/simulate{}",
+                HttpRequestType::make_tip_query_string(tip_req, true)
+            ),
             HttpRequestType::PostBlock(_md, ch, ..) => format!("/v2/blocks/upload/{}", &ch),
             HttpRequestType::PostMicroblock(_md, _, tip_req) => format!(
                 "/v2/microblocks{}",
                 HttpRequestType::make_tip_query_string(tip_req, true)
             ),
-            HttpRequestType::GetAccount(_md, principal, tip_req, with_proof) => {
+            HttpRequestType::GetAccount(_md, principal, tip_req, with_proof, unanchored) => {
                 format!(
-                    "/v2/accounts/{}{}",
+                    "/v2/accounts/{}{}{}",
                     &principal.to_string(),
-                    HttpRequestType::make_tip_query_string(tip_req, *with_proof,)
+                    HttpRequestType::make_tip_query_string(tip_req, *with_proof,),
+                    if *unanchored { "&unanchored=true" } else { "" }
                 )
             }
             HttpRequestType::GetDataVar(
@@ -2814,6 +3728,24 @@ impl HttpRequestType {
                 var_name.as_str(),
                 HttpRequestType::make_tip_query_string(tip_req, *with_proof)
             ),
+            HttpRequestType::GetClarityMarfValue(_md, key, tip_req, with_proof) => format!(
+                "/v2/clarity/marf/{}{}",
+                utf8_percent_encode(key, NON_ALPHANUMERIC),
+                HttpRequestType::make_tip_query_string(tip_req, *with_proof)
+            ),
+            HttpRequestType::GetClarityMetadata(
+                _md,
+                contract_addr,
+                contract_name,
+                key,
+                tip_req,
+            ) => format!(
+                "/v2/clarity/metadata/{}/{}/{}{}",
+                &contract_addr.to_string(),
+                contract_name.as_str(),
+                utf8_percent_encode(key, NON_ALPHANUMERIC),
+                HttpRequestType::make_tip_query_string(tip_req, true)
+            ),
             HttpRequestType::GetMapEntry(
                 _md,
                 contract_addr,
@@ -2879,6 +3811,22 @@ impl HttpRequestType {
                 func_name.as_str(),
                 HttpRequestType::make_tip_query_string(tip_req, true)
             ),
+            HttpRequestType::CallPreviewFunction(
+                _,
+                contract_addr,
+                contract_name,
+                _,
+                _,
+                func_name,
+                _,
+                tip_req,
+            ) => format!(
+                "/v2/contracts/call-preview/{}/{}/{}{}",
+                contract_addr,
+                contract_name.as_str(),
+                func_name.as_str(),
+                HttpRequestType::make_tip_query_string(tip_req, true)
+            ),
             HttpRequestType::OptionsPreflight(_md, path) => path.to_string(),
             HttpRequestType::GetAttachmentsInv(_md, index_block_hash, pages_indexes) => {
                 let pages_query = match pages_indexes.len() {
@@ -2898,13 +3846,29 @@ impl HttpRequestType {
             HttpRequestType::GetAttachment(_, content_hash) => {
                 format!("/v2/attachments/{}", to_hex(&content_hash.0[..]))
             }
-            HttpRequestType::MemPoolQuery(_, _, page_id_opt) => match page_id_opt {
-                Some(page_id) => {
-                    format!("/v2/mempool/query?page_id={}", page_id)
+            HttpRequestType::MemPoolQuery(_, _, page) => {
+                // send the cursor as `page_id`, which older nodes understand
+                let mut query = PageRequest {
+                    cursor: None,
+                    paged: false,
+                    ..page.clone()
                 }
-                None => "/v2/mempool/query".to_string(),
-            },
+                .to_query_string("?");
+                if let Some(ref page_id) = page.cursor {
+                    query.push_str(if query.is_empty() { "?" } else { "&" });
+                    query.push_str(&format!("page_id={}", page_id));
+                }
+                format!("/v2/mempool/query{}", query)
+            }
             HttpRequestType::FeeRateEstimate(_, _, _) => self.get_path().to_string(),
+            HttpRequestType::GetBlockFeeStats(_md, page, tip_req) => {
+                HttpRequestType::make_path_with_count("/v2/fees/blocks", page, tip_req)
+            }
+            HttpRequestType::GetSupply(_md, height, tip_req) => format!(
+                "/v2/supply/{}{}",
+                height,
+                HttpRequestType::make_tip_query_string(tip_req, true)
+            ),
             HttpRequestType::ClientError(_md, e) => match e {
                 ClientError::NotFound(path) => path.to_string(),
                 _ => "error path unknown".into(),
@@ -2912,25 +3876,68 @@ impl HttpRequestType {
         }
     }
 
+    /// Get a mutable reference to the chain tip this request is to be served from, if it takes one
+    pub fn tip_request_mut(&mut self) -> Option<&mut TipRequest> {
+        match self {
+            HttpRequestType::GetPoxInfo(_, ref mut tip_req)
+            | HttpRequestType::OpenSession(_, ref mut tip_req)
+            | HttpRequestType::GetHeaders(_, _, ref mut tip_req)
+            | HttpRequestType::GetBlockFeeStats(_, _, ref mut tip_req)
+            | HttpRequestType::GetSupply(_, _, ref mut tip_req)
+            | HttpRequestType::PostMicroblock(_, _, ref mut tip_req)
+            | HttpRequestType::SimulateTransaction(_, _, ref mut tip_req)
+            | HttpRequestType::GetAccount(_, _, ref mut tip_req, _, _)
+            | HttpRequestType::GetDataVar(_, _, _, _, ref mut tip_req, _)
+            | HttpRequestType::GetMapEntry(_, _, _, _, _, ref mut tip_req, _)
+            | HttpRequestType::GetClarityMarfValue(_, _, ref mut tip_req, _)
+            | HttpRequestType::GetClarityMetadata(_, _, _, _, ref mut tip_req)
+            | HttpRequestType::CallReadOnlyFunction(_, _, _, _, _, _, _, ref mut tip_req)
+            | HttpRequestType::CallPreviewFunction(_, _, _, _, _, _, _, ref mut tip_req)
+            | HttpRequestType::GetContractSrc(_, _, _, ref mut tip_req, _)
+            | HttpRequestType::GetContractABI(_, _, _, ref mut tip_req)
+            | HttpRequestType::GetIsTraitImplemented(_, _, _, _, ref mut tip_req) => Some(tip_req),
+            _ => None,
+        }
+    }
+
     pub fn get_path(&self) -> &'static str {
         match self {
             HttpRequestType::GetInfo(..) => "/v2/info",
             HttpRequestType::GetPoxInfo(..) => "/v2/pox",
             HttpRequestType::GetNeighbors(..) => "/v2/neighbors",
-            HttpRequestType::GetHeaders(..) => "/v2/headers/:height",
+            HttpRequestType::GetNodeMetadata(..) => "/v2/neighbors/metadata",
+            HttpRequestType::GetBannedLeaderKeys(..) => "/v2/miners/banned",
+            HttpRequestType::GetTipSummary(..) => "/v2/summary/tip",
+            HttpRequestType::GetBlocksSummary(..) => "/v2/summary/blocks",
+            HttpRequestType::GetMempoolSummary(..) => "/v2/summary/mempool",
+            HttpRequestType::GetChainQuality(..) => "/v2/summary/chain_quality",
+            HttpRequestType::GetPeersSummary(..) => "/v2/summary/peers",
+            HttpRequestType::OpenSession(..) => "/v2/sessions",
+            HttpRequestType::ReleaseSession(..) => "/v2/sessions/:id/release",
+            HttpRequestType::GetHeaders(..) => "/v2/headers",
+            HttpRequestType::StreamBlocks(..) => "/v2/stream/blocks",
             HttpRequestType::GetBlock(..) => "/v2/blocks/:hash",
+            HttpRequestType::GetBlockWeight(..) => "/v2/blocks/:hash/weight",
+            HttpRequestType::GetStagingQueue(..) => "/v2/blocks/staging",
             HttpRequestType::GetMicroblocksIndexed(..) => "/v2/microblocks/:hash",
             HttpRequestType::GetMicroblocksConfirmed(..) => "/v2/microblocks/confirmed/:hash",
+            HttpRequestType::GetMicroblockForks(..) => "/v2/microblocks/forks/:hash",
             HttpRequestType::GetMicroblocksUnconfirmed(..) => {
                 "/v2/microblocks/unconfirmed/:hash/:seq"
             }
             HttpRequestType::GetTransactionUnconfirmed(..) => "/v2/transactions/unconfirmed/:txid",
             HttpRequestType::PostTransaction(..) => "/v2/transactions",
+            HttpRequestType::PostTransactionBundle(..) => "/v2/transactions/bundle",
+            HttpRequestType::SimulateTransaction(..) => "/v2/transactions/simulate",
             HttpRequestType::PostBlock(..) => "/v2/blocks/upload/:block",
             HttpRequestType::PostMicroblock(..) => "/v2/microblocks",
             HttpRequestType::GetAccount(..) => "/v2/accounts/:principal",
             HttpRequestType::GetDataVar(..) => "/v2/data_var/:principal/:contract_name/:var_name",
             HttpRequestType::GetMapEntry(..) => "/v2/map_entry/:principal/:contract_name/:map_name",
+            HttpRequestType::GetClarityMarfValue(..) => "/v2/clarity/marf/:key",
+            HttpRequestType::GetClarityMetadata(..) => {
+                "/v2/clarity/metadata/:principal/:contract_name/:key"
+            }
             HttpRequestType::GetTransferCost(..) => "/v2/fees/transfer",
             HttpRequestType::GetContractABI(..) => {
                 "/v2/contracts/interface/:principal/:contract_name"
@@ -2939,11 +3946,16 @@ impl HttpRequestType {
             HttpRequestType::CallReadOnlyFunction(..) => {
                 "/v2/contracts/call-read/:principal/:contract_name/:func_name"
             }
+            HttpRequestType::CallPreviewFunction(..) => {
+                "/v2/contracts/call-preview/:principal/:contract_name/:func_name"
+            }
             HttpRequestType::GetAttachmentsInv(..) => "/v2/attachments/inv",
             HttpRequestType::GetAttachment(..) => "/v2/attachments/:hash",
             HttpRequestType::GetIsTraitImplemented(..) => "/v2/traits/:principal/:contract_name",
             HttpRequestType::MemPoolQuery(..) => "/v2/mempool/query",
             HttpRequestType::FeeRateEstimate(_, _, _) => "/v2/fees/transaction",
+            HttpRequestType::GetBlockFeeStats(..) => "/v2/fees/blocks",
+            HttpRequestType::GetSupply(..) => "/v2/supply/:height",
             HttpRequestType::OptionsPreflight(..) | HttpRequestType::ClientError(..) => "/",
         }
     }
@@ -2994,6 +4006,40 @@ impl HttpRequestType {
                 fd.write_all(&request_body_bytes)
                     .map_err(net_error::WriteError)?;
             }
+            HttpRequestType::PostTransactionBundle(md, bundle) => {
+                let mut bundle_bytes = vec![];
+                write_next(&mut bundle_bytes, bundle)?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(bundle_bytes.len() as u32),
+                    Some(&HttpContentType::Bytes),
+                    |fd| stacks_height_headers(fd, md),
+                )?;
+                fd.write_all(&bundle_bytes).map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::SimulateTransaction(md, tx, ..) => {
+                let mut tx_bytes = vec![];
+                write_next(&mut tx_bytes, tx)?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(tx_bytes.len() as u32),
+                    Some(&HttpContentType::Bytes),
+                    |fd| stacks_height_headers(fd, md),
+                )?;
+                fd.write_all(&tx_bytes).map_err(net_error::WriteError)?;
+            }
             HttpRequestType::PostBlock(md, _ch, block) => {
                 let mut block_bytes = vec![];
                 write_next(&mut block_bytes, block)?;
@@ -3064,28 +4110,25 @@ impl HttpRequestType {
                 _func_name,
                 func_args,
                 ..,
+            )
+            | HttpRequestType::CallPreviewFunction(
+                md,
+                _contract_addr,
+                _contract_name,
+                sender,
+                sponsor,
+                _func_name,
+                func_args,
+                ..,
             ) => {
-                let mut args = vec![];
-                for arg in func_args.iter() {
-                    let mut arg_bytes = vec![];
-                    arg.serialize_write(&mut arg_bytes)
-                        .map_err(net_error::WriteError)?;
-                    args.push(to_hex(&arg_bytes));
-                }
-
-                let request_body = CallReadOnlyRequestBody {
-                    sender: sender.to_string(),
-                    sponsor: sponsor.as_ref().map(|sp| sp.to_string()),
-                    arguments: args,
+                // HttpContentType::Bytes (more compressed than hex-encoded JSON arguments)
+                let request_body = CallReadOnlyBinaryRequestBody {
+                    sender: sender.clone(),
+                    sponsor: sponsor.clone(),
+                    arguments: func_args.clone(),
                 };
-
                 let mut request_body_bytes = vec![];
-                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
-                    net_error::SerializeError(format!(
-                        "Failed to serialize read-only call to JSON: {:?}",
-                        &e
-                    ))
-                })?;
+                write_next(&mut request_body_bytes, &request_body)?;
 
                 HttpRequestPreamble::new_serialized(
                     fd,
@@ -3095,7 +4138,7 @@ impl HttpRequestType {
                     &md.peer,
                     md.keep_alive,
                     Some(request_body_bytes.len() as u32),
-                    Some(&HttpContentType::JSON),
+                    Some(&HttpContentType::Bytes),
                     |fd| stacks_height_headers(fd, md),
                 )?;
                 fd.write_all(&request_body_bytes)
@@ -3297,18 +4340,14 @@ impl HttpResponseType {
         })
     }
 
-    fn parse_text<R: Read>(
+    /// Read a response body of any content type, either chunk-encoded or with a
+    /// Content-Length.
+    fn read_body<R: Read>(
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
         max_len: u64,
     ) -> Result<Vec<u8>, net_error> {
-        // content-type has to be text/plain
-        if preamble.content_type != HttpContentType::Text {
-            return Err(net_error::DeserializeError(
-                "Invalid content-type: expected text/plain".to_string(),
-            ));
-        }
         let buf = if preamble.is_chunked() && len_hint.is_none() {
             let mut chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
             let mut buf = vec![];
@@ -3338,6 +4377,21 @@ impl HttpResponseType {
         Ok(buf)
     }
 
+    fn parse_text<R: Read>(
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+        max_len: u64,
+    ) -> Result<Vec<u8>, net_error> {
+        // content-type has to be text/plain
+        if preamble.content_type != HttpContentType::Text {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected text/plain".to_string(),
+            ));
+        }
+        HttpResponseType::read_body(preamble, fd, len_hint, max_len)
+    }
+
     // len_hint is given by the StacksHttp protocol implementation
     pub fn parse<R: Read>(
         protocol: &mut StacksHttp,
@@ -3365,10 +4419,59 @@ impl HttpResponseType {
             (&PATH_GETINFO, &HttpResponseType::parse_peerinfo),
             (&PATH_GETPOXINFO, &HttpResponseType::parse_poxinfo),
             (&PATH_GETNEIGHBORS, &HttpResponseType::parse_neighbors),
+            (
+                &PATH_GET_NODE_METADATA,
+                &HttpResponseType::parse_node_metadata,
+            ),
+            (
+                &PATH_GET_BANNED_LEADER_KEYS,
+                &HttpResponseType::parse_banned_leader_keys,
+            ),
+            (&PATH_GET_TIP_SUMMARY, &HttpResponseType::parse_tip_summary),
+            (
+                &PATH_GET_BLOCKS_SUMMARY,
+                &HttpResponseType::parse_blocks_summary,
+            ),
+            (
+                &PATH_GET_MEMPOOL_SUMMARY,
+                &HttpResponseType::parse_mempool_summary,
+            ),
+            (
+                &PATH_GET_CHAIN_QUALITY,
+                &HttpResponseType::parse_chain_quality,
+            ),
+            (
+                &PATH_GET_PEERS_SUMMARY,
+                &HttpResponseType::parse_peers_summary,
+            ),
+            (&PATH_POST_SESSION, &HttpResponseType::parse_session),
+            (&PATH_POST_RELEASE_SESSION, &HttpResponseType::parse_session),
             (&PATH_GETHEADERS, &HttpResponseType::parse_headers),
+            (
+                &PATH_GET_BLOCK_FEE_STATS,
+                &HttpResponseType::parse_block_fee_stats,
+            ),
+            (&PATH_GET_SUPPLY, &HttpResponseType::parse_supply),
+            (&PATH_STREAM_BLOCKS, &HttpResponseType::parse_block_feed),
             (&PATH_GETBLOCK, &HttpResponseType::parse_block),
+            (
+                &PATH_GET_BLOCK_WEIGHT,
+                &HttpResponseType::parse_block_weight,
+            ),
+            (
+                &PATH_GET_STAGING_QUEUE,
+                &HttpResponseType::parse_staging_queue,
+            ),
             (&PATH_GET_DATA_VAR, &HttpResponseType::parse_get_data_var),
             (&PATH_GET_MAP_ENTRY, &HttpResponseType::parse_get_map_entry),
+            (
+                &PATH_GET_CLARITY_MARF_VALUE,
+                &HttpResponseType::parse_get_clarity_marf_value,
+            ),
+            (
+                &PATH_GET_CLARITY_METADATA,
+                &HttpResponseType::parse_get_clarity_metadata,
+            ),
             (
                 &PATH_GETMICROBLOCKS_INDEXED,
                 &HttpResponseType::parse_microblocks,
@@ -3381,11 +4484,19 @@ impl HttpResponseType {
                 &PATH_GETMICROBLOCKS_UNCONFIRMED,
                 &HttpResponseType::parse_microblocks_unconfirmed,
             ),
+            (
+                &PATH_GET_MICROBLOCK_FORKS,
+                &HttpResponseType::parse_microblock_forks,
+            ),
             (
                 &PATH_GETTRANSACTION_UNCONFIRMED,
                 &HttpResponseType::parse_transaction_unconfirmed,
             ),
             (&PATH_POSTTRANSACTION, &HttpResponseType::parse_txid),
+            (
+                &PATH_POSTTRANSACTION_BUNDLE,
+                &HttpResponseType::parse_tx_bundle_id,
+            ),
             (
                 &PATH_POSTBLOCK,
                 &HttpResponseType::parse_stacks_block_accepted,
@@ -3411,6 +4522,14 @@ impl HttpResponseType {
                 &PATH_POST_CALL_READ_ONLY,
                 &HttpResponseType::parse_call_read_only,
             ),
+            (
+                &PATH_POST_CALL_PREVIEW,
+                &HttpResponseType::parse_call_preview,
+            ),
+            (
+                &PATH_POST_SIMULATE_TRANSACTION,
+                &HttpResponseType::parse_simulate_transaction,
+            ),
             (
                 &PATH_GET_ATTACHMENT,
                 &HttpResponseType::parse_get_attachment,
@@ -3501,6 +4620,35 @@ impl HttpResponseType {
         ))
     }
 
+    fn parse_block_fee_stats<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let fee_stats =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BlockFeeStats(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            fee_stats,
+        ))
+    }
+
+    fn parse_supply<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let supply = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Supply(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            supply,
+        ))
+    }
+
     fn parse_neighbors<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
@@ -3516,6 +4664,171 @@ impl HttpResponseType {
         ))
     }
 
+    fn parse_node_metadata<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let node_metadata =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::NodeMetadata(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            node_metadata,
+        ))
+    }
+
+    fn parse_banned_leader_keys<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let banned_leader_keys =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BannedLeaderKeys(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            banned_leader_keys,
+        ))
+    }
+
+    fn parse_block_weight<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let block_weight =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BlockWeight(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            block_weight,
+        ))
+    }
+
+    fn parse_staging_queue<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let staging_queue =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::StagingQueue(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            staging_queue,
+        ))
+    }
+
+    fn parse_microblock_forks<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let microblock_forks =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MicroblockForks(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            microblock_forks,
+        ))
+    }
+
+    fn parse_tip_summary<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let tip_summary =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::TipSummary(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            tip_summary,
+        ))
+    }
+
+    fn parse_blocks_summary<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let blocks_summary =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BlocksSummary(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            blocks_summary,
+        ))
+    }
+
+    fn parse_mempool_summary<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let mempool_summary =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MempoolSummary(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            mempool_summary,
+        ))
+    }
+
+    fn parse_chain_quality<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let chain_quality =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::ChainQuality(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            chain_quality,
+        ))
+    }
+
+    fn parse_peers_summary<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let peers_summary =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::PeersSummary(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            peers_summary,
+        ))
+    }
+
+    fn parse_session<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let session_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Session(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            session_data,
+        ))
+    }
+
     fn parse_headers<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
@@ -3523,7 +4836,7 @@ impl HttpResponseType {
         fd: &mut R,
         len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        let headers: Vec<ExtendedStacksHeader> =
+        let headers: RPCList<ExtendedStacksHeader> =
             HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
         Ok(HttpResponseType::Headers(
             HttpResponseMetadata::from_preamble(request_version, preamble),
@@ -3531,6 +4844,43 @@ impl HttpResponseType {
         ))
     }
 
+    fn parse_block_feed<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let body = HttpResponseType::read_body(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        let mut frames = vec![];
+        match preamble.content_type {
+            HttpContentType::Bytes => {
+                let mut cursor = io::Cursor::new(&body);
+                while (cursor.position() as usize) < body.len() {
+                    frames.push(read_next(&mut cursor)?);
+                }
+            }
+            HttpContentType::JSON => {
+                for line in body.split(|b| *b == b'\n').filter(|line| line.len() > 0) {
+                    let frame = serde_json::from_slice(line).map_err(|e| {
+                        net_error::DeserializeError(format!("Failed to parse JSON: {:?}", &e))
+                    })?;
+                    frames.push(frame);
+                }
+            }
+            _ => {
+                return Err(net_error::DeserializeError(
+                    "Invalid content-type: expected application/octet-stream or application/json"
+                        .to_string(),
+                ));
+            }
+        }
+        Ok(HttpResponseType::BlockFeed(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            frames,
+        ))
+    }
+
     fn parse_block<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
@@ -3572,37 +4922,67 @@ impl HttpResponseType {
             HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
         Ok(HttpResponseType::GetAccount(
             HttpResponseMetadata::from_preamble(request_version, preamble),
-            account_entry,
+            account_entry,
+        ))
+    }
+
+    fn parse_get_data_var<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let data_var =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetDataVar(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            data_var,
+        ))
+    }
+
+    fn parse_get_map_entry<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let map_entry =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetMapEntry(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            map_entry,
         ))
     }
 
-    fn parse_get_data_var<R: Read>(
+    fn parse_get_clarity_marf_value<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        let data_var =
+        let marf_value =
             HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::GetDataVar(
+        Ok(HttpResponseType::GetClarityMarfValue(
             HttpResponseMetadata::from_preamble(request_version, preamble),
-            data_var,
+            marf_value,
         ))
     }
 
-    fn parse_get_map_entry<R: Read>(
+    fn parse_get_clarity_metadata<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        let map_entry =
+        let metadata =
             HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::GetMapEntry(
+        Ok(HttpResponseType::GetClarityMetadata(
             HttpResponseMetadata::from_preamble(request_version, preamble),
-            map_entry,
+            metadata,
         ))
     }
 
@@ -3665,6 +5045,36 @@ impl HttpResponseType {
         ))
     }
 
+    fn parse_call_preview<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let preview: CallPreviewResponse =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::CallPreviewFunction(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            preview,
+        ))
+    }
+
+    fn parse_simulate_transaction<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let simulation: TransactionSimulationResponse =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::SimulateTransaction(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            simulation,
+        ))
+    }
+
     fn parse_microblocks_unconfirmed<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
@@ -3751,6 +5161,23 @@ impl HttpResponseType {
         ))
     }
 
+    fn parse_tx_bundle_id<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let bundle_id_hex: String = HttpResponseType::parse_json(preamble, fd, len_hint, 66)?;
+        let bundle_id = Sha512Trunc256Sum::from_hex(&bundle_id_hex).map_err(|_e| {
+            net_error::DeserializeError("Failed to decode bundle ID hex".to_string())
+        })?;
+        Ok(HttpResponseType::TransactionBundleID(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            bundle_id,
+        ))
+    }
+
     fn parse_get_attachment<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
@@ -3997,23 +5424,41 @@ impl HttpResponseType {
             HttpResponseType::PeerInfo(ref md, _) => md,
             HttpResponseType::PoxInfo(ref md, _) => md,
             HttpResponseType::Neighbors(ref md, _) => md,
+            HttpResponseType::NodeMetadata(ref md, _) => md,
+            HttpResponseType::BannedLeaderKeys(ref md, _) => md,
+            HttpResponseType::BlockWeight(ref md, _) => md,
+            HttpResponseType::StagingQueue(ref md, _) => md,
+            HttpResponseType::MicroblockForks(ref md, _) => md,
+            HttpResponseType::TipSummary(ref md, _) => md,
+            HttpResponseType::BlocksSummary(ref md, _) => md,
+            HttpResponseType::MempoolSummary(ref md, _) => md,
+            HttpResponseType::ChainQuality(ref md, _) => md,
+            HttpResponseType::PeersSummary(ref md, _) => md,
+            HttpResponseType::Session(ref md, _) => md,
             HttpResponseType::HeaderStream(ref md) => md,
+            HttpResponseType::BlockFeed(ref md, _) => md,
+            HttpResponseType::BlockFeedStream(ref md, _) => md,
             HttpResponseType::Headers(ref md, _) => md,
             HttpResponseType::Block(ref md, _) => md,
             HttpResponseType::BlockStream(ref md) => md,
             HttpResponseType::Microblocks(ref md, _) => md,
             HttpResponseType::MicroblockStream(ref md) => md,
             HttpResponseType::TransactionID(ref md, _) => md,
+            HttpResponseType::TransactionBundleID(ref md, _) => md,
             HttpResponseType::StacksBlockAccepted(ref md, ..) => md,
             HttpResponseType::MicroblockHash(ref md, _) => md,
             HttpResponseType::TokenTransferCost(ref md, _) => md,
             HttpResponseType::GetDataVar(ref md, _) => md,
             HttpResponseType::GetMapEntry(ref md, _) => md,
+            HttpResponseType::GetClarityMarfValue(ref md, _) => md,
+            HttpResponseType::GetClarityMetadata(ref md, _) => md,
             HttpResponseType::GetAccount(ref md, _) => md,
             HttpResponseType::GetContractABI(ref md, _) => md,
             HttpResponseType::GetContractSrc(ref md, _) => md,
             HttpResponseType::GetIsTraitImplemented(ref md, _) => md,
             HttpResponseType::CallReadOnlyFunction(ref md, _) => md,
+            HttpResponseType::CallPreviewFunction(ref md, _) => md,
+            HttpResponseType::SimulateTransaction(ref md, _) => md,
             HttpResponseType::UnconfirmedTransaction(ref md, _) => md,
             HttpResponseType::GetAttachment(ref md, _) => md,
             HttpResponseType::GetAttachmentsInv(ref md, _) => md,
@@ -4021,6 +5466,8 @@ impl HttpResponseType {
             HttpResponseType::MemPoolTxs(ref md, ..) => md,
             HttpResponseType::OptionsPreflight(ref md) => md,
             HttpResponseType::TransactionFeeEstimation(ref md, _) => md,
+            HttpResponseType::BlockFeeStats(ref md, _) => md,
+            HttpResponseType::Supply(ref md, _) => md,
             // errors
             HttpResponseType::BadRequestJSON(ref md, _) => md,
             HttpResponseType::BadRequest(ref md, _) => md,
@@ -4104,6 +5551,14 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
             }
+            HttpResponseType::BlockFeeStats(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::Supply(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
             HttpResponseType::GetContractABI(ref md, ref data) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
@@ -4124,6 +5579,14 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
             }
+            HttpResponseType::CallPreviewFunction(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::SimulateTransaction(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
             HttpResponseType::GetDataVar(ref md, ref var_data) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, var_data)?;
@@ -4132,6 +5595,14 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, map_data)?;
             }
+            HttpResponseType::GetClarityMarfValue(ref md, ref marf_value) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, marf_value)?;
+            }
+            HttpResponseType::GetClarityMetadata(ref md, ref metadata) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, metadata)?;
+            }
             HttpResponseType::PeerInfo(ref md, ref peer_info) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, peer_info)?;
@@ -4144,6 +5615,50 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, neighbor_data)?;
             }
+            HttpResponseType::NodeMetadata(ref md, ref node_metadata) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, node_metadata)?;
+            }
+            HttpResponseType::BannedLeaderKeys(ref md, ref banned_leader_keys) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, banned_leader_keys)?;
+            }
+            HttpResponseType::BlockWeight(ref md, ref block_weight) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, block_weight)?;
+            }
+            HttpResponseType::StagingQueue(ref md, ref staging_queue) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, staging_queue)?;
+            }
+            HttpResponseType::MicroblockForks(ref md, ref microblock_forks) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, microblock_forks)?;
+            }
+            HttpResponseType::TipSummary(ref md, ref tip_summary) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, tip_summary)?;
+            }
+            HttpResponseType::BlocksSummary(ref md, ref blocks_summary) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, blocks_summary)?;
+            }
+            HttpResponseType::MempoolSummary(ref md, ref mempool_summary) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, mempool_summary)?;
+            }
+            HttpResponseType::ChainQuality(ref md, ref chain_quality) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, chain_quality)?;
+            }
+            HttpResponseType::PeersSummary(ref md, ref peers_summary) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, peers_summary)?;
+            }
+            HttpResponseType::Session(ref md, ref session_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, session_data)?;
+            }
             HttpResponseType::GetAttachment(ref md, ref zonefile_data) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, zonefile_data)?;
@@ -4177,6 +5692,40 @@ impl HttpResponseType {
                     |ref mut fd| keep_alive_headers(fd, md),
                 )?;
             }
+            HttpResponseType::BlockFeed(ref md, ref frames) => {
+                // sent as binary frames
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    md.content_length.clone(),
+                    &HttpContentType::Bytes,
+                    md.request_id,
+                    |ref mut fd| keep_alive_headers(fd, md),
+                )?;
+                let mut body = vec![];
+                for frame in frames.iter() {
+                    frame.consensus_serialize(&mut body)?;
+                }
+                HttpResponseType::send_text(protocol, md, fd, &body)?;
+            }
+            HttpResponseType::BlockFeedStream(ref md, ref encoding) => {
+                // only send the preamble.  The caller will need to figure out how to send along
+                // the frames themselves.
+                let content_type = match encoding {
+                    BlockFeedEncoding::Binary => HttpContentType::Bytes,
+                    BlockFeedEncoding::Json => HttpContentType::JSON,
+                };
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    None,
+                    &content_type,
+                    md.request_id,
+                    |ref mut fd| keep_alive_headers(fd, md),
+                )?;
+            }
             HttpResponseType::Block(ref md, ref block) => {
                 HttpResponsePreamble::new_serialized(
                     fd,
@@ -4256,6 +5805,10 @@ impl HttpResponseType {
                 )?;
                 HttpResponseType::send_json(protocol, md, fd, &accepted_data)?;
             }
+            HttpResponseType::TransactionBundleID(ref md, ref bundle_id) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, &bundle_id.to_hex())?;
+            }
             HttpResponseType::MicroblockHash(ref md, ref mblock_hash) => {
                 let mblock_bytes = mblock_hash.to_hex();
                 HttpResponsePreamble::new_serialized(
@@ -4410,56 +5963,94 @@ impl MessageSequence for StacksHttpMessage {
             StacksHttpMessage::Request(ref req) => match req {
                 HttpRequestType::GetInfo(_) => "HTTP(GetInfo)",
                 HttpRequestType::GetPoxInfo(_, _) => "HTTP(GetPoxInfo)",
-                HttpRequestType::GetNeighbors(_) => "HTTP(GetNeighbors)",
+                HttpRequestType::GetNeighbors(..) => "HTTP(GetNeighbors)",
+                HttpRequestType::GetNodeMetadata(_) => "HTTP(GetNodeMetadata)",
+                HttpRequestType::GetBannedLeaderKeys(_) => "HTTP(GetBannedLeaderKeys)",
+                HttpRequestType::GetTipSummary(_) => "HTTP(GetTipSummary)",
+                HttpRequestType::GetBlocksSummary(..) => "HTTP(GetBlocksSummary)",
+                HttpRequestType::GetMempoolSummary(_) => "HTTP(GetMempoolSummary)",
+                HttpRequestType::GetChainQuality(..) => "HTTP(GetChainQuality)",
+                HttpRequestType::GetPeersSummary(..) => "HTTP(GetPeersSummary)",
+                HttpRequestType::OpenSession(..) => "HTTP(OpenSession)",
+                HttpRequestType::ReleaseSession(..) => "HTTP(ReleaseSession)",
                 HttpRequestType::GetHeaders(..) => "HTTP(GetHeaders)",
+                HttpRequestType::StreamBlocks(..) => "HTTP(StreamBlocks)",
                 HttpRequestType::GetBlock(_, _) => "HTTP(GetBlock)",
+                HttpRequestType::GetBlockWeight(_, _) => "HTTP(GetBlockWeight)",
+                HttpRequestType::GetStagingQueue(..) => "HTTP(GetStagingQueue)",
                 HttpRequestType::GetMicroblocksIndexed(_, _) => "HTTP(GetMicroblocksIndexed)",
                 HttpRequestType::GetMicroblocksConfirmed(_, _) => "HTTP(GetMicroblocksConfirmed)",
                 HttpRequestType::GetMicroblocksUnconfirmed(_, _, _) => {
                     "HTTP(GetMicroblocksUnconfirmed)"
                 }
+                HttpRequestType::GetMicroblockForks(_, _) => "HTTP(GetMicroblockForks)",
                 HttpRequestType::GetTransactionUnconfirmed(_, _) => {
                     "HTTP(GetTransactionUnconfirmed)"
                 }
                 HttpRequestType::PostTransaction(_, _, _) => "HTTP(PostTransaction)",
+                HttpRequestType::PostTransactionBundle(_, _) => "HTTP(PostTransactionBundle)",
+                HttpRequestType::SimulateTransaction(..) => "HTTP(SimulateTransaction)",
                 HttpRequestType::PostBlock(..) => "HTTP(PostBlock)",
                 HttpRequestType::PostMicroblock(..) => "HTTP(PostMicroblock)",
                 HttpRequestType::GetAccount(..) => "HTTP(GetAccount)",
                 HttpRequestType::GetDataVar(..) => "HTTP(GetDataVar)",
                 HttpRequestType::GetMapEntry(..) => "HTTP(GetMapEntry)",
+                HttpRequestType::GetClarityMarfValue(..) => "HTTP(GetClarityMarfValue)",
+                HttpRequestType::GetClarityMetadata(..) => "HTTP(GetClarityMetadata)",
                 HttpRequestType::GetTransferCost(_) => "HTTP(GetTransferCost)",
                 HttpRequestType::GetContractABI(..) => "HTTP(GetContractABI)",
                 HttpRequestType::GetContractSrc(..) => "HTTP(GetContractSrc)",
                 HttpRequestType::GetIsTraitImplemented(..) => "HTTP(GetIsTraitImplemented)",
                 HttpRequestType::CallReadOnlyFunction(..) => "HTTP(CallReadOnlyFunction)",
+                HttpRequestType::CallPreviewFunction(..) => "HTTP(CallPreviewFunction)",
                 HttpRequestType::GetAttachment(..) => "HTTP(GetAttachment)",
                 HttpRequestType::GetAttachmentsInv(..) => "HTTP(GetAttachmentsInv)",
                 HttpRequestType::MemPoolQuery(..) => "HTTP(MemPoolQuery)",
                 HttpRequestType::OptionsPreflight(..) => "HTTP(OptionsPreflight)",
                 HttpRequestType::ClientError(..) => "HTTP(ClientError)",
                 HttpRequestType::FeeRateEstimate(_, _, _) => "HTTP(FeeRateEstimate)",
+                HttpRequestType::GetBlockFeeStats(..) => "HTTP(GetBlockFeeStats)",
+                HttpRequestType::GetSupply(..) => "HTTP(GetSupply)",
             },
             StacksHttpMessage::Response(ref res) => match res {
                 HttpResponseType::TokenTransferCost(_, _) => "HTTP(TokenTransferCost)",
                 HttpResponseType::GetDataVar(_, _) => "HTTP(GetDataVar)",
                 HttpResponseType::GetMapEntry(_, _) => "HTTP(GetMapEntry)",
+                HttpResponseType::GetClarityMarfValue(_, _) => "HTTP(GetClarityMarfValue)",
+                HttpResponseType::GetClarityMetadata(_, _) => "HTTP(GetClarityMetadata)",
                 HttpResponseType::GetAccount(_, _) => "HTTP(GetAccount)",
                 HttpResponseType::GetContractABI(..) => "HTTP(GetContractABI)",
                 HttpResponseType::GetContractSrc(..) => "HTTP(GetContractSrc)",
                 HttpResponseType::GetIsTraitImplemented(..) => "HTTP(GetIsTraitImplemented)",
                 HttpResponseType::CallReadOnlyFunction(..) => "HTTP(CallReadOnlyFunction)",
+                HttpResponseType::CallPreviewFunction(..) => "HTTP(CallPreviewFunction)",
+                HttpResponseType::SimulateTransaction(..) => "HTTP(SimulateTransaction)",
                 HttpResponseType::GetAttachment(_, _) => "HTTP(GetAttachment)",
                 HttpResponseType::GetAttachmentsInv(_, _) => "HTTP(GetAttachmentsInv)",
                 HttpResponseType::PeerInfo(_, _) => "HTTP(PeerInfo)",
                 HttpResponseType::PoxInfo(_, _) => "HTTP(PeerInfo)",
                 HttpResponseType::Neighbors(_, _) => "HTTP(Neighbors)",
+                HttpResponseType::NodeMetadata(_, _) => "HTTP(NodeMetadata)",
+                HttpResponseType::BannedLeaderKeys(_, _) => "HTTP(BannedLeaderKeys)",
+                HttpResponseType::BlockWeight(_, _) => "HTTP(BlockWeight)",
+                HttpResponseType::StagingQueue(_, _) => "HTTP(StagingQueue)",
+                HttpResponseType::MicroblockForks(_, _) => "HTTP(MicroblockForks)",
+                HttpResponseType::TipSummary(_, _) => "HTTP(TipSummary)",
+                HttpResponseType::BlocksSummary(_, _) => "HTTP(BlocksSummary)",
+                HttpResponseType::MempoolSummary(_, _) => "HTTP(MempoolSummary)",
+                HttpResponseType::ChainQuality(_, _) => "HTTP(ChainQuality)",
+                HttpResponseType::PeersSummary(_, _) => "HTTP(PeersSummary)",
+                HttpResponseType::Session(_, _) => "HTTP(Session)",
                 HttpResponseType::Headers(..) => "HTTP(Headers)",
                 HttpResponseType::HeaderStream(..) => "HTTP(HeaderStream)",
+                HttpResponseType::BlockFeed(..) => "HTTP(BlockFeed)",
+                HttpResponseType::BlockFeedStream(..) => "HTTP(BlockFeedStream)",
                 HttpResponseType::Block(_, _) => "HTTP(Block)",
                 HttpResponseType::BlockStream(_) => "HTTP(BlockStream)",
                 HttpResponseType::Microblocks(_, _) => "HTTP(Microblocks)",
                 HttpResponseType::MicroblockStream(_) => "HTTP(MicroblockStream)",
                 HttpResponseType::TransactionID(_, _) => "HTTP(Transaction)",
+                HttpResponseType::TransactionBundleID(_, _) => "HTTP(TransactionBundle)",
                 HttpResponseType::StacksBlockAccepted(..) => "HTTP(StacksBlockAccepted)",
                 HttpResponseType::MicroblockHash(_, _) => "HTTP(MicroblockHash)",
                 HttpResponseType::UnconfirmedTransaction(_, _) => "HTTP(UnconfirmedTransaction)",
@@ -4479,6 +6070,8 @@ impl MessageSequence for StacksHttpMessage {
                 HttpResponseType::TransactionFeeEstimation(_, _) => {
                     "HTTP(TransactionFeeEstimation)"
                 }
+                HttpResponseType::BlockFeeStats(_, _) => "HTTP(BlockFeeStats)",
+                HttpResponseType::Supply(_, _) => "HTTP(Supply)",
             },
         }
     }
@@ -4724,8 +6317,7 @@ impl StacksHttp {
         res
     }
 
-    /// Given a HTTP request, serialize it out
-    #[cfg(test)]
+    /// Given a HTTP request, serialize it out (used by clients).
     pub fn serialize_request(req: &HttpRequestType) -> Result<Vec<u8>, net_error> {
         let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
         let mut ret = vec![];
@@ -4734,7 +6326,6 @@ impl StacksHttp {
     }
 
     /// Given a fully-formed single HTTP response, parse it (used by clients).
-    #[cfg(test)]
     pub fn parse_response(
         request_path: &str,
         response_buf: &[u8],
@@ -5010,6 +6601,7 @@ mod test {
     use crate::chainstate::stacks::TransactionVersion;
     use crate::net::codec::test::check_codec_and_corruption;
     use crate::net::test::*;
+    use crate::net::BlockFeedFrame;
     use crate::net::RPCNeighbor;
     use crate::net::RPCNeighborsInfo;
     use stacks_common::util::hash::to_hex;
@@ -5965,7 +7557,7 @@ mod test {
         };
 
         let tests = vec![
-            HttpRequestType::GetNeighbors(http_request_metadata_ip.clone()),
+            HttpRequestType::GetNeighbors(http_request_metadata_ip.clone(), PageRequest::default()),
             HttpRequestType::GetBlock(http_request_metadata_dns.clone(), StacksBlockId([2u8; 32])),
             HttpRequestType::GetMicroblocksIndexed(
                 http_request_metadata_ip.clone(),
@@ -6108,11 +7700,105 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_http_parse_call_read_only_binary_body() {
+        let md = HttpRequestMetadata {
+            version: HttpVersion::Http11,
+            peer: PeerHost::DNS("www.foo.com".to_string(), 80),
+            keep_alive: true,
+            canonical_stacks_tip_height: None,
+        };
+        let sender = PrincipalData::parse("SP000000000000000000002Q6VF78").unwrap();
+        let sponsor = PrincipalData::parse("SP000000000000000000002Q6VF78.pox").unwrap();
+        let arguments = vec![
+            Value::buff_from(vec![0x11; 512 * 1024]).unwrap(),
+            Value::UInt(1),
+        ];
+        let request = HttpRequestType::CallReadOnlyFunction(
+            md,
+            StacksAddress::from_string("SP000000000000000000002Q6VF78").unwrap(),
+            ContractName::try_from("pox".to_string()).unwrap(),
+            sender.clone(),
+            Some(sponsor.clone()),
+            ClarityName::try_from("get-proof".to_string()).unwrap(),
+            arguments.clone(),
+            TipRequest::UseLatestAnchoredTip,
+        );
+
+        let mut bytes = vec![];
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        http.write_message(&mut bytes, &StacksHttpMessage::Request(request))
+            .unwrap();
+
+        // the hex-encoded JSON arguments alone would not fit in this limit
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        http.maximum_call_argument_size = 600 * 1024;
+
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        match preamble {
+            StacksHttpPreamble::Request(ref req) => {
+                assert_eq!(req.content_type, Some(HttpContentType::Bytes));
+            }
+            _ => panic!("not a request"),
+        }
+        let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+        match message {
+            StacksHttpMessage::Request(HttpRequestType::CallReadOnlyFunction(
+                _,
+                _,
+                _,
+                parsed_sender,
+                parsed_sponsor,
+                _,
+                parsed_arguments,
+                _,
+            )) => {
+                assert_eq!(parsed_sender, sender);
+                assert_eq!(parsed_sponsor, Some(sponsor));
+                assert_eq!(parsed_arguments, arguments);
+            }
+            _ => panic!("not a read-only call: {:?}", &message),
+        }
+
+        // the sender must be a principal
+        let mut bad_body = vec![];
+        write_next(&mut bad_body, &Value::UInt(1)).unwrap();
+        write_next(&mut bad_body, &Value::none()).unwrap();
+        write_next(&mut bad_body, &Vec::<Value>::new()).unwrap();
+
+        // no trailing bytes
+        let mut trailing_body = vec![];
+        write_next(
+            &mut trailing_body,
+            &CallReadOnlyBinaryRequestBody {
+                sender: sender.clone(),
+                sponsor: None,
+                arguments: vec![],
+            },
+        )
+        .unwrap();
+        trailing_body.push(0);
+
+        for body in [bad_body, trailing_body].iter() {
+            let mut request_bytes = format!(
+                "POST /v2/contracts/call-read/SP000000000000000000002Q6VF78/pox/get-proof HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            )
+            .into_bytes();
+            request_bytes.extend_from_slice(body);
+
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let (preamble, offset) = http.read_preamble(&request_bytes).unwrap();
+            let e = http.read_payload(&preamble, &request_bytes[offset..]);
+            assert!(e.is_err(), "{:?}", &e);
+        }
+    }
+
     #[test]
     fn test_http_response_type_codec() {
         let test_neighbors_info = RPCNeighborsInfo {
-            bootstrap: vec![],
-            sample: vec![
+            bootstrap: RPCList::Items(vec![]),
+            sample: RPCList::Items(vec![
                 RPCNeighbor {
                     network_id: 1,
                     peer_version: 2,
@@ -6141,9 +7827,16 @@ mod test {
                     .unwrap(),
                     authenticated: false,
                 },
-            ],
-            inbound: vec![],
-            outbound: vec![],
+            ]),
+            inbound: RPCList::Items(vec![]),
+            outbound: RPCList::Items(vec![]),
+        };
+        let page = PageRequest::new(Some(1), 0);
+        let test_paged_neighbors_info = RPCNeighborsInfo {
+            bootstrap: RPCList::from_items(vec![], &page, 1),
+            sample: RPCList::from_items(test_neighbors_info.sample.items().to_vec(), &page, 1),
+            inbound: RPCList::from_items(vec![], &page, 1),
+            outbound: RPCList::from_items(vec![], &page, 1),
         };
 
         let privk = StacksPrivateKey::from_hex(
@@ -6164,6 +7857,31 @@ mod test {
             .consensus_serialize(&mut test_microblock_info_bytes)
             .unwrap();
 
+        let test_block_feed_frames = vec![
+            BlockFeedFrame::Block {
+                cursor: StacksBlockId([0x11; 32]),
+                height: 1,
+                consensus_hash: ConsensusHash([0x22; 20]),
+                parent_block_id: StacksBlockId([0x33; 32]),
+                block: test_block_info.clone(),
+            },
+            BlockFeedFrame::Heartbeat {
+                cursor: Some(StacksBlockId([0x11; 32])),
+                tip_height: 1,
+            },
+            BlockFeedFrame::Rollback {
+                from: StacksBlockId([0x11; 32]),
+                cursor: Some(StacksBlockId([0x33; 32])),
+                height: 0,
+            },
+        ];
+        let mut test_block_feed_bytes = vec![];
+        for frame in test_block_feed_frames.iter() {
+            frame
+                .consensus_serialize(&mut test_block_feed_bytes)
+                .unwrap();
+        }
+
         let tests = vec![
             // length is known
             (
@@ -6221,6 +7939,19 @@ mod test {
                 ),
                 "/v2/transactions".to_string(),
             ),
+            (
+                HttpResponseType::TransactionBundleID(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some((Sha512Trunc256Sum([0x1; 32]).to_hex().len() + 2) as u32),
+                        true,
+                        None,
+                    ),
+                    Sha512Trunc256Sum([0x1; 32]),
+                ),
+                "/v2/transactions/bundle".to_string(),
+            ),
             // length is unknown
             (
                 HttpResponseType::Neighbors(
@@ -6229,6 +7960,13 @@ mod test {
                 ),
                 "/v2/neighbors".to_string(),
             ),
+            (
+                HttpResponseType::Neighbors(
+                    HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true, None),
+                    test_paged_neighbors_info.clone(),
+                ),
+                "/v2/neighbors?limit=1".to_string(),
+            ),
             (
                 HttpResponseType::Block(
                     HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true, None),
@@ -6236,6 +7974,13 @@ mod test {
                 ),
                 format!("/v2/blocks/{}", test_block_info.block_hash().to_hex()),
             ),
+            (
+                HttpResponseType::BlockFeed(
+                    HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true, None),
+                    test_block_feed_frames.clone(),
+                ),
+                "/v2/stream/blocks".to_string(),
+            ),
             (
                 HttpResponseType::Microblocks(
                     HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true, None),
@@ -6405,6 +8150,14 @@ mod test {
                 true,
                 123,
             ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some((Sha512Trunc256Sum([0x1; 32]).to_hex().len() + 2) as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
             // length is unknown
             HttpResponsePreamble::new(
                 200,
@@ -6414,6 +8167,22 @@ mod test {
                 true,
                 123,
             ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                None,
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                None,
+                HttpContentType::Bytes,
+                true,
+                123,
+            ),
             HttpResponsePreamble::new(
                 200,
                 "OK".to_string(),
@@ -6468,12 +8237,14 @@ mod test {
             test_block_info_bytes.clone(),
             test_microblock_info_bytes.clone(),
             Txid([0x1; 32]).to_hex().as_bytes().to_vec(),
+            Sha512Trunc256Sum([0x1; 32]).to_hex().as_bytes().to_vec(),
             // with transfer-encoding: chunked
             serde_json::to_string(&test_neighbors_info)
                 .unwrap()
                 .as_bytes()
                 .to_vec(),
-            test_block_info_bytes,
+            test_block_info_bytes.clone(),
+            test_block_feed_bytes,
             test_microblock_info_bytes,
             Txid([0x1; 32]).to_hex().as_bytes().to_vec(),
             // errors
@@ -6713,6 +8484,13 @@ mod test {
     #[test]
     fn test_http_duplicate_concurrent_streamed_response_fails() {
         // do not permit multiple in-flight chunk-encoded HTTP responses with the same request ID.
+        let empty_neighbors = RPCNeighborsInfo {
+            bootstrap: RPCList::Items(vec![]),
+            sample: RPCList::Items(vec![]),
+            inbound: RPCList::Items(vec![]),
+            outbound: RPCList::Items(vec![]),
+        };
+        // the neighbor lists keep their original shape
         let valid_neighbors_response = "HTTP/1.1 200 OK\r\nServer: stacks/v2.0\r\nX-Request-Id: 123\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n37\r\n{\"bootstrap\":[],\"sample\":[],\"inbound\":[],\"outbound\":[]}\r\n0\r\n\r\n";
         let invalid_neighbors_response = "HTTP/1.1 200 OK\r\nServer: stacks/v2.0\r\nX-Request-Id: 123\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n10\r\nxxxxxxxxxxxxxxxx\r\n0\r\n\r\n";
         let invalid_chunked_response = "HTTP/1.1 200 OK\r\nServer: stacks/v2.0\r\nX-Request-Id: 123\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n38\r\n{\"bootstrap\":[],\"sample\":[],\"inbound\":[],\"outbound\":[]}\r\n0\r\n\r\n";
@@ -6743,15 +8521,7 @@ mod test {
                     _,
                 )),
                 _,
-            ) => assert_eq!(
-                neighbors_data,
-                RPCNeighborsInfo {
-                    bootstrap: vec![],
-                    sample: vec![],
-                    inbound: vec![],
-                    outbound: vec![]
-                }
-            ),
+            ) => assert_eq!(neighbors_data, empty_neighbors),
             _ => {
                 error!("Got {:?}", &msg);
                 assert!(false);
@@ -6924,7 +8694,7 @@ mod test {
     #[test]
     fn test_http_parse_proof_tip_query() {
         let query_txt = "tip=7070f213d719143d6045e08fd80f85014a161f8bbd3a42d1251576740826a392";
-        let tip_req = HttpRequestType::get_chain_tip_query(Some(query_txt));
+        let tip_req = HttpRequestType::get_chain_tip_query(Some(query_txt)).unwrap();
         match tip_req {
             TipRequest::SpecificTip(tip) => assert_eq!(
                 tip,
@@ -6938,7 +8708,7 @@ mod test {
 
         // first parseable tip is taken
         let query_txt_dup = "tip=7070f213d719143d6045e08fd80f85014a161f8bbd3a42d1251576740826a392&tip=03e26bd68a8722f8b3861e2058edcafde094ad059e152754986c3573306698f1";
-        let tip_req = HttpRequestType::get_chain_tip_query(Some(query_txt));
+        let tip_req = HttpRequestType::get_chain_tip_query(Some(query_txt)).unwrap();
         match tip_req {
             TipRequest::SpecificTip(tip) => assert_eq!(
                 tip,
@@ -6952,7 +8722,7 @@ mod test {
 
         // first parseable tip is taken
         let query_txt_dup = "tip=bad&tip=7070f213d719143d6045e08fd80f85014a161f8bbd3a42d1251576740826a392&tip=03e26bd68a8722f8b3861e2058edcafde094ad059e152754986c3573306698f1";
-        let tip_req = HttpRequestType::get_chain_tip_query(Some(query_txt_dup));
+        let tip_req = HttpRequestType::get_chain_tip_query(Some(query_txt_dup)).unwrap();
         match tip_req {
             TipRequest::SpecificTip(tip) => assert_eq!(
                 tip,
@@ -6967,16 +8737,195 @@ mod test {
         // tip can be skipped
         let query_txt_bad = "tip=bad";
         assert_eq!(
-            HttpRequestType::get_chain_tip_query(Some(query_txt_bad)),
+            HttpRequestType::get_chain_tip_query(Some(query_txt_bad)).unwrap(),
             TipRequest::UseLatestAnchoredTip
         );
 
         // tip can be skipped
         let query_txt_none = "tip=bad";
         assert_eq!(
-            HttpRequestType::get_chain_tip_query(Some(query_txt_none)),
+            HttpRequestType::get_chain_tip_query(Some(query_txt_none)).unwrap(),
             TipRequest::UseLatestAnchoredTip
         );
+
+        // session can be given instead of a tip
+        let query_txt_session = "session=00112233445566778899aabbccddeeff&proof=0";
+        assert_eq!(
+            HttpRequestType::get_chain_tip_query(Some(query_txt_session)).unwrap(),
+            TipRequest::Session(
+                RPCSessionId::from_hex("00112233445566778899aabbccddeeff").unwrap()
+            )
+        );
+
+        // a malformed session is rejected, rather than falling back to an unpinned tip
+        let query_txt_bad_session = "session=bad";
+        match HttpRequestType::get_chain_tip_query(Some(query_txt_bad_session)) {
+            Err(net_error::ClientError(ClientError::Message(_))) => {}
+            x => panic!("expected a client error, got {:?}", &x),
+        }
+    }
+
+    #[test]
+    fn test_http_parse_paged_list_requests() {
+        let md = HttpRequestMetadata {
+            version: HttpVersion::Http11,
+            peer: PeerHost::DNS("www.foo.com".to_string(), 80),
+            keep_alive: true,
+            canonical_stacks_tip_height: None,
+        };
+        let tip = StacksBlockId([0x11; 32]);
+        let requests = vec![
+            HttpRequestType::GetNeighbors(md.clone(), PageRequest::new(Some(5), 10)),
+            HttpRequestType::GetHeaders(
+                md.clone(),
+                PageRequest::new(Some(25), 0),
+                TipRequest::SpecificTip(tip.clone()),
+            ),
+            HttpRequestType::GetHeaders(
+                md.clone(),
+                PageRequest::from_cursor(None, StacksBlockId([0x22; 32]).to_hex()),
+                TipRequest::UseLatestAnchoredTip,
+            ),
+            HttpRequestType::MemPoolQuery(
+                md.clone(),
+                MemPoolSyncData::TxTags([0u8; 32], vec![]),
+                PageRequest::from_cursor(Some(7), Txid([0x33; 32]).to_hex()),
+            ),
+            HttpRequestType::GetStagingQueue(md.clone(), PageRequest::new(Some(10), 20)),
+            HttpRequestType::GetStagingQueue(md.clone(), PageRequest::new(None, 0)),
+            HttpRequestType::GetBlockFeeStats(
+                md.clone(),
+                PageRequest::from_cursor(Some(5), StacksBlockId([0x22; 32]).to_hex()),
+                TipRequest::SpecificTip(tip.clone()),
+            ),
+            // no page asked for, so the count goes in the path
+            HttpRequestType::GetHeaders(
+                md.clone(),
+                PageRequest {
+                    limit: Some(25),
+                    ..PageRequest::default()
+                },
+                TipRequest::SpecificTip(tip.clone()),
+            ),
+            HttpRequestType::GetBlockFeeStats(
+                md.clone(),
+                PageRequest {
+                    limit: Some(10),
+                    ..PageRequest::default()
+                },
+                TipRequest::UseLatestAnchoredTip,
+            ),
+            HttpRequestType::GetStagingQueue(md.clone(), PageRequest::default()),
+            HttpRequestType::GetNeighbors(md.clone(), PageRequest::default()),
+        ];
+
+        for request in requests.into_iter() {
+            let mut bytes = vec![];
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            http.write_message(&mut bytes, &StacksHttpMessage::Request(request.clone()))
+                .unwrap();
+
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+            match message {
+                StacksHttpMessage::Request(parsed) => {
+                    assert_eq!(parsed.request_path(), request.request_path());
+                    match (parsed, request) {
+                        (
+                            HttpRequestType::GetNeighbors(_, parsed_page),
+                            HttpRequestType::GetNeighbors(_, page),
+                        ) => assert_eq!(parsed_page, page),
+                        (
+                            HttpRequestType::GetHeaders(_, parsed_page, parsed_tip),
+                            HttpRequestType::GetHeaders(_, page, tip_req),
+                        ) => {
+                            assert_eq!(parsed_page, page);
+                            assert_eq!(parsed_tip, tip_req);
+                        }
+                        (
+                            HttpRequestType::MemPoolQuery(_, _, parsed_page),
+                            HttpRequestType::MemPoolQuery(_, _, page),
+                        ) => assert_eq!(parsed_page, page),
+                        (
+                            HttpRequestType::GetStagingQueue(_, parsed_page),
+                            HttpRequestType::GetStagingQueue(_, page),
+                        ) => assert_eq!(parsed_page, page),
+                        (
+                            HttpRequestType::GetBlockFeeStats(_, parsed_page, parsed_tip),
+                            HttpRequestType::GetBlockFeeStats(_, page, tip_req),
+                        ) => {
+                            assert_eq!(parsed_page, page);
+                            assert_eq!(parsed_tip, tip_req);
+                        }
+                        (parsed, request) => panic!("parsed {:?} from {:?}", &parsed, &request),
+                    }
+                }
+                _ => panic!("not a request: {:?}", &message),
+            }
+        }
+
+        // legacy forms are still understood
+        let legacy_requests = vec![
+            (
+                format!(
+                    "GET /v2/headers/25?tip={} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\n\r\n",
+                    &tip
+                ),
+                PageRequest {
+                    limit: Some(25),
+                    ..PageRequest::default()
+                },
+            ),
+            (
+                format!(
+                    "GET /v2/headers/25?tip={}&offset=0 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\n\r\n",
+                    &tip
+                ),
+                PageRequest::new(Some(25), 0),
+            ),
+            (
+                format!(
+                    "POST /v2/mempool/query?page_id={} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\nContent-Type: application/octet-stream\r\nContent-Length: 37\r\n\r\n",
+                    &Txid([0x33; 32])
+                ),
+                PageRequest::from_cursor(None, Txid([0x33; 32]).to_hex()),
+            ),
+        ];
+        for (request_txt, expected_page) in legacy_requests.into_iter() {
+            let mut request_bytes = request_txt.into_bytes();
+            if request_bytes.starts_with(b"POST") {
+                MemPoolSyncData::TxTags([0u8; 32], vec![])
+                    .consensus_serialize(&mut request_bytes)
+                    .unwrap();
+            }
+
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let (preamble, offset) = http.read_preamble(&request_bytes).unwrap();
+            let (message, _) = http
+                .read_payload(&preamble, &request_bytes[offset..])
+                .unwrap();
+            match message {
+                StacksHttpMessage::Request(HttpRequestType::GetHeaders(_, page, tip_req)) => {
+                    assert_eq!(page, expected_page);
+                    assert_eq!(tip_req, TipRequest::SpecificTip(tip.clone()));
+                }
+                StacksHttpMessage::Request(HttpRequestType::MemPoolQuery(_, _, page)) => {
+                    assert_eq!(page, expected_page);
+                }
+                _ => panic!("unexpected request: {:?}", &message),
+            }
+        }
+
+        // the header count can't be given twice
+        let request_bytes =
+            "GET /v2/headers/25?limit=10 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\n\r\n"
+                .as_bytes();
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let (preamble, offset) = http.read_preamble(request_bytes).unwrap();
+        assert!(http
+            .read_payload(&preamble, &request_bytes[offset..])
+            .is_err());
     }
 
     #[test]
@@ -49,11 +49,15 @@ use crate::burnchains::Txid;
 use crate::chainstate::burn::ConsensusHash;
 use crate::chainstate::coordinator::Error as coordinator_error;
 use crate::chainstate::stacks::db::blocks::MemPoolRejection;
+use crate::chainstate::stacks::db::blocks::{MicroblockForkTree, StagingQueueEntry};
+use crate::chainstate::stacks::db::supply::SupplyInfo;
+use crate::chainstate::stacks::db::BlockFeeStats;
+use crate::chainstate::stacks::db::BlockWeight;
 use crate::chainstate::stacks::index::Error as marf_error;
 use crate::chainstate::stacks::Error as chainstate_error;
 use crate::chainstate::stacks::{
     Error as chain_error, StacksBlock, StacksMicroblock, StacksPublicKey, StacksTransaction,
-    TransactionPayload,
+    StacksTransactionBundle, TransactionPayload,
 };
 use crate::clarity_vm::clarity::Error as clarity_error;
 use crate::core::mempool::*;
@@ -61,21 +65,26 @@ use crate::core::POX_REWARD_CYCLE_LENGTH;
 use crate::net::atlas::{Attachment, AttachmentInstance};
 use crate::net::http::HttpReservedHeader;
 pub use crate::net::http::StacksBlockAcceptedData;
+use crate::net::pagination::{PageRequest, RPCList, RPCPage};
+use crate::net::session::RPCSessionId;
 use crate::util_lib::bloom::{BloomFilter, BloomNodeHasher};
 use crate::util_lib::boot::boot_code_tx_auth;
 use crate::util_lib::db::DBConn;
 use crate::util_lib::db::Error as db_error;
 use crate::util_lib::strings::UrlString;
-use clarity::vm::types::TraitIdentifier;
+use clarity::vm::errors::VmErrorCode;
+use clarity::vm::types::{OptionalData, TraitIdentifier};
 use clarity::vm::{
     analysis::contract_interface_builder::ContractInterface, types::PrincipalData, ClarityName,
     ContractName, Value,
 };
+use clarity_derive::StacksError;
 use stacks_common::codec::Error as codec_error;
 use stacks_common::codec::StacksMessageCodec;
 use stacks_common::codec::{read_next, write_next};
 use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::hash::Hash160;
+use stacks_common::util::hash::Sha512Trunc256Sum;
 use stacks_common::util::hash::DOUBLE_SHA256_ENCODED_SIZE;
 use stacks_common::util::hash::HASH160_ENCODED_SIZE;
 use stacks_common::util::hash::{hex_bytes, to_hex};
@@ -117,6 +126,9 @@ pub mod chat;
 /// Also has functionality to sign, verify, and ensure well-formedness of messages.
 pub mod codec;
 pub mod connection;
+/// Implements `Crawler`, which maps the p2p network's topology by handshaking with and querying
+/// the neighbors of every reachable peer, without syncing any blocks.
+pub mod crawl;
 pub mod db;
 /// Implements `DNSResolver`, a simple DNS resolver state machine. Also implements `DNSClient`,
 /// which serves as an API for `DNSResolver`.  
@@ -126,125 +138,194 @@ pub mod http;
 pub mod inv;
 pub mod neighbors;
 pub mod p2p;
+pub mod pagination;
 /// Implements wrapper around `mio` crate, which itself is a wrapper around Linux's `epoll(2)` syscall.
 /// Creates a pollable interface for sockets, and provides an API for registering and deregistering
 /// sockets. This is used to control how many sockets are allocated for the two network servers: the
 /// p2p server and the http server.
 pub mod poll;
 pub mod prune;
+pub mod readonly_cache;
 pub mod relay;
 pub mod rpc;
+#[cfg(test)]
+pub mod scenario;
 pub mod server;
+pub mod session;
 
-#[derive(Debug)]
+#[derive(Debug, StacksError)]
+#[error(label = "net")]
 pub enum Error {
     /// Failed to encode
+    #[error(code = 3001)]
     SerializeError(String),
     /// Failed to read
+    #[error(code = 3002, source)]
     ReadError(io::Error),
     /// Failed to decode
+    #[error(code = 3003)]
     DeserializeError(String),
     /// Failed to write
+    #[error(code = 3004, source)]
     WriteError(io::Error),
     /// Underflow -- not enough bytes to form the message
+    #[error(code = 3005)]
     UnderflowError(String),
     /// Overflow -- message too big
+    #[error(code = 3006)]
     OverflowError(String),
     /// Wrong protocol family
+    #[error(code = 3007, display = "Improper use of protocol family")]
     WrongProtocolFamily,
     /// Array is too big
+    #[error(code = 3008, display = "Array too long")]
     ArrayTooLong,
     /// Receive timed out
+    #[error(code = 3009, display = "Packet receive timeout")]
     RecvTimeout,
     /// Error signing a message
+    #[error(code = 3010)]
     SigningError(String),
     /// Error verifying a message
+    #[error(code = 3011)]
     VerifyingError(String),
     /// Read stream is drained.  Try again
+    #[error(
+        code = 3012,
+        display = "Temporarily out of bytes to read; try again later"
+    )]
     TemporarilyDrained,
     /// Read stream has reached EOF (socket closed, end-of-file reached, etc.)
+    #[error(code = 3013, display = "Out of bytes to read")]
     PermanentlyDrained,
     /// Failed to read from the FS
+    #[error(code = 3014, display = "Disk I/O error")]
     FilesystemError,
     /// Database error
+    #[error(code = 3015, source)]
     DBError(db_error),
     /// Socket mutex was poisoned
+    #[error(code = 3016, display = "socket mutex was poisoned")]
     SocketMutexPoisoned,
     /// Socket not instantiated
+    #[error(code = 3017, display = "not connected to peer")]
     SocketNotConnectedToPeer,
     /// Not connected to peer
+    #[error(code = 3018, display = "connection to peer node is broken")]
     ConnectionBroken,
     /// Connection could not be (re-)established
+    #[error(
+        code = 3019,
+        display = "connection to peer could not be (re-)established"
+    )]
     ConnectionError,
     /// Too many outgoing messages
+    #[error(code = 3020, display = "too many outgoing messages queued")]
     OutboxOverflow,
     /// Too many incoming messages
+    #[error(code = 3021, display = "too many messages pending")]
     InboxOverflow,
     /// Send error
+    #[error(code = 3022)]
     SendError(String),
     /// Recv error
+    #[error(code = 3023)]
     RecvError(String),
     /// Invalid message
+    #[error(code = 3024, display = "invalid message (malformed or bad signature)")]
     InvalidMessage,
     /// Invalid network handle
+    #[error(code = 3025, display = "invalid network handle")]
     InvalidHandle,
     /// Network handle is full
+    #[error(
+        code = 3026,
+        display = "network handle is full and needs to be drained"
+    )]
     FullHandle,
     /// Invalid handshake
+    #[error(code = 3027, display = "invalid handshake from remote peer")]
     InvalidHandshake,
     /// Stale neighbor
+    #[error(code = 3028, display = "neighbor is too far behind the chain tip")]
     StaleNeighbor,
     /// No such neighbor
+    #[error(code = 3029, display = "no such neighbor")]
     NoSuchNeighbor,
     /// Failed to bind
+    #[error(code = 3030, display = "Failed to bind to the given address")]
     BindError,
     /// Failed to poll
+    #[error(code = 3031, display = "Failed to poll")]
     PollError,
     /// Failed to accept
+    #[error(code = 3032, display = "Failed to accept connection")]
     AcceptError,
     /// Failed to register socket with poller
+    #[error(code = 3033, display = "Failed to register socket with poller")]
     RegisterError,
     /// Failed to query socket metadata
+    #[error(code = 3034, display = "Socket error")]
     SocketError,
     /// server is not bound to a socket
+    #[error(code = 3035, display = "Not connected to peer network")]
     NotConnected,
     /// Remote peer is not connected
+    #[error(code = 3036, display = "Remote peer is not connected to us")]
     PeerNotConnected,
     /// Too many peers
+    #[error(code = 3037, display = "Too many peer connections open")]
     TooManyPeers,
     /// Peer already connected
+    #[error(code = 3038, display = "Peer already connected")]
     AlreadyConnected(usize, NeighborKey),
     /// Message already in progress
+    #[error(code = 3039, display = "Message already in progress")]
     InProgress,
     /// Peer is denied
+    #[error(code = 3040, display = "Peer is denied")]
     Denied,
     /// Data URL is not known
+    #[error(code = 3041, display = "No data URL available")]
     NoDataUrl,
     /// Peer is transmitting too fast
+    #[error(code = 3042, display = "Peer is transmitting too fast")]
     PeerThrottled,
     /// Error resolving a DNS name
+    #[error(code = 3043)]
     LookupError(String),
     /// MARF error, percolated up from chainstate
+    #[error(code = 3044, source)]
     MARFError(marf_error),
     /// Clarity VM error, percolated up from chainstate
+    #[error(code = 3045, source)]
     ClarityError(clarity_error),
     /// Catch-all for chainstate errors that don't map cleanly into network errors
+    #[error(code = 3046)]
     ChainstateError(String),
     /// Catch-all for errors that a client should receive more information about
+    #[error(code = 3047, source, display = "ClientError: {0}")]
     ClientError(ClientError),
     /// Coordinator hung up
+    #[error(code = 3048, display = "Coordinator hung up")]
     CoordinatorClosed,
     /// view of state is stale (e.g. from the sortition db)
+    #[error(code = 3049, display = "State view is stale")]
     StaleView,
     /// Tried to connect to myself
+    #[error(code = 3050, display = "Tried to connect to myself")]
     ConnectionCycle,
     /// Requested data not found
+    #[error(code = 3051, display = "Requested data not found")]
     NotFoundError,
     /// Transient error (akin to EAGAIN)
+    #[error(code = 3052, display = "Transient network error: {0}")]
     Transient(String),
     /// Expected end-of-stream, but had more data
+    #[error(code = 3053, display = "Expected end-of-stream")]
     ExpectedEndOfStream,
     /// burnchain error
+    #[error(code = 3054, source)]
     BurnchainError(burnchain_error),
 }
 
@@ -288,130 +369,6 @@ impl fmt::Display for ClientError {
     }
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Error::SerializeError(ref s) => fmt::Display::fmt(s, f),
-            Error::DeserializeError(ref s) => fmt::Display::fmt(s, f),
-            Error::ReadError(ref io) => fmt::Display::fmt(io, f),
-            Error::WriteError(ref io) => fmt::Display::fmt(io, f),
-            Error::UnderflowError(ref s) => fmt::Display::fmt(s, f),
-            Error::OverflowError(ref s) => fmt::Display::fmt(s, f),
-            Error::WrongProtocolFamily => write!(f, "Improper use of protocol family"),
-            Error::ArrayTooLong => write!(f, "Array too long"),
-            Error::RecvTimeout => write!(f, "Packet receive timeout"),
-            Error::SigningError(ref s) => fmt::Display::fmt(s, f),
-            Error::VerifyingError(ref s) => fmt::Display::fmt(s, f),
-            Error::TemporarilyDrained => {
-                write!(f, "Temporarily out of bytes to read; try again later")
-            }
-            Error::PermanentlyDrained => write!(f, "Out of bytes to read"),
-            Error::FilesystemError => write!(f, "Disk I/O error"),
-            Error::DBError(ref e) => fmt::Display::fmt(e, f),
-            Error::SocketMutexPoisoned => write!(f, "socket mutex was poisoned"),
-            Error::SocketNotConnectedToPeer => write!(f, "not connected to peer"),
-            Error::ConnectionBroken => write!(f, "connection to peer node is broken"),
-            Error::ConnectionError => write!(f, "connection to peer could not be (re-)established"),
-            Error::OutboxOverflow => write!(f, "too many outgoing messages queued"),
-            Error::InboxOverflow => write!(f, "too many messages pending"),
-            Error::SendError(ref s) => fmt::Display::fmt(s, f),
-            Error::RecvError(ref s) => fmt::Display::fmt(s, f),
-            Error::InvalidMessage => write!(f, "invalid message (malformed or bad signature)"),
-            Error::InvalidHandle => write!(f, "invalid network handle"),
-            Error::FullHandle => write!(f, "network handle is full and needs to be drained"),
-            Error::InvalidHandshake => write!(f, "invalid handshake from remote peer"),
-            Error::StaleNeighbor => write!(f, "neighbor is too far behind the chain tip"),
-            Error::NoSuchNeighbor => write!(f, "no such neighbor"),
-            Error::BindError => write!(f, "Failed to bind to the given address"),
-            Error::PollError => write!(f, "Failed to poll"),
-            Error::AcceptError => write!(f, "Failed to accept connection"),
-            Error::RegisterError => write!(f, "Failed to register socket with poller"),
-            Error::SocketError => write!(f, "Socket error"),
-            Error::NotConnected => write!(f, "Not connected to peer network"),
-            Error::PeerNotConnected => write!(f, "Remote peer is not connected to us"),
-            Error::TooManyPeers => write!(f, "Too many peer connections open"),
-            Error::AlreadyConnected(ref _id, ref _nk) => write!(f, "Peer already connected"),
-            Error::InProgress => write!(f, "Message already in progress"),
-            Error::Denied => write!(f, "Peer is denied"),
-            Error::NoDataUrl => write!(f, "No data URL available"),
-            Error::PeerThrottled => write!(f, "Peer is transmitting too fast"),
-            Error::LookupError(ref s) => fmt::Display::fmt(s, f),
-            Error::ChainstateError(ref s) => fmt::Display::fmt(s, f),
-            Error::ClarityError(ref e) => fmt::Display::fmt(e, f),
-            Error::MARFError(ref e) => fmt::Display::fmt(e, f),
-            Error::ClientError(ref e) => write!(f, "ClientError: {}", e),
-            Error::CoordinatorClosed => write!(f, "Coordinator hung up"),
-            Error::StaleView => write!(f, "State view is stale"),
-            Error::ConnectionCycle => write!(f, "Tried to connect to myself"),
-            Error::NotFoundError => write!(f, "Requested data not found"),
-            Error::Transient(ref s) => write!(f, "Transient network error: {}", s),
-            Error::ExpectedEndOfStream => write!(f, "Expected end-of-stream"),
-            Error::BurnchainError(ref e) => fmt::Display::fmt(e, f),
-        }
-    }
-}
-
-impl error::Error for Error {
-    fn cause(&self) -> Option<&dyn error::Error> {
-        match *self {
-            Error::SerializeError(ref _s) => None,
-            Error::ReadError(ref io) => Some(io),
-            Error::DeserializeError(ref _s) => None,
-            Error::WriteError(ref io) => Some(io),
-            Error::UnderflowError(ref _s) => None,
-            Error::OverflowError(ref _s) => None,
-            Error::WrongProtocolFamily => None,
-            Error::ArrayTooLong => None,
-            Error::RecvTimeout => None,
-            Error::SigningError(ref _s) => None,
-            Error::VerifyingError(ref _s) => None,
-            Error::TemporarilyDrained => None,
-            Error::PermanentlyDrained => None,
-            Error::FilesystemError => None,
-            Error::DBError(ref e) => Some(e),
-            Error::SocketMutexPoisoned => None,
-            Error::SocketNotConnectedToPeer => None,
-            Error::ConnectionBroken => None,
-            Error::ConnectionError => None,
-            Error::OutboxOverflow => None,
-            Error::InboxOverflow => None,
-            Error::SendError(ref _s) => None,
-            Error::RecvError(ref _s) => None,
-            Error::InvalidMessage => None,
-            Error::InvalidHandle => None,
-            Error::FullHandle => None,
-            Error::InvalidHandshake => None,
-            Error::StaleNeighbor => None,
-            Error::NoSuchNeighbor => None,
-            Error::BindError => None,
-            Error::PollError => None,
-            Error::AcceptError => None,
-            Error::RegisterError => None,
-            Error::SocketError => None,
-            Error::NotConnected => None,
-            Error::PeerNotConnected => None,
-            Error::TooManyPeers => None,
-            Error::AlreadyConnected(ref _id, ref _nk) => None,
-            Error::InProgress => None,
-            Error::Denied => None,
-            Error::NoDataUrl => None,
-            Error::PeerThrottled => None,
-            Error::LookupError(ref _s) => None,
-            Error::ChainstateError(ref _s) => None,
-            Error::ClientError(ref e) => Some(e),
-            Error::ClarityError(ref e) => Some(e),
-            Error::MARFError(ref e) => Some(e),
-            Error::CoordinatorClosed => None,
-            Error::StaleView => None,
-            Error::ConnectionCycle => None,
-            Error::NotFoundError => None,
-            Error::Transient(ref _s) => None,
-            Error::ExpectedEndOfStream => None,
-            Error::BurnchainError(ref e) => Some(e),
-        }
-    }
-}
-
 impl From<chain_error> for Error {
     fn from(e: chain_error) -> Error {
         match e {
@@ -785,7 +742,9 @@ pub struct BlocksAvailableData {
 }
 
 /// A descriptor of a peer
-#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(
+    Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, StacksMessageCodec,
+)]
 pub struct NeighborAddress {
     #[serde(rename = "ip")]
     pub addrbytes: PeerAddress,
@@ -830,8 +789,10 @@ impl NeighborAddress {
 }
 
 /// A descriptor of a list of known peers
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, StacksMessageCodec)]
 pub struct NeighborsData {
+    /// don't allow a list of more than the pre-set number of neighbors
+    #[codec(max_len = MAX_NEIGHBORS_DATA_LEN)]
     pub neighbors: Vec<NeighborAddress>,
 }
 
@@ -856,6 +817,7 @@ pub struct HandshakeData {
 pub enum ServiceFlags {
     RELAY = 0x01,
     RPC = 0x02,
+    METADATA = 0x04,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -886,13 +848,36 @@ pub struct PongData {
     pub nonce: u32,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, StacksMessageCodec)]
 pub struct NatPunchData {
     pub addrbytes: PeerAddress,
     pub port: u16,
     pub nonce: u32,
 }
 
+/// Maximum length of the free-form strings in a node's metadata record
+pub const MAX_NODE_METADATA_STRING_LEN: u8 = 128;
+
+/// Self-reported information about a node, which crawlers and monitoring services can use to map
+/// the network.  Nodes only exchange it if they both advertise ServiceFlags::METADATA.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeMetadata {
+    pub peer_version: u32,
+    pub node_version: String, // software version string
+    pub services: u16,
+    pub rpc_url: UrlString, // empty if the node's RPC interface is not publicly routable
+    pub operator_contact: String, // empty if the operator did not give one
+    pub timestamp: u64,     // when the record was signed
+}
+
+/// A node's metadata, signed by the node's p2p key
+#[derive(Debug, Clone, PartialEq, StacksMessageCodec)]
+pub struct NodeMetadataData {
+    pub metadata: NodeMetadata,
+    pub node_public_key: StacksPublicKeyBuffer,
+    pub signature: MessageSignature,
+}
+
 define_u8_enum!(MemPoolSyncDataID {
     BloomFilter = 0x01,
     TxTags = 0x02
@@ -932,6 +917,8 @@ pub enum StacksMessageType {
     Pong(PongData),
     NatPunchRequest(u32),
     NatPunchReply(NatPunchData),
+    GetNodeMetadata,
+    NodeMetadata(NodeMetadataData),
 }
 
 /// Peer address variants
@@ -1261,6 +1248,22 @@ pub struct MapEntryResponse {
     pub marf_proof: Option<String>,
 }
 
+/// A raw value from the Clarity key-value store, as the VM stored it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClarityMarfValueResponse {
+    pub data: String,
+    #[serde(rename = "proof")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marf_proof: Option<String>,
+}
+
+/// A raw metadata entry of a contract, as the VM stored it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClarityMetadataResponse {
+    pub data: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ContractSrcResponse {
     pub source: String,
@@ -1287,6 +1290,68 @@ pub struct CallReadOnlyResponse {
     pub cause: Option<String>,
 }
 
+/// Result of previewing a contract call.  The call's changes are never persisted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallPreviewResponse {
+    pub okay: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cause: Option<String>,
+    /// False if the call returned `(err ..)`, in which case a transaction making it would emit
+    /// no events
+    #[serde(default)]
+    pub committed: bool,
+    /// Events the call would emit, in the same format as the event observer interface
+    #[serde(default)]
+    pub events: Vec<serde_json::Value>,
+    /// STX and tokens the call would move out of each principal, i.e. what post-conditions check
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_movements: Option<serde_json::Value>,
+}
+
+/// Result of simulating a transaction on top of a chain tip.  The transaction's effects are never
+/// persisted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionSimulationResponse {
+    pub okay: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cause: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    /// False if the transaction returned `(err ..)` or was aborted by its post-conditions, in
+    /// which case only its fee would be paid
+    #[serde(default)]
+    pub committed: bool,
+    #[serde(default)]
+    pub post_condition_aborted: bool,
+    /// Runtime error the transaction encountered, if any
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vm_error: Option<String>,
+    /// Stable error code and context fields for `vm_error`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vm_error_code: Option<VmErrorCode>,
+    /// Events the transaction would emit, in the same format as the event observer interface
+    #[serde(default)]
+    pub events: Vec<serde_json::Value>,
+    /// STX and tokens the transaction would move out of each principal, i.e. what
+    /// post-conditions check
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_movements: Option<serde_json::Value>,
+    /// Execution cost the transaction would consume
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_cost: Option<ExecutionCost>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AccountEntryResponse {
     pub balance: String,
@@ -1299,6 +1364,30 @@ pub struct AccountEntryResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub nonce_proof: Option<String>,
+    /// Only given if asked for with `unanchored=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub unanchored: Option<UnanchoredAccountEntryResponse>,
+}
+
+/// The state of an account once the microblocks confirmed so far on top of the anchored tip are
+/// applied.  The top-level fields of `AccountEntryResponse` always reflect the anchored tip.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnanchoredAccountEntryResponse {
+    /// Index block hash of the anchored block the microblock stream builds on
+    pub anchored_tip: StacksBlockId,
+    /// Hash and sequence number of the last microblock applied, if there are any
+    pub microblock_tip: Option<BlockHeaderHash>,
+    pub microblock_seq: Option<u16>,
+    pub balance: String,
+    pub locked: String,
+    pub unlock_height: u64,
+    /// The next nonce to use for this account, given its microblock-confirmed transactions
+    pub nonce: u64,
+    /// Unanchored minus anchored unlocked balance, as a signed decimal integer
+    pub balance_delta: String,
+    /// Number of this account's transactions confirmed in microblocks on top of the anchored tip
+    pub nonce_delta: u64,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -1415,6 +1504,67 @@ pub struct CallReadOnlyRequestBody {
     pub arguments: Vec<String>,
 }
 
+/// Binary (`application/octet-stream`) alternative to `CallReadOnlyRequestBody`.  The sender is
+/// a serialized Clarity principal, the sponsor a serialized Clarity `(optional principal)`, and
+/// the arguments a big-endian u32 count followed by that many serialized Clarity values.  The
+/// arguments are not hex-encoded, so they take half the space, and each one is deserialized
+/// straight from the request body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallReadOnlyBinaryRequestBody {
+    pub sender: PrincipalData,
+    pub sponsor: Option<PrincipalData>,
+    pub arguments: Vec<Value>,
+}
+
+impl StacksMessageCodec for CallReadOnlyBinaryRequestBody {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &Value::Principal(self.sender.clone()))?;
+        let sponsor = match self.sponsor {
+            Some(ref sponsor) => Value::some(Value::Principal(sponsor.clone()))
+                .map_err(|e| codec_error::SerializeError(format!("{:?}", &e)))?,
+            None => Value::none(),
+        };
+        write_next(fd, &sponsor)?;
+        write_next(fd, &self.arguments)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(
+        fd: &mut R,
+    ) -> Result<CallReadOnlyBinaryRequestBody, codec_error> {
+        let sender = match read_next(fd)? {
+            Value::Principal(sender) => sender,
+            _ => {
+                return Err(codec_error::DeserializeError(
+                    "Sender is not a principal".to_string(),
+                ))
+            }
+        };
+        let sponsor = match read_next(fd)? {
+            Value::Optional(OptionalData { data: None }) => None,
+            Value::Optional(OptionalData { data: Some(value) }) => match *value {
+                Value::Principal(sponsor) => Some(sponsor),
+                _ => {
+                    return Err(codec_error::DeserializeError(
+                        "Sponsor is not an optional principal".to_string(),
+                    ))
+                }
+            },
+            _ => {
+                return Err(codec_error::DeserializeError(
+                    "Sponsor is not an optional principal".to_string(),
+                ))
+            }
+        };
+        let arguments = read_next(fd)?;
+        Ok(CallReadOnlyBinaryRequestBody {
+            sender,
+            sponsor,
+            arguments,
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct FeeRateEstimateRequestBody {
     #[serde(default)]
@@ -1447,13 +1597,234 @@ impl RPCNeighbor {
     }
 }
 
-/// Struct given back from a call to `/v2/neighbors`.
+/// Struct given back from a call to `/v2/neighbors`.  If a page is asked for, each list is paged
+/// independently, with the same `limit` and `offset`.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RPCNeighborsInfo {
-    pub bootstrap: Vec<RPCNeighbor>,
-    pub sample: Vec<RPCNeighbor>,
-    pub inbound: Vec<RPCNeighbor>,
-    pub outbound: Vec<RPCNeighbor>,
+    pub bootstrap: RPCList<RPCNeighbor>,
+    pub sample: RPCList<RPCNeighbor>,
+    pub inbound: RPCList<RPCNeighbor>,
+    pub outbound: RPCList<RPCNeighbor>,
+}
+
+/// A node's signed metadata record, as given back from `/v2/neighbors/metadata`.  The signature
+/// covers the consensus encoding of the record's fields, so clients can check it themselves.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCNodeMetadata {
+    pub network_id: u32,
+    #[serde(rename = "ip")]
+    pub addrbytes: PeerAddress,
+    pub port: u16,
+    pub public_key: String,
+    pub peer_version: u32,
+    pub node_version: String,
+    pub services: u16,
+    pub rpc_url: String,
+    pub operator_contact: String,
+    pub timestamp: u64,
+    pub signature: String,
+    /// when this node received the record; absent for this node's own record
+    pub received_at: Option<u64>,
+}
+
+/// Struct given back from a call to `/v2/neighbors/metadata`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCNodeMetadataInfo {
+    pub local: RPCNodeMetadata,
+    pub neighbors: Vec<RPCNodeMetadata>,
+}
+
+/// A leader key banned for producing a consensus-invalid block, as given back from a call to
+/// `/v2/miners/banned`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBannedLeaderKey {
+    /// hex-encoded VRF public key
+    pub public_key: String,
+    /// the invalid block that got the key banned
+    pub consensus_hash: ConsensusHash,
+    pub block_hash: BlockHeaderHash,
+    pub reason: String,
+    pub banned_at: u64,
+}
+
+/// Struct given back from a call to `/v2/summary/tip`.
+/// A compact view of the node's burnchain and Stacks chain tips, suitable for polling by an
+/// operator dashboard.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCTipSummary {
+    pub burn_block_height: u64,
+    pub burn_block_hash: BurnchainHeaderHash,
+    pub burn_consensus_hash: ConsensusHash,
+    pub stacks_tip_height: u64,
+    pub stacks_tip: BlockHeaderHash,
+    pub stacks_tip_consensus_hash: ConsensusHash,
+    pub stacks_tip_index_block_hash: StacksBlockId,
+    pub stacks_tip_burn_block_height: u32,
+    pub stacks_tip_burn_block_time: u64,
+    pub unconfirmed_tip: Option<StacksBlockId>,
+    pub unconfirmed_microblocks: u16,
+    pub mempool_tx_count: u64,
+}
+
+/// Items in the list given back from a call to `/v2/summary/blocks`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBlockSummary {
+    pub height: u64,
+    pub index_block_hash: StacksBlockId,
+    pub block_hash: BlockHeaderHash,
+    pub consensus_hash: ConsensusHash,
+    pub parent_index_block_hash: Option<StacksBlockId>,
+    pub burn_block_height: u32,
+    pub burn_block_time: u64,
+    pub size: u64,
+    pub cost: ExecutionCost,
+    /// Percentage of the block's execution budget consumed, in its fullest dimension
+    pub cost_fill_pct: u64,
+    /// Percentage of the maximum block size consumed
+    pub size_fill_pct: u64,
+}
+
+/// Chain-quality metrics over the last `window` burnchain blocks, given back from a call to
+/// `/v2/summary/chain_quality`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCChainQuality {
+    /// Number of burnchain blocks considered, ending at `burn_block_height`
+    pub window: u64,
+    pub burn_block_height: u64,
+    /// Burnchain blocks with a winning block-commit
+    pub sortitions: u64,
+    /// Burnchain blocks without a winning block-commit
+    pub missed_sortitions: u64,
+    /// Fraction of burnchain blocks with a winning block-commit
+    pub sortition_rate: f64,
+    /// Canonical Stacks blocks whose tenures started in the window
+    pub stacks_blocks: u64,
+    /// Sortitions whose tenures did not produce a canonical Stacks block
+    pub empty_tenures: u64,
+    /// Mean percentage of the execution budget consumed by those blocks, in their fullest
+    /// dimensions
+    pub average_block_fill_pct: Option<f64>,
+    /// Microblocks we have seen built on those blocks' parents
+    pub microblocks_produced: u64,
+    /// Microblocks that those blocks confirmed
+    pub microblocks_confirmed: u64,
+    /// Fraction of produced microblocks that were confirmed
+    pub microblock_confirmation_rate: Option<f64>,
+}
+
+/// A fee-rate bucket in the mempool summary.  `max_fee_rate` is `None` for the last bucket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCFeeRateBucket {
+    pub min_fee_rate: f64,
+    pub max_fee_rate: Option<f64>,
+    pub tx_count: u64,
+}
+
+/// Struct given back from a call to `/v2/summary/mempool`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMempoolSummary {
+    pub tx_count: u64,
+    pub total_tx_len: u64,
+    pub total_tx_fee: u64,
+    /// Transactions whose fee rate has not been estimated yet
+    pub unestimated_tx_count: u64,
+    pub fee_rate_histogram: Vec<RPCFeeRateBucket>,
+}
+
+/// Items in the list given back from a call to `/v2/summary/peers`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPeerSummary {
+    pub network_id: u32,
+    pub peer_version: u32,
+    #[serde(rename = "ip")]
+    pub addrbytes: PeerAddress,
+    pub port: u16,
+    pub public_key_hash: Option<Hash160>,
+    pub outbound: bool,
+    pub authenticated: bool,
+    pub burn_block_height: u64,
+    pub first_contact_time: u64,
+    pub last_contact_time: u64,
+    pub last_send_time: u64,
+    pub last_recv_time: u64,
+    pub bytes_tx: u64,
+    pub bytes_rx: u64,
+    pub msgs_tx: u64,
+    pub msgs_rx: u64,
+    pub msgs_err: u64,
+}
+
+/// Struct given back from a call to `/v2/sessions` or `/v2/sessions/:id/release`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCSessionData {
+    pub session_id: RPCSessionId,
+    pub tip: StacksBlockId,
+    pub tip_height: u64,
+    pub expires_at: u64,
+}
+
+/// How the frames of a `/v2/stream/blocks` feed are encoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockFeedEncoding {
+    /// Each frame is a consensus-serialized `BlockFeedFrame`
+    Binary,
+    /// Each frame is a JSON-serialized `BlockFeedFrame`, followed by a newline
+    Json,
+}
+
+/// Where a `/v2/stream/blocks` feed starts
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockFeedStart {
+    /// Start with the canonical block at this height
+    Height(u64),
+    /// Resume right after this block (the `cursor` of the last block frame the client received)
+    Cursor(StacksBlockId),
+}
+
+/// Arguments to a `/v2/stream/blocks` request
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockFeedRequest {
+    pub start: BlockFeedStart,
+    pub encoding: BlockFeedEncoding,
+    /// If given, end the feed after this many blocks instead of following the chain tip
+    pub limit: Option<u64>,
+}
+
+define_u8_enum!(BlockFeedFrameID {
+    Block = 0x01,
+    Heartbeat = 0x02,
+    Rollback = 0x03
+});
+
+/// A frame sent by `/v2/stream/blocks`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BlockFeedFrame {
+    /// The next block on the canonical Stacks fork
+    Block {
+        /// Resume cursor: pass as `cursor=` to continue the feed after this block
+        cursor: StacksBlockId,
+        height: u64,
+        consensus_hash: ConsensusHash,
+        parent_block_id: StacksBlockId,
+        block: StacksBlock,
+    },
+    /// Sent periodically while the feed is caught up with the chain tip
+    Heartbeat {
+        /// Cursor of the last block sent, if any
+        cursor: Option<StacksBlockId>,
+        tip_height: u64,
+    },
+    /// The last block sent (or the block the feed resumed from) is no longer on the canonical
+    /// fork.  The feed rewinds to the highest block both forks share and continues from there.
+    Rollback {
+        /// Cursor of the block that fell off the canonical fork
+        from: StacksBlockId,
+        /// Cursor of the last block the two forks have in common, if any
+        cursor: Option<StacksBlockId>,
+        /// Height of `cursor`, or 0 if there's no common block
+        height: u64,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -1461,6 +1832,8 @@ pub enum TipRequest {
     UseLatestAnchoredTip,
     UseLatestUnconfirmedTip,
     SpecificTip(StacksBlockId),
+    /// Use the tip pinned by a read-only RPC session
+    Session(RPCSessionId),
 }
 
 /// All HTTP request paths we support, and the arguments they carry in their paths
@@ -1468,17 +1841,32 @@ pub enum TipRequest {
 pub enum HttpRequestType {
     GetInfo(HttpRequestMetadata),
     GetPoxInfo(HttpRequestMetadata, TipRequest),
-    GetNeighbors(HttpRequestMetadata),
-    GetHeaders(HttpRequestMetadata, u64, TipRequest),
+    GetNeighbors(HttpRequestMetadata, PageRequest),
+    GetNodeMetadata(HttpRequestMetadata),
+    GetBannedLeaderKeys(HttpRequestMetadata),
+    GetTipSummary(HttpRequestMetadata),
+    GetBlocksSummary(HttpRequestMetadata, PageRequest),
+    GetMempoolSummary(HttpRequestMetadata),
+    GetChainQuality(HttpRequestMetadata, Option<u64>),
+    GetPeersSummary(HttpRequestMetadata, PageRequest),
+    OpenSession(HttpRequestMetadata, TipRequest),
+    ReleaseSession(HttpRequestMetadata, RPCSessionId),
+    GetHeaders(HttpRequestMetadata, PageRequest, TipRequest),
+    StreamBlocks(HttpRequestMetadata, BlockFeedRequest),
     GetBlock(HttpRequestMetadata, StacksBlockId),
+    GetBlockWeight(HttpRequestMetadata, StacksBlockId),
+    GetStagingQueue(HttpRequestMetadata, PageRequest),
     GetMicroblocksIndexed(HttpRequestMetadata, StacksBlockId),
     GetMicroblocksConfirmed(HttpRequestMetadata, StacksBlockId),
     GetMicroblocksUnconfirmed(HttpRequestMetadata, StacksBlockId, u16),
+    GetMicroblockForks(HttpRequestMetadata, StacksBlockId),
     GetTransactionUnconfirmed(HttpRequestMetadata, Txid),
     PostTransaction(HttpRequestMetadata, StacksTransaction, Option<Attachment>),
+    PostTransactionBundle(HttpRequestMetadata, StacksTransactionBundle),
+    SimulateTransaction(HttpRequestMetadata, StacksTransaction, TipRequest),
     PostBlock(HttpRequestMetadata, ConsensusHash, StacksBlock),
     PostMicroblock(HttpRequestMetadata, StacksMicroblock, TipRequest),
-    GetAccount(HttpRequestMetadata, PrincipalData, TipRequest, bool),
+    GetAccount(HttpRequestMetadata, PrincipalData, TipRequest, bool, bool),
     GetDataVar(
         HttpRequestMetadata,
         StacksAddress,
@@ -1487,6 +1875,14 @@ pub enum HttpRequestType {
         TipRequest,
         bool,
     ),
+    GetClarityMarfValue(HttpRequestMetadata, String, TipRequest, bool),
+    GetClarityMetadata(
+        HttpRequestMetadata,
+        StacksAddress,
+        ContractName,
+        String,
+        TipRequest,
+    ),
     GetMapEntry(
         HttpRequestMetadata,
         StacksAddress,
@@ -1497,6 +1893,8 @@ pub enum HttpRequestType {
         bool,
     ),
     FeeRateEstimate(HttpRequestMetadata, TransactionPayload, u64),
+    GetBlockFeeStats(HttpRequestMetadata, PageRequest, TipRequest),
+    GetSupply(HttpRequestMetadata, u64, TipRequest),
     CallReadOnlyFunction(
         HttpRequestMetadata,
         StacksAddress,
@@ -1507,6 +1905,16 @@ pub enum HttpRequestType {
         Vec<Value>,
         TipRequest,
     ),
+    CallPreviewFunction(
+        HttpRequestMetadata,
+        StacksAddress,
+        ContractName,
+        PrincipalData,
+        Option<PrincipalData>,
+        ClarityName,
+        Vec<Value>,
+        TipRequest,
+    ),
     GetTransferCost(HttpRequestMetadata),
     GetContractSrc(
         HttpRequestMetadata,
@@ -1526,7 +1934,7 @@ pub enum HttpRequestType {
         TraitIdentifier,
         TipRequest,
     ),
-    MemPoolQuery(HttpRequestMetadata, MemPoolSyncData, Option<Txid>),
+    MemPoolQuery(HttpRequestMetadata, MemPoolSyncData, PageRequest),
     /// catch-all for any errors we should surface from parsing
     ClientError(HttpRequestMetadata, ClientError),
 }
@@ -1620,19 +2028,37 @@ pub enum HttpResponseType {
     PeerInfo(HttpResponseMetadata, RPCPeerInfoData),
     PoxInfo(HttpResponseMetadata, RPCPoxInfoData),
     Neighbors(HttpResponseMetadata, RPCNeighborsInfo),
-    Headers(HttpResponseMetadata, Vec<ExtendedStacksHeader>),
+    NodeMetadata(HttpResponseMetadata, RPCNodeMetadataInfo),
+    BannedLeaderKeys(HttpResponseMetadata, Vec<RPCBannedLeaderKey>),
+    TipSummary(HttpResponseMetadata, RPCTipSummary),
+    BlocksSummary(HttpResponseMetadata, RPCPage<RPCBlockSummary>),
+    MempoolSummary(HttpResponseMetadata, RPCMempoolSummary),
+    ChainQuality(HttpResponseMetadata, RPCChainQuality),
+    PeersSummary(HttpResponseMetadata, RPCPage<RPCPeerSummary>),
+    Session(HttpResponseMetadata, RPCSessionData),
+    Headers(HttpResponseMetadata, RPCList<ExtendedStacksHeader>),
     HeaderStream(HttpResponseMetadata),
     Block(HttpResponseMetadata, StacksBlock),
+    BlockWeight(HttpResponseMetadata, BlockWeight),
+    StagingQueue(HttpResponseMetadata, RPCList<StagingQueueEntry>),
+    MicroblockForks(HttpResponseMetadata, MicroblockForkTree),
     BlockStream(HttpResponseMetadata),
+    BlockFeed(HttpResponseMetadata, Vec<BlockFeedFrame>),
+    BlockFeedStream(HttpResponseMetadata, BlockFeedEncoding),
     Microblocks(HttpResponseMetadata, Vec<StacksMicroblock>),
     MicroblockStream(HttpResponseMetadata),
     TransactionID(HttpResponseMetadata, Txid),
+    TransactionBundleID(HttpResponseMetadata, Sha512Trunc256Sum),
     StacksBlockAccepted(HttpResponseMetadata, StacksBlockId, bool),
     MicroblockHash(HttpResponseMetadata, BlockHeaderHash),
     TokenTransferCost(HttpResponseMetadata, u64),
     GetDataVar(HttpResponseMetadata, DataVarResponse),
     GetMapEntry(HttpResponseMetadata, MapEntryResponse),
+    GetClarityMarfValue(HttpResponseMetadata, ClarityMarfValueResponse),
+    GetClarityMetadata(HttpResponseMetadata, ClarityMetadataResponse),
     CallReadOnlyFunction(HttpResponseMetadata, CallReadOnlyResponse),
+    CallPreviewFunction(HttpResponseMetadata, CallPreviewResponse),
+    SimulateTransaction(HttpResponseMetadata, TransactionSimulationResponse),
     GetAccount(HttpResponseMetadata, AccountEntryResponse),
     GetContractABI(HttpResponseMetadata, ContractInterface),
     GetContractSrc(HttpResponseMetadata, ContractSrcResponse),
@@ -1644,6 +2070,8 @@ pub enum HttpResponseType {
     MemPoolTxs(HttpResponseMetadata, Option<Txid>, Vec<StacksTransaction>),
     OptionsPreflight(HttpResponseMetadata),
     TransactionFeeEstimation(HttpResponseMetadata, RPCFeeEstimateResponse),
+    BlockFeeStats(HttpResponseMetadata, RPCList<BlockFeeStats>),
+    Supply(HttpResponseMetadata, SupplyInfo),
     // peer-given error responses
     BadRequest(HttpResponseMetadata, String),
     BadRequestJSON(HttpResponseMetadata, serde_json::Value),
@@ -1684,6 +2112,8 @@ pub enum StacksMessageID {
     Pong = 16,
     NatPunchRequest = 17,
     NatPunchReply = 18,
+    GetNodeMetadata = 19,
+    NodeMetadata = 20,
     // reserved
     Reserved = 255,
 }
@@ -1926,6 +2356,34 @@ pub const MAX_MICROBLOCKS_UNCONFIRMED: usize = 1024;
 // maximum number of block headers we'll get streamed to us
 pub const MAX_HEADERS: usize = 2100;
 
+// default and maximum number of blocks whose fee statistics can be fetched in a single
+// /v2/fees/blocks request
+pub const DEFAULT_BLOCK_FEE_STATS: u64 = 20;
+pub const MAX_BLOCK_FEE_STATS: u64 = 100;
+
+// default and maximum number of blocks that can be summarized in a single /v2/summary/blocks request
+pub const DEFAULT_BLOCKS_SUMMARY: u64 = 20;
+pub const MAX_BLOCKS_SUMMARY: u64 = 100;
+
+// default and maximum number of burnchain blocks considered by /v2/summary/chain_quality
+pub const DEFAULT_CHAIN_QUALITY_WINDOW: u64 = 144;
+pub const MAX_CHAIN_QUALITY_WINDOW: u64 = 2100;
+
+// default and maximum number of peers that can be summarized in a single /v2/summary/peers request
+pub const DEFAULT_PEERS_SUMMARY: u64 = 100;
+pub const MAX_PEERS_SUMMARY: u64 = 1000;
+
+// default and maximum number of neighbors in each list of a single /v2/neighbors request
+pub const DEFAULT_NEIGHBORS_PAGE: u64 = 1000;
+pub const MAX_NEIGHBORS_PAGE: u64 = 1000;
+
+// default number of headers in a single /v2/headers request (at most MAX_HEADERS)
+pub const DEFAULT_HEADERS_PAGE: u64 = 100;
+
+// default and maximum number of entries in a single page of /v2/blocks/staging
+pub const DEFAULT_STAGING_QUEUE_PAGE: u64 = 100;
+pub const MAX_STAGING_QUEUE_PAGE: u64 = 1000;
+
 // how long a peer will be denied for if it misbehaves
 #[cfg(test)]
 pub const DENY_BAN_DURATION: u64 = 30; // seconds
@@ -2010,6 +2468,22 @@ impl NetworkResult {
             .collect()
     }
 
+    /// Drop the transactions that peers pushed to us or that we obtained via mempool sync, and
+    /// return how many were dropped.  Used to shed load when the relayer falls behind: these
+    /// transactions will be re-announced or re-synced once it catches up.  Transactions uploaded
+    /// through our own RPC interface are kept.
+    pub fn drop_transactions(&mut self) -> usize {
+        let num_dropped = self
+            .pushed_transactions
+            .values()
+            .map(|pushed_txs| pushed_txs.len())
+            .sum::<usize>()
+            + self.synced_transactions.len();
+        self.pushed_transactions.clear();
+        self.synced_transactions.clear();
+        num_dropped
+    }
+
     pub fn has_data_to_store(&self) -> bool {
         self.has_blocks()
             || self.has_microblocks()
@@ -2170,6 +2644,7 @@ pub mod test {
     use crate::util_lib::boot::boot_code_test_addr;
     use stacks_common::codec::StacksMessageCodec;
     use stacks_common::types::chainstate::TrieHash;
+    use stacks_common::types::NetworkId;
     use stacks_common::types::StacksEpochId;
 
     use crate::burnchains::bitcoin::spv::BITCOIN_GENESIS_BLOCK_HASH_REGTEST;
@@ -2434,6 +2909,17 @@ pub mod test {
         ) {
             // pass
         }
+
+        fn announce_sortition(
+            &self,
+            _snapshot: &BlockSnapshot,
+            _winning_commit: Option<&LeaderBlockCommitOp>,
+            _block_commits: &[LeaderBlockCommitOp],
+            _rewards: Vec<(PoxAddress, u64)>,
+            _burns: u64,
+        ) {
+            // pass
+        }
     }
 
     // describes a peer's initial configuration
@@ -2782,8 +3268,7 @@ pub mod test {
             }
 
             let (chainstate, _) = StacksChainState::open_and_exec(
-                false,
-                config.network_id,
+                NetworkId::from_chain_id(false, config.network_id),
                 &chainstate_path,
                 Some(&mut boot_data),
                 None,
@@ -53,7 +53,7 @@ use crate::chainstate::coordinator::{
 };
 use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::{MAX_BLOCK_LEN, MAX_TRANSACTION_LEN};
-use crate::monitoring::{update_inbound_neighbors, update_outbound_neighbors};
+use crate::monitoring::{set_chain_quality, update_inbound_neighbors, update_outbound_neighbors};
 use crate::net::asn::ASEntry4;
 use crate::net::atlas::AtlasDB;
 use crate::net::atlas::{AttachmentInstance, AttachmentsDownloader};
@@ -67,14 +67,17 @@ use crate::net::db::PeerDB;
 use crate::net::download::BlockDownloader;
 use crate::net::inv::*;
 use crate::net::neighbors::*;
+use crate::net::pagination::PageRequest;
 use crate::net::poll::NetworkPollState;
 use crate::net::poll::NetworkState;
 use crate::net::prune::*;
+use crate::net::readonly_cache::ReadOnlyCallCache;
 use crate::net::relay::RelayerStats;
 use crate::net::relay::*;
 use crate::net::relay::*;
 use crate::net::rpc::RPCHandlerArgs;
 use crate::net::server::*;
+use crate::net::session::RPCSessions;
 use crate::net::Error as net_error;
 use crate::net::Neighbor;
 use crate::net::NeighborKey;
@@ -82,7 +85,9 @@ use crate::net::PeerAddress;
 use crate::net::*;
 use crate::util_lib::db::DBConn;
 use crate::util_lib::db::Error as db_error;
+use crate::util_lib::membudget::{BudgetAccount, MemoryBudget, PEER_MESSAGE_BUFFER};
 use clarity::vm::database::BurnStateDB;
+use stacks_common::codec::StacksMessageCodec;
 use stacks_common::util::get_epoch_time_ms;
 use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::hash::to_hex;
@@ -365,6 +370,14 @@ pub struct PeerNetwork {
     // pending messages (BlocksAvailable, MicroblocksAvailable, BlocksData, Microblocks) that we
     // can't process yet, but might be able to process on the next chain view update
     pub pending_messages: HashMap<usize, Vec<StacksMessage>>,
+    // share of the node's memory budget used by pending_messages
+    pending_messages_budget: BudgetAccount,
+
+    // read-only RPC sessions, pinned to chain tips
+    pub rpc_sessions: RPCSessions,
+
+    // cached read-only function call results
+    pub read_only_call_cache: ReadOnlyCallCache,
 
     // fault injection -- force disconnects
     fault_last_disconnect: u64,
@@ -397,6 +410,17 @@ impl PeerNetwork {
         let first_burn_header_hash = burnchain.first_block_hash.clone();
         let first_burn_header_ts = burnchain.first_block_timestamp;
 
+        let rpc_sessions = RPCSessions::new(
+            connection_opts.max_rpc_sessions,
+            connection_opts.rpc_session_ttl,
+        );
+        let read_only_call_cache = ReadOnlyCallCache::new(
+            connection_opts.read_only_call_cache_size,
+            connection_opts
+                .read_only_call_cache_excluded_contracts
+                .clone(),
+        );
+
         let mut network = PeerNetwork {
             peer_version: peer_version,
             epochs: epochs,
@@ -492,6 +516,10 @@ impl PeerNetwork {
             antientropy_start_reward_cycle: 0,
 
             pending_messages: HashMap::new(),
+            pending_messages_budget: MemoryBudget::register_global(PEER_MESSAGE_BUFFER),
+
+            rpc_sessions: rpc_sessions,
+            read_only_call_cache: read_only_call_cache,
 
             fault_last_disconnect: 0,
         };
@@ -1575,7 +1603,10 @@ impl PeerNetwork {
 
         self.relay_handles.remove(&event_id);
         self.peers.remove(&event_id);
-        self.pending_messages.remove(&event_id);
+        if let Some(msgs) = self.pending_messages.remove(&event_id) {
+            self.pending_messages_budget
+                .release(PeerNetwork::buffered_messages_size(&msgs));
+        }
     }
 
     /// Deregister by neighbor key
@@ -1940,6 +1971,59 @@ impl PeerNetwork {
         }
     }
 
+    /// Ask each neighbor that exchanges node metadata for its signed metadata record, if we
+    /// haven't asked it recently.  The reply is stored by the conversation when it arrives.
+    pub fn queue_node_metadata_requests(&mut self) -> () {
+        if !ConversationP2P::supports_node_metadata(self.local_peer.services) {
+            return;
+        }
+
+        let now = get_epoch_time_secs();
+        let mut relay_handles = HashMap::new();
+        for (_, convo) in self.peers.iter_mut() {
+            if convo.is_authenticated()
+                && convo.stats.last_handshake_time > 0
+                && ConversationP2P::supports_node_metadata(convo.peer_services)
+                && convo.stats.last_node_metadata_request_time
+                    + self.connection_opts.node_metadata_refresh_interval
+                    < now
+            {
+                let request_res = convo.sign_message(
+                    &self.chain_view,
+                    &self.local_peer.private_key,
+                    StacksMessageType::GetNodeMetadata,
+                );
+
+                match request_res {
+                    Ok(request) => {
+                        // NOTE: use "relay" here because we don't wait for the reply
+                        match convo.relay_signed_message(request) {
+                            Ok(handle) => {
+                                convo.stats.last_node_metadata_request_time = now;
+                                relay_handles.insert(convo.conn_id, handle);
+                            }
+                            Err(_e) => {
+                                debug!(
+                                    "Outbox to {:?} is full; cannot request node metadata",
+                                    &convo
+                                );
+                            }
+                        };
+                    }
+                    Err(e) => {
+                        debug!(
+                            "Unable to create GetNodeMetadata message for {:?}: {:?}",
+                            &convo, &e
+                        );
+                    }
+                };
+            }
+        }
+        for (event_id, handle) in relay_handles.drain() {
+            self.add_relay_handle(event_id, handle);
+        }
+    }
+
     /// Remove unresponsive peers
     fn disconnect_unresponsive(&mut self) -> usize {
         let now = get_epoch_time_secs();
@@ -2178,14 +2262,17 @@ impl PeerNetwork {
     }
 
     /// Do a mempool sync. Return any transactions we might receive.
+    /// The sync is paused while the relayer is backpressured, since it would only be handed more
+    /// transactions to drop.
     fn do_network_mempool_sync(
         &mut self,
         dns_client_opt: &mut Option<&mut DNSClient>,
         mempool: &MemPoolDB,
         chainstate: &mut StacksChainState,
+        backpressure: bool,
         ibd: bool,
     ) -> Option<Vec<StacksTransaction>> {
-        if ibd {
+        if ibd || backpressure {
             return None;
         }
 
@@ -3364,7 +3451,7 @@ impl PeerNetwork {
                 Some(self.burnchain_tip.canonical_stacks_tip_height),
             ),
             sync_data,
-            Some(page_id),
+            PageRequest::from_cursor(None, page_id.to_hex()),
         );
 
         let event_id = self.connect_or_send_http_request(
@@ -4209,6 +4296,10 @@ impl PeerNetwork {
                     return;
                 }
             }
+            self.pending_messages_budget
+                .charge(PeerNetwork::buffered_messages_size(std::slice::from_ref(
+                    &msg,
+                )));
             msgs.push(msg);
             debug!(
                 "{:?}: Event {} has {} messages buffered",
@@ -4217,12 +4308,57 @@ impl PeerNetwork {
                 msgs.len()
             );
         } else {
+            self.pending_messages_budget
+                .charge(PeerNetwork::buffered_messages_size(std::slice::from_ref(
+                    &msg,
+                )));
             self.pending_messages.insert(event_id, vec![msg]);
             debug!(
                 "{:?}: Event {} has 1 messages buffered",
                 &self.local_peer, event_id
             );
         }
+        self.enforce_pending_messages_budget();
+    }
+
+    /// Estimated number of bytes a list of buffered messages takes up
+    fn buffered_messages_size(msgs: &[StacksMessage]) -> u64 {
+        msgs.iter()
+            .map(|msg| msg.serialize_to_vec().len() as u64)
+            .sum()
+    }
+
+    /// If the node's memory budget is exhausted, drop buffered messages until they are down to
+    /// their share of the budget.  Buffering is best-effort, so whole peers' buffers are dropped,
+    /// largest first.
+    fn enforce_pending_messages_budget(&mut self) {
+        let target = match self.pending_messages_budget.eviction_target() {
+            Some(target) => target,
+            None => {
+                return;
+            }
+        };
+
+        let mut buffer_sizes: Vec<_> = self
+            .pending_messages
+            .iter()
+            .map(|(event_id, msgs)| (*event_id, PeerNetwork::buffered_messages_size(msgs)))
+            .collect();
+        buffer_sizes.sort_by(|a, b| b.1.cmp(&a.1));
+
+        let mut usage: u64 = buffer_sizes.iter().map(|(_, size)| *size).sum();
+        for (event_id, size) in buffer_sizes.into_iter() {
+            if usage <= target {
+                break;
+            }
+            debug!(
+                "{:?}: Drop {} buffered bytes from event {} -- over memory budget",
+                &self.local_peer, size, event_id
+            );
+            self.pending_messages.remove(&event_id);
+            usage -= size;
+        }
+        self.pending_messages_budget.set_usage(usage);
     }
 
     /// Do we need a block or microblock stream, given its sortition's consensus hash?
@@ -4987,6 +5123,20 @@ impl PeerNetwork {
             // update tx validation information
             self.ast_rules = SortitionDB::get_ast_rules(sortdb.conn(), sn.block_height)?;
 
+            // update chain quality metrics (only worth computing if they're exported)
+            if cfg!(feature = "monitoring_prom") {
+                let chain_quality = chainstate
+                    .read_tx_begin()
+                    .map_err(net_error::from)
+                    .and_then(|read_tx| {
+                        RPCChainQuality::from_db(sortdb, &read_tx, DEFAULT_CHAIN_QUALITY_WINDOW)
+                    });
+                match chain_quality {
+                    Ok(chain_quality) => set_chain_quality(&chain_quality),
+                    Err(e) => warn!("Failed to compute chain quality: {:?}", &e),
+                }
+            }
+
             // update heaviest affirmation map view
             let burnchain_db = self.burnchain.open_burnchain_db(false)?;
 
@@ -5049,6 +5199,7 @@ impl PeerNetwork {
         if sn.burn_header_hash != self.burnchain_tip.burn_header_hash {
             // try processing previously-buffered messages (best-effort)
             let buffered_messages = mem::replace(&mut self.pending_messages, HashMap::new());
+            self.pending_messages_budget.set_usage(0);
             ret =
                 self.handle_unsolicited_messages(sortdb, chainstate, buffered_messages, ibd, false);
         }
@@ -5138,9 +5289,13 @@ impl PeerNetwork {
 
         // In parallel, do a mempool sync.
         // Remember any txs we get, so we can feed them to the relayer thread.
-        if let Some(mut txs) =
-            self.do_network_mempool_sync(&mut dns_client_opt, mempool, chainstate, ibd)
-        {
+        if let Some(mut txs) = self.do_network_mempool_sync(
+            &mut dns_client_opt,
+            mempool,
+            chainstate,
+            download_backpressure,
+            ibd,
+        ) {
             network_result.synced_transactions.append(&mut txs);
         }
 
@@ -5158,6 +5313,9 @@ impl PeerNetwork {
         // queue up pings to neighbors we haven't spoken to in a while
         self.queue_ping_heartbeats();
 
+        // refresh our neighbors' metadata records
+        self.queue_node_metadata_requests();
+
         // move conversations along
         let error_events = self.flush_relay_handles();
         for error_event in error_events {
@@ -0,0 +1,367 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Pagination for list-returning RPC endpoints.
+//!
+//! Every list endpoint takes the same query arguments:
+//!   * `limit`: the maximum number of items to return.  Each endpoint has its own default and
+//!     maximum; asking for more than the maximum is a bad request.
+//!   * `offset`: how many items to skip (default 0).
+//!   * `cursor`: an opaque, endpoint-specific position to resume from.  It is mutually exclusive
+//!     with `offset`, and only endpoints that can resume cheaply accept it.
+//!
+//! Every list endpoint answers with an `RPCPage` envelope, which echoes back the effective
+//! `limit` and `offset`, reports the `total` number of items when it is cheap to compute, and
+//! gives a `next_cursor` to pass back when there may be more items to fetch.
+//!
+//! Endpoints that returned lists before pagination existed (`/v2/headers`, `/v2/neighbors`,
+//! `/v2/blocks/staging` and `/v2/fees/blocks`) keep answering with their original response
+//! shape unless at least one pagination argument is given, so that existing clients don't
+//! break.
+//!
+//! The one exception is `/v2/mempool/query`, which answers with a SIP-003 stream of
+//! transactions rather than JSON.  It takes `limit` and `cursor` like any other list endpoint,
+//! but its next cursor is the page ID that trails the transaction stream.
+
+use std::fmt;
+
+use url::form_urlencoded;
+
+use crate::net::Error as net_error;
+
+/// Pagination arguments for a list-returning RPC endpoint
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PageRequest {
+    pub limit: Option<u64>,
+    pub offset: u64,
+    pub cursor: Option<String>,
+    /// Whether any pagination arguments were given.  Endpoints that predate pagination only
+    /// answer with an `RPCPage` if so.
+    pub paged: bool,
+}
+
+/// Reasons a page request can be refused by an endpoint
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageError {
+    /// `limit` exceeds the endpoint's maximum
+    LimitTooLarge(u64),
+    /// the endpoint does not support resuming from a cursor
+    CursorUnsupported,
+    /// the endpoint does not support skipping items by offset
+    OffsetUnsupported,
+    /// the cursor could not be decoded by the endpoint
+    InvalidCursor(String),
+}
+
+impl fmt::Display for PageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PageError::LimitTooLarge(max) => write!(f, "`limit` must be at most {}", max),
+            PageError::CursorUnsupported => write!(f, "`cursor` is not supported here"),
+            PageError::OffsetUnsupported => write!(f, "`offset` is not supported here"),
+            PageError::InvalidCursor(ref cursor) => write!(f, "Invalid cursor '{}'", cursor),
+        }
+    }
+}
+
+/// The envelope around every page of results from a list-returning RPC endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPage<T> {
+    pub results: Vec<T>,
+    pub limit: u64,
+    pub offset: u64,
+    /// Total number of items available, if it is cheap to compute
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub total: Option<u64>,
+    /// Cursor to pass back to get the next page, if there may be one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// The response of a list endpoint that predates pagination: the bare list of items, as it was
+/// always returned, if no pagination arguments were given, and a page of them otherwise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RPCList<T> {
+    Items(Vec<T>),
+    Page(RPCPage<T>),
+}
+
+impl PageRequest {
+    pub fn new(limit: Option<u64>, offset: u64) -> PageRequest {
+        PageRequest {
+            limit,
+            offset,
+            cursor: None,
+            paged: true,
+        }
+    }
+
+    pub fn from_cursor(limit: Option<u64>, cursor: String) -> PageRequest {
+        PageRequest {
+            limit,
+            offset: 0,
+            cursor: Some(cursor),
+            paged: true,
+        }
+    }
+
+    /// Parse pagination arguments out of an HTTP query string.  Unrelated arguments are ignored.
+    pub fn from_query(query: Option<&str>) -> Result<PageRequest, net_error> {
+        let mut page = PageRequest::default();
+        let mut has_offset = false;
+        let query = match query {
+            Some(query) => query,
+            None => {
+                return Ok(page);
+            }
+        };
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "limit" => {
+                    let limit = value.parse::<u64>().map_err(|_| {
+                        net_error::DeserializeError("Failed to parse `limit`".to_string())
+                    })?;
+                    page.limit = Some(limit);
+                    page.paged = true;
+                }
+                "offset" => {
+                    page.offset = value.parse::<u64>().map_err(|_| {
+                        net_error::DeserializeError("Failed to parse `offset`".to_string())
+                    })?;
+                    has_offset = true;
+                    page.paged = true;
+                }
+                "cursor" => {
+                    page.cursor = Some(value.to_string());
+                    page.paged = true;
+                }
+                _ => {}
+            }
+        }
+        if has_offset && page.cursor.is_some() {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: `offset` and `cursor` are mutually exclusive".to_string(),
+            ));
+        }
+        Ok(page)
+    }
+
+    /// Encode these pagination arguments as query arguments.  Returns an empty string if no
+    /// pagination arguments were given, and otherwise a string prefixed with `prefix` (i.e. `?` or `&`).
+    pub fn to_query_string(&self, prefix: &str) -> String {
+        let mut args = vec![];
+        if let Some(limit) = self.limit {
+            args.push(format!("limit={}", limit));
+        }
+        if self.offset > 0 {
+            args.push(format!("offset={}", self.offset));
+        }
+        if let Some(ref cursor) = self.cursor {
+            let encoded: String = form_urlencoded::byte_serialize(cursor.as_bytes()).collect();
+            args.push(format!("cursor={}", encoded));
+        }
+        if args.is_empty() && self.paged {
+            // still ask for a page
+            args.push("offset=0".to_string());
+        }
+        if args.is_empty() {
+            "".to_string()
+        } else {
+            format!("{}{}", prefix, args.join("&"))
+        }
+    }
+
+    /// Get the number of items to return, given the endpoint's default and maximum
+    pub fn get_limit(&self, default_limit: u64, max_limit: u64) -> Result<u64, PageError> {
+        match self.limit {
+            Some(limit) if limit > max_limit => Err(PageError::LimitTooLarge(max_limit)),
+            Some(limit) => Ok(limit),
+            None => Ok(default_limit.min(max_limit)),
+        }
+    }
+
+    /// Refuse a cursor, for endpoints that can only page by offset
+    pub fn check_no_cursor(&self) -> Result<(), PageError> {
+        match self.cursor {
+            Some(_) => Err(PageError::CursorUnsupported),
+            None => Ok(()),
+        }
+    }
+
+    /// Refuse an offset, for endpoints that can only page by cursor
+    pub fn check_no_offset(&self) -> Result<(), PageError> {
+        if self.offset > 0 {
+            Err(PageError::OffsetUnsupported)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T> RPCPage<T> {
+    pub fn new(
+        results: Vec<T>,
+        limit: u64,
+        offset: u64,
+        total: Option<u64>,
+        next_cursor: Option<String>,
+    ) -> RPCPage<T> {
+        RPCPage {
+            results,
+            limit,
+            offset,
+            total,
+            next_cursor,
+        }
+    }
+
+    /// Page through a fully-materialized list of items by offset.  The total is always known.
+    pub fn from_items(items: Vec<T>, limit: u64, offset: u64) -> RPCPage<T> {
+        let total = items.len() as u64;
+        let results = items
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect();
+        RPCPage::new(results, limit, offset, Some(total), None)
+    }
+}
+
+impl<T> RPCList<T> {
+    /// Answer `page` from a fully-materialized list of items.  If no pagination arguments were
+    /// given, all of the items are returned.
+    pub fn from_items(items: Vec<T>, page: &PageRequest, limit: u64) -> RPCList<T> {
+        if page.paged {
+            RPCList::Page(RPCPage::from_items(items, limit, page.offset))
+        } else {
+            RPCList::Items(items)
+        }
+    }
+
+    pub fn items(&self) -> &[T] {
+        match self {
+            RPCList::Items(ref items) => items,
+            RPCList::Page(ref page) => &page.results,
+        }
+    }
+
+    pub fn into_items(self) -> Vec<T> {
+        match self {
+            RPCList::Items(items) => items,
+            RPCList::Page(page) => page.results,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_page_request_query_roundtrip() {
+        assert_eq!(
+            PageRequest::from_query(None).unwrap(),
+            PageRequest::default()
+        );
+        assert_eq!(
+            PageRequest::from_query(Some("tip=latest")).unwrap(),
+            PageRequest::default()
+        );
+
+        let page = PageRequest::from_query(Some("limit=10&offset=20&tip=latest")).unwrap();
+        assert_eq!(page, PageRequest::new(Some(10), 20));
+        assert_eq!(page.to_query_string("?"), "?limit=10&offset=20");
+
+        let page = PageRequest::from_cursor(Some(5), "a b".to_string());
+        assert_eq!(page.to_query_string("&"), "&limit=5&cursor=a+b");
+        assert_eq!(
+            PageRequest::from_query(Some("limit=5&cursor=a+b")).unwrap(),
+            page
+        );
+        assert_eq!(PageRequest::default().to_query_string("?"), "");
+        assert!(!PageRequest::default().paged);
+        assert!(!PageRequest::from_query(Some("tip=latest")).unwrap().paged);
+
+        // asking for the first page with default arguments still asks for a page
+        let page = PageRequest::from_query(Some("offset=0")).unwrap();
+        assert!(page.paged);
+        assert_eq!(page, PageRequest::new(None, 0));
+        assert_eq!(page.to_query_string("?"), "?offset=0");
+
+        assert!(PageRequest::from_query(Some("offset=1&cursor=abc")).is_err());
+        assert!(PageRequest::from_query(Some("limit=-1")).is_err());
+        assert!(PageRequest::from_query(Some("offset=x")).is_err());
+    }
+
+    #[test]
+    fn test_page_limits_and_envelope() {
+        assert_eq!(PageRequest::default().get_limit(20, 100), Ok(20));
+        assert_eq!(PageRequest::default().get_limit(200, 100), Ok(100));
+        assert_eq!(PageRequest::new(Some(100), 0).get_limit(20, 100), Ok(100));
+        assert_eq!(
+            PageRequest::new(Some(101), 0).get_limit(20, 100),
+            Err(PageError::LimitTooLarge(100))
+        );
+        assert_eq!(
+            PageRequest::from_cursor(None, "abc".to_string()).check_no_cursor(),
+            Err(PageError::CursorUnsupported)
+        );
+        assert_eq!(PageRequest::new(None, 0).check_no_offset(), Ok(()));
+        assert_eq!(
+            PageRequest::new(None, 1).check_no_offset(),
+            Err(PageError::OffsetUnsupported)
+        );
+
+        let page = RPCPage::from_items((0..10).collect(), 4, 8);
+        assert_eq!(page.results, vec![8, 9]);
+        assert_eq!(page.total, Some(10));
+
+        let page = RPCPage::from_items((0..10).collect(), 4, 2);
+        assert_eq!(page.results, vec![2, 3, 4, 5]);
+
+        let page = RPCPage::from_items((0..10).collect::<Vec<u32>>(), 4, 20);
+        assert!(page.results.is_empty());
+
+        let json = serde_json::to_string(&RPCPage::new(vec![1u32], 1, 0, None, None)).unwrap();
+        assert_eq!(json, r#"{"results":[1],"limit":1,"offset":0}"#);
+
+        // lists keep their original shape unless a page is asked for
+        let items: Vec<u32> = (0..10).collect();
+        let list = RPCList::from_items(items.clone(), &PageRequest::default(), 4);
+        assert_eq!(list, RPCList::Items(items.clone()));
+        assert_eq!(
+            serde_json::to_string(&list).unwrap(),
+            "[0,1,2,3,4,5,6,7,8,9]"
+        );
+
+        let list = RPCList::from_items(items.clone(), &PageRequest::new(None, 8), 4);
+        assert_eq!(list.items(), &[8, 9]);
+        let json = serde_json::to_string(&list).unwrap();
+        assert_eq!(json, r#"{"results":[8,9],"limit":4,"offset":8,"total":10}"#);
+
+        // either shape can be decoded
+        assert_eq!(serde_json::from_str::<RPCList<u32>>(&json).unwrap(), list);
+        assert_eq!(
+            serde_json::from_str::<RPCList<u32>>("[1,2]")
+                .unwrap()
+                .into_items(),
+            vec![1, 2]
+        );
+    }
+}
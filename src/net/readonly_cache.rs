@@ -0,0 +1,298 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Read-only function call result cache.
+//!
+//! Public API nodes tend to serve the same handful of read-only calls many times over between
+//! blocks.  The result of a call is fully determined by the chain tip it is evaluated at, the
+//! contract, the function, the sender and sponsor, and the arguments, so it can be served from
+//! this cache instead of being re-evaluated.  All entries are dropped whenever the canonical
+//! Stacks chain tip changes, which bounds how long a result for a non-canonical tip can linger.
+//! Calls against the unconfirmed (microblock) state are never cached, since that state changes
+//! without the tip changing.
+//!
+//! Contracts whose read-only functions should always be evaluated (e.g. because they are being
+//! actively debugged against) can be excluded from the cache.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::monitoring;
+use crate::net::CallReadOnlyResponse;
+use crate::types::chainstate::StacksBlockId;
+use crate::vm::types::{PrincipalData, QualifiedContractIdentifier};
+use crate::vm::{ClarityName, Value};
+
+/// Everything that determines the result of a read-only function call
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReadOnlyCallKey {
+    pub tip: StacksBlockId,
+    pub contract: QualifiedContractIdentifier,
+    pub function: ClarityName,
+    pub sender: PrincipalData,
+    pub sponsor: Option<PrincipalData>,
+    /// consensus-serialized arguments, as hex strings
+    pub args: Vec<String>,
+}
+
+impl ReadOnlyCallKey {
+    pub fn new(
+        tip: &StacksBlockId,
+        contract: &QualifiedContractIdentifier,
+        function: &ClarityName,
+        sender: &PrincipalData,
+        sponsor: Option<&PrincipalData>,
+        args: &[Value],
+    ) -> ReadOnlyCallKey {
+        ReadOnlyCallKey {
+            tip: tip.clone(),
+            contract: contract.clone(),
+            function: function.clone(),
+            sender: sender.clone(),
+            sponsor: sponsor.cloned(),
+            args: args.iter().map(|arg| arg.serialize_to_hex()).collect(),
+        }
+    }
+}
+
+/// Read-only call cache statistics
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ReadOnlyCallCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// entries evicted to make room for new ones
+    pub evictions: u64,
+    /// number of times the cache was cleared because the canonical chain tip changed
+    pub invalidations: u64,
+}
+
+/// Size-bounded LRU cache of read-only function call results
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadOnlyCallCache {
+    /// key => (result, use counter value when it was last used)
+    entries: HashMap<ReadOnlyCallKey, (CallReadOnlyResponse, u64)>,
+    /// use counter value => key last used at that value
+    recency: BTreeMap<u64, ReadOnlyCallKey>,
+    /// monotonically-increasing use counter
+    clock: u64,
+    /// maximum number of entries.  0 disables the cache.
+    capacity: usize,
+    /// contracts whose calls are never cached
+    excluded: HashSet<QualifiedContractIdentifier>,
+    /// canonical chain tip the cached results were obtained under
+    canonical_tip: Option<StacksBlockId>,
+    stats: ReadOnlyCallCacheStats,
+}
+
+impl ReadOnlyCallCache {
+    pub fn new(
+        capacity: usize,
+        excluded: HashSet<QualifiedContractIdentifier>,
+    ) -> ReadOnlyCallCache {
+        ReadOnlyCallCache {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            capacity,
+            excluded,
+            canonical_tip: None,
+            stats: ReadOnlyCallCacheStats::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Number of cached results
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get_stats(&self) -> ReadOnlyCallCacheStats {
+        self.stats
+    }
+
+    /// Can calls to this contract be cached?
+    pub fn is_cacheable(&self, contract: &QualifiedContractIdentifier) -> bool {
+        self.is_enabled() && !self.excluded.contains(contract)
+    }
+
+    /// Tell the cache what the canonical chain tip is.  If it changed, then all entries are
+    /// dropped.
+    pub fn set_canonical_tip(&mut self, canonical_tip: &StacksBlockId) {
+        if self.canonical_tip.as_ref() == Some(canonical_tip) {
+            return;
+        }
+        if self.entries.len() > 0 {
+            debug!(
+                "Canonical Stacks tip is now {}; dropping {} cached read-only call results",
+                canonical_tip,
+                self.entries.len()
+            );
+            self.stats.invalidations += 1;
+            monitoring::increment_readonly_call_cache_counter("invalidation");
+        }
+        self.entries.clear();
+        self.recency.clear();
+        self.canonical_tip = Some(canonical_tip.clone());
+    }
+
+    /// Look up a cached result
+    pub fn get(&mut self, key: &ReadOnlyCallKey) -> Option<CallReadOnlyResponse> {
+        if !self.is_cacheable(&key.contract) {
+            return None;
+        }
+        self.clock += 1;
+        let clock = self.clock;
+        match self.entries.get_mut(key) {
+            Some((response, last_used)) => {
+                self.recency.remove(last_used);
+                self.recency.insert(clock, key.clone());
+                *last_used = clock;
+
+                self.stats.hits += 1;
+                monitoring::increment_readonly_call_cache_counter("hit");
+                Some(response.clone())
+            }
+            None => {
+                self.stats.misses += 1;
+                monitoring::increment_readonly_call_cache_counter("miss");
+                None
+            }
+        }
+    }
+
+    /// Cache a result, evicting the least-recently-used result if the cache is full
+    pub fn insert(&mut self, key: ReadOnlyCallKey, response: CallReadOnlyResponse) {
+        if !self.is_cacheable(&key.contract) {
+            return;
+        }
+        self.clock += 1;
+        if let Some((_, last_used)) = self.entries.remove(&key) {
+            self.recency.remove(&last_used);
+        }
+        while self.entries.len() >= self.capacity {
+            let lru_clock = match self.recency.keys().next() {
+                Some(lru_clock) => *lru_clock,
+                None => {
+                    break;
+                }
+            };
+            if let Some(lru_key) = self.recency.remove(&lru_clock) {
+                self.entries.remove(&lru_key);
+                self.stats.evictions += 1;
+                monitoring::increment_readonly_call_cache_counter("eviction");
+            }
+        }
+        self.recency.insert(self.clock, key.clone());
+        self.entries.insert(key, (response, self.clock));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+
+    use super::*;
+
+    fn make_key(tip: u8, contract: &str, arg: u128) -> ReadOnlyCallKey {
+        let contract = QualifiedContractIdentifier::parse(&format!(
+            "SP000000000000000000002Q6VF78.{}",
+            contract
+        ))
+        .unwrap();
+        ReadOnlyCallKey::new(
+            &StacksBlockId([tip; 32]),
+            &contract,
+            &ClarityName::try_from("get-value".to_string()).unwrap(),
+            &PrincipalData::parse("SP000000000000000000002Q6VF78").unwrap(),
+            None,
+            &[Value::UInt(arg)],
+        )
+    }
+
+    fn make_response(arg: u128) -> CallReadOnlyResponse {
+        CallReadOnlyResponse {
+            okay: true,
+            result: Some(format!("0x{}", Value::UInt(arg).serialize_to_hex())),
+            cause: None,
+        }
+    }
+
+    #[test]
+    fn test_readonly_call_cache_lru() {
+        let mut cache = ReadOnlyCallCache::new(2, HashSet::new());
+        cache.set_canonical_tip(&StacksBlockId([0x01; 32]));
+
+        assert_eq!(cache.get(&make_key(1, "foo", 1)), None);
+        cache.insert(make_key(1, "foo", 1), make_response(1));
+        cache.insert(make_key(1, "foo", 2), make_response(2));
+        assert_eq!(cache.get(&make_key(1, "foo", 1)), Some(make_response(1)));
+
+        // evicts the result for arg 2, since arg 1 was used more recently
+        cache.insert(make_key(1, "foo", 3), make_response(3));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&make_key(1, "foo", 2)), None);
+        assert_eq!(cache.get(&make_key(1, "foo", 1)), Some(make_response(1)));
+        assert_eq!(cache.get(&make_key(1, "foo", 3)), Some(make_response(3)));
+
+        // the tip is part of the key
+        assert_eq!(cache.get(&make_key(2, "foo", 3)), None);
+
+        assert_eq!(
+            cache.get_stats(),
+            ReadOnlyCallCacheStats {
+                hits: 3,
+                misses: 3,
+                evictions: 1,
+                invalidations: 0,
+            }
+        );
+
+        // same tip, nothing happens
+        cache.set_canonical_tip(&StacksBlockId([0x01; 32]));
+        assert_eq!(cache.len(), 2);
+
+        // new tip, everything is dropped
+        cache.set_canonical_tip(&StacksBlockId([0x02; 32]));
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.get(&make_key(1, "foo", 1)), None);
+        assert_eq!(cache.get_stats().invalidations, 1);
+    }
+
+    #[test]
+    fn test_readonly_call_cache_opt_out() {
+        let excluded_key = make_key(1, "bar", 1);
+        let mut excluded = HashSet::new();
+        excluded.insert(excluded_key.contract.clone());
+
+        let mut cache = ReadOnlyCallCache::new(10, excluded);
+        cache.insert(excluded_key.clone(), make_response(1));
+        cache.insert(make_key(1, "foo", 1), make_response(1));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&excluded_key), None);
+        assert_eq!(cache.get(&make_key(1, "foo", 1)), Some(make_response(1)));
+
+        // lookups of excluded contracts aren't misses
+        assert_eq!(cache.get_stats().misses, 0);
+
+        // disabled cache stores nothing
+        let mut cache = ReadOnlyCallCache::new(0, HashSet::new());
+        cache.insert(make_key(1, "foo", 1), make_response(1));
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.get(&make_key(1, "foo", 1)), None);
+    }
+}
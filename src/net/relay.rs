@@ -51,16 +51,17 @@ use crate::net::Error as net_error;
 use crate::net::*;
 use crate::types::chainstate::StacksBlockId;
 use clarity::vm::ast::errors::{ParseError, ParseErrors};
-use clarity::vm::ast::{ast_check_size, ASTRules};
+use clarity::vm::ast::{ast_check_size_with_limits, ASTRules};
 use clarity::vm::costs::ExecutionCost;
 use clarity::vm::errors::RuntimeErrorType;
 use clarity::vm::types::{QualifiedContractIdentifier, StacksAddressExtensions};
-use clarity::vm::ClarityVersion;
+use clarity::vm::VmLimits;
 use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::hash::Sha512Trunc256Sum;
 
 use crate::chainstate::coordinator::BlockEventDispatcher;
 use crate::chainstate::stacks::db::unconfirmed::ProcessedUnconfirmedState;
+use crate::chainstate::stacks::db::validation_cache::BlockValidationContext;
 use crate::monitoring::update_stacks_tip_height;
 use crate::types::chainstate::{PoxId, SortitionId};
 use stacks_common::codec::MAX_PAYLOAD_LEN;
@@ -573,6 +574,33 @@ impl Relayer {
             }
         };
 
+        // don't store this block if we already executed it on this parent and found it invalid
+        let validation_context = BlockValidationContext {
+            consensus_hash: consensus_hash.clone(),
+            parent_block_id: StacksBlockId::new(
+                &parent_block_snapshot.consensus_hash,
+                &block.header.parent_block,
+            ),
+            parent_microblock_hash: block.header.parent_microblock.clone(),
+            parent_microblock_seq: block.header.parent_microblock_sequence,
+        };
+        if let Some(verdict) = chainstate
+            .block_validation_cache
+            .lock()
+            .expect("FATAL: block validation cache lock poisoned")
+            .get(&block.block_hash(), &validation_context)
+        {
+            if !verdict.is_valid() {
+                info!(
+                    "Block is known to be invalid; will not store or relay";
+                    "stacks_block_hash" => %block.block_hash(),
+                    "consensus_hash" => %consensus_hash,
+                    "verdict" => ?verdict,
+                );
+                return Ok(false);
+            }
+        }
+
         // don't relay this block if it's using the wrong AST rules (this would render at least one of its
         // txs problematic).
         let ast_rules = SortitionDB::get_ast_rules(sort_ic, block_sn.block_height)?;
@@ -588,8 +616,9 @@ impl Relayer {
             &ast_rules
         );
         if !Relayer::static_check_problematic_relayed_block(
-            chainstate.mainnet,
+            chainstate.network.mainnet,
             epoch_id,
+            &chainstate.get_vm_limits(epoch_id),
             block,
             ast_rules,
         ) {
@@ -970,8 +999,9 @@ impl Relayer {
                     &mblock.block_hash()
                 );
                 if !Relayer::static_check_problematic_relayed_microblock(
-                    chainstate.mainnet,
+                    chainstate.network.mainnet,
                     epoch_id,
+                    &chainstate.get_vm_limits(epoch_id),
                     mblock,
                     ast_rules,
                 ) {
@@ -1059,8 +1089,9 @@ impl Relayer {
                         &mblock.block_hash()
                     );
                     if !Relayer::static_check_problematic_relayed_microblock(
-                        chainstate.mainnet,
+                        chainstate.network.mainnet,
                         epoch_id,
+                        &chainstate.get_vm_limits(epoch_id),
                         mblock,
                         ast_rules,
                     ) {
@@ -1190,6 +1221,7 @@ impl Relayer {
     pub fn static_check_problematic_relayed_tx(
         mainnet: bool,
         epoch_id: StacksEpochId,
+        vm_limits: &VmLimits,
         tx: &StacksTransaction,
         ast_rules: ASTRules,
     ) -> Result<(), Error> {
@@ -1199,10 +1231,7 @@ impl Relayer {
             &ast_rules
         );
         match tx.payload {
-            TransactionPayload::SmartContract(ref smart_contract, ref clarity_version_opt) => {
-                let clarity_version =
-                    clarity_version_opt.unwrap_or(ClarityVersion::default_for_epoch(epoch_id));
-
+            TransactionPayload::SmartContract(ref smart_contract, _) => {
                 if ast_rules == ASTRules::PrecheckSize {
                     let origin = tx.get_origin();
                     let issuer_principal = {
@@ -1227,8 +1256,12 @@ impl Relayer {
                     let contract_code_str = smart_contract.code_body.to_string();
 
                     // make sure that the AST isn't unreasonably big
-                    let ast_res =
-                        ast_check_size(&contract_id, &contract_code_str, clarity_version, epoch_id);
+                    let ast_res = ast_check_size_with_limits(
+                        &contract_id,
+                        &contract_code_str,
+                        epoch_id,
+                        vm_limits,
+                    );
                     match ast_res {
                         Ok(_) => {}
                         Err(parse_error) => match parse_error.err {
@@ -1256,12 +1289,15 @@ impl Relayer {
     pub fn static_check_problematic_relayed_block(
         mainnet: bool,
         epoch_id: StacksEpochId,
+        vm_limits: &VmLimits,
         block: &StacksBlock,
         ast_rules: ASTRules,
     ) -> bool {
         for tx in block.txs.iter() {
-            if !Relayer::static_check_problematic_relayed_tx(mainnet, epoch_id, tx, ast_rules)
-                .is_ok()
+            if !Relayer::static_check_problematic_relayed_tx(
+                mainnet, epoch_id, vm_limits, tx, ast_rules,
+            )
+            .is_ok()
             {
                 info!(
                     "Block {} with tx {} will not be stored or relayed",
@@ -1283,12 +1319,15 @@ impl Relayer {
     pub fn static_check_problematic_relayed_microblock(
         mainnet: bool,
         epoch_id: StacksEpochId,
+        vm_limits: &VmLimits,
         mblock: &StacksMicroblock,
         ast_rules: ASTRules,
     ) -> bool {
         for tx in mblock.txs.iter() {
-            if !Relayer::static_check_problematic_relayed_tx(mainnet, epoch_id, tx, ast_rules)
-                .is_ok()
+            if !Relayer::static_check_problematic_relayed_tx(
+                mainnet, epoch_id, vm_limits, tx, ast_rules,
+            )
+            .is_ok()
             {
                 info!(
                     "Microblock {} with tx {} will not be stored relayed",
@@ -1334,6 +1373,33 @@ impl Relayer {
         cur_ast_rules != processed_ast_rules
     }
 
+    /// Of the new blocks we just stored, offer the highest one that can be processed right away to
+    /// the chains coordinator, so it gets processed next.  If it's higher than the block the
+    /// coordinator is processing, that block is preempted.
+    fn offer_best_new_block(
+        chainstate: &StacksChainState,
+        new_blocks: &HashMap<ConsensusHash, StacksBlock>,
+    ) {
+        let best_block = new_blocks
+            .iter()
+            .filter_map(|(consensus_hash, block)| {
+                let block_id = StacksBlockId::new(consensus_hash, &block.block_hash());
+                match StacksChainState::load_staging_block_info(chainstate.db(), &block_id) {
+                    Ok(Some(staging_block))
+                        if staging_block.attachable && !staging_block.processed =>
+                    {
+                        Some((block_id, staging_block.height))
+                    }
+                    _ => None,
+                }
+            })
+            .max_by_key(|(_, height)| *height);
+
+        if let Some((block_id, height)) = best_block {
+            chainstate.block_preemption.preempt(&block_id, height);
+        }
+    }
+
     /// Process blocks and microblocks that we recieved, both downloaded (confirmed) and streamed
     /// (unconfirmed). Returns:
     /// * set of consensus hashes that elected the newly-discovered blocks, and the blocks, so we can turn them into BlocksAvailable / BlocksData messages
@@ -1432,6 +1498,7 @@ impl Relayer {
                 new_microblocks.len(),
                 new_confirmed_microblocks.len()
             );
+            Relayer::offer_best_new_block(chainstate, &new_blocks);
             if let Some(coord_comms) = coord_comms {
                 if !coord_comms.announce_new_stacks_block() {
                     return Err(net_error::CoordinatorClosed);
@@ -1472,6 +1539,7 @@ impl Relayer {
         network_result: &mut NetworkResult,
         mainnet: bool,
         epoch_id: StacksEpochId,
+        vm_limits: &VmLimits,
     ) {
         // filter out transactions that prove problematic
         let mut filtered_pushed_transactions = HashMap::new();
@@ -1483,6 +1551,7 @@ impl Relayer {
                     && !Relayer::static_check_problematic_relayed_tx(
                         mainnet,
                         epoch_id,
+                        vm_limits,
                         &tx,
                         ASTRules::PrecheckSize,
                     )
@@ -1506,6 +1575,7 @@ impl Relayer {
                 && !Relayer::static_check_problematic_relayed_tx(
                     mainnet,
                     epoch_id,
+                    vm_limits,
                     &tx,
                     ASTRules::PrecheckSize,
                 )
@@ -1552,7 +1622,12 @@ impl Relayer {
             .epoch_id;
 
         let chain_height = chain_tip.height;
-        Relayer::filter_problematic_transactions(network_result, chainstate.mainnet, epoch_id);
+        Relayer::filter_problematic_transactions(
+            network_result,
+            chainstate.network.mainnet,
+            epoch_id,
+            &chainstate.get_vm_limits(epoch_id),
+        );
 
         if let Err(e) = PeerNetwork::store_transactions(
             mempool,
@@ -4871,6 +4946,7 @@ pub mod test {
         assert!(Relayer::static_check_problematic_relayed_tx(
             false,
             StacksEpochId::Epoch2_05,
+            &VmLimits::for_epoch(StacksEpochId::Epoch2_05),
             &tx_edge,
             ASTRules::Typical
         )
@@ -4878,6 +4954,7 @@ pub mod test {
         assert!(Relayer::static_check_problematic_relayed_tx(
             false,
             StacksEpochId::Epoch2_05,
+            &VmLimits::for_epoch(StacksEpochId::Epoch2_05),
             &tx_exceeds,
             ASTRules::Typical
         )
@@ -4885,6 +4962,7 @@ pub mod test {
         assert!(Relayer::static_check_problematic_relayed_tx(
             false,
             StacksEpochId::Epoch2_05,
+            &VmLimits::for_epoch(StacksEpochId::Epoch2_05),
             &tx_high,
             ASTRules::Typical
         )
@@ -4893,6 +4971,7 @@ pub mod test {
         assert!(Relayer::static_check_problematic_relayed_tx(
             false,
             StacksEpochId::Epoch2_05,
+            &VmLimits::for_epoch(StacksEpochId::Epoch2_05),
             &tx_edge,
             ASTRules::Typical
         )
@@ -4900,6 +4979,7 @@ pub mod test {
         assert!(!Relayer::static_check_problematic_relayed_tx(
             false,
             StacksEpochId::Epoch2_05,
+            &VmLimits::for_epoch(StacksEpochId::Epoch2_05),
             &tx_exceeds,
             ASTRules::PrecheckSize
         )
@@ -4907,6 +4987,7 @@ pub mod test {
         assert!(!Relayer::static_check_problematic_relayed_tx(
             false,
             StacksEpochId::Epoch2_05,
+            &VmLimits::for_epoch(StacksEpochId::Epoch2_05),
             &tx_high,
             ASTRules::PrecheckSize
         )
@@ -5415,7 +5496,7 @@ pub mod test {
                 mblock_pubkey_hash_bytes.copy_from_slice(&coinbase_tx.txid()[0..20]);
 
                 let builder = StacksBlockBuilder::make_block_builder(
-                    chainstate.mainnet,
+                    chainstate.network.mainnet,
                     &parent_tip,
                     vrfproof,
                     tip.total_burn,
@@ -5590,7 +5671,7 @@ pub mod test {
                 mblock_pubkey_hash_bytes.copy_from_slice(&coinbase_tx.txid()[0..20]);
 
                 let builder = StacksBlockBuilder::make_block_builder(
-                    chainstate.mainnet,
+                    chainstate.network.mainnet,
                     &parent_tip,
                     vrfproof,
                     tip.total_burn,
@@ -5773,7 +5854,7 @@ pub mod test {
                 mblock_pubkey_hash_bytes.copy_from_slice(&coinbase_tx.txid()[0..20]);
 
                 let builder = StacksBlockBuilder::make_block_builder(
-                    chainstate.mainnet,
+                    chainstate.network.mainnet,
                     &parent_tip,
                     vrfproof,
                     tip.total_burn,
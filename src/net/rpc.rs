@@ -17,6 +17,7 @@
  along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
 */
 
+use std::cmp;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
@@ -36,16 +37,20 @@ use crate::burnchains::Burnchain;
 use crate::burnchains::BurnchainView;
 use crate::burnchains::*;
 use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::burn::BlockSnapshot;
 use crate::chainstate::burn::ConsensusHash;
+use crate::chainstate::stacks::db::banned_leader_keys::BannedLeaderKey;
 use crate::chainstate::stacks::db::blocks::CheckError;
 use crate::chainstate::stacks::db::{
-    blocks::MINIMUM_TX_FEE_RATE_PER_BYTE, StacksChainState, StreamCursor,
+    blocks::MINIMUM_TX_FEE_RATE_PER_BYTE, ChainstateReadTx, StacksChainState, StreamCursor,
 };
 use crate::chainstate::stacks::Error as chain_error;
 use crate::chainstate::stacks::*;
 use crate::clarity_vm::clarity::ClarityConnection;
 use crate::codec::StacksMessageCodec;
 use crate::core::mempool::*;
+use crate::core::BLOCK_LIMIT_MAINNET_21;
+use crate::core::EMPTY_MICROBLOCK_PARENT_HASH;
 use crate::cost_estimates::metrics::CostMetric;
 use crate::cost_estimates::CostEstimator;
 use crate::cost_estimates::FeeEstimator;
@@ -58,7 +63,11 @@ use crate::net::db::PeerDB;
 use crate::net::http::*;
 use crate::net::p2p::PeerMap;
 use crate::net::p2p::PeerNetwork;
+use crate::net::pagination::{PageError, PageRequest, RPCList, RPCPage};
+use crate::net::readonly_cache::{ReadOnlyCallCache, ReadOnlyCallKey};
 use crate::net::relay::Relayer;
+use crate::net::session::{RPCSessionId, RPCSessions};
+use crate::net::BlockFeedRequest;
 use crate::net::BlocksDatum;
 use crate::net::Error as net_error;
 use crate::net::HttpRequestMetadata;
@@ -72,6 +81,7 @@ use crate::net::NeighborsData;
 use crate::net::PeerAddress;
 use crate::net::PeerHost;
 use crate::net::ProtocolFamily;
+use crate::net::RPCBannedLeaderKey;
 use crate::net::RPCFeeEstimate;
 use crate::net::RPCFeeEstimateResponse;
 use crate::net::StacksHttp;
@@ -81,18 +91,29 @@ use crate::net::UnconfirmedTransactionResponse;
 use crate::net::UnconfirmedTransactionStatus;
 use crate::net::UrlString;
 use crate::net::HTTP_REQUEST_ID_RESERVED;
+use crate::net::MAX_BLOCK_FEE_STATS;
 use crate::net::MAX_HEADERS;
 use crate::net::MAX_NEIGHBORS_DATA_LEN;
 use crate::net::{
-    AccountEntryResponse, AttachmentPage, CallReadOnlyResponse, ContractSrcResponse,
-    DataVarResponse, GetAttachmentResponse, GetAttachmentsInvResponse, MapEntryResponse,
+    AccountEntryResponse, AttachmentPage, CallPreviewResponse, CallReadOnlyResponse,
+    ClarityMarfValueResponse, ClarityMetadataResponse, ContractSrcResponse, DataVarResponse,
+    GetAttachmentResponse, GetAttachmentsInvResponse, MapEntryResponse,
+    TransactionSimulationResponse, UnanchoredAccountEntryResponse,
 };
 use crate::net::{BlocksData, GetIsTraitImplementedResponse};
 use crate::net::{ClientError, TipRequest};
+use crate::net::{NodeMetadata, NodeMetadataData, RPCNodeMetadata, RPCNodeMetadataInfo};
 use crate::net::{
     RPCAffirmationData, RPCLastPoxAnchorData, RPCPeerInfoData, RPCPoxContractVersion,
     RPCPoxInfoData,
 };
+use crate::net::{
+    RPCBlockSummary, RPCChainQuality, RPCFeeRateBucket, RPCMempoolSummary, RPCPeerSummary,
+    RPCTipSummary, DEFAULT_BLOCKS_SUMMARY, DEFAULT_BLOCK_FEE_STATS, DEFAULT_CHAIN_QUALITY_WINDOW,
+    DEFAULT_HEADERS_PAGE, DEFAULT_NEIGHBORS_PAGE, DEFAULT_PEERS_SUMMARY,
+    DEFAULT_STAGING_QUEUE_PAGE, MAX_BLOCKS_SUMMARY, MAX_CHAIN_QUALITY_WINDOW, MAX_NEIGHBORS_PAGE,
+    MAX_PEERS_SUMMARY, MAX_STAGING_QUEUE_PAGE,
+};
 use crate::net::{RPCNeighbor, RPCNeighborsInfo};
 use crate::util_lib::db::DBConn;
 use crate::util_lib::db::Error as db_error;
@@ -116,6 +137,7 @@ use clarity::vm::{
 use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::hash::Hash160;
 use stacks_common::util::hash::{hex_bytes, to_hex};
+use stacks_common::util::secp256k1::MessageSignature;
 
 use crate::chainstate::stacks::boot::{POX_1_NAME, POX_2_NAME, POX_3_NAME};
 use crate::chainstate::stacks::StacksBlockHeader;
@@ -131,7 +153,7 @@ use crate::{
     util::hash::Sha256Sum, version_string,
 };
 
-use crate::util_lib::boot::boot_code_id;
+use crate::util_lib::boot::network_boot_code_id;
 
 use super::{RPCPoxCurrentCycleInfo, RPCPoxNextCycleInfo};
 
@@ -284,8 +306,7 @@ impl RPCPoxInfoData {
         tip: &StacksBlockId,
         burnchain: &Burnchain,
     ) -> Result<RPCPoxInfoData, net_error> {
-        let mainnet = chainstate.mainnet;
-        let chain_id = chainstate.chain_id;
+        let network = chainstate.network;
         let current_burn_height =
             SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?.block_height;
 
@@ -293,7 +314,7 @@ impl RPCPoxInfoData {
             .pox_constants
             .active_pox_contract(current_burn_height);
 
-        let contract_identifier = boot_code_id(pox_contract_name, mainnet);
+        let contract_identifier = network_boot_code_id(pox_contract_name, &network);
         let function = "get-pox-info";
         let cost_track = LimitedCostTracker::new_free();
         let sender = PrincipalData::Standard(StandardPrincipalData::transient());
@@ -327,8 +348,7 @@ impl RPCPoxInfoData {
         let data = chainstate
             .maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
                 clarity_tx.with_readonly_clarity_env(
-                    mainnet,
-                    chain_id,
+                    network,
                     ClarityVersion::Clarity2,
                     sender,
                     None,
@@ -483,7 +503,8 @@ impl RPCPoxInfoData {
         let cur_cycle_pox_active = sortdb.is_pox_active(burnchain, &burnchain_tip)?;
 
         Ok(RPCPoxInfoData {
-            contract_id: boot_code_id(cur_block_pox_contract, chainstate.mainnet).to_string(),
+            contract_id: network_boot_code_id(cur_block_pox_contract, &chainstate.network)
+                .to_string(),
             pox_activation_threshold_ustx,
             first_burnchain_block_height,
             current_burnchain_block_height: burnchain_tip.block_height,
@@ -517,18 +538,18 @@ impl RPCPoxInfoData {
             next_reward_cycle_in,
             contract_versions: vec![
                 RPCPoxContractVersion {
-                    contract_id: boot_code_id(POX_1_NAME, chainstate.mainnet).to_string(),
+                    contract_id: network_boot_code_id(POX_1_NAME, &chainstate.network).to_string(),
                     activation_burnchain_block_height: burnchain.first_block_height,
                     first_reward_cycle_id: pox_1_first_cycle,
                 },
                 RPCPoxContractVersion {
-                    contract_id: boot_code_id(POX_2_NAME, chainstate.mainnet).to_string(),
+                    contract_id: network_boot_code_id(POX_2_NAME, &chainstate.network).to_string(),
                     activation_burnchain_block_height: burnchain.pox_constants.v1_unlock_height
                         as u64,
                     first_reward_cycle_id: pox_2_first_cycle,
                 },
                 RPCPoxContractVersion {
-                    contract_id: boot_code_id(POX_3_NAME, chainstate.mainnet).to_string(),
+                    contract_id: network_boot_code_id(POX_3_NAME, &chainstate.network).to_string(),
                     activation_burnchain_block_height: burnchain
                         .pox_constants
                         .pox_3_activation_height
@@ -541,13 +562,16 @@ impl RPCPoxInfoData {
 }
 
 impl RPCNeighborsInfo {
-    /// Load neighbor address information from the peer network
+    /// Load neighbor address information from the peer network.  If `page` asks for a page,
+    /// then the same page of each list of neighbors is taken.
     pub fn from_p2p(
         network_id: u32,
         network_epoch: u8,
         peers: &PeerMap,
         chain_view: &BurnchainView,
         peerdb: &PeerDB,
+        page: &PageRequest,
+        limit: u64,
     ) -> Result<RPCNeighborsInfo, net_error> {
         let bootstrap_nodes =
             PeerDB::get_bootstrap_peers(peerdb.conn(), network_id).map_err(net_error::DBError)?;
@@ -604,14 +628,447 @@ impl RPCNeighborsInfo {
         }
 
         Ok(RPCNeighborsInfo {
-            bootstrap,
-            sample,
-            inbound,
-            outbound,
+            bootstrap: RPCList::from_items(bootstrap, page, limit),
+            sample: RPCList::from_items(sample, page, limit),
+            inbound: RPCList::from_items(inbound, page, limit),
+            outbound: RPCList::from_items(outbound, page, limit),
+        })
+    }
+}
+
+impl RPCNodeMetadata {
+    pub fn from_signed_metadata(
+        network_id: u32,
+        addrbytes: PeerAddress,
+        port: u16,
+        signed_metadata: &NodeMetadataData,
+        received_at: Option<u64>,
+    ) -> RPCNodeMetadata {
+        let metadata = &signed_metadata.metadata;
+        RPCNodeMetadata {
+            network_id,
+            addrbytes,
+            port,
+            public_key: to_hex(signed_metadata.node_public_key.as_bytes()),
+            peer_version: metadata.peer_version,
+            node_version: metadata.node_version.clone(),
+            services: metadata.services,
+            rpc_url: metadata.rpc_url.to_string(),
+            operator_contact: metadata.operator_contact.clone(),
+            timestamp: metadata.timestamp,
+            signature: signed_metadata.signature.to_hex(),
+            received_at,
+        }
+    }
+
+    /// Recover the signed record, so its signature can be checked with
+    /// `NodeMetadataData::verify()`.  Returns None if the record is malformed.
+    pub fn to_signed_metadata(&self) -> Option<NodeMetadataData> {
+        let node_public_key = hex_bytes(&self.public_key)
+            .ok()
+            .and_then(|bytes| StacksPublicKeyBuffer::from_bytes(&bytes))?;
+        let signature = MessageSignature::from_hex(&self.signature).ok()?;
+        let rpc_url = UrlString::try_from(self.rpc_url.clone()).ok()?;
+        Some(NodeMetadataData {
+            metadata: NodeMetadata {
+                peer_version: self.peer_version,
+                node_version: self.node_version.clone(),
+                services: self.services,
+                rpc_url,
+                operator_contact: self.operator_contact.clone(),
+                timestamp: self.timestamp,
+            },
+            node_public_key,
+            signature,
+        })
+    }
+}
+
+impl RPCNodeMetadataInfo {
+    /// Sign this node's own metadata record, and list the records our neighbors have sent us
+    pub fn from_p2p(network: &PeerNetwork) -> Result<RPCNodeMetadataInfo, net_error> {
+        let local_peer = &network.local_peer;
+        let local_metadata = NodeMetadataData::sign(
+            NodeMetadata::from_local_peer(local_peer, &network.connection_opts.operator_contact),
+            &local_peer.private_key,
+        )?;
+        let (local_addrbytes, local_port) = match local_peer.public_ip_address {
+            Some((ref addrbytes, port)) => (addrbytes.clone(), port),
+            None => (local_peer.addrbytes.clone(), local_peer.port),
+        };
+        let local = RPCNodeMetadata::from_signed_metadata(
+            local_peer.network_id,
+            local_addrbytes,
+            local_port,
+            &local_metadata,
+            None,
+        );
+
+        let neighbors =
+            PeerDB::get_all_node_metadata(network.peerdb.conn(), local_peer.network_id)?
+                .into_iter()
+                .map(|record| {
+                    RPCNodeMetadata::from_signed_metadata(
+                        record.addr.network_id,
+                        record.addr.addrbytes,
+                        record.addr.port,
+                        &record.signed_metadata,
+                        Some(record.received_at),
+                    )
+                })
+                .collect();
+
+        Ok(RPCNodeMetadataInfo { local, neighbors })
+    }
+}
+
+impl RPCBannedLeaderKey {
+    pub fn from_banned_leader_key(banned: &BannedLeaderKey) -> RPCBannedLeaderKey {
+        RPCBannedLeaderKey {
+            public_key: banned.public_key.to_hex(),
+            consensus_hash: banned.consensus_hash.clone(),
+            block_hash: banned.anchored_block_hash.clone(),
+            reason: banned.reason.clone(),
+            banned_at: banned.banned_at,
+        }
+    }
+}
+
+/// Lower bounds (in microSTX per unit of estimated cost) of the fee-rate buckets reported by
+/// `/v2/summary/mempool`
+pub const MEMPOOL_SUMMARY_FEE_RATE_BUCKETS: &[f64] = &[
+    0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0,
+];
+
+impl RPCTipSummary {
+    /// Summarize the node's burnchain and Stacks chain tips
+    pub fn from_db(
+        burnchain_tip: &BlockSnapshot,
+        chainstate: &ChainstateReadTx,
+        mempool: &MemPoolDB,
+    ) -> Result<RPCTipSummary, net_error> {
+        let stacks_tip_index_block_hash = StacksBlockId::new(
+            &burnchain_tip.canonical_stacks_tip_consensus_hash,
+            &burnchain_tip.canonical_stacks_tip_hash,
+        );
+        let (stacks_tip_burn_block_height, stacks_tip_burn_block_time) =
+            match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                chainstate.db(),
+                &stacks_tip_index_block_hash,
+            )? {
+                Some(header_info) => (
+                    header_info.burn_header_height,
+                    header_info.burn_header_timestamp,
+                ),
+                None => (0, 0),
+            };
+
+        let (unconfirmed_tip, unconfirmed_microblocks) = match chainstate.unconfirmed_state {
+            Some(ref unconfirmed) if unconfirmed.last_mblock.is_some() => (
+                Some(unconfirmed.unconfirmed_chain_tip.clone()),
+                unconfirmed.last_mblock_seq.saturating_add(1),
+            ),
+            _ => (None, 0),
+        };
+
+        let mempool_tx_count = mempool.snapshot().num_txs();
+
+        Ok(RPCTipSummary {
+            burn_block_height: burnchain_tip.block_height,
+            burn_block_hash: burnchain_tip.burn_header_hash.clone(),
+            burn_consensus_hash: burnchain_tip.consensus_hash.clone(),
+            stacks_tip_height: burnchain_tip.canonical_stacks_tip_height,
+            stacks_tip: burnchain_tip.canonical_stacks_tip_hash.clone(),
+            stacks_tip_consensus_hash: burnchain_tip.canonical_stacks_tip_consensus_hash.clone(),
+            stacks_tip_index_block_hash,
+            stacks_tip_burn_block_height,
+            stacks_tip_burn_block_time,
+            unconfirmed_tip,
+            unconfirmed_microblocks,
+            mempool_tx_count,
+        })
+    }
+}
+
+impl RPCBlockSummary {
+    /// Summarize up to `count` Stacks blocks, walking back from (and including) `start`.  Blocks
+    /// are returned highest-first, along with the parent of the last block summarized if there
+    /// are more blocks to summarize.
+    pub fn from_db(
+        sortdb: &SortitionDB,
+        chainstate: &ChainstateReadTx,
+        start: &StacksBlockId,
+        count: u64,
+    ) -> Result<(Vec<RPCBlockSummary>, Option<StacksBlockId>), net_error> {
+        let mut summaries = vec![];
+        let mut cursor = Some(start.clone());
+        while let Some(block_id) = cursor.take() {
+            if summaries.len() as u64 >= count {
+                break;
+            }
+            let header_info =
+                match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                    chainstate.db(),
+                    &block_id,
+                )? {
+                    Some(header_info) => header_info,
+                    None => break,
+                };
+            if header_info.stacks_block_height == 0 {
+                // boot block -- nothing to summarize
+                break;
+            }
+
+            let cost =
+                StacksChainState::get_stacks_block_anchored_cost(chainstate.db(), &block_id)?
+                    .unwrap_or(ExecutionCost::zero());
+            let cost_fill_pct = SortitionDB::get_stacks_epoch(
+                sortdb.conn(),
+                header_info.burn_header_height as u64,
+            )?
+            .map(|epoch| epoch.block_limit.proportion_largest_dimension(&cost))
+            .unwrap_or(0);
+            let size_fill_pct =
+                header_info.anchored_block_size.saturating_mul(100) / (MAX_BLOCK_LEN as u64);
+
+            cursor = StacksChainState::get_parent_block_id(chainstate.db(), &block_id)?;
+            summaries.push(RPCBlockSummary {
+                height: header_info.stacks_block_height,
+                index_block_hash: block_id,
+                block_hash: header_info.anchored_header.block_hash(),
+                consensus_hash: header_info.consensus_hash,
+                parent_index_block_hash: cursor.clone(),
+                burn_block_height: header_info.burn_header_height,
+                burn_block_time: header_info.burn_header_timestamp,
+                size: header_info.anchored_block_size,
+                cost,
+                cost_fill_pct,
+                size_fill_pct,
+            });
+        }
+        // don't point at the boot block
+        let next = match cursor {
+            Some(block_id) => StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                chainstate.db(),
+                &block_id,
+            )?
+            .filter(|header_info| header_info.stacks_block_height > 0)
+            .map(|_| block_id),
+            None => None,
+        };
+        Ok((summaries, next))
+    }
+
+    /// Summarize a page of blocks on the fork ending at `tip`, which is at height `tip_height`.
+    /// The page either starts at the block given by the request's cursor (an index block hash),
+    /// or `offset` blocks below `tip`.
+    pub fn get_page(
+        sortdb: &SortitionDB,
+        chainstate: &ChainstateReadTx,
+        tip: &StacksBlockId,
+        tip_height: u64,
+        page: &PageRequest,
+        limit: u64,
+    ) -> Result<Result<RPCPage<RPCBlockSummary>, PageError>, net_error> {
+        let start = match page.cursor {
+            Some(ref cursor) => match StacksBlockId::from_hex(cursor) {
+                Ok(block_id) => Some(block_id),
+                Err(_) => {
+                    return Ok(Err(PageError::InvalidCursor(cursor.clone())));
+                }
+            },
+            None => {
+                if page.offset >= tip_height {
+                    None
+                } else {
+                    chainstate
+                        .index_conn()?
+                        .get_ancestor_block_hash(tip_height - page.offset, tip)?
+                }
+            }
+        };
+        let (results, next_cursor) = match start {
+            Some(start) => RPCBlockSummary::from_db(sortdb, chainstate, &start, limit)?,
+            None => (vec![], None),
+        };
+        Ok(Ok(RPCPage::new(
+            results,
+            limit,
+            page.offset,
+            Some(tip_height),
+            next_cursor.map(|block_id| block_id.to_hex()),
+        )))
+    }
+}
+
+impl RPCChainQuality {
+    /// Compute chain-quality metrics over the last `window` burnchain blocks, ending at the
+    /// canonical burnchain tip.  Stacks blocks are counted if they are on the canonical Stacks
+    /// fork and their tenures started in the window.
+    pub fn from_db(
+        sortdb: &SortitionDB,
+        chainstate: &ChainstateReadTx,
+        window: u64,
+    ) -> Result<RPCChainQuality, net_error> {
+        let burn_tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+
+        // don't look before the first burnchain block
+        let window = cmp::min(
+            window,
+            burn_tip
+                .block_height
+                .saturating_sub(sortdb.first_block_height),
+        );
+        let min_burn_height = burn_tip.block_height.saturating_sub(window) + 1;
+
+        let mut sortitions = 0;
+        let mut cursor = Some(burn_tip.clone());
+        while let Some(sn) = cursor.take() {
+            if sn.block_height < min_burn_height {
+                break;
+            }
+            if sn.sortition {
+                sortitions += 1;
+            }
+            cursor = SortitionDB::get_block_snapshot(sortdb.conn(), &sn.parent_sortition_id)?;
+        }
+
+        let mut stacks_blocks = 0;
+        let mut total_fill_pct = 0;
+        let mut microblocks_produced = 0;
+        let mut microblocks_confirmed = 0;
+        let mut cursor = Some(StacksBlockId::new(
+            &burn_tip.canonical_stacks_tip_consensus_hash,
+            &burn_tip.canonical_stacks_tip_hash,
+        ));
+        while let Some(block_id) = cursor.take() {
+            let header_info =
+                match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                    chainstate.db(),
+                    &block_id,
+                )? {
+                    Some(header_info) => header_info,
+                    None => break,
+                };
+            if header_info.stacks_block_height == 0
+                || (header_info.burn_header_height as u64) < min_burn_height
+            {
+                break;
+            }
+
+            let cost =
+                StacksChainState::get_stacks_block_anchored_cost(chainstate.db(), &block_id)?
+                    .unwrap_or(ExecutionCost::zero());
+            total_fill_pct += SortitionDB::get_stacks_epoch(
+                sortdb.conn(),
+                header_info.burn_header_height as u64,
+            )?
+            .map(|epoch| epoch.block_limit.proportion_largest_dimension(&cost))
+            .unwrap_or(0);
+
+            cursor = StacksChainState::get_parent_block_id(chainstate.db(), &block_id)?;
+            if let Some(ref parent_block_id) = cursor {
+                microblocks_produced +=
+                    StacksChainState::count_microblocks_indexed(chainstate.db(), parent_block_id)?;
+                if header_info.anchored_header.parent_microblock != EMPTY_MICROBLOCK_PARENT_HASH {
+                    microblocks_confirmed +=
+                        header_info.anchored_header.parent_microblock_sequence as u64 + 1;
+                }
+            }
+            stacks_blocks += 1;
+        }
+
+        let ratio = |numerator: u64, denominator: u64| {
+            if denominator == 0 {
+                None
+            } else {
+                Some(numerator as f64 / denominator as f64)
+            }
+        };
+
+        Ok(RPCChainQuality {
+            window,
+            burn_block_height: burn_tip.block_height,
+            sortitions,
+            missed_sortitions: window - sortitions,
+            sortition_rate: ratio(sortitions, window).unwrap_or(0.0),
+            stacks_blocks,
+            empty_tenures: sortitions.saturating_sub(stacks_blocks),
+            average_block_fill_pct: ratio(total_fill_pct, stacks_blocks),
+            microblocks_produced,
+            microblocks_confirmed,
+            // we may not have stored every microblock a block confirmed
+            microblock_confirmation_rate: ratio(microblocks_confirmed, microblocks_produced)
+                .map(|rate| rate.min(1.0)),
+        })
+    }
+}
+
+impl RPCMempoolSummary {
+    /// Summarize the mempool, bucketing transactions by fee rate using the given (ascending)
+    /// lower bounds.  Transactions whose fee rate has not been estimated are not bucketed.
+    pub fn from_db(
+        mempool: &MemPoolDB,
+        fee_rate_buckets: &[f64],
+    ) -> Result<RPCMempoolSummary, net_error> {
+        let snapshot = mempool.snapshot();
+        let (tx_count, total_tx_len, total_tx_fee) = snapshot.get_totals();
+        let unestimated_tx_count = snapshot.count_txs_without_fee_rate();
+
+        let mut fee_rate_histogram = Vec::with_capacity(fee_rate_buckets.len());
+        for (i, min_fee_rate) in fee_rate_buckets.iter().enumerate() {
+            let max_fee_rate = fee_rate_buckets.get(i + 1).cloned();
+            let tx_count = snapshot.count_txs_in_fee_rate_range(*min_fee_rate, max_fee_rate);
+            fee_rate_histogram.push(RPCFeeRateBucket {
+                min_fee_rate: *min_fee_rate,
+                max_fee_rate,
+                tx_count,
+            });
+        }
+
+        Ok(RPCMempoolSummary {
+            tx_count,
+            total_tx_len,
+            total_tx_fee,
+            unestimated_tx_count,
+            fee_rate_histogram,
         })
     }
 }
 
+impl RPCPeerSummary {
+    /// Summarize each of the peers we currently have a conversation with
+    pub fn from_p2p(peers: &PeerMap) -> Vec<RPCPeerSummary> {
+        let mut summaries: Vec<_> = peers
+            .values()
+            .map(|convo| {
+                let nk = convo.to_neighbor_key();
+                RPCPeerSummary {
+                    network_id: nk.network_id,
+                    peer_version: nk.peer_version,
+                    addrbytes: nk.addrbytes,
+                    port: nk.port,
+                    public_key_hash: convo.get_public_key_hash(),
+                    outbound: convo.is_outbound(),
+                    authenticated: convo.is_authenticated(),
+                    burn_block_height: convo.get_burnchain_tip_height(),
+                    first_contact_time: convo.stats.first_contact_time,
+                    last_contact_time: convo.stats.last_contact_time,
+                    last_send_time: convo.stats.last_send_time,
+                    last_recv_time: convo.stats.last_recv_time,
+                    bytes_tx: convo.stats.bytes_tx,
+                    bytes_rx: convo.stats.bytes_rx,
+                    msgs_tx: convo.stats.msgs_tx,
+                    msgs_rx: convo.stats.msgs_rx,
+                    msgs_err: convo.stats.msgs_err,
+                }
+            })
+            .collect();
+        summaries.sort_by_key(|summary| summary.first_contact_time);
+        summaries
+    }
+}
+
 impl ConversationHttp {
     pub fn new(
         peer_addr: SocketAddr,
@@ -902,869 +1359,817 @@ impl ConversationHttp {
         fd: &mut W,
         req: &HttpRequestType,
         network: &PeerNetwork,
+        page: &PageRequest,
         canonical_stacks_tip_height: u64,
     ) -> Result<(), net_error> {
         let epoch = network.get_current_epoch();
 
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let limit = match page
+            .check_no_cursor()
+            .and_then(|_| page.get_limit(DEFAULT_NEIGHBORS_PAGE, MAX_NEIGHBORS_PAGE))
+        {
+            Ok(limit) => limit,
+            Err(e) => {
+                let response = HttpResponseType::BadRequest(
+                    response_metadata,
+                    format!("Invalid request: {}", e),
+                );
+                return response.send(http, fd);
+            }
+        };
         let neighbor_data = RPCNeighborsInfo::from_p2p(
             network.local_peer.network_id,
             epoch.network_epoch,
             &network.peers,
             &network.chain_view,
             &network.peerdb,
+            page,
+            limit,
         )?;
         let response = HttpResponseType::Neighbors(response_metadata, neighbor_data);
         response.send(http, fd)
     }
 
-    /// Handle a not-found
-    fn handle_notfound<W: Write>(
-        http: &mut StacksHttp,
-        fd: &mut W,
-        response_metadata: HttpResponseMetadata,
-        msg: String,
-    ) -> Result<Option<StreamCursor>, net_error> {
-        let response = HttpResponseType::NotFound(response_metadata, msg);
-        return response.send(http, fd).and_then(|_| Ok(None));
-    }
-
-    /// Handle a server error
-    fn handle_server_error<W: Write>(
+    /// Handle a GET node metadata
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_node_metadata<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
-        response_metadata: HttpResponseMetadata,
-        msg: String,
-    ) -> Result<Option<StreamCursor>, net_error> {
-        // oops
-        warn!("{}", &msg);
-        let response = HttpResponseType::ServerError(response_metadata, msg);
-        return response.send(http, fd).and_then(|_| Ok(None));
+        req: &HttpRequestType,
+        network: &PeerNetwork,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let response = match RPCNodeMetadataInfo::from_p2p(network) {
+            Ok(node_metadata) => HttpResponseType::NodeMetadata(response_metadata, node_metadata),
+            Err(e) => {
+                warn!("Failed to load node metadata: {:?}", &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to load node metadata".to_string(),
+                )
+            }
+        };
+        response.send(http, fd)
     }
 
-    /// Handle a GET headers. Start streaming the reply.
-    /// The response's preamble (but not the headers list) will be synchronously written to the fd
-    /// (so use a fd that can buffer!)
-    /// Return a StreamCursor struct for the reward cycle we're sending, so we can continue to
-    /// make progress sending it
-    fn handle_getheaders<W: Write>(
+    /// Handle a GET banned leader keys
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_banned_leader_keys<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        tip: &StacksBlockId,
-        quantity: u64,
         chainstate: &StacksChainState,
         canonical_stacks_tip_height: u64,
-    ) -> Result<Option<StreamCursor>, net_error> {
+    ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        if quantity > (MAX_HEADERS as u64) {
-            // bad request
-            let response = HttpResponseType::BadRequestJSON(
+        let response = match StacksChainState::get_banned_leader_keys(chainstate.db()) {
+            Ok(banned) => HttpResponseType::BannedLeaderKeys(
                 response_metadata,
-                serde_json::Value::String(format!(
-                    "Invalid request: requested more than {} headers",
-                    MAX_HEADERS
-                )),
-            );
-            response.send(http, fd).and_then(|_| Ok(None))
-        } else {
-            let stream = match StreamCursor::new_headers(chainstate, tip, quantity as u32) {
-                Ok(stream) => stream,
-                Err(chain_error::NoSuchBlockError) => {
-                    return ConversationHttp::handle_notfound(
-                        http,
-                        fd,
-                        response_metadata,
-                        format!("No such block {:?}", &tip),
-                    );
-                }
-                Err(e) => {
-                    // nope -- error trying to check
-                    warn!("Failed to load block header {:?}: {:?}", req, &e);
-                    let response = HttpResponseType::ServerError(
-                        response_metadata,
-                        format!("Failed to query block header {}", tip.to_hex()),
-                    );
-                    return response.send(http, fd).and_then(|_| Ok(None));
-                }
-            };
-            let response = HttpResponseType::HeaderStream(response_metadata);
-            response.send(http, fd).and_then(|_| Ok(Some(stream)))
-        }
+                banned
+                    .iter()
+                    .map(RPCBannedLeaderKey::from_banned_leader_key)
+                    .collect(),
+            ),
+            Err(e) => {
+                warn!("Failed to load banned leader keys: {:?}", &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to load banned leader keys".to_string(),
+                )
+            }
+        };
+        response.send(http, fd)
     }
 
-    /// Handle a GET block.  Start streaming the reply.
-    /// The response's preamble (but not the block data) will be synchronously written to the fd
-    /// (so use a fd that can buffer!)
-    /// Return a StreamCursor struct for the block that we're sending, so we can continue to
-    /// make progress sending it.
-    fn handle_getblock<W: Write>(
+    /// Handle a GET tip summary
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_tip_summary<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        index_block_hash: &StacksBlockId,
+        network: &PeerNetwork,
         chainstate: &StacksChainState,
-        canonical_stacks_tip_height: u64,
-    ) -> Result<Option<StreamCursor>, net_error> {
-        monitoring::increment_stx_blocks_served_counter();
-        let response_metadata =
-            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-
-        // do we have this block?
-        match StacksChainState::has_block_indexed(&chainstate.blocks_path, index_block_hash) {
-            Ok(false) => {
-                return ConversationHttp::handle_notfound(
-                    http,
-                    fd,
-                    response_metadata,
-                    format!("No such block {}", index_block_hash.to_hex()),
-                );
+        mempool: &MemPoolDB,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from_http_request_type(
+            req,
+            Some(network.burnchain_tip.canonical_stacks_tip_height),
+        );
+        let summary = chainstate
+            .read_tx_begin()
+            .map_err(net_error::from)
+            .and_then(|read_tx| RPCTipSummary::from_db(&network.burnchain_tip, &read_tx, mempool));
+        match summary {
+            Ok(summary) => {
+                let response = HttpResponseType::TipSummary(response_metadata, summary);
+                response.send(http, fd)
             }
             Err(e) => {
-                // nope -- error trying to check
-                warn!("Failed to serve block {:?}: {:?}", req, &e);
+                warn!("Failed to summarize chain tip {:?}: {:?}", req, &e);
                 let response = HttpResponseType::ServerError(
                     response_metadata,
-                    format!("Failed to query block {}", index_block_hash.to_hex()),
+                    "Failed to summarize chain tip".to_string(),
                 );
-                response.send(http, fd).and_then(|_| Ok(None))
-            }
-            Ok(true) => {
-                // yup! start streaming it back
-                let stream = StreamCursor::new_block(index_block_hash.clone());
-                let response = HttpResponseType::BlockStream(response_metadata);
-                response.send(http, fd).and_then(|_| Ok(Some(stream)))
+                response.send(http, fd)
             }
         }
     }
 
-    /// Handle a GET confirmed microblock stream, by _anchor block hash_.  Start streaming the reply.
-    /// The response's preamble (but not the block data) will be synchronously written to the fd
-    /// (so use a fd that can buffer!)
-    /// Return a StreamCursor struct for the block that we're sending, so we can continue to
-    /// make progress sending it.
-    fn handle_getmicroblocks_confirmed<W: Write>(
+    /// Handle a GET blocks summary
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_blocks_summary<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        index_anchor_block_hash: &StacksBlockId,
+        network: &PeerNetwork,
+        sortdb: &SortitionDB,
         chainstate: &StacksChainState,
-        canonical_stacks_tip_height: u64,
-    ) -> Result<Option<StreamCursor>, net_error> {
-        monitoring::increment_stx_confirmed_micro_blocks_served_counter();
-        let response_metadata =
-            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-
-        match chainstate.has_processed_microblocks(index_anchor_block_hash) {
-            Ok(true) => {}
-            Ok(false) => {
-                return ConversationHttp::handle_notfound(
-                    http,
-                    fd,
+        page: &PageRequest,
+    ) -> Result<(), net_error> {
+        let tip_height = network.burnchain_tip.canonical_stacks_tip_height;
+        let response_metadata = HttpResponseMetadata::from_http_request_type(req, Some(tip_height));
+        let limit = match page.get_limit(DEFAULT_BLOCKS_SUMMARY, MAX_BLOCKS_SUMMARY) {
+            Ok(limit) => limit,
+            Err(e) => {
+                let response = HttpResponseType::BadRequest(
                     response_metadata,
-                    format!(
-                        "No such confirmed microblock stream for anchor block {}",
-                        &index_anchor_block_hash
-                    ),
+                    format!("Invalid request: {}", e),
                 );
+                return response.send(http, fd);
             }
-            Err(e) => {
-                return ConversationHttp::handle_server_error(
-                    http,
-                    fd,
-                    response_metadata,
-                    format!(
-                        "Failed to query confirmed microblock stream {:?}: {:?}",
-                        req, &e
-                    ),
-                );
-            }
-        }
+        };
 
-        match chainstate.get_confirmed_microblock_index_hash(index_anchor_block_hash) {
-            Err(e) => {
-                return ConversationHttp::handle_server_error(
-                    http,
-                    fd,
+        let tip = StacksBlockId::new(
+            &network.burnchain_tip.canonical_stacks_tip_consensus_hash,
+            &network.burnchain_tip.canonical_stacks_tip_hash,
+        );
+        let summaries = chainstate
+            .read_tx_begin()
+            .map_err(net_error::from)
+            .and_then(|read_tx| {
+                RPCBlockSummary::get_page(sortdb, &read_tx, &tip, tip_height, page, limit)
+            });
+        match summaries {
+            Ok(Ok(summaries)) => {
+                let response = HttpResponseType::BlocksSummary(response_metadata, summaries);
+                response.send(http, fd)
+            }
+            Ok(Err(e)) => {
+                let response = HttpResponseType::BadRequest(
                     response_metadata,
-                    format!(
-                        "Failed to serve confirmed microblock stream {:?}: {:?}",
-                        req, &e
-                    ),
+                    format!("Invalid request: {}", e),
                 );
+                response.send(http, fd)
             }
-            Ok(None) => {
-                return ConversationHttp::handle_notfound(
-                    http,
-                    fd,
+            Err(e) => {
+                warn!("Failed to summarize blocks {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(
                     response_metadata,
-                    format!(
-                        "No such confirmed microblock stream for anchor block {}",
-                        &index_anchor_block_hash
-                    ),
+                    "Failed to summarize blocks".to_string(),
                 );
-            }
-            Ok(Some(tail_index_microblock_hash)) => {
-                let (response, stream_opt) = match StreamCursor::new_microblock_confirmed(
-                    chainstate,
-                    tail_index_microblock_hash.clone(),
-                ) {
-                    Ok(stream) => (
-                        HttpResponseType::MicroblockStream(response_metadata),
-                        Some(stream),
-                    ),
-                    Err(chain_error::NoSuchBlockError) => (
-                        HttpResponseType::NotFound(
-                            response_metadata,
-                            format!(
-                                "No such confirmed microblock stream ending with {}",
-                                tail_index_microblock_hash.to_hex()
-                            ),
-                        ),
-                        None,
-                    ),
-                    Err(_e) => {
-                        debug!(
-                            "Failed to load confirmed microblock stream {}: {:?}",
-                            &tail_index_microblock_hash, &_e
-                        );
-                        (
-                            HttpResponseType::ServerError(
-                                response_metadata,
-                                format!(
-                                    "Failed to query confirmed microblock stream {}",
-                                    tail_index_microblock_hash.to_hex()
-                                ),
-                            ),
-                            None,
-                        )
-                    }
-                };
-                response.send(http, fd).and_then(|_| Ok(stream_opt))
+                response.send(http, fd)
             }
         }
     }
 
-    /// Handle a GET confirmed microblock stream, by last _index microblock hash_ in the stream.  Start streaming the reply.
-    /// The response's preamble (but not the block data) will be synchronously written to the fd
-    /// (so use a fd that can buffer!)
-    /// Return a StreamCursor struct for the block that we're sending, so we can continue to
-    /// make progress sending it.
-    fn handle_getmicroblocks_indexed<W: Write>(
+    /// Handle a GET chain quality summary
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_chain_quality<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        tail_index_microblock_hash: &StacksBlockId,
+        sortdb: &SortitionDB,
         chainstate: &StacksChainState,
+        window: Option<u64>,
         canonical_stacks_tip_height: u64,
-    ) -> Result<Option<StreamCursor>, net_error> {
-        monitoring::increment_stx_micro_blocks_served_counter();
+    ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let window = window.unwrap_or(DEFAULT_CHAIN_QUALITY_WINDOW);
+        if window > MAX_CHAIN_QUALITY_WINDOW {
+            let response = HttpResponseType::BadRequest(
+                response_metadata,
+                format!(
+                    "Invalid request: window must be at most {}",
+                    MAX_CHAIN_QUALITY_WINDOW
+                ),
+            );
+            return response.send(http, fd);
+        }
 
-        // do we have this processed microblock stream?
-        match StacksChainState::has_processed_microblocks_indexed(
-            chainstate.db(),
-            tail_index_microblock_hash,
-        ) {
-            Ok(false) => {
-                // nope
-                return ConversationHttp::handle_notfound(
-                    http,
-                    fd,
-                    response_metadata,
-                    format!(
-                        "No such confirmed microblock stream ending with {}",
-                        &tail_index_microblock_hash
-                    ),
-                );
+        let chain_quality = chainstate
+            .read_tx_begin()
+            .map_err(net_error::from)
+            .and_then(|read_tx| RPCChainQuality::from_db(sortdb, &read_tx, window));
+        match chain_quality {
+            Ok(chain_quality) => {
+                let response = HttpResponseType::ChainQuality(response_metadata, chain_quality);
+                response.send(http, fd)
             }
             Err(e) => {
-                // nope
-                return ConversationHttp::handle_server_error(
-                    http,
-                    fd,
+                warn!("Failed to compute chain quality {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(
                     response_metadata,
-                    format!(
-                        "Failed to serve confirmed microblock stream {:?}: {:?}",
-                        req, &e
-                    ),
+                    "Failed to compute chain quality".to_string(),
                 );
-            }
-            Ok(true) => {
-                // yup! start streaming it back
-                let (response, stream_opt) = match StreamCursor::new_microblock_confirmed(
-                    chainstate,
-                    tail_index_microblock_hash.clone(),
-                ) {
-                    Ok(stream) => (
-                        HttpResponseType::MicroblockStream(response_metadata),
-                        Some(stream),
-                    ),
-                    Err(chain_error::NoSuchBlockError) => (
-                        HttpResponseType::NotFound(
-                            response_metadata,
-                            format!(
-                                "No such confirmed microblock stream ending with {}",
-                                tail_index_microblock_hash.to_hex()
-                            ),
-                        ),
-                        None,
-                    ),
-                    Err(_e) => {
-                        debug!(
-                            "Failed to load confirmed indexed microblock stream {}: {:?}",
-                            &tail_index_microblock_hash, &_e
-                        );
-                        (
-                            HttpResponseType::ServerError(
-                                response_metadata,
-                                format!(
-                                    "Failed to query confirmed microblock stream {}",
-                                    tail_index_microblock_hash.to_hex()
-                                ),
-                            ),
-                            None,
-                        )
-                    }
-                };
-                response.send(http, fd).and_then(|_| Ok(stream_opt))
+                response.send(http, fd)
             }
         }
     }
 
-    /// Handle a GET token transfer cost.  Reply the entire response.
-    /// TODO: accurately estimate the cost/length fee for token transfers, based on mempool
-    /// pressure.
-    fn handle_token_transfer_cost<W: Write>(
+    /// Handle a GET mempool summary
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_mempool_summary<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
+        mempool: &MemPoolDB,
         canonical_stacks_tip_height: u64,
     ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-
-        // todo -- need to actually estimate the cost / length for token transfers
-        //   right now, it just uses the minimum.
-        let fee = MINIMUM_TX_FEE_RATE_PER_BYTE;
-        let response = HttpResponseType::TokenTransferCost(response_metadata, fee);
-        response.send(http, fd).map(|_| ())
+        match RPCMempoolSummary::from_db(mempool, MEMPOOL_SUMMARY_FEE_RATE_BUCKETS) {
+            Ok(summary) => {
+                let response = HttpResponseType::MempoolSummary(response_metadata, summary);
+                response.send(http, fd)
+            }
+            Err(e) => {
+                warn!("Failed to summarize mempool {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to summarize mempool".to_string(),
+                );
+                response.send(http, fd)
+            }
+        }
     }
 
-    /// Handle a GET on an existing account, given the current chain tip.  Optionally supplies a
-    /// MARF proof for each account detail loaded from the chain tip.
-    fn handle_get_account_entry<W: Write>(
+    /// Handle a GET peers summary
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_peers_summary<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        sortdb: &SortitionDB,
-        chainstate: &mut StacksChainState,
-        tip: &StacksBlockId,
-        account: &PrincipalData,
-        with_proof: bool,
+        network: &PeerNetwork,
+        page: &PageRequest,
         canonical_stacks_tip_height: u64,
     ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        let response =
-            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                clarity_tx.with_clarity_db_readonly(|clarity_db| {
-                    let key = ClarityDatabase::make_key_for_account_balance(&account);
-                    let burn_block_height = clarity_db.get_current_burnchain_block_height() as u64;
-                    let v1_unlock_height = clarity_db.get_v1_unlock_height();
-                    let v2_unlock_height = clarity_db.get_v2_unlock_height();
-                    let (balance, balance_proof) = if with_proof {
-                        clarity_db
-                            .get_with_proof::<STXBalance>(&key)
-                            .map(|(a, b)| (a, Some(format!("0x{}", to_hex(&b)))))
-                            .unwrap_or_else(|| (STXBalance::zero(), Some("".into())))
-                    } else {
-                        clarity_db
-                            .get::<STXBalance>(&key)
-                            .map(|a| (a, None))
-                            .unwrap_or_else(|| (STXBalance::zero(), None))
-                    };
-
-                    let key = ClarityDatabase::make_key_for_account_nonce(&account);
-                    let (nonce, nonce_proof) = if with_proof {
-                        clarity_db
-                            .get_with_proof(&key)
-                            .map(|(a, b)| (a, Some(format!("0x{}", to_hex(&b)))))
-                            .unwrap_or_else(|| (0, Some("".into())))
-                    } else {
-                        clarity_db
-                            .get(&key)
-                            .map(|a| (a, None))
-                            .unwrap_or_else(|| (0, None))
-                    };
-
-                    let unlocked = balance.get_available_balance_at_burn_block(
-                        burn_block_height,
-                        v1_unlock_height,
-                        v2_unlock_height,
-                    );
-                    let (locked, unlock_height) = balance.get_locked_balance_at_burn_block(
-                        burn_block_height,
-                        v1_unlock_height,
-                        v2_unlock_height,
-                    );
-
-                    let balance = format!("0x{}", to_hex(&unlocked.to_be_bytes()));
-                    let locked = format!("0x{}", to_hex(&locked.to_be_bytes()));
-
-                    AccountEntryResponse {
-                        balance,
-                        locked,
-                        unlock_height,
-                        nonce,
-                        balance_proof,
-                        nonce_proof,
-                    }
-                })
-            }) {
-                Ok(Some(data)) => HttpResponseType::GetAccount(response_metadata, data),
-                Ok(None) | Err(_) => {
-                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
-                }
-            };
-
-        response.send(http, fd).map(|_| ())
+        let limit = match page
+            .check_no_cursor()
+            .and_then(|_| page.get_limit(DEFAULT_PEERS_SUMMARY, MAX_PEERS_SUMMARY))
+        {
+            Ok(limit) => limit,
+            Err(e) => {
+                let response = HttpResponseType::BadRequest(
+                    response_metadata,
+                    format!("Invalid request: {}", e),
+                );
+                return response.send(http, fd);
+            }
+        };
+        let summaries = RPCPeerSummary::from_p2p(&network.peers);
+        let response = HttpResponseType::PeersSummary(
+            response_metadata,
+            RPCPage::from_items(summaries, limit, page.offset),
+        );
+        response.send(http, fd)
     }
 
-    /// Handle a GET on a smart contract's data var, given the current chain tip.  Optionally
-    /// supplies a MARF proof for the value.
-    fn handle_get_data_var<W: Write>(
+    /// Handle a POST to open a read-only session, pinned to the given chain tip.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_open_session<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        sortdb: &SortitionDB,
-        chainstate: &mut StacksChainState,
         tip: &StacksBlockId,
-        contract_addr: &StacksAddress,
-        contract_name: &ContractName,
-        var_name: &ClarityName,
-        with_proof: bool,
+        chainstate: &StacksChainState,
+        sessions: &mut RPCSessions,
         canonical_stacks_tip_height: u64,
     ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        let contract_identifier =
-            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
-
-        let response =
-            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                clarity_tx.with_clarity_db_readonly(|clarity_db| {
-                    let key = ClarityDatabase::make_key_for_trip(
-                        &contract_identifier,
-                        StoreType::Variable,
-                        var_name,
-                    );
 
-                    let (value_hex, marf_proof): (String, _) = if with_proof {
-                        clarity_db
-                            .get_with_proof(&key)
-                            .map(|(a, b)| (a, Some(format!("0x{}", to_hex(&b)))))?
-                    } else {
-                        clarity_db.get(&key).map(|a| (a, None))?
-                    };
+        // only anchored blocks can be pinned, since unconfirmed state can be rewritten
+        // underneath the session
+        let tip_height = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            tip,
+        )? {
+            Some(header_info) => header_info.stacks_block_height,
+            None => {
+                let response = HttpResponseType::NotFound(
+                    response_metadata,
+                    format!("No such anchored block {}", tip),
+                );
+                return response.send(http, fd);
+            }
+        };
 
-                    let data = format!("0x{}", value_hex);
-                    Some(DataVarResponse { data, marf_proof })
-                })
-            }) {
-                Ok(Some(Some(data))) => HttpResponseType::GetDataVar(response_metadata, data),
-                Ok(Some(None)) => {
-                    HttpResponseType::NotFound(response_metadata, "Data var not found".into())
-                }
-                Ok(None) | Err(_) => {
-                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
-                }
-            };
+        match sessions.open(tip.clone(), tip_height, get_epoch_time_secs()) {
+            Some(session) => {
+                debug!(
+                    "Opened read-only session {} at {} (height {})",
+                    &session.session_id, tip, tip_height
+                );
+                let response = HttpResponseType::Session(response_metadata, session.to_rpc_data());
+                response.send(http, fd)
+            }
+            None => {
+                let response = HttpResponseType::ServiceUnavailable(
+                    response_metadata,
+                    "Too many open sessions".to_string(),
+                );
+                response.send(http, fd)
+            }
+        }
+    }
 
-        response.send(http, fd).map(|_| ())
+    /// Handle a POST to release a read-only session.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_release_session<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        session_id: &RPCSessionId,
+        sessions: &mut RPCSessions,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        match sessions.release(session_id, get_epoch_time_secs()) {
+            Some(session) => {
+                let response = HttpResponseType::Session(response_metadata, session.to_rpc_data());
+                response.send(http, fd)
+            }
+            None => {
+                let response = HttpResponseType::NotFound(
+                    response_metadata,
+                    format!("No such session: {}", session_id),
+                );
+                response.send(http, fd)
+            }
+        }
     }
 
-    /// Handle a GET on a smart contract's data map, given the current chain tip.  Optionally
-    /// supplies a MARF proof for the value.
-    fn handle_get_map_entry<W: Write>(
+    /// Handle a not-found
+    fn handle_notfound<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        response_metadata: HttpResponseMetadata,
+        msg: String,
+    ) -> Result<Option<StreamCursor>, net_error> {
+        let response = HttpResponseType::NotFound(response_metadata, msg);
+        return response.send(http, fd).and_then(|_| Ok(None));
+    }
+
+    /// Handle a server error
+    fn handle_server_error<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        response_metadata: HttpResponseMetadata,
+        msg: String,
+    ) -> Result<Option<StreamCursor>, net_error> {
+        // oops
+        warn!("{}", &msg);
+        let response = HttpResponseType::ServerError(response_metadata, msg);
+        return response.send(http, fd).and_then(|_| Ok(None));
+    }
+
+    /// Handle a GET headers. Start streaming the reply.
+    /// The response's preamble (but not the headers list) will be synchronously written to the fd
+    /// (so use a fd that can buffer!)
+    /// The page starts at `tip`, unless the request gives a cursor to resume from.
+    /// Return a StreamCursor struct for the reward cycle we're sending, so we can continue to
+    /// make progress sending it
+    fn handle_getheaders<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        sortdb: &SortitionDB,
-        chainstate: &mut StacksChainState,
         tip: &StacksBlockId,
-        contract_addr: &StacksAddress,
-        contract_name: &ContractName,
-        map_name: &ClarityName,
-        key: &Value,
-        with_proof: bool,
+        page: &PageRequest,
+        chainstate: &StacksChainState,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<Option<StreamCursor>, net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let start = match page.cursor {
+            Some(ref cursor) => StacksBlockId::from_hex(cursor)
+                .map_err(|_| PageError::InvalidCursor(cursor.clone())),
+            None => Ok(tip.clone()),
+        };
+        let quantity = page
+            .check_no_offset()
+            .and_then(|_| page.get_limit(DEFAULT_HEADERS_PAGE, MAX_HEADERS as u64));
+        match (start, quantity) {
+            (Err(e), _) | (_, Err(e)) => {
+                // bad request
+                let response = HttpResponseType::BadRequestJSON(
+                    response_metadata,
+                    serde_json::Value::String(format!("Invalid request: {}", e)),
+                );
+                response.send(http, fd).and_then(|_| Ok(None))
+            }
+            (Ok(start), Ok(quantity)) => {
+                let stream = match StreamCursor::new_headers(
+                    chainstate,
+                    &start,
+                    quantity as u32,
+                    page.paged,
+                ) {
+                    Ok(stream) => stream,
+                    Err(chain_error::NoSuchBlockError) => {
+                        return ConversationHttp::handle_notfound(
+                            http,
+                            fd,
+                            response_metadata,
+                            format!("No such block {:?}", &start),
+                        );
+                    }
+                    Err(e) => {
+                        // nope -- error trying to check
+                        warn!("Failed to load block header {:?}: {:?}", req, &e);
+                        let response = HttpResponseType::ServerError(
+                            response_metadata,
+                            format!("Failed to query block header {}", start.to_hex()),
+                        );
+                        return response.send(http, fd).and_then(|_| Ok(None));
+                    }
+                };
+                let response = HttpResponseType::HeaderStream(response_metadata);
+                response.send(http, fd).and_then(|_| Ok(Some(stream)))
+            }
+        }
+    }
+
+    /// Handle a GET for the fee statistics of the last `limit` blocks, ending at `tip`.
+    /// Statistics are returned newest block first.  If a page is asked for, it starts at the
+    /// block given by the request's cursor (an index block hash) instead of `tip`.
+    fn handle_get_block_fee_stats<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        tip: &StacksBlockId,
+        page: &PageRequest,
+        chainstate: &StacksChainState,
         canonical_stacks_tip_height: u64,
     ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        let contract_identifier =
-            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+        let start = match page.cursor {
+            Some(ref cursor) => StacksBlockId::from_hex(cursor)
+                .map_err(|_| PageError::InvalidCursor(cursor.clone())),
+            None => Ok(tip.clone()),
+        };
+        let count = page
+            .check_no_offset()
+            .and_then(|_| page.get_limit(DEFAULT_BLOCK_FEE_STATS, MAX_BLOCK_FEE_STATS));
+        let (start, count) = match (start, count) {
+            (Ok(start), Ok(count)) => (start, count),
+            (Err(e), _) | (_, Err(e)) => {
+                let response = HttpResponseType::BadRequestJSON(
+                    response_metadata,
+                    serde_json::Value::String(format!("Invalid request: {}", e)),
+                );
+                return response.send(http, fd);
+            }
+        };
 
         let response =
-            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                clarity_tx.with_clarity_db_readonly(|clarity_db| {
-                    let key = ClarityDatabase::make_key_for_data_map_entry(
-                        &contract_identifier,
-                        map_name,
-                        key,
-                    );
-                    let (value_hex, marf_proof): (String, _) = if with_proof {
-                        clarity_db
-                            .get_with_proof(&key)
-                            .map(|(a, b)| (a, Some(format!("0x{}", to_hex(&b)))))
-                            .unwrap_or_else(|| {
-                                test_debug!("No value for '{}' in {}", &key, tip);
-                                (Value::none().serialize_to_hex(), Some("".into()))
-                            })
+            match StacksChainState::get_block_fee_stats_page(chainstate.db(), &start, count) {
+                Ok((fee_stats, next)) => {
+                    let fee_stats = if page.paged {
+                        RPCList::Page(RPCPage::new(
+                            fee_stats,
+                            count,
+                            0,
+                            None,
+                            next.map(|block_id| block_id.to_hex()),
+                        ))
                     } else {
-                        clarity_db.get(&key).map(|a| (a, None)).unwrap_or_else(|| {
-                            test_debug!("No value for '{}' in {}", &key, tip);
-                            (Value::none().serialize_to_hex(), None)
-                        })
+                        RPCList::Items(fee_stats)
                     };
-
-                    let data = format!("0x{}", value_hex);
-                    MapEntryResponse { data, marf_proof }
-                })
-            }) {
-                Ok(Some(data)) => HttpResponseType::GetMapEntry(response_metadata, data),
-                Ok(None) | Err(_) => {
-                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
+                    HttpResponseType::BlockFeeStats(response_metadata, fee_stats)
+                }
+                Err(e) => {
+                    warn!("Failed to load block fee stats {:?}: {:?}", req, &e);
+                    HttpResponseType::ServerError(
+                        response_metadata,
+                        format!("Failed to query block fee stats at {}", start.to_hex()),
+                    )
                 }
             };
-
-        response.send(http, fd).map(|_| ())
+        response.send(http, fd)
     }
 
-    /// Handle a POST to run a read-only function call with the given parameters on the given chain
-    /// tip.  Returns the result of the function call.  Returns a CallReadOnlyResponse on success.
-    fn handle_readonly_function_call<W: Write>(
+    /// Handle a GET for the STX supply at the block at `height` in the fork ending at `tip`
+    fn handle_get_supply<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
         sortdb: &SortitionDB,
         chainstate: &mut StacksChainState,
         tip: &StacksBlockId,
-        contract_addr: &StacksAddress,
-        contract_name: &ContractName,
-        function: &ClarityName,
-        sender: &PrincipalData,
-        sponsor: Option<&PrincipalData>,
-        args: &[Value],
-        options: &ConnectionOptions,
+        height: u64,
+        burnchain: &Burnchain,
         canonical_stacks_tip_height: u64,
     ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        let contract_identifier =
-            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
-
-        let args: Vec<_> = args
-            .iter()
-            .map(|x| SymbolicExpression::atom_value(x.clone()))
-            .collect();
-        let mainnet = chainstate.mainnet;
-        let chain_id = chainstate.chain_id;
-        let mut cost_limit = options.read_only_call_limit.clone();
-        cost_limit.write_length = 0;
-        cost_limit.write_count = 0;
-
-        let data_opt_res =
-            chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                let epoch = clarity_tx.get_epoch();
-                let cost_track = clarity_tx
-                    .with_clarity_db_readonly(|clarity_db| {
-                        LimitedCostTracker::new_mid_block(
-                            mainnet, chain_id, cost_limit, clarity_db, epoch,
-                        )
-                    })
-                    .map_err(|_| {
-                        ClarityRuntimeError::from(InterpreterError::CostContractLoadFailure)
-                    })?;
-
-                let clarity_version = clarity_tx
-                    .with_analysis_db_readonly(|analysis_db| {
-                        analysis_db.get_clarity_version(&contract_identifier)
-                    })
-                    .map_err(|_| {
-                        ClarityRuntimeError::from(CheckErrors::NoSuchContract(format!(
-                            "{}",
-                            &contract_identifier
-                        )))
-                    })?;
-
-                clarity_tx.with_readonly_clarity_env(
-                    mainnet,
-                    chain_id,
-                    clarity_version,
-                    sender.clone(),
-                    sponsor.cloned(),
-                    cost_track,
-                    |env| {
-                        // we want to execute any function as long as no actual writes are made as
-                        // opposed to be limited to purely calling `define-read-only` functions,
-                        // so use `read_only = false`.  This broadens the number of functions that
-                        // can be called, and also circumvents limitations on `define-read-only`
-                        // functions that can not use `contrac-call?`, even when calling other
-                        // read-only functions
-                        env.execute_contract(&contract_identifier, function.as_str(), &args, false)
-                    },
-                )
-            });
-
-        let response = match data_opt_res {
-            Ok(Some(Ok(data))) => HttpResponseType::CallReadOnlyFunction(
+        let response = match chainstate.get_supply_at_height(sortdb, burnchain, tip, height) {
+            Ok(Some(supply)) => HttpResponseType::Supply(response_metadata, supply),
+            Ok(None) => HttpResponseType::NotFound(
                 response_metadata,
-                CallReadOnlyResponse {
-                    okay: true,
-                    result: Some(format!("0x{}", data.serialize_to_hex())),
-                    cause: None,
-                },
+                format!("No supply counters for block at height {}", height),
             ),
-            Ok(Some(Err(e))) => match e {
-                Unchecked(CheckErrors::CostBalanceExceeded(actual_cost, _))
-                    if actual_cost.write_count > 0 =>
-                {
-                    HttpResponseType::CallReadOnlyFunction(
-                        response_metadata,
-                        CallReadOnlyResponse {
-                            okay: false,
-                            result: None,
-                            cause: Some("NotReadOnly".to_string()),
-                        },
-                    )
-                }
-                _ => HttpResponseType::CallReadOnlyFunction(
+            Err(e) => {
+                warn!("Failed to load supply {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(
                     response_metadata,
-                    CallReadOnlyResponse {
-                        okay: false,
-                        result: None,
-                        cause: Some(e.to_string()),
-                    },
-                ),
-            },
-            Ok(None) | Err(_) => {
-                HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
+                    format!("Failed to query supply at {}", tip.to_hex()),
+                )
             }
         };
-        response.send(http, fd).map(|_| ())
+        response.send(http, fd)
     }
 
-    /// Handle a GET to fetch a contract's source code, given the chain tip.  Optionally returns a
-    /// MARF proof as well.
-    fn handle_get_contract_src<W: Write>(
+    /// Handle a GET on the block feed.  Start streaming blocks from the requested height or cursor.
+    /// The response's preamble (but not the frames) will be synchronously written to the fd
+    /// (so use a fd that can buffer!)
+    /// Return a StreamCursor struct for the feed, so we can keep sending blocks as the chain tip
+    /// advances
+    fn handle_stream_blocks<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        sortdb: &SortitionDB,
-        chainstate: &mut StacksChainState,
+        request: &BlockFeedRequest,
         tip: &StacksBlockId,
-        contract_addr: &StacksAddress,
-        contract_name: &ContractName,
-        with_proof: bool,
+        tip_height: u64,
+        chainstate: &StacksChainState,
+        options: &ConnectionOptions,
+    ) -> Result<Option<StreamCursor>, net_error> {
+        let response_metadata = HttpResponseMetadata::from_http_request_type(req, Some(tip_height));
+        let stream = match StreamCursor::new_block_feed(
+            chainstate,
+            request,
+            tip.clone(),
+            tip_height,
+            options.block_feed_heartbeat_interval,
+        ) {
+            Ok(stream) => stream,
+            Err(chain_error::NoSuchBlockError) => {
+                return ConversationHttp::handle_notfound(
+                    http,
+                    fd,
+                    response_metadata,
+                    format!("No such block {:?}", &request.start),
+                );
+            }
+            Err(e) => {
+                warn!("Failed to start block feed {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to start block feed".to_string(),
+                );
+                return response.send(http, fd).and_then(|_| Ok(None));
+            }
+        };
+        let response = HttpResponseType::BlockFeedStream(response_metadata, request.encoding);
+        response.send(http, fd).and_then(|_| Ok(Some(stream)))
+    }
+
+    /// Handle a GET block.  Start streaming the reply.
+    /// The response's preamble (but not the block data) will be synchronously written to the fd
+    /// (so use a fd that can buffer!)
+    /// Return a StreamCursor struct for the block that we're sending, so we can continue to
+    /// make progress sending it.
+    fn handle_getblock<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        index_block_hash: &StacksBlockId,
+        chainstate: &StacksChainState,
         canonical_stacks_tip_height: u64,
-    ) -> Result<(), net_error> {
+    ) -> Result<Option<StreamCursor>, net_error> {
+        monitoring::increment_stx_blocks_served_counter();
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        let contract_identifier =
-            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
-
-        let response =
-            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                clarity_tx.with_clarity_db_readonly(|db| {
-                    let source = db.get_contract_src(&contract_identifier)?;
-                    let contract_commit_key = make_contract_hash_key(&contract_identifier);
-                    let (contract_commit, proof) = if with_proof {
-                        db.get_with_proof::<ContractCommitment>(&contract_commit_key)
-                            .map(|(a, b)| (a, Some(format!("0x{}", to_hex(&b)))))
-                            .expect("BUG: obtained source, but couldn't get contract commit")
-                    } else {
-                        db.get::<ContractCommitment>(&contract_commit_key)
-                            .map(|a| (a, None))
-                            .expect("BUG: obtained source, but couldn't get contract commit")
-                    };
 
-                    let publish_height = contract_commit.block_height;
-                    Some(ContractSrcResponse {
-                        source,
-                        publish_height,
-                        marf_proof: proof,
-                    })
-                })
-            }) {
-                Ok(Some(Some(data))) => HttpResponseType::GetContractSrc(response_metadata, data),
-                Ok(Some(None)) => HttpResponseType::NotFound(
+        // do we have this block?
+        match StacksChainState::has_block_indexed(&chainstate.blocks_path, index_block_hash) {
+            Ok(false) => {
+                return ConversationHttp::handle_notfound(
+                    http,
+                    fd,
                     response_metadata,
-                    "No contract source data found".into(),
-                ),
-                Ok(None) | Err(_) => {
-                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
-                }
-            };
-
-        response.send(http, fd).map(|_| ())
+                    format!("No such block {}", index_block_hash.to_hex()),
+                );
+            }
+            Err(e) => {
+                // nope -- error trying to check
+                warn!("Failed to serve block {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to query block {}", index_block_hash.to_hex()),
+                );
+                response.send(http, fd).and_then(|_| Ok(None))
+            }
+            Ok(true) => {
+                // yup! start streaming it back
+                let stream = StreamCursor::new_block(index_block_hash.clone());
+                let response = HttpResponseType::BlockStream(response_metadata);
+                response.send(http, fd).and_then(|_| Ok(Some(stream)))
+            }
+        }
     }
 
-    /// Handle a GET to fetch whether or not a contract implements a certain trait
-    fn handle_get_is_trait_implemented<W: Write>(
+    /// Handle a GET block weight -- the serialized sizes and execution costs recorded for a
+    /// processed block, its confirmed microblocks, and their transactions.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_block_weight<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        sortdb: &SortitionDB,
-        chainstate: &mut StacksChainState,
-        tip: &StacksBlockId,
-        contract_addr: &StacksAddress,
-        contract_name: &ContractName,
-        trait_id: &TraitIdentifier,
+        index_block_hash: &StacksBlockId,
+        chainstate: &StacksChainState,
         canonical_stacks_tip_height: u64,
     ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        let contract_identifier =
-            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+        let response = match StacksChainState::get_block_weight(chainstate.db(), index_block_hash) {
+            Ok(Some(weight)) => HttpResponseType::BlockWeight(response_metadata, weight),
+            Ok(None) => HttpResponseType::NotFound(
+                response_metadata,
+                format!("No weight recorded for block {}", index_block_hash.to_hex()),
+            ),
+            Err(e) => {
+                warn!(
+                    "Failed to load block weight for {}: {:?}",
+                    index_block_hash, &e
+                );
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to query block {}", index_block_hash.to_hex()),
+                )
+            }
+        };
+        response.send(http, fd)
+    }
 
+    /// Handle a GET staging queue -- every block that has been stored but not processed, and the
+    /// most recent invalid blocks, along with why each one has not been processed.  The entries
+    /// are paged by offset if a page is asked for.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_staging_queue<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        page: &PageRequest,
+        chainstate: &StacksChainState,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let limit = match page
+            .check_no_cursor()
+            .and_then(|_| page.get_limit(DEFAULT_STAGING_QUEUE_PAGE, MAX_STAGING_QUEUE_PAGE))
+        {
+            Ok(limit) => limit,
+            Err(e) => {
+                let response = HttpResponseType::BadRequest(
+                    response_metadata,
+                    format!("Invalid request: {}", e),
+                );
+                return response.send(http, fd);
+            }
+        };
         let response =
-            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                clarity_tx.with_clarity_db_readonly(|db| {
-                    let analysis = db.load_contract_analysis(&contract_identifier)?;
-                    if analysis.implemented_traits.contains(trait_id) {
-                        Some(GetIsTraitImplementedResponse {
-                            is_implemented: true,
-                        })
-                    } else {
-                        let trait_defining_contract =
-                            db.load_contract_analysis(&trait_id.contract_identifier)?;
-                        let trait_definition =
-                            trait_defining_contract.get_defined_trait(&trait_id.name)?;
-                        let is_implemented = analysis
-                            .check_trait_compliance(
-                                &db.get_clarity_epoch_version(),
-                                trait_id,
-                                trait_definition,
-                            )
-                            .is_ok();
-                        Some(GetIsTraitImplementedResponse { is_implemented })
-                    }
-                })
-            }) {
-                Ok(Some(Some(data))) => {
-                    HttpResponseType::GetIsTraitImplemented(response_metadata, data)
-                }
-                Ok(Some(None)) => HttpResponseType::NotFound(
+            match StacksChainState::get_staging_queue(chainstate.db(), get_epoch_time_secs()) {
+                Ok(entries) => HttpResponseType::StagingQueue(
                     response_metadata,
-                    "No contract analysis found or trait definition not found".into(),
+                    RPCList::from_items(entries, page, limit),
                 ),
-                Ok(None) | Err(_) => {
-                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
+                Err(e) => {
+                    warn!("Failed to load staging queue: {:?}", &e);
+                    HttpResponseType::ServerError(
+                        response_metadata,
+                        "Failed to query staging queue".to_string(),
+                    )
                 }
             };
-
-        response.send(http, fd).map(|_| ())
+        response.send(http, fd)
     }
 
-    /// Handle a GET to fetch a contract's analysis data, given the chain tip.  Note that this isn't
-    /// something that's anchored to the blockchain, and can be different across different versions
-    /// of Stacks -- callers must trust the Stacks node to return correct analysis data.
-    /// Callers who don't trust the Stacks node should just fetch the contract source
-    /// code and analyze it offline.
-    fn handle_get_contract_abi<W: Write>(
+    /// Handle a GET microblock forks -- the tree of all known microblock streams built off of an
+    /// anchored block, and which of its children confirmed which prefix of them.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_microblock_forks<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        sortdb: &SortitionDB,
-        chainstate: &mut StacksChainState,
-        tip: &StacksBlockId,
-        contract_addr: &StacksAddress,
-        contract_name: &ContractName,
+        index_block_hash: &StacksBlockId,
+        chainstate: &StacksChainState,
         canonical_stacks_tip_height: u64,
     ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        let contract_identifier =
-            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
-
         let response =
-            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                let epoch = clarity_tx.get_epoch();
-                clarity_tx.with_analysis_db_readonly(|db| {
-                    let contract = db.load_contract(&contract_identifier, &epoch)?;
-                    contract.contract_interface
-                })
-            }) {
-                Ok(Some(Some(data))) => HttpResponseType::GetContractABI(response_metadata, data),
-                Ok(Some(None)) => HttpResponseType::NotFound(
+            match StacksChainState::get_microblock_fork_tree(chainstate.db(), index_block_hash) {
+                Ok(Some(tree)) => HttpResponseType::MicroblockForks(response_metadata, tree),
+                Ok(None) => HttpResponseType::NotFound(
                     response_metadata,
-                    "No contract interface data found".into(),
+                    format!("No such block {}", index_block_hash.to_hex()),
                 ),
-                Ok(None) | Err(_) => {
-                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
+                Err(e) => {
+                    warn!(
+                        "Failed to load microblock forks for {}: {:?}",
+                        index_block_hash, &e
+                    );
+                    HttpResponseType::ServerError(
+                        response_metadata,
+                        format!("Failed to query block {}", index_block_hash.to_hex()),
+                    )
                 }
             };
-
-        response.send(http, fd).map(|_| ())
+        response.send(http, fd)
     }
 
-    /// Handle a GET unconfirmed microblock stream.  Start streaming the reply.
+    /// Handle a GET confirmed microblock stream, by _anchor block hash_.  Start streaming the reply.
     /// The response's preamble (but not the block data) will be synchronously written to the fd
     /// (so use a fd that can buffer!)
     /// Return a StreamCursor struct for the block that we're sending, so we can continue to
     /// make progress sending it.
-    fn handle_getmicroblocks_unconfirmed<W: Write>(
+    fn handle_getmicroblocks_confirmed<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
         index_anchor_block_hash: &StacksBlockId,
-        min_seq: u16,
         chainstate: &StacksChainState,
         canonical_stacks_tip_height: u64,
     ) -> Result<Option<StreamCursor>, net_error> {
+        monitoring::increment_stx_confirmed_micro_blocks_served_counter();
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
 
-        // do we have this unconfirmed microblock stream?
-        match chainstate.has_any_staging_microblock_indexed(index_anchor_block_hash, min_seq) {
+        match chainstate.has_processed_microblocks(index_anchor_block_hash) {
+            Ok(true) => {}
             Ok(false) => {
-                // nope
-                let response = HttpResponseType::NotFound(
+                return ConversationHttp::handle_notfound(
+                    http,
+                    fd,
                     response_metadata,
                     format!(
-                        "No such unconfirmed microblock stream for {} at or after {}",
-                        index_anchor_block_hash.to_hex(),
-                        min_seq
+                        "No such confirmed microblock stream for anchor block {}",
+                        &index_anchor_block_hash
                     ),
                 );
-                response.send(http, fd).and_then(|_| Ok(None))
             }
             Err(e) => {
-                // nope
-                warn!(
-                    "Failed to serve confirmed microblock stream {:?}: {:?}",
-                    req, &e
+                return ConversationHttp::handle_server_error(
+                    http,
+                    fd,
+                    response_metadata,
+                    format!(
+                        "Failed to query confirmed microblock stream {:?}: {:?}",
+                        req, &e
+                    ),
                 );
-                let response = HttpResponseType::ServerError(
+            }
+        }
+
+        match chainstate.get_confirmed_microblock_index_hash(index_anchor_block_hash) {
+            Err(e) => {
+                return ConversationHttp::handle_server_error(
+                    http,
+                    fd,
                     response_metadata,
                     format!(
-                        "Failed to query unconfirmed microblock stream for {} at or after {}",
-                        index_anchor_block_hash.to_hex(),
-                        min_seq
+                        "Failed to serve confirmed microblock stream {:?}: {:?}",
+                        req, &e
                     ),
                 );
-                response.send(http, fd).and_then(|_| Ok(None))
             }
-            Ok(true) => {
-                // yup! start streaming it back
-                let (response, stream_opt) = match StreamCursor::new_microblock_unconfirmed(
+            Ok(None) => {
+                return ConversationHttp::handle_notfound(
+                    http,
+                    fd,
+                    response_metadata,
+                    format!(
+                        "No such confirmed microblock stream for anchor block {}",
+                        &index_anchor_block_hash
+                    ),
+                );
+            }
+            Ok(Some(tail_index_microblock_hash)) => {
+                let (response, stream_opt) = match StreamCursor::new_microblock_confirmed(
                     chainstate,
-                    index_anchor_block_hash.clone(),
-                    min_seq,
+                    tail_index_microblock_hash.clone(),
                 ) {
                     Ok(stream) => (
                         HttpResponseType::MicroblockStream(response_metadata),
@@ -1774,23 +2179,23 @@ impl ConversationHttp {
                         HttpResponseType::NotFound(
                             response_metadata,
                             format!(
-                                "No such unconfirmed microblock stream starting with {}",
-                                index_anchor_block_hash.to_hex()
+                                "No such confirmed microblock stream ending with {}",
+                                tail_index_microblock_hash.to_hex()
                             ),
                         ),
                         None,
                     ),
                     Err(_e) => {
                         debug!(
-                            "Failed to load unconfirmed microblock stream {}: {:?}",
-                            &index_anchor_block_hash, &_e
+                            "Failed to load confirmed microblock stream {}: {:?}",
+                            &tail_index_microblock_hash, &_e
                         );
                         (
                             HttpResponseType::ServerError(
                                 response_metadata,
                                 format!(
-                                    "Failed to query unconfirmed microblock stream {}",
-                                    index_anchor_block_hash.to_hex()
+                                    "Failed to query confirmed microblock stream {}",
+                                    tail_index_microblock_hash.to_hex()
                                 ),
                             ),
                             None,
@@ -1802,2496 +2207,5858 @@ impl ConversationHttp {
         }
     }
 
-    /// Handle a GET unconfirmed transaction.
-    /// The response will be synchronously written to the fd.
-    fn handle_gettransaction_unconfirmed<W: Write>(
+    /// Handle a GET confirmed microblock stream, by last _index microblock hash_ in the stream.  Start streaming the reply.
+    /// The response's preamble (but not the block data) will be synchronously written to the fd
+    /// (so use a fd that can buffer!)
+    /// Return a StreamCursor struct for the block that we're sending, so we can continue to
+    /// make progress sending it.
+    fn handle_getmicroblocks_indexed<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
+        tail_index_microblock_hash: &StacksBlockId,
         chainstate: &StacksChainState,
-        mempool: &MemPoolDB,
-        txid: &Txid,
         canonical_stacks_tip_height: u64,
-    ) -> Result<(), net_error> {
+    ) -> Result<Option<StreamCursor>, net_error> {
+        monitoring::increment_stx_micro_blocks_served_counter();
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
 
-        // present in the unconfirmed state?
-        if let Some(ref unconfirmed) = chainstate.unconfirmed_state.as_ref() {
-            if let Some((transaction, mblock_hash, seq)) =
-                unconfirmed.get_unconfirmed_transaction(txid)
-            {
-                let response = HttpResponseType::UnconfirmedTransaction(
+        // do we have this processed microblock stream?
+        match StacksChainState::has_processed_microblocks_indexed(
+            chainstate.db(),
+            tail_index_microblock_hash,
+        ) {
+            Ok(false) => {
+                // nope
+                return ConversationHttp::handle_notfound(
+                    http,
+                    fd,
                     response_metadata,
-                    UnconfirmedTransactionResponse {
-                        status: UnconfirmedTransactionStatus::Microblock {
-                            block_hash: mblock_hash,
-                            seq: seq,
-                        },
-                        tx: to_hex(&transaction.serialize_to_vec()),
-                    },
+                    format!(
+                        "No such confirmed microblock stream ending with {}",
+                        &tail_index_microblock_hash
+                    ),
                 );
-                return response.send(http, fd).map(|_| ());
             }
-        }
-
-        // present in the mempool?
-        if let Some(txinfo) = MemPoolDB::get_tx(mempool.conn(), txid)? {
-            let response = HttpResponseType::UnconfirmedTransaction(
-                response_metadata,
-                UnconfirmedTransactionResponse {
-                    status: UnconfirmedTransactionStatus::Mempool,
-                    tx: to_hex(&txinfo.tx.serialize_to_vec()),
-                },
-            );
-            return response.send(http, fd).map(|_| ());
-        }
-
-        // not found
-        let response = HttpResponseType::NotFound(
-            response_metadata,
-            format!("No such unconfirmed transaction {}", txid),
-        );
-        return response.send(http, fd).map(|_| ());
-    }
-
-    /// Load up the canonical Stacks chain tip.  Note that this is subject to both burn chain block
-    /// Stacks block availability -- different nodes with different partial replicas of the Stacks chain state
-    /// will return different values here.
-    ///
-    /// # Warn
-    /// - There is a potential race condition. If this function is loading the latest unconfirmed
-    /// tip, that tip may get invalidated by the time it is used in `maybe_read_only_clarity_tx`,
-    /// which is used to load clarity state at a particular tip (which would lead to a 404 error).
-    /// If this race condition occurs frequently, we can modify `maybe_read_only_clarity_tx` to
-    /// re-load the unconfirmed chain tip. Refer to issue #2997.
-    ///
-    /// # Inputs
-    /// - `tip_req` is given by the HTTP request as the optional query parameter for the chain tip
-    /// hash.  It will be UseLatestAnchoredTip if there was no parameter given. If it is set to
-    /// `latest`, the parameter will be set to UseLatestUnconfirmedTip.
-    fn handle_load_stacks_chain_tip<W: Write>(
-        http: &mut StacksHttp,
-        fd: &mut W,
-        req: &HttpRequestType,
-        tip_req: &TipRequest,
-        sortdb: &SortitionDB,
-        chainstate: &mut StacksChainState,
-        canonical_stacks_tip_height: u64,
-    ) -> Result<Option<StacksBlockId>, net_error> {
-        match tip_req {
-            TipRequest::UseLatestUnconfirmedTip => {
-                let unconfirmed_chain_tip_opt = match &mut chainstate.unconfirmed_state {
-                    Some(unconfirmed_state) => {
-                        match unconfirmed_state.get_unconfirmed_state_if_exists() {
-                            Ok(res) => res,
-                            Err(msg) => {
-                                let response_metadata =
-                                    HttpResponseMetadata::from_http_request_type(
-                                        req,
-                                        Some(canonical_stacks_tip_height),
-                                    );
-                                let response = HttpResponseType::NotFound(response_metadata, msg);
-                                return response.send(http, fd).and_then(|_| Ok(None));
-                            }
-                        }
-                    }
-                    None => None,
-                };
-
-                if let Some(unconfirmed_chain_tip) = unconfirmed_chain_tip_opt {
-                    Ok(Some(unconfirmed_chain_tip))
-                } else {
-                    match chainstate.get_stacks_chain_tip(sortdb)? {
-                        Some(tip) => Ok(Some(StacksBlockHeader::make_index_block_hash(
-                            &tip.consensus_hash,
-                            &tip.anchored_block_hash,
-                        ))),
-                        None => {
-                            let response_metadata = HttpResponseMetadata::from_http_request_type(
-                                req,
-                                Some(canonical_stacks_tip_height),
-                            );
-                            warn!("Failed to load Stacks chain tip");
-                            let response = HttpResponseType::NotFound(
+            Err(e) => {
+                // nope
+                return ConversationHttp::handle_server_error(
+                    http,
+                    fd,
+                    response_metadata,
+                    format!(
+                        "Failed to serve confirmed microblock stream {:?}: {:?}",
+                        req, &e
+                    ),
+                );
+            }
+            Ok(true) => {
+                // yup! start streaming it back
+                let (response, stream_opt) = match StreamCursor::new_microblock_confirmed(
+                    chainstate,
+                    tail_index_microblock_hash.clone(),
+                ) {
+                    Ok(stream) => (
+                        HttpResponseType::MicroblockStream(response_metadata),
+                        Some(stream),
+                    ),
+                    Err(chain_error::NoSuchBlockError) => (
+                        HttpResponseType::NotFound(
+                            response_metadata,
+                            format!(
+                                "No such confirmed microblock stream ending with {}",
+                                tail_index_microblock_hash.to_hex()
+                            ),
+                        ),
+                        None,
+                    ),
+                    Err(_e) => {
+                        debug!(
+                            "Failed to load confirmed indexed microblock stream {}: {:?}",
+                            &tail_index_microblock_hash, &_e
+                        );
+                        (
+                            HttpResponseType::ServerError(
                                 response_metadata,
-                                format!("Failed to load Stacks chain tip"),
-                            );
-                            response.send(http, fd).and_then(|_| Ok(None))
-                        }
+                                format!(
+                                    "Failed to query confirmed microblock stream {}",
+                                    tail_index_microblock_hash.to_hex()
+                                ),
+                            ),
+                            None,
+                        )
                     }
-                }
+                };
+                response.send(http, fd).and_then(|_| Ok(stream_opt))
             }
-            TipRequest::SpecificTip(tip) => Ok(Some(*tip).clone()),
-            TipRequest::UseLatestAnchoredTip => match chainstate.get_stacks_chain_tip(sortdb)? {
-                Some(tip) => Ok(Some(StacksBlockHeader::make_index_block_hash(
-                    &tip.consensus_hash,
-                    &tip.anchored_block_hash,
-                ))),
-                None => {
-                    let response_metadata = HttpResponseMetadata::from_http_request_type(
-                        req,
-                        Some(canonical_stacks_tip_height),
-                    );
-                    warn!("Failed to load Stacks chain tip");
-                    let response = HttpResponseType::ServerError(
-                        response_metadata,
-                        format!("Failed to load Stacks chain tip"),
-                    );
-                    response.send(http, fd).and_then(|_| Ok(None))
-                }
-            },
         }
     }
 
-    fn handle_load_stacks_chain_tip_hashes<W: Write>(
+    /// Handle a GET token transfer cost.  Reply the entire response.
+    /// TODO: accurately estimate the cost/length fee for token transfers, based on mempool
+    /// pressure.
+    fn handle_token_transfer_cost<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        tip: StacksBlockId,
-        chainstate: &StacksChainState,
         canonical_stacks_tip_height: u64,
-    ) -> Result<Option<(ConsensusHash, BlockHeaderHash)>, net_error> {
-        match chainstate.get_block_header_hashes(&tip)? {
-            Some((ch, bl)) => {
-                return Ok(Some((ch, bl)));
-            }
-            None => {}
-        }
+    ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        warn!("Failed to load Stacks chain tip");
-        let response = HttpResponseType::ServerError(
-            response_metadata,
-            format!("Failed to load Stacks chain tip"),
-        );
-        response.send(http, fd).and_then(|_| Ok(None))
+
+        // todo -- need to actually estimate the cost / length for token transfers
+        //   right now, it just uses the minimum.
+        let fee = MINIMUM_TX_FEE_RATE_PER_BYTE;
+        let response = HttpResponseType::TokenTransferCost(response_metadata, fee);
+        response.send(http, fd).map(|_| ())
     }
 
-    fn handle_post_fee_rate_estimate<W: Write>(
-        http: &mut StacksHttp,
-        fd: &mut W,
-        req: &HttpRequestType,
-        handler_args: &RPCHandlerArgs,
+    /// Load an account's balance and nonce at the given chain tip.  Optionally supplies a MARF
+    /// proof for each account detail.
+    /// Also returns the unlocked balance as a number, so it can be compared to other chain tips.
+    fn load_account_entry(
         sortdb: &SortitionDB,
-        tx: &TransactionPayload,
-        estimated_len: u64,
-        canonical_stacks_tip_height: u64,
-    ) -> Result<(), net_error> {
-        let response_metadata =
-            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
-        let stacks_epoch = SortitionDB::get_stacks_epoch(sortdb.conn(), tip.block_height)?
-                .ok_or_else(|| {
-                    warn!(
-                        "Failed to get fee rate estimate because could not load Stacks epoch for canonical burn height = {}",
-                        tip.block_height
-                    );
-                    net_error::ChainstateError("Could not load Stacks epoch for canonical burn height".into())
-                })?;
-        if let Some((cost_estimator, fee_estimator, metric)) = handler_args.get_estimators_ref() {
-            let estimated_cost = match cost_estimator.estimate_cost(tx, &stacks_epoch.epoch_id) {
-                Ok(x) => x,
-                Err(e) => {
-                    debug!(
-                        "Estimator RPC endpoint failed to estimate tx: {}",
-                        tx.name()
-                    );
-                    return HttpResponseType::BadRequestJSON(response_metadata, e.into_json())
-                        .send(http, fd);
-                }
-            };
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        account: &PrincipalData,
+        with_proof: bool,
+    ) -> Result<Option<(AccountEntryResponse, u128)>, chain_error> {
+        chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+            clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                let key = ClarityDatabase::make_key_for_account_balance(&account);
+                let burn_block_height = clarity_db.get_current_burnchain_block_height() as u64;
+                let v1_unlock_height = clarity_db.get_v1_unlock_height();
+                let v2_unlock_height = clarity_db.get_v2_unlock_height();
+                let (balance, balance_proof) = if with_proof {
+                    clarity_db
+                        .get_with_proof::<STXBalance>(&key)
+                        .map(|(a, b)| (a, Some(format!("0x{}", to_hex(&b)))))
+                        .unwrap_or_else(|| (STXBalance::zero(), Some("".into())))
+                } else {
+                    clarity_db
+                        .get::<STXBalance>(&key)
+                        .map(|a| (a, None))
+                        .unwrap_or_else(|| (STXBalance::zero(), None))
+                };
 
-            let scalar_cost =
-                metric.from_cost_and_len(&estimated_cost, &stacks_epoch.block_limit, estimated_len);
-            let fee_rates = match fee_estimator.get_rate_estimates() {
-                Ok(x) => x,
-                Err(e) => {
-                    debug!(
-                        "Estimator RPC endpoint failed to estimate fees for tx: {}",
-                        tx.name()
-                    );
-                    return HttpResponseType::BadRequestJSON(response_metadata, e.into_json())
-                        .send(http, fd);
-                }
-            };
+                let key = ClarityDatabase::make_key_for_account_nonce(&account);
+                let (nonce, nonce_proof) = if with_proof {
+                    clarity_db
+                        .get_with_proof(&key)
+                        .map(|(a, b)| (a, Some(format!("0x{}", to_hex(&b)))))
+                        .unwrap_or_else(|| (0, Some("".into())))
+                } else {
+                    clarity_db
+                        .get(&key)
+                        .map(|a| (a, None))
+                        .unwrap_or_else(|| (0, None))
+                };
 
-            let mut estimations = RPCFeeEstimate::estimate_fees(scalar_cost, fee_rates).to_vec();
+                let unlocked = balance.get_available_balance_at_burn_block(
+                    burn_block_height,
+                    v1_unlock_height,
+                    v2_unlock_height,
+                );
+                let (locked, unlock_height) = balance.get_locked_balance_at_burn_block(
+                    burn_block_height,
+                    v1_unlock_height,
+                    v2_unlock_height,
+                );
 
-            let minimum_fee = estimated_len * MINIMUM_TX_FEE_RATE_PER_BYTE;
+                let balance = format!("0x{}", to_hex(&unlocked.to_be_bytes()));
+                let locked = format!("0x{}", to_hex(&locked.to_be_bytes()));
 
-            for estimate in estimations.iter_mut() {
-                if estimate.fee < minimum_fee {
-                    estimate.fee = minimum_fee;
-                }
-            }
+                (
+                    AccountEntryResponse {
+                        balance,
+                        locked,
+                        unlock_height,
+                        nonce,
+                        balance_proof,
+                        nonce_proof,
+                        unanchored: None,
+                    },
+                    unlocked,
+                )
+            })
+        })
+    }
 
-            let response = HttpResponseType::TransactionFeeEstimation(
-                response_metadata,
-                RPCFeeEstimateResponse {
-                    estimated_cost,
-                    estimations,
-                    estimated_cost_scalar: scalar_cost,
-                    cost_scalar_change_by_byte: metric.change_per_byte(),
-                },
-            );
-            response.send(http, fd)
+    /// Load the state of an account once the microblocks confirmed on top of `anchored_tip` are
+    /// applied, and compare it to the anchored state.  If there is no unconfirmed state for
+    /// `anchored_tip`, then the account's unanchored state is its anchored state.
+    fn load_unanchored_account_entry(
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        anchored_tip: &StacksBlockId,
+        account: &PrincipalData,
+        anchored_entry: &AccountEntryResponse,
+        anchored_balance: u128,
+    ) -> Result<UnanchoredAccountEntryResponse, chain_error> {
+        let unconfirmed_opt = chainstate
+            .unconfirmed_state
+            .as_ref()
+            .filter(|unconfirmed| {
+                unconfirmed.is_readable() && unconfirmed.confirmed_chain_tip == *anchored_tip
+            })
+            .map(|unconfirmed| {
+                (
+                    unconfirmed.unconfirmed_chain_tip.clone(),
+                    unconfirmed
+                        .last_mblock
+                        .as_ref()
+                        .map(|mblock| (mblock.block_hash(), unconfirmed.last_mblock_seq)),
+                )
+            });
+
+        let (unanchored_entry, unanchored_balance, last_mblock) = match unconfirmed_opt {
+            Some((unconfirmed_tip, last_mblock)) => match ConversationHttp::load_account_entry(
+                sortdb,
+                chainstate,
+                &unconfirmed_tip,
+                account,
+                false,
+            )? {
+                Some((entry, balance)) => (entry, balance, last_mblock),
+                None => (anchored_entry.clone(), anchored_balance, None),
+            },
+            None => (anchored_entry.clone(), anchored_balance, None),
+        };
+
+        let balance_delta = if unanchored_balance >= anchored_balance {
+            format!("{}", unanchored_balance - anchored_balance)
         } else {
-            debug!("Fee and cost estimation not configured on this stacks node");
-            let response = HttpResponseType::BadRequestJSON(
-                response_metadata,
-                json!({
-                    "error": "Fee and Cost Estimation not configured on this Stacks node",
-                    "reason": "CostEstimationDisabled",
-                }),
-            );
-            response.send(http, fd)
-        }
+            format!("-{}", anchored_balance - unanchored_balance)
+        };
+
+        Ok(UnanchoredAccountEntryResponse {
+            anchored_tip: anchored_tip.clone(),
+            microblock_tip: last_mblock.as_ref().map(|(hash, _)| hash.clone()),
+            microblock_seq: last_mblock.as_ref().map(|(_, seq)| *seq),
+            balance: unanchored_entry.balance,
+            locked: unanchored_entry.locked,
+            unlock_height: unanchored_entry.unlock_height,
+            nonce: unanchored_entry.nonce,
+            balance_delta,
+            nonce_delta: unanchored_entry.nonce.saturating_sub(anchored_entry.nonce),
+        })
     }
 
-    /// Handle a transaction.  Directly submit it to the mempool so the client can see any
-    /// rejection reasons up-front (different from how the peer network handles it).  Indicate
-    /// whether or not the transaction was accepted (and thus needs to be forwarded) in the return
-    /// value.
-    fn handle_post_transaction<W: Write>(
+    /// Handle a GET on an existing account, given the current chain tip.  Optionally supplies a
+    /// MARF proof for each account detail loaded from the chain tip.
+    /// If `unanchored` is set, then the account's state with the unconfirmed microblock stream
+    /// applied is reported as well.  In this mode, the top-level account details are always
+    /// loaded from the anchored tip (even if the request asked for the unconfirmed tip).
+    fn handle_get_account_entry<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        chainstate: &mut StacksChainState,
         sortdb: &SortitionDB,
-        consensus_hash: ConsensusHash,
-        block_hash: BlockHeaderHash,
-        mempool: &mut MemPoolDB,
-        tx: StacksTransaction,
-        atlasdb: &mut AtlasDB,
-        attachment: Option<Attachment>,
-        event_observer: Option<&dyn MemPoolEventDispatcher>,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        account: &PrincipalData,
+        with_proof: bool,
+        unanchored: bool,
         canonical_stacks_tip_height: u64,
-        ast_rules: ASTRules,
-    ) -> Result<bool, net_error> {
-        let txid = tx.txid();
+    ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        let (response, accepted) = if mempool.has_tx(&txid) {
-            debug!("Mempool already has POSTed transaction {}", &txid);
-            (
-                HttpResponseType::TransactionID(response_metadata, txid),
-                false,
-            )
+
+        let anchored_tip = if unanchored {
+            chainstate
+                .unconfirmed_state
+                .as_ref()
+                .filter(|unconfirmed| unconfirmed.unconfirmed_chain_tip == *tip)
+                .map(|unconfirmed| unconfirmed.confirmed_chain_tip.clone())
+                .unwrap_or_else(|| tip.clone())
         } else {
-            let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
-            let stacks_epoch = sortdb
-                .index_conn()
-                .get_stacks_epoch(tip.block_height as u32)
-                .ok_or_else(|| {
-                    warn!(
-                        "Failed to store transaction because could not load Stacks epoch for canonical burn height = {}",
-                        tip.block_height
-                    );
-                    net_error::ChainstateError("Could not load Stacks epoch for canonical burn height".into())
-                })?;
+            tip.clone()
+        };
 
-            if Relayer::do_static_problematic_checks()
-                && !Relayer::static_check_problematic_relayed_tx(
-                    chainstate.mainnet,
-                    stacks_epoch.epoch_id,
-                    &tx,
-                    ast_rules,
-                )
-                .is_ok()
-            {
-                debug!(
-                    "Transaction {} is problematic in rules {:?}; will not store or relay",
-                    &tx.txid(),
-                    ast_rules
-                );
-                (
-                    HttpResponseType::TransactionID(response_metadata, txid),
-                    false,
-                )
-            } else {
-                match mempool.submit(
-                    chainstate,
-                    sortdb,
-                    &consensus_hash,
-                    &block_hash,
-                    &tx,
-                    event_observer,
-                    &stacks_epoch.block_limit,
-                    &stacks_epoch.epoch_id,
-                ) {
-                    Ok(_) => {
-                        debug!("Mempool accepted POSTed transaction {}", &txid);
-                        (
-                            HttpResponseType::TransactionID(response_metadata, txid),
-                            true,
-                        )
-                    }
-                    Err(e) => {
-                        debug!("Mempool rejected POSTed transaction {}: {:?}", &txid, &e);
-                        (
-                            HttpResponseType::BadRequestJSON(response_metadata, e.into_json(&txid)),
-                            false,
-                        )
+        let response = match ConversationHttp::load_account_entry(
+            sortdb,
+            chainstate,
+            &anchored_tip,
+            account,
+            with_proof,
+        ) {
+            Ok(Some((mut data, anchored_balance))) => {
+                if unanchored {
+                    match ConversationHttp::load_unanchored_account_entry(
+                        sortdb,
+                        chainstate,
+                        &anchored_tip,
+                        account,
+                        &data,
+                        anchored_balance,
+                    ) {
+                        Ok(unanchored_data) => {
+                            data.unanchored = Some(unanchored_data);
+                            HttpResponseType::GetAccount(response_metadata, data)
+                        }
+                        Err(e) => {
+                            warn!("Failed to load unanchored account {}: {:?}", account, &e);
+                            HttpResponseType::ServerError(
+                                response_metadata,
+                                "Failed to load unanchored account state".into(),
+                            )
+                        }
                     }
+                } else {
+                    HttpResponseType::GetAccount(response_metadata, data)
                 }
             }
-        };
-
-        if let Some(ref attachment) = attachment {
-            if let TransactionPayload::ContractCall(ref contract_call) = tx.payload {
-                if atlasdb
-                    .should_keep_attachment(&contract_call.to_clarity_contract_id(), &attachment)
-                {
-                    atlasdb
-                        .insert_uninstantiated_attachment(attachment)
-                        .map_err(|e| net_error::DBError(e))?;
-                }
+            Ok(None) | Err(_) => {
+                HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
             }
-        }
+        };
 
-        response.send(http, fd).and_then(|_| Ok(accepted))
+        response.send(http, fd).map(|_| ())
     }
 
-    /// Handle a block.  Directly submit a Stacks block to this node's chain state.
-    /// Indicate whether or not the block was accepted (i.e. it was new, and valid)
-    fn handle_post_block<W: Write>(
+    /// Handle a GET on a smart contract's data var, given the current chain tip.  Optionally
+    /// supplies a MARF proof for the value.
+    fn handle_get_data_var<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
         sortdb: &SortitionDB,
         chainstate: &mut StacksChainState,
-        consensus_hash: &ConsensusHash,
-        block: &StacksBlock,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        var_name: &ClarityName,
+        with_proof: bool,
         canonical_stacks_tip_height: u64,
-    ) -> Result<bool, net_error> {
+    ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        // is this a consensus hash we recognize?
-        let (response, accepted) =
-            match SortitionDB::get_sortition_id_by_consensus(&sortdb.conn(), consensus_hash) {
-                Ok(Some(_)) => {
-                    // we recognize this consensus hash
-                    let ic = sortdb.index_conn();
-                    match Relayer::process_new_anchored_block(
-                        &ic,
-                        chainstate,
-                        consensus_hash,
-                        block,
-                        0,
-                    ) {
-                        Ok(true) => {
-                            debug!(
-                                "Accepted Stacks block {}/{}",
-                                consensus_hash,
-                                &block.block_hash()
-                            );
-                            (
-                                HttpResponseType::StacksBlockAccepted(
-                                    response_metadata,
-                                    StacksBlockHeader::make_index_block_hash(
-                                        consensus_hash,
-                                        &block.block_hash(),
-                                    ),
-                                    true,
-                                ),
-                                true,
-                            )
-                        }
-                        Ok(false) => {
-                            debug!(
-                                "Did not accept Stacks block {}/{}",
-                                consensus_hash,
-                                &block.block_hash()
-                            );
-                            (
-                                HttpResponseType::StacksBlockAccepted(
-                                    response_metadata,
-                                    StacksBlockHeader::make_index_block_hash(
-                                        consensus_hash,
-                                        &block.block_hash(),
-                                    ),
-                                    false,
-                                ),
-                                false,
-                            )
-                        }
-                        Err(e) => {
-                            error!(
-                                "Failed to process anchored block {}/{}: {:?}",
-                                consensus_hash,
-                                &block.block_hash(),
-                                &e
-                            );
-                            (
-                                HttpResponseType::ServerError(
-                                    response_metadata,
-                                    format!(
-                                        "Failed to process anchored block {}/{}: {:?}",
-                                        consensus_hash,
-                                        &block.block_hash(),
-                                        &e
-                                    ),
-                                ),
-                                false,
-                            )
-                        }
-                    }
-                }
-                Ok(None) => {
-                    debug!(
-                        "Unrecognized consensus hash {} for block {}",
-                        consensus_hash,
-                        &block.block_hash()
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                    let key = ClarityDatabase::make_key_for_trip(
+                        &contract_identifier,
+                        StoreType::Variable,
+                        var_name,
                     );
-                    (
-                        HttpResponseType::NotFound(
-                            response_metadata,
-                            format!("No such consensus hash '{}'", consensus_hash),
-                        ),
-                        false,
-                    )
+
+                    let (value_hex, marf_proof): (String, _) = if with_proof {
+                        clarity_db
+                            .get_with_proof(&key)
+                            .map(|(a, b)| (a, Some(format!("0x{}", to_hex(&b)))))?
+                    } else {
+                        clarity_db.get(&key).map(|a| (a, None))?
+                    };
+
+                    let data = format!("0x{}", value_hex);
+                    Some(DataVarResponse { data, marf_proof })
+                })
+            }) {
+                Ok(Some(Some(data))) => HttpResponseType::GetDataVar(response_metadata, data),
+                Ok(Some(None)) => {
+                    HttpResponseType::NotFound(response_metadata, "Data var not found".into())
                 }
-                Err(e) => {
-                    error!(
-                        "Failed to query sortition ID by consensus '{}'",
-                        consensus_hash
-                    );
-                    (
-                        HttpResponseType::ServerError(
-                            response_metadata,
-                            format!(
-                                "Failed to query sortition ID for consensus hash '{}': {:?}",
-                                consensus_hash, &e
-                            ),
-                        ),
-                        false,
-                    )
+                Ok(None) | Err(_) => {
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
                 }
             };
-        response.send(http, fd).and_then(|_| Ok(accepted))
+
+        response.send(http, fd).map(|_| ())
     }
 
-    /// Handle a microblock.  Directly submit it to the microblock store so the client can see any
-    /// rejection reasons up-front (different from how the peer network handles it).  Indicate
-    /// whether or not the microblock was accepted (and thus needs to be forwarded) in the return
-    /// value.
-    fn handle_post_microblock<W: Write>(
+    /// Handle a GET on a smart contract's data map, given the current chain tip.  Optionally
+    /// supplies a MARF proof for the value.
+    fn handle_get_map_entry<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        consensus_hash: &ConsensusHash,
-        block_hash: &BlockHeaderHash,
         sortdb: &SortitionDB,
         chainstate: &mut StacksChainState,
-        microblock: &StacksMicroblock,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        map_name: &ClarityName,
+        key: &Value,
+        with_proof: bool,
         canonical_stacks_tip_height: u64,
-    ) -> Result<bool, net_error> {
+    ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
 
-        // make sure we can accept this
-        let ch_sn = match SortitionDB::get_block_snapshot_consensus(sortdb.conn(), consensus_hash) {
-            Ok(Some(sn)) => sn,
-            Ok(None) => {
-                let resp = HttpResponseType::NotFound(
-                    response_metadata,
-                    "No such consensus hash".to_string(),
-                );
-                return resp.send(http, fd).and_then(|_| Ok(false));
-            }
-            Err(e) => {
-                let resp = HttpResponseType::BadRequestJSON(
-                    response_metadata,
-                    chain_error::DBError(e).into_json(),
-                );
-                return resp.send(http, fd).and_then(|_| Ok(false));
-            }
-        };
-
-        let sort_handle = sortdb.index_handle(&ch_sn.sortition_id);
-        let parent_block_snapshot =
-            Relayer::get_parent_stacks_block_snapshot(&sort_handle, consensus_hash, block_hash)?;
-        let ast_rules =
-            SortitionDB::get_ast_rules(&sort_handle, parent_block_snapshot.block_height)?;
-        let epoch_id =
-            SortitionDB::get_stacks_epoch(&sort_handle, parent_block_snapshot.block_height)?
-                .expect("FATAL: no epoch defined")
-                .epoch_id;
-
-        let (response, accepted) = if !Relayer::static_check_problematic_relayed_microblock(
-            chainstate.mainnet,
-            epoch_id,
-            microblock,
-            ast_rules,
-        ) {
-            info!("Microblock {} from {}/{} is problematic; will not store or relay it, nor its descendants", &microblock.block_hash(), consensus_hash, &block_hash);
-            (
-                // NOTE: txid is ignored in chainstate error .into_json()
-                HttpResponseType::BadRequestJSON(
-                    response_metadata,
-                    chain_error::ProblematicTransaction(Txid([0u8; 32])).into_json(),
-                ),
-                false,
-            )
-        } else {
-            match chainstate.preprocess_streamed_microblock(consensus_hash, block_hash, microblock)
-            {
-                Ok(accepted) => {
-                    if accepted {
-                        debug!(
-                            "Accepted uploaded microblock {}/{}-{}",
-                            &consensus_hash,
-                            &block_hash,
-                            &microblock.block_hash()
-                        );
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                    let key = ClarityDatabase::make_key_for_data_map_entry(
+                        &contract_identifier,
+                        map_name,
+                        key,
+                    );
+                    let (value_hex, marf_proof): (String, _) = if with_proof {
+                        clarity_db
+                            .get_with_proof(&key)
+                            .map(|(a, b)| (a, Some(format!("0x{}", to_hex(&b)))))
+                            .unwrap_or_else(|| {
+                                test_debug!("No value for '{}' in {}", &key, tip);
+                                (Value::none().serialize_to_hex(), Some("".into()))
+                            })
                     } else {
-                        debug!(
-                            "Did not accept microblock {}/{}-{}",
-                            &consensus_hash,
-                            &block_hash,
-                            &microblock.block_hash()
-                        );
-                    }
+                        clarity_db.get(&key).map(|a| (a, None)).unwrap_or_else(|| {
+                            test_debug!("No value for '{}' in {}", &key, tip);
+                            (Value::none().serialize_to_hex(), None)
+                        })
+                    };
 
-                    (
-                        HttpResponseType::MicroblockHash(
-                            response_metadata,
-                            microblock.block_hash(),
-                        ),
-                        accepted,
-                    )
+                    let data = format!("0x{}", value_hex);
+                    MapEntryResponse { data, marf_proof }
+                })
+            }) {
+                Ok(Some(data)) => HttpResponseType::GetMapEntry(response_metadata, data),
+                Ok(None) | Err(_) => {
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
                 }
-                Err(e) => (
-                    HttpResponseType::BadRequestJSON(response_metadata, e.into_json()),
-                    false,
-                ),
-            }
-        };
+            };
 
-        response.send(http, fd).and_then(|_| Ok(accepted))
+        response.send(http, fd).map(|_| ())
     }
 
-    /// Handle a request for mempool transactions in bulk
-    fn handle_mempool_query<W: Write>(
+    /// Handle a GET on a raw value in the Clarity key-value store, given the current chain tip.
+    /// Optionally supplies a MARF proof for the value.
+    fn handle_get_clarity_marf_value<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
         sortdb: &SortitionDB,
-        chainstate: &StacksChainState,
-        query: MemPoolSyncData,
-        max_txs: u64,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        key: &str,
+        with_proof: bool,
         canonical_stacks_tip_height: u64,
-        page_id: Option<Txid>,
-    ) -> Result<StreamCursor, net_error> {
+    ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        let response = HttpResponseType::MemPoolTxStream(response_metadata);
-        let height = chainstate
-            .get_stacks_chain_tip(sortdb)?
-            .map(|blk| blk.height)
-            .unwrap_or(0);
 
-        debug!(
-            "Begin mempool query";
-            "page_id" => %page_id.map(|txid| format!("{}", &txid)).unwrap_or("(none".to_string()),
-            "block_height" => height,
-            "max_txs" => max_txs
-        );
-
-        let stream = StreamCursor::new_tx_stream(query, max_txs, height, page_id);
-        response.send(http, fd).and_then(|_| Ok(stream))
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                    let (data, marf_proof): (String, _) = if with_proof {
+                        clarity_db
+                            .get_with_proof(key)
+                            .map(|(a, b)| (a, Some(format!("0x{}", to_hex(&b)))))?
+                    } else {
+                        clarity_db.get(key).map(|a| (a, None))?
+                    };
+                    Some(ClarityMarfValueResponse { data, marf_proof })
+                })
+            }) {
+                Ok(Some(Some(data))) => {
+                    HttpResponseType::GetClarityMarfValue(response_metadata, data)
+                }
+                Ok(Some(None)) => {
+                    HttpResponseType::NotFound(response_metadata, "Key not found".into())
+                }
+                Ok(None) | Err(_) => {
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
+                }
+            };
+
+        response.send(http, fd).map(|_| ())
     }
 
-    /// Handle an external HTTP request.
-    /// Some requests, such as those for blocks, will create new reply streams.  This method adds
-    /// those new streams into the `reply_streams` set.
-    /// Returns a StacksMessageType option -- it's Some(...) if we need to forward a message to the
-    /// peer network (like a transaction or a block or microblock)
-    pub fn handle_request(
-        &mut self,
-        req: HttpRequestType,
-        network: &mut PeerNetwork,
+    /// Handle a GET on a raw metadata entry of a smart contract, given the current chain tip.
+    fn handle_get_clarity_metadata<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
         sortdb: &SortitionDB,
         chainstate: &mut StacksChainState,
-        mempool: &mut MemPoolDB,
-        handler_opts: &RPCHandlerArgs,
-    ) -> Result<Option<StacksMessageType>, net_error> {
-        let mut reply = self.connection.make_relay_handle(self.conn_id)?;
-        let keep_alive = req.metadata().keep_alive;
-        let mut ret = None;
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        key: &str,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
 
-        let stream_opt = match req {
-            HttpRequestType::GetInfo(ref _md) => {
-                ConversationHttp::handle_getinfo(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    network,
-                    chainstate,
-                    handler_opts,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )?;
-                None
-            }
-            HttpRequestType::GetPoxInfo(ref _md, ref tip_req) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_req,
-                    sortdb,
-                    chainstate,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )? {
-                    ConversationHttp::handle_getpoxinfo(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        &network.burnchain,
-                        network.burnchain_tip.canonical_stacks_tip_height,
-                    )?;
-                }
-                None
-            }
-            HttpRequestType::GetNeighbors(ref _md) => {
-                ConversationHttp::handle_getneighbors(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    network,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )?;
-                None
-            }
-            HttpRequestType::GetHeaders(ref _md, ref quantity, ref tip_req) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_req,
-                    sortdb,
-                    chainstate,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )? {
-                    ConversationHttp::handle_getheaders(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        &tip,
-                        *quantity,
-                        chainstate,
-                        network.burnchain_tip.canonical_stacks_tip_height,
-                    )?
-                } else {
-                    None
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                    clarity_db.store.get_metadata(&contract_identifier, key)
+                })
+            }) {
+                Ok(Some(Ok(Some(data)))) => HttpResponseType::GetClarityMetadata(
+                    response_metadata,
+                    ClarityMetadataResponse { data },
+                ),
+                Ok(Some(Ok(None))) => {
+                    HttpResponseType::NotFound(response_metadata, "Metadata not found".into())
                 }
-            }
-            HttpRequestType::GetBlock(ref _md, ref index_block_hash) => {
-                ConversationHttp::handle_getblock(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    index_block_hash,
-                    chainstate,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )?
-            }
-            HttpRequestType::GetMicroblocksIndexed(ref _md, ref index_head_hash) => {
-                ConversationHttp::handle_getmicroblocks_indexed(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    index_head_hash,
-                    chainstate,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )?
-            }
-            HttpRequestType::GetMicroblocksConfirmed(ref _md, ref anchor_index_block_hash) => {
-                ConversationHttp::handle_getmicroblocks_confirmed(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    anchor_index_block_hash,
-                    chainstate,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )?
-            }
-            HttpRequestType::GetMicroblocksUnconfirmed(
-                ref _md,
-                ref index_anchor_block_hash,
-                ref min_seq,
-            ) => ConversationHttp::handle_getmicroblocks_unconfirmed(
-                &mut self.connection.protocol,
-                &mut reply,
-                &req,
-                index_anchor_block_hash,
-                *min_seq,
-                chainstate,
-                network.burnchain_tip.canonical_stacks_tip_height,
-            )?,
-            HttpRequestType::GetTransactionUnconfirmed(ref _md, ref txid) => {
-                ConversationHttp::handle_gettransaction_unconfirmed(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    chainstate,
-                    mempool,
-                    txid,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )?;
-                None
-            }
-            HttpRequestType::GetAccount(ref _md, ref principal, ref tip_req, ref with_proof) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_req,
-                    sortdb,
-                    chainstate,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )? {
-                    ConversationHttp::handle_get_account_entry(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        principal,
-                        *with_proof,
-                        network.burnchain_tip.canonical_stacks_tip_height,
-                    )?;
+                Ok(Some(Err(_))) => {
+                    HttpResponseType::NotFound(response_metadata, "Contract not found".into())
                 }
-                None
-            }
-            HttpRequestType::GetDataVar(
-                ref _md,
-                ref contract_addr,
-                ref contract_name,
-                ref var_name,
-                ref tip_req,
-                ref with_proof,
-            ) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_req,
-                    sortdb,
-                    chainstate,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )? {
-                    ConversationHttp::handle_get_data_var(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        contract_addr,
-                        contract_name,
-                        var_name,
-                        *with_proof,
-                        network.burnchain_tip.canonical_stacks_tip_height,
-                    )?;
+                Ok(None) | Err(_) => {
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
                 }
-                None
-            }
-            HttpRequestType::GetMapEntry(
-                ref _md,
-                ref contract_addr,
-                ref contract_name,
-                ref map_name,
-                ref key,
-                ref tip_req,
-                ref with_proof,
-            ) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_req,
-                    sortdb,
-                    chainstate,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )? {
-                    ConversationHttp::handle_get_map_entry(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        contract_addr,
-                        contract_name,
-                        map_name,
-                        key,
-                        *with_proof,
-                        network.burnchain_tip.canonical_stacks_tip_height,
-                    )?;
+            };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a POST to run a read-only function call with the given parameters on the given chain
+    /// tip.  Returns the result of the function call.  Returns a CallReadOnlyResponse on success.
+    fn handle_readonly_function_call<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        function: &ClarityName,
+        sender: &PrincipalData,
+        sponsor: Option<&PrincipalData>,
+        args: &[Value],
+        options: &ConnectionOptions,
+        read_only_call_cache: &mut ReadOnlyCallCache,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        // the unconfirmed state changes as microblocks arrive, so its results can't be cached
+        let is_unconfirmed_tip = chainstate
+            .unconfirmed_state
+            .as_ref()
+            .map(|unconfirmed| unconfirmed.unconfirmed_chain_tip == *tip)
+            .unwrap_or(false);
+        let cache_key = if !is_unconfirmed_tip {
+            Some(ReadOnlyCallKey::new(
+                tip,
+                &contract_identifier,
+                function,
+                sender,
+                sponsor,
+                args,
+            ))
+        } else {
+            None
+        };
+        if let Some(cached) = cache_key
+            .as_ref()
+            .and_then(|key| read_only_call_cache.get(key))
+        {
+            let response = HttpResponseType::CallReadOnlyFunction(response_metadata, cached);
+            return response.send(http, fd).map(|_| ());
+        }
+
+        let arg_values = args;
+        let args: Vec<_> = args
+            .iter()
+            .map(|x| SymbolicExpression::atom_value(x.clone()))
+            .collect();
+        let network = chainstate.network;
+        let mut cost_limit = options.read_only_call_limit.clone();
+        cost_limit.write_length = 0;
+        cost_limit.write_count = 0;
+
+        let data_opt_res =
+            chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                let epoch = clarity_tx.get_epoch();
+                let cost_track = clarity_tx
+                    .with_clarity_db_readonly(|clarity_db| {
+                        LimitedCostTracker::new_mid_block(network, cost_limit, clarity_db, epoch)
+                    })
+                    .map_err(|_| {
+                        ClarityRuntimeError::from(InterpreterError::CostContractLoadFailure)
+                    })?;
+
+                let contract_analysis = clarity_tx
+                    .with_analysis_db_readonly(|analysis_db| {
+                        analysis_db.load_contract_non_canonical(&contract_identifier)
+                    })
+                    .ok_or_else(|| {
+                        ClarityRuntimeError::from(CheckErrors::NoSuchContract(format!(
+                            "{}",
+                            &contract_identifier
+                        )))
+                    })?;
+                let clarity_version = contract_analysis.clarity_version;
+
+                // check the arguments against the contract's interface up front, so that callers
+                // get an error that names the function's signature
+                if let Some(interface) = contract_analysis.contract_interface.as_ref() {
+                    if let Err(abi_error) = interface
+                        .get_callable_function(function.as_str())
+                        .and_then(|func| func.check_args(arg_values, &epoch))
+                    {
+                        return Ok(Err(abi_error));
+                    }
                 }
-                None
-            }
-            HttpRequestType::GetTransferCost(ref _md) => {
-                ConversationHttp::handle_token_transfer_cost(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )?;
-                None
+
+                clarity_tx
+                    .with_readonly_clarity_env(
+                        network,
+                        clarity_version,
+                        sender.clone(),
+                        sponsor.cloned(),
+                        cost_track,
+                        |env| {
+                            // we want to execute any function as long as no actual writes are made as
+                            // opposed to be limited to purely calling `define-read-only` functions,
+                            // so use `read_only = false`.  This broadens the number of functions that
+                            // can be called, and also circumvents limitations on `define-read-only`
+                            // functions that can not use `contrac-call?`, even when calling other
+                            // read-only functions
+                            env.execute_contract(
+                                &contract_identifier,
+                                function.as_str(),
+                                &args,
+                                false,
+                            )
+                        },
+                    )
+                    .map(Ok)
+            });
+
+        let response = match data_opt_res {
+            Ok(Some(Ok(Err(abi_error)))) => HttpResponseType::CallReadOnlyFunction(
+                response_metadata,
+                CallReadOnlyResponse {
+                    okay: false,
+                    result: None,
+                    cause: Some(abi_error.to_string()),
+                },
+            ),
+            Ok(Some(Ok(Ok(data)))) => HttpResponseType::CallReadOnlyFunction(
+                response_metadata,
+                CallReadOnlyResponse {
+                    okay: true,
+                    result: Some(format!("0x{}", data.serialize_to_hex())),
+                    cause: None,
+                },
+            ),
+            Ok(Some(Err(e))) => match e {
+                Unchecked(CheckErrors::CostBalanceExceeded(actual_cost, _))
+                    if actual_cost.write_count > 0 =>
+                {
+                    HttpResponseType::CallReadOnlyFunction(
+                        response_metadata,
+                        CallReadOnlyResponse {
+                            okay: false,
+                            result: None,
+                            cause: Some("NotReadOnly".to_string()),
+                        },
+                    )
+                }
+                _ => HttpResponseType::CallReadOnlyFunction(
+                    response_metadata,
+                    CallReadOnlyResponse {
+                        okay: false,
+                        result: None,
+                        cause: Some(e.to_string()),
+                    },
+                ),
+            },
+            Ok(None) | Err(_) => {
+                HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
             }
-            HttpRequestType::GetContractABI(
-                ref _md,
-                ref contract_addr,
-                ref contract_name,
-                ref tip_req,
-            ) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_req,
-                    sortdb,
-                    chainstate,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )? {
-                    ConversationHttp::handle_get_contract_abi(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        contract_addr,
-                        contract_name,
-                        network.burnchain_tip.canonical_stacks_tip_height,
-                    )?;
+        };
+        if let (Some(key), HttpResponseType::CallReadOnlyFunction(_, ref data)) =
+            (cache_key, &response)
+        {
+            read_only_call_cache.insert(key, data.clone());
+        }
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a POST to preview a contract call with the given parameters on the given chain tip.
+    /// Unlike a read-only call, the function may write, but its changes are discarded without
+    /// ever being written to the MARF.  Returns a CallPreviewResponse with the function's result,
+    /// the events it emitted, and the assets it moved.
+    fn handle_call_preview<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        function: &ClarityName,
+        sender: &PrincipalData,
+        sponsor: Option<&PrincipalData>,
+        args: &[Value],
+        options: &ConnectionOptions,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let arg_values = args;
+        let args: Vec<_> = args
+            .iter()
+            .map(|x| SymbolicExpression::atom_value(x.clone()))
+            .collect();
+        let network = chainstate.network;
+        // a preview may write as much as a block could, since its writes are discarded
+        let mut cost_limit = options.read_only_call_limit.clone();
+        cost_limit.write_length = BLOCK_LIMIT_MAINNET_21.write_length;
+        cost_limit.write_count = BLOCK_LIMIT_MAINNET_21.write_count;
+
+        let data_opt_res =
+            chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                let epoch = clarity_tx.get_epoch();
+                let cost_track = clarity_tx
+                    .with_clarity_db_readonly(|clarity_db| {
+                        LimitedCostTracker::new_mid_block(network, cost_limit, clarity_db, epoch)
+                    })
+                    .map_err(|_| {
+                        ClarityRuntimeError::from(InterpreterError::CostContractLoadFailure)
+                    })?;
+
+                let contract_analysis = clarity_tx
+                    .with_analysis_db_readonly(|analysis_db| {
+                        analysis_db.load_contract_non_canonical(&contract_identifier)
+                    })
+                    .ok_or_else(|| {
+                        ClarityRuntimeError::from(CheckErrors::NoSuchContract(format!(
+                            "{}",
+                            &contract_identifier
+                        )))
+                    })?;
+                let clarity_version = contract_analysis.clarity_version;
+
+                if let Some(interface) = contract_analysis.contract_interface.as_ref() {
+                    if let Err(abi_error) = interface
+                        .get_callable_function(function.as_str())
+                        .and_then(|func| func.check_args(arg_values, &epoch))
+                    {
+                        return Ok(Err(abi_error));
+                    }
                 }
-                None
+
+                clarity_tx
+                    .with_readonly_clarity_env_events(
+                        network,
+                        clarity_version,
+                        sender.clone(),
+                        sponsor.cloned(),
+                        cost_track,
+                        |env| {
+                            env.execute_contract(
+                                &contract_identifier,
+                                function.as_str(),
+                                &args,
+                                false,
+                            )
+                        },
+                    )
+                    .map(Ok)
+            });
+
+        let response = match data_opt_res {
+            Ok(Some(Ok(Err(abi_error)))) => HttpResponseType::CallPreviewFunction(
+                response_metadata,
+                CallPreviewResponse {
+                    okay: false,
+                    result: None,
+                    cause: Some(abi_error.to_string()),
+                    committed: false,
+                    events: vec![],
+                    asset_movements: None,
+                },
+            ),
+            Ok(Some(Ok(Ok((data, asset_map, events))))) => {
+                let committed = match data {
+                    Value::Response(ref response) => response.committed,
+                    _ => true,
+                };
+                // an `(err ..)` result rolls back the transaction, along with its events
+                let events = if committed {
+                    events
+                        .iter()
+                        .enumerate()
+                        .map(|(event_index, event)| {
+                            let mut event_json = event.json_serialize(event_index, &"", true);
+                            // there is no transaction to report
+                            if let Some(event_obj) = event_json.as_object_mut() {
+                                event_obj.remove("txid");
+                            }
+                            event_json
+                        })
+                        .collect()
+                } else {
+                    vec![]
+                };
+                HttpResponseType::CallPreviewFunction(
+                    response_metadata,
+                    CallPreviewResponse {
+                        okay: true,
+                        result: Some(format!("0x{}", data.serialize_to_hex())),
+                        cause: None,
+                        committed,
+                        events,
+                        asset_movements: Some(asset_map.to_json()),
+                    },
+                )
             }
-            HttpRequestType::FeeRateEstimate(ref _md, ref tx, estimated_len) => {
-                ConversationHttp::handle_post_fee_rate_estimate(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    handler_opts,
-                    sortdb,
-                    tx,
-                    estimated_len,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )?;
-                None
+            Ok(Some(Err(e))) => HttpResponseType::CallPreviewFunction(
+                response_metadata,
+                CallPreviewResponse {
+                    okay: false,
+                    result: None,
+                    cause: Some(e.to_string()),
+                    committed: false,
+                    events: vec![],
+                    asset_movements: None,
+                },
+            ),
+            Ok(None) | Err(_) => {
+                HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
             }
-            HttpRequestType::CallReadOnlyFunction(
-                ref _md,
-                ref ctrct_addr,
-                ref ctrct_name,
-                ref as_sender,
-                ref as_sponsor,
-                ref func_name,
-                ref args,
-                ref tip_req,
-            ) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_req,
-                    sortdb,
-                    chainstate,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )? {
-                    ConversationHttp::handle_readonly_function_call(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        ctrct_addr,
-                        ctrct_name,
-                        func_name,
-                        as_sender,
-                        as_sponsor.as_ref(),
-                        args,
-                        &self.connection.options,
-                        network.burnchain_tip.canonical_stacks_tip_height,
-                    )?;
-                }
-                None
-            }
-            HttpRequestType::GetContractSrc(
-                ref _md,
-                ref contract_addr,
-                ref contract_name,
-                ref tip_req,
-                ref with_proof,
-            ) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_req,
-                    sortdb,
-                    chainstate,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )? {
-                    ConversationHttp::handle_get_contract_src(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        contract_addr,
-                        contract_name,
-                        *with_proof,
-                        network.burnchain_tip.canonical_stacks_tip_height,
-                    )?;
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET to fetch a contract's source code, given the chain tip.  Optionally returns a
+    /// MARF proof as well.
+    fn handle_get_contract_src<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        with_proof: bool,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|db| {
+                    let source = db.get_contract_src(&contract_identifier)?;
+                    let contract_commit_key = make_contract_hash_key(&contract_identifier);
+                    let (contract_commit, proof) = if with_proof {
+                        db.get_with_proof::<ContractCommitment>(&contract_commit_key)
+                            .map(|(a, b)| (a, Some(format!("0x{}", to_hex(&b)))))
+                            .expect("BUG: obtained source, but couldn't get contract commit")
+                    } else {
+                        db.get::<ContractCommitment>(&contract_commit_key)
+                            .map(|a| (a, None))
+                            .expect("BUG: obtained source, but couldn't get contract commit")
+                    };
+
+                    let publish_height = contract_commit.block_height;
+                    Some(ContractSrcResponse {
+                        source,
+                        publish_height,
+                        marf_proof: proof,
+                    })
+                })
+            }) {
+                Ok(Some(Some(data))) => HttpResponseType::GetContractSrc(response_metadata, data),
+                Ok(Some(None)) => HttpResponseType::NotFound(
+                    response_metadata,
+                    "No contract source data found".into(),
+                ),
+                Ok(None) | Err(_) => {
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
                 }
-                None
-            }
-            HttpRequestType::PostTransaction(ref _md, ref tx, ref attachment) => {
-                match chainstate.get_stacks_chain_tip(sortdb)? {
-                    Some(tip) => {
-                        let accepted = ConversationHttp::handle_post_transaction(
-                            &mut self.connection.protocol,
-                            &mut reply,
-                            &req,
-                            chainstate,
-                            sortdb,
-                            tip.consensus_hash,
-                            tip.anchored_block_hash,
-                            mempool,
-                            tx.clone(),
-                            &mut network.atlasdb,
-                            attachment.clone(),
-                            handler_opts.event_observer.as_deref(),
-                            network.burnchain_tip.canonical_stacks_tip_height,
-                            network.ast_rules,
-                        )?;
-                        if accepted {
-                            // forward to peer network
-                            ret = Some(StacksMessageType::Transaction(tx.clone()));
-                        }
-                    }
-                    None => {
-                        let response_metadata = HttpResponseMetadata::from_http_request_type(
-                            &req,
-                            Some(network.burnchain_tip.canonical_stacks_tip_height),
-                        );
-                        warn!("Failed to load Stacks chain tip");
-                        let response = HttpResponseType::ServerError(
-                            response_metadata,
-                            format!("Failed to load Stacks chain tip"),
-                        );
-                        response.send(&mut self.connection.protocol, &mut reply)?;
+            };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET to fetch whether or not a contract implements a certain trait
+    fn handle_get_is_trait_implemented<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        trait_id: &TraitIdentifier,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|db| {
+                    let analysis = db.load_contract_analysis(&contract_identifier)?;
+                    if analysis.implemented_traits.contains(trait_id) {
+                        Some(GetIsTraitImplementedResponse {
+                            is_implemented: true,
+                        })
+                    } else {
+                        let trait_defining_contract =
+                            db.load_contract_analysis(&trait_id.contract_identifier)?;
+                        let trait_definition =
+                            trait_defining_contract.get_defined_trait(&trait_id.name)?;
+                        let is_implemented = analysis
+                            .check_trait_compliance(
+                                &db.get_clarity_epoch_version(),
+                                trait_id,
+                                trait_definition,
+                            )
+                            .is_ok();
+                        Some(GetIsTraitImplementedResponse { is_implemented })
                     }
+                })
+            }) {
+                Ok(Some(Some(data))) => {
+                    HttpResponseType::GetIsTraitImplemented(response_metadata, data)
                 }
-                None
-            }
-            HttpRequestType::GetAttachment(ref _md, ref content_hash) => {
-                ConversationHttp::handle_getattachment(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    &mut network.atlasdb,
-                    content_hash.clone(),
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )?;
-                None
-            }
-            HttpRequestType::GetAttachmentsInv(
-                ref _md,
-                ref index_block_hash,
-                ref pages_indexes,
-            ) => {
-                ConversationHttp::handle_getattachmentsinv(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    &mut network.atlasdb,
-                    &index_block_hash,
-                    pages_indexes,
-                    &self.connection.options,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )?;
-                None
-            }
-            HttpRequestType::PostBlock(ref _md, ref consensus_hash, ref block) => {
-                let accepted = ConversationHttp::handle_post_block(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    sortdb,
-                    chainstate,
-                    consensus_hash,
-                    block,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )?;
-                if accepted {
-                    // inform the peer network so it can announce its presence
-                    ret = Some(StacksMessageType::Blocks(BlocksData {
-                        blocks: vec![BlocksDatum(consensus_hash.clone(), block.clone())],
-                    }));
+                Ok(Some(None)) => HttpResponseType::NotFound(
+                    response_metadata,
+                    "No contract analysis found or trait definition not found".into(),
+                ),
+                Ok(None) | Err(_) => {
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
                 }
-                None
-            }
-            HttpRequestType::PostMicroblock(ref _md, ref mblock, ref tip_req) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_req,
-                    sortdb,
-                    chainstate,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )? {
-                    if let Some((consensus_hash, block_hash)) =
-                        ConversationHttp::handle_load_stacks_chain_tip_hashes(
-                            &mut self.connection.protocol,
-                            &mut reply,
-                            &req,
-                            tip,
-                            chainstate,
-                            network.burnchain_tip.canonical_stacks_tip_height,
-                        )?
-                    {
-                        let accepted = ConversationHttp::handle_post_microblock(
-                            &mut self.connection.protocol,
-                            &mut reply,
-                            &req,
-                            &consensus_hash,
-                            &block_hash,
-                            sortdb,
-                            chainstate,
-                            mblock,
-                            network.burnchain_tip.canonical_stacks_tip_height,
-                        )?;
-                        if accepted {
-                            // forward to peer network
-                            let tip = StacksBlockHeader::make_index_block_hash(
-                                &consensus_hash,
-                                &block_hash,
-                            );
-                            ret = Some(StacksMessageType::Microblocks(MicroblocksData {
-                                index_anchor_block: tip,
-                                microblocks: vec![(*mblock).clone()],
-                            }));
-                        }
-                    }
+            };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET to fetch a contract's analysis data, given the chain tip.  Note that this isn't
+    /// something that's anchored to the blockchain, and can be different across different versions
+    /// of Stacks -- callers must trust the Stacks node to return correct analysis data.
+    /// Callers who don't trust the Stacks node should just fetch the contract source
+    /// code and analyze it offline.
+    fn handle_get_contract_abi<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                let epoch = clarity_tx.get_epoch();
+                clarity_tx.with_analysis_db_readonly(|db| {
+                    let contract = db.load_contract(&contract_identifier, &epoch)?;
+                    contract.contract_interface
+                })
+            }) {
+                Ok(Some(Some(data))) => HttpResponseType::GetContractABI(response_metadata, data),
+                Ok(Some(None)) => HttpResponseType::NotFound(
+                    response_metadata,
+                    "No contract interface data found".into(),
+                ),
+                Ok(None) | Err(_) => {
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
                 }
-                None
-            }
-            HttpRequestType::MemPoolQuery(ref _md, ref query, ref page_id_opt) => {
-                Some(ConversationHttp::handle_mempool_query(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    sortdb,
-                    chainstate,
-                    query.clone(),
-                    network.connection_opts.mempool_max_tx_query,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                    page_id_opt.clone(),
-                )?)
+            };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET unconfirmed microblock stream.  Start streaming the reply.
+    /// The response's preamble (but not the block data) will be synchronously written to the fd
+    /// (so use a fd that can buffer!)
+    /// Return a StreamCursor struct for the block that we're sending, so we can continue to
+    /// make progress sending it.
+    fn handle_getmicroblocks_unconfirmed<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        index_anchor_block_hash: &StacksBlockId,
+        min_seq: u16,
+        chainstate: &StacksChainState,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<Option<StreamCursor>, net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+
+        // do we have this unconfirmed microblock stream?
+        match chainstate.has_any_staging_microblock_indexed(index_anchor_block_hash, min_seq) {
+            Ok(false) => {
+                // nope
+                let response = HttpResponseType::NotFound(
+                    response_metadata,
+                    format!(
+                        "No such unconfirmed microblock stream for {} at or after {}",
+                        index_anchor_block_hash.to_hex(),
+                        min_seq
+                    ),
+                );
+                response.send(http, fd).and_then(|_| Ok(None))
             }
-            HttpRequestType::OptionsPreflight(ref _md, ref _path) => {
-                let response_metadata = HttpResponseMetadata::from_http_request_type(
-                    &req,
-                    Some(network.burnchain_tip.canonical_stacks_tip_height),
+            Err(e) => {
+                // nope
+                warn!(
+                    "Failed to serve confirmed microblock stream {:?}: {:?}",
+                    req, &e
                 );
-                let response = HttpResponseType::OptionsPreflight(response_metadata);
-                response
-                    .send(&mut self.connection.protocol, &mut reply)
-                    .map(|_| ())?;
-                None
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    format!(
+                        "Failed to query unconfirmed microblock stream for {} at or after {}",
+                        index_anchor_block_hash.to_hex(),
+                        min_seq
+                    ),
+                );
+                response.send(http, fd).and_then(|_| Ok(None))
             }
-            HttpRequestType::GetIsTraitImplemented(
-                ref _md,
-                ref contract_addr,
-                ref contract_name,
-                ref trait_id,
-                ref tip_req,
-            ) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_req,
-                    sortdb,
+            Ok(true) => {
+                // yup! start streaming it back
+                let (response, stream_opt) = match StreamCursor::new_microblock_unconfirmed(
                     chainstate,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )? {
-                    ConversationHttp::handle_get_is_trait_implemented(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        contract_addr,
-                        contract_name,
-                        trait_id,
-                        network.burnchain_tip.canonical_stacks_tip_height,
-                    )?;
-                }
-                None
-            }
-            HttpRequestType::ClientError(ref _md, ref err) => {
-                let response_metadata = HttpResponseMetadata::from_http_request_type(
-                    &req,
-                    Some(network.burnchain_tip.canonical_stacks_tip_height),
-                );
-                let response = match err {
-                    ClientError::Message(s) => HttpResponseType::BadRequestJSON(
-                        response_metadata,
-                        serde_json::Value::String(s.to_string()),
+                    index_anchor_block_hash.clone(),
+                    min_seq,
+                ) {
+                    Ok(stream) => (
+                        HttpResponseType::MicroblockStream(response_metadata),
+                        Some(stream),
                     ),
-                    ClientError::NotFound(path) => {
-                        HttpResponseType::NotFound(response_metadata, path.clone())
+                    Err(chain_error::NoSuchBlockError) => (
+                        HttpResponseType::NotFound(
+                            response_metadata,
+                            format!(
+                                "No such unconfirmed microblock stream starting with {}",
+                                index_anchor_block_hash.to_hex()
+                            ),
+                        ),
+                        None,
+                    ),
+                    Err(_e) => {
+                        debug!(
+                            "Failed to load unconfirmed microblock stream {}: {:?}",
+                            &index_anchor_block_hash, &_e
+                        );
+                        (
+                            HttpResponseType::ServerError(
+                                response_metadata,
+                                format!(
+                                    "Failed to query unconfirmed microblock stream {}",
+                                    index_anchor_block_hash.to_hex()
+                                ),
+                            ),
+                            None,
+                        )
                     }
                 };
+                response.send(http, fd).and_then(|_| Ok(stream_opt))
+            }
+        }
+    }
+
+    /// Handle a GET unconfirmed transaction.
+    /// The response will be synchronously written to the fd.
+    fn handle_gettransaction_unconfirmed<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &StacksChainState,
+        mempool: &MemPoolDB,
+        txid: &Txid,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+
+        // present in the unconfirmed state?
+        if let Some(ref unconfirmed) = chainstate.unconfirmed_state.as_ref() {
+            if let Some((transaction, mblock_hash, seq)) =
+                unconfirmed.get_unconfirmed_transaction(txid)
+            {
+                let response = HttpResponseType::UnconfirmedTransaction(
+                    response_metadata,
+                    UnconfirmedTransactionResponse {
+                        status: UnconfirmedTransactionStatus::Microblock {
+                            block_hash: mblock_hash,
+                            seq: seq,
+                        },
+                        tx: to_hex(&transaction.serialize_to_vec()),
+                    },
+                );
+                return response.send(http, fd).map(|_| ());
+            }
+        }
+
+        // present in the mempool?
+        let tx_bytes_opt = if mempool.snapshot().get(txid).is_some() {
+            // dropped if it was removed since the snapshot was taken
+            MemPoolDB::get_tx_bytes(mempool.conn(), txid)?
+        } else {
+            None
+        };
+        if let Some(tx_bytes) = tx_bytes_opt {
+            let response = HttpResponseType::UnconfirmedTransaction(
+                response_metadata,
+                UnconfirmedTransactionResponse {
+                    status: UnconfirmedTransactionStatus::Mempool,
+                    tx: to_hex(&tx_bytes),
+                },
+            );
+            return response.send(http, fd).map(|_| ());
+        }
+
+        // not found
+        let response = HttpResponseType::NotFound(
+            response_metadata,
+            format!("No such unconfirmed transaction {}", txid),
+        );
+        return response.send(http, fd).map(|_| ());
+    }
+
+    /// Load up the canonical Stacks chain tip.  Note that this is subject to both burn chain block
+    /// Stacks block availability -- different nodes with different partial replicas of the Stacks chain state
+    /// will return different values here.
+    ///
+    /// # Warn
+    /// - There is a potential race condition. If this function is loading the latest unconfirmed
+    /// tip, that tip may get invalidated by the time it is used in `maybe_read_only_clarity_tx`,
+    /// which is used to load clarity state at a particular tip (which would lead to a 404 error).
+    /// If this race condition occurs frequently, we can modify `maybe_read_only_clarity_tx` to
+    /// re-load the unconfirmed chain tip. Refer to issue #2997.
+    ///
+    /// # Inputs
+    /// - `tip_req` is given by the HTTP request as the optional query parameter for the chain tip
+    /// hash.  It will be UseLatestAnchoredTip if there was no parameter given. If it is set to
+    /// `latest`, the parameter will be set to UseLatestUnconfirmedTip.
+    fn handle_load_stacks_chain_tip<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        tip_req: &TipRequest,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<Option<StacksBlockId>, net_error> {
+        match tip_req {
+            TipRequest::UseLatestUnconfirmedTip => {
+                let unconfirmed_chain_tip_opt = match &mut chainstate.unconfirmed_state {
+                    Some(unconfirmed_state) => {
+                        match unconfirmed_state.get_unconfirmed_state_if_exists() {
+                            Ok(res) => res,
+                            Err(msg) => {
+                                let response_metadata =
+                                    HttpResponseMetadata::from_http_request_type(
+                                        req,
+                                        Some(canonical_stacks_tip_height),
+                                    );
+                                let response = HttpResponseType::NotFound(response_metadata, msg);
+                                return response.send(http, fd).and_then(|_| Ok(None));
+                            }
+                        }
+                    }
+                    None => None,
+                };
+
+                if let Some(unconfirmed_chain_tip) = unconfirmed_chain_tip_opt {
+                    Ok(Some(unconfirmed_chain_tip))
+                } else {
+                    match chainstate.get_stacks_chain_tip(sortdb)? {
+                        Some(tip) => Ok(Some(StacksBlockHeader::make_index_block_hash(
+                            &tip.consensus_hash,
+                            &tip.anchored_block_hash,
+                        ))),
+                        None => {
+                            let response_metadata = HttpResponseMetadata::from_http_request_type(
+                                req,
+                                Some(canonical_stacks_tip_height),
+                            );
+                            warn!("Failed to load Stacks chain tip");
+                            let response = HttpResponseType::NotFound(
+                                response_metadata,
+                                format!("Failed to load Stacks chain tip"),
+                            );
+                            response.send(http, fd).and_then(|_| Ok(None))
+                        }
+                    }
+                }
+            }
+            TipRequest::SpecificTip(tip) => Ok(Some(*tip).clone()),
+            TipRequest::Session(session_id) => {
+                // sessions that are still open were already resolved to their pinned tips by
+                // `handle_request()`, so this one has expired, been released, or never existed.
+                let response_metadata = HttpResponseMetadata::from_http_request_type(
+                    req,
+                    Some(canonical_stacks_tip_height),
+                );
+                let response = HttpResponseType::NotFound(
+                    response_metadata,
+                    format!("No such session: {}", session_id),
+                );
+                response.send(http, fd).and_then(|_| Ok(None))
+            }
+            TipRequest::UseLatestAnchoredTip => match chainstate.get_stacks_chain_tip(sortdb)? {
+                Some(tip) => Ok(Some(StacksBlockHeader::make_index_block_hash(
+                    &tip.consensus_hash,
+                    &tip.anchored_block_hash,
+                ))),
+                None => {
+                    let response_metadata = HttpResponseMetadata::from_http_request_type(
+                        req,
+                        Some(canonical_stacks_tip_height),
+                    );
+                    warn!("Failed to load Stacks chain tip");
+                    let response = HttpResponseType::ServerError(
+                        response_metadata,
+                        format!("Failed to load Stacks chain tip"),
+                    );
+                    response.send(http, fd).and_then(|_| Ok(None))
+                }
+            },
+        }
+    }
+
+    fn handle_load_stacks_chain_tip_hashes<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        tip: StacksBlockId,
+        chainstate: &StacksChainState,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<Option<(ConsensusHash, BlockHeaderHash)>, net_error> {
+        match chainstate.get_block_header_hashes(&tip)? {
+            Some((ch, bl)) => {
+                return Ok(Some((ch, bl)));
+            }
+            None => {}
+        }
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        warn!("Failed to load Stacks chain tip");
+        let response = HttpResponseType::ServerError(
+            response_metadata,
+            format!("Failed to load Stacks chain tip"),
+        );
+        response.send(http, fd).and_then(|_| Ok(None))
+    }
+
+    fn handle_post_fee_rate_estimate<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        handler_args: &RPCHandlerArgs,
+        sortdb: &SortitionDB,
+        tx: &TransactionPayload,
+        estimated_len: u64,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+        let stacks_epoch = SortitionDB::get_stacks_epoch(sortdb.conn(), tip.block_height)?
+                .ok_or_else(|| {
+                    warn!(
+                        "Failed to get fee rate estimate because could not load Stacks epoch for canonical burn height = {}",
+                        tip.block_height
+                    );
+                    net_error::ChainstateError("Could not load Stacks epoch for canonical burn height".into())
+                })?;
+        if let Some((cost_estimator, fee_estimator, metric)) = handler_args.get_estimators_ref() {
+            let estimated_cost = match cost_estimator.estimate_cost(tx, &stacks_epoch.epoch_id) {
+                Ok(x) => x,
+                Err(e) => {
+                    debug!(
+                        "Estimator RPC endpoint failed to estimate tx: {}",
+                        tx.name()
+                    );
+                    return HttpResponseType::BadRequestJSON(response_metadata, e.into_json())
+                        .send(http, fd);
+                }
+            };
+
+            let scalar_cost =
+                metric.from_cost_and_len(&estimated_cost, &stacks_epoch.block_limit, estimated_len);
+            let fee_rates = match fee_estimator.get_rate_estimates() {
+                Ok(x) => x,
+                Err(e) => {
+                    debug!(
+                        "Estimator RPC endpoint failed to estimate fees for tx: {}",
+                        tx.name()
+                    );
+                    return HttpResponseType::BadRequestJSON(response_metadata, e.into_json())
+                        .send(http, fd);
+                }
+            };
+
+            let mut estimations = RPCFeeEstimate::estimate_fees(scalar_cost, fee_rates).to_vec();
+
+            let minimum_fee = estimated_len * MINIMUM_TX_FEE_RATE_PER_BYTE;
+
+            for estimate in estimations.iter_mut() {
+                if estimate.fee < minimum_fee {
+                    estimate.fee = minimum_fee;
+                }
+            }
+
+            let response = HttpResponseType::TransactionFeeEstimation(
+                response_metadata,
+                RPCFeeEstimateResponse {
+                    estimated_cost,
+                    estimations,
+                    estimated_cost_scalar: scalar_cost,
+                    cost_scalar_change_by_byte: metric.change_per_byte(),
+                },
+            );
+            response.send(http, fd)
+        } else {
+            debug!("Fee and cost estimation not configured on this stacks node");
+            let response = HttpResponseType::BadRequestJSON(
+                response_metadata,
+                json!({
+                    "error": "Fee and Cost Estimation not configured on this Stacks node",
+                    "reason": "CostEstimationDisabled",
+                }),
+            );
+            response.send(http, fd)
+        }
+    }
+
+    /// Handle a transaction.  Directly submit it to the mempool so the client can see any
+    /// rejection reasons up-front (different from how the peer network handles it).  Indicate
+    /// whether or not the transaction was accepted (and thus needs to be forwarded) in the return
+    /// value.
+    fn handle_post_transaction<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &mut StacksChainState,
+        sortdb: &SortitionDB,
+        consensus_hash: ConsensusHash,
+        block_hash: BlockHeaderHash,
+        mempool: &mut MemPoolDB,
+        tx: StacksTransaction,
+        atlasdb: &mut AtlasDB,
+        attachment: Option<Attachment>,
+        event_observer: Option<&dyn MemPoolEventDispatcher>,
+        canonical_stacks_tip_height: u64,
+        ast_rules: ASTRules,
+    ) -> Result<bool, net_error> {
+        let txid = tx.txid();
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let (response, accepted) = if mempool.has_tx(&txid) {
+            debug!("Mempool already has POSTed transaction {}", &txid);
+            (
+                HttpResponseType::TransactionID(response_metadata, txid),
+                false,
+            )
+        } else {
+            let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+            let stacks_epoch = sortdb
+                .index_conn()
+                .get_stacks_epoch(tip.block_height as u32)
+                .ok_or_else(|| {
+                    warn!(
+                        "Failed to store transaction because could not load Stacks epoch for canonical burn height = {}",
+                        tip.block_height
+                    );
+                    net_error::ChainstateError("Could not load Stacks epoch for canonical burn height".into())
+                })?;
+
+            if Relayer::do_static_problematic_checks()
+                && !Relayer::static_check_problematic_relayed_tx(
+                    chainstate.network.mainnet,
+                    stacks_epoch.epoch_id,
+                    &chainstate.get_vm_limits(stacks_epoch.epoch_id),
+                    &tx,
+                    ast_rules,
+                )
+                .is_ok()
+            {
+                debug!(
+                    "Transaction {} is problematic in rules {:?}; will not store or relay",
+                    &tx.txid(),
+                    ast_rules
+                );
+                (
+                    HttpResponseType::TransactionID(response_metadata, txid),
+                    false,
+                )
+            } else {
+                match mempool.submit(
+                    chainstate,
+                    sortdb,
+                    &consensus_hash,
+                    &block_hash,
+                    &tx,
+                    event_observer,
+                    &stacks_epoch.block_limit,
+                    &stacks_epoch.epoch_id,
+                ) {
+                    Ok(_) => {
+                        debug!("Mempool accepted POSTed transaction {}", &txid);
+                        (
+                            HttpResponseType::TransactionID(response_metadata, txid),
+                            true,
+                        )
+                    }
+                    Err(e) => {
+                        debug!("Mempool rejected POSTed transaction {}: {:?}", &txid, &e);
+                        (
+                            HttpResponseType::BadRequestJSON(response_metadata, e.into_json(&txid)),
+                            false,
+                        )
+                    }
+                }
+            }
+        };
+
+        if let Some(ref attachment) = attachment {
+            if let TransactionPayload::ContractCall(ref contract_call) = tx.payload {
+                if atlasdb
+                    .should_keep_attachment(&contract_call.to_clarity_contract_id(), &attachment)
+                {
+                    atlasdb
+                        .insert_uninstantiated_attachment(attachment)
+                        .map_err(|e| net_error::DBError(e))?;
+                }
+            }
+        }
+
+        response.send(http, fd).and_then(|_| Ok(accepted))
+    }
+
+    /// Handle an experimental transaction bundle.  Submit it to the mempool, where only a miner
+    /// on this node will see it; bundles are not forwarded to the peer network.
+    fn handle_post_transaction_bundle<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &StacksChainState,
+        consensus_hash: ConsensusHash,
+        block_hash: BlockHeaderHash,
+        mempool: &mut MemPoolDB,
+        bundle: &StacksTransactionBundle,
+        options: &ConnectionOptions,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let bundle_id = bundle.bundle_id();
+        let response = if !options.accept_tx_bundles {
+            HttpResponseType::NotFound(
+                response_metadata,
+                "Transaction bundles are not enabled on this node".to_string(),
+            )
+        } else {
+            match mempool.submit_tx_bundle(chainstate, &consensus_hash, &block_hash, bundle) {
+                Ok(_) => {
+                    debug!("Mempool accepted POSTed transaction bundle {}", &bundle_id);
+                    HttpResponseType::TransactionBundleID(response_metadata, bundle_id)
+                }
+                Err(e) => {
+                    debug!(
+                        "Mempool rejected POSTed transaction bundle {}: {:?}",
+                        &bundle_id, &e
+                    );
+                    let mut reason = e.into_json(&Txid(bundle_id.0.clone()));
+                    if let Some(obj) = reason.as_object_mut() {
+                        obj.remove("txid");
+                        obj.insert("bundle_id".to_string(), json!(bundle_id.to_hex()));
+                    }
+                    HttpResponseType::BadRequestJSON(response_metadata, reason)
+                }
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a POST to simulate a transaction on top of the given chain tip.  The transaction is
+    /// processed in a block that is rolled back afterwards, so none of its effects persist, and it
+    /// need not be signed.  Returns a TransactionSimulationResponse with the transaction's result,
+    /// the events it emitted, the assets it moved, and its execution cost.
+    fn handle_simulate_transaction<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        tx: &StacksTransaction,
+        ast_rules: ASTRules,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let txid = tx.txid();
+
+        let response =
+            match chainstate.simulate_transaction(&sortdb.index_conn(), tip, tx, ast_rules) {
+                Ok((_fee, receipt, asset_map)) => {
+                    let committed = !receipt.post_condition_aborted
+                        && match receipt.result {
+                            Value::Response(ref response) => response.committed,
+                            _ => true,
+                        };
+                    // events are reported the same way the event observer would see them
+                    let events = receipt
+                        .events
+                        .iter()
+                        .enumerate()
+                        .map(|(event_index, event)| {
+                            event.json_serialize(
+                                event_index,
+                                &txid,
+                                !receipt.post_condition_aborted,
+                            )
+                        })
+                        .collect();
+                    HttpResponseType::SimulateTransaction(
+                        response_metadata,
+                        TransactionSimulationResponse {
+                            okay: true,
+                            cause: None,
+                            result: Some(format!("0x{}", receipt.result.serialize_to_hex())),
+                            committed,
+                            post_condition_aborted: receipt.post_condition_aborted,
+                            vm_error: receipt.vm_error,
+                            vm_error_code: receipt.vm_error_code,
+                            events,
+                            asset_movements: Some(asset_map.to_json()),
+                            execution_cost: Some(receipt.execution_cost),
+                        },
+                    )
+                }
+                Err(chain_error::NoSuchBlockError) => {
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
+                }
+                Err(e) => {
+                    debug!("Failed to simulate transaction {}: {:?}", &txid, &e);
+                    HttpResponseType::SimulateTransaction(
+                        response_metadata,
+                        TransactionSimulationResponse {
+                            okay: false,
+                            cause: Some(e.to_string()),
+                            result: None,
+                            committed: false,
+                            post_condition_aborted: false,
+                            vm_error: None,
+                            vm_error_code: None,
+                            events: vec![],
+                            asset_movements: None,
+                            execution_cost: None,
+                        },
+                    )
+                }
+            };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a block.  Directly submit a Stacks block to this node's chain state.
+    /// Indicate whether or not the block was accepted (i.e. it was new, and valid)
+    fn handle_post_block<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        consensus_hash: &ConsensusHash,
+        block: &StacksBlock,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<bool, net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        // is this a consensus hash we recognize?
+        let (response, accepted) =
+            match SortitionDB::get_sortition_id_by_consensus(&sortdb.conn(), consensus_hash) {
+                Ok(Some(_)) => {
+                    // we recognize this consensus hash
+                    let ic = sortdb.index_conn();
+                    match Relayer::process_new_anchored_block(
+                        &ic,
+                        chainstate,
+                        consensus_hash,
+                        block,
+                        0,
+                    ) {
+                        Ok(true) => {
+                            debug!(
+                                "Accepted Stacks block {}/{}",
+                                consensus_hash,
+                                &block.block_hash()
+                            );
+                            (
+                                HttpResponseType::StacksBlockAccepted(
+                                    response_metadata,
+                                    StacksBlockHeader::make_index_block_hash(
+                                        consensus_hash,
+                                        &block.block_hash(),
+                                    ),
+                                    true,
+                                ),
+                                true,
+                            )
+                        }
+                        Ok(false) => {
+                            debug!(
+                                "Did not accept Stacks block {}/{}",
+                                consensus_hash,
+                                &block.block_hash()
+                            );
+                            (
+                                HttpResponseType::StacksBlockAccepted(
+                                    response_metadata,
+                                    StacksBlockHeader::make_index_block_hash(
+                                        consensus_hash,
+                                        &block.block_hash(),
+                                    ),
+                                    false,
+                                ),
+                                false,
+                            )
+                        }
+                        Err(e) => {
+                            error!(
+                                "Failed to process anchored block {}/{}: {:?}",
+                                consensus_hash,
+                                &block.block_hash(),
+                                &e
+                            );
+                            (
+                                HttpResponseType::ServerError(
+                                    response_metadata,
+                                    format!(
+                                        "Failed to process anchored block {}/{}: {:?}",
+                                        consensus_hash,
+                                        &block.block_hash(),
+                                        &e
+                                    ),
+                                ),
+                                false,
+                            )
+                        }
+                    }
+                }
+                Ok(None) => {
+                    debug!(
+                        "Unrecognized consensus hash {} for block {}",
+                        consensus_hash,
+                        &block.block_hash()
+                    );
+                    (
+                        HttpResponseType::NotFound(
+                            response_metadata,
+                            format!("No such consensus hash '{}'", consensus_hash),
+                        ),
+                        false,
+                    )
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to query sortition ID by consensus '{}'",
+                        consensus_hash
+                    );
+                    (
+                        HttpResponseType::ServerError(
+                            response_metadata,
+                            format!(
+                                "Failed to query sortition ID for consensus hash '{}': {:?}",
+                                consensus_hash, &e
+                            ),
+                        ),
+                        false,
+                    )
+                }
+            };
+        response.send(http, fd).and_then(|_| Ok(accepted))
+    }
+
+    /// Handle a microblock.  Directly submit it to the microblock store so the client can see any
+    /// rejection reasons up-front (different from how the peer network handles it).  Indicate
+    /// whether or not the microblock was accepted (and thus needs to be forwarded) in the return
+    /// value.
+    fn handle_post_microblock<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        microblock: &StacksMicroblock,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<bool, net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+
+        // make sure we can accept this
+        let ch_sn = match SortitionDB::get_block_snapshot_consensus(sortdb.conn(), consensus_hash) {
+            Ok(Some(sn)) => sn,
+            Ok(None) => {
+                let resp = HttpResponseType::NotFound(
+                    response_metadata,
+                    "No such consensus hash".to_string(),
+                );
+                return resp.send(http, fd).and_then(|_| Ok(false));
+            }
+            Err(e) => {
+                let resp = HttpResponseType::BadRequestJSON(
+                    response_metadata,
+                    chain_error::DBError(e).into_json(),
+                );
+                return resp.send(http, fd).and_then(|_| Ok(false));
+            }
+        };
+
+        let sort_handle = sortdb.index_handle(&ch_sn.sortition_id);
+        let parent_block_snapshot =
+            Relayer::get_parent_stacks_block_snapshot(&sort_handle, consensus_hash, block_hash)?;
+        let ast_rules =
+            SortitionDB::get_ast_rules(&sort_handle, parent_block_snapshot.block_height)?;
+        let epoch_id =
+            SortitionDB::get_stacks_epoch(&sort_handle, parent_block_snapshot.block_height)?
+                .expect("FATAL: no epoch defined")
+                .epoch_id;
+
+        let (response, accepted) = if !Relayer::static_check_problematic_relayed_microblock(
+            chainstate.network.mainnet,
+            epoch_id,
+            &chainstate.get_vm_limits(epoch_id),
+            microblock,
+            ast_rules,
+        ) {
+            info!("Microblock {} from {}/{} is problematic; will not store or relay it, nor its descendants", &microblock.block_hash(), consensus_hash, &block_hash);
+            (
+                // NOTE: txid is ignored in chainstate error .into_json()
+                HttpResponseType::BadRequestJSON(
+                    response_metadata,
+                    chain_error::ProblematicTransaction(Txid([0u8; 32])).into_json(),
+                ),
+                false,
+            )
+        } else {
+            match chainstate.preprocess_streamed_microblock(consensus_hash, block_hash, microblock)
+            {
+                Ok(accepted) => {
+                    if accepted {
+                        debug!(
+                            "Accepted uploaded microblock {}/{}-{}",
+                            &consensus_hash,
+                            &block_hash,
+                            &microblock.block_hash()
+                        );
+                    } else {
+                        debug!(
+                            "Did not accept microblock {}/{}-{}",
+                            &consensus_hash,
+                            &block_hash,
+                            &microblock.block_hash()
+                        );
+                    }
+
+                    (
+                        HttpResponseType::MicroblockHash(
+                            response_metadata,
+                            microblock.block_hash(),
+                        ),
+                        accepted,
+                    )
+                }
+                Err(e) => (
+                    HttpResponseType::BadRequestJSON(response_metadata, e.into_json()),
+                    false,
+                ),
+            }
+        };
+
+        response.send(http, fd).and_then(|_| Ok(accepted))
+    }
+
+    /// Handle a request for mempool transactions in bulk
+    fn handle_mempool_query<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        query: MemPoolSyncData,
+        page: &PageRequest,
+        max_txs: u64,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<Option<StreamCursor>, net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let page_id = match page.cursor {
+            Some(ref cursor) => Txid::from_hex(cursor)
+                .map(Some)
+                .map_err(|_| PageError::InvalidCursor(cursor.clone())),
+            None => Ok(None),
+        };
+        let max_txs = page
+            .check_no_offset()
+            .and_then(|_| page.get_limit(max_txs, max_txs));
+        let (page_id, max_txs) = match (page_id, max_txs) {
+            (Ok(page_id), Ok(max_txs)) => (page_id, max_txs),
+            (Err(e), _) | (_, Err(e)) => {
+                let response = HttpResponseType::BadRequest(
+                    response_metadata,
+                    format!("Invalid request: {}", e),
+                );
+                return response.send(http, fd).and_then(|_| Ok(None));
+            }
+        };
+        let response = HttpResponseType::MemPoolTxStream(response_metadata);
+        let height = chainstate
+            .get_stacks_chain_tip(sortdb)?
+            .map(|blk| blk.height)
+            .unwrap_or(0);
+
+        debug!(
+            "Begin mempool query";
+            "page_id" => %page_id.map(|txid| format!("{}", &txid)).unwrap_or("(none".to_string()),
+            "block_height" => height,
+            "max_txs" => max_txs
+        );
+
+        let stream = StreamCursor::new_tx_stream(query, max_txs, height, page_id);
+        response.send(http, fd).and_then(|_| Ok(Some(stream)))
+    }
+
+    /// Handle an external HTTP request.
+    /// Some requests, such as those for blocks, will create new reply streams.  This method adds
+    /// those new streams into the `reply_streams` set.
+    /// Returns a StacksMessageType option -- it's Some(...) if we need to forward a message to the
+    /// peer network (like a transaction or a block or microblock)
+    pub fn handle_request(
+        &mut self,
+        mut req: HttpRequestType,
+        network: &mut PeerNetwork,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        mempool: &mut MemPoolDB,
+        handler_opts: &RPCHandlerArgs,
+    ) -> Result<Option<StacksMessageType>, net_error> {
+        let mut reply = self.connection.make_relay_handle(self.conn_id)?;
+        let keep_alive = req.metadata().keep_alive;
+        let mut ret = None;
+
+        // serve requests made in a read-only session from the session's pinned tip
+        if let Some(tip_req) = req.tip_request_mut() {
+            let pinned_tip = match tip_req {
+                TipRequest::Session(session_id) => network
+                    .rpc_sessions
+                    .get_tip(session_id, get_epoch_time_secs()),
+                _ => None,
+            };
+            if let Some(tip) = pinned_tip {
+                *tip_req = TipRequest::SpecificTip(tip);
+            }
+        }
+
+        let stream_opt = match req {
+            HttpRequestType::GetInfo(ref _md) => {
+                ConversationHttp::handle_getinfo(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network,
+                    chainstate,
+                    handler_opts,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetPoxInfo(ref _md, ref tip_req) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_getpoxinfo(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        &network.burnchain,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetNeighbors(ref _md, ref page) => {
+                ConversationHttp::handle_getneighbors(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network,
+                    page,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetNodeMetadata(ref _md) => {
+                ConversationHttp::handle_get_node_metadata(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetBannedLeaderKeys(ref _md) => {
+                ConversationHttp::handle_get_banned_leader_keys(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetTipSummary(ref _md) => {
+                ConversationHttp::handle_get_tip_summary(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network,
+                    chainstate,
+                    mempool,
+                )?;
+                None
+            }
+            HttpRequestType::GetBlocksSummary(ref _md, ref page) => {
+                ConversationHttp::handle_get_blocks_summary(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network,
+                    sortdb,
+                    chainstate,
+                    page,
+                )?;
+                None
+            }
+            HttpRequestType::GetMempoolSummary(ref _md) => {
+                ConversationHttp::handle_get_mempool_summary(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    mempool,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetChainQuality(ref _md, window) => {
+                ConversationHttp::handle_get_chain_quality(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    chainstate,
+                    window,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetPeersSummary(ref _md, ref page) => {
+                ConversationHttp::handle_get_peers_summary(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network,
+                    page,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::OpenSession(ref _md, ref tip_req) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_open_session(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        &tip,
+                        chainstate,
+                        &mut network.rpc_sessions,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::ReleaseSession(ref _md, ref session_id) => {
+                ConversationHttp::handle_release_session(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    session_id,
+                    &mut network.rpc_sessions,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetHeaders(ref _md, ref page, ref tip_req) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_getheaders(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        &tip,
+                        page,
+                        chainstate,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?
+                } else {
+                    None
+                }
+            }
+            HttpRequestType::StreamBlocks(ref _md, ref request) => {
+                let tip = StacksBlockId::new(
+                    &network.burnchain_tip.canonical_stacks_tip_consensus_hash,
+                    &network.burnchain_tip.canonical_stacks_tip_hash,
+                );
+                ConversationHttp::handle_stream_blocks(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    request,
+                    &tip,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                    chainstate,
+                    &self.connection.options,
+                )?
+            }
+            HttpRequestType::GetBlock(ref _md, ref index_block_hash) => {
+                ConversationHttp::handle_getblock(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    index_block_hash,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?
+            }
+            HttpRequestType::GetBlockWeight(ref _md, ref index_block_hash) => {
+                ConversationHttp::handle_get_block_weight(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    index_block_hash,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetMicroblocksIndexed(ref _md, ref index_head_hash) => {
+                ConversationHttp::handle_getmicroblocks_indexed(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    index_head_hash,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?
+            }
+            HttpRequestType::GetMicroblocksConfirmed(ref _md, ref anchor_index_block_hash) => {
+                ConversationHttp::handle_getmicroblocks_confirmed(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    anchor_index_block_hash,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?
+            }
+            HttpRequestType::GetMicroblocksUnconfirmed(
+                ref _md,
+                ref index_anchor_block_hash,
+                ref min_seq,
+            ) => ConversationHttp::handle_getmicroblocks_unconfirmed(
+                &mut self.connection.protocol,
+                &mut reply,
+                &req,
+                index_anchor_block_hash,
+                *min_seq,
+                chainstate,
+                network.burnchain_tip.canonical_stacks_tip_height,
+            )?,
+            HttpRequestType::GetStagingQueue(ref _md, ref page) => {
+                ConversationHttp::handle_get_staging_queue(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    page,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetMicroblockForks(ref _md, ref index_block_hash) => {
+                ConversationHttp::handle_get_microblock_forks(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    index_block_hash,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetTransactionUnconfirmed(ref _md, ref txid) => {
+                ConversationHttp::handle_gettransaction_unconfirmed(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    chainstate,
+                    mempool,
+                    txid,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetAccount(
+                ref _md,
+                ref principal,
+                ref tip_req,
+                ref with_proof,
+                ref unanchored,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_account_entry(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        principal,
+                        *with_proof,
+                        *unanchored,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetDataVar(
+                ref _md,
+                ref contract_addr,
+                ref contract_name,
+                ref var_name,
+                ref tip_req,
+                ref with_proof,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_data_var(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        contract_addr,
+                        contract_name,
+                        var_name,
+                        *with_proof,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetClarityMarfValue(ref _md, ref key, ref tip_req, ref with_proof) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_clarity_marf_value(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        key,
+                        *with_proof,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetClarityMetadata(
+                ref _md,
+                ref contract_addr,
+                ref contract_name,
+                ref key,
+                ref tip_req,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_clarity_metadata(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        contract_addr,
+                        contract_name,
+                        key,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetMapEntry(
+                ref _md,
+                ref contract_addr,
+                ref contract_name,
+                ref map_name,
+                ref key,
+                ref tip_req,
+                ref with_proof,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_map_entry(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        contract_addr,
+                        contract_name,
+                        map_name,
+                        key,
+                        *with_proof,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetTransferCost(ref _md) => {
+                ConversationHttp::handle_token_transfer_cost(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetContractABI(
+                ref _md,
+                ref contract_addr,
+                ref contract_name,
+                ref tip_req,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_contract_abi(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        contract_addr,
+                        contract_name,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetBlockFeeStats(ref _md, ref page, ref tip_req) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_block_fee_stats(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        &tip,
+                        page,
+                        chainstate,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetSupply(ref _md, height, ref tip_req) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_supply(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        height,
+                        &network.burnchain,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::FeeRateEstimate(ref _md, ref tx, estimated_len) => {
+                ConversationHttp::handle_post_fee_rate_estimate(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    handler_opts,
+                    sortdb,
+                    tx,
+                    estimated_len,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::CallReadOnlyFunction(
+                ref _md,
+                ref ctrct_addr,
+                ref ctrct_name,
+                ref as_sender,
+                ref as_sponsor,
+                ref func_name,
+                ref args,
+                ref tip_req,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    network
+                        .read_only_call_cache
+                        .set_canonical_tip(&StacksBlockId::new(
+                            &network.burnchain_tip.canonical_stacks_tip_consensus_hash,
+                            &network.burnchain_tip.canonical_stacks_tip_hash,
+                        ));
+                    ConversationHttp::handle_readonly_function_call(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        ctrct_addr,
+                        ctrct_name,
+                        func_name,
+                        as_sender,
+                        as_sponsor.as_ref(),
+                        args,
+                        &self.connection.options,
+                        &mut network.read_only_call_cache,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::CallPreviewFunction(
+                ref _md,
+                ref ctrct_addr,
+                ref ctrct_name,
+                ref as_sender,
+                ref as_sponsor,
+                ref func_name,
+                ref args,
+                ref tip_req,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_call_preview(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        ctrct_addr,
+                        ctrct_name,
+                        func_name,
+                        as_sender,
+                        as_sponsor.as_ref(),
+                        args,
+                        &self.connection.options,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetContractSrc(
+                ref _md,
+                ref contract_addr,
+                ref contract_name,
+                ref tip_req,
+                ref with_proof,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_contract_src(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        contract_addr,
+                        contract_name,
+                        *with_proof,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::PostTransaction(ref _md, ref tx, ref attachment) => {
+                match chainstate.get_stacks_chain_tip(sortdb)? {
+                    Some(tip) => {
+                        let accepted = ConversationHttp::handle_post_transaction(
+                            &mut self.connection.protocol,
+                            &mut reply,
+                            &req,
+                            chainstate,
+                            sortdb,
+                            tip.consensus_hash,
+                            tip.anchored_block_hash,
+                            mempool,
+                            tx.clone(),
+                            &mut network.atlasdb,
+                            attachment.clone(),
+                            handler_opts.event_observer.as_deref(),
+                            network.burnchain_tip.canonical_stacks_tip_height,
+                            network.ast_rules,
+                        )?;
+                        if accepted {
+                            // forward to peer network
+                            ret = Some(StacksMessageType::Transaction(tx.clone()));
+                        }
+                    }
+                    None => {
+                        let response_metadata = HttpResponseMetadata::from_http_request_type(
+                            &req,
+                            Some(network.burnchain_tip.canonical_stacks_tip_height),
+                        );
+                        warn!("Failed to load Stacks chain tip");
+                        let response = HttpResponseType::ServerError(
+                            response_metadata,
+                            format!("Failed to load Stacks chain tip"),
+                        );
+                        response.send(&mut self.connection.protocol, &mut reply)?;
+                    }
+                }
+                None
+            }
+            HttpRequestType::PostTransactionBundle(ref _md, ref bundle) => {
+                match chainstate.get_stacks_chain_tip(sortdb)? {
+                    Some(tip) => {
+                        ConversationHttp::handle_post_transaction_bundle(
+                            &mut self.connection.protocol,
+                            &mut reply,
+                            &req,
+                            chainstate,
+                            tip.consensus_hash,
+                            tip.anchored_block_hash,
+                            mempool,
+                            bundle,
+                            &self.connection.options,
+                            network.burnchain_tip.canonical_stacks_tip_height,
+                        )?;
+                    }
+                    None => {
+                        let response_metadata = HttpResponseMetadata::from_http_request_type(
+                            &req,
+                            Some(network.burnchain_tip.canonical_stacks_tip_height),
+                        );
+                        warn!("Failed to load Stacks chain tip");
+                        let response = HttpResponseType::ServerError(
+                            response_metadata,
+                            format!("Failed to load Stacks chain tip"),
+                        );
+                        response.send(&mut self.connection.protocol, &mut reply)?;
+                    }
+                }
+                None
+            }
+            HttpRequestType::SimulateTransaction(ref _md, ref tx, ref tip_req) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_simulate_transaction(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        tx,
+                        network.ast_rules,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetAttachment(ref _md, ref content_hash) => {
+                ConversationHttp::handle_getattachment(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    &mut network.atlasdb,
+                    content_hash.clone(),
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetAttachmentsInv(
+                ref _md,
+                ref index_block_hash,
+                ref pages_indexes,
+            ) => {
+                ConversationHttp::handle_getattachmentsinv(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    &mut network.atlasdb,
+                    &index_block_hash,
+                    pages_indexes,
+                    &self.connection.options,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::PostBlock(ref _md, ref consensus_hash, ref block) => {
+                let accepted = ConversationHttp::handle_post_block(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    chainstate,
+                    consensus_hash,
+                    block,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                if accepted {
+                    // inform the peer network so it can announce its presence
+                    ret = Some(StacksMessageType::Blocks(BlocksData {
+                        blocks: vec![BlocksDatum(consensus_hash.clone(), block.clone())],
+                    }));
+                }
+                None
+            }
+            HttpRequestType::PostMicroblock(ref _md, ref mblock, ref tip_req) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    if let Some((consensus_hash, block_hash)) =
+                        ConversationHttp::handle_load_stacks_chain_tip_hashes(
+                            &mut self.connection.protocol,
+                            &mut reply,
+                            &req,
+                            tip,
+                            chainstate,
+                            network.burnchain_tip.canonical_stacks_tip_height,
+                        )?
+                    {
+                        let accepted = ConversationHttp::handle_post_microblock(
+                            &mut self.connection.protocol,
+                            &mut reply,
+                            &req,
+                            &consensus_hash,
+                            &block_hash,
+                            sortdb,
+                            chainstate,
+                            mblock,
+                            network.burnchain_tip.canonical_stacks_tip_height,
+                        )?;
+                        if accepted {
+                            // forward to peer network
+                            let tip = StacksBlockHeader::make_index_block_hash(
+                                &consensus_hash,
+                                &block_hash,
+                            );
+                            ret = Some(StacksMessageType::Microblocks(MicroblocksData {
+                                index_anchor_block: tip,
+                                microblocks: vec![(*mblock).clone()],
+                            }));
+                        }
+                    }
+                }
+                None
+            }
+            HttpRequestType::MemPoolQuery(ref _md, ref query, ref page) => {
+                ConversationHttp::handle_mempool_query(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    chainstate,
+                    query.clone(),
+                    page,
+                    network.connection_opts.mempool_max_tx_query,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?
+            }
+            HttpRequestType::OptionsPreflight(ref _md, ref _path) => {
+                let response_metadata = HttpResponseMetadata::from_http_request_type(
+                    &req,
+                    Some(network.burnchain_tip.canonical_stacks_tip_height),
+                );
+                let response = HttpResponseType::OptionsPreflight(response_metadata);
+                response
+                    .send(&mut self.connection.protocol, &mut reply)
+                    .map(|_| ())?;
+                None
+            }
+            HttpRequestType::GetIsTraitImplemented(
+                ref _md,
+                ref contract_addr,
+                ref contract_name,
+                ref trait_id,
+                ref tip_req,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_is_trait_implemented(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        contract_addr,
+                        contract_name,
+                        trait_id,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::ClientError(ref _md, ref err) => {
+                let response_metadata = HttpResponseMetadata::from_http_request_type(
+                    &req,
+                    Some(network.burnchain_tip.canonical_stacks_tip_height),
+                );
+                let response = match err {
+                    ClientError::Message(s) => HttpResponseType::BadRequestJSON(
+                        response_metadata,
+                        serde_json::Value::String(s.to_string()),
+                    ),
+                    ClientError::NotFound(path) => {
+                        HttpResponseType::NotFound(response_metadata, path.clone())
+                    }
+                };
+
+                response
+                    .send(&mut self.connection.protocol, &mut reply)
+                    .map(|_| ())?;
+                None
+            }
+        };
+
+        match stream_opt {
+            None => {
+                self.reply_streams.push_back((reply, None, keep_alive));
+            }
+            Some(stream) => {
+                self.reply_streams.push_back((
+                    reply,
+                    Some((
+                        HttpChunkedTransferWriterState::new(STREAM_CHUNK_SIZE as usize),
+                        stream,
+                    )),
+                    keep_alive,
+                ));
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Make progress on outbound requests.
+    fn send_outbound_responses(
+        &mut self,
+        mempool: &MemPoolDB,
+        chainstate: &mut StacksChainState,
+    ) -> Result<(), net_error> {
+        // send out streamed responses in the order they were requested
+        let mut drained_handle = false;
+        let mut drained_stream = false;
+        let mut broken = false;
+        let mut do_keep_alive = true;
+
+        test_debug!(
+            "{:?}: {} HTTP replies pending",
+            &self,
+            self.reply_streams.len()
+        );
+        let _self_str = format!("{}", &self);
+
+        match self.reply_streams.front_mut() {
+            Some((ref mut reply, ref mut stream_opt, ref keep_alive)) => {
+                do_keep_alive = *keep_alive;
+
+                // if we're streaming, make some progress on the stream
+                match stream_opt {
+                    Some((ref mut http_chunk_state, ref mut stream)) => {
+                        let mut encoder =
+                            HttpChunkedTransferWriter::from_writer_state(reply, http_chunk_state);
+                        match stream.stream_to(mempool, chainstate, &mut encoder, STREAM_CHUNK_SIZE)
+                        {
+                            Ok(nw) => {
+                                test_debug!("{}: Streamed {} bytes", &_self_str, nw);
+                                if stream.is_live() {
+                                    // the stream may have nothing more to send for a while, so
+                                    // don't hold on to what it sent in the chunk buffer
+                                    encoder.send_buffered_chunk().map_err(|e| {
+                                        test_debug!(
+                                            "{}: Write error on encoder send: {:?}",
+                                            &_self_str,
+                                            &e
+                                        );
+                                        net_error::WriteError(e)
+                                    })?;
+                                    if let Err(e) = reply.try_flush() {
+                                        // dead
+                                        warn!("{}: Broken HTTP connection: {:?}", &_self_str, &e);
+                                        broken = true;
+                                    }
+                                } else if nw == 0 {
+                                    // EOF -- finish chunk and stop sending.
+                                    if !encoder.corked() {
+                                        encoder.flush().map_err(|e| {
+                                            test_debug!(
+                                                "{}: Write error on encoder flush: {:?}",
+                                                &_self_str,
+                                                &e
+                                            );
+                                            net_error::WriteError(e)
+                                        })?;
+
+                                        encoder.cork();
+
+                                        test_debug!("{}: Stream indicates EOF", &_self_str);
+                                    }
+
+                                    // try moving some data to the connection only once we're done
+                                    // streaming
+                                    match reply.try_flush() {
+                                        Ok(res) => {
+                                            test_debug!(
+                                                "{}: Streamed reply is drained?: {}",
+                                                &_self_str,
+                                                res
+                                            );
+                                            drained_handle = res;
+                                        }
+                                        Err(e) => {
+                                            // dead
+                                            warn!(
+                                                "{}: Broken HTTP connection: {:?}",
+                                                &_self_str, &e
+                                            );
+                                            broken = true;
+                                        }
+                                    }
+                                    drained_stream = true;
+                                }
+                            }
+                            Err(e) => {
+                                // broken -- terminate the stream.
+                                // For example, if we're streaming an unconfirmed block or
+                                // microblock, the data can get moved to the chunk store out from
+                                // under the stream.
+                                warn!(
+                                    "{}: Failed to send to HTTP connection: {:?}",
+                                    &_self_str, &e
+                                );
+                                broken = true;
+                            }
+                        }
+                    }
+                    None => {
+                        // not streamed; all data is buffered
+                        drained_stream = true;
+
+                        // try moving some data to the connection
+                        match reply.try_flush() {
+                            Ok(res) => {
+                                test_debug!("{}: Reply is drained", &_self_str);
+                                drained_handle = res;
+                            }
+                            Err(e) => {
+                                // dead
+                                warn!("{}: Broken HTTP connection: {:?}", &_self_str, &e);
+                                broken = true;
+                            }
+                        }
+                    }
+                }
+            }
+            None => {}
+        }
+
+        if broken || (drained_handle && drained_stream) {
+            // done with this stream
+            test_debug!(
+                "{:?}: done with stream (broken={}, drained_handle={}, drained_stream={})",
+                &self,
+                broken,
+                drained_handle,
+                drained_stream
+            );
+            self.total_reply_count += 1;
+            self.reply_streams.pop_front();
+
+            if !do_keep_alive {
+                // encountered "Connection: close"
+                self.keep_alive = false;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn try_send_recv_response(
+        req: ReplyHandleHttp,
+    ) -> Result<HttpResponseType, Result<ReplyHandleHttp, net_error>> {
+        match req.try_send_recv() {
+            Ok(message) => match message {
+                StacksHttpMessage::Request(_) => {
+                    warn!("Received response: not a HTTP response");
+                    return Err(Err(net_error::InvalidMessage));
+                }
+                StacksHttpMessage::Response(http_response) => Ok(http_response),
+            },
+            Err(res) => Err(res),
+        }
+    }
+
+    /// Make progress on our request/response
+    fn recv_inbound_response(&mut self) -> Result<(), net_error> {
+        // make progress on our pending request (if it exists).
+        let inprogress = self.pending_request.is_some();
+        let is_pending = self.pending_response.is_none();
+
+        let pending_request = self.pending_request.take();
+        let response = match pending_request {
+            None => Ok(self.pending_response.take()),
+            Some(req) => match ConversationHttp::try_send_recv_response(req) {
+                Ok(response) => Ok(Some(response)),
+                Err(res) => match res {
+                    Ok(handle) => {
+                        // try again
+                        self.pending_request = Some(handle);
+                        Ok(self.pending_response.take())
+                    }
+                    Err(e) => Err(e),
+                },
+            },
+        }?;
+
+        self.pending_response = response;
+
+        if inprogress && self.pending_request.is_none() {
+            test_debug!(
+                "{:?},id={}: HTTP request finished",
+                &self.peer_host,
+                self.conn_id
+            );
+        }
+
+        if is_pending && self.pending_response.is_some() {
+            test_debug!(
+                "{:?},id={}: HTTP response finished",
+                &self.peer_host,
+                self.conn_id
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Try to get our response
+    pub fn try_get_response(&mut self) -> Option<HttpResponseType> {
+        self.pending_response.take()
+    }
+
+    /// Make progress on in-flight messages.
+    pub fn try_flush(
+        &mut self,
+        mempool: &MemPoolDB,
+        chainstate: &mut StacksChainState,
+    ) -> Result<(), net_error> {
+        self.send_outbound_responses(mempool, chainstate)?;
+        self.recv_inbound_response()?;
+        Ok(())
+    }
+
+    /// Is this conversation sending a stream that can produce more data later, even though it has
+    /// nothing to send right now?  Such conversations need to be flushed on every pass of the
+    /// event loop, and not just when their sockets become ready.
+    pub fn has_live_stream(&self) -> bool {
+        self.reply_streams
+            .iter()
+            .any(|(_, stream_opt, _)| match stream_opt {
+                Some((_, stream)) => stream.is_live(),
+                None => false,
+            })
+    }
+
+    /// Tell any block feeds this conversation is streaming about the canonical Stacks chain tip
+    pub fn update_block_feeds(&mut self, tip: &StacksBlockId, tip_height: u64) {
+        for (_, stream_opt, _) in self.reply_streams.iter_mut() {
+            if let Some((_, ref mut stream)) = stream_opt {
+                stream.set_chain_tip(tip, tip_height);
+            }
+        }
+    }
+
+    /// Is the connection idle?
+    pub fn is_idle(&self) -> bool {
+        self.pending_response.is_none()
+            && self.connection.inbox_len() == 0
+            && self.connection.outbox_len() == 0
+            && self.reply_streams.len() == 0
+    }
+
+    /// Is the conversation out of pending data?
+    /// Don't consider it drained if we haven't received anything yet
+    pub fn is_drained(&self) -> bool {
+        ((self.total_request_count > 0 && self.total_reply_count > 0)
+            || self.pending_error_response.is_some())
+            && self.is_idle()
+    }
+
+    /// Should the connection be kept alive even if drained?
+    pub fn is_keep_alive(&self) -> bool {
+        self.keep_alive
+    }
+
+    /// When was the last time we got an inbound request?
+    pub fn get_last_request_time(&self) -> u64 {
+        self.last_request_timestamp
+    }
+
+    /// When was the last time we sent data as part of an outbound response?
+    pub fn get_last_response_time(&self) -> u64 {
+        self.last_response_timestamp
+    }
+
+    /// When was this converation conencted?
+    pub fn get_connection_time(&self) -> u64 {
+        self.connection_time
+    }
+
+    /// Make progress on in-flight requests and replies.
+    /// Returns the list of transactions we'll need to forward to the peer network
+    pub fn chat(
+        &mut self,
+        network: &mut PeerNetwork,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        mempool: &mut MemPoolDB,
+        handler_args: &RPCHandlerArgs,
+    ) -> Result<Vec<StacksMessageType>, net_error> {
+        // if we have an in-flight error, then don't take any more requests.
+        if self.pending_error_response.is_some() {
+            return Ok(vec![]);
+        }
+
+        // handle in-bound HTTP request(s)
+        let num_inbound = self.connection.inbox_len();
+        let mut ret = vec![];
+        test_debug!("{:?}: {} HTTP requests pending", &self, num_inbound);
+
+        for _i in 0..num_inbound {
+            let msg = match self.connection.next_inbox_message() {
+                None => {
+                    continue;
+                }
+                Some(m) => m,
+            };
+
+            match msg {
+                StacksHttpMessage::Request(req) => {
+                    // new request
+                    self.total_request_count += 1;
+                    self.last_request_timestamp = get_epoch_time_secs();
+                    if req.metadata().canonical_stacks_tip_height.is_some() {
+                        test_debug!(
+                            "Request metadata: canonical stacks tip height is {:?}",
+                            &req.metadata().canonical_stacks_tip_height
+                        );
+                        self.canonical_stacks_tip_height =
+                            req.metadata().canonical_stacks_tip_height;
+                    }
+                    let start_time = Instant::now();
+                    let path = req.get_path();
+                    let msg_opt = monitoring::instrument_http_request_handler(req, |req| {
+                        self.handle_request(req, network, sortdb, chainstate, mempool, handler_args)
+                    })?;
+
+                    debug!("Processed HTTPRequest"; "path" => %path, "processing_time_ms" => start_time.elapsed().as_millis(), "conn_id" => self.conn_id, "peer_addr" => &self.peer_addr);
+
+                    if let Some(msg) = msg_opt {
+                        ret.push(msg);
+                    }
+                }
+                StacksHttpMessage::Response(resp) => {
+                    // Is there someone else waiting for this message?  If so, pass it along.
+                    // (this _should_ be our pending_request handle)
+                    if resp.metadata().canonical_stacks_tip_height.is_some() {
+                        test_debug!(
+                            "Response metadata: canonical stacks tip height is {:?}",
+                            &resp.metadata().canonical_stacks_tip_height
+                        );
+                        self.canonical_stacks_tip_height =
+                            resp.metadata().canonical_stacks_tip_height;
+                    }
+                    match self
+                        .connection
+                        .fulfill_request(StacksHttpMessage::Response(resp))
+                    {
+                        None => {
+                            test_debug!("{:?}: Fulfilled pending HTTP request", &self);
+                        }
+                        Some(_msg) => {
+                            // unsolicited; discard
+                            test_debug!("{:?}: Dropping unsolicited HTTP response", &self);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Remove all timed-out messages, and ding the remote peer as unhealthy
+    pub fn clear_timeouts(&mut self) -> () {
+        self.connection.drain_timeouts();
+    }
+
+    /// Load data into our HTTP connection
+    pub fn recv<R: Read>(&mut self, r: &mut R) -> Result<usize, net_error> {
+        let mut total_recv = 0;
+        loop {
+            let nrecv = match self.connection.recv_data(r) {
+                Ok(nr) => nr,
+                Err(e) => {
+                    debug!("{:?}: failed to recv: {:?}", self, &e);
+                    return Err(e);
+                }
+            };
+
+            total_recv += nrecv;
+            if nrecv > 0 {
+                self.last_request_timestamp = get_epoch_time_secs();
+            } else {
+                break;
+            }
+        }
+        monitoring::update_inbound_rpc_bandwidth(total_recv as i64);
+        Ok(total_recv)
+    }
+
+    /// Write data out of our HTTP connection.  Write as much as we can
+    pub fn send<W: Write>(
+        &mut self,
+        w: &mut W,
+        mempool: &MemPoolDB,
+        chainstate: &mut StacksChainState,
+    ) -> Result<usize, net_error> {
+        let mut total_sz = 0;
+        loop {
+            // prime the Write
+            self.try_flush(mempool, chainstate)?;
+
+            let sz = match self.connection.send_data(w) {
+                Ok(sz) => sz,
+                Err(e) => {
+                    info!("{:?}: failed to send on HTTP conversation: {:?}", self, &e);
+                    return Err(e);
+                }
+            };
+
+            total_sz += sz;
+            if sz > 0 {
+                self.last_response_timestamp = get_epoch_time_secs();
+            } else {
+                break;
+            }
+        }
+        monitoring::update_inbound_rpc_bandwidth(total_sz as i64);
+        Ok(total_sz)
+    }
+
+    /// Make a new getinfo request to this endpoint
+    pub fn new_getinfo(&self, stacks_height: Option<u64>) -> HttpRequestType {
+        HttpRequestType::GetInfo(HttpRequestMetadata::from_host(
+            self.peer_host.clone(),
+            stacks_height,
+        ))
+    }
+
+    /// Make a new getinfo request to this endpoint
+    pub fn new_getpoxinfo(&self, tip_req: TipRequest) -> HttpRequestType {
+        HttpRequestType::GetPoxInfo(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            tip_req,
+        )
+    }
+
+    /// Make a new getneighbors request to this endpoint
+    pub fn new_getneighbors(&self, page: PageRequest) -> HttpRequestType {
+        HttpRequestType::GetNeighbors(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            page,
+        )
+    }
+
+    /// Make a new get-node-metadata request to this endpoint
+    pub fn new_get_node_metadata(&self) -> HttpRequestType {
+        HttpRequestType::GetNodeMetadata(HttpRequestMetadata::from_host(
+            self.peer_host.clone(),
+            None,
+        ))
+    }
+
+    /// Make a new get-banned-leader-keys request to this endpoint
+    pub fn new_get_banned_leader_keys(&self) -> HttpRequestType {
+        HttpRequestType::GetBannedLeaderKeys(HttpRequestMetadata::from_host(
+            self.peer_host.clone(),
+            None,
+        ))
+    }
+
+    /// Make a new get-tip-summary request to this endpoint
+    pub fn new_get_tip_summary(&self) -> HttpRequestType {
+        HttpRequestType::GetTipSummary(HttpRequestMetadata::from_host(self.peer_host.clone(), None))
+    }
+
+    /// Make a new get-blocks-summary request to this endpoint
+    pub fn new_get_blocks_summary(&self, page: PageRequest) -> HttpRequestType {
+        HttpRequestType::GetBlocksSummary(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            page,
+        )
+    }
+
+    /// Make a new get-mempool-summary request to this endpoint
+    pub fn new_get_mempool_summary(&self) -> HttpRequestType {
+        HttpRequestType::GetMempoolSummary(HttpRequestMetadata::from_host(
+            self.peer_host.clone(),
+            None,
+        ))
+    }
+
+    /// Make a new get-chain-quality request to this endpoint
+    pub fn new_get_chain_quality(&self, window: Option<u64>) -> HttpRequestType {
+        HttpRequestType::GetChainQuality(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            window,
+        )
+    }
+
+    /// Make a new get-peers-summary request to this endpoint
+    pub fn new_get_peers_summary(&self, page: PageRequest) -> HttpRequestType {
+        HttpRequestType::GetPeersSummary(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            page,
+        )
+    }
+
+    /// Make a new request to open a read-only session pinned to a chain tip
+    pub fn new_open_session(&self, tip_req: TipRequest) -> HttpRequestType {
+        HttpRequestType::OpenSession(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            tip_req,
+        )
+    }
+
+    /// Make a new request to release a read-only session
+    pub fn new_release_session(&self, session_id: RPCSessionId) -> HttpRequestType {
+        HttpRequestType::ReleaseSession(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            session_id,
+        )
+    }
+
+    /// Make a new getheaders request to this endpoint
+    pub fn new_getheaders(&self, page: PageRequest, tip_req: TipRequest) -> HttpRequestType {
+        HttpRequestType::GetHeaders(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            page,
+            tip_req,
+        )
+    }
+
+    /// Make a new request for the fee statistics of a page of blocks
+    pub fn new_get_block_fee_stats(
+        &self,
+        page: PageRequest,
+        tip_req: TipRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::GetBlockFeeStats(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            page,
+            tip_req,
+        )
+    }
+
+    /// Make a new request for the STX supply at the block at `height`
+    pub fn new_get_supply(&self, height: u64, tip_req: TipRequest) -> HttpRequestType {
+        HttpRequestType::GetSupply(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            height,
+            tip_req,
+        )
+    }
+
+    /// Make a new request to this endpoint for a feed of blocks
+    pub fn new_stream_blocks(&self, request: BlockFeedRequest) -> HttpRequestType {
+        HttpRequestType::StreamBlocks(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            request,
+        )
+    }
+
+    /// Make a new getblock request to this endpoint
+    pub fn new_getblock(&self, index_block_hash: StacksBlockId) -> HttpRequestType {
+        HttpRequestType::GetBlock(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            index_block_hash,
+        )
+    }
+
+    /// Make a new get-block-weight request to this endpoint
+    pub fn new_get_block_weight(&self, index_block_hash: StacksBlockId) -> HttpRequestType {
+        HttpRequestType::GetBlockWeight(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            index_block_hash,
+        )
+    }
+
+    /// Make a new get-microblocks request to this endpoint
+    pub fn new_getmicroblocks_indexed(
+        &self,
+        index_microblock_hash: StacksBlockId,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMicroblocksIndexed(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            index_microblock_hash,
+        )
+    }
+
+    /// Make a new get-microblocks-confirmed request to this endpoint
+    pub fn new_getmicroblocks_confirmed(
+        &self,
+        index_anchor_block_hash: StacksBlockId,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMicroblocksConfirmed(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            index_anchor_block_hash,
+        )
+    }
+
+    /// Make a new get-microblocks request for unconfirmed microblocks
+    pub fn new_getmicroblocks_unconfirmed(
+        &self,
+        anchored_index_block_hash: StacksBlockId,
+        min_seq: u16,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMicroblocksUnconfirmed(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            anchored_index_block_hash,
+            min_seq,
+        )
+    }
+
+    /// Make a new get-staging-queue request to this endpoint
+    pub fn new_get_staging_queue(&self, page: PageRequest) -> HttpRequestType {
+        HttpRequestType::GetStagingQueue(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            page,
+        )
+    }
+
+    /// Make a new get-microblock-forks request to this endpoint
+    pub fn new_get_microblock_forks(&self, index_block_hash: StacksBlockId) -> HttpRequestType {
+        HttpRequestType::GetMicroblockForks(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            index_block_hash,
+        )
+    }
+
+    /// Make a new get-unconfirmed-tx request
+    pub fn new_gettransaction_unconfirmed(&self, txid: Txid) -> HttpRequestType {
+        HttpRequestType::GetTransactionUnconfirmed(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            txid,
+        )
+    }
+
+    /// Make a new post-transaction request
+    pub fn new_post_transaction(&self, tx: StacksTransaction) -> HttpRequestType {
+        HttpRequestType::PostTransaction(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            tx,
+            None,
+        )
+    }
+
+    /// Make a new post-transaction-bundle request
+    pub fn new_post_transaction_bundle(&self, bundle: StacksTransactionBundle) -> HttpRequestType {
+        HttpRequestType::PostTransactionBundle(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            bundle,
+        )
+    }
+
+    /// Make a new request to simulate a transaction
+    pub fn new_simulate_transaction(
+        &self,
+        tx: StacksTransaction,
+        tip_req: TipRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::SimulateTransaction(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            tx,
+            tip_req,
+        )
+    }
+
+    /// Make a new post-block request
+    pub fn new_post_block(&self, ch: ConsensusHash, block: StacksBlock) -> HttpRequestType {
+        HttpRequestType::PostBlock(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            ch,
+            block,
+        )
+    }
+
+    /// Make a new post-microblock request
+    pub fn new_post_microblock(
+        &self,
+        mblock: StacksMicroblock,
+        tip_req: TipRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::PostMicroblock(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            mblock,
+            tip_req,
+        )
+    }
+
+    /// Make a new request for an account
+    pub fn new_getaccount(
+        &self,
+        principal: PrincipalData,
+        tip_req: TipRequest,
+        with_proof: bool,
+    ) -> HttpRequestType {
+        HttpRequestType::GetAccount(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            principal,
+            tip_req,
+            with_proof,
+            false,
+        )
+    }
+
+    /// Make a new request for an account's anchored state, along with its state once the
+    /// unconfirmed microblock stream is applied
+    pub fn new_getaccount_unanchored(
+        &self,
+        principal: PrincipalData,
+        tip_req: TipRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::GetAccount(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            principal,
+            tip_req,
+            false,
+            true,
+        )
+    }
+
+    /// Make a new request for a data var
+    pub fn new_getdatavar(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        var_name: ClarityName,
+        tip_req: TipRequest,
+        with_proof: bool,
+    ) -> HttpRequestType {
+        HttpRequestType::GetDataVar(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            contract_addr,
+            contract_name,
+            var_name,
+            tip_req,
+            with_proof,
+        )
+    }
+
+    /// Make a new request for a data map
+    pub fn new_getmapentry(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        map_name: ClarityName,
+        key: Value,
+        tip_req: TipRequest,
+        with_proof: bool,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMapEntry(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            contract_addr,
+            contract_name,
+            map_name,
+            key,
+            tip_req,
+            with_proof,
+        )
+    }
+
+    /// Make a new request for a raw value in the Clarity key-value store
+    pub fn new_getclaritymarfvalue(
+        &self,
+        key: String,
+        tip_req: TipRequest,
+        with_proof: bool,
+    ) -> HttpRequestType {
+        HttpRequestType::GetClarityMarfValue(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            key,
+            tip_req,
+            with_proof,
+        )
+    }
+
+    /// Make a new request for a raw metadata entry of a contract
+    pub fn new_getclaritymetadata(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        key: String,
+        tip_req: TipRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::GetClarityMetadata(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            contract_addr,
+            contract_name,
+            key,
+            tip_req,
+        )
+    }
+
+    /// Make a new request to get a contract's source
+    pub fn new_getcontractsrc(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        tip_req: TipRequest,
+        with_proof: bool,
+    ) -> HttpRequestType {
+        HttpRequestType::GetContractSrc(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            contract_addr,
+            contract_name,
+            tip_req,
+            with_proof,
+        )
+    }
+
+    /// Make a new request to get a contract's ABI
+    pub fn new_getcontractabi(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        tip_req: TipRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::GetContractABI(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            contract_addr,
+            contract_name,
+            tip_req,
+        )
+    }
+
+    /// Make a new request to run a read-only function
+    pub fn new_callreadonlyfunction(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        sender: PrincipalData,
+        sponsor: Option<PrincipalData>,
+        function_name: ClarityName,
+        function_args: Vec<Value>,
+        tip_req: TipRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::CallReadOnlyFunction(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            contract_addr,
+            contract_name,
+            sender,
+            sponsor,
+            function_name,
+            function_args,
+            tip_req,
+        )
+    }
+
+    /// Make a new request to preview a contract call
+    pub fn new_callpreviewfunction(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        sender: PrincipalData,
+        sponsor: Option<PrincipalData>,
+        function_name: ClarityName,
+        function_args: Vec<Value>,
+        tip_req: TipRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::CallPreviewFunction(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            contract_addr,
+            contract_name,
+            sender,
+            sponsor,
+            function_name,
+            function_args,
+            tip_req,
+        )
+    }
+
+    /// Make a new request for attachment inventory page
+    pub fn new_getattachmentsinv(
+        &self,
+        index_block_hash: StacksBlockId,
+        pages_indexes: HashSet<u32>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetAttachmentsInv(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            index_block_hash,
+            pages_indexes,
+        )
+    }
+
+    /// Make a new request for mempool contents
+    pub fn new_mempool_query(&self, query: MemPoolSyncData, page: PageRequest) -> HttpRequestType {
+        HttpRequestType::MemPoolQuery(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            query,
+            page,
+        )
+    }
+}
 
-                response
-                    .send(&mut self.connection.protocol, &mut reply)
-                    .map(|_| ())?;
-                None
-            }
-        };
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::convert::TryInto;
+    use std::iter::FromIterator;
 
-        match stream_opt {
-            None => {
-                self.reply_streams.push_back((reply, None, keep_alive));
-            }
-            Some(stream) => {
-                self.reply_streams.push_back((
-                    reply,
-                    Some((
-                        HttpChunkedTransferWriterState::new(STREAM_CHUNK_SIZE as usize),
-                        stream,
-                    )),
-                    keep_alive,
-                ));
+    use crate::burnchains::bitcoin::indexer::BitcoinIndexer;
+    use crate::burnchains::Burnchain;
+    use crate::burnchains::BurnchainView;
+    use crate::burnchains::*;
+    use crate::chainstate::burn::ConsensusHash;
+    use crate::chainstate::stacks::db::blocks::test::*;
+    use crate::chainstate::stacks::db::StacksChainState;
+    use crate::chainstate::stacks::db::StreamCursor;
+    use crate::chainstate::stacks::db::{BlockWeight, TxWeight};
+    use crate::chainstate::stacks::miner::*;
+    use crate::chainstate::stacks::test::*;
+    use crate::chainstate::stacks::Error as chain_error;
+    use crate::chainstate::stacks::*;
+    use crate::net::codec::*;
+    use crate::net::http::*;
+    use crate::net::test::*;
+    use crate::net::*;
+    use clarity::vm::types::*;
+    use stacks_common::address::*;
+    use stacks_common::util::get_epoch_time_secs;
+    use stacks_common::util::hash::hex_bytes;
+    use stacks_common::util::pipe::*;
+    use stacks_common::util::secp256k1::{Secp256k1PrivateKey, Secp256k1PublicKey};
+    use stacks_common::util::vrf::{VRFPrivateKey, VRFPublicKey};
+
+    use crate::chainstate::stacks::C32_ADDRESS_VERSION_TESTNET_SINGLESIG;
+    use crate::types::chainstate::BlockHeaderHash;
+    use crate::types::chainstate::BurnchainHeaderHash;
+
+    use crate::core::mempool::{BLOOM_COUNTER_ERROR_RATE, MAX_BLOOM_COUNTER_TXS};
+
+    use super::*;
+
+    const TEST_CONTRACT: &'static str = "
+        (define-data-var bar int 0)
+        (define-map unit-map { account: principal } { units: int })
+        (define-public (get-bar) (ok (var-get bar)))
+        (define-public (set-bar (x int) (y int))
+          (begin (var-set bar (/ x y)) (ok (var-get bar))))
+        (define-public (add-unit)
+          (begin
+            (map-set unit-map { account: tx-sender } { units: 1 } )
+            (var-set bar 1)
+            (ok 1)))
+        (begin
+          (map-set unit-map { account: 'ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R } { units: 123 }))";
+
+    const TEST_CONTRACT_UNCONFIRMED: &'static str = "(define-read-only (ro-test) (ok 1))";
+
+    fn convo_send_recv(
+        sender: &mut ConversationHttp,
+        sender_mempool: &MemPoolDB,
+        sender_chainstate: &mut StacksChainState,
+        receiver: &mut ConversationHttp,
+        receiver_mempool: &MemPoolDB,
+        receiver_chainstate: &mut StacksChainState,
+    ) -> () {
+        let (mut pipe_read, mut pipe_write) = Pipe::new();
+        pipe_read.set_nonblocking(true);
+
+        loop {
+            let res = true;
+
+            sender.try_flush(sender_mempool, sender_chainstate).unwrap();
+            receiver
+                .try_flush(sender_mempool, receiver_chainstate)
+                .unwrap();
+
+            pipe_write.try_flush().unwrap();
+
+            let all_relays_flushed =
+                receiver.num_pending_outbound() == 0 && sender.num_pending_outbound() == 0;
+
+            let nw = sender
+                .send(&mut pipe_write, sender_mempool, sender_chainstate)
+                .unwrap();
+            let nr = receiver.recv(&mut pipe_read).unwrap();
+
+            test_debug!(
+                "res = {}, all_relays_flushed = {} ({},{}), nr = {}, nw = {}",
+                res,
+                all_relays_flushed,
+                receiver.num_pending_outbound(),
+                sender.num_pending_outbound(),
+                nr,
+                nw
+            );
+            if res && all_relays_flushed && nr == 0 && nw == 0 {
+                test_debug!("Breaking send_recv");
+                break;
             }
         }
-        Ok(ret)
     }
 
-    /// Make progress on outbound requests.
-    fn send_outbound_responses(
-        &mut self,
-        mempool: &MemPoolDB,
-        chainstate: &mut StacksChainState,
-    ) -> Result<(), net_error> {
-        // send out streamed responses in the order they were requested
-        let mut drained_handle = false;
-        let mut drained_stream = false;
-        let mut broken = false;
-        let mut do_keep_alive = true;
+    /// General testing function to test RPC calls.
+    /// This function sets up two peers, a client and a server.
+    /// It takes in a function of type F that generates the request to be sent to the server
+    /// It takes in another function of type C that verifies that the result from
+    /// the server is as expected.
+    /// The parameter `include_microblocks` determines whether a microblock stream is mined or not.
+    fn test_rpc<F, C>(
+        test_name: &str,
+        peer_1_p2p: u16,
+        peer_1_http: u16,
+        peer_2_p2p: u16,
+        peer_2_http: u16,
+        include_microblocks: bool,
+        make_request: F,
+        check_result: C,
+    ) -> ()
+    where
+        F: FnOnce(
+            &mut TestPeer,
+            &mut ConversationHttp,
+            &mut TestPeer,
+            &mut ConversationHttp,
+        ) -> HttpRequestType,
+        C: FnOnce(
+            &HttpRequestType,
+            &HttpResponseType,
+            &mut TestPeer,
+            &mut TestPeer,
+            &ConversationHttp,
+            &ConversationHttp,
+        ) -> bool,
+    {
+        let mut peer_1_config = TestPeerConfig::new(test_name, peer_1_p2p, peer_1_http);
+        let mut peer_2_config = TestPeerConfig::new(test_name, peer_2_p2p, peer_2_http);
 
-        test_debug!(
-            "{:?}: {} HTTP replies pending",
-            &self,
-            self.reply_streams.len()
-        );
-        let _self_str = format!("{}", &self);
+        let peer_1_indexer = BitcoinIndexer::new_unit_test(&peer_1_config.burnchain.working_dir);
+        let peer_2_indexer = BitcoinIndexer::new_unit_test(&peer_2_config.burnchain.working_dir);
 
-        match self.reply_streams.front_mut() {
-            Some((ref mut reply, ref mut stream_opt, ref keep_alive)) => {
-                do_keep_alive = *keep_alive;
+        // ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R
+        let privk1 = StacksPrivateKey::from_hex(
+            "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
+        )
+        .unwrap();
 
-                // if we're streaming, make some progress on the stream
-                match stream_opt {
-                    Some((ref mut http_chunk_state, ref mut stream)) => {
-                        let mut encoder =
-                            HttpChunkedTransferWriter::from_writer_state(reply, http_chunk_state);
-                        match stream.stream_to(mempool, chainstate, &mut encoder, STREAM_CHUNK_SIZE)
-                        {
-                            Ok(nw) => {
-                                test_debug!("{}: Streamed {} bytes", &_self_str, nw);
-                                if nw == 0 {
-                                    // EOF -- finish chunk and stop sending.
-                                    if !encoder.corked() {
-                                        encoder.flush().map_err(|e| {
-                                            test_debug!(
-                                                "{}: Write error on encoder flush: {:?}",
-                                                &_self_str,
-                                                &e
-                                            );
-                                            net_error::WriteError(e)
-                                        })?;
+        // STVN97YYA10MY5F6KQJHKNYJNM24C4A1AT39WRW
+        let privk2 = StacksPrivateKey::from_hex(
+            "94c319327cc5cd04da7147d32d836eb2e4c44f4db39aa5ede7314a761183d0c701",
+        )
+        .unwrap();
+        let microblock_privkey = StacksPrivateKey::new();
+        let microblock_pubkeyhash =
+            Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
 
-                                        encoder.cork();
+        let addr1 = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk1)],
+        )
+        .unwrap();
+        let addr2 = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk2)],
+        )
+        .unwrap();
+
+        peer_1_config.initial_balances = vec![
+            (addr1.to_account_principal(), 1000000000),
+            (addr2.to_account_principal(), 1000000000),
+        ];
+
+        peer_2_config.initial_balances = vec![
+            (addr1.to_account_principal(), 1000000000),
+            (addr2.to_account_principal(), 1000000000),
+        ];
+
+        peer_1_config.add_neighbor(&peer_2_config.to_neighbor());
+        peer_2_config.add_neighbor(&peer_1_config.to_neighbor());
+
+        let mut peer_1 = TestPeer::new(peer_1_config);
+        let mut peer_2 = TestPeer::new(peer_2_config);
+
+        // mine one block with a contract in it
+        // first the coinbase
+        // make a coinbase for this miner
+        let mut tx_coinbase = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk1).unwrap(),
+            TransactionPayload::Coinbase(CoinbasePayload([0x00; 32]), None),
+        );
+        tx_coinbase.chain_id = 0x80000000;
+        tx_coinbase.anchor_mode = TransactionAnchorMode::OnChainOnly;
+        tx_coinbase.auth.set_origin_nonce(0);
+
+        let mut tx_signer = StacksTransactionSigner::new(&tx_coinbase);
+        tx_signer.sign_origin(&privk1).unwrap();
+        let tx_coinbase_signed = tx_signer.get_tx().unwrap();
+
+        // next the contract
+        let contract = TEST_CONTRACT.clone();
+        let mut tx_contract = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk1).unwrap(),
+            TransactionPayload::new_smart_contract(
+                &format!("hello-world"),
+                &contract.to_string(),
+                None,
+            )
+            .unwrap(),
+        );
 
-                                        test_debug!("{}: Stream indicates EOF", &_self_str);
-                                    }
+        tx_contract.chain_id = 0x80000000;
+        tx_contract.auth.set_origin_nonce(1);
+        tx_contract.set_tx_fee(0);
 
-                                    // try moving some data to the connection only once we're done
-                                    // streaming
-                                    match reply.try_flush() {
-                                        Ok(res) => {
-                                            test_debug!(
-                                                "{}: Streamed reply is drained?: {}",
-                                                &_self_str,
-                                                res
-                                            );
-                                            drained_handle = res;
-                                        }
-                                        Err(e) => {
-                                            // dead
-                                            warn!(
-                                                "{}: Broken HTTP connection: {:?}",
-                                                &_self_str, &e
-                                            );
-                                            broken = true;
-                                        }
-                                    }
-                                    drained_stream = true;
-                                }
-                            }
-                            Err(e) => {
-                                // broken -- terminate the stream.
-                                // For example, if we're streaming an unconfirmed block or
-                                // microblock, the data can get moved to the chunk store out from
-                                // under the stream.
-                                warn!(
-                                    "{}: Failed to send to HTTP connection: {:?}",
-                                    &_self_str, &e
-                                );
-                                broken = true;
-                            }
-                        }
-                    }
-                    None => {
-                        // not streamed; all data is buffered
-                        drained_stream = true;
+        let mut tx_signer = StacksTransactionSigner::new(&tx_contract);
+        tx_signer.sign_origin(&privk1).unwrap();
+        let tx_contract_signed = tx_signer.get_tx().unwrap();
 
-                        // try moving some data to the connection
-                        match reply.try_flush() {
-                            Ok(res) => {
-                                test_debug!("{}: Reply is drained", &_self_str);
-                                drained_handle = res;
-                            }
-                            Err(e) => {
-                                // dead
-                                warn!("{}: Broken HTTP connection: {:?}", &_self_str, &e);
-                                broken = true;
-                            }
-                        }
-                    }
-                }
-            }
-            None => {}
-        }
+        // update account and state in a microblock that will be unconfirmed
+        let mut tx_cc = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk1).unwrap(),
+            TransactionPayload::new_contract_call(addr1.clone(), "hello-world", "add-unit", vec![])
+                .unwrap(),
+        );
 
-        if broken || (drained_handle && drained_stream) {
-            // done with this stream
-            test_debug!(
-                "{:?}: done with stream (broken={}, drained_handle={}, drained_stream={})",
-                &self,
-                broken,
-                drained_handle,
-                drained_stream
-            );
-            self.total_reply_count += 1;
-            self.reply_streams.pop_front();
+        tx_cc.chain_id = 0x80000000;
+        tx_cc.auth.set_origin_nonce(2);
+        tx_cc.set_tx_fee(123);
 
-            if !do_keep_alive {
-                // encountered "Connection: close"
-                self.keep_alive = false;
-            }
-        }
-        Ok(())
-    }
+        let mut tx_signer = StacksTransactionSigner::new(&tx_cc);
+        tx_signer.sign_origin(&privk1).unwrap();
+        let tx_cc_signed = tx_signer.get_tx().unwrap();
+        let tx_cc_len = {
+            let mut bytes = vec![];
+            tx_cc_signed.consensus_serialize(&mut bytes).unwrap();
+            bytes.len() as u64
+        };
 
-    pub fn try_send_recv_response(
-        req: ReplyHandleHttp,
-    ) -> Result<HttpResponseType, Result<ReplyHandleHttp, net_error>> {
-        match req.try_send_recv() {
-            Ok(message) => match message {
-                StacksHttpMessage::Request(_) => {
-                    warn!("Received response: not a HTTP response");
-                    return Err(Err(net_error::InvalidMessage));
-                }
-                StacksHttpMessage::Response(http_response) => Ok(http_response),
-            },
-            Err(res) => Err(res),
-        }
-    }
+        // make an unconfirmed contract
+        let unconfirmed_contract = TEST_CONTRACT_UNCONFIRMED.clone();
+        let mut tx_unconfirmed_contract = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk1).unwrap(),
+            TransactionPayload::new_smart_contract(
+                &format!("hello-world-unconfirmed"),
+                &unconfirmed_contract.to_string(),
+                None,
+            )
+            .unwrap(),
+        );
 
-    /// Make progress on our request/response
-    fn recv_inbound_response(&mut self) -> Result<(), net_error> {
-        // make progress on our pending request (if it exists).
-        let inprogress = self.pending_request.is_some();
-        let is_pending = self.pending_response.is_none();
+        tx_unconfirmed_contract.chain_id = 0x80000000;
+        tx_unconfirmed_contract.auth.set_origin_nonce(3);
+        tx_unconfirmed_contract.set_tx_fee(0);
 
-        let pending_request = self.pending_request.take();
-        let response = match pending_request {
-            None => Ok(self.pending_response.take()),
-            Some(req) => match ConversationHttp::try_send_recv_response(req) {
-                Ok(response) => Ok(Some(response)),
-                Err(res) => match res {
-                    Ok(handle) => {
-                        // try again
-                        self.pending_request = Some(handle);
-                        Ok(self.pending_response.take())
-                    }
-                    Err(e) => Err(e),
-                },
-            },
-        }?;
+        let mut tx_signer = StacksTransactionSigner::new(&tx_unconfirmed_contract);
+        tx_signer.sign_origin(&privk1).unwrap();
+        let tx_unconfirmed_contract_signed = tx_signer.get_tx().unwrap();
+        let tx_unconfirmed_contract_len = {
+            let mut bytes = vec![];
+            tx_unconfirmed_contract_signed
+                .consensus_serialize(&mut bytes)
+                .unwrap();
+            bytes.len() as u64
+        };
 
-        self.pending_response = response;
+        let tip =
+            SortitionDB::get_canonical_burn_chain_tip(&peer_1.sortdb.as_ref().unwrap().conn())
+                .unwrap();
+        let mut anchor_cost = ExecutionCost::zero();
+        let mut anchor_size = 0;
 
-        if inprogress && self.pending_request.is_none() {
-            test_debug!(
-                "{:?},id={}: HTTP request finished",
-                &self.peer_host,
-                self.conn_id
-            );
-        }
+        // make a block and a microblock.
+        // Put the coinbase and smart-contract in the anchored block.
+        // Put the contract-call in the microblock
+        let (burn_ops, stacks_block, microblocks) = peer_1.make_tenure(
+            |ref mut miner, ref mut sortdb, ref mut chainstate, vrf_proof, ref parent_opt, _| {
+                let parent_tip = match parent_opt {
+                    None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                    Some(block) => {
+                        let ic = sortdb.index_conn();
+                        let snapshot = SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                            &ic,
+                            &tip.sortition_id,
+                            &block.block_hash(),
+                        )
+                        .unwrap()
+                        .unwrap(); // succeeds because we don't fork
+                        StacksChainState::get_anchored_block_header_info(
+                            chainstate.db(),
+                            &snapshot.consensus_hash,
+                            &snapshot.winning_stacks_block_hash,
+                        )
+                        .unwrap()
+                        .unwrap()
+                    }
+                };
 
-        if is_pending && self.pending_response.is_some() {
-            test_debug!(
-                "{:?},id={}: HTTP response finished",
-                &self.peer_host,
-                self.conn_id
-            );
-        }
+                let block_builder = StacksBlockBuilder::make_regtest_block_builder(
+                    &parent_tip,
+                    vrf_proof,
+                    tip.total_burn,
+                    microblock_pubkeyhash,
+                )
+                .unwrap();
+                let (anchored_block, anchored_block_size, anchored_block_cost) =
+                    StacksBlockBuilder::make_anchored_block_from_txs(
+                        block_builder,
+                        chainstate,
+                        &sortdb.index_conn(),
+                        vec![tx_coinbase_signed.clone(), tx_contract_signed.clone()],
+                    )
+                    .unwrap();
 
-        Ok(())
-    }
+                anchor_size = anchored_block_size;
+                anchor_cost = anchored_block_cost;
 
-    /// Try to get our response
-    pub fn try_get_response(&mut self) -> Option<HttpResponseType> {
-        self.pending_response.take()
-    }
+                (anchored_block, vec![])
+            },
+        );
 
-    /// Make progress on in-flight messages.
-    pub fn try_flush(
-        &mut self,
-        mempool: &MemPoolDB,
-        chainstate: &mut StacksChainState,
-    ) -> Result<(), net_error> {
-        self.send_outbound_responses(mempool, chainstate)?;
-        self.recv_inbound_response()?;
-        Ok(())
-    }
+        let (_, _, consensus_hash) = peer_1.next_burnchain_block(burn_ops.clone());
+        peer_2.next_burnchain_block(burn_ops.clone());
 
-    /// Is the connection idle?
-    pub fn is_idle(&self) -> bool {
-        self.pending_response.is_none()
-            && self.connection.inbox_len() == 0
-            && self.connection.outbox_len() == 0
-            && self.reply_streams.len() == 0
-    }
+        peer_1.process_stacks_epoch_at_tip(&stacks_block, &vec![]);
+        peer_2.process_stacks_epoch_at_tip(&stacks_block, &vec![]);
 
-    /// Is the conversation out of pending data?
-    /// Don't consider it drained if we haven't received anything yet
-    pub fn is_drained(&self) -> bool {
-        ((self.total_request_count > 0 && self.total_reply_count > 0)
-            || self.pending_error_response.is_some())
-            && self.is_idle()
-    }
+        // begin microblock section
+        if include_microblocks {
+            // build 1-block microblock stream with the contract-call and the unconfirmed contract
+            let microblock = {
+                let sortdb = peer_1.sortdb.take().unwrap();
+                Relayer::setup_unconfirmed_state(peer_1.chainstate(), &sortdb).unwrap();
+                let mblock = {
+                    let sort_iconn = sortdb.index_conn();
+                    let mut microblock_builder = StacksMicroblockBuilder::new(
+                        stacks_block.block_hash(),
+                        consensus_hash.clone(),
+                        peer_1.chainstate(),
+                        &sort_iconn,
+                        BlockBuilderSettings::max_value(),
+                    )
+                    .unwrap();
+                    let microblock = microblock_builder
+                        .mine_next_microblock_from_txs(
+                            vec![
+                                (tx_cc_signed, tx_cc_len),
+                                (tx_unconfirmed_contract_signed, tx_unconfirmed_contract_len),
+                            ],
+                            &microblock_privkey,
+                        )
+                        .unwrap();
+                    microblock
+                };
+                peer_1.sortdb = Some(sortdb);
+                mblock
+            };
 
-    /// Should the connection be kept alive even if drained?
-    pub fn is_keep_alive(&self) -> bool {
-        self.keep_alive
-    }
+            // store microblock stream
+            peer_1
+                .chainstate()
+                .preprocess_streamed_microblock(
+                    &consensus_hash,
+                    &stacks_block.block_hash(),
+                    &microblock,
+                )
+                .unwrap();
+            peer_2
+                .chainstate()
+                .preprocess_streamed_microblock(
+                    &consensus_hash,
+                    &stacks_block.block_hash(),
+                    &microblock,
+                )
+                .unwrap();
 
-    /// When was the last time we got an inbound request?
-    pub fn get_last_request_time(&self) -> u64 {
-        self.last_request_timestamp
-    }
+            // process microblock stream to generate unconfirmed state
+            let canonical_tip = StacksBlockHeader::make_index_block_hash(
+                &consensus_hash,
+                &stacks_block.block_hash(),
+            );
+            let sortdb1 = peer_1.sortdb.take().unwrap();
+            let sortdb2 = peer_2.sortdb.take().unwrap();
+            peer_1
+                .chainstate()
+                .reload_unconfirmed_state(&sortdb1.index_conn(), canonical_tip.clone())
+                .unwrap();
+            peer_2
+                .chainstate()
+                .reload_unconfirmed_state(&sortdb2.index_conn(), canonical_tip.clone())
+                .unwrap();
+            peer_1.sortdb = Some(sortdb1);
+            peer_2.sortdb = Some(sortdb2);
+        }
+        // end microblock section
 
-    /// When was the last time we sent data as part of an outbound response?
-    pub fn get_last_response_time(&self) -> u64 {
-        self.last_response_timestamp
-    }
+        // stuff some transactions into peer_2's mempool
+        // (relates to mempool query tests)
+        let mut mempool = peer_2.mempool.take().unwrap();
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for i in 0..10 {
+            let pk = StacksPrivateKey::new();
+            let addr = StacksAddress::from_public_keys(
+                C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+                &AddressHashMode::SerializeP2PKH,
+                1,
+                &vec![StacksPublicKey::from_private(&StacksPrivateKey::new())],
+            )
+            .unwrap();
+            let mut tx = StacksTransaction {
+                version: TransactionVersion::Testnet,
+                chain_id: 0x80000000,
+                auth: TransactionAuth::from_p2pkh(&pk).unwrap(),
+                anchor_mode: TransactionAnchorMode::Any,
+                post_condition_mode: TransactionPostConditionMode::Allow,
+                post_conditions: vec![],
+                payload: TransactionPayload::TokenTransfer(
+                    addr.to_account_principal(),
+                    123,
+                    TokenTransferMemo([0u8; 34]),
+                ),
+            };
+            tx.set_tx_fee(1000);
+            tx.set_origin_nonce(0);
 
-    /// When was this converation conencted?
-    pub fn get_connection_time(&self) -> u64 {
-        self.connection_time
-    }
+            let txid = tx.txid();
+            let tx_bytes = tx.serialize_to_vec();
+            let origin_addr = tx.origin_address();
+            let origin_nonce = tx.get_origin_nonce();
+            let sponsor_addr = tx.sponsor_address().unwrap_or(origin_addr.clone());
+            let sponsor_nonce = tx.get_sponsor_nonce().unwrap_or(origin_nonce);
+            let tx_fee = tx.get_tx_fee();
 
-    /// Make progress on in-flight requests and replies.
-    /// Returns the list of transactions we'll need to forward to the peer network
-    pub fn chat(
-        &mut self,
-        network: &mut PeerNetwork,
-        sortdb: &SortitionDB,
-        chainstate: &mut StacksChainState,
-        mempool: &mut MemPoolDB,
-        handler_args: &RPCHandlerArgs,
-    ) -> Result<Vec<StacksMessageType>, net_error> {
-        // if we have an in-flight error, then don't take any more requests.
-        if self.pending_error_response.is_some() {
-            return Ok(vec![]);
+            // should succeed
+            MemPoolDB::try_add_tx(
+                &mut mempool_tx,
+                peer_1.chainstate(),
+                &consensus_hash,
+                &stacks_block.block_hash(),
+                txid.clone(),
+                tx_bytes,
+                tx_fee,
+                stacks_block.header.total_work.work,
+                &origin_addr,
+                origin_nonce,
+                &sponsor_addr,
+                sponsor_nonce,
+                None,
+            )
+            .unwrap();
         }
+        mempool_tx.commit().unwrap();
+        peer_2.mempool.replace(mempool);
 
-        // handle in-bound HTTP request(s)
-        let num_inbound = self.connection.inbox_len();
-        let mut ret = vec![];
-        test_debug!("{:?}: {} HTTP requests pending", &self, num_inbound);
+        let peer_1_sortdb = peer_1.sortdb.take().unwrap();
+        let peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
+        let _ = peer_1
+            .network
+            .refresh_burnchain_view(
+                &peer_1_indexer,
+                &peer_1_sortdb,
+                &peer_1_stacks_node.chainstate,
+                false,
+            )
+            .unwrap();
+        peer_1.sortdb = Some(peer_1_sortdb);
+        peer_1.stacks_node = Some(peer_1_stacks_node);
 
-        for _i in 0..num_inbound {
-            let msg = match self.connection.next_inbox_message() {
-                None => {
-                    continue;
-                }
-                Some(m) => m,
-            };
+        let peer_2_sortdb = peer_2.sortdb.take().unwrap();
+        let peer_2_stacks_node = peer_2.stacks_node.take().unwrap();
+        let _ = peer_2
+            .network
+            .refresh_burnchain_view(
+                &peer_2_indexer,
+                &peer_2_sortdb,
+                &peer_2_stacks_node.chainstate,
+                false,
+            )
+            .unwrap();
+        peer_2.sortdb = Some(peer_2_sortdb);
+        peer_2.stacks_node = Some(peer_2_stacks_node);
 
-            match msg {
-                StacksHttpMessage::Request(req) => {
-                    // new request
-                    self.total_request_count += 1;
-                    self.last_request_timestamp = get_epoch_time_secs();
-                    if req.metadata().canonical_stacks_tip_height.is_some() {
-                        test_debug!(
-                            "Request metadata: canonical stacks tip height is {:?}",
-                            &req.metadata().canonical_stacks_tip_height
-                        );
-                        self.canonical_stacks_tip_height =
-                            req.metadata().canonical_stacks_tip_height;
-                    }
-                    let start_time = Instant::now();
-                    let path = req.get_path();
-                    let msg_opt = monitoring::instrument_http_request_handler(req, |req| {
-                        self.handle_request(req, network, sortdb, chainstate, mempool, handler_args)
-                    })?;
+        let view_1 = peer_1.get_burnchain_view().unwrap();
+        let view_2 = peer_2.get_burnchain_view().unwrap();
 
-                    debug!("Processed HTTPRequest"; "path" => %path, "processing_time_ms" => start_time.elapsed().as_millis(), "conn_id" => self.conn_id, "peer_addr" => &self.peer_addr);
+        let mut convo_1 = ConversationHttp::new(
+            format!("127.0.0.1:{}", peer_1_http)
+                .parse::<SocketAddr>()
+                .unwrap(),
+            Some(UrlString::try_from(format!("http://peer1.com")).unwrap()),
+            peer_1.to_peer_host(),
+            &peer_1.config.connection_opts,
+            0,
+        );
 
-                    if let Some(msg) = msg_opt {
-                        ret.push(msg);
-                    }
-                }
-                StacksHttpMessage::Response(resp) => {
-                    // Is there someone else waiting for this message?  If so, pass it along.
-                    // (this _should_ be our pending_request handle)
-                    if resp.metadata().canonical_stacks_tip_height.is_some() {
-                        test_debug!(
-                            "Response metadata: canonical stacks tip height is {:?}",
-                            &resp.metadata().canonical_stacks_tip_height
-                        );
-                        self.canonical_stacks_tip_height =
-                            resp.metadata().canonical_stacks_tip_height;
-                    }
-                    match self
-                        .connection
-                        .fulfill_request(StacksHttpMessage::Response(resp))
-                    {
-                        None => {
-                            test_debug!("{:?}: Fulfilled pending HTTP request", &self);
-                        }
-                        Some(_msg) => {
-                            // unsolicited; discard
-                            test_debug!("{:?}: Dropping unsolicited HTTP response", &self);
-                        }
-                    }
-                }
-            }
-        }
+        let mut convo_2 = ConversationHttp::new(
+            format!("127.0.0.1:{}", peer_2_http)
+                .parse::<SocketAddr>()
+                .unwrap(),
+            Some(UrlString::try_from(format!("http://peer2.com")).unwrap()),
+            peer_2.to_peer_host(),
+            &peer_2.config.connection_opts,
+            1,
+        );
 
-        Ok(ret)
-    }
+        let req = make_request(&mut peer_1, &mut convo_1, &mut peer_2, &mut convo_2);
 
-    /// Remove all timed-out messages, and ding the remote peer as unhealthy
-    pub fn clear_timeouts(&mut self) -> () {
-        self.connection.drain_timeouts();
-    }
+        convo_1.send_request(req.clone()).unwrap();
+        let mut peer_1_mempool = peer_1.mempool.take().unwrap();
+        let peer_2_mempool = peer_2.mempool.take().unwrap();
 
-    /// Load data into our HTTP connection
-    pub fn recv<R: Read>(&mut self, r: &mut R) -> Result<usize, net_error> {
-        let mut total_recv = 0;
-        loop {
-            let nrecv = match self.connection.recv_data(r) {
-                Ok(nr) => nr,
-                Err(e) => {
-                    debug!("{:?}: failed to recv: {:?}", self, &e);
-                    return Err(e);
-                }
-            };
+        test_debug!("convo1 sends to convo2");
+        convo_send_recv(
+            &mut convo_1,
+            &peer_1_mempool,
+            peer_1.chainstate(),
+            &mut convo_2,
+            &peer_2_mempool,
+            peer_2.chainstate(),
+        );
 
-            total_recv += nrecv;
-            if nrecv > 0 {
-                self.last_request_timestamp = get_epoch_time_secs();
-            } else {
-                break;
-            }
-        }
-        monitoring::update_inbound_rpc_bandwidth(total_recv as i64);
-        Ok(total_recv)
-    }
+        // hack around the borrow-checker
+        let mut peer_1_sortdb = peer_1.sortdb.take().unwrap();
+        let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
 
-    /// Write data out of our HTTP connection.  Write as much as we can
-    pub fn send<W: Write>(
-        &mut self,
-        w: &mut W,
-        mempool: &MemPoolDB,
-        chainstate: &mut StacksChainState,
-    ) -> Result<usize, net_error> {
-        let mut total_sz = 0;
-        loop {
-            // prime the Write
-            self.try_flush(mempool, chainstate)?;
+        Relayer::setup_unconfirmed_state(&mut peer_1_stacks_node.chainstate, &peer_1_sortdb)
+            .unwrap();
 
-            let sz = match self.connection.send_data(w) {
-                Ok(sz) => sz,
-                Err(e) => {
-                    info!("{:?}: failed to send on HTTP conversation: {:?}", self, &e);
-                    return Err(e);
-                }
-            };
+        convo_1
+            .chat(
+                &mut peer_1.network,
+                &mut peer_1_sortdb,
+                &mut peer_1_stacks_node.chainstate,
+                &mut peer_1_mempool,
+                &RPCHandlerArgs::default(),
+            )
+            .unwrap();
 
-            total_sz += sz;
-            if sz > 0 {
-                self.last_response_timestamp = get_epoch_time_secs();
-            } else {
-                break;
-            }
-        }
-        monitoring::update_inbound_rpc_bandwidth(total_sz as i64);
-        Ok(total_sz)
-    }
+        peer_1.sortdb = Some(peer_1_sortdb);
+        peer_1.stacks_node = Some(peer_1_stacks_node);
+        peer_1.mempool = Some(peer_1_mempool);
+        peer_2.mempool = Some(peer_2_mempool);
 
-    /// Make a new getinfo request to this endpoint
-    pub fn new_getinfo(&self, stacks_height: Option<u64>) -> HttpRequestType {
-        HttpRequestType::GetInfo(HttpRequestMetadata::from_host(
-            self.peer_host.clone(),
-            stacks_height,
-        ))
-    }
+        test_debug!("convo2 sends to convo1");
 
-    /// Make a new getinfo request to this endpoint
-    pub fn new_getpoxinfo(&self, tip_req: TipRequest) -> HttpRequestType {
-        HttpRequestType::GetPoxInfo(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            tip_req,
-        )
-    }
+        // hack around the borrow-checker
+        let mut peer_2_sortdb = peer_2.sortdb.take().unwrap();
+        let mut peer_2_stacks_node = peer_2.stacks_node.take().unwrap();
+        let mut peer_2_mempool = peer_2.mempool.take().unwrap();
 
-    /// Make a new getneighbors request to this endpoint
-    pub fn new_getneighbors(&self) -> HttpRequestType {
-        HttpRequestType::GetNeighbors(HttpRequestMetadata::from_host(self.peer_host.clone(), None))
-    }
+        let _ = peer_2
+            .network
+            .refresh_burnchain_view(
+                &peer_2_indexer,
+                &peer_2_sortdb,
+                &peer_2_stacks_node.chainstate,
+                false,
+            )
+            .unwrap();
 
-    /// Make a new getheaders request to this endpoint
-    pub fn new_getheaders(&self, quantity: u64, tip_req: TipRequest) -> HttpRequestType {
-        HttpRequestType::GetHeaders(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            quantity,
-            tip_req,
-        )
-    }
+        Relayer::setup_unconfirmed_state(&mut peer_2_stacks_node.chainstate, &peer_2_sortdb)
+            .unwrap();
 
-    /// Make a new getblock request to this endpoint
-    pub fn new_getblock(&self, index_block_hash: StacksBlockId) -> HttpRequestType {
-        HttpRequestType::GetBlock(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            index_block_hash,
-        )
-    }
+        convo_2
+            .chat(
+                &mut peer_2.network,
+                &mut peer_2_sortdb,
+                &mut peer_2_stacks_node.chainstate,
+                &mut peer_2_mempool,
+                &RPCHandlerArgs::default(),
+            )
+            .unwrap();
 
-    /// Make a new get-microblocks request to this endpoint
-    pub fn new_getmicroblocks_indexed(
-        &self,
-        index_microblock_hash: StacksBlockId,
-    ) -> HttpRequestType {
-        HttpRequestType::GetMicroblocksIndexed(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            index_microblock_hash,
-        )
-    }
+        peer_2.sortdb = Some(peer_2_sortdb);
+        peer_2.stacks_node = Some(peer_2_stacks_node);
+        let mut peer_1_mempool = peer_1.mempool.take().unwrap();
 
-    /// Make a new get-microblocks-confirmed request to this endpoint
-    pub fn new_getmicroblocks_confirmed(
-        &self,
-        index_anchor_block_hash: StacksBlockId,
-    ) -> HttpRequestType {
-        HttpRequestType::GetMicroblocksConfirmed(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            index_anchor_block_hash,
-        )
-    }
+        convo_send_recv(
+            &mut convo_2,
+            &peer_2_mempool,
+            peer_2.chainstate(),
+            &mut convo_1,
+            &peer_1_mempool,
+            peer_1.chainstate(),
+        );
 
-    /// Make a new get-microblocks request for unconfirmed microblocks
-    pub fn new_getmicroblocks_unconfirmed(
-        &self,
-        anchored_index_block_hash: StacksBlockId,
-        min_seq: u16,
-    ) -> HttpRequestType {
-        HttpRequestType::GetMicroblocksUnconfirmed(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            anchored_index_block_hash,
-            min_seq,
-        )
-    }
+        test_debug!("flush convo1");
 
-    /// Make a new get-unconfirmed-tx request
-    pub fn new_gettransaction_unconfirmed(&self, txid: Txid) -> HttpRequestType {
-        HttpRequestType::GetTransactionUnconfirmed(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            txid,
-        )
-    }
+        // hack around the borrow-checker
+        convo_send_recv(
+            &mut convo_1,
+            &peer_1_mempool,
+            peer_1.chainstate(),
+            &mut convo_2,
+            &peer_2_mempool,
+            peer_2.chainstate(),
+        );
 
-    /// Make a new post-transaction request
-    pub fn new_post_transaction(&self, tx: StacksTransaction) -> HttpRequestType {
-        HttpRequestType::PostTransaction(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            tx,
-            None,
-        )
-    }
+        peer_2.mempool = Some(peer_2_mempool);
 
-    /// Make a new post-block request
-    pub fn new_post_block(&self, ch: ConsensusHash, block: StacksBlock) -> HttpRequestType {
-        HttpRequestType::PostBlock(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            ch,
-            block,
-        )
-    }
+        let mut peer_1_sortdb = peer_1.sortdb.take().unwrap();
+        let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
+
+        let _ = peer_1
+            .network
+            .refresh_burnchain_view(
+                &peer_1_indexer,
+                &peer_1_sortdb,
+                &peer_1_stacks_node.chainstate,
+                false,
+            )
+            .unwrap();
+
+        Relayer::setup_unconfirmed_state(&mut peer_1_stacks_node.chainstate, &peer_1_sortdb)
+            .unwrap();
+
+        convo_1
+            .chat(
+                &mut peer_1.network,
+                &mut peer_1_sortdb,
+                &mut peer_1_stacks_node.chainstate,
+                &mut peer_1_mempool,
+                &RPCHandlerArgs::default(),
+            )
+            .unwrap();
+
+        convo_1
+            .try_flush(&peer_1_mempool, &mut peer_1_stacks_node.chainstate)
+            .unwrap();
+
+        peer_1.sortdb = Some(peer_1_sortdb);
+        peer_1.stacks_node = Some(peer_1_stacks_node);
+        peer_1.mempool = Some(peer_1_mempool);
 
-    /// Make a new post-microblock request
-    pub fn new_post_microblock(
-        &self,
-        mblock: StacksMicroblock,
-        tip_req: TipRequest,
-    ) -> HttpRequestType {
-        HttpRequestType::PostMicroblock(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            mblock,
-            tip_req,
-        )
-    }
+        // should have gotten a reply
+        let resp_opt = convo_1.try_get_response();
+        assert!(resp_opt.is_some());
 
-    /// Make a new request for an account
-    pub fn new_getaccount(
-        &self,
-        principal: PrincipalData,
-        tip_req: TipRequest,
-        with_proof: bool,
-    ) -> HttpRequestType {
-        HttpRequestType::GetAccount(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            principal,
-            tip_req,
-            with_proof,
-        )
+        let resp = resp_opt.unwrap();
+        assert!(check_result(
+            &req,
+            &resp,
+            &mut peer_1,
+            &mut peer_2,
+            &convo_1,
+            &convo_2
+        ));
     }
 
-    /// Make a new request for a data var
-    pub fn new_getdatavar(
-        &self,
-        contract_addr: StacksAddress,
-        contract_name: ContractName,
-        var_name: ClarityName,
-        tip_req: TipRequest,
-        with_proof: bool,
-    ) -> HttpRequestType {
-        HttpRequestType::GetDataVar(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            contract_addr,
-            contract_name,
-            var_name,
-            tip_req,
-            with_proof,
-        )
+    /// This test tests two things:
+    /// (1) the get info RPC call
+    /// (2) whether the ConversationHttp object gets correctly updated with a peer's canonical
+    /// stacks tip height, which is sent in HTTP headers as part of the request/response
+    #[test]
+    #[ignore]
+    fn test_rpc_getinfo() {
+        let peer_server_info = RefCell::new(None);
+        let client_stacks_height = 17;
+        test_rpc(
+            function_name!(),
+            40000,
+            40001,
+            50000,
+            50001,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let peer_info = RPCPeerInfoData::from_network(
+                    &peer_server.network,
+                    &peer_server.stacks_node.as_ref().unwrap().chainstate,
+                    None,
+                    &Sha256Sum::zero(),
+                );
+
+                *peer_server_info.borrow_mut() = Some(peer_info);
+
+                convo_client.new_getinfo(Some(client_stacks_height))
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                assert_eq!(convo_client.canonical_stacks_tip_height, Some(1));
+                assert_eq!(
+                    convo_server.canonical_stacks_tip_height,
+                    Some(client_stacks_height)
+                );
+                match http_response {
+                    HttpResponseType::PeerInfo(response_md, peer_data) => {
+                        assert_eq!(Some((*peer_data).clone()), *peer_server_info.borrow());
+                        assert!(peer_data.node_public_key.is_some());
+                        assert!(peer_data.node_public_key_hash.is_some());
+                        assert_eq!(
+                            peer_data.node_public_key_hash,
+                            Some(Hash160::from_node_public_key(
+                                &peer_data
+                                    .node_public_key
+                                    .clone()
+                                    .unwrap()
+                                    .to_public_key()
+                                    .unwrap()
+                            ))
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
     }
 
-    /// Make a new request for a data map
-    pub fn new_getmapentry(
-        &self,
-        contract_addr: StacksAddress,
-        contract_name: ContractName,
-        map_name: ClarityName,
-        key: Value,
-        tip_req: TipRequest,
-        with_proof: bool,
-    ) -> HttpRequestType {
-        HttpRequestType::GetMapEntry(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            contract_addr,
-            contract_name,
-            map_name,
-            key,
-            tip_req,
-            with_proof,
-        )
+    #[test]
+    #[ignore]
+    fn test_rpc_getpoxinfo() {
+        // Test v2/pox (aka GetPoxInfo) endpoint.
+        // In this test, `tip_req` is set to UseLatestAnchoredTip.
+        // Thus, the query for pox info will be against the canonical Stacks tip, which we expect to succeed.
+        let pox_server_info = RefCell::new(None);
+        test_rpc(
+            function_name!(),
+            40002,
+            40003,
+            50002,
+            50003,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
+                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
+                let stacks_block_id = {
+                    let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                    StacksBlockHeader::make_index_block_hash(
+                        &tip.consensus_hash,
+                        &tip.anchored_block_hash,
+                    )
+                };
+                let pox_info = RPCPoxInfoData::from_db(
+                    &mut sortdb,
+                    chainstate,
+                    &stacks_block_id,
+                    &peer_client.config.burnchain,
+                )
+                .unwrap();
+                *pox_server_info.borrow_mut() = Some(pox_info);
+                convo_client.new_getpoxinfo(TipRequest::UseLatestAnchoredTip)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             convo_client,
+             convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::PoxInfo(response_md, pox_data) => {
+                        assert_eq!(Some((*pox_data).clone()), *pox_server_info.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
     }
 
-    /// Make a new request to get a contract's source
-    pub fn new_getcontractsrc(
-        &self,
-        contract_addr: StacksAddress,
-        contract_name: ContractName,
-        tip_req: TipRequest,
-        with_proof: bool,
-    ) -> HttpRequestType {
-        HttpRequestType::GetContractSrc(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            contract_addr,
-            contract_name,
-            tip_req,
-            with_proof,
-        )
+    #[test]
+    #[ignore]
+    fn test_rpc_getpoxinfo_use_latest_tip() {
+        // Test v2/pox (aka GetPoxInfo) endpoint.
+        // In this test, we set `tip_req` to UseLatestUnconfirmedTip, and we expect that querying for pox
+        // info against the unconfirmed state will succeed.
+        let pox_server_info = RefCell::new(None);
+        test_rpc(
+            function_name!(),
+            40004,
+            40005,
+            50004,
+            50005,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
+                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
+                let stacks_block_id = chainstate
+                    .unconfirmed_state
+                    .as_ref()
+                    .unwrap()
+                    .unconfirmed_chain_tip
+                    .clone();
+                let pox_info = RPCPoxInfoData::from_db(
+                    &mut sortdb,
+                    chainstate,
+                    &stacks_block_id,
+                    &peer_client.config.burnchain,
+                )
+                .unwrap();
+                *pox_server_info.borrow_mut() = Some(pox_info);
+                convo_client.new_getpoxinfo(TipRequest::UseLatestUnconfirmedTip)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::PoxInfo(response_md, pox_data) => {
+                        assert_eq!(Some((*pox_data).clone()), *pox_server_info.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
     }
 
-    /// Make a new request to get a contract's ABI
-    pub fn new_getcontractabi(
-        &self,
-        contract_addr: StacksAddress,
-        contract_name: ContractName,
-        tip_req: TipRequest,
-    ) -> HttpRequestType {
-        HttpRequestType::GetContractABI(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            contract_addr,
-            contract_name,
-            tip_req,
-        )
+    #[test]
+    #[ignore]
+    fn test_rpc_getneighbors() {
+        test_rpc(
+            function_name!(),
+            40010,
+            40011,
+            50010,
+            50011,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getneighbors(PageRequest::default())
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::Neighbors(response_md, neighbor_info) => {
+                        assert_eq!(neighbor_info.sample.items().len(), 1);
+                        assert_eq!(
+                            neighbor_info.sample.items()[0].port,
+                            peer_client.config.server_port
+                        ); // we see ourselves as the neighbor
+                        assert_eq!(neighbor_info.bootstrap.items().len(), 1);
+                        assert_eq!(
+                            neighbor_info.bootstrap.items()[0].port,
+                            peer_client.config.server_port
+                        ); // we see ourselves as the bootstrap
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
     }
 
-    /// Make a new request to run a read-only function
-    pub fn new_callreadonlyfunction(
-        &self,
-        contract_addr: StacksAddress,
-        contract_name: ContractName,
-        sender: PrincipalData,
-        sponsor: Option<PrincipalData>,
-        function_name: ClarityName,
-        function_args: Vec<Value>,
-        tip_req: TipRequest,
-    ) -> HttpRequestType {
-        HttpRequestType::CallReadOnlyFunction(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            contract_addr,
-            contract_name,
-            sender,
-            sponsor,
-            function_name,
-            function_args,
-            tip_req,
-        )
-    }
+    #[test]
+    #[ignore]
+    fn test_rpc_getheaders() {
+        let server_blocks_cell = RefCell::new(None);
 
-    /// Make a new request for attachment inventory page
-    pub fn new_getattachmentsinv(
-        &self,
-        index_block_hash: StacksBlockId,
-        pages_indexes: HashSet<u32>,
-    ) -> HttpRequestType {
-        HttpRequestType::GetAttachmentsInv(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            index_block_hash,
-            pages_indexes,
-        )
-    }
+        test_rpc(
+            function_name!(),
+            40012,
+            40013,
+            50012,
+            50013,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                // have "server" peer store a few continuous block to staging
+                let mut blocks: Vec<StacksBlock> = vec![];
+                let mut index_block_hashes = vec![];
+                for i in 0..25 {
+                    let mut peer_server_block = make_codec_test_block(25);
 
-    /// Make a new request for mempool contents
-    pub fn new_mempool_query(
-        &self,
-        query: MemPoolSyncData,
-        page_id_opt: Option<Txid>,
-    ) -> HttpRequestType {
-        HttpRequestType::MemPoolQuery(
-            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
-            query,
-            page_id_opt,
-        )
-    }
-}
+                    peer_server_block.header.total_work.work = (i + 1) as u64;
+                    peer_server_block.header.total_work.burn = (i + 1) as u64;
+                    peer_server_block.header.parent_block = blocks
+                        .last()
+                        .map(|blk| blk.block_hash())
+                        .unwrap_or(BlockHeaderHash([0u8; 32]));
 
-#[cfg(test)]
-mod test {
-    use std::cell::RefCell;
-    use std::convert::TryInto;
-    use std::iter::FromIterator;
+                    let peer_server_consensus_hash = ConsensusHash([(i + 1) as u8; 20]);
+                    let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                        &peer_server_consensus_hash,
+                        &peer_server_block.block_hash(),
+                    );
 
-    use crate::burnchains::bitcoin::indexer::BitcoinIndexer;
-    use crate::burnchains::Burnchain;
-    use crate::burnchains::BurnchainView;
-    use crate::burnchains::*;
-    use crate::chainstate::burn::ConsensusHash;
-    use crate::chainstate::stacks::db::blocks::test::*;
-    use crate::chainstate::stacks::db::StacksChainState;
-    use crate::chainstate::stacks::db::StreamCursor;
-    use crate::chainstate::stacks::miner::*;
-    use crate::chainstate::stacks::test::*;
-    use crate::chainstate::stacks::Error as chain_error;
-    use crate::chainstate::stacks::*;
-    use crate::net::codec::*;
-    use crate::net::http::*;
-    use crate::net::test::*;
-    use crate::net::*;
-    use clarity::vm::types::*;
-    use stacks_common::address::*;
-    use stacks_common::util::get_epoch_time_secs;
-    use stacks_common::util::hash::hex_bytes;
-    use stacks_common::util::pipe::*;
+                    test_debug!("Store peer server index block {:?}", &index_block_hash);
+                    store_staging_block(
+                        peer_server.chainstate(),
+                        &peer_server_consensus_hash,
+                        &peer_server_block,
+                        &ConsensusHash([i as u8; 20]),
+                        456,
+                        123,
+                    );
+                    set_block_processed(
+                        peer_server.chainstate(),
+                        &peer_server_consensus_hash,
+                        &peer_server_block.block_hash(),
+                        true,
+                    );
 
-    use crate::chainstate::stacks::C32_ADDRESS_VERSION_TESTNET_SINGLESIG;
-    use crate::types::chainstate::BlockHeaderHash;
-    use crate::types::chainstate::BurnchainHeaderHash;
+                    index_block_hashes.push(index_block_hash);
+                    blocks.push(peer_server_block);
+                }
 
-    use crate::core::mempool::{BLOOM_COUNTER_ERROR_RATE, MAX_BLOOM_COUNTER_TXS};
+                let rev_blocks: Vec<_> = blocks.into_iter().rev().collect();
+                let rev_ibhs: Vec<_> = index_block_hashes.into_iter().rev().collect();
 
-    use super::*;
+                let tip = rev_ibhs[0].clone();
+                *server_blocks_cell.borrow_mut() = Some((rev_blocks, rev_ibhs));
 
-    const TEST_CONTRACT: &'static str = "
-        (define-data-var bar int 0)
-        (define-map unit-map { account: principal } { units: int })
-        (define-public (get-bar) (ok (var-get bar)))
-        (define-public (set-bar (x int) (y int))
-          (begin (var-set bar (/ x y)) (ok (var-get bar))))
-        (define-public (add-unit)
-          (begin
-            (map-set unit-map { account: tx-sender } { units: 1 } )
-            (var-set bar 1)
-            (ok 1)))
-        (begin
-          (map-set unit-map { account: 'ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R } { units: 123 }))";
+                // now ask for it
+                convo_client
+                    .new_getheaders(PageRequest::new(Some(25), 0), TipRequest::SpecificTip(tip))
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::Headers(response_md, RPCList::Page(page)) => {
+                        let headers = &page.results;
+                        assert_eq!(headers.len(), 25);
+                        assert_eq!(page.limit, 25);
+                        let expected = server_blocks_cell.borrow().clone().unwrap();
+                        for (i, h) in headers.iter().enumerate() {
+                            assert_eq!(h.header, expected.0[i].header);
+                            assert_eq!(h.consensus_hash, ConsensusHash([(25 - i) as u8; 20]));
+                            if i + 1 < headers.len() {
+                                assert_eq!(h.parent_block_id, expected.1[i + 1]);
+                            }
+                        }
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_unconfirmed_getblock() {
+        let server_block_cell = RefCell::new(None);
+
+        test_rpc(
+            function_name!(),
+            40020,
+            40021,
+            50020,
+            50021,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                // have "server" peer store a block to staging
+                let peer_server_block = make_codec_test_block(25);
+                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &peer_server_consensus_hash,
+                    &peer_server_block.block_hash(),
+                );
 
-    const TEST_CONTRACT_UNCONFIRMED: &'static str = "(define-read-only (ro-test) (ok 1))";
+                test_debug!("Store peer server index block {:?}", &index_block_hash);
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &peer_server_consensus_hash,
+                    &peer_server_block,
+                    &ConsensusHash([0x03; 20]),
+                    456,
+                    123,
+                );
 
-    fn convo_send_recv(
-        sender: &mut ConversationHttp,
-        sender_mempool: &MemPoolDB,
-        sender_chainstate: &mut StacksChainState,
-        receiver: &mut ConversationHttp,
-        receiver_mempool: &MemPoolDB,
-        receiver_chainstate: &mut StacksChainState,
-    ) -> () {
-        let (mut pipe_read, mut pipe_write) = Pipe::new();
-        pipe_read.set_nonblocking(true);
+                *server_block_cell.borrow_mut() = Some(peer_server_block);
 
-        loop {
-            let res = true;
+                // now ask for it
+                convo_client.new_getblock(index_block_hash)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::Block(response_md, block_info) => {
+                        assert_eq!(
+                            block_info.block_hash(),
+                            (*server_block_cell.borrow()).as_ref().unwrap().block_hash()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-            sender.try_flush(sender_mempool, sender_chainstate).unwrap();
-            receiver
-                .try_flush(sender_mempool, receiver_chainstate)
-                .unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_confirmed_getblock() {
+        let server_block_cell = RefCell::new(None);
 
-            pipe_write.try_flush().unwrap();
+        test_rpc(
+            function_name!(),
+            40030,
+            40031,
+            50030,
+            50031,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                // have "server" peer store a block to staging
+                let peer_server_block = make_codec_test_block(25);
+                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &peer_server_consensus_hash,
+                    &peer_server_block.block_hash(),
+                );
 
-            let all_relays_flushed =
-                receiver.num_pending_outbound() == 0 && sender.num_pending_outbound() == 0;
+                test_debug!("Store peer server index block {:?}", &index_block_hash);
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &peer_server_consensus_hash,
+                    &peer_server_block,
+                    &ConsensusHash([0x03; 20]),
+                    456,
+                    123,
+                );
+                set_block_processed(
+                    peer_server.chainstate(),
+                    &peer_server_consensus_hash,
+                    &peer_server_block.block_hash(),
+                    true,
+                );
 
-            let nw = sender
-                .send(&mut pipe_write, sender_mempool, sender_chainstate)
-                .unwrap();
-            let nr = receiver.recv(&mut pipe_read).unwrap();
+                *server_block_cell.borrow_mut() = Some(peer_server_block);
 
-            test_debug!(
-                "res = {}, all_relays_flushed = {} ({},{}), nr = {}, nw = {}",
-                res,
-                all_relays_flushed,
-                receiver.num_pending_outbound(),
-                sender.num_pending_outbound(),
-                nr,
-                nw
-            );
-            if res && all_relays_flushed && nr == 0 && nw == 0 {
-                test_debug!("Breaking send_recv");
-                break;
-            }
-        }
+                // now ask for it
+                convo_client.new_getblock(index_block_hash)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::Block(response_md, block_info) => {
+                        assert_eq!(
+                            block_info.block_hash(),
+                            (*server_block_cell.borrow()).as_ref().unwrap().block_hash()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
     }
 
-    /// General testing function to test RPC calls.
-    /// This function sets up two peers, a client and a server.
-    /// It takes in a function of type F that generates the request to be sent to the server
-    /// It takes in another function of type C that verifies that the result from
-    /// the server is as expected.
-    /// The parameter `include_microblocks` determines whether a microblock stream is mined or not.
-    fn test_rpc<F, C>(
-        test_name: &str,
-        peer_1_p2p: u16,
-        peer_1_http: u16,
-        peer_2_p2p: u16,
-        peer_2_http: u16,
-        include_microblocks: bool,
-        make_request: F,
-        check_result: C,
-    ) -> ()
-    where
-        F: FnOnce(
-            &mut TestPeer,
-            &mut ConversationHttp,
-            &mut TestPeer,
-            &mut ConversationHttp,
-        ) -> HttpRequestType,
-        C: FnOnce(
-            &HttpRequestType,
-            &HttpResponseType,
-            &mut TestPeer,
-            &mut TestPeer,
-            &ConversationHttp,
-            &ConversationHttp,
-        ) -> bool,
-    {
-        let mut peer_1_config = TestPeerConfig::new(test_name, peer_1_p2p, peer_1_http);
-        let mut peer_2_config = TestPeerConfig::new(test_name, peer_2_p2p, peer_2_http);
+    #[test]
+    #[ignore]
+    fn test_rpc_stream_blocks() {
+        // Test /v2/stream/blocks.  Ask for one block starting from height 1, so the feed ends
+        // instead of following the chain tip.
+        let server_tip = RefCell::new(None);
+        test_rpc(
+            function_name!(),
+            40836,
+            40837,
+            50836,
+            50837,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let sortdb = peer_server.sortdb.as_ref().unwrap();
+                let chainstate = &peer_server.stacks_node.as_ref().unwrap().chainstate;
+                let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                *server_tip.borrow_mut() = Some(tip);
+                convo_client.new_stream_blocks(BlockFeedRequest {
+                    start: BlockFeedStart::Height(1),
+                    encoding: BlockFeedEncoding::Json,
+                    limit: Some(1),
+                })
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::BlockFeed(_, frames) => {
+                        let tip = server_tip.borrow().clone().unwrap();
+                        assert_eq!(tip.height, 1);
+                        let block_frames: Vec<_> = frames
+                            .iter()
+                            .filter_map(|frame| match frame {
+                                BlockFeedFrame::Block {
+                                    cursor,
+                                    height,
+                                    consensus_hash,
+                                    block,
+                                    ..
+                                } => Some((cursor, height, consensus_hash, block)),
+                                BlockFeedFrame::Heartbeat { .. }
+                                | BlockFeedFrame::Rollback { .. } => None,
+                            })
+                            .collect();
+                        assert_eq!(block_frames.len(), 1);
 
-        let peer_1_indexer = BitcoinIndexer::new_unit_test(&peer_1_config.burnchain.working_dir);
-        let peer_2_indexer = BitcoinIndexer::new_unit_test(&peer_2_config.burnchain.working_dir);
+                        let (cursor, height, consensus_hash, block) = block_frames[0];
+                        assert_eq!(
+                            cursor,
+                            &StacksBlockId::new(&tip.consensus_hash, &tip.anchored_block_hash)
+                        );
+                        assert_eq!(*height, 1);
+                        assert_eq!(consensus_hash, &tip.consensus_hash);
+                        assert_eq!(block.block_hash(), tip.anchored_block_hash);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        // ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R
-        let privk1 = StacksPrivateKey::from_hex(
-            "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
-        )
-        .unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_get_indexed_microblocks() {
+        let server_microblocks_cell = RefCell::new(vec![]);
 
-        // STVN97YYA10MY5F6KQJHKNYJNM24C4A1AT39WRW
-        let privk2 = StacksPrivateKey::from_hex(
-            "94c319327cc5cd04da7147d32d836eb2e4c44f4db39aa5ede7314a761183d0c701",
-        )
-        .unwrap();
-        let microblock_privkey = StacksPrivateKey::new();
-        let microblock_pubkeyhash =
-            Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+        test_rpc(
+            function_name!(),
+            40040,
+            40041,
+            50040,
+            50041,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                )
+                .unwrap();
 
-        let addr1 = StacksAddress::from_public_keys(
-            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
-            &AddressHashMode::SerializeP2PKH,
-            1,
-            &vec![StacksPublicKey::from_private(&privk1)],
-        )
-        .unwrap();
-        let addr2 = StacksAddress::from_public_keys(
-            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
-            &AddressHashMode::SerializeP2PKH,
-            1,
-            &vec![StacksPublicKey::from_private(&privk2)],
-        )
-        .unwrap();
+                let parent_block = make_codec_test_block(25);
+                let parent_consensus_hash = ConsensusHash([0x02; 20]);
+                let parent_index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &parent_consensus_hash,
+                    &parent_block.block_hash(),
+                );
 
-        peer_1_config.initial_balances = vec![
-            (addr1.to_account_principal(), 1000000000),
-            (addr2.to_account_principal(), 1000000000),
-        ];
+                let mut mblocks = make_sample_microblock_stream(&privk, &parent_block.block_hash());
+                mblocks.truncate(15);
 
-        peer_2_config.initial_balances = vec![
-            (addr1.to_account_principal(), 1000000000),
-            (addr2.to_account_principal(), 1000000000),
-        ];
+                let mut child_block = make_codec_test_block(25);
+                let child_consensus_hash = ConsensusHash([0x03; 20]);
 
-        peer_1_config.add_neighbor(&peer_2_config.to_neighbor());
-        peer_2_config.add_neighbor(&peer_1_config.to_neighbor());
+                child_block.header.parent_block = parent_block.block_hash();
+                child_block.header.parent_microblock =
+                    mblocks.last().as_ref().unwrap().block_hash();
+                child_block.header.parent_microblock_sequence =
+                    mblocks.last().as_ref().unwrap().header.sequence;
 
-        let mut peer_1 = TestPeer::new(peer_1_config);
-        let mut peer_2 = TestPeer::new(peer_2_config);
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &parent_consensus_hash,
+                    &parent_block,
+                    &ConsensusHash([0x01; 20]),
+                    456,
+                    123,
+                );
+                set_block_processed(
+                    peer_server.chainstate(),
+                    &parent_consensus_hash,
+                    &parent_block.block_hash(),
+                    true,
+                );
 
-        // mine one block with a contract in it
-        // first the coinbase
-        // make a coinbase for this miner
-        let mut tx_coinbase = StacksTransaction::new(
-            TransactionVersion::Testnet,
-            TransactionAuth::from_p2pkh(&privk1).unwrap(),
-            TransactionPayload::Coinbase(CoinbasePayload([0x00; 32]), None),
-        );
-        tx_coinbase.chain_id = 0x80000000;
-        tx_coinbase.anchor_mode = TransactionAnchorMode::OnChainOnly;
-        tx_coinbase.auth.set_origin_nonce(0);
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &child_consensus_hash,
+                    &child_block,
+                    &parent_consensus_hash,
+                    456,
+                    123,
+                );
+                set_block_processed(
+                    peer_server.chainstate(),
+                    &child_consensus_hash,
+                    &child_block.block_hash(),
+                    true,
+                );
 
-        let mut tx_signer = StacksTransactionSigner::new(&tx_coinbase);
-        tx_signer.sign_origin(&privk1).unwrap();
-        let tx_coinbase_signed = tx_signer.get_tx().unwrap();
+                let index_microblock_hash = StacksBlockHeader::make_index_block_hash(
+                    &parent_consensus_hash,
+                    &mblocks.last().as_ref().unwrap().block_hash(),
+                );
 
-        // next the contract
-        let contract = TEST_CONTRACT.clone();
-        let mut tx_contract = StacksTransaction::new(
-            TransactionVersion::Testnet,
-            TransactionAuth::from_p2pkh(&privk1).unwrap(),
-            TransactionPayload::new_smart_contract(
-                &format!("hello-world"),
-                &contract.to_string(),
-                None,
-            )
-            .unwrap(),
-        );
+                for mblock in mblocks.iter() {
+                    store_staging_microblock(
+                        peer_server.chainstate(),
+                        &parent_consensus_hash,
+                        &parent_block.block_hash(),
+                        &mblock,
+                    );
+                }
 
-        tx_contract.chain_id = 0x80000000;
-        tx_contract.auth.set_origin_nonce(1);
-        tx_contract.set_tx_fee(0);
+                set_microblocks_processed(
+                    peer_server.chainstate(),
+                    &child_consensus_hash,
+                    &child_block.block_hash(),
+                    &mblocks.last().as_ref().unwrap().block_hash(),
+                );
 
-        let mut tx_signer = StacksTransactionSigner::new(&tx_contract);
-        tx_signer.sign_origin(&privk1).unwrap();
-        let tx_contract_signed = tx_signer.get_tx().unwrap();
+                *server_microblocks_cell.borrow_mut() = mblocks;
 
-        // update account and state in a microblock that will be unconfirmed
-        let mut tx_cc = StacksTransaction::new(
-            TransactionVersion::Testnet,
-            TransactionAuth::from_p2pkh(&privk1).unwrap(),
-            TransactionPayload::new_contract_call(addr1.clone(), "hello-world", "add-unit", vec![])
-                .unwrap(),
+                convo_client.new_getmicroblocks_indexed(index_microblock_hash)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match (*http_response).clone() {
+                    HttpResponseType::Microblocks(_, mut microblocks) => {
+                        microblocks.reverse();
+                        assert_eq!(microblocks.len(), (*server_microblocks_cell.borrow()).len());
+                        assert_eq!(microblocks, *server_microblocks_cell.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", http_response);
+                        false
+                    }
+                }
+            },
         );
+    }
 
-        tx_cc.chain_id = 0x80000000;
-        tx_cc.auth.set_origin_nonce(2);
-        tx_cc.set_tx_fee(123);
+    #[test]
+    #[ignore]
+    fn test_rpc_get_confirmed_microblocks() {
+        let server_microblocks_cell = RefCell::new(vec![]);
 
-        let mut tx_signer = StacksTransactionSigner::new(&tx_cc);
-        tx_signer.sign_origin(&privk1).unwrap();
-        let tx_cc_signed = tx_signer.get_tx().unwrap();
-        let tx_cc_len = {
-            let mut bytes = vec![];
-            tx_cc_signed.consensus_serialize(&mut bytes).unwrap();
-            bytes.len() as u64
-        };
+        test_rpc(
+            function_name!(),
+            40042,
+            40043,
+            50042,
+            50043,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                )
+                .unwrap();
 
-        // make an unconfirmed contract
-        let unconfirmed_contract = TEST_CONTRACT_UNCONFIRMED.clone();
-        let mut tx_unconfirmed_contract = StacksTransaction::new(
-            TransactionVersion::Testnet,
-            TransactionAuth::from_p2pkh(&privk1).unwrap(),
-            TransactionPayload::new_smart_contract(
-                &format!("hello-world-unconfirmed"),
-                &unconfirmed_contract.to_string(),
-                None,
-            )
-            .unwrap(),
-        );
+                let parent_block = make_codec_test_block(25);
+                let parent_consensus_hash = ConsensusHash([0x02; 20]);
 
-        tx_unconfirmed_contract.chain_id = 0x80000000;
-        tx_unconfirmed_contract.auth.set_origin_nonce(3);
-        tx_unconfirmed_contract.set_tx_fee(0);
+                let mut mblocks = make_sample_microblock_stream(&privk, &parent_block.block_hash());
+                mblocks.truncate(15);
 
-        let mut tx_signer = StacksTransactionSigner::new(&tx_unconfirmed_contract);
-        tx_signer.sign_origin(&privk1).unwrap();
-        let tx_unconfirmed_contract_signed = tx_signer.get_tx().unwrap();
-        let tx_unconfirmed_contract_len = {
-            let mut bytes = vec![];
-            tx_unconfirmed_contract_signed
-                .consensus_serialize(&mut bytes)
-                .unwrap();
-            bytes.len() as u64
-        };
+                let mut child_block = make_codec_test_block(25);
+                let child_consensus_hash = ConsensusHash([0x03; 20]);
 
-        let tip =
-            SortitionDB::get_canonical_burn_chain_tip(&peer_1.sortdb.as_ref().unwrap().conn())
-                .unwrap();
-        let mut anchor_cost = ExecutionCost::zero();
-        let mut anchor_size = 0;
+                child_block.header.parent_block = parent_block.block_hash();
+                child_block.header.parent_microblock =
+                    mblocks.last().as_ref().unwrap().block_hash();
+                child_block.header.parent_microblock_sequence =
+                    mblocks.last().as_ref().unwrap().header.sequence;
 
-        // make a block and a microblock.
-        // Put the coinbase and smart-contract in the anchored block.
-        // Put the contract-call in the microblock
-        let (burn_ops, stacks_block, microblocks) = peer_1.make_tenure(
-            |ref mut miner, ref mut sortdb, ref mut chainstate, vrf_proof, ref parent_opt, _| {
-                let parent_tip = match parent_opt {
-                    None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
-                    Some(block) => {
-                        let ic = sortdb.index_conn();
-                        let snapshot = SortitionDB::get_block_snapshot_for_winning_stacks_block(
-                            &ic,
-                            &tip.sortition_id,
-                            &block.block_hash(),
-                        )
-                        .unwrap()
-                        .unwrap(); // succeeds because we don't fork
-                        StacksChainState::get_anchored_block_header_info(
-                            chainstate.db(),
-                            &snapshot.consensus_hash,
-                            &snapshot.winning_stacks_block_hash,
-                        )
-                        .unwrap()
-                        .unwrap()
-                    }
-                };
+                let child_index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &child_consensus_hash,
+                    &child_block.block_hash(),
+                );
+
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &parent_consensus_hash,
+                    &parent_block,
+                    &ConsensusHash([0x01; 20]),
+                    456,
+                    123,
+                );
+                set_block_processed(
+                    peer_server.chainstate(),
+                    &parent_consensus_hash,
+                    &parent_block.block_hash(),
+                    true,
+                );
+
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &child_consensus_hash,
+                    &child_block,
+                    &parent_consensus_hash,
+                    456,
+                    123,
+                );
+                set_block_processed(
+                    peer_server.chainstate(),
+                    &child_consensus_hash,
+                    &child_block.block_hash(),
+                    true,
+                );
 
-                let block_builder = StacksBlockBuilder::make_regtest_block_builder(
-                    &parent_tip,
-                    vrf_proof,
-                    tip.total_burn,
-                    microblock_pubkeyhash,
-                )
-                .unwrap();
-                let (anchored_block, anchored_block_size, anchored_block_cost) =
-                    StacksBlockBuilder::make_anchored_block_from_txs(
-                        block_builder,
-                        chainstate,
-                        &sortdb.index_conn(),
-                        vec![tx_coinbase_signed.clone(), tx_contract_signed.clone()],
-                    )
-                    .unwrap();
+                for mblock in mblocks.iter() {
+                    store_staging_microblock(
+                        peer_server.chainstate(),
+                        &parent_consensus_hash,
+                        &parent_block.block_hash(),
+                        &mblock,
+                    );
+                }
 
-                anchor_size = anchored_block_size;
-                anchor_cost = anchored_block_cost;
+                set_microblocks_processed(
+                    peer_server.chainstate(),
+                    &child_consensus_hash,
+                    &child_block.block_hash(),
+                    &mblocks.last().as_ref().unwrap().block_hash(),
+                );
 
-                (anchored_block, vec![])
+                *server_microblocks_cell.borrow_mut() = mblocks;
+
+                convo_client.new_getmicroblocks_confirmed(child_index_block_hash)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match (*http_response).clone() {
+                    HttpResponseType::Microblocks(_, mut microblocks) => {
+                        microblocks.reverse();
+                        assert_eq!(microblocks.len(), (*server_microblocks_cell.borrow()).len());
+                        assert_eq!(microblocks, *server_microblocks_cell.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
             },
         );
+    }
 
-        let (_, _, consensus_hash) = peer_1.next_burnchain_block(burn_ops.clone());
-        peer_2.next_burnchain_block(burn_ops.clone());
-
-        peer_1.process_stacks_epoch_at_tip(&stacks_block, &vec![]);
-        peer_2.process_stacks_epoch_at_tip(&stacks_block, &vec![]);
-
-        // begin microblock section
-        if include_microblocks {
-            // build 1-block microblock stream with the contract-call and the unconfirmed contract
-            let microblock = {
-                let sortdb = peer_1.sortdb.take().unwrap();
-                Relayer::setup_unconfirmed_state(peer_1.chainstate(), &sortdb).unwrap();
-                let mblock = {
-                    let sort_iconn = sortdb.index_conn();
-                    let mut microblock_builder = StacksMicroblockBuilder::new(
-                        stacks_block.block_hash(),
-                        consensus_hash.clone(),
-                        peer_1.chainstate(),
-                        &sort_iconn,
-                        BlockBuilderSettings::max_value(),
-                    )
-                    .unwrap();
-                    let microblock = microblock_builder
-                        .mine_next_microblock_from_txs(
-                            vec![
-                                (tx_cc_signed, tx_cc_len),
-                                (tx_unconfirmed_contract_signed, tx_unconfirmed_contract_len),
-                            ],
-                            &microblock_privkey,
-                        )
-                        .unwrap();
-                    microblock
-                };
-                peer_1.sortdb = Some(sortdb);
-                mblock
-            };
+    #[test]
+    #[ignore]
+    fn test_rpc_unconfirmed_microblocks() {
+        let server_microblocks_cell = RefCell::new(vec![]);
 
-            // store microblock stream
-            peer_1
-                .chainstate()
-                .preprocess_streamed_microblock(
-                    &consensus_hash,
-                    &stacks_block.block_hash(),
-                    &microblock,
-                )
-                .unwrap();
-            peer_2
-                .chainstate()
-                .preprocess_streamed_microblock(
-                    &consensus_hash,
-                    &stacks_block.block_hash(),
-                    &microblock,
+        test_rpc(
+            function_name!(),
+            40050,
+            40051,
+            50050,
+            50051,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
                 )
                 .unwrap();
 
-            // process microblock stream to generate unconfirmed state
-            let canonical_tip = StacksBlockHeader::make_index_block_hash(
-                &consensus_hash,
-                &stacks_block.block_hash(),
-            );
-            let sortdb1 = peer_1.sortdb.take().unwrap();
-            let sortdb2 = peer_2.sortdb.take().unwrap();
-            peer_1
-                .chainstate()
-                .reload_unconfirmed_state(&sortdb1.index_conn(), canonical_tip.clone())
-                .unwrap();
-            peer_2
-                .chainstate()
-                .reload_unconfirmed_state(&sortdb2.index_conn(), canonical_tip.clone())
-                .unwrap();
-            peer_1.sortdb = Some(sortdb1);
-            peer_2.sortdb = Some(sortdb2);
-        }
-        // end microblock section
+                let consensus_hash = ConsensusHash([0x02; 20]);
+                let anchored_block_hash = BlockHeaderHash([0x03; 32]);
+                let index_block_hash =
+                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &anchored_block_hash);
 
-        // stuff some transactions into peer_2's mempool
-        // (relates to mempool query tests)
-        let mut mempool = peer_2.mempool.take().unwrap();
-        let mut mempool_tx = mempool.tx_begin().unwrap();
-        for i in 0..10 {
-            let pk = StacksPrivateKey::new();
-            let addr = StacksAddress::from_public_keys(
-                C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
-                &AddressHashMode::SerializeP2PKH,
-                1,
-                &vec![StacksPublicKey::from_private(&StacksPrivateKey::new())],
-            )
-            .unwrap();
-            let mut tx = StacksTransaction {
-                version: TransactionVersion::Testnet,
-                chain_id: 0x80000000,
-                auth: TransactionAuth::from_p2pkh(&pk).unwrap(),
-                anchor_mode: TransactionAnchorMode::Any,
-                post_condition_mode: TransactionPostConditionMode::Allow,
-                post_conditions: vec![],
-                payload: TransactionPayload::TokenTransfer(
-                    addr.to_account_principal(),
-                    123,
-                    TokenTransferMemo([0u8; 34]),
-                ),
-            };
-            tx.set_tx_fee(1000);
-            tx.set_origin_nonce(0);
+                let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
+                mblocks.truncate(15);
 
-            let txid = tx.txid();
-            let tx_bytes = tx.serialize_to_vec();
-            let origin_addr = tx.origin_address();
-            let origin_nonce = tx.get_origin_nonce();
-            let sponsor_addr = tx.sponsor_address().unwrap_or(origin_addr.clone());
-            let sponsor_nonce = tx.get_sponsor_nonce().unwrap_or(origin_nonce);
-            let tx_fee = tx.get_tx_fee();
+                for mblock in mblocks.iter() {
+                    store_staging_microblock(
+                        peer_server.chainstate(),
+                        &consensus_hash,
+                        &anchored_block_hash,
+                        &mblock,
+                    );
+                }
 
-            // should succeed
-            MemPoolDB::try_add_tx(
-                &mut mempool_tx,
-                peer_1.chainstate(),
-                &consensus_hash,
-                &stacks_block.block_hash(),
-                txid.clone(),
-                tx_bytes,
-                tx_fee,
-                stacks_block.header.total_work.work,
-                &origin_addr,
-                origin_nonce,
-                &sponsor_addr,
-                sponsor_nonce,
-                None,
-            )
-            .unwrap();
-        }
-        mempool_tx.commit().unwrap();
-        peer_2.mempool.replace(mempool);
+                *server_microblocks_cell.borrow_mut() = mblocks;
+
+                // start at seq 5
+                convo_client.new_getmicroblocks_unconfirmed(index_block_hash, 5)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::Microblocks(response_md, microblocks) => {
+                        assert_eq!(microblocks.len(), 10);
+                        assert_eq!(
+                            *microblocks,
+                            (*server_microblocks_cell.borrow())[5..].to_vec()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_unconfirmed_transaction() {
+        let last_txid = RefCell::new(Txid([0u8; 32]));
+        let last_mblock = RefCell::new(BlockHeaderHash([0u8; 32]));
+
+        test_rpc(
+            function_name!(),
+            40052,
+            40053,
+            50052,
+            50053,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                )
+                .unwrap();
 
-        let peer_1_sortdb = peer_1.sortdb.take().unwrap();
-        let peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
-        let _ = peer_1
-            .network
-            .refresh_burnchain_view(
-                &peer_1_indexer,
-                &peer_1_sortdb,
-                &peer_1_stacks_node.chainstate,
-                false,
-            )
-            .unwrap();
-        peer_1.sortdb = Some(peer_1_sortdb);
-        peer_1.stacks_node = Some(peer_1_stacks_node);
+                let sortdb = peer_server.sortdb.take().unwrap();
+                Relayer::setup_unconfirmed_state(peer_server.chainstate(), &sortdb).unwrap();
+                peer_server.sortdb = Some(sortdb);
 
-        let peer_2_sortdb = peer_2.sortdb.take().unwrap();
-        let peer_2_stacks_node = peer_2.stacks_node.take().unwrap();
-        let _ = peer_2
-            .network
-            .refresh_burnchain_view(
-                &peer_2_indexer,
-                &peer_2_sortdb,
-                &peer_2_stacks_node.chainstate,
-                false,
-            )
-            .unwrap();
-        peer_2.sortdb = Some(peer_2_sortdb);
-        peer_2.stacks_node = Some(peer_2_stacks_node);
+                assert!(peer_server.chainstate().unconfirmed_state.is_some());
+                let (txid, mblock_hash) = match peer_server.chainstate().unconfirmed_state {
+                    Some(ref unconfirmed) => {
+                        assert!(unconfirmed.mined_txs.len() > 0);
+                        let mut txid = Txid([0u8; 32]);
+                        let mut mblock_hash = BlockHeaderHash([0u8; 32]);
+                        for (next_txid, (_, mbh, ..)) in unconfirmed.mined_txs.iter() {
+                            txid = next_txid.clone();
+                            mblock_hash = mbh.clone();
+                            break;
+                        }
+                        (txid, mblock_hash)
+                    }
+                    None => {
+                        panic!("No unconfirmed state");
+                    }
+                };
 
-        let view_1 = peer_1.get_burnchain_view().unwrap();
-        let view_2 = peer_2.get_burnchain_view().unwrap();
+                *last_txid.borrow_mut() = txid.clone();
+                *last_mblock.borrow_mut() = mblock_hash.clone();
 
-        let mut convo_1 = ConversationHttp::new(
-            format!("127.0.0.1:{}", peer_1_http)
-                .parse::<SocketAddr>()
-                .unwrap(),
-            Some(UrlString::try_from(format!("http://peer1.com")).unwrap()),
-            peer_1.to_peer_host(),
-            &peer_1.config.connection_opts,
-            0,
+                convo_client.new_gettransaction_unconfirmed(txid)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::UnconfirmedTransaction(response_md, unconfirmed_resp) => {
+                        assert_eq!(
+                            unconfirmed_resp.status,
+                            UnconfirmedTransactionStatus::Microblock {
+                                block_hash: (*last_mblock.borrow()).clone(),
+                                seq: 0
+                            }
+                        );
+                        let tx = StacksTransaction::consensus_deserialize(
+                            &mut &hex_bytes(&unconfirmed_resp.tx).unwrap()[..],
+                        )
+                        .unwrap();
+                        assert_eq!(tx.txid(), *last_txid.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
         );
+    }
 
-        let mut convo_2 = ConversationHttp::new(
-            format!("127.0.0.1:{}", peer_2_http)
-                .parse::<SocketAddr>()
-                .unwrap(),
-            Some(UrlString::try_from(format!("http://peer2.com")).unwrap()),
-            peer_2.to_peer_host(),
-            &peer_2.config.connection_opts,
-            1,
+    #[test]
+    #[ignore]
+    fn test_rpc_missing_getblock() {
+        test_rpc(
+            function_name!(),
+            40060,
+            40061,
+            50060,
+            50061,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
+                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &peer_server_consensus_hash,
+                    &peer_server_block_hash,
+                );
+
+                // now ask for it
+                convo_client.new_getblock(index_block_hash)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::NotFound(response_md, msg) => true,
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
         );
+    }
 
-        let req = make_request(&mut peer_1, &mut convo_1, &mut peer_2, &mut convo_2);
+    #[test]
+    #[ignore]
+    fn test_rpc_missing_index_getmicroblocks() {
+        test_rpc(
+            function_name!(),
+            40070,
+            40071,
+            50070,
+            50071,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
+                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &peer_server_consensus_hash,
+                    &peer_server_block_hash,
+                );
 
-        convo_1.send_request(req.clone()).unwrap();
-        let mut peer_1_mempool = peer_1.mempool.take().unwrap();
-        let peer_2_mempool = peer_2.mempool.take().unwrap();
+                // now ask for it
+                convo_client.new_getmicroblocks_indexed(index_block_hash)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::NotFound(response_md, msg) => true,
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        test_debug!("convo1 sends to convo2");
-        convo_send_recv(
-            &mut convo_1,
-            &peer_1_mempool,
-            peer_1.chainstate(),
-            &mut convo_2,
-            &peer_2_mempool,
-            peer_2.chainstate(),
+    #[test]
+    #[ignore]
+    fn test_rpc_missing_confirmed_getmicroblocks() {
+        test_rpc(
+            function_name!(),
+            40072,
+            40073,
+            50072,
+            50073,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
+                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &peer_server_consensus_hash,
+                    &peer_server_block_hash,
+                );
+
+                // now ask for it
+                convo_client.new_getmicroblocks_confirmed(index_block_hash)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::NotFound(response_md, msg) => true,
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
         );
+    }
 
-        // hack around the borrow-checker
-        let mut peer_1_sortdb = peer_1.sortdb.take().unwrap();
-        let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_missing_unconfirmed_microblocks() {
+        let server_microblocks_cell = RefCell::new(vec![]);
 
-        Relayer::setup_unconfirmed_state(&mut peer_1_stacks_node.chainstate, &peer_1_sortdb)
-            .unwrap();
+        test_rpc(
+            function_name!(),
+            40080,
+            40081,
+            50080,
+            50081,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                )
+                .unwrap();
 
-        convo_1
-            .chat(
-                &mut peer_1.network,
-                &mut peer_1_sortdb,
-                &mut peer_1_stacks_node.chainstate,
-                &mut peer_1_mempool,
-                &RPCHandlerArgs::default(),
-            )
-            .unwrap();
+                let consensus_hash = ConsensusHash([0x02; 20]);
+                let anchored_block_hash = BlockHeaderHash([0x03; 32]);
+                let index_block_hash =
+                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &anchored_block_hash);
 
-        peer_1.sortdb = Some(peer_1_sortdb);
-        peer_1.stacks_node = Some(peer_1_stacks_node);
-        peer_1.mempool = Some(peer_1_mempool);
-        peer_2.mempool = Some(peer_2_mempool);
+                let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
+                mblocks.truncate(15);
 
-        test_debug!("convo2 sends to convo1");
+                for mblock in mblocks.iter() {
+                    store_staging_microblock(
+                        peer_server.chainstate(),
+                        &consensus_hash,
+                        &anchored_block_hash,
+                        &mblock,
+                    );
+                }
 
-        // hack around the borrow-checker
-        let mut peer_2_sortdb = peer_2.sortdb.take().unwrap();
-        let mut peer_2_stacks_node = peer_2.stacks_node.take().unwrap();
-        let mut peer_2_mempool = peer_2.mempool.take().unwrap();
+                *server_microblocks_cell.borrow_mut() = mblocks;
 
-        let _ = peer_2
-            .network
-            .refresh_burnchain_view(
-                &peer_2_indexer,
-                &peer_2_sortdb,
-                &peer_2_stacks_node.chainstate,
-                false,
-            )
-            .unwrap();
+                // start at seq 16 (which doesn't exist)
+                convo_client.new_getmicroblocks_unconfirmed(index_block_hash, 16)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::NotFound(response_md, msg) => true,
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        Relayer::setup_unconfirmed_state(&mut peer_2_stacks_node.chainstate, &peer_2_sortdb)
-            .unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_get_contract_src() {
+        // Test v2/contracts/source (aka GetContractSrc) endpoint.
+        // In this test, we don't set any tip parameters, and allow the endpoint to execute against
+        // the canonical Stacks tip.
+        // The contract source we are querying for exists in the anchored state, so we expect the
+        // query to succeed.
+        test_rpc(
+            function_name!(),
+            40090,
+            40091,
+            50090,
+            50091,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getcontractsrc(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    TipRequest::UseLatestAnchoredTip,
+                    false,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetContractSrc(response_md, data) => {
+                        assert_eq!(data.source, TEST_CONTRACT);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        convo_2
-            .chat(
-                &mut peer_2.network,
-                &mut peer_2_sortdb,
-                &mut peer_2_stacks_node.chainstate,
-                &mut peer_2_mempool,
-                &RPCHandlerArgs::default(),
-            )
-            .unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_get_contract_src_unconfirmed_with_canonical_tip() {
+        // Test v2/contracts/source (aka GetContractSrc) endpoint.
+        // In this test, we don't set any tip parameters, and allow the endpoint to execute against
+        // the canonical Stacks tip.
+        // The contract source we are querying for only exists in the unconfirmed state, so we
+        // expect the query to fail.
+        test_rpc(
+            function_name!(),
+            40100,
+            40101,
+            50100,
+            50101,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getcontractsrc(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    TipRequest::UseLatestAnchoredTip,
+                    false,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::NotFound(_, error_str) => {
+                        assert_eq!(error_str, "No contract source data found");
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        peer_2.sortdb = Some(peer_2_sortdb);
-        peer_2.stacks_node = Some(peer_2_stacks_node);
-        let mut peer_1_mempool = peer_1.mempool.take().unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_get_contract_src_with_unconfirmed_tip() {
+        // Test v2/contracts/source (aka GetContractSrc) endpoint.
+        // In this test, we set `tip_req` to be the unconfirmed chain tip.
+        // The contract source we are querying for exists in the unconfirmed state, so we expect
+        // the query to succeed.
+        test_rpc(
+            function_name!(),
+            40102,
+            40103,
+            50102,
+            50103,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let unconfirmed_tip = peer_client
+                    .chainstate()
+                    .unconfirmed_state
+                    .as_ref()
+                    .unwrap()
+                    .unconfirmed_chain_tip
+                    .clone();
+                convo_client.new_getcontractsrc(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    TipRequest::SpecificTip(unconfirmed_tip),
+                    false,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetContractSrc(response_md, data) => {
+                        assert_eq!(data.source, TEST_CONTRACT_UNCONFIRMED);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        convo_send_recv(
-            &mut convo_2,
-            &peer_2_mempool,
-            peer_2.chainstate(),
-            &mut convo_1,
-            &peer_1_mempool,
-            peer_1.chainstate(),
+    #[test]
+    #[ignore]
+    fn test_rpc_get_contract_src_use_latest_tip() {
+        // Test v2/contracts/source (aka GetContractSrc) endpoint.
+        // In this test, we set `tip_req` to UseLatestUnconfirmedTip.
+        // The contract source we are querying for exists in the unconfirmed state, so we expect
+        // the query to succeed.
+        test_rpc(
+            function_name!(),
+            40104,
+            40105,
+            50104,
+            50105,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getcontractsrc(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    TipRequest::UseLatestAnchoredTip,
+                    false,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetContractSrc(response_md, data) => {
+                        assert_eq!(data.source, TEST_CONTRACT_UNCONFIRMED);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
         );
+    }
 
-        test_debug!("flush convo1");
-
-        // hack around the borrow-checker
-        convo_send_recv(
-            &mut convo_1,
-            &peer_1_mempool,
-            peer_1.chainstate(),
-            &mut convo_2,
-            &peer_2_mempool,
-            peer_2.chainstate(),
+    #[test]
+    #[ignore]
+    fn test_rpc_get_account() {
+        test_rpc(
+            function_name!(),
+            40110,
+            40111,
+            50110,
+            50111,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getaccount(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    TipRequest::UseLatestAnchoredTip,
+                    false,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetAccount(response_md, data) => {
+                        assert_eq!(data.nonce, 2);
+                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
+                        assert_eq!(balance, 1000000000);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
         );
+    }
 
-        peer_2.mempool = Some(peer_2_mempool);
-
-        let mut peer_1_sortdb = peer_1.sortdb.take().unwrap();
-        let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
-
-        let _ = peer_1
-            .network
-            .refresh_burnchain_view(
-                &peer_1_indexer,
-                &peer_1_sortdb,
-                &peer_1_stacks_node.chainstate,
-                false,
-            )
-            .unwrap();
-
-        Relayer::setup_unconfirmed_state(&mut peer_1_stacks_node.chainstate, &peer_1_sortdb)
-            .unwrap();
-
-        convo_1
-            .chat(
-                &mut peer_1.network,
-                &mut peer_1_sortdb,
-                &mut peer_1_stacks_node.chainstate,
-                &mut peer_1_mempool,
-                &RPCHandlerArgs::default(),
-            )
-            .unwrap();
-
-        convo_1
-            .try_flush(&peer_1_mempool, &mut peer_1_stacks_node.chainstate)
-            .unwrap();
+    /// In this test, we ask for the account's unanchored state alongside its anchored state.
+    /// We expect the top-level state to come from the anchored tip, and the unanchored state to
+    /// reflect the microblock that spends from the account.
+    #[test]
+    #[ignore]
+    fn test_rpc_get_account_unanchored() {
+        test_rpc(
+            function_name!(),
+            40838,
+            40839,
+            50838,
+            50839,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getaccount_unanchored(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    TipRequest::UseLatestAnchoredTip,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::GetAccount(response_md, data) => {
+                        assert_eq!(data.nonce, 2);
+                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
+                        assert_eq!(balance, 1000000000);
 
-        peer_1.sortdb = Some(peer_1_sortdb);
-        peer_1.stacks_node = Some(peer_1_stacks_node);
-        peer_1.mempool = Some(peer_1_mempool);
+                        let unanchored = data.unanchored.clone().unwrap();
+                        assert_eq!(unanchored.nonce, 4);
+                        assert_eq!(unanchored.nonce_delta, 2);
+                        let balance = u128::from_str_radix(&unanchored.balance[2..], 16).unwrap();
+                        assert_eq!(balance, 1000000000 - 123);
+                        assert_eq!(unanchored.balance_delta, "-123");
+                        assert_eq!(unanchored.microblock_seq, Some(0));
+                        assert!(unanchored.microblock_tip.is_some());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        // should have gotten a reply
-        let resp_opt = convo_1.try_get_response();
-        assert!(resp_opt.is_some());
+    /// In this test, the query parameter `tip_req` is set to UseLatestUnconfirmedTip, and so we expect the
+    /// tip used for the query to be the latest microblock.
+    /// We check that the account state matches the state in the most recent microblock.
+    #[test]
+    #[ignore]
+    fn test_rpc_get_account_use_latest_tip() {
+        test_rpc(
+            function_name!(),
+            40112,
+            40113,
+            50112,
+            50113,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getaccount(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    TipRequest::UseLatestAnchoredTip,
+                    false,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetAccount(response_md, data) => {
+                        assert_eq!(data.nonce, 4);
+                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
+                        assert_eq!(balance, 999999877);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        let resp = resp_opt.unwrap();
-        assert!(check_result(
-            &req,
-            &resp,
-            &mut peer_1,
-            &mut peer_2,
-            &convo_1,
-            &convo_2
-        ));
+    /// In this test, the query parameter `tip_req` is set to UseLatestUnconfirmedTip, but we did not generate
+    /// microblocks in the rpc test. Thus, we expect the tip used for the query to be the previous
+    /// anchor block (which is the latest tip).
+    /// We check that the account state matches the state in the previous anchor block.
+    #[test]
+    #[ignore]
+    fn test_rpc_get_account_use_latest_tip_no_microblocks() {
+        test_rpc(
+            function_name!(),
+            40114,
+            40115,
+            50114,
+            50115,
+            false,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getaccount(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    TipRequest::UseLatestAnchoredTip,
+                    false,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetAccount(response_md, data) => {
+                        assert_eq!(data.nonce, 2);
+                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
+                        assert_eq!(balance, 1000000000);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
     }
 
-    /// This test tests two things:
-    /// (1) the get info RPC call
-    /// (2) whether the ConversationHttp object gets correctly updated with a peer's canonical
-    /// stacks tip height, which is sent in HTTP headers as part of the request/response
     #[test]
     #[ignore]
-    fn test_rpc_getinfo() {
-        let peer_server_info = RefCell::new(None);
-        let client_stacks_height = 17;
+    fn test_rpc_get_account_unconfirmed() {
         test_rpc(
             function_name!(),
-            40000,
-            40001,
-            50000,
-            50001,
+            40120,
+            40121,
+            50120,
+            50121,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let peer_info = RPCPeerInfoData::from_network(
-                    &peer_server.network,
-                    &peer_server.stacks_node.as_ref().unwrap().chainstate,
-                    None,
-                    &Sha256Sum::zero(),
-                );
-
-                *peer_server_info.borrow_mut() = Some(peer_info);
-
-                convo_client.new_getinfo(Some(client_stacks_height))
+                let unconfirmed_tip = peer_client
+                    .chainstate()
+                    .unconfirmed_state
+                    .as_ref()
+                    .unwrap()
+                    .unconfirmed_chain_tip
+                    .clone();
+                convo_client.new_getaccount(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    TipRequest::SpecificTip(unconfirmed_tip),
+                    false,
+                )
             },
             |ref http_request,
              ref http_response,
@@ -4300,31 +8067,15 @@ mod test {
              ref convo_client,
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
-                assert_eq!(convo_client.canonical_stacks_tip_height, Some(1));
-                assert_eq!(
-                    convo_server.canonical_stacks_tip_height,
-                    Some(client_stacks_height)
-                );
                 match http_response {
-                    HttpResponseType::PeerInfo(response_md, peer_data) => {
-                        assert_eq!(Some((*peer_data).clone()), *peer_server_info.borrow());
-                        assert!(peer_data.node_public_key.is_some());
-                        assert!(peer_data.node_public_key_hash.is_some());
-                        assert_eq!(
-                            peer_data.node_public_key_hash,
-                            Some(Hash160::from_node_public_key(
-                                &peer_data
-                                    .node_public_key
-                                    .clone()
-                                    .unwrap()
-                                    .to_public_key()
-                                    .unwrap()
-                            ))
-                        );
+                    HttpResponseType::GetAccount(response_md, data) => {
+                        assert_eq!(data.nonce, 4);
+                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
+                        assert_eq!(balance, 1000000000 - 123);
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -4334,55 +8085,44 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_getpoxinfo() {
-        // Test v2/pox (aka GetPoxInfo) endpoint.
-        // In this test, `tip_req` is set to UseLatestAnchoredTip.
-        // Thus, the query for pox info will be against the canonical Stacks tip, which we expect to succeed.
-        let pox_server_info = RefCell::new(None);
+    fn test_rpc_get_data_var() {
         test_rpc(
             function_name!(),
-            40002,
-            40003,
-            50002,
-            50003,
+            40122,
+            40123,
+            50122,
+            50123,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
-                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
-                let stacks_block_id = {
-                    let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
-                    StacksBlockHeader::make_index_block_hash(
-                        &tip.consensus_hash,
-                        &tip.anchored_block_hash,
-                    )
-                };
-                let pox_info = RPCPoxInfoData::from_db(
-                    &mut sortdb,
-                    chainstate,
-                    &stacks_block_id,
-                    &peer_client.config.burnchain,
+                convo_client.new_getdatavar(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "bar".try_into().unwrap(),
+                    TipRequest::UseLatestAnchoredTip,
+                    false,
                 )
-                .unwrap();
-                *pox_server_info.borrow_mut() = Some(pox_info);
-                convo_client.new_getpoxinfo(TipRequest::UseLatestAnchoredTip)
             },
             |ref http_request,
              ref http_response,
              ref mut peer_client,
              ref mut peer_server,
-             convo_client,
-             convo_server| {
+             ref convo_client,
+             ref convo_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::PoxInfo(response_md, pox_data) => {
-                        assert_eq!(Some((*pox_data).clone()), *pox_server_info.borrow());
+                    HttpResponseType::GetDataVar(response_md, data) => {
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::Int(0)
+                        );
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -4392,39 +8132,33 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_getpoxinfo_use_latest_tip() {
-        // Test v2/pox (aka GetPoxInfo) endpoint.
-        // In this test, we set `tip_req` to UseLatestUnconfirmedTip, and we expect that querying for pox
-        // info against the unconfirmed state will succeed.
-        let pox_server_info = RefCell::new(None);
+    fn test_rpc_get_data_var_unconfirmed() {
         test_rpc(
             function_name!(),
-            40004,
-            40005,
-            50004,
-            50005,
+            40124,
+            40125,
+            50124,
+            50125,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
-                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
-                let stacks_block_id = chainstate
+                let unconfirmed_tip = peer_client
+                    .chainstate()
                     .unconfirmed_state
                     .as_ref()
                     .unwrap()
                     .unconfirmed_chain_tip
                     .clone();
-                let pox_info = RPCPoxInfoData::from_db(
-                    &mut sortdb,
-                    chainstate,
-                    &stacks_block_id,
-                    &peer_client.config.burnchain,
+                convo_client.new_getdatavar(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "bar".try_into().unwrap(),
+                    TipRequest::SpecificTip(unconfirmed_tip),
+                    false,
                 )
-                .unwrap();
-                *pox_server_info.borrow_mut() = Some(pox_info);
-                convo_client.new_getpoxinfo(TipRequest::UseLatestUnconfirmedTip)
             },
             |ref http_request,
              ref http_response,
@@ -4434,12 +8168,15 @@ mod test {
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::PoxInfo(response_md, pox_data) => {
-                        assert_eq!(Some((*pox_data).clone()), *pox_server_info.borrow());
+                    HttpResponseType::GetDataVar(response_md, data) => {
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::Int(1)
+                        );
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -4449,18 +8186,27 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_getneighbors() {
+    fn test_rpc_get_data_var_nonexistant() {
         test_rpc(
             function_name!(),
-            40010,
-            40011,
-            50010,
-            50011,
+            40125,
+            40126,
+            50125,
+            50126,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
-             ref mut convo_server| { convo_client.new_getneighbors() },
+             ref mut convo_server| {
+                convo_client.new_getdatavar(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "bar-nonexistant".try_into().unwrap(),
+                    TipRequest::UseLatestAnchoredTip,
+                    false,
+                )
+            },
             |ref http_request,
              ref http_response,
              ref mut peer_client,
@@ -4469,18 +8215,12 @@ mod test {
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::Neighbors(response_md, neighbor_info) => {
-                        assert_eq!(neighbor_info.sample.len(), 1);
-                        assert_eq!(neighbor_info.sample[0].port, peer_client.config.server_port); // we see ourselves as the neighbor
-                        assert_eq!(neighbor_info.bootstrap.len(), 1);
-                        assert_eq!(
-                            neighbor_info.bootstrap[0].port,
-                            peer_client.config.server_port
-                        ); // we see ourselves as the bootstrap
+                    HttpResponseType::NotFound(_, msg) => {
+                        assert_eq!(msg, "Data var not found");
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -4490,90 +8230,43 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_getheaders() {
-        let server_blocks_cell = RefCell::new(None);
-
+    fn test_rpc_get_clarity_marf_value() {
         test_rpc(
             function_name!(),
-            40012,
-            40013,
-            50012,
-            50013,
+            40900,
+            40901,
+            50900,
+            50901,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                // have "server" peer store a few continuous block to staging
-                let mut blocks: Vec<StacksBlock> = vec![];
-                let mut index_block_hashes = vec![];
-                for i in 0..25 {
-                    let mut peer_server_block = make_codec_test_block(25);
-
-                    peer_server_block.header.total_work.work = (i + 1) as u64;
-                    peer_server_block.header.total_work.burn = (i + 1) as u64;
-                    peer_server_block.header.parent_block = blocks
-                        .last()
-                        .map(|blk| blk.block_hash())
-                        .unwrap_or(BlockHeaderHash([0u8; 32]));
-
-                    let peer_server_consensus_hash = ConsensusHash([(i + 1) as u8; 20]);
-                    let index_block_hash = StacksBlockHeader::make_index_block_hash(
-                        &peer_server_consensus_hash,
-                        &peer_server_block.block_hash(),
-                    );
-
-                    test_debug!("Store peer server index block {:?}", &index_block_hash);
-                    store_staging_block(
-                        peer_server.chainstate(),
-                        &peer_server_consensus_hash,
-                        &peer_server_block,
-                        &ConsensusHash([i as u8; 20]),
-                        456,
-                        123,
-                    );
-                    set_block_processed(
-                        peer_server.chainstate(),
-                        &peer_server_consensus_hash,
-                        &peer_server_block.block_hash(),
-                        true,
-                    );
-
-                    index_block_hashes.push(index_block_hash);
-                    blocks.push(peer_server_block);
-                }
-
-                let rev_blocks: Vec<_> = blocks.into_iter().rev().collect();
-                let rev_ibhs: Vec<_> = index_block_hashes.into_iter().rev().collect();
-
-                let tip = rev_ibhs[0].clone();
-                *server_blocks_cell.borrow_mut() = Some((rev_blocks, rev_ibhs));
-
-                // now ask for it
-                convo_client.new_getheaders(25, TipRequest::SpecificTip(tip))
+                let contract_id = QualifiedContractIdentifier::parse(
+                    "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world",
+                )
+                .unwrap();
+                let key =
+                    ClarityDatabase::make_key_for_trip(&contract_id, StoreType::Variable, "bar");
+                convo_client.new_getclaritymarfvalue(key, TipRequest::UseLatestAnchoredTip, true)
             },
             |ref http_request,
              ref http_response,
              ref mut peer_client,
              ref mut peer_server,
              ref convo_client,
-             ref convo_server| {
-                let req_md = http_request.metadata().clone();
-                match http_response {
-                    HttpResponseType::Headers(response_md, headers) => {
-                        assert_eq!(headers.len(), 25);
-                        let expected = server_blocks_cell.borrow().clone().unwrap();
-                        for (i, h) in headers.iter().enumerate() {
-                            assert_eq!(h.header, expected.0[i].header);
-                            assert_eq!(h.consensus_hash, ConsensusHash([(25 - i) as u8; 20]));
-                            if i + 1 < headers.len() {
-                                assert_eq!(h.parent_block_id, expected.1[i + 1]);
-                            }
-                        }
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::GetClarityMarfValue(response_md, data) => {
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::Int(0)
+                        );
+                        assert!(data.marf_proof.is_some());
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -4583,42 +8276,25 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_unconfirmed_getblock() {
-        let server_block_cell = RefCell::new(None);
-
+    fn test_rpc_get_clarity_metadata() {
         test_rpc(
             function_name!(),
-            40020,
-            40021,
-            50020,
-            50021,
+            40902,
+            40903,
+            50902,
+            50903,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                // have "server" peer store a block to staging
-                let peer_server_block = make_codec_test_block(25);
-                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
-                let index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &peer_server_consensus_hash,
-                    &peer_server_block.block_hash(),
-                );
-
-                test_debug!("Store peer server index block {:?}", &index_block_hash);
-                store_staging_block(
-                    peer_server.chainstate(),
-                    &peer_server_consensus_hash,
-                    &peer_server_block,
-                    &ConsensusHash([0x03; 20]),
-                    456,
-                    123,
-                );
-
-                *server_block_cell.borrow_mut() = Some(peer_server_block);
-
-                // now ask for it
-                convo_client.new_getblock(index_block_hash)
+                convo_client.new_getclaritymetadata(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    ClarityDatabase::make_metadata_key(StoreType::Contract, "contract-size"),
+                    TipRequest::UseLatestAnchoredTip,
+                )
             },
             |ref http_request,
              ref http_response,
@@ -4626,17 +8302,13 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::Block(response_md, block_info) => {
-                        assert_eq!(
-                            block_info.block_hash(),
-                            (*server_block_cell.borrow()).as_ref().unwrap().block_hash()
-                        );
+                    HttpResponseType::GetClarityMetadata(response_md, data) => {
+                        assert!(data.data.parse::<u64>().unwrap() > 0);
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -4646,48 +8318,37 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_confirmed_getblock() {
-        let server_block_cell = RefCell::new(None);
-
+    fn test_rpc_get_map_entry() {
+        // Test v2/map_entry (aka GetMapEntry) endpoint.
+        // In this test, we don't set any tip parameters, and we expect that querying for map data
+        // against the canonical Stacks tip will succeed.
         test_rpc(
             function_name!(),
-            40030,
-            40031,
-            50030,
-            50031,
+            40130,
+            40131,
+            50130,
+            50131,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                // have "server" peer store a block to staging
-                let peer_server_block = make_codec_test_block(25);
-                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
-                let index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &peer_server_consensus_hash,
-                    &peer_server_block.block_hash(),
-                );
-
-                test_debug!("Store peer server index block {:?}", &index_block_hash);
-                store_staging_block(
-                    peer_server.chainstate(),
-                    &peer_server_consensus_hash,
-                    &peer_server_block,
-                    &ConsensusHash([0x03; 20]),
-                    456,
-                    123,
-                );
-                set_block_processed(
-                    peer_server.chainstate(),
-                    &peer_server_consensus_hash,
-                    &peer_server_block.block_hash(),
-                    true,
-                );
-
-                *server_block_cell.borrow_mut() = Some(peer_server_block);
-
-                // now ask for it
-                convo_client.new_getblock(index_block_hash)
+                let principal =
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal();
+                convo_client.new_getmapentry(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "unit-map".try_into().unwrap(),
+                    Value::Tuple(
+                        TupleData::from_data(vec![("account".into(), Value::Principal(principal))])
+                            .unwrap(),
+                    ),
+                    TipRequest::UseLatestAnchoredTip,
+                    false,
+                )
             },
             |ref http_request,
              ref http_response,
@@ -4697,15 +8358,19 @@ mod test {
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::Block(response_md, block_info) => {
+                    HttpResponseType::GetMapEntry(response_md, data) => {
                         assert_eq!(
-                            block_info.block_hash(),
-                            (*server_block_cell.borrow()).as_ref().unwrap().block_hash()
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::some(Value::Tuple(
+                                TupleData::from_data(vec![("units".into(), Value::Int(123))])
+                                    .unwrap()
+                            ))
+                            .unwrap()
                         );
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -4715,98 +8380,103 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_indexed_microblocks() {
-        let server_microblocks_cell = RefCell::new(vec![]);
-
+    fn test_rpc_get_map_entry_unconfirmed() {
+        // Test v2/map_entry (aka GetMapEntry) endpoint.
+        // In this test, we set `tip_req` to UseLatestUnconfirmedTip, and we expect that querying for map data
+        // against the unconfirmed state will succeed.
         test_rpc(
             function_name!(),
-            40040,
-            40041,
-            50040,
-            50041,
+            40140,
+            40141,
+            50140,
+            50141,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let privk = StacksPrivateKey::from_hex(
-                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                let unconfirmed_tip = peer_client
+                    .chainstate()
+                    .unconfirmed_state
+                    .as_ref()
+                    .unwrap()
+                    .unconfirmed_chain_tip
+                    .clone();
+                let principal =
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal();
+                convo_client.new_getmapentry(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "unit-map".try_into().unwrap(),
+                    Value::Tuple(
+                        TupleData::from_data(vec![("account".into(), Value::Principal(principal))])
+                            .unwrap(),
+                    ),
+                    TipRequest::SpecificTip(unconfirmed_tip),
+                    false,
                 )
-                .unwrap();
-
-                let parent_block = make_codec_test_block(25);
-                let parent_consensus_hash = ConsensusHash([0x02; 20]);
-                let parent_index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &parent_consensus_hash,
-                    &parent_block.block_hash(),
-                );
-
-                let mut mblocks = make_sample_microblock_stream(&privk, &parent_block.block_hash());
-                mblocks.truncate(15);
-
-                let mut child_block = make_codec_test_block(25);
-                let child_consensus_hash = ConsensusHash([0x03; 20]);
-
-                child_block.header.parent_block = parent_block.block_hash();
-                child_block.header.parent_microblock =
-                    mblocks.last().as_ref().unwrap().block_hash();
-                child_block.header.parent_microblock_sequence =
-                    mblocks.last().as_ref().unwrap().header.sequence;
-
-                store_staging_block(
-                    peer_server.chainstate(),
-                    &parent_consensus_hash,
-                    &parent_block,
-                    &ConsensusHash([0x01; 20]),
-                    456,
-                    123,
-                );
-                set_block_processed(
-                    peer_server.chainstate(),
-                    &parent_consensus_hash,
-                    &parent_block.block_hash(),
-                    true,
-                );
-
-                store_staging_block(
-                    peer_server.chainstate(),
-                    &child_consensus_hash,
-                    &child_block,
-                    &parent_consensus_hash,
-                    456,
-                    123,
-                );
-                set_block_processed(
-                    peer_server.chainstate(),
-                    &child_consensus_hash,
-                    &child_block.block_hash(),
-                    true,
-                );
-
-                let index_microblock_hash = StacksBlockHeader::make_index_block_hash(
-                    &parent_consensus_hash,
-                    &mblocks.last().as_ref().unwrap().block_hash(),
-                );
-
-                for mblock in mblocks.iter() {
-                    store_staging_microblock(
-                        peer_server.chainstate(),
-                        &parent_consensus_hash,
-                        &parent_block.block_hash(),
-                        &mblock,
-                    );
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetMapEntry(response_md, data) => {
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::some(Value::Tuple(
+                                TupleData::from_data(vec![("units".into(), Value::Int(1))])
+                                    .unwrap()
+                            ))
+                            .unwrap()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
                 }
+            },
+        );
+    }
 
-                set_microblocks_processed(
-                    peer_server.chainstate(),
-                    &child_consensus_hash,
-                    &child_block.block_hash(),
-                    &mblocks.last().as_ref().unwrap().block_hash(),
-                );
-
-                *server_microblocks_cell.borrow_mut() = mblocks;
-
-                convo_client.new_getmicroblocks_indexed(index_microblock_hash)
+    #[test]
+    #[ignore]
+    fn test_rpc_get_map_entry_use_latest_tip() {
+        test_rpc(
+            function_name!(),
+            40142,
+            40143,
+            50142,
+            50143,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let principal =
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal();
+                convo_client.new_getmapentry(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "unit-map".try_into().unwrap(),
+                    Value::Tuple(
+                        TupleData::from_data(vec![("account".into(), Value::Principal(principal))])
+                            .unwrap(),
+                    ),
+                    TipRequest::UseLatestAnchoredTip,
+                    false,
+                )
             },
             |ref http_request,
              ref http_response,
@@ -4815,15 +8485,20 @@ mod test {
              ref convo_client,
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
-                match (*http_response).clone() {
-                    HttpResponseType::Microblocks(_, mut microblocks) => {
-                        microblocks.reverse();
-                        assert_eq!(microblocks.len(), (*server_microblocks_cell.borrow()).len());
-                        assert_eq!(microblocks, *server_microblocks_cell.borrow());
+                match http_response {
+                    HttpResponseType::GetMapEntry(response_md, data) => {
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::some(Value::Tuple(
+                                TupleData::from_data(vec![("units".into(), Value::Int(1))])
+                                    .unwrap()
+                            ))
+                            .unwrap()
+                        );
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -4833,94 +8508,27 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_confirmed_microblocks() {
-        let server_microblocks_cell = RefCell::new(vec![]);
-
+    fn test_rpc_get_contract_abi() {
+        // Test /v2/contracts/interface (aka GetContractABI) endpoint.
+        // In this test, we don't set any tip parameters, and we expect that querying
+        // against the canonical Stacks tip will succeed.
         test_rpc(
             function_name!(),
-            40042,
-            40043,
-            50042,
-            50043,
+            40150,
+            40151,
+            50150,
+            50151,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let privk = StacksPrivateKey::from_hex(
-                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                convo_client.new_getcontractabi(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    TipRequest::UseLatestAnchoredTip,
                 )
-                .unwrap();
-
-                let parent_block = make_codec_test_block(25);
-                let parent_consensus_hash = ConsensusHash([0x02; 20]);
-
-                let mut mblocks = make_sample_microblock_stream(&privk, &parent_block.block_hash());
-                mblocks.truncate(15);
-
-                let mut child_block = make_codec_test_block(25);
-                let child_consensus_hash = ConsensusHash([0x03; 20]);
-
-                child_block.header.parent_block = parent_block.block_hash();
-                child_block.header.parent_microblock =
-                    mblocks.last().as_ref().unwrap().block_hash();
-                child_block.header.parent_microblock_sequence =
-                    mblocks.last().as_ref().unwrap().header.sequence;
-
-                let child_index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &child_consensus_hash,
-                    &child_block.block_hash(),
-                );
-
-                store_staging_block(
-                    peer_server.chainstate(),
-                    &parent_consensus_hash,
-                    &parent_block,
-                    &ConsensusHash([0x01; 20]),
-                    456,
-                    123,
-                );
-                set_block_processed(
-                    peer_server.chainstate(),
-                    &parent_consensus_hash,
-                    &parent_block.block_hash(),
-                    true,
-                );
-
-                store_staging_block(
-                    peer_server.chainstate(),
-                    &child_consensus_hash,
-                    &child_block,
-                    &parent_consensus_hash,
-                    456,
-                    123,
-                );
-                set_block_processed(
-                    peer_server.chainstate(),
-                    &child_consensus_hash,
-                    &child_block.block_hash(),
-                    true,
-                );
-
-                for mblock in mblocks.iter() {
-                    store_staging_microblock(
-                        peer_server.chainstate(),
-                        &parent_consensus_hash,
-                        &parent_block.block_hash(),
-                        &mblock,
-                    );
-                }
-
-                set_microblocks_processed(
-                    peer_server.chainstate(),
-                    &child_consensus_hash,
-                    &child_block.block_hash(),
-                    &mblocks.last().as_ref().unwrap().block_hash(),
-                );
-
-                *server_microblocks_cell.borrow_mut() = mblocks;
-
-                convo_client.new_getmicroblocks_confirmed(child_index_block_hash)
             },
             |ref http_request,
              ref http_response,
@@ -4929,15 +8537,13 @@ mod test {
              ref convo_client,
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
-                match (*http_response).clone() {
-                    HttpResponseType::Microblocks(_, mut microblocks) => {
-                        microblocks.reverse();
-                        assert_eq!(microblocks.len(), (*server_microblocks_cell.borrow()).len());
-                        assert_eq!(microblocks, *server_microblocks_cell.borrow());
+                match http_response {
+                    HttpResponseType::NotFound(..) => {
+                        // not confirmed yet
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -4947,46 +8553,34 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_unconfirmed_microblocks() {
-        let server_microblocks_cell = RefCell::new(vec![]);
-
+    fn test_rpc_get_contract_abi_unconfirmed() {
+        // Test /v2/contracts/interface (aka GetContractABI) endpoint.
+        // In this test, we set `tip_req` to UseLatestUnconfirmedTip, and we expect that querying
+        // against the unconfirmed state will succeed.
         test_rpc(
             function_name!(),
-            40050,
-            40051,
-            50050,
-            50051,
+            40152,
+            40153,
+            50152,
+            50153,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let privk = StacksPrivateKey::from_hex(
-                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                let unconfirmed_tip = peer_client
+                    .chainstate()
+                    .unconfirmed_state
+                    .as_ref()
+                    .unwrap()
+                    .unconfirmed_chain_tip
+                    .clone();
+                convo_client.new_getcontractabi(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    TipRequest::SpecificTip(unconfirmed_tip),
                 )
-                .unwrap();
-
-                let consensus_hash = ConsensusHash([0x02; 20]);
-                let anchored_block_hash = BlockHeaderHash([0x03; 32]);
-                let index_block_hash =
-                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &anchored_block_hash);
-
-                let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
-                mblocks.truncate(15);
-
-                for mblock in mblocks.iter() {
-                    store_staging_microblock(
-                        peer_server.chainstate(),
-                        &consensus_hash,
-                        &anchored_block_hash,
-                        &mblock,
-                    );
-                }
-
-                *server_microblocks_cell.borrow_mut() = mblocks;
-
-                // start at seq 5
-                convo_client.new_getmicroblocks_unconfirmed(index_block_hash, 5)
             },
             |ref http_request,
              ref http_response,
@@ -4996,16 +8590,9 @@ mod test {
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::Microblocks(response_md, microblocks) => {
-                        assert_eq!(microblocks.len(), 10);
-                        assert_eq!(
-                            *microblocks,
-                            (*server_microblocks_cell.borrow())[5..].to_vec()
-                        );
-                        true
-                    }
+                    HttpResponseType::GetContractABI(response_md, data) => true,
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -5015,52 +8602,72 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_unconfirmed_transaction() {
-        let last_txid = RefCell::new(Txid([0u8; 32]));
-        let last_mblock = RefCell::new(BlockHeaderHash([0u8; 32]));
+    fn test_rpc_get_contract_abi_use_latest_tip() {
+        test_rpc(
+            function_name!(),
+            40154,
+            40155,
+            50154,
+            50155,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getcontractabi(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    TipRequest::UseLatestAnchoredTip,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetContractABI(response_md, data) => true,
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
+    #[test]
+    #[ignore]
+    fn test_rpc_call_read_only() {
+        // Test /v2/contracts/call-read (aka CallReadOnlyFunction) endpoint.
+        // In this test, we don't set any tip parameters, and we expect that querying
+        // against the canonical Stacks tip will succeed.
         test_rpc(
             function_name!(),
-            40052,
-            40053,
-            50052,
-            50053,
+            40170,
+            40171,
+            50170,
+            50171,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let privk = StacksPrivateKey::from_hex(
-                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                convo_client.new_callreadonlyfunction(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    None,
+                    "ro-test".try_into().unwrap(),
+                    vec![],
+                    TipRequest::UseLatestAnchoredTip,
                 )
-                .unwrap();
-
-                let sortdb = peer_server.sortdb.take().unwrap();
-                Relayer::setup_unconfirmed_state(peer_server.chainstate(), &sortdb).unwrap();
-                peer_server.sortdb = Some(sortdb);
-
-                assert!(peer_server.chainstate().unconfirmed_state.is_some());
-                let (txid, mblock_hash) = match peer_server.chainstate().unconfirmed_state {
-                    Some(ref unconfirmed) => {
-                        assert!(unconfirmed.mined_txs.len() > 0);
-                        let mut txid = Txid([0u8; 32]);
-                        let mut mblock_hash = BlockHeaderHash([0u8; 32]);
-                        for (next_txid, (_, mbh, ..)) in unconfirmed.mined_txs.iter() {
-                            txid = next_txid.clone();
-                            mblock_hash = mbh.clone();
-                            break;
-                        }
-                        (txid, mblock_hash)
-                    }
-                    None => {
-                        panic!("No unconfirmed state");
-                    }
-                };
-
-                *last_txid.borrow_mut() = txid.clone();
-                *last_mblock.borrow_mut() = mblock_hash.clone();
-
-                convo_client.new_gettransaction_unconfirmed(txid)
             },
             |ref http_request,
              ref http_response,
@@ -5070,23 +8677,15 @@ mod test {
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::UnconfirmedTransaction(response_md, unconfirmed_resp) => {
-                        assert_eq!(
-                            unconfirmed_resp.status,
-                            UnconfirmedTransactionStatus::Microblock {
-                                block_hash: (*last_mblock.borrow()).clone(),
-                                seq: 0
-                            }
-                        );
-                        let tx = StacksTransaction::consensus_deserialize(
-                            &mut &hex_bytes(&unconfirmed_resp.tx).unwrap()[..],
-                        )
-                        .unwrap();
-                        assert_eq!(tx.txid(), *last_txid.borrow());
+                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
+                        assert!(data.cause.is_some());
+                        assert!(data.cause.clone().unwrap().find("NoSuchContract").is_some());
+                        assert!(!data.okay);
+                        assert!(data.result.is_none());
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -5096,27 +8695,32 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_missing_getblock() {
+    fn test_rpc_call_preview() {
+        // Test /v2/contracts/call-preview (aka CallPreviewFunction) endpoint.
+        // `add-unit` writes, so it can't be called read-only, but it can be previewed.
         test_rpc(
             function_name!(),
-            40060,
-            40061,
-            50060,
-            50061,
+            40850,
+            40851,
+            50850,
+            50851,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
-                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
-                let index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &peer_server_consensus_hash,
-                    &peer_server_block_hash,
-                );
-
-                // now ask for it
-                convo_client.new_getblock(index_block_hash)
+                convo_client.new_callpreviewfunction(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    None,
+                    "add-unit".try_into().unwrap(),
+                    vec![],
+                    TipRequest::UseLatestAnchoredTip,
+                )
             },
             |ref http_request,
              ref http_response,
@@ -5124,11 +8728,22 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::NotFound(response_md, msg) => true,
+                    HttpResponseType::CallPreviewFunction(response_md, data) => {
+                        assert!(data.okay);
+                        assert!(data.committed);
+                        assert!(data.cause.is_none());
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.result.clone().unwrap()[2..])
+                                .unwrap(),
+                            Value::okay(Value::Int(1)).unwrap()
+                        );
+                        assert!(data.events.is_empty());
+                        assert!(data.asset_movements.is_some());
+                        true
+                    }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -5138,27 +8753,41 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_missing_index_getmicroblocks() {
+    fn test_rpc_simulate_transaction() {
+        // Test /v2/transactions/simulate (aka SimulateTransaction) endpoint.
+        // The transaction is unsigned, and its effects must not persist.
         test_rpc(
             function_name!(),
-            40070,
-            40071,
-            50070,
-            50071,
-            true,
+            40898,
+            40899,
+            50898,
+            50899,
+            false,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
-                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
-                let index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &peer_server_consensus_hash,
-                    &peer_server_block_hash,
+                // ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R, who has sent two transactions
+                let privk = StacksPrivateKey::from_hex(
+                    "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
+                )
+                .unwrap();
+                let mut tx = StacksTransaction::new(
+                    TransactionVersion::Testnet,
+                    TransactionAuth::from_p2pkh(&privk).unwrap(),
+                    TransactionPayload::TokenTransfer(
+                        StacksAddress::from_string("STVN97YYA10MY5F6KQJHKNYJNM24C4A1AT39WRW")
+                            .unwrap()
+                            .to_account_principal(),
+                        1000,
+                        TokenTransferMemo([0u8; 34]),
+                    ),
                 );
+                tx.chain_id = 0x80000000;
+                tx.set_origin_nonce(2);
+                tx.set_tx_fee(200);
 
-                // now ask for it
-                convo_client.new_getmicroblocks_indexed(index_block_hash)
+                convo_client.new_simulate_transaction(tx, TipRequest::UseLatestAnchoredTip)
             },
             |ref http_request,
              ref http_response,
@@ -5166,11 +8795,53 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::NotFound(response_md, msg) => true,
+                    HttpResponseType::SimulateTransaction(response_md, data) => {
+                        assert!(data.okay);
+                        assert!(data.committed);
+                        assert!(!data.post_condition_aborted);
+                        assert!(data.cause.is_none());
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.result.clone().unwrap()[2..])
+                                .unwrap(),
+                            Value::okay_true()
+                        );
+                        assert_eq!(data.events.len(), 1);
+                        assert_eq!(data.events[0]["type"], "stx_transfer_event");
+                        assert_eq!(
+                            data.asset_movements.as_ref().unwrap()["stx"]
+                                ["ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R"],
+                            "1000"
+                        );
+                        assert!(data.execution_cost.is_some());
+
+                        // nothing was committed, so the sender's nonce is unchanged
+                        let sortdb = peer_server.sortdb.take().unwrap();
+                        let tip = peer_server
+                            .chainstate()
+                            .get_stacks_chain_tip(&sortdb)
+                            .unwrap()
+                            .unwrap();
+                        let tip = StacksBlockHeader::make_index_block_hash(
+                            &tip.consensus_hash,
+                            &tip.anchored_block_hash,
+                        );
+                        let sender =
+                            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                                .unwrap()
+                                .to_account_principal();
+                        let account = peer_server
+                            .chainstate()
+                            .with_read_only_clarity_tx(&sortdb.index_conn(), &tip, |conn| {
+                                StacksChainState::get_account(conn, &sender)
+                            })
+                            .unwrap();
+                        peer_server.sortdb = Some(sortdb);
+                        assert_eq!(account.nonce, 2);
+                        true
+                    }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -5180,27 +8851,33 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_missing_confirmed_getmicroblocks() {
+    fn test_rpc_call_read_only_bad_args() {
+        // Test /v2/contracts/call-read (aka CallReadOnlyFunction) endpoint.
+        // In this test, we pass the wrong number of arguments, and we expect the call to be
+        // rejected with an error that names the function's signature.
         test_rpc(
             function_name!(),
-            40072,
-            40073,
-            50072,
-            50073,
+            40834,
+            40835,
+            50834,
+            50835,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
-                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
-                let index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &peer_server_consensus_hash,
-                    &peer_server_block_hash,
-                );
-
-                // now ask for it
-                convo_client.new_getmicroblocks_confirmed(index_block_hash)
+                convo_client.new_callreadonlyfunction(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    None,
+                    "ro-test".try_into().unwrap(),
+                    vec![Value::Int(1)],
+                    TipRequest::UseLatestAnchoredTip,
+                )
             },
             |ref http_request,
              ref http_response,
@@ -5210,9 +8887,16 @@ mod test {
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::NotFound(response_md, msg) => true,
+                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
+                        assert!(!data.okay);
+                        assert!(data.result.is_none());
+                        let cause = data.cause.clone().unwrap();
+                        assert!(cause.find("(define-read-only (ro-test)").is_some());
+                        assert!(cause.find("expected 0 argument(s), got 1").is_some());
+                        true
+                    }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -5222,46 +8906,33 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_missing_unconfirmed_microblocks() {
-        let server_microblocks_cell = RefCell::new(vec![]);
-
+    fn test_rpc_call_read_only_use_latest_tip() {
+        // Test /v2/contracts/call-read (aka CallReadOnlyFunction) endpoint.
+        // In this test, we set `tip_req` to UseLatestUnconfirmedTip, and we expect that querying
+        // against the unconfirmed state will succeed.
         test_rpc(
             function_name!(),
-            40080,
-            40081,
-            50080,
-            50081,
+            40172,
+            40173,
+            50172,
+            50173,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let privk = StacksPrivateKey::from_hex(
-                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                convo_client.new_callreadonlyfunction(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    None,
+                    "ro-test".try_into().unwrap(),
+                    vec![],
+                    TipRequest::UseLatestAnchoredTip,
                 )
-                .unwrap();
-
-                let consensus_hash = ConsensusHash([0x02; 20]);
-                let anchored_block_hash = BlockHeaderHash([0x03; 32]);
-                let index_block_hash =
-                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &anchored_block_hash);
-
-                let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
-                mblocks.truncate(15);
-
-                for mblock in mblocks.iter() {
-                    store_staging_microblock(
-                        peer_server.chainstate(),
-                        &consensus_hash,
-                        &anchored_block_hash,
-                        &mblock,
-                    );
-                }
-
-                *server_microblocks_cell.borrow_mut() = mblocks;
-
-                // start at seq 16 (which doesn't exist)
-                convo_client.new_getmicroblocks_unconfirmed(index_block_hash, 16)
             },
             |ref http_request,
              ref http_response,
@@ -5271,41 +8942,57 @@ mod test {
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::NotFound(response_md, msg) => true,
+                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
+                        assert!(data.okay);
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.result.clone().unwrap())
+                                .unwrap(),
+                            Value::okay(Value::Int(1)).unwrap()
+                        );
+                        assert!(data.cause.is_none());
+                        true
+                    }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
             },
         );
     }
-
-    #[test]
-    #[ignore]
-    fn test_rpc_get_contract_src() {
-        // Test v2/contracts/source (aka GetContractSrc) endpoint.
-        // In this test, we don't set any tip parameters, and allow the endpoint to execute against
-        // the canonical Stacks tip.
-        // The contract source we are querying for exists in the anchored state, so we expect the
-        // query to succeed.
+
+    #[test]
+    #[ignore]
+    fn test_rpc_call_read_only_unconfirmed() {
         test_rpc(
             function_name!(),
-            40090,
-            40091,
-            50090,
-            50091,
+            40180,
+            40181,
+            50180,
+            50181,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_getcontractsrc(
+                let unconfirmed_tip = peer_client
+                    .chainstate()
+                    .unconfirmed_state
+                    .as_ref()
+                    .unwrap()
+                    .unconfirmed_chain_tip
+                    .clone();
+                convo_client.new_callreadonlyfunction(
                     StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
                         .unwrap(),
-                    "hello-world".try_into().unwrap(),
-                    TipRequest::UseLatestAnchoredTip,
-                    false,
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    None,
+                    "ro-test".try_into().unwrap(),
+                    vec![],
+                    TipRequest::SpecificTip(unconfirmed_tip),
                 )
             },
             |ref http_request,
@@ -5316,8 +9003,14 @@ mod test {
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetContractSrc(response_md, data) => {
-                        assert_eq!(data.source, TEST_CONTRACT);
+                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
+                        assert!(data.okay);
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.result.clone().unwrap())
+                                .unwrap(),
+                            Value::okay(Value::Int(1)).unwrap()
+                        );
+                        assert!(data.cause.is_none());
                         true
                     }
                     _ => {
@@ -5331,30 +9024,20 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_contract_src_unconfirmed_with_canonical_tip() {
-        // Test v2/contracts/source (aka GetContractSrc) endpoint.
-        // In this test, we don't set any tip parameters, and allow the endpoint to execute against
-        // the canonical Stacks tip.
-        // The contract source we are querying for only exists in the unconfirmed state, so we
-        // expect the query to fail.
+    fn test_rpc_getattachmentsinv_limit_reached() {
         test_rpc(
             function_name!(),
-            40100,
-            40101,
-            50100,
-            50101,
+            40190,
+            40191,
+            50190,
+            50191,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_getcontractsrc(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world-unconfirmed".try_into().unwrap(),
-                    TipRequest::UseLatestAnchoredTip,
-                    false,
-                )
+                let pages_indexes = HashSet::from_iter(vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+                convo_client.new_getattachmentsinv(StacksBlockId([0x00; 32]), pages_indexes)
             },
             |ref http_request,
              ref http_response,
@@ -5363,15 +9046,16 @@ mod test {
              ref convo_client,
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
+                println!("{:?}", http_response);
                 match http_response {
-                    HttpResponseType::NotFound(_, error_str) => {
-                        assert_eq!(error_str, "No contract source data found");
+                    HttpResponseType::BadRequest(_, msg) => {
+                        assert_eq!(
+                            msg,
+                            "Number of attachment inv pages is limited by 8 per request"
+                        );
                         true
                     }
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+                    _ => false,
                 }
             },
         );
@@ -5379,35 +9063,21 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_contract_src_with_unconfirmed_tip() {
-        // Test v2/contracts/source (aka GetContractSrc) endpoint.
-        // In this test, we set `tip_req` to be the unconfirmed chain tip.
-        // The contract source we are querying for exists in the unconfirmed state, so we expect
-        // the query to succeed.
+    fn test_rpc_mempool_query_txtags() {
         test_rpc(
             function_name!(),
-            40102,
-            40103,
-            50102,
-            50103,
-            true,
+            40813,
+            40814,
+            50813,
+            50814,
+            false,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let unconfirmed_tip = peer_client
-                    .chainstate()
-                    .unconfirmed_state
-                    .as_ref()
-                    .unwrap()
-                    .unconfirmed_chain_tip
-                    .clone();
-                convo_client.new_getcontractsrc(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world-unconfirmed".try_into().unwrap(),
-                    TipRequest::SpecificTip(unconfirmed_tip),
-                    false,
+                convo_client.new_mempool_query(
+                    MemPoolSyncData::TxTags([0u8; 32], vec![]),
+                    PageRequest::from_cursor(None, Txid([0u8; 32]).to_hex()),
                 )
             },
             |ref http_request,
@@ -5417,15 +9087,14 @@ mod test {
              ref convo_client,
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
+                println!("{:?}", http_response);
                 match http_response {
-                    HttpResponseType::GetContractSrc(response_md, data) => {
-                        assert_eq!(data.source, TEST_CONTRACT_UNCONFIRMED);
+                    HttpResponseType::MemPoolTxs(_, _, txs) => {
+                        // got everything
+                        assert_eq!(txs.len(), 10);
                         true
                     }
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+                    _ => false,
                 }
             },
         );
@@ -5433,28 +9102,26 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_contract_src_use_latest_tip() {
-        // Test v2/contracts/source (aka GetContractSrc) endpoint.
-        // In this test, we set `tip_req` to UseLatestUnconfirmedTip.
-        // The contract source we are querying for exists in the unconfirmed state, so we expect
-        // the query to succeed.
+    fn test_rpc_mempool_query_bloom() {
         test_rpc(
             function_name!(),
-            40104,
-            40105,
-            50104,
-            50105,
-            true,
+            40815,
+            40816,
+            50815,
+            50816,
+            false,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_getcontractsrc(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world-unconfirmed".try_into().unwrap(),
-                    TipRequest::UseLatestAnchoredTip,
-                    false,
+                // empty bloom filter
+                convo_client.new_mempool_query(
+                    MemPoolSyncData::BloomFilter(BloomFilter::new(
+                        BLOOM_COUNTER_ERROR_RATE,
+                        MAX_BLOOM_COUNTER_TXS,
+                        BloomNodeHasher::new(&[0u8; 32]),
+                    )),
+                    PageRequest::from_cursor(None, Txid([0u8; 32]).to_hex()),
                 )
             },
             |ref http_request,
@@ -5464,15 +9131,14 @@ mod test {
              ref convo_client,
              ref convo_server| {
                 let req_md = http_request.metadata().clone();
+                println!("{:?}", http_response);
                 match http_response {
-                    HttpResponseType::GetContractSrc(response_md, data) => {
-                        assert_eq!(data.source, TEST_CONTRACT_UNCONFIRMED);
+                    HttpResponseType::MemPoolTxs(_, _, txs) => {
+                        // got everything
+                        assert_eq!(txs.len(), 10);
                         true
                     }
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+                    _ => false,
                 }
             },
         );
@@ -5480,25 +9146,24 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_account() {
+    fn test_rpc_get_tip_summary() {
+        let server_tip = RefCell::new(None);
         test_rpc(
             function_name!(),
-            40110,
-            40111,
-            50110,
-            50111,
+            40820,
+            40821,
+            50820,
+            50821,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_getaccount(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal(),
-                    TipRequest::UseLatestAnchoredTip,
-                    false,
-                )
+                let sortdb = peer_server.sortdb.as_ref().unwrap();
+                let chainstate = &peer_server.stacks_node.as_ref().unwrap().chainstate;
+                let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                *server_tip.borrow_mut() = Some(tip);
+                convo_client.new_get_tip_summary()
             },
             |ref http_request,
              ref http_response,
@@ -5506,16 +9171,16 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetAccount(response_md, data) => {
-                        assert_eq!(data.nonce, 2);
-                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
-                        assert_eq!(balance, 1000000000);
+                    HttpResponseType::TipSummary(_, summary) => {
+                        let tip = server_tip.borrow().clone().unwrap();
+                        assert_eq!(summary.stacks_tip_height, tip.height);
+                        assert_eq!(summary.stacks_tip, tip.anchored_block_hash);
+                        assert_eq!(summary.stacks_tip_consensus_hash, tip.consensus_hash);
                         true
                     }
                     _ => {
-                        error!("Invalid response; {:?}", &http_response);
+                        error!("Invalid response: {:?}", &http_response);
                         false
                     }
                 }
@@ -5523,30 +9188,21 @@ mod test {
         );
     }
 
-    /// In this test, the query parameter `tip_req` is set to UseLatestUnconfirmedTip, and so we expect the
-    /// tip used for the query to be the latest microblock.
-    /// We check that the account state matches the state in the most recent microblock.
     #[test]
     #[ignore]
-    fn test_rpc_get_account_use_latest_tip() {
+    fn test_rpc_get_blocks_summary() {
         test_rpc(
             function_name!(),
-            40112,
-            40113,
-            50112,
-            50113,
+            40822,
+            40823,
+            50822,
+            50823,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_getaccount(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal(),
-                    TipRequest::UseLatestAnchoredTip,
-                    false,
-                )
+                convo_client.new_get_blocks_summary(PageRequest::new(Some(10), 0))
             },
             |ref http_request,
              ref http_response,
@@ -5554,16 +9210,28 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetAccount(response_md, data) => {
-                        assert_eq!(data.nonce, 4);
-                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
-                        assert_eq!(balance, 999999877);
+                    HttpResponseType::BlocksSummary(_, page) => {
+                        let summaries = &page.results;
+                        assert!(summaries.len() > 0);
+                        assert_eq!(page.limit, 10);
+                        assert_eq!(page.offset, 0);
+                        assert_eq!(page.total, Some(summaries[0].height));
+                        for pair in summaries.windows(2) {
+                            assert_eq!(pair[0].height, pair[1].height + 1);
+                            assert_eq!(
+                                pair[0].parent_index_block_hash,
+                                Some(pair[1].index_block_hash.clone())
+                            );
+                        }
+                        for summary in summaries.iter() {
+                            assert!(summary.cost_fill_pct <= 100);
+                            assert!(summary.size_fill_pct <= 100);
+                        }
                         true
                     }
                     _ => {
-                        error!("Invalid response; {:?}", &http_response);
+                        error!("Invalid response: {:?}", &http_response);
                         false
                     }
                 }
@@ -5571,50 +9239,41 @@ mod test {
         );
     }
 
-    /// In this test, the query parameter `tip_req` is set to UseLatestUnconfirmedTip, but we did not generate
-    /// microblocks in the rpc test. Thus, we expect the tip used for the query to be the previous
-    /// anchor block (which is the latest tip).
-    /// We check that the account state matches the state in the previous anchor block.
     #[test]
     #[ignore]
-    fn test_rpc_get_account_use_latest_tip_no_microblocks() {
+    fn test_rpc_get_blocks_summary_past_end() {
         test_rpc(
             function_name!(),
-            40114,
-            40115,
-            50114,
-            50115,
-            false,
+            40840,
+            40841,
+            50840,
+            50841,
+            true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_getaccount(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal(),
-                    TipRequest::UseLatestAnchoredTip,
-                    false,
-                )
+                let tip_height = peer_server
+                    .network
+                    .burnchain_tip
+                    .canonical_stacks_tip_height;
+                convo_client.new_get_blocks_summary(PageRequest::new(None, tip_height))
             },
             |ref http_request,
              ref http_response,
              ref mut peer_client,
              ref mut peer_server,
              ref convo_client,
-             ref convo_server| {
-                let req_md = http_request.metadata().clone();
-                match http_response {
-                    HttpResponseType::GetAccount(response_md, data) => {
-                        assert_eq!(data.nonce, 2);
-                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
-                        assert_eq!(balance, 1000000000);
-                        true
-                    }
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+             ref convo_server| match http_response {
+                HttpResponseType::BlocksSummary(_, page) => {
+                    assert!(page.results.is_empty());
+                    assert_eq!(page.limit, DEFAULT_BLOCKS_SUMMARY);
+                    assert_eq!(page.next_cursor, None);
+                    true
+                }
+                _ => {
+                    error!("Invalid response: {:?}", &http_response);
+                    false
                 }
             },
         );
@@ -5622,51 +9281,31 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_account_unconfirmed() {
+    fn test_rpc_get_blocks_summary_too_many() {
         test_rpc(
             function_name!(),
-            40120,
-            40121,
-            50120,
-            50121,
+            40824,
+            40825,
+            50824,
+            50825,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let unconfirmed_tip = peer_client
-                    .chainstate()
-                    .unconfirmed_state
-                    .as_ref()
-                    .unwrap()
-                    .unconfirmed_chain_tip
-                    .clone();
-                convo_client.new_getaccount(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal(),
-                    TipRequest::SpecificTip(unconfirmed_tip),
-                    false,
-                )
+                convo_client
+                    .new_get_blocks_summary(PageRequest::new(Some(MAX_BLOCKS_SUMMARY + 1), 0))
             },
             |ref http_request,
              ref http_response,
              ref mut peer_client,
              ref mut peer_server,
              ref convo_client,
-             ref convo_server| {
-                let req_md = http_request.metadata().clone();
-                match http_response {
-                    HttpResponseType::GetAccount(response_md, data) => {
-                        assert_eq!(data.nonce, 4);
-                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
-                        assert_eq!(balance, 1000000000 - 123);
-                        true
-                    }
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+             ref convo_server| match http_response {
+                HttpResponseType::BadRequest(..) => true,
+                _ => {
+                    error!("Invalid response: {:?}", &http_response);
+                    false
                 }
             },
         );
@@ -5674,44 +9313,44 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_data_var() {
+    fn test_rpc_get_chain_quality() {
         test_rpc(
             function_name!(),
-            40122,
-            40123,
-            50122,
-            50123,
+            40846,
+            40847,
+            50846,
+            50847,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
-             ref mut convo_server| {
-                convo_client.new_getdatavar(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world".try_into().unwrap(),
-                    "bar".try_into().unwrap(),
-                    TipRequest::UseLatestAnchoredTip,
-                    false,
-                )
-            },
+             ref mut convo_server| { convo_client.new_get_chain_quality(Some(10)) },
             |ref http_request,
              ref http_response,
              ref mut peer_client,
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetDataVar(response_md, data) => {
+                    HttpResponseType::ChainQuality(_, chain_quality) => {
+                        assert!(chain_quality.window <= 10);
                         assert_eq!(
-                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
-                            Value::Int(0)
+                            chain_quality.sortitions + chain_quality.missed_sortitions,
+                            chain_quality.window
+                        );
+                        assert!(chain_quality.sortitions > 0);
+                        assert!(chain_quality.stacks_blocks > 0);
+                        assert!(chain_quality.stacks_blocks <= chain_quality.sortitions);
+                        assert!(chain_quality.sortition_rate <= 1.0);
+                        assert!(chain_quality.average_block_fill_pct.is_some());
+                        assert!(
+                            chain_quality.microblocks_confirmed
+                                <= chain_quality.microblocks_produced
                         );
                         true
                     }
                     _ => {
-                        error!("Invalid response; {:?}", &http_response);
+                        error!("Invalid response: {:?}", &http_response);
                         false
                     }
                 }
@@ -5721,53 +9360,30 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_data_var_unconfirmed() {
+    fn test_rpc_get_chain_quality_window_too_big() {
         test_rpc(
             function_name!(),
-            40124,
-            40125,
-            50124,
-            50125,
+            40848,
+            40849,
+            50848,
+            50849,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let unconfirmed_tip = peer_client
-                    .chainstate()
-                    .unconfirmed_state
-                    .as_ref()
-                    .unwrap()
-                    .unconfirmed_chain_tip
-                    .clone();
-                convo_client.new_getdatavar(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world".try_into().unwrap(),
-                    "bar".try_into().unwrap(),
-                    TipRequest::SpecificTip(unconfirmed_tip),
-                    false,
-                )
+                convo_client.new_get_chain_quality(Some(MAX_CHAIN_QUALITY_WINDOW + 1))
             },
             |ref http_request,
              ref http_response,
              ref mut peer_client,
              ref mut peer_server,
              ref convo_client,
-             ref convo_server| {
-                let req_md = http_request.metadata().clone();
-                match http_response {
-                    HttpResponseType::GetDataVar(response_md, data) => {
-                        assert_eq!(
-                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
-                            Value::Int(1)
-                        );
-                        true
-                    }
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+             ref convo_server| match http_response {
+                HttpResponseType::BadRequest(..) => true,
+                _ => {
+                    error!("Invalid response: {:?}", &http_response);
+                    false
                 }
             },
         );
@@ -5775,25 +9391,24 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_data_var_nonexistant() {
+    fn test_rpc_get_block_fee_stats() {
         test_rpc(
             function_name!(),
-            40125,
-            40126,
-            50125,
-            50126,
+            40842,
+            40843,
+            50842,
+            50843,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_getdatavar(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world".try_into().unwrap(),
-                    "bar-nonexistant".try_into().unwrap(),
+                convo_client.new_get_block_fee_stats(
+                    PageRequest {
+                        limit: Some(10),
+                        ..PageRequest::default()
+                    },
                     TipRequest::UseLatestAnchoredTip,
-                    false,
                 )
             },
             |ref http_request,
@@ -5802,14 +9417,29 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::NotFound(_, msg) => {
-                        assert_eq!(msg, "Data var not found");
+                    HttpResponseType::BlockFeeStats(_, RPCList::Items(fee_stats)) => {
+                        assert!(fee_stats.len() > 0);
+                        assert!(fee_stats.len() <= 10);
+                        for pair in fee_stats.windows(2) {
+                            assert!(pair[0].block_height > pair[1].block_height);
+                        }
+                        for stats in fee_stats.iter() {
+                            if stats.tx_count == 0 {
+                                assert_eq!(stats.total_fees, 0);
+                                assert!(stats.fee_rates.size.is_none());
+                            } else {
+                                let size_rates = stats.fee_rates.size.as_ref().unwrap();
+                                assert!(size_rates.min <= size_rates.median);
+                                assert!(size_rates.median <= size_rates.max);
+                            }
+                            assert!(stats.fullness.runtime <= 1.0);
+                            assert!(stats.fullness.size <= 1.0);
+                        }
                         true
                     }
                     _ => {
-                        error!("Invalid response; {:?}", &http_response);
+                        error!("Invalid response: {:?}", &http_response);
                         false
                     }
                 }
@@ -5819,37 +9449,19 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_map_entry() {
-        // Test v2/map_entry (aka GetMapEntry) endpoint.
-        // In this test, we don't set any tip parameters, and we expect that querying for map data
-        // against the canonical Stacks tip will succeed.
+    fn test_rpc_get_supply() {
         test_rpc(
             function_name!(),
-            40130,
-            40131,
-            50130,
-            50131,
+            40860,
+            40861,
+            50860,
+            50861,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let principal =
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal();
-                convo_client.new_getmapentry(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world".try_into().unwrap(),
-                    "unit-map".try_into().unwrap(),
-                    Value::Tuple(
-                        TupleData::from_data(vec![("account".into(), Value::Principal(principal))])
-                            .unwrap(),
-                    ),
-                    TipRequest::UseLatestAnchoredTip,
-                    false,
-                )
+                convo_client.new_get_supply(1, TipRequest::UseLatestAnchoredTip)
             },
             |ref http_request,
              ref http_response,
@@ -5857,21 +9469,15 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetMapEntry(response_md, data) => {
-                        assert_eq!(
-                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
-                            Value::some(Value::Tuple(
-                                TupleData::from_data(vec![("units".into(), Value::Int(123))])
-                                    .unwrap()
-                            ))
-                            .unwrap()
-                        );
+                    HttpResponseType::Supply(_, supply) => {
+                        assert_eq!(supply.block_height, 1);
+                        assert!(supply.circulating_supply_ustx > 0);
+                        assert!(supply.total_locked_ustx <= supply.circulating_supply_ustx);
                         true
                     }
                     _ => {
-                        error!("Invalid response; {:?}", &http_response);
+                        error!("Invalid response: {:?}", &http_response);
                         false
                     }
                 }
@@ -5881,44 +9487,43 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_map_entry_unconfirmed() {
-        // Test v2/map_entry (aka GetMapEntry) endpoint.
-        // In this test, we set `tip_req` to UseLatestUnconfirmedTip, and we expect that querying for map data
-        // against the unconfirmed state will succeed.
+    fn test_rpc_get_node_metadata() {
+        let neighbor_privkey = Secp256k1PrivateKey::new();
+        let neighbor_addr = NeighborKey {
+            peer_version: 0x18000007,
+            network_id: 0x80000000,
+            addrbytes: PeerAddress::from_ipv4(1, 2, 3, 4),
+            port: 20444,
+        };
         test_rpc(
             function_name!(),
-            40140,
-            40141,
-            50140,
-            50141,
+            40870,
+            40871,
+            50870,
+            50871,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let unconfirmed_tip = peer_client
-                    .chainstate()
-                    .unconfirmed_state
-                    .as_ref()
-                    .unwrap()
-                    .unconfirmed_chain_tip
-                    .clone();
-                let principal =
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal();
-                convo_client.new_getmapentry(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world".try_into().unwrap(),
-                    "unit-map".try_into().unwrap(),
-                    Value::Tuple(
-                        TupleData::from_data(vec![("account".into(), Value::Principal(principal))])
-                            .unwrap(),
-                    ),
-                    TipRequest::SpecificTip(unconfirmed_tip),
-                    false,
+                let signed_metadata = NodeMetadataData::sign(
+                    NodeMetadata {
+                        peer_version: 0x18000007,
+                        node_version: "stacks-node 2.1".to_string(),
+                        services: 0x0007,
+                        rpc_url: "http://1.2.3.4:20443".into(),
+                        operator_contact: "ops@example.com".to_string(),
+                        timestamp: 1234567890,
+                    },
+                    &neighbor_privkey,
                 )
+                .unwrap();
+                let mut tx = peer_server.network.peerdb.tx_begin().unwrap();
+                PeerDB::store_node_metadata(&mut tx, &neighbor_addr, &signed_metadata, 1234567891)
+                    .unwrap();
+                tx.commit().unwrap();
+
+                convo_client.new_get_node_metadata()
             },
             |ref http_request,
              ref http_response,
@@ -5926,21 +9531,32 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetMapEntry(response_md, data) => {
+                    HttpResponseType::NodeMetadata(_, node_metadata) => {
+                        // the server signs its own record
+                        let local = node_metadata.local.to_signed_metadata().unwrap();
+                        local.verify().unwrap();
                         assert_eq!(
-                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
-                            Value::some(Value::Tuple(
-                                TupleData::from_data(vec![("units".into(), Value::Int(1))])
-                                    .unwrap()
-                            ))
-                            .unwrap()
+                            local.node_public_key,
+                            StacksPublicKeyBuffer::from_public_key(
+                                &Secp256k1PublicKey::from_private(
+                                    &peer_server.network.local_peer.private_key
+                                )
+                            )
                         );
+                        assert!(node_metadata.local.received_at.is_none());
+
+                        // and passes along the neighbor's as-is
+                        assert_eq!(node_metadata.neighbors.len(), 1);
+                        let neighbor = &node_metadata.neighbors[0];
+                        assert_eq!(neighbor.port, 20444);
+                        assert_eq!(neighbor.operator_contact, "ops@example.com");
+                        assert_eq!(neighbor.received_at, Some(1234567891));
+                        neighbor.to_signed_metadata().unwrap().verify().unwrap();
                         true
                     }
                     _ => {
-                        error!("Invalid response; {:?}", &http_response);
+                        error!("Invalid response: {:?}", &http_response);
                         false
                     }
                 }
@@ -5950,34 +9566,32 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_map_entry_use_latest_tip() {
+    fn test_rpc_get_banned_leader_keys() {
+        let public_key = VRFPublicKey::from_private(&VRFPrivateKey::new());
+        let banned = BannedLeaderKey {
+            public_key: public_key.clone(),
+            consensus_hash: ConsensusHash([0x01; 20]),
+            anchored_block_hash: BlockHeaderHash([0x02; 32]),
+            reason: "bad block".to_string(),
+            banned_at: 1234567890,
+        };
         test_rpc(
             function_name!(),
-            40142,
-            40143,
-            50142,
-            50143,
+            40880,
+            40881,
+            50880,
+            50881,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let principal =
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal();
-                convo_client.new_getmapentry(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world".try_into().unwrap(),
-                    "unit-map".try_into().unwrap(),
-                    Value::Tuple(
-                        TupleData::from_data(vec![("account".into(), Value::Principal(principal))])
-                            .unwrap(),
-                    ),
-                    TipRequest::UseLatestAnchoredTip,
-                    false,
-                )
+                let chainstate = peer_server.chainstate();
+                let tx = chainstate.index_tx_begin().unwrap();
+                StacksChainState::ban_leader_key(&tx, &banned).unwrap();
+                tx.commit().unwrap();
+
+                convo_client.new_get_banned_leader_keys()
             },
             |ref http_request,
              ref http_response,
@@ -5985,21 +9599,22 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetMapEntry(response_md, data) => {
+                    HttpResponseType::BannedLeaderKeys(_, banned_leader_keys) => {
                         assert_eq!(
-                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
-                            Value::some(Value::Tuple(
-                                TupleData::from_data(vec![("units".into(), Value::Int(1))])
-                                    .unwrap()
-                            ))
-                            .unwrap()
+                            banned_leader_keys,
+                            &vec![RPCBannedLeaderKey {
+                                public_key: public_key.to_hex(),
+                                consensus_hash: ConsensusHash([0x01; 20]),
+                                block_hash: BlockHeaderHash([0x02; 32]),
+                                reason: "bad block".to_string(),
+                                banned_at: 1234567890,
+                            }]
                         );
                         true
                     }
                     _ => {
-                        error!("Invalid response; {:?}", &http_response);
+                        error!("Invalid response: {:?}", &http_response);
                         false
                     }
                 }
@@ -6009,27 +9624,47 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_contract_abi() {
-        // Test /v2/contracts/interface (aka GetContractABI) endpoint.
-        // In this test, we don't set any tip parameters, and we expect that querying
-        // against the canonical Stacks tip will succeed.
+    fn test_rpc_get_block_weight() {
+        let weight = BlockWeight {
+            index_block_hash: StacksBlockId([0x03; 32]),
+            block_height: 12,
+            burn_block_height: 34,
+            anchored_block_size: 1000,
+            anchored_block_cost: ExecutionCost {
+                write_length: 1,
+                write_count: 2,
+                read_length: 3,
+                read_count: 4,
+                runtime: 5,
+            },
+            parent_microblocks_size: 200,
+            parent_microblocks_cost: ExecutionCost::zero(),
+            txs: vec![TxWeight {
+                txid: Txid([0x04; 32]),
+                tx_index: 0,
+                microblock_hash: None,
+                size: 180,
+                cost: ExecutionCost::zero(),
+            }],
+        };
+        let expected = weight.clone();
         test_rpc(
             function_name!(),
-            40150,
-            40151,
-            50150,
-            50151,
+            40890,
+            40891,
+            50890,
+            50891,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_getcontractabi(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world-unconfirmed".try_into().unwrap(),
-                    TipRequest::UseLatestAnchoredTip,
-                )
+                let chainstate = peer_server.chainstate();
+                let tx = chainstate.index_tx_begin().unwrap();
+                StacksChainState::store_block_weight(&tx, &weight).unwrap();
+                tx.commit().unwrap();
+
+                convo_client.new_get_block_weight(StacksBlockId([0x03; 32]))
             },
             |ref http_request,
              ref http_response,
@@ -6037,14 +9672,13 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::NotFound(..) => {
-                        // not confirmed yet
+                    HttpResponseType::BlockWeight(_, block_weight) => {
+                        assert_eq!(block_weight, &expected);
                         true
                     }
                     _ => {
-                        error!("Invalid response; {:?}", &http_response);
+                        error!("Invalid response: {:?}", &http_response);
                         false
                     }
                 }
@@ -6054,48 +9688,30 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_contract_abi_unconfirmed() {
-        // Test /v2/contracts/interface (aka GetContractABI) endpoint.
-        // In this test, we set `tip_req` to UseLatestUnconfirmedTip, and we expect that querying
-        // against the unconfirmed state will succeed.
+    fn test_rpc_get_block_weight_missing() {
         test_rpc(
             function_name!(),
-            40152,
-            40153,
-            50152,
-            50153,
+            40892,
+            40893,
+            50892,
+            50893,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let unconfirmed_tip = peer_client
-                    .chainstate()
-                    .unconfirmed_state
-                    .as_ref()
-                    .unwrap()
-                    .unconfirmed_chain_tip
-                    .clone();
-                convo_client.new_getcontractabi(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world-unconfirmed".try_into().unwrap(),
-                    TipRequest::SpecificTip(unconfirmed_tip),
-                )
+                convo_client.new_get_block_weight(StacksBlockId([0x05; 32]))
             },
             |ref http_request,
              ref http_response,
              ref mut peer_client,
              ref mut peer_server,
              ref convo_client,
-             ref convo_server| {
-                let req_md = http_request.metadata().clone();
-                match http_response {
-                    HttpResponseType::GetContractABI(response_md, data) => true,
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+             ref convo_server| match http_response {
+                HttpResponseType::NotFound(..) => true,
+                _ => {
+                    error!("Invalid response: {:?}", &http_response);
+                    false
                 }
             },
         );
@@ -6103,24 +9719,78 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_contract_abi_use_latest_tip() {
+    fn test_rpc_get_microblock_forks() {
+        let server_microblocks_cell = RefCell::new(vec![]);
+        let child_index_block_hash_cell = RefCell::new(StacksBlockId([0x00; 32]));
+
         test_rpc(
             function_name!(),
-            40154,
-            40155,
-            50154,
-            50155,
+            40894,
+            40895,
+            50894,
+            50895,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_getcontractabi(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world-unconfirmed".try_into().unwrap(),
-                    TipRequest::UseLatestAnchoredTip,
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
                 )
+                .unwrap();
+
+                let parent_block = make_codec_test_block(25);
+                let parent_consensus_hash = ConsensusHash([0x02; 20]);
+                let parent_index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &parent_consensus_hash,
+                    &parent_block.block_hash(),
+                );
+
+                let mut mblocks = make_sample_microblock_stream(&privk, &parent_block.block_hash());
+                mblocks.truncate(15);
+
+                let mut child_block = make_codec_test_block(25);
+                let child_consensus_hash = ConsensusHash([0x03; 20]);
+
+                // confirm only the first 10 microblocks
+                child_block.header.parent_block = parent_block.block_hash();
+                child_block.header.parent_microblock = mblocks[9].block_hash();
+                child_block.header.parent_microblock_sequence = mblocks[9].header.sequence;
+
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &parent_consensus_hash,
+                    &parent_block,
+                    &ConsensusHash([0x01; 20]),
+                    456,
+                    123,
+                );
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &child_consensus_hash,
+                    &child_block,
+                    &parent_consensus_hash,
+                    456,
+                    123,
+                );
+
+                for mblock in mblocks.iter() {
+                    store_staging_microblock(
+                        peer_server.chainstate(),
+                        &parent_consensus_hash,
+                        &parent_block.block_hash(),
+                        &mblock,
+                    );
+                }
+
+                *server_microblocks_cell.borrow_mut() = mblocks;
+                *child_index_block_hash_cell.borrow_mut() =
+                    StacksBlockHeader::make_index_block_hash(
+                        &child_consensus_hash,
+                        &child_block.block_hash(),
+                    );
+
+                convo_client.new_get_microblock_forks(parent_index_block_hash)
             },
             |ref http_request,
              ref http_response,
@@ -6128,11 +9798,31 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
-                match http_response {
-                    HttpResponseType::GetContractABI(response_md, data) => true,
+                match (*http_response).clone() {
+                    HttpResponseType::MicroblockForks(_, tree) => {
+                        let mblocks = server_microblocks_cell.borrow();
+                        let child_index_block_hash = child_index_block_hash_cell.borrow().clone();
+                        assert_eq!(tree.microblocks.len(), mblocks.len());
+                        assert_eq!(tree.roots, vec![mblocks[0].block_hash()]);
+                        assert!(tree.fork_points.is_empty());
+                        for (i, node) in tree.microblocks.iter().enumerate() {
+                            assert_eq!(node.microblock_hash, mblocks[i].block_hash());
+                            if i < 10 {
+                                assert_eq!(node.confirmed_by, vec![child_index_block_hash.clone()]);
+                            } else {
+                                assert!(node.confirmed_by.is_empty());
+                            }
+                        }
+                        assert_eq!(tree.confirmations.len(), 1);
+                        assert_eq!(
+                            tree.confirmations[0].parent_microblock_hash,
+                            Some(mblocks[9].block_hash())
+                        );
+                        assert!(tree.confirmations[0].tail_known);
+                        true
+                    }
                     _ => {
-                        error!("Invalid response; {:?}", &http_response);
+                        error!("Invalid response: {:?}", &http_response);
                         false
                     }
                 }
@@ -6142,53 +9832,30 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_call_read_only() {
-        // Test /v2/contracts/call-read (aka CallReadOnlyFunction) endpoint.
-        // In this test, we don't set any tip parameters, and we expect that querying
-        // against the canonical Stacks tip will succeed.
+    fn test_rpc_get_microblock_forks_missing() {
         test_rpc(
             function_name!(),
-            40170,
-            40171,
-            50170,
-            50171,
+            40896,
+            40897,
+            50896,
+            50897,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_callreadonlyfunction(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world-unconfirmed".try_into().unwrap(),
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal(),
-                    None,
-                    "ro-test".try_into().unwrap(),
-                    vec![],
-                    TipRequest::UseLatestAnchoredTip,
-                )
+                convo_client.new_get_microblock_forks(StacksBlockId([0x05; 32]))
             },
             |ref http_request,
              ref http_response,
              ref mut peer_client,
              ref mut peer_server,
              ref convo_client,
-             ref convo_server| {
-                let req_md = http_request.metadata().clone();
-                match http_response {
-                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
-                        assert!(data.cause.is_some());
-                        assert!(data.cause.clone().unwrap().find("NoSuchContract").is_some());
-                        assert!(!data.okay);
-                        assert!(data.result.is_none());
-                        true
-                    }
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+             ref convo_server| match http_response {
+                HttpResponseType::NotFound(..) => true,
+                _ => {
+                    error!("Invalid response: {:?}", &http_response);
+                    false
                 }
             },
         );
@@ -6196,31 +9863,23 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_call_read_only_use_latest_tip() {
-        // Test /v2/contracts/call-read (aka CallReadOnlyFunction) endpoint.
-        // In this test, we set `tip_req` to UseLatestUnconfirmedTip, and we expect that querying
-        // against the unconfirmed state will succeed.
+    fn test_rpc_get_block_fee_stats_too_many() {
         test_rpc(
             function_name!(),
-            40172,
-            40173,
-            50172,
-            50173,
+            40844,
+            40845,
+            50844,
+            50845,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_callreadonlyfunction(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world-unconfirmed".try_into().unwrap(),
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal(),
-                    None,
-                    "ro-test".try_into().unwrap(),
-                    vec![],
+                convo_client.new_get_block_fee_stats(
+                    PageRequest {
+                        limit: Some(MAX_BLOCK_FEE_STATS + 1),
+                        ..PageRequest::default()
+                    },
                     TipRequest::UseLatestAnchoredTip,
                 )
             },
@@ -6229,23 +9888,11 @@ mod test {
              ref mut peer_client,
              ref mut peer_server,
              ref convo_client,
-             ref convo_server| {
-                let req_md = http_request.metadata().clone();
-                match http_response {
-                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
-                        assert!(data.okay);
-                        assert_eq!(
-                            Value::try_deserialize_hex_untyped(&data.result.clone().unwrap())
-                                .unwrap(),
-                            Value::okay(Value::Int(1)).unwrap()
-                        );
-                        assert!(data.cause.is_none());
-                        true
-                    }
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+             ref convo_server| match http_response {
+                HttpResponseType::BadRequest(..) => true,
+                _ => {
+                    error!("Invalid response: {:?}", &http_response);
+                    false
                 }
             },
         );
@@ -6253,58 +9900,40 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_call_read_only_unconfirmed() {
+    fn test_rpc_get_mempool_summary() {
         test_rpc(
             function_name!(),
-            40180,
-            40181,
-            50180,
-            50181,
+            40826,
+            40827,
+            50826,
+            50827,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
-             ref mut convo_server| {
-                let unconfirmed_tip = peer_client
-                    .chainstate()
-                    .unconfirmed_state
-                    .as_ref()
-                    .unwrap()
-                    .unconfirmed_chain_tip
-                    .clone();
-                convo_client.new_callreadonlyfunction(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world-unconfirmed".try_into().unwrap(),
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal(),
-                    None,
-                    "ro-test".try_into().unwrap(),
-                    vec![],
-                    TipRequest::SpecificTip(unconfirmed_tip),
-                )
-            },
+             ref mut convo_server| { convo_client.new_get_mempool_summary() },
             |ref http_request,
              ref http_response,
              ref mut peer_client,
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
-                        assert!(data.okay);
+                    HttpResponseType::MempoolSummary(_, summary) => {
                         assert_eq!(
-                            Value::try_deserialize_hex_untyped(&data.result.clone().unwrap())
-                                .unwrap(),
-                            Value::okay(Value::Int(1)).unwrap()
+                            summary.fee_rate_histogram.len(),
+                            MEMPOOL_SUMMARY_FEE_RATE_BUCKETS.len()
                         );
-                        assert!(data.cause.is_none());
+                        let bucketed: u64 = summary
+                            .fee_rate_histogram
+                            .iter()
+                            .map(|bucket| bucket.tx_count)
+                            .sum();
+                        assert_eq!(bucketed + summary.unestimated_tx_count, summary.tx_count);
                         true
                     }
                     _ => {
-                        error!("Invalid response; {:?}", &http_response);
+                        error!("Invalid response: {:?}", &http_response);
                         false
                     }
                 }
@@ -6314,20 +9943,24 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_getattachmentsinv_limit_reached() {
+    fn test_rpc_open_session() {
+        let server_tip = RefCell::new(None);
         test_rpc(
             function_name!(),
-            40190,
-            40191,
-            50190,
-            50191,
+            40828,
+            40829,
+            50828,
+            50829,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let pages_indexes = HashSet::from_iter(vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
-                convo_client.new_getattachmentsinv(StacksBlockId([0x00; 32]), pages_indexes)
+                let sortdb = peer_server.sortdb.as_ref().unwrap();
+                let chainstate = &peer_server.stacks_node.as_ref().unwrap().chainstate;
+                let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                *server_tip.borrow_mut() = Some(tip);
+                convo_client.new_open_session(TipRequest::UseLatestAnchoredTip)
             },
             |ref http_request,
              ref http_response,
@@ -6335,17 +9968,30 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
-                println!("{:?}", http_response);
                 match http_response {
-                    HttpResponseType::BadRequest(_, msg) => {
+                    HttpResponseType::Session(_, session_data) => {
+                        let tip = server_tip.borrow().clone().unwrap();
                         assert_eq!(
-                            msg,
-                            "Number of attachment inv pages is limited by 8 per request"
+                            session_data.tip,
+                            StacksBlockHeader::make_index_block_hash(
+                                &tip.consensus_hash,
+                                &tip.anchored_block_hash
+                            )
+                        );
+                        assert_eq!(session_data.tip_height, tip.height);
+                        assert_eq!(
+                            peer_server
+                                .network
+                                .rpc_sessions
+                                .get_tip(&session_data.session_id, get_epoch_time_secs()),
+                            Some(session_data.tip.clone())
                         );
                         true
                     }
-                    _ => false,
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
                 }
             },
         );
@@ -6353,21 +9999,39 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_mempool_query_txtags() {
+    fn test_rpc_get_account_in_session() {
         test_rpc(
             function_name!(),
-            40813,
-            40814,
-            50813,
-            50814,
-            false,
+            40830,
+            40831,
+            50830,
+            50831,
+            true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_mempool_query(
-                    MemPoolSyncData::TxTags([0u8; 32], vec![]),
-                    Some(Txid([0u8; 32])),
+                let sortdb = peer_server.sortdb.as_ref().unwrap();
+                let chainstate = &peer_server.stacks_node.as_ref().unwrap().chainstate;
+                let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                let session = peer_server
+                    .network
+                    .rpc_sessions
+                    .open(
+                        StacksBlockHeader::make_index_block_hash(
+                            &tip.consensus_hash,
+                            &tip.anchored_block_hash,
+                        ),
+                        tip.height,
+                        get_epoch_time_secs(),
+                    )
+                    .unwrap();
+                convo_client.new_getaccount(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    TipRequest::Session(session.session_id),
+                    false,
                 )
             },
             |ref http_request,
@@ -6376,15 +10040,15 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
-                println!("{:?}", http_response);
                 match http_response {
-                    HttpResponseType::MemPoolTxs(_, _, txs) => {
-                        // got everything
-                        assert_eq!(txs.len(), 10);
+                    HttpResponseType::GetAccount(_, data) => {
+                        assert_eq!(data.nonce, 2);
                         true
                     }
-                    _ => false,
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
                 }
             },
         );
@@ -6392,26 +10056,24 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_mempool_query_bloom() {
+    fn test_rpc_get_account_in_unknown_session() {
         test_rpc(
             function_name!(),
-            40815,
-            40816,
-            50815,
-            50816,
-            false,
+            40832,
+            40833,
+            50832,
+            50833,
+            true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                // empty bloom filter
-                convo_client.new_mempool_query(
-                    MemPoolSyncData::BloomFilter(BloomFilter::new(
-                        BLOOM_COUNTER_ERROR_RATE,
-                        MAX_BLOOM_COUNTER_TXS,
-                        BloomNodeHasher::new(&[0u8; 32]),
-                    )),
-                    Some(Txid([0u8; 32])),
+                convo_client.new_getaccount(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    TipRequest::Session(RPCSessionId([0x11; 16])),
+                    false,
                 )
             },
             |ref http_request,
@@ -6419,16 +10081,11 @@ mod test {
              ref mut peer_client,
              ref mut peer_server,
              ref convo_client,
-             ref convo_server| {
-                let req_md = http_request.metadata().clone();
-                println!("{:?}", http_response);
-                match http_response {
-                    HttpResponseType::MemPoolTxs(_, _, txs) => {
-                        // got everything
-                        assert_eq!(txs.len(), 10);
-                        true
-                    }
-                    _ => false,
+             ref convo_server| match http_response {
+                HttpResponseType::NotFound(..) => true,
+                _ => {
+                    error!("Invalid response: {:?}", &http_response);
+                    false
                 }
             },
         );
@@ -0,0 +1,497 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Declarative scenario runner for multi-peer integration tests.
+//!
+//! A `Scenario` is a sequence of steps ("mine 5 burn blocks, submit these txs, partition peers,
+//! assert tip") which is executed in-process against a set of `TestPeer`s that share a mock
+//! burnchain.  Stacks blocks mined by one peer are delivered to every peer in the same partition;
+//! peers in other partitions only receive them once the partition is healed.
+//!
+//! ```ignore
+//! Scenario::new(function_name!(), 4000)
+//!     .peers(3)
+//!     .mine_burn_blocks(5)
+//!     .mine_tenure(0)
+//!     .partition(&[&[0, 1], &[2]])
+//!     .mine_tenure(0)
+//!     .assert_tip_height(2, 1)
+//!     .heal()
+//!     .assert_tips_agree()
+//!     .run();
+//! ```
+
+use std::collections::HashSet;
+
+use crate::burnchains::Txid;
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::burn::operations::BlockstackOperationType;
+use crate::chainstate::burn::ConsensusHash;
+use crate::chainstate::stacks::boot::test::get_parent_tip;
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::chainstate::stacks::tests::make_coinbase;
+use crate::chainstate::stacks::{
+    StacksBlock, StacksBlockBuilder, StacksMicroblock, StacksPrivateKey, StacksPublicKey,
+    StacksTransaction,
+};
+use crate::net::test::{TestPeer, TestPeerConfig};
+use stacks_common::types::chainstate::StacksBlockId;
+use stacks_common::util::hash::Hash160;
+
+/// A check run against the peers at some point in a scenario
+pub type ScenarioCheck = Box<dyn FnMut(&mut [TestPeer<'static>])>;
+
+/// A single step of a scenario
+pub enum ScenarioStep {
+    /// Mine this many burnchain blocks without any block-commits
+    MineBurnBlocks(u64),
+    /// Have the given peer mine a tenure containing all of its submitted transactions
+    MineTenure(usize),
+    /// Submit transactions to the given peer.  They will be mined in its next tenure.
+    SubmitTxs(usize, Vec<StacksTransaction>),
+    /// Split the peers into groups which do not relay blocks to one another
+    Partition(Vec<Vec<usize>>),
+    /// Remove all partitions, and deliver every block each peer missed
+    Heal,
+    /// Assert that the given peer's Stacks chain tip is at the given height
+    AssertTipHeight(usize, u64),
+    /// Assert that every peer has the same Stacks chain tip
+    AssertTipsAgree,
+    /// Assert that the given peer's canonical chain contains the given transaction
+    AssertTxMined(usize, Txid),
+    /// Run an arbitrary check against the peers
+    Check(String, ScenarioCheck),
+}
+
+/// A Stacks block produced during the scenario, along with the set of peers that have it
+struct MinedBlock {
+    consensus_hash: ConsensusHash,
+    block: StacksBlock,
+    microblocks: Vec<StacksMicroblock>,
+    delivered: HashSet<usize>,
+}
+
+/// Builder and runner for a scenario
+pub struct Scenario {
+    name: String,
+    port_base: u16,
+    num_peers: usize,
+    configure: Option<Box<dyn FnOnce(&mut Vec<TestPeerConfig>)>>,
+    steps: Vec<ScenarioStep>,
+}
+
+/// Mutable state of a running scenario
+struct ScenarioState {
+    peers: Vec<TestPeer<'static>>,
+    partition: Vec<usize>,
+    pending_txs: Vec<Vec<StacksTransaction>>,
+    coinbase_nonces: Vec<usize>,
+    mined: Vec<MinedBlock>,
+}
+
+impl Scenario {
+    /// Create a scenario with a single peer.  Each peer binds two ports, starting at `port_base`.
+    pub fn new(name: &str, port_base: u16) -> Scenario {
+        Scenario {
+            name: name.to_string(),
+            port_base,
+            num_peers: 1,
+            configure: None,
+            steps: vec![],
+        }
+    }
+
+    /// Set the number of peers
+    pub fn peers(mut self, num_peers: usize) -> Scenario {
+        assert!(num_peers > 0, "A scenario needs at least one peer");
+        self.num_peers = num_peers;
+        self
+    }
+
+    /// Adjust the peer configurations before the peers are instantiated.  All peers must share
+    /// the same burnchain and genesis state.
+    pub fn configure<F>(mut self, f: F) -> Scenario
+    where
+        F: FnOnce(&mut Vec<TestPeerConfig>) + 'static,
+    {
+        self.configure = Some(Box::new(f));
+        self
+    }
+
+    pub fn step(mut self, step: ScenarioStep) -> Scenario {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn mine_burn_blocks(self, count: u64) -> Scenario {
+        self.step(ScenarioStep::MineBurnBlocks(count))
+    }
+
+    pub fn mine_tenure(self, peer: usize) -> Scenario {
+        self.step(ScenarioStep::MineTenure(peer))
+    }
+
+    pub fn submit_txs(self, peer: usize, txs: Vec<StacksTransaction>) -> Scenario {
+        self.step(ScenarioStep::SubmitTxs(peer, txs))
+    }
+
+    pub fn partition(self, groups: &[&[usize]]) -> Scenario {
+        self.step(ScenarioStep::Partition(
+            groups.iter().map(|group| group.to_vec()).collect(),
+        ))
+    }
+
+    pub fn heal(self) -> Scenario {
+        self.step(ScenarioStep::Heal)
+    }
+
+    pub fn assert_tip_height(self, peer: usize, height: u64) -> Scenario {
+        self.step(ScenarioStep::AssertTipHeight(peer, height))
+    }
+
+    pub fn assert_tips_agree(self) -> Scenario {
+        self.step(ScenarioStep::AssertTipsAgree)
+    }
+
+    pub fn assert_tx_mined(self, peer: usize, txid: Txid) -> Scenario {
+        self.step(ScenarioStep::AssertTxMined(peer, txid))
+    }
+
+    pub fn check<F>(self, name: &str, f: F) -> Scenario
+    where
+        F: FnMut(&mut [TestPeer<'static>]) + 'static,
+    {
+        self.step(ScenarioStep::Check(name.to_string(), Box::new(f)))
+    }
+
+    /// Execute every step in order, panicking with the step's index and description on the
+    /// first failed assertion.  Returns the peers so callers can make further assertions.
+    pub fn run(self) -> Vec<TestPeer<'static>> {
+        let mut peer_configs: Vec<_> = (0..self.num_peers)
+            .map(|i| {
+                let p2p_port = self.port_base + 2 * (i as u16);
+                TestPeerConfig::new(&self.name, p2p_port, p2p_port + 1)
+            })
+            .collect();
+
+        if let Some(configure) = self.configure {
+            configure(&mut peer_configs);
+        }
+
+        let mut state = ScenarioState {
+            peers: peer_configs.into_iter().map(TestPeer::new).collect(),
+            partition: vec![0; self.num_peers],
+            pending_txs: vec![vec![]; self.num_peers],
+            coinbase_nonces: vec![0; self.num_peers],
+            mined: vec![],
+        };
+
+        for (i, step) in self.steps.into_iter().enumerate() {
+            test_debug!("Scenario {}: run step {}", &self.name, i);
+            state.run_step(i, step);
+        }
+
+        state.peers
+    }
+}
+
+impl ScenarioState {
+    fn check_peer(&self, step_index: usize, peer: usize) {
+        assert!(
+            peer < self.peers.len(),
+            "Step {}: no such peer {} (have {})",
+            step_index,
+            peer,
+            self.peers.len()
+        );
+    }
+
+    fn run_step(&mut self, step_index: usize, step: ScenarioStep) {
+        match step {
+            ScenarioStep::MineBurnBlocks(count) => {
+                for _ in 0..count {
+                    self.next_burnchain_block(vec![]);
+                }
+            }
+            ScenarioStep::MineTenure(peer) => {
+                self.check_peer(step_index, peer);
+                self.mine_tenure(peer);
+            }
+            ScenarioStep::SubmitTxs(peer, txs) => {
+                self.check_peer(step_index, peer);
+                self.pending_txs[peer].extend(txs);
+            }
+            ScenarioStep::Partition(groups) => {
+                // peers not named in any group end up in their own partition
+                let mut partition: Vec<usize> = (0..self.peers.len())
+                    .map(|peer| groups.len() + peer)
+                    .collect();
+                for (group_id, group) in groups.iter().enumerate() {
+                    for peer in group.iter() {
+                        self.check_peer(step_index, *peer);
+                        partition[*peer] = group_id;
+                    }
+                }
+                self.partition = partition;
+            }
+            ScenarioStep::Heal => {
+                self.partition = vec![0; self.peers.len()];
+                for peer in 0..self.peers.len() {
+                    self.deliver_missing_blocks(peer);
+                }
+            }
+            ScenarioStep::AssertTipHeight(peer, height) => {
+                self.check_peer(step_index, peer);
+                let tip_height = self.stacks_tip(peer).map(|(height, _)| height).unwrap_or(0);
+                assert_eq!(
+                    tip_height, height,
+                    "Step {}: peer {} has tip height {}, expected {}",
+                    step_index, peer, tip_height, height
+                );
+            }
+            ScenarioStep::AssertTipsAgree => {
+                let tips: Vec<_> = (0..self.peers.len())
+                    .map(|peer| self.stacks_tip(peer))
+                    .collect();
+                for (peer, tip) in tips.iter().enumerate() {
+                    assert_eq!(
+                        tip, &tips[0],
+                        "Step {}: peer {} has tip {:?}, but peer 0 has tip {:?}",
+                        step_index, peer, tip, &tips[0]
+                    );
+                }
+            }
+            ScenarioStep::AssertTxMined(peer, txid) => {
+                self.check_peer(step_index, peer);
+                assert!(
+                    self.canonical_chain_has_tx(peer, &txid),
+                    "Step {}: transaction {} is not in peer {}'s canonical chain",
+                    step_index,
+                    &txid,
+                    peer
+                );
+            }
+            ScenarioStep::Check(name, mut check) => {
+                test_debug!("Step {}: run check '{}'", step_index, &name);
+                check(&mut self.peers);
+            }
+        }
+    }
+
+    /// Get the (height, block ID) of a peer's canonical Stacks chain tip
+    fn stacks_tip(&self, peer: usize) -> Option<(u64, StacksBlockId)> {
+        let peer = &self.peers[peer];
+        let sortdb = peer.sortdb.as_ref().unwrap();
+        peer.stacks_node
+            .as_ref()
+            .unwrap()
+            .chainstate
+            .get_stacks_chain_tip(sortdb)
+            .unwrap()
+            .map(|tip| {
+                (
+                    tip.height,
+                    StacksBlockId::new(&tip.consensus_hash, &tip.anchored_block_hash),
+                )
+            })
+    }
+
+    /// Does any anchored block in a peer's canonical Stacks chain contain the given transaction?
+    fn canonical_chain_has_tx(&self, peer: usize, txid: &Txid) -> bool {
+        let chainstate = &self.peers[peer].stacks_node.as_ref().unwrap().chainstate;
+        let mut cursor = self.stacks_tip(peer).map(|(_, tip)| tip);
+        while let Some(block_id) = cursor {
+            let header_info = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                chainstate.db(),
+                &block_id,
+            )
+            .unwrap()
+            .expect("FATAL: canonical chain has a block with no header");
+            if header_info.stacks_block_height == 0 {
+                // boot block
+                break;
+            }
+            let block = StacksChainState::load_block(
+                &chainstate.blocks_path,
+                &header_info.consensus_hash,
+                &header_info.anchored_header.block_hash(),
+            )
+            .unwrap()
+            .expect("FATAL: canonical chain has a block with no data");
+            if block.txs.iter().any(|tx| tx.txid() == *txid) {
+                return true;
+            }
+            cursor = StacksChainState::get_parent_block_id(chainstate.db(), &block_id).unwrap();
+        }
+        false
+    }
+
+    /// Process a burnchain block with the given operations on every peer.  The burnchain is
+    /// never partitioned.
+    fn next_burnchain_block(
+        &mut self,
+        mut burn_ops: Vec<BlockstackOperationType>,
+    ) -> ConsensusHash {
+        let (_, burn_header_hash, consensus_hash) =
+            self.peers[0].next_burnchain_block(burn_ops.clone());
+        TestPeer::set_ops_burn_header_hash(&mut burn_ops, &burn_header_hash);
+        for peer in self.peers.iter_mut().skip(1) {
+            let (_, peer_burn_header_hash, peer_consensus_hash) =
+                peer.next_burnchain_block_raw(burn_ops.clone());
+            assert_eq!(peer_burn_header_hash, burn_header_hash);
+            assert_eq!(peer_consensus_hash, consensus_hash);
+        }
+        consensus_hash
+    }
+
+    fn mine_tenure(&mut self, miner: usize) {
+        let microblock_privkey = StacksPrivateKey::new();
+        let microblock_pubkeyhash =
+            Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+        let tip = SortitionDB::get_canonical_burn_chain_tip(
+            &self.peers[miner].sortdb.as_ref().unwrap().conn(),
+        )
+        .unwrap();
+
+        let txs = std::mem::replace(&mut self.pending_txs[miner], vec![]);
+        let coinbase_nonce = self.coinbase_nonces[miner];
+
+        let (burn_ops, stacks_block, microblocks) = self.peers[miner].make_tenure(
+            |ref mut miner,
+             ref mut sortdb,
+             ref mut chainstate,
+             vrf_proof,
+             ref parent_opt,
+             ref _parent_microblock_header_opt| {
+                let parent_tip = get_parent_tip(parent_opt, chainstate, sortdb);
+                let coinbase_tx = make_coinbase(miner, coinbase_nonce);
+
+                let mut block_txs = vec![coinbase_tx];
+                block_txs.extend_from_slice(&txs);
+
+                let block_builder = StacksBlockBuilder::make_regtest_block_builder(
+                    &parent_tip,
+                    vrf_proof,
+                    tip.total_burn,
+                    microblock_pubkeyhash,
+                )
+                .unwrap();
+                let (anchored_block, _size, _cost) =
+                    StacksBlockBuilder::make_anchored_block_from_txs(
+                        block_builder,
+                        chainstate,
+                        &sortdb.index_conn(),
+                        block_txs,
+                    )
+                    .unwrap();
+                (anchored_block, vec![])
+            },
+        );
+        self.coinbase_nonces[miner] += 1;
+
+        let consensus_hash = self.next_burnchain_block(burn_ops);
+        self.mined.push(MinedBlock {
+            consensus_hash,
+            block: stacks_block,
+            microblocks,
+            delivered: HashSet::new(),
+        });
+
+        let mined_index = self.mined.len() - 1;
+        for peer in 0..self.peers.len() {
+            if self.partition[peer] == self.partition[miner] {
+                self.deliver_block(peer, mined_index);
+            }
+        }
+    }
+
+    fn deliver_block(&mut self, peer: usize, mined_index: usize) {
+        let mined = &mut self.mined[mined_index];
+        if !mined.delivered.insert(peer) {
+            return;
+        }
+        self.peers[peer].process_stacks_epoch(
+            &mined.block,
+            &mined.consensus_hash,
+            &mined.microblocks,
+        );
+    }
+
+    fn deliver_missing_blocks(&mut self, peer: usize) {
+        for mined_index in 0..self.mined.len() {
+            self.deliver_block(peer, mined_index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::stacks::tests::make_user_stacks_transfer;
+    use crate::net::test::to_addr;
+    use clarity::vm::types::StacksAddressExtensions;
+
+    #[test]
+    fn test_scenario_partition_and_heal() {
+        let peers = Scenario::new(function_name!(), 4600)
+            .peers(3)
+            .mine_tenure(0)
+            .assert_tips_agree()
+            .assert_tip_height(2, 1)
+            .partition(&[&[0, 1], &[2]])
+            .mine_tenure(0)
+            .mine_tenure(0)
+            .assert_tip_height(0, 3)
+            .assert_tip_height(1, 3)
+            .assert_tip_height(2, 1)
+            .heal()
+            .assert_tips_agree()
+            .assert_tip_height(2, 3)
+            .mine_burn_blocks(2)
+            .assert_tips_agree()
+            .run();
+        assert_eq!(peers.len(), 3);
+    }
+
+    #[test]
+    fn test_scenario_tx_mined() {
+        let privk = StacksPrivateKey::from_hex(
+            "42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01",
+        )
+        .unwrap();
+        let addr = to_addr(&privk);
+        let recipient = to_addr(&StacksPrivateKey::new());
+        let tx = make_user_stacks_transfer(&privk, 0, 200, &recipient.to_account_principal(), 123);
+        let txid = tx.txid();
+
+        Scenario::new(function_name!(), 4620)
+            .peers(2)
+            .configure(move |peer_configs| {
+                for peer_config in peer_configs.iter_mut() {
+                    peer_config.initial_balances =
+                        vec![(addr.to_account_principal(), 1_000_000_000)];
+                }
+            })
+            .partition(&[&[0], &[1]])
+            .submit_txs(0, vec![tx])
+            .mine_tenure(0)
+            .assert_tx_mined(0, txid.clone())
+            .heal()
+            .assert_tips_agree()
+            .assert_tx_mined(1, txid)
+            .run();
+    }
+}
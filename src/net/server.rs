@@ -43,6 +43,7 @@ use crate::net::*;
 
 use crate::chainstate::burn::db::sortdb::SortitionDB;
 use crate::chainstate::stacks::db::StacksChainState;
+use crate::types::chainstate::StacksBlockId;
 
 use crate::burnchains::Burnchain;
 use crate::burnchains::BurnchainView;
@@ -655,6 +656,18 @@ impl HttpPeer {
                     close.push(*event_id);
                 }
             }
+            if convo.has_live_stream() {
+                // this conversation's socket won't necessarily become ready again by itself, so
+                // push out whatever the stream has produced since the last pass
+                if let Some(client_sock) = self.sockets.get_mut(event_id) {
+                    if let Err(_e) =
+                        HttpPeer::saturate_http_socket(client_sock, convo, mempool, chainstate)
+                    {
+                        info!("Broken HTTP connection {:?}: {:?}", convo, &_e);
+                        close.push(*event_id);
+                    }
+                }
+            }
             if convo.is_drained() && !convo.is_keep_alive() {
                 // did some work, but nothing more to do and we're not keep-alive
                 debug!("Close drained HTTP connection {:?}", convo);
@@ -701,6 +714,18 @@ impl HttpPeer {
             self.deregister_http(network_state, error_event);
         }
 
+        // tell any block feeds about the current canonical Stacks tip
+        let stacks_tip = StacksBlockId::new(
+            &network.burnchain_tip.canonical_stacks_tip_consensus_hash,
+            &network.burnchain_tip.canonical_stacks_tip_hash,
+        );
+        for (_, convo) in self.peers.iter_mut() {
+            convo.update_block_feeds(
+                &stacks_tip,
+                network.burnchain_tip.canonical_stacks_tip_height,
+            );
+        }
+
         // move conversations along
         let close_events = self.flush_conversations(mempool, chainstate);
         for close_event in close_events {
@@ -0,0 +1,189 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Read-only RPC sessions.
+//!
+//! A client that needs to make several read-only queries against the same chain state (e.g. read
+//! an account, then a map entry, then call a read-only function) can open a session, which pins
+//! a Stacks chain tip.  Any request that takes a `tip` query argument can instead pass
+//! `session=<id>`, and it will be served from the pinned tip -- even if the canonical chain tip
+//! moves in the meantime -- until the session is released or expires.
+//!
+//! Session IDs are random 128-bit values, so one client cannot guess (and thus read through, or
+//! release) another client's session.
+
+use std::collections::HashMap;
+
+use rand::thread_rng;
+use rand::RngCore;
+
+use crate::net::RPCSessionData;
+use crate::types::chainstate::StacksBlockId;
+
+/// Opaque ID for a read-only RPC session
+pub struct RPCSessionId(pub [u8; 16]);
+impl_array_newtype!(RPCSessionId, u8, 16);
+impl_array_hexstring_fmt!(RPCSessionId);
+impl_byte_array_newtype!(RPCSessionId, u8, 16);
+impl_byte_array_serde!(RPCSessionId);
+
+impl RPCSessionId {
+    pub fn random() -> RPCSessionId {
+        let mut bytes = [0u8; 16];
+        thread_rng().fill_bytes(&mut bytes);
+        RPCSessionId(bytes)
+    }
+}
+
+/// A read-only RPC session, pinned to a Stacks chain tip
+#[derive(Debug, Clone, PartialEq)]
+pub struct RPCSession {
+    pub session_id: RPCSessionId,
+    /// index block hash of the pinned chain tip
+    pub tip: StacksBlockId,
+    /// height of the pinned chain tip
+    pub tip_height: u64,
+    /// UNIX timestamp after which this session is no longer usable
+    pub expires_at: u64,
+}
+
+impl RPCSession {
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at <= now
+    }
+
+    pub fn to_rpc_data(&self) -> RPCSessionData {
+        RPCSessionData {
+            session_id: self.session_id.clone(),
+            tip: self.tip.clone(),
+            tip_height: self.tip_height,
+            expires_at: self.expires_at,
+        }
+    }
+}
+
+/// The set of read-only RPC sessions open on this node
+#[derive(Debug, Clone, PartialEq)]
+pub struct RPCSessions {
+    sessions: HashMap<RPCSessionId, RPCSession>,
+    max_sessions: u64,
+    ttl: u64,
+}
+
+impl RPCSessions {
+    pub fn new(max_sessions: u64, ttl: u64) -> RPCSessions {
+        RPCSessions {
+            sessions: HashMap::new(),
+            max_sessions,
+            ttl,
+        }
+    }
+
+    /// Number of sessions currently tracked (including expired ones that have yet to be pruned)
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Forget about all expired sessions
+    pub fn prune_expired(&mut self, now: u64) {
+        self.sessions.retain(|_, session| !session.is_expired(now));
+    }
+
+    /// Open a new session pinned to `tip`.
+    /// Returns None if there are already `max_sessions` unexpired sessions.
+    pub fn open(&mut self, tip: StacksBlockId, tip_height: u64, now: u64) -> Option<RPCSession> {
+        self.prune_expired(now);
+        if (self.sessions.len() as u64) >= self.max_sessions {
+            return None;
+        }
+
+        let mut session_id = RPCSessionId::random();
+        while self.sessions.contains_key(&session_id) {
+            session_id = RPCSessionId::random();
+        }
+
+        let session = RPCSession {
+            session_id: session_id.clone(),
+            tip,
+            tip_height,
+            expires_at: now.saturating_add(self.ttl),
+        };
+        self.sessions.insert(session_id, session.clone());
+        Some(session)
+    }
+
+    /// Look up an unexpired session
+    pub fn get(&self, session_id: &RPCSessionId, now: u64) -> Option<&RPCSession> {
+        self.sessions
+            .get(session_id)
+            .filter(|session| !session.is_expired(now))
+    }
+
+    /// Get the chain tip an unexpired session is pinned to
+    pub fn get_tip(&self, session_id: &RPCSessionId, now: u64) -> Option<StacksBlockId> {
+        self.get(session_id, now).map(|session| session.tip.clone())
+    }
+
+    /// Release a session.  Returns the session if it existed and had not yet expired.
+    pub fn release(&mut self, session_id: &RPCSessionId, now: u64) -> Option<RPCSession> {
+        self.sessions
+            .remove(session_id)
+            .filter(|session| !session.is_expired(now))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rpc_sessions_lifecycle() {
+        let mut sessions = RPCSessions::new(2, 10);
+        let tip_1 = StacksBlockId([0x01; 32]);
+        let tip_2 = StacksBlockId([0x02; 32]);
+
+        let s1 = sessions.open(tip_1.clone(), 1, 100).unwrap();
+        let s2 = sessions.open(tip_2.clone(), 2, 105).unwrap();
+        assert_ne!(s1.session_id, s2.session_id);
+        assert_eq!(s1.expires_at, 110);
+
+        // full
+        assert!(sessions.open(tip_1.clone(), 1, 105).is_none());
+
+        assert_eq!(sessions.get_tip(&s1.session_id, 109), Some(tip_1.clone()));
+        assert_eq!(sessions.get_tip(&s2.session_id, 109), Some(tip_2.clone()));
+
+        // s1 expires
+        assert_eq!(sessions.get_tip(&s1.session_id, 110), None);
+        assert_eq!(sessions.get_tip(&RPCSessionId([0xff; 16]), 100), None);
+
+        // expired sessions get pruned to make room
+        let s3 = sessions.open(tip_1.clone(), 1, 110).unwrap();
+        assert_eq!(sessions.len(), 2);
+
+        // released sessions are gone
+        assert_eq!(sessions.release(&s2.session_id, 111), Some(s2.clone()));
+        assert_eq!(sessions.get_tip(&s2.session_id, 111), None);
+        assert_eq!(sessions.release(&s2.session_id, 111), None);
+        assert_eq!(sessions.get_tip(&s3.session_id, 111), Some(tip_1));
+
+        // session IDs round-trip through hex
+        assert_eq!(
+            RPCSessionId::from_hex(&s3.session_id.to_hex()).unwrap(),
+            s3.session_id
+        );
+    }
+}
@@ -9,18 +9,28 @@ use clarity::vm::database::STXBalance;
 use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier};
 use clarity::vm::ContractName;
 use stacks_common::types::chainstate::StacksAddress;
+use stacks_common::types::NetworkId;
 use stacks_common::util::secp256k1::MessageSignature;
 
 pub fn boot_code_id(name: &str, mainnet: bool) -> QualifiedContractIdentifier {
-    let addr = boot_code_addr(mainnet);
+    network_boot_code_id(name, &NetworkId::from_mainnet(mainnet))
+}
+
+pub fn boot_code_addr(mainnet: bool) -> StacksAddress {
+    network_boot_code_addr(&NetworkId::from_mainnet(mainnet))
+}
+
+/// Get the identifier of a boot contract on the given network
+pub fn network_boot_code_id(name: &str, network: &NetworkId) -> QualifiedContractIdentifier {
+    let addr = network_boot_code_addr(network);
     QualifiedContractIdentifier::new(
         addr.into(),
         ContractName::try_from(name.to_string()).unwrap(),
     )
 }
 
-pub fn boot_code_addr(mainnet: bool) -> StacksAddress {
-    StacksAddress::burn_address(mainnet)
+pub fn network_boot_code_addr(network: &NetworkId) -> StacksAddress {
+    network.boot_address.clone()
 }
 
 pub fn boot_code_tx_auth(boot_code_address: StacksAddress) -> TransactionAuth {
@@ -303,34 +303,8 @@ impl_byte_array_from_column_only!(StacksBlockId);
 
 macro_rules! impl_byte_array_from_column {
     ($thing:ident) => {
-        impl rusqlite::types::FromSql for $thing {
-            fn column_result(
-                value: rusqlite::types::ValueRef,
-            ) -> rusqlite::types::FromSqlResult<Self> {
-                let hex_str = value.as_str()?;
-                let byte_str = stacks_common::util::hash::hex_bytes(hex_str)
-                    .map_err(|_e| rusqlite::types::FromSqlError::InvalidType)?;
-                let inst = $thing::from_bytes(&byte_str)
-                    .ok_or(rusqlite::types::FromSqlError::InvalidType)?;
-                Ok(inst)
-            }
-        }
-
-        impl crate::util_lib::db::FromColumn<$thing> for $thing {
-            fn from_column(
-                row: &rusqlite::Row,
-                column_name: &str,
-            ) -> Result<Self, crate::util_lib::db::Error> {
-                Ok(row.get_unwrap::<_, Self>(column_name))
-            }
-        }
-
-        impl rusqlite::types::ToSql for $thing {
-            fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput> {
-                let hex_str = self.to_hex();
-                Ok(hex_str.into())
-            }
-        }
+        impl_byte_array_rusqlite_only!($thing);
+        impl_byte_array_from_column_only!($thing);
     };
 }
 
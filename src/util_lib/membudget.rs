@@ -0,0 +1,266 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A process-wide memory budget for in-RAM caches.
+//!
+//! Each cache registers a `BudgetAccount` with the budget and reports how many bytes it holds.
+//! Nothing is enforced while the total across all accounts stays under the limit.  Once it goes
+//! over, every account is asked to shrink to its proportional share of the limit: an account
+//! holding `u` bytes out of a total of `T` must shrink to `u * L / T` for a limit of `L`.
+//!
+//! Eviction is cooperative.  A cache checks `BudgetAccount::eviction_target()` when it grows, and
+//! evicts its own entries on its own thread, so the limit is a soft cap that the node converges
+//! back under rather than a hard allocator limit.  Sizes are estimates of the cached data, not
+//! exact allocator accounting.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Account names for the caches that register with the budget
+pub const MARF_NODE_CACHE: &str = "marf_node_cache";
+pub const PEER_MESSAGE_BUFFER: &str = "peer_message_buffer";
+pub const MEMPOOL_NONCE_CACHE: &str = "mempool_nonce_cache";
+pub const MEMPOOL_CANDIDATE_CACHE: &str = "mempool_candidate_cache";
+pub const MEMPOOL_CANDIDATE_VIEW: &str = "mempool_candidate_view";
+pub const MEMPOOL_MIRROR: &str = "mempool_mirror";
+
+/// Memory budget shared by a set of caches
+#[derive(Debug)]
+pub struct MemoryBudget {
+    /// Limit in bytes; 0 means unlimited
+    limit: AtomicU64,
+    /// Sum of all accounts' usage
+    total: AtomicU64,
+    /// Registered accounts, for reporting
+    accounts: Mutex<HashMap<u64, (String, Arc<AtomicU64>)>>,
+    next_account_id: AtomicU64,
+}
+
+lazy_static! {
+    static ref GLOBAL_MEMORY_BUDGET: Arc<MemoryBudget> = Arc::new(MemoryBudget::new(0));
+}
+
+impl MemoryBudget {
+    /// Make a budget with the given limit in bytes.  A limit of 0 means unlimited.
+    pub fn new(limit: u64) -> MemoryBudget {
+        MemoryBudget {
+            limit: AtomicU64::new(limit),
+            total: AtomicU64::new(0),
+            accounts: Mutex::new(HashMap::new()),
+            next_account_id: AtomicU64::new(0),
+        }
+    }
+
+    /// The process-wide budget.  It is unlimited until `set_limit()` is called on it.
+    pub fn global() -> Arc<MemoryBudget> {
+        GLOBAL_MEMORY_BUDGET.clone()
+    }
+
+    /// Change the limit, in bytes.  Accounts registered before the change are subject to it, and
+    /// will shrink the next time they grow.
+    pub fn set_limit(&self, limit: u64) {
+        self.limit.store(limit, Ordering::SeqCst);
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit.load(Ordering::SeqCst)
+    }
+
+    pub fn total_usage(&self) -> u64 {
+        self.total.load(Ordering::SeqCst)
+    }
+
+    pub fn is_over_limit(&self) -> bool {
+        let limit = self.limit();
+        limit > 0 && self.total_usage() > limit
+    }
+
+    /// Bytes held by each kind of cache, summed over all accounts with the same name
+    pub fn usage_by_name(&self) -> HashMap<String, u64> {
+        let accounts = self
+            .accounts
+            .lock()
+            .expect("FATAL: memory budget lock poisoned");
+        let mut usage = HashMap::new();
+        for (name, account_usage) in accounts.values() {
+            *usage.entry(name.clone()).or_insert(0) += account_usage.load(Ordering::SeqCst);
+        }
+        usage
+    }
+
+    /// Register a new account.  Several accounts may share a name (e.g. one per open MARF).
+    pub fn register(budget: &Arc<MemoryBudget>, name: &str) -> BudgetAccount {
+        let id = budget.next_account_id.fetch_add(1, Ordering::SeqCst);
+        let usage = Arc::new(AtomicU64::new(0));
+        budget
+            .accounts
+            .lock()
+            .expect("FATAL: memory budget lock poisoned")
+            .insert(id, (name.to_string(), usage.clone()));
+        BudgetAccount {
+            id,
+            usage,
+            budget: budget.clone(),
+        }
+    }
+
+    /// Register a new account with the process-wide budget
+    pub fn register_global(name: &str) -> BudgetAccount {
+        MemoryBudget::register(&GLOBAL_MEMORY_BUDGET, name)
+    }
+}
+
+/// One cache's share of a memory budget.  Its usage is released when it is dropped.
+#[derive(Debug)]
+pub struct BudgetAccount {
+    id: u64,
+    usage: Arc<AtomicU64>,
+    budget: Arc<MemoryBudget>,
+}
+
+impl BudgetAccount {
+    /// Bytes this account currently holds
+    pub fn usage(&self) -> u64 {
+        self.usage.load(Ordering::SeqCst)
+    }
+
+    /// Record that the cache grew by `bytes`
+    pub fn charge(&self, bytes: u64) {
+        self.usage.fetch_add(bytes, Ordering::SeqCst);
+        self.budget.total.fetch_add(bytes, Ordering::SeqCst);
+    }
+
+    /// Record that the cache shrank by `bytes`
+    pub fn release(&self, bytes: u64) {
+        let bytes = bytes.min(self.usage());
+        self.usage.fetch_sub(bytes, Ordering::SeqCst);
+        self.budget.total.fetch_sub(bytes, Ordering::SeqCst);
+    }
+
+    /// Record the cache's current size outright
+    pub fn set_usage(&self, bytes: u64) {
+        let prior = self.usage.swap(bytes, Ordering::SeqCst);
+        if bytes >= prior {
+            self.budget.total.fetch_add(bytes - prior, Ordering::SeqCst);
+        } else {
+            self.budget.total.fetch_sub(prior - bytes, Ordering::SeqCst);
+        }
+    }
+
+    /// Can the cache grow by `bytes` without putting the budget over its limit?
+    pub fn has_room(&self, bytes: u64) -> bool {
+        let limit = self.budget.limit();
+        limit == 0 || self.budget.total_usage().saturating_add(bytes) <= limit
+    }
+
+    /// If the budget is over its limit, the size this account must shrink to: its proportional
+    /// share of the limit.  Returns None if no eviction is needed.
+    pub fn eviction_target(&self) -> Option<u64> {
+        let limit = self.budget.limit();
+        let total = self.budget.total_usage();
+        if limit == 0 || total <= limit {
+            return None;
+        }
+        let usage = self.usage();
+        if usage == 0 {
+            return None;
+        }
+        let target = (u128::from(usage) * u128::from(limit)) / u128::from(total);
+        Some(target as u64)
+    }
+}
+
+impl Drop for BudgetAccount {
+    fn drop(&mut self) {
+        self.set_usage(0);
+        if let Ok(mut accounts) = self.budget.accounts.lock() {
+            accounts.remove(&self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_memory_budget_accounting() {
+        let budget = Arc::new(MemoryBudget::new(0));
+        let a = MemoryBudget::register(&budget, "a");
+        let b1 = MemoryBudget::register(&budget, "b");
+        let b2 = MemoryBudget::register(&budget, "b");
+
+        a.charge(100);
+        b1.charge(50);
+        b2.set_usage(25);
+        assert_eq!(budget.total_usage(), 175);
+
+        b2.set_usage(10);
+        a.release(40);
+        assert_eq!(a.usage(), 60);
+        assert_eq!(budget.total_usage(), 120);
+
+        let usage = budget.usage_by_name();
+        assert_eq!(usage.get("a"), Some(&60));
+        assert_eq!(usage.get("b"), Some(&60));
+
+        // releasing more than was charged can't underflow
+        a.release(1000);
+        assert_eq!(a.usage(), 0);
+        assert_eq!(budget.total_usage(), 60);
+
+        // dropping an account releases its usage
+        drop(b1);
+        assert_eq!(budget.total_usage(), 10);
+        assert_eq!(budget.usage_by_name().get("b"), Some(&10));
+    }
+
+    #[test]
+    fn test_memory_budget_proportional_eviction() {
+        let budget = Arc::new(MemoryBudget::new(0));
+        let a = MemoryBudget::register(&budget, "a");
+        let b = MemoryBudget::register(&budget, "b");
+        let c = MemoryBudget::register(&budget, "c");
+        a.charge(600);
+        b.charge(300);
+        c.charge(100);
+
+        // unlimited: never evict
+        assert!(!budget.is_over_limit());
+        assert!(a.has_room(1_000_000));
+        assert_eq!(a.eviction_target(), None);
+
+        // limit set later still applies to existing accounts
+        budget.set_limit(500);
+        assert!(budget.is_over_limit());
+        assert!(!c.has_room(1));
+        assert_eq!(a.eviction_target(), Some(300));
+        assert_eq!(b.eviction_target(), Some(150));
+        assert_eq!(c.eviction_target(), Some(50));
+
+        // once everyone has shrunk to their share, we're back under the limit
+        a.set_usage(300);
+        b.set_usage(150);
+        c.set_usage(50);
+        assert!(!budget.is_over_limit());
+        assert_eq!(a.eviction_target(), None);
+        assert!(!a.has_room(1));
+        c.release(50);
+        assert!(a.has_room(50));
+        assert!(!a.has_room(51));
+    }
+}
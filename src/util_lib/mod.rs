@@ -2,6 +2,8 @@
 pub mod db;
 pub mod bloom;
 pub mod boot;
+pub mod membudget;
+pub mod sched;
 pub mod strings;
 
 #[cfg(test)]
@@ -0,0 +1,557 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Deterministic scheduling of inter-thread messages, for debugging races.
+//!
+//! Every message passed between the node's threads (chains coordinator signals, relayer
+//! directives, attachment notices, miner thread joins) goes through a _scheduling point_.  When
+//! the `deterministic_sched` feature is enabled, the `STACKS_SCHED` environment variable decides
+//! what happens at each one:
+//!
+//! * `record:<path>` appends an event to the trace file at `<path>`, one JSON object per line.
+//! * `replay:<path>` holds each thread at its scheduling point until the trace says it is that
+//! event's turn, so messages are exchanged in exactly the recorded order.
+//!
+//! Racy checks (such as "has the miner thread finished?" or "is there a message waiting?") are
+//! recorded as _decisions_, and their recorded outcome is used in place of the live check on
+//! replay.
+//!
+//! If the replayed run diverges from the trace -- an expected event does not arrive within
+//! `STACKS_SCHED_TIMEOUT_MS` milliseconds (default 30000), or the trace is exhausted -- the
+//! scheduler logs where it diverged and stops enforcing the trace for the rest of the run.
+//!
+//! Only the ordering of inter-thread messages is controlled.  Network I/O, the burnchain, and
+//! wall-clock timeouts are not, so a replay is only faithful up to the first point where those
+//! change what a thread sends.
+//!
+//! Without the feature, scheduling points compile down to direct channel operations.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::mpsc::{
+    self, Receiver, RecvError, SendError, SyncSender, TryRecvError, TrySendError,
+};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "deterministic_sched")]
+use std::env;
+
+/// Name of the environment variable that selects the scheduling mode
+pub const SCHED_ENV_VAR: &str = "STACKS_SCHED";
+/// Name of the environment variable that sets the replay divergence timeout, in milliseconds
+pub const SCHED_TIMEOUT_ENV_VAR: &str = "STACKS_SCHED_TIMEOUT_MS";
+/// Default replay divergence timeout
+pub const DEFAULT_SCHED_TIMEOUT_MS: u64 = 30_000;
+
+/// Label prefix for recorded decisions
+const DECISION_PREFIX: &str = "decide:";
+
+/// One entry in a schedule trace
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchedEvent {
+    /// Position of this event in the trace
+    pub seq: u64,
+    /// Name of the thread that hit the scheduling point.  Informational only; it is not used for
+    /// matching, since thread names need not be unique.
+    pub thread: String,
+    /// Channel the event belongs to
+    pub channel: String,
+    /// What happened on the channel
+    pub label: String,
+}
+
+impl SchedEvent {
+    fn decision(&self) -> Option<bool> {
+        match self.label.strip_prefix(DECISION_PREFIX) {
+            Some("true") => Some(true),
+            Some("false") => Some(false),
+            _ => None,
+        }
+    }
+}
+
+enum SchedMode {
+    /// Scheduling points are pass-throughs
+    Passthrough,
+    /// Scheduling points are appended to this file
+    Record(fs::File),
+    /// Scheduling points are held until they match the next event in this trace
+    Replay(Vec<SchedEvent>),
+}
+
+struct SchedState {
+    mode: SchedMode,
+    /// Number of events recorded or replayed so far
+    pos: u64,
+    /// Set once a replay no longer matches its trace
+    diverged: bool,
+}
+
+/// Records or replays the order in which threads pass through their scheduling points
+pub struct Scheduler {
+    state: Mutex<SchedState>,
+    wakeup: Condvar,
+    timeout: Duration,
+}
+
+impl Scheduler {
+    fn new(mode: SchedMode, timeout: Duration) -> Scheduler {
+        Scheduler {
+            state: Mutex::new(SchedState {
+                mode,
+                pos: 0,
+                diverged: false,
+            }),
+            wakeup: Condvar::new(),
+            timeout,
+        }
+    }
+
+    /// Scheduler that does not record or enforce anything
+    pub fn passthrough() -> Scheduler {
+        Scheduler::new(SchedMode::Passthrough, Duration::from_millis(0))
+    }
+
+    /// Scheduler that appends each event to the trace at `path`, truncating it first
+    pub fn record<P: AsRef<Path>>(path: P) -> Result<Scheduler, String> {
+        let file = fs::File::create(path.as_ref()).map_err(|e| {
+            format!(
+                "Failed to create schedule trace {}: {:?}",
+                path.as_ref().display(),
+                &e
+            )
+        })?;
+        Ok(Scheduler::new(
+            SchedMode::Record(file),
+            Duration::from_millis(0),
+        ))
+    }
+
+    /// Scheduler that enforces the order of the trace at `path`.  A thread waits at most
+    /// `timeout` for its turn before the replay is declared diverged.
+    pub fn replay<P: AsRef<Path>>(path: P, timeout: Duration) -> Result<Scheduler, String> {
+        let file = fs::File::open(path.as_ref()).map_err(|e| {
+            format!(
+                "Failed to open schedule trace {}: {:?}",
+                path.as_ref().display(),
+                &e
+            )
+        })?;
+        let mut trace = vec![];
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("Failed to read schedule trace: {:?}", &e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event: SchedEvent = serde_json::from_str(&line)
+                .map_err(|e| format!("Malformed schedule trace line {}: {:?}", i + 1, &e))?;
+            trace.push(event);
+        }
+        Ok(Scheduler::new(SchedMode::Replay(trace), timeout))
+    }
+
+    /// Instantiate the scheduler selected by the `STACKS_SCHED` environment variable.
+    /// An unset or invalid setting yields a pass-through scheduler.
+    #[cfg(feature = "deterministic_sched")]
+    fn from_env() -> Scheduler {
+        let setting = match env::var(SCHED_ENV_VAR) {
+            Ok(setting) => setting,
+            Err(_) => return Scheduler::passthrough(),
+        };
+        let timeout = env::var(SCHED_TIMEOUT_ENV_VAR)
+            .ok()
+            .and_then(|ms| ms.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SCHED_TIMEOUT_MS);
+
+        let sched_res = if let Some(path) = setting.strip_prefix("record:") {
+            Scheduler::record(path)
+        } else if let Some(path) = setting.strip_prefix("replay:") {
+            Scheduler::replay(path, Duration::from_millis(timeout))
+        } else {
+            Err(format!(
+                "Unrecognized {} setting '{}'; expected record:<path> or replay:<path>",
+                SCHED_ENV_VAR, &setting
+            ))
+        };
+
+        match sched_res {
+            Ok(sched) => {
+                info!("Sched: deterministic scheduling enabled ({})", &setting);
+                sched
+            }
+            Err(msg) => {
+                warn!("Sched: {}; scheduling points are disabled", &msg);
+                Scheduler::passthrough()
+            }
+        }
+    }
+
+    /// Has a replay stopped matching its trace?
+    pub fn has_diverged(&self) -> bool {
+        self.state
+            .lock()
+            .expect("FATAL: scheduler lock poisoned")
+            .diverged
+    }
+
+    /// Pass through a scheduling point.  When recording, `label` is appended to the trace.  When
+    /// replaying, this blocks until the next event in the trace is `label` on `channel`.
+    pub fn point(&self, channel: &str, label: &str) {
+        self.sequence(channel, label, |event| event.label == label);
+    }
+
+    /// Evaluate a racy check at a scheduling point.  When recording (or passing through),
+    /// `check` is evaluated and its outcome recorded.  When replaying, this blocks until the next
+    /// event in the trace is a decision on `channel`, and returns its recorded outcome without
+    /// evaluating `check`.
+    pub fn decide<F: FnOnce() -> bool>(&self, channel: &str, check: F) -> bool {
+        if let Some(event) = self.sequence(channel, "", |event| event.decision().is_some()) {
+            return event.decision().expect("BUG: matched a non-decision event");
+        }
+        let outcome = check();
+        self.record_event(channel, &format!("{}{}", DECISION_PREFIX, outcome));
+        outcome
+    }
+
+    /// Record `label`, or wait for the trace event satisfying `matches`.  Returns the replayed
+    /// event, if there was one.  A decision passes an empty `label` so that it can be recorded
+    /// once its outcome is known.
+    fn sequence<F: Fn(&SchedEvent) -> bool>(
+        &self,
+        channel: &str,
+        label: &str,
+        matches: F,
+    ) -> Option<SchedEvent> {
+        let mut state = self.state.lock().expect("FATAL: scheduler lock poisoned");
+        match state.mode {
+            SchedMode::Passthrough => return None,
+            SchedMode::Record(_) => {
+                drop(state);
+                if !label.is_empty() {
+                    self.record_event(channel, label);
+                }
+                return None;
+            }
+            SchedMode::Replay(_) => {}
+        }
+
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            if state.diverged {
+                return None;
+            }
+            let pos = state.pos;
+            let next = match state.mode {
+                SchedMode::Replay(ref trace) => trace.get(pos as usize).cloned(),
+                _ => unreachable!(),
+            };
+            let next = match next {
+                Some(next) => next,
+                None => {
+                    warn!(
+                        "Sched: trace exhausted after {} events at {}/{}; no longer enforcing the schedule",
+                        pos, channel, label
+                    );
+                    state.diverged = true;
+                    self.wakeup.notify_all();
+                    return None;
+                }
+            };
+
+            if next.channel == channel && matches(&next) {
+                state.pos += 1;
+                self.wakeup.notify_all();
+                return Some(next);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                warn!(
+                    "Sched: replay diverged at event {}: expected {}/{} (from thread '{}'), but {}/{} is still waiting; no longer enforcing the schedule",
+                    next.seq, &next.channel, &next.label, &next.thread, channel, label
+                );
+                state.diverged = true;
+                self.wakeup.notify_all();
+                return None;
+            }
+            state = self
+                .wakeup
+                .wait_timeout(state, deadline - now)
+                .expect("FATAL: scheduler lock poisoned")
+                .0;
+        }
+    }
+
+    fn record_event(&self, channel: &str, label: &str) {
+        let mut state = self.state.lock().expect("FATAL: scheduler lock poisoned");
+        let seq = state.pos;
+        let file = match state.mode {
+            SchedMode::Record(ref mut file) => file,
+            _ => return,
+        };
+        let event = SchedEvent {
+            seq,
+            thread: thread::current().name().unwrap_or("unnamed").to_string(),
+            channel: channel.to_string(),
+            label: label.to_string(),
+        };
+        let line = serde_json::to_string(&event).expect("FATAL: failed to serialize SchedEvent");
+        if let Err(e) = writeln!(file, "{}", &line).and_then(|_| file.flush()) {
+            warn!("Sched: failed to write schedule trace: {:?}", &e);
+        }
+        state.pos += 1;
+    }
+}
+
+#[cfg(feature = "deterministic_sched")]
+lazy_static! {
+    static ref SCHEDULER: Scheduler = Scheduler::from_env();
+}
+
+/// Pass through a scheduling point on the process-wide scheduler.
+/// A no-op unless the `deterministic_sched` feature is enabled.
+#[inline]
+pub fn sched_point(_channel: &str, _label: &str) {
+    #[cfg(feature = "deterministic_sched")]
+    SCHEDULER.point(_channel, _label);
+}
+
+/// Evaluate a racy check on the process-wide scheduler.
+/// Without the `deterministic_sched` feature, this just evaluates `check`.
+#[inline]
+pub fn sched_decision<F: FnOnce() -> bool>(_channel: &str, check: F) -> bool {
+    #[cfg(feature = "deterministic_sched")]
+    return SCHEDULER.decide(_channel, check);
+    #[cfg(not(feature = "deterministic_sched"))]
+    check()
+}
+
+/// Sending half of a scheduled channel.  Each send is a scheduling point labeled with the
+/// message's label.
+pub struct SchedSyncSender<T> {
+    channel: &'static str,
+    label: fn(&T) -> &'static str,
+    inner: SyncSender<T>,
+}
+
+impl<T> Clone for SchedSyncSender<T> {
+    fn clone(&self) -> Self {
+        SchedSyncSender {
+            channel: self.channel,
+            label: self.label,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> SchedSyncSender<T> {
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        sched_point(self.channel, (self.label)(&msg));
+        self.inner.send(msg)
+    }
+
+    pub fn try_send(&self, msg: T) -> Result<(), TrySendError<T>> {
+        sched_point(self.channel, (self.label)(&msg));
+        self.inner.try_send(msg)
+    }
+}
+
+/// Receiving half of a scheduled channel.  Each receive is a scheduling point, so on replay a
+/// message is only taken once all events that preceded it in the trace have happened.
+pub struct SchedReceiver<T> {
+    channel: &'static str,
+    label: fn(&T) -> &'static str,
+    inner: Receiver<T>,
+}
+
+impl<T> SchedReceiver<T> {
+    /// Wrap the receiving half of an existing channel, whose sender is not scheduled
+    pub fn new(
+        channel: &'static str,
+        label: fn(&T) -> &'static str,
+        inner: Receiver<T>,
+    ) -> SchedReceiver<T> {
+        SchedReceiver {
+            channel,
+            label,
+            inner,
+        }
+    }
+
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let msg = self.inner.recv()?;
+        sched_point(self.channel, (self.label)(&msg));
+        Ok(msg)
+    }
+
+    /// Whether or not a message is ready is itself racy, so it is recorded as a decision.  On
+    /// replay, a recorded hit blocks until the message arrives.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut received = None;
+        let ready = sched_decision(self.channel, || {
+            let res = self.inner.try_recv();
+            let ready = res.is_ok();
+            received = Some(res);
+            ready
+        });
+        match received {
+            Some(res) => res,
+            None if ready => self.inner.recv().map_err(|_| TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+/// Create a bounded channel whose sends and receives are scheduling points on `channel`.
+/// `label` names each message in the trace.
+pub fn sync_channel<T>(
+    channel: &'static str,
+    bound: usize,
+    label: fn(&T) -> &'static str,
+) -> (SchedSyncSender<T>, SchedReceiver<T>) {
+    let (inner_send, inner_recv) = mpsc::sync_channel(bound);
+    (
+        SchedSyncSender {
+            channel,
+            label,
+            inner: inner_send,
+        },
+        SchedReceiver::new(channel, label, inner_recv),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    fn trace_path(name: &str) -> String {
+        let path = format!("/tmp/stacks-sched-test-{}.jsonl", name);
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn read_trace(path: &str) -> Vec<SchedEvent> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_sched_record() {
+        let path = trace_path("record");
+        let sched = Scheduler::record(&path).unwrap();
+        sched.point("relayer", "send:RunTenure");
+        assert!(sched.decide("miner", || true));
+        assert!(!sched.decide("miner", || false));
+        sched.point("coordinator", "wake");
+
+        let trace = read_trace(&path);
+        let summary: Vec<_> = trace
+            .iter()
+            .map(|e| (e.seq, e.channel.as_str(), e.label.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, "relayer", "send:RunTenure"),
+                (1, "miner", "decide:true"),
+                (2, "miner", "decide:false"),
+                (3, "coordinator", "wake"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sched_replay_enforces_order() {
+        let path = trace_path("replay-order");
+        {
+            let sched = Scheduler::record(&path).unwrap();
+            sched.point("b", "second");
+            sched.point("a", "first");
+            sched.decide("c", || true);
+        }
+
+        // spawn threads in the opposite order of the trace, and check that they pass
+        // through their scheduling points in the recorded order anyway.
+        let sched = Arc::new(Scheduler::replay(&path, Duration::from_secs(10)).unwrap());
+        let (order_tx, order_rx) = mpsc::channel();
+        let mut handles = vec![];
+        for (channel, label) in [("c", ""), ("a", "first"), ("b", "second")] {
+            let sched = sched.clone();
+            let order_tx = order_tx.clone();
+            handles.push(thread::spawn(move || {
+                if channel == "c" {
+                    // recorded outcome wins over the live check
+                    assert!(sched.decide(channel, || false));
+                } else {
+                    sched.point(channel, label);
+                }
+                order_tx.send(channel).unwrap();
+            }));
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let order: Vec<_> = order_rx.try_iter().collect();
+        assert_eq!(order, vec!["b", "a", "c"]);
+        assert!(!sched.has_diverged());
+    }
+
+    #[test]
+    fn test_sched_replay_divergence() {
+        let path = trace_path("replay-diverge");
+        {
+            let sched = Scheduler::record(&path).unwrap();
+            sched.point("a", "never-happens");
+        }
+
+        let sched = Scheduler::replay(&path, Duration::from_millis(100)).unwrap();
+        sched.point("b", "something-else");
+        assert!(sched.has_diverged());
+
+        // once diverged, scheduling points pass through and decisions are live
+        sched.point("a", "never-happens");
+        assert!(!sched.decide("c", || false));
+
+        // exhausting the trace also counts as diverging
+        let path = trace_path("replay-exhausted");
+        {
+            Scheduler::record(&path).unwrap();
+        }
+        let sched = Scheduler::replay(&path, Duration::from_secs(10)).unwrap();
+        sched.point("a", "extra");
+        assert!(sched.has_diverged());
+    }
+
+    #[test]
+    fn test_sched_channel_passthrough() {
+        let (send, recv) = sync_channel("test", 2, |_: &u32| "num");
+        assert_eq!(recv.try_recv(), Err(TryRecvError::Empty));
+        send.send(1).unwrap();
+        send.clone().try_send(2).unwrap();
+        assert!(send.try_send(3).is_err());
+        assert_eq!(recv.recv(), Ok(1));
+        assert_eq!(recv.try_recv(), Ok(2));
+        drop(send);
+        assert_eq!(recv.try_recv(), Err(TryRecvError::Disconnected));
+    }
+}
@@ -9,6 +9,10 @@ use crate::util::secp256k1::MESSAGE_SIGNATURE_ENCODED_SIZE;
 #[macro_use]
 pub mod macros;
 
+/// Derives `StacksMessageCodec` for a struct, encoding its fields in declaration order.  See the
+/// `stacks-codec-derive` crate for how each kind of field is encoded.
+pub use stacks_codec_derive::StacksMessageCodec;
+
 #[derive(Debug)]
 pub enum Error {
     /// Failed to encode
@@ -217,3 +221,123 @@ pub const RELAY_DATA_ENCODED_SIZE: u32 = NEIGHBOR_ADDRESS_ENCODED_SIZE + 4;
 
 pub const NEIGHBOR_ADDRESS_ENCODED_SIZE: u32 = PEER_ADDRESS_ENCODED_SIZE + 2 + HASH160_ENCODED_SIZE;
 pub const PEER_ADDRESS_ENCODED_SIZE: u32 = 16;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, StacksMessageCodec)]
+    struct Inner {
+        a: u8,
+        b: u32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, StacksMessageCodec)]
+    struct Outer {
+        inner: Inner,
+        bytes: [u8; 4],
+        inners: [Inner; 2],
+        #[codec(max_len = 2)]
+        at_most: Vec<u16>,
+        #[codec(exact_len = 1)]
+        exact: Vec<Inner>,
+        unbounded: Vec<u64>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, StacksMessageCodec)]
+    struct Tuple(u16, Vec<u8>);
+
+    #[derive(Debug, Clone, PartialEq, StacksMessageCodec)]
+    struct Unit;
+
+    #[derive(Debug, Clone, PartialEq, StacksMessageCodec)]
+    struct Generic<T> {
+        items: Vec<T>,
+    }
+
+    fn inner(a: u8, b: u32) -> Inner {
+        Inner { a, b }
+    }
+
+    #[test]
+    fn test_derive_codec_encoding() {
+        let outer = Outer {
+            inner: inner(1, 2),
+            bytes: [3, 4, 5, 6],
+            inners: [inner(7, 8), inner(9, 10)],
+            at_most: vec![11, 12],
+            exact: vec![inner(13, 14)],
+            unbounded: vec![15],
+        };
+
+        let mut expected = vec![];
+        expected.extend_from_slice(&[1, 0, 0, 0, 2]);
+        expected.extend_from_slice(&[3, 4, 5, 6]);
+        expected.extend_from_slice(&[7, 0, 0, 0, 8, 9, 0, 0, 0, 10]);
+        expected.extend_from_slice(&[0, 0, 0, 2, 0, 11, 0, 12]);
+        expected.extend_from_slice(&[0, 0, 0, 1, 13, 0, 0, 0, 14]);
+        expected.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 15]);
+
+        let bytes = outer.serialize_to_vec();
+        assert_eq!(bytes, expected);
+        assert_eq!(
+            Outer::consensus_deserialize(&mut &bytes[..]).unwrap(),
+            outer
+        );
+
+        let tuple = Tuple(0x0102, vec![3, 4]);
+        let bytes = tuple.serialize_to_vec();
+        assert_eq!(bytes, vec![1, 2, 0, 0, 0, 2, 3, 4]);
+        assert_eq!(
+            Tuple::consensus_deserialize(&mut &bytes[..]).unwrap(),
+            tuple
+        );
+
+        assert_eq!(Unit.serialize_to_vec(), Vec::<u8>::new());
+        assert_eq!(Unit::consensus_deserialize(&mut &[][..]).unwrap(), Unit);
+
+        let generic = Generic {
+            items: vec![inner(1, 2)],
+        };
+        let bytes = generic.serialize_to_vec();
+        assert_eq!(bytes, vec![0, 0, 0, 1, 1, 0, 0, 0, 2]);
+        assert_eq!(
+            Generic::<Inner>::consensus_deserialize(&mut &bytes[..]).unwrap(),
+            generic
+        );
+    }
+
+    #[test]
+    fn test_derive_codec_bounds() {
+        let mut outer = Outer {
+            inner: inner(1, 2),
+            bytes: [3, 4, 5, 6],
+            inners: [inner(7, 8), inner(9, 10)],
+            at_most: vec![11, 12, 13],
+            exact: vec![inner(13, 14)],
+            unbounded: vec![],
+        };
+
+        // too many items
+        let bytes = outer.serialize_to_vec();
+        match Outer::consensus_deserialize(&mut &bytes[..]) {
+            Err(Error::DeserializeError(_)) => {}
+            x => panic!("Expected DeserializeError, got {:?}", x),
+        }
+
+        // too few items
+        outer.at_most = vec![];
+        outer.exact = vec![];
+        let bytes = outer.serialize_to_vec();
+        match Outer::consensus_deserialize(&mut &bytes[..]) {
+            Err(Error::DeserializeError(_)) => {}
+            x => panic!("Expected DeserializeError, got {:?}", x),
+        }
+
+        // truncated
+        outer.exact = vec![inner(13, 14)];
+        let bytes = outer.serialize_to_vec();
+        assert!(Outer::consensus_deserialize(&mut &bytes[..bytes.len() - 1]).is_err());
+        assert!(Outer::consensus_deserialize(&mut &bytes[..]).is_ok());
+    }
+}
@@ -6,6 +6,9 @@
 #![allow(non_upper_case_globals)]
 #![cfg_attr(test, allow(unused_variables, unused_assignments))]
 
+// lets code generated by `#[derive(StacksMessageCodec)]` name this crate as `stacks_common`
+extern crate self as stacks_common;
+
 extern crate curve25519_dalek;
 extern crate ed25519_dalek;
 extern crate rand;
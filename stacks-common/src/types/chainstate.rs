@@ -330,7 +330,6 @@ impl_byte_array_rusqlite_only!(Hash160);
 impl_byte_array_rusqlite_only!(BlockHeaderHash);
 impl_byte_array_rusqlite_only!(VRFSeed);
 impl_byte_array_rusqlite_only!(BurnchainHeaderHash);
-impl_byte_array_rusqlite_only!(VRFProof);
 impl_byte_array_rusqlite_only!(TrieHash);
 impl_byte_array_rusqlite_only!(Sha512Trunc256Sum);
 impl_byte_array_rusqlite_only!(MessageSignature);
@@ -412,6 +411,19 @@ impl ToSql for Sha256dHash {
     }
 }
 
+impl FromSql for VRFProof {
+    fn column_result(value: ValueRef) -> FromSqlResult<VRFProof> {
+        let hex_str = value.as_str()?;
+        VRFProof::from_hex(hex_str).ok_or(FromSqlError::InvalidType)
+    }
+}
+
+impl ToSql for VRFProof {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput> {
+        Ok(self.to_hex().into())
+    }
+}
+
 impl VRFSeed {
     /// First-ever VRF seed from the genesis block.  It's all 0's
     pub fn initial() -> VRFSeed {
@@ -13,6 +13,7 @@ use crate::address::{
 use crate::address::c32::c32_address;
 use crate::address::c32::c32_address_decode;
 use crate::address::AddressHashMode;
+use crate::consts::{CHAIN_ID_MAINNET, CHAIN_ID_TESTNET};
 use crate::deps_common::bitcoin::blockdata::transaction::TxOut;
 use crate::types::chainstate::StacksAddress;
 use crate::util::hash::Hash160;
@@ -95,6 +96,21 @@ impl StacksEpochId {
             StacksEpochId::Epoch24 => true,
         }
     }
+
+    /// Whether or not this epoch can record the fee rebates transactions would have received
+    /// under a uniform-price fee market, on nodes that opt in.  The rebates are only recorded,
+    /// never paid, so this does not affect consensus.
+    pub fn fee_rebate_accounting(&self) -> bool {
+        match self {
+            StacksEpochId::Epoch10
+            | StacksEpochId::Epoch20
+            | StacksEpochId::Epoch2_05
+            | StacksEpochId::Epoch21
+            | StacksEpochId::Epoch22
+            | StacksEpochId::Epoch23 => false,
+            StacksEpochId::Epoch24 => true,
+        }
+    }
 }
 
 impl std::fmt::Display for StacksEpochId {
@@ -250,6 +266,101 @@ impl Address for StacksAddress {
     }
 }
 
+/// The parameters that tell one Stacks network apart from another: the chain ID that
+/// transactions are signed for, the magic that peers use to recognize each other, the
+/// address versions that accounts are encoded with, and the address that boot contracts
+/// are published under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NetworkId {
+    /// Whether or not this network follows mainnet consensus rules
+    pub mainnet: bool,
+    /// The chain ID that transactions are signed for
+    pub chain_id: u32,
+    /// The network ID that peers exchange in message preambles
+    pub peer_network_id: u32,
+    pub address_version_singlesig: u8,
+    pub address_version_multisig: u8,
+    pub boot_address: StacksAddress,
+}
+
+impl NetworkId {
+    pub fn mainnet() -> NetworkId {
+        NetworkId {
+            mainnet: true,
+            chain_id: CHAIN_ID_MAINNET,
+            peer_network_id: CHAIN_ID_MAINNET,
+            address_version_singlesig: C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
+            address_version_multisig: C32_ADDRESS_VERSION_MAINNET_MULTISIG,
+            boot_address: StacksAddress::burn_address(true),
+        }
+    }
+
+    pub fn testnet() -> NetworkId {
+        NetworkId {
+            mainnet: false,
+            chain_id: CHAIN_ID_TESTNET,
+            peer_network_id: CHAIN_ID_TESTNET,
+            address_version_singlesig: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            address_version_multisig: C32_ADDRESS_VERSION_TESTNET_MULTISIG,
+            boot_address: StacksAddress::burn_address(false),
+        }
+    }
+
+    /// The stock mainnet or testnet parameters, for code that still only knows whether or
+    /// not it is running on mainnet.
+    pub fn from_mainnet(mainnet: bool) -> NetworkId {
+        if mainnet {
+            NetworkId::mainnet()
+        } else {
+            NetworkId::testnet()
+        }
+    }
+
+    /// The stock mainnet or testnet parameters, but with the given chain ID (which peers also
+    /// use to recognize each other).
+    pub fn from_chain_id(mainnet: bool, chain_id: u32) -> NetworkId {
+        NetworkId {
+            chain_id,
+            peer_network_id: chain_id,
+            ..NetworkId::from_mainnet(mainnet)
+        }
+    }
+
+    /// A network that follows testnet consensus rules, but with its own chain ID and address
+    /// versions.  Its boot contracts live at the burn address of its singlesig version.
+    pub fn custom(
+        chain_id: u32,
+        address_version_singlesig: u8,
+        address_version_multisig: u8,
+    ) -> NetworkId {
+        NetworkId {
+            mainnet: false,
+            chain_id,
+            peer_network_id: chain_id,
+            address_version_singlesig,
+            address_version_multisig,
+            boot_address: StacksAddress {
+                version: address_version_singlesig,
+                bytes: Hash160([0u8; 20]),
+            },
+        }
+    }
+
+    pub fn is_mainnet(&self) -> bool {
+        self.mainnet
+    }
+
+    /// Is this one of this network's address versions?
+    pub fn is_network_version(&self, version: u8) -> bool {
+        version == self.address_version_singlesig || version == self.address_version_multisig
+    }
+
+    /// Is this address encoded with one of this network's address versions?
+    pub fn is_network_address(&self, addr: &StacksAddress) -> bool {
+        self.is_network_version(addr.version)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
 pub struct StacksEpoch<L> {
     pub epoch_id: StacksEpochId,
@@ -21,7 +21,6 @@ use std::fmt::Write;
 use std::mem;
 
 use crate::util::log;
-use crate::util::pair::*;
 use crate::util::secp256k1::Secp256k1PublicKey;
 use crate::util::HexError;
 
@@ -33,8 +32,6 @@ use crate::util::uint::Uint256;
 
 use crate::types::StacksPublicKeyBuffer;
 
-use serde::de::Deserialize;
-use serde::de::Error as de_Error;
 use serde::ser::Error as ser_Error;
 use serde::Serialize;
 
@@ -49,77 +46,21 @@ pub trait MerkleHashFunc {
     fn bits(&self) -> &[u8];
 }
 
-macro_rules! impl_serde_json_hex_string {
-    ($name:ident, $len:expr) => {
-        pub struct $name {}
-        impl $name {
-            pub fn json_serialize<S: serde::Serializer>(
-                inst: &[u8; $len],
-                s: S,
-            ) -> Result<S::Ok, S::Error> {
-                let hex_inst = to_hex(inst);
-                s.serialize_str(&hex_inst.as_str())
-            }
-
-            pub fn json_deserialize<'de, D: serde::Deserializer<'de>>(
-                d: D,
-            ) -> Result<[u8; $len], D::Error> {
-                let hex_inst = String::deserialize(d)?;
-                let inst_bytes = hex_bytes(&hex_inst).map_err(de_Error::custom)?;
-
-                match inst_bytes.len() {
-                    $len => {
-                        let mut byte_slice = [0u8; $len];
-                        byte_slice.copy_from_slice(&inst_bytes);
-                        Ok(byte_slice)
-                    }
-                    _ => Err(de_Error::custom(format!(
-                        "Invalid hex string -- not {} bytes",
-                        $len
-                    ))),
-                }
-            }
-        }
-    };
-}
-
-impl_serde_json_hex_string!(Hash20, 20);
-impl_serde_json_hex_string!(Hash32, 32);
-impl_serde_json_hex_string!(Hash64, 64);
-
 #[derive(Serialize, Deserialize)]
-pub struct Hash160(
-    #[serde(
-        serialize_with = "Hash20::json_serialize",
-        deserialize_with = "Hash20::json_deserialize"
-    )]
-    pub [u8; 20],
-);
+pub struct Hash160(#[serde(with = "crate::util::serde_hex")] pub [u8; 20]);
 impl_array_newtype!(Hash160, u8, 20);
 impl_array_hexstring_fmt!(Hash160);
 impl_byte_array_newtype!(Hash160, u8, 20);
 pub const HASH160_ENCODED_SIZE: u32 = 20;
 
 #[derive(Serialize, Deserialize)]
-pub struct Keccak256Hash(
-    #[serde(
-        serialize_with = "Hash32::json_serialize",
-        deserialize_with = "Hash32::json_deserialize"
-    )]
-    pub [u8; 32],
-);
+pub struct Keccak256Hash(#[serde(with = "crate::util::serde_hex")] pub [u8; 32]);
 impl_array_newtype!(Keccak256Hash, u8, 32);
 impl_array_hexstring_fmt!(Keccak256Hash);
 impl_byte_array_newtype!(Keccak256Hash, u8, 32);
 
 #[derive(Serialize, Deserialize)]
-pub struct Sha256Sum(
-    #[serde(
-        serialize_with = "Hash32::json_serialize",
-        deserialize_with = "Hash32::json_deserialize"
-    )]
-    pub [u8; 32],
-);
+pub struct Sha256Sum(#[serde(with = "crate::util::serde_hex")] pub [u8; 32]);
 impl_array_newtype!(Sha256Sum, u8, 32);
 impl_array_hexstring_fmt!(Sha256Sum);
 impl_byte_array_newtype!(Sha256Sum, u8, 32);
@@ -131,37 +72,19 @@ impl Default for Sha256Sum {
 }
 
 #[derive(Serialize, Deserialize)]
-pub struct Sha512Sum(
-    #[serde(
-        serialize_with = "Hash64::json_serialize",
-        deserialize_with = "Hash64::json_deserialize"
-    )]
-    pub [u8; 64],
-);
+pub struct Sha512Sum(#[serde(with = "crate::util::serde_hex")] pub [u8; 64]);
 impl_array_newtype!(Sha512Sum, u8, 64);
 impl_array_hexstring_fmt!(Sha512Sum);
 impl_byte_array_newtype!(Sha512Sum, u8, 64);
 
 #[derive(Serialize, Deserialize)]
-pub struct Sha512Trunc256Sum(
-    #[serde(
-        serialize_with = "Hash32::json_serialize",
-        deserialize_with = "Hash32::json_deserialize"
-    )]
-    pub [u8; 32],
-);
+pub struct Sha512Trunc256Sum(#[serde(with = "crate::util::serde_hex")] pub [u8; 32]);
 impl_array_newtype!(Sha512Trunc256Sum, u8, 32);
 impl_array_hexstring_fmt!(Sha512Trunc256Sum);
 impl_byte_array_newtype!(Sha512Trunc256Sum, u8, 32);
 
 #[derive(Serialize, Deserialize)]
-pub struct DoubleSha256(
-    #[serde(
-        serialize_with = "Hash32::json_serialize",
-        deserialize_with = "Hash32::json_deserialize"
-    )]
-    pub [u8; 32],
-);
+pub struct DoubleSha256(#[serde(with = "crate::util::serde_hex")] pub [u8; 32]);
 impl_array_newtype!(DoubleSha256, u8, 32);
 impl_array_hexstring_fmt!(DoubleSha256);
 impl_byte_array_newtype!(DoubleSha256, u8, 32);
@@ -611,31 +534,89 @@ where
     }
 }
 
-// borrowed from Andrew Poelstra's rust-bitcoin library
-/// Convert a hexadecimal-encoded string to its corresponding bytes
-pub fn hex_bytes(s: &str) -> Result<Vec<u8>, HexError> {
-    let mut v = vec![];
-    let mut iter = s.chars().pair();
-    // Do the parsing
-    iter.by_ref().fold(Ok(()), |e, (f, s)| {
-        if e.is_err() {
-            e
-        } else {
-            match (f.to_digit(16), s.to_digit(16)) {
-                (None, _) => Err(HexError::BadCharacter(f)),
-                (_, None) => Err(HexError::BadCharacter(s)),
-                (Some(f), Some(s)) => {
-                    v.push((f * 0x10 + s) as u8);
-                    Ok(())
-                }
-            }
+/// Lowercase hex digits, indexed by nibble
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Value of each byte as a hex digit, or 0xff if it is not one
+const HEX_VALUES: [u8; 256] = make_hex_values();
+
+const fn make_hex_values() -> [u8; 256] {
+    let mut values = [0xff; 256];
+    let mut i = 0;
+    while i < 10 {
+        values[b'0' as usize + i] = i as u8;
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 6 {
+        values[b'a' as usize + i] = 10 + i as u8;
+        values[b'A' as usize + i] = 10 + i as u8;
+        i += 1;
+    }
+    values
+}
+
+/// The error for the first non-hex-digit byte of `s`, at `bad_index`.  Every byte before it is
+/// an ASCII hex digit, so `bad_index` is also the index of the offending character.  A
+/// trailing unpaired character is a length error, whatever it is.
+fn hex_error(s: &str, bad_index: usize) -> HexError {
+    let num_chars = s.chars().count();
+    if bad_index < num_chars - num_chars % 2 {
+        HexError::BadCharacter(
+            s[bad_index..]
+                .chars()
+                .next()
+                .expect("BUG: bad hex index past end of string"),
+        )
+    } else {
+        HexError::BadLength(s.len())
+    }
+}
+
+/// Decode the hexadecimal-encoded string `s` into the start of `out`, without allocating, and
+/// return the number of bytes written.  Fails with `BadLength` if `s` has an odd number of
+/// characters or `out` is too short to hold its decoding.  `out` may be partially written on
+/// error.
+pub fn hex_bytes_into(s: &str, out: &mut [u8]) -> Result<usize, HexError> {
+    let bytes = s.as_bytes();
+    let num_bytes = bytes.len() / 2;
+    if out.len() < num_bytes {
+        return Err(HexError::BadLength(s.len()));
+    }
+    for (i, (pair, out_byte)) in bytes.chunks_exact(2).zip(out.iter_mut()).enumerate() {
+        let hi = HEX_VALUES[pair[0] as usize];
+        let lo = HEX_VALUES[pair[1] as usize];
+        if (hi | lo) > 0x0f {
+            let bad_index = if hi > 0x0f { 2 * i } else { 2 * i + 1 };
+            return Err(hex_error(s, bad_index));
         }
-    })?;
-    // Check that there was no remainder
-    match iter.remainder() {
-        Some(_) => Err(HexError::BadLength(s.len())),
-        None => Ok(v),
+        *out_byte = (hi << 4) | lo;
+    }
+    if bytes.len() % 2 != 0 {
+        return Err(HexError::BadLength(s.len()));
     }
+    Ok(num_bytes)
+}
+
+/// Convert a hexadecimal-encoded string to its corresponding bytes
+pub fn hex_bytes(s: &str) -> Result<Vec<u8>, HexError> {
+    let mut v = vec![0u8; s.len() / 2];
+    hex_bytes_into(s, &mut v)?;
+    Ok(v)
+}
+
+/// Convert a hexadecimal-encoded string of exactly `2 * N` characters to an `N`-byte array
+pub fn hex_bytes_exact<const N: usize>(s: &str) -> Result<[u8; N], HexError> {
+    if s.len() != 2 * N {
+        // report a bad character in preference to a bad length, like `hex_bytes`
+        return Err(match hex_bytes(s) {
+            Err(e) => e,
+            Ok(_) => HexError::BadLength(s.len()),
+        });
+    }
+    let mut out = [0u8; N];
+    hex_bytes_into(s, &mut out)?;
+    Ok(out)
 }
 
 /// Convert a binary-encoded string to its corresponding bytes
@@ -661,13 +642,20 @@ pub fn bin_bytes(s: &str) -> Result<Vec<u8>, HexError> {
     Ok(v)
 }
 
+/// Append the lowercase hex encoding of a slice of u8 to `out`
+pub fn to_hex_into(s: &[u8], out: &mut String) {
+    out.reserve(s.len() * 2);
+    for b in s.iter() {
+        out.push(HEX_DIGITS[(b >> 4) as usize] as char);
+        out.push(HEX_DIGITS[(b & 0x0f) as usize] as char);
+    }
+}
+
 /// Convert a slice of u8 to a hex string
 pub fn to_hex(s: &[u8]) -> String {
     let mut r = String::with_capacity(s.len() * 2);
-    for b in s.iter() {
-        write!(r, "{:02x}", b).unwrap();
-    }
-    return r;
+    to_hex_into(s, &mut r);
+    r
 }
 
 /// Convert a slice of u8 into a binary string
@@ -688,11 +676,16 @@ pub fn bytes_to_hex(s: &[u8]) -> String {
 mod test {
     use super::bin_bytes;
     use super::hex_bytes;
+    use super::hex_bytes_exact;
+    use super::hex_bytes_into;
     use super::to_bin;
+    use super::to_hex;
+    use super::to_hex_into;
     use super::DoubleSha256;
     use super::MerkleHashFunc;
     use super::MerklePath;
     use super::MerkleTree;
+    use crate::util::HexError;
 
     struct MerkleTreeFixture {
         data: Vec<Vec<u8>>,
@@ -850,4 +843,49 @@ mod test {
         assert_eq!(bin_bytes("").unwrap().len(), 0);
         assert!(bin_bytes("2").is_err());
     }
+
+    #[test]
+    fn test_hex_str_roundtrip() {
+        let all_bytes: Vec<u8> = (0..=255).collect();
+        let hex = to_hex(&all_bytes);
+        assert_eq!(hex.len(), 512);
+        assert_eq!(&hex[..8], "00010203");
+        assert_eq!(&hex[504..], "fcfdfeff");
+        assert_eq!(hex_bytes(&hex).unwrap(), all_bytes);
+        assert_eq!(hex_bytes(&hex.to_uppercase()).unwrap(), all_bytes);
+
+        let mut out = "0x".to_string();
+        to_hex_into(&[0xde, 0xad], &mut out);
+        assert_eq!(out, "0xdead");
+
+        let mut buf = [0u8; 4];
+        assert_eq!(hex_bytes_into("beef", &mut buf), Ok(2));
+        assert_eq!(&buf[..2], &[0xbe, 0xef]);
+        assert_eq!(
+            hex_bytes_into("beefbeefbe", &mut buf),
+            Err(HexError::BadLength(10))
+        );
+
+        assert_eq!(hex_bytes("").unwrap().len(), 0);
+        assert_eq!(hex_bytes("abc"), Err(HexError::BadLength(3)));
+        assert_eq!(hex_bytes("abz"), Err(HexError::BadLength(3)));
+        assert_eq!(hex_bytes("zbcd"), Err(HexError::BadCharacter('z')));
+        assert_eq!(hex_bytes("abcg"), Err(HexError::BadCharacter('g')));
+        assert_eq!(hex_bytes("a\u{e9}"), Err(HexError::BadCharacter('\u{e9}')));
+        assert_eq!(hex_bytes("\u{e9}"), Err(HexError::BadLength(2)));
+    }
+
+    #[test]
+    fn test_hex_bytes_exact() {
+        assert_eq!(hex_bytes_exact::<2>("beef"), Ok([0xbe, 0xef]));
+        assert_eq!(hex_bytes_exact::<0>(""), Ok([]));
+        assert_eq!(hex_bytes_exact::<2>("beefbe"), Err(HexError::BadLength(6)));
+        assert_eq!(hex_bytes_exact::<2>("bee"), Err(HexError::BadLength(3)));
+        assert_eq!(hex_bytes_exact::<2>("be"), Err(HexError::BadLength(2)));
+        assert_eq!(
+            hex_bytes_exact::<2>("bexf"),
+            Err(HexError::BadCharacter('x'))
+        );
+        assert_eq!(hex_bytes_exact::<2>("bx"), Err(HexError::BadCharacter('x')));
+    }
 }
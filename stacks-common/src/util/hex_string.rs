@@ -0,0 +1,187 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::de::Error as de_Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::util::hash::{hex_bytes_exact, to_hex};
+use crate::util::HexError;
+
+/// A fixed-length byte string whose textual form -- when displayed, serialized with serde, or
+/// stored in a sqlite column -- is lowercase hex.
+///
+/// This is what byte-array newtypes like `Hash160`, `BlockHeaderHash`, and `ConsensusHash` use for
+/// their hex encoding; `impl_byte_array_newtype!`, `impl_byte_array_serde!`, and
+/// `impl_byte_array_rusqlite_only!` all delegate to it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct HexString<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> HexString<N> {
+    /// Parse exactly `2 * N` hex characters
+    pub fn from_hex(hex_str: &str) -> Result<HexString<N>, HexError> {
+        hex_bytes_exact::<N>(hex_str).map(HexString)
+    }
+
+    /// Instantiate from exactly `N` bytes
+    pub fn from_bytes(bytes: &[u8]) -> Option<HexString<N>> {
+        if bytes.len() != N {
+            return None;
+        }
+        let mut ret = [0u8; N];
+        ret.copy_from_slice(bytes);
+        Some(HexString(ret))
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.0)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> fmt::Display for HexString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for b in self.0.iter() {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> fmt::Debug for HexString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<const N: usize> FromStr for HexString<N> {
+    type Err = HexError;
+
+    fn from_str(s: &str) -> Result<HexString<N>, HexError> {
+        HexString::from_hex(s)
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for HexString<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for HexString<N> {
+    fn from(bytes: [u8; N]) -> HexString<N> {
+        HexString(bytes)
+    }
+}
+
+impl<const N: usize> Serialize for HexString<N> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for HexString<N> {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<HexString<N>, D::Error> {
+        let hex_str = String::deserialize(d)?;
+        HexString::from_hex(&hex_str).map_err(de_Error::custom)
+    }
+}
+
+impl<const N: usize> FromSql for HexString<N> {
+    fn column_result(value: ValueRef) -> FromSqlResult<HexString<N>> {
+        let hex_str = value.as_str()?;
+        HexString::from_hex(hex_str).map_err(|_e| FromSqlError::InvalidType)
+    }
+}
+
+impl<const N: usize> ToSql for HexString<N> {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput> {
+        Ok(self.to_hex().into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusqlite::{Connection, NO_PARAMS};
+
+    #[test]
+    fn test_hex_string_parse_and_display() {
+        let h = HexString::<4>::from_hex("0123abcd").unwrap();
+        assert_eq!(h, HexString([0x01, 0x23, 0xab, 0xcd]));
+        assert_eq!(h.to_hex(), "0123abcd");
+        assert_eq!(format!("{}", &h), "0123abcd");
+        assert_eq!(format!("{:?}", &h), "0123abcd");
+        assert_eq!("0123abcd".parse::<HexString<4>>().unwrap(), h);
+
+        assert_eq!(
+            HexString::<4>::from_hex("0123ab"),
+            Err(HexError::BadLength(6))
+        );
+        assert_eq!(
+            HexString::<4>::from_hex("0123abcdef"),
+            Err(HexError::BadLength(10))
+        );
+        assert_eq!(
+            HexString::<4>::from_hex("0123abcg"),
+            Err(HexError::BadCharacter('g'))
+        );
+
+        assert_eq!(
+            HexString::<4>::from_bytes(&[1, 2, 3, 4]),
+            Some(HexString([1, 2, 3, 4]))
+        );
+        assert_eq!(HexString::<4>::from_bytes(&[1, 2, 3]), None);
+    }
+
+    #[test]
+    fn test_hex_string_serde() {
+        let h = HexString([0xde, 0xad, 0xbe, 0xef]);
+        let json = serde_json::to_string(&h).unwrap();
+        assert_eq!(json, "\"deadbeef\"");
+        assert_eq!(serde_json::from_str::<HexString<4>>(&json).unwrap(), h);
+        assert!(serde_json::from_str::<HexString<4>>("\"deadbe\"").is_err());
+        assert!(serde_json::from_str::<HexString<4>>("\"deadbeeg\"").is_err());
+    }
+
+    #[test]
+    fn test_hex_string_sql() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE t(h TEXT NOT NULL)", NO_PARAMS)
+            .unwrap();
+
+        let h = HexString([0xde, 0xad, 0xbe, 0xef]);
+        conn.execute("INSERT INTO t(h) VALUES (?1)", &[&h]).unwrap();
+        let stored: String = conn
+            .query_row("SELECT h FROM t", NO_PARAMS, |row| row.get(0))
+            .unwrap();
+        assert_eq!(stored, "deadbeef");
+        let loaded: HexString<4> = conn
+            .query_row("SELECT h FROM t", NO_PARAMS, |row| row.get(0))
+            .unwrap();
+        assert_eq!(loaded, h);
+
+        // wrong length
+        let res: Result<HexString<8>, _> =
+            conn.query_row("SELECT h FROM t", NO_PARAMS, |row| row.get(0));
+        assert!(res.is_err());
+    }
+}
@@ -445,19 +445,8 @@ macro_rules! impl_byte_array_newtype {
             /// Instantiates from a hex string
             #[allow(dead_code)]
             pub fn from_hex(hex_str: &str) -> Result<$thing, crate::util::HexError> {
-                use crate::util::hash::hex_bytes;
-                let _hex_len = $len * 2;
-                match (hex_str.len(), hex_bytes(hex_str)) {
-                    (_hex_len, Ok(bytes)) => {
-                        if bytes.len() != $len {
-                            return Err(crate::util::HexError::BadLength(hex_str.len()));
-                        }
-                        let mut ret = [0; $len];
-                        ret.copy_from_slice(&bytes);
-                        Ok($thing(ret))
-                    }
-                    (_, Err(e)) => Err(e),
-                }
+                crate::util::hex_string::HexString::<{ $len }>::from_hex(hex_str)
+                    .map(|h| $thing(h.0))
             }
 
             /// Instantiates from a slice of bytes
@@ -513,13 +502,15 @@ macro_rules! impl_byte_array_newtype {
             /// Convert to a hex string
             #[allow(dead_code)]
             pub fn to_hex(&self) -> String {
-                use crate::util::hash::to_hex;
-                to_hex(&self.0)
+                crate::util::hex_string::HexString::<{ $len }>::from(self.0).to_hex()
             }
         }
         impl std::fmt::Display for $thing {
             fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-                write!(f, "{}", self.to_hex())
+                std::fmt::Display::fmt(
+                    &crate::util::hex_string::HexString::<{ $len }>::from(self.0),
+                    f,
+                )
             }
         }
         impl std::convert::AsRef<[u8]> for $thing {
@@ -532,6 +523,16 @@ macro_rules! impl_byte_array_newtype {
                 Self(o)
             }
         }
+        impl std::convert::From<crate::util::hex_string::HexString<{ $len }>> for $thing {
+            fn from(o: crate::util::hex_string::HexString<{ $len }>) -> Self {
+                Self(o.0)
+            }
+        }
+        impl std::convert::From<$thing> for crate::util::hex_string::HexString<{ $len }> {
+            fn from(o: $thing) -> Self {
+                Self(o.0)
+            }
+        }
     };
 }
 
@@ -570,6 +571,29 @@ macro_rules! test_debug {
     )
 }
 
+/// Assert that the consensus serialization of `$value`, a `StacksMessageCodec`, matches the
+/// golden file `$name` of the calling module (see `util::snapshot`).  With `bytes = ...`, snapshot
+/// an already-serialized byte slice instead.
+#[macro_export]
+macro_rules! assert_wire_snapshot {
+    ($name:expr, bytes = $bytes:expr) => {
+        $crate::util::snapshot::check_snapshot(
+            &$crate::util::snapshot::snapshot_path(
+                concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots"),
+                module_path!(),
+                $name,
+            ),
+            &$bytes,
+        )
+    };
+    ($name:expr, $value:expr) => {
+        $crate::assert_wire_snapshot!(
+            $name,
+            bytes = $crate::codec::StacksMessageCodec::serialize_to_vec(&$value)
+        )
+    };
+}
+
 #[cfg(test)]
 pub const TRACE_ENABLED: bool = true;
 
@@ -623,6 +647,7 @@ macro_rules! fmax {
     }}
 }
 
+#[macro_export]
 macro_rules! impl_byte_array_rusqlite_only {
     ($thing:ident) => {
         impl rusqlite::types::FromSql for $thing {
@@ -630,11 +655,7 @@ macro_rules! impl_byte_array_rusqlite_only {
                 value: rusqlite::types::ValueRef,
             ) -> rusqlite::types::FromSqlResult<Self> {
                 let hex_str = value.as_str()?;
-                let byte_str = crate::util::hash::hex_bytes(hex_str)
-                    .map_err(|_e| rusqlite::types::FromSqlError::InvalidType)?;
-                let inst = $thing::from_bytes(&byte_str)
-                    .ok_or(rusqlite::types::FromSqlError::InvalidType)?;
-                Ok(inst)
+                $thing::from_hex(hex_str).map_err(|_e| rusqlite::types::FromSqlError::InvalidType)
             }
         }
 
@@ -19,11 +19,18 @@ pub mod log;
 #[macro_use]
 pub mod macros;
 pub mod hash;
+pub mod hex_string;
 pub mod pair;
 pub mod pipe;
 pub mod retry;
 pub mod secp256k1;
+pub mod serde_hex;
+pub mod serde_hex_prefixed;
+#[cfg(any(test, feature = "testing"))]
+pub mod snapshot;
+pub mod telemetry;
 pub mod uint;
+pub mod ustx;
 pub mod vrf;
 
 use std::error;
@@ -56,7 +63,7 @@ pub fn sleep_ms(millis: u64) -> () {
 /// Hex deserialization error
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum HexError {
-    /// Length was not 64 characters
+    /// Odd length, or the wrong length for the target
     BadLength(usize),
     /// Non-hex character in string
     BadCharacter(char),
@@ -65,8 +72,8 @@ pub enum HexError {
 impl fmt::Display for HexError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            HexError::BadLength(n) => write!(f, "bad length {} for sha256d hex string", n),
-            HexError::BadCharacter(c) => write!(f, "bad character {} in sha256d hex string", c),
+            HexError::BadLength(n) => write!(f, "bad length {} for hex string", n),
+            HexError::BadCharacter(c) => write!(f, "bad character {} in hex string", c),
         }
     }
 }
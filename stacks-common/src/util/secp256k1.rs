@@ -37,8 +37,24 @@ use serde::Serialize;
 use rand::thread_rng;
 use rand::RngCore;
 
-// per-thread Secp256k1 context
-thread_local!(static _secp256k1: Secp256k1<secp256k1::All> = Secp256k1::new());
+lazy_static! {
+    /// Process-wide Secp256k1 context, shared by every signing, verification, and recovery
+    /// operation in this module.  Contexts are immutable once built and are `Send + Sync`, so
+    /// there is no need to build one per thread -- short-lived threads (such as RPC and
+    /// relayer workers) would otherwise pay for allocating a fresh context on first use.
+    ///
+    /// Note that ECDSA has no batch verification scheme, so signatures are always verified one
+    /// at a time against this context.
+    static ref SECP256K1_CONTEXT: Secp256k1<secp256k1::All> = Secp256k1::new();
+}
+
+/// Run `f` with the shared Secp256k1 context
+pub fn with_secp256k1_context<F, R>(f: F) -> R
+where
+    F: FnOnce(&Secp256k1<secp256k1::All>) -> R,
+{
+    f(&SECP256K1_CONTEXT)
+}
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub struct Secp256k1PublicKey {
@@ -139,7 +155,7 @@ impl Secp256k1PublicKey {
     }
 
     pub fn from_private(privk: &Secp256k1PrivateKey) -> Secp256k1PublicKey {
-        _secp256k1.with(|ctx| {
+        with_secp256k1_context(|ctx| {
             let pubk = LibSecp256k1PublicKey::from_secret_key(&ctx, &privk.key);
             Secp256k1PublicKey {
                 key: pubk,
@@ -169,7 +185,7 @@ impl Secp256k1PublicKey {
         msg: &[u8],
         sig: &MessageSignature,
     ) -> Result<Secp256k1PublicKey, &'static str> {
-        _secp256k1.with(|ctx| {
+        with_secp256k1_context(|ctx| {
             let msg = LibSecp256k1Message::from_slice(msg).map_err(|_e| {
                 "Invalid message: failed to decode data hash: must be a 32-byte hash"
             })?;
@@ -195,7 +211,7 @@ impl Secp256k1PublicKey {
         msg: &LibSecp256k1Message,
         sig: &LibSecp256k1RecoverableSignature,
     ) -> Result<LibSecp256k1PublicKey, &'static str> {
-        _secp256k1.with(|ctx| {
+        with_secp256k1_context(|ctx| {
             ctx.recover_ecdsa(msg, sig)
                 .map_err(|_e| "Invalid signature: failed to recover public key")
         })
@@ -212,7 +228,7 @@ impl PublicKey for Secp256k1PublicKey {
     }
 
     fn verify(&self, data_hash: &[u8], sig: &MessageSignature) -> Result<bool, &'static str> {
-        _secp256k1.with(|ctx| {
+        with_secp256k1_context(|ctx| {
             let msg = LibSecp256k1Message::from_slice(data_hash).map_err(|_e| {
                 "Invalid message: failed to decode data hash: must be a 32-byte hash"
             })?;
@@ -325,7 +341,7 @@ impl PrivateKey for Secp256k1PrivateKey {
     }
 
     fn sign(&self, data_hash: &[u8]) -> Result<MessageSignature, &'static str> {
-        _secp256k1.with(|ctx| {
+        with_secp256k1_context(|ctx| {
             let msg = LibSecp256k1Message::from_slice(data_hash).map_err(|_e| {
                 "Invalid message: failed to decode data hash: must be a 32-byte hash"
             })?;
@@ -374,7 +390,7 @@ pub fn secp256k1_recover(
     message_arr: &[u8],
     serialized_signature_arr: &[u8],
 ) -> Result<[u8; 33], LibSecp256k1Error> {
-    _secp256k1.with(|ctx| {
+    with_secp256k1_context(|ctx| {
         let message = LibSecp256k1Message::from_slice(message_arr)?;
 
         let rec_id = LibSecp256k1RecoveryID::from_i32(serialized_signature_arr[64] as i32)?;
@@ -394,7 +410,7 @@ pub fn secp256k1_verify(
     serialized_signature_arr: &[u8],
     pubkey_arr: &[u8],
 ) -> Result<(), LibSecp256k1Error> {
-    _secp256k1.with(|ctx| {
+    with_secp256k1_context(|ctx| {
         let message = LibSecp256k1Message::from_slice(message_arr)?;
         let expanded_sig = LibSecp256k1Signature::from_compact(&serialized_signature_arr[..64])?; // ignore 65th byte if present
         let pubkey = LibSecp256k1PublicKey::from_slice(pubkey_arr)?;
@@ -510,6 +526,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_shared_context_across_threads() {
+        let privk = Secp256k1PrivateKey::new();
+        let pubk = Secp256k1PublicKey::from_private(&privk);
+        let data_hash = [0x11u8; 32];
+        let sig = privk.sign(&data_hash).unwrap();
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let sig = sig.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..16 {
+                        assert_eq!(pubk.verify(&data_hash, &sig), Ok(true));
+                        let recovered =
+                            Secp256k1PublicKey::recover_to_pubkey(&data_hash, &sig).unwrap();
+                        assert_eq!(recovered.to_bytes_compressed(), pubk.to_bytes_compressed());
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
     #[test]
     fn test_verify() {
         let _ctx: Secp256k1<secp256k1::All> = Secp256k1::new();
@@ -0,0 +1,140 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Serializes byte arrays as lowercase hex strings.  Use it on `[u8; N]` and `Vec<u8>` fields
+//! with `#[serde(with = "stacks_common::util::serde_hex")]`.  See `serde_hex_prefixed` for the
+//! `0x`-prefixed form.
+
+use serde::de::Error as de_Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::util::hash::{hex_bytes, hex_bytes_into, to_hex};
+use crate::util::HexError;
+
+/// Byte containers which can be decoded from a hex string
+pub trait FromHexBytes: Sized {
+    /// Decode `hex_str`, which must not have a `0x` prefix.  Fixed-length containers fail with
+    /// `HexError::BadLength` unless `hex_str` encodes exactly as many bytes as they hold.
+    fn from_hex_bytes(hex_str: &str) -> Result<Self, HexError>;
+}
+
+impl<const N: usize> FromHexBytes for [u8; N] {
+    fn from_hex_bytes(hex_str: &str) -> Result<[u8; N], HexError> {
+        if hex_str.len() != 2 * N {
+            return Err(HexError::BadLength(hex_str.len()));
+        }
+        let mut bytes = [0u8; N];
+        hex_bytes_into(hex_str, &mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl FromHexBytes for Vec<u8> {
+    fn from_hex_bytes(hex_str: &str) -> Result<Vec<u8>, HexError> {
+        hex_bytes(hex_str)
+    }
+}
+
+pub fn serialize<S: Serializer, T: AsRef<[u8]>>(bytes: &T, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&to_hex(bytes.as_ref()))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>, T: FromHexBytes>(d: D) -> Result<T, D::Error> {
+    let hex_str = String::deserialize(d)?;
+    T::from_hex_bytes(&hex_str).map_err(de_Error::custom)
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use crate::util::HexError;
+
+    use super::FromHexBytes;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct HexFields {
+        #[serde(with = "crate::util::serde_hex")]
+        fixed: [u8; 4],
+        #[serde(with = "crate::util::serde_hex")]
+        var: Vec<u8>,
+        #[serde(with = "crate::util::serde_hex_prefixed")]
+        prefixed: [u8; 2],
+    }
+
+    #[test]
+    fn test_serde_hex_roundtrip() {
+        let fields = HexFields {
+            fixed: [0x01, 0x23, 0xab, 0xff],
+            var: vec![0xde, 0xad, 0xbe, 0xef, 0x00],
+            prefixed: [0x0a, 0x0b],
+        };
+        let json = serde_json::to_string(&fields).unwrap();
+        assert_eq!(
+            json,
+            r#"{"fixed":"0123abff","var":"deadbeef00","prefixed":"0x0a0b"}"#
+        );
+        assert_eq!(serde_json::from_str::<HexFields>(&json).unwrap(), fields);
+
+        // the prefix is optional when deserializing, and hex digits may be uppercase
+        let unprefixed: HexFields =
+            serde_json::from_str(r#"{"fixed":"0123ABFF","var":"","prefixed":"0a0b"}"#).unwrap();
+        assert_eq!(unprefixed.fixed, fields.fixed);
+        assert_eq!(unprefixed.var, Vec::<u8>::new());
+        assert_eq!(unprefixed.prefixed, fields.prefixed);
+    }
+
+    #[test]
+    fn test_serde_hex_errors() {
+        // wrong length for a fixed-size array
+        assert_eq!(
+            <[u8; 4]>::from_hex_bytes("0123ab"),
+            Err(HexError::BadLength(6))
+        );
+        assert_eq!(
+            <[u8; 4]>::from_hex_bytes("0123abff00"),
+            Err(HexError::BadLength(10))
+        );
+        // odd length
+        assert_eq!(
+            Vec::<u8>::from_hex_bytes("012"),
+            Err(HexError::BadLength(3))
+        );
+        // bad characters
+        assert_eq!(
+            <[u8; 2]>::from_hex_bytes("01zz"),
+            Err(HexError::BadCharacter('z'))
+        );
+        // the raw form does not take a prefix
+        assert_eq!(
+            <[u8; 2]>::from_hex_bytes("0x01"),
+            Err(HexError::BadCharacter('x'))
+        );
+
+        assert!(serde_json::from_str::<HexFields>(
+            r#"{"fixed":"0123","var":"","prefixed":"0x0a0b"}"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<HexFields>(
+            r#"{"fixed":"0123abff","var":"","prefixed":"0x0a0b0c"}"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<HexFields>(
+            r#"{"fixed":"0123abff","var":"","prefixed":"0xzz0b"}"#
+        )
+        .is_err());
+    }
+}
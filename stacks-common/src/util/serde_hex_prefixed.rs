@@ -0,0 +1,37 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Like `serde_hex`, but serializes with a leading `0x`.  Deserialization accepts hex strings
+//! with or without the prefix.  Use it with
+//! `#[serde(with = "stacks_common::util::serde_hex_prefixed")]`.
+
+use serde::de::Error as de_Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::util::hash::to_hex_into;
+use crate::util::serde_hex::FromHexBytes;
+
+pub fn serialize<S: Serializer, T: AsRef<[u8]>>(bytes: &T, s: S) -> Result<S::Ok, S::Error> {
+    let mut hex_str = String::from("0x");
+    to_hex_into(bytes.as_ref(), &mut hex_str);
+    s.serialize_str(&hex_str)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>, T: FromHexBytes>(d: D) -> Result<T, D::Error> {
+    let hex_str = String::deserialize(d)?;
+    let unprefixed = hex_str.strip_prefix("0x").unwrap_or(&hex_str);
+    T::from_hex_bytes(unprefixed).map_err(de_Error::custom)
+}
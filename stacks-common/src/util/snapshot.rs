@@ -0,0 +1,133 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Golden-file snapshots of serialized structures.
+//!
+//! `assert_wire_snapshot!` serializes a structure and compares its hex encoding against a golden
+//! file checked into the calling crate, at `snapshots/<module path>/<name>.hex` (the module path
+//! leaves out the crate name).  A change to a wire format then shows up as a failing test and a
+//! diff to the golden file, instead of going unnoticed.
+//!
+//! To create or update golden files, run the tests with `STACKS_UPDATE_SNAPSHOTS=1`, and commit
+//! the files that changed.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::util::hash::to_hex;
+
+/// Set this environment variable to `1` to write golden files instead of checking them
+pub const UPDATE_SNAPSHOTS_ENV: &str = "STACKS_UPDATE_SNAPSHOTS";
+
+/// Number of bytes per line of a golden file
+const BYTES_PER_LINE: usize = 32;
+
+/// The golden file for the snapshot `name`, taken in the module `module_path`
+pub fn snapshot_path(snapshot_dir: &str, module_path: &str, name: &str) -> PathBuf {
+    let mut path = PathBuf::from(snapshot_dir);
+    for component in module_path.split("::").skip(1) {
+        path.push(component);
+    }
+    path.push(format!("{}.hex", name));
+    path
+}
+
+/// Check `bytes` against the golden file at `path`, or (re)write the golden file if
+/// `STACKS_UPDATE_SNAPSHOTS=1`.
+pub fn check_snapshot(path: &Path, bytes: &[u8]) {
+    let update = env::var(UPDATE_SNAPSHOTS_ENV) == Ok("1".to_string());
+    check_or_update_snapshot(path, bytes, update);
+}
+
+fn check_or_update_snapshot(path: &Path, bytes: &[u8], update: bool) {
+    if update {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).unwrap_or_else(|e| {
+                panic!("Failed to create snapshot dir {}: {:?}", dir.display(), e)
+            });
+        }
+        fs::write(path, encode(bytes))
+            .unwrap_or_else(|e| panic!("Failed to write snapshot {}: {:?}", path.display(), e));
+        return;
+    }
+
+    let golden = fs::read_to_string(path).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read snapshot {} ({:?}); run with {}=1 to create it",
+            path.display(),
+            e,
+            UPDATE_SNAPSHOTS_ENV
+        )
+    });
+    assert_eq!(
+        golden,
+        encode(bytes),
+        "Serialization does not match snapshot {}; if the change is intended, run with {}=1 and commit the new snapshot",
+        path.display(),
+        UPDATE_SNAPSHOTS_ENV
+    );
+}
+
+/// Hex-encode `bytes`, `BYTES_PER_LINE` to a line, so that golden files diff well
+fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2 + bytes.len() / BYTES_PER_LINE + 1);
+    for line in bytes.chunks(BYTES_PER_LINE) {
+        out.push_str(&to_hex(line));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_path() {
+        assert_eq!(
+            snapshot_path(
+                "/src/snapshots",
+                "blockstack_lib::net::codec::test",
+                "preamble"
+            ),
+            PathBuf::from("/src/snapshots/net/codec/test/preamble.hex")
+        );
+    }
+
+    #[test]
+    fn test_check_or_update_snapshot() {
+        let mut path = env::temp_dir();
+        path.push(format!("snapshot-test-{}", std::process::id()));
+        path.push("thing.hex");
+
+        let bytes: Vec<u8> = (0..40).collect();
+        check_or_update_snapshot(&path, &bytes, true);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\n2021222324252627\n"
+        );
+        check_or_update_snapshot(&path, &bytes, false);
+
+        let changed =
+            std::panic::catch_unwind(|| check_or_update_snapshot(&path, &bytes[1..], false));
+        assert!(changed.is_err());
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        let missing = std::panic::catch_unwind(|| check_or_update_snapshot(&path, &bytes, false));
+        assert!(missing.is_err());
+    }
+}
@@ -0,0 +1,39 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Stable identifiers for error variants, so that metrics and logs can count errors by category.
+//!
+//! Error enums get this by deriving `StacksError` (see the `clarity-derive` crate).  Each enum
+//! owns a block of codes, and a variant's code never changes once assigned, even if the variant
+//! is renamed or reordered:
+//!
+//! | Codes       | Enum                                        | Label prefix |
+//! |-------------|---------------------------------------------|--------------|
+//! | 1000 - 1099 | `clarity::vm::errors::Error`                | `vm`         |
+//! | 1100 - 1199 | `clarity::vm::errors::RuntimeErrorType`     | `vm_runtime` |
+//! | 2000 - 2999 | `blockstack_lib::chainstate::stacks::Error` | `chainstate` |
+//! | 3000 - 3999 | `blockstack_lib::net::Error`                | `net`        |
+//!
+//! When removing a variant, retire its code rather than reusing it.
+
+use std::error;
+
+pub trait ErrorTelemetry: error::Error {
+    /// This error's numeric code, which is unique across the workspace and stable across releases
+    fn code(&self) -> u32;
+
+    /// A short snake_case name for this error's category, for use as a metric label
+    fn metric_label(&self) -> &'static str;
+}
@@ -0,0 +1,168 @@
+// Copyright (C) 2023 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Arithmetic on microSTX amounts.
+//!
+//! Fees, rewards, and locked amounts are all denominated in microSTX and carried as `u128`s.
+//! Quantities that are stored as `u64`s (such as burnchain burns) are widened to `u128` before
+//! any arithmetic is done on them.  Every operation here either reports overflow, underflow, and
+//! division by zero as a `UstxError`, or saturates -- none of them wrap or panic.
+
+use std::error;
+use std::fmt;
+
+/// An arithmetic error on a microSTX amount
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum UstxError {
+    /// The result would exceed `u128::MAX`
+    Overflow,
+    /// The result would be negative
+    Underflow,
+    /// The divisor was zero
+    DivisionByZero,
+}
+
+impl fmt::Display for UstxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UstxError::Overflow => write!(f, "microSTX amount overflowed"),
+            UstxError::Underflow => write!(f, "microSTX amount underflowed"),
+            UstxError::DivisionByZero => write!(f, "microSTX amount divided by zero"),
+        }
+    }
+}
+
+impl error::Error for UstxError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        None
+    }
+}
+
+/// `a + b`
+pub fn checked_add(a: u128, b: u128) -> Result<u128, UstxError> {
+    a.checked_add(b).ok_or(UstxError::Overflow)
+}
+
+/// `a - b`
+pub fn checked_sub(a: u128, b: u128) -> Result<u128, UstxError> {
+    a.checked_sub(b).ok_or(UstxError::Underflow)
+}
+
+/// `a * b`
+pub fn checked_mul(a: u128, b: u128) -> Result<u128, UstxError> {
+    a.checked_mul(b).ok_or(UstxError::Overflow)
+}
+
+/// `a / b`, rounded down
+pub fn checked_div(a: u128, b: u128) -> Result<u128, UstxError> {
+    a.checked_div(b).ok_or(UstxError::DivisionByZero)
+}
+
+/// `a + b`, or `u128::MAX` if that overflows
+pub fn saturating_add(a: u128, b: u128) -> u128 {
+    a.saturating_add(b)
+}
+
+/// `a - b`, or 0 if that underflows
+pub fn saturating_sub(a: u128, b: u128) -> u128 {
+    a.saturating_sub(b)
+}
+
+/// The sum of `amounts`
+pub fn checked_sum<I>(amounts: I) -> Result<u128, UstxError>
+where
+    I: IntoIterator,
+    I::Item: Into<u128>,
+{
+    amounts
+        .into_iter()
+        .try_fold(0u128, |total, amount| checked_add(total, amount.into()))
+}
+
+/// The sum of `amounts`, or `u128::MAX` if that overflows
+pub fn saturating_sum<I>(amounts: I) -> u128
+where
+    I: IntoIterator,
+    I::Item: Into<u128>,
+{
+    amounts
+        .into_iter()
+        .fold(0u128, |total, amount| saturating_add(total, amount.into()))
+}
+
+/// `amount * numerator / denominator`, rounded down.  This is how a share of `amount` that is
+/// proportional to `numerator / denominator` is calculated.  The multiplication happens first,
+/// so it must not overflow even if the quotient would fit.
+pub fn checked_mul_div(
+    amount: u128,
+    numerator: u128,
+    denominator: u128,
+) -> Result<u128, UstxError> {
+    checked_div(checked_mul(amount, numerator)?, denominator)
+}
+
+/// `percent`% of `amount`, rounded down
+pub fn checked_percent(amount: u128, percent: u128) -> Result<u128, UstxError> {
+    checked_mul_div(amount, percent, 100)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_checked_ops() {
+        assert_eq!(checked_add(1, 2), Ok(3));
+        assert_eq!(checked_add(u128::MAX, 1), Err(UstxError::Overflow));
+        assert_eq!(checked_sub(3, 2), Ok(1));
+        assert_eq!(checked_sub(2, 3), Err(UstxError::Underflow));
+        assert_eq!(checked_mul(3, 2), Ok(6));
+        assert_eq!(checked_mul(u128::MAX, 2), Err(UstxError::Overflow));
+        assert_eq!(checked_div(7, 2), Ok(3));
+        assert_eq!(checked_div(7, 0), Err(UstxError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_saturating_ops() {
+        assert_eq!(saturating_add(1, 2), 3);
+        assert_eq!(saturating_add(u128::MAX, 1), u128::MAX);
+        assert_eq!(saturating_sub(3, 2), 1);
+        assert_eq!(saturating_sub(2, 3), 0);
+    }
+
+    #[test]
+    fn test_sums() {
+        assert_eq!(checked_sum(Vec::<u128>::new()), Ok(0));
+        assert_eq!(checked_sum(vec![1u64, 2, 3]), Ok(6));
+        assert_eq!(
+            checked_sum(vec![u64::MAX, u64::MAX]),
+            Ok(2 * (u64::MAX as u128))
+        );
+        assert_eq!(checked_sum(vec![u128::MAX, 1]), Err(UstxError::Overflow));
+        assert_eq!(saturating_sum(vec![1u128, 2, 3]), 6);
+        assert_eq!(saturating_sum(vec![u128::MAX, 1]), u128::MAX);
+    }
+
+    #[test]
+    fn test_proportional_shares() {
+        assert_eq!(checked_mul_div(1000, 1, 3), Ok(333));
+        assert_eq!(checked_mul_div(1000, 2, 3), Ok(666));
+        assert_eq!(checked_mul_div(1000, 0, 3), Ok(0));
+        assert_eq!(checked_mul_div(1000, 1, 0), Err(UstxError::DivisionByZero));
+        assert_eq!(checked_mul_div(u128::MAX, 2, 2), Err(UstxError::Overflow));
+        assert_eq!(checked_percent(1000, 10), Ok(100));
+        assert_eq!(checked_percent(999, 10), Ok(99));
+    }
+}
@@ -0,0 +1,384 @@
+//! Operator alerting.
+//!
+//! When the `[alerts]` config section lists one or more webhooks, the node checks a few health
+//! conditions every `check_interval_secs` and POSTs a JSON `AlertNotice` to each webhook when a
+//! condition starts firing, and again when it resolves.  A condition which keeps firing is only
+//! reported once.  Each condition is enabled by setting its threshold:
+//!
+//! * `no_sortition_won_blocks`: this node's miner has not won a sortition in this many burnchain
+//! blocks
+//! * `tip_stall_secs`: the Stacks chain tip has not advanced in this many seconds
+//! * `marf_flush_ms`: flushing the last processed block's MARF writes took longer than this
+//! * `observer_backlog`: more than this many payloads are waiting to be delivered to event
+//! observers
+//!
+//! Webhooks must be `http://` URLs.  Each notice is sent once, and is not retried.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use async_h1::client;
+use async_std::net::TcpStream;
+use http_types::{Method, Request, Url};
+
+use stacks::monitoring::{
+    get_burnchain_height, get_last_marf_flush_time_ms, get_last_sortition_won_height,
+    get_stacks_tip_height, NetworkMetrics,
+};
+use stacks_common::util::{get_epoch_time_secs, sleep_ms};
+
+use crate::config::AlertConfig;
+use crate::event_dispatcher::get_pending_observer_payloads;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    NoSortitionWon,
+    TipStalled,
+    SlowMarfFlush,
+    ObserverBacklog,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertStatus {
+    Firing,
+    Resolved,
+}
+
+/// Body of a webhook request
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlertNotice {
+    pub alert: AlertKind,
+    pub status: AlertStatus,
+    /// the observed value of the condition's signal, and the threshold it was compared against
+    pub value: u64,
+    pub threshold: u64,
+    pub message: String,
+    pub timestamp: u64,
+}
+
+/// The node health signals which the alert conditions are evaluated on
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeSignals {
+    pub burn_height: u64,
+    pub stacks_tip_height: u64,
+    /// None if this node is not mining.  Some(0) if its miner has not won a sortition yet.
+    pub last_sortition_won_height: Option<u64>,
+    pub last_marf_flush_ms: u64,
+    pub pending_observer_payloads: u64,
+}
+
+impl NodeSignals {
+    fn current(is_miner: bool) -> NodeSignals {
+        NodeSignals {
+            burn_height: get_burnchain_height(),
+            stacks_tip_height: get_stacks_tip_height(),
+            last_sortition_won_height: if is_miner {
+                Some(get_last_sortition_won_height())
+            } else {
+                None
+            },
+            last_marf_flush_ms: get_last_marf_flush_time_ms(),
+            pending_observer_payloads: get_pending_observer_payloads(),
+        }
+    }
+}
+
+/// Evaluates the alert conditions, and remembers which ones are firing
+pub struct AlertMonitor {
+    config: AlertConfig,
+    /// burnchain height when the monitor first saw the burnchain.  A miner which has never won a
+    /// sortition is considered to have last won here.
+    first_burn_height: Option<u64>,
+    /// the Stacks tip height, and the time at which the monitor first saw it
+    last_tip: Option<(u64, u64)>,
+    firing: HashSet<AlertKind>,
+}
+
+impl AlertMonitor {
+    pub fn new(config: AlertConfig) -> AlertMonitor {
+        AlertMonitor {
+            config,
+            first_burn_height: None,
+            last_tip: None,
+            firing: HashSet::new(),
+        }
+    }
+
+    /// Evaluate every enabled condition on `signals`, observed at `now` (in seconds).
+    /// Returns a notice for each condition which started firing or resolved since the last check.
+    pub fn check(&mut self, now: u64, signals: &NodeSignals) -> Vec<AlertNotice> {
+        // (alert, observed value, threshold, firing, description)
+        let mut conditions = vec![];
+
+        if self.first_burn_height.is_none() && signals.burn_height > 0 {
+            self.first_burn_height = Some(signals.burn_height);
+        }
+        if let (Some(threshold), Some(last_won), Some(first_burn_height)) = (
+            self.config.no_sortition_won_blocks,
+            signals.last_sortition_won_height,
+            self.first_burn_height,
+        ) {
+            let blocks = signals
+                .burn_height
+                .saturating_sub(last_won.max(first_burn_height));
+            conditions.push((
+                AlertKind::NoSortitionWon,
+                blocks,
+                threshold,
+                blocks >= threshold,
+                format!("No sortition won in the last {} burnchain blocks", blocks),
+            ));
+        }
+
+        match self.last_tip {
+            Some((height, _)) if height == signals.stacks_tip_height => {}
+            _ => {
+                self.last_tip = Some((signals.stacks_tip_height, now));
+            }
+        }
+        if let (Some(threshold), Some((height, since))) =
+            (self.config.tip_stall_secs, self.last_tip)
+        {
+            let stalled_secs = now.saturating_sub(since);
+            conditions.push((
+                AlertKind::TipStalled,
+                stalled_secs,
+                threshold,
+                stalled_secs >= threshold,
+                format!(
+                    "Stacks chain tip has been at height {} for {} seconds",
+                    height, stalled_secs
+                ),
+            ));
+        }
+
+        if let Some(threshold) = self.config.marf_flush_ms {
+            conditions.push((
+                AlertKind::SlowMarfFlush,
+                signals.last_marf_flush_ms,
+                threshold,
+                signals.last_marf_flush_ms > threshold,
+                format!(
+                    "Flushing the last block's MARF writes took {} ms",
+                    signals.last_marf_flush_ms
+                ),
+            ));
+        }
+
+        if let Some(threshold) = self.config.observer_backlog {
+            conditions.push((
+                AlertKind::ObserverBacklog,
+                signals.pending_observer_payloads,
+                threshold,
+                signals.pending_observer_payloads > threshold,
+                format!(
+                    "{} payloads are waiting to be delivered to event observers",
+                    signals.pending_observer_payloads
+                ),
+            ));
+        }
+
+        let mut notices = vec![];
+        for (alert, value, threshold, firing, message) in conditions.into_iter() {
+            let status = if firing && self.firing.insert(alert) {
+                AlertStatus::Firing
+            } else if !firing && self.firing.remove(&alert) {
+                AlertStatus::Resolved
+            } else {
+                continue;
+            };
+            notices.push(AlertNotice {
+                alert,
+                status,
+                value,
+                threshold,
+                message,
+                timestamp: now,
+            });
+        }
+        notices
+    }
+}
+
+/// POST a notice to a webhook.  Failures are logged, and the notice is dropped.
+fn send_notice(webhook: &str, notice: &AlertNotice) {
+    let url = match Url::parse(webhook) {
+        Ok(url) => url,
+        Err(e) => {
+            warn!("Alerts: invalid webhook URL"; "url" => webhook, "err" => %e);
+            return;
+        }
+    };
+    let addr = match (url.host_str(), url.port_or_known_default()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        _ => {
+            warn!("Alerts: webhook URL has no host"; "url" => webhook);
+            return;
+        }
+    };
+    let body = serde_json::to_vec(notice).expect("FATAL: failed to serialize alert notice");
+
+    let mut req = Request::new(Method::Post, url.clone());
+    req.append_header("Content-Type", "application/json");
+    req.set_body(body);
+
+    let result = async_std::task::block_on(async {
+        let stream = TcpStream::connect(addr).await?;
+        client::connect(stream, req).await
+    });
+    match result {
+        Ok(response) if response.status().is_success() => {
+            debug!("Alerts: sent notice"; "url" => %url, "alert" => ?notice.alert);
+        }
+        Ok(response) => {
+            warn!("Alerts: webhook rejected notice"; "url" => %url, "status" => %response.status());
+        }
+        Err(e) => {
+            warn!("Alerts: failed to send notice"; "url" => %url, "err" => %e);
+        }
+    }
+}
+
+/// Start a thread which checks the alert conditions every `config.check_interval_secs`, and
+/// notifies every webhook of each change, until `should_keep_running` is cleared.
+pub fn start_alert_monitor(
+    config: AlertConfig,
+    is_miner: bool,
+    should_keep_running: Arc<AtomicBool>,
+) {
+    let metrics = NetworkMetrics::current();
+    thread::Builder::new()
+        .name("alerts".to_string())
+        .spawn(move || {
+            debug!("alerts thread ID is {:?}", thread::current().id());
+            metrics.enter();
+            let check_interval_secs = config.check_interval_secs.max(1);
+            let webhooks = config.webhooks.clone();
+            let mut monitor = AlertMonitor::new(config);
+            let mut last_check = 0;
+            while should_keep_running.load(Ordering::SeqCst) {
+                let now = get_epoch_time_secs();
+                if now.saturating_sub(last_check) < check_interval_secs {
+                    sleep_ms(1000);
+                    continue;
+                }
+                last_check = now;
+
+                for notice in monitor.check(now, &NodeSignals::current(is_miner)) {
+                    match notice.status {
+                        AlertStatus::Firing => {
+                            warn!("Alerts: condition firing: {}", &notice.message);
+                        }
+                        AlertStatus::Resolved => {
+                            info!("Alerts: condition resolved: {}", &notice.message);
+                        }
+                    }
+                    for webhook in webhooks.iter() {
+                        send_notice(webhook, &notice);
+                    }
+                }
+            }
+        })
+        .expect("FATAL: failed to start alerts thread");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn signals(burn_height: u64, stacks_tip_height: u64) -> NodeSignals {
+        NodeSignals {
+            burn_height,
+            stacks_tip_height,
+            last_sortition_won_height: Some(0),
+            last_marf_flush_ms: 0,
+            pending_observer_payloads: 0,
+        }
+    }
+
+    fn statuses(notices: &[AlertNotice]) -> Vec<(AlertKind, AlertStatus)> {
+        notices.iter().map(|n| (n.alert, n.status)).collect()
+    }
+
+    #[test]
+    fn test_alerts_fire_once_and_resolve() {
+        let mut monitor = AlertMonitor::new(AlertConfig {
+            webhooks: vec![],
+            check_interval_secs: 1,
+            no_sortition_won_blocks: Some(10),
+            tip_stall_secs: Some(60),
+            marf_flush_ms: Some(500),
+            observer_backlog: Some(100),
+        });
+
+        assert!(monitor.check(1000, &signals(100, 50)).is_empty());
+
+        // nothing won in 10 blocks, and the tip has not moved in 60 seconds
+        let notices = monitor.check(1060, &signals(110, 50));
+        assert_eq!(
+            statuses(&notices),
+            vec![
+                (AlertKind::NoSortitionWon, AlertStatus::Firing),
+                (AlertKind::TipStalled, AlertStatus::Firing),
+            ]
+        );
+        assert_eq!(notices[0].value, 10);
+        assert_eq!(notices[1].value, 60);
+
+        // still firing, so not reported again
+        assert!(monitor.check(1070, &signals(112, 50)).is_empty());
+
+        // the tip moves, and the miner wins a sortition
+        let mut won = signals(113, 51);
+        won.last_sortition_won_height = Some(113);
+        assert_eq!(
+            statuses(&monitor.check(1080, &won)),
+            vec![
+                (AlertKind::NoSortitionWon, AlertStatus::Resolved),
+                (AlertKind::TipStalled, AlertStatus::Resolved),
+            ]
+        );
+
+        let mut slow = signals(114, 52);
+        slow.last_sortition_won_height = Some(113);
+        slow.last_marf_flush_ms = 501;
+        slow.pending_observer_payloads = 101;
+        assert_eq!(
+            statuses(&monitor.check(1090, &slow)),
+            vec![
+                (AlertKind::SlowMarfFlush, AlertStatus::Firing),
+                (AlertKind::ObserverBacklog, AlertStatus::Firing),
+            ]
+        );
+        slow.pending_observer_payloads = 100;
+        assert_eq!(
+            statuses(&monitor.check(1100, &slow)),
+            vec![(AlertKind::ObserverBacklog, AlertStatus::Resolved)]
+        );
+    }
+
+    #[test]
+    fn test_alerts_disabled_conditions() {
+        let mut monitor = AlertMonitor::new(AlertConfig {
+            webhooks: vec![],
+            check_interval_secs: 1,
+            no_sortition_won_blocks: Some(10),
+            tip_stall_secs: None,
+            marf_flush_ms: None,
+            observer_backlog: None,
+        });
+
+        // not a miner, so the sortition condition does not apply either
+        let mut not_mining = signals(100, 50);
+        not_mining.last_sortition_won_height = None;
+        not_mining.last_marf_flush_ms = 10_000;
+        not_mining.pending_observer_payloads = 10_000;
+        assert!(monitor.check(1000, &not_mining).is_empty());
+        not_mining.burn_height = 200;
+        assert!(monitor.check(10_000, &not_mining).is_empty());
+    }
+}
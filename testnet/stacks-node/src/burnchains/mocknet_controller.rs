@@ -1,4 +1,5 @@
 use std::collections::VecDeque;
+use std::thread;
 use std::time::Instant;
 
 use stacks::burnchains::bitcoin::BitcoinBlock;
@@ -22,6 +23,7 @@ use stacks::util::hash::Sha256Sum;
 
 use super::super::operations::BurnchainOpSigner;
 use super::super::Config;
+use super::regtest_control::{start_serving_regtest_control, RegtestBlockQueue};
 use super::{BurnchainController, BurnchainTip, Error as BurnchainControllerError};
 use stacks::vm::costs::ExecutionCost;
 
@@ -32,6 +34,8 @@ pub struct MocknetController {
     db: Option<SortitionDB>,
     chain_tip: Option<BurnchainTip>,
     queued_operations: VecDeque<BlockstackOperationType>,
+    /// In `regtest` mode, burnchain blocks are only produced when requested through this queue
+    regtest_queue: Option<RegtestBlockQueue>,
 }
 
 impl MocknetController {
@@ -39,6 +43,25 @@ impl MocknetController {
         Box::new(Self::new(config))
     }
 
+    /// Instantiate a mock burnchain which only produces blocks on demand, and start serving the
+    /// regtest control endpoint through which blocks are requested.
+    pub fn regtest(config: Config) -> Box<dyn BurnchainController> {
+        let mut controller = Self::new(config);
+        let queue = RegtestBlockQueue::new();
+        controller.regtest_queue = Some(queue.clone());
+
+        let bind_address = controller.config.burnchain.get_regtest_control_bind();
+        thread::Builder::new()
+            .name("regtest-control".to_string())
+            .spawn(move || {
+                debug!("regtest-control thread ID is {:?}", thread::current().id());
+                start_serving_regtest_control(bind_address, queue);
+            })
+            .expect("FATAL: failed to start regtest control thread");
+
+        Box::new(controller)
+    }
+
     fn new(config: Config) -> Self {
         debug!("Opening Burnchain at {}", &config.get_burn_db_path());
         let burnchain = config.get_burnchain();
@@ -49,10 +72,14 @@ impl MocknetController {
             db: None,
             queued_operations: VecDeque::new(),
             chain_tip: None,
+            regtest_queue: None,
         }
     }
 
-    fn build_next_block_header(current_block: &BlockSnapshot) -> BurnchainBlockHeader {
+    fn build_next_block_header(
+        current_block: &BlockSnapshot,
+        timestamp: u64,
+    ) -> BurnchainBlockHeader {
         let curr_hash = &current_block.burn_header_hash.to_bytes()[..];
         let next_hash = Sha256Sum::from_data(&curr_hash);
 
@@ -61,7 +88,7 @@ impl MocknetController {
             &BurnchainHeaderHash::from_bytes(next_hash.as_bytes()).unwrap(),
             &current_block.burn_header_hash,
             vec![],
-            get_epoch_time_secs(),
+            timestamp,
         ));
         block.header()
     }
@@ -179,8 +206,17 @@ impl BurnchainController for MocknetController {
     ) -> Result<(BurnchainTip, u64), BurnchainControllerError> {
         let chain_tip = self.get_chain_tip();
 
+        // In regtest mode, wait until someone asks for a block
+        let timestamp = match self.regtest_queue {
+            Some(ref queue) => {
+                let request = queue.wait_next();
+                RegtestBlockQueue::block_timestamp(&request)
+            }
+            None => get_epoch_time_secs(),
+        };
+
         // Simulating mining
-        let next_block_header = Self::build_next_block_header(&chain_tip.block_snapshot);
+        let next_block_header = Self::build_next_block_header(&chain_tip.block_snapshot, timestamp);
         let mut ops = vec![];
 
         while let Some(payload) = self.queued_operations.pop_front() {
@@ -0,0 +1,321 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+
+use async_std::net::{TcpListener, TcpStream};
+use async_std::prelude::*;
+use async_std::task;
+use http_types::{Method, Response, StatusCode};
+
+use stacks::util::get_epoch_time_secs;
+
+/// Maximum number of burnchain blocks that can be requested in a single call
+pub const MAX_REGTEST_BLOCKS_PER_REQUEST: u64 = 1000;
+
+/// A request to mint a single burnchain block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MineBlockRequest {
+    /// UNIX timestamp to stamp the burnchain block header with.
+    /// If not given, the current time is used.
+    pub timestamp: Option<u64>,
+}
+
+/// Body of a `POST /regtest/mine` request
+#[derive(Debug, Clone, Deserialize)]
+struct MineBlocksBody {
+    count: Option<u64>,
+    timestamp: Option<u64>,
+}
+
+/// Queue of burnchain blocks that have been requested over the regtest control endpoint, but not
+/// yet produced by the mock burnchain.  The `MocknetController` blocks on this queue in
+/// `regtest` mode, so burnchain blocks are produced exactly when (and as fast as) they are asked
+/// for.
+#[derive(Clone)]
+pub struct RegtestBlockQueue {
+    inner: Arc<(Mutex<VecDeque<MineBlockRequest>>, Condvar)>,
+}
+
+impl RegtestBlockQueue {
+    pub fn new() -> RegtestBlockQueue {
+        RegtestBlockQueue {
+            inner: Arc::new((Mutex::new(VecDeque::new()), Condvar::new())),
+        }
+    }
+
+    /// Request `count` burnchain blocks.  If `timestamp` is given, the first block is stamped with
+    /// it and each subsequent block is one second later.
+    pub fn request_blocks(&self, count: u64, timestamp: Option<u64>) {
+        let (ref lock, ref cvar) = *self.inner;
+        let mut queue = lock
+            .lock()
+            .expect("FATAL: regtest block queue lock poisoned");
+        for i in 0..count {
+            queue.push_back(MineBlockRequest {
+                timestamp: timestamp.map(|ts| ts.saturating_add(i)),
+            });
+        }
+        cvar.notify_all();
+    }
+
+    /// How many requested blocks have yet to be mined
+    pub fn pending(&self) -> usize {
+        let (ref lock, _) = *self.inner;
+        lock.lock()
+            .expect("FATAL: regtest block queue lock poisoned")
+            .len()
+    }
+
+    /// Block until a burnchain block is requested, and return the request
+    pub fn wait_next(&self) -> MineBlockRequest {
+        let (ref lock, ref cvar) = *self.inner;
+        let mut queue = lock
+            .lock()
+            .expect("FATAL: regtest block queue lock poisoned");
+        loop {
+            if let Some(request) = queue.pop_front() {
+                return request;
+            }
+            queue = cvar
+                .wait(queue)
+                .expect("FATAL: regtest block queue lock poisoned");
+        }
+    }
+
+    /// Get the timestamp to use for the next block, given a request
+    pub fn block_timestamp(request: &MineBlockRequest) -> u64 {
+        request.timestamp.unwrap_or_else(get_epoch_time_secs)
+    }
+}
+
+/// Serve the regtest control endpoint on `bind_address`.  This blocks the calling thread.
+///
+/// Supported requests:
+///   * `POST /regtest/mine` with an optional JSON body `{"count": N, "timestamp": T}` queues up N
+///     (default 1) burnchain blocks, the first of which is stamped with T (default: now).
+///   * `GET /regtest/status` reports how many requested blocks are still pending.
+pub fn start_serving_regtest_control(bind_address: String, queue: RegtestBlockQueue) {
+    task::block_on(async {
+        let listener = TcpListener::bind(bind_address)
+            .await
+            .expect("Regtest control: unable to bind address");
+        info!(
+            "Regtest control: server listening on http://{}",
+            listener
+                .local_addr()
+                .expect("Regtest control: unable to get addr")
+        );
+
+        let mut incoming = listener.incoming();
+        while let Some(stream) = incoming.next().await {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    error!("Regtest control: unable to open socket - {:?}", err);
+                    continue;
+                }
+            };
+            let queue = queue.clone();
+            task::spawn(async move {
+                if let Err(err) = accept(stream, queue).await {
+                    warn!("Regtest control: failed to handle request: {}", err);
+                }
+            });
+        }
+    });
+}
+
+async fn accept(stream: TcpStream, queue: RegtestBlockQueue) -> http_types::Result<()> {
+    debug!("Handle regtest control request ({})", stream.peer_addr()?);
+    async_h1::accept(stream.clone(), |mut req| {
+        let queue = queue.clone();
+        async move {
+            let method = req.method();
+            let path = req.url().path().to_string();
+            let mut response = match (method, path.as_str()) {
+                (Method::Post, "/regtest/mine") => {
+                    let body = req.body_string().await?;
+                    let request = if body.trim().is_empty() {
+                        MineBlocksBody {
+                            count: None,
+                            timestamp: None,
+                        }
+                    } else {
+                        match serde_json::from_str::<MineBlocksBody>(&body) {
+                            Ok(request) => request,
+                            Err(e) => {
+                                let mut response = Response::new(StatusCode::BadRequest);
+                                response.set_body(format!("Invalid request body: {}", e));
+                                return Ok(response);
+                            }
+                        }
+                    };
+                    let count = request.count.unwrap_or(1);
+                    if count == 0 || count > MAX_REGTEST_BLOCKS_PER_REQUEST {
+                        let mut response = Response::new(StatusCode::BadRequest);
+                        response.set_body(format!(
+                            "`count` must be between 1 and {}",
+                            MAX_REGTEST_BLOCKS_PER_REQUEST
+                        ));
+                        return Ok(response);
+                    }
+                    queue.request_blocks(count, request.timestamp);
+                    let mut response = Response::new(StatusCode::Ok);
+                    response.set_body(json!({ "queued": count, "pending": queue.pending() }));
+                    response
+                }
+                (Method::Get, "/regtest/status") => {
+                    let mut response = Response::new(StatusCode::Ok);
+                    response.set_body(json!({ "pending": queue.pending() }));
+                    response
+                }
+                _ => Response::new(StatusCode::NotFound),
+            };
+            response.append_header("Content-Type", "application/json");
+            Ok(response)
+        }
+    })
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_regtest_block_queue() {
+        let queue = RegtestBlockQueue::new();
+        assert_eq!(queue.pending(), 0);
+
+        queue.request_blocks(3, Some(100));
+        assert_eq!(queue.pending(), 3);
+        for i in 0..3 {
+            assert_eq!(
+                queue.wait_next(),
+                MineBlockRequest {
+                    timestamp: Some(100 + i)
+                }
+            );
+        }
+        assert_eq!(queue.pending(), 0);
+
+        // a waiting miner is woken up by a request from another thread
+        let requester = queue.clone();
+        let handle = thread::spawn(move || requester.request_blocks(1, None));
+        assert_eq!(queue.wait_next(), MineBlockRequest { timestamp: None });
+        handle.join().unwrap();
+    }
+
+    /// Send a single HTTP request to the regtest control endpoint, and return the status line and
+    /// body of the response.
+    fn send_request(bind: &str, method: &str, path: &str, body: &str) -> (String, String) {
+        let mut stream = None;
+        for _ in 0..50 {
+            match std::net::TcpStream::connect(bind) {
+                Ok(s) => {
+                    stream = Some(s);
+                    break;
+                }
+                Err(_) => thread::sleep(std::time::Duration::from_millis(100)),
+            }
+        }
+        let mut stream = stream.expect("Regtest control endpoint never came up");
+
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            bind,
+            body.len(),
+            body
+        );
+        std::io::Write::write_all(&mut stream, request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        std::io::Read::read_to_string(&mut stream, &mut response).unwrap();
+        let status_line = response.lines().next().unwrap().to_string();
+        let body = response
+            .splitn(2, "\r\n\r\n")
+            .nth(1)
+            .unwrap_or("")
+            .to_string();
+        (status_line, body)
+    }
+
+    #[test]
+    fn test_regtest_control_endpoint() {
+        let bind = "127.0.0.1:20545";
+        let queue = RegtestBlockQueue::new();
+        let server_queue = queue.clone();
+        thread::spawn(move || start_serving_regtest_control(bind.to_string(), server_queue));
+
+        let (status, body) = send_request(bind, "GET", "/regtest/status", "");
+        assert!(status.contains("200"), "{}", status);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+            json!({ "pending": 0 })
+        );
+
+        // an empty body requests a single block
+        let (status, body) = send_request(bind, "POST", "/regtest/mine", "");
+        assert!(status.contains("200"), "{}", status);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+            json!({ "queued": 1, "pending": 1 })
+        );
+
+        let (status, body) = send_request(
+            bind,
+            "POST",
+            "/regtest/mine",
+            r#"{"count": 2, "timestamp": 1000}"#,
+        );
+        assert!(status.contains("200"), "{}", status);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+            json!({ "queued": 2, "pending": 3 })
+        );
+
+        let (status, body) = send_request(bind, "GET", "/regtest/status", "");
+        assert!(status.contains("200"), "{}", status);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+            json!({ "pending": 3 })
+        );
+
+        assert_eq!(queue.wait_next(), MineBlockRequest { timestamp: None });
+        assert_eq!(
+            queue.wait_next(),
+            MineBlockRequest {
+                timestamp: Some(1000)
+            }
+        );
+        assert_eq!(
+            queue.wait_next(),
+            MineBlockRequest {
+                timestamp: Some(1001)
+            }
+        );
+
+        // malformed and out-of-range requests are rejected without queueing anything
+        let (status, _) = send_request(bind, "POST", "/regtest/mine", "not json");
+        assert!(status.contains("400"), "{}", status);
+        let (status, _) = send_request(bind, "POST", "/regtest/mine", r#"{"count": 0}"#);
+        assert!(status.contains("400"), "{}", status);
+        let (status, _) = send_request(
+            bind,
+            "POST",
+            "/regtest/mine",
+            &format!(r#"{{"count": {}}}"#, MAX_REGTEST_BLOCKS_PER_REQUEST + 1),
+        );
+        assert!(status.contains("400"), "{}", status);
+        assert_eq!(queue.pending(), 0);
+
+        // unknown routes and methods
+        let (status, _) = send_request(bind, "GET", "/regtest/mine", "");
+        assert!(status.contains("404"), "{}", status);
+        let (status, _) = send_request(bind, "GET", "/v2/info", "");
+        assert!(status.contains("404"), "{}", status);
+    }
+}
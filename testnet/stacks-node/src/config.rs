@@ -1,3 +1,4 @@
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::fs;
 use std::net::{SocketAddr, ToSocketAddrs};
@@ -10,12 +11,14 @@ use rand::RngCore;
 use stacks::burnchains::bitcoin::BitcoinNetworkType;
 use stacks::burnchains::Burnchain;
 use stacks::burnchains::{MagicBytes, BLOCKSTACK_MAGIC_MAINNET};
+use stacks::chainstate::stacks::boot::BOOT_CONTRACT_NAMES;
+use stacks::chainstate::stacks::db::ChainstateAccountLockup;
 use stacks::chainstate::stacks::index::marf::MARFOpenOpts;
 use stacks::chainstate::stacks::index::storage::TrieHashCalculationMode;
 use stacks::chainstate::stacks::miner::BlockBuilderSettings;
 use stacks::chainstate::stacks::miner::MinerStatus;
 use stacks::chainstate::stacks::MAX_BLOCK_LEN;
-use stacks::core::mempool::MemPoolWalkSettings;
+use stacks::core::mempool::{AnchorModePolicy, MemPoolWalkSettings, TransactionAnchorPolicy};
 use stacks::core::StacksEpoch;
 use stacks::core::StacksEpochExtension;
 use stacks::core::StacksEpochId;
@@ -30,14 +33,18 @@ use stacks::cost_estimates::metrics::ProportionalDotProduct;
 use stacks::cost_estimates::CostEstimator;
 use stacks::cost_estimates::FeeEstimator;
 use stacks::cost_estimates::PessimisticEstimator;
-use stacks::net::connection::ConnectionOptions;
+use stacks::indexer::pipeline::IndexerPipeline;
+use stacks::indexer::IndexerDB;
+use stacks::net::connection::{BannedLeaderKeyPolicy, ConnectionOptions};
 use stacks::net::{Neighbor, NeighborKey, PeerAddress};
+use stacks::types::NetworkId;
 use stacks::util::get_epoch_time_ms;
 use stacks::util::hash::hex_bytes;
 use stacks::util::secp256k1::Secp256k1PrivateKey;
 use stacks::util::secp256k1::Secp256k1PublicKey;
 use stacks::vm::costs::ExecutionCost;
 use stacks::vm::types::{AssetIdentifier, PrincipalData, QualifiedContractIdentifier};
+use stacks::vm::VmLimits;
 
 const DEFAULT_SATS_PER_VB: u64 = 50;
 const DEFAULT_MAX_RBF_RATE: u64 = 150; // 1.5x
@@ -45,16 +52,19 @@ const DEFAULT_RBF_FEE_RATE_INCREMENT: u64 = 5;
 const LEADER_KEY_TX_ESTIM_SIZE: u64 = 290;
 const BLOCK_COMMIT_TX_ESTIM_SIZE: u64 = 350;
 const INV_REWARD_CYCLES_TESTNET: u64 = 6;
+const DEFAULT_REGTEST_CONTROL_BIND: &str = "127.0.0.1:20445";
 
 #[derive(Clone, Deserialize, Default, Debug)]
 pub struct ConfigFile {
     pub burnchain: Option<BurnchainConfigFile>,
     pub node: Option<NodeConfigFile>,
     pub ustx_balance: Option<Vec<InitialBalanceFile>>,
+    pub ustx_lockup: Option<Vec<InitialLockupFile>>,
     pub events_observer: Option<Vec<EventObserverConfigFile>>,
     pub connection_options: Option<ConnectionOptionsFile>,
     pub fee_estimation: Option<FeeEstimationConfigFile>,
     pub miner: Option<MinerConfigFile>,
+    pub alerts: Option<AlertConfigFile>,
 }
 
 #[derive(Clone, Deserialize, Default)]
@@ -129,6 +139,375 @@ mod tests {
         assert!(Config::from_config_file(ConfigFile::from_str("").unwrap()).is_ok());
     }
 
+    #[test]
+    fn test_pox_length_overrides() {
+        let config_with_lengths = |lengths: &str| {
+            Config::from_config_file(
+                ConfigFile::from_str(&format!(
+                    r#"
+                    [burnchain]
+                    mode = "regtest"
+                    {}
+                    "#,
+                    lengths
+                ))
+                .unwrap(),
+            )
+        };
+
+        let config = config_with_lengths("pox_reward_length = 20\npox_prepare_length = 5").unwrap();
+        let burnchain = config.get_burnchain();
+        assert_eq!(burnchain.pox_constants.reward_cycle_length, 20);
+        assert_eq!(burnchain.pox_constants.prepare_length, 5);
+        assert_eq!(burnchain.pox_constants.anchor_threshold, 3);
+
+        // prepare phase must fit inside the reward cycle
+        assert!(config_with_lengths("pox_reward_length = 5\npox_prepare_length = 5").is_err());
+        assert!(config_with_lengths("pox_reward_length = 5\npox_prepare_length = 10").is_err());
+        assert!(config_with_lengths("pox_reward_length = 5\npox_prepare_length = 0").is_err());
+
+        // a lone override is checked against the network's default for the other length
+        assert!(config_with_lengths("pox_reward_length = 1").is_err());
+    }
+
+    #[test]
+    fn test_custom_network_config() {
+        let config_with_network = |mode: &str, params: &str| {
+            Config::from_config_file(
+                ConfigFile::from_str(&format!(
+                    r#"
+                    [burnchain]
+                    mode = "{}"
+                    {}
+                    "#,
+                    mode, params
+                ))
+                .unwrap(),
+            )
+        };
+
+        let config = config_with_network(
+            "regtest",
+            "chain_id = 0x12345678\naddress_version_singlesig = 20\naddress_version_multisig = 21",
+        )
+        .unwrap();
+        let network = config.get_network_id();
+        assert!(!network.mainnet);
+        assert_eq!(network.chain_id, 0x12345678);
+        assert_eq!(network.peer_network_id, 0x12345678);
+        assert_eq!(network.address_version_singlesig, 20);
+        assert_eq!(network.address_version_multisig, 21);
+        assert_eq!(network.boot_address.version, 20);
+
+        // stock address versions, but a custom chain ID
+        let network = config_with_network("regtest", "chain_id = 0x12345678")
+            .unwrap()
+            .get_network_id();
+        assert_eq!(network.chain_id, 0x12345678);
+        assert_eq!(network.boot_address, NetworkId::testnet().boot_address);
+
+        assert_eq!(
+            config_with_network("regtest", "").unwrap().get_network_id(),
+            NetworkId::testnet()
+        );
+
+        // not in mainnet
+        assert!(config_with_network("mainnet", "chain_id = 0x12345678").is_err());
+        // both versions or neither
+        assert!(config_with_network("regtest", "address_version_singlesig = 20").is_err());
+        // versions must be valid c32 versions, and distinct
+        assert!(config_with_network(
+            "regtest",
+            "address_version_singlesig = 32\naddress_version_multisig = 21"
+        )
+        .is_err());
+        assert!(config_with_network(
+            "regtest",
+            "address_version_singlesig = 20\naddress_version_multisig = 20"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_custom_vm_limits_config() {
+        let config_with_limits = |mode: &str, params: &str| {
+            Config::from_config_file(
+                ConfigFile::from_str(&format!(
+                    r#"
+                    [burnchain]
+                    mode = "{}"
+                    {}
+                    "#,
+                    mode, params
+                ))
+                .unwrap(),
+            )
+        };
+
+        assert_eq!(
+            config_with_limits("regtest", "")
+                .unwrap()
+                .burnchain
+                .vm_limits,
+            None
+        );
+
+        let vm_limits = config_with_limits(
+            "regtest",
+            "max_call_stack_depth = 128\nmax_contract_size = 4194304",
+        )
+        .unwrap()
+        .burnchain
+        .vm_limits
+        .unwrap();
+        assert_eq!(vm_limits.max_call_stack_depth, 128);
+        assert_eq!(vm_limits.max_contract_size, 4 * 1024 * 1024);
+        assert_eq!(
+            vm_limits.ast_call_stack_depth_buffer,
+            VmLimits::default().ast_call_stack_depth_buffer
+        );
+
+        // not in mainnet
+        assert!(config_with_limits("mainnet", "max_call_stack_depth = 128").is_err());
+    }
+
+    #[test]
+    fn test_fee_rebate_accounting_config() {
+        let config_with_node = |params: &str| {
+            Config::from_config_file(
+                ConfigFile::from_str(&format!(
+                    r#"
+                    [node]
+                    {}
+                    "#,
+                    params
+                ))
+                .unwrap(),
+            )
+            .unwrap()
+        };
+
+        assert!(!Config::default().node.fee_rebate_accounting);
+        assert!(!config_with_node("").node.fee_rebate_accounting);
+        assert!(
+            config_with_node("fee_rebate_accounting = true")
+                .node
+                .fee_rebate_accounting
+        );
+    }
+
+    #[test]
+    fn test_check_multi_network() {
+        let make_config = |mode: &str, working_dir: &str, port: u16| {
+            let mut config = Config::default();
+            config.burnchain.mode = mode.to_string();
+            config.node.working_dir = working_dir.to_string();
+            config.node.rpc_bind = format!("127.0.0.1:{}", port);
+            config.node.p2p_bind = format!("127.0.0.1:{}", port + 1);
+            config
+        };
+
+        let mainnet = make_config("mainnet", "/tmp/mainnet", 20443);
+        let testnet = make_config("xenon", "/tmp/testnet", 30443);
+        assert!(Config::check_multi_network(&[mainnet.clone(), testnet.clone()]).is_ok());
+
+        // shared working directory
+        let clash = make_config("xenon", "/tmp/mainnet", 30443);
+        assert!(Config::check_multi_network(&[mainnet.clone(), clash]).is_err());
+
+        // shared port
+        let clash = make_config("xenon", "/tmp/testnet", 20444);
+        assert!(Config::check_multi_network(&[mainnet.clone(), clash]).is_err());
+
+        // helium-style run loops can't share a process
+        let helium = make_config("helium", "/tmp/helium", 40443);
+        assert!(Config::check_multi_network(&[mainnet.clone(), helium]).is_err());
+
+        // each network serves its own metrics
+        let mut mainnet_prom = mainnet.clone();
+        mainnet_prom.node.prometheus_bind = Some("127.0.0.1:9153".to_string());
+        let mut testnet_prom = testnet.clone();
+        testnet_prom.node.prometheus_bind = Some("127.0.0.1:9154".to_string());
+        assert!(Config::check_multi_network(&[mainnet_prom.clone(), testnet_prom]).is_ok());
+
+        // ...but not on the same address
+        let mut testnet_prom = testnet.clone();
+        testnet_prom.node.prometheus_bind = Some("127.0.0.1:9153".to_string());
+        assert!(Config::check_multi_network(&[mainnet_prom, testnet_prom]).is_err());
+    }
+
+    #[test]
+    fn test_boot_contract_overrides_config() {
+        let path = "/tmp/test_boot_contract_overrides_config.clar";
+        std::fs::write(path, "(define-read-only (hello) u1)").unwrap();
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(&format!(
+                r#"
+                [burnchain]
+                mode = "mocknet"
+                boot_contract_overrides = {{ pox-2 = "{}" }}
+                "#,
+                path
+            ))
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            config.burnchain.boot_contract_overrides.get("pox-2"),
+            Some(&"(define-read-only (hello) u1)".to_string())
+        );
+
+        // only in mocknet and regtest
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(&format!(
+                r#"
+                [burnchain]
+                mode = "xenon"
+                boot_contract_overrides = {{ pox-2 = "{}" }}
+                "#,
+                path
+            ))
+            .unwrap(),
+        )
+        .is_err());
+
+        // only boot contracts
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(&format!(
+                r#"
+                [burnchain]
+                mode = "mocknet"
+                boot_contract_overrides = {{ not-a-boot-contract = "{}" }}
+                "#,
+                path
+            ))
+            .unwrap(),
+        )
+        .is_err());
+
+        // the file has to exist
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                mode = "mocknet"
+                boot_contract_overrides = { pox-2 = "/tmp/does-not-exist.clar" }
+                "#,
+            )
+            .unwrap(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_anchor_mode_policy_config() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [miner]
+                anchor_mode_policy = "anchored_only"
+                smart_contract_anchor_mode_policy = "any"
+                mempool_enforce_anchor_policy = true
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            config.miner.anchor_policy,
+            TransactionAnchorPolicy {
+                token_transfer: AnchorModePolicy::AnchoredOnly,
+                contract_call: AnchorModePolicy::AnchoredOnly,
+                smart_contract: AnchorModePolicy::Any,
+            }
+        );
+        assert!(config.miner.mempool_enforce_anchor_policy);
+
+        let config = Config::from_config_file(ConfigFile::from_str("[miner]").unwrap()).unwrap();
+        assert_eq!(config.miner.anchor_policy, TransactionAnchorPolicy::any());
+        assert!(!config.miner.mempool_enforce_anchor_policy);
+
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [miner]
+                contract_call_anchor_mode_policy = "sometimes"
+                "#,
+            )
+            .unwrap(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_ustx_lockup_config() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [[ustx_lockup]]
+                address = "ST2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ1R5HBP2"
+                amount = 1000
+                unlock_height = 10
+
+                [[ustx_lockup]]
+                address = "ST2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ1R5HBP2"
+                amount = 2000
+                unlock_height = 20
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(config.initial_lockups.len(), 2);
+        let lockups = config.get_initial_lockups();
+        assert_eq!(
+            lockups[1].address,
+            "ST2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ1R5HBP2"
+        );
+        assert_eq!(lockups[1].amount, 2000);
+        assert_eq!(lockups[1].block_height, 20);
+
+        // locked STX aren't liquid at genesis
+        assert_eq!(config.get_initial_liquid_ustx(), 0);
+
+        // nothing can unlock in the genesis block
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [[ustx_lockup]]
+                address = "ST2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ1R5HBP2"
+                amount = 1000
+                unlock_height = 0
+                "#,
+            )
+            .unwrap(),
+        )
+        .is_err());
+
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [[ustx_lockup]]
+                address = "not-an-address"
+                amount = 1000
+                unlock_height = 10
+                "#,
+            )
+            .unwrap(),
+        )
+        .is_err());
+
+        // not allowed on mainnet
+        let mut mainnet = ConfigFile::mainnet();
+        mainnet.ustx_lockup = Some(vec![InitialLockupFile {
+            address: "SP2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ0Z5ZM6T".to_string(),
+            amount: 1000,
+            unlock_height: 10,
+        }]);
+        assert!(Config::from_config_file(mainnet).is_err());
+    }
+
     #[test]
     fn should_load_legacy_mstx_balances_toml() {
         let config = ConfigFile::from_str(
@@ -299,6 +678,17 @@ impl ConfigFile {
         }
     }
 
+    /// Like `mocknet`, but burnchain blocks are only produced when requested through the regtest
+    /// control endpoint, and Stacks blocks are assembled as soon as a burnchain block arrives.
+    pub fn regtest() -> ConfigFile {
+        let mut config_file = ConfigFile::mocknet();
+        if let Some(burnchain) = config_file.burnchain.as_mut() {
+            burnchain.mode = Some("regtest".to_string());
+            burnchain.commit_anchor_block_within = Some(0);
+        }
+        config_file
+    }
+
     pub fn mocknet() -> ConfigFile {
         let burnchain = BurnchainConfigFile {
             mode: Some("mocknet".to_string()),
@@ -356,10 +746,12 @@ pub struct Config {
     pub burnchain: BurnchainConfig,
     pub node: NodeConfig,
     pub initial_balances: Vec<InitialBalance>,
+    pub initial_lockups: Vec<InitialLockup>,
     pub events_observers: Vec<EventObserverConfig>,
     pub connection_options: ConnectionOptions,
     pub miner: MinerConfig,
     pub estimation: FeeEstimationConfig,
+    pub alerts: AlertConfig,
 }
 
 lazy_static! {
@@ -451,6 +843,24 @@ impl Config {
             );
             burnchain.pox_constants.sunset_end = sunset_end.into();
         }
+
+        if let Some(pox_reward_length) = self.burnchain.pox_reward_length {
+            debug!(
+                "Override reward_cycle_length from {} to {}",
+                burnchain.pox_constants.reward_cycle_length, pox_reward_length
+            );
+            burnchain.pox_constants.reward_cycle_length = pox_reward_length;
+        }
+
+        if let Some(pox_prepare_length) = self.burnchain.pox_prepare_length {
+            debug!(
+                "Override prepare_length from {} to {}",
+                burnchain.pox_constants.prepare_length, pox_prepare_length
+            );
+            burnchain.pox_constants.prepare_length = pox_prepare_length;
+            // the anchor block must be confirmed by a majority of the prepare phase
+            burnchain.pox_constants.anchor_threshold = pox_prepare_length / 2 + 1;
+        }
     }
 
     /// Load up a Burnchain and apply config settings to it.
@@ -609,7 +1019,7 @@ impl Config {
                 .map_err(|_| "End height must be a non-negative integer")?;
         }
 
-        if burn_mode == "mocknet" {
+        if burn_mode == "mocknet" || burn_mode == "regtest" {
             for epoch in out_epochs.iter_mut() {
                 epoch.block_limit = ExecutionCost::max_value();
             }
@@ -628,6 +1038,39 @@ impl Config {
         Ok(out_epochs)
     }
 
+    /// Load the source code of each overridden boot contract.  `overrides` maps each boot
+    /// contract name to the path of the file to load it from.
+    fn load_boot_contract_overrides(
+        overrides: &HashMap<String, String>,
+        burn_mode: &str,
+    ) -> Result<HashMap<String, String>, String> {
+        if burn_mode != "mocknet" && burn_mode != "regtest" {
+            return Err(format!(
+                "Boot contracts can only be overridden in mocknet and regtest mode (mode is '{}')",
+                burn_mode
+            ));
+        }
+
+        let mut out_overrides = HashMap::new();
+        for (name, path) in overrides.iter() {
+            if !BOOT_CONTRACT_NAMES.contains(&name.as_str()) {
+                return Err(format!(
+                    "Cannot override unknown boot contract '{}' (should be one of: {})",
+                    name,
+                    BOOT_CONTRACT_NAMES.join(", ")
+                ));
+            }
+            let code = fs::read_to_string(path).map_err(|e| {
+                format!(
+                    "Failed to read overridden boot contract '{}' from {}: {:?}",
+                    name, path, &e
+                )
+            })?;
+            out_overrides.insert(name.clone(), code);
+        }
+        Ok(out_overrides)
+    }
+
     pub fn from_config_file(config_file: ConfigFile) -> Result<Config, String> {
         let default_node_config = NodeConfig::default();
         let mut has_require_affirmed_anchor_blocks = false;
@@ -676,7 +1119,30 @@ impl Config {
                         .wait_time_for_blocks
                         .unwrap_or(default_node_config.wait_time_for_blocks),
                     prometheus_bind: node.prometheus_bind,
+                    indexer_bind: node.indexer_bind,
+                    event_replay_bind: node.event_replay_bind,
+                    event_dispatch_threads: node
+                        .event_dispatch_threads
+                        .unwrap_or(default_node_config.event_dispatch_threads),
+                    event_dispatch_queue_depth: node
+                        .event_dispatch_queue_depth
+                        .unwrap_or(default_node_config.event_dispatch_queue_depth),
+                    block_validation_threads: node
+                        .block_validation_threads
+                        .unwrap_or(default_node_config.block_validation_threads),
+                    block_validation_queue_depth: node
+                        .block_validation_queue_depth
+                        .unwrap_or(default_node_config.block_validation_queue_depth),
+                    burnchain_load_threads: node
+                        .burnchain_load_threads
+                        .unwrap_or(default_node_config.burnchain_load_threads),
+                    burnchain_load_queue_depth: node
+                        .burnchain_load_queue_depth
+                        .unwrap_or(default_node_config.burnchain_load_queue_depth),
                     marf_cache_strategy: node.marf_cache_strategy,
+                    memory_budget_mb: node
+                        .memory_budget_mb
+                        .unwrap_or(default_node_config.memory_budget_mb),
                     marf_defer_hashing: node
                         .marf_defer_hashing
                         .unwrap_or(default_node_config.marf_defer_hashing),
@@ -705,6 +1171,12 @@ impl Config {
                     chain_liveness_poll_time_secs: node
                         .chain_liveness_poll_time_secs
                         .unwrap_or(default_node_config.chain_liveness_poll_time_secs),
+                    contract_reanalysis_batch_size: node
+                        .contract_reanalysis_batch_size
+                        .unwrap_or(default_node_config.contract_reanalysis_batch_size),
+                    fee_rebate_accounting: node
+                        .fee_rebate_accounting
+                        .unwrap_or(default_node_config.fee_rebate_accounting),
                 };
                 (node_config, node.bootstrap_node, node.deny_nodes)
             }
@@ -747,6 +1219,13 @@ impl Config {
                             ));
                         }
                     }
+                    if let Some(ref lockups) = config_file.ustx_lockup {
+                        if lockups.len() > 0 {
+                            return Err(format!(
+                                "Attempted to run mainnet node with specified `ustx_lockup`"
+                            ));
+                        }
+                    }
                 } else {
                     // testnet requires that we use the 2.05 rules for anchor block affirmations,
                     // because reward cycle 360 (and possibly future ones) has a different anchor
@@ -762,7 +1241,7 @@ impl Config {
                     chain_id: if &burnchain_mode == "mainnet" {
                         CHAIN_ID_MAINNET
                     } else {
-                        CHAIN_ID_TESTNET
+                        burnchain.chain_id.unwrap_or(CHAIN_ID_TESTNET)
                     },
                     peer_version: if &burnchain_mode == "mainnet" {
                         PEER_VERSION_MAINNET
@@ -849,21 +1328,105 @@ impl Config {
                         .sunset_start
                         .or(default_burnchain_config.sunset_start),
                     sunset_end: burnchain.sunset_end.or(default_burnchain_config.sunset_end),
+                    pox_reward_length: burnchain
+                        .pox_reward_length
+                        .or(default_burnchain_config.pox_reward_length),
+                    pox_prepare_length: burnchain
+                        .pox_prepare_length
+                        .or(default_burnchain_config.pox_prepare_length),
+                    regtest_control_bind: burnchain
+                        .regtest_control_bind
+                        .or(default_burnchain_config.regtest_control_bind),
                     wallet_name: burnchain
                         .wallet_name
                         .unwrap_or(default_burnchain_config.wallet_name.clone()),
+                    // will be overwritten below
+                    boot_contract_overrides: default_burnchain_config.boot_contract_overrides,
+                    address_version_singlesig: burnchain.address_version_singlesig,
+                    address_version_multisig: burnchain.address_version_multisig,
+                    vm_limits: if burnchain.max_call_stack_depth.is_some()
+                        || burnchain.ast_call_stack_depth_buffer.is_some()
+                        || burnchain.max_contract_size.is_some()
+                    {
+                        let default_vm_limits = VmLimits::default();
+                        Some(VmLimits {
+                            max_call_stack_depth: burnchain
+                                .max_call_stack_depth
+                                .unwrap_or(default_vm_limits.max_call_stack_depth),
+                            ast_call_stack_depth_buffer: burnchain
+                                .ast_call_stack_depth_buffer
+                                .unwrap_or(default_vm_limits.ast_call_stack_depth_buffer),
+                            max_contract_size: burnchain
+                                .max_contract_size
+                                .unwrap_or(default_vm_limits.max_contract_size),
+                        })
+                    } else {
+                        None
+                    },
                 };
 
+                if &result.mode == "mainnet"
+                    && (burnchain.chain_id.is_some()
+                        || result.address_version_singlesig.is_some()
+                        || result.address_version_multisig.is_some()
+                        || result.vm_limits.is_some())
+                {
+                    return Err("Network parameters are not configurable in mainnet".into());
+                }
+
+                match (
+                    result.address_version_singlesig,
+                    result.address_version_multisig,
+                ) {
+                    (None, None) => {}
+                    (Some(singlesig), Some(multisig)) => {
+                        if singlesig >= 32 || multisig >= 32 || singlesig == multisig {
+                            return Err(format!(
+                                "Invalid address versions: singlesig ({}) and multisig ({}) must be distinct and less than 32",
+                                singlesig, multisig
+                            ));
+                        }
+                    }
+                    _ => {
+                        return Err(
+                            "Both address_version_singlesig and address_version_multisig must be set"
+                                .into(),
+                        );
+                    }
+                }
+
                 if let BitcoinNetworkType::Mainnet = result.get_bitcoin_network().1 {
                     // check that pox_2_activation hasn't been set in mainnet
                     if result.pox_2_activation.is_some()
                         || result.sunset_start.is_some()
                         || result.sunset_end.is_some()
+                        || result.pox_reward_length.is_some()
+                        || result.pox_prepare_length.is_some()
                     {
                         return Err("PoX-2 parameters are not configurable in mainnet".into());
                     }
                 }
 
+                if result.pox_reward_length.is_some() || result.pox_prepare_length.is_some() {
+                    // check the overrides against the defaults they are applied on top of
+                    let (network_name, _) = result.get_bitcoin_network();
+                    let default_pox_constants = Burnchain::new("", &result.chain, &network_name)
+                        .map_err(|e| format!("Failed to instantiate burnchain: {}", e))?
+                        .pox_constants;
+                    let reward_length = result
+                        .pox_reward_length
+                        .unwrap_or(default_pox_constants.reward_cycle_length);
+                    let prepare_length = result
+                        .pox_prepare_length
+                        .unwrap_or(default_pox_constants.prepare_length);
+                    if prepare_length == 0 || prepare_length >= reward_length {
+                        return Err(format!(
+                            "Invalid PoX lengths: prepare length ({}) must be non-zero and less than the reward cycle length ({})",
+                            prepare_length, reward_length
+                        ));
+                    }
+                }
+
                 if let Some(ref conf_epochs) = burnchain.epochs {
                     result.epochs = Some(Self::make_epochs(
                         conf_epochs,
@@ -873,6 +1436,11 @@ impl Config {
                     )?);
                 }
 
+                if let Some(ref overrides) = burnchain.boot_contract_overrides {
+                    result.boot_contract_overrides =
+                        Self::load_boot_contract_overrides(overrides, &result.mode)?;
+                }
+
                 result
             }
             None => default_burnchain_config,
@@ -909,12 +1477,16 @@ impl Config {
                 unprocessed_block_deadline_secs: miner
                     .unprocessed_block_deadline_secs
                     .unwrap_or(miner_default_config.unprocessed_block_deadline_secs),
+                anchor_policy: miner.get_anchor_policy()?,
+                mempool_enforce_anchor_policy: miner
+                    .mempool_enforce_anchor_policy
+                    .unwrap_or(miner_default_config.mempool_enforce_anchor_policy),
             },
             None => miner_default_config,
         };
 
         let supported_modes = vec![
-            "mocknet", "helium", "neon", "argon", "krypton", "xenon", "mainnet",
+            "mocknet", "regtest", "helium", "neon", "argon", "krypton", "xenon", "mainnet",
         ];
 
         if !supported_modes.contains(&burnchain.mode.as_str()) {
@@ -961,6 +1533,30 @@ impl Config {
             None => vec![],
         };
 
+        let mut initial_lockups = vec![];
+        for lockup in config_file.ustx_lockup.unwrap_or(vec![]).iter() {
+            let address: PrincipalData = PrincipalData::parse_standard_principal(&lockup.address)
+                .map_err(|e| {
+                    format!(
+                        "Invalid `ustx_lockup` address {}: {:?}",
+                        &lockup.address, &e
+                    )
+                })?
+                .into();
+            if lockup.unlock_height == 0 {
+                // the genesis block is never processed, so nothing unlocks at height 0
+                return Err(format!(
+                    "Invalid `ustx_lockup` for {}: `unlock_height` must be at least 1",
+                    &lockup.address
+                ));
+            }
+            initial_lockups.push(InitialLockup {
+                address,
+                amount: lockup.amount,
+                unlock_height: lockup.unlock_height,
+            });
+        }
+
         let mut events_observers = match config_file.events_observer {
             Some(raw_observers) => {
                 let mut observers = vec![];
@@ -1136,6 +1732,47 @@ impl Config {
                     handshake_timeout: opts.connect_timeout.unwrap_or(5),
                     max_sockets: opts.max_sockets.unwrap_or(800) as usize,
                     antientropy_public: opts.antientropy_public.unwrap_or(true),
+                    max_rpc_sessions: opts.max_rpc_sessions.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.max_rpc_sessions.clone()
+                    }),
+                    rpc_session_ttl: opts.rpc_session_ttl.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_session_ttl.clone()
+                    }),
+                    block_feed_heartbeat_interval: opts
+                        .block_feed_heartbeat_interval
+                        .unwrap_or_else(|| {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS
+                                .block_feed_heartbeat_interval
+                                .clone()
+                        }),
+                    accept_tx_bundles: opts.accept_tx_bundles.unwrap_or(false),
+                    read_only_call_cache_size: opts.read_only_call_cache_size.unwrap_or(0),
+                    read_only_call_cache_excluded_contracts: opts
+                        .read_only_call_cache_excluded_contracts
+                        .unwrap_or(vec![])
+                        .iter()
+                        .map(|contract_id| {
+                            QualifiedContractIdentifier::parse(contract_id).expect(&format!(
+                                "Invalid read_only_call_cache_excluded_contracts entry: {}",
+                                contract_id
+                            ))
+                        })
+                        .collect(),
+                    operator_contact: opts.operator_contact.unwrap_or_default(),
+                    node_metadata_refresh_interval: opts
+                        .node_metadata_refresh_interval
+                        .unwrap_or_else(|| {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS
+                                .node_metadata_refresh_interval
+                                .clone()
+                        }),
+                    banned_leader_key_policy: match opts.banned_leader_key_policy {
+                        Some(policy) => BannedLeaderKeyPolicy::from_str(&policy).expect(&format!(
+                            "Invalid banned_leader_key_policy: {} (expected ignore, deprioritize, or refuse)",
+                            &policy
+                        )),
+                        None => HELIUM_DEFAULT_CONNECTION_OPTIONS.banned_leader_key_policy,
+                    },
                     ..ConnectionOptions::default()
                 }
             }
@@ -1147,14 +1784,21 @@ impl Config {
             None => FeeEstimationConfig::default(),
         };
 
+        let alerts = match config_file.alerts {
+            Some(alerts) => AlertConfig::from(alerts),
+            None => AlertConfig::default(),
+        };
+
         Ok(Config {
             node,
             burnchain,
             initial_balances,
+            initial_lockups,
             events_observers,
             connection_options,
             estimation,
             miner,
+            alerts,
         })
     }
 
@@ -1228,6 +1872,26 @@ impl Config {
         path.to_str().expect("Unable to produce path").to_string()
     }
 
+    /// Returns the path `{working_dir}/{mode}/indexer.sqlite`, and ensures its directory exists.
+    pub fn get_indexer_db_file_path(&self) -> String {
+        let mut path = self.get_chainstate_path();
+        path.set_file_name("indexer.sqlite");
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).expect(&format!(
+                "Failed to create indexer directory at {}",
+                dir.to_string_lossy()
+            ));
+        }
+        path.to_str().expect("Unable to produce path").to_string()
+    }
+
+    /// Returns the path `{working_dir}/{mode}/indexer_checkpoints.sqlite`
+    pub fn get_indexer_checkpoints_db_file_path(&self) -> String {
+        let mut path = self.get_chainstate_path();
+        path.set_file_name("indexer_checkpoints.sqlite");
+        path.to_str().expect("Unable to produce path").to_string()
+    }
+
     pub fn add_initial_balance(&mut self, address: String, amount: u64) {
         let new_balance = InitialBalance {
             address: PrincipalData::parse_standard_principal(&address)
@@ -1238,6 +1902,29 @@ impl Config {
         self.initial_balances.push(new_balance);
     }
 
+    pub fn add_initial_lockup(&mut self, address: String, amount: u64, unlock_height: u64) {
+        let new_lockup = InitialLockup {
+            address: PrincipalData::parse_standard_principal(&address)
+                .unwrap()
+                .into(),
+            amount,
+            unlock_height,
+        };
+        self.initial_lockups.push(new_lockup);
+    }
+
+    /// The config's lockup schedules, in the form the chainstate boots with
+    pub fn get_initial_lockups(&self) -> Vec<ChainstateAccountLockup> {
+        self.initial_lockups
+            .iter()
+            .map(|lockup| ChainstateAccountLockup {
+                address: lockup.address.to_string(),
+                amount: lockup.amount,
+                block_height: lockup.unlock_height,
+            })
+            .collect()
+    }
+
     pub fn get_initial_liquid_ustx(&self) -> u128 {
         let mut total = 0;
         for ib in self.initial_balances.iter() {
@@ -1253,10 +1940,64 @@ impl Config {
         }
     }
 
+    /// The parameters of the network this node runs on.  The node identifies itself to its
+    /// peers by its chain ID.
+    pub fn get_network_id(&self) -> NetworkId {
+        match (
+            self.burnchain.address_version_singlesig,
+            self.burnchain.address_version_multisig,
+        ) {
+            (Some(singlesig), Some(multisig)) => {
+                NetworkId::custom(self.burnchain.chain_id, singlesig, multisig)
+            }
+            _ => NetworkId::from_chain_id(self.is_mainnet(), self.burnchain.chain_id),
+        }
+    }
+
     pub fn is_node_event_driven(&self) -> bool {
         self.events_observers.len() > 0
     }
 
+    /// Check that these networks can run side by side in one process.  Each network needs its
+    /// own working directory and ports, and only neon-style run loops can share a process.
+    pub fn check_multi_network(configs: &[Config]) -> Result<(), String> {
+        let mut working_dirs = HashSet::new();
+        let mut binds = HashSet::new();
+        for config in configs.iter() {
+            match config.burnchain.mode.as_str() {
+                "neon" | "xenon" | "krypton" | "mainnet" => {}
+                mode => {
+                    return Err(format!(
+                        "burnchain.mode '{}' cannot share a process with other networks",
+                        mode
+                    ));
+                }
+            }
+            if !working_dirs.insert(config.node.working_dir.clone()) {
+                return Err(format!(
+                    "node.working_dir '{}' is used by more than one network",
+                    &config.node.working_dir
+                ));
+            }
+            let node_binds = [
+                Some(&config.node.rpc_bind),
+                Some(&config.node.p2p_bind),
+                config.node.prometheus_bind.as_ref(),
+                config.node.indexer_bind.as_ref(),
+                config.node.event_replay_bind.as_ref(),
+            ];
+            for bind in node_binds.iter().filter_map(|bind| bind.clone()) {
+                if !binds.insert(bind.clone()) {
+                    return Err(format!(
+                        "Address '{}' is bound by more than one network",
+                        bind
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn make_block_builder_settings(
         &self,
         attempt: u64,
@@ -1287,6 +2028,7 @@ impl Config {
                 consider_no_estimate_tx_prob: self.miner.probability_pick_no_estimate_tx,
                 nonce_cache_size: self.miner.nonce_cache_size,
                 candidate_retry_cache_size: self.miner.candidate_retry_cache_size,
+                anchor_policy: self.miner.anchor_policy.clone(),
             },
             miner_status,
         }
@@ -1311,10 +2053,12 @@ impl std::default::Default for Config {
             burnchain,
             node,
             initial_balances: vec![],
+            initial_lockups: vec![],
             events_observers: vec![],
             connection_options,
             estimation,
             miner: MinerConfig::default(),
+            alerts: AlertConfig::default(),
         }
     }
 }
@@ -1349,8 +2093,24 @@ pub struct BurnchainConfig {
     pub pox_2_activation: Option<u32>,
     pub sunset_start: Option<u32>,
     pub sunset_end: Option<u32>,
+    /// Custom override for the PoX reward cycle length.  Not applied in mainnet.
+    pub pox_reward_length: Option<u32>,
+    /// Custom override for the PoX prepare phase length.  Not applied in mainnet.
+    pub pox_prepare_length: Option<u32>,
+    /// Address on which to serve the regtest control endpoint (regtest mode only)
+    pub regtest_control_bind: Option<String>,
     pub wallet_name: String,
     pub ast_precheck_size_height: Option<u64>,
+    /// Source code to instantiate boot contracts from instead of the built-in code, by contract
+    /// name (mocknet and regtest mode only)
+    pub boot_contract_overrides: HashMap<String, String>,
+    /// Custom address versions for a network with its own addresses.  Set together, and not
+    /// applied in mainnet.
+    pub address_version_singlesig: Option<u8>,
+    pub address_version_multisig: Option<u8>,
+    /// Custom parser and interpreter limits for a network whose contracts need more room than
+    /// the consensus limits allow.  Not applied in mainnet.
+    pub vm_limits: Option<VmLimits>,
 }
 
 impl BurnchainConfig {
@@ -1382,11 +2142,25 @@ impl BurnchainConfig {
             pox_2_activation: None,
             sunset_start: None,
             sunset_end: None,
+            pox_reward_length: None,
+            pox_prepare_length: None,
+            regtest_control_bind: None,
             wallet_name: "".to_string(),
             ast_precheck_size_height: None,
+            boot_contract_overrides: HashMap::new(),
+            address_version_singlesig: None,
+            address_version_multisig: None,
+            vm_limits: None,
         }
     }
 
+    /// Get the address to serve the regtest control endpoint on
+    pub fn get_regtest_control_bind(&self) -> String {
+        self.regtest_control_bind
+            .clone()
+            .unwrap_or(DEFAULT_REGTEST_CONTROL_BIND.to_string())
+    }
+
     pub fn get_rpc_url(&self, wallet: Option<String>) -> String {
         let scheme = match self.rpc_ssl {
             true => "https://",
@@ -1415,7 +2189,7 @@ impl BurnchainConfig {
         match self.mode.as_str() {
             "mainnet" => ("mainnet".to_string(), BitcoinNetworkType::Mainnet),
             "xenon" => ("testnet".to_string(), BitcoinNetworkType::Testnet),
-            "helium" | "neon" | "argon" | "krypton" | "mocknet" => {
+            "helium" | "neon" | "argon" | "krypton" | "mocknet" | "regtest" => {
                 ("regtest".to_string(), BitcoinNetworkType::Regtest)
             }
             _ => panic!("Invalid bitcoin mode -- expected mainnet, testnet, or regtest"),
@@ -1463,8 +2237,20 @@ pub struct BurnchainConfigFile {
     pub pox_2_activation: Option<u32>,
     pub sunset_start: Option<u32>,
     pub sunset_end: Option<u32>,
+    pub pox_reward_length: Option<u32>,
+    pub pox_prepare_length: Option<u32>,
+    pub regtest_control_bind: Option<String>,
     pub wallet_name: Option<String>,
     pub ast_precheck_size_height: Option<u64>,
+    /// Paths of files to load boot contracts from, by contract name
+    pub boot_contract_overrides: Option<HashMap<String, String>>,
+    pub chain_id: Option<u32>,
+    pub address_version_singlesig: Option<u8>,
+    pub address_version_multisig: Option<u8>,
+    /// VM limits for a private network.  Any that are not set keep their consensus values.
+    pub max_call_stack_depth: Option<usize>,
+    pub ast_call_stack_depth_buffer: Option<u64>,
+    pub max_contract_size: Option<usize>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -1487,7 +2273,30 @@ pub struct NodeConfig {
     pub wait_time_for_microblocks: u64,
     pub wait_time_for_blocks: u64,
     pub prometheus_bind: Option<String>,
+    /// If set, run the embedded indexer and serve its query API on this address
+    pub indexer_bind: Option<String>,
+    /// If set, serve the event replay control API on this address
+    pub event_replay_bind: Option<String>,
+    /// Number of threads that POST payloads to event observers, so that block processing does not
+    /// wait on them.  0 means payloads are POSTed by the thread that produced them.
+    pub event_dispatch_threads: u64,
+    /// How many payloads each event dispatch thread can have queued before producers block
+    pub event_dispatch_queue_depth: u64,
+    /// Number of threads that verify the transactions of downloaded Stacks blocks while the chains
+    /// coordinator processes earlier blocks.  0 means transactions are only verified by the
+    /// coordinator.
+    pub block_validation_threads: u64,
+    /// How many Stacks blocks can be queued for the block validation threads
+    pub block_validation_queue_depth: u64,
+    /// Number of threads that load unprocessed burnchain blocks while the chains coordinator
+    /// evaluates the sortitions before them.  0 means the coordinator loads each block itself.
+    pub burnchain_load_threads: u64,
+    /// How many burnchain blocks each burnchain loading thread can load ahead of the coordinator
+    pub burnchain_load_queue_depth: u64,
     pub marf_cache_strategy: Option<String>,
+    /// Soft cap, in MiB, on the memory held by the node's in-RAM caches (MARF nodes, buffered peer
+    /// messages, mempool walk caches).  0 means unlimited.
+    pub memory_budget_mb: u64,
     pub marf_defer_hashing: bool,
     pub pox_sync_sample_secs: u64,
     pub use_test_genesis_chainstate: Option<bool>,
@@ -1499,6 +2308,13 @@ pub struct NodeConfig {
     /// At most, how often should the chain-liveness thread
     ///  wake up the chains-coordinator. Defaults to 300s (5 min).
     pub chain_liveness_poll_time_secs: u64,
+    /// How many contracts the relayer re-analyzes per pass after an epoch
+    ///  transition. 0 disables contract re-analysis. Defaults to 8.
+    pub contract_reanalysis_batch_size: u32,
+    /// Record the fee rebates each processed block's transactions would have received under a
+    ///  uniform-price fee market in the chainstate's `fee_rebates` table. The table gains a row
+    ///  per transaction and is never pruned. Defaults to false.
+    pub fee_rebate_accounting: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -1637,6 +2453,22 @@ impl From<FeeEstimationConfigFile> for FeeEstimationConfig {
 }
 
 impl Config {
+    /// Build the pipeline of sidecar indexers the chains coordinator feeds, if any are enabled
+    pub fn make_indexer_pipeline(&self) -> Option<IndexerPipeline> {
+        self.node.indexer_bind.as_ref()?;
+
+        let mut pipeline = IndexerPipeline::open(&self.get_indexer_checkpoints_db_file_path())
+            .expect("FATAL: failed to open indexer checkpoint database");
+        let indexer = IndexerDB::connect(&self.get_indexer_db_file_path(), true)
+            .expect("FATAL: failed to open indexer database");
+        info!("Registering embedded indexer");
+        pipeline
+            .register(Box::new(indexer))
+            .expect("FATAL: failed to register embedded indexer");
+
+        Some(pipeline)
+    }
+
     pub fn make_cost_estimator(&self) -> Option<Box<dyn CostEstimator>> {
         let cost_estimator: Box<dyn CostEstimator> =
             match self.estimation.cost_estimator.as_ref()? {
@@ -1769,7 +2601,16 @@ impl NodeConfig {
             wait_time_for_microblocks: 30_000,
             wait_time_for_blocks: 30_000,
             prometheus_bind: None,
+            indexer_bind: None,
+            event_replay_bind: None,
+            event_dispatch_threads: 0,
+            event_dispatch_queue_depth: 64,
+            block_validation_threads: 0,
+            block_validation_queue_depth: 16,
+            burnchain_load_threads: 0,
+            burnchain_load_queue_depth: 16,
             marf_cache_strategy: None,
+            memory_budget_mb: 0,
             marf_defer_hashing: true,
             pox_sync_sample_secs: 30,
             use_test_genesis_chainstate: None,
@@ -1777,6 +2618,8 @@ impl NodeConfig {
             require_affirmed_anchor_blocks: true,
             fault_injection_hide_blocks: false,
             chain_liveness_poll_time_secs: 300,
+            contract_reanalysis_batch_size: 8,
+            fee_rebate_accounting: false,
         }
     }
 
@@ -1890,6 +2733,10 @@ pub struct MinerConfig {
     pub nonce_cache_size: u64,
     pub candidate_retry_cache_size: u64,
     pub unprocessed_block_deadline_secs: u64,
+    /// Which transactions to mine into anchored blocks and microblocks
+    pub anchor_policy: TransactionAnchorPolicy,
+    /// Reject transactions from the mempool that `anchor_policy` would never mine
+    pub mempool_enforce_anchor_policy: bool,
 }
 
 impl MinerConfig {
@@ -1906,6 +2753,8 @@ impl MinerConfig {
             nonce_cache_size: 10_000,
             candidate_retry_cache_size: 10_000,
             unprocessed_block_deadline_secs: 30,
+            anchor_policy: TransactionAnchorPolicy::any(),
+            mempool_enforce_anchor_policy: false,
         }
     }
 }
@@ -1952,6 +2801,15 @@ pub struct ConnectionOptionsFile {
     pub disable_block_download: Option<bool>,
     pub force_disconnect_interval: Option<u64>,
     pub antientropy_public: Option<bool>,
+    pub max_rpc_sessions: Option<u64>,
+    pub rpc_session_ttl: Option<u64>,
+    pub block_feed_heartbeat_interval: Option<u64>,
+    pub accept_tx_bundles: Option<bool>,
+    pub read_only_call_cache_size: Option<usize>,
+    pub read_only_call_cache_excluded_contracts: Option<Vec<String>>,
+    pub operator_contact: Option<String>,
+    pub node_metadata_refresh_interval: Option<u64>,
+    pub banned_leader_key_policy: Option<String>,
 }
 
 #[derive(Clone, Deserialize, Default, Debug)]
@@ -1974,7 +2832,16 @@ pub struct NodeConfigFile {
     pub wait_time_for_microblocks: Option<u64>,
     pub wait_time_for_blocks: Option<u64>,
     pub prometheus_bind: Option<String>,
+    pub indexer_bind: Option<String>,
+    pub event_replay_bind: Option<String>,
+    pub event_dispatch_threads: Option<u64>,
+    pub event_dispatch_queue_depth: Option<u64>,
+    pub block_validation_threads: Option<u64>,
+    pub block_validation_queue_depth: Option<u64>,
+    pub burnchain_load_threads: Option<u64>,
+    pub burnchain_load_queue_depth: Option<u64>,
     pub marf_cache_strategy: Option<String>,
+    pub memory_budget_mb: Option<u64>,
     pub marf_defer_hashing: Option<bool>,
     pub pox_sync_sample_secs: Option<u64>,
     pub use_test_genesis_chainstate: Option<bool>,
@@ -1983,6 +2850,11 @@ pub struct NodeConfigFile {
     /// At most, how often should the chain-liveness thread
     ///  wake up the chains-coordinator. Defaults to 300s (5 min).
     pub chain_liveness_poll_time_secs: Option<u64>,
+    /// How many contracts the relayer re-analyzes per pass after an epoch
+    ///  transition. 0 disables contract re-analysis. Defaults to 8.
+    pub contract_reanalysis_batch_size: Option<u32>,
+    /// Record would-be fee rebates in the chainstate's `fee_rebates` table. Defaults to false.
+    pub fee_rebate_accounting: Option<bool>,
 }
 
 #[derive(Clone, Deserialize, Debug)]
@@ -2022,6 +2894,96 @@ pub struct MinerConfigFile {
     pub nonce_cache_size: Option<u64>,
     pub candidate_retry_cache_size: Option<u64>,
     pub unprocessed_block_deadline_secs: Option<u64>,
+    pub anchor_mode_policy: Option<String>,
+    pub token_transfer_anchor_mode_policy: Option<String>,
+    pub contract_call_anchor_mode_policy: Option<String>,
+    pub smart_contract_anchor_mode_policy: Option<String>,
+    pub mempool_enforce_anchor_policy: Option<bool>,
+}
+
+impl MinerConfigFile {
+    /// Parse the anchor mode policies.  The per-class policies override `anchor_mode_policy`.
+    fn get_anchor_policy(&self) -> Result<TransactionAnchorPolicy, String> {
+        let parse = |setting: &str, value: &Option<String>, default: AnchorModePolicy| match value {
+            Some(value) => AnchorModePolicy::from_str(value).ok_or(format!(
+                "Invalid setting `miner.{}` (should be: anchored_only, microblocks_only, any)",
+                setting
+            )),
+            None => Ok(default),
+        };
+        let default = parse(
+            "anchor_mode_policy",
+            &self.anchor_mode_policy,
+            AnchorModePolicy::Any,
+        )?;
+        Ok(TransactionAnchorPolicy {
+            token_transfer: parse(
+                "token_transfer_anchor_mode_policy",
+                &self.token_transfer_anchor_mode_policy,
+                default,
+            )?,
+            contract_call: parse(
+                "contract_call_anchor_mode_policy",
+                &self.contract_call_anchor_mode_policy,
+                default,
+            )?,
+            smart_contract: parse(
+                "smart_contract_anchor_mode_policy",
+                &self.smart_contract_anchor_mode_policy,
+                default,
+            )?,
+        })
+    }
+}
+
+/// Operator alerting settings.  See `alerts` for what each threshold means; a condition with no
+/// threshold is not checked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlertConfig {
+    /// `http://` URLs to POST alert notices to.  Alerting is off if there are none.
+    pub webhooks: Vec<String>,
+    pub check_interval_secs: u64,
+    pub no_sortition_won_blocks: Option<u64>,
+    pub tip_stall_secs: Option<u64>,
+    pub marf_flush_ms: Option<u64>,
+    pub observer_backlog: Option<u64>,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            webhooks: vec![],
+            check_interval_secs: 30,
+            no_sortition_won_blocks: None,
+            tip_stall_secs: None,
+            marf_flush_ms: None,
+            observer_backlog: None,
+        }
+    }
+}
+
+impl From<AlertConfigFile> for AlertConfig {
+    fn from(f: AlertConfigFile) -> Self {
+        let default = AlertConfig::default();
+        Self {
+            webhooks: f.webhooks.unwrap_or(default.webhooks),
+            check_interval_secs: f.check_interval_secs.unwrap_or(default.check_interval_secs),
+            no_sortition_won_blocks: f.no_sortition_won_blocks,
+            tip_stall_secs: f.tip_stall_secs,
+            marf_flush_ms: f.marf_flush_ms,
+            observer_backlog: f.observer_backlog,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Default, Debug)]
+pub struct AlertConfigFile {
+    pub webhooks: Option<Vec<String>>,
+    pub check_interval_secs: Option<u64>,
+    pub no_sortition_won_blocks: Option<u64>,
+    pub tip_stall_secs: Option<u64>,
+    pub marf_flush_ms: Option<u64>,
+    pub observer_backlog: Option<u64>,
 }
 
 #[derive(Clone, Deserialize, Default, Debug)]
@@ -2119,3 +3081,20 @@ pub struct InitialBalanceFile {
     pub address: String,
     pub amount: u64,
 }
+
+/// STX that are minted to `address` when the Stacks chain reaches `unlock_height`, via the `lockup`
+/// boot contract.  This is the same mechanism mainnet's genesis vesting schedules use.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InitialLockup {
+    pub address: PrincipalData,
+    pub amount: u64,
+    /// Stacks block height at which the STX unlock
+    pub unlock_height: u64,
+}
+
+#[derive(Clone, Deserialize, Default, Debug)]
+pub struct InitialLockupFile {
+    pub address: String,
+    pub amount: u64,
+    pub unlock_height: u64,
+}
@@ -1,5 +1,7 @@
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread;
 use std::thread::sleep;
 use std::time::Duration;
 
@@ -11,6 +13,7 @@ use serde_json::json;
 use stacks::burnchains::{PoxConstants, Txid};
 use stacks::chainstate::coordinator::BlockEventDispatcher;
 use stacks::chainstate::stacks::address::PoxAddress;
+use stacks::chainstate::stacks::db::blocks::ReplayedStacksBlock;
 use stacks::chainstate::stacks::db::StacksHeaderInfo;
 use stacks::chainstate::stacks::events::{
     StacksTransactionEvent, StacksTransactionReceipt, TransactionOrigin,
@@ -21,6 +24,7 @@ use stacks::chainstate::stacks::{
 use stacks::chainstate::stacks::{StacksBlock, StacksMicroblock};
 use stacks::codec::StacksMessageCodec;
 use stacks::core::mempool::{MemPoolDropReason, MemPoolEventDispatcher};
+use stacks::monitoring::{self, NetworkMetrics};
 use stacks::net::atlas::{Attachment, AttachmentInstance};
 use stacks::types::chainstate::{BlockHeaderHash, BurnchainHeaderHash, StacksBlockId};
 use stacks::util::hash::bytes_to_hex;
@@ -30,8 +34,8 @@ use stacks::vm::events::{FTEventType, NFTEventType, STXEventType};
 use stacks::vm::types::{AssetIdentifier, QualifiedContractIdentifier, Value};
 
 use super::config::{EventKeyType, EventObserverConfig};
-use stacks::chainstate::burn::operations::BlockstackOperationType;
-use stacks::chainstate::burn::ConsensusHash;
+use stacks::chainstate::burn::operations::{BlockstackOperationType, LeaderBlockCommitOp};
+use stacks::chainstate::burn::{BlockSnapshot, ConsensusHash};
 use stacks::chainstate::stacks::db::unconfirmed::ProcessedUnconfirmedState;
 use stacks::chainstate::stacks::miner::TransactionEvent;
 use stacks::chainstate::stacks::TransactionPayload;
@@ -39,6 +43,17 @@ use stacks::chainstate::stacks::TransactionPayload;
 #[derive(Debug, Clone)]
 struct EventObserver {
     endpoint: String,
+    /// If set, payloads are handed to a dispatch thread over this queue instead of being
+    /// POSTed by the calling thread
+    dispatch_queue: Option<SyncSender<DispatchJob>>,
+}
+
+/// A payload waiting on a dispatch thread to be POSTed to `observer`
+#[derive(Debug)]
+struct DispatchJob {
+    observer: EventObserver,
+    payload: serde_json::Value,
+    path: String,
 }
 
 struct ReceiptPayloadInfo<'a> {
@@ -50,6 +65,12 @@ struct ReceiptPayloadInfo<'a> {
     burnchain_op_json: serde_json::Value,
 }
 
+/// Get the number of this network's payloads which are waiting to be delivered to an observer.
+/// Delivery is retried until it succeeds, so this grows while an observer is down or slow.
+pub fn get_pending_observer_payloads() -> u64 {
+    monitoring::get_pending_observer_payloads()
+}
+
 const STATUS_RESP_TRUE: &str = "success";
 const STATUS_RESP_NOT_COMMITTED: &str = "abort_by_response";
 const STATUS_RESP_POST_CONDITION: &str = "abort_by_post_condition";
@@ -61,6 +82,7 @@ pub const PATH_MEMPOOL_TX_DROP: &str = "drop_mempool_tx";
 pub const PATH_MINED_BLOCK: &str = "mined_block";
 pub const PATH_MINED_MICROBLOCK: &str = "mined_microblock";
 pub const PATH_BURN_BLOCK_SUBMIT: &str = "new_burn_block";
+pub const PATH_SORTITION_PROCESSED: &str = "new_sortition";
 pub const PATH_BLOCK_PROCESSED: &str = "new_block";
 pub const PATH_ATTACHMENT_PROCESSED: &str = "attachments/new";
 
@@ -85,11 +107,41 @@ pub struct MinedMicroblockEvent {
 }
 
 impl EventObserver {
+    /// POST the payload to this observer, or queue it for a dispatch thread to POST.  Queuing
+    /// blocks while the dispatch thread's queue is full.
     pub fn send_payload(&self, payload: &serde_json::Value, path: &str) {
+        // counted from here, so that payloads waiting in a dispatch queue are pending too
+        monitoring::increment_pending_observer_payloads();
+        if let Some(ref queue) = self.dispatch_queue {
+            let job = DispatchJob {
+                observer: EventObserver {
+                    endpoint: self.endpoint.clone(),
+                    dispatch_queue: None,
+                },
+                payload: payload.clone(),
+                path: path.to_string(),
+            };
+            match queue.send(job) {
+                Ok(()) => return,
+                Err(_) => {
+                    warn!(
+                        "Event dispatcher: dispatch thread for {} is gone; sending inline",
+                        &self.endpoint
+                    );
+                }
+            }
+        }
+        self.deliver_payload(payload, path)
+    }
+
+    /// POST the payload to this observer, retrying until it is accepted.  The payload must have
+    /// been counted as pending by `send_payload()`.
+    fn deliver_payload(&self, payload: &serde_json::Value, path: &str) {
         let body = match serde_json::to_vec(&payload) {
             Ok(body) => body,
             Err(err) => {
                 error!("Event dispatcher: serialization failed  - {:?}", err);
+                monitoring::decrement_pending_observer_payloads();
                 return;
             }
         };
@@ -146,6 +198,7 @@ impl EventObserver {
             }
             sleep(backoff);
         }
+        monitoring::decrement_pending_observer_payloads();
     }
 
     fn make_new_mempool_txs_payload(transactions: Vec<StacksTransaction>) -> serde_json::Value {
@@ -190,6 +243,69 @@ impl EventObserver {
         })
     }
 
+    fn make_block_commit_json(commit: &LeaderBlockCommitOp) -> serde_json::Value {
+        let commit_outs: Vec<_> = commit
+            .commit_outs
+            .iter()
+            .map(|pox_addr| json!(pox_addr.clone().to_b58()))
+            .collect();
+
+        json!({
+            "txid": format!("0x{}", commit.txid),
+            "vtxindex": commit.vtxindex,
+            "sender": commit.apparent_sender.to_string(),
+            "stacks_block_hash": format!("0x{}", commit.block_header_hash),
+            "parent_block_ptr": commit.parent_block_ptr,
+            "parent_vtxindex": commit.parent_vtxindex,
+            "burn_fee": commit.burn_fee,
+            "sunset_burn": commit.sunset_burn,
+            "commit_outs": serde_json::Value::Array(commit_outs),
+        })
+    }
+
+    fn make_new_sortition_payload(
+        snapshot: &BlockSnapshot,
+        winning_commit: Option<&LeaderBlockCommitOp>,
+        block_commits: &[LeaderBlockCommitOp],
+        rewards: Vec<(PoxAddress, u64)>,
+        burns: u64,
+    ) -> serde_json::Value {
+        let reward_recipients = rewards
+            .into_iter()
+            .map(|(pox_addr, amt)| {
+                json!({
+                    "recipient": pox_addr.to_b58(),
+                    "amt": amt,
+                })
+            })
+            .collect();
+
+        let block_commits = block_commits
+            .iter()
+            .map(EventObserver::make_block_commit_json)
+            .collect();
+
+        let winning_commit = match winning_commit {
+            Some(commit) => EventObserver::make_block_commit_json(commit),
+            None => serde_json::Value::Null,
+        };
+
+        json!({
+            "burn_block_hash": format!("0x{}", snapshot.burn_header_hash),
+            "burn_block_height": snapshot.block_height,
+            "burn_block_timestamp": snapshot.burn_header_timestamp,
+            "parent_burn_block_hash": format!("0x{}", snapshot.parent_burn_header_hash),
+            "consensus_hash": format!("0x{}", snapshot.consensus_hash),
+            "sortition_id": format!("0x{}", snapshot.sortition_id),
+            "sortition": snapshot.sortition,
+            "winning_commit": winning_commit,
+            "block_commits": serde_json::Value::Array(block_commits),
+            "total_burn": snapshot.total_burn,
+            "reward_recipients": serde_json::Value::Array(reward_recipients),
+            "burn_amount": burns
+        })
+    }
+
     /// Returns tuple of (txid, success, raw_result, raw_tx, contract_interface_json)
     fn generate_payload_info_for_receipt(receipt: &StacksTransactionReceipt) -> ReceiptPayloadInfo {
         let tx = &receipt.transaction;
@@ -265,6 +381,8 @@ impl EventObserver {
             "contract_abi": receipt_payload_info.contract_interface_json,
             "burnchain_op": receipt_payload_info.burnchain_op_json,
             "execution_cost": receipt.execution_cost,
+            "vm_error_code": receipt.vm_error_code,
+            "tx_size": receipt.tx_size,
             "microblock_sequence": receipt.microblock_header.as_ref().map(|x| x.sequence),
             "microblock_hash": receipt.microblock_header.as_ref().map(|x| format!("0x{}", x.block_hash())),
             "microblock_parent_hash": receipt.microblock_header.as_ref().map(|x| format!("0x{}", x.prev_block)),
@@ -340,6 +458,10 @@ impl EventObserver {
         self.send_payload(payload, PATH_BURN_BLOCK_SUBMIT);
     }
 
+    fn send_new_sortition(&self, payload: &serde_json::Value) {
+        self.send_payload(payload, PATH_SORTITION_PROCESSED);
+    }
+
     fn make_new_block_processed_payload(
         &self,
         filtered_events: Vec<(usize, &(bool, Txid, &StacksTransactionEvent))>,
@@ -392,6 +514,7 @@ impl EventObserver {
             "parent_burn_block_height": parent_burn_block_height,
             "parent_burn_block_timestamp": parent_burn_block_timestamp,
             "anchored_cost": anchored_consumed,
+            "anchored_block_size": metadata.anchored_block_size,
             "confirmed_microblocks_cost": mblock_confirmed_consumed,
             "pox_v1_unlock_height": pox_constants.v1_unlock_height,
             "pox_v2_unlock_height": pox_constants.v2_unlock_height,
@@ -505,6 +628,17 @@ impl BlockEventDispatcher for EventDispatcher {
             recipient_info,
         )
     }
+
+    fn announce_sortition(
+        &self,
+        snapshot: &BlockSnapshot,
+        winning_commit: Option<&LeaderBlockCommitOp>,
+        block_commits: &[LeaderBlockCommitOp],
+        rewards: Vec<(PoxAddress, u64)>,
+        burns: u64,
+    ) {
+        self.process_sortition(snapshot, winning_commit, block_commits, rewards, burns)
+    }
 }
 
 impl EventDispatcher {
@@ -558,6 +692,42 @@ impl EventDispatcher {
         }
     }
 
+    /// Sortition outcomes go to the same observers as burn blocks
+    pub fn process_sortition(
+        &self,
+        snapshot: &BlockSnapshot,
+        winning_commit: Option<&LeaderBlockCommitOp>,
+        block_commits: &[LeaderBlockCommitOp],
+        rewards: Vec<(PoxAddress, u64)>,
+        burns: u64,
+    ) {
+        // lazily assemble payload only if we have observers
+        let interested_observers: Vec<_> = self
+            .registered_observers
+            .iter()
+            .enumerate()
+            .filter(|(obs_id, _observer)| {
+                self.burn_block_observers_lookup.contains(&(*obs_id as u16))
+                    || self.any_event_observers_lookup.contains(&(*obs_id as u16))
+            })
+            .collect();
+        if interested_observers.len() < 1 {
+            return;
+        }
+
+        let payload = EventObserver::make_new_sortition_payload(
+            snapshot,
+            winning_commit,
+            block_commits,
+            rewards,
+            burns,
+        );
+
+        for (_, observer) in interested_observers.iter() {
+            observer.send_new_sortition(&payload);
+        }
+    }
+
     /// Iterates through tx receipts, and then the events corresponding to each receipt to
     /// generate a dispatch matrix & event vector.
     ///
@@ -596,7 +766,8 @@ impl EventDispatcher {
                     StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(_))
                     | StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(_))
                     | StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(_))
-                    | StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(_)) => {
+                    | StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(_))
+                    | StacksTransactionEvent::STXEvent(STXEventType::STXUnlockEvent(_)) => {
                         for o_i in &self.stx_observers_lookup {
                             dispatch_matrix[*o_i as usize].insert(i);
                         }
@@ -645,6 +816,11 @@ impl EventDispatcher {
                             &mut dispatch_matrix,
                         );
                     }
+                    StacksTransactionEvent::PoxDefunctCallEvent(_) => {
+                        for o_i in &self.stx_observers_lookup {
+                            dispatch_matrix[*o_i as usize].insert(i);
+                        }
+                    }
                 }
                 events.push((!receipt.post_condition_aborted, tx_hash, event));
                 for o_i in &self.any_event_observers_lookup {
@@ -657,6 +833,35 @@ impl EventDispatcher {
         (dispatch_matrix, events)
     }
 
+    /// Serialize the miner rewards that matured in a block
+    fn make_mature_rewards_payload(
+        mature_rewards: &[MinerReward],
+        mature_rewards_info: Option<&MinerRewardInfo>,
+    ) -> serde_json::Value {
+        let mature_rewards_vec = if let Some(rewards_info) = mature_rewards_info {
+            mature_rewards
+                .iter()
+                .map(|reward| {
+                    json!({
+                        "recipient": reward.recipient.to_string(),
+                        "miner_address": reward.address.to_string(),
+                        "coinbase_amount": reward.coinbase.to_string(),
+                        "tx_fees_anchored": reward.tx_fees_anchored.to_string(),
+                        "tx_fees_streamed_confirmed": reward.tx_fees_streamed_confirmed.to_string(),
+                        "tx_fees_streamed_produced": reward.tx_fees_streamed_produced.to_string(),
+                        "from_stacks_block_hash": format!("0x{}", rewards_info.from_stacks_block_hash),
+                        "from_index_consensus_hash": format!("0x{}", StacksBlockId::new(&rewards_info.from_block_consensus_hash,
+                                                                                        &rewards_info.from_stacks_block_hash)),
+                    })
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        serde_json::Value::Array(mature_rewards_vec)
+    }
+
     pub fn process_chain_tip(
         &self,
         block: &StacksBlock,
@@ -677,28 +882,8 @@ impl EventDispatcher {
         let (dispatch_matrix, events) = self.create_dispatch_matrix_and_event_vector(&all_receipts);
 
         if dispatch_matrix.len() > 0 {
-            let mature_rewards_vec = if let Some(rewards_info) = mature_rewards_info {
-                mature_rewards
-                    .iter()
-                    .map(|reward| {
-                        json!({
-                            "recipient": reward.recipient.to_string(),
-                            "miner_address": reward.address.to_string(),
-                            "coinbase_amount": reward.coinbase.to_string(),
-                            "tx_fees_anchored": reward.tx_fees_anchored.to_string(),
-                            "tx_fees_streamed_confirmed": reward.tx_fees_streamed_confirmed.to_string(),
-                            "tx_fees_streamed_produced": reward.tx_fees_streamed_produced.to_string(),
-                            "from_stacks_block_hash": format!("0x{}", rewards_info.from_stacks_block_hash),
-                            "from_index_consensus_hash": format!("0x{}", StacksBlockId::new(&rewards_info.from_block_consensus_hash,
-                                                                                            &rewards_info.from_stacks_block_hash)),
-                        })
-                    })
-                    .collect()
-            } else {
-                vec![]
-            };
-
-            let mature_rewards = serde_json::Value::Array(mature_rewards_vec);
+            let mature_rewards =
+                EventDispatcher::make_mature_rewards_payload(mature_rewards, mature_rewards_info);
 
             for (observer_id, filtered_events_ids) in dispatch_matrix.iter().enumerate() {
                 let filtered_events: Vec<_> = filtered_events_ids
@@ -729,6 +914,66 @@ impl EventDispatcher {
         }
     }
 
+    /// Is an observer registered at this endpoint?
+    pub fn has_observer(&self, endpoint: &str) -> bool {
+        self.registered_observers
+            .iter()
+            .any(|observer| observer.endpoint == endpoint)
+    }
+
+    /// Re-send a replayed block's `new_block` event to the registered observer at `endpoint`
+    /// only, filtered by that observer's subscriptions.
+    /// Returns false if there is no such observer.
+    pub fn replay_block_to_observer(
+        &self,
+        endpoint: &str,
+        replayed: &ReplayedStacksBlock,
+        pox_constants: &PoxConstants,
+    ) -> bool {
+        let observer_id = match self
+            .registered_observers
+            .iter()
+            .position(|observer| observer.endpoint == endpoint)
+        {
+            Some(observer_id) => observer_id,
+            None => {
+                return false;
+            }
+        };
+
+        let epoch_receipt = &replayed.epoch_receipt;
+        let (dispatch_matrix, events) =
+            self.create_dispatch_matrix_and_event_vector(&epoch_receipt.tx_receipts);
+        let filtered_events: Vec<_> = dispatch_matrix[observer_id]
+            .iter()
+            .map(|event_id| (*event_id, &events[*event_id]))
+            .collect();
+
+        let mature_rewards = EventDispatcher::make_mature_rewards_payload(
+            &epoch_receipt.matured_rewards,
+            epoch_receipt.matured_rewards_info.as_ref(),
+        );
+
+        let observer = &self.registered_observers[observer_id];
+        let payload = observer.make_new_block_processed_payload(
+            filtered_events,
+            &replayed.block,
+            &epoch_receipt.header,
+            &epoch_receipt.tx_receipts,
+            &replayed.parent_index_hash,
+            &replayed.winner_txid,
+            &mature_rewards,
+            epoch_receipt.parent_burn_block_hash,
+            epoch_receipt.parent_burn_block_height,
+            epoch_receipt.parent_burn_block_timestamp,
+            &epoch_receipt.anchored_block_cost,
+            &epoch_receipt.parent_microblocks_cost,
+            pox_constants,
+        );
+        observer.send_payload(&payload, PATH_BLOCK_PROCESSED);
+        true
+    }
+
     /// Creates a list of observers that are interested in the new microblocks event,
     /// creates a mapping from observers to the event ids that are relevant to each, and then
     /// sends the event to each interested observer.
@@ -940,10 +1185,48 @@ impl EventDispatcher {
         }
     }
 
+    /// Move delivery of observer payloads off the threads that produce them (e.g. the chains
+    /// coordinator) and onto up to `num_threads` dispatch threads, each with a queue of at most
+    /// `queue_depth` payloads.  Each observer is served by a single dispatch thread, so it still
+    /// receives its payloads in the order they were produced.  Only observers registered before
+    /// this is called are affected.  Returns the number of dispatch threads started.
+    pub fn start_dispatch_threads(&mut self, num_threads: usize, queue_depth: usize) -> usize {
+        let num_threads = num_threads.min(self.registered_observers.len());
+        let mut queues = Vec::with_capacity(num_threads);
+        for thread_id in 0..num_threads {
+            let (queue_tx, queue_rx) = sync_channel(queue_depth);
+            let metrics = NetworkMetrics::current();
+            thread::Builder::new()
+                .name(format!("event-dispatch-{}", thread_id))
+                .spawn(move || {
+                    metrics.enter();
+                    EventDispatcher::run_dispatch_thread(queue_rx)
+                })
+                .expect("FATAL: failed to start event dispatch thread");
+            queues.push(queue_tx);
+        }
+        if num_threads > 0 {
+            for (observer_id, observer) in self.registered_observers.iter_mut().enumerate() {
+                observer.dispatch_queue = Some(queues[observer_id % num_threads].clone());
+            }
+            info!("Started {} event dispatch thread(s)", num_threads);
+        }
+        num_threads
+    }
+
+    /// Deliver queued payloads until every producer has gone away
+    fn run_dispatch_thread(queue: Receiver<DispatchJob>) {
+        while let Ok(job) = queue.recv() {
+            job.observer.deliver_payload(&job.payload, &job.path);
+        }
+        debug!("Event dispatch thread exiting");
+    }
+
     pub fn register_observer(&mut self, conf: &EventObserverConfig) {
         info!("Registering event observer at: {}", conf.endpoint);
         let event_observer = EventObserver {
             endpoint: conf.endpoint.clone(),
+            dispatch_queue: None,
         };
 
         let observer_index = self.registered_observers.len() as u16;
@@ -1008,9 +1291,11 @@ impl EventDispatcher {
 
 #[cfg(test)]
 mod test {
-    use crate::event_dispatcher::EventObserver;
+    use crate::config::EventObserverConfig;
+    use crate::event_dispatcher::{EventDispatcher, EventObserver};
     use clarity::vm::costs::ExecutionCost;
     use stacks::burnchains::{PoxConstants, Txid};
+    use stacks::chainstate::burn::BlockSnapshot;
     use stacks::chainstate::stacks::db::StacksHeaderInfo;
     use stacks::chainstate::stacks::StacksBlock;
     use stacks_common::types::chainstate::{BurnchainHeaderHash, StacksBlockId};
@@ -1019,6 +1304,7 @@ mod test {
     fn build_block_processed_event() {
         let observer = EventObserver {
             endpoint: "nowhere".to_string(),
+            dispatch_queue: None,
         };
 
         let filtered_events = vec![];
@@ -1059,4 +1345,54 @@ mod test {
             pox_constants.v1_unlock_height as u64
         );
     }
+
+    #[test]
+    fn build_missed_sortition_event() {
+        let snapshot = BlockSnapshot::initial(100, &BurnchainHeaderHash([1; 32]), 1234);
+        let payload = EventObserver::make_new_sortition_payload(&snapshot, None, &[], vec![], 10);
+
+        assert_eq!(payload.get("sortition").unwrap().as_bool(), Some(false));
+        assert!(payload.get("winning_commit").unwrap().is_null());
+        assert_eq!(
+            payload
+                .get("block_commits")
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            0
+        );
+        assert_eq!(
+            payload.get("burn_block_height").unwrap().as_u64(),
+            Some(100)
+        );
+        assert_eq!(payload.get("burn_amount").unwrap().as_u64(), Some(10));
+    }
+
+    #[test]
+    fn start_dispatch_threads() {
+        let mut dispatcher = EventDispatcher::new();
+        assert_eq!(dispatcher.start_dispatch_threads(4, 16), 0);
+
+        for port in 0..3 {
+            dispatcher.register_observer(&EventObserverConfig {
+                endpoint: format!("127.0.0.1:{}", 20000 + port),
+                events_keys: vec![],
+            });
+        }
+        assert!(dispatcher
+            .registered_observers
+            .iter()
+            .all(|observer| observer.dispatch_queue.is_none()));
+
+        // never more threads than observers
+        let mut capped = dispatcher.clone();
+        assert_eq!(capped.start_dispatch_threads(8, 16), 3);
+
+        assert_eq!(dispatcher.start_dispatch_threads(2, 16), 2);
+        assert!(dispatcher
+            .registered_observers
+            .iter()
+            .all(|observer| observer.dispatch_queue.is_some()));
+    }
 }
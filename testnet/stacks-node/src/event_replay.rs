@@ -0,0 +1,462 @@
+//! Event replay for late-joining event observers.
+//!
+//! When `node.event_replay_bind` is set, the node serves a small control API on that address
+//! which re-sends the `new_block` events for a range of canonical Stacks block heights to one of
+//! its configured event observers.  Receipts are not stored, so each block is re-executed on top
+//! of its parent's state to regenerate them.  See `docs/event-replay.md` for the endpoints.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use async_std::net::{TcpListener, TcpStream};
+use async_std::prelude::*;
+use async_std::task;
+use http_types::{Method, Request, Response, StatusCode};
+
+use stacks::burnchains::PoxConstants;
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::types::chainstate::StacksBlockId;
+use stacks_common::util::sleep_ms;
+
+use crate::{Config, EventDispatcher};
+
+/// Default and maximum number of blocks re-sent per second
+pub const DEFAULT_REPLAY_BLOCKS_PER_SECOND: u64 = 10;
+pub const MAX_REPLAY_BLOCKS_PER_SECOND: u64 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayState {
+    Running,
+    Finished,
+    Cancelled,
+    Failed,
+}
+
+/// Progress of the current (or last) replay
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayStatus {
+    pub observer: String,
+    pub start_height: u64,
+    pub end_height: u64,
+    /// The next height to send.  A cancelled or failed replay resumes from here.
+    pub next_height: u64,
+    pub blocks_per_second: u64,
+    pub state: ReplayState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Body of a request to start a replay
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayRequest {
+    pub observer: String,
+    /// Defaults to where the last replay to this observer stopped
+    #[serde(default)]
+    pub start_height: Option<u64>,
+    /// Defaults to the height of the canonical Stacks tip
+    #[serde(default)]
+    pub end_height: Option<u64>,
+    #[serde(default)]
+    pub blocks_per_second: Option<u64>,
+}
+
+/// Runs at most one replay at a time, on its own thread
+#[derive(Clone)]
+pub struct EventReplayer {
+    config: Config,
+    event_dispatcher: EventDispatcher,
+    pox_constants: PoxConstants,
+    status: Arc<Mutex<Option<ReplayStatus>>>,
+}
+
+impl ReplayStatus {
+    fn is_running(&self) -> bool {
+        self.state == ReplayState::Running
+    }
+}
+
+impl EventReplayer {
+    pub fn new(
+        config: Config,
+        event_dispatcher: EventDispatcher,
+        pox_constants: PoxConstants,
+    ) -> EventReplayer {
+        EventReplayer {
+            config,
+            event_dispatcher,
+            pox_constants,
+            status: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn get_status(&self) -> Option<ReplayStatus> {
+        self.status
+            .lock()
+            .expect("FATAL: replay status lock poisoned")
+            .clone()
+    }
+
+    fn open_dbs(&self) -> Result<(SortitionDB, StacksChainState), String> {
+        let sortdb = SortitionDB::open(
+            &self.config.get_burn_db_file_path(),
+            false,
+            self.pox_constants.clone(),
+        )
+        .map_err(|e| format!("Failed to open sortition DB: {:?}", &e))?;
+        let (chainstate, _) = StacksChainState::open_and_exec(
+            self.config.get_network_id(),
+            &self.config.get_chainstate_path_str(),
+            None,
+            Some(self.config.node.get_marf_opts()),
+        )
+        .map_err(|e| format!("Failed to open chainstate: {:?}", &e))?;
+        Ok((sortdb, chainstate))
+    }
+
+    /// Start replaying blocks to an observer.  Fails if a replay is already running, if the
+    /// observer is not registered, or if the height range is empty.
+    pub fn start(&self, request: ReplayRequest) -> Result<ReplayStatus, (StatusCode, String)> {
+        let mut status_guard = self
+            .status
+            .lock()
+            .expect("FATAL: replay status lock poisoned");
+        if let Some(ref status) = *status_guard {
+            if status.is_running() {
+                return Err((
+                    StatusCode::Conflict,
+                    format!("Already replaying events to {}", &status.observer),
+                ));
+            }
+        }
+        if !self.event_dispatcher.has_observer(&request.observer) {
+            return Err((
+                StatusCode::BadRequest,
+                format!("No event observer is registered at {}", &request.observer),
+            ));
+        }
+
+        let blocks_per_second = request
+            .blocks_per_second
+            .unwrap_or(DEFAULT_REPLAY_BLOCKS_PER_SECOND);
+        if blocks_per_second == 0 || blocks_per_second > MAX_REPLAY_BLOCKS_PER_SECOND {
+            return Err((
+                StatusCode::BadRequest,
+                format!(
+                    "`blocks_per_second` must be between 1 and {}",
+                    MAX_REPLAY_BLOCKS_PER_SECOND
+                ),
+            ));
+        }
+
+        let start_height = match (request.start_height, status_guard.as_ref()) {
+            (Some(start_height), _) => start_height,
+            (None, Some(last)) if last.observer == request.observer => last.next_height,
+            (None, _) => {
+                return Err((
+                    StatusCode::BadRequest,
+                    "`start_height` is required, since there is no replay to this observer to resume"
+                        .to_string(),
+                ));
+            }
+        };
+
+        let (sortdb, chainstate) = self
+            .open_dbs()
+            .map_err(|msg| (StatusCode::ServiceUnavailable, msg))?;
+        let tip_height = get_canonical_stacks_tip(&sortdb, &chainstate)
+            .map_err(|msg| (StatusCode::ServiceUnavailable, msg))?
+            .map(|(_, tip_height)| tip_height)
+            .unwrap_or(0);
+
+        let end_height = request.end_height.unwrap_or(tip_height).min(tip_height);
+        if start_height == 0 || start_height > end_height {
+            return Err((
+                StatusCode::BadRequest,
+                format!(
+                    "Nothing to replay: heights must be between 1 and {}, but got {} to {}",
+                    tip_height, start_height, end_height
+                ),
+            ));
+        }
+
+        let status = ReplayStatus {
+            observer: request.observer,
+            start_height,
+            end_height,
+            next_height: start_height,
+            blocks_per_second,
+            state: ReplayState::Running,
+            error: None,
+        };
+        *status_guard = Some(status.clone());
+
+        let replayer = self.clone();
+        thread::Builder::new()
+            .name("event-replay".to_string())
+            .spawn(move || {
+                debug!("event replay thread ID is {:?}", thread::current().id());
+                replayer.run(sortdb, chainstate);
+            })
+            .map_err(|e| {
+                *status_guard = None;
+                (
+                    StatusCode::InternalServerError,
+                    format!("Failed to start replay thread: {:?}", &e),
+                )
+            })?;
+
+        Ok(status)
+    }
+
+    /// Cancel the running replay, if any.  It stops before sending its next block.
+    pub fn cancel(&self) -> Option<ReplayStatus> {
+        let mut status_guard = self
+            .status
+            .lock()
+            .expect("FATAL: replay status lock poisoned");
+        if let Some(ref mut status) = *status_guard {
+            if status.is_running() {
+                status.state = ReplayState::Cancelled;
+            }
+        }
+        status_guard.clone()
+    }
+
+    /// Record that `height` was sent, and find the next height to send, if the replay has not
+    /// finished or been cancelled.
+    fn advance(&self, height: u64) -> Option<u64> {
+        let mut status_guard = self
+            .status
+            .lock()
+            .expect("FATAL: replay status lock poisoned");
+        let status = status_guard.as_mut()?;
+        status.next_height = height + 1;
+        if !status.is_running() {
+            return None;
+        }
+        if status.next_height > status.end_height {
+            status.state = ReplayState::Finished;
+            return None;
+        }
+        Some(status.next_height)
+    }
+
+    fn fail(&self, error: String) {
+        warn!("Event replay: {}", &error);
+        let mut status_guard = self
+            .status
+            .lock()
+            .expect("FATAL: replay status lock poisoned");
+        if let Some(ref mut status) = *status_guard {
+            status.state = ReplayState::Failed;
+            status.error = Some(error);
+        }
+    }
+
+    /// Replay each block in the requested range, no faster than the requested rate
+    fn run(&self, sortdb: SortitionDB, mut chainstate: StacksChainState) {
+        let (observer, mut height, blocks_per_second) = match self.get_status() {
+            Some(status) => (
+                status.observer,
+                status.start_height,
+                status.blocks_per_second,
+            ),
+            None => {
+                return;
+            }
+        };
+        let min_block_time_ms = 1000 / blocks_per_second;
+
+        info!(
+            "Event replay: re-sending blocks to {} starting at height {}",
+            &observer, height
+        );
+
+        loop {
+            let start_time = Instant::now();
+
+            let tip = match get_canonical_stacks_tip(&sortdb, &chainstate) {
+                Ok(Some((tip, _))) => tip,
+                Ok(None) => {
+                    return self.fail("No canonical Stacks tip".to_string());
+                }
+                Err(msg) => {
+                    return self.fail(msg);
+                }
+            };
+            let index_block_hash = match chainstate
+                .index_conn()
+                .and_then(|conn| Ok(conn.get_ancestor_block_hash(height, &tip)?))
+            {
+                Ok(Some(index_block_hash)) => index_block_hash,
+                Ok(None) => {
+                    return self.fail(format!("No canonical block at height {}", height));
+                }
+                Err(e) => {
+                    return self.fail(format!(
+                        "Failed to find canonical block at height {}: {:?}",
+                        height, &e
+                    ));
+                }
+            };
+
+            let replayed =
+                match chainstate.replay_processed_block(&sortdb.index_conn(), &index_block_hash) {
+                    Ok(Some(replayed)) => replayed,
+                    Ok(None) => {
+                        return self.fail(format!(
+                            "Block {} at height {} is not available",
+                            &index_block_hash, height
+                        ));
+                    }
+                    Err(e) => {
+                        return self.fail(format!(
+                            "Failed to replay block {} at height {}: {:?}",
+                            &index_block_hash, height, &e
+                        ));
+                    }
+                };
+
+            if !self.event_dispatcher.replay_block_to_observer(
+                &observer,
+                &replayed,
+                &self.pox_constants,
+            ) {
+                return self.fail(format!("Event observer {} went away", &observer));
+            }
+
+            height = match self.advance(height) {
+                Some(next_height) => next_height,
+                None => {
+                    info!(
+                        "Event replay: stopped re-sending blocks to {} after height {}",
+                        &observer, height
+                    );
+                    return;
+                }
+            };
+
+            let elapsed_ms = start_time.elapsed().as_millis() as u64;
+            if elapsed_ms < min_block_time_ms {
+                sleep_ms(min_block_time_ms - elapsed_ms);
+            }
+        }
+    }
+}
+
+/// Get the canonical Stacks tip and its height, if there is one
+fn get_canonical_stacks_tip(
+    sortdb: &SortitionDB,
+    chainstate: &StacksChainState,
+) -> Result<Option<(StacksBlockId, u64)>, String> {
+    let (consensus_hash, block_hash) =
+        SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())
+            .map_err(|e| format!("Failed to load canonical Stacks tip: {:?}", &e))?;
+    let tip = StacksBlockId::new(&consensus_hash, &block_hash);
+    let header_info =
+        StacksChainState::get_stacks_block_header_info_by_index_block_hash(chainstate.db(), &tip)
+            .map_err(|e| format!("Failed to load canonical Stacks tip: {:?}", &e))?;
+    Ok(header_info.map(|header_info| (tip, header_info.stacks_block_height)))
+}
+
+/// Serve the event replay control API on `bind_address`.  This blocks the calling thread.
+pub fn start_serving_event_replay_api(bind_address: String, replayer: EventReplayer) {
+    task::block_on(async {
+        let listener = TcpListener::bind(bind_address)
+            .await
+            .expect("Event replay: unable to bind address");
+        info!(
+            "Event replay: server listening on http://{}",
+            listener
+                .local_addr()
+                .expect("Event replay: unable to get addr")
+        );
+
+        let mut incoming = listener.incoming();
+        while let Some(stream) = incoming.next().await {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    error!("Event replay: unable to open socket - {:?}", err);
+                    continue;
+                }
+            };
+            let replayer = replayer.clone();
+            task::spawn(async move {
+                if let Err(err) = accept(stream, replayer).await {
+                    warn!("Event replay: failed to handle request: {}", err);
+                }
+            });
+        }
+    });
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response {
+    let mut response = Response::new(status);
+    response.set_body(body);
+    response
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    json_response(status, json!({ "error": message }))
+}
+
+fn status_response(status: Option<ReplayStatus>) -> Response {
+    match status {
+        Some(status) => json_response(StatusCode::Ok, json!(status)),
+        None => error_response(
+            StatusCode::NotFound,
+            "No replay has been started".to_string(),
+        ),
+    }
+}
+
+async fn handle_request(mut req: Request, replayer: EventReplayer) -> Response {
+    if req.url().path() != "/v1/replay" {
+        return error_response(
+            StatusCode::NotFound,
+            format!("No such endpoint {}", req.url().path()),
+        );
+    }
+    match req.method() {
+        Method::Get => status_response(replayer.get_status()),
+        Method::Delete => status_response(replayer.cancel()),
+        Method::Post => {
+            let request: ReplayRequest = match req.body_json().await {
+                Ok(request) => request,
+                Err(e) => {
+                    return error_response(
+                        StatusCode::BadRequest,
+                        format!("Invalid replay request: {}", e),
+                    );
+                }
+            };
+            match replayer.start(request) {
+                Ok(status) => json_response(StatusCode::Accepted, json!(status)),
+                Err((code, message)) => error_response(code, message),
+            }
+        }
+        _ => error_response(
+            StatusCode::MethodNotAllowed,
+            "Only GET, POST, and DELETE are supported".to_string(),
+        ),
+    }
+}
+
+async fn accept(stream: TcpStream, replayer: EventReplayer) -> http_types::Result<()> {
+    debug!("Handle event replay request ({})", stream.peer_addr()?);
+    async_h1::accept(stream.clone(), |req| {
+        let replayer = replayer.clone();
+        async move {
+            let mut response = handle_request(req, replayer).await;
+            response.append_header("Content-Type", "application/json");
+            Ok(response)
+        }
+    })
+    .await?;
+    Ok(())
+}
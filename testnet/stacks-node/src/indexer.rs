@@ -0,0 +1,240 @@
+//! Query API for the embedded indexer.
+//!
+//! When `node.indexer_bind` is set, the node indexes every processed block into
+//! `{working_dir}/{mode}/indexer.sqlite` and serves the indices over HTTP on that address.  See
+//! `docs/embedded-indexer.md` for the endpoints.
+//!
+//! Blocks processed before the indexer was enabled can be indexed with the `rebuild-indexer`
+//! subcommand, which re-executes them from the chainstate.
+
+use std::time::Instant;
+
+use async_std::net::{TcpListener, TcpStream};
+use async_std::prelude::*;
+use async_std::task;
+use http_types::{Method, Response, StatusCode};
+
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::indexer::rebuild::{rebuild_index, RebuildProgress};
+use stacks::indexer::IndexerDB;
+use stacks::net::pagination::{PageRequest, RPCPage};
+use stacks::vm::types::{PrincipalData, QualifiedContractIdentifier};
+
+use crate::Config;
+
+/// Default and maximum number of items in a page of indexer results
+pub const DEFAULT_INDEXER_PAGE_LIMIT: u64 = 50;
+pub const MAX_INDEXER_PAGE_LIMIT: u64 = 200;
+
+/// How often a rebuild logs its progress
+pub const REBUILD_PROGRESS_INTERVAL_SECS: u64 = 30;
+
+/// Index the canonical Stacks blocks from `start_height` (default 1) to `end_height` (default:
+/// the canonical tip) into the node's indexer database, re-executing any block the indexer does
+/// not have yet.  Run it while the node is stopped.  If it is interrupted, running it again
+/// resumes where it stopped.
+pub fn rebuild_indexer(
+    config: &Config,
+    start_height: Option<u64>,
+    end_height: Option<u64>,
+) -> Result<RebuildProgress, String> {
+    let sortdb = SortitionDB::open(
+        &config.get_burn_db_file_path(),
+        false,
+        config.get_burnchain().pox_constants,
+    )
+    .map_err(|e| format!("Failed to open sortition DB: {:?}", &e))?;
+    let (mut chainstate, _) = StacksChainState::open(
+        config.is_mainnet(),
+        config.burnchain.chain_id,
+        &config.get_chainstate_path_str(),
+        Some(config.node.get_marf_opts()),
+    )
+    .map_err(|e| format!("Failed to open chainstate: {:?}", &e))?;
+    chainstate.set_vm_limits(config.burnchain.vm_limits);
+    let mut indexer = IndexerDB::connect(&config.get_indexer_db_file_path(), true)
+        .map_err(|e| format!("Failed to open indexer database: {:?}", &e))?;
+
+    let mut last_report = Instant::now();
+    let progress = rebuild_index(
+        &mut indexer,
+        &mut chainstate,
+        &sortdb,
+        start_height.unwrap_or(1),
+        end_height,
+        |progress| {
+            if last_report.elapsed().as_secs() >= REBUILD_PROGRESS_INTERVAL_SECS {
+                info!(
+                    "Indexer: rebuilt up to height {} of {} ({} indexed, {} already indexed)",
+                    progress.next_height - 1,
+                    progress.end_height,
+                    progress.blocks_indexed,
+                    progress.blocks_skipped
+                );
+                last_report = Instant::now();
+            }
+            true
+        },
+    )
+    .map_err(|e| format!("Failed to rebuild indexer: {:?}", &e))?;
+
+    info!(
+        "Indexer: rebuilt heights {} to {} ({} indexed, {} already indexed)",
+        progress.start_height,
+        progress.end_height,
+        progress.blocks_indexed,
+        progress.blocks_skipped
+    );
+    Ok(progress)
+}
+
+/// Serve the indexer query API on `bind_address`, reading from the indexer database at
+/// `db_path`.  This blocks the calling thread.
+pub fn start_serving_indexer_api(bind_address: String, db_path: String) {
+    task::block_on(async {
+        let listener = TcpListener::bind(bind_address)
+            .await
+            .expect("Indexer: unable to bind address");
+        info!(
+            "Indexer: server listening on http://{}",
+            listener.local_addr().expect("Indexer: unable to get addr")
+        );
+
+        let mut incoming = listener.incoming();
+        while let Some(stream) = incoming.next().await {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    error!("Indexer: unable to open socket - {:?}", err);
+                    continue;
+                }
+            };
+            let db_path = db_path.clone();
+            task::spawn(async move {
+                if let Err(err) = accept(stream, db_path).await {
+                    warn!("Indexer: failed to handle request: {}", err);
+                }
+            });
+        }
+    });
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response {
+    let mut response = Response::new(status);
+    response.set_body(body);
+    response
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    json_response(status, json!({ "error": message }))
+}
+
+/// Answer a single query against the indexer database
+fn handle_query(db_path: &str, path: &str, query: Option<&str>) -> Response {
+    let db = match IndexerDB::connect(db_path, false) {
+        Ok(db) => db,
+        Err(e) => {
+            return error_response(
+                StatusCode::ServiceUnavailable,
+                format!("Indexer database is unavailable: {:?}", e),
+            );
+        }
+    };
+
+    let page = match PageRequest::from_query(query) {
+        Ok(page) => page,
+        Err(e) => {
+            return error_response(StatusCode::BadRequest, format!("{}", e));
+        }
+    };
+    let limit = match page
+        .check_no_cursor()
+        .and_then(|_| page.get_limit(DEFAULT_INDEXER_PAGE_LIMIT, MAX_INDEXER_PAGE_LIMIT))
+    {
+        Ok(limit) => limit,
+        Err(e) => {
+            return error_response(StatusCode::BadRequest, format!("{}", e));
+        }
+    };
+
+    let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let result = match parts.as_slice() {
+        ["indexer", "v1", "status"] => IndexerDB::get_status(db.conn()).map(|status| json!(status)),
+        ["indexer", "v1", "addresses", principal, "transactions"] => {
+            let principal = match PrincipalData::parse(principal) {
+                Ok(principal) => principal,
+                Err(_) => {
+                    return error_response(
+                        StatusCode::BadRequest,
+                        format!("Invalid principal '{}'", principal),
+                    );
+                }
+            };
+            IndexerDB::get_address_txs(db.conn(), &principal, limit, page.offset)
+                .map(|(txs, total)| json!(RPCPage::new(txs, limit, page.offset, Some(total), None)))
+        }
+        ["indexer", "v1", "addresses", principal, "balances"] => {
+            let principal = match PrincipalData::parse(principal) {
+                Ok(principal) => principal,
+                Err(_) => {
+                    return error_response(
+                        StatusCode::BadRequest,
+                        format!("Invalid principal '{}'", principal),
+                    );
+                }
+            };
+            IndexerDB::get_balances(db.conn(), &principal).map(|balances| json!(balances))
+        }
+        ["indexer", "v1", "contracts", contract_id, "events"] => {
+            let contract_id = match QualifiedContractIdentifier::parse(contract_id) {
+                Ok(contract_id) => contract_id,
+                Err(_) => {
+                    return error_response(
+                        StatusCode::BadRequest,
+                        format!("Invalid contract identifier '{}'", contract_id),
+                    );
+                }
+            };
+            IndexerDB::get_contract_events(db.conn(), &contract_id, limit, page.offset).map(
+                |(events, total)| {
+                    json!(RPCPage::new(events, limit, page.offset, Some(total), None))
+                },
+            )
+        }
+        _ => {
+            return error_response(StatusCode::NotFound, format!("No such endpoint {}", path));
+        }
+    };
+
+    match result {
+        Ok(body) => json_response(StatusCode::Ok, body),
+        Err(e) => {
+            warn!("Indexer: failed to query {}: {:?}", path, &e);
+            error_response(
+                StatusCode::InternalServerError,
+                "Failed to query indexer".to_string(),
+            )
+        }
+    }
+}
+
+async fn accept(stream: TcpStream, db_path: String) -> http_types::Result<()> {
+    debug!("Handle indexer request ({})", stream.peer_addr()?);
+    async_h1::accept(stream.clone(), |req| {
+        let db_path = db_path.clone();
+        async move {
+            let mut response = match req.method() {
+                Method::Get => handle_query(&db_path, req.url().path(), req.url().query()),
+                _ => error_response(
+                    StatusCode::MethodNotAllowed,
+                    "Only GET is supported".to_string(),
+                ),
+            };
+            response.append_header("Content-Type", "application/json");
+            Ok(response)
+        }
+    })
+    .await?;
+    Ok(())
+}
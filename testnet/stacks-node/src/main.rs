@@ -17,15 +17,20 @@ extern crate stacks;
 #[macro_use(o, slog_log, slog_trace, slog_debug, slog_info, slog_warn, slog_error)]
 extern crate slog;
 
+use stacks::monitoring::NetworkMetrics;
 pub use stacks::util;
 use stacks::util::hash::hex_bytes;
+use stacks::util_lib::membudget::MemoryBudget;
 
 pub mod monitoring;
 
+pub mod alerts;
 pub mod burnchains;
 pub mod config;
 pub mod event_dispatcher;
+pub mod event_replay;
 pub mod genesis_data;
+pub mod indexer;
 pub mod keychain;
 pub mod neon_node;
 pub mod node;
@@ -44,12 +49,15 @@ pub use self::node::{ChainTip, Node};
 pub use self::run_loop::{helium, neon};
 pub use self::tenure::Tenure;
 
+use crate::neon_node::BLOCK_PROCESSOR_STACK_SIZE;
+
 use pico_args::Arguments;
 use std::env;
 
 use std::convert::TryInto;
 use std::panic;
 use std::process;
+use std::thread;
 
 use backtrace::Backtrace;
 
@@ -97,6 +105,10 @@ fn main() {
             args.finish().unwrap();
             ConfigFile::mocknet()
         }
+        "regtest" => {
+            args.finish().unwrap();
+            ConfigFile::regtest()
+        }
         "helium" => {
             args.finish().unwrap();
             ConfigFile::helium()
@@ -135,8 +147,19 @@ fn main() {
             };
         }
         "start" => {
-            let config_path: String = args.value_from_str("--config").unwrap();
+            let mut config_paths: Vec<String> = args.values_from_str("--config").unwrap();
             args.finish().unwrap();
+            if config_paths.len() > 1 {
+                start_networks(config_paths, mine_start.unwrap_or(0));
+                return;
+            }
+            let config_path = match config_paths.pop() {
+                Some(config_path) => config_path,
+                None => {
+                    print_help();
+                    process::exit(1);
+                }
+            };
             info!("Loading config at path {}", config_path);
             match ConfigFile::from_path(&config_path) {
                 Ok(config_file) => config_file,
@@ -146,6 +169,32 @@ fn main() {
                 }
             }
         }
+        "rebuild-indexer" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let start_height: Option<u64> = args
+                .opt_value_from_str("--start-height")
+                .expect("Failed to parse --start-height argument");
+            let end_height: Option<u64> = args
+                .opt_value_from_str("--end-height")
+                .expect("Failed to parse --end-height argument");
+            args.finish().unwrap();
+            info!("Loading config at path {}", config_path);
+            let conf = match ConfigFile::from_path(&config_path).and_then(Config::from_config_file)
+            {
+                Ok(conf) => conf,
+                Err(e) => {
+                    warn!("Invalid config: {}", e);
+                    process::exit(1);
+                }
+            };
+            match indexer::rebuild_indexer(&conf, start_height, end_height) {
+                Ok(_) => process::exit(0),
+                Err(e) => {
+                    warn!("{}", e);
+                    process::exit(1);
+                }
+            }
+        }
         "version" => {
             println!("{}", &version());
             return;
@@ -195,9 +244,14 @@ fn main() {
     debug!("burnchain configuration {:?}", &conf.burnchain);
     debug!("connection configuration {:?}", &conf.connection_options);
 
+    set_memory_budget(conf.node.memory_budget_mb);
+
     let num_round: u64 = 0; // Infinite number of rounds
 
-    if conf.burnchain.mode == "helium" || conf.burnchain.mode == "mocknet" {
+    if conf.burnchain.mode == "helium"
+        || conf.burnchain.mode == "mocknet"
+        || conf.burnchain.mode == "regtest"
+    {
         let mut run_loop = helium::RunLoop::new(conf);
         if let Err(e) = run_loop.start(num_round) {
             warn!("Helium runloop exited: {}", e);
@@ -215,6 +269,64 @@ fn main() {
     }
 }
 
+/// Run several networks side by side in this process -- e.g. mainnet and testnet -- each with
+/// its own config, and so its own working directory, ports, and event observers.  Each network
+/// gets its own neon run loop on its own thread.  Returns once every network has shut down.
+fn start_networks(config_paths: Vec<String>, mine_start: u64) {
+    let mut confs = vec![];
+    for config_path in config_paths.iter() {
+        info!("Loading config at path {}", config_path);
+        match ConfigFile::from_path(config_path).and_then(Config::from_config_file) {
+            Ok(conf) => confs.push(conf),
+            Err(e) => {
+                warn!("Invalid config {}: {}", config_path, e);
+                process::exit(1);
+            }
+        }
+    }
+    if let Err(e) = Config::check_multi_network(&confs) {
+        warn!("Invalid config: {}", e);
+        process::exit(1);
+    }
+
+    // the memory budget is process-wide, so the networks share the sum of their budgets.  Any
+    // network without a budget makes the whole process unlimited.
+    if confs.iter().all(|conf| conf.node.memory_budget_mb > 0) {
+        set_memory_budget(confs.iter().map(|conf| conf.node.memory_budget_mb).sum());
+    }
+
+    let mut run_loop_handles = vec![];
+    for conf in confs.into_iter() {
+        let thread_name = format!("{}:{}", &conf.burnchain.mode, &conf.node.rpc_bind);
+        info!("Starting network {}", &thread_name);
+        let handle = thread::Builder::new()
+            .name(thread_name)
+            .stack_size(BLOCK_PROCESSOR_STACK_SIZE)
+            .spawn(move || {
+                // each network records its metrics separately, from every thread it starts
+                NetworkMetrics::new().enter();
+                let mut run_loop = neon::RunLoop::new(conf);
+                run_loop.start(None, mine_start);
+            })
+            .expect("FATAL: failed to start network run loop thread");
+        run_loop_handles.push(handle);
+    }
+    for handle in run_loop_handles.into_iter() {
+        if let Err(e) = handle.join() {
+            warn!("Network run loop thread panicked: {:?}", &e);
+        }
+    }
+}
+
+/// Apply the configured memory budget to the node's in-RAM caches.  0 means unlimited.
+fn set_memory_budget(memory_budget_mb: u64) {
+    if memory_budget_mb == 0 {
+        return;
+    }
+    info!("Capping in-RAM caches at {} MiB", memory_budget_mb);
+    MemoryBudget::global().set_limit(memory_budget_mb.saturating_mul(1024 * 1024));
+}
+
 fn version() -> String {
     stacks::version_string(
         "stacks-node",
@@ -241,6 +353,10 @@ mainnet\t\tStart a node that will join and stream blocks from the public mainnet
 
 mocknet\t\tStart a node based on a fast local setup emulating a burnchain. Ideal for smart contract development. 
 
+regtest\t\tStart a node emulating a burnchain, which only produces burnchain blocks on demand.
+\t\tBlocks are requested with `POST /regtest/mine` (optional JSON body: {{\"count\": N, \"timestamp\": T}})
+\t\ton the regtest control endpoint (default: 127.0.0.1:20445).
+
 helium\t\tStart a node based on a local setup relying on a local instance of bitcoind.
 \t\tThe following bitcoin.conf is expected:
 \t\t  chain=regtest
@@ -257,9 +373,17 @@ start\t\tStart a node with a config of your own. Can be used for joining a netwo
 \t\t  --config: path of the config (such as https://github.com/blockstack/stacks-blockchain/blob/master/testnet/stacks-node/conf/testnet-follower-conf.toml).
 \t\tExample:
 \t\t  stacks-node start --config=/path/to/config.toml
+\t\tPass --config more than once to run several networks (e.g. mainnet and testnet) in one process.
+\t\tEach network needs its own working directory and ports.
 
 check-config\t\tValidates the config file without starting up the node. Uses same arguments as start subcommand.
 
+rebuild-indexer\tIndex the blocks this node processed before its embedded indexer was enabled, by re-executing
+\t\tthem from the chainstate.  Run it while the node is stopped; if interrupted, run it again to resume.
+\t\tArguments:
+\t\t  --config: path of the node's config.
+\t\t  --start-height, --end-height: optional range of Stacks block heights (default: 1 to the chain tip).
+
 version\t\tDisplay information about the current version and our release cycle.
 
 key-for-seed\tOutput the associated secret key for a burnchain signer created with a given seed.
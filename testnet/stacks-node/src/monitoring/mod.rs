@@ -2,11 +2,16 @@
 
 pub use stacks::monitoring::{increment_errors_emitted_counter, increment_warning_emitted_counter};
 
+use std::sync::Arc;
+
+use stacks::monitoring::NetworkMetrics;
+
 #[cfg(feature = "monitoring_prom")]
 mod prometheus;
 
-pub fn start_serving_monitoring_metrics(bind_address: String) {
+/// Serve the given network's metrics
+pub fn start_serving_monitoring_metrics(bind_address: String, metrics: Arc<NetworkMetrics>) {
     info!("Start serving prometheus metrics");
     #[cfg(feature = "monitoring_prom")]
-    prometheus::start_serving_prometheus_metrics(bind_address);
+    prometheus::start_serving_prometheus_metrics(bind_address, metrics);
 }
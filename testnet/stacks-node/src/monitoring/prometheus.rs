@@ -1,11 +1,13 @@
 use async_std::net::{TcpListener, TcpStream};
 use async_std::prelude::*;
 use async_std::task;
-use stacks::prometheus::{gather, Encoder, TextEncoder};
+use stacks::monitoring::NetworkMetrics;
+use stacks::prometheus::{Encoder, TextEncoder};
+use std::sync::Arc;
 
 use http_types::{Body, Response, StatusCode};
 
-pub fn start_serving_prometheus_metrics(bind_address: String) {
+pub fn start_serving_prometheus_metrics(bind_address: String, metrics: Arc<NetworkMetrics>) {
     let addr = bind_address.clone();
 
     async_std::task::block_on(async {
@@ -33,9 +35,10 @@ pub fn start_serving_prometheus_metrics(bind_address: String) {
                 }
             };
             let addr = addr.clone();
+            let metrics = metrics.clone();
 
             task::spawn(async {
-                if let Err(err) = accept(stream).await {
+                if let Err(err) = accept(stream, metrics).await {
                     eprintln!("{}", err);
                 }
             });
@@ -43,11 +46,11 @@ pub fn start_serving_prometheus_metrics(bind_address: String) {
     });
 }
 
-async fn accept(stream: TcpStream) -> http_types::Result<()> {
+async fn accept(stream: TcpStream, metrics: Arc<NetworkMetrics>) -> http_types::Result<()> {
     debug!("Handle Prometheus polling ({})", stream.peer_addr()?);
     async_h1::accept(stream.clone(), |_| async {
         let encoder = TextEncoder::new();
-        let metric_families = gather();
+        let metric_families = metrics.gather();
         let mut buffer = vec![];
         encoder.encode(&metric_families, &mut buffer).unwrap();
 
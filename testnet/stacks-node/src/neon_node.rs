@@ -144,7 +144,7 @@ use std::convert::{TryFrom, TryInto};
 use std::default::Default;
 use std::mem;
 use std::net::SocketAddr;
-use std::sync::mpsc::{Receiver, SyncSender, TrySendError};
+use std::sync::mpsc::{Receiver, TrySendError};
 use std::sync::{atomic::AtomicBool, atomic::Ordering, Arc, Mutex};
 use std::time::Duration;
 use std::{thread, thread::JoinHandle};
@@ -174,6 +174,7 @@ use stacks::chainstate::stacks::{
     CoinbasePayload, StacksBlock, StacksMicroblock, StacksTransaction, StacksTransactionSigner,
     TransactionAnchorMode, TransactionPayload, TransactionVersion,
 };
+use stacks::clarity_vm::reanalysis::ContractReanalysisDB;
 use stacks::codec::StacksMessageCodec;
 use stacks::core::mempool::MemPoolDB;
 use stacks::core::FIRST_BURNCHAIN_CONSENSUS_HASH;
@@ -182,7 +183,10 @@ use stacks::cost_estimates::metrics::CostMetric;
 use stacks::cost_estimates::metrics::UnitMetric;
 use stacks::cost_estimates::UnitEstimator;
 use stacks::cost_estimates::{CostEstimator, FeeEstimator};
-use stacks::monitoring::{increment_stx_blocks_mined_counter, update_active_miners_count_gauge};
+use stacks::monitoring::{
+    increment_stx_blocks_mined_counter, note_sortition_won, update_active_miners_count_gauge,
+    NetworkMetrics,
+};
 use stacks::net::{
     atlas::{AtlasConfig, AtlasDB, AttachmentInstance},
     db::{LocalPeer, PeerDB},
@@ -202,6 +206,7 @@ use stacks::util::get_epoch_time_secs;
 use stacks::util::hash::{to_hex, Hash160, Sha256Sum};
 use stacks::util::secp256k1::Secp256k1PrivateKey;
 use stacks::util::vrf::VRFPublicKey;
+use stacks::util_lib::sched::{sched_decision, SchedReceiver, SchedSyncSender};
 use stacks::util_lib::strings::{UrlString, VecDisplay};
 use stacks::vm::costs::ExecutionCost;
 
@@ -225,6 +230,9 @@ use clarity::vm::ast::ASTRules;
 use clarity::vm::types::PrincipalData;
 
 pub const RELAYER_MAX_BUFFER: usize = 100;
+/// Number of network results the p2p thread buffers while the relayer is backpressured before
+/// it starts dropping low-value data from them
+pub const P2P_MAX_BUFFERED_RESULTS: usize = 32;
 const VRF_MOCK_MINER_KEY: u64 = 1;
 
 pub const BLOCK_PROCESSOR_STACK_SIZE: usize = 32 * 1024 * 1024; // 32 MB
@@ -280,6 +288,19 @@ pub enum RelayerDirective {
     Exit,
 }
 
+impl RelayerDirective {
+    /// Name of this directive in a recorded schedule
+    pub fn sched_label(&self) -> &'static str {
+        match self {
+            RelayerDirective::HandleNetResult(..) => "HandleNetResult",
+            RelayerDirective::ProcessTenure(..) => "ProcessTenure",
+            RelayerDirective::RunTenure(..) => "RunTenure",
+            RelayerDirective::RegisterKey(..) => "RegisterKey",
+            RelayerDirective::Exit => "Exit",
+        }
+    }
+}
+
 /// Inter-thread communication structure, shared between threads
 #[derive(Clone)]
 pub struct Globals {
@@ -292,7 +313,7 @@ pub struct Globals {
     /// Unconfirmed transactions (shared between the relayer and p2p threads)
     unconfirmed_txs: Arc<Mutex<UnconfirmedTxMap>>,
     /// Writer endpoint to the relayer thread
-    relay_send: SyncSender<RelayerDirective>,
+    relay_send: SchedSyncSender<RelayerDirective>,
     /// Cointer state in the main thread
     counters: Counters,
     /// Connection to the PoX sync watchdog
@@ -340,7 +361,7 @@ impl Globals {
     pub fn new(
         coord_comms: CoordinatorChannels,
         miner_status: Arc<Mutex<MinerStatus>>,
-        relay_send: SyncSender<RelayerDirective>,
+        relay_send: SchedSyncSender<RelayerDirective>,
         counters: Counters,
         sync_comms: PoxSyncWatchdogComms,
         should_keep_running: Arc<AtomicBool>,
@@ -617,16 +638,17 @@ fn fault_injection_skip_mining(_rpc_bind: &str, _target_burn_height: u64) -> boo
     false
 }
 
-/// Open the chainstate, and inject faults from the config file
+/// Open the chainstate, apply the network's VM limits, and inject faults from the config file
 fn open_chainstate_with_faults(config: &Config) -> Result<StacksChainState, ChainstateError> {
     let stacks_chainstate_path = config.get_chainstate_path_str();
-    let (mut chainstate, _) = StacksChainState::open(
-        config.is_mainnet(),
-        config.burnchain.chain_id,
+    let (mut chainstate, _) = StacksChainState::open_and_exec(
+        config.get_network_id(),
         &stacks_chainstate_path,
+        None,
         Some(config.node.get_marf_opts()),
     )?;
 
+    chainstate.set_vm_limits(config.burnchain.vm_limits);
     chainstate.fault_injection.hide_blocks = config.node.fault_injection_hide_blocks;
     Ok(chainstate)
 }
@@ -738,6 +760,9 @@ pub struct RelayerThread {
     /// to neighbors
     relayer: Relayer,
 
+    /// progress of re-analyzing deployed contracts after an epoch transition (None if disabled)
+    contract_reanalysis_db: Option<ContractReanalysisDB>,
+
     /// handle to the subordinate miner thread
     miner_thread: Option<JoinHandle<Option<MinerThreadResult>>>,
     /// if true, then the last time the miner thread was launched, it was used to mine a Stacks
@@ -1050,8 +1075,9 @@ impl MicroblockMinerThread {
 
         // failsafe
         if !Relayer::static_check_problematic_relayed_microblock(
-            chainstate.mainnet,
+            chainstate.network.mainnet,
             epoch_id,
+            &chainstate.get_vm_limits(epoch_id),
             &mined_microblock,
             ASTRules::PrecheckSize,
         ) {
@@ -2135,6 +2161,19 @@ impl RelayerThread {
         )
         .expect("Database failure opening mempool");
 
+        let contract_reanalysis_db = if config.node.contract_reanalysis_batch_size > 0 {
+            ContractReanalysisDB::open(&chainstate.contract_reanalysis_db_path())
+                .map_err(|e| {
+                    warn!(
+                        "Failed to open contract re-analysis DB; re-analysis is disabled: {:?}",
+                        &e
+                    );
+                })
+                .ok()
+        } else {
+            None
+        };
+
         let keychain = Keychain::default(config.node.seed.clone());
         let bitcoin_controller = BitcoinRegtestController::new_dummy(config.clone());
 
@@ -2168,6 +2207,8 @@ impl RelayerThread {
 
             relayer,
 
+            contract_reanalysis_db,
+
             miner_thread: None,
             mined_stacks_block: false,
         }
@@ -2367,8 +2408,9 @@ impl RelayerThread {
 
         // failsafe
         if !Relayer::static_check_problematic_relayed_block(
-            self.chainstate_ref().mainnet,
+            self.chainstate_ref().network.mainnet,
             epoch_id,
+            &self.chainstate_ref().get_vm_limits(epoch_id),
             &anchored_block,
             ASTRules::PrecheckSize,
         ) {
@@ -2435,7 +2477,15 @@ impl RelayerThread {
                 &anchored_block,
                 &parent_consensus_hash,
                 0,
-            )
+            )?;
+
+            // our block builds on the canonical tip, so have the coordinator process it next
+            // (and abandon any lower block it's busy with)
+            chainstate.block_preemption.preempt(
+                &StacksBlockId::new(consensus_hash, &anchored_block.block_hash()),
+                anchored_block.header.total_work.work,
+            );
+            Ok::<(), ChainstateError>(())
         })?;
 
         Ok(true)
@@ -2539,6 +2589,7 @@ impl RelayerThread {
             );
 
             increment_stx_blocks_mined_counter();
+            note_sortition_won(sn.block_height);
             let has_new_data = match self.accept_winning_tenure(
                 &mined_block,
                 &consensus_hash,
@@ -3069,10 +3120,14 @@ impl RelayerThread {
                 }
             };
 
+        let metrics = NetworkMetrics::current();
         if let Ok(miner_handle) = thread::Builder::new()
             .name(format!("miner-block-{}", self.local_peer.data_url))
             .stack_size(BLOCK_PROCESSOR_STACK_SIZE)
-            .spawn(move || miner_thread_state.run_tenure())
+            .spawn(move || {
+                metrics.enter();
+                miner_thread_state.run_tenure()
+            })
             .map_err(|e| {
                 error!("Relayer: Failed to start tenure thread: {:?}", &e);
                 e
@@ -3186,10 +3241,12 @@ impl RelayerThread {
             }
         };
 
+        let metrics = NetworkMetrics::current();
         if let Ok(miner_handle) = thread::Builder::new()
             .name(format!("miner-microblock-{}", self.local_peer.data_url))
             .stack_size(BLOCK_PROCESSOR_STACK_SIZE)
             .spawn(move || {
+                metrics.enter();
                 Some(MinerThreadResult::Microblock(
                     microblock_thread_state.try_mine_microblock(miner_tip.clone()),
                     miner_tip,
@@ -3215,7 +3272,7 @@ impl RelayerThread {
         thread_handle: JoinHandle<Option<MinerThreadResult>>,
     ) -> Option<JoinHandle<Option<MinerThreadResult>>> {
         // tenure run already in progress; try and join
-        if !thread_handle.is_finished() {
+        if !sched_decision("miner", || thread_handle.is_finished()) {
             debug!("Relayer: RunTenure thread not finished / is in-progress");
             return Some(thread_handle);
         }
@@ -3381,6 +3438,36 @@ impl RelayerThread {
         self.miner_thread.is_none()
     }
 
+    /// Re-analyze the next batch of deployed contracts under the type rules of the canonical
+    /// Stacks tip's epoch, if contract re-analysis is enabled.  This is a no-op once all
+    /// contracts have been re-analyzed for that epoch.
+    fn run_contract_reanalysis_step(&mut self) {
+        let mut reanalysis_db = match self.contract_reanalysis_db.take() {
+            Some(db) => db,
+            None => {
+                return;
+            }
+        };
+        let batch_size = self.config.node.contract_reanalysis_batch_size;
+        let res = self.with_chainstate(|_relayer_thread, sortdb, chainstate, _mempool| {
+            let (ch, bhh) = SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())?;
+            let tip = StacksBlockHeader::make_index_block_hash(&ch, &bhh);
+            chainstate.run_contract_reanalysis_step(
+                &mut reanalysis_db,
+                &sortdb.index_conn(),
+                &tip,
+                batch_size,
+            )
+        });
+        match res {
+            Ok(_) | Err(ChainstateError::NoSuchBlockError) => {}
+            Err(e) => {
+                warn!("Relayer: failed to run contract re-analysis: {:?}", &e);
+            }
+        }
+        self.contract_reanalysis_db = Some(reanalysis_db);
+    }
+
     /// Top-level dispatcher
     pub fn handle_directive(&mut self, directive: RelayerDirective) -> bool {
         debug!("Relayer: received next directive");
@@ -3388,6 +3475,7 @@ impl RelayerThread {
             RelayerDirective::HandleNetResult(net_result) => {
                 debug!("Relayer: directive Handle network result");
                 self.process_network_result(net_result);
+                self.run_contract_reanalysis_step();
                 debug!("Relayer: directive Handled network result");
                 true
             }
@@ -3542,7 +3630,7 @@ pub struct PeerThread {
     /// how long to wait for network messages on each poll, in millis
     poll_timeout: u64,
     /// receiver for attachments discovered by the chains coordinator thread
-    attachments_rx: Receiver<HashSet<AttachmentInstance>>,
+    attachments_rx: SchedReceiver<HashSet<AttachmentInstance>>,
     /// handle to the sortition DB (optional so we can take/replace it)
     sortdb: Option<SortitionDB>,
     /// handle to the chainstate DB (optional so we can take/replace it)
@@ -3578,7 +3666,7 @@ impl PeerThread {
             .make_cost_metric()
             .unwrap_or_else(|| Box::new(UnitMetric));
 
-        let mempool = MemPoolDB::open(
+        let mut mempool = MemPoolDB::open(
             config.is_mainnet(),
             config.burnchain.chain_id,
             &config.get_chainstate_path_str(),
@@ -3587,6 +3675,10 @@ impl PeerThread {
         )
         .expect("Database failure opening mempool");
 
+        if config.miner.mempool_enforce_anchor_policy {
+            mempool.anchor_policy = config.miner.anchor_policy.clone();
+        }
+
         mempool
     }
 
@@ -3628,7 +3720,7 @@ impl PeerThread {
             net: Some(net),
             globals: runloop.get_globals(),
             poll_timeout,
-            attachments_rx,
+            attachments_rx: SchedReceiver::new("attachments", |_| "attachments", attachments_rx),
             sortdb: Some(sortdb),
             chainstate: Some(chainstate),
             mempool: Some(mempool),
@@ -3684,6 +3776,48 @@ impl PeerThread {
         res
     }
 
+    /// Apply the drop policy to the network results waiting on the relayer, once more than
+    /// P2P_MAX_BUFFERED_RESULTS of them are buffered.  Blocks, microblocks, attachments, and
+    /// uploaded transactions are never dropped.  Otherwise:
+    /// * pushed and mempool-synced transactions are dropped, since peers will re-announce them
+    /// and mempool sync will fetch them again once the relayer catches up;
+    /// * results left with no data are dropped, except for the newest one, since the relayer
+    /// only needs the latest view of the burnchain and the p2p state machines.
+    fn shed_buffered_results(&mut self) {
+        if self.results_with_data.len() <= P2P_MAX_BUFFERED_RESULTS {
+            return;
+        }
+
+        let mut dropped_txs = 0;
+        for directive in self.results_with_data.iter_mut() {
+            if let RelayerDirective::HandleNetResult(ref mut net_result) = directive {
+                dropped_txs += net_result.drop_transactions();
+            }
+        }
+
+        let num_buffered = self.results_with_data.len();
+        let mut i = 0;
+        self.results_with_data.retain(|directive| {
+            i += 1;
+            match directive {
+                RelayerDirective::HandleNetResult(ref net_result) => {
+                    net_result.has_data_to_store() || i == num_buffered
+                }
+                _ => true,
+            }
+        });
+        let dropped_results = num_buffered - self.results_with_data.len();
+
+        warn!(
+            "P2P: relayer is backpressured; dropped {} buffered transactions and {} status-only network results ({} results still buffered)",
+            dropped_txs,
+            dropped_results,
+            self.results_with_data.len()
+        );
+        monitoring::increment_p2p_backpressure_drops("transaction", dropped_txs as u64);
+        monitoring::increment_p2p_backpressure_drops("status", dropped_results as u64);
+    }
+
     /// Run one pass of the p2p/http state machine
     /// Return true if we should continue running passes; false if not
     pub fn run_one_pass<B: BurnchainHeaderReader>(
@@ -3710,16 +3844,16 @@ impl PeerThread {
             self.poll_timeout
         };
 
-        let mut expected_attachments = match self.attachments_rx.try_recv() {
-            Ok(expected_attachments) => {
-                debug!("Atlas: received attachments: {:?}", &expected_attachments);
-                expected_attachments
-            }
-            _ => {
-                debug!("Atlas: attachment channel is empty");
-                HashSet::new()
-            }
-        };
+        // drain the attachments channel, so the chains coordinator never blocks on it for more
+        // than one pass
+        let mut expected_attachments = HashSet::new();
+        while let Ok(attachments) = self.attachments_rx.try_recv() {
+            debug!("Atlas: received attachments: {:?}", &attachments);
+            expected_attachments.extend(attachments);
+        }
+        if expected_attachments.is_empty() {
+            debug!("Atlas: attachment channel is empty");
+        }
 
         // move over unconfirmed state obtained from the relayer
         self.with_chainstate(|p2p_thread, sortdb, chainstate, _mempool| {
@@ -3806,6 +3940,8 @@ impl PeerThread {
             }
         };
 
+        self.shed_buffered_results();
+
         while let Some(next_result) = self.results_with_data.pop_front() {
             // have blocks, microblocks, and/or transactions (don't care about anything else),
             // or a directive to mine microblocks
@@ -3995,12 +4131,14 @@ impl StacksNode {
             tx.commit().unwrap();
         }
 
-        // update services to indicate we can support mempool sync
+        // update services to indicate we can support mempool sync and node metadata exchange
         {
             let mut tx = peerdb.tx_begin().unwrap();
             PeerDB::set_local_services(
                 &mut tx,
-                (ServiceFlags::RPC as u16) | (ServiceFlags::RELAY as u16),
+                (ServiceFlags::RPC as u16)
+                    | (ServiceFlags::RELAY as u16)
+                    | (ServiceFlags::METADATA as u16),
             )
             .unwrap();
             tx.commit().unwrap();
@@ -4058,7 +4196,10 @@ impl StacksNode {
     /// Main loop of the relayer.
     /// Runs in a separate thread.
     /// Continuously receives
-    pub fn relayer_main(mut relayer_thread: RelayerThread, relay_recv: Receiver<RelayerDirective>) {
+    pub fn relayer_main(
+        mut relayer_thread: RelayerThread,
+        relay_recv: SchedReceiver<RelayerDirective>,
+    ) {
         while let Ok(directive) = relay_recv.recv() {
             if !relayer_thread.globals.keep_running() {
                 break;
@@ -4149,7 +4290,7 @@ impl StacksNode {
         runloop: &RunLoop,
         globals: Globals,
         // relay receiver endpoint for the p2p thread, so the relayer can feed it data to push
-        relay_recv: Receiver<RelayerDirective>,
+        relay_recv: SchedReceiver<RelayerDirective>,
         // attachments receiver endpoint for the p2p thread, so the chains coordinator can feed it
         // attachments it discovers
         attachments_receiver: Receiver<HashSet<AttachmentInstance>>,
@@ -4157,7 +4298,7 @@ impl StacksNode {
         let config = runloop.config().clone();
         let is_miner = runloop.is_miner();
         let burnchain = runloop.get_burnchain();
-        let atlas_config = AtlasConfig::default(config.is_mainnet());
+        let atlas_config = AtlasConfig::for_network(&config.get_network_id());
         let keychain = Keychain::default(config.node.seed.clone());
 
         // we can call _open_ here rather than _connect_, since connect is first called in
@@ -4202,17 +4343,20 @@ impl StacksNode {
         globals.set_initial_leader_key_registration_state(leader_key_registration_state);
 
         let relayer_thread = RelayerThread::new(runloop, local_peer.clone(), relayer);
+        let relayer_metrics = NetworkMetrics::current();
         let relayer_thread_handle = thread::Builder::new()
             .name(format!("relayer-{}", &local_peer.data_url))
             .stack_size(BLOCK_PROCESSOR_STACK_SIZE)
             .spawn(move || {
                 debug!("relayer thread ID is {:?}", thread::current().id());
+                relayer_metrics.enter();
                 Self::relayer_main(relayer_thread, relay_recv);
             })
             .expect("FATAL: failed to start relayer thread");
 
         let p2p_event_dispatcher = runloop.get_event_dispatcher();
         let p2p_thread = PeerThread::new(runloop, p2p_net, attachments_receiver);
+        let p2p_metrics = NetworkMetrics::current();
         let p2p_thread_handle = thread::Builder::new()
             .stack_size(BLOCK_PROCESSOR_STACK_SIZE)
             .name(format!(
@@ -4221,6 +4365,7 @@ impl StacksNode {
             ))
             .spawn(move || {
                 debug!("p2p thread ID is {:?}", thread::current().id());
+                p2p_metrics.enter();
                 Self::p2p_main(p2p_thread, p2p_event_dispatcher);
             })
             .expect("FATAL: failed to start p2p thread");
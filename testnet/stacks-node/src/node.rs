@@ -63,6 +63,7 @@ use stacks::vm::database::BurnStateDB;
 
 use rand::RngCore;
 use stacks::chainstate::stacks::address::PoxAddress;
+use stacks::chainstate::stacks::boot::set_boot_code_overrides;
 
 #[derive(Debug, Clone)]
 pub struct ChainTip {
@@ -107,8 +108,10 @@ pub struct Node {
     block_commits: HashSet<Txid>,
 }
 
+/// Get the genesis lockup schedules, followed by any additional ones from the node config
 pub fn get_account_lockups(
     use_test_chainstate_data: bool,
+    config_lockups: Vec<ChainstateAccountLockup>,
 ) -> Box<dyn Iterator<Item = ChainstateAccountLockup>> {
     Box::new(
         stx_genesis::GenesisData::new(use_test_chainstate_data)
@@ -117,7 +120,8 @@ pub fn get_account_lockups(
                 address: item.address,
                 amount: item.amount,
                 block_height: item.block_height,
-            }),
+            })
+            .chain(config_lockups.into_iter()),
     )
 }
 
@@ -210,10 +214,10 @@ fn spawn_peer(
                     continue;
                 }
             };
-            let (mut chainstate, _) = match StacksChainState::open(
-                is_mainnet,
-                chain_id,
+            let (mut chainstate, _) = match StacksChainState::open_and_exec(
+                config.get_network_id(),
                 &stacks_chainstate_path,
+                None,
                 Some(config.node.get_marf_opts()),
             ) {
                 Ok(x) => x,
@@ -223,6 +227,7 @@ fn spawn_peer(
                     continue;
                 }
             };
+            chainstate.set_vm_limits(config.burnchain.vm_limits);
 
             let estimator = Box::new(UnitEstimator);
             let metric = Box::new(UnitMetric);
@@ -297,11 +302,16 @@ impl Node {
         boot_block_exec: Box<dyn FnOnce(&mut ClarityTx) -> ()>,
         attachments_tx: SyncSender<HashSet<AttachmentInstance>>,
     ) -> Self {
-        let use_test_genesis_data = if config.burnchain.mode == "mocknet" {
-            use_test_genesis_chainstate(&config)
-        } else {
-            USE_TEST_GENESIS_CHAINSTATE
-        };
+        let use_test_genesis_data =
+            if config.burnchain.mode == "mocknet" || config.burnchain.mode == "regtest" {
+                use_test_genesis_chainstate(&config)
+            } else {
+                USE_TEST_GENESIS_CHAINSTATE
+            };
+
+        if !config.burnchain.boot_contract_overrides.is_empty() {
+            set_boot_code_overrides(config.burnchain.boot_contract_overrides.clone());
+        }
 
         let keychain = Keychain::default(config.node.seed.clone());
 
@@ -310,6 +320,7 @@ impl Node {
             .iter()
             .map(|e| (e.address.clone(), e.amount))
             .collect();
+        let initial_lockups = config.get_initial_lockups();
         let pox_constants = match config.burnchain.get_bitcoin_network() {
             (_, BitcoinNetworkType::Mainnet) => PoxConstants::mainnet_default(),
             (_, BitcoinNetworkType::Testnet) => PoxConstants::testnet_default(),
@@ -324,7 +335,7 @@ impl Node {
             pox_constants,
             post_flight_callback: Some(boot_block_exec),
             get_bulk_initial_lockups: Some(Box::new(move || {
-                get_account_lockups(use_test_genesis_data)
+                get_account_lockups(use_test_genesis_data, initial_lockups)
             })),
             get_bulk_initial_balances: Some(Box::new(move || {
                 get_account_balances(use_test_genesis_data)
@@ -336,14 +347,13 @@ impl Node {
         };
 
         let chain_state_result = StacksChainState::open_and_exec(
-            config.is_mainnet(),
-            config.burnchain.chain_id,
+            config.get_network_id(),
             &config.get_chainstate_path_str(),
             Some(&mut boot_data),
             Some(config.node.get_marf_opts()),
         );
 
-        let (chain_state, receipts) = match chain_state_result {
+        let (mut chain_state, receipts) = match chain_state_result {
             Ok(res) => res,
             Err(err) => panic!(
                 "Error while opening chain state at path {}: {:?}",
@@ -351,6 +361,8 @@ impl Node {
                 err
             ),
         };
+        chain_state.set_vm_limits(config.burnchain.vm_limits);
+        chain_state.set_fee_rebate_accounting(config.node.fee_rebate_accounting);
 
         let estimator = Box::new(UnitEstimator);
         let metric = Box::new(UnitMetric);
@@ -413,15 +425,17 @@ impl Node {
         let chainstate_path = config.get_chainstate_path_str();
         let sortdb_path = config.get_burn_db_file_path();
 
-        let (chain_state, _) = match StacksChainState::open(
-            config.is_mainnet(),
-            config.burnchain.chain_id,
+        let (mut chain_state, _) = match StacksChainState::open_and_exec(
+            config.get_network_id(),
             &chainstate_path,
+            None,
             Some(config.node.get_marf_opts()),
         ) {
             Ok(x) => x,
             Err(_e) => panic!(),
         };
+        chain_state.set_vm_limits(config.burnchain.vm_limits);
+        chain_state.set_fee_rebate_accounting(config.node.fee_rebate_accounting);
 
         let (attachments_tx, attachments_rx) = sync_channel(1);
         let mut node = Node {
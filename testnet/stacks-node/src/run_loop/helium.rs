@@ -52,6 +52,7 @@ impl RunLoop {
         let mut burnchain: Box<dyn BurnchainController> = match &self.config.burnchain.mode[..] {
             "helium" => Box::new(BitcoinRegtestController::new(self.config.clone(), None)),
             "mocknet" => MocknetController::generic(self.config.clone()),
+            "regtest" => MocknetController::regtest(self.config.clone()),
             _ => unreachable!(),
         };
 
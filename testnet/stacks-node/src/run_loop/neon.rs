@@ -28,14 +28,20 @@ use stacks::chainstate::coordinator::{
 };
 use stacks::chainstate::stacks::db::{ChainStateBootData, StacksChainState};
 use stacks::core::StacksEpochId;
+use stacks::monitoring::NetworkMetrics;
 use stacks::net::atlas::{AtlasConfig, Attachment, AttachmentInstance, ATTACHMENTS_CHANNEL_SIZE};
 use stacks::util_lib::db::Error as db_error;
+use stacks::util_lib::sched;
 use stx_genesis::GenesisData;
 
 use super::RunLoopCallbacks;
+use crate::alerts::start_alert_monitor;
 use crate::burnchains::make_bitcoin_indexer;
+use crate::event_replay::{start_serving_event_replay_api, EventReplayer};
+use crate::indexer::start_serving_indexer_api;
 use crate::monitoring::start_serving_monitoring_metrics;
 use crate::neon_node::Globals;
+use crate::neon_node::RelayerDirective;
 use crate::neon_node::StacksNode;
 use crate::neon_node::BLOCK_PROCESSOR_STACK_SIZE;
 use crate::neon_node::RELAYER_MAX_BUFFER;
@@ -66,6 +72,11 @@ const UNCONDITIONAL_CHAIN_LIVENESS_CHECK: u64 = 30;
 #[cfg(not(test))]
 const UNCONDITIONAL_CHAIN_LIVENESS_CHECK: u64 = 300;
 
+lazy_static! {
+    /// The `should_keep_running` switches of every run loop in this process
+    static ref TERMINATION_SWITCHES: Mutex<Vec<Arc<AtomicBool>>> = Mutex::new(vec![]);
+}
+
 #[derive(Clone)]
 pub struct Counters {
     pub blocks_processed: RunLoopCounter,
@@ -189,6 +200,10 @@ impl RunLoop {
         for observer in config.events_observers.iter() {
             event_dispatcher.register_observer(observer);
         }
+        event_dispatcher.start_dispatch_threads(
+            config.node.event_dispatch_threads as usize,
+            config.node.event_dispatch_queue_depth.max(1) as usize,
+        );
 
         Self {
             config,
@@ -281,9 +296,18 @@ impl RunLoop {
     }
 
     /// Set up termination handler.  Have a signal set the `should_keep_running` atomic bool to
-    /// false.  Panics of called more than once.
+    /// false.  Signals are process-wide, so if several run loops share this process (i.e. one per
+    /// network), the handler is installed once and terminates all of them.
     fn setup_termination_handler(&self) {
-        let keep_running_writer = self.should_keep_running.clone();
+        let mut switches = TERMINATION_SWITCHES
+            .lock()
+            .expect("FATAL: termination switches lock poisoned");
+        switches.push(self.should_keep_running.clone());
+        if switches.len() > 1 {
+            // another run loop already installed the handler
+            return;
+        }
+
         let install = termination::set_handler(move |sig_id| match sig_id {
             SignalId::Bus => {
                 let msg = "Caught SIGBUS; crashing immediately and dumping core\n";
@@ -295,7 +319,12 @@ impl RunLoop {
             _ => {
                 let msg = format!("Graceful termination request received (signal `{}`), will complete the ongoing runloop cycles and terminate\n", sig_id);
                 async_safe_write_stderr(&msg);
-                keep_running_writer.store(false, Ordering::SeqCst);
+                let switches = TERMINATION_SWITCHES
+                    .lock()
+                    .expect("FATAL: termination switches lock poisoned");
+                for keep_running_writer in switches.iter() {
+                    keep_running_writer.store(false, Ordering::SeqCst);
+                }
             }
         });
 
@@ -456,6 +485,7 @@ impl RunLoop {
             .iter()
             .map(|e| (e.address.clone(), e.amount))
             .collect();
+        let initial_lockups = self.config.get_initial_lockups();
 
         // instantiate chainstate
         let mut boot_data = ChainStateBootData {
@@ -466,7 +496,7 @@ impl RunLoop {
             first_burnchain_block_timestamp: burnchain_config.first_block_timestamp,
             pox_constants: burnchain_config.pox_constants.clone(),
             get_bulk_initial_lockups: Some(Box::new(move || {
-                get_account_lockups(use_test_genesis_data)
+                get_account_lockups(use_test_genesis_data, initial_lockups)
             })),
             get_bulk_initial_balances: Some(Box::new(move || {
                 get_account_balances(use_test_genesis_data)
@@ -477,14 +507,15 @@ impl RunLoop {
             get_bulk_initial_names: Some(Box::new(move || get_names(use_test_genesis_data))),
         };
 
-        let (chain_state_db, receipts) = StacksChainState::open_and_exec(
-            self.config.is_mainnet(),
-            self.config.burnchain.chain_id,
+        let (mut chain_state_db, receipts) = StacksChainState::open_and_exec(
+            self.config.get_network_id(),
             &self.config.get_chainstate_path_str(),
             Some(&mut boot_data),
             Some(self.config.node.get_marf_opts()),
         )
         .unwrap();
+        chain_state_db.set_vm_limits(self.config.burnchain.vm_limits);
+        chain_state_db.set_fee_rebate_accounting(self.config.node.fee_rebate_accounting);
         run_loop::announce_boot_receipts(
             &mut self.event_dispatcher,
             &chain_state_db,
@@ -506,7 +537,7 @@ impl RunLoop {
         let use_test_genesis_data = use_test_genesis_chainstate(&self.config);
 
         // load up genesis Atlas attachments
-        let mut atlas_config = AtlasConfig::default(self.config.is_mainnet());
+        let mut atlas_config = AtlasConfig::for_network(&self.config.get_network_id());
         let genesis_attachments = GenesisData::new(use_test_genesis_data)
             .read_name_zonefiles()
             .into_iter()
@@ -517,12 +548,13 @@ impl RunLoop {
         let chain_state_db = self.boot_chainstate(burnchain_config);
 
         // NOTE: re-instantiate AtlasConfig so we don't have to keep the genesis attachments around
-        let moved_atlas_config = AtlasConfig::default(self.config.is_mainnet());
+        let moved_atlas_config = AtlasConfig::for_network(&self.config.get_network_id());
         let moved_config = self.config.clone();
         let moved_burnchain_config = burnchain_config.clone();
         let mut coordinator_dispatcher = self.event_dispatcher.clone();
         let (attachments_tx, attachments_rx) = sync_channel(ATTACHMENTS_CHANNEL_SIZE);
         let coordinator_indexer = make_bitcoin_indexer(&self.config);
+        let coordinator_metrics = NetworkMetrics::current();
 
         let coordinator_thread_handle = thread::Builder::new()
             .name(format!(
@@ -535,14 +567,22 @@ impl RunLoop {
                     "chains-coordinator thread ID is {:?}",
                     thread::current().id()
                 );
+                coordinator_metrics.enter();
                 let mut cost_estimator = moved_config.make_cost_estimator();
                 let mut fee_estimator = moved_config.make_fee_estimator();
+                let mut indexers = moved_config.make_indexer_pipeline();
 
                 let coord_config = ChainsCoordinatorConfig {
                     always_use_affirmation_maps: moved_config.node.always_use_affirmation_maps,
                     require_affirmed_anchor_blocks: moved_config
                         .node
                         .require_affirmed_anchor_blocks,
+                    block_validation_threads: moved_config.node.block_validation_threads as usize,
+                    block_validation_queue_depth: moved_config.node.block_validation_queue_depth
+                        as usize,
+                    burnchain_load_threads: moved_config.node.burnchain_load_threads as usize,
+                    burnchain_load_queue_depth: moved_config.node.burnchain_load_queue_depth
+                        as usize,
                     ..ChainsCoordinatorConfig::new()
                 };
                 ChainsCoordinator::run(
@@ -555,6 +595,7 @@ impl RunLoop {
                     moved_atlas_config,
                     cost_estimator.as_deref_mut(),
                     fee_estimator.as_deref_mut(),
+                    indexers.as_mut(),
                     miner_status,
                     coordinator_indexer,
                 );
@@ -575,11 +616,58 @@ impl RunLoop {
     fn start_prometheus(&mut self) {
         let prometheus_bind = self.config.node.prometheus_bind.clone();
         if let Some(prometheus_bind) = prometheus_bind {
+            let metrics = NetworkMetrics::current();
             thread::Builder::new()
                 .name("prometheus".to_string())
                 .spawn(move || {
                     debug!("prometheus thread ID is {:?}", thread::current().id());
-                    start_serving_monitoring_metrics(prometheus_bind);
+                    start_serving_monitoring_metrics(prometheus_bind, metrics);
+                })
+                .unwrap();
+        }
+    }
+
+    /// Start checking the operator alert conditions, if any webhooks are configured
+    fn start_alerts(&mut self) {
+        if self.config.alerts.webhooks.is_empty() {
+            return;
+        }
+        start_alert_monitor(
+            self.config.alerts.clone(),
+            self.config.node.miner,
+            self.should_keep_running.clone(),
+        );
+    }
+
+    /// Start serving the embedded indexer's query API
+    fn start_indexer_api(&mut self) {
+        let indexer_bind = self.config.node.indexer_bind.clone();
+        if let Some(indexer_bind) = indexer_bind {
+            let db_path = self.config.get_indexer_db_file_path();
+            thread::Builder::new()
+                .name("indexer-api".to_string())
+                .spawn(move || {
+                    debug!("indexer API thread ID is {:?}", thread::current().id());
+                    start_serving_indexer_api(indexer_bind, db_path);
+                })
+                .unwrap();
+        }
+    }
+
+    /// Start serving the event replay control API
+    fn start_event_replay_api(&mut self) {
+        let event_replay_bind = self.config.node.event_replay_bind.clone();
+        if let Some(event_replay_bind) = event_replay_bind {
+            let replayer = EventReplayer::new(
+                self.config.clone(),
+                self.event_dispatcher.clone(),
+                self.get_burnchain().pox_constants,
+            );
+            thread::Builder::new()
+                .name("event-replay-api".to_string())
+                .spawn(move || {
+                    debug!("event replay API thread ID is {:?}", thread::current().id());
+                    start_serving_event_replay_api(event_replay_bind, replayer);
                 })
                 .unwrap();
         }
@@ -924,18 +1012,20 @@ impl RunLoop {
             .open_sortition_db(true)
             .expect("FATAL: could not open sortition DB");
 
-        let (chain_state_db, _) = StacksChainState::open(
-            config.is_mainnet(),
-            config.burnchain.chain_id,
+        let (chain_state_db, _) = StacksChainState::open_and_exec(
+            config.get_network_id(),
             &config.get_chainstate_path_str(),
+            None,
             Some(config.node.get_marf_opts()),
         )
         .unwrap();
 
+        let metrics = NetworkMetrics::current();
         let liveness_thread_handle = thread::Builder::new()
             .name(format!("chain-liveness-{}", config.node.rpc_bind))
             .stack_size(BLOCK_PROCESSOR_STACK_SIZE)
             .spawn(move || {
+                metrics.enter();
                 Self::drive_chain_liveness(globals, config, burnchain, sortdb, chain_state_db)
             })
             .expect("FATAL: failed to spawn chain liveness thread");
@@ -967,7 +1057,8 @@ impl RunLoop {
         self.is_miner = Some(is_miner);
 
         // relayer linkup
-        let (relay_send, relay_recv) = sync_channel(RELAYER_MAX_BUFFER);
+        let (relay_send, relay_recv) =
+            sched::sync_channel("relayer", RELAYER_MAX_BUFFER, RelayerDirective::sched_label);
 
         // set up globals so other subsystems can instantiate off of the runloop state.
         let globals = Globals::new(
@@ -988,6 +1079,9 @@ impl RunLoop {
         );
         self.instantiate_pox_watchdog();
         self.start_prometheus();
+        self.start_alerts();
+        self.start_indexer_api();
+        self.start_event_replay_api();
 
         // We announce a new burn block so that the chains coordinator
         // can resume prior work and handle eventual unprocessed sortitions
@@ -170,8 +170,7 @@ impl PoxSyncWatchdog {
         config: &Config,
         watchdog_comms: PoxSyncWatchdogComms,
     ) -> Result<PoxSyncWatchdog, String> {
-        let mainnet = config.is_mainnet();
-        let chain_id = config.burnchain.chain_id;
+        let network = config.get_network_id();
         let chainstate_path = config.get_chainstate_path_str();
         let burnchain_poll_time = config.burnchain.poll_time_secs;
         let download_timeout = config.connection_options.timeout;
@@ -180,7 +179,8 @@ impl PoxSyncWatchdog {
         let marf_opts = config.node.get_marf_opts();
 
         let (chainstate, _) =
-            match StacksChainState::open(mainnet, chain_id, &chainstate_path, Some(marf_opts)) {
+            match StacksChainState::open_and_exec(network, &chainstate_path, None, Some(marf_opts))
+            {
                 Ok(cs) => cs,
                 Err(e) => {
                     return Err(format!(
@@ -81,13 +81,14 @@ impl<'a> Tenure {
             elapsed = Instant::now().duration_since(self.burnchain_tip.received_at);
         }
 
-        let (mut chain_state, _) = StacksChainState::open(
-            self.config.is_mainnet(),
-            self.config.burnchain.chain_id,
+        let (mut chain_state, _) = StacksChainState::open_and_exec(
+            self.config.get_network_id(),
             &self.config.get_chainstate_path_str(),
+            None,
             Some(self.config.node.get_marf_opts()),
         )
         .unwrap();
+        chain_state.set_vm_limits(self.config.burnchain.vm_limits);
 
         let (anchored_block, _, _) = StacksBlockBuilder::build_anchored_block(
             &mut chain_state,
@@ -2042,7 +2042,7 @@ fn bitcoind_resubmission_test() {
             None,
         )
         .unwrap();
-        let mut tx = chainstate.db_tx_begin().unwrap();
+        let mut tx = chainstate.write_tx_begin().unwrap();
 
         let (consensus_hash, stacks_block) = get_tip_anchored_block(&conf);
 